@@ -33,16 +33,12 @@ fn main() {
         khr_storage_buffer_storage_class: true,
         ..DeviceExtensions::none()
     };
-    let (physical_device, queue_family) = PhysicalDevice::enumerate(&instance)
+    let physical_device = PhysicalDevice::enumerate(&instance)
         .filter(|&p| p.supported_extensions().is_superset_of(&device_extensions))
-        .filter_map(|p| {
-            // The Vulkan specs guarantee that a compliant implementation must provide at least one queue
-            // that supports compute operations.
-            p.queue_families()
-                .find(|&q| q.supports_compute())
-                .map(|q| (p, q))
-        })
-        .min_by_key(|(p, _)| match p.properties().device_type {
+        // The Vulkan specs guarantee that a compliant implementation must provide at least one
+        // queue that supports compute operations.
+        .filter(|&p| p.queue_families().any(|q| q.supports_compute()))
+        .min_by_key(|p| match p.properties().device_type {
             PhysicalDeviceType::DiscreteGpu => 0,
             PhysicalDeviceType::IntegratedGpu => 1,
             PhysicalDeviceType::VirtualGpu => 2,
@@ -57,22 +53,18 @@ fn main() {
         physical_device.properties().device_type
     );
 
-    // Now initializing the device.
-    let (device, mut queues) = Device::new(
+    // Now initializing the device. Since this example doesn't draw anything, we use
+    // `Device::new_compute_only`, which picks a queue family that supports compute operations
+    // and creates a single queue on it.
+    let (device, queue) = Device::new_compute_only(
         physical_device,
         &Features::none(),
         &physical_device
             .required_extensions()
             .union(&device_extensions),
-        [(queue_family, 0.5)].iter().cloned(),
     )
     .unwrap();
 
-    // Since we can request multiple queues, the `queues` variable is in fact an iterator. In this
-    // example we use only one queue, so we just retrieve the first and only element of the
-    // iterator and throw it away.
-    let queue = queues.next().unwrap();
-
     // Now let's get to the actual example.
     //
     // What we are going to do is very basic: we are going to fill a buffer with 64k integers
@@ -213,6 +213,7 @@ fn main() {
             input_attachments: vec![],
             resolve_attachments: vec![],
             preserve_attachments: vec![],
+            fragment_shading_rate_attachment: None,
         }],
         vec![],
         MultiviewDesc {
@@ -265,12 +265,16 @@ where
         pub fn #load_name(device: ::std::sync::Arc<::vulkano::device::Device>)
             -> Result<::std::sync::Arc<::vulkano::shader::ShaderModule>, ::vulkano::shader::ShaderCreationError>
         {
+            use vulkano::shader::ComputeShaderExecution;
             use vulkano::shader::EntryPointInfo;
             use vulkano::shader::GeometryShaderExecution;
             use vulkano::shader::ShaderExecution;
             use vulkano::shader::ShaderModule;
             use vulkano::shader::ShaderStage;
             use vulkano::shader::SpecializationConstantRequirements;
+            use vulkano::shader::SpecializationConstantType;
+            use vulkano::shader::TessellationShaderExecution;
+            use vulkano::shader::TessellationShaderSubdivision;
             use vulkano::shader::spirv::Capability;
             use vulkano::Version;
 
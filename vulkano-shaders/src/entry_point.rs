@@ -11,8 +11,9 @@ use fnv::FnvHashMap;
 use proc_macro2::TokenStream;
 use vulkano::pipeline::layout::PipelineLayoutPcRange;
 use vulkano::shader::{
-    DescriptorRequirements, GeometryShaderExecution, ShaderExecution, ShaderInterfaceEntry,
-    SpecializationConstantRequirements,
+    ComputeShaderExecution, DescriptorRequirements, GeometryShaderExecution, ShaderExecution,
+    ShaderInterfaceEntry, SpecializationConstantRequirements, SpecializationConstantType,
+    TessellationShaderExecution, TransformFeedbackOutput,
 };
 use vulkano::shader::{EntryPointInfo, ShaderInterface, ShaderStages};
 
@@ -44,9 +45,13 @@ pub(super) fn write_entry_point(name: &str, info: &EntryPointInfo) -> TokenStrea
 fn write_shader_execution(execution: &ShaderExecution) -> TokenStream {
     match execution {
         ShaderExecution::Vertex => quote! { ShaderExecution::Vertex },
-        ShaderExecution::TessellationControl => quote! { ShaderExecution::TessellationControl },
-        ShaderExecution::TessellationEvaluation => {
-            quote! { ShaderExecution::TessellationEvaluation }
+        ShaderExecution::TessellationControl(execution) => {
+            let execution = write_tessellation_execution(execution);
+            quote! { ShaderExecution::TessellationControl(#execution) }
+        }
+        ShaderExecution::TessellationEvaluation(execution) => {
+            let execution = write_tessellation_execution(execution);
+            quote! { ShaderExecution::TessellationEvaluation(#execution) }
         }
         ShaderExecution::Geometry(GeometryShaderExecution { input }) => {
             let input = format_ident!("{}", format!("{:?}", input));
@@ -57,7 +62,51 @@ fn write_shader_execution(execution: &ShaderExecution) -> TokenStream {
             }
         }
         ShaderExecution::Fragment => quote! { ShaderExecution::Fragment },
-        ShaderExecution::Compute => quote! { ShaderExecution::Compute },
+        ShaderExecution::Compute(ComputeShaderExecution {
+            local_size,
+            local_size_specialization,
+        }) => {
+            let local_size_specialization =
+                local_size_specialization
+                    .iter()
+                    .map(|constant_id| match constant_id {
+                        Some(constant_id) => quote! { Some(#constant_id) },
+                        None => quote! { None },
+                    });
+            quote! {
+                ShaderExecution::Compute(ComputeShaderExecution {
+                    local_size: [#( #local_size ),*],
+                    local_size_specialization: [#( #local_size_specialization ),*],
+                })
+            }
+        }
+    }
+}
+
+fn write_tessellation_execution(execution: &TessellationShaderExecution) -> TokenStream {
+    let TessellationShaderExecution {
+        output_vertices,
+        subdivision,
+        point_mode,
+    } = execution;
+    let output_vertices = match output_vertices {
+        Some(output_vertices) => quote! { Some(#output_vertices) },
+        None => quote! { None },
+    };
+    let subdivision = match subdivision {
+        Some(subdivision) => {
+            let subdivision = format_ident!("{}", format!("{:?}", subdivision));
+            quote! { Some(TessellationShaderSubdivision::#subdivision) }
+        }
+        None => quote! { None },
+    };
+
+    quote! {
+        TessellationShaderExecution {
+            output_vertices: #output_vertices,
+            subdivision: #subdivision,
+            point_mode: #point_mode,
+        }
     }
 }
 
@@ -214,12 +263,33 @@ fn write_specialization_constant_requirements(
     let specialization_constant_requirements = specialization_constant_requirements
         .into_iter()
         .map(|(&constant_id, reqs)| {
-            let SpecializationConstantRequirements { size } = reqs;
+            let SpecializationConstantRequirements { size, ty, name } = reqs;
+            let ty = match *ty {
+                SpecializationConstantType::Bool => quote! { SpecializationConstantType::Bool },
+                SpecializationConstantType::Int { width, signed } => quote! {
+                    SpecializationConstantType::Int {
+                        width: #width,
+                        signed: #signed,
+                    }
+                },
+                SpecializationConstantType::Float { width } => quote! {
+                    SpecializationConstantType::Float { width: #width }
+                },
+                SpecializationConstantType::Composite => {
+                    quote! { SpecializationConstantType::Composite }
+                }
+            };
+            let name = match name {
+                Some(name) => quote! { Some(::std::borrow::Cow::Borrowed(#name)) },
+                None => quote! { None },
+            };
             quote! {
                 (
                     #constant_id,
                     SpecializationConstantRequirements {
                         size: #size,
+                        ty: #ty,
+                        name: #name,
                     },
                 ),
             }
@@ -238,16 +308,34 @@ fn write_interface(interface: &ShaderInterface) -> TokenStream {
              location,
              format,
              name,
+             stream,
+             transform_feedback,
          }| {
             let start = location.start;
             let end = location.end;
             let format = format_ident!("{}", format!("{:?}", format));
+            let transform_feedback = match transform_feedback {
+                Some(TransformFeedbackOutput {
+                    buffer,
+                    stride,
+                    offset,
+                }) => quote! {
+                    Some(::vulkano::shader::TransformFeedbackOutput {
+                        buffer: #buffer,
+                        stride: #stride,
+                        offset: #offset,
+                    })
+                },
+                None => quote! { None },
+            };
 
             quote! {
                 ::vulkano::shader::ShaderInterfaceEntry {
                     location: #start .. #end,
                     format: ::vulkano::format::Format::#format,
-                    name: Some(::std::borrow::Cow::Borrowed(#name))
+                    name: Some(::std::borrow::Cow::Borrowed(#name)),
+                    stream: #stream,
+                    transform_feedback: #transform_feedback,
                 },
             }
         },
@@ -0,0 +1,466 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Acceleration structures, used by ray queries and ray tracing pipelines.
+//!
+//! An acceleration structure is an opaque object that stores geometry in a form that the
+//! implementation can quickly trace rays through. Its storage lives in a buffer, which must have
+//! been created with the `acceleration_structure_storage` usage.
+//!
+//! Creating an acceleration structure requires the `khr_acceleration_structure` extension and the
+//! [`acceleration_structure`](crate::device::Features::acceleration_structure) feature to be
+//! enabled on the device. Once created, it can be bound to a descriptor of type
+//! [`AccelerationStructure`](crate::descriptor_set::layout::DescriptorType::AccelerationStructure).
+//!
+//! Vulkano does not build acceleration structures yet. A newly created acceleration structure has
+//! undefined content, and must be built with raw Vulkan commands before being read by a shader.
+
+use crate::buffer::BufferAccess;
+use crate::buffer::BufferInner;
+use crate::check_errors;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::Error;
+use crate::OomError;
+use crate::VulkanObject;
+use std::error;
+use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::Arc;
+
+/// An opaque structure used to trace rays through geometry, stored in a buffer.
+pub struct AccelerationStructure {
+    handle: ash::vk::AccelerationStructureKHR,
+    buffer: Arc<dyn BufferAccess>,
+    ty: AccelerationStructureType,
+}
+
+impl AccelerationStructure {
+    /// Creates a new acceleration structure of type `ty`, stored in the whole of `buffer`.
+    ///
+    /// The buffer must have been created with the `acceleration_structure_storage` usage, and
+    /// must start at an offset that is a multiple of 256 bytes in its underlying Vulkan buffer.
+    pub fn new(
+        buffer: Arc<dyn BufferAccess>,
+        ty: AccelerationStructureType,
+    ) -> Result<Arc<AccelerationStructure>, AccelerationStructureCreationError> {
+        let size = buffer.size();
+        let BufferInner {
+            buffer: inner,
+            offset,
+        } = buffer.inner();
+        let device = inner.device();
+
+        if !device.enabled_extensions().khr_acceleration_structure {
+            return Err(AccelerationStructureCreationError::ExtensionNotEnabled);
+        }
+
+        if !device.enabled_features().acceleration_structure {
+            return Err(AccelerationStructureCreationError::FeatureNotEnabled);
+        }
+
+        // VUID-VkAccelerationStructureCreateInfoKHR-buffer-03614
+        if !inner.usage().acceleration_structure_storage {
+            return Err(AccelerationStructureCreationError::WrongBufferUsage);
+        }
+
+        // VUID-VkAccelerationStructureCreateInfoKHR-offset-03734
+        if offset % 256 != 0 {
+            return Err(AccelerationStructureCreationError::WrongBufferAlignment);
+        }
+
+        let handle = unsafe {
+            let infos = ash::vk::AccelerationStructureCreateInfoKHR {
+                create_flags: ash::vk::AccelerationStructureCreateFlagsKHR::empty(),
+                buffer: inner.internal_object(),
+                offset,
+                size,
+                ty: ty.into(),
+                device_address: 0,
+                ..Default::default()
+            };
+
+            let fns = device.fns();
+            let mut output = MaybeUninit::uninit();
+            check_errors(
+                fns.khr_acceleration_structure
+                    .create_acceleration_structure_khr(
+                        device.internal_object(),
+                        &infos,
+                        ptr::null(),
+                        output.as_mut_ptr(),
+                    ),
+            )?;
+            output.assume_init()
+        };
+
+        Ok(Arc::new(AccelerationStructure { handle, buffer, ty }))
+    }
+
+    /// Returns the buffer that stores the acceleration structure.
+    #[inline]
+    pub fn buffer(&self) -> &Arc<dyn BufferAccess> {
+        &self.buffer
+    }
+
+    /// Returns the type of the acceleration structure.
+    #[inline]
+    pub fn ty(&self) -> AccelerationStructureType {
+        self.ty
+    }
+
+    /// Returns the device address of the acceleration structure, which is how top-level
+    /// acceleration structures refer to bottom-level ones.
+    #[inline]
+    pub fn device_address(&self) -> ash::vk::DeviceAddress {
+        let device = self.device();
+        let info = ash::vk::AccelerationStructureDeviceAddressInfoKHR {
+            acceleration_structure: self.handle,
+            ..Default::default()
+        };
+
+        unsafe {
+            let fns = device.fns();
+            fns.khr_acceleration_structure
+                .get_acceleration_structure_device_address_khr(device.internal_object(), &info)
+        }
+    }
+}
+
+unsafe impl VulkanObject for AccelerationStructure {
+    type Object = ash::vk::AccelerationStructureKHR;
+
+    #[inline]
+    fn internal_object(&self) -> ash::vk::AccelerationStructureKHR {
+        self.handle
+    }
+}
+
+unsafe impl DeviceOwned for AccelerationStructure {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.buffer.device()
+    }
+}
+
+impl fmt::Debug for AccelerationStructure {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("AccelerationStructure")
+            .field("raw", &self.handle)
+            .field("ty", &self.ty)
+            .finish()
+    }
+}
+
+impl PartialEq for AccelerationStructure {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.handle == other.handle && self.device() == other.device()
+    }
+}
+
+impl Eq for AccelerationStructure {}
+
+impl Hash for AccelerationStructure {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.handle.hash(state);
+        self.device().hash(state);
+    }
+}
+
+impl Drop for AccelerationStructure {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            let device = self.buffer.device();
+            let fns = device.fns();
+            fns.khr_acceleration_structure
+                .destroy_acceleration_structure_khr(
+                    device.internal_object(),
+                    self.handle,
+                    ptr::null(),
+                );
+        }
+    }
+}
+
+/// The level of an acceleration structure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum AccelerationStructureType {
+    /// Contains instances of bottom-level acceleration structures.
+    TopLevel = ash::vk::AccelerationStructureTypeKHR::TOP_LEVEL.as_raw(),
+    /// Contains the actual geometry, as triangles or axis-aligned bounding boxes.
+    BottomLevel = ash::vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL.as_raw(),
+    /// The level is only known when the acceleration structure is built.
+    Generic = ash::vk::AccelerationStructureTypeKHR::GENERIC.as_raw(),
+}
+
+impl From<AccelerationStructureType> for ash::vk::AccelerationStructureTypeKHR {
+    #[inline]
+    fn from(val: AccelerationStructureType) -> Self {
+        Self::from_raw(val as i32)
+    }
+}
+
+/// Error that can happen when creating an acceleration structure.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AccelerationStructureCreationError {
+    /// Out of memory.
+    OomError(OomError),
+
+    /// The `khr_acceleration_structure` extension isn't enabled on the device.
+    ExtensionNotEnabled,
+
+    /// The `acceleration_structure` feature isn't enabled on the device.
+    FeatureNotEnabled,
+
+    /// The buffer was not created with the `acceleration_structure_storage` usage.
+    WrongBufferUsage,
+
+    /// The offset of the buffer is not a multiple of 256.
+    WrongBufferAlignment,
+}
+
+impl error::Error for AccelerationStructureCreationError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            AccelerationStructureCreationError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AccelerationStructureCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                AccelerationStructureCreationError::OomError(_) =>
+                    "out of memory when creating \
+                                                                    acceleration structure",
+                AccelerationStructureCreationError::ExtensionNotEnabled => {
+                    "the `khr_acceleration_structure` extension isn't enabled"
+                }
+                AccelerationStructureCreationError::FeatureNotEnabled => {
+                    "the `acceleration_structure` feature isn't enabled"
+                }
+                AccelerationStructureCreationError::WrongBufferUsage => {
+                    "the buffer was not created with the `acceleration_structure_storage` usage"
+                }
+                AccelerationStructureCreationError::WrongBufferAlignment => {
+                    "the offset of the buffer is not a multiple of 256"
+                }
+            }
+        )
+    }
+}
+
+impl From<OomError> for AccelerationStructureCreationError {
+    #[inline]
+    fn from(err: OomError) -> AccelerationStructureCreationError {
+        AccelerationStructureCreationError::OomError(err)
+    }
+}
+
+impl From<Error> for AccelerationStructureCreationError {
+    #[inline]
+    fn from(err: Error) -> AccelerationStructureCreationError {
+        match err {
+            err @ Error::OutOfHostMemory => OomError::from(err).into(),
+            err @ Error::OutOfDeviceMemory => OomError::from(err).into(),
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::acceleration_structure::AccelerationStructure;
+    use crate::acceleration_structure::AccelerationStructureCreationError;
+    use crate::acceleration_structure::AccelerationStructureType;
+    use crate::buffer::BufferAccess;
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::descriptor_set::layout::DescriptorDesc;
+    use crate::descriptor_set::layout::DescriptorSetDesc;
+    use crate::descriptor_set::layout::DescriptorSetLayout;
+    use crate::descriptor_set::layout::DescriptorType;
+    use crate::descriptor_set::DescriptorBindingResources;
+    use crate::descriptor_set::DescriptorSet;
+    use crate::descriptor_set::PersistentDescriptorSet;
+    use crate::shader::ShaderStages;
+    use std::iter;
+
+    // Creates a device with the `khr_acceleration_structure` extension and the features it
+    // requires, or returns if they aren't supported.
+    macro_rules! acceleration_structure_dev_and_queue {
+        () => {{
+            use crate::device::physical::PhysicalDevice;
+            use crate::device::Device;
+            use crate::device::DeviceExtensions;
+            use crate::device::Features;
+
+            let instance = instance!();
+
+            let physical = match PhysicalDevice::enumerate(&instance).next() {
+                Some(p) => p,
+                None => return,
+            };
+
+            let queue = match physical.queue_families().find(|q| q.supports_graphics()) {
+                Some(q) => q,
+                None => return,
+            };
+
+            let extensions = DeviceExtensions {
+                khr_acceleration_structure: true,
+                khr_buffer_device_address: true,
+                khr_deferred_host_operations: true,
+                ext_descriptor_indexing: true,
+                ..DeviceExtensions::none()
+            };
+            let features = Features {
+                acceleration_structure: true,
+                buffer_device_address: true,
+                ..Features::none()
+            };
+
+            if !physical.supported_extensions().is_superset_of(&extensions)
+                || !physical.supported_features().is_superset_of(&features)
+            {
+                return;
+            }
+
+            let (device, mut queues) =
+                match Device::new(physical, &features, &extensions, iter::once((queue, 0.5))) {
+                    Ok(r) => r,
+                    Err(_) => return,
+                };
+
+            (device, queues.next().unwrap())
+        }};
+    }
+
+    fn storage_usage() -> BufferUsage {
+        BufferUsage {
+            acceleration_structure_storage: true,
+            device_address: true,
+            ..BufferUsage::none()
+        }
+    }
+
+    #[test]
+    fn extension_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_iter(
+            device,
+            BufferUsage::storage_buffer(),
+            false,
+            (0..256).map(|_| 0u8),
+        )
+        .unwrap();
+
+        match AccelerationStructure::new(buffer, AccelerationStructureType::BottomLevel) {
+            Err(AccelerationStructureCreationError::ExtensionNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn create() {
+        let (device, _) = acceleration_structure_dev_and_queue!();
+
+        let buffer =
+            CpuAccessibleBuffer::from_iter(device, storage_usage(), false, (0..1024).map(|_| 0u8))
+                .unwrap();
+
+        let acceleration_structure =
+            AccelerationStructure::new(buffer.clone(), AccelerationStructureType::BottomLevel)
+                .unwrap();
+        assert_eq!(
+            acceleration_structure.ty(),
+            AccelerationStructureType::BottomLevel
+        );
+        assert_eq!(acceleration_structure.buffer().size(), buffer.size());
+        assert_eq!(acceleration_structure.buffer().size(), 1024);
+        assert_ne!(acceleration_structure.device_address(), 0);
+    }
+
+    #[test]
+    fn wrong_buffer_usage() {
+        let (device, _) = acceleration_structure_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_iter(
+            device,
+            BufferUsage::storage_buffer(),
+            false,
+            (0..256).map(|_| 0u8),
+        )
+        .unwrap();
+
+        match AccelerationStructure::new(buffer, AccelerationStructureType::TopLevel) {
+            Err(AccelerationStructureCreationError::WrongBufferUsage) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn descriptor_write() {
+        let (device, _) = acceleration_structure_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            storage_usage(),
+            false,
+            (0..1024).map(|_| 0u8),
+        )
+        .unwrap();
+        let acceleration_structure =
+            AccelerationStructure::new(buffer, AccelerationStructureType::TopLevel).unwrap();
+
+        let layout = DescriptorSetLayout::new(
+            device,
+            DescriptorSetDesc::new(iter::once(Some(DescriptorDesc {
+                ty: DescriptorType::AccelerationStructure,
+                descriptor_count: 1,
+                variable_count: false,
+                update_after_bind: false,
+                update_unused_while_pending: false,
+                partially_bound: false,
+                stages: ShaderStages::all(),
+                immutable_samplers: Vec::new(),
+                mutable_types: Vec::new(),
+            }))),
+        )
+        .unwrap();
+
+        let mut builder = PersistentDescriptorSet::start(layout);
+        builder
+            .add_acceleration_structure(acceleration_structure.clone())
+            .unwrap();
+        let set = builder.build().unwrap();
+
+        match set.resources().binding(0).unwrap() {
+            DescriptorBindingResources::AccelerationStructure(elements) => {
+                assert_eq!(elements.len(), 1);
+                assert_eq!(elements[0].as_ref(), Some(&acceleration_structure));
+            }
+            _ => panic!(),
+        }
+    }
+}
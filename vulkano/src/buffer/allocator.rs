@@ -0,0 +1,712 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Allocator of long-lived sub-buffers in device-local memory.
+//!
+//! Creating one `DeviceLocalBuffer` per object (for example per mesh) costs one memory
+//! allocation and one buffer object each, and the number of memory allocations is limited by
+//! the `max_memory_allocation_count` property of the device. A `SubbufferAllocator` instead
+//! creates a few large buffers, called arenas, and carves sub-ranges out of them. Each sub-range
+//! is returned as a `Subbuffer`, which can be used like any other buffer and gives its range back
+//! to the arena when it is dropped.
+//!
+//! Contrary to `CpuBufferPool`, which is a ring buffer meant for data that is replaced every
+//! frame, the sub-buffers can be freed in any order.
+
+use crate::buffer::device_local::DeviceLocalBuffer;
+use crate::buffer::traits::BufferAccess;
+use crate::buffer::traits::BufferInner;
+use crate::buffer::traits::TypedBufferAccess;
+use crate::buffer::BufferUsage;
+use crate::device::physical::QueueFamily;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::device::Queue;
+use crate::memory::DeviceMemoryAllocError;
+use crate::sync::AccessError;
+use crate::DeviceSize;
+use smallvec::SmallVec;
+use std::cmp;
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// How a `SubbufferAllocator` finds a free range in an arena.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AllocationStrategy {
+    /// The free ranges of each arena are kept in a sorted list, and the first one that is large
+    /// enough is used. Freed ranges are merged with their free neighbours.
+    ///
+    /// This wastes little memory, but fragments when objects of very different sizes are freed
+    /// in a random order.
+    FreeList,
+
+    /// Each arena is recursively split into halves, and a request uses the smallest half that is
+    /// large enough. Freed halves are merged back with their buddy.
+    ///
+    /// This fragments less and is fast, but sizes are rounded up to a power of two.
+    Buddy,
+}
+
+/// Allocator that carves sub-buffers out of large device-local buffers.
+///
+/// Arenas are created lazily, the first time that no existing arena has enough free space for a
+/// request. A request larger than the arena size gets an arena of its own.
+///
+/// # Example
+///
+/// ```
+/// use vulkano::buffer::allocator::AllocationStrategy;
+/// use vulkano::buffer::BufferUsage;
+/// use vulkano::buffer::SubbufferAllocator;
+/// # let device: std::sync::Arc<vulkano::device::Device> = return;
+/// # let queue: std::sync::Arc<vulkano::device::Queue> = return;
+///
+/// let allocator = SubbufferAllocator::new(
+///     device.clone(),
+///     BufferUsage::vertex_buffer_transfer_destination(),
+///     Some(queue.family()),
+///     64 * 1024 * 1024,
+///     AllocationStrategy::FreeList,
+/// );
+///
+/// // Each mesh gets a subbuffer instead of a buffer of its own.
+/// let vertices = allocator.alloc_array::<[f32; 3]>(1024).unwrap();
+/// ```
+pub struct SubbufferAllocator {
+    device: Arc<Device>,
+    usage: BufferUsage,
+    queue_families: SmallVec<[u32; 4]>,
+    arena_size: DeviceSize,
+    strategy: AllocationStrategy,
+    // Minimum alignment of the offset of every subbuffer, in bytes. Always a power of two.
+    alignment: DeviceSize,
+    arenas: Mutex<Vec<Arc<Arena>>>,
+}
+
+impl SubbufferAllocator {
+    /// Builds a new allocator whose arenas are `arena_size` bytes large.
+    ///
+    /// No memory is allocated until the first subbuffer is requested.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `arena_size` is 0.
+    ///
+    pub fn new<'a, I>(
+        device: Arc<Device>,
+        usage: BufferUsage,
+        queue_families: I,
+        arena_size: DeviceSize,
+        strategy: AllocationStrategy,
+    ) -> SubbufferAllocator
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        assert!(arena_size != 0);
+
+        let queue_families = queue_families
+            .into_iter()
+            .map(|f| f.id())
+            .collect::<SmallVec<[u32; 4]>>();
+
+        let properties = device.physical_device().properties();
+        // Offsets must be multiples of 4 for copies, fills and indirect commands anyway.
+        let mut alignment: DeviceSize = 4;
+        if usage.uniform_buffer {
+            alignment = cmp::max(alignment, properties.min_uniform_buffer_offset_alignment);
+        }
+        if usage.storage_buffer {
+            alignment = cmp::max(alignment, properties.min_storage_buffer_offset_alignment);
+        }
+        if usage.uniform_texel_buffer || usage.storage_texel_buffer {
+            alignment = cmp::max(alignment, properties.min_texel_buffer_offset_alignment);
+        }
+
+        let arena_size = match strategy {
+            AllocationStrategy::FreeList => arena_size,
+            AllocationStrategy::Buddy => cmp::max(arena_size, alignment).next_power_of_two(),
+        };
+
+        SubbufferAllocator {
+            device,
+            usage,
+            queue_families,
+            arena_size,
+            strategy,
+            alignment,
+            arenas: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the usage of the arenas, and therefore of the subbuffers.
+    #[inline]
+    pub fn usage(&self) -> BufferUsage {
+        self.usage
+    }
+
+    /// Returns the size in bytes of the arenas.
+    #[inline]
+    pub fn arena_size(&self) -> DeviceSize {
+        self.arena_size
+    }
+
+    /// Returns the strategy that is used to find free ranges.
+    #[inline]
+    pub fn strategy(&self) -> AllocationStrategy {
+        self.strategy
+    }
+
+    /// Returns the minimum alignment in bytes of the offset of the subbuffers, which depends on
+    /// the usage.
+    #[inline]
+    pub fn alignment(&self) -> DeviceSize {
+        self.alignment
+    }
+
+    /// Returns the number of arenas that have been created, which is the number of memory
+    /// allocations that the allocator holds.
+    #[inline]
+    pub fn arena_count(&self) -> usize {
+        self.arenas.lock().unwrap().len()
+    }
+
+    /// Destroys the arenas that don't contain any subbuffer anymore.
+    pub fn free_empty_arenas(&self) {
+        self.arenas
+            .lock()
+            .unwrap()
+            .retain(|arena| !arena.state.lock().unwrap().is_empty());
+    }
+
+    /// Allocates a subbuffer for a single element.
+    // TODO: unsafe because uninitialized data
+    #[inline]
+    pub fn alloc<T>(&self) -> Result<Arc<Subbuffer<T>>, DeviceMemoryAllocError>
+    where
+        T: Send + Sync + 'static,
+    {
+        unsafe { self.alloc_raw(mem::size_of::<T>() as DeviceSize, mem::align_of::<T>()) }
+    }
+
+    /// Allocates a subbuffer for `len` elements.
+    // TODO: unsafe because uninitialized data
+    #[inline]
+    pub fn alloc_array<T>(
+        &self,
+        len: DeviceSize,
+    ) -> Result<Arc<Subbuffer<[T]>>, DeviceMemoryAllocError>
+    where
+        T: Send + Sync + 'static,
+    {
+        unsafe {
+            self.alloc_raw(
+                len * mem::size_of::<T>() as DeviceSize,
+                mem::align_of::<T>(),
+            )
+        }
+    }
+
+    /// Allocates a subbuffer of `size` bytes whose offset is a multiple of `align`, in addition
+    /// to the alignment required by the usage.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that the size that you pass is correct for `T`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `align` is not a power of two.
+    ///
+    pub unsafe fn alloc_raw<T: ?Sized>(
+        &self,
+        size: DeviceSize,
+        align: usize,
+    ) -> Result<Arc<Subbuffer<T>>, DeviceMemoryAllocError> {
+        assert!(align.is_power_of_two());
+
+        let align = cmp::max(self.alignment, align as DeviceSize);
+        let requested_size = size;
+        // Empty subbuffers still get a range, so that they have a unique offset.
+        let size = cmp::max(size, 1);
+
+        let mut arenas = self.arenas.lock().unwrap();
+
+        for arena in arenas.iter() {
+            if let Some(block) = arena.state.lock().unwrap().alloc(size, align) {
+                return Ok(Subbuffer::new(arena.clone(), block, requested_size));
+            }
+        }
+
+        let arena_size = match self.strategy {
+            AllocationStrategy::FreeList => cmp::max(self.arena_size, size),
+            AllocationStrategy::Buddy => {
+                cmp::max(self.arena_size, cmp::max(size, align).next_power_of_two())
+            }
+        };
+        let buffer = DeviceLocalBuffer::raw(
+            self.device.clone(),
+            arena_size,
+            self.usage,
+            self.queue_families.iter().map(|&id| {
+                self.device
+                    .physical_device()
+                    .queue_family_by_id(id)
+                    .unwrap()
+            }),
+        )?;
+
+        let mut state = ArenaState::new(self.strategy, arena_size, self.alignment);
+        let block = state
+            .alloc(size, align)
+            .expect("a new arena must fit the request");
+        let arena = Arc::new(Arena {
+            buffer,
+            state: Mutex::new(state),
+        });
+        arenas.push(arena.clone());
+
+        Ok(Subbuffer::new(arena, block, requested_size))
+    }
+}
+
+unsafe impl DeviceOwned for SubbufferAllocator {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+// A large buffer that subbuffers are carved out of.
+struct Arena {
+    buffer: Arc<DeviceLocalBuffer<[u8]>>,
+    state: Mutex<ArenaState>,
+}
+
+// A range of an arena that has been handed out.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Block {
+    offset: DeviceSize,
+    size: DeviceSize,
+}
+
+// Book-keeping of the free ranges of an arena.
+#[derive(Debug)]
+enum ArenaState {
+    FreeList {
+        size: DeviceSize,
+        // Free ranges as `(offset, size)`, sorted by offset and never adjacent.
+        free: Vec<(DeviceSize, DeviceSize)>,
+    },
+    Buddy {
+        // Size of the blocks of order 0. Always a power of two.
+        min_block_size: DeviceSize,
+        // Offsets of the free blocks, indexed by order. The blocks of order `n` are
+        // `min_block_size << n` bytes large.
+        free: Vec<Vec<DeviceSize>>,
+    },
+}
+
+impl ArenaState {
+    fn new(strategy: AllocationStrategy, size: DeviceSize, alignment: DeviceSize) -> ArenaState {
+        match strategy {
+            AllocationStrategy::FreeList => ArenaState::FreeList {
+                size,
+                free: vec![(0, size)],
+            },
+            AllocationStrategy::Buddy => {
+                debug_assert!(size.is_power_of_two() && size >= alignment);
+                let max_order = (size / alignment).trailing_zeros() as usize;
+                let mut free = vec![Vec::new(); max_order + 1];
+                free[max_order].push(0);
+
+                ArenaState::Buddy {
+                    min_block_size: alignment,
+                    free,
+                }
+            }
+        }
+    }
+
+    // Returns true if nothing is allocated in the arena.
+    fn is_empty(&self) -> bool {
+        match self {
+            ArenaState::FreeList { size, free } => free.len() == 1 && free[0] == (0, *size),
+            ArenaState::Buddy { free, .. } => free.last().unwrap().len() == 1,
+        }
+    }
+
+    fn alloc(&mut self, size: DeviceSize, align: DeviceSize) -> Option<Block> {
+        match self {
+            ArenaState::FreeList { free, .. } => {
+                let (index, offset) =
+                    free.iter().enumerate().find_map(|(index, &(start, len))| {
+                        let offset = (start + align - 1) / align * align;
+                        if offset + size <= start + len {
+                            Some((index, offset))
+                        } else {
+                            None
+                        }
+                    })?;
+
+                // Split the free range into what remains before and after the block.
+                let (start, len) = free.remove(index);
+                let end = offset + size;
+                if end < start + len {
+                    free.insert(index, (end, start + len - end));
+                }
+                if start < offset {
+                    free.insert(index, (start, offset - start));
+                }
+
+                Some(Block { offset, size })
+            }
+            ArenaState::Buddy {
+                min_block_size,
+                free,
+            } => {
+                let block_size = cmp::max(size, align).next_power_of_two();
+                let order = (cmp::max(block_size, *min_block_size) / *min_block_size)
+                    .trailing_zeros() as usize;
+
+                let mut current = (order..free.len()).find(|&o| !free[o].is_empty())?;
+                let offset = free[current].pop().unwrap();
+
+                // Split the block until it has the right order, freeing the upper halves.
+                while current > order {
+                    current -= 1;
+                    free[current].push(offset + (*min_block_size << current));
+                }
+
+                Some(Block {
+                    offset,
+                    size: *min_block_size << order,
+                })
+            }
+        }
+    }
+
+    fn free(&mut self, block: Block) {
+        match self {
+            ArenaState::FreeList { free, .. } => {
+                let index = free
+                    .iter()
+                    .position(|&(start, _)| start > block.offset)
+                    .unwrap_or(free.len());
+                free.insert(index, (block.offset, block.size));
+
+                // Merge with the next range, then with the previous one.
+                if index + 1 < free.len() && block.offset + block.size == free[index + 1].0 {
+                    free[index].1 += free.remove(index + 1).1;
+                }
+                if index > 0 && free[index - 1].0 + free[index - 1].1 == block.offset {
+                    free[index - 1].1 += free.remove(index).1;
+                }
+            }
+            ArenaState::Buddy {
+                min_block_size,
+                free,
+            } => {
+                let mut order = (block.size / *min_block_size).trailing_zeros() as usize;
+                let mut offset = block.offset;
+
+                while order + 1 < free.len() {
+                    let buddy = offset ^ (*min_block_size << order);
+
+                    match free[order].iter().position(|&o| o == buddy) {
+                        Some(index) => {
+                            free[order].swap_remove(index);
+                            offset = cmp::min(offset, buddy);
+                            order += 1;
+                        }
+                        None => break,
+                    }
+                }
+
+                free[order].push(offset);
+            }
+        }
+    }
+}
+
+/// A range of an arena of a `SubbufferAllocator`.
+///
+/// The range is given back to the allocator when the `Subbuffer` is dropped.
+pub struct Subbuffer<T: ?Sized> {
+    arena: Arc<Arena>,
+    block: Block,
+    // The size that was requested, which can be smaller than the block.
+    size: DeviceSize,
+    // Number of times this subbuffer is locked on the GPU side.
+    gpu_lock: Mutex<GpuAccess>,
+    marker: PhantomData<Box<T>>,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum GpuAccess {
+    None,
+    NonExclusive { num: u32 },
+    Exclusive { num: u32 },
+}
+
+impl<T: ?Sized> Subbuffer<T> {
+    fn new(arena: Arc<Arena>, block: Block, size: DeviceSize) -> Arc<Subbuffer<T>> {
+        Arc::new(Subbuffer {
+            arena,
+            block,
+            size,
+            gpu_lock: Mutex::new(GpuAccess::None),
+            marker: PhantomData,
+        })
+    }
+
+    /// Returns the offset in bytes of the subbuffer in its arena.
+    #[inline]
+    pub fn offset(&self) -> DeviceSize {
+        self.block.offset
+    }
+}
+
+unsafe impl<T: ?Sized> DeviceOwned for Subbuffer<T> {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.arena.buffer.device()
+    }
+}
+
+unsafe impl<T> BufferAccess for Subbuffer<T>
+where
+    T: Send + Sync + ?Sized,
+{
+    #[inline]
+    fn inner(&self) -> BufferInner {
+        let inner = self.arena.buffer.inner();
+
+        BufferInner {
+            buffer: inner.buffer,
+            offset: inner.offset + self.block.offset,
+        }
+    }
+
+    #[inline]
+    fn size(&self) -> DeviceSize {
+        self.size
+    }
+
+    #[inline]
+    fn conflict_key(&self) -> (u64, u64) {
+        (self.inner().buffer.key(), self.block.offset)
+    }
+
+    #[inline]
+    fn try_gpu_lock(&self, exclusive: bool, _: &Queue) -> Result<(), AccessError> {
+        let mut lock = self.gpu_lock.lock().unwrap();
+        match *lock {
+            GpuAccess::None => {
+                if exclusive {
+                    *lock = GpuAccess::Exclusive { num: 1 };
+                } else {
+                    *lock = GpuAccess::NonExclusive { num: 1 };
+                }
+
+                Ok(())
+            }
+            GpuAccess::NonExclusive { ref mut num } => {
+                if exclusive {
+                    Err(AccessError::AlreadyInUse)
+                } else {
+                    *num += 1;
+                    Ok(())
+                }
+            }
+            GpuAccess::Exclusive { .. } => Err(AccessError::AlreadyInUse),
+        }
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self) {
+        let mut lock = self.gpu_lock.lock().unwrap();
+        match *lock {
+            GpuAccess::None => panic!(),
+            GpuAccess::NonExclusive { ref mut num } | GpuAccess::Exclusive { ref mut num } => {
+                debug_assert!(*num >= 1);
+                *num += 1;
+            }
+        }
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        let mut lock = self.gpu_lock.lock().unwrap();
+
+        match *lock {
+            GpuAccess::None => panic!("Tried to unlock a buffer that isn't locked"),
+            GpuAccess::NonExclusive { ref mut num } | GpuAccess::Exclusive { ref mut num } => {
+                assert!(*num >= 1);
+                *num -= 1;
+                if *num >= 1 {
+                    return;
+                }
+            }
+        };
+
+        *lock = GpuAccess::None;
+    }
+}
+
+unsafe impl<T> TypedBufferAccess for Subbuffer<T>
+where
+    T: Send + Sync + ?Sized,
+{
+    type Content = T;
+}
+
+impl<T: ?Sized> Drop for Subbuffer<T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.arena.state.lock().unwrap().free(self.block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AllocationStrategy;
+    use super::ArenaState;
+    use super::Block;
+    use super::SubbufferAllocator;
+    use crate::buffer::BufferAccess;
+    use crate::buffer::BufferUsage;
+
+    #[test]
+    fn free_list_reuse_and_merge() {
+        let mut state = ArenaState::new(AllocationStrategy::FreeList, 256, 16);
+
+        let a = state.alloc(10, 16).unwrap();
+        let b = state.alloc(100, 16).unwrap();
+        let c = state.alloc(32, 64).unwrap();
+        assert_eq!(
+            a,
+            Block {
+                offset: 0,
+                size: 10
+            }
+        );
+        assert_eq!(
+            b,
+            Block {
+                offset: 16,
+                size: 100
+            }
+        );
+        assert_eq!(
+            c,
+            Block {
+                offset: 128,
+                size: 32
+            }
+        );
+        assert!(state.alloc(128, 16).is_none());
+
+        state.free(b);
+        assert_eq!(
+            state.alloc(96, 16),
+            Some(Block {
+                offset: 16,
+                size: 96
+            })
+        );
+
+        state.free(Block {
+            offset: 16,
+            size: 96,
+        });
+        state.free(a);
+        state.free(c);
+        assert!(state.is_empty());
+    }
+
+    #[test]
+    fn buddy_split_and_merge() {
+        let mut state = ArenaState::new(AllocationStrategy::Buddy, 256, 16);
+
+        let a = state.alloc(10, 16).unwrap();
+        let b = state.alloc(100, 16).unwrap();
+        let c = state.alloc(20, 16).unwrap();
+        assert_eq!(
+            a,
+            Block {
+                offset: 0,
+                size: 16
+            }
+        );
+        assert_eq!(
+            b,
+            Block {
+                offset: 128,
+                size: 128
+            }
+        );
+        assert_eq!(
+            c,
+            Block {
+                offset: 32,
+                size: 32
+            }
+        );
+        assert!(state.alloc(128, 16).is_none());
+
+        state.free(b);
+        state.free(a);
+        state.free(c);
+        assert!(state.is_empty());
+        assert_eq!(
+            state.alloc(256, 16),
+            Some(Block {
+                offset: 0,
+                size: 256
+            })
+        );
+    }
+
+    #[test]
+    fn subbuffers_share_arenas() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        for &strategy in &[AllocationStrategy::FreeList, AllocationStrategy::Buddy] {
+            let allocator = SubbufferAllocator::new(
+                device.clone(),
+                BufferUsage::all(),
+                Some(queue.family()),
+                4096,
+                strategy,
+            );
+
+            let a = allocator.alloc_array::<u32>(64).unwrap();
+            let b = allocator.alloc::<[f32; 4]>().unwrap();
+            assert_eq!(allocator.arena_count(), 1);
+            assert_eq!(a.size(), 256);
+            assert_eq!(a.inner().buffer, b.inner().buffer);
+            assert_ne!(a.conflict_key(), b.conflict_key());
+            assert_eq!(b.inner().offset % allocator.alignment(), 0);
+
+            // Too large for the existing arena.
+            let c = allocator.alloc_array::<u8>(8192).unwrap();
+            assert_eq!(allocator.arena_count(), 2);
+
+            drop(c);
+            allocator.free_empty_arenas();
+            assert_eq!(allocator.arena_count(), 1);
+
+            drop((a, b));
+            allocator.free_empty_arenas();
+            assert_eq!(allocator.arena_count(), 0);
+        }
+    }
+}
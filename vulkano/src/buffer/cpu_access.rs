@@ -22,6 +22,7 @@ use crate::buffer::traits::BufferAccess;
 use crate::buffer::traits::BufferInner;
 use crate::buffer::traits::TypedBufferAccess;
 use crate::buffer::BufferUsage;
+use crate::device::physical::MemoryType;
 use crate::device::physical::QueueFamily;
 use crate::device::Device;
 use crate::device::DeviceOwned;
@@ -31,12 +32,15 @@ use crate::memory::pool::AllocLayout;
 use crate::memory::pool::MappingRequirement;
 use crate::memory::pool::MemoryPool;
 use crate::memory::pool::MemoryPoolAlloc;
+use crate::memory::pool::MemoryUsage;
 use crate::memory::pool::PotentialDedicatedAllocation;
 use crate::memory::pool::StdMemoryPoolAlloc;
 use crate::memory::Content;
 use crate::memory::CpuAccess as MemCpuAccess;
+use crate::memory::CpuReadAccess as MemCpuReadAccess;
 use crate::memory::DedicatedAlloc;
 use crate::memory::DeviceMemoryAllocError;
+use crate::memory::MemoryRequirements;
 use crate::sync::AccessError;
 use crate::sync::Sharing;
 use crate::DeviceSize;
@@ -222,6 +226,88 @@ impl<T: ?Sized> CpuAccessibleBuffer<T> {
     where
         I: IntoIterator<Item = QueueFamily<'a>>,
     {
+        CpuAccessibleBuffer::raw_with_pool(
+            &Device::standard_pool(&device),
+            size,
+            usage,
+            host_cached,
+            queue_families,
+        )
+    }
+
+    /// Same as `raw`, but chooses the memory type according to `memory_usage` instead of
+    /// `host_cached`.
+    ///
+    /// With `MemoryUsage::DeviceLocalHostWritable`, the buffer is put in device-local memory
+    /// if possible, which makes data that the CPU writes every frame faster to read for the GPU.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that the size that you pass is correct for `T`.
+    ///
+    pub unsafe fn raw_with_memory_usage<'a, I>(
+        device: Arc<Device>,
+        size: DeviceSize,
+        usage: BufferUsage,
+        memory_usage: MemoryUsage,
+        queue_families: I,
+    ) -> Result<Arc<CpuAccessibleBuffer<T>>, DeviceMemoryAllocError>
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        CpuAccessibleBuffer::raw_impl(
+            &Device::standard_pool(&device),
+            size,
+            usage,
+            queue_families,
+            |mem_reqs| memory_usage.filter(&device, mem_reqs.size),
+        )
+    }
+
+    /// Same as `raw`, but allocates the memory of the buffer from `pool` instead of the standard
+    /// pool of the device.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that the size that you pass is correct for `T`.
+    ///
+    pub unsafe fn raw_with_pool<'a, I, P>(
+        pool: &P,
+        size: DeviceSize,
+        usage: BufferUsage,
+        host_cached: bool,
+        queue_families: I,
+    ) -> Result<
+        Arc<CpuAccessibleBuffer<T, PotentialDedicatedAllocation<P::Alloc>>>,
+        DeviceMemoryAllocError,
+    >
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+        P: MemoryPool,
+    {
+        CpuAccessibleBuffer::raw_impl(pool, size, usage, queue_families, |_| {
+            host_cached_filter(host_cached)
+        })
+    }
+
+    // `make_filter` returns the filter of the memory types, given the requirements of the buffer.
+    unsafe fn raw_impl<'a, I, P, M, F>(
+        pool: &P,
+        size: DeviceSize,
+        usage: BufferUsage,
+        queue_families: I,
+        make_filter: M,
+    ) -> Result<
+        Arc<CpuAccessibleBuffer<T, PotentialDedicatedAllocation<P::Alloc>>>,
+        DeviceMemoryAllocError,
+    >
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+        P: MemoryPool,
+        M: FnOnce(&MemoryRequirements) -> F,
+        F: FnMut(MemoryType) -> AllocFromRequirementsFilter,
+    {
+        let device = pool.device();
         let queue_families = queue_families
             .into_iter()
             .map(|f| f.id())
@@ -243,26 +329,12 @@ impl<T: ?Sized> CpuAccessibleBuffer<T> {
         };
 
         let mem = MemoryPool::alloc_from_requirements(
-            &Device::standard_pool(&device),
+            pool,
             &mem_reqs,
             AllocLayout::Linear,
             MappingRequirement::Map,
             DedicatedAlloc::Buffer(&buffer),
-            |m| {
-                if m.is_host_cached() {
-                    if host_cached {
-                        AllocFromRequirementsFilter::Preferred
-                    } else {
-                        AllocFromRequirementsFilter::Allowed
-                    }
-                } else {
-                    if host_cached {
-                        AllocFromRequirementsFilter::Allowed
-                    } else {
-                        AllocFromRequirementsFilter::Preferred
-                    }
-                }
-            },
+            make_filter(&mem_reqs),
         )?;
         debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
         debug_assert!(mem.mapped_memory().is_some());
@@ -329,7 +401,7 @@ where
         let range = offset..offset + self.inner.size();
 
         Ok(ReadLock {
-            inner: unsafe { self.memory.mapped_memory().unwrap().read_write(range) },
+            inner: unsafe { self.memory.mapped_memory().unwrap().read(range) },
             lock: lock,
         })
     }
@@ -530,12 +602,31 @@ where
     }
 }
 
+// Returns the filter of the memory types of a buffer created with `host_cached`.
+fn host_cached_filter(host_cached: bool) -> impl FnMut(MemoryType) -> AllocFromRequirementsFilter {
+    move |m| {
+        if m.is_host_cached() {
+            if host_cached {
+                AllocFromRequirementsFilter::Preferred
+            } else {
+                AllocFromRequirementsFilter::Allowed
+            }
+        } else {
+            if host_cached {
+                AllocFromRequirementsFilter::Allowed
+            } else {
+                AllocFromRequirementsFilter::Preferred
+            }
+        }
+    }
+}
+
 /// Object that can be used to read or write the content of a `CpuAccessibleBuffer`.
 ///
 /// Note that this object holds a rwlock read guard on the chunk. If another thread tries to access
 /// this buffer's content or tries to submit a GPU command that uses this buffer, it will block.
 pub struct ReadLock<'a, T: ?Sized + 'a> {
-    inner: MemCpuAccess<'a, T>,
+    inner: MemCpuReadAccess<'a, T>,
     lock: RwLockReadGuard<'a, CurrentGpuAccess>,
 }
 
@@ -19,10 +19,14 @@ use crate::buffer::traits::BufferAccess;
 use crate::buffer::traits::BufferInner;
 use crate::buffer::traits::TypedBufferAccess;
 use crate::buffer::BufferUsage;
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::PrimaryAutoCommandBuffer;
 use crate::device::physical::QueueFamily;
 use crate::device::Device;
 use crate::device::DeviceOwned;
 use crate::device::Queue;
+use crate::memory::defragmentation::DefragmentationError;
+use crate::memory::defragmentation::Relocate;
 #[cfg(any(
     target_os = "linux",
     target_os = "dragonflybsd",
@@ -36,9 +40,15 @@ use crate::memory::pool::AllocLayout;
 use crate::memory::pool::MappingRequirement;
 use crate::memory::pool::MemoryPool;
 use crate::memory::pool::MemoryPoolAlloc;
+use crate::memory::pool::MemoryUsage;
 use crate::memory::pool::PotentialDedicatedAllocation;
 use crate::memory::pool::StdMemoryPoolAlloc;
+use crate::memory::DeviceMemory;
 use crate::memory::DeviceMemoryAllocError;
+use crate::memory::DeviceMemoryBuilder;
+use crate::memory::ExternalMemoryHandleType;
+#[cfg(target_os = "windows")]
+use crate::memory::Win32Handle;
 use crate::memory::{DedicatedAlloc, MemoryRequirements};
 use crate::sync::AccessError;
 use crate::sync::Sharing;
@@ -48,6 +58,7 @@ use std::hash::Hash;
 use std::hash::Hasher;
 use std::marker::PhantomData;
 use std::mem;
+use std::os::raw::c_void;
 use std::sync::Arc;
 use std::sync::Mutex;
 #[cfg(any(
@@ -57,7 +68,10 @@ use std::sync::Mutex;
     target_os = "netbsd",
     target_os = "openbsd"
 ))]
-use {crate::memory::ExternalMemoryHandleType, std::fs::File};
+use {
+    crate::check_errors, crate::VulkanObject, std::fs::File, std::mem::MaybeUninit,
+    std::os::unix::io::AsRawFd,
+};
 
 /// Buffer whose content is in device-local memory.
 ///
@@ -154,27 +168,55 @@ impl<T: ?Sized> DeviceLocalBuffer<T> {
     ) -> Result<Arc<DeviceLocalBuffer<T>>, DeviceMemoryAllocError>
     where
         I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        DeviceLocalBuffer::raw_with_pool(
+            &Device::standard_pool(&device),
+            size,
+            usage,
+            queue_families,
+        )
+    }
+
+    /// Same as `raw`, but allocates the memory of the buffer from `pool` instead of the standard
+    /// pool of the device.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that the size that you pass is correct for `T`.
+    ///
+    pub unsafe fn raw_with_pool<'a, I, P>(
+        pool: &P,
+        size: DeviceSize,
+        usage: BufferUsage,
+        queue_families: I,
+    ) -> Result<
+        Arc<DeviceLocalBuffer<T, PotentialDedicatedAllocation<P::Alloc>>>,
+        DeviceMemoryAllocError,
+    >
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+        P: MemoryPool,
     {
         let queue_families = queue_families
             .into_iter()
             .map(|f| f.id())
             .collect::<SmallVec<[u32; 4]>>();
 
-        let (buffer, mem_reqs) = Self::build_buffer(&device, size, usage, &queue_families)?;
+        let (buffer, mem_reqs) = Self::build_buffer(
+            pool.device(),
+            size,
+            usage,
+            &queue_families,
+            ExternalMemoryHandleType::none(),
+        )?;
 
         let mem = MemoryPool::alloc_from_requirements(
-            &Device::standard_pool(&device),
+            pool,
             &mem_reqs,
             AllocLayout::Linear,
             MappingRequirement::DoNotMap,
             DedicatedAlloc::Buffer(&buffer),
-            |t| {
-                if t.is_device_local() {
-                    AllocFromRequirementsFilter::Preferred
-                } else {
-                    AllocFromRequirementsFilter::Allowed
-                }
-            },
+            MemoryUsage::GpuOnly.filter(pool.device(), mem_reqs.size),
         )?;
         debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
         buffer.bind_memory(mem.memory(), mem.offset())?;
@@ -213,7 +255,13 @@ impl<T: ?Sized> DeviceLocalBuffer<T> {
             .map(|f| f.id())
             .collect::<SmallVec<[u32; 4]>>();
 
-        let (buffer, mem_reqs) = Self::build_buffer(&device, size, usage, &queue_families)?;
+        let (buffer, mem_reqs) = Self::build_buffer(
+            &device,
+            size,
+            usage,
+            &queue_families,
+            ExternalMemoryHandleType::posix(),
+        )?;
 
         let mem = alloc_dedicated_with_exportable_fd(
             device.clone(),
@@ -242,11 +290,268 @@ impl<T: ?Sized> DeviceLocalBuffer<T> {
         }))
     }
 
+    /// Same as `raw`, but the memory of the buffer is a dedicated allocation that can be
+    /// exported as any of `handle_types` with [`export_fd`](DeviceLocalBuffer::export_fd) or
+    /// [`export_win32_handle`](DeviceLocalBuffer::export_win32_handle).
+    ///
+    /// The extensions that correspond to `handle_types` must be enabled on the device.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that the size that you pass is correct for `T`.
+    ///
+    pub unsafe fn raw_with_exportable_handles<'a, I>(
+        device: Arc<Device>,
+        size: DeviceSize,
+        usage: BufferUsage,
+        queue_families: I,
+        handle_types: ExternalMemoryHandleType,
+    ) -> Result<Arc<DeviceLocalBuffer<T>>, DeviceMemoryAllocError>
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        let queue_families = queue_families
+            .into_iter()
+            .map(|f| f.id())
+            .collect::<SmallVec<[u32; 4]>>();
+
+        let (buffer, mem_reqs) =
+            Self::build_buffer(&device, size, usage, &queue_families, handle_types)?;
+        let memory_type = external_memory_type(&device, mem_reqs.memory_type_bits)?;
+
+        let memory = DeviceMemoryBuilder::new(device, memory_type, mem_reqs.size)
+            .export_info(handle_types)
+            .dedicated_info(DedicatedAlloc::Buffer(&buffer))
+            .build()?;
+
+        Self::from_external_memory(buffer, memory, 0, queue_families)
+    }
+
+    /// Builds a buffer whose memory is imported from `fd`, for example a dma-buf exported by
+    /// another API or an opaque file descriptor exported by another Vulkan device.
+    ///
+    /// On success, the ownership of `fd` is transferred to the Vulkan implementation, which
+    /// closes it when the memory is freed. On failure, the file descriptor is closed too.
+    ///
+    /// # Safety
+    ///
+    /// - You must ensure that the size that you pass is correct for `T`.
+    /// - `fd` must be a valid handle of type `handle_type`, at least `size` bytes large. If it is
+    ///   an opaque file descriptor, it must have been exported from a device with the same
+    ///   `device_uuid` and `driver_uuid` as `device`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `handle_type` isn't exactly one of `opaque_fd` or `dma_buf`.
+    ///
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonflybsd",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub unsafe fn import_fd<'a, I>(
+        device: Arc<Device>,
+        size: DeviceSize,
+        usage: BufferUsage,
+        queue_families: I,
+        fd: File,
+        handle_type: ExternalMemoryHandleType,
+    ) -> Result<Arc<DeviceLocalBuffer<T>>, DeviceMemoryAllocError>
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        assert!(
+            handle_type
+                == ExternalMemoryHandleType {
+                    opaque_fd: true,
+                    ..ExternalMemoryHandleType::none()
+                }
+                || handle_type
+                    == ExternalMemoryHandleType {
+                        dma_buf: true,
+                        ..ExternalMemoryHandleType::none()
+                    }
+        );
+
+        let queue_families = queue_families
+            .into_iter()
+            .map(|f| f.id())
+            .collect::<SmallVec<[u32; 4]>>();
+
+        let (buffer, mem_reqs) =
+            Self::build_buffer(&device, size, usage, &queue_families, handle_type)?;
+        let mut memory_type_bits = mem_reqs.memory_type_bits;
+
+        // The memory types that an opaque file descriptor can be imported into are the same as
+        // on the exporting side, but the other handle types must be queried.
+        if handle_type.dma_buf {
+            if !device.enabled_extensions().khr_external_memory_fd {
+                return Err(DeviceMemoryAllocError::MissingExtension(
+                    "khr_external_memory_fd",
+                ));
+            }
+
+            let fns = device.fns();
+            let mut properties = MaybeUninit::<ash::vk::MemoryFdPropertiesKHR>::zeroed();
+            (*properties.as_mut_ptr()).s_type = ash::vk::StructureType::MEMORY_FD_PROPERTIES_KHR;
+            check_errors(fns.khr_external_memory_fd.get_memory_fd_properties_khr(
+                device.internal_object(),
+                handle_type.into(),
+                fd.as_raw_fd(),
+                properties.as_mut_ptr(),
+            ))?;
+            memory_type_bits &= properties.assume_init().memory_type_bits;
+        }
+
+        let memory_type = external_memory_type(&device, memory_type_bits)?;
+        let memory = DeviceMemoryBuilder::new(device, memory_type, mem_reqs.size)
+            .import_info(fd, handle_type)
+            .dedicated_info(DedicatedAlloc::Buffer(&buffer))
+            .build()?;
+
+        Self::from_external_memory(buffer, memory, 0, queue_families)
+    }
+
+    /// Builds a buffer whose memory is imported from a Windows handle.
+    ///
+    /// Contrary to file descriptors, the ownership of `handle` is not transferred: it can be
+    /// closed as soon as this function returns, and the memory stays alive until the buffer is
+    /// destroyed.
+    ///
+    /// # Safety
+    ///
+    /// - You must ensure that the size that you pass is correct for `T`.
+    /// - `handle` must be a valid handle of type `handle_type`, at least `size` bytes large, that
+    ///   was exported from a device with the same `device_uuid` and `driver_uuid` as `device`.
+    ///
+    #[cfg(target_os = "windows")]
+    pub unsafe fn import_win32_handle<'a, I>(
+        device: Arc<Device>,
+        size: DeviceSize,
+        usage: BufferUsage,
+        queue_families: I,
+        handle: *mut c_void,
+        handle_type: ExternalMemoryHandleType,
+    ) -> Result<Arc<DeviceLocalBuffer<T>>, DeviceMemoryAllocError>
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        let queue_families = queue_families
+            .into_iter()
+            .map(|f| f.id())
+            .collect::<SmallVec<[u32; 4]>>();
+
+        let (buffer, mem_reqs) =
+            Self::build_buffer(&device, size, usage, &queue_families, handle_type)?;
+        let memory_type = external_memory_type(&device, mem_reqs.memory_type_bits)?;
+
+        let memory = DeviceMemoryBuilder::new(device, memory_type, mem_reqs.size)
+            .import_win32_info(handle, handle_type)
+            .dedicated_info(DedicatedAlloc::Buffer(&buffer))
+            .build()?;
+
+        Self::from_external_memory(buffer, memory, 0, queue_families)
+    }
+
+    /// Builds a buffer whose memory is the host allocation of `size` bytes at `pointer`, for
+    /// example a memory-mapped file. The device reads and writes the host allocation directly,
+    /// without any copy.
+    ///
+    /// The imported range is extended to the `min_imported_host_pointer_alignment` property of
+    /// the physical device on both sides, which is usually the page size. The buffer is then
+    /// bound at the offset of `pointer` within that range, which must be a multiple of the
+    /// alignment required by the buffer.
+    ///
+    /// Requires the `ext_external_memory_host` extension.
+    ///
+    /// # Safety
+    ///
+    /// - You must ensure that the size that you pass is correct for `T`.
+    /// - The pages containing `pointer..pointer + size` must be valid host memory of type
+    ///   `handle_type`, and must outlive the buffer and any command buffer using it.
+    /// - The content of the allocation must not be accessed by the host while the device accesses
+    ///   the buffer.
+    ///
+    pub unsafe fn import_host_pointer<'a, I>(
+        device: Arc<Device>,
+        size: DeviceSize,
+        usage: BufferUsage,
+        queue_families: I,
+        pointer: *mut c_void,
+        handle_type: ExternalMemoryHandleType,
+    ) -> Result<Arc<DeviceLocalBuffer<T>>, DeviceMemoryAllocError>
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        if !device.enabled_extensions().ext_external_memory_host {
+            return Err(DeviceMemoryAllocError::MissingExtension(
+                "ext_external_memory_host",
+            ));
+        }
+
+        let alignment = device
+            .physical_device()
+            .properties()
+            .min_imported_host_pointer_alignment
+            .unwrap();
+        let offset = pointer as DeviceSize % alignment;
+        let base = (pointer as *mut u8).offset(-(offset as isize)) as *mut c_void;
+
+        let queue_families = queue_families
+            .into_iter()
+            .map(|f| f.id())
+            .collect::<SmallVec<[u32; 4]>>();
+
+        let (buffer, mem_reqs) =
+            Self::build_buffer(&device, size, usage, &queue_families, handle_type)?;
+
+        // VUID-vkBindBufferMemory-memoryOffset-01036: "memoryOffset must be an integer multiple of
+        // the alignment member of the VkMemoryRequirements structure returned from a call to
+        // vkGetBufferMemoryRequirements with buffer".
+        if offset % mem_reqs.alignment != 0 {
+            return Err(DeviceMemoryAllocError::SpecViolation(1036));
+        }
+
+        let allocation_size = (offset + mem_reqs.size + alignment - 1) / alignment * alignment;
+        let memory_type_bits = mem_reqs.memory_type_bits
+            & DeviceMemory::host_pointer_memory_type_bits(&device, base, handle_type)?;
+        let memory_type = external_memory_type(&device, memory_type_bits)?;
+
+        let memory = DeviceMemoryBuilder::new(device, memory_type, allocation_size)
+            .import_host_pointer(base, handle_type)
+            .build()?;
+
+        Self::from_external_memory(buffer, memory, offset, queue_families)
+    }
+
+    unsafe fn from_external_memory(
+        buffer: UnsafeBuffer,
+        memory: Arc<DeviceMemory>,
+        offset: DeviceSize,
+        queue_families: SmallVec<[u32; 4]>,
+    ) -> Result<Arc<DeviceLocalBuffer<T>>, DeviceMemoryAllocError> {
+        // Will never panic because the builder only returns an atomically refcounted
+        // DeviceMemory object on success.
+        let memory = Arc::try_unwrap(memory).unwrap();
+        buffer.bind_memory(&memory, offset)?;
+
+        Ok(Arc::new(DeviceLocalBuffer {
+            inner: buffer,
+            memory: PotentialDedicatedAllocation::Dedicated(memory),
+            queue_families: queue_families,
+            gpu_lock: Mutex::new(GpuAccess::None),
+            marker: PhantomData,
+        }))
+    }
+
     unsafe fn build_buffer(
         device: &Arc<Device>,
         size: DeviceSize,
         usage: BufferUsage,
         queue_families: &SmallVec<[u32; 4]>,
+        external_memory: ExternalMemoryHandleType,
     ) -> Result<(UnsafeBuffer, MemoryRequirements), DeviceMemoryAllocError> {
         let (buffer, mem_reqs) = {
             let sharing = if queue_families.len() >= 2 {
@@ -255,9 +560,26 @@ impl<T: ?Sized> DeviceLocalBuffer<T> {
                 Sharing::Exclusive
             };
 
-            match UnsafeBuffer::new(device.clone(), size, usage, sharing, None) {
+            let result = if external_memory == ExternalMemoryHandleType::none() {
+                UnsafeBuffer::new(device.clone(), size, usage, sharing, None)
+            } else {
+                UnsafeBuffer::new_with_external_memory(
+                    device.clone(),
+                    size,
+                    usage,
+                    sharing,
+                    external_memory,
+                )
+            };
+
+            match result {
                 Ok(b) => b,
                 Err(BufferCreationError::AllocError(err)) => return Err(err),
+                Err(BufferCreationError::ExternalMemoryExtensionNotEnabled) => {
+                    return Err(DeviceMemoryAllocError::MissingExtension(
+                        "khr_external_memory",
+                    ))
+                }
                 Err(_) => unreachable!(), // We don't use sparse binding, therefore the other
                                           // errors can't happen
             }
@@ -280,6 +602,62 @@ impl<T: ?Sized> DeviceLocalBuffer<T> {
             .memory()
             .export_fd(ExternalMemoryHandleType::posix())
     }
+
+    /// Exports the memory of the buffer as a file descriptor of type `handle_type`.
+    ///
+    /// The buffer must have been created with
+    /// [`raw_with_exportable_handles`](DeviceLocalBuffer::raw_with_exportable_handles) or
+    /// [`raw_with_exportable_fd`](DeviceLocalBuffer::raw_with_exportable_fd), with
+    /// `handle_type` included. The returned file is owned by the caller and keeps the memory
+    /// alive even after the buffer is destroyed.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonflybsd",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    #[inline]
+    pub fn export_fd(
+        &self,
+        handle_type: ExternalMemoryHandleType,
+    ) -> Result<File, DeviceMemoryAllocError> {
+        self.memory.memory().export_fd(handle_type)
+    }
+
+    /// Exports the memory of the buffer as a Windows handle of type `handle_type`.
+    ///
+    /// The buffer must have been created with
+    /// [`raw_with_exportable_handles`](DeviceLocalBuffer::raw_with_exportable_handles), with
+    /// `handle_type` included. NT handles are closed when the returned `Win32Handle` is dropped.
+    #[cfg(target_os = "windows")]
+    #[inline]
+    pub fn export_win32_handle(
+        &self,
+        handle_type: ExternalMemoryHandleType,
+    ) -> Result<Win32Handle, DeviceMemoryAllocError> {
+        self.memory.memory().export_win32_handle(handle_type)
+    }
+}
+
+// Picks the memory type of an external allocation among `memory_type_bits`, preferring
+// device-local memory.
+fn external_memory_type(
+    device: &Arc<Device>,
+    memory_type_bits: u32,
+) -> Result<u32, DeviceMemoryAllocError> {
+    let allowed = || {
+        device
+            .physical_device()
+            .memory_types()
+            .filter(|t| (memory_type_bits & (1 << t.id())) != 0)
+    };
+
+    allowed()
+        .find(|t| t.is_device_local())
+        .or_else(|| allowed().next())
+        .map(|t| t.id())
+        .ok_or(DeviceMemoryAllocError::MemoryIndexInvalid)
 }
 
 impl<T: ?Sized, A> DeviceLocalBuffer<T, A> {
@@ -306,6 +684,48 @@ unsafe impl<T: ?Sized, A> DeviceOwned for DeviceLocalBuffer<T, A> {
     }
 }
 
+// Buffers with the transfer source and destination usages can be moved by a `Defragmenter`.
+unsafe impl<T: ?Sized> Relocate for DeviceLocalBuffer<T>
+where
+    T: Send + Sync + 'static,
+{
+    #[inline]
+    fn allocation(&self) -> Option<(&DeviceMemory, DeviceSize)> {
+        let usage = self.inner.usage();
+        if !usage.transfer_source || !usage.transfer_destination {
+            return None;
+        }
+
+        match self.memory {
+            PotentialDedicatedAllocation::Generic(ref alloc) => {
+                Some((alloc.memory(), alloc.size()))
+            }
+            _ => None,
+        }
+    }
+
+    fn duplicate(&self) -> Result<Arc<Self>, DefragmentationError> {
+        unsafe {
+            Ok(DeviceLocalBuffer::raw(
+                self.device().clone(),
+                self.inner.size(),
+                self.inner.usage(),
+                self.queue_families(),
+            )?)
+        }
+    }
+
+    #[inline]
+    fn copy(
+        source: Arc<Self>,
+        destination: Arc<Self>,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) -> Result<(), DefragmentationError> {
+        builder.copy_buffer(source, destination)?;
+        Ok(())
+    }
+}
+
 unsafe impl<T: ?Sized, A> BufferAccess for DeviceLocalBuffer<T, A>
 where
     T: Send + Sync + 'static,
@@ -281,23 +281,55 @@ impl<T: ?Sized> ImmutableBuffer<T> {
         I: IntoIterator<Item = QueueFamily<'a>>,
     {
         let queue_families = queue_families.into_iter().map(|f| f.id()).collect();
-        ImmutableBuffer::raw_impl(device, size, usage, queue_families)
+        ImmutableBuffer::raw_impl(&Device::standard_pool(&device), size, usage, queue_families)
+    }
+
+    /// Same as `raw`, but allocates the memory of the buffer from `pool` instead of the standard
+    /// pool of the device.
+    ///
+    /// # Safety
+    ///
+    /// Same as `raw`.
+    ///
+    #[inline]
+    pub unsafe fn raw_with_pool<'a, I, P>(
+        pool: &P,
+        size: DeviceSize,
+        usage: BufferUsage,
+        queue_families: I,
+    ) -> Result<
+        (
+            Arc<ImmutableBuffer<T, PotentialDedicatedAllocation<P::Alloc>>>,
+            Arc<ImmutableBufferInitialization<T, PotentialDedicatedAllocation<P::Alloc>>>,
+        ),
+        DeviceMemoryAllocError,
+    >
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+        P: MemoryPool,
+    {
+        let queue_families = queue_families.into_iter().map(|f| f.id()).collect();
+        ImmutableBuffer::raw_impl(pool, size, usage, queue_families)
     }
 
     // Internal implementation of `raw`. This is separated from `raw` so that it doesn't need to be
     // inlined.
-    unsafe fn raw_impl(
-        device: Arc<Device>,
+    unsafe fn raw_impl<P>(
+        pool: &P,
         size: DeviceSize,
         usage: BufferUsage,
         queue_families: SmallVec<[u32; 4]>,
     ) -> Result<
         (
-            Arc<ImmutableBuffer<T>>,
-            Arc<ImmutableBufferInitialization<T>>,
+            Arc<ImmutableBuffer<T, PotentialDedicatedAllocation<P::Alloc>>>,
+            Arc<ImmutableBufferInitialization<T, PotentialDedicatedAllocation<P::Alloc>>>,
         ),
         DeviceMemoryAllocError,
-    > {
+    >
+    where
+        P: MemoryPool,
+    {
+        let device = pool.device();
         let (buffer, mem_reqs) = {
             let sharing = if queue_families.len() >= 2 {
                 Sharing::Concurrent(queue_families.iter().cloned())
@@ -314,7 +346,7 @@ impl<T: ?Sized> ImmutableBuffer<T> {
         };
 
         let mem = MemoryPool::alloc_from_requirements(
-            &Device::standard_pool(&device),
+            pool,
             &mem_reqs,
             AllocLayout::Linear,
             MappingRequirement::DoNotMap,
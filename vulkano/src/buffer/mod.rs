@@ -33,8 +33,13 @@
 //!   and writes.
 //! - A [`CpuBufferPool`](crate::buffer::cpu_pool::CpuBufferPool) is a ring buffer that can be used to
 //!   transfer data between the CPU and the GPU at a high rate.
+//! - A [`RingBuffer`](crate::buffer::ring::RingBuffer) is a persistently mapped buffer for dynamic
+//!   data that is written every frame and bound with an offset, for example dynamic uniforms.
 //! - A [`CpuAccessibleBuffer`](crate::buffer::cpu_access::CpuAccessibleBuffer) is a simple buffer that
 //!   can be used to prototype. It may be removed from vulkano in the far future.
+//! - A [`SubbufferAllocator`](crate::buffer::allocator::SubbufferAllocator) hands out sub-ranges
+//!   of a few large device-local buffers. It is a replacement for creating many small
+//!   `DeviceLocalBuffer`s, for example one per mesh.
 //!
 //! Here is a quick way to choose which buffer to use. Do you often need to read or write
 //! the content of the buffer? If so, use a `CpuBufferPool`. Otherwise, do you need to be able to
@@ -77,11 +82,15 @@
 //! for how to create a buffer view.
 //!
 
+pub use self::allocator::SubbufferAllocator;
 pub use self::cpu_access::CpuAccessibleBuffer;
 pub use self::cpu_pool::CpuBufferPool;
 pub use self::device_local::DeviceLocalBuffer;
 pub use self::immutable::ImmutableBuffer;
+pub use self::pod::Pod;
+pub use self::ring::RingBuffer;
 pub use self::slice::BufferSlice;
+pub use self::slice::CastError;
 pub use self::sys::BufferCreationError;
 pub use self::traits::BufferAccess;
 pub use self::traits::BufferInner;
@@ -90,13 +99,16 @@ pub use self::usage::BufferUsage;
 pub use self::view::BufferView;
 pub use self::view::BufferViewAbstract;
 
+pub mod allocator;
 pub mod cpu_access;
 pub mod cpu_pool;
 pub mod device_local;
 pub mod immutable;
+pub mod ring;
 pub mod sys;
 pub mod view;
 
+mod pod;
 mod slice;
 mod traits;
 mod usage;
@@ -0,0 +1,32 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+/// Plain data type that can be stored in a buffer and reinterpreted from its bytes.
+///
+/// This trait allows `BufferSlice::cast` to reinterpret the content of a buffer between types
+/// without an `unsafe` block at the call site.
+///
+/// # Safety
+///
+/// - Any bit pattern of the size of the type must be a valid value of the type.
+/// - The type must not contain any padding bytes, and should be `#[repr(C)]` or
+///   `#[repr(transparent)]` so that its layout matches the one expected by shaders.
+/// - The type must not contain any pointer or reference.
+///
+pub unsafe trait Pod: Copy + Send + Sync + 'static {}
+
+macro_rules! impl_pod {
+    ($($ty:ty),*) => {
+        $(unsafe impl Pod for $ty {})*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64, half::f16);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
@@ -0,0 +1,573 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Persistently mapped buffer for data that changes every frame.
+//!
+//! A `RingBuffer` is a single host-visible buffer that stays mapped for its whole lifetime. Each
+//! frame, data such as uniforms or dynamic vertices is written at the head of the ring, and it is
+//! bound with the whole buffer plus the offset of the allocation, for example as a dynamic offset
+//! of a descriptor set. Each frame is ended with a fence that the GPU signals once it has
+//! finished using the memory of the frame. The memory is retired, and can be overwritten, when
+//! the frame slot is reused `frames_in_flight` frames later and that fence is signaled.
+//!
+//! The device must only read the content of the buffer, which is why the usages that let it
+//! write to the buffer aren't allowed.
+
+use crate::buffer::sys::BufferCreationError;
+use crate::buffer::sys::UnsafeBuffer;
+use crate::buffer::traits::BufferAccess;
+use crate::buffer::traits::BufferInner;
+use crate::buffer::traits::TypedBufferAccess;
+use crate::buffer::BufferUsage;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::device::Queue;
+use crate::memory::pool::AllocLayout;
+use crate::memory::pool::MappingRequirement;
+use crate::memory::pool::MemoryPool;
+use crate::memory::pool::MemoryPoolAlloc;
+use crate::memory::pool::MemoryUsage;
+use crate::memory::pool::PotentialDedicatedAllocation;
+use crate::memory::pool::StdMemoryPoolAlloc;
+use crate::memory::CpuAccess;
+use crate::memory::DedicatedAlloc;
+use crate::memory::DeviceMemoryAllocError;
+use crate::sync::AccessError;
+use crate::sync::FenceSignalFuture;
+use crate::sync::FlushError;
+use crate::sync::GpuFuture;
+use crate::sync::Sharing;
+use crate::DeviceSize;
+use std::cmp;
+use std::error;
+use std::fmt;
+use std::iter;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// Persistently mapped ring buffer, whose memory is retired one frame slot at a time.
+///
+/// # Example
+///
+/// ```
+/// use vulkano::buffer::BufferUsage;
+/// use vulkano::buffer::RingBuffer;
+/// use vulkano::sync::GpuFuture;
+/// use std::sync::Arc;
+/// # let device: std::sync::Arc<vulkano::device::Device> = return;
+/// # let queue: std::sync::Arc<vulkano::device::Queue> = return;
+///
+/// let ring = RingBuffer::new(device.clone(), BufferUsage::uniform_buffer(), 1024 * 1024, 2)
+///     .unwrap();
+///
+/// // Each frame:
+/// let mut allocation = ring.allocate(64, 16).unwrap();
+/// allocation.copy_from_slice(&[0; 64]);
+/// let dynamic_offset = allocation.offset();
+/// drop(allocation);
+///
+/// // ... submit the command buffers that use the allocations of the frame ...
+/// let future = vulkano::sync::now(device.clone())
+///     .then_signal_fence_and_flush()
+///     .unwrap();
+/// ring.end_frame(Arc::new(future)).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct RingBuffer {
+    inner: UnsafeBuffer,
+    memory: PotentialDedicatedAllocation<StdMemoryPoolAlloc>,
+    // Minimum alignment of the allocations, in bytes. Always a power of two.
+    alignment: DeviceSize,
+    // If the memory isn't host-coherent, the `non_coherent_atom_size` of the device. Otherwise 1.
+    atom_size: DeviceSize,
+    state: Mutex<RingState>,
+}
+
+#[derive(Debug)]
+struct RingState {
+    // Offset where the next allocation starts looking for space.
+    head: DeviceSize,
+    // Offset of the oldest byte that is still in use.
+    tail: DeviceSize,
+    // Number of bytes between `tail` and `head`, including the padding.
+    used: DeviceSize,
+    // Number of bytes consumed by each frame slot, including the padding.
+    frames: Vec<DeviceSize>,
+    // Fence signaled once the device has finished using the memory of each frame slot.
+    fences: Vec<Option<Arc<dyn FrameFence>>>,
+    current_frame: usize,
+}
+
+// Type-erased `FenceSignalFuture` handed to `end_frame`.
+trait FrameFence: Send + Sync {
+    fn wait(&self) -> Result<(), FlushError>;
+}
+
+impl<F> FrameFence for FenceSignalFuture<F>
+where
+    F: GpuFuture + Send,
+{
+    #[inline]
+    fn wait(&self) -> Result<(), FlushError> {
+        FenceSignalFuture::wait(self, None)
+    }
+}
+
+impl fmt::Debug for dyn FrameFence {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<frame fence>")
+    }
+}
+
+impl RingBuffer {
+    /// Builds a new ring buffer of `size` bytes, whose memory is split between
+    /// `frames_in_flight` frame slots.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `size` or `frames_in_flight` is 0.
+    /// - Panics if `usage` lets the device write to the buffer, which is the case of
+    ///   `transfer_destination`, `storage_buffer`, `storage_texel_buffer`, `device_address`,
+    ///   `transform_feedback_buffer`, `transform_feedback_counter_buffer` and
+    ///   `acceleration_structure_storage`.
+    ///
+    pub fn new(
+        device: Arc<Device>,
+        usage: BufferUsage,
+        size: DeviceSize,
+        frames_in_flight: usize,
+    ) -> Result<Arc<RingBuffer>, DeviceMemoryAllocError> {
+        assert!(size != 0);
+        assert!(frames_in_flight != 0);
+        assert!(
+            !usage.transfer_destination
+                && !usage.storage_buffer
+                && !usage.storage_texel_buffer
+                && !usage.device_address
+                && !usage.transform_feedback_buffer
+                && !usage.transform_feedback_counter_buffer
+                && !usage.acceleration_structure_storage,
+            "the usage of a ring buffer must not let the device write to it"
+        );
+
+        let (buffer, mut mem_reqs) = unsafe {
+            match UnsafeBuffer::new(
+                device.clone(),
+                size,
+                usage,
+                Sharing::Exclusive::<iter::Empty<_>>,
+                None,
+            ) {
+                Ok(b) => b,
+                Err(BufferCreationError::AllocError(err)) => return Err(err),
+                Err(_) => unreachable!(), // We don't use sparse binding, therefore the other
+                                          // errors can't happen
+            }
+        };
+
+        // If the memory isn't host-coherent, the offsets of the allocations in the memory must
+        // be multiples of `non_coherent_atom_size`, so that flushing an allocation doesn't
+        // flush its neighbours.
+        let properties = device.physical_device().properties();
+        mem_reqs.alignment = cmp::max(mem_reqs.alignment, properties.non_coherent_atom_size);
+
+        // The data is written by the CPU every frame and read by the GPU right after, which is
+        // what device-local host-visible memory is the fastest for.
+        let mem = MemoryPool::alloc_from_requirements(
+            &Device::standard_pool(&device),
+            &mem_reqs,
+            AllocLayout::Linear,
+            MappingRequirement::Map,
+            DedicatedAlloc::Buffer(&buffer),
+            MemoryUsage::DeviceLocalHostWritable.filter(&device, mem_reqs.size),
+        )?;
+        debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
+        debug_assert!(mem.mapped_memory().is_some());
+        unsafe {
+            buffer.bind_memory(mem.memory(), mem.offset())?;
+        }
+
+        let mut alignment: DeviceSize = 4;
+        if usage.uniform_buffer {
+            alignment = cmp::max(alignment, properties.min_uniform_buffer_offset_alignment);
+        }
+        if usage.uniform_texel_buffer {
+            alignment = cmp::max(alignment, properties.min_texel_buffer_offset_alignment);
+        }
+
+        let atom_size = if mem.mapped_memory().unwrap().is_coherent() {
+            1
+        } else {
+            properties.non_coherent_atom_size
+        };
+
+        Ok(Arc::new(RingBuffer {
+            inner: buffer,
+            memory: mem,
+            alignment,
+            atom_size,
+            state: Mutex::new(RingState {
+                head: 0,
+                tail: 0,
+                used: 0,
+                frames: vec![0; frames_in_flight],
+                fences: (0..frames_in_flight).map(|_| None).collect(),
+                current_frame: 0,
+            }),
+        }))
+    }
+
+    /// Returns the number of frame slots.
+    #[inline]
+    pub fn frames_in_flight(&self) -> usize {
+        self.state.lock().unwrap().frames.len()
+    }
+
+    /// Returns the frame slot that allocations are currently made in.
+    #[inline]
+    pub fn current_frame(&self) -> usize {
+        self.state.lock().unwrap().current_frame
+    }
+
+    /// Returns the minimum alignment in bytes of the allocations, which depends on the usage.
+    #[inline]
+    pub fn alignment(&self) -> DeviceSize {
+        self.alignment
+    }
+
+    /// Ends the current frame and switches to the next frame slot, whose memory is retired.
+    ///
+    /// `fence` must be signaled after all the submissions that use the memory allocated during
+    /// the current frame. Before retiring the memory of the next frame slot, this function
+    /// blocks until the fence that was handed in for it `frames_in_flight` calls ago is
+    /// signaled.
+    pub fn end_frame<F>(&self, fence: Arc<FenceSignalFuture<F>>) -> Result<(), FlushError>
+    where
+        F: GpuFuture + Send + 'static,
+    {
+        let mut state = self.state.lock().unwrap();
+        let size = self.inner.size();
+
+        let current_frame = state.current_frame;
+        state.fences[current_frame] = Some(fence);
+
+        let next = (current_frame + 1) % state.frames.len();
+        if let Some(previous) = state.fences[next].take() {
+            if let Err(err) = previous.wait() {
+                state.fences[next] = Some(previous);
+                return Err(err);
+            }
+        }
+
+        let retired = state.frames[next];
+        state.tail = (state.tail + retired) % size;
+        state.used -= retired;
+        state.frames[next] = 0;
+        state.current_frame = next;
+
+        if state.used == 0 {
+            state.head = 0;
+            state.tail = 0;
+        }
+
+        Ok(())
+    }
+
+    /// Allocates `size` bytes in the current frame slot, at an offset that is a multiple of
+    /// `align` and of the alignment required by the usage.
+    ///
+    /// If the memory isn't host-coherent, the offset and the memory consumed by the allocation
+    /// are also rounded up to the `non_coherent_atom_size` of the device.
+    ///
+    /// Returns an error if the memory of the frames in flight leaves no room for the allocation.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `size` is 0.
+    /// - Panics if `align` is not a power of two.
+    ///
+    pub fn allocate(
+        &self,
+        size: DeviceSize,
+        align: DeviceSize,
+    ) -> Result<RingBufferAllocation, RingBufferFullError> {
+        assert!(size != 0);
+        assert!(align.is_power_of_two());
+
+        let align = cmp::max(cmp::max(align, self.alignment), self.atom_size);
+        let capacity = self.inner.size();
+        // The end of the buffer counts as a multiple of the atom size.
+        let reserved = cmp::min(
+            (size + self.atom_size - 1) / self.atom_size * self.atom_size,
+            capacity,
+        );
+        let mut state = self.state.lock().unwrap();
+
+        // The free memory is either `head..tail`, or `head..capacity` followed by `0..tail`.
+        let (offset, consumed) = {
+            let aligned = (state.head + align - 1) / align * align;
+            let wraps = state.head >= state.tail && state.used < capacity;
+            let end = if wraps { capacity } else { state.tail };
+
+            if state.used < capacity && aligned + size <= end {
+                let reserved = cmp::min(reserved, end - aligned);
+                (aligned, aligned + reserved - state.head)
+            } else if wraps && size <= state.tail {
+                let reserved = cmp::min(reserved, state.tail);
+                (0, capacity - state.head + reserved)
+            } else {
+                return Err(RingBufferFullError);
+            }
+        };
+
+        state.head = (state.head + consumed) % capacity;
+        state.used += consumed;
+        let current_frame = state.current_frame;
+        state.frames[current_frame] += consumed;
+        drop(state);
+
+        // The device never writes to the buffer, so there is nothing to invalidate. Invalidating
+        // would also discard the unflushed writes to the rest of the atoms.
+        let mem_offset = self.memory.offset();
+        let data = unsafe {
+            self.memory
+                .mapped_memory()
+                .unwrap()
+                .write::<[u8]>(mem_offset + offset..mem_offset + offset + size)
+        };
+
+        Ok(RingBufferAllocation { data, offset })
+    }
+}
+
+unsafe impl DeviceOwned for RingBuffer {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.inner.device()
+    }
+}
+
+unsafe impl BufferAccess for RingBuffer {
+    #[inline]
+    fn inner(&self) -> BufferInner {
+        BufferInner {
+            buffer: &self.inner,
+            offset: 0,
+        }
+    }
+
+    #[inline]
+    fn size(&self) -> DeviceSize {
+        self.inner.size()
+    }
+
+    #[inline]
+    fn conflict_key(&self) -> (u64, u64) {
+        (self.inner.key(), 0)
+    }
+
+    // The device only reads the buffer, and the bytes that the host writes are retired by the
+    // fences of the frame slots, so shared accesses don't need to be tracked.
+    #[inline]
+    fn try_gpu_lock(&self, exclusive: bool, _: &Queue) -> Result<(), AccessError> {
+        if exclusive {
+            return Err(AccessError::ExclusiveDenied);
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self) {}
+
+    #[inline]
+    unsafe fn unlock(&self) {}
+}
+
+unsafe impl TypedBufferAccess for RingBuffer {
+    type Content = [u8];
+}
+
+/// Memory allocated in a `RingBuffer`.
+///
+/// This object derefs to the allocated bytes. They are flushed when it is dropped, if the memory
+/// isn't host-coherent.
+pub struct RingBufferAllocation<'a> {
+    data: CpuAccess<'a, [u8]>,
+    offset: DeviceSize,
+}
+
+impl<'a> RingBufferAllocation<'a> {
+    /// Returns the offset in bytes of the allocation in the buffer, to use when binding it.
+    #[inline]
+    pub fn offset(&self) -> DeviceSize {
+        self.offset
+    }
+
+    /// Returns the size in bytes of the allocation.
+    #[inline]
+    pub fn size(&self) -> DeviceSize {
+        self.data.len() as DeviceSize
+    }
+}
+
+impl<'a> Deref for RingBufferAllocation<'a> {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl<'a> DerefMut for RingBufferAllocation<'a> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
+
+/// Error that can happen when allocating in a `RingBuffer`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RingBufferFullError;
+
+impl error::Error for RingBufferFullError {}
+
+impl fmt::Display for RingBufferFullError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "the memory of the frames in flight leaves no room in the ring buffer"
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RingBuffer;
+    use super::RingBufferFullError;
+    use crate::buffer::BufferUsage;
+    use crate::device::Device;
+    use crate::sync::FenceSignalFuture;
+    use crate::sync::GpuFuture;
+    use crate::sync::NowFuture;
+    use std::cmp;
+    use std::sync::Arc;
+
+    fn frame_fence(device: &Arc<Device>) -> Arc<FenceSignalFuture<NowFuture>> {
+        Arc::new(
+            crate::sync::now(device.clone())
+                .then_signal_fence_and_flush()
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn wrap_around() {
+        let (device, _) = gfx_dev_and_queue!();
+        // The allocations are aligned to multiples of `unit`, so that their offsets are the same
+        // whether the memory is host-coherent or not.
+        let unit = cmp::max(
+            device.physical_device().properties().non_coherent_atom_size,
+            16,
+        );
+        let ring =
+            RingBuffer::new(device.clone(), BufferUsage::transfer_source(), 16 * unit, 2).unwrap();
+
+        let mut a = ring.allocate(unit / 2, 4).unwrap();
+        for byte in a.iter_mut() {
+            *byte = 1;
+        }
+        assert_eq!(a.offset(), 0);
+        drop(a);
+        assert_eq!(ring.allocate(9 * unit, unit).unwrap().offset(), unit);
+        assert_eq!(
+            ring.allocate(7 * unit, unit).unwrap_err(),
+            RingBufferFullError
+        );
+
+        ring.end_frame(frame_fence(&device)).unwrap();
+        assert_eq!(ring.current_frame(), 1);
+        assert_eq!(ring.allocate(3 * unit, unit).unwrap().offset(), 10 * unit);
+        assert_eq!(
+            ring.allocate(4 * unit, unit).unwrap_err(),
+            RingBufferFullError
+        );
+
+        // Retires the first frame, whose memory is reused from the start of the buffer.
+        ring.end_frame(frame_fence(&device)).unwrap();
+        assert_eq!(ring.allocate(8 * unit, unit).unwrap().offset(), 0);
+        assert_eq!(
+            ring.allocate(3 * unit, unit).unwrap_err(),
+            RingBufferFullError
+        );
+        assert_eq!(ring.allocate(2 * unit, unit).unwrap().offset(), 8 * unit);
+    }
+
+    #[test]
+    fn reuse_across_frames() {
+        let (device, _) = gfx_dev_and_queue!();
+        let ring = RingBuffer::new(device.clone(), BufferUsage::uniform_buffer(), 4096, 3).unwrap();
+
+        let first = ring.allocate(1024, 4).unwrap().offset();
+        for frame in 1..3 {
+            ring.end_frame(frame_fence(&device)).unwrap();
+            assert_eq!(ring.current_frame(), frame);
+            ring.allocate(1024, 4).unwrap();
+        }
+        assert_eq!(ring.allocate(1024, 4).unwrap_err(), RingBufferFullError);
+
+        // Reusing the first slot waits for its fence and gives its memory back.
+        ring.end_frame(frame_fence(&device)).unwrap();
+        assert_eq!(ring.current_frame(), 0);
+        assert_eq!(ring.allocate(1024, 4).unwrap().offset(), first);
+    }
+
+    #[test]
+    fn non_coherent_atoms() {
+        let (device, _) = gfx_dev_and_queue!();
+        let ring =
+            RingBuffer::new(device.clone(), BufferUsage::transfer_source(), 4096, 2).unwrap();
+        let atom_size = ring.atom_size;
+        let coherent = ring.memory.mapped_memory().unwrap().is_coherent();
+        assert_eq!(atom_size == 1, coherent);
+
+        let mem_offset = ring.memory.offset();
+        assert_eq!(
+            mem_offset % device.physical_device().properties().non_coherent_atom_size,
+            0
+        );
+
+        // Two small neighbours never share an atom, so that flushing one doesn't flush the other.
+        let a = ring.allocate(1, 1).unwrap().offset();
+        let b = ring.allocate(1, 1).unwrap().offset();
+        assert_eq!(a % atom_size, 0);
+        assert_eq!(b % atom_size, 0);
+        assert!(b >= a + atom_size);
+        if coherent {
+            assert_eq!(b, a + ring.alignment());
+        }
+    }
+
+    #[test]
+    fn writable_usage() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        assert_should_panic!(
+            "the usage of a ring buffer must not let the device write to it",
+            {
+                let _ = RingBuffer::new(device, BufferUsage::storage_buffer(), 1024, 2);
+            }
+        );
+    }
+}
@@ -7,20 +7,31 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use crate::buffer::cpu_access::ReadLock;
+use crate::buffer::cpu_access::ReadLockError;
+use crate::buffer::cpu_access::WriteLock;
+use crate::buffer::cpu_access::WriteLockError;
+use crate::buffer::pod::Pod;
 use crate::buffer::traits::BufferAccess;
 use crate::buffer::traits::BufferInner;
 use crate::buffer::traits::TypedBufferAccess;
+use crate::buffer::CpuAccessibleBuffer;
 use crate::device::Device;
 use crate::device::DeviceOwned;
 use crate::device::Queue;
+use crate::memory::pool::MemoryPoolAlloc;
+use crate::memory::Content;
 use crate::sync::AccessError;
 use crate::DeviceSize;
+use std::error;
+use std::fmt;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::marker::PhantomData;
 use std::mem;
 use std::mem::MaybeUninit;
 use std::ops::Range;
+use std::slice;
 use std::sync::Arc;
 
 /// A subpart of a buffer.
@@ -208,6 +219,155 @@ impl<T, B> BufferSlice<[T], B> {
             size: (range.end - range.start) * mem::size_of::<T>() as DeviceSize,
         }))
     }
+
+    /// Splits the slice in two at the element `mid`. The first slice contains the elements
+    /// `0..mid` and the second one the elements `mid..len`.
+    ///
+    /// Returns `None` if `mid` is out of range.
+    #[inline]
+    pub fn split_at(
+        &self,
+        mid: DeviceSize,
+    ) -> Option<(Arc<BufferSlice<[T], B>>, Arc<BufferSlice<[T], B>>)> {
+        let len = self.len();
+        Some((self.slice(0..mid)?, self.slice(mid..len)?))
+    }
+}
+
+impl<T: Pod, B> BufferSlice<[T], B>
+where
+    B: BufferAccess,
+{
+    /// Reinterprets the slice as a slice of `U`.
+    ///
+    /// Contrary to `reinterpret`, this is safe because both types are `Pod`. The size of the
+    /// slice must be a multiple of the size of `U`, and its offset in the buffer must be a
+    /// multiple of the alignment of `U`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use vulkano::buffer::BufferSlice;
+    /// # use vulkano::buffer::CpuAccessibleBuffer;
+    /// let bytes: Arc<BufferSlice<[u8], CpuAccessibleBuffer<[u8]>>> = return;
+    /// let vec4s = bytes.cast::<[f32; 4]>().unwrap();
+    /// ```
+    pub fn cast<U: Pod>(&self) -> Result<Arc<BufferSlice<[U], B>>, CastError> {
+        if mem::size_of::<U>() == 0 || self.size % mem::size_of::<U>() as DeviceSize != 0 {
+            return Err(CastError::SizeMismatch {
+                size: self.size,
+                element_size: mem::size_of::<U>() as DeviceSize,
+            });
+        }
+
+        let offset = self.resource.inner().offset + self.offset;
+        if offset % mem::align_of::<U>() as DeviceSize != 0 {
+            return Err(CastError::Misaligned {
+                offset,
+                alignment: mem::align_of::<U>() as DeviceSize,
+            });
+        }
+
+        Ok(Arc::new(BufferSlice {
+            marker: PhantomData,
+            resource: self.resource.clone(),
+            offset: self.offset,
+            size: self.size,
+        }))
+    }
+}
+
+impl<T: Pod, U: ?Sized, A> BufferSlice<[T], CpuAccessibleBuffer<U, A>>
+where
+    U: Content + 'static,
+    A: MemoryPoolAlloc,
+{
+    /// Locks the buffer in order to read the elements of this slice from the CPU.
+    ///
+    /// The whole buffer is locked, as with `CpuAccessibleBuffer::read`.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the slice isn't aligned for `T` in the mapped memory.
+    #[inline]
+    pub fn read(&self) -> Result<ReadLock<[T]>, ReadLockError> {
+        let lock = self.resource.read()?;
+        let (offset, len) = self.mapped_range(&*lock);
+        Ok(lock.map(|content| unsafe {
+            let ptr = (content as *mut U as *mut u8).add(offset) as *mut T;
+            slice::from_raw_parts_mut(ptr, len)
+        }))
+    }
+
+    /// Locks the buffer in order to write the elements of this slice from the CPU.
+    ///
+    /// The whole buffer is locked, as with `CpuAccessibleBuffer::write`.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the slice isn't aligned for `T` in the mapped memory.
+    #[inline]
+    pub fn write(&self) -> Result<WriteLock<[T]>, WriteLockError> {
+        let lock = self.resource.write()?;
+        let (offset, len) = self.mapped_range(&*lock);
+        Ok(lock.map(|content| unsafe {
+            let ptr = (content as *mut U as *mut u8).add(offset) as *mut T;
+            slice::from_raw_parts_mut(ptr, len)
+        }))
+    }
+
+    // Returns the offset in bytes of the slice in `content`, the mapped content of the buffer,
+    // and its number of elements.
+    #[inline]
+    fn mapped_range(&self, content: &U) -> (usize, usize) {
+        // A buffer allocated from a memory pool starts at an offset in the mapping that is only
+        // aligned as required by its usage, so the alignment of the offset of the slice says
+        // nothing about the alignment of its elements. Check the actual pointer instead.
+        let ptr = content as *const U as *const u8 as usize + self.offset as usize;
+        assert!(
+            ptr % mem::align_of::<T>() == 0,
+            "the slice isn't aligned for its element type in the mapped memory"
+        );
+        (self.offset as usize, self.len() as usize)
+    }
+}
+
+/// Error that can happen when casting a `BufferSlice` to another type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CastError {
+    /// The size of the slice isn't a multiple of the size of the new element type.
+    SizeMismatch {
+        size: DeviceSize,
+        element_size: DeviceSize,
+    },
+    /// The offset of the slice in the buffer isn't a multiple of the alignment of the new
+    /// element type.
+    Misaligned {
+        offset: DeviceSize,
+        alignment: DeviceSize,
+    },
+}
+
+impl error::Error for CastError {}
+
+impl fmt::Display for CastError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            CastError::SizeMismatch { size, element_size } => write!(
+                fmt,
+                "the size of the slice ({}) isn't a multiple of the size of the element type ({})",
+                size, element_size
+            ),
+            CastError::Misaligned { offset, alignment } => write!(
+                fmt,
+                "the offset of the slice ({}) isn't a multiple of the alignment of the element \
+                 type ({})",
+                offset, alignment
+            ),
+        }
+    }
 }
 
 unsafe impl<T, B> BufferAccess for BufferSlice<T, B>
@@ -319,3 +479,50 @@ macro_rules! buffer_slice_field {
         unsafe { $slice.slice_custom(|s| &s.$field) }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::BufferSlice;
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CastError;
+    use crate::buffer::CpuAccessibleBuffer;
+
+    #[test]
+    fn split_and_write() {
+        let (device, _) = gfx_dev_and_queue!();
+        let buffer =
+            CpuAccessibleBuffer::from_iter(device, BufferUsage::all(), false, 0..8u32).unwrap();
+        let slice = BufferSlice::from_typed_buffer_access(buffer.clone());
+
+        let (first, second) = slice.split_at(3).unwrap();
+        assert_eq!(first.len(), 3);
+        assert_eq!(second.len(), 5);
+        assert!(slice.split_at(9).is_none());
+
+        second.write().unwrap()[0] = 42;
+        assert_eq!(&*first.read().unwrap(), &[0, 1, 2]);
+        assert_eq!(buffer.read().unwrap()[3], 42);
+    }
+
+    #[test]
+    fn cast() {
+        let (device, _) = gfx_dev_and_queue!();
+        let buffer =
+            CpuAccessibleBuffer::from_iter(device, BufferUsage::all(), false, 0..16u8).unwrap();
+        let slice = BufferSlice::from_typed_buffer_access(buffer);
+
+        let words = slice.cast::<u32>().unwrap();
+        assert_eq!(words.len(), 4);
+        assert_eq!(words.read().unwrap()[1], u32::from_ne_bytes([4, 5, 6, 7]));
+
+        match slice.slice(1..16).unwrap().cast::<u32>() {
+            Err(CastError::SizeMismatch { .. }) => (),
+            _ => panic!(),
+        }
+
+        match slice.slice(1..5).unwrap().cast::<u32>() {
+            Err(CastError::Misaligned { offset: 1, .. }) => (),
+            _ => panic!(),
+        }
+    }
+}
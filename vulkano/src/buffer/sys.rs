@@ -29,6 +29,7 @@ use crate::device::Device;
 use crate::device::DeviceOwned;
 use crate::memory::DeviceMemory;
 use crate::memory::DeviceMemoryAllocError;
+use crate::memory::ExternalMemoryHandleType;
 use crate::memory::MemoryRequirements;
 use crate::sync::Sharing;
 use crate::DeviceSize;
@@ -43,6 +44,8 @@ use std::fmt;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::mem::MaybeUninit;
+use std::num::NonZeroU64;
+use std::os::raw::c_void;
 use std::ptr;
 use std::sync::Arc;
 
@@ -52,6 +55,8 @@ pub struct UnsafeBuffer {
     device: Arc<Device>,
     size: DeviceSize,
     usage: BufferUsage,
+    sparse: Option<SparseLevel>,
+    capture_replay: bool,
 }
 
 impl UnsafeBuffer {
@@ -64,12 +69,105 @@ impl UnsafeBuffer {
     /// - Panics if `sparse.sparse` is false and `sparse.sparse_residency` or `sparse.sparse_aliased` is true.
     /// - Panics if `usage` is empty.
     ///
+    #[inline]
     pub unsafe fn new<'a, I>(
+        device: Arc<Device>,
+        size: DeviceSize,
+        usage: BufferUsage,
+        sharing: Sharing<I>,
+        sparse: Option<SparseLevel>,
+    ) -> Result<(UnsafeBuffer, MemoryRequirements), BufferCreationError>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        UnsafeBuffer::new_impl(
+            device,
+            size,
+            usage,
+            sharing,
+            sparse,
+            None,
+            ExternalMemoryHandleType::none(),
+        )
+    }
+
+    /// Same as `new`, but the buffer can be bound to memory that is exported to or imported
+    /// from `handle_types`.
+    ///
+    /// The `khr_external_memory` extension must be enabled on the device, unless its API version
+    /// is at least 1.1.
+    pub unsafe fn new_with_external_memory<'a, I>(
+        device: Arc<Device>,
+        size: DeviceSize,
+        usage: BufferUsage,
+        sharing: Sharing<I>,
+        handle_types: ExternalMemoryHandleType,
+    ) -> Result<(UnsafeBuffer, MemoryRequirements), BufferCreationError>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        if !(device.api_version() >= Version::V1_1
+            || device.enabled_extensions().khr_external_memory)
+        {
+            return Err(BufferCreationError::ExternalMemoryExtensionNotEnabled);
+        }
+
+        UnsafeBuffer::new_impl(device, size, usage, sharing, None, None, handle_types)
+    }
+
+    /// Same as `new`, but the device address of the buffer can be captured and replayed by
+    /// tools.
+    ///
+    /// When capturing, `opaque_capture_address` must be 0, and the address to replay with must be
+    /// obtained with [`opaque_capture_address`](UnsafeBuffer::opaque_capture_address). When
+    /// replaying, `opaque_capture_address` must be this address, and the memory that the buffer
+    /// is bound to must have been replayed too.
+    ///
+    /// The `buffer_device_address_capture_replay` feature must be enabled on the device.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `usage.device_address` is false.
+    ///
+    pub unsafe fn new_with_capture_replay<'a, I>(
+        device: Arc<Device>,
+        size: DeviceSize,
+        usage: BufferUsage,
+        sharing: Sharing<I>,
+        sparse: Option<SparseLevel>,
+        opaque_capture_address: u64,
+    ) -> Result<(UnsafeBuffer, MemoryRequirements), BufferCreationError>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        assert!(usage.device_address);
+
+        if !device
+            .enabled_features()
+            .buffer_device_address_capture_replay
+        {
+            return Err(BufferCreationError::DeviceAddressCaptureReplayFeatureNotEnabled);
+        }
+
+        UnsafeBuffer::new_impl(
+            device,
+            size,
+            usage,
+            sharing,
+            sparse,
+            Some(opaque_capture_address),
+            ExternalMemoryHandleType::none(),
+        )
+    }
+
+    unsafe fn new_impl<'a, I>(
         device: Arc<Device>,
         size: DeviceSize,
         mut usage: BufferUsage,
         sharing: Sharing<I>,
         sparse: Option<SparseLevel>,
+        capture_replay: Option<u64>,
+        external_memory: ExternalMemoryHandleType,
     ) -> Result<(UnsafeBuffer, MemoryRequirements), BufferCreationError>
     where
         I: IntoIterator<Item = u32>,
@@ -85,7 +183,7 @@ impl UnsafeBuffer {
         };
 
         // Checking sparse features.
-        let flags = if let Some(sparse_level) = sparse {
+        let mut flags = if let Some(sparse_level) = sparse {
             if !device.enabled_features().sparse_binding {
                 return Err(BufferCreationError::SparseBindingFeatureNotEnabled);
             }
@@ -103,6 +201,21 @@ impl UnsafeBuffer {
             ash::vk::BufferCreateFlags::empty()
         };
 
+        let capture_replay_info = if let Some(opaque_capture_address) = capture_replay {
+            flags |= ash::vk::BufferCreateFlags::DEVICE_ADDRESS_CAPTURE_REPLAY;
+
+            if opaque_capture_address != 0 {
+                Some(ash::vk::BufferOpaqueCaptureAddressCreateInfo {
+                    opaque_capture_address,
+                    ..Default::default()
+                })
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
         if usage.device_address && !device.enabled_features().buffer_device_address {
             usage.device_address = false;
             if ash::vk::BufferUsageFlags::from(usage).is_empty() {
@@ -114,6 +227,32 @@ impl UnsafeBuffer {
             }
         }
 
+        if usage.conditional_rendering && !device.enabled_extensions().ext_conditional_rendering {
+            usage.conditional_rendering = false;
+            if ash::vk::BufferUsageFlags::from(usage).is_empty() {
+                return Err(BufferCreationError::ConditionalRenderingExtensionNotEnabled);
+            }
+        }
+
+        if (usage.transform_feedback_buffer || usage.transform_feedback_counter_buffer)
+            && !device.enabled_extensions().ext_transform_feedback
+        {
+            usage.transform_feedback_buffer = false;
+            usage.transform_feedback_counter_buffer = false;
+            if ash::vk::BufferUsageFlags::from(usage).is_empty() {
+                return Err(BufferCreationError::TransformFeedbackExtensionNotEnabled);
+            }
+        }
+
+        if usage.acceleration_structure_storage
+            && !device.enabled_extensions().khr_acceleration_structure
+        {
+            usage.acceleration_structure_storage = false;
+            if ash::vk::BufferUsageFlags::from(usage).is_empty() {
+                return Err(BufferCreationError::AccelerationStructureExtensionNotEnabled);
+            }
+        }
+
         let usage_bits = ash::vk::BufferUsageFlags::from(usage);
         // Checking for empty BufferUsage.
         assert!(
@@ -131,7 +270,25 @@ impl UnsafeBuffer {
                 }
             };
 
+            let mut p_next: *const c_void = capture_replay_info
+                .as_ref()
+                .map_or(ptr::null(), |info| info as *const _ as *const _);
+
+            let external_memory_info = if external_memory != ExternalMemoryHandleType::none() {
+                Some(ash::vk::ExternalMemoryBufferCreateInfo {
+                    p_next,
+                    handle_types: external_memory.into(),
+                    ..Default::default()
+                })
+            } else {
+                None
+            };
+            if let Some(info) = external_memory_info.as_ref() {
+                p_next = info as *const _ as *const _;
+            }
+
             let infos = ash::vk::BufferCreateInfo {
+                p_next,
                 flags,
                 size,
                 usage: usage_bits,
@@ -249,6 +406,8 @@ impl UnsafeBuffer {
             device: device.clone(),
             size,
             usage,
+            sparse,
+            capture_replay: capture_replay.is_some(),
         };
 
         Ok((obj, mem_reqs))
@@ -312,6 +471,75 @@ impl UnsafeBuffer {
         self.usage
     }
 
+    /// Returns the level of sparse binding the buffer was created with, or `None` if its memory
+    /// is bound with `bind_memory`.
+    #[inline]
+    pub fn sparse_level(&self) -> Option<SparseLevel> {
+        self.sparse
+    }
+
+    /// Returns the device address of the start of the buffer, which shaders can access through
+    /// `PhysicalStorageBuffer` pointers.
+    ///
+    /// The buffer must have been created with the `device_address` usage. The memory that it is
+    /// bound to is allocated with the required flags automatically.
+    ///
+    /// No lock checking or waiting is performed. This is nevertheless still safe because the
+    /// returned value isn't directly dereferencable. Unsafe code is required to dereference the
+    /// value in a shader.
+    pub fn device_address(&self) -> Result<NonZeroU64, DeviceAddressUsageNotEnabledError> {
+        if !self.usage.device_address {
+            return Err(DeviceAddressUsageNotEnabledError);
+        }
+
+        unsafe {
+            let info = ash::vk::BufferDeviceAddressInfo {
+                buffer: self.buffer,
+                ..Default::default()
+            };
+            let fns = self.device.fns();
+            let ptr = if self.device.api_version() >= Version::V1_2 {
+                fns.v1_2
+                    .get_buffer_device_address(self.device.internal_object(), &info)
+            } else if self.device.enabled_extensions().khr_buffer_device_address {
+                fns.khr_buffer_device_address
+                    .get_buffer_device_address_khr(self.device.internal_object(), &info)
+            } else {
+                fns.ext_buffer_device_address
+                    .get_buffer_device_address_ext(self.device.internal_object(), &info)
+            };
+
+            Ok(
+                NonZeroU64::new(ptr)
+                    .expect("got null ptr from a valid GetBufferDeviceAddress call"),
+            )
+        }
+    }
+
+    /// Returns the opaque address that the buffer must be created with to replay its device
+    /// address, or `None` if it wasn't created with
+    /// [`new_with_capture_replay`](UnsafeBuffer::new_with_capture_replay).
+    pub fn opaque_capture_address(&self) -> Option<u64> {
+        if !self.capture_replay {
+            return None;
+        }
+
+        unsafe {
+            let info = ash::vk::BufferDeviceAddressInfo {
+                buffer: self.buffer,
+                ..Default::default()
+            };
+            let fns = self.device.fns();
+            Some(if self.device.api_version() >= Version::V1_2 {
+                fns.v1_2
+                    .get_buffer_opaque_capture_address(self.device.internal_object(), &info)
+            } else {
+                fns.khr_buffer_device_address
+                    .get_buffer_opaque_capture_address_khr(self.device.internal_object(), &info)
+            })
+        }
+    }
+
     /// Returns a key unique to each `UnsafeBuffer`. Can be used for the `conflicts_key` method.
     #[inline]
     pub fn key(&self) -> u64 {
@@ -425,6 +653,18 @@ pub enum BufferCreationError {
     SparseResidencyAliasedFeatureNotEnabled,
     /// Device address was requested but the corresponding feature wasn't enabled.
     DeviceAddressFeatureNotEnabled,
+    /// Device address capture and replay was requested but the corresponding feature wasn't
+    /// enabled.
+    DeviceAddressCaptureReplayFeatureNotEnabled,
+    /// Conditional rendering usage was requested but the corresponding extension wasn't enabled.
+    ConditionalRenderingExtensionNotEnabled,
+    /// Transform feedback usage was requested but the corresponding extension wasn't enabled.
+    TransformFeedbackExtensionNotEnabled,
+    /// Acceleration structure storage usage was requested but the corresponding extension wasn't
+    /// enabled.
+    AccelerationStructureExtensionNotEnabled,
+    /// External memory was requested but the corresponding extension wasn't enabled.
+    ExternalMemoryExtensionNotEnabled,
 }
 
 impl error::Error for BufferCreationError {
@@ -457,6 +697,25 @@ impl fmt::Display for BufferCreationError {
                 BufferCreationError::DeviceAddressFeatureNotEnabled => {
                     "device address was requested but the corresponding feature wasn't enabled"
                 }
+                BufferCreationError::DeviceAddressCaptureReplayFeatureNotEnabled => {
+                    "device address capture and replay was requested but the corresponding feature \
+                     wasn't enabled"
+                }
+                BufferCreationError::ConditionalRenderingExtensionNotEnabled => {
+                    "conditional rendering usage was requested but the corresponding extension \
+                     wasn't enabled"
+                }
+                BufferCreationError::TransformFeedbackExtensionNotEnabled => {
+                    "transform feedback usage was requested but the corresponding extension \
+                     wasn't enabled"
+                }
+                BufferCreationError::AccelerationStructureExtensionNotEnabled => {
+                    "acceleration structure storage usage was requested but the corresponding \
+                     extension wasn't enabled"
+                }
+                BufferCreationError::ExternalMemoryExtensionNotEnabled => {
+                    "external memory was requested but the corresponding extension wasn't enabled"
+                }
             }
         )
     }
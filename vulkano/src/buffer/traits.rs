@@ -14,7 +14,6 @@ use crate::device::Queue;
 use crate::memory::Content;
 use crate::sync::AccessError;
 use crate::DeviceSize;
-use crate::VulkanObject;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::num::NonZeroU64;
@@ -114,28 +113,9 @@ pub unsafe trait BufferAccess: DeviceOwned + Send + Sync {
     /// value in a shader.
     fn raw_device_address(&self) -> Result<NonZeroU64, DeviceAddressUsageNotEnabledError> {
         let inner = self.inner();
+        let address = inner.buffer.device_address()?;
 
-        if !inner.buffer.usage().device_address {
-            return Err(DeviceAddressUsageNotEnabledError);
-        }
-
-        let dev = self.device();
-        unsafe {
-            let info = ash::vk::BufferDeviceAddressInfo {
-                buffer: inner.buffer.internal_object(),
-                ..Default::default()
-            };
-            let ptr = dev
-                .fns()
-                .ext_buffer_device_address
-                .get_buffer_device_address_ext(dev.internal_object(), &info);
-
-            if ptr == 0 {
-                panic!("got null ptr from a valid GetBufferDeviceAddressEXT call");
-            }
-
-            Ok(NonZeroU64::new_unchecked(ptr + inner.offset))
-        }
+        unsafe { Ok(NonZeroU64::new_unchecked(address.get() + inner.offset)) }
     }
 }
 
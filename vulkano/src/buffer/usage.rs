@@ -29,6 +29,21 @@ pub struct BufferUsage {
     /// Requires the `buffer_device_address` feature. If that feature is not enabled, this will
     /// be silently ignored.
     pub device_address: bool,
+    /// Requires the `ext_conditional_rendering` extension. If that extension is not enabled, this
+    /// will be silently ignored.
+    pub conditional_rendering: bool,
+    /// Requires the `ext_transform_feedback` extension. If that extension is not enabled, this
+    /// will be silently ignored.
+    pub transform_feedback_buffer: bool,
+    /// Requires the `ext_transform_feedback` extension. If that extension is not enabled, this
+    /// will be silently ignored.
+    pub transform_feedback_counter_buffer: bool,
+    /// The buffer can hold the data of
+    /// [`AccelerationStructure`](crate::acceleration_structure::AccelerationStructure)s.
+    ///
+    /// Requires the `khr_acceleration_structure` extension. If that extension is not enabled,
+    /// this will be silently ignored.
+    pub acceleration_structure_storage: bool,
 }
 
 impl BufferUsage {
@@ -46,6 +61,10 @@ impl BufferUsage {
             vertex_buffer: false,
             indirect_buffer: false,
             device_address: false,
+            conditional_rendering: false,
+            transform_feedback_buffer: false,
+            transform_feedback_counter_buffer: false,
+            acceleration_structure_storage: false,
         }
     }
 
@@ -63,6 +82,10 @@ impl BufferUsage {
             vertex_buffer: true,
             indirect_buffer: true,
             device_address: true,
+            conditional_rendering: true,
+            transform_feedback_buffer: true,
+            transform_feedback_counter_buffer: true,
+            acceleration_structure_storage: true,
         }
     }
 
@@ -180,6 +203,28 @@ impl BufferUsage {
             ..BufferUsage::none()
         }
     }
+
+    /// Builds a `BufferUsage` with `conditional_rendering` and `transfer_destination` set to true
+    /// and the rest to false.
+    #[inline]
+    pub const fn conditional_rendering_transfer_destination() -> BufferUsage {
+        BufferUsage {
+            conditional_rendering: true,
+            transfer_destination: true,
+            ..BufferUsage::none()
+        }
+    }
+
+    /// Builds a `BufferUsage` with `transform_feedback_buffer` and `transfer_source` set to true
+    /// and the rest to false.
+    #[inline]
+    pub const fn transform_feedback_buffer_transfer_source() -> BufferUsage {
+        BufferUsage {
+            transform_feedback_buffer: true,
+            transfer_source: true,
+            ..BufferUsage::none()
+        }
+    }
 }
 
 impl From<BufferUsage> for ash::vk::BufferUsageFlags {
@@ -215,6 +260,18 @@ impl From<BufferUsage> for ash::vk::BufferUsageFlags {
         if val.device_address {
             result |= ash::vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS;
         }
+        if val.conditional_rendering {
+            result |= ash::vk::BufferUsageFlags::CONDITIONAL_RENDERING_EXT;
+        }
+        if val.transform_feedback_buffer {
+            result |= ash::vk::BufferUsageFlags::TRANSFORM_FEEDBACK_BUFFER_EXT;
+        }
+        if val.transform_feedback_counter_buffer {
+            result |= ash::vk::BufferUsageFlags::TRANSFORM_FEEDBACK_COUNTER_BUFFER_EXT;
+        }
+        if val.acceleration_structure_storage {
+            result |= ash::vk::BufferUsageFlags::ACCELERATION_STRUCTURE_STORAGE_KHR;
+        }
         result
     }
 }
@@ -235,6 +292,13 @@ impl BitOr for BufferUsage {
             vertex_buffer: self.vertex_buffer || rhs.vertex_buffer,
             indirect_buffer: self.indirect_buffer || rhs.indirect_buffer,
             device_address: self.device_address || rhs.device_address,
+            conditional_rendering: self.conditional_rendering || rhs.conditional_rendering,
+            transform_feedback_buffer: self.transform_feedback_buffer
+                || rhs.transform_feedback_buffer,
+            transform_feedback_counter_buffer: self.transform_feedback_counter_buffer
+                || rhs.transform_feedback_counter_buffer,
+            acceleration_structure_storage: self.acceleration_structure_storage
+                || rhs.acceleration_structure_storage,
         }
     }
 }
@@ -35,21 +35,28 @@
 //!                                                             queue.clone()).unwrap();
 //! let _view = BufferView::new(buffer, Format::R32_UINT).unwrap();
 //! ```
+//!
+//! A view can also cover only a range of the buffer with `BufferView::with_range`, and the same
+//! range can be viewed with other formats with `BufferView::reinterpret`. This allows many texel
+//! buffers to share a single large buffer.
 
 use crate::buffer::BufferAccess;
 use crate::buffer::BufferInner;
+use crate::buffer::BufferUsage;
 use crate::buffer::TypedBufferAccess;
 use crate::check_errors;
 use crate::device::Device;
 use crate::device::DeviceOwned;
 use crate::format::Format;
 use crate::format::Pixel;
+use crate::DeviceSize;
 use crate::Error;
 use crate::OomError;
 use crate::VulkanObject;
 use std::error;
 use std::fmt;
 use std::mem::MaybeUninit;
+use std::ops::Range;
 use std::ptr;
 use std::sync::Arc;
 
@@ -61,6 +68,8 @@ where
 {
     handle: ash::vk::BufferView,
     buffer: Arc<B>,
+    format: Format,
+    range: Range<DeviceSize>,
     atomic_accesses: bool,
 }
 
@@ -90,84 +99,63 @@ where
         B: BufferAccess,
     {
         let size = org_buffer.size();
-        let BufferInner { buffer, offset } = org_buffer.inner();
-
-        let device = buffer.device();
-
-        if (offset
-            % device
-                .physical_device()
-                .properties()
-                .min_texel_buffer_offset_alignment)
-            != 0
-        {
-            return Err(BufferViewCreationError::WrongBufferAlignment);
-        }
-
-        if !(buffer.usage().uniform_texel_buffer || buffer.usage().storage_texel_buffer) {
-            return Err(BufferViewCreationError::WrongBufferUsage);
-        }
-
-        let buffer_features = format.properties(device.physical_device()).buffer_features;
-
-        if buffer.usage().uniform_texel_buffer {
-            if !buffer_features.uniform_texel_buffer {
-                return Err(BufferViewCreationError::UnsupportedFormat);
-            }
-        }
-
-        if buffer.usage().storage_texel_buffer {
-            if !buffer_features.storage_texel_buffer {
-                return Err(BufferViewCreationError::UnsupportedFormat);
-            }
-        }
-
-        let elements = size / format.size().expect(
-            "Format has no size. If you see this error, please submit a new bug report to Vulkano.",
-        );
-        if elements as u32
-            > device
-                .physical_device()
-                .properties()
-                .max_texel_buffer_elements
-        {
-            return Err(BufferViewCreationError::MaxTexelBufferElementsExceeded);
-        }
+        BufferView::with_range(org_buffer, format, 0..size)
+    }
 
-        let handle = {
-            let infos = ash::vk::BufferViewCreateInfo {
-                flags: ash::vk::BufferViewCreateFlags::empty(),
-                buffer: buffer.internal_object(),
-                format: format.into(),
-                offset,
-                range: size,
-                ..Default::default()
-            };
-
-            let fns = device.fns();
-            let mut output = MaybeUninit::uninit();
-            check_errors(fns.v1_0.create_buffer_view(
-                device.internal_object(),
-                &infos,
-                ptr::null(),
-                output.as_mut_ptr(),
-            ))?;
-            output.assume_init()
-        };
+    /// Builds a new buffer view of the bytes `range` of `buffer`.
+    ///
+    /// The content of the buffer isn't checked against the format, so that ranges of a buffer of
+    /// bytes can be viewed with any format. The start of the range must be aligned as required by
+    /// the `texel_buffer_alignment` feature if it is enabled, or to the
+    /// `min_texel_buffer_offset_alignment` property otherwise, and its length must be a multiple
+    /// of the size of the format.
+    pub fn with_range(
+        buffer: Arc<B>,
+        format: Format,
+        range: Range<DeviceSize>,
+    ) -> Result<Arc<BufferView<B>>, BufferViewCreationError> {
+        let (handle, atomic_accesses) = unsafe { create_view(&*buffer, format, range.clone())? };
 
         Ok(Arc::new(BufferView {
             handle,
-            buffer: org_buffer,
-            atomic_accesses: buffer_features.storage_texel_buffer_atomic,
+            buffer,
+            format,
+            range,
+            atomic_accesses,
         }))
     }
 
+    /// Builds a new view of the same range of the same buffer as this one, with a different
+    /// format.
+    ///
+    /// The range must be a valid range for `format`, as described in
+    /// [`with_range`](BufferView::with_range).
+    #[inline]
+    pub fn reinterpret(
+        &self,
+        format: Format,
+    ) -> Result<Arc<BufferView<B>>, BufferViewCreationError> {
+        BufferView::with_range(self.buffer.clone(), format, self.range.clone())
+    }
+
     /// Returns the buffer associated to this view.
     #[inline]
     pub fn buffer(&self) -> &Arc<B> {
         &self.buffer
     }
 
+    /// Returns the format of this view.
+    #[inline]
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Returns the range of bytes of the buffer covered by this view.
+    #[inline]
+    pub fn range(&self) -> Range<DeviceSize> {
+        self.range.clone()
+    }
+
     /// Returns true if the buffer view can be used as a uniform texel buffer.
     #[inline]
     pub fn uniform_texel_buffer(&self) -> bool {
@@ -187,6 +175,140 @@ where
     }
 }
 
+// Checks the parameters and creates the view of `range` of `org_buffer`. Returns the view and
+// whether the format supports atomic accesses.
+unsafe fn create_view<B>(
+    org_buffer: &B,
+    format: Format,
+    range: Range<DeviceSize>,
+) -> Result<(ash::vk::BufferView, bool), BufferViewCreationError>
+where
+    B: BufferAccess + ?Sized,
+{
+    let size = org_buffer.size();
+    let BufferInner { buffer, offset } = org_buffer.inner();
+
+    let device = buffer.device();
+
+    // VUID-VkBufferViewCreateInfo-offset-00925, VUID-VkBufferViewCreateInfo-range-00928
+    if range.start >= range.end || range.end > size {
+        return Err(BufferViewCreationError::RangeOutOfBounds);
+    }
+
+    let format_size = format.size().expect(
+        "Format has no size. If you see this error, please submit a new bug report to Vulkano.",
+    );
+
+    // VUID-VkBufferViewCreateInfo-range-00929
+    if (range.end - range.start) % format_size != 0 {
+        return Err(BufferViewCreationError::RangeNotMultipleOfFormatSize);
+    }
+
+    let offset = offset + range.start;
+
+    if offset % offset_alignment(device, buffer.usage(), format) != 0 {
+        return Err(BufferViewCreationError::WrongBufferAlignment);
+    }
+
+    if !(buffer.usage().uniform_texel_buffer || buffer.usage().storage_texel_buffer) {
+        return Err(BufferViewCreationError::WrongBufferUsage);
+    }
+
+    let buffer_features = format.properties(device.physical_device()).buffer_features;
+
+    if buffer.usage().uniform_texel_buffer {
+        if !buffer_features.uniform_texel_buffer {
+            return Err(BufferViewCreationError::UnsupportedFormat);
+        }
+    }
+
+    if buffer.usage().storage_texel_buffer {
+        if !buffer_features.storage_texel_buffer {
+            return Err(BufferViewCreationError::UnsupportedFormat);
+        }
+    }
+
+    let elements = (range.end - range.start) / format_size;
+    if elements as u32
+        > device
+            .physical_device()
+            .properties()
+            .max_texel_buffer_elements
+    {
+        return Err(BufferViewCreationError::MaxTexelBufferElementsExceeded);
+    }
+
+    let handle = {
+        let infos = ash::vk::BufferViewCreateInfo {
+            flags: ash::vk::BufferViewCreateFlags::empty(),
+            buffer: buffer.internal_object(),
+            format: format.into(),
+            offset,
+            range: range.end - range.start,
+            ..Default::default()
+        };
+
+        let fns = device.fns();
+        let mut output = MaybeUninit::uninit();
+        check_errors(fns.v1_0.create_buffer_view(
+            device.internal_object(),
+            &infos,
+            ptr::null(),
+            output.as_mut_ptr(),
+        ))?;
+        output.assume_init()
+    };
+
+    Ok((handle, buffer_features.storage_texel_buffer_atomic))
+}
+
+// Returns the alignment required for the offset of a view with the given usage and format.
+fn offset_alignment(device: &Device, usage: BufferUsage, format: Format) -> DeviceSize {
+    let properties = device.physical_device().properties();
+
+    if !device.enabled_features().texel_buffer_alignment {
+        return properties.min_texel_buffer_offset_alignment;
+    }
+
+    // With `texel_buffer_alignment`, the alignment can be as low as the size of a texel, or of a
+    // component for three-component formats.
+    let texel_size = {
+        let size = format.size().unwrap();
+        if format.components().iter().filter(|&&c| c != 0).count() == 3 {
+            size / 3
+        } else {
+            size
+        }
+    };
+
+    let alignment = |bytes: Option<DeviceSize>, single_texel: Option<bool>| {
+        let bytes = bytes.unwrap_or(properties.min_texel_buffer_offset_alignment);
+        if single_texel.unwrap_or(false) {
+            bytes.min(texel_size)
+        } else {
+            bytes
+        }
+    };
+
+    let mut result = 1;
+
+    if usage.storage_texel_buffer {
+        result = result.max(alignment(
+            properties.storage_texel_buffer_offset_alignment_bytes,
+            properties.storage_texel_buffer_offset_single_texel_alignment,
+        ));
+    }
+
+    if usage.uniform_texel_buffer {
+        result = result.max(alignment(
+            properties.uniform_texel_buffer_offset_alignment_bytes,
+            properties.uniform_texel_buffer_offset_single_texel_alignment,
+        ));
+    }
+
+    result
+}
+
 unsafe impl<B> VulkanObject for BufferView<B>
 where
     B: BufferAccess,
@@ -272,9 +394,15 @@ pub enum BufferViewCreationError {
     WrongBufferUsage,
 
     /// The offset within the buffer is not a multiple of the `min_texel_buffer_offset_alignment`
-    /// limit.
+    /// limit, or of the alignment required by the `texel_buffer_alignment` feature.
     WrongBufferAlignment,
 
+    /// The range of the view is empty or goes past the end of the buffer.
+    RangeOutOfBounds,
+
+    /// The size of the range of the view is not a multiple of the size of the format.
+    RangeNotMultipleOfFormatSize,
+
     /// The requested format is not supported for this usage.
     UnsupportedFormat,
 
@@ -307,6 +435,12 @@ impl fmt::Display for BufferViewCreationError {
                     "the offset within the buffer is not a multiple of the
                  `min_texel_buffer_offset_alignment` limit"
                 }
+                BufferViewCreationError::RangeOutOfBounds => {
+                    "the range of the view is empty or goes past the end of the buffer"
+                }
+                BufferViewCreationError::RangeNotMultipleOfFormatSize => {
+                    "the size of the range of the view is not a multiple of the size of the format"
+                }
                 BufferViewCreationError::UnsupportedFormat => {
                     "the requested format is not supported for this usage"
                 }
@@ -394,6 +528,42 @@ mod tests {
         assert!(view.storage_texel_buffer_atomic());
     }
 
+    #[test]
+    fn range_and_reinterpret() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let usage = BufferUsage {
+            storage_texel_buffer: true,
+            ..BufferUsage::none()
+        };
+
+        let (buffer, _) =
+            ImmutableBuffer::<[u8]>::from_iter((0..4096).map(|_| 0), usage, queue.clone()).unwrap();
+        let alignment = device
+            .physical_device()
+            .properties()
+            .min_texel_buffer_offset_alignment;
+
+        let view =
+            BufferView::with_range(buffer.clone(), Format::R32_UINT, alignment..alignment + 256)
+                .unwrap();
+        assert_eq!(view.range(), alignment..alignment + 256);
+
+        let view = view.reinterpret(Format::R8G8B8A8_UNORM).unwrap();
+        assert_eq!(view.format(), Format::R8G8B8A8_UNORM);
+        assert_eq!(view.range(), alignment..alignment + 256);
+
+        match BufferView::with_range(buffer.clone(), Format::R32_UINT, 0..4100) {
+            Err(BufferViewCreationError::RangeOutOfBounds) => (),
+            _ => panic!(),
+        }
+
+        match BufferView::with_range(buffer, Format::R32_UINT, 0..6) {
+            Err(BufferViewCreationError::RangeNotMultipleOfFormatSize) => (),
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn wrong_usage() {
         // `VK_FORMAT_R8G8B8A8_UNORM` guaranteed to be a supported format
@@ -13,27 +13,45 @@ use crate::command_buffer::pool::standard::StandardCommandPoolAlloc;
 use crate::command_buffer::pool::standard::StandardCommandPoolBuilder;
 use crate::command_buffer::pool::CommandPool;
 use crate::command_buffer::pool::CommandPoolAlloc;
+use crate::command_buffer::pool::CommandPoolAllocReset;
 use crate::command_buffer::pool::CommandPoolBuilderAlloc;
 use crate::command_buffer::synced::CommandBufferState;
+#[cfg(feature = "command_introspection")]
+use crate::command_buffer::synced::RecordedCommand;
 use crate::command_buffer::synced::SyncCommandBuffer;
 use crate::command_buffer::synced::SyncCommandBufferBuilder;
 use crate::command_buffer::synced::SyncCommandBufferBuilderError;
+use crate::command_buffer::synced::SyncGraph;
 use crate::command_buffer::sys::UnsafeCommandBuffer;
 use crate::command_buffer::sys::UnsafeCommandBufferBuilderBufferImageCopy;
 use crate::command_buffer::sys::UnsafeCommandBufferBuilderColorImageClear;
 use crate::command_buffer::sys::UnsafeCommandBufferBuilderDepthStencilImageClear;
 use crate::command_buffer::sys::UnsafeCommandBufferBuilderImageBlit;
 use crate::command_buffer::sys::UnsafeCommandBufferBuilderImageCopy;
+use crate::command_buffer::sys::UnsafeCommandBufferBuilderImageResolve;
 use crate::command_buffer::validity::*;
+use crate::command_buffer::BufferCopy;
+use crate::command_buffer::BufferImageCopy;
+use crate::command_buffer::BufferMemoryBarrier;
+use crate::command_buffer::ClearAttachment;
+use crate::command_buffer::ClearRect;
 use crate::command_buffer::CommandBufferExecError;
 use crate::command_buffer::CommandBufferInheritance;
 use crate::command_buffer::CommandBufferInheritanceRenderPass;
 use crate::command_buffer::CommandBufferLevel;
 use crate::command_buffer::CommandBufferUsage;
+use crate::command_buffer::ConditionalRenderingFlags;
 use crate::command_buffer::DispatchIndirectCommand;
 use crate::command_buffer::DrawIndexedIndirectCommand;
 use crate::command_buffer::DrawIndirectCommand;
+use crate::command_buffer::ImageBlit;
+use crate::command_buffer::ImageCopy;
+use crate::command_buffer::ImageMemoryBarrier;
+use crate::command_buffer::ImageResolve;
 use crate::command_buffer::ImageUninitializedSafe;
+use crate::command_buffer::MemoryBarrier;
+use crate::command_buffer::MultiDrawCommand;
+use crate::command_buffer::MultiDrawIndexedCommand;
 use crate::command_buffer::PrimaryCommandBuffer;
 use crate::command_buffer::SecondaryCommandBuffer;
 use crate::command_buffer::SubpassContents;
@@ -46,14 +64,16 @@ use crate::device::Queue;
 use crate::format::ClearValue;
 use crate::format::NumericType;
 use crate::format::Pixel;
+use crate::image::immutable::SubImage;
+use crate::image::CubeFace;
 use crate::image::ImageAccess;
-use crate::image::ImageAspect;
-use crate::image::ImageAspects;
 use crate::image::ImageLayout;
+use crate::instance::debug::DebugUtilsLabel;
 use crate::pipeline::color_blend::LogicOp;
 use crate::pipeline::depth_stencil::CompareOp;
 use crate::pipeline::depth_stencil::StencilFaces;
 use crate::pipeline::depth_stencil::StencilOp;
+use crate::pipeline::fragment_shading_rate::FragmentShadingRateCombinerOp;
 use crate::pipeline::input_assembly::Index;
 use crate::pipeline::input_assembly::IndexType;
 use crate::pipeline::input_assembly::PrimitiveTopology;
@@ -61,6 +81,8 @@ use crate::pipeline::layout::PipelineLayout;
 use crate::pipeline::rasterization::CullMode;
 use crate::pipeline::rasterization::FrontFace;
 use crate::pipeline::vertex::VertexBuffersCollection;
+use crate::pipeline::vertex::VertexInput;
+use crate::pipeline::vertex::VertexInputRate;
 use crate::pipeline::viewport::Scissor;
 use crate::pipeline::viewport::Viewport;
 use crate::pipeline::ComputePipeline;
@@ -90,12 +112,16 @@ use crate::VulkanObject;
 use crate::{OomError, SafeDeref};
 use fnv::FnvHashMap;
 use smallvec::SmallVec;
+use std::cmp;
 use std::error;
 use std::ffi::CStr;
+use std::ffi::CString;
 use std::fmt;
 use std::iter;
 use std::marker::PhantomData;
 use std::mem;
+use std::ops::Deref;
+use std::ops::DerefMut;
 use std::ops::Range;
 use std::slice;
 use std::sync::atomic::AtomicBool;
@@ -126,6 +152,20 @@ pub struct AutoCommandBufferBuilder<L, P = StandardCommandPoolBuilder> {
     // If any queries are active, this hashmap contains their state.
     query_state: FnvHashMap<ash::vk::QueryType, QueryState>,
 
+    // If conditional rendering is active, contains its state.
+    conditional_rendering_state: Option<ConditionalRenderingState>,
+
+    // Whether transform feedback is active. It can only be active inside a subpass.
+    transform_feedback_active: bool,
+
+    // The number of debug label regions that were opened in this command buffer and not closed
+    // yet.
+    debug_utils_label_depth: u32,
+
+    // The array elements of partially-bound descriptor bindings that the user declared as
+    // accessed, indexed by set and binding number.
+    accessed_descriptors: FnvHashMap<(u32, u32), Vec<u32>>,
+
     _data: PhantomData<L>,
 }
 
@@ -134,6 +174,7 @@ struct RenderPassState {
     subpass: Subpass,
     contents: SubpassContents,
     framebuffer: ash::vk::Framebuffer, // Always null for secondary command buffers
+    framebuffer_dimensions: Option<[u32; 3]>, // None if the framebuffer is unknown
 }
 
 // The state of an active query.
@@ -145,6 +186,11 @@ struct QueryState {
     in_subpass: bool,
 }
 
+// The state of active conditional rendering.
+struct ConditionalRenderingState {
+    in_subpass: bool,
+}
+
 impl AutoCommandBufferBuilder<PrimaryAutoCommandBuffer, StandardCommandPoolBuilder> {
     /// Starts building a primary command buffer.
     #[inline]
@@ -229,6 +275,7 @@ impl AutoCommandBufferBuilder<SecondaryAutoCommandBuffer, StandardCommandPoolBui
             }),
             occlusion_query: None,
             query_statistics_flags: QueryPipelineStatisticFlags::none(),
+            viewport_scissor_depths: None,
         });
 
         AutoCommandBufferBuilder::with_level(device, queue_family, usage, level)
@@ -264,6 +311,66 @@ impl AutoCommandBufferBuilder<SecondaryAutoCommandBuffer, StandardCommandPoolBui
             }),
             occlusion_query,
             query_statistics_flags,
+            viewport_scissor_depths: None,
+        });
+
+        Ok(AutoCommandBufferBuilder::with_level(
+            device,
+            queue_family,
+            usage,
+            level,
+        )?)
+    }
+
+    /// Same as `secondary_graphics`, but the command buffer inherits the viewports and scissors
+    /// that are set on the primary command buffer it is executed in.
+    ///
+    /// One viewport and scissor is inherited for each element of `viewport_depths`, which
+    /// contains the depth range of each viewport. The depth ranges must match the ones of the
+    /// viewports that are set on the primary command buffer. Draw commands in the secondary
+    /// command buffer don't need to set these viewports and scissors themselves.
+    ///
+    /// The [`nv_inherited_viewport_scissor`](crate::device::DeviceExtensions::nv_inherited_viewport_scissor)
+    /// extension and the
+    /// [`inherited_viewport_scissor2_d`](crate::device::Features::inherited_viewport_scissor2_d)
+    /// feature must be enabled on the device.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `viewport_depths` is empty.
+    /// - Panics if `viewport_depths` contains more elements than the `max_viewports` device
+    ///   property, or more than one if the `multi_viewport` feature is not enabled.
+    pub fn secondary_graphics_inherit_viewport_scissor(
+        device: Arc<Device>,
+        queue_family: QueueFamily,
+        usage: CommandBufferUsage,
+        subpass: Subpass,
+        viewport_depths: impl IntoIterator<Item = Range<f32>>,
+    ) -> Result<
+        AutoCommandBufferBuilder<SecondaryAutoCommandBuffer, StandardCommandPoolBuilder>,
+        BeginError,
+    > {
+        if !device.enabled_extensions().nv_inherited_viewport_scissor
+            || !device.enabled_features().inherited_viewport_scissor2_d
+        {
+            return Err(BeginError::InheritedViewportScissorFeatureNotEnabled);
+        }
+
+        let viewport_depths: Vec<_> = viewport_depths.into_iter().collect();
+        assert!(!viewport_depths.is_empty());
+        assert!(
+            viewport_depths.len() as u32 <= device.physical_device().properties().max_viewports
+        );
+        assert!(viewport_depths.len() == 1 || device.enabled_features().multi_viewport);
+
+        let level = CommandBufferLevel::Secondary(CommandBufferInheritance {
+            render_pass: Some(CommandBufferInheritanceRenderPass {
+                subpass,
+                framebuffer: None,
+            }),
+            occlusion_query: None,
+            query_statistics_flags: QueryPipelineStatisticFlags::none(),
+            viewport_scissor_depths: Some(viewport_depths),
         });
 
         Ok(AutoCommandBufferBuilder::with_level(
@@ -276,15 +383,73 @@ impl AutoCommandBufferBuilder<SecondaryAutoCommandBuffer, StandardCommandPoolBui
 }
 
 impl<L> AutoCommandBufferBuilder<L, StandardCommandPoolBuilder> {
-    // Actual constructor. Private.
-    fn with_level(
+    // Actual constructor.
+    pub(super) fn with_level(
         device: Arc<Device>,
         queue_family: QueueFamily,
         usage: CommandBufferUsage,
         level: CommandBufferLevel,
     ) -> Result<AutoCommandBufferBuilder<L, StandardCommandPoolBuilder>, OomError> {
-        let (inheritance, render_pass_state) = match &level {
-            CommandBufferLevel::Primary => (None, None),
+        unsafe {
+            let pool = Device::standard_command_pool(&device, queue_family);
+            let pool_builder_alloc = pool
+                .alloc(!matches!(level, CommandBufferLevel::Primary), 1)?
+                .next()
+                .expect("Requested one command buffer from the command pool, but got zero.");
+            let builder_state = BuilderState::new(&level);
+            let inner = SyncCommandBufferBuilder::new(pool_builder_alloc.inner(), level, usage)?;
+
+            Ok(AutoCommandBufferBuilder::from_parts(
+                inner,
+                pool_builder_alloc,
+                usage,
+                builder_state,
+            ))
+        }
+    }
+}
+
+impl<L, P> AutoCommandBufferBuilder<L, P>
+where
+    P: CommandPoolBuilderAlloc,
+{
+    // Builds the builder around a command buffer that has just begun recording.
+    fn from_parts(
+        inner: SyncCommandBufferBuilder,
+        pool_builder_alloc: P,
+        usage: CommandBufferUsage,
+        builder_state: BuilderState,
+    ) -> AutoCommandBufferBuilder<L, P> {
+        AutoCommandBufferBuilder {
+            inner,
+            queue_family_id: pool_builder_alloc.queue_family().id(),
+            pool_builder_alloc,
+            render_pass_state: builder_state.render_pass_state,
+            query_state: FnvHashMap::default(),
+            conditional_rendering_state: None,
+            transform_feedback_active: false,
+            debug_utils_label_depth: 0,
+            accessed_descriptors: FnvHashMap::default(),
+            inheritance: builder_state.inheritance,
+            usage,
+            _data: PhantomData,
+        }
+    }
+}
+
+// The initial state of a builder, which depends on the level of the command buffer.
+struct BuilderState {
+    inheritance: Option<CommandBufferInheritance>,
+    render_pass_state: Option<RenderPassState>,
+}
+
+impl BuilderState {
+    fn new(level: &CommandBufferLevel) -> BuilderState {
+        match level {
+            CommandBufferLevel::Primary => BuilderState {
+                inheritance: None,
+                render_pass_state: None,
+            },
             CommandBufferLevel::Secondary(inheritance) => {
                 let render_pass_state = inheritance.render_pass.as_ref().map(
                     |CommandBufferInheritanceRenderPass {
@@ -294,38 +459,22 @@ impl<L> AutoCommandBufferBuilder<L, StandardCommandPoolBuilder> {
                         subpass: subpass.clone(),
                         contents: SubpassContents::Inline,
                         framebuffer: ash::vk::Framebuffer::null(), // Only needed for primary command buffers
+                        framebuffer_dimensions: framebuffer
+                            .as_ref()
+                            .map(|framebuffer| framebuffer.dimensions()),
                     },
                 );
 
-                (
-                    Some(CommandBufferInheritance {
+                BuilderState {
+                    inheritance: Some(CommandBufferInheritance {
                         render_pass: inheritance.render_pass.clone(),
                         occlusion_query: inheritance.occlusion_query,
                         query_statistics_flags: inheritance.query_statistics_flags,
+                        viewport_scissor_depths: inheritance.viewport_scissor_depths.clone(),
                     }),
                     render_pass_state,
-                )
+                }
             }
-        };
-
-        unsafe {
-            let pool = Device::standard_command_pool(&device, queue_family);
-            let pool_builder_alloc = pool
-                .alloc(!matches!(level, CommandBufferLevel::Primary), 1)?
-                .next()
-                .expect("Requested one command buffer from the command pool, but got zero.");
-            let inner = SyncCommandBufferBuilder::new(pool_builder_alloc.inner(), level, usage)?;
-
-            Ok(AutoCommandBufferBuilder {
-                inner,
-                pool_builder_alloc,
-                queue_family_id: queue_family.id(),
-                render_pass_state,
-                query_state: FnvHashMap::default(),
-                inheritance,
-                usage,
-                _data: PhantomData,
-            })
         }
     }
 }
@@ -334,6 +483,9 @@ impl<L> AutoCommandBufferBuilder<L, StandardCommandPoolBuilder> {
 pub enum BeginError {
     /// Occlusion query inheritance was requested, but the `inherited_queries` feature was not enabled.
     InheritedQueriesFeatureNotEnabled,
+    /// Viewport and scissor inheritance was requested, but the `nv_inherited_viewport_scissor`
+    /// extension or the `inherited_viewport_scissor2_d` feature was not enabled.
+    InheritedViewportScissorFeatureNotEnabled,
     /// Not enough memory.
     OomError(OomError),
     /// Pipeline statistics query inheritance was requested, but the `pipeline_statistics_query` feature was not enabled.
@@ -361,6 +513,10 @@ impl fmt::Display for BeginError {
                     "occlusion query inheritance was requested but the corresponding feature \
                  wasn't enabled"
                 }
+                Self::InheritedViewportScissorFeatureNotEnabled => {
+                    "viewport and scissor inheritance was requested but the corresponding \
+                 extension or feature wasn't enabled"
+                }
                 Self::OomError(_) => "not enough memory available",
                 Self::PipelineStatisticsQueryFeatureNotEnabled => {
                     "pipeline statistics query inheritance was requested but the corresponding \
@@ -378,6 +534,50 @@ impl From<OomError> for BeginError {
     }
 }
 
+/// Error that can happen when resetting a command buffer to record it again.
+#[derive(Clone, Copy, Debug)]
+pub enum ResetError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The command buffer can't be recorded on the current thread, because its pool belongs to
+    /// another thread.
+    WrongThread,
+}
+
+impl error::Error for ResetError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Self::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ResetError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                Self::OomError(_) => "not enough memory available",
+                Self::WrongThread => {
+                    "the command buffer can't be recorded on the current thread, because its pool \
+                     belongs to another thread"
+                }
+            }
+        )
+    }
+}
+
+impl From<OomError> for ResetError {
+    #[inline]
+    fn from(err: OomError) -> Self {
+        Self::OomError(err)
+    }
+}
+
 impl<P> AutoCommandBufferBuilder<PrimaryAutoCommandBuffer<P::Alloc>, P>
 where
     P: CommandPoolBuilderAlloc,
@@ -393,6 +593,14 @@ where
             return Err(AutoCommandBufferBuilderContextError::QueryIsActive.into());
         }
 
+        if self.conditional_rendering_state.is_some() {
+            return Err(AutoCommandBufferBuilderContextError::ConditionalRenderingIsActive.into());
+        }
+
+        if self.transform_feedback_active {
+            return Err(AutoCommandBufferBuilderContextError::TransformFeedbackIsActive.into());
+        }
+
         let submit_state = match self.usage {
             CommandBufferUsage::MultipleSubmit => SubmitState::ExclusiveUse {
                 in_use: AtomicBool::new(false),
@@ -422,6 +630,18 @@ where
             return Err(AutoCommandBufferBuilderContextError::QueryIsActive.into());
         }
 
+        if self.conditional_rendering_state.is_some() {
+            return Err(AutoCommandBufferBuilderContextError::ConditionalRenderingIsActive.into());
+        }
+
+        if self.transform_feedback_active {
+            return Err(AutoCommandBufferBuilderContextError::TransformFeedbackIsActive.into());
+        }
+
+        if self.debug_utils_label_depth != 0 {
+            return Err(AutoCommandBufferBuilderContextError::DebugUtilsLabelIsActive.into());
+        }
+
         let submit_state = match self.usage {
             CommandBufferUsage::MultipleSubmit => SubmitState::ExclusiveUse {
                 in_use: AtomicBool::new(false),
@@ -498,6 +718,17 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         self.inner.state()
     }
 
+    /// Returns the dependencies that the automatic synchronization computed for the commands
+    /// recorded so far, and the pipeline barriers that it inserted for them.
+    ///
+    /// This is meant for debugging, for example to find out why recording a command returned a
+    /// `SyncCommandBufferBuilderError::Conflict`. The graph can be written to a file with
+    /// [`SyncGraph::write_dot`] or [`SyncGraph::write_json`].
+    #[inline]
+    pub fn sync_graph(&self) -> SyncGraph {
+        self.inner.sync_graph()
+    }
+
     /// Binds descriptor sets for future dispatch or draw calls.
     ///
     /// # Panics
@@ -569,6 +800,43 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         self
     }
 
+    /// Declares the array elements of a descriptor binding that are accessed by the shaders of
+    /// future dispatch or draw calls.
+    ///
+    /// For bindings of a descriptor set layout with
+    /// [`partially_bound`](crate::descriptor_set::layout::DescriptorDesc::partially_bound)
+    /// enabled, not all array elements need to be written. Instead of requiring every element to
+    /// be written, the descriptor sets are then only checked to contain resources for the
+    /// elements that are declared here, in addition to the single element of bindings that have
+    /// a descriptor count of 1. The elements that are written are always checked to be valid for
+    /// the shader.
+    ///
+    /// The elements replace any that were previously declared for the same set and binding
+    /// number, and apply to both the compute and graphics bind points until they are cleared with
+    /// [`clear_accessed_descriptors`](Self::clear_accessed_descriptors). They have no effect on
+    /// bindings that are not partially bound.
+    pub fn set_accessed_descriptors(
+        &mut self,
+        set_num: u32,
+        binding_num: u32,
+        elements: impl IntoIterator<Item = u32>,
+    ) -> &mut Self {
+        let mut elements: Vec<u32> = elements.into_iter().collect();
+        elements.sort_unstable();
+        elements.dedup();
+        self.accessed_descriptors
+            .insert((set_num, binding_num), elements);
+        self
+    }
+
+    /// Clears the array elements that were declared with
+    /// [`set_accessed_descriptors`](Self::set_accessed_descriptors).
+    #[inline]
+    pub fn clear_accessed_descriptors(&mut self) -> &mut Self {
+        self.accessed_descriptors.clear();
+        self
+    }
+
     /// Binds an index buffer for future indexed draw calls.
     ///
     /// # Panics
@@ -711,6 +979,45 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         self
     }
 
+    /// Binds buffers that capture the outputs of the vertex processing stages while transform
+    /// feedback is active.
+    ///
+    /// The buffers are bound to consecutive bindings starting at `first_binding`. Each buffer
+    /// captures the outputs that were declared with the corresponding `XfbBuffer` decoration in
+    /// the last vertex processing shader of the pipeline, see
+    /// [`ShaderInterfaceEntry::transform_feedback`](crate::shader::ShaderInterfaceEntry::transform_feedback).
+    ///
+    /// The [`ext_transform_feedback`](crate::device::DeviceExtensions::ext_transform_feedback)
+    /// extension and the [`transform_feedback`](crate::device::Features::transform_feedback)
+    /// feature must be enabled on the device, and the buffers must have the
+    /// `transform_feedback_buffer` usage. Transform feedback must not be active.
+    pub fn bind_transform_feedback_buffers<I>(
+        &mut self,
+        first_binding: u32,
+        buffers: I,
+    ) -> Result<&mut Self, BindTransformFeedbackBuffersError>
+    where
+        I: IntoIterator<Item = Arc<dyn BufferAccess>>,
+    {
+        let buffers: SmallVec<[_; 4]> = buffers.into_iter().collect();
+        check_bind_transform_feedback_buffers(self.device(), first_binding, &buffers)?;
+
+        if !self.queue_family().supports_graphics() {
+            return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+        }
+
+        if self.transform_feedback_active {
+            return Err(AutoCommandBufferBuilderContextError::TransformFeedbackIsActive.into());
+        }
+
+        unsafe {
+            self.inner
+                .bind_transform_feedback_buffers(first_binding, buffers)?;
+        }
+
+        Ok(self)
+    }
+
     /// Binds vertex buffers for future draw calls.
     ///
     /// # Panics
@@ -825,6 +1132,51 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         layer_count: u32,
         filter: Filter,
     ) -> Result<&mut Self, BlitImageError> {
+        // Negative offsets wrap around, and are rejected as out of range.
+        let to_u32 = |offset: [i32; 3]| [offset[0] as u32, offset[1] as u32, offset[2] as u32];
+        let region = ImageBlit {
+            aspects: blit_aspects(source.format().aspects()),
+            source_mip_level,
+            source_base_array_layer,
+            source_offsets: [to_u32(source_top_left), to_u32(source_bottom_right)],
+            destination_mip_level,
+            destination_base_array_layer,
+            destination_offsets: [
+                to_u32(destination_top_left),
+                to_u32(destination_bottom_right),
+            ],
+            layer_count,
+        };
+
+        self.blit_image_regions(source, destination, iter::once(region), filter)
+    }
+
+    /// Adds a command that blits the given regions of an image to another.
+    ///
+    /// The same restrictions as [`blit_image`](Self::blit_image) apply to each region. In
+    /// addition:
+    ///
+    /// - The aspects of each region must exist in the formats of both images, and must not
+    ///   include color together with depth or stencil.
+    /// - The `Linear` filter can only be used if the format of the source image supports linear
+    ///   filtering.
+    /// - The `Cubic` filter can only be used with a two-dimensional source image whose format
+    ///   supports cubic filtering.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the source or the destination was not created with `device`.
+    ///
+    pub fn blit_image_regions<R>(
+        &mut self,
+        source: Arc<dyn ImageAccess>,
+        destination: Arc<dyn ImageAccess>,
+        regions: R,
+        filter: Filter,
+    ) -> Result<&mut Self, BlitImageError>
+    where
+        R: IntoIterator<Item = ImageBlit>,
+    {
         unsafe {
             if !self.queue_family().supports_graphics() {
                 return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
@@ -832,55 +1184,167 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
 
             self.ensure_outside_render_pass()?;
 
-            check_blit_image(
+            let regions: SmallVec<[_; 4]> = regions.into_iter().collect();
+            check_blit_image_regions(
                 self.device(),
                 source.as_ref(),
-                source_top_left,
-                source_bottom_right,
-                source_base_array_layer,
-                source_mip_level,
                 destination.as_ref(),
-                destination_top_left,
-                destination_bottom_right,
-                destination_base_array_layer,
-                destination_mip_level,
-                layer_count,
+                &regions,
                 filter,
             )?;
 
-            let blit = UnsafeCommandBufferBuilderImageBlit {
-                // TODO:
-                aspects: if source.format().aspects().color {
-                    ImageAspects {
-                        color: true,
-                        ..ImageAspects::none()
-                    }
-                } else {
-                    unimplemented!()
-                },
-                source_mip_level,
-                destination_mip_level,
-                source_base_array_layer,
-                destination_base_array_layer,
-                layer_count,
-                source_top_left,
-                source_bottom_right,
-                destination_top_left,
-                destination_bottom_right,
-            };
+            let blits: SmallVec<[_; 4]> = regions
+                .iter()
+                .map(|region| UnsafeCommandBufferBuilderImageBlit {
+                    aspects: region.aspects,
+                    source_mip_level: region.source_mip_level,
+                    destination_mip_level: region.destination_mip_level,
+                    source_base_array_layer: region.source_base_array_layer,
+                    destination_base_array_layer: region.destination_base_array_layer,
+                    layer_count: region.layer_count,
+                    source_top_left: to_i32(region.source_offsets[0]),
+                    source_bottom_right: to_i32(region.source_offsets[1]),
+                    destination_top_left: to_i32(region.destination_offsets[0]),
+                    destination_bottom_right: to_i32(region.destination_offsets[1]),
+                })
+                .collect();
 
             self.inner.blit_image(
                 source,
                 ImageLayout::TransferSrcOptimal,
                 destination, // TODO: let choose layout
                 ImageLayout::TransferDstOptimal,
-                iter::once(blit),
+                blits,
                 filter,
             )?;
             Ok(self)
         }
     }
 
+    /// Adds commands that fill the mipmap levels of an image from its first level.
+    ///
+    /// Each level is blitted from the previous one, with the `Linear` filter if the format of
+    /// the image supports it and the `Nearest` filter otherwise. The image must have been created
+    /// with the `transfer_source` and `transfer_destination` usages, and the same restrictions as
+    /// [`blit_image`](Self::blit_image) apply, in particular its format must support blit
+    /// operations.
+    ///
+    /// The levels are accessed through a [`SubImage`] each, so that the command buffer
+    /// transitions them to the layouts of the blits one by one. The command buffer tracks these
+    /// accesses separately from accesses to the image as a whole, so the first level should have
+    /// been written in a previous command buffer, or through a `SubImage` of the first level.
+    ///
+    /// Does nothing if the image has a single mipmap level.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the image was not created with `device`.
+    ///
+    pub fn generate_mipmaps(
+        &mut self,
+        image: Arc<dyn ImageAccess>,
+    ) -> Result<&mut Self, BlitImageError> {
+        let dimensions = image.dimensions();
+        let layout = image.final_layout_requirement();
+        let filter = if image
+            .inner()
+            .image
+            .format_features()
+            .sampled_image_filter_linear
+        {
+            Filter::Linear
+        } else {
+            Filter::Nearest
+        };
+
+        for level in 1..image.mipmap_levels() {
+            let [xs, ys, ds] = dimensions
+                .mipmap_dimensions(level - 1)
+                .unwrap()
+                .width_height_depth();
+            let [xd, yd, dd] = dimensions
+                .mipmap_dimensions(level)
+                .unwrap()
+                .width_height_depth();
+
+            let source = SubImage::new(
+                image.clone(),
+                level - 1,
+                1,
+                0,
+                dimensions.array_layers(),
+                layout,
+            );
+            let destination = SubImage::new(
+                image.clone(),
+                level,
+                1,
+                0,
+                dimensions.array_layers(),
+                layout,
+            );
+
+            self.blit_image(
+                source,
+                [0, 0, 0],
+                [xs as i32, ys as i32, ds as i32],
+                0,
+                level - 1,
+                destination,
+                [0, 0, 0],
+                [xd as i32, yd as i32, dd as i32],
+                0,
+                level,
+                dimensions.array_layers(),
+                filter,
+            )?;
+        }
+
+        Ok(self)
+    }
+
+    /// Adds a command that clears regions of attachments of the current subpass.
+    ///
+    /// Unlike the load operations of a render pass, this can be used in the middle of a subpass,
+    /// for example to clear the part of the framebuffer of each view of a split-screen game.
+    /// Each attachment is cleared in all of the rects.
+    ///
+    /// This must be called inside a subpass whose contents are recorded inline.
+    pub fn clear_attachments<A, R>(
+        &mut self,
+        attachments: A,
+        rects: R,
+    ) -> Result<&mut Self, ClearAttachmentsError>
+    where
+        A: IntoIterator<Item = ClearAttachment>,
+        R: IntoIterator<Item = ClearRect>,
+    {
+        let render_pass_state = self
+            .render_pass_state
+            .as_ref()
+            .ok_or(AutoCommandBufferBuilderContextError::ForbiddenOutsideRenderPass)?;
+
+        if render_pass_state.contents != SubpassContents::Inline {
+            return Err(AutoCommandBufferBuilderContextError::WrongSubpassType.into());
+        }
+
+        let attachments: SmallVec<[_; 3]> = attachments.into_iter().collect();
+        let rects: SmallVec<[_; 4]> = rects.into_iter().collect();
+
+        check_clear_attachments(
+            &render_pass_state.subpass,
+            render_pass_state.framebuffer_dimensions,
+            &attachments,
+            &rects,
+        )?;
+
+        unsafe {
+            self.inner.clear_attachments(attachments, rects);
+        }
+
+        Ok(self)
+    }
+
     /// Adds a command that clears all the layers and mipmap levels of a color image with a
     /// specific value.
     ///
@@ -1035,17 +1499,21 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         D: TypedBufferAccess<Content = T> + 'static,
         T: ?Sized,
     {
-        unsafe {
-            self.ensure_outside_render_pass()?;
-            let infos = check_copy_buffer(self.device(), source.as_ref(), destination.as_ref())?;
-            self.inner
-                .copy_buffer(source, destination, iter::once((0, 0, infos.copy_size)))?;
-            Ok(self)
-        }
+        let copy_size = cmp::min(source.size(), destination.size());
+        self.copy_buffer_regions(
+            source,
+            destination,
+            iter::once(BufferCopy {
+                source_offset: 0,
+                destination_offset: 0,
+                size: copy_size,
+            }),
+        )
     }
 
     /// Adds a command that copies a range from the source to the destination buffer.
-    /// Panics if out of bounds.
+    ///
+    /// The offsets and `count` are in elements of `T`.
     #[inline]
     pub fn copy_buffer_dimensions<S, D, T>(
         &mut self,
@@ -1059,29 +1527,62 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         S: TypedBufferAccess<Content = [T]> + 'static,
         D: TypedBufferAccess<Content = [T]> + 'static,
     {
-        self.ensure_outside_render_pass()?;
-
-        let _infos = check_copy_buffer(self.device(), source.as_ref(), destination.as_ref())?;
-        debug_assert!(source_offset + count <= source.len());
-        debug_assert!(destination_offset + count <= destination.len());
-
-        let size = std::mem::size_of::<T>() as DeviceSize;
-        unsafe {
-            self.inner.copy_buffer(
-                source,
-                destination,
-                iter::once((
-                    source_offset * size,
-                    destination_offset * size,
-                    count * size,
-                )),
-            )?;
-        }
-        Ok(self)
+        let size = mem::size_of::<T>() as DeviceSize;
+        self.copy_buffer_regions(
+            source,
+            destination,
+            iter::once(BufferCopy {
+                source_offset: source_offset * size,
+                destination_offset: destination_offset * size,
+                size: count * size,
+            }),
+        )
     }
 
-    /// Adds a command that copies from a buffer to an image.
-    pub fn copy_buffer_to_image<S, Px>(
+    /// Adds a command that copies the given regions from the source to the destination buffer.
+    ///
+    /// The offsets and sizes of the regions are in bytes, relative to the start of `source` and
+    /// `destination`. This can be used to gather or scatter strided data, for example to copy
+    /// one member out of each element of an array. The regions must be within both buffers, and
+    /// the destination ranges of two regions must not overlap.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the source or the destination was not created with `device`.
+    ///
+    pub fn copy_buffer_regions<S, D, R>(
+        &mut self,
+        source: Arc<S>,
+        destination: Arc<D>,
+        regions: R,
+    ) -> Result<&mut Self, CopyBufferError>
+    where
+        S: BufferAccess + 'static,
+        D: BufferAccess + 'static,
+        R: IntoIterator<Item = BufferCopy>,
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+
+            let regions: SmallVec<[_; 4]> = regions.into_iter().collect();
+            check_copy_buffer_regions(
+                self.device(),
+                source.as_ref(),
+                destination.as_ref(),
+                &regions,
+            )?;
+
+            let copies: SmallVec<[_; 4]> = regions
+                .iter()
+                .map(|region| (region.source_offset, region.destination_offset, region.size))
+                .collect();
+            self.inner.copy_buffer(source, destination, copies)?;
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that copies from a buffer to an image.
+    pub fn copy_buffer_to_image<S, Px>(
         &mut self,
         source: Arc<S>,
         destination: Arc<dyn ImageAccess>,
@@ -1110,43 +1611,88 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
     where
         S: TypedBufferAccess<Content = [Px]> + 'static,
         Px: Pixel,
+    {
+        let region = BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_aspect: default_aspect(destination.format().aspects()),
+            image_mip_level: mipmap,
+            image_base_array_layer: first_layer,
+            image_layer_count: num_layers,
+            image_offset: offset,
+            image_extent: size,
+        };
+
+        self.copy_buffer_to_image_regions(source, destination, iter::once(region))
+    }
+
+    /// Adds a command that copies from a buffer to a whole face of a cube map, at `mipmap` level.
+    ///
+    /// `cube_index` is the index of the cube in a cube array, or 0 for an image that contains a
+    /// single cube.
+    pub fn copy_buffer_to_cube_face<S, Px>(
+        &mut self,
+        source: Arc<S>,
+        destination: Arc<dyn ImageAccess>,
+        face: CubeFace,
+        cube_index: u32,
+        mipmap: u32,
+    ) -> Result<&mut Self, CopyBufferImageError>
+    where
+        S: TypedBufferAccess<Content = [Px]> + 'static,
+        Px: Pixel,
+    {
+        let size = destination
+            .dimensions()
+            .mipmap_dimensions(mipmap)
+            .map_or([0, 0, 0], |dimensions| dimensions.width_height_depth());
+
+        self.copy_buffer_to_image_dimensions(
+            source,
+            destination,
+            [0, 0, 0],
+            size,
+            face.array_layer(cube_index),
+            1,
+            mipmap,
+        )
+    }
+
+    /// Adds a command that copies from a buffer to the given regions of an image.
+    ///
+    /// The offsets of the regions are in bytes from the start of `source`, and their row length
+    /// and image height are in texels. A row length or image height of 0 means that the data is
+    /// tightly packed in the buffer.
+    pub fn copy_buffer_to_image_regions<S, Px, R>(
+        &mut self,
+        source: Arc<S>,
+        destination: Arc<dyn ImageAccess>,
+        regions: R,
+    ) -> Result<&mut Self, CopyBufferImageError>
+    where
+        S: TypedBufferAccess<Content = [Px]> + 'static,
+        Px: Pixel,
+        R: IntoIterator<Item = BufferImageCopy>,
     {
         unsafe {
             self.ensure_outside_render_pass()?;
 
-            check_copy_buffer_image(
+            let regions: SmallVec<[_; 4]> = regions.into_iter().collect();
+            check_copy_buffer_image_regions(
                 self.device(),
                 source.as_ref(),
                 destination.as_ref(),
                 CheckCopyBufferImageTy::BufferToImage,
-                offset,
-                size,
-                first_layer,
-                num_layers,
-                mipmap,
+                &regions,
             )?;
 
-            let copy = UnsafeCommandBufferBuilderBufferImageCopy {
-                buffer_offset: 0,
-                buffer_row_length: 0,
-                buffer_image_height: 0,
-                image_aspect: if destination.format().aspects().color {
-                    ImageAspect::Color
-                } else {
-                    unimplemented!()
-                },
-                image_mip_level: mipmap,
-                image_base_array_layer: first_layer,
-                image_layer_count: num_layers,
-                image_offset: [offset[0] as i32, offset[1] as i32, offset[2] as i32],
-                image_extent: size,
-            };
-
+            let copies: SmallVec<[_; 4]> = regions.iter().map(buffer_image_copy).collect();
             self.inner.copy_buffer_to_image(
                 source,
                 destination,
                 ImageLayout::TransferDstOptimal, // TODO: let choose layout
-                iter::once(copy),
+                copies,
             )?;
             Ok(self)
         }
@@ -1187,47 +1733,82 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         extent: [u32; 3],
         layer_count: u32,
     ) -> Result<&mut Self, CopyImageError> {
+        let region = ImageCopy {
+            aspects: copy_aspects(source.format().aspects(), destination.format().aspects()),
+            source_mip_level,
+            source_base_array_layer,
+            // Negative offsets wrap around, and are rejected as out of range.
+            source_offset: [
+                source_offset[0] as u32,
+                source_offset[1] as u32,
+                source_offset[2] as u32,
+            ],
+            destination_mip_level,
+            destination_base_array_layer,
+            destination_offset: [
+                destination_offset[0] as u32,
+                destination_offset[1] as u32,
+                destination_offset[2] as u32,
+            ],
+            layer_count,
+            extent,
+        };
+
+        self.copy_image_regions(source, destination, iter::once(region))
+    }
+
+    /// Adds a command that copies the given regions of an image to another.
+    ///
+    /// The same restrictions as [`copy_image`](Self::copy_image) apply to each region. In
+    /// addition, the aspects of each region must exist in the formats of both images, and must
+    /// not include color together with depth or stencil.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the source or the destination was not created with `device`.
+    ///
+    pub fn copy_image_regions<R>(
+        &mut self,
+        source: Arc<dyn ImageAccess>,
+        destination: Arc<dyn ImageAccess>,
+        regions: R,
+    ) -> Result<&mut Self, CopyImageError>
+    where
+        R: IntoIterator<Item = ImageCopy>,
+    {
         unsafe {
             self.ensure_outside_render_pass()?;
 
-            check_copy_image(
+            let regions: SmallVec<[_; 4]> = regions.into_iter().collect();
+            check_copy_image_regions(
                 self.device(),
                 source.as_ref(),
-                source_offset,
-                source_base_array_layer,
-                source_mip_level,
                 destination.as_ref(),
-                destination_offset,
-                destination_base_array_layer,
-                destination_mip_level,
-                extent,
-                layer_count,
+                &regions,
             )?;
 
-            let source_aspects = source.format().aspects();
-            let destination_aspects = destination.format().aspects();
-            let copy = UnsafeCommandBufferBuilderImageCopy {
-                // TODO: Allowing choosing a subset of the image aspects, but note that if color
-                // is included, neither depth nor stencil may.
-                aspects: ImageAspects {
-                    color: source_aspects.color,
-                    depth: !source_aspects.color
-                        && source_aspects.depth
-                        && destination_aspects.depth,
-                    stencil: !source_aspects.color
-                        && source_aspects.stencil
-                        && destination_aspects.stencil,
-                    ..ImageAspects::none()
-                },
-                source_mip_level,
-                destination_mip_level,
-                source_base_array_layer,
-                destination_base_array_layer,
-                layer_count,
-                source_offset,
-                destination_offset,
-                extent,
-            };
+            let copies: SmallVec<[_; 4]> = regions
+                .iter()
+                .map(|region| UnsafeCommandBufferBuilderImageCopy {
+                    aspects: region.aspects,
+                    source_mip_level: region.source_mip_level,
+                    destination_mip_level: region.destination_mip_level,
+                    source_base_array_layer: region.source_base_array_layer,
+                    destination_base_array_layer: region.destination_base_array_layer,
+                    layer_count: region.layer_count,
+                    source_offset: [
+                        region.source_offset[0] as i32,
+                        region.source_offset[1] as i32,
+                        region.source_offset[2] as i32,
+                    ],
+                    destination_offset: [
+                        region.destination_offset[0] as i32,
+                        region.destination_offset[1] as i32,
+                        region.destination_offset[2] as i32,
+                    ],
+                    extent: region.extent,
+                })
+                .collect();
 
             // TODO: Allow choosing layouts, but note that only Transfer*Optimal and General are
             // valid.
@@ -1236,7 +1817,7 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
                 ImageLayout::TransferSrcOptimal,
                 destination,
                 ImageLayout::TransferDstOptimal,
-                iter::once(copy),
+                copies,
             )?;
             Ok(self)
         }
@@ -1274,73 +1855,176 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
     where
         D: TypedBufferAccess<Content = [Px]> + 'static,
         Px: Pixel,
+    {
+        let region = BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_aspect: default_aspect(source.format().aspects()),
+            image_mip_level: mipmap,
+            image_base_array_layer: first_layer,
+            image_layer_count: num_layers,
+            image_offset: offset,
+            image_extent: size,
+        };
+
+        self.copy_image_to_buffer_regions(source, destination, iter::once(region))
+    }
+
+    /// Adds a command that copies the given regions of an image to a buffer.
+    ///
+    /// The regions are interpreted in the same way as in
+    /// [`copy_buffer_to_image_regions`](Self::copy_buffer_to_image_regions).
+    pub fn copy_image_to_buffer_regions<D, Px, R>(
+        &mut self,
+        source: Arc<dyn ImageAccess>,
+        destination: Arc<D>,
+        regions: R,
+    ) -> Result<&mut Self, CopyBufferImageError>
+    where
+        D: TypedBufferAccess<Content = [Px]> + 'static,
+        Px: Pixel,
+        R: IntoIterator<Item = BufferImageCopy>,
     {
         unsafe {
             self.ensure_outside_render_pass()?;
 
-            check_copy_buffer_image(
+            let regions: SmallVec<[_; 4]> = regions.into_iter().collect();
+            check_copy_buffer_image_regions(
                 self.device(),
                 destination.as_ref(),
                 source.as_ref(),
                 CheckCopyBufferImageTy::ImageToBuffer,
-                offset,
-                size,
-                first_layer,
-                num_layers,
-                mipmap,
+                &regions,
             )?;
 
-            let source_aspects = source.format().aspects();
-            let copy = UnsafeCommandBufferBuilderBufferImageCopy {
-                buffer_offset: 0,
-                buffer_row_length: 0,
-                buffer_image_height: 0,
-                // TODO: Allow the user to choose aspect
-                image_aspect: if source_aspects.color {
-                    ImageAspect::Color
-                } else if source_aspects.depth {
-                    ImageAspect::Depth
-                } else if source_aspects.stencil {
-                    ImageAspect::Stencil
-                } else {
-                    unimplemented!()
-                },
-                image_mip_level: mipmap,
-                image_base_array_layer: first_layer,
-                image_layer_count: num_layers,
-                image_offset: [offset[0] as i32, offset[1] as i32, offset[2] as i32],
-                image_extent: size,
-            };
-
+            let copies: SmallVec<[_; 4]> = regions.iter().map(buffer_image_copy).collect();
             self.inner.copy_image_to_buffer(
                 source,
                 ImageLayout::TransferSrcOptimal,
                 destination, // TODO: let choose layout
-                iter::once(copy),
+                copies,
             )?;
             Ok(self)
         }
     }
 
+    /// Opens a debug label region in the command buffer.
+    ///
+    /// Debugging tools such as RenderDoc show the commands that are recorded until the matching
+    /// [`end_debug_utils_label`](Self::end_debug_utils_label) as a group with the name of the
+    /// label. Regions can be nested. See also
+    /// [`debug_utils_label_scope`](Self::debug_utils_label_scope), which ends the region
+    /// automatically.
+    ///
+    /// The [`ext_debug_utils`](crate::instance::InstanceExtensions::ext_debug_utils) extension
+    /// must be enabled on the instance, and the queue family must support graphics or compute
+    /// operations.
+    #[inline]
+    pub fn begin_debug_utils_label(
+        &mut self,
+        label: DebugUtilsLabel,
+    ) -> Result<&mut Self, DebugUtilsLabelError> {
+        self.ensure_debug_utils_queue_family()?;
+        check_debug_utils_label(self.device(), &label)?;
+
+        unsafe {
+            self.inner
+                .begin_debug_utils_label(CString::new(label.label_name).unwrap(), label.color);
+        }
+
+        self.debug_utils_label_depth += 1;
+        Ok(self)
+    }
+
+    /// Closes the debug label region that was most recently opened with
+    /// [`begin_debug_utils_label`](Self::begin_debug_utils_label).
+    ///
+    /// The region must have been opened in this command buffer.
+    #[inline]
+    pub fn end_debug_utils_label(&mut self) -> Result<&mut Self, DebugUtilsLabelError> {
+        self.ensure_debug_utils_queue_family()?;
+        check_debug_utils_enabled(self.device())?;
+
+        if self.debug_utils_label_depth == 0 {
+            return Err(AutoCommandBufferBuilderContextError::DebugUtilsLabelNotActive.into());
+        }
+
+        unsafe {
+            self.inner.end_debug_utils_label();
+        }
+
+        self.debug_utils_label_depth -= 1;
+        Ok(self)
+    }
+
+    /// Inserts a single debug label into the command buffer.
+    ///
+    /// The same requirements as for [`begin_debug_utils_label`](Self::begin_debug_utils_label)
+    /// apply.
+    #[inline]
+    pub fn insert_debug_utils_label(
+        &mut self,
+        label: DebugUtilsLabel,
+    ) -> Result<&mut Self, DebugUtilsLabelError> {
+        self.ensure_debug_utils_queue_family()?;
+        check_debug_utils_label(self.device(), &label)?;
+
+        unsafe {
+            self.inner
+                .insert_debug_utils_label(CString::new(label.label_name).unwrap(), label.color);
+        }
+
+        Ok(self)
+    }
+
+    /// Opens a debug label region that is closed when the returned guard is dropped.
+    ///
+    /// The guard dereferences to the builder, so the commands of the region can be recorded
+    /// through it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use vulkano::command_buffer::AutoCommandBufferBuilder;
+    /// # use vulkano::command_buffer::PrimaryAutoCommandBuffer;
+    /// # use vulkano::instance::debug::DebugUtilsLabel;
+    /// # let builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer> = return;
+    /// {
+    ///     let mut scope = builder
+    ///         .debug_utils_label_scope(DebugUtilsLabel {
+    ///             label_name: "Shadow pass".into(),
+    ///             color: [0.5, 0.5, 0.5, 1.0],
+    ///         })
+    ///         .unwrap();
+    ///
+    ///     // Record the commands of the shadow pass through `scope`.
+    /// }
+    /// ```
+    #[inline]
+    pub fn debug_utils_label_scope(
+        &mut self,
+        label: DebugUtilsLabel,
+    ) -> Result<DebugUtilsLabelScope<L, P>, DebugUtilsLabelError> {
+        self.begin_debug_utils_label(label)?;
+        Ok(DebugUtilsLabelScope { builder: self })
+    }
+
     /// Open a command buffer debug label region.
     ///
     /// Note: you need to enable `VK_EXT_debug_utils` extension when creating an instance.
+    #[deprecated(since = "0.27", note = "Use `begin_debug_utils_label` instead")]
     #[inline]
     pub fn debug_marker_begin(
         &mut self,
         name: &'static CStr,
         color: [f32; 4],
     ) -> Result<&mut Self, DebugMarkerError> {
-        if !self.queue_family().supports_graphics() && self.queue_family().supports_compute() {
-            return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
-        }
-
         check_debug_marker_color(color)?;
-
-        unsafe {
-            self.inner.debug_marker_begin(name.into(), color);
-        }
-
+        self.begin_debug_utils_label(DebugUtilsLabel {
+            label_name: name.to_string_lossy().into_owned(),
+            color,
+        })?;
         Ok(self)
     }
 
@@ -1348,41 +2032,38 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
     ///
     /// Note: you need to open a command buffer label region first with `debug_marker_begin`.
     /// Note: you need to enable `VK_EXT_debug_utils` extension when creating an instance.
+    #[deprecated(since = "0.27", note = "Use `end_debug_utils_label` instead")]
     #[inline]
     pub fn debug_marker_end(&mut self) -> Result<&mut Self, DebugMarkerError> {
-        if !self.queue_family().supports_graphics() && self.queue_family().supports_compute() {
-            return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
-        }
-
-        // TODO: validate that debug_marker_begin with same name was sent earlier
-
-        unsafe {
-            self.inner.debug_marker_end();
-        }
-
+        self.end_debug_utils_label()?;
         Ok(self)
     }
 
     /// Insert a label into a command buffer.
     ///
     /// Note: you need to enable `VK_EXT_debug_utils` extension when creating an instance.
+    #[deprecated(since = "0.27", note = "Use `insert_debug_utils_label` instead")]
     #[inline]
     pub fn debug_marker_insert(
         &mut self,
         name: &'static CStr,
         color: [f32; 4],
     ) -> Result<&mut Self, DebugMarkerError> {
-        if !self.queue_family().supports_graphics() && self.queue_family().supports_compute() {
-            return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
-        }
-
         check_debug_marker_color(color)?;
+        self.insert_debug_utils_label(DebugUtilsLabel {
+            label_name: name.to_string_lossy().into_owned(),
+            color,
+        })?;
+        Ok(self)
+    }
 
-        unsafe {
-            self.inner.debug_marker_insert(name.into(), color);
+    #[inline]
+    fn ensure_debug_utils_queue_family(&self) -> Result<(), AutoCommandBufferBuilderContextError> {
+        if !self.queue_family().supports_graphics() && !self.queue_family().supports_compute() {
+            return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily);
         }
 
-        Ok(self)
+        Ok(())
     }
 
     /// Perform a single compute operation using a compute pipeline.
@@ -1398,7 +2079,12 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
 
         let pipeline = check_pipeline_compute(self.state())?;
         self.ensure_outside_render_pass()?;
-        check_descriptor_sets_validity(self.state(), pipeline, pipeline.descriptor_requirements())?;
+        check_descriptor_sets_validity(
+            self.state(),
+            pipeline,
+            pipeline.descriptor_requirements(),
+            &self.accessed_descriptors,
+        )?;
         check_push_constants_validity(self.state(), pipeline.layout())?;
         check_dispatch(self.device(), group_counts)?;
 
@@ -1409,8 +2095,47 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         Ok(self)
     }
 
-    /// Perform multiple compute operations using a compute pipeline. One dispatch is performed for
-    /// each [`DispatchIndirectCommand`] struct in `indirect_buffer`.
+    /// Perform a single compute operation using a compute pipeline, with the workgroup IDs
+    /// starting at `base_group` instead of zero.
+    ///
+    /// The `WorkgroupId` builtin of the shader ranges from `base_group` to
+    /// `base_group + group_counts`, which makes it possible to split a large domain into several
+    /// dispatches without passing the offset in push constants. This requires Vulkan 1.1 or the
+    /// [`khr_device_group`](crate::device::DeviceExtensions::khr_device_group) extension.
+    ///
+    /// A compute pipeline must have been bound using
+    /// [`bind_pipeline_compute`](Self::bind_pipeline_compute). Any resources used by the compute
+    /// pipeline, such as descriptor sets, must have been set beforehand.
+    #[inline]
+    pub fn dispatch_base(
+        &mut self,
+        base_group: [u32; 3],
+        group_counts: [u32; 3],
+    ) -> Result<&mut Self, DispatchError> {
+        if !self.queue_family().supports_compute() {
+            return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+        }
+
+        let pipeline = check_pipeline_compute(self.state())?;
+        self.ensure_outside_render_pass()?;
+        check_descriptor_sets_validity(
+            self.state(),
+            pipeline,
+            pipeline.descriptor_requirements(),
+            &self.accessed_descriptors,
+        )?;
+        check_push_constants_validity(self.state(), pipeline.layout())?;
+        check_dispatch_base(self.device(), base_group, group_counts)?;
+
+        unsafe {
+            self.inner.dispatch_base(base_group, group_counts);
+        }
+
+        Ok(self)
+    }
+
+    /// Perform a single compute operation using a compute pipeline, with the group counts read
+    /// from the first [`DispatchIndirectCommand`] struct in `indirect_buffer`.
     ///
     /// A compute pipeline must have been bound using
     /// [`bind_pipeline_compute`](Self::bind_pipeline_compute). Any resources used by the compute
@@ -1429,9 +2154,15 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
 
         let pipeline = check_pipeline_compute(self.state())?;
         self.ensure_outside_render_pass()?;
-        check_descriptor_sets_validity(self.state(), pipeline, pipeline.descriptor_requirements())?;
+        check_descriptor_sets_validity(
+            self.state(),
+            pipeline,
+            pipeline.descriptor_requirements(),
+            &self.accessed_descriptors,
+        )?;
         check_push_constants_validity(self.state(), pipeline.layout())?;
         check_indirect_buffer(self.device(), indirect_buffer.as_ref())?;
+        check_dispatch_indirect(indirect_buffer.as_ref())?;
 
         unsafe {
             self.inner.dispatch_indirect(indirect_buffer)?;
@@ -1461,8 +2192,13 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
     ) -> Result<&mut Self, DrawError> {
         let pipeline = check_pipeline_graphics(self.state())?;
         self.ensure_inside_render_pass_inline(pipeline)?;
-        check_dynamic_state_validity(self.state(), pipeline)?;
-        check_descriptor_sets_validity(self.state(), pipeline, pipeline.descriptor_requirements())?;
+        check_dynamic_state_validity(self.state(), pipeline, self.inherited_viewport_count())?;
+        check_descriptor_sets_validity(
+            self.state(),
+            pipeline,
+            pipeline.descriptor_requirements(),
+            &self.accessed_descriptors,
+        )?;
         check_push_constants_validity(self.state(), pipeline.layout())?;
         check_vertex_buffers(
             self.state(),
@@ -1479,6 +2215,57 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         Ok(self)
     }
 
+    /// Perform multiple draw operations using a graphics pipeline, with a single command.
+    ///
+    /// This is equivalent to calling [`draw`](Self::draw) once for each element of
+    /// `vertex_info`, with the same `instance_count` and `first_instance`, but is cheaper when
+    /// there are many small draws.
+    ///
+    /// The [`ext_multi_draw`](crate::device::DeviceExtensions::ext_multi_draw) extension and the
+    /// [`multi_draw`](crate::device::Features::multi_draw) feature must be enabled on the device.
+    /// The number of draws is limited by the
+    /// [`max_multi_draw_count`](crate::device::Properties::max_multi_draw_count) limit.
+    #[inline]
+    pub fn draw_multi<I>(
+        &mut self,
+        vertex_info: I,
+        instance_count: u32,
+        first_instance: u32,
+    ) -> Result<&mut Self, DrawMultiError>
+    where
+        I: IntoIterator<Item = MultiDrawCommand>,
+    {
+        let vertex_info: SmallVec<[_; 8]> = vertex_info.into_iter().collect();
+
+        let pipeline = check_pipeline_graphics(self.state())?;
+        self.ensure_inside_render_pass_inline(pipeline)?;
+        check_dynamic_state_validity(self.state(), pipeline, self.inherited_viewport_count())?;
+        check_descriptor_sets_validity(
+            self.state(),
+            pipeline,
+            pipeline.descriptor_requirements(),
+            &self.accessed_descriptors,
+        )?;
+        check_push_constants_validity(self.state(), pipeline.layout())?;
+        check_multi_draw(self.device(), vertex_info.len() as u32)?;
+
+        for info in &vertex_info {
+            check_vertex_buffers(
+                self.state(),
+                pipeline,
+                Some((info.first_vertex, info.vertex_count)),
+                Some((first_instance, instance_count)),
+            )?;
+        }
+
+        unsafe {
+            self.inner
+                .draw_multi(vertex_info, instance_count, first_instance);
+        }
+
+        Ok(self)
+    }
+
     /// Perform multiple draw operations using a graphics pipeline.
     ///
     /// One draw is performed for each [`DrawIndirectCommand`] struct in `indirect_buffer`.
@@ -1504,8 +2291,13 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
     {
         let pipeline = check_pipeline_graphics(self.state())?;
         self.ensure_inside_render_pass_inline(pipeline)?;
-        check_dynamic_state_validity(self.state(), pipeline)?;
-        check_descriptor_sets_validity(self.state(), pipeline, pipeline.descriptor_requirements())?;
+        check_dynamic_state_validity(self.state(), pipeline, self.inherited_viewport_count())?;
+        check_descriptor_sets_validity(
+            self.state(),
+            pipeline,
+            pipeline.descriptor_requirements(),
+            &self.accessed_descriptors,
+        )?;
         check_push_constants_validity(self.state(), pipeline.layout())?;
         check_vertex_buffers(self.state(), pipeline, None, None)?;
         check_indirect_buffer(self.device(), indirect_buffer.as_ref())?;
@@ -1535,6 +2327,118 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         Ok(self)
     }
 
+    /// Perform multiple draw operations using a graphics pipeline, reading the number of draws
+    /// from a buffer.
+    ///
+    /// Same as [`draw_indirect`](Self::draw_indirect), except that the number of draws is read
+    /// from `count_buffer` when the command is executed, and is clamped to `max_draw_count`. This
+    /// allows the number of draws to be decided on the GPU, for example by a compute shader that
+    /// culls objects.
+    ///
+    /// The [`draw_indirect_count`](crate::device::Features::draw_indirect_count) feature or the
+    /// [`khr_draw_indirect_count`](crate::device::DeviceExtensions::khr_draw_indirect_count)
+    /// extension must be enabled on the device. `max_draw_count` must not be larger than the
+    /// number of [`DrawIndirectCommand`] structs in `indirect_buffer`, nor than the
+    /// [`max_draw_indirect_count`](crate::device::Properties::max_draw_indirect_count) limit.
+    #[inline]
+    pub fn draw_indirect_count<Inb, Cb>(
+        &mut self,
+        indirect_buffer: Arc<Inb>,
+        count_buffer: Arc<Cb>,
+        max_draw_count: u32,
+    ) -> Result<&mut Self, DrawIndirectError>
+    where
+        Inb: TypedBufferAccess<Content = [DrawIndirectCommand]> + Send + Sync + 'static,
+        Cb: TypedBufferAccess<Content = u32> + Send + Sync + 'static,
+    {
+        let pipeline = check_pipeline_graphics(self.state())?;
+        self.ensure_inside_render_pass_inline(pipeline)?;
+        check_dynamic_state_validity(self.state(), pipeline, self.inherited_viewport_count())?;
+        check_descriptor_sets_validity(
+            self.state(),
+            pipeline,
+            pipeline.descriptor_requirements(),
+            &self.accessed_descriptors,
+        )?;
+        check_push_constants_validity(self.state(), pipeline.layout())?;
+        check_vertex_buffers(self.state(), pipeline, None, None)?;
+        check_indirect_buffer(self.device(), indirect_buffer.as_ref())?;
+        check_indirect_count_buffer(
+            self.device(),
+            count_buffer.as_ref(),
+            max_draw_count,
+            indirect_buffer.len() as u32,
+        )?;
+
+        unsafe {
+            self.inner.draw_indirect_count(
+                indirect_buffer,
+                count_buffer,
+                max_draw_count,
+                mem::size_of::<DrawIndirectCommand>() as u32,
+            )?;
+        }
+
+        Ok(self)
+    }
+
+    /// Perform a single draw operation using a graphics pipeline, taking the number of vertices
+    /// from the number of bytes that were captured by transform feedback.
+    ///
+    /// `counter_buffer` must be a counter buffer that was written by
+    /// [`end_transform_feedback`](Self::end_transform_feedback). The number of vertices is the
+    /// byte count in the counter buffer minus `counter_offset`, divided by `vertex_stride`. This
+    /// allows drawing the vertices that were captured by transform feedback without reading back
+    /// how many there are.
+    ///
+    /// The [`ext_transform_feedback`](crate::device::DeviceExtensions::ext_transform_feedback)
+    /// extension and the [`transform_feedback`](crate::device::Features::transform_feedback)
+    /// feature must be enabled on the device, and the
+    /// [`transform_feedback_draw`](crate::device::Properties::transform_feedback_draw) property
+    /// must be `true`. `counter_buffer` must have the `indirect_buffer` usage.
+    #[inline]
+    pub fn draw_indirect_byte_count<Cb>(
+        &mut self,
+        instance_count: u32,
+        first_instance: u32,
+        counter_buffer: Arc<Cb>,
+        counter_offset: u32,
+        vertex_stride: u32,
+    ) -> Result<&mut Self, DrawIndirectByteCountError>
+    where
+        Cb: TypedBufferAccess<Content = u32> + Send + Sync + 'static,
+    {
+        let pipeline = check_pipeline_graphics(self.state())?;
+        self.ensure_inside_render_pass_inline(pipeline)?;
+        check_dynamic_state_validity(self.state(), pipeline, self.inherited_viewport_count())?;
+        check_descriptor_sets_validity(
+            self.state(),
+            pipeline,
+            pipeline.descriptor_requirements(),
+            &self.accessed_descriptors,
+        )?;
+        check_push_constants_validity(self.state(), pipeline.layout())?;
+        check_vertex_buffers(
+            self.state(),
+            pipeline,
+            None,
+            Some((first_instance, instance_count)),
+        )?;
+        check_draw_indirect_byte_count(self.device(), counter_buffer.as_ref(), vertex_stride)?;
+
+        unsafe {
+            self.inner.draw_indirect_byte_count(
+                instance_count,
+                first_instance,
+                counter_buffer,
+                counter_offset,
+                vertex_stride,
+            )?;
+        }
+
+        Ok(self)
+    }
+
     /// Perform a single draw operation using a graphics pipeline, using an index buffer.
     ///
     /// The parameters specify the first index and the number of indices in the index buffer that
@@ -1565,8 +2469,13 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         // TODO: how to handle an index out of range of the vertex buffers?
         let pipeline = check_pipeline_graphics(self.state())?;
         self.ensure_inside_render_pass_inline(pipeline)?;
-        check_dynamic_state_validity(self.state(), pipeline)?;
-        check_descriptor_sets_validity(self.state(), pipeline, pipeline.descriptor_requirements())?;
+        check_dynamic_state_validity(self.state(), pipeline, self.inherited_viewport_count())?;
+        check_descriptor_sets_validity(
+            self.state(),
+            pipeline,
+            pipeline.descriptor_requirements(),
+            &self.accessed_descriptors,
+        )?;
         check_push_constants_validity(self.state(), pipeline.layout())?;
         check_vertex_buffers(
             self.state(),
@@ -1589,6 +2498,59 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         Ok(self)
     }
 
+    /// Perform multiple draw operations using a graphics pipeline, using an index buffer, with a
+    /// single command.
+    ///
+    /// This is equivalent to calling [`draw_indexed`](Self::draw_indexed) once for each element
+    /// of `index_info`, with the same `instance_count` and `first_instance`, but is cheaper when
+    /// there are many small draws.
+    ///
+    /// The [`ext_multi_draw`](crate::device::DeviceExtensions::ext_multi_draw) extension and the
+    /// [`multi_draw`](crate::device::Features::multi_draw) feature must be enabled on the device.
+    /// The number of draws is limited by the
+    /// [`max_multi_draw_count`](crate::device::Properties::max_multi_draw_count) limit.
+    #[inline]
+    pub fn draw_multi_indexed<I>(
+        &mut self,
+        index_info: I,
+        instance_count: u32,
+        first_instance: u32,
+    ) -> Result<&mut Self, DrawMultiIndexedError>
+    where
+        I: IntoIterator<Item = MultiDrawIndexedCommand>,
+    {
+        let index_info: SmallVec<[_; 8]> = index_info.into_iter().collect();
+
+        let pipeline = check_pipeline_graphics(self.state())?;
+        self.ensure_inside_render_pass_inline(pipeline)?;
+        check_dynamic_state_validity(self.state(), pipeline, self.inherited_viewport_count())?;
+        check_descriptor_sets_validity(
+            self.state(),
+            pipeline,
+            pipeline.descriptor_requirements(),
+            &self.accessed_descriptors,
+        )?;
+        check_push_constants_validity(self.state(), pipeline.layout())?;
+        check_multi_draw(self.device(), index_info.len() as u32)?;
+        check_vertex_buffers(
+            self.state(),
+            pipeline,
+            None,
+            Some((first_instance, instance_count)),
+        )?;
+
+        for info in &index_info {
+            check_index_buffer(self.state(), Some((info.first_index, info.index_count)))?;
+        }
+
+        unsafe {
+            self.inner
+                .draw_multi_indexed(index_info, instance_count, first_instance);
+        }
+
+        Ok(self)
+    }
+
     /// Perform multiple draw operations using a graphics pipeline, using an index buffer.
     ///
     /// One draw is performed for each [`DrawIndexedIndirectCommand`] struct in `indirect_buffer`.
@@ -1619,8 +2581,13 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
     {
         let pipeline = check_pipeline_graphics(self.state())?;
         self.ensure_inside_render_pass_inline(pipeline)?;
-        check_dynamic_state_validity(self.state(), pipeline)?;
-        check_descriptor_sets_validity(self.state(), pipeline, pipeline.descriptor_requirements())?;
+        check_dynamic_state_validity(self.state(), pipeline, self.inherited_viewport_count())?;
+        check_descriptor_sets_validity(
+            self.state(),
+            pipeline,
+            pipeline.descriptor_requirements(),
+            &self.accessed_descriptors,
+        )?;
         check_push_constants_validity(self.state(), pipeline.layout())?;
         check_vertex_buffers(self.state(), pipeline, None, None)?;
         check_index_buffer(self.state(), None)?;
@@ -1641,9 +2608,64 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         }
 
         unsafe {
-            self.inner.draw_indexed_indirect(
+            self.inner.draw_indexed_indirect(
+                indirect_buffer,
+                requested,
+                mem::size_of::<DrawIndexedIndirectCommand>() as u32,
+            )?;
+        }
+
+        Ok(self)
+    }
+
+    /// Perform multiple draw operations using a graphics pipeline, using an index buffer, reading
+    /// the number of draws from a buffer.
+    ///
+    /// Same as [`draw_indexed_indirect`](Self::draw_indexed_indirect), except that the number of
+    /// draws is read from `count_buffer` when the command is executed, and is clamped to
+    /// `max_draw_count`.
+    ///
+    /// The [`draw_indirect_count`](crate::device::Features::draw_indirect_count) feature or the
+    /// [`khr_draw_indirect_count`](crate::device::DeviceExtensions::khr_draw_indirect_count)
+    /// extension must be enabled on the device. `max_draw_count` must not be larger than the
+    /// number of [`DrawIndexedIndirectCommand`] structs in `indirect_buffer`, nor than the
+    /// [`max_draw_indirect_count`](crate::device::Properties::max_draw_indirect_count) limit.
+    #[inline]
+    pub fn draw_indexed_indirect_count<Inb, Cb>(
+        &mut self,
+        indirect_buffer: Arc<Inb>,
+        count_buffer: Arc<Cb>,
+        max_draw_count: u32,
+    ) -> Result<&mut Self, DrawIndexedIndirectError>
+    where
+        Inb: TypedBufferAccess<Content = [DrawIndexedIndirectCommand]> + 'static,
+        Cb: TypedBufferAccess<Content = u32> + 'static,
+    {
+        let pipeline = check_pipeline_graphics(self.state())?;
+        self.ensure_inside_render_pass_inline(pipeline)?;
+        check_dynamic_state_validity(self.state(), pipeline, self.inherited_viewport_count())?;
+        check_descriptor_sets_validity(
+            self.state(),
+            pipeline,
+            pipeline.descriptor_requirements(),
+            &self.accessed_descriptors,
+        )?;
+        check_push_constants_validity(self.state(), pipeline.layout())?;
+        check_vertex_buffers(self.state(), pipeline, None, None)?;
+        check_index_buffer(self.state(), None)?;
+        check_indirect_buffer(self.device(), indirect_buffer.as_ref())?;
+        check_indirect_count_buffer(
+            self.device(),
+            count_buffer.as_ref(),
+            max_draw_count,
+            indirect_buffer.len() as u32,
+        )?;
+
+        unsafe {
+            self.inner.draw_indexed_indirect_count(
                 indirect_buffer,
-                requested,
+                count_buffer,
+                max_draw_count,
                 mem::size_of::<DrawIndexedIndirectCommand>() as u32,
             )?;
         }
@@ -1654,7 +2676,8 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
     /// Adds a command that writes the content of a buffer.
     ///
     /// This function is similar to the `memset` function in C. The `data` parameter is a number
-    /// that will be repeatedly written through the entire buffer.
+    /// that will be repeatedly written through the entire buffer. To only fill a range of a
+    /// buffer, pass a slice of it. The offset and size of the buffer must be multiples of 4.
     ///
     /// > **Note**: This function is technically safe because buffers can only contain integers or
     /// > floating point numbers, which are always valid whatever their memory representation is.
@@ -1675,6 +2698,87 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         }
     }
 
+    /// Adds a pipeline barrier.
+    ///
+    /// The builder already inserts the barriers that are needed between the commands that it
+    /// records, so this is only needed for dependencies that it doesn't know about, such as
+    /// accesses from the host, transitioning an image to a layout ahead of its next use, or
+    /// transferring the ownership of a resource between queue families.
+    ///
+    /// The buffer and image barriers go through the synchronization of the builder: the source
+    /// of their dependency is the previous use of the resource in the command buffer, the commands
+    /// that are recorded afterwards synchronize with the destination stages and accesses of the
+    /// barrier, and the new layout of an image is the layout that the following commands
+    /// transition it from.
+    ///
+    /// After the ownership of a resource has been released to another queue family, it must not
+    /// be used again in the command buffer. Acquiring the ownership of an image must be done with
+    /// the layout that it was released with.
+    ///
+    /// Inside a render pass, only memory barriers can be added, and the subpass must have a
+    /// dependency on itself that includes their stages and accesses. This makes it possible to
+    /// read an attachment as an input attachment after a previous draw of the same subpass wrote
+    /// to it, without ending the render pass. See
+    /// [`Subpass::self_dependency`](crate::render_pass::Subpass::self_dependency).
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the buffers or images was not created with the same device as this
+    ///   command buffer.
+    ///
+    pub fn pipeline_barrier<M, B, I>(
+        &mut self,
+        memory_barriers: M,
+        buffer_barriers: B,
+        image_barriers: I,
+    ) -> Result<&mut Self, PipelineBarrierError>
+    where
+        M: IntoIterator<Item = MemoryBarrier>,
+        B: IntoIterator<Item = BufferMemoryBarrier>,
+        I: IntoIterator<Item = ImageMemoryBarrier>,
+    {
+        let memory_barriers: SmallVec<[_; 2]> = memory_barriers.into_iter().collect();
+        let buffer_barriers: SmallVec<[_; 4]> = buffer_barriers.into_iter().collect();
+        let image_barriers: SmallVec<[_; 4]> = image_barriers.into_iter().collect();
+
+        check_pipeline_barrier(
+            self.device(),
+            self.queue_family(),
+            &memory_barriers,
+            &buffer_barriers,
+            &image_barriers,
+        )?;
+
+        let by_region = if let Some(render_pass_state) = self.render_pass_state.as_ref() {
+            check_pipeline_barrier_in_subpass(
+                &render_pass_state.subpass,
+                &memory_barriers,
+                &buffer_barriers,
+                &image_barriers,
+            )?;
+
+            render_pass_state
+                .subpass
+                .self_dependency()
+                .unwrap()
+                .by_region
+        } else {
+            false
+        };
+
+        unsafe {
+            self.inner.pipeline_barrier(
+                self.queue_family_id,
+                by_region,
+                memory_barriers,
+                buffer_barriers,
+                image_barriers,
+            )?;
+        }
+
+        Ok(self)
+    }
+
     /// Sets push constants for future dispatch or draw calls.
     ///
     /// # Panics
@@ -1751,6 +2855,11 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
 
     /// Pushes descriptor data directly into the command buffer for future dispatch or draw calls.
     ///
+    /// Descriptors that are not written by `descriptor_writes` keep the value they had from a
+    /// previous push to the same set, as long as the pipeline layout remains compatible. Use
+    /// [`DescriptorSetBuilder::build_partial`](crate::descriptor_set::DescriptorSetBuilder::build_partial)
+    /// to only write some of the descriptors of the set.
+    ///
     /// # Panics
     ///
     /// - Panics if the queue family of the command buffer does not support `pipeline_bind_point`.
@@ -1758,12 +2867,16 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
     ///   [`khr_push_descriptor`](crate::device::DeviceExtensions::khr_push_descriptor)
     ///   extension is not enabled on the device.
     /// - Panics if `set_num` is not less than the number of sets in `pipeline_layout`.
+    /// - Panics if the descriptor set layout at `set_num` in `pipeline_layout` was not created
+    ///   for push descriptors.
+    /// - Panics if `descriptor_writes` was built for a layout that is not compatible with slot
+    ///   `set_num` in `pipeline_layout`.
     pub fn push_descriptor_set(
         &mut self,
         pipeline_bind_point: PipelineBindPoint,
         pipeline_layout: Arc<PipelineLayout>,
         set_num: u32,
-        descriptor_writes: DescriptorSetBuilderOutput, // TODO: make partial writes possible
+        descriptor_writes: DescriptorSetBuilderOutput,
     ) -> &mut Self {
         match pipeline_bind_point {
             PipelineBindPoint::Compute => assert!(
@@ -1781,11 +2894,16 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
             "the khr_push_descriptor extension must be enabled on the device"
         );
         assert!(
-            set_num as usize <= pipeline_layout.descriptor_set_layouts().len(),
+            (set_num as usize) < pipeline_layout.descriptor_set_layouts().len(),
             "the descriptor set slot being bound must be less than the number of sets in pipeline_layout"
         );
 
         let pipeline_set = &pipeline_layout.descriptor_set_layouts()[set_num as usize];
+        assert!(
+            pipeline_set.desc().is_push_descriptor(),
+            "slot {} in pipeline_layout is not a push descriptor set layout",
+            set_num as usize,
+        );
         assert!(
             pipeline_set.is_compatible_with(descriptor_writes.layout()),
             "descriptor_writes is not compatible with slot {} in pipeline_layout",
@@ -1812,6 +2930,97 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
             .unwrap_or(false)
     }
 
+    /// Adds a command that resolves a multisampled image into a non-multisampled image.
+    ///
+    /// The first mip level and array layer of `source` are resolved into the first mip level and
+    /// array layer of `destination`, over the whole extent of `source`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the source or the destination was not created with `device`.
+    ///
+    pub fn resolve_image(
+        &mut self,
+        source: Arc<dyn ImageAccess>,
+        destination: Arc<dyn ImageAccess>,
+    ) -> Result<&mut Self, ResolveImageError> {
+        let region = ImageResolve {
+            source_mip_level: 0,
+            source_base_array_layer: 0,
+            source_offset: [0, 0, 0],
+            destination_mip_level: 0,
+            destination_base_array_layer: 0,
+            destination_offset: [0, 0, 0],
+            layer_count: 1,
+            extent: source.dimensions().width_height_depth(),
+        };
+
+        self.resolve_image_regions(source, destination, iter::once(region))
+    }
+
+    /// Adds a command that resolves the given regions of a multisampled image into a
+    /// non-multisampled image.
+    ///
+    /// This can be used instead of the resolve attachments of a subpass, for example to resolve
+    /// only part of an image, or an image that is not an attachment.
+    ///
+    /// - The source image must be multisampled, and the destination image must not be.
+    /// - Both images must have the same color format, and the format must support being used as a
+    ///   color attachment.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the source or the destination was not created with `device`.
+    ///
+    pub fn resolve_image_regions<R>(
+        &mut self,
+        source: Arc<dyn ImageAccess>,
+        destination: Arc<dyn ImageAccess>,
+        regions: R,
+    ) -> Result<&mut Self, ResolveImageError>
+    where
+        R: IntoIterator<Item = ImageResolve>,
+    {
+        unsafe {
+            if !self.queue_family().supports_graphics() {
+                return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+            }
+
+            self.ensure_outside_render_pass()?;
+
+            let regions: SmallVec<[_; 4]> = regions.into_iter().collect();
+            check_resolve_image_regions(
+                self.device(),
+                source.as_ref(),
+                destination.as_ref(),
+                &regions,
+            )?;
+
+            let resolves: SmallVec<[_; 4]> = regions
+                .iter()
+                .map(|region| UnsafeCommandBufferBuilderImageResolve {
+                    source_mip_level: region.source_mip_level,
+                    destination_mip_level: region.destination_mip_level,
+                    source_base_array_layer: region.source_base_array_layer,
+                    destination_base_array_layer: region.destination_base_array_layer,
+                    layer_count: region.layer_count,
+                    source_offset: to_i32(region.source_offset),
+                    destination_offset: to_i32(region.destination_offset),
+                    extent: region.extent,
+                })
+                .collect();
+
+            self.inner.resolve_image(
+                source,
+                ImageLayout::TransferSrcOptimal,
+                destination, // TODO: let choose layout
+                ImageLayout::TransferDstOptimal,
+                resolves,
+            )?;
+            Ok(self)
+        }
+    }
+
     /// Sets the dynamic blend constants for future draw calls.
     ///
     /// # Panics
@@ -2187,6 +3396,84 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         self
     }
 
+    /// Sets the dynamic fragment shading rate for future draw calls.
+    ///
+    /// `combiner_ops` specifies how the rate is combined with the rate of the primitive and of
+    /// the fragment shading rate attachment. See
+    /// [`FragmentShadingRate`](crate::pipeline::fragment_shading_rate::FragmentShadingRate) for
+    /// details.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the queue family of the command buffer does not support graphics operations.
+    /// - Panics if the
+    ///   [`pipeline_fragment_shading_rate`](crate::device::Features::pipeline_fragment_shading_rate)
+    ///   feature is not enabled on the device.
+    /// - Panics if the currently bound graphics pipeline already contains this state internally.
+    /// - Panics if the elements of `fragment_size` are not 1, 2 or 4.
+    /// - Panics if the first element of `combiner_ops` is not `Keep` and the
+    ///   [`primitive_fragment_shading_rate`](crate::device::Features::primitive_fragment_shading_rate)
+    ///   feature is not enabled on the device.
+    /// - Panics if the second element of `combiner_ops` is not `Keep` and the
+    ///   [`attachment_fragment_shading_rate`](crate::device::Features::attachment_fragment_shading_rate)
+    ///   feature is not enabled on the device.
+    /// - Panics if an element of `combiner_ops` is not `Keep` or `Replace` and the
+    ///   [`fragment_shading_rate_non_trivial_combiner_ops`](crate::device::Properties::fragment_shading_rate_non_trivial_combiner_ops)
+    ///   device property is `false`.
+    pub fn set_fragment_shading_rate(
+        &mut self,
+        fragment_size: [u32; 2],
+        combiner_ops: [FragmentShadingRateCombinerOp; 2],
+    ) -> &mut Self {
+        assert!(
+            self.queue_family().supports_graphics(),
+            "the queue family of the command buffer must support graphics operations"
+        );
+        assert!(
+            self.device()
+                .enabled_features()
+                .pipeline_fragment_shading_rate,
+            "the pipeline_fragment_shading_rate feature must be enabled on the device"
+        );
+        assert!(
+            !self.has_fixed_state(DynamicState::FragmentShadingRate),
+            "the currently bound graphics pipeline must not contain this state internally"
+        );
+        assert!(
+            fragment_size
+                .iter()
+                .all(|&size| size == 1 || size == 2 || size == 4),
+            "the elements of fragment_size must be 1, 2 or 4"
+        );
+        assert!(
+            combiner_ops[0] == FragmentShadingRateCombinerOp::Keep
+                || self.device().enabled_features().primitive_fragment_shading_rate,
+            "the primitive_fragment_shading_rate feature must be enabled on the device if the first combiner operation is not Keep"
+        );
+        assert!(
+            combiner_ops[1] == FragmentShadingRateCombinerOp::Keep
+                || self.device().enabled_features().attachment_fragment_shading_rate,
+            "the attachment_fragment_shading_rate feature must be enabled on the device if the second combiner operation is not Keep"
+        );
+        assert!(
+            !combiner_ops.iter().any(|op| op.is_non_trivial())
+                || self
+                    .device()
+                    .physical_device()
+                    .properties()
+                    .fragment_shading_rate_non_trivial_combiner_ops
+                    .unwrap_or(false),
+            "the combiner operations must be Keep or Replace if the fragment_shading_rate_non_trivial_combiner_ops device property is false"
+        );
+
+        unsafe {
+            self.inner
+                .set_fragment_shading_rate(fragment_size, combiner_ops);
+        }
+
+        self
+    }
+
     /// Sets the dynamic front face for future draw calls.
     ///
     /// # Panics
@@ -2722,6 +4009,94 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
         self
     }
 
+    /// Sets the dynamic vertex input for future draw calls.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the queue family of the command buffer does not support graphics operations.
+    /// - Panics if the
+    ///   [`vertex_input_dynamic_state`](crate::device::Features::vertex_input_dynamic_state)
+    ///   feature is not enabled on the device.
+    /// - Panics if the currently bound graphics pipeline already contains this state internally.
+    /// - Panics if a binding number is not less than, or if the number of bindings or attributes
+    ///   is greater than, the corresponding `max_vertex_input_*` device property.
+    /// - Panics if the stride of a binding or the offset of an attribute is greater than the
+    ///   corresponding `max_vertex_input_*` device property.
+    /// - If the
+    ///   [`vertex_attribute_instance_rate_divisor`](crate::device::Features::vertex_attribute_instance_rate_divisor)
+    ///   feature is not enabled, panics if an instance divisor is not 1.
+    /// - If the
+    ///   [`vertex_attribute_instance_rate_zero_divisor`](crate::device::Features::vertex_attribute_instance_rate_zero_divisor)
+    ///   feature is not enabled, panics if an instance divisor is 0.
+    pub fn set_vertex_input(&mut self, vertex_input: VertexInput) -> &mut Self {
+        assert!(
+            self.queue_family().supports_graphics(),
+            "the queue family of the command buffer must support graphics operations"
+        );
+        assert!(
+            self.device().enabled_features().vertex_input_dynamic_state,
+            "the vertex_input_dynamic_state feature must be enabled on the device"
+        );
+        assert!(
+            !self.has_fixed_state(DynamicState::VertexInput),
+            "the currently bound graphics pipeline must not contain this state internally"
+        );
+
+        let properties = self.device().physical_device().properties();
+        assert!(
+            vertex_input.bindings().len() as u32 <= properties.max_vertex_input_bindings,
+            "the number of bindings must not be greater than the max_vertex_input_bindings device property"
+        );
+        assert!(
+            vertex_input.attributes().len() as u32 <= properties.max_vertex_input_attributes,
+            "the number of attributes must not be greater than the max_vertex_input_attributes device property"
+        );
+
+        for (binding, binding_desc) in vertex_input.bindings() {
+            assert!(
+                binding < properties.max_vertex_input_bindings,
+                "binding numbers must be less than the max_vertex_input_bindings device property"
+            );
+            assert!(
+                binding_desc.stride <= properties.max_vertex_input_binding_stride,
+                "strides must not be greater than the max_vertex_input_binding_stride device property"
+            );
+
+            if let VertexInputRate::Instance { divisor } = binding_desc.input_rate {
+                if divisor != 1 {
+                    assert!(
+                        self.device()
+                            .enabled_features()
+                            .vertex_attribute_instance_rate_divisor,
+                        "if the vertex_attribute_instance_rate_divisor feature is not enabled, divisors must be 1"
+                    );
+                }
+
+                if divisor == 0 {
+                    assert!(
+                        self.device()
+                            .enabled_features()
+                            .vertex_attribute_instance_rate_zero_divisor,
+                        "if the vertex_attribute_instance_rate_zero_divisor feature is not enabled, divisors must not be 0"
+                    );
+                }
+            }
+        }
+
+        for (_, attribute_desc) in vertex_input.attributes() {
+            assert!(
+                attribute_desc.offset <= properties.max_vertex_input_attribute_offset,
+                "offsets must not be greater than the max_vertex_input_attribute_offset device property"
+            );
+        }
+
+        unsafe {
+            self.inner.set_vertex_input(vertex_input);
+        }
+
+        self
+    }
+
     /// Sets the dynamic viewports for future draw calls.
     ///
     /// # Panics
@@ -2828,38 +4203,184 @@ impl<L, P> AutoCommandBufferBuilder<L, P> {
             self.inner.set_viewport_with_count(viewports);
         }
 
-        self
+        self
+    }
+
+    /// Adds a command that writes data to a buffer.
+    ///
+    /// If `data` is larger than the buffer, only the part of `data` that fits is written. If the
+    /// buffer is larger than `data`, only the start of the buffer is written. To write at an
+    /// offset, pass a slice of the buffer.
+    ///
+    /// The data is stored inline in the command buffer, which avoids going through a staging
+    /// buffer for small updates. The written size must be a multiple of 4 and at most 65536
+    /// bytes, and the offset of the buffer must be a multiple of 4.
+    #[inline]
+    pub fn update_buffer<B, D, Dd>(
+        &mut self,
+        buffer: Arc<B>,
+        data: Dd,
+    ) -> Result<&mut Self, UpdateBufferError>
+    where
+        B: TypedBufferAccess<Content = D> + 'static,
+        D: ?Sized,
+        Dd: SafeDeref<Target = D> + Send + Sync + 'static,
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            check_update_buffer(self.device(), buffer.as_ref(), data.deref())?;
+            self.inner.update_buffer(buffer, data);
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that begins conditional rendering.
+    ///
+    /// Until [`end_conditional_rendering`](Self::end_conditional_rendering) is called, draw,
+    /// dispatch and clear attachment commands are discarded if the 32-bit value in `buffer` is
+    /// zero, or if it is not zero when `flags.inverted` is `true`. The value is read when the
+    /// commands are executed, so it can be written by the GPU, for example from the result of an
+    /// occlusion query.
+    ///
+    /// The [`ext_conditional_rendering`](crate::device::DeviceExtensions::ext_conditional_rendering)
+    /// extension and the
+    /// [`conditional_rendering`](crate::device::Features::conditional_rendering) feature must be
+    /// enabled on the device, and `buffer` must have the `conditional_rendering` usage.
+    ///
+    /// If conditional rendering is begun inside a render pass, it must be ended in the same
+    /// subpass. If it is begun outside a render pass, it must be ended outside a render pass.
+    pub fn begin_conditional_rendering<B>(
+        &mut self,
+        buffer: Arc<B>,
+        flags: ConditionalRenderingFlags,
+    ) -> Result<&mut Self, BeginConditionalRenderingError>
+    where
+        B: TypedBufferAccess<Content = u32> + 'static,
+    {
+        check_begin_conditional_rendering(self.device(), buffer.as_ref())?;
+
+        if !(self.queue_family().supports_graphics() || self.queue_family().supports_compute()) {
+            return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+        }
+
+        if self.conditional_rendering_state.is_some() {
+            return Err(AutoCommandBufferBuilderContextError::ConditionalRenderingIsActive.into());
+        }
+
+        unsafe {
+            self.inner.begin_conditional_rendering(buffer, flags)?;
+        }
+
+        self.conditional_rendering_state = Some(ConditionalRenderingState {
+            in_subpass: self.render_pass_state.is_some(),
+        });
+
+        Ok(self)
+    }
+
+    /// Adds a command that ends conditional rendering.
+    pub fn end_conditional_rendering(
+        &mut self,
+    ) -> Result<&mut Self, AutoCommandBufferBuilderContextError> {
+        let state = self
+            .conditional_rendering_state
+            .as_ref()
+            .ok_or(AutoCommandBufferBuilderContextError::ConditionalRenderingNotActive)?;
+
+        // Ending a render pass or going to the next subpass is forbidden while conditional
+        // rendering that was begun in a subpass is active, so it can only be in the same subpass.
+        if !state.in_subpass && self.render_pass_state.is_some() {
+            return Err(AutoCommandBufferBuilderContextError::ForbiddenInsideRenderPass);
+        }
+
+        unsafe {
+            self.inner.end_conditional_rendering();
+        }
+
+        self.conditional_rendering_state = None;
+        Ok(self)
+    }
+
+    /// Adds a command that begins transform feedback.
+    ///
+    /// Until [`end_transform_feedback`](Self::end_transform_feedback) is called, the outputs of
+    /// the vertex processing stages of the draw commands are written to the buffers that were
+    /// bound with [`bind_transform_feedback_buffers`](Self::bind_transform_feedback_buffers).
+    ///
+    /// `counter_buffers` contains one optional counter buffer for each transform feedback buffer
+    /// binding, starting at binding 0. If a counter buffer is given, capturing resumes at the
+    /// byte count that was written to it by a previous `end_transform_feedback`. Otherwise it
+    /// starts at the beginning of the buffer.
+    ///
+    /// The [`ext_transform_feedback`](crate::device::DeviceExtensions::ext_transform_feedback)
+    /// extension and the [`transform_feedback`](crate::device::Features::transform_feedback)
+    /// feature must be enabled on the device, and the counter buffers must have the
+    /// `transform_feedback_counter_buffer` usage. Transform feedback can only be begun inside a
+    /// render pass, and must be ended in the same subpass.
+    pub fn begin_transform_feedback<I>(
+        &mut self,
+        counter_buffers: I,
+    ) -> Result<&mut Self, BeginTransformFeedbackError>
+    where
+        I: IntoIterator<Item = Option<Arc<dyn BufferAccess>>>,
+    {
+        let counter_buffers: SmallVec<[_; 4]> = counter_buffers.into_iter().collect();
+        check_transform_feedback_counter_buffers(self.device(), &counter_buffers)?;
+
+        let render_pass_state = self
+            .render_pass_state
+            .as_ref()
+            .ok_or(AutoCommandBufferBuilderContextError::ForbiddenOutsideRenderPass)?;
+
+        if render_pass_state
+            .subpass
+            .render_pass()
+            .desc()
+            .multiview()
+            .is_some()
+        {
+            return Err(CheckTransformFeedbackError::MultiviewEnabled.into());
+        }
+
+        if self.transform_feedback_active {
+            return Err(AutoCommandBufferBuilderContextError::TransformFeedbackIsActive.into());
+        }
+
+        unsafe {
+            self.inner.begin_transform_feedback(counter_buffers)?;
+        }
+
+        self.transform_feedback_active = true;
+        Ok(self)
     }
 
-    /// Adds a command that writes data to a buffer.
+    /// Adds a command that ends transform feedback.
     ///
-    /// If `data` is larger than the buffer, only the part of `data` that fits is written. If the
-    /// buffer is larger than `data`, only the start of the buffer is written.
-    #[inline]
-    pub fn update_buffer<B, D, Dd>(
+    /// `counter_buffers` contains one optional counter buffer for each transform feedback buffer
+    /// binding, starting at binding 0. If a counter buffer is given, the number of bytes that
+    /// were captured in the binding is written to it, so that it can be used to resume capturing
+    /// with [`begin_transform_feedback`](Self::begin_transform_feedback), or to draw the
+    /// captured vertices with [`draw_indirect_byte_count`](Self::draw_indirect_byte_count).
+    pub fn end_transform_feedback<I>(
         &mut self,
-        buffer: Arc<B>,
-        data: Dd,
-    ) -> Result<&mut Self, UpdateBufferError>
+        counter_buffers: I,
+    ) -> Result<&mut Self, EndTransformFeedbackError>
     where
-        B: TypedBufferAccess<Content = D> + 'static,
-        D: ?Sized,
-        Dd: SafeDeref<Target = D> + Send + Sync + 'static,
+        I: IntoIterator<Item = Option<Arc<dyn BufferAccess>>>,
     {
-        unsafe {
-            self.ensure_outside_render_pass()?;
-            check_update_buffer(self.device(), buffer.as_ref(), data.deref())?;
+        let counter_buffers: SmallVec<[_; 4]> = counter_buffers.into_iter().collect();
+        check_transform_feedback_counter_buffers(self.device(), &counter_buffers)?;
 
-            let size_of_data = mem::size_of_val(data.deref()) as DeviceSize;
-            if buffer.size() >= size_of_data {
-                self.inner.update_buffer(buffer, data);
-            } else {
-                unimplemented!() // TODO:
-                                 //self.inner.update_buffer(buffer.slice(0 .. size_of_data), data);
-            }
+        if !self.transform_feedback_active {
+            return Err(AutoCommandBufferBuilderContextError::TransformFeedbackNotActive.into());
+        }
 
-            Ok(self)
+        unsafe {
+            self.inner.end_transform_feedback(counter_buffers);
         }
+
+        self.transform_feedback_active = false;
+        Ok(self)
     }
 
     /// Adds a command that begins a query.
@@ -3196,6 +4717,7 @@ where
                 subpass: framebuffer.render_pass().clone().first_subpass(),
                 contents,
                 framebuffer: framebuffer_object,
+                framebuffer_dimensions: Some(framebuffer.dimensions()),
             });
             Ok(self)
         }
@@ -3228,6 +4750,18 @@ where
                 return Err(AutoCommandBufferBuilderContextError::QueryIsActive);
             }
 
+            if self
+                .conditional_rendering_state
+                .as_ref()
+                .map_or(false, |state| state.in_subpass)
+            {
+                return Err(AutoCommandBufferBuilderContextError::ConditionalRenderingIsActive);
+            }
+
+            if self.transform_feedback_active {
+                return Err(AutoCommandBufferBuilderContextError::TransformFeedbackIsActive);
+            }
+
             debug_assert!(self.queue_family().supports_graphics());
 
             self.inner.end_render_pass();
@@ -3316,6 +4850,15 @@ where
             self.ensure_outside_render_pass()?;
         }
 
+        // TODO: allow this with the `inherited_conditional_rendering` feature.
+        if self.conditional_rendering_state.is_some() {
+            return Err(AutoCommandBufferBuilderContextError::ConditionalRenderingIsActive);
+        }
+
+        if self.transform_feedback_active {
+            return Err(AutoCommandBufferBuilderContextError::TransformFeedbackIsActive);
+        }
+
         for state in self.query_state.values() {
             match state.ty {
                 QueryType::Occlusion => match command_buffer.inheritance().occlusion_query {
@@ -3343,9 +4886,29 @@ where
             }
         }
 
+        if let Some(depths) = &command_buffer.inheritance().viewport_scissor_depths {
+            let state = self.state();
+
+            for num in 0..depths.len() as u32 {
+                if state.viewport(num).is_none() || state.scissor(num).is_none() {
+                    return Err(AutoCommandBufferBuilderContextError::ViewportScissorNotSet);
+                }
+            }
+        }
+
         Ok(())
     }
 
+    // Returns the number of viewports and scissors that this command buffer inherits from the
+    // primary command buffer it is executed in.
+    #[inline]
+    fn inherited_viewport_count(&self) -> u32 {
+        self.inheritance
+            .as_ref()
+            .and_then(|inheritance| inheritance.viewport_scissor_depths.as_ref())
+            .map_or(0, |depths| depths.len() as u32)
+    }
+
     #[inline]
     fn ensure_inside_render_pass_secondary(
         &self,
@@ -3421,6 +4984,18 @@ where
                 return Err(AutoCommandBufferBuilderContextError::QueryIsActive);
             }
 
+            if self
+                .conditional_rendering_state
+                .as_ref()
+                .map_or(false, |state| state.in_subpass)
+            {
+                return Err(AutoCommandBufferBuilderContextError::ConditionalRenderingIsActive);
+            }
+
+            if self.transform_feedback_active {
+                return Err(AutoCommandBufferBuilderContextError::TransformFeedbackIsActive);
+            }
+
             debug_assert!(self.queue_family().supports_graphics());
 
             self.inner.next_subpass(contents);
@@ -3441,6 +5016,39 @@ unsafe impl<L, P> DeviceOwned for AutoCommandBufferBuilder<L, P> {
     }
 }
 
+/// A debug label region of a command buffer builder, which is closed when the scope is dropped.
+///
+/// Returned by
+/// [`debug_utils_label_scope`](AutoCommandBufferBuilder::debug_utils_label_scope). It
+/// dereferences to the builder, so that the commands of the region can be recorded through it.
+pub struct DebugUtilsLabelScope<'a, L, P = StandardCommandPoolBuilder> {
+    builder: &'a mut AutoCommandBufferBuilder<L, P>,
+}
+
+impl<'a, L, P> Deref for DebugUtilsLabelScope<'a, L, P> {
+    type Target = AutoCommandBufferBuilder<L, P>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.builder
+    }
+}
+
+impl<'a, L, P> DerefMut for DebugUtilsLabelScope<'a, L, P> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.builder
+    }
+}
+
+impl<'a, L, P> Drop for DebugUtilsLabelScope<'a, L, P> {
+    #[inline]
+    fn drop(&mut self) {
+        // Only fails if the region was already closed manually.
+        let _ = self.builder.end_debug_utils_label();
+    }
+}
+
 pub struct PrimaryAutoCommandBuffer<P = StandardCommandPoolAlloc> {
     inner: SyncCommandBuffer,
     pool_alloc: P, // Safety: must be dropped after `inner`
@@ -3449,6 +5057,53 @@ pub struct PrimaryAutoCommandBuffer<P = StandardCommandPoolAlloc> {
     submit_state: SubmitState,
 }
 
+impl<P> PrimaryAutoCommandBuffer<P>
+where
+    P: CommandPoolAllocReset,
+{
+    /// Resets the command buffer and starts recording it again.
+    ///
+    /// Contrary to building a new command buffer, this reuses the Vulkan command buffer and the
+    /// memory that was allocated to track its commands and resources. The resources that were
+    /// used by the previous recording are released.
+    ///
+    /// The command buffer can only be recorded again on the thread that allocated it. Otherwise
+    /// `ResetError::WrongThread` is returned, and the command buffer is dropped.
+    pub fn reset(
+        self,
+        usage: CommandBufferUsage,
+    ) -> Result<AutoCommandBufferBuilder<PrimaryAutoCommandBuffer<P>, P::Builder>, ResetError> {
+        let PrimaryAutoCommandBuffer {
+            inner, pool_alloc, ..
+        } = self;
+        reset_command_buffer(inner, pool_alloc, CommandBufferLevel::primary(), usage)
+    }
+}
+
+impl<P> PrimaryAutoCommandBuffer<P> {
+    /// Returns the dependencies that the automatic synchronization computed for this command
+    /// buffer, and the pipeline barriers that it inserted for them.
+    ///
+    /// See [`AutoCommandBufferBuilder::sync_graph`].
+    #[inline]
+    pub fn sync_graph(&self) -> SyncGraph {
+        self.inner.sync_graph()
+    }
+
+    /// Returns a description of every command recorded in the command buffer, in order, with
+    /// the buffers and images that it accesses and the pipelines and descriptor sets that were
+    /// bound when it was recorded.
+    ///
+    /// The commands are the ones that were given to Vulkan, so a single call on the builder can
+    /// result in several commands, and the barriers that the automatic synchronization inserted
+    /// are not included.
+    #[cfg(feature = "command_introspection")]
+    #[inline]
+    pub fn recorded_commands(&self) -> &[RecordedCommand] {
+        self.inner.recorded_commands()
+    }
+}
+
 unsafe impl<P> DeviceOwned for PrimaryAutoCommandBuffer<P> {
     #[inline]
     fn device(&self) -> &Arc<Device> {
@@ -3561,6 +5216,53 @@ pub struct SecondaryAutoCommandBuffer<P = StandardCommandPoolAlloc> {
     submit_state: SubmitState,
 }
 
+impl<P> SecondaryAutoCommandBuffer<P>
+where
+    P: CommandPoolAllocReset,
+{
+    /// Resets the command buffer and starts recording it again, with the same inheritance.
+    ///
+    /// See [`PrimaryAutoCommandBuffer::reset`].
+    pub fn reset(
+        self,
+        usage: CommandBufferUsage,
+    ) -> Result<AutoCommandBufferBuilder<SecondaryAutoCommandBuffer<P>, P::Builder>, ResetError>
+    {
+        let SecondaryAutoCommandBuffer {
+            inner,
+            pool_alloc,
+            inheritance,
+            ..
+        } = self;
+        reset_command_buffer(
+            inner,
+            pool_alloc,
+            CommandBufferLevel::Secondary(inheritance),
+            usage,
+        )
+    }
+}
+
+impl<P> SecondaryAutoCommandBuffer<P> {
+    /// Returns the dependencies that the automatic synchronization computed for this command
+    /// buffer, and the pipeline barriers that it inserted for them.
+    ///
+    /// See [`AutoCommandBufferBuilder::sync_graph`].
+    #[inline]
+    pub fn sync_graph(&self) -> SyncGraph {
+        self.inner.sync_graph()
+    }
+
+    /// Returns a description of every command recorded in the command buffer, in order.
+    ///
+    /// See [`PrimaryAutoCommandBuffer::recorded_commands`].
+    #[cfg(feature = "command_introspection")]
+    #[inline]
+    pub fn recorded_commands(&self) -> &[RecordedCommand] {
+        self.inner.recorded_commands()
+    }
+}
+
 unsafe impl<P> DeviceOwned for SecondaryAutoCommandBuffer<P> {
     #[inline]
     fn device(&self) -> &Arc<Device> {
@@ -3651,6 +5353,63 @@ where
     }
 }
 
+// Starts recording a command buffer that was built by an `AutoCommandBufferBuilder` again.
+//
+// Owning the command buffer guarantees that it isn't pending execution, since the futures and the
+// primary command buffers that execute it keep it alive until the execution is finished.
+fn reset_command_buffer<L, P>(
+    inner: SyncCommandBuffer,
+    pool_alloc: P,
+    level: CommandBufferLevel,
+    usage: CommandBufferUsage,
+) -> Result<AutoCommandBufferBuilder<L, P::Builder>, ResetError>
+where
+    P: CommandPoolAllocReset,
+{
+    let pool_builder_alloc = match pool_alloc.into_builder() {
+        Ok(pool_builder_alloc) => pool_builder_alloc,
+        Err(pool_alloc) => {
+            // `inner` must be dropped before the command buffer it was recorded into.
+            drop(inner);
+            drop(pool_alloc);
+            return Err(ResetError::WrongThread);
+        }
+    };
+
+    unsafe {
+        let builder_state = BuilderState::new(&level);
+        let inner =
+            SyncCommandBufferBuilder::reset(inner, pool_builder_alloc.inner(), level, usage)?;
+
+        Ok(AutoCommandBufferBuilder::from_parts(
+            inner,
+            pool_builder_alloc,
+            usage,
+            builder_state,
+        ))
+    }
+}
+
+// Converts unsigned texel coordinates to the signed offsets of the unsafe commands.
+fn to_i32(offset: [u32; 3]) -> [i32; 3] {
+    [offset[0] as i32, offset[1] as i32, offset[2] as i32]
+}
+
+// Converts a region of a copy between a buffer and an image to the region of the unsafe command.
+fn buffer_image_copy(region: &BufferImageCopy) -> UnsafeCommandBufferBuilderBufferImageCopy {
+    UnsafeCommandBufferBuilderBufferImageCopy {
+        buffer_offset: region.buffer_offset,
+        buffer_row_length: region.buffer_row_length,
+        buffer_image_height: region.buffer_image_height,
+        image_aspect: region.image_aspect,
+        image_mip_level: region.image_mip_level,
+        image_base_array_layer: region.image_base_array_layer,
+        image_layer_count: region.image_layer_count,
+        image_offset: to_i32(region.image_offset),
+        image_extent: region.image_extent,
+    }
+}
+
 // Whether the command buffer can be submitted.
 #[derive(Debug)]
 enum SubmitState {
@@ -3721,11 +5480,29 @@ err_gen!(BuildError {
     OomError,
 });
 
+err_gen!(BeginConditionalRenderingError {
+    AutoCommandBufferBuilderContextError,
+    CheckBeginConditionalRenderingError,
+    SyncCommandBufferBuilderError,
+});
+
 err_gen!(BeginRenderPassError {
     AutoCommandBufferBuilderContextError,
     SyncCommandBufferBuilderError,
 });
 
+err_gen!(BeginTransformFeedbackError {
+    AutoCommandBufferBuilderContextError,
+    CheckTransformFeedbackError,
+    SyncCommandBufferBuilderError,
+});
+
+err_gen!(BindTransformFeedbackBuffersError {
+    AutoCommandBufferBuilderContextError,
+    CheckTransformFeedbackError,
+    SyncCommandBufferBuilderError,
+});
+
 err_gen!(CopyImageError {
     AutoCommandBufferBuilderContextError,
     CheckCopyImageError,
@@ -3738,6 +5515,17 @@ err_gen!(BlitImageError {
     SyncCommandBufferBuilderError,
 });
 
+err_gen!(ResolveImageError {
+    AutoCommandBufferBuilderContextError,
+    CheckResolveImageError,
+    SyncCommandBufferBuilderError,
+});
+
+err_gen!(ClearAttachmentsError {
+    AutoCommandBufferBuilderContextError,
+    CheckClearAttachmentsError,
+});
+
 err_gen!(ClearColorImageError {
     AutoCommandBufferBuilderContextError,
     CheckClearColorImageError,
@@ -3773,11 +5561,33 @@ err_gen!(FillBufferError {
     CheckFillBufferError,
 });
 
+err_gen!(PipelineBarrierError {
+    AutoCommandBufferBuilderContextError,
+    CheckPipelineBarrierError,
+    SyncCommandBufferBuilderError,
+});
+
+err_gen!(DebugUtilsLabelError {
+    AutoCommandBufferBuilderContextError,
+    CheckDebugUtilsLabelError,
+});
+
 err_gen!(DebugMarkerError {
     AutoCommandBufferBuilderContextError,
     CheckColorError,
+    CheckDebugUtilsLabelError,
 });
 
+impl From<DebugUtilsLabelError> for DebugMarkerError {
+    #[inline]
+    fn from(err: DebugUtilsLabelError) -> DebugMarkerError {
+        match err {
+            DebugUtilsLabelError::AutoCommandBufferBuilderContextError(err) => err.into(),
+            DebugUtilsLabelError::CheckDebugUtilsLabelError(err) => err.into(),
+        }
+    }
+}
+
 err_gen!(DispatchError {
     AutoCommandBufferBuilderContextError,
     CheckPipelineError,
@@ -3818,6 +5628,27 @@ err_gen!(DrawIndexedError {
     SyncCommandBufferBuilderError,
 });
 
+err_gen!(DrawMultiError {
+    AutoCommandBufferBuilderContextError,
+    CheckPipelineError,
+    CheckDynamicStateValidityError,
+    CheckPushConstantsValidityError,
+    CheckDescriptorSetsValidityError,
+    CheckMultiDrawError,
+    CheckVertexBufferError,
+});
+
+err_gen!(DrawMultiIndexedError {
+    AutoCommandBufferBuilderContextError,
+    CheckPipelineError,
+    CheckDynamicStateValidityError,
+    CheckPushConstantsValidityError,
+    CheckDescriptorSetsValidityError,
+    CheckMultiDrawError,
+    CheckVertexBufferError,
+    CheckIndexBufferError,
+});
+
 err_gen!(DrawIndirectError {
     AutoCommandBufferBuilderContextError,
     CheckPipelineError,
@@ -3829,6 +5660,17 @@ err_gen!(DrawIndirectError {
     SyncCommandBufferBuilderError,
 });
 
+err_gen!(DrawIndirectByteCountError {
+    AutoCommandBufferBuilderContextError,
+    CheckPipelineError,
+    CheckDynamicStateValidityError,
+    CheckPushConstantsValidityError,
+    CheckDescriptorSetsValidityError,
+    CheckVertexBufferError,
+    CheckTransformFeedbackError,
+    SyncCommandBufferBuilderError,
+});
+
 err_gen!(DrawIndexedIndirectError {
     AutoCommandBufferBuilderContextError,
     CheckPipelineError,
@@ -3846,6 +5688,11 @@ err_gen!(ExecuteCommandsError {
     SyncCommandBufferBuilderError,
 });
 
+err_gen!(EndTransformFeedbackError {
+    AutoCommandBufferBuilderContextError,
+    CheckTransformFeedbackError,
+});
+
 err_gen!(BeginQueryError {
     AutoCommandBufferBuilderContextError,
     CheckBeginQueryError,
@@ -3873,6 +5720,14 @@ err_gen!(UpdateBufferError {
 
 #[derive(Debug, Copy, Clone)]
 pub enum AutoCommandBufferBuilderContextError {
+    /// Conditional rendering is active, which conflicts with the current operation.
+    ConditionalRenderingIsActive,
+    /// Conditional rendering was not active.
+    ConditionalRenderingNotActive,
+    /// A debug label region is open, which conflicts with the current operation.
+    DebugUtilsLabelIsActive,
+    /// No debug label region was open in the command buffer.
+    DebugUtilsLabelNotActive,
     /// Operation forbidden inside of a render pass.
     ForbiddenInsideRenderPass,
     /// Operation forbidden outside of a render pass.
@@ -3899,6 +5754,13 @@ pub enum AutoCommandBufferBuilderContextError {
     QueryNotActive,
     /// A query is active that is not included in the `inheritance` of the secondary command buffer.
     QueryNotInherited,
+    /// Transform feedback is active, which conflicts with the current operation.
+    TransformFeedbackIsActive,
+    /// Transform feedback was not active.
+    TransformFeedbackNotActive,
+    /// Tried to execute a secondary command buffer that inherits viewports and scissors, but they
+    /// were not all set on the primary command buffer.
+    ViewportScissorNotSet,
     /// Tried to use a graphics pipeline or secondary command buffer whose subpass index
     /// didn't match the current subpass index.
     WrongSubpassIndex,
@@ -3916,6 +5778,18 @@ impl fmt::Display for AutoCommandBufferBuilderContextError {
             fmt,
             "{}",
             match *self {
+                AutoCommandBufferBuilderContextError::ConditionalRenderingIsActive => {
+                    "conditional rendering is active, which conflicts with the current operation"
+                }
+                AutoCommandBufferBuilderContextError::ConditionalRenderingNotActive => {
+                    "conditional rendering was not active"
+                }
+                AutoCommandBufferBuilderContextError::DebugUtilsLabelIsActive => {
+                    "a debug label region is open, which conflicts with the current operation"
+                }
+                AutoCommandBufferBuilderContextError::DebugUtilsLabelNotActive => {
+                    "no debug label region was open in the command buffer"
+                }
                 AutoCommandBufferBuilderContextError::ForbiddenInsideRenderPass => {
                     "operation forbidden inside of a render pass"
                 }
@@ -3946,6 +5820,16 @@ impl fmt::Display for AutoCommandBufferBuilderContextError {
                 AutoCommandBufferBuilderContextError::QueryNotInherited => {
                     "a query is active that is not included in the inheritance of the secondary command buffer"
                 }
+                AutoCommandBufferBuilderContextError::TransformFeedbackIsActive => {
+                    "transform feedback is active, which conflicts with the current operation"
+                }
+                AutoCommandBufferBuilderContextError::TransformFeedbackNotActive => {
+                    "transform feedback was not active"
+                }
+                AutoCommandBufferBuilderContextError::ViewportScissorNotSet => {
+                    "tried to execute a secondary command buffer that inherits viewports and \
+                 scissors, but they were not all set on the primary command buffer"
+                }
                 AutoCommandBufferBuilderContextError::WrongSubpassIndex => {
                     "tried to use a graphics pipeline whose subpass index didn't match the current \
                  subpass index"
@@ -3965,7 +5849,10 @@ mod tests {
     use crate::buffer::BufferUsage;
     use crate::buffer::CpuAccessibleBuffer;
     use crate::command_buffer::synced::SyncCommandBufferBuilderError;
+    use crate::command_buffer::validity::CheckBlitImageError;
     use crate::command_buffer::AutoCommandBufferBuilder;
+    use crate::command_buffer::BeginError;
+    use crate::command_buffer::BlitImageError;
     use crate::command_buffer::CommandBufferExecError;
     use crate::command_buffer::CommandBufferUsage;
     use crate::command_buffer::ExecuteCommandsError;
@@ -3974,7 +5861,16 @@ mod tests {
     use crate::device::Device;
     use crate::device::DeviceExtensions;
     use crate::device::Features;
+    use crate::format::Format;
+    use crate::image::ImageCreateFlags;
+    use crate::image::ImageDimensions;
+    use crate::image::ImageLayout;
+    use crate::image::ImageUsage;
+    use crate::image::ImmutableImage;
+    use crate::image::MipmapsCount;
+    use crate::render_pass::Subpass;
     use crate::sync::GpuFuture;
+    use crate::VulkanObject;
     use std::sync::Arc;
 
     #[test]
@@ -4111,4 +6007,147 @@ mod tests {
             builder.execute_commands(secondary.clone()).unwrap();
         }
     }
+
+    #[test]
+    fn reset_reuses_command_buffer() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let source = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::all(),
+            true,
+            [1_u32, 2].iter().copied(),
+        )
+        .unwrap();
+        let destination = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::all(),
+            true,
+            [0_u32; 2].iter().copied(),
+        )
+        .unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            queue.family(),
+            CommandBufferUsage::MultipleSubmit,
+        )
+        .unwrap();
+        builder.fill_buffer(destination.clone(), 0).unwrap();
+        let cb = builder.build().unwrap();
+        let raw = cb.inner().internal_object();
+
+        let mut builder = cb.reset(CommandBufferUsage::OneTimeSubmit).unwrap();
+        builder
+            .copy_buffer(source.clone(), destination.clone())
+            .unwrap();
+        let cb = builder.build().unwrap();
+        assert_eq!(cb.inner().internal_object(), raw);
+
+        cb.execute(queue)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        let result = destination.read().unwrap();
+        assert_eq!(*result, [1_u32, 2]);
+    }
+
+    #[test]
+    fn inherit_viewport_scissor_feature_not_enabled() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let render_pass = crate::single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: DontCare,
+                    format: Format::R8G8B8A8_UNORM,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )
+        .unwrap();
+
+        assert!(matches!(
+            AutoCommandBufferBuilder::secondary_graphics_inherit_viewport_scissor(
+                device.clone(),
+                queue.family(),
+                CommandBufferUsage::OneTimeSubmit,
+                Subpass::from(render_pass, 0).unwrap(),
+                [0.0..1.0],
+            ),
+            Err(BeginError::InheritedViewportScissorFeatureNotEnabled)
+        ));
+    }
+
+    #[test]
+    fn generate_mipmaps_missing_usage() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let (image, _) = ImmutableImage::uninitialized(
+            device.clone(),
+            ImageDimensions::Dim2d {
+                width: 4,
+                height: 4,
+                array_layers: 1,
+            },
+            Format::R8G8B8A8_UNORM,
+            MipmapsCount::Log2,
+            ImageUsage {
+                transfer_destination: true,
+                sampled: true,
+                ..ImageUsage::none()
+            },
+            ImageCreateFlags::none(),
+            ImageLayout::ShaderReadOnlyOptimal,
+            Some(queue.family()),
+        )
+        .unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device,
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            builder.generate_mipmaps(image),
+            Err(BlitImageError::CheckBlitImageError(
+                CheckBlitImageError::MissingTransferSourceUsage
+            ))
+        ));
+    }
+
+    #[test]
+    fn set_accessed_descriptors() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device,
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        builder
+            .set_accessed_descriptors(0, 1, [5, 2, 5, 0])
+            .set_accessed_descriptors(1, 0, [3]);
+        assert_eq!(builder.accessed_descriptors[&(0, 1)], [0, 2, 5]);
+        assert_eq!(builder.accessed_descriptors[&(1, 0)], [3]);
+
+        // Declaring the elements again replaces the previous ones.
+        builder.set_accessed_descriptors(0, 1, [1]);
+        assert_eq!(builder.accessed_descriptors[&(0, 1)], [1]);
+
+        builder.clear_accessed_descriptors();
+        assert!(builder.accessed_descriptors.is_empty());
+    }
 }
@@ -0,0 +1,539 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Commands that are generated by the device.
+//!
+//! With the [`nv_device_generated_commands`](crate::device::DeviceExtensions::nv_device_generated_commands)
+//! extension, the commands of a sequence of draws can be read from buffers that were filled on
+//! the device, for example by a compute shader that culls the objects of a scene. An
+//! [`IndirectCommandsLayout`] describes where each sequence finds its commands in these buffers,
+//! which are called *streams*.
+//!
+//! The generated commands are executed with
+//! [`UnsafeCommandBufferBuilder::execute_generated_commands`](crate::command_buffer::sys::UnsafeCommandBufferBuilder::execute_generated_commands).
+//! The graphics pipeline that they are executed with must have been built with
+//! [`indirect_bindable`](crate::pipeline::GraphicsPipelineBuilder::indirect_bindable) enabled.
+
+use crate::check_errors;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::memory::MemoryRequirements;
+use crate::pipeline::layout::PipelineLayout;
+use crate::pipeline::GraphicsPipeline;
+use crate::shader::ShaderStages;
+use crate::Error;
+use crate::OomError;
+use crate::VulkanObject;
+use smallvec::SmallVec;
+use std::error;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::Arc;
+
+/// Describes the layout of the commands of each sequence in the streams of generated commands.
+#[derive(Debug)]
+pub struct IndirectCommandsLayout {
+    handle: ash::vk::IndirectCommandsLayoutNV,
+    device: Arc<Device>,
+    usage: IndirectCommandsLayoutUsage,
+    stream_strides: SmallVec<[u32; 4]>,
+}
+
+impl IndirectCommandsLayout {
+    /// Creates a new `IndirectCommandsLayout` for graphics commands.
+    ///
+    /// Each sequence reads `tokens` from the streams, with `stream_strides` containing the
+    /// distance in bytes between two sequences in each stream. The last token must be the only
+    /// one that draws, and a `ShaderGroup` token must be the first one.
+    pub fn new(
+        device: Arc<Device>,
+        usage: IndirectCommandsLayoutUsage,
+        tokens: &[IndirectCommandsLayoutToken],
+        stream_strides: &[u32],
+    ) -> Result<Arc<IndirectCommandsLayout>, IndirectCommandsLayoutCreationError> {
+        if !device.enabled_extensions().nv_device_generated_commands
+            || !device.enabled_features().device_generated_commands
+        {
+            return Err(IndirectCommandsLayoutCreationError::FeatureNotEnabled);
+        }
+
+        let properties = device.physical_device().properties();
+
+        let limit = properties.max_indirect_commands_stream_count.unwrap_or(0);
+        if stream_strides.is_empty() || stream_strides.len() as u32 > limit {
+            return Err(
+                IndirectCommandsLayoutCreationError::MaxIndirectCommandsStreamCountExceeded {
+                    limit,
+                    requested: stream_strides.len() as u32,
+                },
+            );
+        }
+
+        let limit = properties.max_indirect_commands_stream_stride.unwrap_or(0);
+        for &stride in stream_strides {
+            if stride > limit {
+                return Err(
+                    IndirectCommandsLayoutCreationError::MaxIndirectCommandsStreamStrideExceeded {
+                        limit,
+                        requested: stride,
+                    },
+                );
+            }
+        }
+
+        let limit = properties.max_indirect_commands_token_count.unwrap_or(0);
+        if tokens.len() as u32 > limit {
+            return Err(
+                IndirectCommandsLayoutCreationError::MaxIndirectCommandsTokenCountExceeded {
+                    limit,
+                    requested: tokens.len() as u32,
+                },
+            );
+        }
+
+        match tokens.split_last() {
+            Some((last, others))
+                if last.ty.is_action() && !others.iter().any(|token| token.ty.is_action()) => {}
+            _ => return Err(IndirectCommandsLayoutCreationError::ActionTokenNotLast),
+        }
+
+        if tokens
+            .iter()
+            .skip(1)
+            .any(|token| matches!(token.ty, IndirectCommandsTokenType::ShaderGroup))
+        {
+            return Err(IndirectCommandsLayoutCreationError::ShaderGroupTokenNotFirst);
+        }
+
+        let limit = properties.max_indirect_commands_token_offset.unwrap_or(0);
+        for token in tokens {
+            if token.stream as usize >= stream_strides.len() {
+                return Err(IndirectCommandsLayoutCreationError::StreamOutOfRange {
+                    stream: token.stream,
+                });
+            }
+
+            if token.offset > limit {
+                return Err(
+                    IndirectCommandsLayoutCreationError::MaxIndirectCommandsTokenOffsetExceeded {
+                        limit,
+                        requested: token.offset,
+                    },
+                );
+            }
+
+            if let IndirectCommandsTokenType::PushConstant {
+                ref pipeline_layout,
+                ..
+            } = token.ty
+            {
+                assert_eq!(
+                    pipeline_layout.device().internal_object(),
+                    device.internal_object()
+                );
+            }
+        }
+
+        let handle = unsafe {
+            let tokens: SmallVec<[_; 8]> = tokens.iter().map(|token| token.to_vulkan()).collect();
+
+            let infos = ash::vk::IndirectCommandsLayoutCreateInfoNV {
+                flags: usage.into(),
+                pipeline_bind_point: ash::vk::PipelineBindPoint::GRAPHICS,
+                token_count: tokens.len() as u32,
+                p_tokens: tokens.as_ptr(),
+                stream_count: stream_strides.len() as u32,
+                p_stream_strides: stream_strides.as_ptr(),
+                ..Default::default()
+            };
+
+            let fns = device.fns();
+            let mut output = MaybeUninit::uninit();
+            check_errors(
+                fns.nv_device_generated_commands
+                    .create_indirect_commands_layout_nv(
+                        device.internal_object(),
+                        &infos,
+                        ptr::null(),
+                        output.as_mut_ptr(),
+                    ),
+            )?;
+            output.assume_init()
+        };
+
+        Ok(Arc::new(IndirectCommandsLayout {
+            handle,
+            device,
+            usage,
+            stream_strides: stream_strides.iter().copied().collect(),
+        }))
+    }
+
+    /// Returns the usage that the layout was created with.
+    #[inline]
+    pub fn usage(&self) -> IndirectCommandsLayoutUsage {
+        self.usage
+    }
+
+    /// Returns the stride of each stream of the layout.
+    #[inline]
+    pub fn stream_strides(&self) -> &[u32] {
+        &self.stream_strides
+    }
+
+    /// Returns the memory requirements of the preprocess buffer that is needed to execute up to
+    /// `max_sequences_count` sequences with `pipeline`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `pipeline` doesn't belong to the same device as the layout.
+    pub fn memory_requirements(
+        &self,
+        pipeline: &GraphicsPipeline,
+        max_sequences_count: u32,
+    ) -> MemoryRequirements {
+        assert_eq!(
+            pipeline.device().internal_object(),
+            self.device.internal_object()
+        );
+
+        let infos = ash::vk::GeneratedCommandsMemoryRequirementsInfoNV {
+            pipeline_bind_point: ash::vk::PipelineBindPoint::GRAPHICS,
+            pipeline: pipeline.internal_object(),
+            indirect_commands_layout: self.handle,
+            max_sequences_count,
+            ..Default::default()
+        };
+        let mut output = ash::vk::MemoryRequirements2::default();
+
+        unsafe {
+            let fns = self.device.fns();
+            fns.nv_device_generated_commands
+                .get_generated_commands_memory_requirements_nv(
+                    self.device.internal_object(),
+                    &infos,
+                    &mut output,
+                );
+        }
+
+        output.memory_requirements.into()
+    }
+}
+
+unsafe impl VulkanObject for IndirectCommandsLayout {
+    type Object = ash::vk::IndirectCommandsLayoutNV;
+
+    #[inline]
+    fn internal_object(&self) -> ash::vk::IndirectCommandsLayoutNV {
+        self.handle
+    }
+}
+
+unsafe impl DeviceOwned for IndirectCommandsLayout {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+impl Drop for IndirectCommandsLayout {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            let fns = self.device.fns();
+            fns.nv_device_generated_commands
+                .destroy_indirect_commands_layout_nv(
+                    self.device.internal_object(),
+                    self.handle,
+                    ptr::null(),
+                );
+        }
+    }
+}
+
+/// How an `IndirectCommandsLayout` is going to be used.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct IndirectCommandsLayoutUsage {
+    /// The generated commands are preprocessed with `preprocess_generated_commands` before they
+    /// are executed.
+    pub explicit_preprocess: bool,
+    /// The sequences are taken in the order of a buffer of indices instead of in their order in
+    /// the streams.
+    pub indexed_sequences: bool,
+    /// The sequences can be executed in any order.
+    pub unordered_sequences: bool,
+}
+
+impl From<IndirectCommandsLayoutUsage> for ash::vk::IndirectCommandsLayoutUsageFlagsNV {
+    #[inline]
+    fn from(val: IndirectCommandsLayoutUsage) -> Self {
+        let mut result = ash::vk::IndirectCommandsLayoutUsageFlagsNV::empty();
+        if val.explicit_preprocess {
+            result |= ash::vk::IndirectCommandsLayoutUsageFlagsNV::EXPLICIT_PREPROCESS;
+        }
+        if val.indexed_sequences {
+            result |= ash::vk::IndirectCommandsLayoutUsageFlagsNV::INDEXED_SEQUENCES;
+        }
+        if val.unordered_sequences {
+            result |= ash::vk::IndirectCommandsLayoutUsageFlagsNV::UNORDERED_SEQUENCES;
+        }
+        result
+    }
+}
+
+/// A command that each sequence reads from one of the streams.
+#[derive(Debug, Clone)]
+pub struct IndirectCommandsLayoutToken {
+    /// The index of the stream that the command is read from.
+    pub stream: u32,
+    /// The offset in bytes of the command from the start of the sequence in the stream.
+    pub offset: u32,
+    /// The command.
+    pub ty: IndirectCommandsTokenType,
+}
+
+impl IndirectCommandsLayoutToken {
+    fn to_vulkan(&self) -> ash::vk::IndirectCommandsLayoutTokenNV {
+        let mut token = ash::vk::IndirectCommandsLayoutTokenNV {
+            stream: self.stream,
+            offset: self.offset,
+            ..Default::default()
+        };
+
+        token.token_type = match self.ty {
+            IndirectCommandsTokenType::ShaderGroup => {
+                ash::vk::IndirectCommandsTokenTypeNV::SHADER_GROUP
+            }
+            IndirectCommandsTokenType::StateFlags { front_face } => {
+                if front_face {
+                    token.indirect_state_flags = ash::vk::IndirectStateFlagsNV::FLAG_FRONTFACE;
+                }
+                ash::vk::IndirectCommandsTokenTypeNV::STATE_FLAGS
+            }
+            IndirectCommandsTokenType::IndexBuffer => {
+                ash::vk::IndirectCommandsTokenTypeNV::INDEX_BUFFER
+            }
+            IndirectCommandsTokenType::VertexBuffer {
+                binding,
+                dynamic_stride,
+            } => {
+                token.vertex_binding_unit = binding;
+                token.vertex_dynamic_stride = dynamic_stride as ash::vk::Bool32;
+                ash::vk::IndirectCommandsTokenTypeNV::VERTEX_BUFFER
+            }
+            IndirectCommandsTokenType::PushConstant {
+                ref pipeline_layout,
+                stages,
+                offset,
+                size,
+            } => {
+                token.pushconstant_pipeline_layout = pipeline_layout.internal_object();
+                token.pushconstant_shader_stage_flags = stages.into();
+                token.pushconstant_offset = offset;
+                token.pushconstant_size = size;
+                ash::vk::IndirectCommandsTokenTypeNV::PUSH_CONSTANT
+            }
+            IndirectCommandsTokenType::DrawIndexed => {
+                ash::vk::IndirectCommandsTokenTypeNV::DRAW_INDEXED
+            }
+            IndirectCommandsTokenType::Draw => ash::vk::IndirectCommandsTokenTypeNV::DRAW,
+            IndirectCommandsTokenType::DrawTasks => {
+                ash::vk::IndirectCommandsTokenTypeNV::DRAW_TASKS
+            }
+        };
+
+        token
+    }
+}
+
+/// The type of a command that is read from a stream.
+#[derive(Debug, Clone)]
+pub enum IndirectCommandsTokenType {
+    /// Binds a shader group of the graphics pipeline.
+    ShaderGroup,
+    /// Sets the state flags of the sequence.
+    StateFlags {
+        /// Whether the front face is read from the stream.
+        front_face: bool,
+    },
+    /// Binds an index buffer.
+    IndexBuffer,
+    /// Binds a vertex buffer.
+    VertexBuffer {
+        /// The vertex buffer binding to bind to.
+        binding: u32,
+        /// Whether the stride of the vertex buffer is read from the stream.
+        dynamic_stride: bool,
+    },
+    /// Updates push constants.
+    PushConstant {
+        /// The pipeline layout that the push constants belong to.
+        pipeline_layout: Arc<PipelineLayout>,
+        /// The shader stages that the push constants are updated for.
+        stages: ShaderStages,
+        /// The offset in bytes of the push constants that are updated.
+        offset: u32,
+        /// The size in bytes of the push constants that are updated.
+        size: u32,
+    },
+    /// Draws with an index buffer.
+    DrawIndexed,
+    /// Draws.
+    Draw,
+    /// Draws mesh shader tasks.
+    DrawTasks,
+}
+
+impl IndirectCommandsTokenType {
+    #[inline]
+    fn is_action(&self) -> bool {
+        matches!(self, Self::DrawIndexed | Self::Draw | Self::DrawTasks)
+    }
+}
+
+/// Error that can happen when creating an `IndirectCommandsLayout`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IndirectCommandsLayoutCreationError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The last token must be the only one that draws.
+    ActionTokenNotLast,
+    /// The `nv_device_generated_commands` extension and the `device_generated_commands` feature
+    /// must be enabled.
+    FeatureNotEnabled,
+    /// The number of streams was 0 or exceeded the limit.
+    MaxIndirectCommandsStreamCountExceeded {
+        /// The limit that must be fulfilled.
+        limit: u32,
+        /// What was requested.
+        requested: u32,
+    },
+    /// The maximum stride of a stream has been exceeded.
+    MaxIndirectCommandsStreamStrideExceeded {
+        /// The limit that must be fulfilled.
+        limit: u32,
+        /// What was requested.
+        requested: u32,
+    },
+    /// The maximum number of tokens has been exceeded.
+    MaxIndirectCommandsTokenCountExceeded {
+        /// The limit that must be fulfilled.
+        limit: u32,
+        /// What was requested.
+        requested: u32,
+    },
+    /// The maximum offset of a token has been exceeded.
+    MaxIndirectCommandsTokenOffsetExceeded {
+        /// The limit that must be fulfilled.
+        limit: u32,
+        /// What was requested.
+        requested: u32,
+    },
+    /// A `ShaderGroup` token must be the first token.
+    ShaderGroupTokenNotFirst,
+    /// A token was read from a stream that doesn't exist.
+    StreamOutOfRange {
+        /// The stream of the token.
+        stream: u32,
+    },
+}
+
+impl error::Error for IndirectCommandsLayoutCreationError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Self::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for IndirectCommandsLayoutCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                Self::OomError(_) => "not enough memory available",
+                Self::ActionTokenNotLast => "the last token must be the only one that draws",
+                Self::FeatureNotEnabled => {
+                    "the nv_device_generated_commands extension and the device_generated_commands \
+                     feature must be enabled"
+                }
+                Self::MaxIndirectCommandsStreamCountExceeded { .. } => {
+                    "the number of streams was 0 or exceeded the limit"
+                }
+                Self::MaxIndirectCommandsStreamStrideExceeded { .. } => {
+                    "the maximum stride of a stream has been exceeded"
+                }
+                Self::MaxIndirectCommandsTokenCountExceeded { .. } => {
+                    "the maximum number of tokens has been exceeded"
+                }
+                Self::MaxIndirectCommandsTokenOffsetExceeded { .. } => {
+                    "the maximum offset of a token has been exceeded"
+                }
+                Self::ShaderGroupTokenNotFirst => "a shader group token must be the first token",
+                Self::StreamOutOfRange { .. } => {
+                    "a token was read from a stream that doesn't exist"
+                }
+            }
+        )
+    }
+}
+
+impl From<OomError> for IndirectCommandsLayoutCreationError {
+    #[inline]
+    fn from(err: OomError) -> Self {
+        Self::OomError(err)
+    }
+}
+
+impl From<Error> for IndirectCommandsLayoutCreationError {
+    #[inline]
+    fn from(err: Error) -> Self {
+        match err {
+            err @ Error::OutOfHostMemory => Self::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => Self::OomError(OomError::from(err)),
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_buffer::indirect_commands::IndirectCommandsLayout;
+    use crate::command_buffer::indirect_commands::IndirectCommandsLayoutCreationError;
+    use crate::command_buffer::indirect_commands::IndirectCommandsLayoutToken;
+    use crate::command_buffer::indirect_commands::IndirectCommandsLayoutUsage;
+    use crate::command_buffer::indirect_commands::IndirectCommandsTokenType;
+
+    #[test]
+    fn feature_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let tokens = [IndirectCommandsLayoutToken {
+            stream: 0,
+            offset: 0,
+            ty: IndirectCommandsTokenType::Draw,
+        }];
+
+        assert_eq!(
+            IndirectCommandsLayout::new(
+                device,
+                IndirectCommandsLayoutUsage::default(),
+                &tokens,
+                &[16],
+            )
+            .unwrap_err(),
+            IndirectCommandsLayoutCreationError::FeatureNotEnabled
+        );
+    }
+}
@@ -0,0 +1,303 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::command_buffer::DispatchIndirectCommand;
+use crate::command_buffer::DrawIndexedIndirectCommand;
+use crate::command_buffer::DrawIndirectCommand;
+use crate::command_buffer::DrawMeshTasksIndirectCommand;
+use crate::DeviceSize;
+use std::error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr;
+
+/// A command that the device can read from an indirect buffer.
+///
+/// # Safety
+///
+/// The type must have the same layout as the corresponding Vulkan structure.
+pub unsafe trait IndirectCommand: Copy + Send + Sync + 'static {}
+
+unsafe impl IndirectCommand for DrawIndirectCommand {}
+unsafe impl IndirectCommand for DrawIndexedIndirectCommand {}
+unsafe impl IndirectCommand for DispatchIndirectCommand {}
+unsafe impl IndirectCommand for DrawMeshTasksIndirectCommand {}
+
+/// Describes how indirect commands of type `T` are laid out in a buffer.
+///
+/// This is useful when the commands are written by the device, for example by a compute shader,
+/// so that the buffer can be checked with
+/// [`check_indirect_buffer_layout`](crate::command_buffer::validity::check_indirect_buffer_layout)
+/// before it is used for indirect draws.
+#[derive(Debug)]
+pub struct IndirectBufferLayout<T> {
+    stride: u32,
+    count: u32,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> IndirectBufferLayout<T>
+where
+    T: IndirectCommand,
+{
+    /// Describes `count` commands with `stride` bytes between the start of two commands.
+    ///
+    /// The stride must be a multiple of 4 and at least the size of `T`.
+    pub fn new(
+        stride: u32,
+        count: u32,
+    ) -> Result<IndirectBufferLayout<T>, IndirectBufferWriterError> {
+        if stride % 4 != 0 {
+            return Err(IndirectBufferWriterError::StrideNotAligned { stride });
+        }
+
+        let command_size = mem::size_of::<T>() as u32;
+        if stride < command_size {
+            return Err(IndirectBufferWriterError::StrideTooSmall {
+                stride,
+                command_size,
+            });
+        }
+
+        Ok(IndirectBufferLayout {
+            stride,
+            count,
+            marker: PhantomData,
+        })
+    }
+
+    /// Describes `count` commands that are tightly packed.
+    #[inline]
+    pub fn packed(count: u32) -> IndirectBufferLayout<T> {
+        IndirectBufferLayout {
+            stride: mem::size_of::<T>() as u32,
+            count,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of bytes between the start of two commands.
+    #[inline]
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    /// Returns the number of commands.
+    #[inline]
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns the number of bytes that the commands occupy in the buffer.
+    #[inline]
+    pub fn size(&self) -> DeviceSize {
+        if self.count == 0 {
+            0
+        } else {
+            (self.count - 1) as DeviceSize * self.stride as DeviceSize
+                + mem::size_of::<T>() as DeviceSize
+        }
+    }
+}
+
+impl<T> Clone for IndirectBufferLayout<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for IndirectBufferLayout<T> {}
+
+/// Writes indirect commands of type `T` from the CPU into the memory of an indirect buffer, for
+/// example obtained by [writing](crate::buffer::CpuAccessibleBuffer::write) to a
+/// `CpuAccessibleBuffer<[u8]>`.
+pub struct IndirectBufferWriter<'a, T> {
+    data: &'a mut [u8],
+    layout: IndirectBufferLayout<T>,
+}
+
+impl<'a, T> IndirectBufferWriter<'a, T>
+where
+    T: IndirectCommand,
+{
+    /// Builds a writer that writes commands in `data` with `stride` bytes between the start of
+    /// two commands.
+    ///
+    /// The stride must be a multiple of 4 and at least the size of `T`. The number of commands
+    /// that can be written is the number that fit in `data`.
+    pub fn new(
+        data: &'a mut [u8],
+        stride: u32,
+    ) -> Result<IndirectBufferWriter<'a, T>, IndirectBufferWriterError> {
+        let command_size = mem::size_of::<T>();
+        let count = if data.len() < command_size {
+            0
+        } else {
+            (data.len() - command_size) / stride.max(1) as usize + 1
+        };
+
+        Ok(IndirectBufferWriter {
+            layout: IndirectBufferLayout::new(stride, count as u32)?,
+            data,
+        })
+    }
+
+    /// Returns the layout of the commands that can be written.
+    #[inline]
+    pub fn layout(&self) -> IndirectBufferLayout<T> {
+        self.layout
+    }
+
+    /// Returns the number of commands that can be written.
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.layout.count
+    }
+
+    /// Returns true if no command fits in the memory of the writer.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.layout.count == 0
+    }
+
+    /// Writes `command` at position `index`.
+    pub fn write(&mut self, index: u32, command: T) -> Result<(), IndirectBufferWriterError> {
+        if index >= self.layout.count {
+            return Err(IndirectBufferWriterError::IndexOutOfRange {
+                index,
+                len: self.layout.count,
+            });
+        }
+
+        let offset = index as usize * self.layout.stride as usize;
+        let dest = &mut self.data[offset..offset + mem::size_of::<T>()];
+
+        // Safe because `dest` is large enough and `T` is a plain Vulkan structure.
+        unsafe {
+            ptr::write_unaligned(dest.as_mut_ptr() as *mut T, command);
+        }
+
+        Ok(())
+    }
+
+    /// Writes `commands` starting at position 0, and returns the number of commands that were
+    /// written.
+    pub fn write_all<I>(&mut self, commands: I) -> Result<u32, IndirectBufferWriterError>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut count = 0;
+
+        for command in commands {
+            self.write(count, command)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
+
+/// Error that can happen when writing indirect commands.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IndirectBufferWriterError {
+    /// The index of the command is out of range.
+    IndexOutOfRange {
+        /// The index that was requested.
+        index: u32,
+        /// The number of commands that can be written.
+        len: u32,
+    },
+    /// The stride must be a multiple of 4.
+    StrideNotAligned {
+        /// The stride that was requested.
+        stride: u32,
+    },
+    /// The stride must be at least the size of a command.
+    StrideTooSmall {
+        /// The stride that was requested.
+        stride: u32,
+        /// The size of a command.
+        command_size: u32,
+    },
+}
+
+impl error::Error for IndirectBufferWriterError {}
+
+impl fmt::Display for IndirectBufferWriterError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                IndirectBufferWriterError::IndexOutOfRange { .. } => {
+                    "the index of the command is out of range"
+                }
+                IndirectBufferWriterError::StrideNotAligned { .. } => {
+                    "the stride must be a multiple of 4"
+                }
+                IndirectBufferWriterError::StrideTooSmall { .. } => {
+                    "the stride must be at least the size of a command"
+                }
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IndirectBufferLayout;
+    use super::IndirectBufferWriter;
+    use super::IndirectBufferWriterError;
+    use crate::command_buffer::DrawIndirectCommand;
+    use std::mem;
+
+    #[test]
+    fn write_strided() {
+        let mut data = [0u8; 44];
+        let mut writer = IndirectBufferWriter::<DrawIndirectCommand>::new(&mut data, 24).unwrap();
+        assert_eq!(writer.len(), 2);
+        assert_eq!(writer.layout().size(), 40);
+
+        let command = DrawIndirectCommand {
+            vertex_count: 3,
+            instance_count: 1,
+            first_vertex: 0,
+            first_instance: 7,
+        };
+        assert_eq!(writer.write_all(vec![command, command]), Ok(2));
+        assert_eq!(
+            writer.write(2, command),
+            Err(IndirectBufferWriterError::IndexOutOfRange { index: 2, len: 2 })
+        );
+
+        assert_eq!(&data[24..28], &3u32.to_ne_bytes());
+        assert_eq!(&data[36..40], &7u32.to_ne_bytes());
+        assert_eq!(&data[40..44], &[0; 4]);
+    }
+
+    #[test]
+    fn invalid_stride() {
+        let command_size = mem::size_of::<DrawIndirectCommand>() as u32;
+
+        assert_eq!(
+            IndirectBufferLayout::<DrawIndirectCommand>::new(18, 1).unwrap_err(),
+            IndirectBufferWriterError::StrideNotAligned { stride: 18 }
+        );
+        assert_eq!(
+            IndirectBufferLayout::<DrawIndirectCommand>::new(12, 1).unwrap_err(),
+            IndirectBufferWriterError::StrideTooSmall {
+                stride: 12,
+                command_size,
+            }
+        );
+    }
+}
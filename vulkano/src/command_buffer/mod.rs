@@ -78,42 +78,74 @@
 
 pub use self::auto::AutoCommandBufferBuilder;
 pub use self::auto::AutoCommandBufferBuilderContextError;
+pub use self::auto::BeginConditionalRenderingError;
 pub use self::auto::BeginError;
 pub use self::auto::BeginQueryError;
 pub use self::auto::BeginRenderPassError;
+pub use self::auto::BeginTransformFeedbackError;
+pub use self::auto::BindTransformFeedbackBuffersError;
 pub use self::auto::BlitImageError;
 pub use self::auto::BuildError;
+pub use self::auto::ClearAttachmentsError;
 pub use self::auto::ClearColorImageError;
 pub use self::auto::CopyBufferError;
 pub use self::auto::CopyBufferImageError;
 pub use self::auto::CopyImageError;
 pub use self::auto::CopyQueryPoolResultsError;
 pub use self::auto::DebugMarkerError;
+pub use self::auto::DebugUtilsLabelError;
+pub use self::auto::DebugUtilsLabelScope;
 pub use self::auto::DispatchError;
 pub use self::auto::DispatchIndirectError;
 pub use self::auto::DrawError;
 pub use self::auto::DrawIndexedError;
 pub use self::auto::DrawIndexedIndirectError;
+pub use self::auto::DrawIndirectByteCountError;
 pub use self::auto::DrawIndirectError;
+pub use self::auto::DrawMultiError;
+pub use self::auto::DrawMultiIndexedError;
 pub use self::auto::EndQueryError;
+pub use self::auto::EndTransformFeedbackError;
 pub use self::auto::ExecuteCommandsError;
 pub use self::auto::FillBufferError;
+pub use self::auto::PipelineBarrierError;
 pub use self::auto::PrimaryAutoCommandBuffer;
+pub use self::auto::ResetError;
 pub use self::auto::ResetQueryPoolError;
+pub use self::auto::ResolveImageError;
 pub use self::auto::SecondaryAutoCommandBuffer;
 pub use self::auto::UpdateBufferError;
 pub use self::auto::WriteTimestampError;
+pub use self::indirect_writer::IndirectBufferLayout;
+pub use self::indirect_writer::IndirectBufferWriter;
+pub use self::indirect_writer::IndirectBufferWriterError;
+pub use self::indirect_writer::IndirectCommand;
+pub use self::parallel::ParallelRecordError;
+pub use self::parallel::ParallelRecorder;
 pub use self::traits::CommandBufferExecError;
 pub use self::traits::CommandBufferExecFuture;
 pub use self::traits::PrimaryCommandBuffer;
 pub use self::traits::SecondaryCommandBuffer;
+use crate::buffer::BufferAccess;
+use crate::format::ClearValue;
+use crate::image::ImageAccess;
+use crate::image::ImageAspect;
+use crate::image::ImageAspects;
+use crate::image::ImageLayout;
 use crate::query::QueryControlFlags;
 use crate::query::QueryPipelineStatisticFlags;
+use crate::render_pass::Framebuffer;
 use crate::render_pass::Subpass;
+use crate::sync::AccessFlags;
+use crate::sync::PipelineStages;
+use crate::DeviceSize;
+use std::ops::Range;
 use std::sync::Arc;
-use crate::render_pass::Framebuffer;
 
 mod auto;
+pub mod indirect_commands;
+mod indirect_writer;
+mod parallel;
 pub mod pool;
 pub mod submit;
 pub mod synced;
@@ -136,6 +168,7 @@ impl ImageUninitializedSafe {
     }
 }
 
+/// A draw read from an indirect buffer, matching `VkDrawIndirectCommand`.
 #[repr(C)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct DrawIndirectCommand {
@@ -145,16 +178,36 @@ pub struct DrawIndirectCommand {
     pub first_instance: u32,
 }
 
+/// An indexed draw read from an indirect buffer, matching `VkDrawIndexedIndirectCommand`.
 #[repr(C)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct DrawIndexedIndirectCommand {
     pub index_count: u32,
     pub instance_count: u32,
     pub first_index: u32,
-    pub vertex_offset: u32,
+    pub vertex_offset: i32,
     pub first_instance: u32,
 }
 
+/// One of the draws of a [`draw_multi`](AutoCommandBufferBuilder::draw_multi) command.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MultiDrawCommand {
+    pub first_vertex: u32,
+    pub vertex_count: u32,
+}
+
+/// One of the draws of a [`draw_multi_indexed`](AutoCommandBufferBuilder::draw_multi_indexed)
+/// command.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MultiDrawIndexedCommand {
+    pub first_index: u32,
+    pub index_count: u32,
+    pub vertex_offset: i32,
+}
+
+/// A dispatch read from an indirect buffer, matching `VkDispatchIndirectCommand`.
 #[repr(C)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct DispatchIndirectCommand {
@@ -163,6 +216,307 @@ pub struct DispatchIndirectCommand {
     pub z: u32,
 }
 
+/// A draw of mesh shader tasks read from an indirect buffer, matching
+/// `VkDrawMeshTasksIndirectCommandEXT`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DrawMeshTasksIndirectCommand {
+    pub group_count_x: u32,
+    pub group_count_y: u32,
+    pub group_count_z: u32,
+}
+
+/// A region of a copy between two buffers, for
+/// [`copy_buffer_regions`](AutoCommandBufferBuilder::copy_buffer_regions).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BufferCopy {
+    /// The offset in bytes from the start of the source buffer to the data of the region.
+    pub source_offset: DeviceSize,
+    /// The offset in bytes from the start of the destination buffer to the data of the region.
+    pub destination_offset: DeviceSize,
+    /// The size in bytes of the region.
+    pub size: DeviceSize,
+}
+
+/// A region of a copy between a buffer and an image, for
+/// [`copy_buffer_to_image_regions`](AutoCommandBufferBuilder::copy_buffer_to_image_regions) and
+/// [`copy_image_to_buffer_regions`](AutoCommandBufferBuilder::copy_image_to_buffer_regions).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BufferImageCopy {
+    /// The offset in bytes from the start of the buffer to the data of the region.
+    pub buffer_offset: DeviceSize,
+    /// The number of texels in each row of the data in the buffer. If 0, the rows are tightly
+    /// packed according to `image_extent`.
+    pub buffer_row_length: u32,
+    /// The number of rows in each depth slice or array layer of the data in the buffer. If 0,
+    /// the rows are tightly packed according to `image_extent`.
+    pub buffer_image_height: u32,
+    /// The aspect of the image that is copied.
+    pub image_aspect: ImageAspect,
+    /// The mip level of the image that is copied.
+    pub image_mip_level: u32,
+    /// The first array layer of the image that is copied.
+    pub image_base_array_layer: u32,
+    /// The number of array layers of the image that are copied.
+    pub image_layer_count: u32,
+    /// The offset in texels of the region in the mip level of the image.
+    pub image_offset: [u32; 3],
+    /// The size in texels of the region.
+    pub image_extent: [u32; 3],
+}
+
+/// A region of a copy between two images, for
+/// [`copy_image_regions`](AutoCommandBufferBuilder::copy_image_regions).
+///
+/// If `layer_count` is greater than 1, the copy happens between each individual layer as if they
+/// were separate images.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ImageCopy {
+    /// The aspects of the images that are copied. If `color` is included, neither `depth` nor
+    /// `stencil` may be.
+    pub aspects: ImageAspects,
+    /// The mip level of the source image that is copied.
+    pub source_mip_level: u32,
+    /// The first array layer of the source image that is copied.
+    pub source_base_array_layer: u32,
+    /// The offset in texels of the region in the mip level of the source image.
+    pub source_offset: [u32; 3],
+    /// The mip level of the destination image that is copied to.
+    pub destination_mip_level: u32,
+    /// The first array layer of the destination image that is copied to.
+    pub destination_base_array_layer: u32,
+    /// The offset in texels of the region in the mip level of the destination image.
+    pub destination_offset: [u32; 3],
+    /// The number of array layers that are copied.
+    pub layer_count: u32,
+    /// The size in texels of the region.
+    pub extent: [u32; 3],
+}
+
+/// A region of a blit between two images, for
+/// [`blit_image_regions`](AutoCommandBufferBuilder::blit_image_regions).
+///
+/// Each region is given by two opposite corners. If a corner of the destination is on the other
+/// side than the matching corner of the source, the blit is mirrored along that axis.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ImageBlit {
+    /// The aspects of the images that are blitted. If `color` is included, neither `depth` nor
+    /// `stencil` may be.
+    pub aspects: ImageAspects,
+    /// The mip level of the source image that is blitted.
+    pub source_mip_level: u32,
+    /// The first array layer of the source image that is blitted.
+    pub source_base_array_layer: u32,
+    /// The two corners in texels of the region in the mip level of the source image.
+    pub source_offsets: [[u32; 3]; 2],
+    /// The mip level of the destination image that is blitted to.
+    pub destination_mip_level: u32,
+    /// The first array layer of the destination image that is blitted to.
+    pub destination_base_array_layer: u32,
+    /// The two corners in texels of the region in the mip level of the destination image.
+    pub destination_offsets: [[u32; 3]; 2],
+    /// The number of array layers that are blitted.
+    pub layer_count: u32,
+}
+
+/// A region of a resolve of a multisampled image, for
+/// [`resolve_image_regions`](AutoCommandBufferBuilder::resolve_image_regions).
+///
+/// Only the color aspect can be resolved.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ImageResolve {
+    /// The mip level of the source image that is resolved.
+    pub source_mip_level: u32,
+    /// The first array layer of the source image that is resolved.
+    pub source_base_array_layer: u32,
+    /// The offset in texels of the region in the mip level of the source image.
+    pub source_offset: [u32; 3],
+    /// The mip level of the destination image that is resolved to.
+    pub destination_mip_level: u32,
+    /// The first array layer of the destination image that is resolved to.
+    pub destination_base_array_layer: u32,
+    /// The offset in texels of the region in the mip level of the destination image.
+    pub destination_offset: [u32; 3],
+    /// The number of array layers that are resolved.
+    pub layer_count: u32,
+    /// The size in texels of the region.
+    pub extent: [u32; 3],
+}
+
+/// An attachment of the current subpass to clear with
+/// [`clear_attachments`](AutoCommandBufferBuilder::clear_attachments).
+#[derive(Clone, Copy, Debug)]
+pub enum ClearAttachment {
+    /// Clears the color attachment of the subpass at the given index, which is an index into the
+    /// color attachments of the subpass and not into the attachments of the render pass. The
+    /// value must be a `Float`, `Int` or `Uint` value that matches the format of the attachment.
+    Color(ClearValue, u32),
+    /// Clears the depth aspect of the depth-stencil attachment of the subpass.
+    Depth(f32),
+    /// Clears the stencil aspect of the depth-stencil attachment of the subpass.
+    Stencil(u32),
+    /// Clears both aspects of the depth-stencil attachment of the subpass.
+    DepthStencil((f32, u32)),
+}
+
+impl From<ClearAttachment> for ash::vk::ClearAttachment {
+    #[inline]
+    fn from(val: ClearAttachment) -> Self {
+        match val {
+            ClearAttachment::Color(clear_value, color_attachment) => {
+                let color = match clear_value {
+                    ClearValue::Float(val) => ash::vk::ClearColorValue { float32: val },
+                    ClearValue::Int(val) => ash::vk::ClearColorValue { int32: val },
+                    ClearValue::Uint(val) => ash::vk::ClearColorValue { uint32: val },
+                    _ => ash::vk::ClearColorValue { float32: [0.0; 4] },
+                };
+
+                ash::vk::ClearAttachment {
+                    aspect_mask: ash::vk::ImageAspectFlags::COLOR,
+                    color_attachment,
+                    clear_value: ash::vk::ClearValue { color },
+                }
+            }
+            ClearAttachment::Depth(depth) => ash::vk::ClearAttachment {
+                aspect_mask: ash::vk::ImageAspectFlags::DEPTH,
+                color_attachment: 0,
+                clear_value: ash::vk::ClearValue {
+                    depth_stencil: ash::vk::ClearDepthStencilValue { depth, stencil: 0 },
+                },
+            },
+            ClearAttachment::Stencil(stencil) => ash::vk::ClearAttachment {
+                aspect_mask: ash::vk::ImageAspectFlags::STENCIL,
+                color_attachment: 0,
+                clear_value: ash::vk::ClearValue {
+                    depth_stencil: ash::vk::ClearDepthStencilValue {
+                        depth: 0.0,
+                        stencil,
+                    },
+                },
+            },
+            ClearAttachment::DepthStencil((depth, stencil)) => ash::vk::ClearAttachment {
+                aspect_mask: ash::vk::ImageAspectFlags::DEPTH | ash::vk::ImageAspectFlags::STENCIL,
+                color_attachment: 0,
+                clear_value: ash::vk::ClearValue {
+                    depth_stencil: ash::vk::ClearDepthStencilValue { depth, stencil },
+                },
+            },
+        }
+    }
+}
+
+/// A region of the framebuffer to clear with
+/// [`clear_attachments`](AutoCommandBufferBuilder::clear_attachments).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ClearRect {
+    /// The offset in pixels of the region in the framebuffer.
+    pub rect_offset: [u32; 2],
+    /// The size in pixels of the region.
+    pub rect_extent: [u32; 2],
+    /// The first layer of the framebuffer to clear.
+    pub base_array_layer: u32,
+    /// The number of layers of the framebuffer to clear.
+    pub layer_count: u32,
+}
+
+impl From<ClearRect> for ash::vk::ClearRect {
+    #[inline]
+    fn from(val: ClearRect) -> Self {
+        ash::vk::ClearRect {
+            rect: ash::vk::Rect2D {
+                offset: ash::vk::Offset2D {
+                    x: val.rect_offset[0] as i32,
+                    y: val.rect_offset[1] as i32,
+                },
+                extent: ash::vk::Extent2D {
+                    width: val.rect_extent[0],
+                    height: val.rect_extent[1],
+                },
+            },
+            base_array_layer: val.base_array_layer,
+            layer_count: val.layer_count,
+        }
+    }
+}
+
+/// A global memory barrier, for
+/// [`pipeline_barrier`](AutoCommandBufferBuilder::pipeline_barrier).
+///
+/// Unlike buffer and image barriers, a memory barrier isn't tied to a resource that the builder
+/// tracks, so both sides of the dependency must be given.
+#[derive(Debug, Copy, Clone)]
+pub struct MemoryBarrier {
+    /// The stages of the previous commands that must be finished.
+    pub source_stages: PipelineStages,
+    /// The memory accesses of the previous commands that are made available.
+    pub source_access: AccessFlags,
+    /// The stages of the following commands that wait for the source stages.
+    pub destination_stages: PipelineStages,
+    /// The memory accesses of the following commands that the memory is made visible to.
+    pub destination_access: AccessFlags,
+}
+
+/// A barrier on a buffer, for [`pipeline_barrier`](AutoCommandBufferBuilder::pipeline_barrier).
+///
+/// The source of the dependency is the previous use of the buffer in the command buffer, which is
+/// already known to the builder.
+#[derive(Clone)]
+pub struct BufferMemoryBarrier {
+    /// The buffer to put a barrier on.
+    pub buffer: Arc<dyn BufferAccess>,
+    /// The stages of the following commands that wait for the previous uses of the buffer.
+    pub destination_stages: PipelineStages,
+    /// The memory accesses of the following commands that the buffer is made visible to.
+    pub destination_access: AccessFlags,
+    /// If `Some`, transfers the ownership of the buffer from the first queue family to the
+    /// second, given by their ids. One of them must be the queue family of the command buffer.
+    ///
+    /// The buffer must have been created with exclusive sharing, and the same transfer must be
+    /// recorded in a command buffer of the other queue family.
+    pub queue_family_transfer: Option<(u32, u32)>,
+}
+
+/// A barrier on an image, for [`pipeline_barrier`](AutoCommandBufferBuilder::pipeline_barrier).
+///
+/// The source of the dependency and the layout that the image is transitioned from are those of
+/// the previous use of the image in the command buffer, which are already known to the builder.
+#[derive(Clone)]
+pub struct ImageMemoryBarrier {
+    /// The image to put a barrier on.
+    pub image: Arc<dyn ImageAccess>,
+    /// The stages of the following commands that wait for the previous uses of the image.
+    pub destination_stages: PipelineStages,
+    /// The memory accesses of the following commands that the image is made visible to.
+    pub destination_access: AccessFlags,
+    /// The layout to transition the image to. Must not be `Undefined` or `Preinitialized`.
+    pub new_layout: ImageLayout,
+    /// If `Some`, transfers the ownership of the image from the first queue family to the
+    /// second, given by their ids. One of them must be the queue family of the command buffer.
+    ///
+    /// The image must have been created with exclusive sharing, and the same transfer, with the
+    /// same `new_layout`, must be recorded in a command buffer of the other queue family.
+    pub queue_family_transfer: Option<(u32, u32)>,
+}
+
+/// Flags that modify how conditional rendering behaves.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConditionalRenderingFlags {
+    /// If `false`, the commands are executed if the value in the buffer is not zero. If `true`,
+    /// they are executed if the value is zero.
+    pub inverted: bool,
+}
+
+impl From<ConditionalRenderingFlags> for ash::vk::ConditionalRenderingFlagsEXT {
+    #[inline]
+    fn from(val: ConditionalRenderingFlags) -> Self {
+        if val.inverted {
+            ash::vk::ConditionalRenderingFlagsEXT::INVERTED
+        } else {
+            ash::vk::ConditionalRenderingFlagsEXT::empty()
+        }
+    }
+}
+
 /// Describes what a subpass in a command buffer will contain.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(i32)]
@@ -217,6 +571,17 @@ pub struct CommandBufferInheritance {
     /// The `pipeline_statistics_query` feature must be enabled if any of the flags of this value
     /// are set.
     query_statistics_flags: QueryPipelineStatisticFlags,
+
+    /// If `Some`, the secondary command buffer inherits the viewports and scissors that are set
+    /// on the primary command buffer when it is executed, instead of having to set them itself.
+    /// The inner value contains the depth range of each inherited viewport, which must match the
+    /// depth ranges that are set on the primary command buffer.
+    ///
+    /// The [`nv_inherited_viewport_scissor`](crate::device::DeviceExtensions::nv_inherited_viewport_scissor)
+    /// extension and the
+    /// [`inherited_viewport_scissor2_d`](crate::device::Features::inherited_viewport_scissor2_d)
+    /// feature must be enabled if this is `Some`.
+    viewport_scissor_depths: Option<Vec<Range<f32>>>,
 }
 
 /// The render pass context that a secondary command buffer is created for.
@@ -255,6 +620,7 @@ impl CommandBufferLevel {
             render_pass: None,
             occlusion_query,
             query_statistics_flags,
+            viewport_scissor_depths: None,
         })
     }
 }
@@ -0,0 +1,329 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::command_buffer::pool::CommandPoolBuilderAlloc;
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::BuildError;
+use crate::command_buffer::CommandBufferInheritance;
+use crate::command_buffer::CommandBufferInheritanceRenderPass;
+use crate::command_buffer::CommandBufferLevel;
+use crate::command_buffer::CommandBufferUsage;
+use crate::command_buffer::ExecuteCommandsError;
+use crate::command_buffer::PrimaryAutoCommandBuffer;
+use crate::command_buffer::SecondaryAutoCommandBuffer;
+use crate::device::physical::QueueFamily;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::query::QueryPipelineStatisticFlags;
+use crate::render_pass::Subpass;
+use crate::OomError;
+use crate::VulkanObject;
+use std::error;
+use std::fmt;
+use std::panic;
+use std::sync::Arc;
+use std::thread;
+
+/// Records secondary command buffers on multiple threads.
+///
+/// All the secondary command buffers that are created by a `ParallelRecorder` share the same
+/// inheritance, so that they can all be executed by the same primary command buffer. Each thread
+/// allocates its command buffers from its own command pool, which is handled by the
+/// [standard command pool](crate::command_buffer::pool::standard::StandardCommandPool) of the
+/// device, so recording doesn't need any locking between threads.
+///
+/// # Example
+///
+/// ```
+/// # use vulkano::command_buffer::AutoCommandBufferBuilder;
+/// # use vulkano::command_buffer::CommandBufferUsage;
+/// # use vulkano::render_pass::Subpass;
+/// use vulkano::command_buffer::ParallelRecorder;
+/// # let device: std::sync::Arc<vulkano::device::Device> = return;
+/// # let queue: std::sync::Arc<vulkano::device::Queue> = return;
+/// # let subpass: Subpass = return;
+/// # let mut primary: AutoCommandBufferBuilder<vulkano::command_buffer::PrimaryAutoCommandBuffer> = return;
+///
+/// let recorder = ParallelRecorder::graphics(
+///     device.clone(),
+///     queue.family(),
+///     CommandBufferUsage::OneTimeSubmit,
+///     subpass,
+/// );
+///
+/// // Record the draw commands of each quarter of the scene on its own thread, then execute them
+/// // in the current subpass of `primary`.
+/// recorder
+///     .record_into(&mut primary, 4, |index, builder| {
+///         // builder.draw(...).unwrap();
+///     })
+///     .unwrap();
+/// ```
+#[derive(Clone)]
+pub struct ParallelRecorder {
+    device: Arc<Device>,
+    queue_family_id: u32,
+    usage: CommandBufferUsage,
+    level: CommandBufferLevel,
+}
+
+impl ParallelRecorder {
+    /// Creates a `ParallelRecorder` for secondary compute command buffers, which are executed
+    /// outside of a render pass.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the queue family doesn't belong to the same physical device as `device`.
+    #[inline]
+    pub fn compute(
+        device: Arc<Device>,
+        queue_family: QueueFamily,
+        usage: CommandBufferUsage,
+    ) -> ParallelRecorder {
+        let level = CommandBufferLevel::secondary(None, QueryPipelineStatisticFlags::none());
+        ParallelRecorder::with_level(device, queue_family, usage, level)
+    }
+
+    /// Creates a `ParallelRecorder` for secondary graphics command buffers, which are executed
+    /// within `subpass`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the queue family doesn't belong to the same physical device as `device`.
+    #[inline]
+    pub fn graphics(
+        device: Arc<Device>,
+        queue_family: QueueFamily,
+        usage: CommandBufferUsage,
+        subpass: Subpass,
+    ) -> ParallelRecorder {
+        let level = CommandBufferLevel::Secondary(CommandBufferInheritance {
+            render_pass: Some(CommandBufferInheritanceRenderPass {
+                subpass,
+                framebuffer: None,
+            }),
+            ..Default::default()
+        });
+        ParallelRecorder::with_level(device, queue_family, usage, level)
+    }
+
+    fn with_level(
+        device: Arc<Device>,
+        queue_family: QueueFamily,
+        usage: CommandBufferUsage,
+        level: CommandBufferLevel,
+    ) -> ParallelRecorder {
+        assert_eq!(
+            device.physical_device().internal_object(),
+            queue_family.physical_device().internal_object()
+        );
+
+        ParallelRecorder {
+            device,
+            queue_family_id: queue_family.id(),
+            usage,
+            level,
+        }
+    }
+
+    /// Returns the queue family that the command buffers are created for.
+    #[inline]
+    pub fn queue_family(&self) -> QueueFamily {
+        self.device
+            .physical_device()
+            .queue_family_by_id(self.queue_family_id)
+            .unwrap()
+    }
+
+    /// Starts building a secondary command buffer with the inheritance of the recorder.
+    ///
+    /// The command buffer is allocated from the command pool of the current thread. The builder
+    /// can't be sent to another thread, so this must be called on the thread that records the
+    /// commands.
+    #[inline]
+    pub fn builder(
+        &self,
+    ) -> Result<AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>, OomError> {
+        AutoCommandBufferBuilder::with_level(
+            self.device.clone(),
+            self.queue_family(),
+            self.usage,
+            self.level.clone(),
+        )
+    }
+
+    /// Records `num_threads` secondary command buffers, each on its own thread.
+    ///
+    /// `record` is called on each thread with the index of the thread and the builder of its
+    /// command buffer. The command buffers are returned in the order of the indices.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `num_threads` is 0.
+    /// - Panics if `record` panics on any of the threads.
+    pub fn record<F>(
+        &self,
+        num_threads: usize,
+        record: F,
+    ) -> Result<Vec<SecondaryAutoCommandBuffer>, BuildError>
+    where
+        F: Fn(usize, &mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>)
+            + Send
+            + Sync
+            + 'static,
+    {
+        assert!(num_threads != 0);
+
+        let record = Arc::new(record);
+        let threads: Vec<_> = (0..num_threads)
+            .map(|index| {
+                let recorder = self.clone();
+                let record = record.clone();
+
+                thread::Builder::new()
+                    .name(format!("vulkano command buffer recorder {}", index))
+                    .spawn(move || -> Result<_, BuildError> {
+                        let mut builder = recorder.builder()?;
+                        record(index, &mut builder);
+                        builder.build()
+                    })
+                    .expect("failed to spawn a command buffer recorder thread")
+            })
+            .collect();
+
+        // All the threads are joined before returning, even if one of them failed.
+        let results: Vec<_> = threads.into_iter().map(|thread| thread.join()).collect();
+        results
+            .into_iter()
+            .map(|result| result.unwrap_or_else(|err| panic::resume_unwind(err)))
+            .collect()
+    }
+
+    /// Same as [`record`](Self::record), then executes the recorded command buffers in order in
+    /// `primary`.
+    ///
+    /// For a graphics recorder, `primary` must be inside the subpass of the recorder, which must
+    /// have been entered with `SubpassContents::SecondaryCommandBuffers`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `num_threads` is 0.
+    /// - Panics if `record` panics on any of the threads.
+    /// - Panics if `primary` doesn't belong to the same device as the recorder.
+    pub fn record_into<P, F>(
+        &self,
+        primary: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer<P::Alloc>, P>,
+        num_threads: usize,
+        record: F,
+    ) -> Result<(), ParallelRecordError>
+    where
+        P: CommandPoolBuilderAlloc,
+        F: Fn(usize, &mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>)
+            + Send
+            + Sync
+            + 'static,
+    {
+        assert_eq!(
+            primary.device().internal_object(),
+            self.device.internal_object()
+        );
+
+        let command_buffers = self.record(num_threads, record)?;
+        primary.execute_commands_from_vec(command_buffers)?;
+        Ok(())
+    }
+}
+
+unsafe impl DeviceOwned for ParallelRecorder {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+/// Error that can happen when recording command buffers into a primary command buffer with a
+/// `ParallelRecorder`.
+#[derive(Debug, Clone)]
+pub enum ParallelRecordError {
+    /// Building one of the secondary command buffers failed.
+    BuildError(BuildError),
+    /// Executing the secondary command buffers in the primary command buffer failed.
+    ExecuteCommandsError(ExecuteCommandsError),
+}
+
+impl error::Error for ParallelRecordError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Self::BuildError(ref err) => Some(err),
+            Self::ExecuteCommandsError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for ParallelRecordError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                Self::BuildError(_) => "building one of the secondary command buffers failed",
+                Self::ExecuteCommandsError(_) => {
+                    "executing the secondary command buffers in the primary command buffer failed"
+                }
+            }
+        )
+    }
+}
+
+impl From<BuildError> for ParallelRecordError {
+    #[inline]
+    fn from(err: BuildError) -> Self {
+        Self::BuildError(err)
+    }
+}
+
+impl From<ExecuteCommandsError> for ParallelRecordError {
+    #[inline]
+    fn from(err: ExecuteCommandsError) -> Self {
+        Self::ExecuteCommandsError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_buffer::CommandBufferUsage;
+    use crate::command_buffer::ParallelRecorder;
+
+    #[test]
+    fn record_compute() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let recorder =
+            ParallelRecorder::compute(device, queue.family(), CommandBufferUsage::OneTimeSubmit);
+
+        let command_buffers = recorder.record(4, |_, _| ()).unwrap();
+        assert_eq!(command_buffers.len(), 4);
+    }
+
+    #[test]
+    fn panicking_record() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let recorder =
+            ParallelRecorder::compute(device, queue.family(), CommandBufferUsage::OneTimeSubmit);
+
+        assert_should_panic!({
+            let _ = recorder.record(2, |index, _| {
+                if index == 1 {
+                    panic!()
+                }
+            });
+        });
+    }
+}
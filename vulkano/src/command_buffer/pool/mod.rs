@@ -98,3 +98,24 @@ pub unsafe trait CommandPoolAlloc: DeviceOwned + Send + Sync {
     /// Returns the queue family that the pool targets.
     fn queue_family(&self) -> QueueFamily;
 }
+
+/// A command buffer allocated from a pool that has finished being recorded, and that can be
+/// recorded again.
+///
+/// # Safety
+///
+/// See `CommandPool` for information about safety.
+///
+/// The pool must have been created with the `RESET_COMMAND_BUFFER` flag, so that beginning to
+/// record the command buffer again resets it. The returned builder must lock the pool for the
+/// current thread, like the builders returned by `CommandPool::alloc`.
+///
+pub unsafe trait CommandPoolAllocReset: CommandPoolAlloc + Sized {
+    /// Return type of `into_builder`.
+    type Builder: CommandPoolBuilderAlloc<Alloc = Self>;
+
+    /// Turns this command buffer back into a builder, so that it can be recorded again.
+    ///
+    /// Returns `Err` with the command buffer if it can't be recorded on the current thread.
+    fn into_builder(self) -> Result<Self::Builder, Self>;
+}
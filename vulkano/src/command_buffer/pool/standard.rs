@@ -12,6 +12,8 @@ use fnv::FnvHashMap;
 use std::marker::PhantomData;
 use std::mem::ManuallyDrop;
 use std::ptr;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::Weak;
@@ -20,7 +22,9 @@ use std::vec::IntoIter as VecIntoIter;
 
 use crate::command_buffer::pool::CommandPool;
 use crate::command_buffer::pool::CommandPoolAlloc;
+use crate::command_buffer::pool::CommandPoolAllocReset;
 use crate::command_buffer::pool::CommandPoolBuilderAlloc;
+use crate::command_buffer::pool::CommandPoolTrimError;
 use crate::command_buffer::pool::UnsafeCommandPool;
 use crate::command_buffer::pool::UnsafeCommandPoolAlloc;
 use crate::device::physical::QueueFamily;
@@ -47,18 +51,112 @@ pub struct StandardCommandPool {
 
     // For each thread, we store thread-specific info.
     per_thread: Mutex<FnvHashMap<thread::ThreadId, Weak<StandardCommandPoolPerThread>>>,
+
+    // Decides whether the pool of the current thread should be trimmed before allocating.
+    trim_policy: Mutex<Option<Box<dyn Fn(&StandardCommandPoolStatistics) -> bool + Send + Sync>>>,
 }
 
 unsafe impl Send for StandardCommandPool {}
 unsafe impl Sync for StandardCommandPool {}
 
 struct StandardCommandPoolPerThread {
+    // The thread that uses this pool.
+    thread: thread::ThreadId,
     // The Vulkan pool of this thread.
     pool: Mutex<UnsafeCommandPool>,
     // List of existing primary command buffers that are available for reuse.
     available_primary_command_buffers: SegQueue<UnsafeCommandPoolAlloc>,
     // List of existing secondary command buffers that are available for reuse.
     available_secondary_command_buffers: SegQueue<UnsafeCommandPoolAlloc>,
+    // Number of primary command buffers allocated from the Vulkan pool and not freed yet.
+    primary_command_buffers: AtomicUsize,
+    // Number of secondary command buffers allocated from the Vulkan pool and not freed yet.
+    secondary_command_buffers: AtomicUsize,
+}
+
+impl StandardCommandPoolPerThread {
+    fn statistics(&self) -> StandardCommandPoolStatistics {
+        let primary = self.primary_command_buffers.load(Ordering::SeqCst);
+        let secondary = self.secondary_command_buffers.load(Ordering::SeqCst);
+        let available_primary = self.available_primary_command_buffers.len();
+        let available_secondary = self.available_secondary_command_buffers.len();
+
+        StandardCommandPoolStatistics {
+            thread_pools: 1,
+            live_primary_command_buffers: primary.saturating_sub(available_primary),
+            live_secondary_command_buffers: secondary.saturating_sub(available_secondary),
+            available_primary_command_buffers: available_primary,
+            available_secondary_command_buffers: available_secondary,
+        }
+    }
+
+    // Frees the command buffers that are available for reuse, then trims the Vulkan pool.
+    //
+    // Must only be called from `self.thread`, as the Vulkan pool is used without locking while
+    // recording.
+    fn trim(&self) -> Result<(), CommandPoolTrimError> {
+        debug_assert_eq!(self.thread, thread::current().id());
+        let pool = self.pool.lock().unwrap();
+
+        let mut primary = Vec::new();
+        while let Some(cmd) = self.available_primary_command_buffers.pop() {
+            primary.push(cmd);
+        }
+        let mut secondary = Vec::new();
+        while let Some(cmd) = self.available_secondary_command_buffers.pop() {
+            secondary.push(cmd);
+        }
+
+        self.primary_command_buffers
+            .fetch_sub(primary.len(), Ordering::SeqCst);
+        self.secondary_command_buffers
+            .fetch_sub(secondary.len(), Ordering::SeqCst);
+
+        // Safe because the command buffers available for reuse are not in use anymore.
+        unsafe {
+            if !primary.is_empty() {
+                pool.free_command_buffers(primary);
+            }
+            if !secondary.is_empty() {
+                pool.free_command_buffers(secondary);
+            }
+        }
+
+        pool.trim()
+    }
+}
+
+/// Statistics about the command buffers of a `StandardCommandPool`.
+///
+/// Vulkan doesn't report how much memory a command pool holds. The number of command buffers that
+/// are available for reuse is the best approximation of the memory that the pool retains without
+/// using it, since they keep their memory until they are reused or the pool is trimmed.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct StandardCommandPoolStatistics {
+    /// Number of Vulkan pools, one for each thread that has live or available command buffers.
+    pub thread_pools: usize,
+    /// Number of primary command buffers that are being built or that are alive.
+    pub live_primary_command_buffers: usize,
+    /// Number of secondary command buffers that are being built or that are alive.
+    pub live_secondary_command_buffers: usize,
+    /// Number of primary command buffers that were dropped and are kept for reuse.
+    pub available_primary_command_buffers: usize,
+    /// Number of secondary command buffers that were dropped and are kept for reuse.
+    pub available_secondary_command_buffers: usize,
+}
+
+impl StandardCommandPoolStatistics {
+    /// Returns the total number of command buffers that are being built or that are alive.
+    #[inline]
+    pub fn live_command_buffers(&self) -> usize {
+        self.live_primary_command_buffers + self.live_secondary_command_buffers
+    }
+
+    /// Returns the total number of command buffers that are kept for reuse.
+    #[inline]
+    pub fn available_command_buffers(&self) -> usize {
+        self.available_primary_command_buffers + self.available_secondary_command_buffers
+    }
 }
 
 impl StandardCommandPool {
@@ -78,8 +176,75 @@ impl StandardCommandPool {
             device: device,
             queue_family: queue_family.id(),
             per_thread: Mutex::new(Default::default()),
+            trim_policy: Mutex::new(None),
+        }
+    }
+
+    /// Returns statistics about the command buffers allocated from this pool, summed over all
+    /// threads.
+    pub fn statistics(&self) -> StandardCommandPoolStatistics {
+        let hashmap = self.per_thread.lock().unwrap();
+
+        hashmap
+            .values()
+            .filter_map(Weak::upgrade)
+            .map(|per_thread| per_thread.statistics())
+            .fold(Default::default(), |total, stats| {
+                StandardCommandPoolStatistics {
+                    thread_pools: total.thread_pools + stats.thread_pools,
+                    live_primary_command_buffers: total.live_primary_command_buffers
+                        + stats.live_primary_command_buffers,
+                    live_secondary_command_buffers: total.live_secondary_command_buffers
+                        + stats.live_secondary_command_buffers,
+                    available_primary_command_buffers: total.available_primary_command_buffers
+                        + stats.available_primary_command_buffers,
+                    available_secondary_command_buffers: total.available_secondary_command_buffers
+                        + stats.available_secondary_command_buffers,
+                }
+            })
+    }
+
+    /// Frees the command buffers of the current thread that are kept for reuse, then trims the
+    /// Vulkan pool of the current thread with `vkTrimCommandPool`.
+    ///
+    /// Only the pool of the current thread is affected, because the pools of the other threads
+    /// may be in use for recording.
+    ///
+    /// The command buffers are freed even if an error is returned. Since trimming is purely an
+    /// optimization, it is legitimate to ignore any possible error.
+    pub fn trim(&self) -> Result<(), CommandPoolTrimError> {
+        let this_thread = thread::current().id();
+        let per_thread = self
+            .per_thread
+            .lock()
+            .unwrap()
+            .get(&this_thread)
+            .and_then(Weak::upgrade);
+
+        match per_thread {
+            Some(per_thread) => per_thread.trim(),
+            None => Ok(()),
         }
     }
+
+    /// Sets a policy that decides when the pool of a thread should be trimmed.
+    ///
+    /// Each time command buffers are allocated, `policy` is called with the statistics of the
+    /// pool of the current thread. If it returns `true`, the pool is trimmed as if `trim()` had
+    /// been called before allocating. This can be used to keep the memory retained by the pool
+    /// within a budget, for example with
+    /// `|stats| stats.available_command_buffers() > 64`.
+    pub fn set_trim_policy<F>(&self, policy: F)
+    where
+        F: Fn(&StandardCommandPoolStatistics) -> bool + Send + Sync + 'static,
+    {
+        *self.trim_policy.lock().unwrap() = Some(Box::new(policy));
+    }
+
+    /// Removes the policy set with `set_trim_policy`.
+    pub fn clear_trim_policy(&self) {
+        *self.trim_policy.lock().unwrap() = None;
+    }
 }
 
 unsafe impl CommandPool for Arc<StandardCommandPool> {
@@ -102,15 +267,25 @@ unsafe impl CommandPool for Arc<StandardCommandPool> {
             let new_pool =
                 UnsafeCommandPool::new(self.device.clone(), self.queue_family(), false, true)?;
             let pt = Arc::new(StandardCommandPoolPerThread {
+                thread: this_thread,
                 pool: Mutex::new(new_pool),
                 available_primary_command_buffers: SegQueue::new(),
                 available_secondary_command_buffers: SegQueue::new(),
+                primary_command_buffers: AtomicUsize::new(0),
+                secondary_command_buffers: AtomicUsize::new(0),
             });
 
             hashmap.insert(this_thread, Arc::downgrade(&pt));
             pt
         };
 
+        if let Some(policy) = &*self.trim_policy.lock().unwrap() {
+            if policy(&per_thread.statistics()) {
+                // Trimming is only an optimization, and the command buffers are freed anyway.
+                let _ = per_thread.trim();
+            }
+        }
+
         // The final output.
         let mut output = Vec::with_capacity(count as usize);
 
@@ -144,8 +319,16 @@ unsafe impl CommandPool for Arc<StandardCommandPool> {
         if output.len() < count as usize {
             let pool_lock = per_thread.pool.lock().unwrap();
             let num_new = count as usize - output.len();
+            let new_command_buffers = pool_lock.alloc_command_buffers(secondary, num_new as u32)?;
+
+            if secondary {
+                &per_thread.secondary_command_buffers
+            } else {
+                &per_thread.primary_command_buffers
+            }
+            .fetch_add(num_new, Ordering::SeqCst);
 
-            for cmd in pool_lock.alloc_command_buffers(secondary, num_new as u32)? {
+            for cmd in new_command_buffers {
                 output.push(StandardCommandPoolBuilder {
                     inner: StandardCommandPoolAlloc {
                         cmd: ManuallyDrop::new(cmd),
@@ -248,6 +431,24 @@ unsafe impl CommandPoolAlloc for StandardCommandPoolAlloc {
     }
 }
 
+// Command buffers can only be recorded again on the thread of their pool, since the pool of each
+// thread is used without locking while recording.
+unsafe impl CommandPoolAllocReset for StandardCommandPoolAlloc {
+    type Builder = StandardCommandPoolBuilder;
+
+    #[inline]
+    fn into_builder(self) -> Result<StandardCommandPoolBuilder, StandardCommandPoolAlloc> {
+        if self.pool.thread != thread::current().id() {
+            return Err(self);
+        }
+
+        Ok(StandardCommandPoolBuilder {
+            inner: self,
+            dummy_avoid_send_sync: PhantomData,
+        })
+    }
+}
+
 unsafe impl DeviceOwned for StandardCommandPoolAlloc {
     #[inline]
     fn device(&self) -> &Arc<Device> {
@@ -273,11 +474,14 @@ impl Drop for StandardCommandPoolAlloc {
 #[cfg(test)]
 mod tests {
     use crate::command_buffer::pool::CommandPool;
+    use crate::command_buffer::pool::CommandPoolAlloc;
+    use crate::command_buffer::pool::CommandPoolAllocReset;
     use crate::command_buffer::pool::CommandPoolBuilderAlloc;
     use crate::command_buffer::pool::StandardCommandPool;
     use crate::device::Device;
     use crate::VulkanObject;
     use std::sync::Arc;
+    use std::thread;
 
     #[test]
     fn reuse_command_buffers() {
@@ -296,6 +500,21 @@ mod tests {
         assert_eq!(raw, cb2.inner().internal_object());
     }
 
+    #[test]
+    fn into_builder_only_on_pool_thread() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let pool = Device::standard_command_pool(&device, queue.family());
+
+        let cb = pool.alloc(false, 1).unwrap().next().unwrap().into_alloc();
+        let raw = cb.inner().internal_object();
+
+        let cb = thread::spawn(move || cb.into_builder().map(|_| ()).unwrap_err())
+            .join()
+            .unwrap();
+        let builder = cb.into_builder().ok().unwrap();
+        assert_eq!(raw, builder.inner().internal_object());
+    }
+
     #[test]
     fn pool_kept_alive_by_allocs() {
         let (device, queue) = gfx_dev_and_queue!();
@@ -310,4 +529,36 @@ mod tests {
         drop(cb);
         assert!(pool_weak.upgrade().is_none());
     }
+
+    #[test]
+    fn statistics_and_trim() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let pool = Arc::new(StandardCommandPool::new(device, queue.family()));
+
+        let mut cbs: Vec<_> = pool.alloc(false, 2).unwrap().collect();
+        let _secondary = pool.alloc(true, 1).unwrap().next().unwrap();
+        cbs.pop();
+
+        let stats = pool.statistics();
+        assert_eq!(stats.thread_pools, 1);
+        assert_eq!(stats.live_primary_command_buffers, 1);
+        assert_eq!(stats.live_secondary_command_buffers, 1);
+        assert_eq!(stats.available_primary_command_buffers, 1);
+        assert_eq!(stats.available_command_buffers(), 1);
+
+        // The command buffers are freed even without `khr_maintenance1`.
+        let _ = pool.trim();
+        let stats = pool.statistics();
+        assert_eq!(stats.live_command_buffers(), 2);
+        assert_eq!(stats.available_command_buffers(), 0);
+
+        cbs.clear();
+        assert_eq!(pool.statistics().available_primary_command_buffers, 1);
+
+        pool.set_trim_policy(|stats| stats.available_command_buffers() > 0);
+        let _cb = pool.alloc(false, 1).unwrap().next().unwrap();
+        let stats = pool.statistics();
+        assert_eq!(stats.live_primary_command_buffers, 1);
+        assert_eq!(stats.available_primary_command_buffers, 0);
+    }
 }
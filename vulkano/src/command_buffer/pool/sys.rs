@@ -286,6 +286,35 @@ pub struct UnsafeCommandPoolAlloc {
     device: Arc<Device>,
 }
 
+impl UnsafeCommandPoolAlloc {
+    /// Resets the command buffer, which puts it back in the initial state.
+    ///
+    /// If `release_resources` is true, it is a hint to the implementation that it should free all
+    /// the memory internally allocated for this command buffer.
+    ///
+    /// > **Note**: Beginning to record a command buffer implicitly resets it, so this is only
+    /// > needed to release its resources without recording it again.
+    ///
+    /// # Safety
+    ///
+    /// - The pool that the command buffer was allocated from must have been created with
+    ///   `reset_cb` set to true.
+    /// - The command buffer must not be pending execution.
+    /// - The pool must be externally synchronized.
+    ///
+    pub unsafe fn reset(&self, release_resources: bool) -> Result<(), OomError> {
+        let flags = if release_resources {
+            ash::vk::CommandBufferResetFlags::RELEASE_RESOURCES
+        } else {
+            ash::vk::CommandBufferResetFlags::empty()
+        };
+
+        let fns = self.device.fns();
+        check_errors(fns.v1_0.reset_command_buffer(self.command_buffer, flags))?;
+        Ok(())
+    }
+}
+
 unsafe impl DeviceOwned for UnsafeCommandPoolAlloc {
     #[inline]
     fn device(&self) -> &Arc<Device> {
@@ -11,9 +11,11 @@ use crate::buffer::sys::UnsafeBuffer;
 use crate::check_errors;
 use crate::device::Queue;
 use crate::image::sys::UnsafeImage;
+use crate::image::ImageAspect;
 use crate::memory::DeviceMemory;
 use crate::sync::Fence;
 use crate::sync::Semaphore;
+use crate::sync::TimelineSemaphore;
 use crate::DeviceSize;
 use crate::Error;
 use crate::OomError;
@@ -23,6 +25,7 @@ use smallvec::SmallVec;
 use std::error;
 use std::fmt;
 use std::marker::PhantomData;
+use std::ptr;
 
 // TODO: correctly implement Debug on all the structs of this module
 
@@ -53,6 +56,31 @@ impl<'a> SubmitBindSparseBuilder<'a> {
         self.infos.push(builder);
     }
 
+    /// Adds a timeline semaphore that is going to be set to `value` at the end of the last batch,
+    /// or of a new empty batch if there is none.
+    ///
+    /// As explained in [`add`](Self::add), this does not mean that the previous batches have been
+    /// completed when the semaphore reaches `value`.
+    ///
+    /// # Safety
+    ///
+    /// See [`SubmitBindSparseBatchBuilder::add_signal_timeline_semaphore`].
+    #[inline]
+    pub unsafe fn add_signal_timeline_semaphore(
+        &mut self,
+        semaphore: &'a TimelineSemaphore,
+        value: u64,
+    ) {
+        if self.infos.is_empty() {
+            self.infos.push(SubmitBindSparseBatchBuilder::new());
+        }
+
+        self.infos
+            .last_mut()
+            .unwrap()
+            .add_signal_timeline_semaphore(semaphore, value);
+    }
+
     /// Returns true if this builder will signal a fence when submitted.
     ///
     /// # Example
@@ -180,6 +208,27 @@ impl<'a> SubmitBindSparseBuilder<'a> {
                 })
                 .collect();
 
+            // The values of the timeline semaphores of each batch, which must stay alive until the
+            // submission.
+            let timeline_infos: SmallVec<[_; 4]> = self
+                .infos
+                .iter()
+                .map(|builder| {
+                    if builder.has_timeline_semaphores {
+                        Some(ash::vk::TimelineSemaphoreSubmitInfo {
+                            wait_semaphore_value_count: builder.wait_semaphore_values.len() as u32,
+                            p_wait_semaphore_values: builder.wait_semaphore_values.as_ptr(),
+                            signal_semaphore_value_count: builder.signal_semaphore_values.len()
+                                as u32,
+                            p_signal_semaphore_values: builder.signal_semaphore_values.as_ptr(),
+                            ..Default::default()
+                        })
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
             // Now building the collection of `VkBindSparseInfo`s.
             let bs_infos = {
                 let mut bs_infos: SmallVec<[_; 4]> = SmallVec::new();
@@ -190,8 +239,11 @@ impl<'a> SubmitBindSparseBuilder<'a> {
                 let mut next_image_opaque_bind = 0;
                 let mut next_image_bind = 0;
 
-                for builder in self.infos.iter() {
+                for (builder, timeline_info) in self.infos.iter().zip(timeline_infos.iter()) {
                     bs_infos.push(ash::vk::BindSparseInfo {
+                        p_next: timeline_info
+                            .as_ref()
+                            .map_or(ptr::null(), |info| info as *const _ as *const _),
                         wait_semaphore_count: builder.wait_semaphores.len() as u32,
                         p_wait_semaphores: builder.wait_semaphores.as_ptr(),
                         buffer_bind_count: builder.buffer_binds.len() as u32,
@@ -264,6 +316,11 @@ pub struct SubmitBindSparseBatchBuilder<'a> {
     image_opaque_binds: SmallVec<[SubmitBindSparseImageOpaqueBindBuilder<'a>; 2]>,
     image_binds: SmallVec<[SubmitBindSparseImageBindBuilder<'a>; 2]>,
     signal_semaphores: SmallVec<[ash::vk::Semaphore; 8]>,
+    // Values of the semaphores to wait upon and signal, which are ignored for binary semaphores.
+    wait_semaphore_values: SmallVec<[u64; 8]>,
+    signal_semaphore_values: SmallVec<[u64; 8]>,
+    // True if any of the semaphores is a timeline semaphore.
+    has_timeline_semaphores: bool,
     marker: PhantomData<&'a ()>,
 }
 
@@ -277,6 +334,9 @@ impl<'a> SubmitBindSparseBatchBuilder<'a> {
             image_opaque_binds: SmallVec::new(),
             image_binds: SmallVec::new(),
             signal_semaphores: SmallVec::new(),
+            wait_semaphore_values: SmallVec::new(),
+            signal_semaphore_values: SmallVec::new(),
+            has_timeline_semaphores: false,
             marker: PhantomData,
         }
     }
@@ -316,6 +376,32 @@ impl<'a> SubmitBindSparseBatchBuilder<'a> {
     #[inline]
     pub unsafe fn add_wait_semaphore(&mut self, semaphore: &'a Semaphore) {
         self.wait_semaphores.push(semaphore.internal_object());
+        self.wait_semaphore_values.push(0);
+    }
+
+    /// Adds a timeline semaphore to be waited upon before the sparse binding is executed, until
+    /// it reaches `value`.
+    ///
+    /// # Safety
+    ///
+    /// - If you submit this builder, the semaphore must be kept alive until you are guaranteed
+    ///   that the GPU has at least started executing the operation.
+    ///
+    /// - If you submit this builder, the semaphore must reach `value` at some point, through
+    ///   the host or through other submissions. In other words, you must not block the queue
+    ///   with semaphores that can't get signaled.
+    ///
+    /// - The fence, buffers, images, and semaphores must all belong to the same device.
+    ///
+    #[inline]
+    pub unsafe fn add_wait_timeline_semaphore(
+        &mut self,
+        semaphore: &'a TimelineSemaphore,
+        value: u64,
+    ) {
+        self.wait_semaphores.push(semaphore.internal_object());
+        self.wait_semaphore_values.push(value);
+        self.has_timeline_semaphores = true;
     }
 
     /// Returns the number of semaphores to signal.
@@ -341,6 +427,31 @@ impl<'a> SubmitBindSparseBatchBuilder<'a> {
     #[inline]
     pub unsafe fn add_signal_semaphore(&mut self, semaphore: &'a Semaphore) {
         self.signal_semaphores.push(semaphore.internal_object());
+        self.signal_semaphore_values.push(0);
+    }
+
+    /// Adds a timeline semaphore that is going to be set to `value` at the end of the batch.
+    ///
+    /// # Safety
+    ///
+    /// - If you submit this builder, the semaphore must be kept alive until you are guaranteed
+    ///   that the GPU has finished executing this submission.
+    ///
+    /// - `value` must be greater than the value of the semaphore, and than the value of all its
+    ///   signal operations that come before this one, when queue execution reaches this
+    ///   submission.
+    ///
+    /// - The fence, buffers, images, and semaphores must all belong to the same device.
+    ///
+    #[inline]
+    pub unsafe fn add_signal_timeline_semaphore(
+        &mut self,
+        semaphore: &'a TimelineSemaphore,
+        value: u64,
+    ) {
+        self.signal_semaphores.push(semaphore.internal_object());
+        self.signal_semaphore_values.push(value);
+        self.has_timeline_semaphores = true;
     }
 }
 
@@ -460,7 +571,85 @@ impl<'a> SubmitBindSparseImageBindBuilder<'a> {
         }
     }
 
-    // TODO: finish
+    /// Adds an operation that binds memory to a region of a mip level and array layer of the
+    /// image.
+    ///
+    /// # Safety
+    ///
+    /// - The image must have been created with the `sparse_residency` flag.
+    /// - `offset` and `extent` must be multiples of the sparse block size of `aspect`, except
+    ///   where the region reaches the edge of the mip level.
+    /// - `memory_offset` must be a multiple of the alignment of the memory requirements of the
+    ///   image.
+    /// - If you submit this builder, the memory must be kept alive until the image is destroyed
+    ///   or the region is unbound.
+    pub unsafe fn add_bind(
+        &mut self,
+        aspect: ImageAspect,
+        mip_level: u32,
+        array_layer: u32,
+        offset: [i32; 3],
+        extent: [u32; 3],
+        memory: &DeviceMemory,
+        memory_offset: DeviceSize,
+    ) {
+        self.binds.push(ash::vk::SparseImageMemoryBind {
+            subresource: ash::vk::ImageSubresource {
+                aspect_mask: aspect.into(),
+                mip_level,
+                array_layer,
+            },
+            offset: ash::vk::Offset3D {
+                x: offset[0],
+                y: offset[1],
+                z: offset[2],
+            },
+            extent: ash::vk::Extent3D {
+                width: extent[0],
+                height: extent[1],
+                depth: extent[2],
+            },
+            memory: memory.internal_object(),
+            memory_offset,
+            flags: ash::vk::SparseMemoryBindFlags::empty(),
+        });
+    }
+
+    /// Adds an operation that removes the memory of a region of a mip level and array layer of
+    /// the image.
+    ///
+    /// # Safety
+    ///
+    /// Same as `add_bind`.
+    pub unsafe fn add_unbind(
+        &mut self,
+        aspect: ImageAspect,
+        mip_level: u32,
+        array_layer: u32,
+        offset: [i32; 3],
+        extent: [u32; 3],
+    ) {
+        self.binds.push(ash::vk::SparseImageMemoryBind {
+            subresource: ash::vk::ImageSubresource {
+                aspect_mask: aspect.into(),
+                mip_level,
+                array_layer,
+            },
+            offset: ash::vk::Offset3D {
+                x: offset[0],
+                y: offset[1],
+                z: offset[2],
+            },
+            extent: ash::vk::Extent3D {
+                width: extent[0],
+                height: extent[1],
+                depth: extent[2],
+            },
+            memory: ash::vk::DeviceMemory::null(),
+            memory_offset: 0,
+            flags: ash::vk::SparseMemoryBindFlags::empty(),
+        });
+    }
 }
 
 /// Error that can happen when submitting the present prototype.
@@ -509,3 +698,29 @@ impl From<Error> for SubmitBindSparseError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::command_buffer::submit::SubmitBindSparseBuilder;
+    use crate::sync::TimelineSemaphore;
+    use std::time::Duration;
+
+    #[test]
+    fn signal_timeline_semaphore() {
+        let (device, queue) = gfx_dev_and_queue!(timeline_semaphore);
+
+        if !queue.family().supports_sparse_binding() {
+            return;
+        }
+
+        let semaphore = TimelineSemaphore::new(device, 0).unwrap();
+
+        unsafe {
+            let mut builder = SubmitBindSparseBuilder::new();
+            builder.add_signal_timeline_semaphore(&semaphore, 1);
+            builder.submit(&queue).unwrap();
+        }
+
+        semaphore.wait(1, Some(Duration::from_secs(5))).unwrap();
+    }
+}
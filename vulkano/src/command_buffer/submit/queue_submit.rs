@@ -9,10 +9,15 @@
 
 use crate::check_errors;
 use crate::command_buffer::sys::UnsafeCommandBuffer;
+#[cfg(target_os = "windows")]
+use crate::device::DeviceOwned;
 use crate::device::Queue;
+#[cfg(target_os = "windows")]
+use crate::memory::DeviceMemory;
 use crate::sync::Fence;
 use crate::sync::PipelineStages;
 use crate::sync::Semaphore;
+use crate::sync::TimelineSemaphore;
 use crate::Error;
 use crate::OomError;
 use crate::SynchronizedVulkanObject;
@@ -21,15 +26,26 @@ use smallvec::SmallVec;
 use std::error;
 use std::fmt;
 use std::marker::PhantomData;
+use std::ptr;
+#[cfg(target_os = "windows")]
+use std::time::Duration;
 
 /// Prototype for a submission that executes command buffers.
 // TODO: example here
 #[derive(Debug)]
 pub struct SubmitCommandBufferBuilder<'a> {
     wait_semaphores: SmallVec<[ash::vk::Semaphore; 16]>,
-    destination_stages: SmallVec<[ash::vk::PipelineStageFlags; 8]>,
+    destination_stages: SmallVec<[PipelineStages; 8]>,
     signal_semaphores: SmallVec<[ash::vk::Semaphore; 16]>,
+    // Values of the semaphores to wait upon and signal, which are ignored for binary semaphores.
+    wait_semaphore_values: SmallVec<[u64; 16]>,
+    signal_semaphore_values: SmallVec<[u64; 16]>,
+    // True if any of the semaphores is a timeline semaphore.
+    has_timeline_semaphores: bool,
     command_buffers: SmallVec<[ash::vk::CommandBuffer; 4]>,
+    // Memory, key and timeout in milliseconds of the keyed mutexes to acquire and release.
+    keyed_mutex_acquires: SmallVec<[(ash::vk::DeviceMemory, u64, u32); 2]>,
+    keyed_mutex_releases: SmallVec<[(ash::vk::DeviceMemory, u64); 2]>,
     fence: ash::vk::Fence,
     marker: PhantomData<&'a ()>,
 }
@@ -42,7 +58,12 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
             wait_semaphores: SmallVec::new(),
             destination_stages: SmallVec::new(),
             signal_semaphores: SmallVec::new(),
+            wait_semaphore_values: SmallVec::new(),
+            signal_semaphore_values: SmallVec::new(),
+            has_timeline_semaphores: false,
             command_buffers: SmallVec::new(),
+            keyed_mutex_acquires: SmallVec::new(),
+            keyed_mutex_releases: SmallVec::new(),
             fence: ash::vk::Fence::null(),
             marker: PhantomData,
         }
@@ -137,10 +158,44 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     ///
     #[inline]
     pub unsafe fn add_wait_semaphore(&mut self, semaphore: &'a Semaphore, stages: PipelineStages) {
-        debug_assert!(!ash::vk::PipelineStageFlags::from(stages).is_empty());
+        debug_assert_ne!(stages, PipelineStages::none());
         // TODO: debug assert that the device supports the stages
         self.wait_semaphores.push(semaphore.internal_object());
-        self.destination_stages.push(stages.into());
+        self.wait_semaphore_values.push(0);
+        self.destination_stages.push(stages);
+    }
+
+    /// Adds a timeline semaphore to be waited upon before the command buffers are executed, until
+    /// it reaches `value`.
+    ///
+    /// Only the given `stages` of the command buffers added afterwards will wait upon
+    /// the semaphore. Other stages not included in `stages` can execute before waiting.
+    ///
+    /// # Safety
+    ///
+    /// - The stages must be supported by the device.
+    ///
+    /// - If you submit this builder, the semaphore must be kept alive until you are guaranteed
+    ///   that the GPU has at least started executing the command buffers.
+    ///
+    /// - If you submit this builder, the semaphore must reach `value` at some point, through
+    ///   the host or through other submissions. In other words, you must not block the queue
+    ///   with semaphores that can't get signaled.
+    ///
+    /// - The fence, command buffers, and semaphores must all belong to the same device.
+    ///
+    #[inline]
+    pub unsafe fn add_wait_timeline_semaphore(
+        &mut self,
+        semaphore: &'a TimelineSemaphore,
+        value: u64,
+        stages: PipelineStages,
+    ) {
+        debug_assert_ne!(stages, PipelineStages::none());
+        self.wait_semaphores.push(semaphore.internal_object());
+        self.wait_semaphore_values.push(value);
+        self.destination_stages.push(stages);
+        self.has_timeline_semaphores = true;
     }
 
     /// Adds a command buffer that is executed as part of this command.
@@ -189,24 +244,109 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     #[inline]
     pub unsafe fn add_signal_semaphore(&mut self, semaphore: &'a Semaphore) {
         self.signal_semaphores.push(semaphore.internal_object());
+        self.signal_semaphore_values.push(0);
+    }
+
+    /// Adds a timeline semaphore that is going to be set to `value` at the end of the submission.
+    ///
+    /// # Safety
+    ///
+    /// - If you submit this builder, the semaphore must be kept alive until you are guaranteed
+    ///   that the GPU has finished executing this submission.
+    ///
+    /// - `value` must be greater than the value of the semaphore, and than the value of all its
+    ///   signal operations that come before this one, when queue execution reaches this
+    ///   submission.
+    ///
+    /// - The fence, command buffers, and semaphores must all belong to the same device.
+    ///
+    #[inline]
+    pub unsafe fn add_signal_timeline_semaphore(
+        &mut self,
+        semaphore: &'a TimelineSemaphore,
+        value: u64,
+    ) {
+        self.signal_semaphores.push(semaphore.internal_object());
+        self.signal_semaphore_values.push(value);
+        self.has_timeline_semaphores = true;
+    }
+
+    /// Adds an operation that acquires the keyed mutex of `memory` with `key` before the command
+    /// buffers are executed, waiting at most `timeout` for it.
+    ///
+    /// This is needed to access memory imported from a Direct3D 11 resource that was created with
+    /// a keyed mutex, or that is shared with Direct3D through a keyed mutex.
+    ///
+    /// # Safety
+    ///
+    /// - The `khr_win32_keyed_mutex` extension must be enabled on the device.
+    /// - `memory` must have been imported from or exported as a Windows handle of a resource with
+    ///   a keyed mutex.
+    /// - If you submit this builder, the memory must be kept alive until the GPU has finished
+    ///   executing this submission.
+    ///
+    #[cfg(target_os = "windows")]
+    #[inline]
+    pub unsafe fn add_keyed_mutex_acquire(
+        &mut self,
+        memory: &'a DeviceMemory,
+        key: u64,
+        timeout: Duration,
+    ) {
+        debug_assert!(memory.device().enabled_extensions().khr_win32_keyed_mutex);
+        let timeout = timeout.as_millis().min(u32::MAX as u128) as u32;
+        self.keyed_mutex_acquires
+            .push((memory.internal_object(), key, timeout));
+    }
+
+    /// Adds an operation that releases the keyed mutex of `memory` with `key` once the command
+    /// buffers have been executed.
+    ///
+    /// # Safety
+    ///
+    /// - The `khr_win32_keyed_mutex` extension must be enabled on the device.
+    /// - `memory` must have been imported from or exported as a Windows handle of a resource with
+    ///   a keyed mutex, and the mutex must be acquired by this submission or a previous one.
+    /// - If you submit this builder, the memory must be kept alive until the GPU has finished
+    ///   executing this submission.
+    ///
+    #[cfg(target_os = "windows")]
+    #[inline]
+    pub unsafe fn add_keyed_mutex_release(&mut self, memory: &'a DeviceMemory, key: u64) {
+        debug_assert!(memory.device().enabled_extensions().khr_win32_keyed_mutex);
+        self.keyed_mutex_releases
+            .push((memory.internal_object(), key));
     }
 
     /// Submits the command buffer to the given queue.
     ///
+    /// This calls `vkQueueSubmit2KHR` if the `synchronization2` feature is enabled on the device,
+    /// or `vkQueueSubmit` otherwise.
+    ///
     /// > **Note**: This is an expensive operation, so you may want to merge as many builders as
     /// > possible together and avoid submitting them one by one.
     ///
     pub fn submit(self, queue: &Queue) -> Result<(), SubmitCommandBufferError> {
+        debug_assert_eq!(self.wait_semaphores.len(), self.destination_stages.len());
+
+        if queue.device().enabled_features().synchronization2 {
+            return self.submit2(queue);
+        }
+
         unsafe {
             let fns = queue.device().fns();
             let queue = queue.internal_object_guard();
 
-            debug_assert_eq!(self.wait_semaphores.len(), self.destination_stages.len());
+            let destination_stages: SmallVec<[ash::vk::PipelineStageFlags; 8]> = self
+                .destination_stages
+                .iter()
+                .map(|&stages| stages.into())
+                .collect();
 
             let batch = ash::vk::SubmitInfo {
                 wait_semaphore_count: self.wait_semaphores.len() as u32,
                 p_wait_semaphores: self.wait_semaphores.as_ptr(),
-                p_wait_dst_stage_mask: self.destination_stages.as_ptr(),
+                p_wait_dst_stage_mask: destination_stages.as_ptr(),
                 command_buffer_count: self.command_buffers.len() as u32,
                 p_command_buffers: self.command_buffers.as_ptr(),
                 signal_semaphore_count: self.signal_semaphores.len() as u32,
@@ -214,11 +354,104 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
                 ..Default::default()
             };
 
+            let timeline_info = if self.has_timeline_semaphores {
+                Some(ash::vk::TimelineSemaphoreSubmitInfo {
+                    wait_semaphore_value_count: self.wait_semaphore_values.len() as u32,
+                    p_wait_semaphore_values: self.wait_semaphore_values.as_ptr(),
+                    signal_semaphore_value_count: self.signal_semaphore_values.len() as u32,
+                    p_signal_semaphore_values: self.signal_semaphore_values.as_ptr(),
+                    ..Default::default()
+                })
+            } else {
+                None
+            };
+            let mut p_next = timeline_info
+                .as_ref()
+                .map_or(ptr::null(), |info| info as *const _ as *const _);
+
+            let keyed_mutexes = self.keyed_mutexes();
+            let mut keyed_mutex_info = keyed_mutexes.info();
+            if let Some(info) = keyed_mutex_info.as_mut() {
+                info.p_next = p_next;
+                p_next = info as *const _ as *const _;
+            }
+
+            let batch = ash::vk::SubmitInfo { p_next, ..batch };
+
             check_errors(fns.v1_0.queue_submit(*queue, 1, &batch, self.fence))?;
             Ok(())
         }
     }
 
+    // Same as `submit`, but with `vkQueueSubmit2KHR`.
+    fn submit2(self, queue: &Queue) -> Result<(), SubmitCommandBufferError> {
+        unsafe {
+            let fns = queue.device().fns();
+            let queue = queue.internal_object_guard();
+
+            let wait_semaphore_infos: SmallVec<[_; 16]> = self
+                .wait_semaphores
+                .iter()
+                .zip(self.destination_stages.iter())
+                .zip(self.wait_semaphore_values.iter())
+                .map(
+                    |((&semaphore, &stages), &value)| ash::vk::SemaphoreSubmitInfoKHR {
+                        semaphore,
+                        value,
+                        stage_mask: stages.into(),
+                        ..Default::default()
+                    },
+                )
+                .collect();
+            let command_buffer_infos: SmallVec<[_; 4]> = self
+                .command_buffers
+                .iter()
+                .map(|&command_buffer| ash::vk::CommandBufferSubmitInfoKHR {
+                    command_buffer,
+                    ..Default::default()
+                })
+                .collect();
+            // The semaphores are signaled once all the commands of the batch are finished,
+            // like with `vkQueueSubmit`.
+            let signal_semaphore_infos: SmallVec<[_; 16]> = self
+                .signal_semaphores
+                .iter()
+                .zip(self.signal_semaphore_values.iter())
+                .map(|(&semaphore, &value)| ash::vk::SemaphoreSubmitInfoKHR {
+                    semaphore,
+                    value,
+                    stage_mask: ash::vk::PipelineStageFlags2KHR::ALL_COMMANDS,
+                    ..Default::default()
+                })
+                .collect();
+
+            let batch = ash::vk::SubmitInfo2KHR {
+                wait_semaphore_info_count: wait_semaphore_infos.len() as u32,
+                p_wait_semaphore_infos: wait_semaphore_infos.as_ptr(),
+                command_buffer_info_count: command_buffer_infos.len() as u32,
+                p_command_buffer_infos: command_buffer_infos.as_ptr(),
+                signal_semaphore_info_count: signal_semaphore_infos.len() as u32,
+                p_signal_semaphore_infos: signal_semaphore_infos.as_ptr(),
+                ..Default::default()
+            };
+
+            let keyed_mutexes = self.keyed_mutexes();
+            let keyed_mutex_info = keyed_mutexes.info();
+            let batch = ash::vk::SubmitInfo2KHR {
+                p_next: keyed_mutex_info
+                    .as_ref()
+                    .map_or(ptr::null(), |info| info as *const _ as *const _),
+                ..batch
+            };
+
+            check_errors(
+                fns.khr_synchronization2
+                    .queue_submit2_khr(*queue, 1, &batch, self.fence),
+            )?;
+            Ok(())
+        }
+    }
+
     /// Merges this builder with another builder.
     ///
     /// # Panic
@@ -234,7 +467,14 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
         self.wait_semaphores.extend(other.wait_semaphores);
         self.destination_stages.extend(other.destination_stages); // TODO: meh? will be solved if we submit multiple batches
         self.signal_semaphores.extend(other.signal_semaphores);
+        self.wait_semaphore_values
+            .extend(other.wait_semaphore_values);
+        self.signal_semaphore_values
+            .extend(other.signal_semaphore_values);
+        self.has_timeline_semaphores |= other.has_timeline_semaphores;
         self.command_buffers.extend(other.command_buffers);
+        self.keyed_mutex_acquires.extend(other.keyed_mutex_acquires);
+        self.keyed_mutex_releases.extend(other.keyed_mutex_releases);
 
         if self.fence == ash::vk::Fence::null() {
             self.fence = other.fence;
@@ -244,6 +484,49 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     }
 }
 
+impl<'a> SubmitCommandBufferBuilder<'a> {
+    // Splits the keyed mutex operations into the arrays expected by Vulkan.
+    fn keyed_mutexes(&self) -> KeyedMutexes {
+        KeyedMutexes {
+            acquire_syncs: self.keyed_mutex_acquires.iter().map(|a| a.0).collect(),
+            acquire_keys: self.keyed_mutex_acquires.iter().map(|a| a.1).collect(),
+            acquire_timeouts: self.keyed_mutex_acquires.iter().map(|a| a.2).collect(),
+            release_syncs: self.keyed_mutex_releases.iter().map(|r| r.0).collect(),
+            release_keys: self.keyed_mutex_releases.iter().map(|r| r.1).collect(),
+        }
+    }
+}
+
+// Keyed mutex operations of a submission, that must outlive the
+// `VkWin32KeyedMutexAcquireReleaseInfoKHR` that points to them.
+struct KeyedMutexes {
+    acquire_syncs: SmallVec<[ash::vk::DeviceMemory; 2]>,
+    acquire_keys: SmallVec<[u64; 2]>,
+    acquire_timeouts: SmallVec<[u32; 2]>,
+    release_syncs: SmallVec<[ash::vk::DeviceMemory; 2]>,
+    release_keys: SmallVec<[u64; 2]>,
+}
+
+impl KeyedMutexes {
+    // Returns `None` if there is no keyed mutex to acquire or release.
+    fn info(&self) -> Option<ash::vk::Win32KeyedMutexAcquireReleaseInfoKHR> {
+        if self.acquire_syncs.is_empty() && self.release_syncs.is_empty() {
+            return None;
+        }
+
+        Some(ash::vk::Win32KeyedMutexAcquireReleaseInfoKHR {
+            acquire_count: self.acquire_syncs.len() as u32,
+            p_acquire_syncs: self.acquire_syncs.as_ptr(),
+            p_acquire_keys: self.acquire_keys.as_ptr(),
+            p_acquire_timeouts: self.acquire_timeouts.as_ptr(),
+            release_count: self.release_syncs.len() as u32,
+            p_release_syncs: self.release_syncs.as_ptr(),
+            p_release_keys: self.release_keys.as_ptr(),
+            ..Default::default()
+        })
+    }
+}
+
 /// Error that can happen when submitting the prototype.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(u32)]
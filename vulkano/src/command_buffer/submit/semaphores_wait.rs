@@ -9,6 +9,7 @@
 
 use smallvec::SmallVec;
 
+use crate::command_buffer::submit::SubmitBindSparseBatchBuilder;
 use crate::command_buffer::submit::SubmitCommandBufferBuilder;
 use crate::command_buffer::submit::SubmitPresentBuilder;
 use crate::sync::PipelineStages;
@@ -67,6 +68,19 @@ impl<'a> Into<SubmitCommandBufferBuilder<'a>> for SubmitSemaphoresWaitBuilder<'a
     }
 }
 
+impl<'a> Into<SubmitBindSparseBatchBuilder<'a>> for SubmitSemaphoresWaitBuilder<'a> {
+    #[inline]
+    fn into(mut self) -> SubmitBindSparseBatchBuilder<'a> {
+        unsafe {
+            let mut builder = SubmitBindSparseBatchBuilder::new();
+            for sem in self.semaphores.drain(..) {
+                builder.add_wait_semaphore(sem);
+            }
+            builder
+        }
+    }
+}
+
 impl<'a> Into<SubmitPresentBuilder<'a>> for SubmitSemaphoresWaitBuilder<'a> {
     #[inline]
     fn into(mut self) -> SubmitPresentBuilder<'a> {
@@ -10,7 +10,12 @@
 pub use self::commands::SyncCommandBufferBuilderBindDescriptorSets;
 pub use self::commands::SyncCommandBufferBuilderBindVertexBuffer;
 pub use self::commands::SyncCommandBufferBuilderExecuteCommands;
-use super::{Command, KeyTy, ResourceFinalState, ResourceKey, ResourceUse, SyncCommandBuffer};
+use super::{
+    Command, KeyTy, ResourceDependency, ResourceFinalState, ResourceKey, ResourceUse,
+    SyncCommandBuffer, SyncGraph,
+};
+#[cfg(feature = "command_introspection")]
+use super::{RecordedCommand, RecordedResource, RecordedResourceUse};
 use crate::buffer::BufferAccess;
 use crate::command_buffer::pool::UnsafeCommandPoolAlloc;
 use crate::command_buffer::sys::UnsafeCommandBufferBuilder;
@@ -29,6 +34,7 @@ use crate::pipeline::color_blend::LogicOp;
 use crate::pipeline::depth_stencil::CompareOp;
 use crate::pipeline::depth_stencil::StencilOp;
 use crate::pipeline::depth_stencil::StencilOps;
+use crate::pipeline::fragment_shading_rate::FragmentShadingRate;
 use crate::pipeline::input_assembly::IndexType;
 use crate::pipeline::input_assembly::PrimitiveTopology;
 use crate::pipeline::layout::PipelineLayout;
@@ -36,6 +42,7 @@ use crate::pipeline::rasterization::CullMode;
 use crate::pipeline::rasterization::DepthBias;
 use crate::pipeline::rasterization::FrontFace;
 use crate::pipeline::rasterization::LineStipple;
+use crate::pipeline::vertex::VertexInput;
 use crate::pipeline::viewport::Scissor;
 use crate::pipeline::viewport::Viewport;
 use crate::pipeline::ComputePipeline;
@@ -90,6 +97,14 @@ pub struct SyncCommandBufferBuilder {
     // TODO: present only in cfg(debug_assertions)?
     barriers: Vec<usize>,
 
+    // Dependencies between uses of a resource that a barrier was added for. For debugging
+    // purposes.
+    dependencies: Vec<ResourceDependency>,
+
+    // Description of every command, with the state when it was recorded.
+    #[cfg(feature = "command_introspection")]
+    recorded_commands: Vec<RecordedCommand>,
+
     // Only the commands before `first_unflushed` have already been sent to the inner
     // `UnsafeCommandBufferBuilder`.
     first_unflushed: usize,
@@ -152,6 +167,58 @@ impl SyncCommandBufferBuilder {
         ))
     }
 
+    /// Starts recording `command_buffer` again, reusing the memory that was allocated to track
+    /// its commands and resources. The resources that were used by `command_buffer` are released.
+    ///
+    /// # Safety
+    ///
+    /// See `UnsafeCommandBufferBuilder::new()`.
+    ///
+    /// In addition to this, `command_buffer` must have been recorded into `pool_alloc`, and the
+    /// pool that `pool_alloc` was allocated from must have been created with `reset_cb` set to
+    /// true.
+    pub unsafe fn reset(
+        command_buffer: SyncCommandBuffer,
+        pool_alloc: &UnsafeCommandPoolAlloc,
+        level: CommandBufferLevel,
+        usage: CommandBufferUsage,
+    ) -> Result<SyncCommandBufferBuilder, OomError> {
+        let SyncCommandBuffer {
+            inner,
+            mut commands,
+            mut barriers,
+            mut dependencies,
+            #[cfg(feature = "command_introspection")]
+            mut recorded_commands,
+            resources,
+            mut buffers,
+            mut images,
+        } = command_buffer;
+        debug_assert_eq!(inner.internal_object(), pool_alloc.internal_object());
+
+        commands.clear();
+        barriers.clear();
+        dependencies.clear();
+        #[cfg(feature = "command_introspection")]
+        recorded_commands.clear();
+        buffers.clear();
+        images.clear();
+
+        // Beginning the command buffer implicitly resets it.
+        let mut builder = SyncCommandBufferBuilder::new(pool_alloc, level, usage)?;
+        builder.commands = commands;
+        builder.barriers = barriers;
+        builder.dependencies = dependencies;
+        #[cfg(feature = "command_introspection")]
+        {
+            builder.recorded_commands = recorded_commands;
+        }
+        builder.resources.reserve(resources.len());
+        builder.buffers = buffers;
+        builder.images = images;
+        Ok(builder)
+    }
+
     /// Builds a `SyncCommandBufferBuilder` from an existing `UnsafeCommandBufferBuilder`.
     ///
     /// # Safety
@@ -174,6 +241,9 @@ impl SyncCommandBufferBuilder {
             commands: Vec::new(),
             pending_barrier: UnsafeCommandBufferBuilderPipelineBarrier::new(),
             barriers: Vec::new(),
+            dependencies: Vec::new(),
+            #[cfg(feature = "command_introspection")]
+            recorded_commands: Vec::new(),
             first_unflushed: 0,
             latest_render_pass_enter,
             resources: FnvHashMap::default(),
@@ -202,6 +272,26 @@ impl SyncCommandBufferBuilder {
         self.current_state = Default::default();
     }
 
+    /// Returns the dependencies that were computed for the commands recorded so far.
+    ///
+    /// The final layout transitions of images are only added when the command buffer is built,
+    /// and are part of the graph returned by `SyncCommandBuffer::sync_graph`.
+    pub fn sync_graph(&self) -> SyncGraph {
+        SyncGraph::new(
+            &self.commands,
+            self.resources.iter().map(|(key, state)| {
+                (
+                    key,
+                    state.resource_uses.as_slice(),
+                    state.initial_layout,
+                    state.current_layout,
+                )
+            }),
+            &self.dependencies,
+            &self.barriers,
+        )
+    }
+
     // Adds a command to be processed by the builder.
     //
     // The `resources` argument should contain each buffer or image used by the command.
@@ -247,8 +337,25 @@ impl SyncCommandBufferBuilder {
             (latest_command_id, end)
         };
 
+        #[cfg(feature = "command_introspection")]
+        let mut recorded_command = self
+            .current_state
+            .recorded_command(self.commands[latest_command_id].name());
+
         for (resource_ty, resource_name, resource) in resources {
             if let Some((memory, start_layout, end_layout, image_uninitialized_safe)) = resource {
+                #[cfg(feature = "command_introspection")]
+                recorded_command.resources.push(RecordedResourceUse {
+                    resource: match &resource_ty {
+                        KeyTy::Buffer(buffer) => RecordedResource::Buffer(buffer.clone()),
+                        KeyTy::Image(image) => RecordedResource::Image(image.clone()),
+                    },
+                    parameter: resource_name.clone(),
+                    memory,
+                    start_layout,
+                    end_layout,
+                });
+
                 // Anti-dumbness checks.
                 debug_assert!(memory.exclusive || start_layout == end_layout);
                 debug_assert!(memory.access.is_compatible_with(&memory.stages));
@@ -279,10 +386,20 @@ impl SyncCommandBufferBuilder {
                             .iter()
                             .all(|resource_use| resource_use.command_index <= latest_command_id));
 
+                        // A read doesn't need a barrier if the previous use didn't write, and was
+                        // already the destination of a barrier for the same stages and accesses.
+                        // This happens for reads that follow a layout transition.
+                        let already_synchronized = !memory.exclusive
+                            && entry.get().current_layout == start_layout
+                            && !entry.get().memory.access.has_writes()
+                            && entry.get().memory.stages.contains(&memory.stages)
+                            && entry.get().memory.access.contains(&memory.access);
+
                         // Find out if we have a collision with the pending commands.
-                        if memory.exclusive
-                            || entry.get().memory.exclusive
-                            || entry.get().current_layout != start_layout
+                        if !already_synchronized
+                            && (memory.exclusive
+                                || entry.get().memory.exclusive
+                                || entry.get().current_layout != start_layout)
                         {
                             // Collision found between `latest_command_id` and `collision_cmd_id`.
 
@@ -337,8 +454,25 @@ impl SyncCommandBufferBuilder {
                                 }
                             }
 
+                            let resource_key = entry.key().clone();
                             let entry = entry.into_mut();
 
+                            self.dependencies.push(ResourceDependency {
+                                resource: resource_key,
+                                source_command: entry
+                                    .resource_uses
+                                    .last()
+                                    .map(|resource_use| resource_use.command_index),
+                                destination_command: Some(latest_command_id),
+                                barrier_position: self.first_unflushed,
+                                source_stages: entry.memory.stages,
+                                source_access: entry.memory.access,
+                                destination_stages: memory.stages,
+                                destination_access: memory.access,
+                                old_layout: entry.current_layout,
+                                new_layout: start_layout,
+                            });
+
                             // Modify the pipeline barrier to handle the collision.
                             unsafe {
                                 match &resource_ty {
@@ -445,6 +579,21 @@ impl SyncCommandBufferBuilder {
                                         if initial_layout_requirement != start_layout {
                                             actual_start_layout = initial_layout_requirement;
                                         }
+                                        self.dependencies.push(ResourceDependency {
+                                            resource: entry.key().clone(),
+                                            source_command: None,
+                                            destination_command: Some(latest_command_id),
+                                            barrier_position: self.first_unflushed,
+                                            source_stages: PipelineStages {
+                                                bottom_of_pipe: true,
+                                                ..PipelineStages::none()
+                                            },
+                                            source_access: AccessFlags::none(),
+                                            destination_stages: memory.stages,
+                                            destination_access: memory.access,
+                                            old_layout: from_layout,
+                                            new_layout: start_layout,
+                                        });
                                         let b = &mut self.pending_barrier;
                                         b.add_image_memory_barrier(
                                             image.as_ref(),
@@ -509,6 +658,9 @@ impl SyncCommandBufferBuilder {
             }
         }
 
+        #[cfg(feature = "command_introspection")]
+        self.recorded_commands.push(recorded_command);
+
         Ok(())
     }
 
@@ -570,6 +722,25 @@ impl SyncCommandBufferBuilder {
                         requested_layout,
                     );
 
+                    self.dependencies.push(ResourceDependency {
+                        resource: key.clone(),
+                        source_command: state
+                            .resource_uses
+                            .last()
+                            .map(|resource_use| resource_use.command_index),
+                        destination_command: None,
+                        barrier_position: self.commands.len(),
+                        source_stages: state.memory.stages,
+                        source_access: state.memory.access,
+                        destination_stages: PipelineStages {
+                            top_of_pipe: true,
+                            ..PipelineStages::none()
+                        },
+                        destination_access: AccessFlags::none(),
+                        old_layout: state.current_layout,
+                        new_layout: requested_layout,
+                    });
+
                     state.exclusive_any = true;
                     state.current_layout = requested_layout;
                 }
@@ -604,6 +775,9 @@ impl SyncCommandBufferBuilder {
             resources: final_resources_states,
             commands: self.commands,
             barriers: self.barriers,
+            dependencies: self.dependencies,
+            #[cfg(feature = "command_introspection")]
+            recorded_commands: self.recorded_commands,
         })
     }
 }
@@ -705,6 +879,7 @@ struct CurrentState {
     depth_test_enable: Option<bool>,
     depth_write_enable: Option<bool>,
     discard_rectangle: FnvHashMap<u32, Scissor>,
+    fragment_shading_rate: Option<FragmentShadingRate>,
     front_face: Option<FrontFace>,
     line_stipple: Option<LineStipple>,
     line_width: Option<f32>,
@@ -720,11 +895,36 @@ struct CurrentState {
     stencil_reference: StencilStateDynamic,
     stencil_test_enable: Option<bool>,
     stencil_write_mask: StencilStateDynamic,
+    vertex_input: Option<VertexInput>,
     viewport: FnvHashMap<u32, Viewport>,
     viewport_with_count: Option<SmallVec<[Viewport; 2]>>,
 }
 
 impl CurrentState {
+    // Returns a description of a command that is recorded with this state, without resources.
+    #[cfg(feature = "command_introspection")]
+    fn recorded_command(&self, name: &'static str) -> RecordedCommand {
+        let mut descriptor_sets: Vec<_> = self
+            .descriptor_sets
+            .iter()
+            .flat_map(|(&bind_point, state)| {
+                state
+                    .descriptor_sets
+                    .iter()
+                    .map(move |(&set_num, set)| (bind_point, set_num, set.clone()))
+            })
+            .collect();
+        descriptor_sets.sort_by_key(|&(bind_point, set_num, _)| (bind_point as i32, set_num));
+
+        RecordedCommand {
+            name,
+            resources: Vec::new(),
+            pipeline_compute: self.pipeline_compute.clone(),
+            pipeline_graphics: self.pipeline_graphics.clone(),
+            descriptor_sets,
+        }
+    }
+
     fn reset_dynamic_states(&mut self, states: impl IntoIterator<Item = DynamicState>) {
         for state in states {
             match state {
@@ -740,7 +940,7 @@ impl CurrentState {
                 DynamicState::DepthWriteEnable => self.depth_write_enable = None,
                 DynamicState::DiscardRectangle => self.discard_rectangle.clear(),
                 DynamicState::ExclusiveScissor => (), // TODO;
-                DynamicState::FragmentShadingRate => (), // TODO:
+                DynamicState::FragmentShadingRate => self.fragment_shading_rate = None,
                 DynamicState::FrontFace => self.front_face = None,
                 DynamicState::LineStipple => self.line_stipple = None,
                 DynamicState::LineWidth => self.line_width = None,
@@ -758,7 +958,7 @@ impl CurrentState {
                 DynamicState::StencilReference => self.stencil_reference = Default::default(),
                 DynamicState::StencilTestEnable => self.stencil_test_enable = None,
                 DynamicState::StencilWriteMask => self.stencil_write_mask = Default::default(),
-                DynamicState::VertexInput => (), // TODO:
+                DynamicState::VertexInput => self.vertex_input = None,
                 DynamicState::VertexInputBindingStride => (), // TODO:
                 DynamicState::Viewport => self.viewport.clear(),
                 DynamicState::ViewportCoarseSampleOrder => (), // TODO:
@@ -995,6 +1195,12 @@ impl<'a> CommandBufferState<'a> {
         self.current_state.discard_rectangle.get(&num)
     }
 
+    /// Returns the current fragment shading rate, or `None` if nothing has been set yet.
+    #[inline]
+    pub fn fragment_shading_rate(&self) -> Option<FragmentShadingRate> {
+        self.current_state.fragment_shading_rate
+    }
+
     /// Returns the current front face, or `None` if nothing has been set yet.
     #[inline]
     pub fn front_face(&self) -> Option<FrontFace> {
@@ -1088,6 +1294,29 @@ impl<'a> CommandBufferState<'a> {
         self.current_state.stencil_write_mask
     }
 
+    /// Returns the current dynamic vertex input, or `None` if nothing has been set yet.
+    #[inline]
+    pub fn vertex_input(&self) -> Option<&'a VertexInput> {
+        self.current_state.vertex_input.as_ref()
+    }
+
+    /// Returns the vertex input that draw commands use with `pipeline`: the dynamic vertex input
+    /// if the pipeline has a dynamic vertex input, and the vertex input of the pipeline
+    /// otherwise.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the pipeline has a dynamic vertex input, but it has not been set.
+    #[inline]
+    pub fn vertex_input_for(&self, pipeline: &'a GraphicsPipeline) -> &'a VertexInput {
+        if pipeline.dynamic_state(DynamicState::VertexInput) == Some(true) {
+            self.vertex_input()
+                .expect("the dynamic vertex input has not been set")
+        } else {
+            pipeline.vertex_input()
+        }
+    }
+
     /// Returns the current viewport for a given viewport slot, or `None` if nothing has been set yet.
     #[inline]
     pub fn viewport(&self, num: u32) -> Option<&'a Viewport> {
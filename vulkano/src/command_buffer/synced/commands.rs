@@ -21,18 +21,31 @@ use crate::command_buffer::sys::UnsafeCommandBufferBuilderDepthStencilImageClear
 use crate::command_buffer::sys::UnsafeCommandBufferBuilderExecuteCommands;
 use crate::command_buffer::sys::UnsafeCommandBufferBuilderImageBlit;
 use crate::command_buffer::sys::UnsafeCommandBufferBuilderImageCopy;
+use crate::command_buffer::sys::UnsafeCommandBufferBuilderImageResolve;
+use crate::command_buffer::sys::UnsafeCommandBufferBuilderPipelineBarrier;
+use crate::command_buffer::BufferMemoryBarrier;
+use crate::command_buffer::ClearAttachment;
+use crate::command_buffer::ClearRect;
 use crate::command_buffer::CommandBufferExecError;
+use crate::command_buffer::ConditionalRenderingFlags;
+use crate::command_buffer::ImageMemoryBarrier;
 use crate::command_buffer::ImageUninitializedSafe;
+use crate::command_buffer::MemoryBarrier;
+use crate::command_buffer::MultiDrawCommand;
+use crate::command_buffer::MultiDrawIndexedCommand;
 use crate::command_buffer::SecondaryCommandBuffer;
 use crate::command_buffer::SubpassContents;
 use crate::descriptor_set::builder::DescriptorSetBuilderOutput;
 use crate::descriptor_set::layout::DescriptorType;
 use crate::descriptor_set::DescriptorBindingResources;
+use crate::descriptor_set::DescriptorResource;
 use crate::descriptor_set::DescriptorSetWithOffsets;
 use crate::format::ClearValue;
 use crate::image::ImageAccess;
 use crate::image::ImageLayout;
 use crate::pipeline::depth_stencil::StencilFaces;
+use crate::pipeline::fragment_shading_rate::FragmentShadingRate;
+use crate::pipeline::fragment_shading_rate::FragmentShadingRateCombinerOp;
 use crate::pipeline::input_assembly::IndexType;
 use crate::pipeline::layout::PipelineLayout;
 use crate::pipeline::vertex::VertexInput;
@@ -60,13 +73,65 @@ use crate::SafeDeref;
 use crate::VulkanObject;
 use smallvec::SmallVec;
 use std::borrow::Cow;
-use std::ffi::CStr;
+use std::ffi::CString;
 use std::mem;
 use std::ops::Range;
 use std::ptr;
 use std::sync::{Arc, Mutex};
 
 impl SyncCommandBufferBuilder {
+    /// Calls `vkCmdBeginConditionalRenderingEXT` on the builder.
+    #[inline]
+    pub unsafe fn begin_conditional_rendering(
+        &mut self,
+        buffer: Arc<dyn BufferAccess>,
+        flags: ConditionalRenderingFlags,
+    ) -> Result<(), SyncCommandBufferBuilderError> {
+        struct Cmd {
+            buffer: Arc<dyn BufferAccess>,
+            flags: ConditionalRenderingFlags,
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdBeginConditionalRenderingEXT"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.begin_conditional_rendering(self.buffer.as_ref(), self.flags);
+            }
+        }
+
+        self.append_command(
+            Cmd {
+                buffer: buffer.clone(),
+                flags,
+            },
+            [(
+                KeyTy::Buffer(buffer),
+                "conditional rendering buffer".into(),
+                Some((
+                    PipelineMemoryAccess {
+                        stages: PipelineStages {
+                            conditional_rendering: true,
+                            ..PipelineStages::none()
+                        },
+                        access: AccessFlags {
+                            conditional_rendering_read: true,
+                            ..AccessFlags::none()
+                        },
+                        exclusive: false,
+                    },
+                    ImageLayout::Undefined,
+                    ImageLayout::Undefined,
+                    ImageUninitializedSafe::Unsafe,
+                )),
+            )],
+        )?;
+
+        Ok(())
+    }
+
     /// Calls `vkCmdBeginQuery` on the builder.
     #[inline]
     pub unsafe fn begin_query(
@@ -139,32 +204,55 @@ impl SyncCommandBufferBuilder {
             }
         }
 
-        let resources = framebuffer
-            .render_pass()
-            .desc()
+        let render_pass_desc = framebuffer.render_pass().desc();
+        let resources = render_pass_desc
             .attachments()
             .iter()
             .enumerate()
             .map(|(num, desc)| {
+                // The fragment shading rate attachment is only read, before rasterization.
+                let is_fragment_shading_rate_attachment =
+                    render_pass_desc.subpasses().iter().any(|subpass| {
+                        subpass
+                            .fragment_shading_rate_attachment
+                            .map_or(false, |attachment| attachment.attachment == num)
+                    });
+
+                let memory = if is_fragment_shading_rate_attachment {
+                    PipelineMemoryAccess {
+                        stages: PipelineStages {
+                            fragment_shading_rate_attachment: true,
+                            ..PipelineStages::none()
+                        },
+                        access: AccessFlags {
+                            fragment_shading_rate_attachment_read: true,
+                            ..AccessFlags::none()
+                        },
+                        exclusive: desc.initial_layout != desc.final_layout,
+                    }
+                } else {
+                    PipelineMemoryAccess {
+                        stages: PipelineStages {
+                            all_commands: true,
+                            ..PipelineStages::none()
+                        }, // TODO: wrong!
+                        access: AccessFlags {
+                            input_attachment_read: true,
+                            color_attachment_read: true,
+                            color_attachment_write: true,
+                            depth_stencil_attachment_read: true,
+                            depth_stencil_attachment_write: true,
+                            ..AccessFlags::none()
+                        }, // TODO: suboptimal
+                        exclusive: true, // TODO: suboptimal ; note: remember to always pass true if desc.initial_layout != desc.final_layout
+                    }
+                };
+
                 (
                     KeyTy::Image(framebuffer.attached_image_view(num).unwrap().image()),
                     format!("attachment {}", num).into(),
                     Some((
-                        PipelineMemoryAccess {
-                            stages: PipelineStages {
-                                all_commands: true,
-                                ..PipelineStages::none()
-                            }, // TODO: wrong!
-                            access: AccessFlags {
-                                input_attachment_read: true,
-                                color_attachment_read: true,
-                                color_attachment_write: true,
-                                depth_stencil_attachment_read: true,
-                                depth_stencil_attachment_write: true,
-                                ..AccessFlags::none()
-                            }, // TODO: suboptimal
-                            exclusive: true, // TODO: suboptimal ; note: remember to always pass true if desc.initial_layout != desc.final_layout
-                        },
+                        memory,
                         desc.initial_layout,
                         desc.final_layout,
                         match desc.initial_layout != ImageLayout::Undefined
@@ -191,6 +279,62 @@ impl SyncCommandBufferBuilder {
         Ok(())
     }
 
+    /// Calls `vkCmdBeginTransformFeedbackEXT` on the builder.
+    #[inline]
+    pub unsafe fn begin_transform_feedback(
+        &mut self,
+        counter_buffers: SmallVec<[Option<Arc<dyn BufferAccess>>; 4]>,
+    ) -> Result<(), SyncCommandBufferBuilderError> {
+        struct Cmd {
+            counter_buffers: SmallVec<[Option<Arc<dyn BufferAccess>>; 4]>,
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdBeginTransformFeedbackEXT"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.begin_transform_feedback(&self.counter_buffers);
+            }
+        }
+
+        // The counter buffers are also written by `end_transform_feedback`, but as both commands
+        // are in the same render pass, the accesses are all registered here.
+        let resources: Vec<_> = counter_buffers
+            .iter()
+            .enumerate()
+            .filter_map(|(binding, counter_buffer)| Some((binding, counter_buffer.clone()?)))
+            .map(|(binding, counter_buffer)| {
+                (
+                    KeyTy::Buffer(counter_buffer),
+                    format!("transform feedback counter buffer {}", binding).into(),
+                    Some((
+                        PipelineMemoryAccess {
+                            stages: PipelineStages {
+                                transform_feedback: true,
+                                ..PipelineStages::none()
+                            },
+                            access: AccessFlags {
+                                transform_feedback_counter_read: true,
+                                transform_feedback_counter_write: true,
+                                ..AccessFlags::none()
+                            },
+                            exclusive: true,
+                        },
+                        ImageLayout::Undefined,
+                        ImageLayout::Undefined,
+                        ImageUninitializedSafe::Unsafe,
+                    )),
+                )
+            })
+            .collect();
+
+        self.append_command(Cmd { counter_buffers }, resources)?;
+
+        Ok(())
+    }
+
     /// Starts the process of binding descriptor sets. Returns an intermediate struct which can be
     /// used to add the sets.
     #[inline]
@@ -273,6 +417,68 @@ impl SyncCommandBufferBuilder {
         self.append_command(Cmd { pipeline }, []).unwrap();
     }
 
+    /// Calls `vkCmdBindTransformFeedbackBuffersEXT` on the builder.
+    #[inline]
+    pub unsafe fn bind_transform_feedback_buffers(
+        &mut self,
+        first_binding: u32,
+        buffers: SmallVec<[Arc<dyn BufferAccess>; 4]>,
+    ) -> Result<(), SyncCommandBufferBuilderError> {
+        struct Cmd {
+            first_binding: u32,
+            buffers: SmallVec<[Arc<dyn BufferAccess>; 4]>,
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdBindTransformFeedbackBuffersEXT"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.bind_transform_feedback_buffers(self.first_binding, &self.buffers);
+            }
+        }
+
+        // The buffers are written by the draw commands between `begin_transform_feedback` and
+        // `end_transform_feedback`, but the accesses are registered when binding them.
+        let resources: Vec<_> = buffers
+            .iter()
+            .enumerate()
+            .map(|(num, buffer)| {
+                (
+                    KeyTy::Buffer(buffer.clone()),
+                    format!("transform feedback buffer {}", first_binding as usize + num).into(),
+                    Some((
+                        PipelineMemoryAccess {
+                            stages: PipelineStages {
+                                transform_feedback: true,
+                                ..PipelineStages::none()
+                            },
+                            access: AccessFlags {
+                                transform_feedback_write: true,
+                                ..AccessFlags::none()
+                            },
+                            exclusive: true,
+                        },
+                        ImageLayout::Undefined,
+                        ImageLayout::Undefined,
+                        ImageUninitializedSafe::Unsafe,
+                    )),
+                )
+            })
+            .collect();
+
+        self.append_command(
+            Cmd {
+                first_binding,
+                buffers,
+            },
+            resources,
+        )?;
+
+        Ok(())
+    }
+
     /// Starts the process of binding vertex buffers. Returns an intermediate struct which can be
     /// used to add the buffers.
     #[inline]
@@ -484,6 +690,49 @@ impl SyncCommandBufferBuilder {
         Ok(())
     }
 
+    /// Calls `vkCmdClearAttachments` on the builder.
+    ///
+    /// Does nothing if the list of attachments or the list of rects is empty, as it would be a
+    /// no-op and isn't a valid usage of the command anyway.
+    #[inline]
+    pub unsafe fn clear_attachments<A, R>(&mut self, attachments: A, rects: R)
+    where
+        A: IntoIterator<Item = ClearAttachment> + Send + Sync + 'static,
+        R: IntoIterator<Item = ClearRect> + Send + Sync + 'static,
+    {
+        struct Cmd<A, R> {
+            attachments: Mutex<Option<A>>,
+            rects: Mutex<Option<R>>,
+        }
+
+        impl<A, R> Command for Cmd<A, R>
+        where
+            A: IntoIterator<Item = ClearAttachment> + Send + Sync,
+            R: IntoIterator<Item = ClearRect> + Send + Sync,
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdClearAttachments"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.clear_attachments(
+                    self.attachments.lock().unwrap().take().unwrap(),
+                    self.rects.lock().unwrap().take().unwrap(),
+                );
+            }
+        }
+
+        // The attachments are already synchronized by the render pass.
+        self.append_command(
+            Cmd {
+                attachments: Mutex::new(Some(attachments)),
+                rects: Mutex::new(Some(rects)),
+            },
+            [],
+        )
+        .unwrap();
+    }
+
     /// Calls `vkCmdClearColorImage` on the builder.
     ///
     /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
@@ -995,9 +1244,9 @@ impl SyncCommandBufferBuilder {
     /// The command pool that this command buffer was allocated from must support graphics or
     /// compute operations
     #[inline]
-    pub unsafe fn debug_marker_begin(&mut self, name: &'static CStr, color: [f32; 4]) {
+    pub unsafe fn begin_debug_utils_label(&mut self, name: CString, color: [f32; 4]) {
         struct Cmd {
-            name: &'static CStr,
+            name: CString,
             color: [f32; 4],
         }
 
@@ -1007,7 +1256,7 @@ impl SyncCommandBufferBuilder {
             }
 
             unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
-                out.debug_marker_begin(self.name, self.color);
+                out.begin_debug_utils_label(&self.name, self.color);
             }
         }
 
@@ -1019,10 +1268,10 @@ impl SyncCommandBufferBuilder {
     /// # Safety
     /// - The command pool that this command buffer was allocated from must support graphics or
     /// compute operations
-    /// - There must be an outstanding `debug_marker_begin` command prior to the
-    /// `debug_marker_end` on the queue.
+    /// - There must be an outstanding `begin_debug_utils_label` command prior to the
+    /// `end_debug_utils_label` on the queue.
     #[inline]
-    pub unsafe fn debug_marker_end(&mut self) {
+    pub unsafe fn end_debug_utils_label(&mut self) {
         struct Cmd {}
 
         impl Command for Cmd {
@@ -1031,7 +1280,7 @@ impl SyncCommandBufferBuilder {
             }
 
             unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
-                out.debug_marker_end();
+                out.end_debug_utils_label();
             }
         }
 
@@ -1044,9 +1293,9 @@ impl SyncCommandBufferBuilder {
     /// The command pool that this command buffer was allocated from must support graphics or
     /// compute operations
     #[inline]
-    pub unsafe fn debug_marker_insert(&mut self, name: &'static CStr, color: [f32; 4]) {
+    pub unsafe fn insert_debug_utils_label(&mut self, name: CString, color: [f32; 4]) {
         struct Cmd {
-            name: &'static CStr,
+            name: CString,
             color: [f32; 4],
         }
 
@@ -1056,7 +1305,7 @@ impl SyncCommandBufferBuilder {
             }
 
             unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
-                out.debug_marker_insert(self.name, self.color);
+                out.insert_debug_utils_label(&self.name, self.color);
             }
         }
 
@@ -1093,6 +1342,43 @@ impl SyncCommandBufferBuilder {
             .unwrap();
     }
 
+    /// Calls `vkCmdDispatchBase` on the builder.
+    #[inline]
+    pub unsafe fn dispatch_base(&mut self, base_group: [u32; 3], group_counts: [u32; 3]) {
+        struct Cmd {
+            base_group: [u32; 3],
+            group_counts: [u32; 3],
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdDispatchBase"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.dispatch_base(self.base_group, self.group_counts);
+            }
+        }
+
+        let pipeline = self.current_state.pipeline_compute.as_ref().unwrap();
+
+        let mut resources = Vec::new();
+        self.add_descriptor_set_resources(
+            &mut resources,
+            PipelineBindPoint::Compute,
+            pipeline.descriptor_requirements(),
+        );
+
+        self.append_command(
+            Cmd {
+                base_group,
+                group_counts,
+            },
+            resources,
+        )
+        .unwrap();
+    }
+
     /// Calls `vkCmdDispatchIndirect` on the builder.
     #[inline]
     pub unsafe fn dispatch_indirect(
@@ -1167,7 +1453,7 @@ impl SyncCommandBufferBuilder {
             PipelineBindPoint::Graphics,
             pipeline.descriptor_requirements(),
         );
-        self.add_vertex_buffer_resources(&mut resources, pipeline.vertex_input());
+        self.add_vertex_buffer_resources(&mut resources, pipeline);
 
         self.append_command(
             Cmd {
@@ -1223,7 +1509,7 @@ impl SyncCommandBufferBuilder {
             PipelineBindPoint::Graphics,
             pipeline.descriptor_requirements(),
         );
-        self.add_vertex_buffer_resources(&mut resources, pipeline.vertex_input());
+        self.add_vertex_buffer_resources(&mut resources, pipeline);
         self.add_index_buffer_resources(&mut resources);
 
         self.append_command(
@@ -1239,6 +1525,97 @@ impl SyncCommandBufferBuilder {
         .unwrap();
     }
 
+    /// Calls `vkCmdDrawMultiEXT` on the builder.
+    #[inline]
+    pub unsafe fn draw_multi(
+        &mut self,
+        vertex_info: SmallVec<[MultiDrawCommand; 8]>,
+        instance_count: u32,
+        first_instance: u32,
+    ) {
+        struct Cmd {
+            vertex_info: SmallVec<[MultiDrawCommand; 8]>,
+            instance_count: u32,
+            first_instance: u32,
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdDrawMultiEXT"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.draw_multi(&self.vertex_info, self.instance_count, self.first_instance);
+            }
+        }
+
+        let pipeline = self.current_state.pipeline_graphics.as_ref().unwrap();
+
+        let mut resources = Vec::new();
+        self.add_descriptor_set_resources(
+            &mut resources,
+            PipelineBindPoint::Graphics,
+            pipeline.descriptor_requirements(),
+        );
+        self.add_vertex_buffer_resources(&mut resources, pipeline);
+
+        self.append_command(
+            Cmd {
+                vertex_info,
+                instance_count,
+                first_instance,
+            },
+            resources,
+        )
+        .unwrap();
+    }
+
+    /// Calls `vkCmdDrawMultiIndexedEXT` on the builder.
+    #[inline]
+    pub unsafe fn draw_multi_indexed(
+        &mut self,
+        index_info: SmallVec<[MultiDrawIndexedCommand; 8]>,
+        instance_count: u32,
+        first_instance: u32,
+    ) {
+        struct Cmd {
+            index_info: SmallVec<[MultiDrawIndexedCommand; 8]>,
+            instance_count: u32,
+            first_instance: u32,
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdDrawMultiIndexedEXT"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.draw_multi_indexed(&self.index_info, self.instance_count, self.first_instance);
+            }
+        }
+
+        let pipeline = self.current_state.pipeline_graphics.as_ref().unwrap();
+
+        let mut resources = Vec::new();
+        self.add_descriptor_set_resources(
+            &mut resources,
+            PipelineBindPoint::Graphics,
+            pipeline.descriptor_requirements(),
+        );
+        self.add_vertex_buffer_resources(&mut resources, pipeline);
+        self.add_index_buffer_resources(&mut resources);
+
+        self.append_command(
+            Cmd {
+                index_info,
+                instance_count,
+                first_instance,
+            },
+            resources,
+        )
+        .unwrap();
+    }
+
     /// Calls `vkCmdDrawIndirect` on the builder.
     #[inline]
     pub unsafe fn draw_indirect(
@@ -1271,7 +1648,193 @@ impl SyncCommandBufferBuilder {
             PipelineBindPoint::Graphics,
             pipeline.descriptor_requirements(),
         );
-        self.add_vertex_buffer_resources(&mut resources, pipeline.vertex_input());
+        self.add_vertex_buffer_resources(&mut resources, pipeline);
+        self.add_indirect_buffer_resources(&mut resources, indirect_buffer.clone());
+
+        self.append_command(
+            Cmd {
+                indirect_buffer,
+                draw_count,
+                stride,
+            },
+            resources,
+        )?;
+
+        Ok(())
+    }
+
+    /// Calls `vkCmdDrawIndirectCount` on the builder.
+    #[inline]
+    pub unsafe fn draw_indirect_count(
+        &mut self,
+        indirect_buffer: Arc<dyn BufferAccess>,
+        count_buffer: Arc<dyn BufferAccess>,
+        max_draw_count: u32,
+        stride: u32,
+    ) -> Result<(), SyncCommandBufferBuilderError> {
+        struct Cmd {
+            indirect_buffer: Arc<dyn BufferAccess>,
+            count_buffer: Arc<dyn BufferAccess>,
+            max_draw_count: u32,
+            stride: u32,
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdDrawIndirectCount"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.draw_indirect_count(
+                    self.indirect_buffer.as_ref(),
+                    self.count_buffer.as_ref(),
+                    self.max_draw_count,
+                    self.stride,
+                );
+            }
+        }
+
+        let pipeline = self.current_state.pipeline_graphics.as_ref().unwrap();
+
+        let mut resources = Vec::new();
+        self.add_descriptor_set_resources(
+            &mut resources,
+            PipelineBindPoint::Graphics,
+            pipeline.descriptor_requirements(),
+        );
+        self.add_vertex_buffer_resources(&mut resources, pipeline);
+        self.add_indirect_buffer_resources(&mut resources, indirect_buffer.clone());
+        self.add_indirect_buffer_resources(&mut resources, count_buffer.clone());
+
+        self.append_command(
+            Cmd {
+                indirect_buffer,
+                count_buffer,
+                max_draw_count,
+                stride,
+            },
+            resources,
+        )?;
+
+        Ok(())
+    }
+
+    /// Calls `vkCmdDrawIndirectByteCountEXT` on the builder.
+    #[inline]
+    pub unsafe fn draw_indirect_byte_count(
+        &mut self,
+        instance_count: u32,
+        first_instance: u32,
+        counter_buffer: Arc<dyn BufferAccess>,
+        counter_offset: u32,
+        vertex_stride: u32,
+    ) -> Result<(), SyncCommandBufferBuilderError> {
+        struct Cmd {
+            instance_count: u32,
+            first_instance: u32,
+            counter_buffer: Arc<dyn BufferAccess>,
+            counter_offset: u32,
+            vertex_stride: u32,
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdDrawIndirectByteCountEXT"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.draw_indirect_byte_count(
+                    self.instance_count,
+                    self.first_instance,
+                    self.counter_buffer.as_ref(),
+                    self.counter_offset,
+                    self.vertex_stride,
+                );
+            }
+        }
+
+        let pipeline = self.current_state.pipeline_graphics.as_ref().unwrap();
+
+        let mut resources = Vec::new();
+        self.add_descriptor_set_resources(
+            &mut resources,
+            PipelineBindPoint::Graphics,
+            pipeline.descriptor_requirements(),
+        );
+        self.add_vertex_buffer_resources(&mut resources, pipeline);
+        resources.push((
+            KeyTy::Buffer(counter_buffer.clone()),
+            "transform feedback counter buffer".into(),
+            Some((
+                PipelineMemoryAccess {
+                    stages: PipelineStages {
+                        draw_indirect: true,
+                        ..PipelineStages::none()
+                    },
+                    access: AccessFlags {
+                        transform_feedback_counter_read: true,
+                        ..AccessFlags::none()
+                    },
+                    exclusive: false,
+                },
+                ImageLayout::Undefined,
+                ImageLayout::Undefined,
+                ImageUninitializedSafe::Unsafe,
+            )),
+        ));
+
+        self.append_command(
+            Cmd {
+                instance_count,
+                first_instance,
+                counter_buffer,
+                counter_offset,
+                vertex_stride,
+            },
+            resources,
+        )?;
+
+        Ok(())
+    }
+
+    /// Calls `vkCmdDrawIndexedIndirect` on the builder.
+    #[inline]
+    pub unsafe fn draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: Arc<dyn BufferAccess>,
+        draw_count: u32,
+        stride: u32,
+    ) -> Result<(), SyncCommandBufferBuilderError> {
+        struct Cmd {
+            indirect_buffer: Arc<dyn BufferAccess>,
+            draw_count: u32,
+            stride: u32,
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdDrawIndexedIndirect"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.draw_indexed_indirect(
+                    self.indirect_buffer.as_ref(),
+                    self.draw_count,
+                    self.stride,
+                );
+            }
+        }
+
+        let pipeline = self.current_state.pipeline_graphics.as_ref().unwrap();
+
+        let mut resources = Vec::new();
+        self.add_descriptor_set_resources(
+            &mut resources,
+            PipelineBindPoint::Graphics,
+            pipeline.descriptor_requirements(),
+        );
+        self.add_vertex_buffer_resources(&mut resources, pipeline);
+        self.add_index_buffer_resources(&mut resources);
         self.add_indirect_buffer_resources(&mut resources, indirect_buffer.clone());
 
         self.append_command(
@@ -1286,29 +1849,32 @@ impl SyncCommandBufferBuilder {
         Ok(())
     }
 
-    /// Calls `vkCmdDrawIndexedIndirect` on the builder.
+    /// Calls `vkCmdDrawIndexedIndirectCount` on the builder.
     #[inline]
-    pub unsafe fn draw_indexed_indirect(
+    pub unsafe fn draw_indexed_indirect_count(
         &mut self,
         indirect_buffer: Arc<dyn BufferAccess>,
-        draw_count: u32,
+        count_buffer: Arc<dyn BufferAccess>,
+        max_draw_count: u32,
         stride: u32,
     ) -> Result<(), SyncCommandBufferBuilderError> {
         struct Cmd {
             indirect_buffer: Arc<dyn BufferAccess>,
-            draw_count: u32,
+            count_buffer: Arc<dyn BufferAccess>,
+            max_draw_count: u32,
             stride: u32,
         }
 
         impl Command for Cmd {
             fn name(&self) -> &'static str {
-                "vkCmdDrawIndexedIndirect"
+                "vkCmdDrawIndexedIndirectCount"
             }
 
             unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
-                out.draw_indexed_indirect(
+                out.draw_indexed_indirect_count(
                     self.indirect_buffer.as_ref(),
-                    self.draw_count,
+                    self.count_buffer.as_ref(),
+                    self.max_draw_count,
                     self.stride,
                 );
             }
@@ -1322,14 +1888,16 @@ impl SyncCommandBufferBuilder {
             PipelineBindPoint::Graphics,
             pipeline.descriptor_requirements(),
         );
-        self.add_vertex_buffer_resources(&mut resources, pipeline.vertex_input());
+        self.add_vertex_buffer_resources(&mut resources, pipeline);
         self.add_index_buffer_resources(&mut resources);
         self.add_indirect_buffer_resources(&mut resources, indirect_buffer.clone());
+        self.add_indirect_buffer_resources(&mut resources, count_buffer.clone());
 
         self.append_command(
             Cmd {
                 indirect_buffer,
-                draw_count,
+                count_buffer,
+                max_draw_count,
                 stride,
             },
             resources,
@@ -1338,6 +1906,24 @@ impl SyncCommandBufferBuilder {
         Ok(())
     }
 
+    /// Calls `vkCmdEndConditionalRenderingEXT` on the builder.
+    #[inline]
+    pub unsafe fn end_conditional_rendering(&mut self) {
+        struct Cmd;
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdEndConditionalRenderingEXT"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.end_conditional_rendering();
+            }
+        }
+
+        self.append_command(Cmd, []).unwrap();
+    }
+
     /// Calls `vkCmdEndQuery` on the builder.
     #[inline]
     pub unsafe fn end_query(&mut self, query_pool: Arc<QueryPool>, query: u32) {
@@ -1379,6 +1965,30 @@ impl SyncCommandBufferBuilder {
         self.latest_render_pass_enter = None;
     }
 
+    /// Calls `vkCmdEndTransformFeedbackEXT` on the builder.
+    #[inline]
+    pub unsafe fn end_transform_feedback(
+        &mut self,
+        counter_buffers: SmallVec<[Option<Arc<dyn BufferAccess>>; 4]>,
+    ) {
+        struct Cmd {
+            counter_buffers: SmallVec<[Option<Arc<dyn BufferAccess>>; 4]>,
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdEndTransformFeedbackEXT"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.end_transform_feedback(&self.counter_buffers);
+            }
+        }
+
+        // The accesses to the counter buffers were registered by `begin_transform_feedback`.
+        self.append_command(Cmd { counter_buffers }, []).unwrap();
+    }
+
     /// Starts the process of executing secondary command buffers. Returns an intermediate struct
     /// which can be used to add the command buffers.
     #[inline]
@@ -1456,6 +2066,188 @@ impl SyncCommandBufferBuilder {
         self.append_command(Cmd { subpass_contents }, []).unwrap();
     }
 
+    /// Adds explicit barriers to the builder.
+    ///
+    /// The buffers and images of the barriers are registered as being accessed with the
+    /// destination stages and accesses of their barrier, so the builder inserts the dependency
+    /// on their previous uses, and the images are transitioned to their new layout. The memory
+    /// barriers and the queue family ownership transfers are then recorded with
+    /// `vkCmdPipelineBarrier`. `queue_family_id` is the id of the queue family of the command
+    /// buffer, which determines whether a transfer releases or acquires the ownership.
+    ///
+    /// `by_region` must be true if the memory barriers are recorded inside a subpass whose
+    /// self-dependency is by region.
+    pub unsafe fn pipeline_barrier(
+        &mut self,
+        queue_family_id: u32,
+        by_region: bool,
+        memory_barriers: SmallVec<[MemoryBarrier; 2]>,
+        buffer_barriers: SmallVec<[BufferMemoryBarrier; 4]>,
+        image_barriers: SmallVec<[ImageMemoryBarrier; 4]>,
+    ) -> Result<(), SyncCommandBufferBuilderError> {
+        struct Cmd {
+            queue_family_id: u32,
+            by_region: bool,
+            memory_barriers: SmallVec<[MemoryBarrier; 2]>,
+            buffer_barriers: SmallVec<[BufferMemoryBarrier; 4]>,
+            image_barriers: SmallVec<[ImageMemoryBarrier; 4]>,
+        }
+
+        impl Cmd {
+            // Returns the stages and accesses of both sides of a queue family ownership transfer.
+            // The half of the dependency that belongs to the other queue family is ignored.
+            fn transfer_scopes(
+                &self,
+                (source, _): (u32, u32),
+                stages: PipelineStages,
+                access: AccessFlags,
+            ) -> (PipelineStages, AccessFlags, PipelineStages, AccessFlags) {
+                if source == self.queue_family_id {
+                    let bottom_of_pipe = PipelineStages {
+                        bottom_of_pipe: true,
+                        ..PipelineStages::none()
+                    };
+                    (stages, access, bottom_of_pipe, AccessFlags::none())
+                } else {
+                    let top_of_pipe = PipelineStages {
+                        top_of_pipe: true,
+                        ..PipelineStages::none()
+                    };
+                    (top_of_pipe, AccessFlags::none(), stages, access)
+                }
+            }
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdPipelineBarrier"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+
+                for memory_barrier in &self.memory_barriers {
+                    barrier.add_memory_barrier(
+                        memory_barrier.source_stages,
+                        memory_barrier.source_access,
+                        memory_barrier.destination_stages,
+                        memory_barrier.destination_access,
+                        self.by_region,
+                    );
+                }
+
+                // The other buffer and image barriers have already been handled by the
+                // synchronization of the builder.
+                for buffer_barrier in &self.buffer_barriers {
+                    let transfer = match buffer_barrier.queue_family_transfer {
+                        Some(transfer) => transfer,
+                        None => continue,
+                    };
+                    let (src_stages, src_access, dst_stages, dst_access) = self.transfer_scopes(
+                        transfer,
+                        buffer_barrier.destination_stages,
+                        buffer_barrier.destination_access,
+                    );
+                    let buffer = buffer_barrier.buffer.as_ref();
+
+                    barrier.add_buffer_memory_barrier(
+                        buffer,
+                        src_stages,
+                        src_access,
+                        dst_stages,
+                        dst_access,
+                        false,
+                        Some(transfer),
+                        0,
+                        buffer.size(),
+                    );
+                }
+
+                for image_barrier in &self.image_barriers {
+                    let transfer = match image_barrier.queue_family_transfer {
+                        Some(transfer) => transfer,
+                        None => continue,
+                    };
+                    let (src_stages, src_access, dst_stages, dst_access) = self.transfer_scopes(
+                        transfer,
+                        image_barrier.destination_stages,
+                        image_barrier.destination_access,
+                    );
+                    let image = image_barrier.image.as_ref();
+
+                    barrier.add_image_memory_barrier(
+                        image,
+                        image.current_miplevels_access(),
+                        image.current_layer_levels_access(),
+                        src_stages,
+                        src_access,
+                        dst_stages,
+                        dst_access,
+                        false,
+                        Some(transfer),
+                        image_barrier.new_layout,
+                        image_barrier.new_layout,
+                    );
+                }
+
+                out.pipeline_barrier(&barrier);
+            }
+        }
+
+        let resources: SmallVec<[_; 8]> = buffer_barriers
+            .iter()
+            .enumerate()
+            .map(|(num, buffer_barrier)| {
+                (
+                    KeyTy::Buffer(buffer_barrier.buffer.clone()),
+                    format!("buffer barrier {}", num).into(),
+                    Some((
+                        PipelineMemoryAccess {
+                            stages: buffer_barrier.destination_stages,
+                            access: buffer_barrier.destination_access,
+                            exclusive: true,
+                        },
+                        ImageLayout::Undefined,
+                        ImageLayout::Undefined,
+                        ImageUninitializedSafe::Unsafe,
+                    )),
+                )
+            })
+            .chain(
+                image_barriers
+                    .iter()
+                    .enumerate()
+                    .map(|(num, image_barrier)| {
+                        (
+                            KeyTy::Image(image_barrier.image.clone()),
+                            format!("image barrier {}", num).into(),
+                            Some((
+                                PipelineMemoryAccess {
+                                    stages: image_barrier.destination_stages,
+                                    access: image_barrier.destination_access,
+                                    exclusive: true,
+                                },
+                                image_barrier.new_layout,
+                                image_barrier.new_layout,
+                                ImageUninitializedSafe::Unsafe,
+                            )),
+                        )
+                    }),
+            )
+            .collect();
+
+        self.append_command(
+            Cmd {
+                queue_family_id,
+                by_region,
+                memory_barriers,
+                buffer_barriers,
+                image_barriers,
+            },
+            resources,
+        )
+    }
+
     /// Calls `vkCmdPushConstants` on the builder.
     #[inline]
     pub unsafe fn push_constants<D>(
@@ -1635,6 +2427,104 @@ impl SyncCommandBufferBuilder {
         .unwrap();
     }
 
+    /// Calls `vkCmdResolveImage` on the builder.
+    ///
+    /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
+    /// usage of the command anyway.
+    #[inline]
+    pub unsafe fn resolve_image<R>(
+        &mut self,
+        source: Arc<dyn ImageAccess>,
+        source_layout: ImageLayout,
+        destination: Arc<dyn ImageAccess>,
+        destination_layout: ImageLayout,
+        regions: R,
+    ) -> Result<(), SyncCommandBufferBuilderError>
+    where
+        R: IntoIterator<Item = UnsafeCommandBufferBuilderImageResolve> + Send + Sync + 'static,
+    {
+        struct Cmd<R> {
+            source: Arc<dyn ImageAccess>,
+            source_layout: ImageLayout,
+            destination: Arc<dyn ImageAccess>,
+            destination_layout: ImageLayout,
+            regions: Mutex<Option<R>>,
+        }
+
+        impl<R> Command for Cmd<R>
+        where
+            R: IntoIterator<Item = UnsafeCommandBufferBuilderImageResolve> + Send + Sync,
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdResolveImage"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.resolve_image(
+                    self.source.as_ref(),
+                    self.source_layout,
+                    self.destination.as_ref(),
+                    self.destination_layout,
+                    self.regions.lock().unwrap().take().unwrap(),
+                );
+            }
+        }
+
+        self.append_command(
+            Cmd {
+                source: source.clone(),
+                source_layout,
+                destination: destination.clone(),
+                destination_layout,
+                regions: Mutex::new(Some(regions)),
+            },
+            [
+                (
+                    KeyTy::Image(source),
+                    "source".into(),
+                    Some((
+                        PipelineMemoryAccess {
+                            stages: PipelineStages {
+                                transfer: true,
+                                ..PipelineStages::none()
+                            },
+                            access: AccessFlags {
+                                transfer_read: true,
+                                ..AccessFlags::none()
+                            },
+                            exclusive: false,
+                        },
+                        source_layout,
+                        source_layout,
+                        ImageUninitializedSafe::Unsafe,
+                    )),
+                ),
+                (
+                    KeyTy::Image(destination),
+                    "destination".into(),
+                    Some((
+                        PipelineMemoryAccess {
+                            stages: PipelineStages {
+                                transfer: true,
+                                ..PipelineStages::none()
+                            },
+                            access: AccessFlags {
+                                transfer_write: true,
+                                ..AccessFlags::none()
+                            },
+                            exclusive: true,
+                        },
+                        destination_layout,
+                        destination_layout,
+                        ImageUninitializedSafe::Safe,
+                    )),
+                ),
+            ],
+        )?;
+
+        Ok(())
+    }
+
     /// Calls `vkCmdSetBlendConstants` on the builder.
     #[inline]
     pub unsafe fn set_blend_constants(&mut self, constants: [f32; 4]) {
@@ -1941,6 +2831,42 @@ impl SyncCommandBufferBuilder {
         self.append_command(Cmd { event, stages }, []).unwrap();
     }
 
+    /// Calls `vkCmdSetFragmentShadingRateKHR` on the builder.
+    #[inline]
+    pub unsafe fn set_fragment_shading_rate(
+        &mut self,
+        fragment_size: [u32; 2],
+        combiner_ops: [FragmentShadingRateCombinerOp; 2],
+    ) {
+        struct Cmd {
+            fragment_size: [u32; 2],
+            combiner_ops: [FragmentShadingRateCombinerOp; 2],
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdSetFragmentShadingRateKHR"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.set_fragment_shading_rate(self.fragment_size, self.combiner_ops);
+            }
+        }
+
+        self.append_command(
+            Cmd {
+                fragment_size,
+                combiner_ops,
+            },
+            [],
+        )
+        .unwrap();
+        self.current_state.fragment_shading_rate = Some(FragmentShadingRate {
+            fragment_size,
+            combiner_ops,
+        });
+    }
+
     /// Calls `vkCmdSetFrontFaceEXT` on the builder.
     #[inline]
     pub unsafe fn set_front_face(&mut self, face: FrontFace) {
@@ -2371,6 +3297,33 @@ impl SyncCommandBufferBuilder {
         .unwrap();
     }
 
+    /// Calls `vkCmdSetVertexInputEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_vertex_input(&mut self, vertex_input: VertexInput) {
+        struct Cmd {
+            vertex_input: VertexInput,
+        }
+
+        impl Command for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdSetVertexInputEXT"
+            }
+
+            unsafe fn send(&self, out: &mut UnsafeCommandBufferBuilder) {
+                out.set_vertex_input(&self.vertex_input);
+            }
+        }
+
+        self.append_command(
+            Cmd {
+                vertex_input: vertex_input.clone(),
+            },
+            [],
+        )
+        .unwrap();
+        self.current_state.vertex_input = Some(vertex_input);
+    }
+
     /// Calls `vkCmdSetViewport` on the builder.
     ///
     /// If the list is empty then the command is automatically ignored.
@@ -2590,6 +3543,20 @@ impl SyncCommandBufferBuilder {
                             ..AccessFlags::none()
                         }
                     }
+                    DescriptorType::AccelerationStructure => AccessFlags {
+                        acceleration_structure_read: true,
+                        ..AccessFlags::none()
+                    },
+                    // The resources can be of any of the mutable types, so we include the
+                    // accesses of all of them.
+                    DescriptorType::Mutable => AccessFlags {
+                        shader_read: true,
+                        shader_write: reqs.mutable,
+                        uniform_read: true,
+                        input_attachment_read: true,
+                        acceleration_structure_read: true,
+                        ..AccessFlags::none()
+                    },
                 },
                 exclusive: reqs.mutable,
             };
@@ -2606,26 +3573,27 @@ impl SyncCommandBufferBuilder {
                     )),
                 )
             };
-            let image_resource = move |image: Arc<dyn ImageAccess>| {
-                let layout = image
-                    .descriptor_layouts()
-                    .expect("descriptor_layouts must return Some when used in an image view")
-                    .layout_for(descriptor_type);
-                (
-                    KeyTy::Image(image),
-                    format!("Image bound to set {} descriptor {}", set, binding).into(),
-                    if descriptor_type == DescriptorType::InputAttachment {
-                        // FIXME: This is tricky. Since we read from the input attachment
-                        // and this input attachment is being written in an earlier pass,
-                        // vulkano will think that it needs to put a pipeline barrier and will
-                        // return a `Conflict` error. For now as a work-around we simply ignore
-                        // input attachments.
-                        None
-                    } else {
-                        Some((access, layout, layout, ImageUninitializedSafe::Unsafe))
-                    },
-                )
-            };
+            let image_resource =
+                move |(image, descriptor_type): (Arc<dyn ImageAccess>, DescriptorType)| {
+                    let layout = image
+                        .descriptor_layouts()
+                        .expect("descriptor_layouts must return Some when used in an image view")
+                        .layout_for(descriptor_type);
+                    (
+                        KeyTy::Image(image),
+                        format!("Image bound to set {} descriptor {}", set, binding).into(),
+                        if descriptor_type == DescriptorType::InputAttachment {
+                            // FIXME: This is tricky. Since we read from the input attachment
+                            // and this input attachment is being written in an earlier pass,
+                            // vulkano will think that it needs to put a pipeline barrier and will
+                            // return a `Conflict` error. For now as a work-around we simply ignore
+                            // input attachments.
+                            None
+                        } else {
+                            Some((access, layout, layout, ImageUninitializedSafe::Unsafe))
+                        },
+                    )
+                };
 
             match state.descriptor_sets[&set]
                 .resources()
@@ -2650,7 +3618,7 @@ impl SyncCommandBufferBuilder {
                         elements
                             .iter()
                             .flatten()
-                            .map(|image_view| image_view.image())
+                            .map(|image_view| (image_view.image(), descriptor_type))
                             .map(image_resource),
                     );
                 }
@@ -2659,11 +3627,41 @@ impl SyncCommandBufferBuilder {
                         elements
                             .iter()
                             .flatten()
-                            .map(|(image_view, _)| image_view.image())
+                            .map(|(image_view, _)| (image_view.image(), descriptor_type))
                             .map(image_resource),
                     );
                 }
                 DescriptorBindingResources::Sampler(_) => (),
+                DescriptorBindingResources::AccelerationStructure(elements) => {
+                    resources.extend(
+                        elements
+                            .iter()
+                            .flatten()
+                            .map(|acceleration_structure| acceleration_structure.buffer().clone())
+                            .map(buffer_resource),
+                    );
+                }
+                DescriptorBindingResources::Mutable(elements) => {
+                    for (ty, resource) in elements.iter().flatten() {
+                        match resource {
+                            DescriptorResource::Buffer(buffer) => {
+                                resources.push(buffer_resource(buffer.clone()));
+                            }
+                            DescriptorResource::BufferView(buffer_view) => {
+                                resources.push(buffer_resource(buffer_view.buffer()));
+                            }
+                            DescriptorResource::ImageView(image_view)
+                            | DescriptorResource::ImageViewSampler(image_view, _) => {
+                                resources.push(image_resource((image_view.image(), *ty)));
+                            }
+                            DescriptorResource::Sampler(_) => (),
+                            DescriptorResource::AccelerationStructure(acceleration_structure) => {
+                                resources
+                                    .push(buffer_resource(acceleration_structure.buffer().clone()));
+                            }
+                        }
+                    }
+                }
             }
         }
     }
@@ -2680,8 +3678,10 @@ impl SyncCommandBufferBuilder {
                 ImageUninitializedSafe,
             )>,
         )>,
-        vertex_input: &VertexInput,
+        pipeline: &GraphicsPipeline,
     ) {
+        let vertex_input = self.state().vertex_input_for(pipeline);
+
         resources.extend(vertex_input.bindings().map(|(binding_num, _)| {
             let buffer = self.current_state.vertex_buffers[&binding_num].clone();
             (
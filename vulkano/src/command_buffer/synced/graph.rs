@@ -0,0 +1,378 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Debug view of the dependencies that the synchronization layer computed.
+
+use super::Command;
+use super::KeyTy;
+use super::ResourceDependency;
+use super::ResourceKey;
+use super::ResourceUse;
+use crate::image::ImageLayout;
+use crate::sync::AccessFlags;
+use crate::sync::PipelineStages;
+use crate::VulkanObject;
+use ash::vk::Handle;
+use fnv::FnvHashMap;
+use std::borrow::Cow;
+use std::io;
+use std::io::Write;
+
+/// The dependency graph that the synchronization layer computed for a command buffer.
+///
+/// This is meant for debugging. It lists the recorded commands, the resources that they use, and
+/// every dependency between two uses of a resource that the synchronization layer inserted a
+/// pipeline barrier for, including layout transitions. It can be written in the DOT format of
+/// Graphviz with [`write_dot`](Self::write_dot), or as JSON with
+/// [`write_json`](Self::write_json).
+#[derive(Clone, Debug, Default)]
+pub struct SyncGraph {
+    /// The names of the recorded commands, in order.
+    pub commands: Vec<&'static str>,
+    /// The resources that are used by the commands, ordered by their first use.
+    pub resources: Vec<SyncGraphResource>,
+    /// The dependencies that barriers were inserted for, in the order they were found.
+    pub dependencies: Vec<SyncGraphDependency>,
+    /// The indices of the commands that a pipeline barrier was recorded before. An index equal
+    /// to the number of commands is a barrier at the end of the command buffer.
+    pub barriers: Vec<usize>,
+}
+
+/// A resource of a [`SyncGraph`].
+#[derive(Clone, Debug)]
+pub struct SyncGraphResource {
+    /// A description of the resource, with the handle of the buffer or image and the range that
+    /// is used.
+    pub description: String,
+    /// The index of each command that uses the resource in a way that had to be synchronized,
+    /// with the name of the parameter of the command.
+    pub uses: Vec<(usize, Cow<'static, str>)>,
+    /// The layout that an image must be in at the start of the command buffer. Always
+    /// `Undefined` for buffers.
+    pub initial_layout: ImageLayout,
+    /// The layout that an image is in after the last command. Always `Undefined` for buffers.
+    pub final_layout: ImageLayout,
+}
+
+/// A dependency of a [`SyncGraph`].
+#[derive(Clone, Debug)]
+pub struct SyncGraphDependency {
+    /// The index of the resource in [`SyncGraph::resources`].
+    pub resource: usize,
+    /// The index of the command that the dependency waits for, or `None` for the beginning of
+    /// the command buffer.
+    pub source_command: Option<usize>,
+    /// The index of the command that waits, or `None` for the end of the command buffer.
+    pub destination_command: Option<usize>,
+    /// The index of the command that the barrier of the dependency was recorded before.
+    pub barrier_position: usize,
+    /// The stages of the source of the dependency.
+    pub source_stages: PipelineStages,
+    /// The accesses of the source of the dependency.
+    pub source_access: AccessFlags,
+    /// The stages of the destination of the dependency.
+    pub destination_stages: PipelineStages,
+    /// The accesses of the destination of the dependency.
+    pub destination_access: AccessFlags,
+    /// The layout that an image is transitioned from. Always `Undefined` for buffers.
+    pub old_layout: ImageLayout,
+    /// The layout that an image is transitioned to. Always `Undefined` for buffers.
+    pub new_layout: ImageLayout,
+}
+
+impl SyncGraph {
+    pub(super) fn new<'a, R>(
+        commands: &[Box<dyn Command>],
+        resources: R,
+        dependencies: &[ResourceDependency],
+        barriers: &[usize],
+    ) -> SyncGraph
+    where
+        R: IntoIterator<Item = (&'a ResourceKey, &'a [ResourceUse], ImageLayout, ImageLayout)>,
+    {
+        let mut resources: Vec<_> = resources.into_iter().collect();
+        resources.sort_by_key(|(_, uses, _, _)| uses.first().map(|u| u.command_index));
+
+        // Barriers that are still pending in a builder haven't been tracked yet.
+        let mut barriers = barriers.to_vec();
+        barriers.extend(dependencies.iter().map(|d| d.barrier_position));
+        barriers.sort_unstable();
+        barriers.dedup();
+
+        let indices: FnvHashMap<&ResourceKey, usize> = resources
+            .iter()
+            .enumerate()
+            .map(|(index, (key, _, _, _))| (*key, index))
+            .collect();
+
+        SyncGraph {
+            commands: commands.iter().map(|command| command.name()).collect(),
+            resources: resources
+                .iter()
+                .map(
+                    |(_, uses, initial_layout, final_layout)| SyncGraphResource {
+                        description: uses
+                            .first()
+                            .map(|u| describe(&u.resource))
+                            .unwrap_or_default(),
+                        uses: uses
+                            .iter()
+                            .map(|u| (u.command_index, u.name.clone()))
+                            .collect(),
+                        initial_layout: *initial_layout,
+                        final_layout: *final_layout,
+                    },
+                )
+                .collect(),
+            dependencies: dependencies
+                .iter()
+                .map(|dependency| SyncGraphDependency {
+                    resource: indices[&dependency.resource],
+                    source_command: dependency.source_command,
+                    destination_command: dependency.destination_command,
+                    barrier_position: dependency.barrier_position,
+                    source_stages: dependency.source_stages,
+                    source_access: dependency.source_access,
+                    destination_stages: dependency.destination_stages,
+                    destination_access: dependency.destination_access,
+                    old_layout: dependency.old_layout,
+                    new_layout: dependency.new_layout,
+                })
+                .collect(),
+            barriers,
+        }
+    }
+
+    /// Writes the graph in the DOT format of Graphviz.
+    ///
+    /// Commands are drawn in order from top to bottom, with the pipeline barriers between them.
+    /// Each dependency is an edge from the command that it waits for to the command that waits,
+    /// labelled with the resource, the stages and accesses, and the layout transition if there
+    /// is one.
+    pub fn write_dot<W>(&self, out: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        writeln!(out, "digraph sync {{")?;
+        writeln!(out, "    node [shape=box];")?;
+        writeln!(out, "    start [shape=circle, label=\"start\"];")?;
+        writeln!(out, "    end [shape=doublecircle, label=\"end\"];")?;
+
+        let mut previous = "start".to_owned();
+
+        for (index, name) in self.commands.iter().enumerate() {
+            if self.barriers.contains(&index) {
+                writeln!(out, "    b{} [shape=octagon, label=\"barrier\"];", index)?;
+                writeln!(out, "    {} -> b{} [style=dotted];", previous, index)?;
+                previous = format!("b{}", index);
+            }
+
+            writeln!(
+                out,
+                "    c{} [label=\"{}: {}\"];",
+                index,
+                index,
+                escape(name)
+            )?;
+            writeln!(out, "    {} -> c{} [style=dotted];", previous, index)?;
+            previous = format!("c{}", index);
+        }
+
+        if self.barriers.contains(&self.commands.len()) {
+            let index = self.commands.len();
+            writeln!(out, "    b{} [shape=octagon, label=\"barrier\"];", index)?;
+            writeln!(out, "    {} -> b{} [style=dotted];", previous, index)?;
+            previous = format!("b{}", index);
+        }
+
+        writeln!(out, "    {} -> end [style=dotted];", previous)?;
+
+        for (index, resource) in self.resources.iter().enumerate() {
+            writeln!(
+                out,
+                "    r{} [shape=ellipse, label=\"{}\\ninitial: {:?}\\nfinal: {:?}\"];",
+                index,
+                escape(&resource.description),
+                resource.initial_layout,
+                resource.final_layout,
+            )?;
+
+            for (command_index, parameter) in &resource.uses {
+                writeln!(
+                    out,
+                    "    r{} -> c{} [style=dashed, dir=none, label=\"{}\"];",
+                    index,
+                    command_index,
+                    escape(parameter),
+                )?;
+            }
+        }
+
+        for dependency in &self.dependencies {
+            let source = match dependency.source_command {
+                Some(index) => format!("c{}", index),
+                None => "start".to_owned(),
+            };
+            let destination = match dependency.destination_command {
+                Some(index) => format!("c{}", index),
+                None => "end".to_owned(),
+            };
+
+            write!(
+                out,
+                "    {} -> {} [color=red, label=\"r{}\\n{} ({})\\n-> {} ({})",
+                source,
+                destination,
+                dependency.resource,
+                dependency.source_stages.names().join(" | "),
+                dependency.source_access.names().join(" | "),
+                dependency.destination_stages.names().join(" | "),
+                dependency.destination_access.names().join(" | "),
+            )?;
+
+            if dependency.old_layout != dependency.new_layout {
+                write!(
+                    out,
+                    "\\n{:?} -> {:?}",
+                    dependency.old_layout, dependency.new_layout
+                )?;
+            }
+
+            writeln!(out, "\"];")?;
+        }
+
+        writeln!(out, "}}")
+    }
+
+    /// Writes the graph as a JSON object, with `commands`, `barriers`, `resources` and
+    /// `dependencies` members that match the fields of this struct.
+    pub fn write_json<W>(&self, out: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        write!(out, "{{\"commands\":[")?;
+        for (index, name) in self.commands.iter().enumerate() {
+            if index != 0 {
+                write!(out, ",")?;
+            }
+            write!(out, "\"{}\"", escape(name))?;
+        }
+
+        write!(out, "],\"barriers\":[")?;
+        for (index, position) in self.barriers.iter().enumerate() {
+            if index != 0 {
+                write!(out, ",")?;
+            }
+            write!(out, "{}", position)?;
+        }
+
+        write!(out, "],\"resources\":[")?;
+        for (index, resource) in self.resources.iter().enumerate() {
+            if index != 0 {
+                write!(out, ",")?;
+            }
+            write!(
+                out,
+                "{{\"description\":\"{}\",\"initial_layout\":\"{:?}\",\"final_layout\":\"{:?}\",\
+                 \"uses\":[",
+                escape(&resource.description),
+                resource.initial_layout,
+                resource.final_layout,
+            )?;
+            for (use_index, (command_index, parameter)) in resource.uses.iter().enumerate() {
+                if use_index != 0 {
+                    write!(out, ",")?;
+                }
+                write!(
+                    out,
+                    "{{\"command\":{},\"parameter\":\"{}\"}}",
+                    command_index,
+                    escape(parameter)
+                )?;
+            }
+            write!(out, "]}}")?;
+        }
+
+        write!(out, "],\"dependencies\":[")?;
+        for (index, dependency) in self.dependencies.iter().enumerate() {
+            if index != 0 {
+                write!(out, ",")?;
+            }
+            write!(
+                out,
+                "{{\"resource\":{},\"source_command\":{},\"destination_command\":{},\
+                 \"barrier_position\":{},\"source_stages\":{},\"source_access\":{},\
+                 \"destination_stages\":{},\"destination_access\":{},\"old_layout\":\"{:?}\",\
+                 \"new_layout\":\"{:?}\"}}",
+                dependency.resource,
+                json_index(dependency.source_command),
+                json_index(dependency.destination_command),
+                dependency.barrier_position,
+                json_names(&dependency.source_stages.names()),
+                json_names(&dependency.source_access.names()),
+                json_names(&dependency.destination_stages.names()),
+                json_names(&dependency.destination_access.names()),
+                dependency.old_layout,
+                dependency.new_layout,
+            )?;
+        }
+
+        write!(out, "]}}")
+    }
+}
+
+// Returns a description of a resource for the user.
+fn describe(resource: &KeyTy) -> String {
+    match resource {
+        KeyTy::Buffer(buffer) => {
+            let inner = buffer.inner();
+            format!(
+                "buffer {:#x} bytes {}..{}",
+                inner.buffer.internal_object().as_raw(),
+                inner.offset,
+                inner.offset + buffer.size(),
+            )
+        }
+        KeyTy::Image(image) => format!(
+            "image {:#x} mip levels {:?} array layers {:?}",
+            image.inner().image.internal_object().as_raw(),
+            image.current_miplevels_access(),
+            image.current_layer_levels_access(),
+        ),
+    }
+}
+
+// Escapes a string so that it can be put between double quotes in DOT or JSON.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn json_index(index: Option<usize>) -> String {
+    match index {
+        Some(index) => index.to_string(),
+        None => "null".to_owned(),
+    }
+}
+
+fn json_names(names: &[&'static str]) -> String {
+    let names: Vec<_> = names.iter().map(|name| format!("\"{}\"", name)).collect();
+    format!("[{}]", names.join(","))
+}
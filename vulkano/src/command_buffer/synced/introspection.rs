@@ -0,0 +1,63 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Description of the commands recorded in a command buffer.
+//!
+//! This is only available with the `command_introspection` feature, because keeping track of
+//! the state at every command has a cost.
+
+use super::SetOrPush;
+use crate::buffer::BufferAccess;
+use crate::image::ImageAccess;
+use crate::image::ImageLayout;
+use crate::pipeline::ComputePipeline;
+use crate::pipeline::GraphicsPipeline;
+use crate::pipeline::PipelineBindPoint;
+use crate::sync::PipelineMemoryAccess;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// A command recorded in a command buffer, with the state that was bound when it was recorded.
+#[derive(Clone)]
+pub struct RecordedCommand {
+    /// The name of the Vulkan command, for example `vkCmdDraw`.
+    pub name: &'static str,
+    /// The buffers and images that the command accesses.
+    pub resources: Vec<RecordedResourceUse>,
+    /// The compute pipeline that was bound.
+    pub pipeline_compute: Option<Arc<ComputePipeline>>,
+    /// The graphics pipeline that was bound.
+    pub pipeline_graphics: Option<Arc<GraphicsPipeline>>,
+    /// The descriptor sets that were bound, with their bind point and set number, ordered by
+    /// bind point and set number.
+    pub descriptor_sets: Vec<(PipelineBindPoint, u32, SetOrPush)>,
+}
+
+/// A use of a buffer or image by a [`RecordedCommand`].
+#[derive(Clone)]
+pub struct RecordedResourceUse {
+    /// The buffer or image.
+    pub resource: RecordedResource,
+    /// The parameter of the command that the resource was given as.
+    pub parameter: Cow<'static, str>,
+    /// The stages and accesses of the command on the resource.
+    pub memory: PipelineMemoryAccess,
+    /// The layout that an image must be in when the command starts. Always `Undefined` for
+    /// buffers.
+    pub start_layout: ImageLayout,
+    /// The layout that an image is in when the command ends. Always `Undefined` for buffers.
+    pub end_layout: ImageLayout,
+}
+
+/// A buffer or image used by a [`RecordedCommand`].
+#[derive(Clone)]
+pub enum RecordedResource {
+    Buffer(Arc<dyn BufferAccess>),
+    Image(Arc<dyn ImageAccess>),
+}
@@ -73,6 +73,11 @@ pub use self::builder::SyncCommandBufferBuilderBindDescriptorSets;
 pub use self::builder::SyncCommandBufferBuilderBindVertexBuffer;
 pub use self::builder::SyncCommandBufferBuilderError;
 pub use self::builder::SyncCommandBufferBuilderExecuteCommands;
+pub use self::graph::SyncGraph;
+pub use self::graph::SyncGraphDependency;
+pub use self::graph::SyncGraphResource;
+#[cfg(feature = "command_introspection")]
+pub use self::introspection::{RecordedCommand, RecordedResource, RecordedResourceUse};
 use crate::buffer::BufferAccess;
 use crate::command_buffer::sys::UnsafeCommandBuffer;
 use crate::command_buffer::sys::UnsafeCommandBufferBuilder;
@@ -95,6 +100,9 @@ use std::ops::Range;
 use std::sync::Arc;
 
 mod builder;
+mod graph;
+#[cfg(feature = "command_introspection")]
+mod introspection;
 
 /// Command buffer built from a `SyncCommandBufferBuilder` that provides utilities to handle
 /// synchronization.
@@ -110,6 +118,14 @@ pub struct SyncCommandBuffer {
     // TODO: present only in cfg(debug_assertions)?
     barriers: Vec<usize>,
 
+    // Dependencies between uses of a resource that a barrier was added for. For debugging
+    // purposes.
+    dependencies: Vec<ResourceDependency>,
+
+    // Description of every command, with the state when it was recorded.
+    #[cfg(feature = "command_introspection")]
+    recorded_commands: Vec<RecordedCommand>,
+
     // State of all the resources used by this command buffer.
     resources: FnvHashMap<ResourceKey, ResourceFinalState>,
 
@@ -331,6 +347,31 @@ impl SyncCommandBuffer {
         Err(AccessCheckError::Unknown)
     }
 
+    /// Returns the dependencies that the synchronization layer computed for this command buffer,
+    /// and the barriers that it inserted for them.
+    pub fn sync_graph(&self) -> SyncGraph {
+        SyncGraph::new(
+            &self.commands,
+            self.resources.iter().map(|(key, state)| {
+                (
+                    key,
+                    state.resource_uses.as_slice(),
+                    state.initial_layout,
+                    state.final_layout,
+                )
+            }),
+            &self.dependencies,
+            &self.barriers,
+        )
+    }
+
+    /// Returns a description of every command recorded in the command buffer, in order.
+    #[cfg(feature = "command_introspection")]
+    #[inline]
+    pub fn recorded_commands(&self) -> &[RecordedCommand] {
+        &self.recorded_commands
+    }
+
     #[inline]
     pub fn num_buffers(&self) -> usize {
         self.buffers.len()
@@ -389,7 +430,7 @@ unsafe impl DeviceOwned for SyncCommandBuffer {
 
 // Key that identifies a resource. Implements `PartialEq`, `Eq` and `Hash` so that two resources
 // that conflict with each other compare equal.
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 enum ResourceKey {
     Buffer((u64, u64)),
     Image(u64, Range<u32>, Range<u32>),
@@ -444,6 +485,26 @@ struct ResourceUse {
     name: Cow<'static, str>,
 }
 
+// Dependency between two uses of a resource, that a pipeline barrier was added for. For
+// debugging purposes.
+#[derive(Clone)]
+struct ResourceDependency {
+    resource: ResourceKey,
+    // Index of the command that the dependency waits for, or `None` for the start of the command
+    // buffer.
+    source_command: Option<usize>,
+    // Index of the command that waits, or `None` for the end of the command buffer.
+    destination_command: Option<usize>,
+    // Index of the command that the barrier is submitted before.
+    barrier_position: usize,
+    source_stages: PipelineStages,
+    source_access: AccessFlags,
+    destination_stages: PipelineStages,
+    destination_access: AccessFlags,
+    old_layout: ImageLayout,
+    new_layout: ImageLayout,
+}
+
 /// Type of resource whose state is to be tracked.
 #[derive(Clone)]
 enum KeyTy {
@@ -485,6 +546,7 @@ mod tests {
     use crate::buffer::ImmutableBuffer;
     use crate::command_buffer::pool::CommandPool;
     use crate::command_buffer::pool::CommandPoolBuilderAlloc;
+    use crate::command_buffer::sys::UnsafeCommandBufferBuilderImageCopy;
     use crate::command_buffer::AutoCommandBufferBuilder;
     use crate::command_buffer::CommandBufferLevel;
     use crate::command_buffer::CommandBufferUsage;
@@ -493,6 +555,12 @@ mod tests {
     use crate::descriptor_set::layout::DescriptorType;
     use crate::descriptor_set::PersistentDescriptorSet;
     use crate::device::Device;
+    use crate::format::Format;
+    use crate::image::AttachmentImage;
+    use crate::image::ImageAccess;
+    use crate::image::ImageAspects;
+    use crate::image::ImageLayout;
+    use crate::image::ImageUsage;
     use crate::pipeline::layout::PipelineLayout;
     use crate::pipeline::PipelineBindPoint;
     use crate::sampler::Sampler;
@@ -541,6 +609,153 @@ mod tests {
         }
     }
 
+    #[test]
+    fn sync_graph_dependency() {
+        unsafe {
+            let (device, queue) = gfx_dev_and_queue!();
+
+            let pool = Device::standard_command_pool(&device, queue.family());
+            let pool_builder_alloc = pool.alloc(false, 1).unwrap().next().unwrap();
+            let mut sync = SyncCommandBufferBuilder::new(
+                &pool_builder_alloc.inner(),
+                CommandBufferLevel::primary(),
+                CommandBufferUsage::MultipleSubmit,
+            )
+            .unwrap();
+            let bufs: Vec<_> = (0..3)
+                .map(|_| {
+                    CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                        .unwrap()
+                })
+                .collect();
+
+            sync.copy_buffer(bufs[0].clone(), bufs[1].clone(), std::iter::once((0, 0, 4)))
+                .unwrap();
+            sync.copy_buffer(bufs[1].clone(), bufs[2].clone(), std::iter::once((0, 0, 4)))
+                .unwrap();
+
+            let graph = sync.build().unwrap().sync_graph();
+            assert_eq!(graph.commands, ["vkCmdCopyBuffer", "vkCmdCopyBuffer"]);
+            assert_eq!(graph.resources.len(), 3);
+            assert_eq!(graph.dependencies.len(), 1);
+
+            let dependency = &graph.dependencies[0];
+            assert_eq!(dependency.source_command, Some(0));
+            assert_eq!(dependency.destination_command, Some(1));
+            assert_eq!(dependency.barrier_position, 1);
+            assert_eq!(graph.resources[dependency.resource].uses.len(), 2);
+
+            let mut dot = Vec::new();
+            graph.write_dot(&mut dot).unwrap();
+            assert!(String::from_utf8(dot)
+                .unwrap()
+                .contains("c0 -> c1 [color=red"));
+        }
+    }
+
+    #[test]
+    fn already_synchronized_read() {
+        unsafe {
+            let (device, queue) = gfx_dev_and_queue!();
+
+            let pool = Device::standard_command_pool(&device, queue.family());
+            let pool_builder_alloc = pool.alloc(false, 1).unwrap().next().unwrap();
+            let mut sync = SyncCommandBufferBuilder::new(
+                &pool_builder_alloc.inner(),
+                CommandBufferLevel::primary(),
+                CommandBufferUsage::MultipleSubmit,
+            )
+            .unwrap();
+            let image = |usage| {
+                AttachmentImage::with_usage(device.clone(), [16, 16], Format::R8G8B8A8_UNORM, usage)
+                    .unwrap()
+            };
+            let source = image(ImageUsage {
+                transfer_source: true,
+                ..ImageUsage::none()
+            });
+            let destinations = [
+                image(ImageUsage {
+                    transfer_destination: true,
+                    ..ImageUsage::none()
+                }),
+                image(ImageUsage {
+                    transfer_destination: true,
+                    ..ImageUsage::none()
+                }),
+            ];
+            let region = UnsafeCommandBufferBuilderImageCopy {
+                aspects: ImageAspects {
+                    color: true,
+                    ..ImageAspects::none()
+                },
+                source_mip_level: 0,
+                destination_mip_level: 0,
+                source_base_array_layer: 0,
+                destination_base_array_layer: 0,
+                layer_count: 1,
+                source_offset: [0; 3],
+                destination_offset: [0; 3],
+                extent: [16, 16, 1],
+            };
+
+            // The second copy only reads the source in the same layout and with the same stages
+            // and accesses as the first one, so no barrier is needed between the two.
+            for destination in destinations.iter() {
+                sync.copy_image(
+                    source.clone() as Arc<dyn ImageAccess>,
+                    ImageLayout::TransferSrcOptimal,
+                    destination.clone() as Arc<dyn ImageAccess>,
+                    ImageLayout::TransferDstOptimal,
+                    std::iter::once(region),
+                )
+                .unwrap();
+            }
+
+            let graph = sync.build().unwrap().sync_graph();
+            assert_eq!(graph.commands, ["vkCmdCopyImage", "vkCmdCopyImage"]);
+            assert!(graph
+                .dependencies
+                .iter()
+                .all(|dependency| dependency.source_command.is_none()
+                    || dependency.destination_command.is_none()));
+        }
+    }
+
+    #[cfg(feature = "command_introspection")]
+    #[test]
+    fn recorded_commands() {
+        unsafe {
+            let (device, queue) = gfx_dev_and_queue!();
+
+            let pool = Device::standard_command_pool(&device, queue.family());
+            let pool_builder_alloc = pool.alloc(false, 1).unwrap().next().unwrap();
+            let mut sync = SyncCommandBufferBuilder::new(
+                &pool_builder_alloc.inner(),
+                CommandBufferLevel::primary(),
+                CommandBufferUsage::MultipleSubmit,
+            )
+            .unwrap();
+            let source =
+                CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                    .unwrap();
+            let destination =
+                CpuAccessibleBuffer::from_data(device, BufferUsage::all(), false, 0u32).unwrap();
+
+            sync.copy_buffer(source, destination, std::iter::once((0, 0, 4)))
+                .unwrap();
+
+            let command_buffer = sync.build().unwrap();
+            let commands = command_buffer.recorded_commands();
+            assert_eq!(commands.len(), 1);
+            assert_eq!(commands[0].name, "vkCmdCopyBuffer");
+            assert_eq!(commands[0].resources.len(), 2);
+            assert_eq!(commands[0].resources[1].parameter, "destination");
+            assert!(commands[0].resources[1].memory.exclusive);
+            assert!(commands[0].pipeline_compute.is_none());
+        }
+    }
+
     #[test]
     fn secondary_conflicting_writes() {
         unsafe {
@@ -673,8 +888,12 @@ mod tests {
                     ty: DescriptorType::Sampler,
                     descriptor_count: 1,
                     variable_count: false,
+                    update_after_bind: false,
+                    update_unused_while_pending: false,
+                    partially_bound: false,
                     stages: ShaderStages::all(),
                     immutable_samplers: Vec::new(),
+                    mutable_types: Vec::new(),
                 })],
             )
             .unwrap();
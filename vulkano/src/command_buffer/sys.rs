@@ -11,10 +11,16 @@ use crate::buffer::BufferAccess;
 use crate::buffer::BufferInner;
 use crate::buffer::TypedBufferAccess;
 use crate::check_errors;
+use crate::command_buffer::indirect_commands::IndirectCommandsLayout;
 use crate::command_buffer::pool::UnsafeCommandPoolAlloc;
+use crate::command_buffer::ClearAttachment;
+use crate::command_buffer::ClearRect;
 use crate::command_buffer::CommandBufferInheritance;
 use crate::command_buffer::CommandBufferLevel;
 use crate::command_buffer::CommandBufferUsage;
+use crate::command_buffer::ConditionalRenderingFlags;
+use crate::command_buffer::MultiDrawCommand;
+use crate::command_buffer::MultiDrawIndexedCommand;
 use crate::command_buffer::SecondaryCommandBuffer;
 use crate::command_buffer::SubpassContents;
 use crate::descriptor_set::sys::DescriptorWrite;
@@ -33,11 +39,14 @@ use crate::pipeline::color_blend::LogicOp;
 use crate::pipeline::depth_stencil::CompareOp;
 use crate::pipeline::depth_stencil::StencilFaces;
 use crate::pipeline::depth_stencil::StencilOp;
+use crate::pipeline::fragment_shading_rate::FragmentShadingRateCombinerOp;
 use crate::pipeline::input_assembly::IndexType;
 use crate::pipeline::input_assembly::PrimitiveTopology;
 use crate::pipeline::layout::PipelineLayout;
 use crate::pipeline::rasterization::CullMode;
 use crate::pipeline::rasterization::FrontFace;
+use crate::pipeline::vertex::VertexInput;
+use crate::pipeline::vertex::VertexInputRate;
 use crate::pipeline::viewport::Scissor;
 use crate::pipeline::viewport::Viewport;
 use crate::pipeline::ComputePipeline;
@@ -57,13 +66,16 @@ use crate::sync::PipelineStage;
 use crate::sync::PipelineStages;
 use crate::DeviceSize;
 use crate::OomError;
+use crate::Version;
 use crate::VulkanObject;
 use ash::vk::Handle;
 use smallvec::SmallVec;
+use std::cmp;
 use std::ffi::CStr;
 use std::fmt;
 use std::mem;
 use std::ops::Range;
+use std::ptr;
 use std::sync::Arc;
 
 /// Command buffer being built.
@@ -178,7 +190,33 @@ impl UnsafeCommandBufferBuilder {
             ),
         };
 
-        let inheritance = ash::vk::CommandBufferInheritanceInfo {
+        let viewport_depths: SmallVec<[_; 2]> = match level {
+            CommandBufferLevel::Secondary(CommandBufferInheritance {
+                viewport_scissor_depths: Some(ref depths),
+                ..
+            }) => depths
+                .iter()
+                .map(|depth_range| ash::vk::Viewport {
+                    min_depth: depth_range.start,
+                    max_depth: depth_range.end,
+                    ..Default::default()
+                })
+                .collect(),
+            _ => SmallVec::new(),
+        };
+
+        let mut viewport_scissor_info = if viewport_depths.is_empty() {
+            None
+        } else {
+            Some(ash::vk::CommandBufferInheritanceViewportScissorInfoNV {
+                viewport_scissor2_d: ash::vk::TRUE,
+                viewport_depth_count: viewport_depths.len() as u32,
+                p_viewport_depths: viewport_depths.as_ptr(),
+                ..Default::default()
+            })
+        };
+
+        let mut inheritance = ash::vk::CommandBufferInheritanceInfo {
             render_pass: rp,
             subpass: sp,
             framebuffer: fb,
@@ -188,6 +226,11 @@ impl UnsafeCommandBufferBuilder {
             ..Default::default()
         };
 
+        if let Some(viewport_scissor_info) = viewport_scissor_info.as_mut() {
+            viewport_scissor_info.p_next = inheritance.p_next;
+            inheritance.p_next = viewport_scissor_info as *const _ as *const _;
+        }
+
         let infos = ash::vk::CommandBufferBeginInfo {
             flags: vk_flags,
             p_inheritance_info: &inheritance,
@@ -221,6 +264,34 @@ impl UnsafeCommandBufferBuilder {
         }
     }
 
+    /// Calls `vkCmdBeginConditionalRenderingEXT` on the builder.
+    #[inline]
+    pub unsafe fn begin_conditional_rendering<B>(
+        &mut self,
+        buffer: &B,
+        flags: ConditionalRenderingFlags,
+    ) where
+        B: ?Sized + BufferAccess,
+    {
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+
+        let inner = buffer.inner();
+        debug_assert!(inner.offset < inner.buffer.size());
+        debug_assert!(inner.offset % 4 == 0);
+        debug_assert!(inner.buffer.usage().conditional_rendering);
+
+        let begin_info = ash::vk::ConditionalRenderingBeginInfoEXT {
+            buffer: inner.buffer.internal_object(),
+            offset: inner.offset,
+            flags: flags.into(),
+            ..Default::default()
+        };
+
+        fns.ext_conditional_rendering
+            .cmd_begin_conditional_rendering_ext(cmd, &begin_info);
+    }
+
     /// Calls `vkCmdBeginQuery` on the builder.
     #[inline]
     pub unsafe fn begin_query(&mut self, query: Query, flags: QueryControlFlags) {
@@ -313,6 +384,29 @@ impl UnsafeCommandBufferBuilder {
             .cmd_begin_render_pass(cmd, &begin, subpass_contents.into());
     }
 
+    /// Calls `vkCmdBeginTransformFeedbackEXT` on the builder.
+    ///
+    /// `counter_buffers` contains the counter buffer of each transform feedback buffer binding,
+    /// starting at binding 0, or `None` to start capturing at the start of the buffer.
+    #[inline]
+    pub unsafe fn begin_transform_feedback(
+        &mut self,
+        counter_buffers: &[Option<Arc<dyn BufferAccess>>],
+    ) {
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+
+        let (raw_buffers, offsets) = transform_feedback_counter_buffers(counter_buffers);
+
+        fns.ext_transform_feedback.cmd_begin_transform_feedback_ext(
+            cmd,
+            0,
+            raw_buffers.len() as u32,
+            raw_buffers.as_ptr(),
+            offsets.as_ptr(),
+        );
+    }
+
     /// Calls `vkCmdBindDescriptorSets` on the builder.
     ///
     /// Does nothing if the list of descriptor sets is empty, as it would be a no-op and isn't a
@@ -400,6 +494,49 @@ impl UnsafeCommandBufferBuilder {
         );
     }
 
+    /// Calls `vkCmdBindTransformFeedbackBuffersEXT` on the builder.
+    ///
+    /// Does nothing if the list of buffers is empty, as it would be a no-op and isn't a valid
+    /// usage of the command anyway.
+    #[inline]
+    pub unsafe fn bind_transform_feedback_buffers(
+        &mut self,
+        first_binding: u32,
+        buffers: &[Arc<dyn BufferAccess>],
+    ) {
+        let mut raw_buffers: SmallVec<[_; 4]> = SmallVec::new();
+        let mut offsets: SmallVec<[_; 4]> = SmallVec::new();
+        let mut sizes: SmallVec<[_; 4]> = SmallVec::new();
+
+        for buffer in buffers {
+            let inner = buffer.inner();
+            debug_assert!(inner.offset < inner.buffer.size());
+            debug_assert!(inner.offset % 4 == 0);
+            debug_assert!(inner.buffer.usage().transform_feedback_buffer);
+
+            raw_buffers.push(inner.buffer.internal_object());
+            offsets.push(inner.offset);
+            sizes.push(buffer.size());
+        }
+
+        if raw_buffers.is_empty() {
+            return;
+        }
+
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+
+        fns.ext_transform_feedback
+            .cmd_bind_transform_feedback_buffers_ext(
+                cmd,
+                first_binding,
+                raw_buffers.len() as u32,
+                raw_buffers.as_ptr(),
+                offsets.as_ptr(),
+                sizes.as_ptr(),
+            );
+    }
+
     /// Calls `vkCmdBindVertexBuffers` on the builder.
     ///
     /// Does nothing if the list of buffers is empty, as it would be a no-op and isn't a valid
@@ -498,7 +635,7 @@ impl UnsafeCommandBufferBuilder {
                     copy.destination_base_array_layer + copy.layer_count
                         <= destination.num_layers as u32
                 );
-                debug_assert!(copy.source_mip_level < destination.num_mipmap_levels as u32);
+                debug_assert!(copy.source_mip_level < source.num_mipmap_levels as u32);
                 debug_assert!(copy.destination_mip_level < destination.num_mipmap_levels as u32);
 
                 if copy.layer_count == 0 {
@@ -619,7 +756,7 @@ impl UnsafeCommandBufferBuilder {
                     blit.destination_base_array_layer + blit.layer_count
                         <= destination.num_layers as u32
                 );
-                debug_assert!(blit.source_mip_level < destination.num_mipmap_levels as u32);
+                debug_assert!(blit.source_mip_level < source.num_mipmap_levels as u32);
                 debug_assert!(blit.destination_mip_level < destination.num_mipmap_levels as u32);
 
                 if blit.layer_count == 0 {
@@ -686,18 +823,30 @@ impl UnsafeCommandBufferBuilder {
         );
     }
 
-    // TODO: missing structs
-    /*/// Calls `vkCmdClearAttachments` on the builder.
+    /// Calls `vkCmdClearAttachments` on the builder.
     ///
     /// Does nothing if the list of attachments or the list of rects is empty, as it would be a
     /// no-op and isn't a valid usage of the command anyway.
     #[inline]
     pub unsafe fn clear_attachments<A, R>(&mut self, attachments: A, rects: R)
-        where A: IntoIterator<Item = >,
-              R: IntoIterator<Item = >
+    where
+        A: IntoIterator<Item = ClearAttachment>,
+        R: IntoIterator<Item = ClearRect>,
     {
-        let attachments: SmallVec<[_; 16]> = attachments.map().collect();
-        let rects: SmallVec<[_; 4]> = rects.map().collect();
+        let attachments: SmallVec<[_; 16]> = attachments
+            .into_iter()
+            .map(ash::vk::ClearAttachment::from)
+            .collect();
+        let rects: SmallVec<[_; 4]> = rects
+            .into_iter()
+            .filter_map(|rect| {
+                if rect.layer_count == 0 || rect.rect_extent.contains(&0) {
+                    return None;
+                }
+
+                Some(ash::vk::ClearRect::from(rect))
+            })
+            .collect();
 
         if attachments.is_empty() || rects.is_empty() {
             return;
@@ -705,9 +854,14 @@ impl UnsafeCommandBufferBuilder {
 
         let fns = self.device().fns();
         let cmd = self.internal_object();
-        fns.v1_0.CmdClearAttachments(cmd, attachments.len() as u32, attachments.as_ptr(),
-                               rects.len() as u32, rects.as_ptr());
-    }*/
+        fns.v1_0.cmd_clear_attachments(
+            cmd,
+            attachments.len() as u32,
+            attachments.as_ptr(),
+            rects.len() as u32,
+            rects.as_ptr(),
+        );
+    }
 
     /// Calls `vkCmdClearColorImage` on the builder.
     ///
@@ -1114,6 +1268,48 @@ impl UnsafeCommandBufferBuilder {
             .cmd_dispatch(cmd, group_counts[0], group_counts[1], group_counts[2]);
     }
 
+    /// Calls `vkCmdDispatchBase` on the builder.
+    #[inline]
+    pub unsafe fn dispatch_base(&mut self, base_group: [u32; 3], group_counts: [u32; 3]) {
+        debug_assert!({
+            let max_group_counts = self
+                .device()
+                .physical_device()
+                .properties()
+                .max_compute_work_group_count;
+            (0..3).all(|i| {
+                base_group[i] < max_group_counts[i]
+                    && group_counts[i] <= max_group_counts[i] - base_group[i]
+            })
+        });
+
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+
+        if self.device().api_version() >= Version::V1_1 {
+            fns.v1_1.cmd_dispatch_base(
+                cmd,
+                base_group[0],
+                base_group[1],
+                base_group[2],
+                group_counts[0],
+                group_counts[1],
+                group_counts[2],
+            );
+        } else {
+            debug_assert!(self.device().enabled_extensions().khr_device_group);
+            fns.khr_device_group.cmd_dispatch_base_khr(
+                cmd,
+                base_group[0],
+                base_group[1],
+                base_group[2],
+                group_counts[0],
+                group_counts[1],
+                group_counts[2],
+            );
+        }
+    }
+
     /// Calls `vkCmdDispatchIndirect` on the builder.
     #[inline]
     pub unsafe fn dispatch_indirect<B>(&mut self, buffer: &B)
@@ -1174,6 +1370,47 @@ impl UnsafeCommandBufferBuilder {
         );
     }
 
+    /// Calls `vkCmdDrawMultiEXT` on the builder.
+    #[inline]
+    pub unsafe fn draw_multi(
+        &mut self,
+        vertex_info: &[MultiDrawCommand],
+        instance_count: u32,
+        first_instance: u32,
+    ) {
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+        fns.ext_multi_draw.cmd_draw_multi_ext(
+            cmd,
+            vertex_info.len() as u32,
+            vertex_info.as_ptr() as *const ash::vk::MultiDrawInfoEXT,
+            instance_count,
+            first_instance,
+            mem::size_of::<MultiDrawCommand>() as u32,
+        );
+    }
+
+    /// Calls `vkCmdDrawMultiIndexedEXT` on the builder.
+    #[inline]
+    pub unsafe fn draw_multi_indexed(
+        &mut self,
+        index_info: &[MultiDrawIndexedCommand],
+        instance_count: u32,
+        first_instance: u32,
+    ) {
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+        fns.ext_multi_draw.cmd_draw_multi_indexed_ext(
+            cmd,
+            index_info.len() as u32,
+            index_info.as_ptr() as *const ash::vk::MultiDrawIndexedInfoEXT,
+            instance_count,
+            first_instance,
+            mem::size_of::<MultiDrawIndexedCommand>() as u32,
+            ptr::null(),
+        );
+    }
+
     /// Calls `vkCmdDrawIndirect` on the builder.
     #[inline]
     pub unsafe fn draw_indirect<B>(&mut self, buffer: &B, draw_count: u32, stride: u32)
@@ -1202,6 +1439,89 @@ impl UnsafeCommandBufferBuilder {
         );
     }
 
+    /// Calls `vkCmdDrawIndirectCount` on the builder.
+    #[inline]
+    pub unsafe fn draw_indirect_count<B, C>(
+        &mut self,
+        buffer: &B,
+        count_buffer: &C,
+        max_draw_count: u32,
+        stride: u32,
+    ) where
+        B: ?Sized + BufferAccess,
+        C: ?Sized + BufferAccess,
+    {
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+
+        debug_assert!(
+            ((stride % 4) == 0)
+                && stride as usize >= mem::size_of::<ash::vk::DrawIndirectCommand>()
+        );
+
+        let inner = buffer.inner();
+        debug_assert!(inner.offset < inner.buffer.size());
+        debug_assert!(inner.buffer.usage().indirect_buffer);
+
+        let count_inner = count_buffer.inner();
+        debug_assert!(count_inner.offset < count_inner.buffer.size());
+        debug_assert!(count_inner.offset % 4 == 0);
+        debug_assert!(count_inner.buffer.usage().indirect_buffer);
+
+        if self.device().api_version() >= Version::V1_2 {
+            fns.v1_2.cmd_draw_indirect_count(
+                cmd,
+                inner.buffer.internal_object(),
+                inner.offset,
+                count_inner.buffer.internal_object(),
+                count_inner.offset,
+                max_draw_count,
+                stride,
+            );
+        } else {
+            fns.khr_draw_indirect_count.cmd_draw_indirect_count_khr(
+                cmd,
+                inner.buffer.internal_object(),
+                inner.offset,
+                count_inner.buffer.internal_object(),
+                count_inner.offset,
+                max_draw_count,
+                stride,
+            );
+        }
+    }
+
+    /// Calls `vkCmdDrawIndirectByteCountEXT` on the builder.
+    #[inline]
+    pub unsafe fn draw_indirect_byte_count<B>(
+        &mut self,
+        instance_count: u32,
+        first_instance: u32,
+        counter_buffer: &B,
+        counter_offset: u32,
+        vertex_stride: u32,
+    ) where
+        B: ?Sized + BufferAccess,
+    {
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+
+        let inner = counter_buffer.inner();
+        debug_assert!(inner.offset < inner.buffer.size());
+        debug_assert!(inner.offset % 4 == 0);
+        debug_assert!(inner.buffer.usage().indirect_buffer);
+
+        fns.ext_transform_feedback.cmd_draw_indirect_byte_count_ext(
+            cmd,
+            instance_count,
+            first_instance,
+            inner.buffer.internal_object(),
+            inner.offset,
+            counter_offset,
+            vertex_stride,
+        );
+    }
+
     /// Calls `vkCmdDrawIndexedIndirect` on the builder.
     #[inline]
     pub unsafe fn draw_indexed_indirect<B>(&mut self, buffer: &B, draw_count: u32, stride: u32)
@@ -1224,6 +1544,68 @@ impl UnsafeCommandBufferBuilder {
         );
     }
 
+    /// Calls `vkCmdDrawIndexedIndirectCount` on the builder.
+    #[inline]
+    pub unsafe fn draw_indexed_indirect_count<B, C>(
+        &mut self,
+        buffer: &B,
+        count_buffer: &C,
+        max_draw_count: u32,
+        stride: u32,
+    ) where
+        B: ?Sized + BufferAccess,
+        C: ?Sized + BufferAccess,
+    {
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+
+        debug_assert!(
+            ((stride % 4) == 0)
+                && stride as usize >= mem::size_of::<ash::vk::DrawIndexedIndirectCommand>()
+        );
+
+        let inner = buffer.inner();
+        debug_assert!(inner.offset < inner.buffer.size());
+        debug_assert!(inner.buffer.usage().indirect_buffer);
+
+        let count_inner = count_buffer.inner();
+        debug_assert!(count_inner.offset < count_inner.buffer.size());
+        debug_assert!(count_inner.offset % 4 == 0);
+        debug_assert!(count_inner.buffer.usage().indirect_buffer);
+
+        if self.device().api_version() >= Version::V1_2 {
+            fns.v1_2.cmd_draw_indexed_indirect_count(
+                cmd,
+                inner.buffer.internal_object(),
+                inner.offset,
+                count_inner.buffer.internal_object(),
+                count_inner.offset,
+                max_draw_count,
+                stride,
+            );
+        } else {
+            fns.khr_draw_indirect_count
+                .cmd_draw_indexed_indirect_count_khr(
+                    cmd,
+                    inner.buffer.internal_object(),
+                    inner.offset,
+                    count_inner.buffer.internal_object(),
+                    count_inner.offset,
+                    max_draw_count,
+                    stride,
+                );
+        }
+    }
+
+    /// Calls `vkCmdEndConditionalRenderingEXT` on the builder.
+    #[inline]
+    pub unsafe fn end_conditional_rendering(&mut self) {
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+        fns.ext_conditional_rendering
+            .cmd_end_conditional_rendering_ext(cmd);
+    }
+
     /// Calls `vkCmdEndQuery` on the builder.
     #[inline]
     pub unsafe fn end_query(&mut self, query: Query) {
@@ -1241,6 +1623,29 @@ impl UnsafeCommandBufferBuilder {
         fns.v1_0.cmd_end_render_pass(cmd);
     }
 
+    /// Calls `vkCmdEndTransformFeedbackEXT` on the builder.
+    ///
+    /// `counter_buffers` contains the counter buffer of each transform feedback buffer binding,
+    /// starting at binding 0, or `None` to not save the capture position of the binding.
+    #[inline]
+    pub unsafe fn end_transform_feedback(
+        &mut self,
+        counter_buffers: &[Option<Arc<dyn BufferAccess>>],
+    ) {
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+
+        let (raw_buffers, offsets) = transform_feedback_counter_buffers(counter_buffers);
+
+        fns.ext_transform_feedback.cmd_end_transform_feedback_ext(
+            cmd,
+            0,
+            raw_buffers.len() as u32,
+            raw_buffers.as_ptr(),
+            offsets.as_ptr(),
+        );
+    }
+
     /// Calls `vkCmdExecuteCommands` on the builder.
     ///
     /// Does nothing if the list of command buffers is empty, as it would be a no-op and isn't a
@@ -1257,6 +1662,27 @@ impl UnsafeCommandBufferBuilder {
             .cmd_execute_commands(cmd, cbs.raw_cbs.len() as u32, cbs.raw_cbs.as_ptr());
     }
 
+    /// Calls `vkCmdExecuteGeneratedCommandsNV` on the builder.
+    ///
+    /// If `is_preprocessed` is true, the commands must have been preprocessed with
+    /// `preprocess_generated_commands` with the same parameters, and the indirect commands layout
+    /// must have been created with the `explicit_preprocess` usage.
+    #[inline]
+    pub unsafe fn execute_generated_commands(
+        &mut self,
+        is_preprocessed: bool,
+        commands: &UnsafeCommandBufferBuilderGeneratedCommands,
+    ) {
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+        fns.nv_device_generated_commands
+            .cmd_execute_generated_commands_nv(
+                cmd,
+                is_preprocessed as ash::vk::Bool32,
+                &commands.to_vulkan(),
+            );
+    }
+
     /// Calls `vkCmdFillBuffer` on the builder.
     #[inline]
     pub unsafe fn fill_buffer<B>(&mut self, buffer: &B, data: u32)
@@ -1290,6 +1716,8 @@ impl UnsafeCommandBufferBuilder {
         fns.v1_0.cmd_next_subpass(cmd, subpass_contents.into());
     }
 
+    /// Calls `vkCmdPipelineBarrier2KHR` on the builder if the `synchronization2` feature is
+    /// enabled on the device, or `vkCmdPipelineBarrier` otherwise.
     #[inline]
     pub unsafe fn pipeline_barrier(&mut self, command: &UnsafeCommandBufferBuilderPipelineBarrier) {
         // If barrier is empty, don't do anything.
@@ -1304,8 +1732,22 @@ impl UnsafeCommandBufferBuilder {
         let fns = self.device().fns();
         let cmd = self.internal_object();
 
-        debug_assert!(!command.src_stage_mask.is_empty());
-        debug_assert!(!command.dst_stage_mask.is_empty());
+        if self.device().enabled_features().synchronization2 {
+            let dependency_info = ash::vk::DependencyInfoKHR {
+                dependency_flags: command.dependency_flags,
+                memory_barrier_count: command.memory_barriers2.len() as u32,
+                p_memory_barriers: command.memory_barriers2.as_ptr(),
+                buffer_memory_barrier_count: command.buffer_barriers2.len() as u32,
+                p_buffer_memory_barriers: command.buffer_barriers2.as_ptr(),
+                image_memory_barrier_count: command.image_barriers2.len() as u32,
+                p_image_memory_barriers: command.image_barriers2.as_ptr(),
+                ..Default::default()
+            };
+
+            fns.khr_synchronization2
+                .cmd_pipeline_barrier2_khr(cmd, &dependency_info);
+            return;
+        }
 
         fns.v1_0.cmd_pipeline_barrier(
             cmd,
@@ -1321,6 +1763,20 @@ impl UnsafeCommandBufferBuilder {
         );
     }
 
+    /// Calls `vkCmdPreprocessGeneratedCommandsNV` on the builder.
+    ///
+    /// The indirect commands layout must have been created with the `explicit_preprocess` usage.
+    #[inline]
+    pub unsafe fn preprocess_generated_commands(
+        &mut self,
+        commands: &UnsafeCommandBufferBuilderGeneratedCommands,
+    ) {
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+        fns.nv_device_generated_commands
+            .cmd_preprocess_generated_commands_nv(cmd, &commands.to_vulkan());
+    }
+
     /// Calls `vkCmdPushConstants` on the builder.
     #[inline]
     pub unsafe fn push_constants<D>(
@@ -1383,8 +1839,19 @@ impl UnsafeCommandBufferBuilder {
             })
             .unzip();
 
+        // Acceleration structures are written through a structure in the `p_next` chain, which
+        // must stay alive until the update.
+        let acceleration_structure_infos: SmallVec<[_; 8]> = infos
+            .iter()
+            .map(DescriptorWriteInfo::to_vulkan_acceleration_structure)
+            .collect();
+
         // Set the info pointers separately.
-        for (info, write) in infos.iter().zip(writes.iter_mut()) {
+        for ((info, acceleration_structure_info), write) in infos
+            .iter()
+            .zip(acceleration_structure_infos.iter())
+            .zip(writes.iter_mut())
+        {
             match info {
                 DescriptorWriteInfo::Image(info) => {
                     write.descriptor_count = info.len() as u32;
@@ -1398,6 +1865,10 @@ impl UnsafeCommandBufferBuilder {
                     write.descriptor_count = info.len() as u32;
                     write.p_texel_buffer_view = info.as_ptr();
                 }
+                DescriptorWriteInfo::AccelerationStructure(info) => {
+                    write.descriptor_count = info.len() as u32;
+                    write.p_next = acceleration_structure_info as *const _ as *const _;
+                }
             }
 
             debug_assert!(write.descriptor_count != 0);
@@ -1443,6 +1914,111 @@ impl UnsafeCommandBufferBuilder {
         );
     }
 
+    /// Calls `vkCmdResolveImage` on the builder.
+    ///
+    /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
+    /// usage of the command anyway.
+    #[inline]
+    pub unsafe fn resolve_image<S, D, R>(
+        &mut self,
+        source: &S,
+        source_layout: ImageLayout,
+        destination: &D,
+        destination_layout: ImageLayout,
+        regions: R,
+    ) where
+        S: ?Sized + ImageAccess,
+        D: ?Sized + ImageAccess,
+        R: IntoIterator<Item = UnsafeCommandBufferBuilderImageResolve>,
+    {
+        debug_assert_eq!(source.format(), destination.format());
+        debug_assert!(source.format().aspects().color);
+
+        debug_assert_ne!(source.samples(), SampleCount::Sample1);
+        let source = source.inner();
+        debug_assert!(
+            source_layout == ImageLayout::General
+                || source_layout == ImageLayout::TransferSrcOptimal
+        );
+
+        debug_assert_eq!(destination.samples(), SampleCount::Sample1);
+        let destination = destination.inner();
+        debug_assert!(destination.image.format_features().color_attachment);
+        debug_assert!(
+            destination_layout == ImageLayout::General
+                || destination_layout == ImageLayout::TransferDstOptimal
+        );
+
+        let regions: SmallVec<[_; 8]> = regions
+            .into_iter()
+            .filter_map(|resolve| {
+                // TODO: not everything is checked here
+                debug_assert!(
+                    resolve.source_base_array_layer + resolve.layer_count
+                        <= source.num_layers as u32
+                );
+                debug_assert!(
+                    resolve.destination_base_array_layer + resolve.layer_count
+                        <= destination.num_layers as u32
+                );
+                debug_assert!(resolve.source_mip_level < source.num_mipmap_levels as u32);
+                debug_assert!(resolve.destination_mip_level < destination.num_mipmap_levels as u32);
+
+                if resolve.layer_count == 0 {
+                    return None;
+                }
+
+                Some(ash::vk::ImageResolve {
+                    src_subresource: ash::vk::ImageSubresourceLayers {
+                        aspect_mask: ash::vk::ImageAspectFlags::COLOR,
+                        mip_level: resolve.source_mip_level,
+                        base_array_layer: resolve.source_base_array_layer
+                            + source.first_layer as u32,
+                        layer_count: resolve.layer_count,
+                    },
+                    src_offset: ash::vk::Offset3D {
+                        x: resolve.source_offset[0],
+                        y: resolve.source_offset[1],
+                        z: resolve.source_offset[2],
+                    },
+                    dst_subresource: ash::vk::ImageSubresourceLayers {
+                        aspect_mask: ash::vk::ImageAspectFlags::COLOR,
+                        mip_level: resolve.destination_mip_level,
+                        base_array_layer: resolve.destination_base_array_layer
+                            + destination.first_layer as u32,
+                        layer_count: resolve.layer_count,
+                    },
+                    dst_offset: ash::vk::Offset3D {
+                        x: resolve.destination_offset[0],
+                        y: resolve.destination_offset[1],
+                        z: resolve.destination_offset[2],
+                    },
+                    extent: ash::vk::Extent3D {
+                        width: resolve.extent[0],
+                        height: resolve.extent[1],
+                        depth: resolve.extent[2],
+                    },
+                })
+            })
+            .collect();
+
+        if regions.is_empty() {
+            return;
+        }
+
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+        fns.v1_0.cmd_resolve_image(
+            cmd,
+            source.image.internal_object(),
+            source_layout.into(),
+            destination.image.internal_object(),
+            destination_layout.into(),
+            regions.len() as u32,
+            regions.as_ptr(),
+        );
+    }
+
     /// Calls `vkCmdSetBlendConstants` on the builder.
     #[inline]
     pub unsafe fn set_blend_constants(&mut self, constants: [f32; 4]) {
@@ -1638,6 +2214,25 @@ impl UnsafeCommandBufferBuilder {
             .cmd_set_event(cmd, event.internal_object(), stages.into());
     }
 
+    /// Calls `vkCmdSetFragmentShadingRateKHR` on the builder.
+    #[inline]
+    pub unsafe fn set_fragment_shading_rate(
+        &mut self,
+        fragment_size: [u32; 2],
+        combiner_ops: [FragmentShadingRateCombinerOp; 2],
+    ) {
+        debug_assert!(self.device().enabled_extensions().khr_fragment_shading_rate);
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+        let fragment_size = ash::vk::Extent2D {
+            width: fragment_size[0],
+            height: fragment_size[1],
+        };
+        let combiner_ops = [combiner_ops[0].into(), combiner_ops[1].into()];
+        fns.khr_fragment_shading_rate
+            .cmd_set_fragment_shading_rate_khr(cmd, &fragment_size, &combiner_ops);
+    }
+
     /// Calls `vkCmdSetFrontFaceEXT` on the builder.
     #[inline]
     pub unsafe fn set_front_face(&mut self, face: FrontFace) {
@@ -1914,6 +2509,54 @@ impl UnsafeCommandBufferBuilder {
             .cmd_set_scissor_with_count_ext(cmd, scissors.len() as u32, scissors.as_ptr());
     }
 
+    /// Calls `vkCmdSetVertexInputEXT` on the builder.
+    pub unsafe fn set_vertex_input(&mut self, vertex_input: &VertexInput) {
+        debug_assert!(
+            self.device()
+                .enabled_extensions()
+                .ext_vertex_input_dynamic_state
+        );
+        debug_assert!(self.device().enabled_features().vertex_input_dynamic_state);
+
+        let bindings: SmallVec<[_; 8]> = vertex_input
+            .bindings()
+            .map(
+                |(binding, binding_desc)| ash::vk::VertexInputBindingDescription2EXT {
+                    binding,
+                    stride: binding_desc.stride,
+                    input_rate: binding_desc.input_rate.into(),
+                    divisor: match binding_desc.input_rate {
+                        VertexInputRate::Vertex => 1,
+                        VertexInputRate::Instance { divisor } => divisor,
+                    },
+                    ..Default::default()
+                },
+            )
+            .collect();
+        let attributes: SmallVec<[_; 8]> = vertex_input
+            .attributes()
+            .map(
+                |(location, attribute_desc)| ash::vk::VertexInputAttributeDescription2EXT {
+                    location,
+                    binding: attribute_desc.binding,
+                    format: attribute_desc.format.into(),
+                    offset: attribute_desc.offset,
+                    ..Default::default()
+                },
+            )
+            .collect();
+
+        let fns = self.device().fns();
+        let cmd = self.internal_object();
+        fns.ext_vertex_input_dynamic_state.cmd_set_vertex_input_ext(
+            cmd,
+            bindings.len() as u32,
+            bindings.as_ptr(),
+            attributes.len() as u32,
+            attributes.as_ptr(),
+        );
+    }
+
     /// Calls `vkCmdSetViewport` on the builder.
     ///
     /// If the list is empty then the command is automatically ignored.
@@ -1993,10 +2636,9 @@ impl UnsafeCommandBufferBuilder {
         let fns = self.device().fns();
         let cmd = self.internal_object();
 
-        let size = buffer.size();
+        let size = cmp::min(buffer.size(), mem::size_of_val(data) as DeviceSize);
         debug_assert_eq!(size % 4, 0);
         debug_assert!(size <= 65536);
-        debug_assert!(size <= mem::size_of_val(data) as DeviceSize);
 
         let (buffer_handle, offset) = {
             let BufferInner {
@@ -2017,17 +2659,28 @@ impl UnsafeCommandBufferBuilder {
         );
     }
 
-    /// Calls `vkCmdWriteTimestamp` on the builder.
+    /// Calls `vkCmdWriteTimestamp2KHR` on the builder if the `synchronization2` feature is
+    /// enabled on the device, or `vkCmdWriteTimestamp` otherwise.
     #[inline]
     pub unsafe fn write_timestamp(&mut self, query: Query, stage: PipelineStage) {
         let fns = self.device().fns();
         let cmd = self.internal_object();
-        fns.v1_0.cmd_write_timestamp(
-            cmd,
-            stage.into(),
-            query.pool().internal_object(),
-            query.index(),
-        );
+
+        if self.device().enabled_features().synchronization2 {
+            fns.khr_synchronization2.cmd_write_timestamp2_khr(
+                cmd,
+                stage.into(),
+                query.pool().internal_object(),
+                query.index(),
+            );
+        } else {
+            fns.v1_0.cmd_write_timestamp(
+                cmd,
+                stage.into(),
+                query.pool().internal_object(),
+                query.index(),
+            );
+        }
     }
 
     /// Calls `vkCmdBeginDebugUtilsLabelEXT` on the builder.
@@ -2036,7 +2689,7 @@ impl UnsafeCommandBufferBuilder {
     /// The command pool that this command buffer was allocated from must support graphics or
     /// compute operations
     #[inline]
-    pub unsafe fn debug_marker_begin(&mut self, name: &CStr, color: [f32; 4]) {
+    pub unsafe fn begin_debug_utils_label(&mut self, name: &CStr, color: [f32; 4]) {
         let fns = self.device().instance().fns();
         let cmd = self.internal_object();
         let info = ash::vk::DebugUtilsLabelEXT {
@@ -2052,9 +2705,9 @@ impl UnsafeCommandBufferBuilder {
     ///
     /// # Safety
     /// There must be an outstanding `vkCmdBeginDebugUtilsLabelEXT` command prior to the
-    /// `vkQueueEndDebugUtilsLabelEXT` on the queue tha `CommandBuffer` is submitted to.
+    /// `vkCmdEndDebugUtilsLabelEXT` on the queue that the command buffer is submitted to.
     #[inline]
-    pub unsafe fn debug_marker_end(&mut self) {
+    pub unsafe fn end_debug_utils_label(&mut self) {
         let fns = self.device().instance().fns();
         let cmd = self.internal_object();
         fns.ext_debug_utils.cmd_end_debug_utils_label_ext(cmd);
@@ -2066,7 +2719,7 @@ impl UnsafeCommandBufferBuilder {
     /// The command pool that this command buffer was allocated from must support graphics or
     /// compute operations
     #[inline]
-    pub unsafe fn debug_marker_insert(&mut self, name: &CStr, color: [f32; 4]) {
+    pub unsafe fn insert_debug_utils_label(&mut self, name: &CStr, color: [f32; 4]) {
         let fns = self.device().instance().fns();
         let cmd = self.internal_object();
         let info = ash::vk::DebugUtilsLabelEXT {
@@ -2095,6 +2748,27 @@ unsafe impl VulkanObject for UnsafeCommandBufferBuilder {
     }
 }
 
+// Turns the counter buffers of `vkCmdBeginTransformFeedbackEXT` and
+// `vkCmdEndTransformFeedbackEXT` into the raw handles and offsets. A counter buffer of `None` is
+// passed as a null handle, which the implementation ignores.
+fn transform_feedback_counter_buffers(
+    counter_buffers: &[Option<Arc<dyn BufferAccess>>],
+) -> (SmallVec<[ash::vk::Buffer; 4]>, SmallVec<[DeviceSize; 4]>) {
+    counter_buffers
+        .iter()
+        .map(|counter_buffer| match counter_buffer {
+            Some(counter_buffer) => {
+                let inner = counter_buffer.inner();
+                debug_assert!(inner.offset < inner.buffer.size());
+                debug_assert!(inner.offset % 4 == 0);
+                debug_assert!(inner.buffer.usage().transform_feedback_counter_buffer);
+                (inner.buffer.internal_object(), inner.offset)
+            }
+            None => (ash::vk::Buffer::null(), 0),
+        })
+        .unzip()
+}
+
 /// Prototype for a `vkCmdBindVertexBuffers`.
 pub struct UnsafeCommandBufferBuilderBindVertexBuffer {
     // Raw handles of the buffers to bind.
@@ -2158,6 +2832,113 @@ impl UnsafeCommandBufferBuilderExecuteCommands {
     }
 }
 
+/// Prototype for a `vkCmdExecuteGeneratedCommandsNV` or a `vkCmdPreprocessGeneratedCommandsNV`.
+pub struct UnsafeCommandBufferBuilderGeneratedCommands {
+    pipeline: ash::vk::Pipeline,
+    indirect_commands_layout: ash::vk::IndirectCommandsLayoutNV,
+    streams: SmallVec<[ash::vk::IndirectCommandsStreamNV; 4]>,
+    sequences_count: u32,
+    preprocess_buffer: (ash::vk::Buffer, DeviceSize, DeviceSize),
+    sequences_count_buffer: (ash::vk::Buffer, DeviceSize),
+    sequences_index_buffer: (ash::vk::Buffer, DeviceSize),
+}
+
+impl UnsafeCommandBufferBuilderGeneratedCommands {
+    /// Builds a new prototype that generates up to `sequences_count` sequences of commands with
+    /// `pipeline` and `indirect_commands_layout`.
+    ///
+    /// `preprocess_buffer` is where the implementation stores the commands while they are
+    /// generated. Its size must be at least the size that is returned by
+    /// `IndirectCommandsLayout::memory_requirements`.
+    #[inline]
+    pub fn new<B>(
+        pipeline: &GraphicsPipeline,
+        indirect_commands_layout: &IndirectCommandsLayout,
+        sequences_count: u32,
+        preprocess_buffer: &B,
+    ) -> UnsafeCommandBufferBuilderGeneratedCommands
+    where
+        B: ?Sized + BufferAccess,
+    {
+        let inner = preprocess_buffer.inner();
+
+        UnsafeCommandBufferBuilderGeneratedCommands {
+            pipeline: pipeline.internal_object(),
+            indirect_commands_layout: indirect_commands_layout.internal_object(),
+            streams: SmallVec::new(),
+            sequences_count,
+            preprocess_buffer: (
+                inner.buffer.internal_object(),
+                inner.offset,
+                preprocess_buffer.size(),
+            ),
+            sequences_count_buffer: (ash::vk::Buffer::null(), 0),
+            sequences_index_buffer: (ash::vk::Buffer::null(), 0),
+        }
+    }
+
+    /// Adds a stream that the commands are read from. Streams must be added in the order of
+    /// their index in the indirect commands layout.
+    #[inline]
+    pub fn add_stream<B>(&mut self, buffer: &B)
+    where
+        B: ?Sized + BufferAccess,
+    {
+        let inner = buffer.inner();
+        debug_assert!(inner.buffer.usage().indirect_buffer);
+
+        self.streams.push(ash::vk::IndirectCommandsStreamNV {
+            buffer: inner.buffer.internal_object(),
+            offset: inner.offset,
+        });
+    }
+
+    /// Reads the number of sequences from `buffer` instead. `sequences_count` is then the
+    /// maximum number of sequences.
+    #[inline]
+    pub fn sequences_count_buffer<B>(&mut self, buffer: &B)
+    where
+        B: ?Sized + BufferAccess,
+    {
+        let inner = buffer.inner();
+        debug_assert!(inner.buffer.usage().indirect_buffer);
+        self.sequences_count_buffer = (inner.buffer.internal_object(), inner.offset);
+    }
+
+    /// Reads the index of each sequence in the streams from `buffer`. The indirect commands
+    /// layout must have been created with the `indexed_sequences` usage.
+    #[inline]
+    pub fn sequences_index_buffer<B>(&mut self, buffer: &B)
+    where
+        B: ?Sized + BufferAccess,
+    {
+        let inner = buffer.inner();
+        debug_assert!(inner.buffer.usage().indirect_buffer);
+        self.sequences_index_buffer = (inner.buffer.internal_object(), inner.offset);
+    }
+
+    // The returned struct borrows the streams of `self`.
+    #[inline]
+    fn to_vulkan(&self) -> ash::vk::GeneratedCommandsInfoNV {
+        ash::vk::GeneratedCommandsInfoNV {
+            pipeline_bind_point: ash::vk::PipelineBindPoint::GRAPHICS,
+            pipeline: self.pipeline,
+            indirect_commands_layout: self.indirect_commands_layout,
+            stream_count: self.streams.len() as u32,
+            p_streams: self.streams.as_ptr(),
+            sequences_count: self.sequences_count,
+            preprocess_buffer: self.preprocess_buffer.0,
+            preprocess_offset: self.preprocess_buffer.1,
+            preprocess_size: self.preprocess_buffer.2,
+            sequences_count_buffer: self.sequences_count_buffer.0,
+            sequences_count_offset: self.sequences_count_buffer.1,
+            sequences_index_buffer: self.sequences_index_buffer.0,
+            sequences_index_offset: self.sequences_index_buffer.1,
+            ..Default::default()
+        }
+    }
+}
+
 // TODO: move somewhere else?
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct UnsafeCommandBufferBuilderColorImageClear {
@@ -2219,6 +3000,19 @@ pub struct UnsafeCommandBufferBuilderImageBlit {
     pub destination_bottom_right: [i32; 3],
 }
 
+// TODO: move somewhere else?
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UnsafeCommandBufferBuilderImageResolve {
+    pub source_mip_level: u32,
+    pub destination_mip_level: u32,
+    pub source_base_array_layer: u32,
+    pub destination_base_array_layer: u32,
+    pub layer_count: u32,
+    pub source_offset: [i32; 3],
+    pub destination_offset: [i32; 3],
+    pub extent: [u32; 3],
+}
+
 /// Command that adds a pipeline barrier to a command buffer builder.
 ///
 /// A pipeline barrier is a low-level system-ish command that is often necessary for safety. By
@@ -2232,10 +3026,19 @@ pub struct UnsafeCommandBufferBuilderImageBlit {
 /// > **Note**: We use a builder-like API here so that users can pass multiple buffers or images of
 /// > multiple different types. Doing so with a single function would be very tedious in terms of
 /// > API.
+///
+/// The barriers are stored both in the form of `VK_KHR_synchronization2`, where each barrier has
+/// its own stages, and in the form of Vulkan 1.0, where the stages of all the barriers are merged
+/// together. The first one is used if the `synchronization2` feature is enabled on the device.
 pub struct UnsafeCommandBufferBuilderPipelineBarrier {
+    dependency_flags: ash::vk::DependencyFlags,
+
+    memory_barriers2: SmallVec<[ash::vk::MemoryBarrier2KHR; 2]>,
+    buffer_barriers2: SmallVec<[ash::vk::BufferMemoryBarrier2KHR; 8]>,
+    image_barriers2: SmallVec<[ash::vk::ImageMemoryBarrier2KHR; 8]>,
+
     src_stage_mask: ash::vk::PipelineStageFlags,
     dst_stage_mask: ash::vk::PipelineStageFlags,
-    dependency_flags: ash::vk::DependencyFlags,
     memory_barriers: SmallVec<[ash::vk::MemoryBarrier; 2]>,
     buffer_barriers: SmallVec<[ash::vk::BufferMemoryBarrier; 8]>,
     image_barriers: SmallVec<[ash::vk::ImageMemoryBarrier; 8]>,
@@ -2246,9 +3049,12 @@ impl UnsafeCommandBufferBuilderPipelineBarrier {
     #[inline]
     pub fn new() -> UnsafeCommandBufferBuilderPipelineBarrier {
         UnsafeCommandBufferBuilderPipelineBarrier {
+            dependency_flags: ash::vk::DependencyFlags::BY_REGION,
+            memory_barriers2: SmallVec::new(),
+            buffer_barriers2: SmallVec::new(),
+            image_barriers2: SmallVec::new(),
             src_stage_mask: ash::vk::PipelineStageFlags::empty(),
             dst_stage_mask: ash::vk::PipelineStageFlags::empty(),
-            dependency_flags: ash::vk::DependencyFlags::BY_REGION,
             memory_barriers: SmallVec::new(),
             buffer_barriers: SmallVec::new(),
             image_barriers: SmallVec::new(),
@@ -2264,10 +3070,17 @@ impl UnsafeCommandBufferBuilderPipelineBarrier {
     /// Merges another pipeline builder into this one.
     #[inline]
     pub fn merge(&mut self, other: UnsafeCommandBufferBuilderPipelineBarrier) {
-        self.src_stage_mask |= other.src_stage_mask;
-        self.dst_stage_mask |= other.dst_stage_mask;
         self.dependency_flags &= other.dependency_flags;
 
+        self.memory_barriers2
+            .extend(other.memory_barriers2.into_iter());
+        self.buffer_barriers2
+            .extend(other.buffer_barriers2.into_iter());
+        self.image_barriers2
+            .extend(other.image_barriers2.into_iter());
+
+        self.src_stage_mask |= other.src_stage_mask;
+        self.dst_stage_mask |= other.dst_stage_mask;
         self.memory_barriers
             .extend(other.memory_barriers.into_iter());
         self.buffer_barriers
@@ -2298,6 +3111,27 @@ impl UnsafeCommandBufferBuilderPipelineBarrier {
         debug_assert_ne!(source, PipelineStages::none());
         debug_assert_ne!(destination, PipelineStages::none());
 
+        self.add_stages(source, destination);
+
+        // With `VK_KHR_synchronization2`, an execution dependency is a memory barrier without
+        // any access. All of them are merged into one, like in the Vulkan 1.0 form.
+        if let Some(barrier) = self.memory_barriers2.iter_mut().find(|barrier| {
+            barrier.src_access_mask.is_empty() && barrier.dst_access_mask.is_empty()
+        }) {
+            barrier.src_stage_mask |= ash::vk::PipelineStageFlags2KHR::from(source);
+            barrier.dst_stage_mask |= ash::vk::PipelineStageFlags2KHR::from(destination);
+        } else {
+            self.memory_barriers2.push(ash::vk::MemoryBarrier2KHR {
+                src_stage_mask: source.into(),
+                dst_stage_mask: destination.into(),
+                ..Default::default()
+            });
+        }
+    }
+
+    // Adds the stages to the stage masks of the Vulkan 1.0 form of the barrier.
+    #[inline]
+    fn add_stages(&mut self, source: PipelineStages, destination: PipelineStages) {
         self.src_stage_mask |= ash::vk::PipelineStageFlags::from(source);
         self.dst_stage_mask |= ash::vk::PipelineStageFlags::from(destination);
     }
@@ -2322,9 +3156,25 @@ impl UnsafeCommandBufferBuilderPipelineBarrier {
     ) {
         debug_assert!(source_access.is_compatible_with(&source_stage));
         debug_assert!(destination_access.is_compatible_with(&destination_stage));
+        debug_assert_ne!(source_stage, PipelineStages::none());
+        debug_assert_ne!(destination_stage, PipelineStages::none());
+
+        if !by_region {
+            self.dependency_flags = ash::vk::DependencyFlags::empty();
+        }
+
+        // Only writes need to be made available, reads in the source scope have no effect.
+        let source_access = source_access.writes();
 
-        self.add_execution_dependency(source_stage, destination_stage, by_region);
+        self.add_stages(source_stage, destination_stage);
 
+        self.memory_barriers2.push(ash::vk::MemoryBarrier2KHR {
+            src_stage_mask: source_stage.into(),
+            src_access_mask: source_access.into(),
+            dst_stage_mask: destination_stage.into(),
+            dst_access_mask: destination_access.into(),
+            ..Default::default()
+        });
         self.memory_barriers.push(ash::vk::MemoryBarrier {
             src_access_mask: source_access.into(),
             dst_access_mask: destination_access.into(),
@@ -2363,8 +3213,23 @@ impl UnsafeCommandBufferBuilderPipelineBarrier {
     {
         debug_assert!(source_access.is_compatible_with(&source_stage));
         debug_assert!(destination_access.is_compatible_with(&destination_stage));
+        debug_assert_ne!(source_stage, PipelineStages::none());
+        debug_assert_ne!(destination_stage, PipelineStages::none());
 
-        self.add_execution_dependency(source_stage, destination_stage, by_region);
+        let source_access = source_access.writes();
+
+        // Without writes to make available or an ownership transfer, the barrier is only an
+        // execution dependency.
+        if !source_access.has_writes() && queue_transfer.is_none() {
+            self.add_execution_dependency(source_stage, destination_stage, by_region);
+            return;
+        }
+
+        if !by_region {
+            self.dependency_flags = ash::vk::DependencyFlags::empty();
+        }
+
+        self.add_stages(source_stage, destination_stage);
 
         debug_assert!(size <= buffer.size());
         let BufferInner {
@@ -2379,6 +3244,40 @@ impl UnsafeCommandBufferBuilderPipelineBarrier {
             (ash::vk::QUEUE_FAMILY_IGNORED, ash::vk::QUEUE_FAMILY_IGNORED)
         };
 
+        // Merge with a barrier for the same range of the same buffer, if there is one.
+        if queue_transfer.is_none() {
+            if let Some(index) = self.buffer_barriers.iter().position(|barrier| {
+                barrier.buffer == buffer.internal_object()
+                    && barrier.offset == offset
+                    && barrier.size == size
+                    && barrier.src_queue_family_index == ash::vk::QUEUE_FAMILY_IGNORED
+            }) {
+                let barrier2 = &mut self.buffer_barriers2[index];
+                barrier2.src_stage_mask |= ash::vk::PipelineStageFlags2KHR::from(source_stage);
+                barrier2.src_access_mask |= ash::vk::AccessFlags2KHR::from(source_access);
+                barrier2.dst_stage_mask |= ash::vk::PipelineStageFlags2KHR::from(destination_stage);
+                barrier2.dst_access_mask |= ash::vk::AccessFlags2KHR::from(destination_access);
+
+                let barrier = &mut self.buffer_barriers[index];
+                barrier.src_access_mask |= ash::vk::AccessFlags::from(source_access);
+                barrier.dst_access_mask |= ash::vk::AccessFlags::from(destination_access);
+                return;
+            }
+        }
+
+        self.buffer_barriers2
+            .push(ash::vk::BufferMemoryBarrier2KHR {
+                src_stage_mask: source_stage.into(),
+                src_access_mask: source_access.into(),
+                dst_stage_mask: destination_stage.into(),
+                dst_access_mask: destination_access.into(),
+                src_queue_family_index: src_queue,
+                dst_queue_family_index: dest_queue,
+                buffer: buffer.internal_object(),
+                offset,
+                size,
+                ..Default::default()
+            });
         self.buffer_barriers.push(ash::vk::BufferMemoryBarrier {
             src_access_mask: source_access.into(),
             dst_access_mask: destination_access.into(),
@@ -2426,8 +3325,16 @@ impl UnsafeCommandBufferBuilderPipelineBarrier {
     {
         debug_assert!(source_access.is_compatible_with(&source_stage));
         debug_assert!(destination_access.is_compatible_with(&destination_stage));
+        debug_assert_ne!(source_stage, PipelineStages::none());
+        debug_assert_ne!(destination_stage, PipelineStages::none());
 
-        self.add_execution_dependency(source_stage, destination_stage, by_region);
+        if !by_region {
+            self.dependency_flags = ash::vk::DependencyFlags::empty();
+        }
+
+        let source_access = source_access.writes();
+
+        self.add_stages(source_stage, destination_stage);
 
         debug_assert_ne!(new_layout, ImageLayout::Undefined);
         debug_assert_ne!(new_layout, ImageLayout::Preinitialized);
@@ -2450,7 +3357,62 @@ impl UnsafeCommandBufferBuilderPipelineBarrier {
         // TODO: Let user choose
         let aspects = image.format().aspects();
         let image = image.inner();
+        let subresource_range = ash::vk::ImageSubresourceRange {
+            aspect_mask: aspects.into(),
+            base_mip_level: mipmaps.start + image.first_mipmap_level as u32,
+            level_count: mipmaps.end - mipmaps.start,
+            base_array_layer: layers.start + image.first_layer as u32,
+            layer_count: layers.end - layers.start,
+        };
 
+        // Merge with a barrier for the same subresources of the same image, if there is one. If
+        // that barrier transitions the image to the layout that this one transitions from, the
+        // two transitions are done as one.
+        if queue_transfer.is_none() {
+            let old_layout = ash::vk::ImageLayout::from(current_layout);
+            let new_layout = ash::vk::ImageLayout::from(new_layout);
+
+            if let Some(index) = self.image_barriers.iter().position(|barrier| {
+                let range = &barrier.subresource_range;
+
+                barrier.image == image.image.internal_object()
+                    && range.aspect_mask == subresource_range.aspect_mask
+                    && range.base_mip_level == subresource_range.base_mip_level
+                    && range.level_count == subresource_range.level_count
+                    && range.base_array_layer == subresource_range.base_array_layer
+                    && range.layer_count == subresource_range.layer_count
+                    && barrier.src_queue_family_index == ash::vk::QUEUE_FAMILY_IGNORED
+                    && (barrier.new_layout == old_layout
+                        || (barrier.old_layout == old_layout && barrier.new_layout == new_layout))
+            }) {
+                let barrier2 = &mut self.image_barriers2[index];
+                barrier2.src_stage_mask |= ash::vk::PipelineStageFlags2KHR::from(source_stage);
+                barrier2.src_access_mask |= ash::vk::AccessFlags2KHR::from(source_access);
+                barrier2.dst_stage_mask |= ash::vk::PipelineStageFlags2KHR::from(destination_stage);
+                barrier2.dst_access_mask |= ash::vk::AccessFlags2KHR::from(destination_access);
+                barrier2.new_layout = new_layout;
+
+                let barrier = &mut self.image_barriers[index];
+                barrier.src_access_mask |= ash::vk::AccessFlags::from(source_access);
+                barrier.dst_access_mask |= ash::vk::AccessFlags::from(destination_access);
+                barrier.new_layout = new_layout;
+                return;
+            }
+        }
+
+        self.image_barriers2.push(ash::vk::ImageMemoryBarrier2KHR {
+            src_stage_mask: source_stage.into(),
+            src_access_mask: source_access.into(),
+            dst_stage_mask: destination_stage.into(),
+            dst_access_mask: destination_access.into(),
+            old_layout: current_layout.into(),
+            new_layout: new_layout.into(),
+            src_queue_family_index: src_queue,
+            dst_queue_family_index: dest_queue,
+            image: image.image.internal_object(),
+            subresource_range,
+            ..Default::default()
+        });
         self.image_barriers.push(ash::vk::ImageMemoryBarrier {
             src_access_mask: source_access.into(),
             dst_access_mask: destination_access.into(),
@@ -2459,13 +3421,7 @@ impl UnsafeCommandBufferBuilderPipelineBarrier {
             src_queue_family_index: src_queue,
             dst_queue_family_index: dest_queue,
             image: image.image.internal_object(),
-            subresource_range: ash::vk::ImageSubresourceRange {
-                aspect_mask: aspects.into(),
-                base_mip_level: mipmaps.start + image.first_mipmap_level as u32,
-                level_count: mipmaps.end - mipmaps.start,
-                base_array_layer: layers.start + image.first_layer as u32,
-                layer_count: layers.end - layers.start,
-            },
+            subresource_range,
             ..Default::default()
         });
     }
@@ -2505,3 +3461,260 @@ unsafe impl VulkanObject for UnsafeCommandBuffer {
         self.command_buffer
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::UnsafeCommandBufferBuilderPipelineBarrier;
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::format::Format;
+    use crate::image::AttachmentImage;
+    use crate::image::ImageLayout;
+    use crate::image::ImageUsage;
+    use crate::sync::AccessFlags;
+    use crate::sync::PipelineStages;
+
+    #[test]
+    fn merge_buffer_barriers() {
+        let (device, _) = gfx_dev_and_queue!();
+        let buffer =
+            CpuAccessibleBuffer::from_data(device, BufferUsage::all(), false, 0u32).unwrap();
+        let transfer = PipelineStages {
+            transfer: true,
+            ..PipelineStages::none()
+        };
+        let compute_shader = PipelineStages {
+            compute_shader: true,
+            ..PipelineStages::none()
+        };
+
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+
+        unsafe {
+            for _ in 0..2 {
+                barrier.add_buffer_memory_barrier(
+                    buffer.as_ref(),
+                    transfer,
+                    AccessFlags {
+                        transfer_write: true,
+                        ..AccessFlags::none()
+                    },
+                    compute_shader,
+                    AccessFlags {
+                        shader_read: true,
+                        ..AccessFlags::none()
+                    },
+                    true,
+                    None,
+                    0,
+                    4,
+                );
+            }
+
+            // Only reads in the source scope, so this is only an execution dependency.
+            barrier.add_buffer_memory_barrier(
+                buffer.as_ref(),
+                compute_shader,
+                AccessFlags {
+                    shader_read: true,
+                    ..AccessFlags::none()
+                },
+                transfer,
+                AccessFlags {
+                    transfer_write: true,
+                    ..AccessFlags::none()
+                },
+                true,
+                None,
+                0,
+                4,
+            );
+        }
+
+        assert_eq!(barrier.buffer_barriers.len(), 1);
+        assert_eq!(barrier.buffer_barriers2.len(), 1);
+        assert_eq!(barrier.memory_barriers2.len(), 1);
+        assert!(barrier.memory_barriers.is_empty());
+    }
+
+    #[test]
+    fn synchronization2_stages_downgraded() {
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+
+        unsafe {
+            barrier.add_memory_barrier(
+                PipelineStages {
+                    copy: true,
+                    ..PipelineStages::none()
+                },
+                AccessFlags {
+                    transfer_write: true,
+                    ..AccessFlags::none()
+                },
+                PipelineStages {
+                    pre_rasterization_shaders: true,
+                    ..PipelineStages::none()
+                },
+                AccessFlags {
+                    shader_storage_read: true,
+                    ..AccessFlags::none()
+                },
+                true,
+            );
+        }
+
+        // The synchronization2 form keeps the stages and accesses as they are.
+        assert_eq!(barrier.memory_barriers2.len(), 1);
+        assert_eq!(
+            barrier.memory_barriers2[0].src_stage_mask,
+            ash::vk::PipelineStageFlags2KHR::COPY
+        );
+        assert_eq!(
+            barrier.memory_barriers2[0].dst_stage_mask,
+            ash::vk::PipelineStageFlags2KHR::PRE_RASTERIZATION_SHADERS
+        );
+        assert_eq!(
+            barrier.memory_barriers2[0].dst_access_mask,
+            ash::vk::AccessFlags2KHR::SHADER_STORAGE_READ
+        );
+
+        // The Vulkan 1.0 form, used if synchronization2 isn't enabled, replaces them with the
+        // ones that include them.
+        assert_eq!(
+            barrier.src_stage_mask,
+            ash::vk::PipelineStageFlags::TRANSFER
+        );
+        assert_eq!(
+            barrier.dst_stage_mask,
+            ash::vk::PipelineStageFlags::VERTEX_SHADER
+                | ash::vk::PipelineStageFlags::TESSELLATION_CONTROL_SHADER
+                | ash::vk::PipelineStageFlags::TESSELLATION_EVALUATION_SHADER
+                | ash::vk::PipelineStageFlags::GEOMETRY_SHADER
+        );
+        assert_eq!(barrier.memory_barriers.len(), 1);
+        assert_eq!(
+            barrier.memory_barriers[0].src_access_mask,
+            ash::vk::AccessFlags::TRANSFER_WRITE
+        );
+        assert_eq!(
+            barrier.memory_barriers[0].dst_access_mask,
+            ash::vk::AccessFlags::SHADER_READ
+        );
+    }
+
+    #[test]
+    fn merge_chained_image_barriers() {
+        let (device, _) = gfx_dev_and_queue!();
+        let image = AttachmentImage::with_usage(
+            device,
+            [16, 16],
+            Format::R8G8B8A8_UNORM,
+            ImageUsage {
+                transfer_destination: true,
+                sampled: true,
+                ..ImageUsage::none()
+            },
+        )
+        .unwrap();
+        let transfer = PipelineStages {
+            transfer: true,
+            ..PipelineStages::none()
+        };
+        let fragment_shader = PipelineStages {
+            fragment_shader: true,
+            ..PipelineStages::none()
+        };
+
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+
+        unsafe {
+            barrier.add_image_memory_barrier(
+                image.as_ref(),
+                0..1,
+                0..1,
+                PipelineStages {
+                    top_of_pipe: true,
+                    ..PipelineStages::none()
+                },
+                AccessFlags::none(),
+                transfer,
+                AccessFlags {
+                    transfer_write: true,
+                    ..AccessFlags::none()
+                },
+                true,
+                None,
+                ImageLayout::Undefined,
+                ImageLayout::TransferDstOptimal,
+            );
+
+            // Continues from the layout of the previous barrier, so it is merged into it.
+            barrier.add_image_memory_barrier(
+                image.as_ref(),
+                0..1,
+                0..1,
+                transfer,
+                AccessFlags {
+                    transfer_write: true,
+                    ..AccessFlags::none()
+                },
+                fragment_shader,
+                AccessFlags {
+                    shader_read: true,
+                    ..AccessFlags::none()
+                },
+                true,
+                None,
+                ImageLayout::TransferDstOptimal,
+                ImageLayout::ShaderReadOnlyOptimal,
+            );
+        }
+
+        assert_eq!(barrier.image_barriers.len(), 1);
+        assert_eq!(barrier.image_barriers2.len(), 1);
+        assert_eq!(
+            barrier.image_barriers[0].old_layout,
+            ash::vk::ImageLayout::UNDEFINED
+        );
+        assert_eq!(
+            barrier.image_barriers[0].new_layout,
+            ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+        );
+        assert_eq!(
+            barrier.image_barriers2[0].old_layout,
+            ash::vk::ImageLayout::UNDEFINED
+        );
+        assert_eq!(
+            barrier.image_barriers2[0].new_layout,
+            ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+        );
+
+        unsafe {
+            // Doesn't start from the layout that the image is transitioned to, so it can't be
+            // merged.
+            barrier.add_image_memory_barrier(
+                image.as_ref(),
+                0..1,
+                0..1,
+                fragment_shader,
+                AccessFlags::none(),
+                transfer,
+                AccessFlags {
+                    transfer_write: true,
+                    ..AccessFlags::none()
+                },
+                true,
+                None,
+                ImageLayout::General,
+                ImageLayout::TransferDstOptimal,
+            );
+        }
+
+        assert_eq!(barrier.image_barriers.len(), 2);
+        assert_eq!(barrier.image_barriers2.len(), 2);
+        assert_eq!(
+            barrier.image_barriers[0].new_layout,
+            ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+        );
+    }
+}
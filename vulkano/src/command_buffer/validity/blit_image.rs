@@ -7,9 +7,13 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use crate::command_buffer::validity::copy_image::aspects_supported;
+use crate::command_buffer::validity::CheckCopyRegionError;
+use crate::command_buffer::ImageBlit;
 use crate::device::Device;
 use crate::format::NumericType;
 use crate::image::ImageAccess;
+use crate::image::ImageAspects;
 use crate::image::ImageDimensions;
 use crate::image::SampleCount;
 use crate::sampler::Filter;
@@ -19,7 +23,8 @@ use std::fmt;
 
 /// Checks whether a blit image command is valid.
 ///
-/// Note that this doesn't check whether `layer_count` is equal to 0. TODO: change that?
+/// Same as `check_blit_image_regions` with a single region, that blits the color aspect of the
+/// images, or all their depth and stencil aspects if they have no color.
 ///
 /// # Panic
 ///
@@ -40,6 +45,53 @@ pub fn check_blit_image<S, D>(
     layer_count: u32,
     filter: Filter,
 ) -> Result<(), CheckBlitImageError>
+where
+    S: ?Sized + ImageAccess,
+    D: ?Sized + ImageAccess,
+{
+    // Negative offsets wrap around, and are rejected as out of range.
+    let to_u32 = |offset: [i32; 3]| [offset[0] as u32, offset[1] as u32, offset[2] as u32];
+    let region = ImageBlit {
+        aspects: blit_aspects(source.format().aspects()),
+        source_mip_level,
+        source_base_array_layer,
+        source_offsets: [to_u32(source_top_left), to_u32(source_bottom_right)],
+        destination_mip_level,
+        destination_base_array_layer,
+        destination_offsets: [
+            to_u32(destination_top_left),
+            to_u32(destination_bottom_right),
+        ],
+        layer_count,
+    };
+
+    check_blit_image_regions(device, source, destination, &[region], filter)
+}
+
+/// Returns the aspects that are blitted from an image of the given aspects, if the user doesn't
+/// choose them.
+pub(in super::super) fn blit_aspects(aspects: ImageAspects) -> ImageAspects {
+    ImageAspects {
+        color: aspects.color,
+        depth: !aspects.color && aspects.depth,
+        stencil: !aspects.color && aspects.stencil,
+        ..ImageAspects::none()
+    }
+}
+
+/// Checks whether a blit image command with the given regions is valid.
+///
+/// # Panic
+///
+/// - Panics if the source or the destination was not created with `device`.
+///
+pub fn check_blit_image_regions<S, D>(
+    device: &Device,
+    source: &S,
+    destination: &D,
+    regions: &[ImageBlit],
+    filter: Filter,
+) -> Result<(), CheckBlitImageError>
 where
     S: ?Sized + ImageAccess,
     D: ?Sized + ImageAccess,
@@ -64,7 +116,9 @@ where
         return Err(CheckBlitImageError::MissingTransferDestinationUsage);
     }
 
-    if !source_inner.image.format_features().blit_src {
+    let source_features = source_inner.image.format_features();
+
+    if !source_features.blit_src {
         return Err(CheckBlitImageError::SourceFormatNotSupported);
     }
 
@@ -100,125 +154,155 @@ where
         }
     }
 
-    let source_dimensions = match source.dimensions().mipmap_dimensions(source_mip_level) {
-        Some(d) => d,
-        None => return Err(CheckBlitImageError::SourceCoordinatesOutOfRange),
-    };
-
-    let destination_dimensions = match destination
-        .dimensions()
-        .mipmap_dimensions(destination_mip_level)
-    {
-        Some(d) => d,
-        None => return Err(CheckBlitImageError::DestinationCoordinatesOutOfRange),
-    };
-
-    if source_base_array_layer + layer_count > source_dimensions.array_layers() {
-        return Err(CheckBlitImageError::SourceCoordinatesOutOfRange);
-    }
-
-    if destination_base_array_layer + layer_count > destination_dimensions.array_layers() {
-        return Err(CheckBlitImageError::DestinationCoordinatesOutOfRange);
-    }
+    match filter {
+        Filter::Nearest => (),
+        Filter::Linear => {
+            if !source_features.sampled_image_filter_linear {
+                return Err(CheckBlitImageError::FilterNotSupportedByFormat);
+            }
+        }
+        Filter::Cubic => {
+            if !(device.enabled_extensions().ext_filter_cubic
+                || device.enabled_extensions().img_filter_cubic)
+            {
+                return Err(CheckBlitImageError::FilterCubicExtensionNotEnabled);
+            }
 
-    if source_top_left[0] < 0 || source_top_left[0] > source_dimensions.width() as i32 {
-        return Err(CheckBlitImageError::SourceCoordinatesOutOfRange);
-    }
+            if !source_features.img_sampled_image_filter_cubic {
+                return Err(CheckBlitImageError::FilterNotSupportedByFormat);
+            }
 
-    if source_top_left[1] < 0 || source_top_left[1] > source_dimensions.height() as i32 {
-        return Err(CheckBlitImageError::SourceCoordinatesOutOfRange);
+            if !matches!(source.dimensions(), ImageDimensions::Dim2d { .. }) {
+                return Err(CheckBlitImageError::FilterCubicNot2d);
+            }
+        }
     }
 
-    if source_top_left[2] < 0 || source_top_left[2] > source_dimensions.depth() as i32 {
-        return Err(CheckBlitImageError::SourceCoordinatesOutOfRange);
-    }
+    for (region_index, region) in regions.iter().enumerate() {
+        let aspects = region.aspects;
 
-    if source_bottom_right[0] < 0 || source_bottom_right[0] > source_dimensions.width() as i32 {
-        return Err(CheckBlitImageError::SourceCoordinatesOutOfRange);
-    }
+        if (aspects.color && (aspects.depth || aspects.stencil))
+            || !aspects_supported(source.format().aspects(), aspects)
+        {
+            return Err(CheckBlitImageError::SourceRegion {
+                region_index,
+                error: CheckCopyRegionError::AspectsNotAllowed,
+            });
+        }
 
-    if source_bottom_right[1] < 0 || source_bottom_right[1] > source_dimensions.height() as i32 {
-        return Err(CheckBlitImageError::SourceCoordinatesOutOfRange);
-    }
+        if !aspects_supported(destination.format().aspects(), aspects) {
+            return Err(CheckBlitImageError::DestinationRegion {
+                region_index,
+                error: CheckCopyRegionError::AspectsNotAllowed,
+            });
+        }
 
-    if source_bottom_right[2] < 0 || source_bottom_right[2] > source_dimensions.depth() as i32 {
-        return Err(CheckBlitImageError::SourceCoordinatesOutOfRange);
+        check_blit_subresource_region(
+            source,
+            region.source_mip_level,
+            region.source_base_array_layer,
+            region.layer_count,
+            region.source_offsets,
+        )
+        .map_err(|error| CheckBlitImageError::SourceRegion {
+            region_index,
+            error,
+        })?;
+
+        check_blit_subresource_region(
+            destination,
+            region.destination_mip_level,
+            region.destination_base_array_layer,
+            region.layer_count,
+            region.destination_offsets,
+        )
+        .map_err(|error| CheckBlitImageError::DestinationRegion {
+            region_index,
+            error,
+        })?;
     }
 
-    if destination_top_left[0] < 0
-        || destination_top_left[0] > destination_dimensions.width() as i32
-    {
-        return Err(CheckBlitImageError::DestinationCoordinatesOutOfRange);
-    }
+    Ok(())
+}
 
-    if destination_top_left[1] < 0
-        || destination_top_left[1] > destination_dimensions.height() as i32
-    {
-        return Err(CheckBlitImageError::DestinationCoordinatesOutOfRange);
+// Checks whether the region between the two corners `offsets` fits in the given mip level and
+// array layers of `image`.
+fn check_blit_subresource_region<I>(
+    image: &I,
+    mip_level: u32,
+    base_array_layer: u32,
+    layer_count: u32,
+    offsets: [[u32; 3]; 2],
+) -> Result<(), CheckCopyRegionError>
+where
+    I: ?Sized + ImageAccess,
+{
+    if layer_count == 0 {
+        return Err(CheckCopyRegionError::Empty);
     }
 
-    if destination_top_left[2] < 0
-        || destination_top_left[2] > destination_dimensions.depth() as i32
-    {
-        return Err(CheckBlitImageError::DestinationCoordinatesOutOfRange);
-    }
+    let mip_levels = image.mipmap_levels();
+    let dimensions = match image.dimensions().mipmap_dimensions(mip_level) {
+        Some(dimensions) if mip_level < mip_levels => dimensions,
+        _ => {
+            return Err(CheckCopyRegionError::MipLevelOutOfRange {
+                mip_level,
+                mip_levels,
+            })
+        }
+    };
 
-    if destination_bottom_right[0] < 0
-        || destination_bottom_right[0] > destination_dimensions.width() as i32
+    let array_layers = dimensions.array_layers();
+    if base_array_layer
+        .checked_add(layer_count)
+        .map_or(true, |end| end > array_layers)
     {
-        return Err(CheckBlitImageError::DestinationCoordinatesOutOfRange);
+        return Err(CheckCopyRegionError::ArrayLayersOutOfRange {
+            base_array_layer,
+            layer_count,
+            array_layers,
+        });
     }
 
-    if destination_bottom_right[1] < 0
-        || destination_bottom_right[1] > destination_dimensions.height() as i32
-    {
-        return Err(CheckBlitImageError::DestinationCoordinatesOutOfRange);
-    }
+    let size = dimensions.width_height_depth();
+    if (0..3).any(|i| offsets[0][i] > size[i] || offsets[1][i] > size[i]) {
+        let mut offset = [0; 3];
+        let mut extent = [0; 3];
+        for i in 0..3 {
+            offset[i] = offsets[0][i].min(offsets[1][i]);
+            extent[i] = offsets[0][i].max(offsets[1][i]) - offset[i];
+        }
 
-    if destination_bottom_right[2] < 0
-        || destination_bottom_right[2] > destination_dimensions.depth() as i32
-    {
-        return Err(CheckBlitImageError::DestinationCoordinatesOutOfRange);
+        return Err(CheckCopyRegionError::TexelsOutOfRange {
+            offset,
+            extent,
+            mip_level_size: size,
+        });
     }
 
-    match source_dimensions {
+    match dimensions {
         ImageDimensions::Dim1d { .. } => {
-            if source_top_left[1] != 0 || source_bottom_right[1] != 1 {
-                return Err(CheckBlitImageError::IncompatibleRangeForImageType);
-            }
-            if source_top_left[2] != 0 || source_bottom_right[2] != 1 {
-                return Err(CheckBlitImageError::IncompatibleRangeForImageType);
+            if offsets[0][1] != 0 || offsets[1][1] != 1 || offsets[0][2] != 0 || offsets[1][2] != 1
+            {
+                return Err(CheckCopyRegionError::IncompatibleRangeForImageType);
             }
         }
         ImageDimensions::Dim2d { .. } => {
-            if source_top_left[2] != 0 || source_bottom_right[2] != 1 {
-                return Err(CheckBlitImageError::IncompatibleRangeForImageType);
-            }
-        }
-        ImageDimensions::Dim3d { .. } => {}
-    }
-
-    match destination_dimensions {
-        ImageDimensions::Dim1d { .. } => {
-            if destination_top_left[1] != 0 || destination_bottom_right[1] != 1 {
-                return Err(CheckBlitImageError::IncompatibleRangeForImageType);
-            }
-            if destination_top_left[2] != 0 || destination_bottom_right[2] != 1 {
-                return Err(CheckBlitImageError::IncompatibleRangeForImageType);
+            if offsets[0][2] != 0 || offsets[1][2] != 1 {
+                return Err(CheckCopyRegionError::IncompatibleRangeForImageType);
             }
         }
-        ImageDimensions::Dim2d { .. } => {
-            if destination_top_left[2] != 0 || destination_bottom_right[2] != 1 {
-                return Err(CheckBlitImageError::IncompatibleRangeForImageType);
+        ImageDimensions::Dim3d { .. } => {
+            if base_array_layer != 0 || layer_count != 1 {
+                return Err(CheckCopyRegionError::IncompatibleRangeForImageType);
             }
         }
-        ImageDimensions::Dim3d { .. } => {}
     }
 
     Ok(())
 }
 
-/// Error that can happen from `check_clear_color_image`.
+/// Error that can happen from `check_blit_image`.
 #[derive(Debug, Copy, Clone)]
 pub enum CheckBlitImageError {
     /// The source is missing the transfer source usage.
@@ -231,6 +315,13 @@ pub enum CheckBlitImageError {
     DestinationFormatNotSupported,
     /// You must use the nearest filter when blitting depth/stencil images.
     DepthStencilNearestMandatory,
+    /// The `Cubic` filter requires enabling the `VK_EXT_filter_cubic` or `VK_IMG_filter_cubic`
+    /// extension.
+    FilterCubicExtensionNotEnabled,
+    /// The `Cubic` filter can only be used with a two-dimensional source image.
+    FilterCubicNot2d,
+    /// The format of the source image doesn't support filtering with the requested filter.
+    FilterNotSupportedByFormat,
     /// The format of the source and destination must be equal when blitting depth/stencil images.
     DepthStencilFormatMismatch,
     /// The types of the source format and the destination format aren't compatible.
@@ -240,15 +331,32 @@ pub enum CheckBlitImageError {
     },
     /// Blitting between multisampled images is forbidden.
     UnexpectedMultisampled,
-    /// The offsets, array layers and/or mipmap levels are out of range in the source image.
-    SourceCoordinatesOutOfRange,
-    /// The offsets, array layers and/or mipmap levels are out of range in the destination image.
-    DestinationCoordinatesOutOfRange,
-    /// The top-left and/or bottom-right coordinates are incompatible with the image type.
-    IncompatibleRangeForImageType,
+    /// A region is invalid for the source image.
+    SourceRegion {
+        /// The index of the region.
+        region_index: usize,
+        /// Why the region is invalid.
+        error: CheckCopyRegionError,
+    },
+    /// A region is invalid for the destination image.
+    DestinationRegion {
+        /// The index of the region.
+        region_index: usize,
+        /// Why the region is invalid.
+        error: CheckCopyRegionError,
+    },
 }
 
-impl error::Error for CheckBlitImageError {}
+impl error::Error for CheckBlitImageError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            CheckBlitImageError::SourceRegion { ref error, .. } => Some(error),
+            CheckBlitImageError::DestinationRegion { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
 
 impl fmt::Display for CheckBlitImageError {
     #[inline]
@@ -272,6 +380,16 @@ impl fmt::Display for CheckBlitImageError {
                 CheckBlitImageError::DepthStencilNearestMandatory => {
                     "you must use the nearest filter when blitting depth/stencil images"
                 }
+                CheckBlitImageError::FilterCubicExtensionNotEnabled => {
+                    "the cubic filter requires the VK_EXT_filter_cubic or VK_IMG_filter_cubic \
+                     extension"
+                }
+                CheckBlitImageError::FilterCubicNot2d => {
+                    "the cubic filter can only be used with a two-dimensional source image"
+                }
+                CheckBlitImageError::FilterNotSupportedByFormat => {
+                    "the format of the source image doesn't support the requested filter"
+                }
                 CheckBlitImageError::DepthStencilFormatMismatch => {
                     "the format of the source and destination must be equal when blitting \
                  depth/stencil images"
@@ -282,16 +400,11 @@ impl fmt::Display for CheckBlitImageError {
                 CheckBlitImageError::UnexpectedMultisampled => {
                     "blitting between multisampled images is forbidden"
                 }
-                CheckBlitImageError::SourceCoordinatesOutOfRange => {
-                    "the offsets, array layers and/or mipmap levels are out of range in the source \
-                 image"
-                }
-                CheckBlitImageError::DestinationCoordinatesOutOfRange => {
-                    "the offsets, array layers and/or mipmap levels are out of range in the \
-                 destination image"
+                CheckBlitImageError::SourceRegion { .. } => {
+                    "a region is invalid for the source image"
                 }
-                CheckBlitImageError::IncompatibleRangeForImageType => {
-                    "the top-left and/or bottom-right coordinates are incompatible with the image type"
+                CheckBlitImageError::DestinationRegion { .. } => {
+                    "a region is invalid for the destination image"
                 }
             }
         )
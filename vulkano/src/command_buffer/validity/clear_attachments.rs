@@ -0,0 +1,256 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::command_buffer::ClearAttachment;
+use crate::command_buffer::ClearRect;
+use crate::format::ClearValue;
+use crate::format::NumericType;
+use crate::render_pass::Subpass;
+use std::error;
+use std::fmt;
+
+/// Checks whether the given attachments and rects can be cleared in `subpass`.
+///
+/// `framebuffer_dimensions` are the width, height and number of layers of the framebuffer, if
+/// known. If `None`, the rects are not checked against the framebuffer.
+pub fn check_clear_attachments(
+    subpass: &Subpass,
+    framebuffer_dimensions: Option<[u32; 3]>,
+    attachments: &[ClearAttachment],
+    rects: &[ClearRect],
+) -> Result<(), CheckClearAttachmentsError> {
+    let subpass_desc = subpass.subpass_desc();
+    let render_pass_desc = subpass.render_pass().desc();
+
+    for attachment in attachments {
+        match *attachment {
+            ClearAttachment::Color(clear_value, color_attachment) => {
+                let atch_num = match subpass_desc
+                    .color_attachments
+                    .get(color_attachment as usize)
+                {
+                    Some(&(atch_num, _)) => atch_num,
+                    None => {
+                        return Err(CheckClearAttachmentsError::InvalidColorAttachmentIndex(
+                            color_attachment,
+                        ))
+                    }
+                };
+
+                let format = render_pass_desc.attachments()[atch_num].format;
+                let matches = match (format.type_color(), clear_value) {
+                    (Some(NumericType::SINT), ClearValue::Int(_)) => true,
+                    (Some(NumericType::UINT), ClearValue::Uint(_)) => true,
+                    (Some(NumericType::SINT), _) | (Some(NumericType::UINT), _) => false,
+                    (Some(_), ClearValue::Float(_)) => true,
+                    _ => false,
+                };
+
+                if !matches {
+                    return Err(CheckClearAttachmentsError::ColorClearValueMismatch(
+                        color_attachment,
+                    ));
+                }
+            }
+            ClearAttachment::Depth(_) => {
+                if !subpass.has_depth() {
+                    return Err(CheckClearAttachmentsError::NoDepthAttachment);
+                }
+            }
+            ClearAttachment::Stencil(_) => {
+                if !subpass.has_stencil() {
+                    return Err(CheckClearAttachmentsError::NoStencilAttachment);
+                }
+            }
+            ClearAttachment::DepthStencil(_) => {
+                if !subpass.has_depth() {
+                    return Err(CheckClearAttachmentsError::NoDepthAttachment);
+                }
+
+                if !subpass.has_stencil() {
+                    return Err(CheckClearAttachmentsError::NoStencilAttachment);
+                }
+            }
+        }
+    }
+
+    let multiview = render_pass_desc
+        .multiview()
+        .as_ref()
+        .map_or(false, |multiview| {
+            multiview.view_masks[subpass.index() as usize] != 0
+        });
+
+    for (rect_index, rect) in rects.iter().enumerate() {
+        if rect.rect_extent.contains(&0) || rect.layer_count == 0 {
+            return Err(CheckClearAttachmentsError::RectEmpty { rect_index });
+        }
+
+        // With multiview, the layers that are cleared are the views of the subpass.
+        if multiview && (rect.base_array_layer != 0 || rect.layer_count != 1) {
+            return Err(CheckClearAttachmentsError::MultiviewLayersInvalid { rect_index });
+        }
+
+        if let Some([width, height, layers]) = framebuffer_dimensions {
+            let out_of_bounds = rect.rect_offset[0]
+                .checked_add(rect.rect_extent[0])
+                .map_or(true, |end| end > width)
+                || rect.rect_offset[1]
+                    .checked_add(rect.rect_extent[1])
+                    .map_or(true, |end| end > height)
+                || (!multiview
+                    && rect
+                        .base_array_layer
+                        .checked_add(rect.layer_count)
+                        .map_or(true, |end| end > layers));
+
+            if out_of_bounds {
+                return Err(CheckClearAttachmentsError::RectOutOfBounds { rect_index });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to clear attachments.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckClearAttachmentsError {
+    /// The clear value of a color attachment is not a color value that matches the format of the
+    /// attachment.
+    ColorClearValueMismatch(u32),
+    /// The index of a color attachment is not less than the number of color attachments of the
+    /// subpass.
+    InvalidColorAttachmentIndex(u32),
+    /// With multiview enabled, the base array layer of a rect must be 0 and its layer count must
+    /// be 1.
+    MultiviewLayersInvalid {
+        /// The index of the rect.
+        rect_index: usize,
+    },
+    /// The depth aspect was cleared, but the subpass has no depth attachment.
+    NoDepthAttachment,
+    /// The stencil aspect was cleared, but the subpass has no stencil attachment.
+    NoStencilAttachment,
+    /// The extent or the layer count of a rect is 0.
+    RectEmpty {
+        /// The index of the rect.
+        rect_index: usize,
+    },
+    /// A rect is not inside the framebuffer.
+    RectOutOfBounds {
+        /// The index of the rect.
+        rect_index: usize,
+    },
+}
+
+impl error::Error for CheckClearAttachmentsError {}
+
+impl fmt::Display for CheckClearAttachmentsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                Self::ColorClearValueMismatch(_) => {
+                    "the clear value of a color attachment doesn't match the format of the \
+                     attachment"
+                }
+                Self::InvalidColorAttachmentIndex(_) => {
+                    "the index of a color attachment is out of range for the subpass"
+                }
+                Self::MultiviewLayersInvalid { .. } => {
+                    "with multiview enabled, the base array layer of a rect must be 0 and its \
+                     layer count must be 1"
+                }
+                Self::NoDepthAttachment => "the subpass has no depth attachment",
+                Self::NoStencilAttachment => "the subpass has no stencil attachment",
+                Self::RectEmpty { .. } => "the extent or the layer count of a rect is 0",
+                Self::RectOutOfBounds { .. } => "a rect is not inside the framebuffer",
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_buffer::validity;
+    use crate::command_buffer::ClearAttachment;
+    use crate::command_buffer::ClearRect;
+    use crate::format::ClearValue;
+    use crate::format::Format;
+    use crate::render_pass::Subpass;
+
+    #[test]
+    fn check_attachments_and_rects() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let render_pass = crate::single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: DontCare,
+                    format: Format::R8G8B8A8_UNORM,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )
+        .unwrap();
+        let subpass = Subpass::from(render_pass, 0).unwrap();
+
+        let rect = ClearRect {
+            rect_offset: [0, 0],
+            rect_extent: [8, 8],
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+        assert_eq!(
+            validity::check_clear_attachments(
+                &subpass,
+                Some([16, 16, 1]),
+                &[ClearAttachment::Color(ClearValue::Float([0.0; 4]), 0)],
+                &[rect],
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validity::check_clear_attachments(
+                &subpass,
+                Some([16, 16, 1]),
+                &[ClearAttachment::Color(ClearValue::Uint([0; 4]), 0)],
+                &[rect],
+            ),
+            Err(validity::CheckClearAttachmentsError::ColorClearValueMismatch(0))
+        );
+        assert_eq!(
+            validity::check_clear_attachments(
+                &subpass,
+                Some([16, 16, 1]),
+                &[ClearAttachment::Depth(1.0)],
+                &[rect],
+            ),
+            Err(validity::CheckClearAttachmentsError::NoDepthAttachment)
+        );
+        assert_eq!(
+            validity::check_clear_attachments(
+                &subpass,
+                Some([4, 4, 1]),
+                &[ClearAttachment::Color(ClearValue::Float([0.0; 4]), 0)],
+                &[rect],
+            ),
+            Err(validity::CheckClearAttachmentsError::RectOutOfBounds { rect_index: 0 })
+        );
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::buffer::TypedBufferAccess;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::VulkanObject;
+use std::error;
+use std::fmt;
+
+/// Checks whether conditional rendering can be begun with the given buffer.
+pub fn check_begin_conditional_rendering<B>(
+    device: &Device,
+    buffer: &B,
+) -> Result<(), CheckBeginConditionalRenderingError>
+where
+    B: ?Sized + TypedBufferAccess<Content = u32>,
+{
+    assert_eq!(
+        buffer.inner().buffer.device().internal_object(),
+        device.internal_object()
+    );
+
+    if !device.enabled_extensions().ext_conditional_rendering
+        || !device.enabled_features().conditional_rendering
+    {
+        return Err(CheckBeginConditionalRenderingError::FeatureNotEnabled);
+    }
+
+    if !buffer.inner().buffer.usage().conditional_rendering {
+        return Err(CheckBeginConditionalRenderingError::BufferMissingUsage);
+    }
+
+    if buffer.inner().offset % 4 != 0 {
+        return Err(CheckBeginConditionalRenderingError::OffsetNotAligned);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to begin conditional rendering.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckBeginConditionalRenderingError {
+    /// The "conditional rendering" usage must be enabled on the buffer.
+    BufferMissingUsage,
+    /// The `ext_conditional_rendering` extension and the `conditional_rendering` feature must be
+    /// enabled.
+    FeatureNotEnabled,
+    /// The offset of the buffer must be a multiple of 4.
+    OffsetNotAligned,
+}
+
+impl error::Error for CheckBeginConditionalRenderingError {}
+
+impl fmt::Display for CheckBeginConditionalRenderingError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                Self::BufferMissingUsage => {
+                    "the conditional rendering usage must be enabled on the buffer"
+                }
+                Self::FeatureNotEnabled => {
+                    "the ext_conditional_rendering extension and the conditional_rendering \
+                     feature must be enabled"
+                }
+                Self::OffsetNotAligned => "the offset of the buffer must be a multiple of 4",
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::command_buffer::validity;
+
+    #[test]
+    fn feature_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+        let buffer =
+            CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                .unwrap();
+
+        assert_eq!(
+            validity::check_begin_conditional_rendering(&device, buffer.as_ref()),
+            Err(validity::CheckBeginConditionalRenderingError::FeatureNotEnabled)
+        );
+    }
+}
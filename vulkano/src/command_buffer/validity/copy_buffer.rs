@@ -7,7 +7,9 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use crate::buffer::BufferAccess;
 use crate::buffer::TypedBufferAccess;
+use crate::command_buffer::BufferCopy;
 use crate::device::Device;
 use crate::device::DeviceOwned;
 use crate::DeviceSize;
@@ -31,6 +33,34 @@ where
     S: ?Sized + TypedBufferAccess<Content = T>,
     D: ?Sized + TypedBufferAccess<Content = T>,
     T: ?Sized,
+{
+    let copy_size = cmp::min(source.size(), destination.size());
+    let region = BufferCopy {
+        source_offset: 0,
+        destination_offset: 0,
+        size: copy_size,
+    };
+
+    check_copy_buffer_regions(device, source, destination, &[region])?;
+
+    Ok(CheckCopyBuffer { copy_size })
+}
+
+/// Checks whether a copy buffer command with the given regions is valid.
+///
+/// # Panic
+///
+/// - Panics if the source and destination were not created with `device`.
+///
+pub fn check_copy_buffer_regions<S, D>(
+    device: &Device,
+    source: &S,
+    destination: &D,
+    regions: &[BufferCopy],
+) -> Result<(), CheckCopyBufferError>
+where
+    S: ?Sized + BufferAccess,
+    D: ?Sized + BufferAccess,
 {
     assert_eq!(
         source.inner().buffer.device().internal_object(),
@@ -49,18 +79,52 @@ where
         return Err(CheckCopyBufferError::DestinationMissingTransferUsage);
     }
 
-    let copy_size = cmp::min(source.size(), destination.size());
-
+    // The same buffer can't be used twice by one command of the builder, even if the ranges are
+    // disjoint.
     if source.conflict_key() == destination.conflict_key() {
         return Err(CheckCopyBufferError::OverlappingRanges);
-    } else {
-        debug_assert!(destination.conflict_key() != source.conflict_key());
     }
 
-    Ok(CheckCopyBuffer { copy_size })
+    for (region_index, region) in regions.iter().enumerate() {
+        if region.size == 0 {
+            return Err(CheckCopyBufferError::RegionEmpty { region_index });
+        }
+
+        if region.source_offset + region.size > source.size() {
+            return Err(CheckCopyBufferError::SourceRegionOutOfRange {
+                region_index,
+                end: region.source_offset + region.size,
+                buffer_size: source.size(),
+            });
+        }
+
+        if region.destination_offset + region.size > destination.size() {
+            return Err(CheckCopyBufferError::DestinationRegionOutOfRange {
+                region_index,
+                end: region.destination_offset + region.size,
+                buffer_size: destination.size(),
+            });
+        }
+    }
+
+    // The destination regions must not overlap each other, otherwise the result is undefined.
+    for (region_index, region) in regions.iter().enumerate() {
+        let start = region.destination_offset;
+        let end = start + region.size;
+
+        if let Some(other_region_index) = regions[..region_index].iter().position(|other| {
+            other.destination_offset < end && start < other.destination_offset + other.size
+        }) {
+            return Err(CheckCopyBufferError::OverlappingDestinationRegions {
+                region_index: other_region_index,
+                other_region_index: region_index,
+            });
+        }
+    }
+
+    Ok(())
 }
 
-/// Information returned if `check_copy_buffer` succeeds.
 pub struct CheckCopyBuffer {
     /// Size of the transfer in bytes.
     ///
@@ -69,8 +133,8 @@ pub struct CheckCopyBuffer {
     pub copy_size: DeviceSize,
 }
 
-/// Error that can happen from `check_copy_buffer`.
-#[derive(Debug, Copy, Clone)]
+/// Error that can happen from `check_copy_buffer` and `check_copy_buffer_regions`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum CheckCopyBufferError {
     /// The source buffer is missing the transfer source usage.
     SourceMissingTransferUsage,
@@ -78,6 +142,36 @@ pub enum CheckCopyBufferError {
     DestinationMissingTransferUsage,
     /// The source and destination are overlapping.
     OverlappingRanges,
+    /// Two regions write to overlapping ranges of the destination.
+    OverlappingDestinationRegions {
+        /// The index of the first region.
+        region_index: usize,
+        /// The index of the second region.
+        other_region_index: usize,
+    },
+    /// The size of a region is 0.
+    RegionEmpty {
+        /// The index of the region.
+        region_index: usize,
+    },
+    /// A region reads past the end of the source buffer.
+    SourceRegionOutOfRange {
+        /// The index of the region.
+        region_index: usize,
+        /// The offset in bytes of the end of the region.
+        end: DeviceSize,
+        /// The size in bytes of the source buffer.
+        buffer_size: DeviceSize,
+    },
+    /// A region writes past the end of the destination buffer.
+    DestinationRegionOutOfRange {
+        /// The index of the region.
+        region_index: usize,
+        /// The offset in bytes of the end of the region.
+        end: DeviceSize,
+        /// The size in bytes of the destination buffer.
+        buffer_size: DeviceSize,
+    },
 }
 
 impl error::Error for CheckCopyBufferError {}
@@ -97,7 +191,95 @@ impl fmt::Display for CheckCopyBufferError {
                 }
                 CheckCopyBufferError::OverlappingRanges =>
                     "the source and destination are overlapping",
+                CheckCopyBufferError::OverlappingDestinationRegions { .. } => {
+                    "two regions write to overlapping ranges of the destination"
+                }
+                CheckCopyBufferError::RegionEmpty { .. } => "the size of a region is 0",
+                CheckCopyBufferError::SourceRegionOutOfRange { .. } => {
+                    "a region reads past the end of the source buffer"
+                }
+                CheckCopyBufferError::DestinationRegionOutOfRange { .. } => {
+                    "a region writes past the end of the destination buffer"
+                }
             }
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::command_buffer::validity;
+    use crate::command_buffer::BufferCopy;
+
+    #[test]
+    fn region_out_of_range() {
+        let (device, _) = gfx_dev_and_queue!();
+        let source =
+            CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), false, 0..16u32)
+                .unwrap();
+        let destination =
+            CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), false, 0..8u32)
+                .unwrap();
+        let region = BufferCopy {
+            source_offset: 0,
+            destination_offset: 16,
+            size: 32,
+        };
+
+        assert_eq!(
+            validity::check_copy_buffer_regions(
+                &device,
+                source.as_ref(),
+                destination.as_ref(),
+                &[region]
+            ),
+            Err(
+                validity::CheckCopyBufferError::DestinationRegionOutOfRange {
+                    region_index: 0,
+                    end: 48,
+                    buffer_size: 32,
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn overlapping_destination_regions() {
+        let (device, _) = gfx_dev_and_queue!();
+        let source =
+            CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), false, 0..16u32)
+                .unwrap();
+        let destination =
+            CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), false, 0..16u32)
+                .unwrap();
+        let regions = [
+            BufferCopy {
+                source_offset: 0,
+                destination_offset: 0,
+                size: 16,
+            },
+            BufferCopy {
+                source_offset: 32,
+                destination_offset: 12,
+                size: 16,
+            },
+        ];
+
+        assert_eq!(
+            validity::check_copy_buffer_regions(
+                &device,
+                source.as_ref(),
+                destination.as_ref(),
+                &regions
+            ),
+            Err(
+                validity::CheckCopyBufferError::OverlappingDestinationRegions {
+                    region_index: 0,
+                    other_region_index: 1,
+                }
+            )
+        );
+    }
+}
@@ -7,17 +7,21 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use crate::command_buffer::ImageCopy;
 use crate::device::Device;
 use crate::format::NumericType;
 use crate::image::ImageAccess;
+use crate::image::ImageAspects;
 use crate::image::ImageDimensions;
+use crate::DeviceSize;
 use crate::VulkanObject;
 use std::error;
 use std::fmt;
 
 /// Checks whether a copy image command is valid.
 ///
-/// Note that this doesn't check whether `layer_count` is equal to 0. TODO: change that?
+/// Same as `check_copy_image_regions` with a single region, that copies all the aspects of the
+/// format that the images have in common.
 ///
 /// # Panic
 ///
@@ -36,6 +40,60 @@ pub fn check_copy_image<S, D>(
     extent: [u32; 3],
     layer_count: u32,
 ) -> Result<(), CheckCopyImageError>
+where
+    S: ?Sized + ImageAccess,
+    D: ?Sized + ImageAccess,
+{
+    let region = ImageCopy {
+        aspects: copy_aspects(source.format().aspects(), destination.format().aspects()),
+        source_mip_level,
+        source_base_array_layer,
+        // Negative offsets wrap around, and are rejected as out of range.
+        source_offset: [
+            source_offset[0] as u32,
+            source_offset[1] as u32,
+            source_offset[2] as u32,
+        ],
+        destination_mip_level,
+        destination_base_array_layer,
+        destination_offset: [
+            destination_offset[0] as u32,
+            destination_offset[1] as u32,
+            destination_offset[2] as u32,
+        ],
+        layer_count,
+        extent,
+    };
+
+    check_copy_image_regions(device, source, destination, &[region])
+}
+
+/// Returns the aspects that are copied between images of the given aspects, if the user doesn't
+/// choose them.
+pub(in super::super) fn copy_aspects(
+    source_aspects: ImageAspects,
+    destination_aspects: ImageAspects,
+) -> ImageAspects {
+    ImageAspects {
+        color: source_aspects.color,
+        depth: !source_aspects.color && source_aspects.depth && destination_aspects.depth,
+        stencil: !source_aspects.color && source_aspects.stencil && destination_aspects.stencil,
+        ..ImageAspects::none()
+    }
+}
+
+/// Checks whether a copy image command with the given regions is valid.
+///
+/// # Panic
+///
+/// - Panics if the source or the destination was not created with `device`.
+///
+pub fn check_copy_image_regions<S, D>(
+    device: &Device,
+    source: &S,
+    destination: &D,
+    regions: &[ImageCopy],
+) -> Result<(), CheckCopyImageError>
 where
     S: ?Sized + ImageAccess,
     D: ?Sized + ImageAccess,
@@ -86,89 +144,151 @@ where
         }
     }
 
-    let source_dimensions = match source.dimensions().mipmap_dimensions(source_mip_level) {
-        Some(d) => d,
-        None => return Err(CheckCopyImageError::SourceCoordinatesOutOfRange),
-    };
+    for (region_index, region) in regions.iter().enumerate() {
+        let aspects = region.aspects;
 
-    let destination_dimensions = match destination
-        .dimensions()
-        .mipmap_dimensions(destination_mip_level)
-    {
-        Some(d) => d,
-        None => return Err(CheckCopyImageError::DestinationCoordinatesOutOfRange),
-    };
+        if aspects.color && (aspects.depth || aspects.stencil) {
+            return Err(CheckCopyImageError::SourceRegion {
+                region_index,
+                error: CheckCopyRegionError::AspectsNotAllowed,
+            });
+        }
 
-    if source_base_array_layer + layer_count > source_dimensions.array_layers() {
-        return Err(CheckCopyImageError::SourceCoordinatesOutOfRange);
-    }
+        if !aspects_supported(source.format().aspects(), aspects) {
+            return Err(CheckCopyImageError::SourceRegion {
+                region_index,
+                error: CheckCopyRegionError::AspectsNotAllowed,
+            });
+        }
 
-    if destination_base_array_layer + layer_count > destination_dimensions.array_layers() {
-        return Err(CheckCopyImageError::DestinationCoordinatesOutOfRange);
-    }
+        if !aspects_supported(destination.format().aspects(), aspects) {
+            return Err(CheckCopyImageError::DestinationRegion {
+                region_index,
+                error: CheckCopyRegionError::AspectsNotAllowed,
+            });
+        }
 
-    if source_offset[0] < 0 || source_offset[0] as u32 + extent[0] > source_dimensions.width() {
-        return Err(CheckCopyImageError::SourceCoordinatesOutOfRange);
-    }
+        check_image_subresource_region(
+            source,
+            region.source_mip_level,
+            region.source_base_array_layer,
+            region.layer_count,
+            region.source_offset,
+            region.extent,
+        )
+        .map_err(|error| CheckCopyImageError::SourceRegion {
+            region_index,
+            error,
+        })?;
 
-    if source_offset[1] < 0 || source_offset[1] as u32 + extent[1] > source_dimensions.height() {
-        return Err(CheckCopyImageError::SourceCoordinatesOutOfRange);
+        check_image_subresource_region(
+            destination,
+            region.destination_mip_level,
+            region.destination_base_array_layer,
+            region.layer_count,
+            region.destination_offset,
+            region.extent,
+        )
+        .map_err(|error| CheckCopyImageError::DestinationRegion {
+            region_index,
+            error,
+        })?;
     }
 
-    if source_offset[2] < 0 || source_offset[2] as u32 + extent[2] > source_dimensions.depth() {
-        return Err(CheckCopyImageError::SourceCoordinatesOutOfRange);
-    }
+    // TODO: check memory overlap?
 
-    if destination_offset[0] < 0
-        || destination_offset[0] as u32 + extent[0] > destination_dimensions.width()
-    {
-        return Err(CheckCopyImageError::DestinationCoordinatesOutOfRange);
-    }
+    Ok(())
+}
 
-    if destination_offset[1] < 0
-        || destination_offset[1] as u32 + extent[1] > destination_dimensions.height()
-    {
-        return Err(CheckCopyImageError::DestinationCoordinatesOutOfRange);
+// Returns true if `aspects` is not empty and only contains aspects of `format_aspects`.
+pub(super) fn aspects_supported(format_aspects: ImageAspects, aspects: ImageAspects) -> bool {
+    let format_aspects = ash::vk::ImageAspectFlags::from(format_aspects);
+    let aspects = ash::vk::ImageAspectFlags::from(aspects);
+    !aspects.is_empty() && format_aspects.contains(aspects)
+}
+
+/// Checks whether a region of a copy fits in the given mip level and array layers of `image`.
+pub(in super::super) fn check_image_subresource_region<I>(
+    image: &I,
+    mip_level: u32,
+    base_array_layer: u32,
+    layer_count: u32,
+    offset: [u32; 3],
+    extent: [u32; 3],
+) -> Result<(), CheckCopyRegionError>
+where
+    I: ?Sized + ImageAccess,
+{
+    if layer_count == 0 || extent.contains(&0) {
+        return Err(CheckCopyRegionError::Empty);
     }
 
-    if destination_offset[2] < 0
-        || destination_offset[2] as u32 + extent[2] > destination_dimensions.depth()
+    let mip_levels = image.mipmap_levels();
+    let dimensions = match image.dimensions().mipmap_dimensions(mip_level) {
+        Some(dimensions) if mip_level < mip_levels => dimensions,
+        _ => {
+            return Err(CheckCopyRegionError::MipLevelOutOfRange {
+                mip_level,
+                mip_levels,
+            })
+        }
+    };
+
+    let array_layers = dimensions.array_layers();
+    if base_array_layer
+        .checked_add(layer_count)
+        .map_or(true, |end| end > array_layers)
     {
-        return Err(CheckCopyImageError::DestinationCoordinatesOutOfRange);
+        return Err(CheckCopyRegionError::ArrayLayersOutOfRange {
+            base_array_layer,
+            layer_count,
+            array_layers,
+        });
     }
 
-    match source_dimensions {
-        ImageDimensions::Dim1d { .. } => {
-            if source_offset[1] != 0 || extent[1] != 1 {
-                return Err(CheckCopyImageError::IncompatibleRangeForImageType);
-            }
-            if source_offset[2] != 0 || extent[2] != 1 {
-                return Err(CheckCopyImageError::IncompatibleRangeForImageType);
-            }
-        }
-        ImageDimensions::Dim2d { .. } => {
-            if source_offset[2] != 0 || extent[2] != 1 {
-                return Err(CheckCopyImageError::IncompatibleRangeForImageType);
-            }
+    let size = dimensions.width_height_depth();
+    for i in 0..3 {
+        if offset[i]
+            .checked_add(extent[i])
+            .map_or(true, |end| end > size[i])
+        {
+            return Err(CheckCopyRegionError::TexelsOutOfRange {
+                offset,
+                extent,
+                mip_level_size: size,
+            });
         }
-        ImageDimensions::Dim3d { .. } => {}
     }
 
-    match destination_dimensions {
+    match dimensions {
         ImageDimensions::Dim1d { .. } => {
-            if destination_offset[1] != 0 || extent[1] != 1 {
-                return Err(CheckCopyImageError::IncompatibleRangeForImageType);
-            }
-            if destination_offset[2] != 0 || extent[2] != 1 {
-                return Err(CheckCopyImageError::IncompatibleRangeForImageType);
+            if offset[1] != 0 || extent[1] != 1 || offset[2] != 0 || extent[2] != 1 {
+                return Err(CheckCopyRegionError::IncompatibleRangeForImageType);
             }
         }
         ImageDimensions::Dim2d { .. } => {
-            if destination_offset[2] != 0 || extent[2] != 1 {
-                return Err(CheckCopyImageError::IncompatibleRangeForImageType);
+            if offset[2] != 0 || extent[2] != 1 {
+                return Err(CheckCopyRegionError::IncompatibleRangeForImageType);
+            }
+        }
+        ImageDimensions::Dim3d { .. } => {
+            if base_array_layer != 0 || layer_count != 1 {
+                return Err(CheckCopyRegionError::IncompatibleRangeForImageType);
             }
         }
-        ImageDimensions::Dim3d { .. } => {}
+    }
+
+    // The regions of compressed images must be made of whole blocks, except at the edges of the
+    // mip level.
+    let [block_width, block_height] = image.format().block_dimensions();
+    if offset[0] % block_width != 0
+        || offset[1] % block_height != 0
+        || (extent[0] % block_width != 0 && offset[0] + extent[0] != size[0])
+        || (extent[1] % block_height != 0 && offset[1] + extent[1] != size[1])
+    {
+        return Err(CheckCopyRegionError::NotBlockAligned {
+            block_dimensions: [block_width, block_height],
+        });
     }
 
     Ok(())
@@ -190,15 +310,32 @@ pub enum CheckCopyImageError {
         source_type: NumericType,
         destination_type: NumericType,
     },
-    /// The offsets, array layers and/or mipmap levels are out of range in the source image.
-    SourceCoordinatesOutOfRange,
-    /// The offsets, array layers and/or mipmap levels are out of range in the destination image.
-    DestinationCoordinatesOutOfRange,
-    /// The offsets or extent are incompatible with the image type.
-    IncompatibleRangeForImageType,
+    /// A region is invalid for the source image.
+    SourceRegion {
+        /// The index of the region.
+        region_index: usize,
+        /// Why the region is invalid.
+        error: CheckCopyRegionError,
+    },
+    /// A region is invalid for the destination image.
+    DestinationRegion {
+        /// The index of the region.
+        region_index: usize,
+        /// Why the region is invalid.
+        error: CheckCopyRegionError,
+    },
 }
 
-impl error::Error for CheckCopyImageError {}
+impl error::Error for CheckCopyImageError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            CheckCopyImageError::SourceRegion { ref error, .. } => Some(error),
+            CheckCopyImageError::DestinationRegion { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
 
 impl fmt::Display for CheckCopyImageError {
     #[inline]
@@ -223,16 +360,112 @@ impl fmt::Display for CheckCopyImageError {
                 CheckCopyImageError::SizeIncompatibleFormatTypes { .. } => {
                     "the types of the source format and the destination format aren't size-compatible"
                 }
-                CheckCopyImageError::SourceCoordinatesOutOfRange => {
-                    "the offsets, array layers and/or mipmap levels are out of range in the source \
-                 image"
+                CheckCopyImageError::SourceRegion { .. } => {
+                    "a region is invalid for the source image"
+                }
+                CheckCopyImageError::DestinationRegion { .. } => {
+                    "a region is invalid for the destination image"
+                }
+            }
+        )
+    }
+}
+
+/// Error that can happen when checking a region of a copy command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckCopyRegionError {
+    /// The aspects of the region are empty, aren't aspects of the format of the image, or mix
+    /// color with depth or stencil.
+    AspectsNotAllowed,
+    /// The array layers of the region don't exist in the image.
+    ArrayLayersOutOfRange {
+        /// The first array layer of the region.
+        base_array_layer: u32,
+        /// The number of array layers of the region.
+        layer_count: u32,
+        /// The number of array layers of the image.
+        array_layers: u32,
+    },
+    /// The buffer row length or image height is not 0 and is smaller than the extent of the
+    /// region.
+    BufferLayoutTooSmall,
+    /// The buffer offset is not a multiple of the size of a texel block of the format, or of 4
+    /// for depth/stencil formats.
+    BufferOffsetNotAligned,
+    /// The buffer is too small for the region.
+    BufferTooSmall {
+        /// Required size of the buffer in bytes.
+        required_size: DeviceSize,
+        /// Actual size of the buffer in bytes.
+        actual_size: DeviceSize,
+    },
+    /// The extent or the number of array layers of the region is 0.
+    Empty,
+    /// The offset or extent of the region is incompatible with the type of the image.
+    IncompatibleRangeForImageType,
+    /// The mip level of the region doesn't exist in the image.
+    MipLevelOutOfRange {
+        /// The mip level of the region.
+        mip_level: u32,
+        /// The number of mip levels of the image.
+        mip_levels: u32,
+    },
+    /// The region of a compressed image isn't made of whole texel blocks.
+    NotBlockAligned {
+        /// The dimensions of a texel block of the format.
+        block_dimensions: [u32; 2],
+    },
+    /// The offset and extent of the region are out of range in the mip level of the image.
+    TexelsOutOfRange {
+        /// The offset of the region.
+        offset: [u32; 3],
+        /// The extent of the region.
+        extent: [u32; 3],
+        /// The size of the mip level of the image.
+        mip_level_size: [u32; 3],
+    },
+}
+
+impl error::Error for CheckCopyRegionError {}
+
+impl fmt::Display for CheckCopyRegionError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                CheckCopyRegionError::AspectsNotAllowed => {
+                    "the aspects of the region are not allowed for the format of the image"
+                }
+                CheckCopyRegionError::ArrayLayersOutOfRange { .. } => {
+                    "the array layers of the region don't exist in the image"
+                }
+                CheckCopyRegionError::BufferLayoutTooSmall => {
+                    "the buffer row length or image height is smaller than the extent of the \
+                     region"
+                }
+                CheckCopyRegionError::BufferOffsetNotAligned => {
+                    "the buffer offset is not aligned for the format of the image"
+                }
+                CheckCopyRegionError::BufferTooSmall { .. } => {
+                    "the buffer is too small for the region"
+                }
+                CheckCopyRegionError::Empty => {
+                    "the extent or the number of array layers of the region is 0"
+                }
+                CheckCopyRegionError::IncompatibleRangeForImageType => {
+                    "the offset or extent of the region is incompatible with the image type"
+                }
+                CheckCopyRegionError::MipLevelOutOfRange { .. } => {
+                    "the mip level of the region doesn't exist in the image"
                 }
-                CheckCopyImageError::DestinationCoordinatesOutOfRange => {
-                    "the offsets, array layers and/or mipmap levels are out of range in the \
-                 destination image"
+                CheckCopyRegionError::NotBlockAligned { .. } => {
+                    "the region of a compressed image isn't made of whole texel blocks"
                 }
-                CheckCopyImageError::IncompatibleRangeForImageType => {
-                    "the offsets or extent are incompatible with the image type"
+                CheckCopyRegionError::TexelsOutOfRange { .. } => {
+                    "the offset and extent of the region are out of range in the mip level of \
+                     the image"
                 }
             }
         )
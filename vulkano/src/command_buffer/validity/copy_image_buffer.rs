@@ -8,18 +8,23 @@
 // according to those terms.
 
 use crate::buffer::TypedBufferAccess;
+use crate::command_buffer::validity::copy_image::check_image_subresource_region;
+use crate::command_buffer::validity::CheckCopyRegionError;
+use crate::command_buffer::BufferImageCopy;
 use crate::device::Device;
 use crate::device::DeviceOwned;
 use crate::format::Format;
 use crate::format::IncompatiblePixelsType;
 use crate::format::Pixel;
 use crate::image::ImageAccess;
-use crate::image::ImageDimensions;
+use crate::image::ImageAspect;
+use crate::image::ImageAspects;
 use crate::image::SampleCount;
 use crate::DeviceSize;
 use crate::VulkanObject;
 use std::error;
 use std::fmt;
+use std::mem;
 
 /// Type of operation to check.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -31,6 +36,10 @@ pub enum CheckCopyBufferImageTy {
 /// Checks whether a copy buffer-image command is valid. Can check both buffer-to-image copies and
 /// image-to-buffer copies.
 ///
+/// Same as `check_copy_buffer_image_regions` with a single region that starts at the beginning of
+/// the buffer, is tightly packed, and copies the color aspect of the image, or its depth or
+/// stencil aspect if it has no color.
+///
 /// # Panic
 ///
 /// - Panics if the buffer and image were not created with `device`.
@@ -46,6 +55,56 @@ pub fn check_copy_buffer_image<B, I, Px>(
     image_num_layers: u32,
     image_mipmap: u32,
 ) -> Result<(), CheckCopyBufferImageError>
+where
+    I: ?Sized + ImageAccess,
+    B: ?Sized + TypedBufferAccess<Content = [Px]>,
+    Px: Pixel, // TODO: use a trait on the image itself instead
+{
+    let region = BufferImageCopy {
+        buffer_offset: 0,
+        buffer_row_length: 0,
+        buffer_image_height: 0,
+        image_aspect: default_aspect(image.format().aspects()),
+        image_mip_level: image_mipmap,
+        image_base_array_layer: image_first_layer,
+        image_layer_count: image_num_layers,
+        image_offset,
+        image_extent: image_size,
+    };
+
+    check_copy_buffer_image_regions(device, buffer, image, ty, &[region])
+}
+
+/// Returns the aspect that is copied from or to an image of the given aspects, if the user
+/// doesn't choose it.
+pub(in super::super) fn default_aspect(aspects: ImageAspects) -> ImageAspect {
+    if aspects.color {
+        ImageAspect::Color
+    } else if aspects.depth {
+        ImageAspect::Depth
+    } else if aspects.stencil {
+        ImageAspect::Stencil
+    } else {
+        // Formats always have at least one of these aspects; the region check will reject the
+        // others.
+        ImageAspect::Color
+    }
+}
+
+/// Checks whether a copy buffer-image command with the given regions is valid. Can check both
+/// buffer-to-image copies and image-to-buffer copies.
+///
+/// # Panic
+///
+/// - Panics if the buffer and image were not created with `device`.
+///
+pub fn check_copy_buffer_image_regions<B, I, Px>(
+    device: &Device,
+    buffer: &B,
+    image: &I,
+    ty: CheckCopyBufferImageTy,
+    regions: &[BufferImageCopy],
+) -> Result<(), CheckCopyBufferImageError>
 where
     I: ?Sized + ImageAccess,
     B: ?Sized + TypedBufferAccess<Content = [Px]>,
@@ -86,63 +145,68 @@ where
         return Err(CheckCopyBufferImageError::UnexpectedMultisampled);
     }
 
-    let image_dimensions = match image.dimensions().mipmap_dimensions(image_mipmap) {
-        Some(d) => d,
-        None => return Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange),
-    };
+    Px::ensure_accepts(image.format())?;
 
-    if image_first_layer + image_num_layers > image_dimensions.array_layers() {
-        return Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange);
-    }
+    let format = image.format();
+    let format_aspects = format.aspects();
+    let block_size = Px::rate(format) as DeviceSize * mem::size_of::<Px>() as DeviceSize;
 
-    if image_offset[0] + image_size[0] > image_dimensions.width() {
-        return Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange);
-    }
+    for (region_index, region) in regions.iter().enumerate() {
+        let invalid = |error| CheckCopyBufferImageError::InvalidRegion {
+            region_index,
+            error,
+        };
 
-    if image_offset[1] + image_size[1] > image_dimensions.height() {
-        return Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange);
-    }
+        let aspect_supported = match region.image_aspect {
+            ImageAspect::Color => format_aspects.color,
+            ImageAspect::Depth => format_aspects.depth,
+            ImageAspect::Stencil => format_aspects.stencil,
+            _ => false,
+        };
 
-    if image_offset[2] + image_size[2] > image_dimensions.depth() {
-        return Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange);
-    }
+        if !aspect_supported {
+            return Err(invalid(CheckCopyRegionError::AspectsNotAllowed));
+        }
 
-    match image.dimensions() {
-        ImageDimensions::Dim1d { .. } => {
-            // VUID-vkCmdCopyBufferToImage-srcImage-00199
-            if image_offset[1] != 0 || image_size[1] != 1 {
-                return Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange);
-            }
+        check_image_subresource_region(
+            image,
+            region.image_mip_level,
+            region.image_base_array_layer,
+            region.image_layer_count,
+            region.image_offset,
+            region.image_extent,
+        )
+        .map_err(invalid)?;
 
-            // VUID-vkCmdCopyBufferToImage-srcImage-00201
-            if image_offset[2] != 0 || image_size[2] != 1 {
-                return Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange);
-            }
-        }
-        ImageDimensions::Dim2d { .. } => {
-            // VUID-vkCmdCopyBufferToImage-srcImage-00201
-            if image_offset[2] != 0 || image_size[2] != 1 {
-                return Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange);
-            }
+        if (region.buffer_row_length != 0 && region.buffer_row_length < region.image_extent[0])
+            || (region.buffer_image_height != 0
+                && region.buffer_image_height < region.image_extent[1])
+        {
+            return Err(invalid(CheckCopyRegionError::BufferLayoutTooSmall));
         }
-        ImageDimensions::Dim3d { .. } => {
-            // VUID-vkCmdCopyBufferToImage-baseArrayLayer-00213
-            if image_first_layer != 0 || image_num_layers != 1 {
-                return Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange);
-            }
-        }
-    }
 
-    Px::ensure_accepts(image.format())?;
+        // The offset must be a multiple of the texel block size, or of 4 for depth/stencil.
+        let offset_alignment = if format_aspects.depth || format_aspects.stencil {
+            4
+        } else {
+            block_size
+        };
+        if (buffer_inner.offset + region.buffer_offset) % offset_alignment != 0 {
+            return Err(invalid(CheckCopyRegionError::BufferOffsetNotAligned));
+        }
 
-    {
-        let required_len =
-            required_len_for_format::<Px>(image.format(), image_size, image_num_layers);
-        if required_len > buffer.len() {
-            return Err(CheckCopyBufferImageError::BufferTooSmall {
-                required_len,
-                actual_len: buffer.len(),
-            });
+        let required_size = region.buffer_offset
+            + required_len_for_layout::<Px>(
+                format,
+                region.image_extent,
+                region.image_layer_count,
+                [region.buffer_row_length, region.buffer_image_height],
+            ) * mem::size_of::<Px>() as DeviceSize;
+        if required_size > buffer.size() {
+            return Err(invalid(CheckCopyRegionError::BufferTooSmall {
+                required_size,
+                actual_size: buffer.size(),
+            }));
         }
     }
 
@@ -161,20 +225,61 @@ fn required_len_for_format<Px>(
 where
     Px: Pixel,
 {
+    required_len_for_layout::<Px>(format, image_size, image_num_layers, [0, 0])
+}
+
+/// Same as `required_len_for_format`, but with the row length and image height of the data in the
+/// buffer, in texels. A value of 0 means that the data is tightly packed in that dimension.
+fn required_len_for_layout<Px>(
+    format: Format,
+    image_size: [u32; 3],
+    image_num_layers: u32,
+    [row_length, image_height]: [u32; 2],
+) -> DeviceSize
+where
+    Px: Pixel,
+{
+    if image_size.contains(&0) || image_num_layers == 0 {
+        return 0;
+    }
+
     let [block_width, block_height] = format.block_dimensions();
-    let num_blocks = (image_size[0] + block_width - 1) / block_width
-        * ((image_size[1] + block_height - 1) / block_height)
-        * image_size[2]
-        * image_num_layers;
-    let required_len = num_blocks as DeviceSize * Px::rate(format) as DeviceSize;
+    let row_length = if row_length == 0 {
+        image_size[0]
+    } else {
+        row_length
+    };
+    let image_height = if image_height == 0 {
+        image_size[1]
+    } else {
+        image_height
+    };
+
+    let blocks_per_row = ((row_length + block_width - 1) / block_width) as DeviceSize;
+    let rows_per_image = ((image_height + block_height - 1) / block_height) as DeviceSize;
+    let num_images = image_size[2] as DeviceSize * image_num_layers as DeviceSize;
+
+    // The last row of the last image only needs to hold the blocks of the region.
+    let num_blocks = (num_images - 1) * rows_per_image * blocks_per_row
+        + ((image_size[1] + block_height - 1) / block_height - 1) as DeviceSize * blocks_per_row
+        + ((image_size[0] + block_width - 1) / block_width) as DeviceSize;
+    let required_len = num_blocks * Px::rate(format) as DeviceSize;
 
     return required_len;
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::command_buffer::validity;
     use crate::command_buffer::validity::copy_image_buffer::required_len_for_format;
+    use crate::command_buffer::validity::copy_image_buffer::required_len_for_layout;
+    use crate::command_buffer::BufferImageCopy;
     use crate::format::Format;
+    use crate::image::ImageAspect;
+    use crate::image::ImageDimensions;
+    use crate::image::StorageImage;
 
     #[test]
     fn test_required_len_for_format() {
@@ -217,6 +322,95 @@ mod tests {
             29584
         );
     }
+
+    #[test]
+    fn test_required_len_for_layout() {
+        // The last row only needs to hold the texels of the region.
+        assert_eq!(
+            required_len_for_layout::<u8>(Format::R8G8B8A8_UNORM, [16, 16, 1], 1, [32, 0]),
+            (15 * 32 + 16) * 4
+        );
+        assert_eq!(
+            required_len_for_layout::<u8>(Format::R8G8B8A8_UNORM, [16, 16, 1], 2, [0, 32]),
+            (32 * 16 + 16 * 16) * 4
+        );
+        assert_eq!(
+            required_len_for_layout::<u8>(Format::BC1_RGB_UNORM_BLOCK, [8, 8, 1], 1, [16, 0]),
+            (4 + 2) * 8
+        );
+    }
+
+    #[test]
+    fn invalid_region_index() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = StorageImage::new(
+            device.clone(),
+            ImageDimensions::Dim2d {
+                width: 16,
+                height: 16,
+                array_layers: 1,
+            },
+            Format::R8G8B8A8_UNORM,
+            Some(queue.family()),
+        )
+        .unwrap();
+
+        let buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::all(),
+            false,
+            (0..16 * 16 * 4).map(|_| 0u8),
+        )
+        .unwrap();
+
+        let region = BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_aspect: ImageAspect::Color,
+            image_mip_level: 0,
+            image_base_array_layer: 0,
+            image_layer_count: 1,
+            image_offset: [0, 0, 0],
+            image_extent: [16, 16, 1],
+        };
+
+        validity::check_copy_buffer_image_regions(
+            &device,
+            buffer.as_ref(),
+            image.as_ref(),
+            validity::CheckCopyBufferImageTy::BufferToImage,
+            &[region],
+        )
+        .unwrap();
+
+        let regions = [
+            region,
+            BufferImageCopy {
+                image_mip_level: 1,
+                ..region
+            },
+        ];
+
+        match validity::check_copy_buffer_image_regions(
+            &device,
+            buffer.as_ref(),
+            image.as_ref(),
+            validity::CheckCopyBufferImageTy::BufferToImage,
+            &regions,
+        ) {
+            Err(validity::CheckCopyBufferImageError::InvalidRegion {
+                region_index: 1,
+                error:
+                    validity::CheckCopyRegionError::MipLevelOutOfRange {
+                        mip_level: 1,
+                        mip_levels: 1,
+                    },
+            }) => (),
+            _ => panic!(),
+        }
+    }
 }
 
 /// Error that can happen from `check_copy_buffer_image`.
@@ -230,16 +424,14 @@ pub enum CheckCopyBufferImageError {
     OverlappingRanges,
     /// The image must not be multisampled.
     UnexpectedMultisampled,
-    /// The image coordinates are out of range.
-    ImageCoordinatesOutOfRange,
     /// The type of pixels in the buffer isn't compatible with the image format.
     WrongPixelType(IncompatiblePixelsType),
-    /// The buffer is too small for the copy operation.
-    BufferTooSmall {
-        /// Required number of elements in the buffer.
-        required_len: DeviceSize,
-        /// Actual number of elements in the buffer.
-        actual_len: DeviceSize,
+    /// A region of the copy is invalid.
+    InvalidRegion {
+        /// The index of the region.
+        region_index: usize,
+        /// Why the region is invalid.
+        error: CheckCopyRegionError,
     },
 }
 
@@ -247,6 +439,7 @@ impl error::Error for CheckCopyBufferImageError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
             CheckCopyBufferImageError::WrongPixelType(ref err) => Some(err),
+            CheckCopyBufferImageError::InvalidRegion { ref error, .. } => Some(error),
             _ => None,
         }
     }
@@ -271,14 +464,11 @@ impl fmt::Display for CheckCopyBufferImageError {
                 CheckCopyBufferImageError::UnexpectedMultisampled => {
                     "the image must not be multisampled"
                 }
-                CheckCopyBufferImageError::ImageCoordinatesOutOfRange => {
-                    "the image coordinates are out of range"
-                }
                 CheckCopyBufferImageError::WrongPixelType(_) => {
                     "the type of pixels in the buffer isn't compatible with the image format"
                 }
-                CheckCopyBufferImageError::BufferTooSmall { .. } => {
-                    "the buffer is too small for the copy operation"
+                CheckCopyBufferImageError::InvalidRegion { .. } => {
+                    "a region of the copy is invalid"
                 }
             }
         )
@@ -0,0 +1,94 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::device::Device;
+use crate::instance::debug::DebugUtilsLabel;
+use std::error;
+use std::fmt;
+
+/// Checks whether a debug label can be inserted, or used to begin a label region, in a command
+/// buffer or queue of `device`.
+pub fn check_debug_utils_label(
+    device: &Device,
+    label: &DebugUtilsLabel,
+) -> Result<(), CheckDebugUtilsLabelError> {
+    check_debug_utils_enabled(device)?;
+
+    if label.label_name.contains('\0') {
+        return Err(CheckDebugUtilsLabelError::LabelNameContainsNul);
+    }
+
+    // The values contain RGBA values in order, in the range 0.0 to 1.0.
+    if label.color.iter().any(|x| !(0f32..=1f32).contains(x)) {
+        return Err(CheckDebugUtilsLabelError::ColorOutOfRange);
+    }
+
+    Ok(())
+}
+
+/// Checks whether a debug label region can be ended in a command buffer or queue of `device`.
+pub fn check_debug_utils_enabled(device: &Device) -> Result<(), CheckDebugUtilsLabelError> {
+    if !device.instance().enabled_extensions().ext_debug_utils {
+        return Err(CheckDebugUtilsLabelError::ExtensionNotEnabled);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen from `check_debug_utils_label` and `check_debug_utils_enabled`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckDebugUtilsLabelError {
+    /// The color of the label contains values outside the range 0.0 to 1.0.
+    ColorOutOfRange,
+    /// The `ext_debug_utils` extension was not enabled on the instance.
+    ExtensionNotEnabled,
+    /// The name of the label contains a nul character.
+    LabelNameContainsNul,
+}
+
+impl error::Error for CheckDebugUtilsLabelError {}
+
+impl fmt::Display for CheckDebugUtilsLabelError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                Self::ColorOutOfRange => {
+                    "the color of the label contains values outside the range 0.0 to 1.0"
+                }
+                Self::ExtensionNotEnabled => {
+                    "the ext_debug_utils extension was not enabled on the instance"
+                }
+                Self::LabelNameContainsNul => "the name of the label contains a nul character",
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_buffer::validity;
+    use crate::instance::debug::DebugUtilsLabel;
+
+    #[test]
+    fn extension_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+        let label = DebugUtilsLabel {
+            label_name: "label".into(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            validity::check_debug_utils_label(&device, &label),
+            Err(validity::CheckDebugUtilsLabelError::ExtensionNotEnabled)
+        );
+    }
+}
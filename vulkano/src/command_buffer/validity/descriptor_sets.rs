@@ -8,22 +8,29 @@
 // according to those terms.
 
 use crate::command_buffer::synced::CommandBufferState;
+use crate::descriptor_set::layout::DescriptorType;
 use crate::descriptor_set::DescriptorBindingResources;
+use crate::descriptor_set::DescriptorResource;
 use crate::format::Format;
 use crate::image::view::ImageViewType;
 use crate::image::ImageViewAbstract;
 use crate::image::SampleCount;
 use crate::pipeline::Pipeline;
 use crate::shader::DescriptorRequirements;
+use fnv::FnvHashMap;
 use std::error;
 use std::fmt;
 use std::sync::Arc;
 
 /// Checks whether descriptor sets are compatible with the pipeline.
+///
+/// For partially-bound bindings, only the elements in `accessed_descriptors` for the binding, and
+/// the element of bindings with a descriptor count of 1, are required to be written.
 pub(in super::super) fn check_descriptor_sets_validity<'a, P: Pipeline>(
     current_state: CommandBufferState,
     pipeline: &P,
     descriptor_requirements: impl IntoIterator<Item = ((u32, u32), &'a DescriptorRequirements)>,
+    accessed_descriptors: &FnvHashMap<(u32, u32), Vec<u32>>,
 ) -> Result<(), CheckDescriptorSetsValidityError> {
     if pipeline.num_used_descriptor_sets() == 0 {
         return Ok(());
@@ -72,33 +79,74 @@ pub(in super::super) fn check_descriptor_sets_validity<'a, P: Pipeline>(
             Ok(())
         };
 
-        let set_resources = match current_state.descriptor_set(pipeline.bind_point(), set_num) {
-            Some(x) => x.resources(),
+        let set = match current_state.descriptor_set(pipeline.bind_point(), set_num) {
+            Some(x) => x,
             None => return Err(CheckDescriptorSetsValidityError::MissingDescriptorSet { set_num }),
         };
 
+        // The layout of the bound set is compatible with the pipeline layout, so they have the
+        // same binding flags.
+        let partially_bound = pipeline.layout().descriptor_set_layouts()[set_num as usize]
+            .desc()
+            .descriptor(binding_num)
+            .map_or(false, |desc| desc.partially_bound);
+        let accessed = if partially_bound {
+            let accessed = accessed_descriptors
+                .get(&(set_num, binding_num))
+                .map_or(&[][..], |elements| elements.as_slice());
+
+            Some(if reqs.descriptor_count == 1 && accessed.is_empty() {
+                &[0][..]
+            } else {
+                accessed
+            })
+        } else {
+            None
+        };
+        let check = Check {
+            set_num,
+            binding_num,
+            reqs,
+            accessed,
+        };
+
+        let set_resources = set.resources();
         let binding_resources = set_resources.binding(binding_num).unwrap();
 
         match binding_resources {
             DescriptorBindingResources::None => (),
             DescriptorBindingResources::Buffer(elements) => {
-                check_resources(set_num, binding_num, reqs, elements, |_| Ok(()))?;
+                check.resources(elements, |_| Ok(()))?;
             }
             DescriptorBindingResources::BufferView(elements) => {
-                check_resources(set_num, binding_num, reqs, elements, |_| Ok(()))?;
+                check.resources(elements, |_| Ok(()))?;
             }
             DescriptorBindingResources::ImageView(elements) => {
-                check_resources(set_num, binding_num, reqs, elements, |i| {
-                    check_image_view(i)
-                })?;
+                check.resources(elements, |i| check_image_view(i))?;
             }
             DescriptorBindingResources::ImageViewSampler(elements) => {
-                check_resources(set_num, binding_num, reqs, elements, |(i, s)| {
-                    check_image_view(i)
-                })?;
+                check.resources(elements, |(i, s)| check_image_view(i))?;
             }
             DescriptorBindingResources::Sampler(elements) => {
-                check_resources(set_num, binding_num, reqs, elements, |_| Ok(()))?;
+                check.resources(elements, |_| Ok(()))?;
+            }
+            DescriptorBindingResources::AccelerationStructure(elements) => {
+                check.resources(elements, |_| Ok(()))?;
+            }
+            DescriptorBindingResources::Mutable(elements) => {
+                check.resources(elements, |(ty, resource)| {
+                    if !reqs.descriptor_types.contains(ty) {
+                        return Err(InvalidDescriptorResource::MutableTypeMismatch {
+                            obtained: *ty,
+                        });
+                    }
+
+                    match resource {
+                        DescriptorResource::ImageView(i)
+                        | DescriptorResource::ImageViewSampler(i, _) => check_image_view(i),
+                        _ => Ok(()),
+                    }
+                })?;
             }
         }
     }
@@ -157,44 +205,68 @@ impl fmt::Display for CheckDescriptorSetsValidityError {
     }
 }
 
-fn check_resources<T>(
+// The binding that is being checked.
+struct Check<'a> {
     set_num: u32,
     binding_num: u32,
-    reqs: &DescriptorRequirements,
-    elements: &[Option<T>],
-    mut extra_check: impl FnMut(&T) -> Result<(), InvalidDescriptorResource>,
-) -> Result<(), CheckDescriptorSetsValidityError> {
-    for (index, element) in elements[0..reqs.descriptor_count as usize]
-        .iter()
-        .enumerate()
-    {
-        let element = match element {
-            Some(x) => x,
+    reqs: &'a DescriptorRequirements,
+    // For partially-bound bindings, the elements that must be written.
+    accessed: Option<&'a [u32]>,
+}
+
+impl<'a> Check<'a> {
+    fn resources<T>(
+        &self,
+        elements: &[Option<T>],
+        mut extra_check: impl FnMut(&T) -> Result<(), InvalidDescriptorResource>,
+    ) -> Result<(), CheckDescriptorSetsValidityError> {
+        let error =
+            |index: usize, error| CheckDescriptorSetsValidityError::InvalidDescriptorResource {
+                set_num: self.set_num,
+                binding_num: self.binding_num,
+                index: index as u32,
+                error,
+            };
+
+        match self.accessed {
             None => {
-                return Err(
-                    CheckDescriptorSetsValidityError::InvalidDescriptorResource {
-                        set_num,
-                        binding_num,
-                        index: index as u32,
-                        error: InvalidDescriptorResource::Missing,
-                    },
-                )
+                for (index, element) in elements[0..self.reqs.descriptor_count as usize]
+                    .iter()
+                    .enumerate()
+                {
+                    let element = match element {
+                        Some(x) => x,
+                        None => return Err(error(index, InvalidDescriptorResource::Missing)),
+                    };
+
+                    extra_check(element).map_err(|err| error(index, err))?;
+                }
             }
-        };
+            Some(accessed) => {
+                let len = if self.reqs.descriptor_count == 0 {
+                    elements.len()
+                } else {
+                    (self.reqs.descriptor_count as usize).min(elements.len())
+                };
+
+                for (index, element) in elements[0..len].iter().enumerate() {
+                    if let Some(element) = element {
+                        extra_check(element).map_err(|err| error(index, err))?;
+                    }
+                }
 
-        if let Err(error) = extra_check(element) {
-            return Err(
-                CheckDescriptorSetsValidityError::InvalidDescriptorResource {
-                    set_num,
-                    binding_num,
-                    index: index as u32,
-                    error,
-                },
-            );
+                for &index in accessed {
+                    let index = index as usize;
+
+                    if !matches!(elements.get(index), Some(Some(_))) {
+                        return Err(error(index, InvalidDescriptorResource::Missing));
+                    }
+                }
+            }
         }
-    }
 
-    Ok(())
+        Ok(())
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -212,6 +284,9 @@ pub enum InvalidDescriptorResource {
         obtained: ImageViewType,
     },
     Missing,
+    MutableTypeMismatch {
+        obtained: DescriptorType,
+    },
 }
 
 impl error::Error for InvalidDescriptorResource {}
@@ -232,6 +307,82 @@ impl fmt::Display for InvalidDescriptorResource {
             Self::ImageViewTypeMismatch { required, obtained } => {
                 write!(fmt, "the bound image view did not have the required type; required {:?}, obtained {:?}", required, obtained)
             }
+            Self::MutableTypeMismatch { obtained } => {
+                write!(fmt, "the resource bound to the mutable descriptor was written as a descriptor type that the shader does not accept; obtained {:?}", obtained)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Check;
+    use super::CheckDescriptorSetsValidityError;
+    use super::InvalidDescriptorResource;
+    use crate::shader::DescriptorRequirements;
+
+    fn array_requirements(descriptor_count: u32) -> DescriptorRequirements {
+        DescriptorRequirements {
+            descriptor_count,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn partially_bound_unaccessed_unwritten() {
+        let reqs = array_requirements(4);
+        let check = Check {
+            set_num: 0,
+            binding_num: 1,
+            reqs: &reqs,
+            accessed: Some(&[0, 2][..]),
+        };
+
+        // Elements 1 and 3 are not accessed, so they don't need to be written.
+        assert!(check
+            .resources(&[Some(()), None, Some(()), None], |_| Ok(()))
+            .is_ok());
+    }
+
+    #[test]
+    fn partially_bound_accessed_unwritten() {
+        let reqs = array_requirements(4);
+        let check = Check {
+            set_num: 0,
+            binding_num: 1,
+            reqs: &reqs,
+            accessed: Some(&[0, 3][..]),
+        };
+
+        match check.resources(&[Some(()), None, Some(()), None], |_| Ok(())) {
+            Err(CheckDescriptorSetsValidityError::InvalidDescriptorResource {
+                set_num: 0,
+                binding_num: 1,
+                index: 3,
+                error: InvalidDescriptorResource::Missing,
+            }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn not_partially_bound_unwritten() {
+        let reqs = array_requirements(4);
+        let check = Check {
+            set_num: 0,
+            binding_num: 1,
+            reqs: &reqs,
+            accessed: None,
+        };
+
+        // Without partial binding, every element required by the shader must be written.
+        match check.resources(&[Some(()), None, Some(()), Some(())], |_| Ok(())) {
+            Err(CheckDescriptorSetsValidityError::InvalidDescriptorResource {
+                index: 1,
+                error: InvalidDescriptorResource::Missing,
+                ..
+            }) => (),
+            _ => panic!(),
         }
     }
 }
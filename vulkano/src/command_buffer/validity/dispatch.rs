@@ -10,7 +10,10 @@
 use std::error;
 use std::fmt;
 
+use crate::buffer::TypedBufferAccess;
+use crate::command_buffer::DispatchIndirectCommand;
 use crate::device::Device;
+use crate::Version;
 
 /// Checks whether the dispatch dimensions are supported by the device.
 pub fn check_dispatch(device: &Device, dimensions: [u32; 3]) -> Result<(), CheckDispatchError> {
@@ -29,9 +32,66 @@ pub fn check_dispatch(device: &Device, dimensions: [u32; 3]) -> Result<(), Check
     Ok(())
 }
 
+/// Checks whether a dispatch with the given base workgroup and dimensions is supported by the
+/// device.
+pub fn check_dispatch_base(
+    device: &Device,
+    base_group: [u32; 3],
+    dimensions: [u32; 3],
+) -> Result<(), CheckDispatchError> {
+    if !(device.api_version() >= Version::V1_1 || device.enabled_extensions().khr_device_group) {
+        return Err(CheckDispatchError::DispatchBaseNotSupported);
+    }
+
+    let max = device
+        .physical_device()
+        .properties()
+        .max_compute_work_group_count;
+
+    for i in 0..3 {
+        if base_group[i] >= max[i] || dimensions[i] > max[i] - base_group[i] {
+            return Err(CheckDispatchError::UnsupportedBaseDimensions {
+                base_group,
+                requested: dimensions,
+                max_supported: max,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks whether `indirect_buffer` can be used for an indirect dispatch.
+///
+/// The usage and the alignment of the buffer are checked by `check_indirect_buffer`.
+pub fn check_dispatch_indirect<Inb>(indirect_buffer: &Inb) -> Result<(), CheckDispatchError>
+where
+    Inb: ?Sized + TypedBufferAccess<Content = [DispatchIndirectCommand]>,
+{
+    if indirect_buffer.len() == 0 {
+        return Err(CheckDispatchError::IndirectBufferEmpty);
+    }
+
+    Ok(())
+}
+
 /// Error that can happen when checking dispatch command validity.
 #[derive(Debug, Copy, Clone)]
 pub enum CheckDispatchError {
+    /// Dispatching with a base workgroup requires Vulkan 1.1 or the `khr_device_group`
+    /// extension.
+    DispatchBaseNotSupported,
+    /// The indirect buffer doesn't contain a `DispatchIndirectCommand`.
+    IndirectBufferEmpty,
+    /// The base workgroup plus the dimensions are too large for the device's limits.
+    UnsupportedBaseDimensions {
+        /// The requested base workgroup.
+        base_group: [u32; 3],
+        /// The requested dimensions.
+        requested: [u32; 3],
+        /// The actual supported dimensions.
+        max_supported: [u32; 3],
+    },
     /// The dimensions are too large for the device's limits.
     UnsupportedDimensions {
         /// The requested dimensions.
@@ -50,6 +110,16 @@ impl fmt::Display for CheckDispatchError {
             fmt,
             "{}",
             match *self {
+                CheckDispatchError::DispatchBaseNotSupported => {
+                    "dispatching with a base workgroup requires Vulkan 1.1 or the khr_device_group \
+                     extension"
+                }
+                CheckDispatchError::IndirectBufferEmpty => {
+                    "the indirect buffer doesn't contain a DispatchIndirectCommand"
+                }
+                CheckDispatchError::UnsupportedBaseDimensions { .. } => {
+                    "the base workgroup plus the dimensions are too large for the device's limits"
+                }
                 CheckDispatchError::UnsupportedDimensions { .. } => {
                     "the dimensions are too large for the device's limits"
                 }
@@ -85,4 +155,22 @@ mod tests {
             _ => panic!(),
         }
     }
+
+    #[test]
+    fn base_max_checked() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let max = device
+            .physical_device()
+            .properties()
+            .max_compute_work_group_count;
+
+        match validity::check_dispatch_base(&device, [1, 0, 0], [max[0], 1, 1]) {
+            Err(validity::CheckDispatchError::UnsupportedBaseDimensions { base_group, .. }) => {
+                assert_eq!(base_group, [1, 0, 0]);
+            }
+            Err(validity::CheckDispatchError::DispatchBaseNotSupported) => (),
+            _ => panic!(),
+        }
+    }
 }
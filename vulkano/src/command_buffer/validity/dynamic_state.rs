@@ -20,6 +20,7 @@ use std::fmt;
 pub(in super::super) fn check_dynamic_state_validity(
     current_state: CommandBufferState,
     pipeline: &GraphicsPipeline,
+    inherited_viewport_count: u32,
 ) -> Result<(), CheckDynamicStateValidityError> {
     let device = pipeline.device();
 
@@ -105,7 +106,11 @@ pub(in super::super) fn check_dynamic_state_validity(
                 }
             }
             DynamicState::ExclusiveScissor => todo!(),
-            DynamicState::FragmentShadingRate => todo!(),
+            DynamicState::FragmentShadingRate => {
+                if current_state.fragment_shading_rate().is_none() {
+                    return Err(CheckDynamicStateValidityError::NotSet { dynamic_state });
+                }
+            }
             DynamicState::FrontFace => {
                 if current_state.front_face().is_none() {
                     return Err(CheckDynamicStateValidityError::NotSet { dynamic_state });
@@ -228,7 +233,11 @@ pub(in super::super) fn check_dynamic_state_validity(
             ),
             DynamicState::SampleLocations => todo!(),
             DynamicState::Scissor => {
-                for num in 0..pipeline.viewport_state().unwrap().count().unwrap() {
+                // Viewports and scissors inherited from the primary command buffer are checked
+                // when the secondary command buffer is executed.
+                for num in
+                    inherited_viewport_count..pipeline.viewport_state().unwrap().count().unwrap()
+                {
                     if current_state.scissor(num).is_none() {
                         return Err(CheckDynamicStateValidityError::NotSet { dynamic_state });
                     }
@@ -290,10 +299,31 @@ pub(in super::super) fn check_dynamic_state_validity(
                     return Err(CheckDynamicStateValidityError::NotSet { dynamic_state });
                 }
             }
-            DynamicState::VertexInput => todo!(),
+            DynamicState::VertexInput => {
+                let vertex_input = if let Some(vertex_input) = current_state.vertex_input() {
+                    vertex_input
+                } else {
+                    return Err(CheckDynamicStateValidityError::NotSet { dynamic_state });
+                };
+
+                // TODO: check that the formats are compatible with the shader
+                for element in pipeline.vertex_shader_input().elements() {
+                    for location in element.location.clone() {
+                        if !vertex_input.attributes().any(|(l, _)| l == location) {
+                            return Err(
+                                CheckDynamicStateValidityError::VertexInputAttributeMissing {
+                                    location,
+                                },
+                            );
+                        }
+                    }
+                }
+            }
             DynamicState::VertexInputBindingStride => todo!(),
             DynamicState::Viewport => {
-                for num in 0..pipeline.viewport_state().unwrap().count().unwrap() {
+                for num in
+                    inherited_viewport_count..pipeline.viewport_state().unwrap().count().unwrap()
+                {
                     if current_state.viewport(num).is_none() {
                         return Err(CheckDynamicStateValidityError::NotSet { dynamic_state });
                     }
@@ -372,6 +402,10 @@ pub enum CheckDynamicStateValidityError {
     /// only partially set.
     NotSet { dynamic_state: DynamicState },
 
+    /// The dynamic vertex input doesn't provide an attribute for a location that the vertex
+    /// shader reads from.
+    VertexInputAttributeMissing { location: u32 },
+
     /// The viewport count and scissor count do not match.
     ViewportScissorCountMismatch {
         viewport_count: u32,
@@ -404,6 +438,9 @@ impl fmt::Display for CheckDynamicStateValidityError {
             Self::NotSet { dynamic_state } => {
                 write!(fmt, "the pipeline requires the dynamic state {:?} to be set, but the value was not or only partially set", dynamic_state)
             }
+            Self::VertexInputAttributeMissing { location } => {
+                write!(fmt, "the dynamic vertex input doesn't provide an attribute for location {}, which the vertex shader reads from", location)
+            }
             Self::ViewportScissorCountMismatch {
                 viewport_count,
                 scissor_count,
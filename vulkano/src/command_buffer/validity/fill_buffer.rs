@@ -34,7 +34,7 @@ where
         return Err(CheckFillBufferError::BufferMissingUsage);
     }
 
-    if buffer.inner().offset % 4 != 0 {
+    if buffer.inner().offset % 4 != 0 || buffer.size() % 4 != 0 {
         return Err(CheckFillBufferError::WrongAlignment);
     }
 
@@ -92,6 +92,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn wrong_alignment() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::transfer_destination(),
+            false,
+            0..100u8,
+        )
+        .unwrap();
+
+        match check_fill_buffer(&device, buffer.slice(4..50).unwrap().as_ref()) {
+            Err(CheckFillBufferError::WrongAlignment) => (),
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn wrong_device() {
         let (dev1, queue) = gfx_dev_and_queue!();
@@ -8,8 +8,11 @@
 // according to those terms.
 
 use crate::buffer::BufferAccess;
+use crate::command_buffer::IndirectBufferLayout;
+use crate::command_buffer::IndirectCommand;
 use crate::device::Device;
 use crate::device::DeviceOwned;
+use crate::DeviceSize;
 use crate::VulkanObject;
 use std::error;
 use std::fmt;
@@ -31,6 +34,90 @@ where
         return Err(CheckIndirectBufferError::BufferMissingUsage);
     }
 
+    if buffer.inner().offset % 4 != 0 {
+        return Err(CheckIndirectBufferError::BufferOffsetNotAligned);
+    }
+
+    Ok(())
+}
+
+/// Checks whether an indirect buffer can be bound and is large enough to hold the commands
+/// described by `layout`, for example when the commands are written by the device.
+pub fn check_indirect_buffer_layout<Inb, T>(
+    device: &Device,
+    buffer: &Inb,
+    layout: &IndirectBufferLayout<T>,
+) -> Result<(), CheckIndirectBufferError>
+where
+    Inb: BufferAccess + 'static,
+    T: IndirectCommand,
+{
+    check_indirect_buffer(device, buffer)?;
+
+    let required_size = layout.size();
+    let buffer_size = buffer.size();
+
+    if required_size > buffer_size {
+        return Err(CheckIndirectBufferError::BufferTooSmall {
+            required_size,
+            buffer_size,
+        });
+    }
+
+    Ok(())
+}
+
+/// Checks whether a count buffer and a maximum draw count can be used for an indirect draw with
+/// a count, with an indirect buffer that contains `indirect_buffer_len` draw commands.
+pub fn check_indirect_count_buffer<Cb>(
+    device: &Device,
+    count_buffer: &Cb,
+    max_draw_count: u32,
+    indirect_buffer_len: u32,
+) -> Result<(), CheckIndirectBufferError>
+where
+    Cb: BufferAccess + 'static,
+{
+    assert_eq!(
+        count_buffer.inner().buffer.device().internal_object(),
+        device.internal_object()
+    );
+
+    if !(device.enabled_features().draw_indirect_count
+        || device.enabled_extensions().khr_draw_indirect_count)
+    {
+        return Err(CheckIndirectBufferError::DrawIndirectCountFeatureNotEnabled);
+    }
+
+    if !count_buffer.inner().buffer.usage().indirect_buffer {
+        return Err(CheckIndirectBufferError::CountBufferMissingUsage);
+    }
+
+    if count_buffer.inner().offset % 4 != 0 {
+        return Err(CheckIndirectBufferError::CountBufferOffsetNotAligned);
+    }
+
+    let limit = device
+        .physical_device()
+        .properties()
+        .max_draw_indirect_count;
+
+    if max_draw_count > limit {
+        return Err(
+            CheckIndirectBufferError::MaxDrawIndirectCountLimitExceeded {
+                limit,
+                requested: max_draw_count,
+            },
+        );
+    }
+
+    if max_draw_count > indirect_buffer_len {
+        return Err(CheckIndirectBufferError::MaxDrawCountOutOfRange {
+            max_draw_count,
+            indirect_buffer_len,
+        });
+    }
+
     Ok(())
 }
 
@@ -39,6 +126,29 @@ where
 pub enum CheckIndirectBufferError {
     /// The "indirect buffer" usage must be enabled on the indirect buffer.
     BufferMissingUsage,
+    /// The offset of the indirect buffer must be a multiple of 4.
+    BufferOffsetNotAligned,
+    /// The indirect buffer is too small for the commands that it must contain.
+    BufferTooSmall {
+        /// The size in bytes that the commands require.
+        required_size: DeviceSize,
+        /// The size in bytes of the indirect buffer.
+        buffer_size: DeviceSize,
+    },
+    /// The "indirect buffer" usage must be enabled on the count buffer.
+    CountBufferMissingUsage,
+    /// The offset of the count buffer must be a multiple of 4.
+    CountBufferOffsetNotAligned,
+    /// The `draw_indirect_count` feature or the `khr_draw_indirect_count` extension must be
+    /// enabled to draw with a count buffer.
+    DrawIndirectCountFeatureNotEnabled,
+    /// The maximum draw count is larger than the number of draw commands in the indirect buffer.
+    MaxDrawCountOutOfRange {
+        /// The maximum draw count that was requested.
+        max_draw_count: u32,
+        /// The number of draw commands in the indirect buffer.
+        indirect_buffer_len: u32,
+    },
     /// The maximum number of indirect draws has been exceeded.
     MaxDrawIndirectCountLimitExceeded {
         /// The limit that must be fulfilled.
@@ -60,6 +170,26 @@ impl fmt::Display for CheckIndirectBufferError {
                 CheckIndirectBufferError::BufferMissingUsage => {
                     "the indirect buffer usage must be enabled on the indirect buffer"
                 }
+                CheckIndirectBufferError::BufferOffsetNotAligned => {
+                    "the offset of the indirect buffer must be a multiple of 4"
+                }
+                CheckIndirectBufferError::BufferTooSmall { .. } => {
+                    "the indirect buffer is too small for the commands that it must contain"
+                }
+                CheckIndirectBufferError::CountBufferMissingUsage => {
+                    "the indirect buffer usage must be enabled on the count buffer"
+                }
+                CheckIndirectBufferError::CountBufferOffsetNotAligned => {
+                    "the offset of the count buffer must be a multiple of 4"
+                }
+                CheckIndirectBufferError::DrawIndirectCountFeatureNotEnabled => {
+                    "the draw_indirect_count feature or the khr_draw_indirect_count extension must \
+                     be enabled to draw with a count buffer"
+                }
+                CheckIndirectBufferError::MaxDrawCountOutOfRange { .. } => {
+                    "the maximum draw count is larger than the number of draw commands in the \
+                     indirect buffer"
+                }
                 CheckIndirectBufferError::MaxDrawIndirectCountLimitExceeded {
                     limit,
                     requested,
@@ -70,3 +200,114 @@ impl fmt::Display for CheckIndirectBufferError {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::command_buffer::validity;
+
+    #[test]
+    fn count_feature_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+        let count_buffer =
+            CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                .unwrap();
+
+        match validity::check_indirect_count_buffer(&device, count_buffer.as_ref(), 1, 1) {
+            Err(validity::CheckIndirectBufferError::DrawIndirectCountFeatureNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn count_buffer_missing_usage() {
+        let (device, _) = gfx_dev_and_queue!(draw_indirect_count);
+        let count_buffer = CpuAccessibleBuffer::from_data(
+            device.clone(),
+            BufferUsage::transfer_destination(),
+            false,
+            0u32,
+        )
+        .unwrap();
+
+        match validity::check_indirect_count_buffer(&device, count_buffer.as_ref(), 1, 1) {
+            Err(validity::CheckIndirectBufferError::CountBufferMissingUsage) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn count_buffer_offset_not_aligned() {
+        let (device, _) = gfx_dev_and_queue!(draw_indirect_count);
+        let count_buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::indirect_buffer(),
+            false,
+            0..8u8,
+        )
+        .unwrap();
+
+        match validity::check_indirect_count_buffer(
+            &device,
+            count_buffer.slice(2..6).unwrap().as_ref(),
+            1,
+            1,
+        ) {
+            Err(validity::CheckIndirectBufferError::CountBufferOffsetNotAligned) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn max_draw_count_out_of_range() {
+        let (device, _) = gfx_dev_and_queue!(draw_indirect_count);
+        let count_buffer = CpuAccessibleBuffer::from_data(
+            device.clone(),
+            BufferUsage::indirect_buffer(),
+            false,
+            0u32,
+        )
+        .unwrap();
+
+        match validity::check_indirect_count_buffer(&device, count_buffer.as_ref(), 3, 2) {
+            Err(validity::CheckIndirectBufferError::MaxDrawCountOutOfRange {
+                max_draw_count: 3,
+                indirect_buffer_len: 2,
+            }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn max_draw_indirect_count_exceeded() {
+        let (device, _) = gfx_dev_and_queue!(draw_indirect_count);
+        let limit = device
+            .physical_device()
+            .properties()
+            .max_draw_indirect_count;
+        let requested = match limit.checked_add(1) {
+            Some(requested) => requested,
+            None => return,
+        };
+        let count_buffer = CpuAccessibleBuffer::from_data(
+            device.clone(),
+            BufferUsage::indirect_buffer(),
+            false,
+            0u32,
+        )
+        .unwrap();
+
+        match validity::check_indirect_count_buffer(
+            &device,
+            count_buffer.as_ref(),
+            requested,
+            requested,
+        ) {
+            Err(validity::CheckIndirectBufferError::MaxDrawIndirectCountLimitExceeded {
+                ..
+            }) => (),
+            _ => panic!(),
+        }
+    }
+}
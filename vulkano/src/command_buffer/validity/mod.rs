@@ -9,52 +9,86 @@
 
 //! Functions that check the validity of commands.
 
-pub use self::blit_image::{check_blit_image, CheckBlitImageError};
+pub use self::blit_image::{check_blit_image, check_blit_image_regions, CheckBlitImageError};
+pub use self::clear_attachments::{check_clear_attachments, CheckClearAttachmentsError};
 pub use self::clear_color_image::{check_clear_color_image, CheckClearColorImageError};
 pub use self::clear_depth_stencil_image::{
     check_clear_depth_stencil_image, CheckClearDepthStencilImageError,
 };
-pub use self::copy_buffer::{check_copy_buffer, CheckCopyBuffer, CheckCopyBufferError};
-pub use self::copy_image::{check_copy_image, CheckCopyImageError};
+pub use self::conditional_rendering::{
+    check_begin_conditional_rendering, CheckBeginConditionalRenderingError,
+};
+pub use self::copy_buffer::{
+    check_copy_buffer, check_copy_buffer_regions, CheckCopyBuffer, CheckCopyBufferError,
+};
+pub use self::copy_image::{
+    check_copy_image, check_copy_image_regions, CheckCopyImageError, CheckCopyRegionError,
+};
 pub use self::copy_image_buffer::{
-    check_copy_buffer_image, CheckCopyBufferImageError, CheckCopyBufferImageTy,
+    check_copy_buffer_image, check_copy_buffer_image_regions, CheckCopyBufferImageError,
+    CheckCopyBufferImageTy,
 };
 pub use self::debug_marker::{check_debug_marker_color, CheckColorError};
+pub use self::debug_utils::{
+    check_debug_utils_enabled, check_debug_utils_label, CheckDebugUtilsLabelError,
+};
 pub use self::descriptor_sets::CheckDescriptorSetsValidityError;
-pub use self::dispatch::{check_dispatch, CheckDispatchError};
+pub use self::dispatch::{
+    check_dispatch, check_dispatch_base, check_dispatch_indirect, CheckDispatchError,
+};
 pub use self::dynamic_state::CheckDynamicStateValidityError;
 pub use self::fill_buffer::{check_fill_buffer, CheckFillBufferError};
 pub use self::index_buffer::CheckIndexBufferError;
-pub use self::indirect_buffer::{check_indirect_buffer, CheckIndirectBufferError};
+pub use self::indirect_buffer::{
+    check_indirect_buffer, check_indirect_buffer_layout, check_indirect_count_buffer,
+    CheckIndirectBufferError,
+};
+pub use self::multi_draw::{check_multi_draw, CheckMultiDrawError};
 pub use self::pipeline::CheckPipelineError;
+pub use self::pipeline_barrier::{
+    check_pipeline_barrier, check_pipeline_barrier_in_subpass, CheckPipelineBarrierError,
+};
 pub use self::push_constants::CheckPushConstantsValidityError;
 pub use self::query::{
     check_begin_query, check_copy_query_pool_results, check_end_query, check_reset_query_pool,
     check_write_timestamp, CheckBeginQueryError, CheckCopyQueryPoolResultsError,
     CheckEndQueryError, CheckResetQueryPoolError, CheckWriteTimestampError,
 };
+pub use self::resolve_image::{check_resolve_image_regions, CheckResolveImageError};
+pub use self::transform_feedback::{
+    check_bind_transform_feedback_buffers, check_draw_indirect_byte_count,
+    check_transform_feedback_counter_buffers, CheckTransformFeedbackError,
+};
 pub use self::update_buffer::{check_update_buffer, CheckUpdateBufferError};
 pub use self::vertex_buffers::CheckVertexBufferError;
 pub(super) use {
+    blit_image::blit_aspects, copy_image::copy_aspects, copy_image_buffer::default_aspect,
     descriptor_sets::*, dynamic_state::*, index_buffer::*, pipeline::*, push_constants::*,
     vertex_buffers::*,
 };
 
 mod blit_image;
+mod clear_attachments;
 mod clear_color_image;
 mod clear_depth_stencil_image;
+mod conditional_rendering;
 mod copy_buffer;
 mod copy_image;
 mod copy_image_buffer;
 mod debug_marker;
+mod debug_utils;
 mod descriptor_sets;
 mod dispatch;
 mod dynamic_state;
 mod fill_buffer;
 mod index_buffer;
 mod indirect_buffer;
+mod multi_draw;
 mod pipeline;
+mod pipeline_barrier;
 mod push_constants;
 mod query;
+mod resolve_image;
+mod transform_feedback;
 mod update_buffer;
 mod vertex_buffers;
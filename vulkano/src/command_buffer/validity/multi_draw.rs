@@ -0,0 +1,84 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use crate::device::Device;
+
+/// Checks whether `draw_count` draws can be submitted with a single multi-draw command.
+pub fn check_multi_draw(device: &Device, draw_count: u32) -> Result<(), CheckMultiDrawError> {
+    if !device.enabled_extensions().ext_multi_draw || !device.enabled_features().multi_draw {
+        return Err(CheckMultiDrawError::FeatureNotEnabled);
+    }
+
+    let limit = device
+        .physical_device()
+        .properties()
+        .max_multi_draw_count
+        .unwrap_or(0);
+
+    if draw_count > limit {
+        return Err(CheckMultiDrawError::MaxMultiDrawCountExceeded {
+            limit,
+            requested: draw_count,
+        });
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when checking multi-draw command validity.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckMultiDrawError {
+    /// The `ext_multi_draw` extension and the `multi_draw` feature must be enabled.
+    FeatureNotEnabled,
+    /// The maximum number of draws in a multi-draw command has been exceeded.
+    MaxMultiDrawCountExceeded {
+        /// The limit that must be fulfilled.
+        limit: u32,
+        /// What was requested.
+        requested: u32,
+    },
+}
+
+impl error::Error for CheckMultiDrawError {}
+
+impl fmt::Display for CheckMultiDrawError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                CheckMultiDrawError::FeatureNotEnabled => {
+                    "the ext_multi_draw extension and the multi_draw feature must be enabled"
+                }
+                CheckMultiDrawError::MaxMultiDrawCountExceeded { .. } => {
+                    "the maximum number of draws in a multi-draw command has been exceeded"
+                }
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_buffer::validity;
+
+    #[test]
+    fn feature_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match validity::check_multi_draw(&device, 1) {
+            Err(validity::CheckMultiDrawError::FeatureNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+}
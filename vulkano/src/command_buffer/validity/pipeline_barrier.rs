@@ -0,0 +1,386 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::command_buffer::BufferMemoryBarrier;
+use crate::command_buffer::ImageMemoryBarrier;
+use crate::command_buffer::MemoryBarrier;
+use crate::device::physical::QueueFamily;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::image::ImageLayout;
+use crate::render_pass::Subpass;
+use crate::sync::AccessFlags;
+use crate::sync::PipelineStages;
+use crate::VulkanObject;
+use std::error;
+use std::fmt;
+
+/// Checks whether the given barriers can be recorded in a command buffer of `queue_family`.
+///
+/// # Panic
+///
+/// - Panics if one of the buffers or images was not created with `device`.
+///
+pub fn check_pipeline_barrier(
+    device: &Device,
+    queue_family: QueueFamily,
+    memory_barriers: &[MemoryBarrier],
+    buffer_barriers: &[BufferMemoryBarrier],
+    image_barriers: &[ImageMemoryBarrier],
+) -> Result<(), CheckPipelineBarrierError> {
+    for barrier in memory_barriers {
+        check_stages_access(
+            device,
+            queue_family,
+            barrier.source_stages,
+            barrier.source_access,
+        )?;
+        check_stages_access(
+            device,
+            queue_family,
+            barrier.destination_stages,
+            barrier.destination_access,
+        )?;
+    }
+
+    for barrier in buffer_barriers {
+        assert_eq!(
+            barrier.buffer.inner().buffer.device().internal_object(),
+            device.internal_object()
+        );
+
+        check_stages_access(
+            device,
+            queue_family,
+            barrier.destination_stages,
+            barrier.destination_access,
+        )?;
+        check_queue_family_transfer(device, queue_family, barrier.queue_family_transfer)?;
+    }
+
+    for barrier in image_barriers {
+        assert_eq!(
+            barrier.image.inner().image.device().internal_object(),
+            device.internal_object()
+        );
+
+        check_stages_access(
+            device,
+            queue_family,
+            barrier.destination_stages,
+            barrier.destination_access,
+        )?;
+        check_queue_family_transfer(device, queue_family, barrier.queue_family_transfer)?;
+
+        let usage = barrier.image.inner().image.usage();
+        let usage_supported = match barrier.new_layout {
+            ImageLayout::Undefined | ImageLayout::Preinitialized => {
+                return Err(CheckPipelineBarrierError::InvalidLayout);
+            }
+            ImageLayout::General | ImageLayout::PresentSrc => true,
+            ImageLayout::ColorAttachmentOptimal => usage.color_attachment,
+            ImageLayout::DepthStencilAttachmentOptimal => usage.depth_stencil_attachment,
+            ImageLayout::DepthStencilReadOnlyOptimal => {
+                usage.depth_stencil_attachment || usage.sampled || usage.input_attachment
+            }
+            ImageLayout::ShaderReadOnlyOptimal => usage.sampled || usage.input_attachment,
+            ImageLayout::TransferSrcOptimal => usage.transfer_source,
+            ImageLayout::TransferDstOptimal => usage.transfer_destination,
+            ImageLayout::FragmentShadingRateAttachmentOptimal => {
+                usage.fragment_shading_rate_attachment
+            }
+        };
+
+        if !usage_supported {
+            return Err(CheckPipelineBarrierError::LayoutNotSupportedByUsage);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks whether the given barriers can be recorded inside `subpass`.
+///
+/// Inside a render pass, only memory barriers can be recorded, and only if the render pass has a
+/// dependency of the subpass on itself whose stages and accesses include those of the barriers.
+/// This must be checked in addition to `check_pipeline_barrier`.
+pub fn check_pipeline_barrier_in_subpass(
+    subpass: &Subpass,
+    memory_barriers: &[MemoryBarrier],
+    buffer_barriers: &[BufferMemoryBarrier],
+    image_barriers: &[ImageMemoryBarrier],
+) -> Result<(), CheckPipelineBarrierError> {
+    let self_dependency = subpass
+        .self_dependency()
+        .ok_or(CheckPipelineBarrierError::NoSelfDependency)?;
+
+    if !buffer_barriers.is_empty() || !image_barriers.is_empty() {
+        return Err(CheckPipelineBarrierError::ResourceBarrierInsideRenderPass);
+    }
+
+    for barrier in memory_barriers {
+        if !self_dependency
+            .source_stages
+            .contains(&barrier.source_stages)
+            || !self_dependency
+                .source_access
+                .contains(&barrier.source_access)
+            || !self_dependency
+                .destination_stages
+                .contains(&barrier.destination_stages)
+            || !self_dependency
+                .destination_access
+                .contains(&barrier.destination_access)
+        {
+            return Err(CheckPipelineBarrierError::NotCoveredBySelfDependency);
+        }
+    }
+
+    Ok(())
+}
+
+fn check_stages_access(
+    device: &Device,
+    queue_family: QueueFamily,
+    stages: PipelineStages,
+    access: AccessFlags,
+) -> Result<(), CheckPipelineBarrierError> {
+    if stages == PipelineStages::none() {
+        return Err(CheckPipelineBarrierError::StagesEmpty);
+    }
+
+    if !access.is_compatible_with(&stages) {
+        return Err(CheckPipelineBarrierError::AccessNotSupportedByStages);
+    }
+
+    if !stages.supported_by_queue_family(queue_family) {
+        return Err(CheckPipelineBarrierError::StageNotSupported);
+    }
+
+    if (stages.requires_synchronization2() || access.requires_synchronization2())
+        && !device.enabled_features().synchronization2
+    {
+        return Err(CheckPipelineBarrierError::Synchronization2FeatureNotEnabled);
+    }
+
+    if stages.geometry_shader && !device.enabled_features().geometry_shader {
+        return Err(CheckPipelineBarrierError::GeometryShaderFeatureNotEnabled);
+    }
+
+    if (stages.tessellation_control_shader || stages.tessellation_evaluation_shader)
+        && !device.enabled_features().tessellation_shader
+    {
+        return Err(CheckPipelineBarrierError::TessellationShaderFeatureNotEnabled);
+    }
+
+    Ok(())
+}
+
+fn check_queue_family_transfer(
+    device: &Device,
+    queue_family: QueueFamily,
+    transfer: Option<(u32, u32)>,
+) -> Result<(), CheckPipelineBarrierError> {
+    if let Some((source, destination)) = transfer {
+        let physical_device = device.physical_device();
+
+        if source == destination
+            || (source != queue_family.id() && destination != queue_family.id())
+            || physical_device.queue_family_by_id(source).is_none()
+            || physical_device.queue_family_by_id(destination).is_none()
+        {
+            return Err(CheckPipelineBarrierError::InvalidQueueFamilyTransfer);
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen from `check_pipeline_barrier`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckPipelineBarrierError {
+    /// The access flags of a barrier are not supported by its pipeline stages.
+    AccessNotSupportedByStages,
+    /// The stages of a barrier include the geometry shader stage, but the `geometry_shader`
+    /// feature is not enabled.
+    GeometryShaderFeatureNotEnabled,
+    /// The new layout of an image barrier is `Undefined` or `Preinitialized`.
+    InvalidLayout,
+    /// The queue families of an ownership transfer are the same, don't exist, or don't include
+    /// the queue family of the command buffer.
+    InvalidQueueFamilyTransfer,
+    /// The new layout of an image barrier requires a usage that the image wasn't created with.
+    LayoutNotSupportedByUsage,
+    /// The barriers are recorded inside a subpass that doesn't have a dependency on itself.
+    NoSelfDependency,
+    /// The stages or accesses of a barrier recorded inside a subpass are not included in the
+    /// dependency of the subpass on itself.
+    NotCoveredBySelfDependency,
+    /// A buffer or image barrier is recorded inside a render pass.
+    ResourceBarrierInsideRenderPass,
+    /// The stages of a barrier include a stage that the queue family of the command buffer
+    /// doesn't support.
+    StageNotSupported,
+    /// No pipeline stages were given for one side of a barrier.
+    StagesEmpty,
+    /// The stages or accesses of a barrier include one that was added by
+    /// `VK_KHR_synchronization2`, but the `synchronization2` feature is not enabled.
+    Synchronization2FeatureNotEnabled,
+    /// The stages of a barrier include a tessellation shader stage, but the
+    /// `tessellation_shader` feature is not enabled.
+    TessellationShaderFeatureNotEnabled,
+}
+
+impl error::Error for CheckPipelineBarrierError {}
+
+impl fmt::Display for CheckPipelineBarrierError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                Self::AccessNotSupportedByStages => {
+                    "the access flags of a barrier are not supported by its pipeline stages"
+                }
+                Self::GeometryShaderFeatureNotEnabled => {
+                    "the stages of a barrier include the geometry shader stage, but the \
+                     geometry_shader feature is not enabled"
+                }
+                Self::InvalidLayout => {
+                    "the new layout of an image barrier is Undefined or Preinitialized"
+                }
+                Self::InvalidQueueFamilyTransfer => {
+                    "the queue families of an ownership transfer are the same, don't exist, or \
+                     don't include the queue family of the command buffer"
+                }
+                Self::LayoutNotSupportedByUsage => {
+                    "the new layout of an image barrier requires a usage that the image wasn't \
+                     created with"
+                }
+                Self::NoSelfDependency => {
+                    "the barriers are recorded inside a subpass that doesn't have a dependency on \
+                     itself"
+                }
+                Self::NotCoveredBySelfDependency => {
+                    "the stages or accesses of a barrier recorded inside a subpass are not \
+                     included in the dependency of the subpass on itself"
+                }
+                Self::ResourceBarrierInsideRenderPass => {
+                    "a buffer or image barrier is recorded inside a render pass"
+                }
+                Self::StageNotSupported => {
+                    "the stages of a barrier include a stage that the queue family of the command \
+                     buffer doesn't support"
+                }
+                Self::StagesEmpty => "no pipeline stages were given for one side of a barrier",
+                Self::Synchronization2FeatureNotEnabled => {
+                    "the stages or accesses of a barrier include one that was added by \
+                     VK_KHR_synchronization2, but the synchronization2 feature is not enabled"
+                }
+                Self::TessellationShaderFeatureNotEnabled => {
+                    "the stages of a barrier include a tessellation shader stage, but the \
+                     tessellation_shader feature is not enabled"
+                }
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::command_buffer::validity;
+    use crate::command_buffer::BufferMemoryBarrier;
+    use crate::command_buffer::MemoryBarrier;
+    use crate::sync::AccessFlags;
+    use crate::sync::PipelineStages;
+
+    #[test]
+    fn access_not_supported_by_stages() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let barrier = MemoryBarrier {
+            source_stages: PipelineStages {
+                transfer: true,
+                ..PipelineStages::none()
+            },
+            source_access: AccessFlags {
+                transfer_write: true,
+                ..AccessFlags::none()
+            },
+            destination_stages: PipelineStages {
+                transfer: true,
+                ..PipelineStages::none()
+            },
+            destination_access: AccessFlags {
+                shader_read: true,
+                ..AccessFlags::none()
+            },
+        };
+
+        assert_eq!(
+            validity::check_pipeline_barrier(&device, queue.family(), &[barrier], &[], &[]),
+            Err(validity::CheckPipelineBarrierError::AccessNotSupportedByStages)
+        );
+    }
+
+    #[test]
+    fn synchronization2_not_enabled() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let barrier = MemoryBarrier {
+            source_stages: PipelineStages {
+                copy: true,
+                ..PipelineStages::none()
+            },
+            source_access: AccessFlags {
+                transfer_write: true,
+                ..AccessFlags::none()
+            },
+            destination_stages: PipelineStages {
+                transfer: true,
+                ..PipelineStages::none()
+            },
+            destination_access: AccessFlags {
+                transfer_read: true,
+                ..AccessFlags::none()
+            },
+        };
+
+        assert_eq!(
+            validity::check_pipeline_barrier(&device, queue.family(), &[barrier], &[], &[]),
+            Err(validity::CheckPipelineBarrierError::Synchronization2FeatureNotEnabled)
+        );
+    }
+
+    #[test]
+    fn transfer_to_same_queue_family() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let buffer =
+            CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                .unwrap();
+        let barrier = BufferMemoryBarrier {
+            buffer,
+            destination_stages: PipelineStages {
+                transfer: true,
+                ..PipelineStages::none()
+            },
+            destination_access: AccessFlags {
+                transfer_read: true,
+                ..AccessFlags::none()
+            },
+            queue_family_transfer: Some((queue.family().id(), queue.family().id())),
+        };
+
+        assert_eq!(
+            validity::check_pipeline_barrier(&device, queue.family(), &[], &[barrier], &[]),
+            Err(validity::CheckPipelineBarrierError::InvalidQueueFamilyTransfer)
+        );
+    }
+}
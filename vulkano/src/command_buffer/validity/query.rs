@@ -189,6 +189,17 @@ pub fn check_write_timestamp(
                 return Err(CheckWriteTimestampError::TessellationShaderFeatureNotEnabled);
             }
         }
+        PipelineStage::Copy
+        | PipelineStage::Resolve
+        | PipelineStage::Blit
+        | PipelineStage::Clear
+        | PipelineStage::IndexInput
+        | PipelineStage::VertexAttributeInput
+        | PipelineStage::PreRasterizationShaders => {
+            if !device.enabled_features().synchronization2 {
+                return Err(CheckWriteTimestampError::Synchronization2FeatureNotEnabled);
+            }
+        }
         _ => (),
     }
 
@@ -208,6 +219,9 @@ pub enum CheckWriteTimestampError {
     OutOfRange,
     /// The provided stage is not supported by the queue family.
     StageNotSupported,
+    /// A stage that was added by `VK_KHR_synchronization2` was requested, but the
+    /// `synchronization2` feature was not enabled.
+    Synchronization2FeatureNotEnabled,
     /// A tessellation shader stage was requested, but the `tessellation_shader` feature was not enabled.
     TessellationShaderFeatureNotEnabled,
 }
@@ -236,6 +250,10 @@ impl fmt::Display for CheckWriteTimestampError {
                 Self::StageNotSupported => {
                     "the provided stage is not supported by the queue family"
                 }
+                Self::Synchronization2FeatureNotEnabled => {
+                    "a stage that was added by VK_KHR_synchronization2 was requested, but the \
+                     synchronization2 feature was not enabled"
+                }
                 Self::TessellationShaderFeatureNotEnabled => {
                     "a tessellation shader stage was requested, but the tessellation_shader feature was not enabled"
                 }
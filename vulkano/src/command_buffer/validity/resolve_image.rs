@@ -0,0 +1,245 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::command_buffer::validity::copy_image::check_image_subresource_region;
+use crate::command_buffer::validity::CheckCopyRegionError;
+use crate::command_buffer::ImageResolve;
+use crate::device::Device;
+use crate::image::ImageAccess;
+use crate::image::SampleCount;
+use crate::VulkanObject;
+use std::error;
+use std::fmt;
+
+/// Checks whether a resolve image command with the given regions is valid.
+///
+/// # Panic
+///
+/// - Panics if the source or the destination was not created with `device`.
+///
+pub fn check_resolve_image_regions<S, D>(
+    device: &Device,
+    source: &S,
+    destination: &D,
+    regions: &[ImageResolve],
+) -> Result<(), CheckResolveImageError>
+where
+    S: ?Sized + ImageAccess,
+    D: ?Sized + ImageAccess,
+{
+    let source_inner = source.inner();
+    let destination_inner = destination.inner();
+
+    assert_eq!(
+        source_inner.image.device().internal_object(),
+        device.internal_object()
+    );
+    assert_eq!(
+        destination_inner.image.device().internal_object(),
+        device.internal_object()
+    );
+
+    // The images are transitioned to the transfer layouts, which require the transfer usages.
+    if !source_inner.image.usage().transfer_source {
+        return Err(CheckResolveImageError::MissingTransferSourceUsage);
+    }
+
+    if !destination_inner.image.usage().transfer_destination {
+        return Err(CheckResolveImageError::MissingTransferDestinationUsage);
+    }
+
+    if source.samples() == SampleCount::Sample1 {
+        return Err(CheckResolveImageError::SourceNotMultisampled);
+    }
+
+    if destination.samples() != SampleCount::Sample1 {
+        return Err(CheckResolveImageError::DestinationMultisampled);
+    }
+
+    if source.format() != destination.format() {
+        return Err(CheckResolveImageError::FormatMismatch);
+    }
+
+    if !source.format().aspects().color {
+        return Err(CheckResolveImageError::NotColorFormat);
+    }
+
+    if !destination_inner.image.format_features().color_attachment {
+        return Err(CheckResolveImageError::DestinationFormatNotSupported);
+    }
+
+    for (region_index, region) in regions.iter().enumerate() {
+        check_image_subresource_region(
+            source,
+            region.source_mip_level,
+            region.source_base_array_layer,
+            region.layer_count,
+            region.source_offset,
+            region.extent,
+        )
+        .map_err(|error| CheckResolveImageError::SourceRegion {
+            region_index,
+            error,
+        })?;
+
+        check_image_subresource_region(
+            destination,
+            region.destination_mip_level,
+            region.destination_base_array_layer,
+            region.layer_count,
+            region.destination_offset,
+            region.extent,
+        )
+        .map_err(|error| CheckResolveImageError::DestinationRegion {
+            region_index,
+            error,
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Error that can happen from `check_resolve_image_regions`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckResolveImageError {
+    /// The source is missing the transfer source usage.
+    MissingTransferSourceUsage,
+    /// The destination is missing the transfer destination usage.
+    MissingTransferDestinationUsage,
+    /// The source image must be multisampled.
+    SourceNotMultisampled,
+    /// The destination image must not be multisampled.
+    DestinationMultisampled,
+    /// The format of the source and destination must be equal.
+    FormatMismatch,
+    /// The format of the images must be a color format.
+    NotColorFormat,
+    /// The format of the destination image doesn't support being used as a color attachment.
+    DestinationFormatNotSupported,
+    /// A region is invalid for the source image.
+    SourceRegion {
+        /// The index of the region.
+        region_index: usize,
+        /// Why the region is invalid.
+        error: CheckCopyRegionError,
+    },
+    /// A region is invalid for the destination image.
+    DestinationRegion {
+        /// The index of the region.
+        region_index: usize,
+        /// Why the region is invalid.
+        error: CheckCopyRegionError,
+    },
+}
+
+impl error::Error for CheckResolveImageError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            CheckResolveImageError::SourceRegion { ref error, .. } => Some(error),
+            CheckResolveImageError::DestinationRegion { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CheckResolveImageError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                CheckResolveImageError::MissingTransferSourceUsage => {
+                    "the source is missing the transfer source usage"
+                }
+                CheckResolveImageError::MissingTransferDestinationUsage => {
+                    "the destination is missing the transfer destination usage"
+                }
+                CheckResolveImageError::SourceNotMultisampled => {
+                    "the source image must be multisampled"
+                }
+                CheckResolveImageError::DestinationMultisampled => {
+                    "the destination image must not be multisampled"
+                }
+                CheckResolveImageError::FormatMismatch => {
+                    "the format of the source and destination must be equal"
+                }
+                CheckResolveImageError::NotColorFormat => {
+                    "the format of the images must be a color format"
+                }
+                CheckResolveImageError::DestinationFormatNotSupported => {
+                    "the format of the destination image doesn't support being used as a color \
+                     attachment"
+                }
+                CheckResolveImageError::SourceRegion { .. } => {
+                    "a region is invalid for the source image"
+                }
+                CheckResolveImageError::DestinationRegion { .. } => {
+                    "a region is invalid for the destination image"
+                }
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_buffer::validity;
+    use crate::command_buffer::ImageResolve;
+    use crate::format::Format;
+    use crate::image::ImageDimensions;
+    use crate::image::StorageImage;
+
+    #[test]
+    fn source_not_multisampled() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let dimensions = ImageDimensions::Dim2d {
+            width: 16,
+            height: 16,
+            array_layers: 1,
+        };
+        let source = StorageImage::new(
+            device.clone(),
+            dimensions,
+            Format::R8G8B8A8_UNORM,
+            Some(queue.family()),
+        )
+        .unwrap();
+        let destination = StorageImage::new(
+            device.clone(),
+            dimensions,
+            Format::R8G8B8A8_UNORM,
+            Some(queue.family()),
+        )
+        .unwrap();
+
+        let region = ImageResolve {
+            source_mip_level: 0,
+            source_base_array_layer: 0,
+            source_offset: [0, 0, 0],
+            destination_mip_level: 0,
+            destination_base_array_layer: 0,
+            destination_offset: [0, 0, 0],
+            layer_count: 1,
+            extent: [16, 16, 1],
+        };
+
+        assert_eq!(
+            validity::check_resolve_image_regions(
+                &device,
+                source.as_ref(),
+                destination.as_ref(),
+                &[region]
+            ),
+            Err(validity::CheckResolveImageError::SourceNotMultisampled)
+        );
+    }
+}
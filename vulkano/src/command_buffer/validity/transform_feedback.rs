@@ -0,0 +1,281 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::buffer::BufferAccess;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::DeviceSize;
+use crate::VulkanObject;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+/// Checks whether the given buffers can be bound as transform feedback buffers, starting at
+/// `first_binding`.
+pub fn check_bind_transform_feedback_buffers(
+    device: &Device,
+    first_binding: u32,
+    buffers: &[Arc<dyn BufferAccess>],
+) -> Result<(), CheckTransformFeedbackError> {
+    check_feature(device)?;
+
+    let properties = device.physical_device().properties();
+    let limit = properties.max_transform_feedback_buffers.unwrap_or(0);
+    let requested = first_binding + buffers.len() as u32;
+
+    if requested > limit {
+        return Err(
+            CheckTransformFeedbackError::MaxTransformFeedbackBuffersExceeded { limit, requested },
+        );
+    }
+
+    let size_limit = properties.max_transform_feedback_buffer_size.unwrap_or(0);
+
+    for buffer in buffers {
+        assert_eq!(
+            buffer.inner().buffer.device().internal_object(),
+            device.internal_object()
+        );
+
+        if !buffer.inner().buffer.usage().transform_feedback_buffer {
+            return Err(CheckTransformFeedbackError::BufferMissingUsage);
+        }
+
+        if buffer.inner().offset % 4 != 0 {
+            return Err(CheckTransformFeedbackError::BufferOffsetNotAligned);
+        }
+
+        if buffer.size() > size_limit {
+            return Err(
+                CheckTransformFeedbackError::MaxTransformFeedbackBufferSizeExceeded {
+                    limit: size_limit,
+                    requested: buffer.size(),
+                },
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks whether the given counter buffers can be used to begin or end transform feedback.
+pub fn check_transform_feedback_counter_buffers(
+    device: &Device,
+    counter_buffers: &[Option<Arc<dyn BufferAccess>>],
+) -> Result<(), CheckTransformFeedbackError> {
+    check_feature(device)?;
+
+    let limit = device
+        .physical_device()
+        .properties()
+        .max_transform_feedback_buffers
+        .unwrap_or(0);
+    let requested = counter_buffers.len() as u32;
+
+    if requested > limit {
+        return Err(
+            CheckTransformFeedbackError::MaxTransformFeedbackBuffersExceeded { limit, requested },
+        );
+    }
+
+    for counter_buffer in counter_buffers.iter().flatten() {
+        assert_eq!(
+            counter_buffer.inner().buffer.device().internal_object(),
+            device.internal_object()
+        );
+
+        if !counter_buffer
+            .inner()
+            .buffer
+            .usage()
+            .transform_feedback_counter_buffer
+        {
+            return Err(CheckTransformFeedbackError::CounterBufferMissingUsage);
+        }
+
+        if counter_buffer.inner().offset % 4 != 0 {
+            return Err(CheckTransformFeedbackError::CounterBufferOffsetNotAligned);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks whether a draw command can take its vertex count from `counter_buffer`, with the given
+/// vertex stride.
+pub fn check_draw_indirect_byte_count<B>(
+    device: &Device,
+    counter_buffer: &B,
+    vertex_stride: u32,
+) -> Result<(), CheckTransformFeedbackError>
+where
+    B: ?Sized + BufferAccess,
+{
+    assert_eq!(
+        counter_buffer.inner().buffer.device().internal_object(),
+        device.internal_object()
+    );
+
+    check_feature(device)?;
+
+    let properties = device.physical_device().properties();
+
+    if !properties.transform_feedback_draw.unwrap_or(false) {
+        return Err(CheckTransformFeedbackError::DrawNotSupported);
+    }
+
+    if !counter_buffer.inner().buffer.usage().indirect_buffer {
+        return Err(CheckTransformFeedbackError::CounterBufferMissingIndirectUsage);
+    }
+
+    if counter_buffer.inner().offset % 4 != 0 {
+        return Err(CheckTransformFeedbackError::CounterBufferOffsetNotAligned);
+    }
+
+    let limit = properties
+        .max_transform_feedback_buffer_data_stride
+        .unwrap_or(0);
+
+    if vertex_stride == 0 || vertex_stride > limit {
+        return Err(CheckTransformFeedbackError::VertexStrideOutOfRange {
+            limit,
+            requested: vertex_stride,
+        });
+    }
+
+    Ok(())
+}
+
+fn check_feature(device: &Device) -> Result<(), CheckTransformFeedbackError> {
+    if !device.enabled_extensions().ext_transform_feedback
+        || !device.enabled_features().transform_feedback
+    {
+        return Err(CheckTransformFeedbackError::FeatureNotEnabled);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when checking the validity of a transform feedback command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckTransformFeedbackError {
+    /// The "transform feedback buffer" usage must be enabled on the buffer.
+    BufferMissingUsage,
+    /// The offset of the buffer must be a multiple of 4.
+    BufferOffsetNotAligned,
+    /// The "indirect buffer" usage must be enabled on the counter buffer.
+    CounterBufferMissingIndirectUsage,
+    /// The "transform feedback counter buffer" usage must be enabled on the counter buffer.
+    CounterBufferMissingUsage,
+    /// The offset of the counter buffer must be a multiple of 4.
+    CounterBufferOffsetNotAligned,
+    /// The [`transform_feedback_draw`](crate::device::Properties::transform_feedback_draw)
+    /// device property is `false`.
+    DrawNotSupported,
+    /// The `ext_transform_feedback` extension and the `transform_feedback` feature must be
+    /// enabled.
+    FeatureNotEnabled,
+    /// The maximum size of a transform feedback buffer has been exceeded.
+    MaxTransformFeedbackBufferSizeExceeded {
+        /// The limit that must be fulfilled.
+        limit: DeviceSize,
+        /// What was requested.
+        requested: DeviceSize,
+    },
+    /// The maximum number of transform feedback buffers has been exceeded.
+    MaxTransformFeedbackBuffersExceeded {
+        /// The limit that must be fulfilled.
+        limit: u32,
+        /// What was requested.
+        requested: u32,
+    },
+    /// Transform feedback can't be used in a subpass that has multiview enabled.
+    MultiviewEnabled,
+    /// The vertex stride must be greater than 0 and not greater than the
+    /// [`max_transform_feedback_buffer_data_stride`](crate::device::Properties::max_transform_feedback_buffer_data_stride)
+    /// limit.
+    VertexStrideOutOfRange {
+        /// The limit that must be fulfilled.
+        limit: u32,
+        /// What was requested.
+        requested: u32,
+    },
+}
+
+impl error::Error for CheckTransformFeedbackError {}
+
+impl fmt::Display for CheckTransformFeedbackError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                Self::BufferMissingUsage => {
+                    "the transform feedback buffer usage must be enabled on the buffer"
+                }
+                Self::BufferOffsetNotAligned => "the offset of the buffer must be a multiple of 4",
+                Self::CounterBufferMissingIndirectUsage => {
+                    "the indirect buffer usage must be enabled on the counter buffer"
+                }
+                Self::CounterBufferMissingUsage => {
+                    "the transform feedback counter buffer usage must be enabled on the counter \
+                     buffer"
+                }
+                Self::CounterBufferOffsetNotAligned => {
+                    "the offset of the counter buffer must be a multiple of 4"
+                }
+                Self::DrawNotSupported => "the transform_feedback_draw device property is false",
+                Self::FeatureNotEnabled => {
+                    "the ext_transform_feedback extension and the transform_feedback feature must \
+                     be enabled"
+                }
+                Self::MaxTransformFeedbackBufferSizeExceeded { .. } => {
+                    "the maximum size of a transform feedback buffer has been exceeded"
+                }
+                Self::MaxTransformFeedbackBuffersExceeded { .. } => {
+                    "the maximum number of transform feedback buffers has been exceeded"
+                }
+                Self::MultiviewEnabled => {
+                    "transform feedback can't be used in a subpass that has multiview enabled"
+                }
+                Self::VertexStrideOutOfRange { .. } => {
+                    "the vertex stride was 0 or greater than the \
+                     max_transform_feedback_buffer_data_stride limit"
+                }
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::BufferAccess;
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::command_buffer::validity;
+    use std::sync::Arc;
+
+    #[test]
+    fn feature_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+        let buffer =
+            CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                .unwrap();
+
+        assert_eq!(
+            validity::check_bind_transform_feedback_buffers(
+                &device,
+                0,
+                &[buffer as Arc<dyn BufferAccess>]
+            ),
+            Err(validity::CheckTransformFeedbackError::FeatureNotEnabled)
+        );
+    }
+}
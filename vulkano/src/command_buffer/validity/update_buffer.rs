@@ -47,6 +47,10 @@ where
 
     let size = cmp::min(buffer.size(), mem::size_of_val(data) as DeviceSize);
 
+    if size == 0 {
+        return Err(CheckUpdateBufferError::DataEmpty);
+    }
+
     if size % 4 != 0 {
         return Err(CheckUpdateBufferError::WrongAlignment);
     }
@@ -67,6 +71,8 @@ pub enum CheckUpdateBufferError {
     WrongAlignment,
     /// The data must not be larger than 64k bytes.
     DataTooLarge,
+    /// The data must not be empty.
+    DataEmpty,
 }
 
 impl error::Error for CheckUpdateBufferError {}
@@ -85,6 +91,7 @@ impl fmt::Display for CheckUpdateBufferError {
                     "the offset or size are not aligned to 4 bytes"
                 }
                 CheckUpdateBufferError::DataTooLarge => "data is too large",
+                CheckUpdateBufferError::DataEmpty => "data is empty",
             }
         )
     }
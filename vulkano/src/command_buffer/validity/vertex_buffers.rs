@@ -21,7 +21,7 @@ pub(in super::super) fn check_vertex_buffers(
     vertices: Option<(u32, u32)>,
     instances: Option<(u32, u32)>,
 ) -> Result<(), CheckVertexBufferError> {
-    let vertex_input = pipeline.vertex_input();
+    let vertex_input = current_state.vertex_input_for(pipeline);
     let mut max_vertex_count: Option<u32> = None;
     let mut max_instance_count: Option<u32> = None;
 
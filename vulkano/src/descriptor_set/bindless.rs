@@ -0,0 +1,528 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! A helper for "bindless" rendering, built on top of descriptor indexing.
+//!
+//! With bindless rendering, all the textures and buffers that shaders may need are put in large
+//! descriptor arrays of one descriptor set. This descriptor set is bound once, and shaders then
+//! pick the resources they need by index, usually passed through push constants or buffers.
+//!
+//! `BindlessResources` manages such a descriptor set. It hands out stable `u32` indices for the
+//! textures and buffers that are added to it, and keeps track of the slots that are free. It
+//! creates its own descriptor set layout with the following bindings:
+//!
+//! - Binding [`IMAGE_BINDING`](BindlessResources::IMAGE_BINDING) is an array of combined image
+//!   samplers, that can be declared in GLSL as `uniform sampler2D textures[];`.
+//! - Binding [`BUFFER_BINDING`](BindlessResources::BUFFER_BINDING) is an array of storage
+//!   buffers, that can be declared in GLSL as `buffer Buffers { ... } buffers[];`.
+//!
+//! Both bindings are created with the `partially_bound`, `update_after_bind` and
+//! `update_unused_while_pending` flags, which means that:
+//!
+//! - Slots that have never been written to are valid, as long as shaders don't access them.
+//! - Resources can be added after the descriptor set has been bound in a command buffer that
+//!   hasn't been submitted yet.
+//!
+//! Adding resources still writes to the descriptor set with
+//! [`UpdateAfterBindDescriptorSet::update`], which requires that the descriptor set is not in use
+//! by a command buffer pending execution. You must therefore wait for the command buffers that
+//! use the descriptor set to finish executing before adding resources, even if they don't use the
+//! new slots.
+//!
+//! The last binding, [`BUFFER_BINDING`](BindlessResources::BUFFER_BINDING), also has a variable
+//! descriptor count, whose actual count is given when the descriptor set is allocated.
+//!
+//! The device must have been created with the `runtime_descriptor_array`,
+//! `descriptor_binding_partially_bound`, `descriptor_binding_update_unused_while_pending`,
+//! `descriptor_binding_variable_descriptor_count`,
+//! `descriptor_binding_sampled_image_update_after_bind` and
+//! `descriptor_binding_storage_buffer_update_after_bind` features enabled.
+
+use crate::buffer::BufferAccess;
+use crate::descriptor_set::layout::{
+    DescriptorDesc, DescriptorSetDesc, DescriptorSetLayout, DescriptorSetLayoutError,
+    DescriptorType,
+};
+use crate::descriptor_set::pool::{
+    DescriptorPool, DescriptorPoolAlloc, DescriptorPoolAllocError, DescriptorSetAllocateInfo,
+    UnsafeDescriptorPool,
+};
+use crate::descriptor_set::sys::DescriptorWrite;
+use crate::descriptor_set::{
+    DescriptorSetError, MissingBufferUsage, MissingImageUsage, UnsafeDescriptorSet,
+    UpdateAfterBindDescriptorSet,
+};
+use crate::device::{Device, DeviceOwned};
+use crate::image::ImageViewAbstract;
+use crate::sampler::Sampler;
+use crate::shader::ShaderStages;
+use crate::VulkanObject;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// Manages a descriptor set of large arrays of textures and buffers, indexed by shaders.
+///
+/// See [the module-level documentation](self) for more information.
+pub struct BindlessResources {
+    descriptor_set: Arc<UpdateAfterBindDescriptorSet<BindlessDescriptorPoolAlloc>>,
+    images: Mutex<SlotAllocator>,
+    buffers: Mutex<SlotAllocator>,
+}
+
+impl BindlessResources {
+    /// The binding number of the array of combined image samplers.
+    pub const IMAGE_BINDING: u32 = 0;
+
+    /// The binding number of the array of storage buffers.
+    pub const BUFFER_BINDING: u32 = 1;
+
+    /// Creates a new `BindlessResources` with room for `max_images` textures and `max_buffers`
+    /// buffers, accessible from the shader stages `stages`.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `max_images` or `max_buffers` is 0.
+    pub fn new(
+        device: Arc<Device>,
+        stages: ShaderStages,
+        max_images: u32,
+        max_buffers: u32,
+    ) -> Result<BindlessResources, BindlessResourcesError> {
+        assert_ne!(max_images, 0);
+        assert_ne!(max_buffers, 0);
+
+        if !device.enabled_features().runtime_descriptor_array {
+            return Err(BindlessResourcesError::FeatureNotEnabled {
+                feature: "runtime_descriptor_array",
+                reason: "shaders index into arrays of unknown size",
+            });
+        }
+
+        let binding = |ty, descriptor_count, variable_count| DescriptorDesc {
+            ty,
+            descriptor_count,
+            variable_count,
+            update_after_bind: true,
+            update_unused_while_pending: true,
+            partially_bound: true,
+            stages,
+            immutable_samplers: Vec::new(),
+            mutable_types: Vec::new(),
+        };
+
+        let layout = DescriptorSetLayout::new(
+            device.clone(),
+            DescriptorSetDesc::new([
+                Some(binding(
+                    DescriptorType::CombinedImageSampler,
+                    max_images,
+                    false,
+                )),
+                // Only the last binding of a layout can have a variable count.
+                Some(binding(DescriptorType::StorageBuffer, max_buffers, true)),
+            ]),
+        )?;
+
+        let descriptor_set = UpdateAfterBindDescriptorSet::new_with_pool(
+            layout,
+            max_buffers,
+            &mut BindlessDescriptorPool { device },
+        )?;
+
+        Ok(BindlessResources {
+            descriptor_set,
+            images: Mutex::new(SlotAllocator::new(max_images)),
+            buffers: Mutex::new(SlotAllocator::new(max_buffers)),
+        })
+    }
+
+    /// Returns the layout of the descriptor set. Use it to create pipeline layouts.
+    #[inline]
+    pub fn layout(&self) -> &Arc<DescriptorSetLayout> {
+        self.descriptor_set.layout()
+    }
+
+    /// Returns the descriptor set, to bind in command buffers.
+    #[inline]
+    pub fn descriptor_set(
+        &self,
+    ) -> &Arc<UpdateAfterBindDescriptorSet<BindlessDescriptorPoolAlloc>> {
+        &self.descriptor_set
+    }
+
+    /// Returns the maximum number of textures.
+    #[inline]
+    pub fn max_images(&self) -> u32 {
+        self.images.lock().unwrap().capacity
+    }
+
+    /// Returns the maximum number of buffers.
+    #[inline]
+    pub fn max_buffers(&self) -> u32 {
+        self.buffers.lock().unwrap().capacity
+    }
+
+    /// Adds a texture, and returns its index in the array of binding
+    /// [`IMAGE_BINDING`](Self::IMAGE_BINDING).
+    ///
+    /// # Safety
+    ///
+    /// - See the safety requirements of [`UpdateAfterBindDescriptorSet::update`]. Removed slots
+    ///   are reused, so you must also ensure that command buffers pending execution are not using
+    ///   a slot that was removed, as it may be overwritten.
+    pub unsafe fn add_image(
+        &self,
+        image_view: Arc<dyn ImageViewAbstract>,
+        sampler: Arc<Sampler>,
+    ) -> Result<u32, BindlessResourcesError> {
+        if image_view.image().inner().image.device().internal_object()
+            != self.device().internal_object()
+            || sampler.device().internal_object() != self.device().internal_object()
+        {
+            return Err(DescriptorSetError::ResourceWrongDevice.into());
+        }
+
+        if !image_view.usage().sampled {
+            return Err(DescriptorSetError::MissingImageUsage(MissingImageUsage::Sampled).into());
+        }
+
+        if !image_view.can_be_sampled(&sampler) {
+            return Err(DescriptorSetError::IncompatibleImageViewSampler.into());
+        }
+
+        let index = self
+            .images
+            .lock()
+            .unwrap()
+            .alloc()
+            .ok_or(BindlessResourcesError::OutOfSlots)?;
+        self.descriptor_set
+            .update([&DescriptorWrite::image_view_sampler(
+                Self::IMAGE_BINDING,
+                index,
+                [(image_view, sampler)],
+            )]);
+
+        Ok(index)
+    }
+
+    /// Adds a storage buffer, and returns its index in the array of binding
+    /// [`BUFFER_BINDING`](Self::BUFFER_BINDING).
+    ///
+    /// # Safety
+    ///
+    /// - See [`add_image`](Self::add_image).
+    pub unsafe fn add_buffer(
+        &self,
+        buffer: Arc<dyn BufferAccess>,
+    ) -> Result<u32, BindlessResourcesError> {
+        if buffer.inner().buffer.device().internal_object() != self.device().internal_object() {
+            return Err(DescriptorSetError::ResourceWrongDevice.into());
+        }
+
+        if !buffer.inner().buffer.usage().storage_buffer {
+            return Err(
+                DescriptorSetError::MissingBufferUsage(MissingBufferUsage::StorageBuffer).into(),
+            );
+        }
+
+        let index = self
+            .buffers
+            .lock()
+            .unwrap()
+            .alloc()
+            .ok_or(BindlessResourcesError::OutOfSlots)?;
+        self.descriptor_set.update([&DescriptorWrite::buffer(
+            Self::BUFFER_BINDING,
+            index,
+            [buffer],
+        )]);
+
+        Ok(index)
+    }
+
+    /// Marks the texture slot `index` as free, so that it can be handed out again by
+    /// `add_image`.
+    ///
+    /// The texture and sampler are kept alive until the slot is reused.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `index` is not a slot that is currently in use.
+    ///
+    /// # Safety
+    ///
+    /// - Shaders must no longer access the slot once a new texture has been added to it.
+    #[inline]
+    pub unsafe fn remove_image(&self, index: u32) {
+        self.images.lock().unwrap().free(index);
+    }
+
+    /// Marks the buffer slot `index` as free, so that it can be handed out again by
+    /// `add_buffer`.
+    ///
+    /// The buffer is kept alive until the slot is reused.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `index` is not a slot that is currently in use.
+    ///
+    /// # Safety
+    ///
+    /// - Shaders must no longer access the slot once a new buffer has been added to it.
+    #[inline]
+    pub unsafe fn remove_buffer(&self, index: u32) {
+        self.buffers.lock().unwrap().free(index);
+    }
+}
+
+unsafe impl DeviceOwned for BindlessResources {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.descriptor_set.device()
+    }
+}
+
+/// Hands out indices in `0..capacity`, reusing the ones that have been freed.
+#[derive(Debug)]
+struct SlotAllocator {
+    capacity: u32,
+    // The next index that has never been handed out.
+    next: u32,
+    free: Vec<u32>,
+    // Whether each index below `next` is in `free`.
+    is_free: Vec<bool>,
+}
+
+impl SlotAllocator {
+    fn new(capacity: u32) -> SlotAllocator {
+        SlotAllocator {
+            capacity,
+            next: 0,
+            free: Vec::new(),
+            is_free: Vec::new(),
+        }
+    }
+
+    fn alloc(&mut self) -> Option<u32> {
+        if let Some(index) = self.free.pop() {
+            self.is_free[index as usize] = false;
+            return Some(index);
+        }
+
+        if self.next < self.capacity {
+            self.next += 1;
+            self.is_free.push(false);
+            Some(self.next - 1)
+        } else {
+            None
+        }
+    }
+
+    fn free(&mut self, index: u32) {
+        assert!(
+            index < self.next && !self.is_free[index as usize],
+            "slot {} is not in use",
+            index,
+        );
+        self.is_free[index as usize] = true;
+        self.free.push(index);
+    }
+}
+
+// Creates a Vulkan pool with exactly the room needed for one descriptor set, as the descriptor
+// arrays of bindless layouts are usually too large to be shared with other descriptor sets.
+struct BindlessDescriptorPool {
+    device: Arc<Device>,
+}
+
+unsafe impl DescriptorPool for BindlessDescriptorPool {
+    type Alloc = BindlessDescriptorPoolAlloc;
+
+    fn alloc(
+        &mut self,
+        layout: &Arc<DescriptorSetLayout>,
+        variable_descriptor_count: u32,
+    ) -> Result<BindlessDescriptorPoolAlloc, DescriptorPoolAllocError> {
+        let mut pool = UnsafeDescriptorPool::new(
+            self.device.clone(),
+            layout.descriptors_count(),
+            1,
+            false,
+            layout.desc().is_update_after_bind(),
+        )?;
+
+        // The pool is created with exactly the right capacity, but the implementation may still
+        // need more room than what we have computed from the layout.
+        let set = unsafe {
+            pool.alloc([DescriptorSetAllocateInfo {
+                layout,
+                variable_descriptor_count,
+            }])?
+            .next()
+            .unwrap()
+        };
+
+        Ok(BindlessDescriptorPoolAlloc { _pool: pool, set })
+    }
+}
+
+unsafe impl DeviceOwned for BindlessDescriptorPool {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+/// The allocation of the descriptor set of a `BindlessResources`.
+///
+/// The descriptor set has its own Vulkan pool, which is destroyed along with it.
+pub struct BindlessDescriptorPoolAlloc {
+    // Destroying the pool frees the descriptor set.
+    _pool: UnsafeDescriptorPool,
+    set: UnsafeDescriptorSet,
+}
+
+impl DescriptorPoolAlloc for BindlessDescriptorPoolAlloc {
+    #[inline]
+    fn inner(&self) -> &UnsafeDescriptorSet {
+        &self.set
+    }
+
+    #[inline]
+    fn inner_mut(&mut self) -> &mut UnsafeDescriptorSet {
+        &mut self.set
+    }
+}
+
+/// Error that can happen when creating or adding resources to a `BindlessResources`.
+#[derive(Clone, Debug)]
+pub enum BindlessResourcesError {
+    /// Creating the descriptor set failed.
+    DescriptorSetCreationFailed(DescriptorSetError),
+
+    /// Creating the descriptor set layout failed.
+    DescriptorSetLayoutCreationFailed(DescriptorSetLayoutError),
+
+    /// A required feature is not enabled on the device.
+    FeatureNotEnabled {
+        feature: &'static str,
+        reason: &'static str,
+    },
+
+    /// All the slots of the array are in use.
+    OutOfSlots,
+}
+
+impl error::Error for BindlessResourcesError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Self::DescriptorSetCreationFailed(ref err) => Some(err),
+            Self::DescriptorSetLayoutCreationFailed(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for BindlessResourcesError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Self::DescriptorSetCreationFailed(_) => {
+                write!(fmt, "creating the descriptor set failed")
+            }
+            Self::DescriptorSetLayoutCreationFailed(_) => {
+                write!(fmt, "creating the descriptor set layout failed")
+            }
+            Self::FeatureNotEnabled { feature, reason } => {
+                write!(fmt, "the feature {} must be enabled: {}", feature, reason)
+            }
+            Self::OutOfSlots => write!(fmt, "all the slots of the array are in use"),
+        }
+    }
+}
+
+impl From<DescriptorSetError> for BindlessResourcesError {
+    #[inline]
+    fn from(err: DescriptorSetError) -> Self {
+        Self::DescriptorSetCreationFailed(err)
+    }
+}
+
+impl From<DescriptorSetLayoutError> for BindlessResourcesError {
+    #[inline]
+    fn from(err: DescriptorSetLayoutError) -> Self {
+        Self::DescriptorSetLayoutCreationFailed(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BindlessResources;
+    use super::SlotAllocator;
+    use crate::descriptor_set::DescriptorSet;
+    use crate::shader::ShaderStages;
+
+    #[test]
+    fn variable_count_buffers() {
+        let (device, _) = gfx_dev_and_queue!(
+            runtime_descriptor_array,
+            descriptor_binding_partially_bound,
+            descriptor_binding_update_unused_while_pending,
+            descriptor_binding_variable_descriptor_count,
+            descriptor_binding_sampled_image_update_after_bind,
+            descriptor_binding_storage_buffer_update_after_bind
+        );
+
+        let bindless = BindlessResources::new(device, ShaderStages::all(), 16, 8).unwrap();
+        assert_eq!(bindless.layout().variable_descriptor_count(), 8);
+        assert_eq!(bindless.descriptor_set().variable_descriptor_count(), 8);
+        assert_eq!(
+            bindless
+                .descriptor_set()
+                .resources()
+                .binding(BindlessResources::BUFFER_BINDING)
+                .unwrap()
+                .count(),
+            8
+        );
+    }
+
+    #[test]
+    fn slot_allocator_reuse() {
+        let mut slots = SlotAllocator::new(2);
+        assert_eq!(slots.alloc(), Some(0));
+        assert_eq!(slots.alloc(), Some(1));
+        assert_eq!(slots.alloc(), None);
+
+        slots.free(0);
+        assert_eq!(slots.alloc(), Some(0));
+        assert_eq!(slots.alloc(), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn slot_allocator_double_free() {
+        let mut slots = SlotAllocator::new(2);
+        slots.alloc();
+        slots.free(0);
+        slots.free(0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn slot_allocator_free_unallocated() {
+        let mut slots = SlotAllocator::new(2);
+        slots.alloc();
+        slots.free(1);
+    }
+}
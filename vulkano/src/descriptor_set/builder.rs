@@ -7,11 +7,13 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use crate::acceleration_structure::AccelerationStructure;
 use crate::buffer::{BufferAccess, BufferView};
 use crate::descriptor_set::layout::{DescriptorDesc, DescriptorType};
 use crate::descriptor_set::sys::DescriptorWrite;
 use crate::descriptor_set::{
-    DescriptorSetError, DescriptorSetLayout, MissingBufferUsage, MissingImageUsage,
+    DescriptorResourceType, DescriptorSetError, DescriptorSetLayout, MissingBufferUsage,
+    MissingImageUsage,
 };
 use crate::device::{Device, DeviceOwned};
 use crate::image::ImageViewAbstract;
@@ -97,6 +99,28 @@ impl DescriptorSetBuilder {
         }
     }
 
+    /// Finalizes the building process and returns the generated output, without requiring that
+    /// all descriptors have been added.
+    ///
+    /// Only the descriptors that were added before calling this function are written. This is
+    /// intended for push descriptors, where descriptors that are not written keep the value of
+    /// a previous push.
+    pub fn build_partial(self) -> Result<DescriptorSetBuilderOutput, DescriptorSetError> {
+        if self.poisoned {
+            return Err(DescriptorSetError::BuilderPoisoned);
+        }
+
+        if self.in_array {
+            return Err(DescriptorSetError::InArray);
+        }
+
+        Ok(DescriptorSetBuilderOutput {
+            layout: self.layout,
+            writes: self.writes,
+            variable_descriptor_count: self.variable_descriptor_count,
+        })
+    }
+
     fn poison_on_err(
         &mut self,
         func: impl FnOnce(&mut Self) -> Result<(), DescriptorSetError>,
@@ -192,6 +216,71 @@ impl DescriptorSetBuilder {
         })
     }
 
+    /// Writes a null descriptor as the next descriptor or array element.
+    ///
+    /// Shaders accessing a null descriptor behave as if the resource was entirely out of bounds.
+    /// This requires the `null_descriptor` feature, and is not possible for samplers, input
+    /// attachments, and combined image samplers without an immutable sampler.
+    pub fn add_null(&mut self) -> Result<&mut Self, DescriptorSetError> {
+        self.poison_on_err(|builder| {
+            if !builder.layout.device().enabled_features().null_descriptor {
+                return Err(DescriptorSetError::NullDescriptorNotEnabled);
+            }
+
+            let leave_array = if !builder.in_array {
+                builder.enter_array()?;
+                true
+            } else {
+                false
+            };
+
+            let descriptor = &mut builder.descriptors[builder.cur_binding as usize];
+            let inner_desc = match descriptor.desc.as_ref() {
+                Some(some) => some,
+                None => return Err(DescriptorSetError::WrongDescriptorType),
+            };
+
+            let resource_type = match inner_desc.ty {
+                DescriptorType::UniformBuffer
+                | DescriptorType::StorageBuffer
+                | DescriptorType::UniformBufferDynamic
+                | DescriptorType::StorageBufferDynamic => DescriptorResourceType::Buffer,
+                DescriptorType::UniformTexelBuffer | DescriptorType::StorageTexelBuffer => {
+                    DescriptorResourceType::BufferView
+                }
+                DescriptorType::SampledImage | DescriptorType::StorageImage => {
+                    DescriptorResourceType::ImageView
+                }
+                DescriptorType::CombinedImageSampler
+                    if !inner_desc.immutable_samplers.is_empty() =>
+                {
+                    DescriptorResourceType::ImageView
+                }
+                DescriptorType::AccelerationStructure => {
+                    DescriptorResourceType::AccelerationStructure
+                }
+                _ => return Err(DescriptorSetError::WrongDescriptorType),
+            };
+
+            unsafe {
+                builder.writes.push(DescriptorWrite::none(
+                    builder.cur_binding,
+                    descriptor.array_element,
+                    resource_type,
+                    1,
+                ));
+            }
+
+            descriptor.array_element += 1;
+
+            if leave_array {
+                builder.leave_array()?;
+            }
+
+            Ok(())
+        })
+    }
+
     /// Binds a buffer as the next descriptor or array element.
     pub fn add_buffer(
         &mut self,
@@ -387,7 +476,7 @@ impl DescriptorSetBuilder {
                 DescriptorType::CombinedImageSampler
                     if !inner_desc.immutable_samplers.is_empty() =>
                 {
-                    if !image_view.image().inner().image.usage().sampled {
+                    if !image_view.usage().sampled {
                         return Err(DescriptorSetError::MissingImageUsage(
                             MissingImageUsage::Sampled,
                         ));
@@ -399,15 +488,18 @@ impl DescriptorSetBuilder {
                         return Err(DescriptorSetError::IncompatibleImageViewSampler);
                     }
                 }
+                _ if image_view.sampler_ycbcr_conversion().is_some() => {
+                    return Err(DescriptorSetError::SamplerYcbcrConversionNotImmutable);
+                }
                 DescriptorType::SampledImage => {
-                    if !image_view.image().inner().image.usage().sampled {
+                    if !image_view.usage().sampled {
                         return Err(DescriptorSetError::MissingImageUsage(
                             MissingImageUsage::Sampled,
                         ));
                     }
                 }
                 DescriptorType::StorageImage => {
-                    if !image_view.image().inner().image.usage().storage {
+                    if !image_view.usage().storage {
                         return Err(DescriptorSetError::MissingImageUsage(
                             MissingImageUsage::Storage,
                         ));
@@ -418,7 +510,7 @@ impl DescriptorSetBuilder {
                     }
                 }
                 DescriptorType::InputAttachment => {
-                    if !image_view.image().inner().image.usage().input_attachment {
+                    if !image_view.usage().input_attachment {
                         return Err(DescriptorSetError::MissingImageUsage(
                             MissingImageUsage::InputAttachment,
                         ));
@@ -476,12 +568,18 @@ impl DescriptorSetBuilder {
                 return Err(DescriptorSetError::ResourceWrongDevice);
             }
 
-            if !image_view.image().inner().image.usage().sampled {
+            if !image_view.usage().sampled {
                 return Err(DescriptorSetError::MissingImageUsage(
                     MissingImageUsage::Sampled,
                 ));
             }
 
+            if sampler.sampler_ycbcr_conversion().is_some()
+                || image_view.sampler_ycbcr_conversion().is_some()
+            {
+                return Err(DescriptorSetError::SamplerYcbcrConversionNotImmutable);
+            }
+
             if !image_view.can_be_sampled(&sampler) {
                 return Err(DescriptorSetError::IncompatibleImageViewSampler);
             }
@@ -533,6 +631,10 @@ impl DescriptorSetBuilder {
                 return Err(DescriptorSetError::ResourceWrongDevice);
             }
 
+            if sampler.sampler_ycbcr_conversion().is_some() {
+                return Err(DescriptorSetError::SamplerYcbcrConversionNotImmutable);
+            }
+
             let leave_array = if !builder.in_array {
                 builder.enter_array()?;
                 true
@@ -572,6 +674,53 @@ impl DescriptorSetBuilder {
             Ok(())
         })
     }
+
+    /// Binds an acceleration structure as the next descriptor or array element.
+    pub fn add_acceleration_structure(
+        &mut self,
+        acceleration_structure: Arc<AccelerationStructure>,
+    ) -> Result<&mut Self, DescriptorSetError> {
+        self.poison_on_err(|builder| {
+            if acceleration_structure.device().internal_object()
+                != builder.layout.device().internal_object()
+            {
+                return Err(DescriptorSetError::ResourceWrongDevice);
+            }
+
+            let leave_array = if !builder.in_array {
+                builder.enter_array()?;
+                true
+            } else {
+                false
+            };
+
+            let descriptor = &mut builder.descriptors[builder.cur_binding as usize];
+            let inner_desc = match descriptor.desc.as_ref() {
+                Some(some) => some,
+                None => return Err(DescriptorSetError::WrongDescriptorType),
+            };
+
+            if inner_desc.ty != DescriptorType::AccelerationStructure {
+                return Err(DescriptorSetError::WrongDescriptorType);
+            }
+
+            unsafe {
+                builder.writes.push(DescriptorWrite::acceleration_structure(
+                    builder.cur_binding,
+                    descriptor.array_element,
+                    [acceleration_structure],
+                ));
+            }
+
+            descriptor.array_element += 1;
+
+            if leave_array {
+                builder.leave_array()?;
+            }
+
+            Ok(())
+        })
+    }
 }
 
 unsafe impl DeviceOwned for DescriptorSetBuilder {
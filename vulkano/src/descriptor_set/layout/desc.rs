@@ -50,7 +50,7 @@ use std::error;
 use std::fmt;
 use std::sync::Arc;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct DescriptorSetDesc {
     descriptors: SmallVec<[Option<DescriptorDesc>; 4]>,
     push_descriptor: bool,
@@ -101,6 +101,57 @@ impl DescriptorSetDesc {
         descriptor_sets
     }
 
+    /// Builds a list of `DescriptorSetDesc` from an iterator of `DescriptorRequirement` originating
+    /// from a shader, like `from_requirements`, and sets immutable samplers on some of the
+    /// bindings.
+    ///
+    /// `immutable_samplers` yields the set and binding numbers of a binding together with the
+    /// samplers to use for it. These are checked against the requirements of the shader: the
+    /// binding must be used by the shader as a sampler or combined image sampler descriptor, and
+    /// there must be exactly one sampler for each array element. Immutable samplers can't be used
+    /// for runtime-sized arrays.
+    pub fn from_requirements_with_immutable_samplers<'a>(
+        descriptor_requirements: impl IntoIterator<Item = ((u32, u32), &'a DescriptorRequirements)>,
+        immutable_samplers: impl IntoIterator<Item = ((u32, u32), Vec<Arc<Sampler>>)>,
+    ) -> Result<Vec<Self>, ImmutableSamplersError> {
+        let mut descriptor_sets = Self::from_requirements(descriptor_requirements);
+
+        for ((set_num, binding_num), samplers) in immutable_samplers {
+            let desc = descriptor_sets
+                .get_mut(set_num as usize)
+                .and_then(|set| set.descriptors.get_mut(binding_num as usize))
+                .and_then(|b| b.as_mut())
+                .ok_or(ImmutableSamplersError::BindingNotUsed {
+                    set_num,
+                    binding_num,
+                })?;
+
+            if !matches!(
+                desc.ty,
+                DescriptorType::Sampler | DescriptorType::CombinedImageSampler
+            ) {
+                return Err(ImmutableSamplersError::WrongDescriptorType {
+                    set_num,
+                    binding_num,
+                    ty: desc.ty,
+                });
+            }
+
+            if samplers.len() as u32 != desc.descriptor_count {
+                return Err(ImmutableSamplersError::SamplerCountMismatch {
+                    set_num,
+                    binding_num,
+                    sampler_count: samplers.len() as u32,
+                    descriptor_count: desc.descriptor_count,
+                });
+            }
+
+            desc.immutable_samplers = samplers;
+        }
+
+        Ok(descriptor_sets)
+    }
+
     /// Builds a new empty `DescriptorSetDesc`.
     #[inline]
     pub fn empty() -> DescriptorSetDesc {
@@ -193,6 +244,36 @@ impl DescriptorSetDesc {
         immutable_samplers.extend(samplers.into_iter());
     }
 
+    /// Turns a descriptor into a mutable descriptor that can hold any of the descriptor types in
+    /// `types`. The descriptor types that the descriptor currently accepts are added to `types`.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the binding number refers to an empty descriptor.
+    pub fn set_mutable_types(
+        &mut self,
+        binding_num: u32,
+        types: impl IntoIterator<Item = DescriptorType>,
+    ) {
+        let desc = self
+            .descriptors
+            .get_mut(binding_num as usize)
+            .and_then(|b| b.as_mut())
+            .expect("descriptor is empty");
+
+        if desc.ty != DescriptorType::Mutable && !desc.mutable_types.contains(&desc.ty) {
+            desc.mutable_types.push(desc.ty);
+        }
+
+        for ty in types {
+            if !desc.mutable_types.contains(&ty) {
+                desc.mutable_types.push(ty);
+            }
+        }
+
+        desc.ty = DescriptorType::Mutable;
+    }
+
     /// Sets the descriptor set layout to use push descriptors instead of descriptor sets.
     ///
     /// If set to enabled, the
@@ -234,6 +315,67 @@ impl DescriptorSetDesc {
         }
     }
 
+    /// Sets whether the descriptors of a binding can be updated after the descriptor set has been
+    /// bound.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the binding number refers to an empty descriptor.
+    pub fn set_update_after_bind(&mut self, binding_num: u32, enabled: bool) {
+        match self
+            .descriptors
+            .get_mut(binding_num as usize)
+            .and_then(|b| b.as_mut())
+        {
+            Some(desc) => desc.update_after_bind = enabled,
+            None => panic!("descriptor is empty"),
+        }
+    }
+
+    /// Sets whether the descriptors of a binding can be updated while a command buffer that uses
+    /// the descriptor set is pending execution, if they are not used by that command buffer.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the binding number refers to an empty descriptor.
+    pub fn set_update_unused_while_pending(&mut self, binding_num: u32, enabled: bool) {
+        match self
+            .descriptors
+            .get_mut(binding_num as usize)
+            .and_then(|b| b.as_mut())
+        {
+            Some(desc) => desc.update_unused_while_pending = enabled,
+            None => panic!("descriptor is empty"),
+        }
+    }
+
+    /// Sets whether not all descriptors of a binding need to contain a valid resource.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the binding number refers to an empty descriptor.
+    pub fn set_partially_bound(&mut self, binding_num: u32, enabled: bool) {
+        match self
+            .descriptors
+            .get_mut(binding_num as usize)
+            .and_then(|b| b.as_mut())
+        {
+            Some(desc) => desc.partially_bound = enabled,
+            None => panic!("descriptor is empty"),
+        }
+    }
+
+    /// Returns whether any of the descriptors in the set can be updated after the descriptor set
+    /// has been bound. If so, the descriptor set must be allocated from a pool that was created
+    /// for update-after-bind.
+    #[inline]
+    pub fn is_update_after_bind(&self) -> bool {
+        self.descriptors
+            .iter()
+            .flatten()
+            .any(|desc| desc.update_after_bind)
+    }
+
     /// Returns whether `self` is compatible with `other`.
     ///
     /// "Compatible" in this sense is defined by the Vulkan specification under the section
@@ -289,6 +431,32 @@ pub struct DescriptorDesc {
     /// variable count in a descriptor set, and it must be the last binding.
     pub variable_count: bool,
 
+    /// True if the descriptors of this binding can be updated after a descriptor set has been
+    /// bound in a command buffer, and before that command buffer is submitted. Descriptor sets
+    /// with such a binding must be allocated from a pool that was created for update-after-bind.
+    ///
+    /// This requires the `descriptor_binding_*_update_after_bind` feature corresponding to `ty`
+    /// to be enabled on the device, and is not allowed for dynamic buffers, input attachments
+    /// and push descriptors.
+    pub update_after_bind: bool,
+
+    /// True if the descriptors of this binding can be updated while a command buffer that uses
+    /// the descriptor set is pending execution, as long as the descriptors being updated are not
+    /// used by that command buffer.
+    ///
+    /// This requires the
+    /// [`descriptor_binding_update_unused_while_pending`](crate::device::Features::descriptor_binding_update_unused_while_pending)
+    /// feature to be enabled on the device, and is not allowed for push descriptors.
+    pub update_unused_while_pending: bool,
+
+    /// True if not all descriptors of this binding need to contain a valid resource, as long as
+    /// the descriptors that are not valid are not accessed by shaders.
+    ///
+    /// This requires the
+    /// [`descriptor_binding_partially_bound`](crate::device::Features::descriptor_binding_partially_bound)
+    /// feature to be enabled on the device.
+    pub partially_bound: bool,
+
     /// Which shader stages are going to access this descriptor.
     pub stages: ShaderStages,
 
@@ -298,6 +466,13 @@ pub struct DescriptorDesc {
     /// The list must be either empty, or contain exactly `descriptor_count` samplers. It must be
     /// empty if `ty` is something other than `Sampler` or `CombinedImageSampler`.
     pub immutable_samplers: Vec<Arc<Sampler>>,
+
+    /// If `ty` is `Mutable`, the descriptor types that the descriptors of this binding can hold.
+    ///
+    /// The list must contain at least one element if `ty` is `Mutable`, and must be empty
+    /// otherwise. It must not contain `Mutable`, `UniformBufferDynamic` or `StorageBufferDynamic`,
+    /// and must not contain duplicates.
+    pub mutable_types: Vec<DescriptorType>,
 }
 
 impl DescriptorDesc {
@@ -318,7 +493,17 @@ impl DescriptorDesc {
             stages,
         } = descriptor_requirements;
 
-        if !descriptor_types.contains(&self.ty) {
+        if self.ty == DescriptorType::Mutable {
+            if !descriptor_types
+                .iter()
+                .any(|ty| self.mutable_types.contains(ty))
+            {
+                return Err(DescriptorRequirementsNotMet::DescriptorType {
+                    required: descriptor_types.clone(),
+                    obtained: self.ty,
+                });
+            }
+        } else if !descriptor_types.contains(&self.ty) {
             return Err(DescriptorRequirementsNotMet::DescriptorType {
                 required: descriptor_types.clone(),
                 obtained: self.ty,
@@ -357,14 +542,20 @@ impl From<&DescriptorRequirements> for DescriptorDesc {
             DescriptorType::UniformBufferDynamic => DescriptorType::UniformBufferDynamic,
             DescriptorType::StorageBufferDynamic => DescriptorType::StorageBufferDynamic,
             DescriptorType::InputAttachment => DescriptorType::InputAttachment,
+            DescriptorType::AccelerationStructure => DescriptorType::AccelerationStructure,
+            DescriptorType::Mutable => DescriptorType::Mutable,
         };
 
         Self {
             ty,
             descriptor_count: reqs.descriptor_count,
             variable_count: false,
+            update_after_bind: false,
+            update_unused_while_pending: false,
+            partially_bound: false,
             stages: reqs.stages,
             immutable_samplers: Vec::new(),
+            mutable_types: Vec::new(),
         }
     }
 }
@@ -384,6 +575,16 @@ pub enum DescriptorType {
     UniformBufferDynamic = ash::vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC.as_raw(),
     StorageBufferDynamic = ash::vk::DescriptorType::STORAGE_BUFFER_DYNAMIC.as_raw(),
     InputAttachment = ash::vk::DescriptorType::INPUT_ATTACHMENT.as_raw(),
+    /// A descriptor that holds an
+    /// [`AccelerationStructure`](crate::acceleration_structure::AccelerationStructure).
+    AccelerationStructure = ash::vk::DescriptorType::ACCELERATION_STRUCTURE_KHR.as_raw(),
+    /// A descriptor that can hold one of several descriptor types, listed in
+    /// [`DescriptorDesc::mutable_types`].
+    ///
+    /// This requires the
+    /// [`mutable_descriptor_type`](crate::device::Features::mutable_descriptor_type) feature to be
+    /// enabled on the device.
+    Mutable = ash::vk::DescriptorType::MUTABLE_VALVE.as_raw(),
 }
 
 impl From<DescriptorType> for ash::vk::DescriptorType {
@@ -393,6 +594,67 @@ impl From<DescriptorType> for ash::vk::DescriptorType {
     }
 }
 
+/// Error when setting immutable samplers on descriptor set descriptions built from the
+/// requirements of a shader.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImmutableSamplersError {
+    /// The binding is not used by the shader.
+    BindingNotUsed { set_num: u32, binding_num: u32 },
+
+    /// The number of samplers does not match the number of descriptors that the shader
+    /// declares.
+    SamplerCountMismatch {
+        set_num: u32,
+        binding_num: u32,
+        sampler_count: u32,
+        descriptor_count: u32,
+    },
+
+    /// The shader does not use the binding as a sampler or combined image sampler descriptor.
+    WrongDescriptorType {
+        set_num: u32,
+        binding_num: u32,
+        ty: DescriptorType,
+    },
+}
+
+impl error::Error for ImmutableSamplersError {}
+
+impl fmt::Display for ImmutableSamplersError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Self::BindingNotUsed {
+                set_num,
+                binding_num,
+            } => write!(
+                fmt,
+                "immutable samplers were provided for set {} binding {}, but the binding is not used by the shader",
+                set_num, binding_num,
+            ),
+            Self::SamplerCountMismatch {
+                set_num,
+                binding_num,
+                sampler_count,
+                descriptor_count,
+            } => write!(
+                fmt,
+                "{} immutable samplers were provided for set {} binding {}, but the shader declares {} descriptors",
+                sampler_count, set_num, binding_num, descriptor_count,
+            ),
+            Self::WrongDescriptorType {
+                set_num,
+                binding_num,
+                ty,
+            } => write!(
+                fmt,
+                "immutable samplers were provided for set {} binding {}, but the shader uses it as a {:?} descriptor",
+                set_num, binding_num, ty,
+            ),
+        }
+    }
+}
+
 /// Error when checking whether the requirements for a descriptor have been met.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DescriptorRequirementsNotMet {
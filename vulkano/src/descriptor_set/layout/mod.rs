@@ -17,6 +17,7 @@ pub use self::desc::DescriptorDesc;
 pub use self::desc::DescriptorRequirementsNotMet;
 pub use self::desc::DescriptorSetDesc;
 pub use self::desc::DescriptorType;
+pub use self::desc::ImmutableSamplersError;
 pub use self::sys::DescriptorSetLayout;
 pub use self::sys::DescriptorSetLayoutError;
 
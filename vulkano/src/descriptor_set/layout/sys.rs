@@ -17,9 +17,12 @@ use crate::device::DeviceOwned;
 use crate::OomError;
 use crate::Version;
 use crate::VulkanObject;
+use smallvec::SmallVec;
 use std::mem::MaybeUninit;
 use std::ptr;
+use std::slice;
 use std::sync::Arc;
+use std::sync::Weak;
 
 /// Describes to the Vulkan implementation the layout of all descriptors within a descriptor set.
 #[derive(Debug)]
@@ -40,6 +43,9 @@ impl DescriptorSetLayout {
     /// The descriptors must be passed in the order of the bindings. In order words, descriptor
     /// at bind point 0 first, then descriptor at bind point 1, and so on. If a binding must remain
     /// empty, you can make the iterator yield `None` for an element.
+    ///
+    /// If [object deduplication](Device::set_object_deduplication) is enabled on the device, an
+    /// existing layout with the same descriptors may be returned.
     pub fn new<D>(
         device: Arc<Device>,
         set_desc: D,
@@ -48,11 +54,38 @@ impl DescriptorSetLayout {
         D: Into<DescriptorSetDesc>,
     {
         let set_desc = set_desc.into();
+
+        if !device.object_deduplication() {
+            return Self::create(device, set_desc);
+        }
+
+        let mut cache = device.descriptor_set_layout_cache().lock().unwrap();
+        cache.retain(|layout| layout.strong_count() > 0);
+
+        if let Some(layout) = cache
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|layout| layout.desc == set_desc)
+        {
+            return Ok(layout);
+        }
+
+        let layout = Self::create(device.clone(), set_desc)?;
+        cache.push(Arc::downgrade(&layout));
+        Ok(layout)
+    }
+
+    fn create(
+        device: Arc<Device>,
+        set_desc: DescriptorSetDesc,
+    ) -> Result<Arc<DescriptorSetLayout>, DescriptorSetLayoutError> {
         let mut descriptors_count = DescriptorsCount::zero();
         let bindings = set_desc.bindings();
         let mut bindings_vk = Vec::with_capacity(bindings.len());
         let mut binding_flags_vk = Vec::with_capacity(bindings.len());
         let mut immutable_samplers_vk: Vec<Box<[ash::vk::Sampler]>> = Vec::new(); // only to keep the arrays of handles alive
+        let mut mutable_type_lists_vk: Vec<Box<[ash::vk::DescriptorType]>> =
+            Vec::with_capacity(bindings.len());
 
         let mut flags = ash::vk::DescriptorSetLayoutCreateFlags::empty();
 
@@ -94,24 +127,68 @@ impl DescriptorSetLayout {
                     return Err(DescriptorSetLayoutError::PushDescriptorVariableCount);
                 }
 
-                // TODO: VUID-VkDescriptorSetLayoutBindingFlagsCreateInfo-flags-03003
-                // If VkDescriptorSetLayoutCreateInfo::flags includes
-                // VK_DESCRIPTOR_SET_LAYOUT_CREATE_PUSH_DESCRIPTOR_BIT_KHR,
-                // then all elements of pBindingFlags must not include
-                // VK_DESCRIPTOR_BINDING_UPDATE_AFTER_BIND_BIT,
-                // VK_DESCRIPTOR_BINDING_UPDATE_UNUSED_WHILE_PENDING_BIT, or
-                // VK_DESCRIPTOR_BINDING_VARIABLE_DESCRIPTOR_COUNT_BIT
+                if binding_desc.update_after_bind || binding_desc.update_unused_while_pending {
+                    return Err(DescriptorSetLayoutError::PushDescriptorUpdateAfterBind);
+                }
 
                 // TODO: VUID-VkDescriptorSetLayoutCreateInfo-flags-02208
                 // If flags contains VK_DESCRIPTOR_SET_LAYOUT_CREATE_PUSH_DESCRIPTOR_BIT_KHR, then all
                 // elements of pBindings must not have a descriptorType of
                 // VK_DESCRIPTOR_TYPE_INLINE_UNIFORM_BLOCK_EXT
 
-                // TODO: VUID-VkDescriptorSetLayoutCreateInfo-flags-04591
-                // If flags contains VK_DESCRIPTOR_SET_LAYOUT_CREATE_PUSH_DESCRIPTOR_BIT_KHR,
-                // pBindings must not have a descriptorType of VK_DESCRIPTOR_TYPE_MUTABLE_VALVE
+                if ty == DescriptorType::Mutable {
+                    return Err(DescriptorSetLayoutError::PushDescriptorMutable);
+                }
             }
 
+            if ty == DescriptorType::AccelerationStructure
+                || binding_desc
+                    .mutable_types
+                    .contains(&DescriptorType::AccelerationStructure)
+            {
+                if !device.enabled_extensions().khr_acceleration_structure {
+                    return Err(DescriptorSetLayoutError::ExtensionNotEnabled {
+                        extension: "khr_acceleration_structure",
+                        reason: "binding has an acceleration structure descriptor type",
+                    });
+                }
+            }
+
+            if ty == DescriptorType::Mutable {
+                if !device.enabled_features().mutable_descriptor_type {
+                    return Err(DescriptorSetLayoutError::FeatureNotEnabled {
+                        feature: "mutable_descriptor_type",
+                        reason: "binding has a mutable descriptor type",
+                    });
+                }
+
+                let types = &binding_desc.mutable_types;
+
+                if types.is_empty()
+                    || types.iter().enumerate().any(|(i, ty)| {
+                        matches!(
+                            ty,
+                            DescriptorType::Mutable
+                                | DescriptorType::UniformBufferDynamic
+                                | DescriptorType::StorageBufferDynamic
+                        ) || types[..i].contains(ty)
+                    })
+                {
+                    return Err(DescriptorSetLayoutError::MutableTypesInvalid);
+                }
+            } else if !binding_desc.mutable_types.is_empty() {
+                return Err(DescriptorSetLayoutError::MutableTypesWrongDescriptorType);
+            }
+
+            mutable_type_lists_vk.push(
+                binding_desc
+                    .mutable_types
+                    .iter()
+                    .map(|&ty| ty.into())
+                    .collect::<Vec<ash::vk::DescriptorType>>()
+                    .into_boxed_slice(),
+            );
+
             descriptors_count.add_num(ty, binding_desc.descriptor_count);
             let mut binding_flags = ash::vk::DescriptorBindingFlags::empty();
 
@@ -130,6 +207,32 @@ impl DescriptorSetLayout {
                     });
                 }
 
+                let ycbcr_planes = binding_desc
+                    .immutable_samplers
+                    .iter()
+                    .filter_map(|sampler| sampler.sampler_ycbcr_conversion())
+                    .map(|conversion| {
+                        conversion
+                            .format()
+                            .map_or(1, |format| format.planes().len().max(1))
+                            as u32
+                    })
+                    .collect::<Vec<_>>();
+
+                if !ycbcr_planes.is_empty() {
+                    if ty != DescriptorType::CombinedImageSampler {
+                        return Err(
+                            DescriptorSetLayoutError::SamplerYcbcrConversionWrongDescriptorType,
+                        );
+                    }
+
+                    // A combined image sampler with a multi-planar conversion can consume one
+                    // descriptor per plane from the pool. The exact number is
+                    // implementation-dependent, so count the maximum.
+                    let extra = ycbcr_planes.iter().map(|&planes| planes - 1).sum();
+                    descriptors_count.add_num(ty, extra);
+                }
+
                 // TODO: VUID-VkDescriptorSetLayoutBinding-pImmutableSamplers-04009
                 // The sampler objects indicated by pImmutableSamplers must not have a borderColor
                 // with one of the values VK_BORDER_COLOR_FLOAT_CUSTOM_EXT or
@@ -179,6 +282,109 @@ impl DescriptorSetLayout {
                 binding_flags |= ash::vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT;
             }
 
+            if binding_desc.update_after_bind {
+                // A mutable descriptor can be updated with any of its mutable types, so all of
+                // them must support update-after-bind.
+                let update_types = if ty == DescriptorType::Mutable {
+                    &binding_desc.mutable_types[..]
+                } else {
+                    slice::from_ref(&ty)
+                };
+
+                for &ty in update_types {
+                    let (feature, enabled) = match ty {
+                        DescriptorType::UniformBuffer => (
+                            "descriptor_binding_uniform_buffer_update_after_bind",
+                            device
+                                .enabled_features()
+                                .descriptor_binding_uniform_buffer_update_after_bind,
+                        ),
+                        DescriptorType::StorageBuffer => (
+                            "descriptor_binding_storage_buffer_update_after_bind",
+                            device
+                                .enabled_features()
+                                .descriptor_binding_storage_buffer_update_after_bind,
+                        ),
+                        DescriptorType::UniformTexelBuffer => (
+                            "descriptor_binding_uniform_texel_buffer_update_after_bind",
+                            device
+                                .enabled_features()
+                                .descriptor_binding_uniform_texel_buffer_update_after_bind,
+                        ),
+                        DescriptorType::StorageTexelBuffer => (
+                            "descriptor_binding_storage_texel_buffer_update_after_bind",
+                            device
+                                .enabled_features()
+                                .descriptor_binding_storage_texel_buffer_update_after_bind,
+                        ),
+                        DescriptorType::Sampler
+                        | DescriptorType::CombinedImageSampler
+                        | DescriptorType::SampledImage => (
+                            "descriptor_binding_sampled_image_update_after_bind",
+                            device
+                                .enabled_features()
+                                .descriptor_binding_sampled_image_update_after_bind,
+                        ),
+                        DescriptorType::StorageImage => (
+                            "descriptor_binding_storage_image_update_after_bind",
+                            device
+                                .enabled_features()
+                                .descriptor_binding_storage_image_update_after_bind,
+                        ),
+                        DescriptorType::AccelerationStructure => (
+                            "descriptor_binding_acceleration_structure_update_after_bind",
+                            device
+                                .enabled_features()
+                                .descriptor_binding_acceleration_structure_update_after_bind,
+                        ),
+                        DescriptorType::UniformBufferDynamic
+                        | DescriptorType::StorageBufferDynamic
+                        | DescriptorType::InputAttachment => {
+                            return Err(
+                                DescriptorSetLayoutError::UpdateAfterBindWrongDescriptorType,
+                            );
+                        }
+                        // Checked above that the mutable types don't include `Mutable`.
+                        DescriptorType::Mutable => unreachable!(),
+                    };
+
+                    if !enabled {
+                        return Err(DescriptorSetLayoutError::FeatureNotEnabled {
+                            feature,
+                            reason: "binding can be updated after bind",
+                        });
+                    }
+                }
+
+                binding_flags |= ash::vk::DescriptorBindingFlags::UPDATE_AFTER_BIND;
+                flags |= ash::vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL;
+            }
+
+            if binding_desc.update_unused_while_pending {
+                if !device
+                    .enabled_features()
+                    .descriptor_binding_update_unused_while_pending
+                {
+                    return Err(DescriptorSetLayoutError::FeatureNotEnabled {
+                        feature: "descriptor_binding_update_unused_while_pending",
+                        reason: "binding can be updated while unused and pending",
+                    });
+                }
+
+                binding_flags |= ash::vk::DescriptorBindingFlags::UPDATE_UNUSED_WHILE_PENDING;
+            }
+
+            if binding_desc.partially_bound {
+                if !device.enabled_features().descriptor_binding_partially_bound {
+                    return Err(DescriptorSetLayoutError::FeatureNotEnabled {
+                        feature: "descriptor_binding_partially_bound",
+                        reason: "binding is partially bound",
+                    });
+                }
+
+                binding_flags |= ash::vk::DescriptorBindingFlags::PARTIALLY_BOUND;
+            }
+
             bindings_vk.push(ash::vk::DescriptorSetLayoutBinding {
                 binding: binding as u32,
                 descriptor_type: ty.into(),
@@ -208,7 +414,7 @@ impl DescriptorSetLayout {
         }
 
         let handle = unsafe {
-            let binding_flags_infos = if device.api_version() >= Version::V1_2
+            let mut binding_flags_infos = if device.api_version() >= Version::V1_2
                 || device.enabled_extensions().ext_descriptor_indexing
             {
                 Some(ash::vk::DescriptorSetLayoutBindingFlagsCreateInfo {
@@ -221,18 +427,49 @@ impl DescriptorSetLayout {
                 None
             };
 
-            let infos = ash::vk::DescriptorSetLayoutCreateInfo {
+            // Only needed if there is a mutable binding. There is one list for each element of
+            // `bindings_vk`.
+            let mutable_type_lists_vk: SmallVec<[_; 8]> = if bindings
+                .iter()
+                .flatten()
+                .any(|b| b.ty == DescriptorType::Mutable)
+            {
+                mutable_type_lists_vk
+                    .iter()
+                    .map(|types| ash::vk::MutableDescriptorTypeListVALVE {
+                        descriptor_type_count: types.len() as u32,
+                        p_descriptor_types: types.as_ptr(),
+                    })
+                    .collect()
+            } else {
+                SmallVec::new()
+            };
+            let mutable_type_infos = if !mutable_type_lists_vk.is_empty() {
+                Some(ash::vk::MutableDescriptorTypeCreateInfoVALVE {
+                    mutable_descriptor_type_list_count: mutable_type_lists_vk.len() as u32,
+                    p_mutable_descriptor_type_lists: mutable_type_lists_vk.as_ptr(),
+                    ..Default::default()
+                })
+            } else {
+                None
+            };
+
+            let mut infos = ash::vk::DescriptorSetLayoutCreateInfo {
                 flags,
                 binding_count: bindings_vk.len() as u32,
                 p_bindings: bindings_vk.as_ptr(),
-                p_next: if let Some(next) = binding_flags_infos.as_ref() {
-                    next as *const _ as *const _
-                } else {
-                    ptr::null()
-                },
                 ..Default::default()
             };
 
+            if let Some(next) = mutable_type_infos.as_ref() {
+                infos.p_next = next as *const _ as *const _;
+            }
+
+            if let Some(next) = binding_flags_infos.as_mut() {
+                next.p_next = infos.p_next as *mut _;
+                infos.p_next = next as *const _ as *const _;
+            }
+
             let mut output = MaybeUninit::uninit();
             let fns = device.fns();
 
@@ -370,15 +607,36 @@ pub enum DescriptorSetLayoutError {
         obtained: u32,
     },
 
+    /// The mutable types of a mutable binding were empty, contained duplicates, or contained
+    /// `Mutable` or a dynamic buffer type.
+    MutableTypesInvalid,
+
+    /// Mutable types were included on a descriptor type other than `Mutable`.
+    MutableTypesWrongDescriptorType,
+
     /// Out of Memory.
     OomError(OomError),
 
     /// The layout was being created for push descriptors, but included a dynamic buffer binding.
     PushDescriptorDynamicBuffer,
 
+    /// The layout was being created for push descriptors, but included a mutable binding.
+    PushDescriptorMutable,
+
+    /// The layout was being created for push descriptors, but included an update-after-bind or
+    /// update-unused-while-pending binding.
+    PushDescriptorUpdateAfterBind,
+
     /// The layout was being created for push descriptors, but included a variable count binding.
     PushDescriptorVariableCount,
 
+    /// Immutable samplers with a sampler YCbCr conversion were included on a descriptor type
+    /// other than `CombinedImageSampler`.
+    SamplerYcbcrConversionWrongDescriptorType,
+
+    /// Update-after-bind was enabled on a dynamic buffer or input attachment binding.
+    UpdateAfterBindWrongDescriptorType,
+
     /// Variable count descriptor must be last binding.
     VariableCountDescMustBeLast,
 
@@ -426,15 +684,33 @@ impl std::fmt::Display for DescriptorSetLayoutError {
                     "the maximum number of push descriptors has been exceeded"
                 )
             }
+            Self::MutableTypesInvalid => {
+                write!(fmt, "the mutable types of a mutable binding were empty, contained duplicates, or contained Mutable or a dynamic buffer type")
+            }
+            Self::MutableTypesWrongDescriptorType => {
+                write!(
+                    fmt,
+                    "mutable types were included on a descriptor type other than Mutable"
+                )
+            }
             Self::PushDescriptorDynamicBuffer => {
                 write!(fmt, "the layout was being created for push descriptors, but included a dynamic buffer binding")
             }
+            Self::PushDescriptorMutable => {
+                write!(fmt, "the layout was being created for push descriptors, but included a mutable binding")
+            }
+            Self::PushDescriptorUpdateAfterBind => {
+                write!(fmt, "the layout was being created for push descriptors, but included an update-after-bind or update-unused-while-pending binding")
+            }
             Self::PushDescriptorVariableCount => {
                 write!(fmt, "the layout was being created for push descriptors, but included a variable count binding")
             }
             Self::OomError(_) => {
                 write!(fmt, "out of memory")
             }
+            Self::SamplerYcbcrConversionWrongDescriptorType => {
+                write!(fmt, "immutable samplers with a sampler YCbCr conversion were included on a descriptor type other than CombinedImageSampler")
+            }
             Self::VariableCountDescMustBeLast => {
                 write!(fmt, "variable count descriptor must be last binding")
             }
@@ -444,6 +720,12 @@ impl std::fmt::Display for DescriptorSetLayoutError {
                     "variable count descriptor must not be a dynamic buffer"
                 )
             }
+            Self::UpdateAfterBindWrongDescriptorType => {
+                write!(
+                    fmt,
+                    "update-after-bind was enabled on a dynamic buffer or input attachment binding"
+                )
+            }
         }
     }
 }
@@ -453,6 +735,7 @@ mod tests {
     use crate::descriptor_set::layout::DescriptorDesc;
     use crate::descriptor_set::layout::DescriptorSetDesc;
     use crate::descriptor_set::layout::DescriptorSetLayout;
+    use crate::descriptor_set::layout::DescriptorSetLayoutError;
     use crate::descriptor_set::layout::DescriptorType;
     use crate::descriptor_set::pool::DescriptorsCount;
     use crate::shader::ShaderStages;
@@ -472,8 +755,12 @@ mod tests {
             ty: DescriptorType::UniformBuffer,
             descriptor_count: 1,
             variable_count: false,
+            update_after_bind: false,
+            update_unused_while_pending: false,
+            partially_bound: false,
             stages: ShaderStages::all_graphics(),
             immutable_samplers: Vec::new(),
+            mutable_types: Vec::new(),
         };
 
         let sl = DescriptorSetLayout::new(
@@ -490,4 +777,50 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn update_after_bind_dynamic_buffer() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let layout = DescriptorDesc {
+            ty: DescriptorType::UniformBufferDynamic,
+            descriptor_count: 1,
+            variable_count: false,
+            update_after_bind: true,
+            update_unused_while_pending: false,
+            partially_bound: false,
+            stages: ShaderStages::all_graphics(),
+            immutable_samplers: Vec::new(),
+            mutable_types: Vec::new(),
+        };
+
+        assert_eq!(
+            DescriptorSetLayout::new(device, DescriptorSetDesc::new(iter::once(Some(layout))))
+                .unwrap_err(),
+            DescriptorSetLayoutError::UpdateAfterBindWrongDescriptorType,
+        );
+    }
+
+    #[test]
+    fn mutable_types_wrong_descriptor_type() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let layout = DescriptorDesc {
+            ty: DescriptorType::StorageBuffer,
+            descriptor_count: 1,
+            variable_count: false,
+            update_after_bind: false,
+            update_unused_while_pending: false,
+            partially_bound: false,
+            stages: ShaderStages::all_graphics(),
+            immutable_samplers: Vec::new(),
+            mutable_types: vec![DescriptorType::StorageImage],
+        };
+
+        assert_eq!(
+            DescriptorSetLayout::new(device, DescriptorSetDesc::new(iter::once(Some(layout))))
+                .unwrap_err(),
+            DescriptorSetLayoutError::MutableTypesWrongDescriptorType,
+        );
+    }
 }
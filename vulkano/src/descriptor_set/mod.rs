@@ -70,18 +70,39 @@
 //!   a safe way. A Vulkan descriptor set is inherently unsafe, so we need safe wrappers around
 //!   them.
 //! - The `SimpleDescriptorSet` type is a default implementation of the `DescriptorSet` trait.
+//! - The `UpdateAfterBindDescriptorSet` type is an implementation of the `DescriptorSet` trait
+//!   whose descriptors can be rewritten after it has been created, including after it has been
+//!   bound in a command buffer if the layout allows it.
+//! - The `BindlessResources` type manages an `UpdateAfterBindDescriptorSet` of large arrays of
+//!   textures and buffers, and hands out indices into these arrays.
+//! - The `DescriptorSetRegistry` type keeps track of which descriptor sets reference which images
+//!   and buffers, so that the sets that use a resource can be found and rebuilt.
+//! - The `DescriptorUpdateTemplate` type records which descriptors of a set are updated, so that
+//!   they can later be updated from a block of memory without building descriptor writes.
+//! - The `DescriptorWritesBuilder` type checks descriptor writes against the descriptor
+//!   requirements that were reflected from a shader, before they are written to a descriptor set.
 //! - The `DescriptorSetsCollection` trait is implemented on collections of types that implement
 //!   `DescriptorSet`. It is what you pass to the draw functions.
 
+pub use self::bindless::BindlessResources;
 pub use self::builder::DescriptorSetBuilder;
 pub use self::collection::DescriptorSetsCollection;
 use self::layout::DescriptorSetLayout;
 pub use self::persistent::PersistentDescriptorSet;
-pub use self::resources::{DescriptorBindingResources, DescriptorSetResources};
+pub use self::registry::DescriptorSetRegistry;
+pub use self::resources::{
+    DescriptorBindingResources, DescriptorResource, DescriptorResourceType, DescriptorSetResources,
+    DescriptorSetResourcesDump, DescriptorWriteError,
+};
 pub use self::single_layout_pool::SingleLayoutDescSetPool;
+pub use self::single_layout_pool::SingleLayoutVariableDescSetPool;
 use self::sys::UnsafeDescriptorSet;
+pub use self::update_after_bind::UpdateAfterBindDescriptorSet;
+pub use self::update_template::DescriptorUpdateTemplate;
+pub use self::writes::DescriptorWritesBuilder;
 use crate::buffer::BufferAccess;
 use crate::descriptor_set::layout::DescriptorType;
+use crate::descriptor_set::pool::DescriptorPoolAllocError;
 use crate::device::DeviceOwned;
 use crate::OomError;
 use crate::VulkanObject;
@@ -92,14 +113,19 @@ use std::hash::Hash;
 use std::hash::Hasher;
 use std::sync::Arc;
 
+pub mod bindless;
 pub mod builder;
 mod collection;
 pub mod layout;
 pub mod persistent;
 pub mod pool;
+pub mod registry;
 mod resources;
 pub mod single_layout_pool;
 pub mod sys;
+pub mod update_after_bind;
+pub mod update_template;
+pub mod writes;
 
 /// Trait for objects that contain a collection of resources that will be accessible by shaders.
 ///
@@ -293,15 +319,25 @@ pub enum DescriptorSetError {
     /// The builder is not in an array, but the operation requires it to be.
     NotInArray,
 
+    /// Provided a null descriptor, but the `null_descriptor` feature is not enabled.
+    NullDescriptorNotEnabled,
+
     /// Out of memory
     OomError(OomError),
 
+    /// The descriptor pool has no room for the descriptor set.
+    PoolAllocError(DescriptorPoolAllocError),
+
     /// Resource belongs to another device.
     ResourceWrongDevice,
 
     /// Provided a dynamically assigned sampler, but the descriptor has an immutable sampler.
     SamplerIsImmutable,
 
+    /// Provided a sampler or image view with a sampler YCbCr conversion, but the descriptor is
+    /// not a combined image sampler with an immutable sampler.
+    SamplerYcbcrConversionNotImmutable,
+
     /// Builder doesn't expect anymore descriptors
     TooManyDescriptors,
 
@@ -318,6 +354,18 @@ impl From<OomError> for DescriptorSetError {
     }
 }
 
+impl From<DescriptorPoolAllocError> for DescriptorSetError {
+    fn from(error: DescriptorPoolAllocError) -> Self {
+        match error {
+            DescriptorPoolAllocError::OutOfHostMemory => Self::OomError(OomError::OutOfHostMemory),
+            DescriptorPoolAllocError::OutOfDeviceMemory => {
+                Self::OomError(OomError::OutOfDeviceMemory)
+            }
+            _ => Self::PoolAllocError(error),
+        }
+    }
+}
+
 impl error::Error for DescriptorSetError {}
 
 impl fmt::Display for DescriptorSetError {
@@ -345,9 +393,13 @@ impl fmt::Display for DescriptorSetError {
                 Self::NotIdentitySwizzled =>
                     "the image view has a component swizzle that is different from identity",
                 Self::NotInArray => "the builder is not in an array, but the operation requires it to be",
+                Self::NullDescriptorNotEnabled =>
+                    "provided a null descriptor, but the `null_descriptor` feature is not enabled",
                 Self::OomError(_) => "out of memory",
+                Self::PoolAllocError(_) => "the descriptor pool has no room for the descriptor set",
                 Self::ResourceWrongDevice => "resource belongs to another device",
                 Self::SamplerIsImmutable => "provided a dynamically assigned sampler, but the descriptor has an immutable sampler",
+                Self::SamplerYcbcrConversionNotImmutable => "provided a sampler or image view with a sampler YCbCr conversion, but the descriptor is not a combined image sampler with an immutable sampler",
                 Self::TooManyDescriptors => "builder doesn't expect anymore descriptors",
                 Self::UnexpectedArrayed => "expected a non-arrayed image, but got an arrayed image",
                 Self::WrongDescriptorType => "expected one type of resource but got another",
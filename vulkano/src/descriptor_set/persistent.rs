@@ -21,6 +21,7 @@
 //! # Example
 //! TODO:
 
+use crate::acceleration_structure::AccelerationStructure;
 use crate::buffer::BufferView;
 use crate::descriptor_set::builder::DescriptorSetBuilder;
 use crate::descriptor_set::pool::standard::StdDescriptorPoolAlloc;
@@ -211,6 +212,19 @@ impl PersistentDescriptorSetBuilder {
         Ok(self)
     }
 
+    /// Binds an acceleration structure as the next descriptor.
+    ///
+    /// An error is returned if the acceleration structure isn't compatible with the descriptor.
+    #[inline]
+    pub fn add_acceleration_structure(
+        &mut self,
+        acceleration_structure: Arc<AccelerationStructure>,
+    ) -> Result<&mut Self, DescriptorSetError> {
+        self.inner
+            .add_acceleration_structure(acceleration_structure)?;
+        Ok(self)
+    }
+
     /// Builds a `PersistentDescriptorSet` from the builder.
     #[inline]
     pub fn build(
@@ -9,6 +9,8 @@
 
 //! A pool from which descriptor sets can be allocated.
 
+pub use self::sharded::ShardedDescriptorPool;
+pub use self::standard::DescriptorPoolCreateInfo;
 pub use self::standard::StdDescriptorPool;
 pub use self::sys::DescriptorPoolAllocError;
 pub use self::sys::DescriptorSetAllocateInfo;
@@ -17,10 +19,11 @@ use crate::descriptor_set::layout::DescriptorSetLayout;
 use crate::descriptor_set::layout::DescriptorType;
 use crate::descriptor_set::UnsafeDescriptorSet;
 use crate::device::DeviceOwned;
-use crate::OomError;
 use std::cmp;
 use std::ops;
+use std::sync::Arc;
 
+pub mod sharded;
 pub mod standard;
 mod sys;
 
@@ -35,11 +38,14 @@ pub unsafe trait DescriptorPool: DeviceOwned {
     type Alloc: DescriptorPoolAlloc;
 
     /// Allocates a descriptor set.
+    ///
+    /// Returns `OutOfPoolMemory` or `FragmentedPool` if the pool has no room for the set and
+    /// can't make room for it.
     fn alloc(
         &mut self,
-        layout: &DescriptorSetLayout,
+        layout: &Arc<DescriptorSetLayout>,
         variable_descriptor_count: u32,
-    ) -> Result<Self::Alloc, OomError>;
+    ) -> Result<Self::Alloc, DescriptorPoolAllocError>;
 }
 
 /// An allocated descriptor set.
@@ -112,6 +118,10 @@ macro_rules! descriptors_count {
                     DescriptorType::UniformBufferDynamic => self.uniform_buffer_dynamic += num,
                     DescriptorType::StorageBufferDynamic => self.storage_buffer_dynamic += num,
                     DescriptorType::InputAttachment => self.input_attachment += num,
+                    DescriptorType::AccelerationStructure => {
+                        self.acceleration_structure += num
+                    }
+                    DescriptorType::Mutable => self.mutable += num,
                 };
             }
         }
@@ -228,4 +238,6 @@ descriptors_count! {
     sampler,
     combined_image_sampler,
     input_attachment,
+    acceleration_structure,
+    mutable,
 }
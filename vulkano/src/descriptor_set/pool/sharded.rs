@@ -0,0 +1,278 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::descriptor_set::layout::DescriptorSetLayout;
+use crate::descriptor_set::pool::sys::DescriptorSetAllocateInfo;
+use crate::descriptor_set::pool::DescriptorPool;
+use crate::descriptor_set::pool::DescriptorPoolAlloc;
+use crate::descriptor_set::pool::DescriptorPoolAllocError;
+use crate::descriptor_set::pool::UnsafeDescriptorPool;
+use crate::descriptor_set::UnsafeDescriptorSet;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::VulkanObject;
+use fnv::FnvHashMap;
+use std::cmp;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+
+/// Implementation of a descriptor pool that is optimized for allocating many descriptor sets from
+/// many threads.
+///
+/// It is guaranteed that the `Arc<ShardedDescriptorPool>` is kept alive by its allocations.
+///
+/// The pool is split into a number of shards, each protected by its own mutex. A thread always
+/// allocates from the same shard, chosen from its thread ID, so that threads rarely contend with
+/// each other.
+///
+/// Within a shard, descriptor sets are grouped by layout and variable descriptor count. When an
+/// allocation is dropped, its descriptor set is not freed but put in a free list, and is reused
+/// for the next allocation with the same layout and variable descriptor count. Vulkan pools are
+/// only created when the free list is empty, and are dedicated to a single layout. The first pool
+/// of a layout has room for 8 descriptor sets, and each following pool has room for twice as
+/// many as the previous one, up to 256.
+///
+/// Note that a reused descriptor set still contains the descriptors that were written to it
+/// before, until they are overwritten.
+pub struct ShardedDescriptorPool {
+    device: Arc<Device>,
+    shards: Vec<Mutex<Shard>>,
+    allocated_sets: AtomicUsize,
+    pool_count: AtomicUsize,
+}
+
+#[derive(Default)]
+struct Shard {
+    layouts: FnvHashMap<(ash::vk::DescriptorSetLayout, u32), LayoutPools>,
+}
+
+struct LayoutPools {
+    // Keeps the layout alive, so that its handle can't be reused for another layout.
+    _layout: Arc<DescriptorSetLayout>,
+    // The Vulkan pools that were created for this layout. Never read, but destroying them frees
+    // their descriptor sets.
+    _pools: Vec<UnsafeDescriptorPool>,
+    // The descriptor sets that are not currently allocated.
+    free: Vec<UnsafeDescriptorSet>,
+    // The number of sets of the next pool to create.
+    next_pool_sets: u32,
+}
+
+impl ShardedDescriptorPool {
+    /// The default number of shards.
+    pub const DEFAULT_SHARDS: usize = 16;
+
+    /// Builds a new `ShardedDescriptorPool` with `DEFAULT_SHARDS` shards.
+    #[inline]
+    pub fn new(device: Arc<Device>) -> ShardedDescriptorPool {
+        ShardedDescriptorPool::with_shards(device, Self::DEFAULT_SHARDS)
+    }
+
+    /// Builds a new `ShardedDescriptorPool` with `shards` shards.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `shards` is 0.
+    pub fn with_shards(device: Arc<Device>, shards: usize) -> ShardedDescriptorPool {
+        assert_ne!(shards, 0);
+
+        ShardedDescriptorPool {
+            device,
+            shards: (0..shards).map(|_| Mutex::new(Shard::default())).collect(),
+            allocated_sets: AtomicUsize::new(0),
+            pool_count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of descriptor sets that are currently allocated from this pool.
+    #[inline]
+    pub fn allocated_sets(&self) -> usize {
+        self.allocated_sets.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of Vulkan descriptor pools that have been created by this pool.
+    #[inline]
+    pub fn pool_count(&self) -> usize {
+        self.pool_count.load(Ordering::Relaxed)
+    }
+
+    // Returns the index of the shard that the current thread allocates from.
+    fn current_shard(&self) -> usize {
+        let mut hasher = DefaultHasher::new();
+        thread::current().id().hash(&mut hasher);
+        hasher.finish() as usize % self.shards.len()
+    }
+}
+
+unsafe impl DescriptorPool for Arc<ShardedDescriptorPool> {
+    type Alloc = ShardedDescriptorPoolAlloc;
+
+    fn alloc(
+        &mut self,
+        layout: &Arc<DescriptorSetLayout>,
+        variable_descriptor_count: u32,
+    ) -> Result<ShardedDescriptorPoolAlloc, DescriptorPoolAllocError> {
+        assert!(
+            !layout.desc().is_push_descriptor(),
+            "the provided descriptor set layout is for push descriptors, and cannot be used to build a descriptor set object",
+        );
+
+        let max_count = layout.variable_descriptor_count();
+
+        assert!(
+            variable_descriptor_count <= max_count,
+            "the provided variable_descriptor_count ({}) is greater than the maximum number of variable count descriptors in the set ({})",
+            variable_descriptor_count,
+            max_count,
+        );
+
+        let shard_index = self.current_shard();
+        let key = (layout.internal_object(), variable_descriptor_count);
+        let mut shard = self.shards[shard_index].lock().unwrap();
+
+        let layout_pools = shard.layouts.entry(key).or_insert_with(|| LayoutPools {
+            _layout: layout.clone(),
+            _pools: Vec::new(),
+            free: Vec::new(),
+            next_pool_sets: 8,
+        });
+
+        if layout_pools.free.is_empty() {
+            let sets_count = layout_pools.next_pool_sets;
+            let mut pool = UnsafeDescriptorPool::new(
+                self.device.clone(),
+                &(*layout.descriptors_count() * sets_count),
+                sets_count,
+                false,
+                layout.desc().is_update_after_bind(),
+            )?;
+
+            // The pool is created with exactly the right capacity, but the implementation may
+            // still need more room than what we have computed from the layout.
+            let sets = unsafe {
+                pool.alloc((0..sets_count).map(|_| DescriptorSetAllocateInfo {
+                    layout,
+                    variable_descriptor_count,
+                }))?
+            };
+
+            layout_pools.free.extend(sets);
+            layout_pools._pools.push(pool);
+            layout_pools.next_pool_sets = cmp::min(sets_count * 2, 256);
+            self.pool_count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let set = layout_pools.free.pop().unwrap();
+        self.allocated_sets.fetch_add(1, Ordering::Relaxed);
+
+        Ok(ShardedDescriptorPoolAlloc {
+            pool: self.clone(),
+            shard_index,
+            key,
+            set: Some(set),
+        })
+    }
+}
+
+unsafe impl DeviceOwned for ShardedDescriptorPool {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+/// A descriptor set allocated from a `ShardedDescriptorPool`.
+pub struct ShardedDescriptorPoolAlloc {
+    // We keep the pool alive, otherwise it would be destroyed.
+    pool: Arc<ShardedDescriptorPool>,
+    // The shard and free list to return the set to.
+    shard_index: usize,
+    key: (ash::vk::DescriptorSetLayout, u32),
+    // The set. Inside an option so that we can extract it in the destructor.
+    set: Option<UnsafeDescriptorSet>,
+}
+
+impl DescriptorPoolAlloc for ShardedDescriptorPoolAlloc {
+    #[inline]
+    fn inner(&self) -> &UnsafeDescriptorSet {
+        self.set.as_ref().unwrap()
+    }
+
+    #[inline]
+    fn inner_mut(&mut self) -> &mut UnsafeDescriptorSet {
+        self.set.as_mut().unwrap()
+    }
+}
+
+impl Drop for ShardedDescriptorPoolAlloc {
+    // This is the destructor of a single allocation (not of the whole pool).
+    fn drop(&mut self) {
+        let mut shard = self.pool.shards[self.shard_index].lock().unwrap();
+        shard
+            .layouts
+            .get_mut(&self.key)
+            .unwrap()
+            .free
+            .push(self.set.take().unwrap());
+        self.pool.allocated_sets.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::descriptor_set::layout::DescriptorDesc;
+    use crate::descriptor_set::layout::DescriptorSetDesc;
+    use crate::descriptor_set::layout::DescriptorSetLayout;
+    use crate::descriptor_set::layout::DescriptorType;
+    use crate::descriptor_set::pool::DescriptorPool;
+    use crate::descriptor_set::pool::ShardedDescriptorPool;
+    use crate::shader::ShaderStages;
+    use std::iter;
+    use std::sync::Arc;
+
+    #[test]
+    fn recycle_sets() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let desc = DescriptorDesc {
+            ty: DescriptorType::Sampler,
+            descriptor_count: 1,
+            variable_count: false,
+            update_after_bind: false,
+            update_unused_while_pending: false,
+            partially_bound: false,
+            stages: ShaderStages::all(),
+            immutable_samplers: Vec::new(),
+            mutable_types: Vec::new(),
+        };
+        let layout = DescriptorSetLayout::new(
+            device.clone(),
+            DescriptorSetDesc::new(iter::once(Some(desc))),
+        )
+        .unwrap();
+
+        let mut pool = Arc::new(ShardedDescriptorPool::new(device));
+        let allocs: Vec<_> = (0..8).map(|_| pool.alloc(&layout, 0).unwrap()).collect();
+        assert_eq!(pool.allocated_sets(), 8);
+        assert_eq!(pool.pool_count(), 1);
+
+        drop(allocs);
+        assert_eq!(pool.allocated_sets(), 0);
+
+        let _alloc = pool.alloc(&layout, 0).unwrap();
+        assert_eq!(pool.allocated_sets(), 1);
+        assert_eq!(pool.pool_count(), 1);
+    }
+}
@@ -17,7 +17,10 @@ use crate::descriptor_set::pool::UnsafeDescriptorPool;
 use crate::descriptor_set::UnsafeDescriptorSet;
 use crate::device::Device;
 use crate::device::DeviceOwned;
-use crate::OomError;
+use std::cmp;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -27,11 +30,25 @@ use std::sync::Mutex;
 /// desirable so that we can store a `Weak<StdDescriptorPool>`.
 ///
 /// Whenever a set is allocated, this implementation will try to find a pool that has some space
-/// for it. If there is one, allocate from it. If there is none, create a new pool whose capacity
-/// is 40 sets and 40 times the requested descriptors. This number is arbitrary.
+/// for it. If there is one, allocate from it. If there is none, create a new pool. The capacity
+/// of the new pools is determined by the [`DescriptorPoolCreateInfo`] of the pool: by default,
+/// the first pool has room for 40 sets and 40 times the requested descriptors, and each following
+/// pool has room for twice as many, up to 640 sets.
+///
+/// If the implementation can't allocate a set from a new pool because the pool is out of memory,
+/// for example because it needs more room than the descriptor counts of the layout, a bigger pool
+/// is created and the allocation is retried. An error is only returned once the pools can't grow
+/// any further.
+///
+/// Layouts with update-after-bind bindings are allocated from separate pools, that are created
+/// with the update-after-bind flag.
 pub struct StdDescriptorPool {
     device: Arc<Device>,
+    create_info: DescriptorPoolCreateInfo,
     pools: Mutex<Vec<Arc<Mutex<Pool>>>>,
+    // The number of sets of the next pool to create. Only modified while `pools` is locked.
+    next_max_sets: AtomicU32,
+    allocated_sets: AtomicUsize,
 }
 
 struct Pool {
@@ -41,11 +58,98 @@ struct Pool {
 }
 
 impl StdDescriptorPool {
-    /// Builds a new `StdDescriptorPool`.
+    /// Builds a new `StdDescriptorPool` with the default `DescriptorPoolCreateInfo`.
+    #[inline]
     pub fn new(device: Arc<Device>) -> StdDescriptorPool {
+        StdDescriptorPool::with_create_info(device, DescriptorPoolCreateInfo::default())
+    }
+
+    /// Builds a new `StdDescriptorPool` that creates Vulkan pools according to `create_info`.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `create_info.max_sets` or `create_info.max_sets_growth` is 0.
+    /// - Panics if `create_info.max_sets_limit` is less than `create_info.max_sets`.
+    pub fn with_create_info(
+        device: Arc<Device>,
+        create_info: DescriptorPoolCreateInfo,
+    ) -> StdDescriptorPool {
+        assert_ne!(create_info.max_sets, 0);
+        assert_ne!(create_info.max_sets_growth, 0);
+        assert!(create_info.max_sets_limit >= create_info.max_sets);
+
         StdDescriptorPool {
-            device: device,
+            device,
+            next_max_sets: AtomicU32::new(create_info.max_sets),
+            create_info,
             pools: Mutex::new(Vec::new()),
+            allocated_sets: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the `DescriptorPoolCreateInfo` that the pool was created with.
+    #[inline]
+    pub fn create_info(&self) -> &DescriptorPoolCreateInfo {
+        &self.create_info
+    }
+
+    /// Returns the number of descriptor sets that are currently allocated from this pool.
+    #[inline]
+    pub fn allocated_sets(&self) -> usize {
+        self.allocated_sets.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of Vulkan descriptor pools that currently exist in this pool.
+    #[inline]
+    pub fn pool_count(&self) -> usize {
+        self.pools.lock().unwrap().len()
+    }
+
+    /// Returns the number of sets that the next Vulkan pool will have room for.
+    #[inline]
+    pub fn next_max_sets(&self) -> u32 {
+        self.next_max_sets.load(Ordering::Relaxed)
+    }
+
+    // Returns the number of sets of the pool that follows a pool of `max_sets` sets.
+    fn grow(&self, max_sets: u32) -> u32 {
+        cmp::min(
+            max_sets.saturating_mul(self.create_info.max_sets_growth),
+            self.create_info.max_sets_limit,
+        )
+    }
+}
+
+/// Parameters that determine the capacity of the Vulkan pools that a `StdDescriptorPool`
+/// creates.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DescriptorPoolCreateInfo {
+    /// The number of sets that the first Vulkan pool has room for. Each pool has room for this
+    /// number of times the descriptors of the layout that caused it to be created.
+    ///
+    /// The default value is 40.
+    pub max_sets: u32,
+
+    /// The factor by which the number of sets is multiplied for each new Vulkan pool. A value of
+    /// 1 creates pools of a constant size, but then allocations that fail because a new pool is
+    /// out of memory are not retried.
+    ///
+    /// The default value is 2.
+    pub max_sets_growth: u32,
+
+    /// The maximum number of sets that a Vulkan pool has room for.
+    ///
+    /// The default value is 640.
+    pub max_sets_limit: u32,
+}
+
+impl Default for DescriptorPoolCreateInfo {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            max_sets: 40,
+            max_sets_growth: 2,
+            max_sets_limit: 640,
         }
     }
 }
@@ -67,9 +171,9 @@ unsafe impl DescriptorPool for Arc<StdDescriptorPool> {
     // TODO: eventually use a lock-free algorithm?
     fn alloc(
         &mut self,
-        layout: &DescriptorSetLayout,
+        layout: &Arc<DescriptorSetLayout>,
         variable_descriptor_count: u32,
-    ) -> Result<StdDescriptorPoolAlloc, OomError> {
+    ) -> Result<StdDescriptorPoolAlloc, DescriptorPoolAllocError> {
         assert!(
             !layout.desc().is_push_descriptor(),
             "the provided descriptor set layout is for push descriptors, and cannot be used to build a descriptor set object",
@@ -94,6 +198,10 @@ unsafe impl DescriptorPool for Arc<StdDescriptorPool> {
                 continue;
             }
 
+            if pool.pool.is_update_after_bind() != layout.desc().is_update_after_bind() {
+                continue;
+            }
+
             if !(pool.remaining_capacity >= *layout.descriptors_count()) {
                 continue;
             }
@@ -117,6 +225,8 @@ unsafe impl DescriptorPool for Arc<StdDescriptorPool> {
                 }
             };
 
+            self.allocated_sets.fetch_add(1, Ordering::Relaxed);
+
             return Ok(StdDescriptorPoolAlloc {
                 pool: pool_arc.clone(),
                 set: Some(alloc),
@@ -125,46 +235,66 @@ unsafe impl DescriptorPool for Arc<StdDescriptorPool> {
             });
         }
 
-        // No existing pool can be used. Create a new one.
-        // We use an arbitrary number of 40 sets and 40 times the requested descriptors.
-        let count = layout.descriptors_count().clone() * 40;
-        // Failure to allocate a new pool results in an error for the whole function because
-        // there's no way we can recover from that.
-        let mut new_pool = UnsafeDescriptorPool::new(self.device.clone(), &count, 40, true)?;
-
-        let alloc = unsafe {
-            match new_pool.alloc([DescriptorSetAllocateInfo {
-                layout,
-                variable_descriptor_count,
-            }]) {
-                Ok(mut sets) => sets.next().unwrap(),
-                Err(DescriptorPoolAllocError::OutOfHostMemory) => {
-                    return Err(OomError::OutOfHostMemory);
-                }
-                Err(DescriptorPoolAllocError::OutOfDeviceMemory) => {
-                    return Err(OomError::OutOfDeviceMemory);
+        // No existing pool can be used. Create a new one, growing it until the set fits.
+        loop {
+            let max_sets = self.next_max_sets.load(Ordering::Relaxed);
+            let next_max_sets = self.grow(max_sets);
+            let count = *layout.descriptors_count() * max_sets;
+
+            // Failure to allocate a new pool results in an error for the whole function because
+            // there's no way we can recover from that.
+            let mut new_pool = UnsafeDescriptorPool::new(
+                self.device.clone(),
+                &count,
+                max_sets,
+                true,
+                layout.desc().is_update_after_bind(),
+            )?;
+
+            let alloc = unsafe {
+                match new_pool.alloc([DescriptorSetAllocateInfo {
+                    layout,
+                    variable_descriptor_count,
+                }]) {
+                    Ok(mut sets) => sets.next().unwrap(),
+                    // The implementation needs more room than what we have computed from the
+                    // layout. Retry with a bigger pool if the pools can still grow.
+                    Err(err)
+                        if matches!(
+                            err,
+                            DescriptorPoolAllocError::OutOfPoolMemory
+                                | DescriptorPoolAllocError::FragmentedPool
+                        ) =>
+                    {
+                        if next_max_sets <= max_sets {
+                            return Err(err);
+                        }
+
+                        self.next_max_sets.store(next_max_sets, Ordering::Relaxed);
+                        continue;
+                    }
+                    Err(err) => return Err(err),
                 }
-                // A fragmented pool error can't happen at the first ever allocation.
-                Err(DescriptorPoolAllocError::FragmentedPool) => unreachable!(),
-                // Out of pool memory cannot happen at the first ever allocation.
-                Err(DescriptorPoolAllocError::OutOfPoolMemory) => unreachable!(),
-            }
-        };
+            };
+
+            self.next_max_sets.store(next_max_sets, Ordering::Relaxed);
 
-        let pool_obj = Arc::new(Mutex::new(Pool {
-            pool: new_pool,
-            remaining_capacity: count - *layout.descriptors_count(),
-            remaining_sets_count: 40 - 1,
-        }));
+            let pool_obj = Arc::new(Mutex::new(Pool {
+                pool: new_pool,
+                remaining_capacity: count - *layout.descriptors_count(),
+                remaining_sets_count: max_sets - 1,
+            }));
 
-        pools.push(pool_obj.clone());
+            pools.push(pool_obj.clone());
+            self.allocated_sets.fetch_add(1, Ordering::Relaxed);
 
-        Ok(StdDescriptorPoolAlloc {
-            pool: pool_obj,
-            set: Some(alloc),
-            descriptors: *layout.descriptors_count(),
-            pool_parent: self.clone(),
-        })
+            return Ok(StdDescriptorPoolAlloc {
+                pool: pool_obj,
+                set: Some(alloc),
+                descriptors: *layout.descriptors_count(),
+                pool_parent: self.clone(),
+            });
+        }
     }
 }
 
@@ -197,6 +327,10 @@ impl Drop for StdDescriptorPoolAlloc {
             pool.remaining_sets_count += 1;
             pool.remaining_capacity += self.descriptors;
         }
+
+        self.pool_parent
+            .allocated_sets
+            .fetch_sub(1, Ordering::Relaxed);
     }
 }
 
@@ -207,6 +341,7 @@ mod tests {
     use crate::descriptor_set::layout::DescriptorSetLayout;
     use crate::descriptor_set::layout::DescriptorType;
     use crate::descriptor_set::pool::DescriptorPool;
+    use crate::descriptor_set::pool::DescriptorPoolCreateInfo;
     use crate::descriptor_set::pool::StdDescriptorPool;
     use crate::shader::ShaderStages;
     use std::iter;
@@ -221,8 +356,12 @@ mod tests {
             ty: DescriptorType::Sampler,
             descriptor_count: 1,
             variable_count: false,
+            update_after_bind: false,
+            update_unused_while_pending: false,
+            partially_bound: false,
             stages: ShaderStages::all(),
             immutable_samplers: Vec::new(),
+            mutable_types: Vec::new(),
         };
         let layout = DescriptorSetLayout::new(
             device.clone(),
@@ -236,4 +375,44 @@ mod tests {
         drop(pool);
         assert!(pool_weak.upgrade().is_some());
     }
+
+    #[test]
+    fn pools_grow() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let desc = DescriptorDesc {
+            ty: DescriptorType::Sampler,
+            descriptor_count: 1,
+            variable_count: false,
+            update_after_bind: false,
+            update_unused_while_pending: false,
+            partially_bound: false,
+            stages: ShaderStages::all(),
+            immutable_samplers: Vec::new(),
+            mutable_types: Vec::new(),
+        };
+        let layout = DescriptorSetLayout::new(
+            device.clone(),
+            DescriptorSetDesc::new(iter::once(Some(desc))),
+        )
+        .unwrap();
+
+        let mut pool = Arc::new(StdDescriptorPool::with_create_info(
+            device,
+            DescriptorPoolCreateInfo {
+                max_sets: 1,
+                max_sets_growth: 2,
+                max_sets_limit: 4,
+            },
+        ));
+
+        // The pools have room for 1, 2 and 4 sets.
+        let allocs: Vec<_> = (0..4).map(|_| pool.alloc(&layout, 0).unwrap()).collect();
+        assert_eq!(pool.allocated_sets(), 4);
+        assert_eq!(pool.pool_count(), 3);
+        assert_eq!(pool.next_max_sets(), 4);
+
+        drop(allocs);
+        assert_eq!(pool.allocated_sets(), 0);
+    }
 }
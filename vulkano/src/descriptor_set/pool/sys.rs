@@ -30,6 +30,7 @@ use std::sync::Arc;
 pub struct UnsafeDescriptorPool {
     pool: ash::vk::DescriptorPool,
     device: Arc<Device>,
+    update_after_bind: bool,
 }
 
 impl UnsafeDescriptorPool {
@@ -41,6 +42,9 @@ impl UnsafeDescriptorPool {
     /// If `free_descriptor_set_bit` is `true`, then individual descriptor sets can be free'd from
     /// the pool. Otherwise you must reset or destroy the whole pool at once.
     ///
+    /// If `update_after_bind` is `true`, then descriptor sets whose layout contains
+    /// update-after-bind bindings can be allocated from the pool.
+    ///
     /// # Panic
     ///
     /// - Panics if all the descriptors count are 0.
@@ -51,12 +55,13 @@ impl UnsafeDescriptorPool {
         count: &DescriptorsCount,
         max_sets: u32,
         free_descriptor_set_bit: bool,
+        update_after_bind: bool,
     ) -> Result<UnsafeDescriptorPool, OomError> {
         let fns = device.fns();
 
         assert_ne!(max_sets, 0, "The maximum number of sets can't be 0");
 
-        let mut pool_sizes: SmallVec<[_; 10]> = SmallVec::new();
+        let mut pool_sizes: SmallVec<[_; 12]> = SmallVec::new();
 
         macro_rules! elem {
             ($field:ident, $ty:expr) => {
@@ -95,19 +100,30 @@ impl UnsafeDescriptorPool {
             ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER
         );
         elem!(input_attachment, ash::vk::DescriptorType::INPUT_ATTACHMENT);
+        elem!(
+            acceleration_structure,
+            ash::vk::DescriptorType::ACCELERATION_STRUCTURE_KHR
+        );
+        elem!(mutable, ash::vk::DescriptorType::MUTABLE_VALVE);
 
         assert!(
             !pool_sizes.is_empty(),
             "All the descriptors count of a pool are 0"
         );
 
+        let mut flags = ash::vk::DescriptorPoolCreateFlags::empty();
+
+        if free_descriptor_set_bit {
+            flags |= ash::vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET;
+        }
+
+        if update_after_bind {
+            flags |= ash::vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND;
+        }
+
         let pool = unsafe {
             let infos = ash::vk::DescriptorPoolCreateInfo {
-                flags: if free_descriptor_set_bit {
-                    ash::vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET
-                } else {
-                    ash::vk::DescriptorPoolCreateFlags::empty()
-                },
+                flags,
                 max_sets: max_sets,
                 pool_size_count: pool_sizes.len() as u32,
                 p_pool_sizes: pool_sizes.as_ptr(),
@@ -127,9 +143,17 @@ impl UnsafeDescriptorPool {
         Ok(UnsafeDescriptorPool {
             pool,
             device: device.clone(),
+            update_after_bind,
         })
     }
 
+    /// Returns whether descriptor sets with update-after-bind bindings can be allocated from
+    /// this pool.
+    #[inline]
+    pub fn is_update_after_bind(&self) -> bool {
+        self.update_after_bind
+    }
+
     /// Allocates descriptor sets from the pool, one for each element in `create_info`.
     /// Returns an iterator to the allocated sets, or an error.
     ///
@@ -163,6 +187,9 @@ impl UnsafeDescriptorPool {
                         "Tried to allocate from a pool with a set layout of a different device"
                     );
                     debug_assert!(!info.layout.desc().is_push_descriptor());
+                    debug_assert!(
+                        self.update_after_bind || !info.layout.desc().is_update_after_bind()
+                    );
                     debug_assert!(
                         info.variable_descriptor_count <= info.layout.variable_descriptor_count()
                     );
@@ -349,6 +376,16 @@ pub enum DescriptorPoolAllocError {
 
 impl error::Error for DescriptorPoolAllocError {}
 
+impl From<OomError> for DescriptorPoolAllocError {
+    #[inline]
+    fn from(err: OomError) -> Self {
+        match err {
+            OomError::OutOfHostMemory => DescriptorPoolAllocError::OutOfHostMemory,
+            OomError::OutOfDeviceMemory => DescriptorPoolAllocError::OutOfDeviceMemory,
+        }
+    }
+}
+
 impl fmt::Display for DescriptorPoolAllocError {
     #[inline]
     fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
@@ -391,7 +428,7 @@ mod tests {
             ..DescriptorsCount::zero()
         };
 
-        let _ = UnsafeDescriptorPool::new(device, &desc, 10, false).unwrap();
+        let _ = UnsafeDescriptorPool::new(device, &desc, 10, false, false).unwrap();
     }
 
     #[test]
@@ -403,7 +440,7 @@ mod tests {
         };
 
         assert_should_panic!("The maximum number of sets can't be 0", {
-            let _ = UnsafeDescriptorPool::new(device, &desc, 0, false);
+            let _ = UnsafeDescriptorPool::new(device, &desc, 0, false, false);
         });
     }
 
@@ -412,7 +449,7 @@ mod tests {
         let (device, _) = gfx_dev_and_queue!();
 
         assert_should_panic!("All the descriptors count of a pool are 0", {
-            let _ = UnsafeDescriptorPool::new(device, &DescriptorsCount::zero(), 10, false);
+            let _ = UnsafeDescriptorPool::new(device, &DescriptorsCount::zero(), 10, false, false);
         });
     }
 
@@ -424,8 +461,12 @@ mod tests {
             ty: DescriptorType::UniformBuffer,
             descriptor_count: 1,
             variable_count: false,
+            update_after_bind: false,
+            update_unused_while_pending: false,
+            partially_bound: false,
             stages: ShaderStages::all_graphics(),
             immutable_samplers: Vec::new(),
+            mutable_types: Vec::new(),
         };
 
         let set_layout = DescriptorSetLayout::new(
@@ -439,7 +480,7 @@ mod tests {
             ..DescriptorsCount::zero()
         };
 
-        let mut pool = UnsafeDescriptorPool::new(device, &desc, 10, false).unwrap();
+        let mut pool = UnsafeDescriptorPool::new(device, &desc, 10, false, false).unwrap();
         unsafe {
             let sets = pool
                 .alloc([DescriptorSetAllocateInfo {
@@ -460,8 +501,12 @@ mod tests {
             ty: DescriptorType::UniformBuffer,
             descriptor_count: 1,
             variable_count: false,
+            update_after_bind: false,
+            update_unused_while_pending: false,
+            partially_bound: false,
             stages: ShaderStages::all_graphics(),
             immutable_samplers: Vec::new(),
+            mutable_types: Vec::new(),
         };
 
         let set_layout =
@@ -476,7 +521,7 @@ mod tests {
         assert_should_panic!(
             "Tried to allocate from a pool with a set layout of a different device",
             {
-                let mut pool = UnsafeDescriptorPool::new(device2, &desc, 10, false).unwrap();
+                let mut pool = UnsafeDescriptorPool::new(device2, &desc, 10, false, false).unwrap();
 
                 unsafe {
                     let _ = pool.alloc([DescriptorSetAllocateInfo {
@@ -497,7 +542,7 @@ mod tests {
             ..DescriptorsCount::zero()
         };
 
-        let mut pool = UnsafeDescriptorPool::new(device, &desc, 1, false).unwrap();
+        let mut pool = UnsafeDescriptorPool::new(device, &desc, 1, false, false).unwrap();
         unsafe {
             let sets = pool.alloc(iter::empty()).unwrap();
             assert_eq!(sets.count(), 0);
@@ -0,0 +1,358 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Finding the descriptor sets that reference a resource.
+//!
+//! A descriptor set keeps the images and buffers that are bound to it alive, but there is no way
+//! to go from a resource back to the descriptor sets that reference it. This is needed for
+//! example when the swapchain is recreated after a resize: all the descriptor sets that contain
+//! views of the old swapchain images, or of images whose size depends on the swapchain, must be
+//! rebuilt.
+//!
+//! The `DescriptorSetRegistry` keeps track of this. Descriptor sets are registered after they
+//! have been written, and the registry can then be asked which sets reference a given image or
+//! buffer. `invalidate` additionally removes these sets from the registry and returns them, so
+//! that only the affected sets are rebuilt and registered again.
+//!
+//! Resources are identified by their underlying Vulkan image or buffer, so that all views of an
+//! image, and all parts of a buffer, are considered to be the same resource.
+
+use crate::buffer::BufferAccess;
+use crate::descriptor_set::DescriptorBindingResources;
+use crate::descriptor_set::DescriptorResource;
+use crate::descriptor_set::DescriptorSet;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::image::ImageAccess;
+use crate::VulkanObject;
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use std::sync::Arc;
+use std::sync::Weak;
+
+/// Keeps track of the resources that are referenced by descriptor sets.
+///
+/// The registry only holds weak references to the descriptor sets. Sets that have been destroyed
+/// are never returned, and are removed from the registry by `cleanup`.
+///
+/// The resources of a set are collected when it is registered. If the descriptors of a set are
+/// updated afterwards, for example with an `UpdateAfterBindDescriptorSet`, the set must be
+/// registered again.
+pub struct DescriptorSetRegistry {
+    device: Arc<Device>,
+    sets: FnvHashMap<ash::vk::DescriptorSet, RegisteredSet>,
+    resources: FnvHashMap<DescriptorResourceKey, FnvHashSet<ash::vk::DescriptorSet>>,
+}
+
+struct RegisteredSet {
+    set: Weak<dyn DescriptorSet>,
+    keys: FnvHashSet<DescriptorResourceKey>,
+}
+
+impl DescriptorSetRegistry {
+    /// Builds a new empty `DescriptorSetRegistry` for descriptor sets of `device`.
+    #[inline]
+    pub fn new(device: Arc<Device>) -> DescriptorSetRegistry {
+        DescriptorSetRegistry {
+            device,
+            sets: FnvHashMap::default(),
+            resources: FnvHashMap::default(),
+        }
+    }
+
+    /// Registers a descriptor set, or updates the resources of a set that was already
+    /// registered.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the descriptor set doesn't belong to the device of the registry.
+    pub fn register(&mut self, set: &Arc<dyn DescriptorSet>) {
+        assert_eq!(
+            set.device().internal_object(),
+            self.device.internal_object()
+        );
+
+        let handle = set.inner().internal_object();
+        self.unregister_handle(handle);
+
+        let mut keys = FnvHashSet::default();
+        {
+            let resources = set.resources();
+
+            for binding_num in 0..set.layout().num_bindings() {
+                if let Some(binding_resources) = resources.binding(binding_num) {
+                    collect_keys(binding_resources, &mut keys);
+                }
+            }
+        }
+
+        for &key in &keys {
+            self.resources.entry(key).or_default().insert(handle);
+        }
+
+        self.sets.insert(
+            handle,
+            RegisteredSet {
+                set: Arc::downgrade(set),
+                keys,
+            },
+        );
+    }
+
+    /// Removes a descriptor set from the registry. Does nothing if it wasn't registered.
+    #[inline]
+    pub fn unregister(&mut self, set: &dyn DescriptorSet) {
+        self.unregister_handle(set.inner().internal_object());
+    }
+
+    /// Returns the registered descriptor sets that reference `key`.
+    pub fn sets_using(&self, key: DescriptorResourceKey) -> Vec<Arc<dyn DescriptorSet>> {
+        self.resources
+            .get(&key)
+            .into_iter()
+            .flatten()
+            .filter_map(|handle| self.sets[handle].set.upgrade())
+            .collect()
+    }
+
+    /// Returns whether any registered descriptor set references `key`.
+    #[inline]
+    pub fn is_used(&self, key: DescriptorResourceKey) -> bool {
+        !self.sets_using(key).is_empty()
+    }
+
+    /// Removes the descriptor sets that reference `key` from the registry, and returns them.
+    ///
+    /// The returned sets should be rebuilt with new resources, and the new sets registered.
+    pub fn invalidate(&mut self, key: DescriptorResourceKey) -> Vec<Arc<dyn DescriptorSet>> {
+        let handles = match self.resources.get(&key) {
+            Some(handles) => handles.iter().copied().collect::<Vec<_>>(),
+            None => return Vec::new(),
+        };
+
+        handles
+            .into_iter()
+            .filter_map(|handle| self.unregister_handle(handle))
+            .filter_map(|registered| registered.set.upgrade())
+            .collect()
+    }
+
+    /// Removes the descriptor sets that have been destroyed from the registry.
+    pub fn cleanup(&mut self) {
+        let dead: Vec<_> = self
+            .sets
+            .iter()
+            .filter(|(_, registered)| registered.set.strong_count() == 0)
+            .map(|(&handle, _)| handle)
+            .collect();
+
+        for handle in dead {
+            self.unregister_handle(handle);
+        }
+    }
+
+    /// Returns the number of descriptor sets in the registry, including sets that have been
+    /// destroyed but not yet removed by `cleanup`.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.sets.len()
+    }
+
+    /// Returns whether the registry contains no descriptor sets.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty()
+    }
+
+    fn unregister_handle(&mut self, handle: ash::vk::DescriptorSet) -> Option<RegisteredSet> {
+        let registered = self.sets.remove(&handle)?;
+
+        for key in &registered.keys {
+            if let Some(handles) = self.resources.get_mut(key) {
+                handles.remove(&handle);
+
+                if handles.is_empty() {
+                    self.resources.remove(key);
+                }
+            }
+        }
+
+        Some(registered)
+    }
+}
+
+unsafe impl DeviceOwned for DescriptorSetRegistry {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+/// Identifies a resource that can be referenced by descriptor sets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DescriptorResourceKey {
+    /// A Vulkan buffer, to which buffers and buffer views belong.
+    Buffer(ash::vk::Buffer),
+
+    /// A Vulkan image, to which image views belong.
+    Image(ash::vk::Image),
+}
+
+impl DescriptorResourceKey {
+    /// Returns the key of the Vulkan buffer that `buffer` belongs to.
+    #[inline]
+    pub fn buffer(buffer: &dyn BufferAccess) -> DescriptorResourceKey {
+        DescriptorResourceKey::Buffer(buffer.inner().buffer.internal_object())
+    }
+
+    /// Returns the key of the Vulkan image that `image` belongs to.
+    #[inline]
+    pub fn image(image: &dyn ImageAccess) -> DescriptorResourceKey {
+        DescriptorResourceKey::Image(image.inner().image.internal_object())
+    }
+}
+
+fn collect_keys(
+    binding_resources: &DescriptorBindingResources,
+    keys: &mut FnvHashSet<DescriptorResourceKey>,
+) {
+    match binding_resources {
+        DescriptorBindingResources::None | DescriptorBindingResources::Sampler(_) => (),
+        DescriptorBindingResources::Buffer(elements) => {
+            keys.extend(
+                elements
+                    .iter()
+                    .flatten()
+                    .map(|buffer| DescriptorResourceKey::buffer(buffer.as_ref())),
+            );
+        }
+        DescriptorBindingResources::BufferView(elements) => {
+            keys.extend(
+                elements
+                    .iter()
+                    .flatten()
+                    .map(|view| DescriptorResourceKey::buffer(view.buffer().as_ref())),
+            );
+        }
+        DescriptorBindingResources::ImageView(elements) => {
+            keys.extend(
+                elements
+                    .iter()
+                    .flatten()
+                    .map(|view| DescriptorResourceKey::image(view.image().as_ref())),
+            );
+        }
+        DescriptorBindingResources::ImageViewSampler(elements) => {
+            keys.extend(
+                elements
+                    .iter()
+                    .flatten()
+                    .map(|(view, _)| DescriptorResourceKey::image(view.image().as_ref())),
+            );
+        }
+        DescriptorBindingResources::AccelerationStructure(elements) => {
+            keys.extend(elements.iter().flatten().map(|acceleration_structure| {
+                DescriptorResourceKey::buffer(acceleration_structure.buffer().as_ref())
+            }));
+        }
+        DescriptorBindingResources::Mutable(elements) => {
+            keys.extend(
+                elements
+                    .iter()
+                    .flatten()
+                    .filter_map(|(_, resource)| match resource {
+                        DescriptorResource::Buffer(buffer) => {
+                            Some(DescriptorResourceKey::buffer(buffer.as_ref()))
+                        }
+                        DescriptorResource::BufferView(view) => {
+                            Some(DescriptorResourceKey::buffer(view.buffer().as_ref()))
+                        }
+                        DescriptorResource::ImageView(view)
+                        | DescriptorResource::ImageViewSampler(view, _) => {
+                            Some(DescriptorResourceKey::image(view.image().as_ref()))
+                        }
+                        DescriptorResource::Sampler(_) => None,
+                        DescriptorResource::AccelerationStructure(acceleration_structure) => Some(
+                            DescriptorResourceKey::buffer(acceleration_structure.buffer().as_ref()),
+                        ),
+                    }),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DescriptorResourceKey;
+    use super::DescriptorSetRegistry;
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::descriptor_set::layout::DescriptorDesc;
+    use crate::descriptor_set::layout::DescriptorSetDesc;
+    use crate::descriptor_set::layout::DescriptorSetLayout;
+    use crate::descriptor_set::layout::DescriptorType;
+    use crate::descriptor_set::DescriptorSet;
+    use crate::descriptor_set::PersistentDescriptorSet;
+    use crate::shader::ShaderStages;
+    use std::iter;
+    use std::sync::Arc;
+
+    #[test]
+    fn invalidate() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let desc = DescriptorDesc {
+            ty: DescriptorType::StorageBuffer,
+            descriptor_count: 1,
+            variable_count: false,
+            update_after_bind: false,
+            update_unused_while_pending: false,
+            partially_bound: false,
+            stages: ShaderStages::all(),
+            immutable_samplers: Vec::new(),
+            mutable_types: Vec::new(),
+        };
+        let layout = DescriptorSetLayout::new(
+            device.clone(),
+            DescriptorSetDesc::new(iter::once(Some(desc))),
+        )
+        .unwrap();
+
+        let buffer_a =
+            CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                .unwrap();
+        let buffer_b =
+            CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                .unwrap();
+
+        let mut builder = PersistentDescriptorSet::start(layout.clone());
+        builder.add_buffer(buffer_a.clone()).unwrap();
+        let set_a: Arc<dyn DescriptorSet> = builder.build().unwrap();
+
+        let mut builder = PersistentDescriptorSet::start(layout);
+        builder.add_buffer(buffer_b.clone()).unwrap();
+        let set_b: Arc<dyn DescriptorSet> = builder.build().unwrap();
+
+        let mut registry = DescriptorSetRegistry::new(device);
+        registry.register(&set_a);
+        registry.register(&set_b);
+        assert_eq!(registry.len(), 2);
+
+        let key_a = DescriptorResourceKey::buffer(&*buffer_a);
+        let invalidated = registry.invalidate(key_a);
+        assert_eq!(invalidated.len(), 1);
+        assert!(*invalidated[0] == *set_a);
+        assert!(!registry.is_used(key_a));
+        assert!(registry.is_used(DescriptorResourceKey::buffer(&*buffer_b)));
+
+        drop(set_b);
+        registry.cleanup();
+        assert!(registry.is_empty());
+    }
+}
@@ -7,20 +7,37 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use crate::acceleration_structure::AccelerationStructure;
 use crate::buffer::BufferViewAbstract;
 use crate::descriptor_set::layout::{DescriptorSetLayout, DescriptorType};
-use crate::descriptor_set::sys::{DescriptorWrite, DescriptorWriteElements};
+use crate::descriptor_set::sys::{DescriptorCopy, DescriptorWrite, DescriptorWriteElements};
 use crate::descriptor_set::BufferAccess;
 use crate::image::ImageViewAbstract;
 use crate::sampler::Sampler;
-use fnv::FnvHashMap;
+use crate::VulkanObject;
 use smallvec::{smallvec, SmallVec};
+use std::error;
+use std::fmt;
+use std::ops::Range;
 use std::sync::Arc;
 
 /// The resources that are bound to a descriptor set.
+///
+/// The resources are stored in a dense array indexed by binding number, which is kept inline for
+/// layouts of up to 4 bindings. Together with the inline storage of single descriptors in each
+/// binding, this means that no memory is allocated for the resources of layouts of up to 4
+/// bindings without arrays. Larger layouts allocate the array of bindings, and each binding with
+/// more than one descriptor allocates its array of elements.
+///
+/// There is no variant whose number of bindings is a const generic parameter, as
+/// [`DescriptorSet::resources`](crate::descriptor_set::DescriptorSet::resources) returns this
+/// type so that command buffers can go through the resources of any `dyn DescriptorSet`.
 #[derive(Clone)]
 pub struct DescriptorSetResources {
-    descriptors: FnvHashMap<u32, DescriptorBindingResources>,
+    descriptors: SmallVec<[Option<DescriptorBindingResources>; 4]>,
+    // Whether each binding is a `CombinedImageSampler` binding with immutable samplers, to
+    // report writes that provide samplers to it.
+    immutable_samplers: SmallVec<[bool; 4]>,
 }
 
 impl DescriptorSetResources {
@@ -29,13 +46,24 @@ impl DescriptorSetResources {
     pub fn new(layout: &DescriptorSetLayout, variable_descriptor_count: u32) -> Self {
         assert!(variable_descriptor_count <= layout.variable_descriptor_count());
 
+        let immutable_samplers = layout
+            .desc()
+            .bindings()
+            .iter()
+            .map(|binding_desc| {
+                binding_desc.as_ref().map_or(false, |binding_desc| {
+                    binding_desc.ty == DescriptorType::CombinedImageSampler
+                        && !binding_desc.immutable_samplers.is_empty()
+                })
+            })
+            .collect();
+
         let descriptors = layout
             .desc()
             .bindings()
             .iter()
-            .enumerate()
-            .filter_map(|(b, d)| d.as_ref().map(|d| (b as u32, d)))
-            .map(|(binding_num, binding_desc)| {
+            .map(|binding_desc| {
+                let binding_desc = binding_desc.as_ref()?;
                 let count = if binding_desc.variable_count {
                     variable_descriptor_count
                 } else {
@@ -71,34 +99,133 @@ impl DescriptorSetResources {
                             DescriptorBindingResources::None
                         }
                     }
+                    DescriptorType::AccelerationStructure => {
+                        DescriptorBindingResources::AccelerationStructure(smallvec![None; count])
+                    }
+                    DescriptorType::Mutable => {
+                        DescriptorBindingResources::Mutable(smallvec![None; count])
+                    }
                 };
-                (binding_num, binding_resources)
+                Some(binding_resources)
             })
             .collect();
 
-        Self { descriptors }
+        Self {
+            descriptors,
+            immutable_samplers,
+        }
     }
 
     /// Applies descriptor writes to the resources.
     ///
     /// # Panics
     ///
-    /// - Panics if the binding number of a write does not exist in the resources.
-    /// - See also [`DescriptorBindingResources::update`].
+    /// - Panics if a write is not valid for the resources. See [`try_update`](Self::try_update)
+    ///   for a version that returns an error instead.
+    #[inline]
     pub fn update<'a>(&mut self, writes: impl IntoIterator<Item = &'a DescriptorWrite>) {
+        if let Err(err) = self.try_update(writes) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Applies descriptor writes to the resources, or returns an error if one of the writes is
+    /// not valid for the resources.
+    ///
+    /// All writes are checked before any of them is applied, so the resources are left unchanged
+    /// if an error is returned.
+    pub fn try_update<'a>(
+        &mut self,
+        writes: impl IntoIterator<Item = &'a DescriptorWrite>,
+    ) -> Result<(), DescriptorWriteError> {
+        let writes: SmallVec<[_; 8]> = writes.into_iter().collect();
+
+        for write in writes.iter() {
+            let binding =
+                self.binding(write.binding_num)
+                    .ok_or(DescriptorWriteError::InvalidBinding {
+                        binding: write.binding_num,
+                    })?;
+
+            if self.immutable_samplers[write.binding_num as usize]
+                && write.elements().resource_type() == DescriptorResourceType::ImageViewSampler
+            {
+                return Err(DescriptorWriteError::SamplerIsImmutable {
+                    binding: write.binding_num,
+                });
+            }
+
+            binding.check_write(write)?;
+        }
+
         for write in writes {
-            self.descriptors
-                .get_mut(&write.binding_num)
-                .expect("descriptor write has invalid binding number")
-                .update(write)
+            self.binding_mut(write.binding_num)
+                .unwrap()
+                .write_unchecked(write);
         }
+
+        Ok(())
+    }
+
+    /// Applies a descriptor copy to the resources. `src` must be the resources of the source
+    /// binding of the copy.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the destination binding number of the copy does not exist in the resources.
+    /// - See also [`DescriptorBindingResources::copy_from`].
+    #[inline]
+    pub fn copy(&mut self, copy: &DescriptorCopy, src: &DescriptorBindingResources) {
+        self.binding_mut(copy.dst_binding())
+            .expect("descriptor copy has invalid destination binding number")
+            .copy_from(
+                copy.dst_first_array_element(),
+                src,
+                copy.src_first_array_element(),
+                copy.descriptor_count(),
+            );
     }
 
     /// Returns a reference to the bound resources for `binding`. Returns `None` if the binding
     /// doesn't exist.
     #[inline]
     pub fn binding(&self, binding: u32) -> Option<&DescriptorBindingResources> {
-        self.descriptors.get(&binding)
+        self.descriptors
+            .get(binding as usize)
+            .and_then(|b| b.as_ref())
+    }
+
+    /// Returns an iterator over the bindings that exist, along with their binding number.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &DescriptorBindingResources)> {
+        self.descriptors
+            .iter()
+            .enumerate()
+            .filter_map(|(binding, resources)| Some((binding as u32, resources.as_ref()?)))
+    }
+
+    /// Returns an object that can be used to print the resources with `{:?}` or `{}`, using
+    /// `layout` for the descriptor types and array sizes of the bindings.
+    ///
+    /// For every binding, this prints the binding number, the descriptor type, the number of
+    /// descriptors, the array elements that haven't been written yet, and the Vulkan handles of
+    /// the bound objects. The handles are the ones that validation layers and debuggers report,
+    /// and that the names set with `Device::set_object_name` are attached to.
+    ///
+    /// `layout` must be the layout that the resources were created for.
+    #[inline]
+    pub fn dump<'a>(&'a self, layout: &'a DescriptorSetLayout) -> DescriptorSetResourcesDump<'a> {
+        DescriptorSetResourcesDump {
+            resources: self,
+            layout,
+        }
+    }
+
+    #[inline]
+    fn binding_mut(&mut self, binding: u32) -> Option<&mut DescriptorBindingResources> {
+        self.descriptors
+            .get_mut(binding as usize)
+            .and_then(|b| b.as_mut())
     }
 }
 
@@ -111,22 +238,246 @@ pub enum DescriptorBindingResources {
     ImageView(Elements<Arc<dyn ImageViewAbstract>>),
     ImageViewSampler(Elements<(Arc<dyn ImageViewAbstract>, Arc<Sampler>)>),
     Sampler(Elements<Arc<Sampler>>),
+    AccelerationStructure(Elements<Arc<AccelerationStructure>>),
+    /// The resources of a `Mutable` binding, along with the descriptor type that each of them was
+    /// written as.
+    Mutable(Elements<(DescriptorType, DescriptorResource)>),
 }
 
 type Elements<T> = SmallVec<[Option<T>; 1]>;
 
+/// A single resource bound to a descriptor.
+#[derive(Clone)]
+pub enum DescriptorResource {
+    Buffer(Arc<dyn BufferAccess>),
+    BufferView(Arc<dyn BufferViewAbstract>),
+    ImageView(Arc<dyn ImageViewAbstract>),
+    ImageViewSampler(Arc<dyn ImageViewAbstract>, Arc<Sampler>),
+    Sampler(Arc<Sampler>),
+    AccelerationStructure(Arc<AccelerationStructure>),
+}
+
+impl DescriptorResource {
+    /// Returns the type of the resource.
+    #[inline]
+    pub fn resource_type(&self) -> DescriptorResourceType {
+        match self {
+            DescriptorResource::Buffer(_) => DescriptorResourceType::Buffer,
+            DescriptorResource::BufferView(_) => DescriptorResourceType::BufferView,
+            DescriptorResource::ImageView(_) => DescriptorResourceType::ImageView,
+            DescriptorResource::ImageViewSampler(_, _) => DescriptorResourceType::ImageViewSampler,
+            DescriptorResource::Sampler(_) => DescriptorResourceType::Sampler,
+            DescriptorResource::AccelerationStructure(_) => {
+                DescriptorResourceType::AccelerationStructure
+            }
+        }
+    }
+}
+
 impl DescriptorBindingResources {
     /// Applies a descriptor write to the resources.
     ///
     /// # Panics
     ///
-    /// - Panics if the resource types do not match.
-    /// - Panics if the write goes out of bounds.
+    /// - Panics if the write is not valid for the resources. See
+    ///   [`try_update`](Self::try_update) for a version that returns an error instead.
+    #[inline]
     pub fn update(&mut self, write: &DescriptorWrite) {
+        if let Err(err) = self.try_update(write) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Applies a descriptor write to the resources, or returns an error if the resource types do
+    /// not match or if the write goes out of bounds.
+    #[inline]
+    pub fn try_update(&mut self, write: &DescriptorWrite) -> Result<(), DescriptorWriteError> {
+        self.check_write(write)?;
+        self.write_unchecked(write);
+        Ok(())
+    }
+
+    /// Returns the type of resources that the binding holds, or `None` if it holds no resources
+    /// because all of its descriptors are immutable samplers.
+    #[inline]
+    pub fn resource_type(&self) -> Option<DescriptorResourceType> {
+        Some(match self {
+            DescriptorBindingResources::None => return None,
+            DescriptorBindingResources::Buffer(_) => DescriptorResourceType::Buffer,
+            DescriptorBindingResources::BufferView(_) => DescriptorResourceType::BufferView,
+            DescriptorBindingResources::ImageView(_) => DescriptorResourceType::ImageView,
+            DescriptorBindingResources::ImageViewSampler(_) => {
+                DescriptorResourceType::ImageViewSampler
+            }
+            DescriptorBindingResources::Sampler(_) => DescriptorResourceType::Sampler,
+            DescriptorBindingResources::AccelerationStructure(_) => {
+                DescriptorResourceType::AccelerationStructure
+            }
+            DescriptorBindingResources::Mutable(_) => DescriptorResourceType::Mutable,
+        })
+    }
+
+    /// Returns the number of descriptors in the binding.
+    #[inline]
+    pub fn count(&self) -> u32 {
+        (match self {
+            DescriptorBindingResources::None => 0,
+            DescriptorBindingResources::Buffer(resources) => resources.len(),
+            DescriptorBindingResources::BufferView(resources) => resources.len(),
+            DescriptorBindingResources::ImageView(resources) => resources.len(),
+            DescriptorBindingResources::ImageViewSampler(resources) => resources.len(),
+            DescriptorBindingResources::Sampler(resources) => resources.len(),
+            DescriptorBindingResources::AccelerationStructure(resources) => resources.len(),
+            DescriptorBindingResources::Mutable(resources) => resources.len(),
+        }) as u32
+    }
+
+    /// Returns whether the array element `index` of the binding has been written.
+    ///
+    /// Returns `false` if `index` is out of bounds, for bindings that only hold immutable
+    /// samplers, and for elements that were written as null descriptors.
+    #[inline]
+    pub fn is_written(&self, index: u32) -> bool {
+        fn is_some<T>(elements: &[Option<T>], index: u32) -> bool {
+            matches!(elements.get(index as usize), Some(Some(_)))
+        }
+
+        match self {
+            DescriptorBindingResources::None => false,
+            DescriptorBindingResources::Buffer(elements) => is_some(elements, index),
+            DescriptorBindingResources::BufferView(elements) => is_some(elements, index),
+            DescriptorBindingResources::ImageView(elements) => is_some(elements, index),
+            DescriptorBindingResources::ImageViewSampler(elements) => is_some(elements, index),
+            DescriptorBindingResources::Sampler(elements) => is_some(elements, index),
+            DescriptorBindingResources::AccelerationStructure(elements) => is_some(elements, index),
+            DescriptorBindingResources::Mutable(elements) => is_some(elements, index),
+        }
+    }
+
+    /// Returns an iterator over the array elements of the binding that haven't been written.
+    #[inline]
+    pub fn unwritten(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.count()).filter(move |&index| !self.is_written(index))
+    }
+
+    /// Copies `count` resources of `src` starting at `src_first`, to the resources of `self`
+    /// starting at `dst_first`.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the resource types do not match.
+    /// - Panics if the copy goes out of bounds of either binding.
+    pub fn copy_from(
+        &mut self,
+        dst_first: u32,
+        src: &DescriptorBindingResources,
+        src_first: u32,
+        count: u32,
+    ) {
+        fn copy_resources<T: Clone>(
+            dst: &mut [Option<T>],
+            dst_first: usize,
+            src: &[Option<T>],
+            src_first: usize,
+            count: usize,
+        ) {
+            let src = src
+                .get(src_first..src_first + count)
+                .expect("descriptor copy out of bounds of the source binding");
+            dst.get_mut(dst_first..dst_first + count)
+                .expect("descriptor copy out of bounds of the destination binding")
+                .clone_from_slice(src);
+        }
+
+        let (dst_first, src_first, count) =
+            (dst_first as usize, src_first as usize, count as usize);
+
+        match (self, src) {
+            (DescriptorBindingResources::Buffer(dst), DescriptorBindingResources::Buffer(src)) => {
+                copy_resources(dst, dst_first, src, src_first, count)
+            }
+            (
+                DescriptorBindingResources::BufferView(dst),
+                DescriptorBindingResources::BufferView(src),
+            ) => copy_resources(dst, dst_first, src, src_first, count),
+            (
+                DescriptorBindingResources::ImageView(dst),
+                DescriptorBindingResources::ImageView(src),
+            ) => copy_resources(dst, dst_first, src, src_first, count),
+            (
+                DescriptorBindingResources::ImageViewSampler(dst),
+                DescriptorBindingResources::ImageViewSampler(src),
+            ) => copy_resources(dst, dst_first, src, src_first, count),
+            (
+                DescriptorBindingResources::Sampler(dst),
+                DescriptorBindingResources::Sampler(src),
+            ) => copy_resources(dst, dst_first, src, src_first, count),
+            (
+                DescriptorBindingResources::AccelerationStructure(dst),
+                DescriptorBindingResources::AccelerationStructure(src),
+            ) => copy_resources(dst, dst_first, src, src_first, count),
+            (
+                DescriptorBindingResources::Mutable(dst),
+                DescriptorBindingResources::Mutable(src),
+            ) => copy_resources(dst, dst_first, src, src_first, count),
+            (DescriptorBindingResources::None, DescriptorBindingResources::None) => (),
+            _ => panic!("descriptor copy has wrong resource type"),
+        }
+    }
+
+    fn check_write(&self, write: &DescriptorWrite) -> Result<(), DescriptorWriteError> {
+        let provided = write.elements().resource_type();
+        let expected = match (self, write.mutable_type()) {
+            (DescriptorBindingResources::Mutable(_), Some(ty)) => {
+                Some(DescriptorResourceType::for_mutable_type(ty).ok_or(
+                    DescriptorWriteError::MutableTypeNotAllowed {
+                        binding: write.binding_num,
+                        ty,
+                    },
+                )?)
+            }
+            (DescriptorBindingResources::Mutable(_), None) => {
+                return Err(DescriptorWriteError::MutableTypeMissing {
+                    binding: write.binding_num,
+                });
+            }
+            (_, Some(_)) => {
+                return Err(DescriptorWriteError::MutableTypeUnexpected {
+                    binding: write.binding_num,
+                });
+            }
+            (_, None) => self.resource_type(),
+        };
+
+        if expected != Some(provided) {
+            return Err(DescriptorWriteError::WrongResourceType {
+                binding: write.binding_num,
+                expected,
+                provided,
+            });
+        }
+
+        let count = self.count();
+        let range = write.first_array_element()
+            ..write
+                .first_array_element()
+                .saturating_add(write.elements().len());
+
+        if range.end > count {
+            return Err(DescriptorWriteError::OutOfBounds {
+                binding: write.binding_num,
+                range,
+                count,
+            });
+        }
+
+        Ok(())
+    }
+
+    // The write must have been checked with `check_write`.
+    fn write_unchecked(&mut self, write: &DescriptorWrite) {
         fn write_resources<T: Clone>(first: usize, resources: &mut [Option<T>], elements: &[T]) {
-            resources
-                .get_mut(first..first + elements.len())
-                .expect("descriptor write for binding out of bounds")
+            resources[first..first + elements.len()]
                 .iter_mut()
                 .zip(elements)
                 .for_each(|(resource, element)| {
@@ -136,6 +487,11 @@ impl DescriptorBindingResources {
 
         let first = write.first_array_element() as usize;
 
+        if let DescriptorWriteElements::None(_, count) = write.elements() {
+            self.clear(first..first + *count as usize);
+            return;
+        }
+
         match (self, write.elements()) {
             (
                 DescriptorBindingResources::Buffer(resources),
@@ -157,10 +513,724 @@ impl DescriptorBindingResources {
                 DescriptorBindingResources::Sampler(resources),
                 DescriptorWriteElements::Sampler(elements),
             ) => write_resources(first, resources, elements),
-            _ => panic!(
-                "descriptor write for binding {} has wrong resource type",
-                write.binding_num,
+            (
+                DescriptorBindingResources::AccelerationStructure(resources),
+                DescriptorWriteElements::AccelerationStructure(elements),
+            ) => write_resources(first, resources, elements),
+            (DescriptorBindingResources::Mutable(resources), elements) => {
+                let ty = write.mutable_type().unwrap();
+                let elements: SmallVec<[_; 1]> = match elements {
+                    DescriptorWriteElements::Buffer(elements) => elements
+                        .iter()
+                        .map(|e| (ty, DescriptorResource::Buffer(e.clone())))
+                        .collect(),
+                    DescriptorWriteElements::BufferView(elements) => elements
+                        .iter()
+                        .map(|e| (ty, DescriptorResource::BufferView(e.clone())))
+                        .collect(),
+                    DescriptorWriteElements::ImageView(elements) => elements
+                        .iter()
+                        .map(|e| (ty, DescriptorResource::ImageView(e.clone())))
+                        .collect(),
+                    DescriptorWriteElements::ImageViewSampler(elements) => elements
+                        .iter()
+                        .map(|(i, s)| {
+                            (
+                                ty,
+                                DescriptorResource::ImageViewSampler(i.clone(), s.clone()),
+                            )
+                        })
+                        .collect(),
+                    DescriptorWriteElements::Sampler(elements) => elements
+                        .iter()
+                        .map(|e| (ty, DescriptorResource::Sampler(e.clone())))
+                        .collect(),
+                    DescriptorWriteElements::AccelerationStructure(elements) => elements
+                        .iter()
+                        .map(|e| (ty, DescriptorResource::AccelerationStructure(e.clone())))
+                        .collect(),
+                    DescriptorWriteElements::None(_, _) => unreachable!(),
+                };
+                write_resources(first, resources, &elements)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // Resets the array elements in `range` to null, for null descriptor writes.
+    fn clear(&mut self, range: Range<usize>) {
+        fn clear_resources<T>(resources: &mut [Option<T>], range: Range<usize>) {
+            resources[range]
+                .iter_mut()
+                .for_each(|resource| *resource = None);
+        }
+
+        match self {
+            DescriptorBindingResources::None => (),
+            DescriptorBindingResources::Buffer(resources) => clear_resources(resources, range),
+            DescriptorBindingResources::BufferView(resources) => clear_resources(resources, range),
+            DescriptorBindingResources::ImageView(resources) => clear_resources(resources, range),
+            DescriptorBindingResources::ImageViewSampler(resources) => {
+                clear_resources(resources, range)
+            }
+            DescriptorBindingResources::Sampler(resources) => clear_resources(resources, range),
+            DescriptorBindingResources::AccelerationStructure(resources) => {
+                clear_resources(resources, range)
+            }
+            DescriptorBindingResources::Mutable(resources) => clear_resources(resources, range),
+        }
+    }
+}
+
+/// Prints the resources of a descriptor set. Returned by [`DescriptorSetResources::dump`].
+#[derive(Clone, Copy)]
+pub struct DescriptorSetResourcesDump<'a> {
+    resources: &'a DescriptorSetResources,
+    layout: &'a DescriptorSetLayout,
+}
+
+impl<'a> fmt::Debug for DescriptorSetResourcesDump<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        struct Binding<'a> {
+            binding: u32,
+            ty: Option<DescriptorType>,
+            descriptor_count: u32,
+            resources: &'a DescriptorBindingResources,
+        }
+
+        impl<'a> fmt::Debug for Binding<'a> {
+            fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+                fmt.debug_struct("Binding")
+                    .field("binding", &self.binding)
+                    .field("ty", &self.ty)
+                    .field("descriptor_count", &self.descriptor_count)
+                    .field(
+                        "unwritten",
+                        &self.resources.unwritten().collect::<SmallVec<[_; 8]>>(),
+                    )
+                    .field("resources", &ElementsDump(self.resources))
+                    .finish()
+            }
+        }
+
+        let bindings = self.layout.desc().bindings();
+
+        fmt.debug_list()
+            .entries(self.resources.iter().map(|(binding, resources)| {
+                let desc = bindings.get(binding as usize).and_then(|b| b.as_ref());
+
+                Binding {
+                    binding,
+                    ty: desc.map(|desc| desc.ty),
+                    descriptor_count: resources.count(),
+                    resources,
+                }
+            }))
+            .finish()
+    }
+}
+
+impl<'a> fmt::Display for DescriptorSetResourcesDump<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let bindings = self.layout.desc().bindings();
+
+        for (binding, resources) in self.resources.iter() {
+            let desc = bindings.get(binding as usize).and_then(|b| b.as_ref());
+
+            match desc {
+                Some(desc) if desc.variable_count => write!(
+                    fmt,
+                    "binding {}: {:?}[{} (variable)]",
+                    binding,
+                    desc.ty,
+                    resources.count()
+                )?,
+                Some(desc) => write!(
+                    fmt,
+                    "binding {}: {:?}[{}]",
+                    binding, desc.ty, desc.descriptor_count
+                )?,
+                None => write!(fmt, "binding {}: [{}]", binding, resources.count())?,
+            }
+
+            if let DescriptorBindingResources::None = resources {
+                writeln!(fmt, ", immutable samplers only")?;
+                continue;
+            }
+
+            let unwritten: SmallVec<[_; 8]> = resources.unwritten().collect();
+
+            if unwritten.is_empty() {
+                writeln!(fmt)?;
+            } else {
+                writeln!(fmt, ", unwritten: {:?}", unwritten)?;
+            }
+
+            for index in 0..resources.count() {
+                if let Some(element) = element_dump(resources, index) {
+                    writeln!(fmt, "    [{}] {}", index, element)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Prints the bound elements of a binding, as a map from array index to handles.
+struct ElementsDump<'a>(&'a DescriptorBindingResources);
+
+impl<'a> fmt::Debug for ElementsDump<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_map()
+            .entries((0..self.0.count()).filter_map(|index| {
+                element_dump(self.0, index).map(|element| (index, DisplayAsDebug(element)))
+            }))
+            .finish()
+    }
+}
+
+struct DisplayAsDebug(String);
+
+impl fmt::Debug for DisplayAsDebug {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.write_str(&self.0)
+    }
+}
+
+// Returns a description of the element `index` of the binding with the Vulkan handles of the
+// bound objects, or `None` if the element has not been written.
+fn element_dump(resources: &DescriptorBindingResources, index: u32) -> Option<String> {
+    fn buffer_dump(buffer: &Arc<dyn BufferAccess>) -> String {
+        let inner = buffer.inner();
+        format!(
+            "Buffer({:?}, offset {}, size {})",
+            inner.buffer.internal_object(),
+            inner.offset,
+            buffer.size()
+        )
+    }
+
+    fn buffer_view_dump(view: &Arc<dyn BufferViewAbstract>) -> String {
+        format!("BufferView({:?})", view.inner())
+    }
+
+    fn image_view_dump(view: &Arc<dyn ImageViewAbstract>) -> String {
+        format!("ImageView({:?})", view.inner().internal_object())
+    }
+
+    fn image_view_sampler_dump(
+        (view, sampler): &(Arc<dyn ImageViewAbstract>, Arc<Sampler>),
+    ) -> String {
+        format!("{}, {}", image_view_dump(view), sampler_dump(sampler))
+    }
+
+    fn sampler_dump(sampler: &Arc<Sampler>) -> String {
+        format!("Sampler({:?})", sampler.internal_object())
+    }
+
+    fn acceleration_structure_dump(acceleration_structure: &Arc<AccelerationStructure>) -> String {
+        format!(
+            "AccelerationStructure({:?})",
+            acceleration_structure.internal_object()
+        )
+    }
+
+    fn get<T>(elements: &[Option<T>], index: u32) -> Option<&T> {
+        elements.get(index as usize)?.as_ref()
+    }
+
+    Some(match resources {
+        DescriptorBindingResources::None => return None,
+        DescriptorBindingResources::Buffer(elements) => buffer_dump(get(elements, index)?),
+        DescriptorBindingResources::BufferView(elements) => buffer_view_dump(get(elements, index)?),
+        DescriptorBindingResources::ImageView(elements) => image_view_dump(get(elements, index)?),
+        DescriptorBindingResources::ImageViewSampler(elements) => {
+            image_view_sampler_dump(get(elements, index)?)
+        }
+        DescriptorBindingResources::Sampler(elements) => sampler_dump(get(elements, index)?),
+        DescriptorBindingResources::AccelerationStructure(elements) => {
+            acceleration_structure_dump(get(elements, index)?)
+        }
+        DescriptorBindingResources::Mutable(elements) => {
+            let (ty, resource) = get(elements, index)?;
+            let resource = match resource {
+                DescriptorResource::Buffer(buffer) => buffer_dump(buffer),
+                DescriptorResource::BufferView(view) => buffer_view_dump(view),
+                DescriptorResource::ImageView(view) => image_view_dump(view),
+                DescriptorResource::ImageViewSampler(view, sampler) => {
+                    format!("{}, {}", image_view_dump(view), sampler_dump(sampler))
+                }
+                DescriptorResource::Sampler(sampler) => sampler_dump(sampler),
+                DescriptorResource::AccelerationStructure(acceleration_structure) => {
+                    acceleration_structure_dump(acceleration_structure)
+                }
+            };
+            format!("{:?}: {}", ty, resource)
+        }
+    })
+}
+
+/// The type of resources held by a descriptor write or a descriptor set binding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DescriptorResourceType {
+    Buffer,
+    BufferView,
+    ImageView,
+    ImageViewSampler,
+    Sampler,
+    AccelerationStructure,
+    /// The resources of a `Mutable` binding, which can each be of any other type.
+    Mutable,
+}
+
+impl DescriptorResourceType {
+    // Returns the type of resources that are written to a descriptor of type `ty`.
+    pub(crate) fn for_descriptor_type(ty: DescriptorType) -> DescriptorResourceType {
+        match ty {
+            DescriptorType::UniformBuffer
+            | DescriptorType::StorageBuffer
+            | DescriptorType::UniformBufferDynamic
+            | DescriptorType::StorageBufferDynamic => Self::Buffer,
+            DescriptorType::UniformTexelBuffer | DescriptorType::StorageTexelBuffer => {
+                Self::BufferView
+            }
+            DescriptorType::SampledImage
+            | DescriptorType::StorageImage
+            | DescriptorType::InputAttachment => Self::ImageView,
+            DescriptorType::CombinedImageSampler => Self::ImageViewSampler,
+            DescriptorType::Sampler => Self::Sampler,
+            DescriptorType::AccelerationStructure => Self::AccelerationStructure,
+            DescriptorType::Mutable => Self::Mutable,
+        }
+    }
+
+    // Returns the type of resources that are written to a `Mutable` binding as descriptors of
+    // type `ty`, or `None` if `ty` can't be one of the mutable types of a binding.
+    fn for_mutable_type(ty: DescriptorType) -> Option<DescriptorResourceType> {
+        match ty {
+            DescriptorType::UniformBufferDynamic
+            | DescriptorType::StorageBufferDynamic
+            | DescriptorType::Mutable => None,
+            _ => Some(Self::for_descriptor_type(ty)),
+        }
+    }
+}
+
+impl fmt::Display for DescriptorResourceType {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                Self::Buffer => "buffer",
+                Self::BufferView => "buffer view",
+                Self::ImageView => "image view",
+                Self::ImageViewSampler => "image view and sampler",
+                Self::Sampler => "sampler",
+                Self::AccelerationStructure => "acceleration structure",
+                Self::Mutable => "mutable",
+            }
+        )
+    }
+}
+
+/// Error that can be returned when a descriptor write is not valid for a descriptor set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DescriptorWriteError {
+    /// The binding number of the write does not exist in the descriptor set.
+    InvalidBinding { binding: u32 },
+
+    /// The write is for a `Mutable` binding, but no mutable type was given.
+    MutableTypeMissing { binding: u32 },
+
+    /// The mutable type of the write is not one of the mutable types of the binding.
+    MutableTypeNotAllowed { binding: u32, ty: DescriptorType },
+
+    /// The write has a mutable type, but the binding is not `Mutable`.
+    MutableTypeUnexpected { binding: u32 },
+
+    /// The array elements written to are out of the bounds of the binding.
+    OutOfBounds {
+        binding: u32,
+        /// The range of array elements that was written to.
+        range: Range<u32>,
+        /// The number of descriptors in the binding.
+        count: u32,
+    },
+
+    /// The write provides samplers to a `CombinedImageSampler` binding, but the samplers of the
+    /// binding are immutable. Only image views must be written.
+    SamplerIsImmutable { binding: u32 },
+
+    /// The type of resources of the write does not match the binding.
+    WrongResourceType {
+        binding: u32,
+        /// The type of resources held by the binding, or `None` if the binding holds no
+        /// resources because all of its descriptors are immutable samplers.
+        expected: Option<DescriptorResourceType>,
+        provided: DescriptorResourceType,
+    },
+
+    /// The write contains null descriptors, but the `null_descriptor` feature is not enabled.
+    NullDescriptorNotEnabled { binding: u32 },
+}
+
+impl error::Error for DescriptorWriteError {}
+
+impl fmt::Display for DescriptorWriteError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Self::InvalidBinding { binding } => {
+                write!(fmt, "descriptor write has invalid binding number {}", binding)
+            }
+            Self::MutableTypeMissing { binding } => write!(
+                fmt,
+                "descriptor write for mutable binding {} has no mutable type",
+                binding,
+            ),
+            Self::MutableTypeNotAllowed { binding, ty } => write!(
+                fmt,
+                "descriptor write for binding {} has mutable type {:?}, which is not one of the mutable types of the binding",
+                binding, ty,
+            ),
+            Self::MutableTypeUnexpected { binding } => write!(
+                fmt,
+                "descriptor write for binding {} has a mutable type, but the binding is not mutable",
+                binding,
+            ),
+            Self::OutOfBounds {
+                binding,
+                range,
+                count,
+            } => write!(
+                fmt,
+                "descriptor write for binding {} writes to elements {}..{}, but the binding only has {} descriptors",
+                binding, range.start, range.end, count,
+            ),
+            Self::SamplerIsImmutable { binding } => write!(
+                fmt,
+                "descriptor write for binding {} provides samplers, but the samplers of the binding are immutable; only image views must be written",
+                binding,
+            ),
+            Self::WrongResourceType {
+                binding,
+                expected: Some(expected),
+                provided,
+            } => write!(
+                fmt,
+                "descriptor write for binding {} provides {} resources, but the binding expects {} resources",
+                binding, provided, expected,
+            ),
+            Self::WrongResourceType {
+                binding,
+                expected: None,
+                provided,
+            } => write!(
+                fmt,
+                "descriptor write for binding {} provides {} resources, but the binding only has immutable samplers",
+                binding, provided,
+            ),
+            Self::NullDescriptorNotEnabled { binding } => write!(
+                fmt,
+                "descriptor write for binding {} contains null descriptors, but the `null_descriptor` feature is not enabled",
+                binding,
             ),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        DescriptorBindingResources, DescriptorResourceType, DescriptorSetResources,
+        DescriptorWriteError,
+    };
+    use crate::descriptor_set::layout::{
+        DescriptorDesc, DescriptorSetDesc, DescriptorSetLayout, DescriptorType,
+    };
+    use crate::descriptor_set::sys::DescriptorWrite;
+    use crate::format::Format;
+    use crate::image::view::ImageView;
+    use crate::image::AttachmentImage;
+    use crate::sampler::Sampler;
+    use crate::shader::ShaderStages;
+    use smallvec::smallvec;
+    use std::iter;
+    use std::sync::Arc;
+
+    #[test]
+    fn try_update_out_of_bounds() {
+        let (device, _) = gfx_dev_and_queue!();
+        let sampler = Sampler::simple_repeat_linear(device);
+        let mut resources = DescriptorBindingResources::Sampler(smallvec![None; 2]);
+
+        let write = unsafe { DescriptorWrite::sampler(0, 1, [sampler.clone(), sampler]) };
+        assert_eq!(
+            resources.try_update(&write),
+            Err(DescriptorWriteError::OutOfBounds {
+                binding: 0,
+                range: 1..3,
+                count: 2,
+            }),
+        );
+    }
+
+    #[test]
+    fn try_update_wrong_resource_type() {
+        let (device, _) = gfx_dev_and_queue!();
+        let sampler = Sampler::simple_repeat_linear(device);
+        let mut resources = DescriptorBindingResources::None;
+
+        let write = unsafe { DescriptorWrite::sampler(3, 0, [sampler]) };
+        assert_eq!(
+            resources.try_update(&write),
+            Err(DescriptorWriteError::WrongResourceType {
+                binding: 3,
+                expected: None,
+                provided: DescriptorResourceType::Sampler,
+            }),
+        );
+    }
+
+    #[test]
+    fn try_update_null() {
+        let mut resources = DescriptorBindingResources::BufferView(smallvec![None; 3]);
+
+        let write = unsafe { DescriptorWrite::none(0, 1, DescriptorResourceType::BufferView, 2) };
+        assert_eq!(resources.try_update(&write), Ok(()));
+        assert_eq!(resources.unwritten().count(), 3);
+
+        let write = unsafe { DescriptorWrite::none(0, 2, DescriptorResourceType::BufferView, 2) };
+        assert_eq!(
+            resources.try_update(&write),
+            Err(DescriptorWriteError::OutOfBounds {
+                binding: 0,
+                range: 2..4,
+                count: 3,
+            }),
+        );
+
+        let write = unsafe { DescriptorWrite::none(0, 0, DescriptorResourceType::Buffer, 1) };
+        assert_eq!(
+            resources.try_update(&write),
+            Err(DescriptorWriteError::WrongResourceType {
+                binding: 0,
+                expected: Some(DescriptorResourceType::BufferView),
+                provided: DescriptorResourceType::Buffer,
+            }),
+        );
+    }
+
+    #[test]
+    fn copy_from() {
+        let (device, _) = gfx_dev_and_queue!();
+        let sampler = Sampler::simple_repeat_linear(device);
+        let src = DescriptorBindingResources::Sampler(smallvec![None, Some(sampler)]);
+        let mut dst = DescriptorBindingResources::Sampler(smallvec![None; 3]);
+
+        dst.copy_from(2, &src, 1, 1);
+
+        match dst {
+            DescriptorBindingResources::Sampler(elements) => {
+                assert!(elements[0].is_none());
+                assert!(elements[1].is_none());
+                assert!(elements[2].is_some());
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn binding_desc(descriptor_count: u32) -> DescriptorDesc {
+        DescriptorDesc {
+            ty: DescriptorType::Sampler,
+            descriptor_count,
+            variable_count: false,
+            update_after_bind: false,
+            update_unused_while_pending: false,
+            partially_bound: false,
+            stages: ShaderStages::all(),
+            immutable_samplers: Vec::new(),
+            mutable_types: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn inline_small_layout() {
+        let (device, _) = gfx_dev_and_queue!();
+        let layout = DescriptorSetLayout::new(
+            device,
+            DescriptorSetDesc::new([Some(binding_desc(1)), None, Some(binding_desc(1))]),
+        )
+        .unwrap();
+
+        let resources = DescriptorSetResources::new(&layout, 0);
+        assert!(!resources.descriptors.spilled());
+        assert_eq!(resources.iter().map(|(b, _)| b).collect::<Vec<_>>(), [0, 2]);
+        assert!(resources.binding(1).is_none());
+        assert!(resources.binding(3).is_none());
+
+        match resources.binding(2).unwrap() {
+            DescriptorBindingResources::Sampler(elements) => assert!(!elements.spilled()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn more_than_four_bindings() {
+        let (device, _) = gfx_dev_and_queue!();
+        let sampler = Sampler::simple_repeat_linear(device.clone());
+        let layout = DescriptorSetLayout::new(
+            device,
+            DescriptorSetDesc::new(
+                (0..6).map(|b| if b == 4 { None } else { Some(binding_desc(1)) }),
+            ),
+        )
+        .unwrap();
+
+        let mut resources = DescriptorSetResources::new(&layout, 0);
+        assert_eq!(
+            resources.iter().map(|(b, _)| b).collect::<Vec<_>>(),
+            [0, 1, 2, 3, 5]
+        );
+        assert!(resources.binding(4).is_none());
+        assert!(resources.binding(6).is_none());
+
+        resources.update([&unsafe { DescriptorWrite::sampler(5, 0, [sampler.clone()]) }]);
+        assert_eq!(resources.binding(5).unwrap().unwritten().count(), 0);
+        assert_eq!(resources.binding(3).unwrap().unwritten().count(), 1);
+        assert_eq!(
+            resources.try_update([&unsafe { DescriptorWrite::sampler(4, 0, [sampler]) }]),
+            Err(DescriptorWriteError::InvalidBinding { binding: 4 }),
+        );
+    }
+
+    #[test]
+    fn array_binding() {
+        let (device, _) = gfx_dev_and_queue!();
+        let sampler = Sampler::simple_repeat_linear(device.clone());
+        let layout = DescriptorSetLayout::new(
+            device,
+            DescriptorSetDesc::new([Some(binding_desc(1)), Some(binding_desc(8))]),
+        )
+        .unwrap();
+
+        let mut resources = DescriptorSetResources::new(&layout, 0);
+        assert_eq!(resources.binding(1).unwrap().count(), 8);
+
+        resources.update([&unsafe {
+            DescriptorWrite::sampler(1, 5, [sampler.clone(), sampler.clone(), sampler])
+        }]);
+        assert_eq!(
+            resources
+                .binding(1)
+                .unwrap()
+                .unwritten()
+                .collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4]
+        );
+        assert_eq!(resources.binding(0).unwrap().unwritten().count(), 1);
+    }
+
+    fn image_binding_desc(
+        ty: DescriptorType,
+        immutable_samplers: Vec<Arc<Sampler>>,
+    ) -> DescriptorDesc {
+        DescriptorDesc {
+            ty,
+            descriptor_count: 1,
+            variable_count: false,
+            update_after_bind: false,
+            update_unused_while_pending: false,
+            partially_bound: false,
+            stages: ShaderStages::all(),
+            immutable_samplers,
+            mutable_types: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn sampler_written_to_image_bindings() {
+        let (device, _) = gfx_dev_and_queue!();
+        let sampler = Sampler::simple_repeat_linear(device.clone());
+        let image_view = ImageView::new(
+            AttachmentImage::sampled(device.clone(), [1, 1], Format::R8G8B8A8_UNORM).unwrap(),
+        )
+        .unwrap();
+        let layout = DescriptorSetLayout::new(
+            device,
+            DescriptorSetDesc::new([
+                Some(image_binding_desc(DescriptorType::SampledImage, Vec::new())),
+                Some(image_binding_desc(
+                    DescriptorType::CombinedImageSampler,
+                    vec![sampler.clone()],
+                )),
+            ]),
+        )
+        .unwrap();
+        let mut resources = DescriptorSetResources::new(&layout, 0);
+
+        // The binding doesn't hold samplers at all.
+        assert_eq!(
+            resources.try_update([&unsafe {
+                DescriptorWrite::image_view_sampler(
+                    0,
+                    0,
+                    [(image_view.clone() as _, sampler.clone())],
+                )
+            }]),
+            Err(DescriptorWriteError::WrongResourceType {
+                binding: 0,
+                expected: Some(DescriptorResourceType::ImageView),
+                provided: DescriptorResourceType::ImageViewSampler,
+            }),
+        );
+
+        // The binding holds samplers, but they are immutable.
+        assert_eq!(
+            resources.try_update([&unsafe {
+                DescriptorWrite::image_view_sampler(1, 0, [(image_view.clone() as _, sampler)])
+            }]),
+            Err(DescriptorWriteError::SamplerIsImmutable { binding: 1 }),
+        );
+
+        resources.update([&unsafe {
+            DescriptorWrite::image_view_with_immutable_sampler(1, 0, [image_view as _])
+        }]);
+        assert_eq!(resources.binding(1).unwrap().unwritten().count(), 0);
+    }
+
+    #[test]
+    fn dump_unwritten() {
+        let (device, _) = gfx_dev_and_queue!();
+        let sampler = Sampler::simple_repeat_linear(device.clone());
+
+        let layout = DescriptorSetLayout::new(
+            device,
+            DescriptorSetDesc::new(iter::once(Some(DescriptorDesc {
+                ty: DescriptorType::Sampler,
+                descriptor_count: 3,
+                variable_count: false,
+                update_after_bind: false,
+                update_unused_while_pending: false,
+                partially_bound: false,
+                stages: ShaderStages::all(),
+                immutable_samplers: Vec::new(),
+                mutable_types: Vec::new(),
+            }))),
+        )
+        .unwrap();
+
+        let mut resources = DescriptorSetResources::new(&layout, 0);
+        resources.update([&unsafe { DescriptorWrite::sampler(0, 1, [sampler]) }]);
+
+        let (binding, binding_resources) = resources.iter().next().unwrap();
+        assert_eq!(binding, 0);
+        assert_eq!(binding_resources.unwritten().collect::<Vec<_>>(), [0, 2]);
+
+        let dump = resources.dump(&layout).to_string();
+        assert!(dump.starts_with("binding 0: Sampler[3], unwritten: [0, 2]\n    [1] Sampler("));
+    }
+}
@@ -8,19 +8,22 @@
 // according to those terms.
 
 use super::resources::DescriptorSetResources;
+use crate::acceleration_structure::AccelerationStructure;
 use crate::buffer::BufferView;
 use crate::descriptor_set::builder::DescriptorSetBuilder;
 use crate::descriptor_set::layout::DescriptorSetLayout;
 use crate::descriptor_set::pool::{
-    DescriptorPoolAlloc, DescriptorPoolAllocError, DescriptorSetAllocateInfo, UnsafeDescriptorPool,
+    DescriptorPoolAlloc, DescriptorPoolAllocError, DescriptorSetAllocateInfo, DescriptorsCount,
+    UnsafeDescriptorPool,
 };
 use crate::descriptor_set::{BufferAccess, DescriptorSet, DescriptorSetError, UnsafeDescriptorSet};
 use crate::device::{Device, DeviceOwned};
 use crate::image::ImageViewAbstract;
 use crate::sampler::Sampler;
-use crate::OomError;
 use crate::VulkanObject;
 use crossbeam_queue::SegQueue;
+use parking_lot::Mutex;
+use std::cmp;
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 
@@ -47,7 +50,8 @@ impl SingleLayoutDescSetPool {
     ///
     /// - Panics if the provided `layout` is for push descriptors rather than regular descriptor
     ///   sets.
-    /// - Panics if the provided `layout` has a binding with a variable descriptor count.
+    /// - Panics if the provided `layout` has a binding with a variable descriptor count. Use
+    ///   [`SingleLayoutVariableDescSetPool`] for these layouts instead.
     pub fn new(layout: Arc<DescriptorSetLayout>) -> Self {
         assert!(
             !layout.desc().is_push_descriptor(),
@@ -78,7 +82,7 @@ impl SingleLayoutDescSetPool {
         }
     }
 
-    fn next_alloc(&mut self) -> Result<SingleLayoutPoolAlloc, OomError> {
+    fn next_alloc(&mut self) -> Result<SingleLayoutPoolAlloc, DescriptorPoolAllocError> {
         loop {
             let mut not_enough_sets = false;
 
@@ -103,34 +107,24 @@ impl SingleLayoutDescSetPool {
                 &count,
                 self.set_count as u32,
                 false,
+                self.layout.desc().is_update_after_bind(),
             )?;
 
+            // The pool is created with exactly the right capacity, but the implementation may
+            // still need more room than what we have computed from the layout.
             let reserve = unsafe {
-                match unsafe_pool.alloc((0..self.set_count).map(|_| DescriptorSetAllocateInfo {
-                    layout: self.layout.as_ref(),
-                    variable_descriptor_count: 0,
-                })) {
-                    Ok(alloc_iter) => {
-                        let reserve = SegQueue::new();
-
-                        for alloc in alloc_iter {
-                            reserve.push(alloc);
-                        }
-
-                        reserve
-                    }
-                    Err(DescriptorPoolAllocError::OutOfHostMemory) => {
-                        return Err(OomError::OutOfHostMemory);
-                    }
-                    Err(DescriptorPoolAllocError::OutOfDeviceMemory) => {
-                        return Err(OomError::OutOfDeviceMemory);
-                    }
-                    Err(DescriptorPoolAllocError::FragmentedPool) => {
-                        // This can't happen as we don't free individual sets.
-                        unreachable!()
-                    }
-                    Err(DescriptorPoolAllocError::OutOfPoolMemory) => unreachable!(),
+                let alloc_iter =
+                    unsafe_pool.alloc((0..self.set_count).map(|_| DescriptorSetAllocateInfo {
+                        layout: self.layout.as_ref(),
+                        variable_descriptor_count: 0,
+                    }))?;
+                let reserve = SegQueue::new();
+
+                for alloc in alloc_iter {
+                    reserve.push(alloc);
                 }
+
+                reserve
             };
 
             self.inner = Some(Arc::new(SingleLayoutPool {
@@ -330,6 +324,19 @@ impl<'a> SingleLayoutDescSetBuilder<'a> {
         Ok(self)
     }
 
+    /// Binds an acceleration structure as the next descriptor.
+    ///
+    /// An error is returned if the acceleration structure isn't compatible with the descriptor.
+    #[inline]
+    pub fn add_acceleration_structure(
+        &mut self,
+        acceleration_structure: Arc<AccelerationStructure>,
+    ) -> Result<&mut Self, DescriptorSetError> {
+        self.inner
+            .add_acceleration_structure(acceleration_structure)?;
+        Ok(self)
+    }
+
     /// Builds a `SingleLayoutDescSet` from the builder.
     pub fn build(self) -> Result<Arc<SingleLayoutDescSet>, DescriptorSetError> {
         let writes = self.inner.build()?;
@@ -349,3 +356,513 @@ impl<'a> SingleLayoutDescSetBuilder<'a> {
         }))
     }
 }
+
+// The number of sets that are allocated from each Vulkan pool of a
+// `SingleLayoutVariableDescSetPool`.
+const MAX_VARIABLE_SETS: u32 = 32;
+
+/// Much like [`SingleLayoutDescSetPool`], except that it allows you to allocate descriptor sets
+/// with a variable descriptor count, for example for bindless texture arrays.
+///
+/// As the sets can have different variable descriptor counts, they cannot be reused once they
+/// are dropped. Instead, each Vulkan pool has room for a fixed number of sets, and is destroyed
+/// once all the sets that were allocated from it are dropped.
+///
+/// The descriptors of the variable-count binding are shared by all the sets of a Vulkan pool,
+/// instead of reserving the maximum count of the layout for each set. The first pool has room
+/// for the variable descriptor count of the first set, and each following pool has room for
+/// twice as many descriptors as the previous one, or for the requested count if it is larger.
+/// The variable-count binding of bindless layouts can have a very large maximum count, which is
+/// never reserved unless sets actually need it.
+pub struct SingleLayoutVariableDescSetPool {
+    // The Vulkan pool that sets are currently allocated from. It is replaced with a new one when
+    // it is full.
+    inner: Option<Arc<SingleLayoutVariablePool>>,
+    // The number of sets that can still be allocated from `inner`.
+    remaining_sets: u32,
+    // The number of variable-count descriptors that can still be allocated from `inner`.
+    remaining_variable_descriptors: u32,
+    // The number of variable-count descriptors that `inner` was created with.
+    variable_capacity: u32,
+    // The Vulkan device.
+    device: Arc<Device>,
+    // The descriptor layout that this pool is for.
+    layout: Arc<DescriptorSetLayout>,
+}
+
+impl SingleLayoutVariableDescSetPool {
+    /// Initializes a new pool. The pool is configured to allocate sets that corresponds to the
+    /// parameters passed to this function.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if the provided `layout` is for push descriptors rather than regular descriptor
+    ///   sets.
+    pub fn new(layout: Arc<DescriptorSetLayout>) -> Self {
+        assert!(
+            !layout.desc().is_push_descriptor(),
+            "the provided descriptor set layout is for push descriptors, and cannot be used to build a descriptor set object"
+        );
+
+        Self {
+            inner: None,
+            remaining_sets: 0,
+            remaining_variable_descriptors: 0,
+            variable_capacity: 0,
+            device: layout.device().clone(),
+            layout,
+        }
+    }
+
+    /// Starts the process of building a new descriptor set.
+    ///
+    /// The set will corresponds to the set layout that was passed to `new`. The variable
+    /// descriptor count of the set is the number of descriptors that are added to the
+    /// variable-count binding.
+    pub fn next(&mut self) -> SingleLayoutVariableDescSetBuilder {
+        let layout = self.layout.clone();
+
+        SingleLayoutVariableDescSetBuilder {
+            pool: self,
+            inner: DescriptorSetBuilder::start(layout),
+        }
+    }
+
+    fn next_alloc(
+        &mut self,
+        variable_descriptor_count: u32,
+    ) -> Result<SingleLayoutVariablePoolAlloc, DescriptorPoolAllocError> {
+        let mut new_pool = false;
+
+        if self.inner.is_none()
+            || self.remaining_sets == 0
+            || self.remaining_variable_descriptors < variable_descriptor_count
+        {
+            self.create_pool(variable_descriptor_count)?;
+            new_pool = true;
+        }
+
+        // The allocation borrows the layout while `create_pool` may be called.
+        let layout = self.layout.clone();
+
+        loop {
+            let pool = self.inner.clone().unwrap();
+            let result = unsafe {
+                pool.inner
+                    .lock()
+                    .alloc(std::iter::once(DescriptorSetAllocateInfo {
+                        layout: layout.as_ref(),
+                        variable_descriptor_count,
+                    }))
+            };
+
+            match result {
+                Ok(mut alloc_iter) => {
+                    self.remaining_sets -= 1;
+                    self.remaining_variable_descriptors -= variable_descriptor_count;
+
+                    return Ok(SingleLayoutVariablePoolAlloc {
+                        _pool: pool,
+                        inner: alloc_iter.next().unwrap(),
+                    });
+                }
+                // The implementation may need more room than what we have computed from the
+                // layout. Try again once with a new pool.
+                Err(DescriptorPoolAllocError::FragmentedPool)
+                | Err(DescriptorPoolAllocError::OutOfPoolMemory)
+                    if !new_pool =>
+                {
+                    self.create_pool(variable_descriptor_count)?;
+                    new_pool = true;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    // Replaces the current Vulkan pool with a new one that has room for at least one set with
+    // `variable_descriptor_count` variable-count descriptors.
+    fn create_pool(
+        &mut self,
+        variable_descriptor_count: u32,
+    ) -> Result<(), DescriptorPoolAllocError> {
+        let variable_binding = self
+            .layout
+            .desc()
+            .bindings()
+            .last()
+            .and_then(|binding| binding.as_ref())
+            .filter(|binding| binding.variable_count);
+
+        // The descriptor count of the variable-count binding in the layout is its maximum. It is
+        // replaced with the capacity of the new pool, and only the other bindings are reserved
+        // for each set.
+        let mut count = *self.layout.descriptors_count();
+        let variable_capacity = if let Some(binding) = variable_binding {
+            let mut variable_count = DescriptorsCount::zero();
+            variable_count.add_num(binding.ty, binding.descriptor_count);
+            count -= variable_count;
+            count *= MAX_VARIABLE_SETS;
+
+            let capacity = cmp::max(
+                variable_descriptor_count,
+                cmp::min(
+                    self.variable_capacity.saturating_mul(2),
+                    binding.descriptor_count.saturating_mul(MAX_VARIABLE_SETS),
+                ),
+            );
+            // A pool needs at least one descriptor.
+            let capacity = cmp::max(capacity, 1);
+            count.add_num(binding.ty, capacity);
+            capacity
+        } else {
+            count *= MAX_VARIABLE_SETS;
+            0
+        };
+
+        let unsafe_pool = UnsafeDescriptorPool::new(
+            self.device.clone(),
+            &count,
+            MAX_VARIABLE_SETS,
+            false,
+            self.layout.desc().is_update_after_bind(),
+        )?;
+
+        self.inner = Some(Arc::new(SingleLayoutVariablePool {
+            inner: Mutex::new(unsafe_pool),
+        }));
+        self.remaining_sets = MAX_VARIABLE_SETS;
+        self.remaining_variable_descriptors = variable_capacity;
+        self.variable_capacity = variable_capacity;
+
+        Ok(())
+    }
+}
+
+struct SingleLayoutVariablePool {
+    // The actual Vulkan descriptor pool. It is only locked when allocating, which requires
+    // exclusive access, while the sets that were allocated from it keep it alive.
+    inner: Mutex<UnsafeDescriptorPool>,
+}
+
+struct SingleLayoutVariablePoolAlloc {
+    // The `SingleLayoutVariablePool` were we allocated from. The set is freed when the pool is
+    // destroyed, after all of its sets are dropped.
+    _pool: Arc<SingleLayoutVariablePool>,
+
+    // The actual descriptor set.
+    inner: UnsafeDescriptorSet,
+}
+
+impl DescriptorPoolAlloc for SingleLayoutVariablePoolAlloc {
+    #[inline]
+    fn inner(&self) -> &UnsafeDescriptorSet {
+        &self.inner
+    }
+
+    #[inline]
+    fn inner_mut(&mut self) -> &mut UnsafeDescriptorSet {
+        &mut self.inner
+    }
+}
+
+/// A descriptor set created from a `SingleLayoutVariableDescSetPool`.
+pub struct SingleLayoutVariableDescSet {
+    alloc: SingleLayoutVariablePoolAlloc,
+    resources: DescriptorSetResources,
+    layout: Arc<DescriptorSetLayout>,
+}
+
+unsafe impl DescriptorSet for SingleLayoutVariableDescSet {
+    #[inline]
+    fn inner(&self) -> &UnsafeDescriptorSet {
+        self.alloc.inner()
+    }
+
+    #[inline]
+    fn layout(&self) -> &Arc<DescriptorSetLayout> {
+        &self.layout
+    }
+
+    #[inline]
+    fn resources(&self) -> &DescriptorSetResources {
+        &self.resources
+    }
+}
+
+unsafe impl DeviceOwned for SingleLayoutVariableDescSet {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.layout.device()
+    }
+}
+
+impl PartialEq for SingleLayoutVariableDescSet {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.inner().internal_object() == other.inner().internal_object()
+            && self.device() == other.device()
+    }
+}
+
+impl Eq for SingleLayoutVariableDescSet {}
+
+impl Hash for SingleLayoutVariableDescSet {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner().internal_object().hash(state);
+        self.device().hash(state);
+    }
+}
+
+/// Prototype of a `SingleLayoutVariableDescSet`.
+pub struct SingleLayoutVariableDescSetBuilder<'a> {
+    pool: &'a mut SingleLayoutVariableDescSetPool,
+    inner: DescriptorSetBuilder,
+}
+
+impl<'a> SingleLayoutVariableDescSetBuilder<'a> {
+    /// Call this function if the next element of the set is an array in order to set the value of
+    /// each element.
+    ///
+    /// Returns an error if the descriptor is empty, there are no remaining descriptors, or if the
+    /// builder is already in an error.
+    ///
+    /// This function can be called even if the descriptor isn't an array, and it is valid to enter
+    /// the "array", add one element, then leave.
+    #[inline]
+    pub fn enter_array(&mut self) -> Result<&mut Self, DescriptorSetError> {
+        self.inner.enter_array()?;
+        Ok(self)
+    }
+
+    /// Leaves the array. Call this once you added all the elements of the array.
+    ///
+    /// Returns an error if the array is missing elements, or if the builder is not in an array.
+    #[inline]
+    pub fn leave_array(&mut self) -> Result<&mut Self, DescriptorSetError> {
+        self.inner.leave_array()?;
+        Ok(self)
+    }
+
+    /// Skips the current descriptor if it is empty.
+    #[inline]
+    pub fn add_empty(&mut self) -> Result<&mut Self, DescriptorSetError> {
+        self.inner.add_empty()?;
+        Ok(self)
+    }
+
+    /// Binds a buffer as the next descriptor.
+    ///
+    /// An error is returned if the buffer isn't compatible with the descriptor.
+    #[inline]
+    pub fn add_buffer(
+        &mut self,
+        buffer: Arc<dyn BufferAccess>,
+    ) -> Result<&mut Self, DescriptorSetError> {
+        self.inner.add_buffer(buffer)?;
+        Ok(self)
+    }
+
+    /// Binds a buffer view as the next descriptor.
+    ///
+    /// An error is returned if the buffer isn't compatible with the descriptor.
+    #[inline]
+    pub fn add_buffer_view<B>(
+        &mut self,
+        view: Arc<BufferView<B>>,
+    ) -> Result<&mut Self, DescriptorSetError>
+    where
+        B: BufferAccess + 'static,
+    {
+        self.inner.add_buffer_view(view)?;
+        Ok(self)
+    }
+
+    /// Binds an image view as the next descriptor.
+    ///
+    /// An error is returned if the image view isn't compatible with the descriptor.
+    #[inline]
+    pub fn add_image(
+        &mut self,
+        image_view: Arc<dyn ImageViewAbstract + 'static>,
+    ) -> Result<&mut Self, DescriptorSetError> {
+        self.inner.add_image(image_view)?;
+        Ok(self)
+    }
+
+    /// Binds an image view with a sampler as the next descriptor.
+    ///
+    /// If the descriptor set layout contains immutable samplers for this descriptor, use
+    /// `add_image` instead.
+    ///
+    /// An error is returned if the image view isn't compatible with the descriptor.
+    #[inline]
+    pub fn add_sampled_image(
+        &mut self,
+        image_view: Arc<dyn ImageViewAbstract + 'static>,
+        sampler: Arc<Sampler>,
+    ) -> Result<&mut Self, DescriptorSetError> {
+        self.inner.add_sampled_image(image_view, sampler)?;
+        Ok(self)
+    }
+
+    /// Binds a sampler as the next descriptor.
+    ///
+    /// An error is returned if the sampler isn't compatible with the descriptor.
+    #[inline]
+    pub fn add_sampler(&mut self, sampler: Arc<Sampler>) -> Result<&mut Self, DescriptorSetError> {
+        self.inner.add_sampler(sampler)?;
+        Ok(self)
+    }
+
+    /// Binds an acceleration structure as the next descriptor.
+    ///
+    /// An error is returned if the acceleration structure isn't compatible with the descriptor.
+    #[inline]
+    pub fn add_acceleration_structure(
+        &mut self,
+        acceleration_structure: Arc<AccelerationStructure>,
+    ) -> Result<&mut Self, DescriptorSetError> {
+        self.inner
+            .add_acceleration_structure(acceleration_structure)?;
+        Ok(self)
+    }
+
+    /// Builds a `SingleLayoutVariableDescSet` from the builder.
+    pub fn build(self) -> Result<Arc<SingleLayoutVariableDescSet>, DescriptorSetError> {
+        let writes = self.inner.build()?;
+        let variable_descriptor_count = writes.variable_descriptor_count();
+        let mut alloc = self.pool.next_alloc(variable_descriptor_count)?;
+        let mut resources = DescriptorSetResources::new(writes.layout(), variable_descriptor_count);
+
+        unsafe {
+            alloc.inner_mut().write(writes.layout(), writes.writes());
+            resources.update(writes.writes());
+        }
+
+        Ok(Arc::new(SingleLayoutVariableDescSet {
+            alloc,
+            resources,
+            layout: writes.layout().clone(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SingleLayoutVariableDescSet;
+    use super::SingleLayoutVariableDescSetPool;
+    use super::MAX_VARIABLE_SETS;
+    use crate::buffer::BufferAccess;
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::descriptor_set::layout::DescriptorDesc;
+    use crate::descriptor_set::layout::DescriptorSetDesc;
+    use crate::descriptor_set::layout::DescriptorSetLayout;
+    use crate::descriptor_set::layout::DescriptorType;
+    use crate::descriptor_set::DescriptorSet;
+    use crate::device::Device;
+    use crate::shader::ShaderStages;
+    use std::sync::Arc;
+
+    fn variable_pool(device: Arc<Device>) -> SingleLayoutVariableDescSetPool {
+        let layout = DescriptorSetLayout::new(
+            device,
+            DescriptorSetDesc::new([Some(DescriptorDesc {
+                ty: DescriptorType::StorageBuffer,
+                descriptor_count: 1000,
+                variable_count: true,
+                update_after_bind: false,
+                update_unused_while_pending: false,
+                partially_bound: false,
+                stages: ShaderStages::all(),
+                immutable_samplers: Vec::new(),
+                mutable_types: Vec::new(),
+            })]),
+        )
+        .unwrap();
+
+        SingleLayoutVariableDescSetPool::new(layout)
+    }
+
+    fn build_set(
+        pool: &mut SingleLayoutVariableDescSetPool,
+        buffer: &Arc<dyn BufferAccess>,
+        count: u32,
+    ) -> Arc<SingleLayoutVariableDescSet> {
+        let mut builder = pool.next();
+        builder.enter_array().unwrap();
+
+        for _ in 0..count {
+            builder.add_buffer(buffer.clone()).unwrap();
+        }
+
+        builder.leave_array().unwrap();
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn variable_descriptor_counts() {
+        let (device, _) = gfx_dev_and_queue!(
+            runtime_descriptor_array,
+            descriptor_binding_variable_descriptor_count
+        );
+
+        let buffer: Arc<dyn BufferAccess> =
+            CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                .unwrap();
+        let mut pool = variable_pool(device);
+
+        let sets: Vec<_> = [1, 4, 2, 7]
+            .iter()
+            .map(|&count| (count, build_set(&mut pool, &buffer, count)))
+            .collect();
+
+        for (count, set) in &sets {
+            assert_eq!(set.resources().binding(0).unwrap().count(), *count);
+        }
+
+        // The pools are sized from the requested counts, not from the maximum of the layout.
+        assert!(pool.variable_capacity <= 16);
+    }
+
+    #[test]
+    fn pool_exhaustion() {
+        let (device, _) = gfx_dev_and_queue!(
+            runtime_descriptor_array,
+            descriptor_binding_variable_descriptor_count
+        );
+
+        let buffer: Arc<dyn BufferAccess> =
+            CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), false, 0u32)
+                .unwrap();
+        let mut pool = variable_pool(device);
+
+        let mut sets = Vec::new();
+        let mut out_of_sets = false;
+        let mut out_of_descriptors = false;
+
+        for _ in 0..100 {
+            let previous = pool.inner.clone();
+            let (remaining_sets, remaining_variable_descriptors) =
+                (pool.remaining_sets, pool.remaining_variable_descriptors);
+            sets.push(build_set(&mut pool, &buffer, 1));
+
+            match previous {
+                Some(previous) if !Arc::ptr_eq(&previous, pool.inner.as_ref().unwrap()) => {
+                    // A new pool is only created when the previous one is full.
+                    assert!(remaining_sets == 0 || remaining_variable_descriptors == 0);
+                    out_of_sets |= remaining_sets == 0;
+                    out_of_descriptors |= remaining_variable_descriptors == 0;
+                }
+                _ => (),
+            }
+
+            assert!(pool.remaining_sets < MAX_VARIABLE_SETS);
+        }
+
+        assert!(out_of_sets);
+        assert!(out_of_descriptors);
+    }
+}
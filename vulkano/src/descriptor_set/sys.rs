@@ -9,15 +9,21 @@
 
 //! Low-level descriptor set.
 
+use crate::acceleration_structure::AccelerationStructure;
 use crate::buffer::{BufferAccess, BufferInner, BufferViewAbstract};
 use crate::descriptor_set::layout::{DescriptorSetLayout, DescriptorType};
+use crate::descriptor_set::update_template::DescriptorUpdateTemplate;
+use crate::descriptor_set::{DescriptorResourceType, DescriptorSet};
 use crate::device::DeviceOwned;
 use crate::image::view::ImageViewAbstract;
 use crate::sampler::Sampler;
 use crate::DeviceSize;
+use crate::Version;
 use crate::VulkanObject;
 use smallvec::SmallVec;
+use std::error;
 use std::fmt;
+use std::ops::Range;
 use std::ptr;
 use std::sync::Arc;
 
@@ -55,7 +61,12 @@ impl UnsafeDescriptorSet {
         let (infos, mut writes): (SmallVec<[_; 8]>, SmallVec<[_; 8]>) = writes
             .into_iter()
             .map(|write| {
-                let descriptor_type = layout.descriptor(write.binding_num).unwrap().ty;
+                let descriptor_type = match layout.descriptor(write.binding_num).unwrap().ty {
+                    DescriptorType::Mutable => write
+                        .mutable_type
+                        .expect("descriptor write for a mutable binding has no mutable type"),
+                    ty => ty,
+                };
 
                 (
                     write.to_vulkan_info(descriptor_type),
@@ -70,8 +81,19 @@ impl UnsafeDescriptorSet {
             return;
         }
 
+        // Acceleration structures are written through a structure in the `p_next` chain, which
+        // must stay alive until the update.
+        let acceleration_structure_infos: SmallVec<[_; 8]> = infos
+            .iter()
+            .map(DescriptorWriteInfo::to_vulkan_acceleration_structure)
+            .collect();
+
         // Set the info pointers separately.
-        for (info, write) in infos.iter().zip(writes.iter_mut()) {
+        for ((info, acceleration_structure_info), write) in infos
+            .iter()
+            .zip(acceleration_structure_infos.iter())
+            .zip(writes.iter_mut())
+        {
             match info {
                 DescriptorWriteInfo::Image(info) => {
                     write.descriptor_count = info.len() as u32;
@@ -85,6 +107,10 @@ impl UnsafeDescriptorSet {
                     write.descriptor_count = info.len() as u32;
                     write.p_texel_buffer_view = info.as_ptr();
                 }
+                DescriptorWriteInfo::AccelerationStructure(info) => {
+                    write.descriptor_count = info.len() as u32;
+                    write.p_next = acceleration_structure_info as *const _ as *const _;
+                }
             }
 
             debug_assert!(write.descriptor_count != 0);
@@ -101,9 +127,95 @@ impl UnsafeDescriptorSet {
         );
     }
 
-    // TODO: add copying from other descriptor sets
-    //       add a `copy` method that just takes a copy, and an `update` method that takes both
-    //       writes and copies and that actually performs the operation
+    /// Copies descriptors from other descriptor sets into this descriptor set. Doesn't check
+    /// that the copies are correct, and doesn't check whether the descriptor sets are in use.
+    ///
+    /// # Safety
+    ///
+    /// - The `Device` must be the device the pool of this set was created with.
+    /// - Doesn't verify that the copies are valid for the layouts of the source and destination
+    ///   descriptor sets.
+    /// - Doesn't keep the resources alive. You have to do that yourself.
+    /// - Updating a descriptor set obeys synchronization rules that aren't checked here. Once a
+    ///   command buffer contains a pointer/reference to a descriptor set, it is illegal to write
+    ///   to it.
+    ///
+    pub unsafe fn copy<'a>(
+        &mut self,
+        layout: &DescriptorSetLayout,
+        copies: impl IntoIterator<Item = &'a DescriptorCopy>,
+    ) {
+        let copies: SmallVec<[_; 8]> = copies
+            .into_iter()
+            .map(|copy| copy.to_vulkan(self.handle))
+            .collect();
+
+        // It is forbidden to call `vkUpdateDescriptorSets` with 0 copies, so we need to perform
+        // this emptiness check.
+        if copies.is_empty() {
+            return;
+        }
+
+        let fns = layout.device().fns();
+
+        fns.v1_0.update_descriptor_sets(
+            layout.device().internal_object(),
+            0,
+            ptr::null(),
+            copies.len() as u32,
+            copies.as_ptr(),
+        );
+    }
+
+    /// Modifies a descriptor set using a descriptor update template.
+    ///
+    /// `data` must contain the Vulkan structures for all the descriptors that the template
+    /// updates, at the offsets described by the template entries.
+    ///
+    /// # Safety
+    ///
+    /// - The `Device` must be the device the pool of this set was created with.
+    /// - The template must have been created for a layout that is compatible with the layout of
+    ///   this set.
+    /// - `data` must contain valid Vulkan handles of objects that are alive, that belong to the
+    ///   same device, and that are valid for the descriptors they are written to.
+    /// - The set must not be in use by the GPU, unless the bindings have the update-after-bind
+    ///   flag.
+    /// - Doesn't update the `DescriptorSetResources` of the descriptor set that owns this set, so
+    ///   command buffers don't know about the written resources. You have to keep them alive and
+    ///   synchronize their accesses yourself.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `data` is smaller than `template.data_size()`.
+    ///
+    pub unsafe fn update_with_template(
+        &mut self,
+        template: &DescriptorUpdateTemplate,
+        data: &[u8],
+    ) {
+        assert!(data.len() >= template.data_size());
+
+        let device = template.device();
+        let fns = device.fns();
+
+        if device.api_version() >= Version::V1_1 {
+            fns.v1_1.update_descriptor_set_with_template(
+                device.internal_object(),
+                self.handle,
+                template.internal_object(),
+                data.as_ptr() as *const _,
+            );
+        } else {
+            fns.khr_descriptor_update_template
+                .update_descriptor_set_with_template_khr(
+                    device.internal_object(),
+                    self.handle,
+                    template.internal_object(),
+                    data.as_ptr() as *const _,
+                );
+        }
+    }
 }
 
 unsafe impl VulkanObject for UnsafeDescriptorSet {
@@ -129,6 +241,7 @@ pub struct DescriptorWrite {
     pub(crate) binding_num: u32,
     first_array_element: u32,
     elements: DescriptorWriteElements,
+    mutable_type: Option<DescriptorType>,
 }
 
 impl DescriptorWrite {
@@ -144,6 +257,7 @@ impl DescriptorWrite {
             binding_num,
             first_array_element,
             elements: DescriptorWriteElements::Buffer(elements),
+            mutable_type: None,
         }
     }
 
@@ -159,6 +273,7 @@ impl DescriptorWrite {
             binding_num,
             first_array_element,
             elements: DescriptorWriteElements::BufferView(elements),
+            mutable_type: None,
         }
     }
 
@@ -174,6 +289,7 @@ impl DescriptorWrite {
             binding_num,
             first_array_element,
             elements: DescriptorWriteElements::ImageView(elements),
+            mutable_type: None,
         }
     }
 
@@ -189,9 +305,24 @@ impl DescriptorWrite {
             binding_num,
             first_array_element,
             elements: DescriptorWriteElements::ImageViewSampler(elements),
+            mutable_type: None,
         }
     }
 
+    /// Writes image views to a `CombinedImageSampler` binding whose samplers are immutable.
+    ///
+    /// The samplers of such a binding are set in the descriptor set layout, so only the image
+    /// views are written. If a binding of this type has no immutable samplers, use
+    /// `image_view_sampler` instead.
+    #[inline]
+    pub unsafe fn image_view_with_immutable_sampler(
+        binding_num: u32,
+        first_array_element: u32,
+        elements: impl IntoIterator<Item = Arc<dyn ImageViewAbstract>>,
+    ) -> Self {
+        Self::image_view(binding_num, first_array_element, elements)
+    }
+
     #[inline]
     pub unsafe fn sampler(
         binding_num: u32,
@@ -204,9 +335,78 @@ impl DescriptorWrite {
             binding_num,
             first_array_element,
             elements: DescriptorWriteElements::Sampler(elements),
+            mutable_type: None,
         }
     }
 
+    #[inline]
+    pub unsafe fn acceleration_structure(
+        binding_num: u32,
+        first_array_element: u32,
+        elements: impl IntoIterator<Item = Arc<AccelerationStructure>>,
+    ) -> Self {
+        let elements: SmallVec<_> = elements.into_iter().collect();
+        assert!(!elements.is_empty());
+        Self {
+            binding_num,
+            first_array_element,
+            elements: DescriptorWriteElements::AccelerationStructure(elements),
+            mutable_type: None,
+        }
+    }
+
+    /// Writes `count` null descriptors, which behave like a resource that is entirely out of
+    /// bounds when accessed by a shader. `resource_type` is the type of resources held by the
+    /// binding, or for a `Mutable` binding the type of resources of the mutable type of the
+    /// write.
+    ///
+    /// Null descriptors require the `null_descriptor` feature of `ext_robustness2` to be enabled
+    /// on the device.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `count` is 0.
+    /// - Panics if `resource_type` is not `Buffer`, `BufferView`, `ImageView` or
+    ///   `AccelerationStructure`, as samplers,
+    ///   combined image samplers without immutable samplers and mutable resources can't be null.
+    #[inline]
+    pub unsafe fn none(
+        binding_num: u32,
+        first_array_element: u32,
+        resource_type: DescriptorResourceType,
+        count: u32,
+    ) -> Self {
+        assert!(count != 0);
+        assert!(matches!(
+            resource_type,
+            DescriptorResourceType::Buffer
+                | DescriptorResourceType::BufferView
+                | DescriptorResourceType::ImageView
+                | DescriptorResourceType::AccelerationStructure
+        ));
+        Self {
+            binding_num,
+            first_array_element,
+            elements: DescriptorWriteElements::None(resource_type, count),
+            mutable_type: None,
+        }
+    }
+
+    /// Sets the descriptor type that the descriptors are written as, for a binding whose type is
+    /// `Mutable`. This must be one of the mutable types of the binding.
+    #[inline]
+    pub fn with_mutable_type(mut self, ty: DescriptorType) -> Self {
+        self.mutable_type = Some(ty);
+        self
+    }
+
+    /// Returns the descriptor type that the descriptors are written as, if the write is for a
+    /// `Mutable` binding.
+    #[inline]
+    pub fn mutable_type(&self) -> Option<DescriptorType> {
+        self.mutable_type
+    }
+
     /// Returns the binding number that is updated by this descriptor write.
     #[inline]
     pub fn binding_num(&self) -> u32 {
@@ -326,6 +526,44 @@ impl DescriptorWrite {
                         .collect(),
                 )
             }
+            DescriptorWriteElements::None(_, count) => {
+                let count = *count as usize;
+                match descriptor_type {
+                    DescriptorType::UniformBuffer
+                    | DescriptorType::StorageBuffer
+                    | DescriptorType::UniformBufferDynamic
+                    | DescriptorType::StorageBufferDynamic => DescriptorWriteInfo::Buffer(
+                        (0..count)
+                            .map(|_| ash::vk::DescriptorBufferInfo {
+                                buffer: ash::vk::Buffer::null(),
+                                offset: 0,
+                                range: ash::vk::WHOLE_SIZE,
+                            })
+                            .collect(),
+                    ),
+                    DescriptorType::UniformTexelBuffer | DescriptorType::StorageTexelBuffer => {
+                        DescriptorWriteInfo::BufferView(
+                            (0..count).map(|_| ash::vk::BufferView::null()).collect(),
+                        )
+                    }
+                    DescriptorType::AccelerationStructure => {
+                        DescriptorWriteInfo::AccelerationStructure(
+                            (0..count)
+                                .map(|_| ash::vk::AccelerationStructureKHR::null())
+                                .collect(),
+                        )
+                    }
+                    _ => DescriptorWriteInfo::Image(
+                        (0..count)
+                            .map(|_| ash::vk::DescriptorImageInfo {
+                                sampler: ash::vk::Sampler::null(),
+                                image_view: ash::vk::ImageView::null(),
+                                image_layout: ash::vk::ImageLayout::UNDEFINED,
+                            })
+                            .collect(),
+                    ),
+                }
+            }
             DescriptorWriteElements::Sampler(elements) => {
                 debug_assert!(matches!(descriptor_type, DescriptorType::Sampler));
                 DescriptorWriteInfo::Image(
@@ -339,6 +577,18 @@ impl DescriptorWrite {
                         .collect(),
                 )
             }
+            DescriptorWriteElements::AccelerationStructure(elements) => {
+                debug_assert!(matches!(
+                    descriptor_type,
+                    DescriptorType::AccelerationStructure
+                ));
+                DescriptorWriteInfo::AccelerationStructure(
+                    elements
+                        .iter()
+                        .map(|acceleration_structure| acceleration_structure.internal_object())
+                        .collect(),
+                )
+            }
         }
     }
 
@@ -361,6 +611,191 @@ impl DescriptorWrite {
     }
 }
 
+/// Represents a single copy of descriptors from one descriptor set to another.
+///
+/// The copy goes from the descriptors of binding `src_binding` of `src`, starting at array
+/// element `src_first_array_element`, to the descriptors of binding `dst_binding` of the
+/// descriptor set that the copy is applied to, starting at array element
+/// `dst_first_array_element`.
+#[derive(Clone)]
+pub struct DescriptorCopy {
+    src: Arc<dyn DescriptorSet>,
+    src_binding: u32,
+    src_first_array_element: u32,
+    dst_binding: u32,
+    dst_first_array_element: u32,
+    descriptor_count: u32,
+}
+
+impl DescriptorCopy {
+    /// Builds a new `DescriptorCopy`.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `descriptor_count` is 0.
+    #[inline]
+    pub fn new(
+        src: Arc<dyn DescriptorSet>,
+        src_binding: u32,
+        src_first_array_element: u32,
+        dst_binding: u32,
+        dst_first_array_element: u32,
+        descriptor_count: u32,
+    ) -> Self {
+        assert_ne!(descriptor_count, 0);
+
+        Self {
+            src,
+            src_binding,
+            src_first_array_element,
+            dst_binding,
+            dst_first_array_element,
+            descriptor_count,
+        }
+    }
+
+    /// Returns the descriptor set that is copied from.
+    #[inline]
+    pub fn src(&self) -> &Arc<dyn DescriptorSet> {
+        &self.src
+    }
+
+    /// Returns the binding number that is copied from.
+    #[inline]
+    pub fn src_binding(&self) -> u32 {
+        self.src_binding
+    }
+
+    /// Returns the first array element in the binding that is copied from.
+    #[inline]
+    pub fn src_first_array_element(&self) -> u32 {
+        self.src_first_array_element
+    }
+
+    /// Returns the binding number that is copied to.
+    #[inline]
+    pub fn dst_binding(&self) -> u32 {
+        self.dst_binding
+    }
+
+    /// Returns the first array element in the binding that is copied to.
+    #[inline]
+    pub fn dst_first_array_element(&self) -> u32 {
+        self.dst_first_array_element
+    }
+
+    /// Returns the number of descriptors that are copied.
+    #[inline]
+    pub fn descriptor_count(&self) -> u32 {
+        self.descriptor_count
+    }
+
+    pub(crate) fn to_vulkan(&self, dst_set: ash::vk::DescriptorSet) -> ash::vk::CopyDescriptorSet {
+        ash::vk::CopyDescriptorSet {
+            src_set: self.src.inner().internal_object(),
+            src_binding: self.src_binding,
+            src_array_element: self.src_first_array_element,
+            dst_set,
+            dst_binding: self.dst_binding,
+            dst_array_element: self.dst_first_array_element,
+            descriptor_count: self.descriptor_count,
+            ..Default::default()
+        }
+    }
+}
+
+/// Error that can be returned when a descriptor copy is not valid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DescriptorCopyError {
+    /// The descriptor types of the source and destination bindings are different.
+    DescriptorTypeMismatch {
+        src: DescriptorType,
+        dst: DescriptorType,
+    },
+
+    /// The descriptors copied to are out of the bounds of the destination binding.
+    DstOutOfBounds {
+        /// The range of array elements that was copied to.
+        range: Range<u32>,
+        /// The number of descriptors in the binding.
+        count: u32,
+    },
+
+    /// One of the source and destination bindings has immutable samplers, and the other doesn't.
+    ImmutableSamplersMismatch,
+
+    /// The destination binding number does not exist in the destination descriptor set.
+    InvalidDstBinding { binding: u32 },
+
+    /// The source binding number does not exist in the source descriptor set.
+    InvalidSrcBinding { binding: u32 },
+
+    /// The source and destination ranges overlap in the same descriptor set.
+    Overlapping,
+
+    /// The descriptors copied from are out of the bounds of the source binding.
+    SrcOutOfBounds {
+        /// The range of array elements that was copied from.
+        range: Range<u32>,
+        /// The number of descriptors in the binding.
+        count: u32,
+    },
+
+    /// The source descriptor set belongs to a different device than the destination.
+    SrcWrongDevice,
+
+    /// Only one of the layouts of the source and destination descriptor sets has
+    /// update-after-bind bindings.
+    UpdateAfterBindMismatch,
+}
+
+impl error::Error for DescriptorCopyError {}
+
+impl fmt::Display for DescriptorCopyError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Self::DescriptorTypeMismatch { src, dst } => write!(
+                fmt,
+                "the source binding has descriptor type {:?}, but the destination binding has descriptor type {:?}",
+                src, dst,
+            ),
+            Self::DstOutOfBounds { range, count } => write!(
+                fmt,
+                "descriptor copy writes to elements {}..{}, but the destination binding only has {} descriptors",
+                range.start, range.end, count,
+            ),
+            Self::ImmutableSamplersMismatch => write!(
+                fmt,
+                "one of the source and destination bindings has immutable samplers, and the other doesn't",
+            ),
+            Self::InvalidDstBinding { binding } => {
+                write!(fmt, "descriptor copy has invalid destination binding number {}", binding)
+            }
+            Self::InvalidSrcBinding { binding } => {
+                write!(fmt, "descriptor copy has invalid source binding number {}", binding)
+            }
+            Self::Overlapping => write!(
+                fmt,
+                "the source and destination ranges overlap in the same descriptor set",
+            ),
+            Self::SrcOutOfBounds { range, count } => write!(
+                fmt,
+                "descriptor copy reads from elements {}..{}, but the source binding only has {} descriptors",
+                range.start, range.end, count,
+            ),
+            Self::SrcWrongDevice => write!(
+                fmt,
+                "the source descriptor set belongs to a different device than the destination",
+            ),
+            Self::UpdateAfterBindMismatch => write!(
+                fmt,
+                "only one of the layouts of the source and destination descriptor sets has update-after-bind bindings",
+            ),
+        }
+    }
+}
+
 /// The elements held by a descriptor write.
 pub enum DescriptorWriteElements {
     Buffer(SmallVec<[Arc<dyn BufferAccess>; 1]>),
@@ -368,9 +803,30 @@ pub enum DescriptorWriteElements {
     ImageView(SmallVec<[Arc<dyn ImageViewAbstract>; 1]>),
     ImageViewSampler(SmallVec<[(Arc<dyn ImageViewAbstract>, Arc<Sampler>); 1]>),
     Sampler(SmallVec<[Arc<Sampler>; 1]>),
+    AccelerationStructure(SmallVec<[Arc<AccelerationStructure>; 1]>),
+    /// Null descriptors for a binding holding the given type of resources, and their number.
+    None(DescriptorResourceType, u32),
 }
 
 impl DescriptorWriteElements {
+    /// Returns the type of the elements.
+    #[inline]
+    pub fn resource_type(&self) -> DescriptorResourceType {
+        match self {
+            DescriptorWriteElements::Buffer(_) => DescriptorResourceType::Buffer,
+            DescriptorWriteElements::BufferView(_) => DescriptorResourceType::BufferView,
+            DescriptorWriteElements::ImageView(_) => DescriptorResourceType::ImageView,
+            DescriptorWriteElements::ImageViewSampler(_) => {
+                DescriptorResourceType::ImageViewSampler
+            }
+            DescriptorWriteElements::Sampler(_) => DescriptorResourceType::Sampler,
+            DescriptorWriteElements::AccelerationStructure(_) => {
+                DescriptorResourceType::AccelerationStructure
+            }
+            DescriptorWriteElements::None(ty, _) => *ty,
+        }
+    }
+
     /// Returns the number of elements.
     #[inline]
     pub fn len(&self) -> u32 {
@@ -380,6 +836,8 @@ impl DescriptorWriteElements {
             DescriptorWriteElements::ImageView(elements) => elements.len() as u32,
             DescriptorWriteElements::ImageViewSampler(elements) => elements.len() as u32,
             DescriptorWriteElements::Sampler(elements) => elements.len() as u32,
+            DescriptorWriteElements::AccelerationStructure(elements) => elements.len() as u32,
+            DescriptorWriteElements::None(_, count) => *count,
         }
     }
 }
@@ -389,9 +847,27 @@ pub(crate) enum DescriptorWriteInfo {
     Image(SmallVec<[ash::vk::DescriptorImageInfo; 1]>),
     Buffer(SmallVec<[ash::vk::DescriptorBufferInfo; 1]>),
     BufferView(SmallVec<[ash::vk::BufferView; 1]>),
+    AccelerationStructure(SmallVec<[ash::vk::AccelerationStructureKHR; 1]>),
 }
 
 impl DescriptorWriteInfo {
+    // Returns the structure to put in the `p_next` chain of the write for acceleration
+    // structures, or a default structure for other descriptors.
+    pub(crate) fn to_vulkan_acceleration_structure(
+        &self,
+    ) -> ash::vk::WriteDescriptorSetAccelerationStructureKHR {
+        match self {
+            DescriptorWriteInfo::AccelerationStructure(info) => {
+                ash::vk::WriteDescriptorSetAccelerationStructureKHR {
+                    acceleration_structure_count: info.len() as u32,
+                    p_acceleration_structures: info.as_ptr(),
+                    ..Default::default()
+                }
+            }
+            _ => Default::default(),
+        }
+    }
+
     fn set_info(&self, write: &mut ash::vk::WriteDescriptorSet) {
         match self {
             DescriptorWriteInfo::Image(info) => {
@@ -406,6 +882,10 @@ impl DescriptorWriteInfo {
                 write.descriptor_count = info.len() as u32;
                 write.p_texel_buffer_view = info.as_ptr();
             }
+            DescriptorWriteInfo::AccelerationStructure(info) => {
+                // The handles are passed in the `p_next` chain, which is set by the caller.
+                write.descriptor_count = info.len() as u32;
+            }
         }
 
         debug_assert!(write.descriptor_count != 0);
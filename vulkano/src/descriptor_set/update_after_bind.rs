@@ -0,0 +1,462 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! A descriptor set whose descriptors can be rewritten after it has been created.
+//!
+//! Contrary to a `PersistentDescriptorSet`, an `UpdateAfterBindDescriptorSet` starts out with all
+//! of its descriptors empty, and descriptors are written to it with the `update` method. If the
+//! bindings of the layout were created with `update_after_bind` enabled, this can happen even
+//! after the descriptor set has been bound in a command buffer, as long as that command buffer
+//! has not been submitted yet. This is the basis of "bindless" rendering, where a single large
+//! descriptor set is bound once and new resources are added to it over time.
+
+use crate::descriptor_set::layout::DescriptorType;
+use crate::descriptor_set::pool::standard::StdDescriptorPoolAlloc;
+use crate::descriptor_set::pool::{DescriptorPool, DescriptorPoolAlloc};
+use crate::descriptor_set::resources::DescriptorSetResources;
+use crate::descriptor_set::sys::{
+    DescriptorCopy, DescriptorCopyError, DescriptorWrite, DescriptorWriteElements,
+};
+use crate::descriptor_set::UnsafeDescriptorSet;
+use crate::descriptor_set::{
+    DescriptorSet, DescriptorSetError, DescriptorSetLayout, DescriptorWriteError,
+};
+use crate::device::{Device, DeviceOwned};
+use crate::VulkanObject;
+use parking_lot::Mutex;
+use smallvec::SmallVec;
+use std::cell::UnsafeCell;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// A descriptor set whose descriptors can be rewritten after it has been created.
+pub struct UpdateAfterBindDescriptorSet<P = StdDescriptorPoolAlloc> {
+    alloc: P,
+    // Only modified by `try_update` and `copy` while holding `update_lock`. Their safety
+    // requirements guarantee that no reference returned by `resources` is alive at that time.
+    resources: UnsafeCell<DescriptorSetResources>,
+    update_lock: Mutex<()>,
+    layout: Arc<DescriptorSetLayout>,
+    variable_descriptor_count: u32,
+}
+
+unsafe impl<P> Send for UpdateAfterBindDescriptorSet<P> where P: Send {}
+unsafe impl<P> Sync for UpdateAfterBindDescriptorSet<P> where P: Sync {}
+
+impl UpdateAfterBindDescriptorSet {
+    /// Allocates a new descriptor set from the standard descriptor pool of the device. All
+    /// descriptors are initially empty.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `layout` is for push descriptors rather than regular descriptor sets.
+    /// - Panics if `variable_descriptor_count` is greater than the maximum number of variable
+    ///   count descriptors in `layout`.
+    #[inline]
+    pub fn new(
+        layout: Arc<DescriptorSetLayout>,
+        variable_descriptor_count: u32,
+    ) -> Result<Arc<UpdateAfterBindDescriptorSet<StdDescriptorPoolAlloc>>, DescriptorSetError> {
+        let mut pool = Device::standard_descriptor_pool(layout.device());
+        UpdateAfterBindDescriptorSet::new_with_pool(layout, variable_descriptor_count, &mut pool)
+    }
+
+    /// Allocates a new descriptor set from `pool`. All descriptors are initially empty.
+    ///
+    /// If `layout` contains update-after-bind bindings, then the pool must allocate its
+    /// descriptor sets from Vulkan pools that were created with the update-after-bind flag.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `layout` is for push descriptors rather than regular descriptor sets.
+    pub fn new_with_pool<P>(
+        layout: Arc<DescriptorSetLayout>,
+        variable_descriptor_count: u32,
+        pool: &mut P,
+    ) -> Result<Arc<UpdateAfterBindDescriptorSet<P::Alloc>>, DescriptorSetError>
+    where
+        P: ?Sized + DescriptorPool,
+    {
+        assert!(
+            !layout.desc().is_push_descriptor(),
+            "the provided descriptor set layout is for push descriptors, and cannot be used to build a descriptor set object"
+        );
+
+        let alloc = pool.alloc(&layout, variable_descriptor_count)?;
+        let resources = DescriptorSetResources::new(&layout, variable_descriptor_count);
+
+        Ok(Arc::new(UpdateAfterBindDescriptorSet {
+            alloc,
+            resources: UnsafeCell::new(resources),
+            update_lock: Mutex::new(()),
+            layout,
+            variable_descriptor_count,
+        }))
+    }
+}
+
+impl<P> UpdateAfterBindDescriptorSet<P>
+where
+    P: DescriptorPoolAlloc,
+{
+    /// Returns the number of descriptors that were allocated for the variable count binding.
+    #[inline]
+    pub fn variable_descriptor_count(&self) -> u32 {
+        self.variable_descriptor_count
+    }
+
+    /// Writes descriptors to the descriptor set, and updates its resources accordingly.
+    ///
+    /// This blocks until the other updates of the descriptor set have finished.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if a write is not valid for the layout of the descriptor set. See
+    ///   [`try_update`](Self::try_update) for a version that returns an error instead.
+    ///
+    /// # Safety
+    ///
+    /// - If the descriptor set is bound in a command buffer that is recording or that has been
+    ///   built, then all written bindings must have been created with `update_after_bind`
+    ///   enabled. Otherwise that command buffer becomes invalid and must not be submitted.
+    /// - The descriptor set must not be in use by a command buffer that is pending execution.
+    /// - No reference returned by [`resources`](DescriptorSet::resources) must be alive during
+    ///   the update. In particular, the descriptor set must not be bound or validated in a
+    ///   command buffer that is being recorded on another thread.
+    /// - The written resources are not known to command buffers that were recorded before the
+    ///   write, and are therefore not synchronized by them. You must ensure that the resources
+    ///   are not accessed in a conflicting way.
+    /// - The written resources must belong to the same device as the descriptor set.
+    #[inline]
+    pub unsafe fn update<'a>(&self, writes: impl IntoIterator<Item = &'a DescriptorWrite>) {
+        if let Err(err) = self.try_update(writes) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Same as [`update`](Self::update), but returns an error instead of panicking if a write is
+    /// not valid for the layout of the descriptor set. Nothing is written if an error is
+    /// returned.
+    ///
+    /// # Safety
+    ///
+    /// - Same as [`update`](Self::update).
+    pub unsafe fn try_update<'a>(
+        &self,
+        writes: impl IntoIterator<Item = &'a DescriptorWrite>,
+    ) -> Result<(), DescriptorWriteError> {
+        let writes: SmallVec<[_; 8]> = writes.into_iter().collect();
+
+        for write in writes.iter() {
+            if matches!(write.elements(), DescriptorWriteElements::None(_, _))
+                && !self.device().enabled_features().null_descriptor
+            {
+                return Err(DescriptorWriteError::NullDescriptorNotEnabled {
+                    binding: write.binding_num(),
+                });
+            }
+
+            if let (Some(ty), Some(desc)) = (
+                write.mutable_type(),
+                self.layout.desc().descriptor(write.binding_num()),
+            ) {
+                if desc.ty == DescriptorType::Mutable && !desc.mutable_types.contains(&ty) {
+                    return Err(DescriptorWriteError::MutableTypeNotAllowed {
+                        binding: write.binding_num(),
+                        ty,
+                    });
+                }
+            }
+        }
+
+        let _lock = self.update_lock.lock();
+        let resources = &mut *self.resources.get();
+
+        // Update the resources first, so that an invalid write is rejected before anything is
+        // written to the Vulkan descriptor set.
+        resources.try_update(writes.iter().copied())?;
+
+        // `UnsafeDescriptorSet` is only a wrapper around the handle, and doesn't free itself on
+        // drop. Writing through a temporary wrapper is fine as we're holding the update lock.
+        let mut inner = UnsafeDescriptorSet::new(self.alloc.inner().internal_object());
+        inner.write(&self.layout, writes.iter().copied());
+
+        Ok(())
+    }
+
+    /// Copies descriptors from other descriptor sets, or from other bindings of this descriptor
+    /// set, and updates its resources accordingly. Nothing is copied if an error is returned.
+    ///
+    /// This can be used to keep several descriptor sets of which only a few bindings change, for
+    /// example one per frame in flight, without having to rewrite all of their descriptors.
+    ///
+    /// This blocks until the other updates of the descriptor set have finished.
+    ///
+    /// # Safety
+    ///
+    /// - Same as [`update`](Self::update).
+    /// - The source descriptor sets must not be written to while the copies are performed.
+    pub unsafe fn copy<'a>(
+        &self,
+        copies: impl IntoIterator<Item = &'a DescriptorCopy>,
+    ) -> Result<(), DescriptorCopyError> {
+        let copies: SmallVec<[_; 8]> = copies.into_iter().collect();
+
+        // Take a snapshot of the bindings of the other descriptor sets before locking, as they
+        // may be copying from this one at the same time. The copies within this descriptor set
+        // read its resources under the lock, so that they see the result of the previous copies.
+        let sources: SmallVec<[_; 8]> = copies
+            .iter()
+            .map(|copy| {
+                if copy.src().inner().internal_object() == self.inner().internal_object() {
+                    None
+                } else {
+                    copy.src().resources().binding(copy.src_binding()).cloned()
+                }
+            })
+            .collect();
+
+        let _lock = self.update_lock.lock();
+        let resources = &mut *self.resources.get();
+
+        for (copy, src) in copies.iter().zip(sources.iter()) {
+            let is_self = copy.src().inner().internal_object() == self.inner().internal_object();
+
+            if copy.src().device().internal_object() != self.device().internal_object() {
+                return Err(DescriptorCopyError::SrcWrongDevice);
+            }
+
+            if copy.src().layout().desc().is_update_after_bind()
+                != self.layout.desc().is_update_after_bind()
+            {
+                return Err(DescriptorCopyError::UpdateAfterBindMismatch);
+            }
+
+            let src_desc = copy
+                .src()
+                .layout()
+                .desc()
+                .descriptor(copy.src_binding())
+                .ok_or(DescriptorCopyError::InvalidSrcBinding {
+                    binding: copy.src_binding(),
+                })?;
+            let dst_desc = self.layout.desc().descriptor(copy.dst_binding()).ok_or(
+                DescriptorCopyError::InvalidDstBinding {
+                    binding: copy.dst_binding(),
+                },
+            )?;
+
+            if src_desc.ty != dst_desc.ty {
+                return Err(DescriptorCopyError::DescriptorTypeMismatch {
+                    src: src_desc.ty,
+                    dst: dst_desc.ty,
+                });
+            }
+
+            if src_desc.immutable_samplers.is_empty() != dst_desc.immutable_samplers.is_empty() {
+                return Err(DescriptorCopyError::ImmutableSamplersMismatch);
+            }
+
+            let src_count = if is_self {
+                resources.binding(copy.src_binding()).unwrap().count()
+            } else {
+                src.as_ref().unwrap().count()
+            };
+            let src_range = copy.src_first_array_element()
+                ..copy
+                    .src_first_array_element()
+                    .saturating_add(copy.descriptor_count());
+
+            if src_range.end > src_count {
+                return Err(DescriptorCopyError::SrcOutOfBounds {
+                    range: src_range,
+                    count: src_count,
+                });
+            }
+
+            let dst_count = resources.binding(copy.dst_binding()).unwrap().count();
+            let dst_range = copy.dst_first_array_element()
+                ..copy
+                    .dst_first_array_element()
+                    .saturating_add(copy.descriptor_count());
+
+            if dst_range.end > dst_count {
+                return Err(DescriptorCopyError::DstOutOfBounds {
+                    range: dst_range,
+                    count: dst_count,
+                });
+            }
+
+            if is_self
+                && copy.src_binding() == copy.dst_binding()
+                && src_range.start < dst_range.end
+                && dst_range.start < src_range.end
+            {
+                return Err(DescriptorCopyError::Overlapping);
+            }
+        }
+
+        for (copy, src) in copies.iter().zip(sources) {
+            let src = match src {
+                Some(src) => src,
+                // Can't borrow the source and destination bindings at the same time.
+                None => resources.binding(copy.src_binding()).unwrap().clone(),
+            };
+            resources.copy(copy, &src);
+        }
+
+        let mut inner = UnsafeDescriptorSet::new(self.alloc.inner().internal_object());
+        inner.copy(&self.layout, copies.iter().copied());
+
+        Ok(())
+    }
+}
+
+unsafe impl<P> DescriptorSet for UpdateAfterBindDescriptorSet<P>
+where
+    P: DescriptorPoolAlloc,
+{
+    #[inline]
+    fn inner(&self) -> &UnsafeDescriptorSet {
+        self.alloc.inner()
+    }
+
+    #[inline]
+    fn layout(&self) -> &Arc<DescriptorSetLayout> {
+        &self.layout
+    }
+
+    #[inline]
+    fn resources(&self) -> &DescriptorSetResources {
+        unsafe { &*self.resources.get() }
+    }
+}
+
+unsafe impl<P> DeviceOwned for UpdateAfterBindDescriptorSet<P>
+where
+    P: DescriptorPoolAlloc,
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.layout.device()
+    }
+}
+
+impl<P> PartialEq for UpdateAfterBindDescriptorSet<P>
+where
+    P: DescriptorPoolAlloc,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.inner().internal_object() == other.inner().internal_object()
+            && self.device() == other.device()
+    }
+}
+
+impl<P> Eq for UpdateAfterBindDescriptorSet<P> where P: DescriptorPoolAlloc {}
+
+impl<P> Hash for UpdateAfterBindDescriptorSet<P>
+where
+    P: DescriptorPoolAlloc,
+{
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner().internal_object().hash(state);
+        self.device().hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UpdateAfterBindDescriptorSet;
+    use crate::buffer::BufferAccess;
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+    use crate::descriptor_set::layout::DescriptorDesc;
+    use crate::descriptor_set::layout::DescriptorSetDesc;
+    use crate::descriptor_set::layout::DescriptorSetLayout;
+    use crate::descriptor_set::layout::DescriptorType;
+    use crate::descriptor_set::sys::{DescriptorCopy, DescriptorCopyError, DescriptorWrite};
+    use crate::descriptor_set::{DescriptorBindingResources, DescriptorSet};
+    use crate::shader::ShaderStages;
+    use std::sync::Arc;
+
+    fn filled(set: &dyn DescriptorSet, binding: u32) -> Vec<bool> {
+        match set.resources().binding(binding).unwrap() {
+            DescriptorBindingResources::Buffer(elements) => {
+                elements.iter().map(|element| element.is_some()).collect()
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn overlapping_copies() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let desc = DescriptorDesc {
+            ty: DescriptorType::StorageBuffer,
+            descriptor_count: 4,
+            variable_count: false,
+            update_after_bind: false,
+            update_unused_while_pending: false,
+            partially_bound: false,
+            stages: ShaderStages::all(),
+            immutable_samplers: Vec::new(),
+            mutable_types: Vec::new(),
+        };
+        let layout = DescriptorSetLayout::new(
+            device.clone(),
+            DescriptorSetDesc::new([Some(desc.clone()), Some(desc)]),
+        )
+        .unwrap();
+
+        let buffer: Arc<dyn BufferAccess> =
+            CpuAccessibleBuffer::from_data(device, BufferUsage::all(), false, 0u32).unwrap();
+        let set_a = UpdateAfterBindDescriptorSet::new(layout.clone(), 0).unwrap();
+        let set_b = UpdateAfterBindDescriptorSet::new(layout, 0).unwrap();
+
+        unsafe {
+            set_a.update(&[DescriptorWrite::buffer(
+                0,
+                0,
+                [buffer.clone(), buffer.clone()],
+            )]);
+
+            // Overlapping ranges of the same binding are rejected.
+            assert!(matches!(
+                set_a.copy(&[DescriptorCopy::new(set_a.clone(), 0, 0, 0, 1, 2)]),
+                Err(DescriptorCopyError::Overlapping)
+            ));
+            assert_eq!(filled(&*set_a, 0), [true, true, false, false]);
+
+            // The same ranges of two different bindings are not overlapping, and a copy sees the
+            // result of the previous copies of the same call.
+            set_a
+                .copy(&[
+                    DescriptorCopy::new(set_a.clone(), 0, 0, 1, 1, 2),
+                    DescriptorCopy::new(set_a.clone(), 1, 1, 0, 2, 2),
+                ])
+                .unwrap();
+            assert_eq!(filled(&*set_a, 0), [true, true, true, true]);
+            assert_eq!(filled(&*set_a, 1), [false, true, true, false]);
+
+            // Copies in both directions between two descriptor sets.
+            set_b
+                .copy(&[DescriptorCopy::new(set_a.clone(), 1, 1, 0, 0, 2)])
+                .unwrap();
+            set_a
+                .copy(&[DescriptorCopy::new(set_b.clone(), 1, 0, 1, 0, 4)])
+                .unwrap();
+        }
+
+        assert_eq!(filled(&*set_b, 0), [true, true, false, false]);
+        assert_eq!(filled(&*set_a, 1), [false, false, false, false]);
+    }
+}
@@ -0,0 +1,480 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Pre-recorded descriptor updates.
+//!
+//! A descriptor update template describes, once and for all, which descriptors of a descriptor
+//! set are updated and where the data for each of them is located in a block of memory. The
+//! descriptor set can then be updated with `UnsafeDescriptorSet::update_with_template` by
+//! only providing this block of memory, which is much cheaper than building a list of
+//! `DescriptorWrite`s each time, for example when the same uniform buffer bindings are
+//! rewritten every frame.
+//!
+//! The data block contains raw Vulkan structures: `ash::vk::DescriptorImageInfo` for sampler and
+//! image descriptors, `ash::vk::DescriptorBufferInfo` for buffer descriptors, and
+//! `ash::vk::BufferView` for texel buffer descriptors.
+//!
+//! Descriptor update templates require Vulkan 1.1 or the `khr_descriptor_update_template`
+//! extension.
+
+use crate::check_errors;
+use crate::descriptor_set::layout::DescriptorSetLayout;
+use crate::descriptor_set::layout::DescriptorType;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::OomError;
+use crate::Version;
+use crate::VulkanObject;
+use std::error;
+use std::fmt;
+use std::mem;
+use std::mem::MaybeUninit;
+use std::ops::Range;
+use std::ptr;
+use std::sync::Arc;
+
+/// Describes how to update the descriptors of a descriptor set from a block of memory.
+pub struct DescriptorUpdateTemplate {
+    handle: ash::vk::DescriptorUpdateTemplate,
+    device: Arc<Device>,
+    layout: Arc<DescriptorSetLayout>,
+    entries: Vec<DescriptorUpdateTemplateEntry>,
+    data_size: usize,
+}
+
+impl DescriptorUpdateTemplate {
+    /// Builds a new `DescriptorUpdateTemplate` for descriptor sets with the given layout.
+    ///
+    /// The descriptor type of each entry is taken from the layout.
+    pub fn new(
+        layout: Arc<DescriptorSetLayout>,
+        entries: impl IntoIterator<Item = DescriptorUpdateTemplateEntry>,
+    ) -> Result<Arc<DescriptorUpdateTemplate>, DescriptorUpdateTemplateCreationError> {
+        let device = layout.device().clone();
+
+        if !(device.api_version() >= Version::V1_1
+            || device.enabled_extensions().khr_descriptor_update_template)
+        {
+            return Err(DescriptorUpdateTemplateCreationError::ExtensionNotEnabled {
+                extension: "khr_descriptor_update_template",
+                reason: "tried to create a descriptor update template",
+            });
+        }
+
+        if layout.desc().is_push_descriptor() {
+            return Err(DescriptorUpdateTemplateCreationError::PushDescriptorLayout);
+        }
+
+        let entries: Vec<_> = entries.into_iter().collect();
+        let mut entries_vk = Vec::with_capacity(entries.len());
+        let mut data_size = 0;
+
+        for entry in &entries {
+            let binding_desc = match layout.descriptor(entry.binding_num) {
+                Some(x) => x,
+                None => {
+                    return Err(DescriptorUpdateTemplateCreationError::InvalidBinding {
+                        binding_num: entry.binding_num,
+                    })
+                }
+            };
+
+            let info_size = match binding_desc.ty {
+                DescriptorType::Sampler
+                | DescriptorType::CombinedImageSampler
+                | DescriptorType::SampledImage
+                | DescriptorType::StorageImage
+                | DescriptorType::InputAttachment => mem::size_of::<ash::vk::DescriptorImageInfo>(),
+                DescriptorType::UniformBuffer
+                | DescriptorType::StorageBuffer
+                | DescriptorType::UniformBufferDynamic
+                | DescriptorType::StorageBufferDynamic => {
+                    mem::size_of::<ash::vk::DescriptorBufferInfo>()
+                }
+                DescriptorType::UniformTexelBuffer | DescriptorType::StorageTexelBuffer => {
+                    mem::size_of::<ash::vk::BufferView>()
+                }
+                DescriptorType::AccelerationStructure => {
+                    mem::size_of::<ash::vk::AccelerationStructureKHR>()
+                }
+                DescriptorType::Mutable => {
+                    return Err(DescriptorUpdateTemplateCreationError::MutableBinding {
+                        binding_num: entry.binding_num,
+                    })
+                }
+            };
+
+            let overflow = DescriptorUpdateTemplateCreationError::Overflow {
+                binding_num: entry.binding_num,
+            };
+
+            let range = entry.first_array_element
+                ..entry
+                    .first_array_element
+                    .checked_add(entry.descriptor_count)
+                    .ok_or_else(|| overflow.clone())?;
+
+            if entry.descriptor_count == 0 || range.end > binding_desc.descriptor_count {
+                return Err(DescriptorUpdateTemplateCreationError::OutOfBounds {
+                    binding_num: entry.binding_num,
+                    range,
+                    descriptor_count: binding_desc.descriptor_count,
+                });
+            }
+
+            let entry_end = (entry.descriptor_count as usize - 1)
+                .checked_mul(entry.stride)
+                .and_then(|size| size.checked_add(entry.offset))
+                .and_then(|size| size.checked_add(info_size))
+                .ok_or(overflow)?;
+            data_size = data_size.max(entry_end);
+
+            entries_vk.push(ash::vk::DescriptorUpdateTemplateEntry {
+                dst_binding: entry.binding_num,
+                dst_array_element: entry.first_array_element,
+                descriptor_count: entry.descriptor_count,
+                descriptor_type: binding_desc.ty.into(),
+                offset: entry.offset,
+                stride: entry.stride,
+            });
+        }
+
+        let handle = unsafe {
+            let infos = ash::vk::DescriptorUpdateTemplateCreateInfo {
+                flags: ash::vk::DescriptorUpdateTemplateCreateFlags::empty(),
+                descriptor_update_entry_count: entries_vk.len() as u32,
+                p_descriptor_update_entries: entries_vk.as_ptr(),
+                template_type: ash::vk::DescriptorUpdateTemplateType::DESCRIPTOR_SET,
+                descriptor_set_layout: layout.internal_object(),
+                ..Default::default()
+            };
+
+            let mut output = MaybeUninit::uninit();
+            let fns = device.fns();
+
+            if device.api_version() >= Version::V1_1 {
+                check_errors(fns.v1_1.create_descriptor_update_template(
+                    device.internal_object(),
+                    &infos,
+                    ptr::null(),
+                    output.as_mut_ptr(),
+                ))?;
+            } else {
+                check_errors(
+                    fns.khr_descriptor_update_template
+                        .create_descriptor_update_template_khr(
+                            device.internal_object(),
+                            &infos,
+                            ptr::null(),
+                            output.as_mut_ptr(),
+                        ),
+                )?;
+            }
+
+            output.assume_init()
+        };
+
+        Ok(Arc::new(DescriptorUpdateTemplate {
+            handle,
+            device,
+            layout,
+            entries,
+            data_size,
+        }))
+    }
+
+    /// Returns the layout of the descriptor sets that this template updates.
+    #[inline]
+    pub fn layout(&self) -> &Arc<DescriptorSetLayout> {
+        &self.layout
+    }
+
+    /// Returns the entries of the template.
+    #[inline]
+    pub fn entries(&self) -> &[DescriptorUpdateTemplateEntry] {
+        &self.entries
+    }
+
+    /// Returns the minimum size in bytes of the data that is given when updating a descriptor set
+    /// with this template.
+    #[inline]
+    pub fn data_size(&self) -> usize {
+        self.data_size
+    }
+}
+
+unsafe impl DeviceOwned for DescriptorUpdateTemplate {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+unsafe impl VulkanObject for DescriptorUpdateTemplate {
+    type Object = ash::vk::DescriptorUpdateTemplate;
+
+    #[inline]
+    fn internal_object(&self) -> ash::vk::DescriptorUpdateTemplate {
+        self.handle
+    }
+}
+
+impl fmt::Debug for DescriptorUpdateTemplate {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("DescriptorUpdateTemplate")
+            .field("handle", &self.handle)
+            .field("entries", &self.entries)
+            .finish()
+    }
+}
+
+impl Drop for DescriptorUpdateTemplate {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            let fns = self.device.fns();
+
+            if self.device.api_version() >= Version::V1_1 {
+                fns.v1_1.destroy_descriptor_update_template(
+                    self.device.internal_object(),
+                    self.handle,
+                    ptr::null(),
+                );
+            } else {
+                fns.khr_descriptor_update_template
+                    .destroy_descriptor_update_template_khr(
+                        self.device.internal_object(),
+                        self.handle,
+                        ptr::null(),
+                    );
+            }
+        }
+    }
+}
+
+/// Describes where the data of consecutive descriptors of a binding is located.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DescriptorUpdateTemplateEntry {
+    /// The binding to update.
+    pub binding_num: u32,
+
+    /// The first array element in the binding to update.
+    pub first_array_element: u32,
+
+    /// The number of array elements to update. Must be at least 1.
+    pub descriptor_count: u32,
+
+    /// The offset in bytes in the data of the structure for the first array element.
+    pub offset: usize,
+
+    /// The distance in bytes in the data between the structures for two consecutive array
+    /// elements.
+    pub stride: usize,
+}
+
+/// Error that can happen when creating a `DescriptorUpdateTemplate`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DescriptorUpdateTemplateCreationError {
+    ExtensionNotEnabled {
+        extension: &'static str,
+        reason: &'static str,
+    },
+
+    /// The binding of an entry does not exist in the layout.
+    InvalidBinding { binding_num: u32 },
+
+    /// The binding of an entry is a `Mutable` binding, which templates don't support.
+    MutableBinding { binding_num: u32 },
+
+    /// Not enough memory.
+    OomError(OomError),
+
+    /// The array elements of an entry are out of the bounds of the binding, or the entry has no
+    /// array elements.
+    OutOfBounds {
+        binding_num: u32,
+        range: Range<u32>,
+        descriptor_count: u32,
+    },
+
+    /// The last array element of an entry, or the end of its data, overflows.
+    Overflow { binding_num: u32 },
+
+    /// The layout is for push descriptors.
+    PushDescriptorLayout,
+}
+
+impl From<OomError> for DescriptorUpdateTemplateCreationError {
+    #[inline]
+    fn from(err: OomError) -> Self {
+        Self::OomError(err)
+    }
+}
+
+impl From<crate::Error> for DescriptorUpdateTemplateCreationError {
+    #[inline]
+    fn from(err: crate::Error) -> Self {
+        Self::OomError(err.into())
+    }
+}
+
+impl error::Error for DescriptorUpdateTemplateCreationError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::OomError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DescriptorUpdateTemplateCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Self::ExtensionNotEnabled { extension, reason } => write!(
+                fmt,
+                "the extension {} must be enabled: {}",
+                extension, reason
+            ),
+            Self::InvalidBinding { binding_num } => write!(
+                fmt,
+                "an entry updates binding {}, which does not exist in the layout",
+                binding_num,
+            ),
+            Self::MutableBinding { binding_num } => write!(
+                fmt,
+                "an entry updates binding {}, which is a mutable binding",
+                binding_num,
+            ),
+            Self::OomError(_) => write!(fmt, "out of memory"),
+            Self::OutOfBounds {
+                binding_num,
+                range,
+                descriptor_count,
+            } => write!(
+                fmt,
+                "an entry updates elements {}..{} of binding {}, but the binding has {} descriptors",
+                range.start, range.end, binding_num, descriptor_count,
+            ),
+            Self::Overflow { binding_num } => write!(
+                fmt,
+                "the array elements or the data of the entry that updates binding {} overflow",
+                binding_num,
+            ),
+            Self::PushDescriptorLayout => write!(
+                fmt,
+                "the layout is for push descriptors, which are not supported by descriptor update templates",
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DescriptorUpdateTemplate;
+    use super::DescriptorUpdateTemplateCreationError;
+    use super::DescriptorUpdateTemplateEntry;
+    use crate::descriptor_set::layout::DescriptorDesc;
+    use crate::descriptor_set::layout::DescriptorSetDesc;
+    use crate::descriptor_set::layout::DescriptorSetLayout;
+    use crate::descriptor_set::layout::DescriptorType;
+    use crate::shader::ShaderStages;
+    use crate::Version;
+    use std::iter;
+    use std::mem;
+
+    #[test]
+    fn data_size_and_bounds() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        if device.api_version() < Version::V1_1 {
+            return;
+        }
+
+        let desc = DescriptorDesc {
+            ty: DescriptorType::UniformBuffer,
+            descriptor_count: 4,
+            variable_count: false,
+            update_after_bind: false,
+            update_unused_while_pending: false,
+            partially_bound: false,
+            stages: ShaderStages::all(),
+            immutable_samplers: Vec::new(),
+            mutable_types: Vec::new(),
+        };
+        let layout =
+            DescriptorSetLayout::new(device, DescriptorSetDesc::new(iter::once(Some(desc))))
+                .unwrap();
+
+        let stride = mem::size_of::<ash::vk::DescriptorBufferInfo>();
+        let template = DescriptorUpdateTemplate::new(
+            layout.clone(),
+            iter::once(DescriptorUpdateTemplateEntry {
+                binding_num: 0,
+                first_array_element: 1,
+                descriptor_count: 3,
+                offset: 16,
+                stride,
+            }),
+        )
+        .unwrap();
+        assert_eq!(template.data_size(), 16 + 3 * stride);
+
+        assert_eq!(
+            DescriptorUpdateTemplate::new(
+                layout,
+                iter::once(DescriptorUpdateTemplateEntry {
+                    binding_num: 0,
+                    first_array_element: 2,
+                    descriptor_count: 3,
+                    offset: 0,
+                    stride,
+                }),
+            )
+            .unwrap_err(),
+            DescriptorUpdateTemplateCreationError::OutOfBounds {
+                binding_num: 0,
+                range: 2..5,
+                descriptor_count: 4,
+            },
+        );
+
+        assert_eq!(
+            DescriptorUpdateTemplate::new(
+                layout.clone(),
+                iter::once(DescriptorUpdateTemplateEntry {
+                    binding_num: 0,
+                    first_array_element: u32::MAX,
+                    descriptor_count: 1,
+                    offset: 0,
+                    stride,
+                }),
+            )
+            .unwrap_err(),
+            DescriptorUpdateTemplateCreationError::Overflow { binding_num: 0 },
+        );
+
+        assert_eq!(
+            DescriptorUpdateTemplate::new(
+                layout,
+                iter::once(DescriptorUpdateTemplateEntry {
+                    binding_num: 0,
+                    first_array_element: 0,
+                    descriptor_count: 2,
+                    offset: 0,
+                    stride: usize::MAX,
+                }),
+            )
+            .unwrap_err(),
+            DescriptorUpdateTemplateCreationError::Overflow { binding_num: 0 },
+        );
+    }
+}
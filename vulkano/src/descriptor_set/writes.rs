@@ -0,0 +1,648 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Checking descriptor writes against the requirements of a shader.
+//!
+//! When descriptor writes are applied to a descriptor set, they are only checked against the
+//! layout of the set. Problems such as an image view of the wrong type or format are otherwise
+//! only detected when drawing or dispatching. The `DescriptorWritesBuilder` checks each write
+//! against the `DescriptorRequirements` that were reflected from a shader module as soon as it
+//! is added, so that errors can be reported close to where the write was built.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let mut builder = DescriptorWritesBuilder::new(0, entry_point.descriptor_requirements());
+//! builder.add(unsafe { DescriptorWrite::image_view_sampler(0, 0, [(view, sampler)]) })?;
+//! let writes = builder.build();
+//! ```
+
+use crate::descriptor_set::layout::DescriptorSetDesc;
+use crate::descriptor_set::layout::DescriptorType;
+use crate::descriptor_set::sys::DescriptorWrite;
+use crate::descriptor_set::sys::DescriptorWriteElements;
+use crate::descriptor_set::DescriptorResourceType;
+use crate::format::Format;
+use crate::image::view::ImageViewType;
+use crate::image::ImageLayout;
+use crate::image::ImageViewAbstract;
+use crate::image::SampleCount;
+use crate::shader::DescriptorRequirements;
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use smallvec::{smallvec, SmallVec};
+use std::error;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Collects descriptor writes for one descriptor set, and checks each of them against the
+/// requirements of a shader.
+///
+/// The requirements can be obtained from `EntryPoint::descriptor_requirements`, or from the
+/// `descriptor_requirements` method of a pipeline to check against all of its shaders at once.
+/// The following is checked for each write:
+///
+/// - The binding is used by the shader.
+/// - The resources of the write match one of the descriptor types accepted by the shader. For a
+///   write with a mutable type, the mutable type itself must be accepted by the shader.
+/// - The written array elements are within the array declared by the shader, unless it is a
+///   runtime-sized array.
+/// - Image views have the view type, format and multisampling that the shader expects, and the
+///   layout that their image uses in descriptors is valid for the descriptor type.
+pub struct DescriptorWritesBuilder<'a> {
+    set_num: u32,
+    requirements: FnvHashMap<u32, &'a DescriptorRequirements>,
+    immutable_sampler_bindings: FnvHashSet<u32>,
+    writes: Vec<DescriptorWrite>,
+}
+
+impl<'a> DescriptorWritesBuilder<'a> {
+    /// Builds a new `DescriptorWritesBuilder` for descriptor set `set_num`.
+    ///
+    /// Requirements for other descriptor sets than `set_num` are ignored.
+    pub fn new(
+        set_num: u32,
+        descriptor_requirements: impl IntoIterator<Item = ((u32, u32), &'a DescriptorRequirements)>,
+    ) -> DescriptorWritesBuilder<'a> {
+        let requirements = descriptor_requirements
+            .into_iter()
+            .filter(|&((set, _), _)| set == set_num)
+            .map(|((_, binding), reqs)| (binding, reqs))
+            .collect();
+
+        DescriptorWritesBuilder {
+            set_num,
+            requirements,
+            immutable_sampler_bindings: FnvHashSet::default(),
+            writes: Vec::new(),
+        }
+    }
+
+    /// Takes into account the immutable samplers of the layout that the writes are for.
+    ///
+    /// Without this, writes to a `CombinedImageSampler` binding are expected to provide both
+    /// image views and samplers. For bindings with immutable samplers in `layout`, they must
+    /// instead provide only image views, as built with
+    /// `DescriptorWrite::image_view_with_immutable_sampler`, and `Sampler` bindings can't be
+    /// written at all.
+    pub fn with_layout(mut self, layout: &DescriptorSetDesc) -> Self {
+        self.immutable_sampler_bindings = layout
+            .bindings()
+            .iter()
+            .enumerate()
+            .filter(|(_, desc)| {
+                desc.as_ref()
+                    .map_or(false, |desc| !desc.immutable_samplers.is_empty())
+            })
+            .map(|(binding_num, _)| binding_num as u32)
+            .collect();
+        self
+    }
+
+    /// Returns the number of the descriptor set that the writes are checked for.
+    #[inline]
+    pub fn set_num(&self) -> u32 {
+        self.set_num
+    }
+
+    /// Checks `write` against the requirements of the shader, and adds it to the builder if it
+    /// is valid.
+    pub fn add(
+        &mut self,
+        write: DescriptorWrite,
+    ) -> Result<&mut Self, DescriptorWritesBuilderError> {
+        self.check(&write)?;
+        self.writes.push(write);
+        Ok(self)
+    }
+
+    /// Returns the writes that have been added so far.
+    #[inline]
+    pub fn writes(&self) -> &[DescriptorWrite] {
+        &self.writes
+    }
+
+    /// Returns the writes that have been added, in the order they were added.
+    #[inline]
+    pub fn build(self) -> Vec<DescriptorWrite> {
+        self.writes
+    }
+
+    fn check(&self, write: &DescriptorWrite) -> Result<(), DescriptorWritesBuilderError> {
+        let binding = write.binding_num();
+        let reqs = match self.requirements.get(&binding) {
+            Some(reqs) => *reqs,
+            None => return Err(DescriptorWritesBuilderError::BindingNotUsed { binding }),
+        };
+
+        let elements = write.elements();
+        let provided = elements.resource_type();
+        let immutable_samplers = self.immutable_sampler_bindings.contains(&binding);
+
+        if immutable_samplers
+            && matches!(
+                provided,
+                DescriptorResourceType::ImageViewSampler | DescriptorResourceType::Sampler
+            )
+        {
+            return Err(DescriptorWritesBuilderError::SamplerIsImmutable { binding });
+        }
+
+        // The resources of a binding with immutable samplers don't include the samplers.
+        let resource_type = |ty: DescriptorType| match ty {
+            DescriptorType::CombinedImageSampler if immutable_samplers => {
+                Some(DescriptorResourceType::ImageView)
+            }
+            DescriptorType::Sampler if immutable_samplers => None,
+            _ => Some(DescriptorResourceType::for_descriptor_type(ty)),
+        };
+
+        // The descriptor types that the write can be interpreted as.
+        let descriptor_types: SmallVec<[DescriptorType; 2]> = match write.mutable_type() {
+            Some(ty) => {
+                if !reqs.descriptor_types.contains(&ty) {
+                    return Err(DescriptorWritesBuilderError::MutableTypeNotAccepted {
+                        binding,
+                        ty,
+                    });
+                }
+
+                if resource_type(ty) != Some(provided) {
+                    return Err(DescriptorWritesBuilderError::ResourceTypeMismatch {
+                        binding,
+                        required: vec![ty],
+                        provided,
+                    });
+                }
+
+                smallvec![ty]
+            }
+            None => {
+                let descriptor_types: SmallVec<_> = reqs
+                    .descriptor_types
+                    .iter()
+                    .copied()
+                    .filter(|&ty| resource_type(ty) == Some(provided))
+                    .collect();
+
+                if descriptor_types.is_empty() {
+                    return Err(DescriptorWritesBuilderError::ResourceTypeMismatch {
+                        binding,
+                        required: reqs.descriptor_types.clone(),
+                        provided,
+                    });
+                }
+
+                descriptor_types
+            }
+        };
+
+        let range = match write.first_array_element().checked_add(elements.len()) {
+            Some(end) => write.first_array_element()..end,
+            None => {
+                return Err(DescriptorWritesBuilderError::ArrayIndexOverflow {
+                    binding,
+                    first_array_element: write.first_array_element(),
+                    num_elements: elements.len(),
+                })
+            }
+        };
+
+        // A descriptor count of 0 means a runtime-sized array.
+        if reqs.descriptor_count != 0 && range.end > reqs.descriptor_count {
+            return Err(DescriptorWritesBuilderError::ArrayOutOfBounds {
+                binding,
+                range,
+                descriptor_count: reqs.descriptor_count,
+            });
+        }
+
+        let check_view = |index: u32, image_view: &Arc<dyn ImageViewAbstract>| -> Result<(), _> {
+            check_image_view(reqs, &descriptor_types, image_view).map_err(|error| {
+                DescriptorWritesBuilderError::InvalidImageView {
+                    binding,
+                    index,
+                    error,
+                }
+            })
+        };
+
+        match elements {
+            DescriptorWriteElements::ImageView(elements) => {
+                for (index, image_view) in range.clone().zip(elements) {
+                    check_view(index, image_view)?;
+                }
+            }
+            DescriptorWriteElements::ImageViewSampler(elements) => {
+                for (index, (image_view, _)) in range.clone().zip(elements) {
+                    check_view(index, image_view)?;
+                }
+            }
+            DescriptorWriteElements::Buffer(_)
+            | DescriptorWriteElements::BufferView(_)
+            | DescriptorWriteElements::Sampler(_)
+            | DescriptorWriteElements::AccelerationStructure(_)
+            | DescriptorWriteElements::None(_, _) => (),
+        }
+
+        Ok(())
+    }
+}
+
+fn check_image_view(
+    reqs: &DescriptorRequirements,
+    descriptor_types: &[DescriptorType],
+    image_view: &Arc<dyn ImageViewAbstract>,
+) -> Result<(), InvalidImageView> {
+    if let Some(required) = reqs.image_view_type {
+        if image_view.ty() != required {
+            return Err(InvalidImageView::ViewTypeMismatch {
+                required,
+                obtained: image_view.ty(),
+            });
+        }
+    }
+
+    if let Some(required) = reqs.format {
+        if image_view.format() != required {
+            return Err(InvalidImageView::FormatMismatch {
+                required,
+                obtained: image_view.format(),
+            });
+        }
+    }
+
+    let multisampled = image_view.image().samples() != SampleCount::Sample1;
+
+    if reqs.multisampled != multisampled {
+        return Err(InvalidImageView::MultisampledMismatch {
+            required: reqs.multisampled,
+            obtained: multisampled,
+        });
+    }
+
+    let descriptor_layouts = match image_view.image().descriptor_layouts() {
+        Some(x) => x,
+        None => return Err(InvalidImageView::DescriptorLayoutsUnavailable),
+    };
+
+    for &descriptor_type in descriptor_types {
+        let layout = descriptor_layouts.layout_for(descriptor_type);
+
+        if !is_valid_descriptor_layout(descriptor_type, layout) {
+            return Err(InvalidImageView::LayoutInvalid {
+                descriptor_type,
+                layout,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// Returns whether an image in `layout` can be used in a descriptor of type `descriptor_type`.
+fn is_valid_descriptor_layout(descriptor_type: DescriptorType, layout: ImageLayout) -> bool {
+    match descriptor_type {
+        DescriptorType::StorageImage => layout == ImageLayout::General,
+        DescriptorType::SampledImage
+        | DescriptorType::CombinedImageSampler
+        | DescriptorType::InputAttachment => matches!(
+            layout,
+            ImageLayout::General
+                | ImageLayout::ShaderReadOnlyOptimal
+                | ImageLayout::DepthStencilReadOnlyOptimal
+        ),
+        // These descriptor types don't hold images.
+        DescriptorType::Sampler
+        | DescriptorType::UniformTexelBuffer
+        | DescriptorType::StorageTexelBuffer
+        | DescriptorType::UniformBuffer
+        | DescriptorType::StorageBuffer
+        | DescriptorType::UniformBufferDynamic
+        | DescriptorType::StorageBufferDynamic
+        | DescriptorType::AccelerationStructure
+        | DescriptorType::Mutable => false,
+    }
+}
+
+/// Error that can happen when adding a descriptor write to a `DescriptorWritesBuilder`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DescriptorWritesBuilderError {
+    /// The array elements written to are out of the bounds of the array declared by the shader.
+    ArrayOutOfBounds {
+        binding: u32,
+        range: Range<u32>,
+        descriptor_count: u32,
+    },
+
+    /// The index of the last array element written to doesn't fit in a `u32`.
+    ArrayIndexOverflow {
+        binding: u32,
+        first_array_element: u32,
+        num_elements: u32,
+    },
+
+    /// The binding is not used by the shader.
+    BindingNotUsed { binding: u32 },
+
+    /// An image view of the write does not satisfy the requirements of the shader.
+    InvalidImageView {
+        binding: u32,
+        index: u32,
+        error: InvalidImageView,
+    },
+
+    /// The mutable type of the write is not one of the descriptor types accepted by the shader.
+    MutableTypeNotAccepted { binding: u32, ty: DescriptorType },
+
+    /// The resources of the write don't match any of the descriptor types accepted by the shader.
+    ResourceTypeMismatch {
+        binding: u32,
+        required: Vec<DescriptorType>,
+        provided: DescriptorResourceType,
+    },
+
+    /// The write provides samplers, but the samplers of the binding are immutable.
+    SamplerIsImmutable { binding: u32 },
+}
+
+impl error::Error for DescriptorWritesBuilderError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::InvalidImageView { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DescriptorWritesBuilderError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Self::ArrayOutOfBounds {
+                binding,
+                range,
+                descriptor_count,
+            } => write!(
+                fmt,
+                "tried to write array elements {}..{} of binding {}, but the shader declares only {} elements",
+                range.start, range.end, binding, descriptor_count,
+            ),
+            Self::ArrayIndexOverflow {
+                binding,
+                first_array_element,
+                num_elements,
+            } => write!(
+                fmt,
+                "tried to write {} array elements of binding {} starting at element {}, which overflows the array index",
+                num_elements, binding, first_array_element,
+            ),
+            Self::BindingNotUsed { binding } => write!(
+                fmt,
+                "tried to write to binding {}, but it is not used by the shader",
+                binding,
+            ),
+            Self::InvalidImageView { binding, index, .. } => write!(
+                fmt,
+                "the image view written to binding {} index {} does not satisfy the requirements of the shader",
+                binding, index,
+            ),
+            Self::MutableTypeNotAccepted { binding, ty } => write!(
+                fmt,
+                "tried to write to binding {} as descriptor type {:?}, but the shader does not accept this type",
+                binding, ty,
+            ),
+            Self::ResourceTypeMismatch {
+                binding,
+                required,
+                provided,
+            } => write!(
+                fmt,
+                "tried to write {} resources to binding {}, but the shader requires one of the descriptor types {:?}",
+                provided, binding, required,
+            ),
+            Self::SamplerIsImmutable { binding } => write!(
+                fmt,
+                "tried to write samplers to binding {}, but the samplers of the binding are immutable",
+                binding,
+            ),
+        }
+    }
+}
+
+/// Reason why an image view does not satisfy the requirements of a shader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidImageView {
+    /// The image of the view can't be used in descriptors.
+    DescriptorLayoutsUnavailable,
+
+    /// The image view does not have the format that the shader requires.
+    FormatMismatch { required: Format, obtained: Format },
+
+    /// The layout that the image uses in descriptors of this type is not valid for them.
+    LayoutInvalid {
+        descriptor_type: DescriptorType,
+        layout: ImageLayout,
+    },
+
+    /// The image does not have the multisampling that the shader requires.
+    MultisampledMismatch { required: bool, obtained: bool },
+
+    /// The image view does not have the view type that the shader requires.
+    ViewTypeMismatch {
+        required: ImageViewType,
+        obtained: ImageViewType,
+    },
+}
+
+impl error::Error for InvalidImageView {}
+
+impl fmt::Display for InvalidImageView {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Self::DescriptorLayoutsUnavailable => {
+                write!(fmt, "the image of the view can't be used in descriptors")
+            }
+            Self::FormatMismatch { required, obtained } => write!(
+                fmt,
+                "the image view does not have the required format; required {:?}, obtained {:?}",
+                required, obtained,
+            ),
+            Self::LayoutInvalid {
+                descriptor_type,
+                layout,
+            } => write!(
+                fmt,
+                "the image uses layout {:?} in descriptors of type {:?}, which is not valid for them",
+                layout, descriptor_type,
+            ),
+            Self::MultisampledMismatch { required, obtained } => write!(
+                fmt,
+                "the image does not have the required multisampling; required {}, obtained {}",
+                required, obtained,
+            ),
+            Self::ViewTypeMismatch { required, obtained } => write!(
+                fmt,
+                "the image view does not have the required type; required {:?}, obtained {:?}",
+                required, obtained,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DescriptorWritesBuilder;
+    use super::DescriptorWritesBuilderError;
+    use crate::descriptor_set::layout::DescriptorSetDesc;
+    use crate::descriptor_set::layout::DescriptorType;
+    use crate::descriptor_set::sys::DescriptorWrite;
+    use crate::descriptor_set::DescriptorResourceType;
+    use crate::sampler::Sampler;
+    use crate::shader::DescriptorRequirements;
+    use crate::shader::ShaderStages;
+    use std::iter;
+
+    fn sampler_requirements(descriptor_count: u32) -> DescriptorRequirements {
+        DescriptorRequirements {
+            descriptor_types: vec![DescriptorType::Sampler],
+            descriptor_count,
+            format: None,
+            image_view_type: None,
+            multisampled: false,
+            mutable: false,
+            stages: ShaderStages::all(),
+        }
+    }
+
+    #[test]
+    fn valid_write() {
+        let (device, _) = gfx_dev_and_queue!();
+        let sampler = Sampler::simple_repeat_linear(device);
+        let reqs = sampler_requirements(2);
+
+        let mut builder = DescriptorWritesBuilder::new(0, iter::once(((0, 0), &reqs)));
+        builder
+            .add(unsafe { DescriptorWrite::sampler(0, 0, vec![sampler.clone(), sampler]) })
+            .unwrap();
+        assert_eq!(builder.build().len(), 1);
+    }
+
+    #[test]
+    fn binding_not_used() {
+        let (device, _) = gfx_dev_and_queue!();
+        let sampler = Sampler::simple_repeat_linear(device);
+        let reqs = sampler_requirements(1);
+
+        // Requirements of other sets are ignored.
+        let mut builder = DescriptorWritesBuilder::new(0, iter::once(((1, 0), &reqs)));
+        assert_eq!(
+            builder
+                .add(unsafe { DescriptorWrite::sampler(0, 0, vec![sampler]) })
+                .err(),
+            Some(DescriptorWritesBuilderError::BindingNotUsed { binding: 0 }),
+        );
+    }
+
+    #[test]
+    fn array_out_of_bounds() {
+        let (device, _) = gfx_dev_and_queue!();
+        let sampler = Sampler::simple_repeat_linear(device);
+        let reqs = sampler_requirements(2);
+
+        let mut builder = DescriptorWritesBuilder::new(0, iter::once(((0, 3), &reqs)));
+        assert_eq!(
+            builder
+                .add(unsafe { DescriptorWrite::sampler(3, 1, vec![sampler.clone(), sampler]) })
+                .err(),
+            Some(DescriptorWritesBuilderError::ArrayOutOfBounds {
+                binding: 3,
+                range: 1..3,
+                descriptor_count: 2,
+            }),
+        );
+    }
+
+    #[test]
+    fn runtime_array() {
+        let (device, _) = gfx_dev_and_queue!();
+        let sampler = Sampler::simple_repeat_linear(device);
+        let reqs = sampler_requirements(0);
+
+        let mut builder = DescriptorWritesBuilder::new(0, iter::once(((0, 0), &reqs)));
+        builder
+            .add(unsafe { DescriptorWrite::sampler(0, 100, vec![sampler]) })
+            .unwrap();
+    }
+
+    #[test]
+    fn array_index_overflow() {
+        let (device, _) = gfx_dev_and_queue!();
+        let sampler = Sampler::simple_repeat_linear(device);
+        let reqs = sampler_requirements(0);
+
+        let mut builder = DescriptorWritesBuilder::new(0, iter::once(((0, 0), &reqs)));
+        assert_eq!(
+            builder
+                .add(unsafe {
+                    DescriptorWrite::sampler(0, u32::MAX, vec![sampler.clone(), sampler])
+                })
+                .err(),
+            Some(DescriptorWritesBuilderError::ArrayIndexOverflow {
+                binding: 0,
+                first_array_element: u32::MAX,
+                num_elements: 2,
+            }),
+        );
+    }
+
+    #[test]
+    fn sampler_is_immutable() {
+        let (device, _) = gfx_dev_and_queue!();
+        let sampler = Sampler::simple_repeat_linear(device);
+        let reqs = sampler_requirements(1);
+
+        let mut layout = DescriptorSetDesc::new(iter::once(Some((&reqs).into())));
+        layout.set_immutable_samplers(0, iter::once(sampler.clone()));
+
+        let mut builder =
+            DescriptorWritesBuilder::new(0, iter::once(((0, 0), &reqs))).with_layout(&layout);
+        assert_eq!(
+            builder
+                .add(unsafe { DescriptorWrite::sampler(0, 0, vec![sampler]) })
+                .err(),
+            Some(DescriptorWritesBuilderError::SamplerIsImmutable { binding: 0 }),
+        );
+    }
+
+    #[test]
+    fn resource_type_mismatch() {
+        let (device, _) = gfx_dev_and_queue!();
+        let sampler = Sampler::simple_repeat_linear(device);
+        let reqs = DescriptorRequirements {
+            descriptor_types: vec![DescriptorType::UniformBuffer],
+            ..sampler_requirements(1)
+        };
+
+        let mut builder = DescriptorWritesBuilder::new(0, iter::once(((0, 0), &reqs)));
+        assert_eq!(
+            builder
+                .add(unsafe { DescriptorWrite::sampler(0, 0, vec![sampler]) })
+                .err(),
+            Some(DescriptorWritesBuilderError::ResourceTypeMismatch {
+                binding: 0,
+                required: vec![DescriptorType::UniformBuffer],
+                provided: DescriptorResourceType::Sampler,
+            }),
+        );
+    }
+}
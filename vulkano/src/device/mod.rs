@@ -96,10 +96,16 @@ pub use self::properties::Properties;
 pub(crate) use self::properties::PropertiesFfi;
 use crate::check_errors;
 use crate::command_buffer::pool::StandardCommandPool;
+use crate::command_buffer::validity::check_debug_utils_enabled;
+use crate::command_buffer::validity::check_debug_utils_label;
+use crate::command_buffer::validity::CheckDebugUtilsLabelError;
+use crate::descriptor_set::layout::DescriptorSetLayout;
 use crate::descriptor_set::pool::StdDescriptorPool;
 pub use crate::device::extensions::DeviceExtensions;
+use crate::device::physical::MemoryHeapBudget;
 use crate::device::physical::PhysicalDevice;
 use crate::device::physical::QueueFamily;
+use crate::device::physical::TimeDomain;
 pub use crate::extensions::{
     ExtensionRestriction, ExtensionRestrictionError, SupportedExtensionsError,
 };
@@ -110,8 +116,13 @@ use crate::image::ImageFormatProperties;
 use crate::image::ImageTiling;
 use crate::image::ImageType;
 use crate::image::ImageUsage;
+use crate::instance::debug::DebugUtilsLabel;
 use crate::instance::Instance;
 use crate::memory::pool::StdMemoryPool;
+use crate::pipeline::layout::PipelineLayout;
+use crate::sampler::Sampler;
+use crate::sampler::SamplerKey;
+use crate::shader::ShaderModule;
 use crate::Error;
 use crate::OomError;
 use crate::SynchronizedVulkanObject;
@@ -129,10 +140,13 @@ use std::fmt;
 use std::hash::BuildHasherDefault;
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::iter;
 use std::mem;
 use std::mem::MaybeUninit;
 use std::ops::Deref;
 use std::ptr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::MutexGuard;
@@ -158,15 +172,27 @@ pub struct Device {
     standard_descriptor_pool: Mutex<Weak<StdDescriptorPool>>,
     standard_command_pools:
         Mutex<HashMap<u32, Weak<StandardCommandPool>, BuildHasherDefault<FnvHasher>>>,
+    pipeline_layout_cache: Mutex<Vec<Weak<PipelineLayout>>>,
+    object_deduplication: AtomicBool,
+    shader_module_cache: Mutex<Vec<(u64, Vec<u32>, Weak<ShaderModule>)>>,
+    sampler_cache: Mutex<Vec<(SamplerKey, Weak<Sampler>)>>,
+    descriptor_set_layout_cache: Mutex<Vec<Weak<DescriptorSetLayout>>>,
     features: Features,
     extensions: DeviceExtensions,
     active_queue_families: SmallVec<[u32; 8]>,
     allocation_count: Mutex<u32>,
+    memory_budget_callback: Mutex<Option<MemoryBudgetCallback>>,
     fence_pool: Mutex<Vec<ash::vk::Fence>>,
     semaphore_pool: Mutex<Vec<ash::vk::Semaphore>>,
     event_pool: Mutex<Vec<ash::vk::Event>>,
 }
 
+// Callback called by `Device::check_memory_budget`, along with its threshold.
+struct MemoryBudgetCallback {
+    threshold: f32,
+    callback: Box<dyn FnMut(MemoryHeapBudget) + Send>,
+}
+
 // The `StandardCommandPool` type doesn't implement Send/Sync, so we have to manually reimplement
 // them for the device itself.
 unsafe impl Send for Device {}
@@ -379,6 +405,11 @@ impl Device {
             standard_pool: Mutex::new(Weak::new()),
             standard_descriptor_pool: Mutex::new(Weak::new()),
             standard_command_pools: Mutex::new(Default::default()),
+            pipeline_layout_cache: Mutex::new(Vec::new()),
+            object_deduplication: AtomicBool::new(false),
+            shader_module_cache: Mutex::new(Vec::new()),
+            sampler_cache: Mutex::new(Vec::new()),
+            descriptor_set_layout_cache: Mutex::new(Vec::new()),
             features: Features {
                 // Always enabled ; see above
                 robust_buffer_access: true,
@@ -387,6 +418,7 @@ impl Device {
             extensions: requested_extensions.clone(),
             active_queue_families,
             allocation_count: Mutex::new(0),
+            memory_budget_callback: Mutex::new(None),
             fence_pool: Mutex::new(Vec::new()),
             semaphore_pool: Mutex::new(Vec::new()),
             event_pool: Mutex::new(Vec::new()),
@@ -402,6 +434,37 @@ impl Device {
         Ok((device, queues))
     }
 
+    /// Builds a new Vulkan device with a single queue that supports compute operations, for
+    /// applications that don't draw anything.
+    ///
+    /// The queue family is chosen among the families of `physical_device` that support compute
+    /// operations. Families that don't support graphics operations are preferred, as they are
+    /// usually dedicated to compute work. No surface or swapchain is needed, and the only
+    /// extensions that are enabled are `requested_extensions`.
+    ///
+    /// Returns `DeviceCreationError::NoComputeQueueFamily` if no queue family of the physical
+    /// device supports compute operations.
+    pub fn new_compute_only(
+        physical_device: PhysicalDevice,
+        requested_features: &Features,
+        requested_extensions: &DeviceExtensions,
+    ) -> Result<(Arc<Device>, Arc<Queue>), DeviceCreationError> {
+        let queue_family = physical_device
+            .queue_families()
+            .filter(|family| family.supports_compute())
+            .min_by_key(|family| family.supports_graphics())
+            .ok_or(DeviceCreationError::NoComputeQueueFamily)?;
+
+        let (device, mut queues) = Device::new(
+            physical_device,
+            requested_features,
+            requested_extensions,
+            iter::once((queue_family, 0.5)),
+        )?;
+
+        Ok((device, queues.next().unwrap()))
+    }
+
     /// Returns the Vulkan version supported by the device.
     ///
     /// This is the lower of the
@@ -470,6 +533,68 @@ impl Device {
         &self.extensions
     }
 
+    /// Sets a callback that is called every time memory is allocated on this device from a heap
+    /// whose usage has reached `threshold` times its budget. Streaming systems can use it to
+    /// know when to evict resources, before allocations start failing. Any previous callback is
+    /// replaced.
+    ///
+    /// The budgets are those of
+    /// [`PhysicalDevice::memory_budget`](crate::device::physical::PhysicalDevice::memory_budget),
+    /// and the callback is never called if the `ext_memory_budget` extension isn't enabled on
+    /// the device.
+    ///
+    /// The callback is called on the thread that allocates the memory, and must not allocate
+    /// memory itself. To handle the event on another thread, send it through a channel.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `threshold` is not between 0.0 and 1.0.
+    ///
+    pub fn set_memory_budget_callback<F>(&self, threshold: f32, callback: F)
+    where
+        F: FnMut(MemoryHeapBudget) + Send + 'static,
+    {
+        assert!((0.0..=1.0).contains(&threshold));
+
+        *self.memory_budget_callback.lock().unwrap() = Some(MemoryBudgetCallback {
+            threshold,
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Removes the callback set with `set_memory_budget_callback`.
+    #[inline]
+    pub fn clear_memory_budget_callback(&self) {
+        *self.memory_budget_callback.lock().unwrap() = None;
+    }
+
+    /// Calls the memory budget callback if the usage of the given heap has reached the
+    /// threshold. Called after every allocation.
+    pub(crate) fn check_memory_budget(&self, heap_index: u32) {
+        if !self.extensions.ext_memory_budget {
+            return;
+        }
+
+        let mut callback = self.memory_budget_callback.lock().unwrap();
+        let callback = match callback.as_mut() {
+            Some(callback) => callback,
+            None => return,
+        };
+
+        let heap_budget = match self
+            .physical_device()
+            .memory_budget()
+            .and_then(|budgets| budgets.into_iter().nth(heap_index as usize))
+        {
+            Some(heap_budget) => heap_budget,
+            None => return,
+        };
+
+        if heap_budget.usage as f64 >= heap_budget.budget as f64 * callback.threshold as f64 {
+            (callback.callback)(heap_budget);
+        }
+    }
+
     /// Returns the standard memory pool used by default if you don't provide any other pool.
     pub fn standard_pool(me: &Arc<Self>) -> Arc<StdMemoryPool> {
         let mut pool = me.standard_pool.lock().unwrap();
@@ -498,6 +623,55 @@ impl Device {
         new_pool
     }
 
+    /// Returns the pipeline layouts that were created with `PipelineLayout::get_or_create`.
+    #[inline]
+    pub(crate) fn pipeline_layout_cache(&self) -> &Mutex<Vec<Weak<PipelineLayout>>> {
+        &self.pipeline_layout_cache
+    }
+
+    /// Enables or disables the deduplication of shader modules, samplers and descriptor set
+    /// layouts.
+    ///
+    /// When enabled, creating one of these objects returns an existing object of this device
+    /// that was created with the same parameters and is still alive, instead of creating a new
+    /// one. Shader modules are compared by the hash of their SPIR-V code, then by the code itself.
+    /// This reduces the number of driver objects for applications that create the same objects
+    /// many times, for example once per material, at the cost of a lookup at creation time.
+    ///
+    /// Objects that were created while deduplication was disabled are never returned.
+    /// Deduplication is disabled by default.
+    #[inline]
+    pub fn set_object_deduplication(&self, enable: bool) {
+        self.object_deduplication.store(enable, Ordering::Relaxed);
+    }
+
+    /// Returns whether the deduplication of shader modules, samplers and descriptor set layouts
+    /// is enabled. See [`set_object_deduplication`](Device::set_object_deduplication).
+    #[inline]
+    pub fn object_deduplication(&self) -> bool {
+        self.object_deduplication.load(Ordering::Relaxed)
+    }
+
+    /// Returns the shader modules that were created while object deduplication was enabled,
+    /// with the hash of their code and the code itself.
+    #[inline]
+    pub(crate) fn shader_module_cache(&self) -> &Mutex<Vec<(u64, Vec<u32>, Weak<ShaderModule>)>> {
+        &self.shader_module_cache
+    }
+
+    /// Returns the samplers that were created while object deduplication was enabled.
+    #[inline]
+    pub(crate) fn sampler_cache(&self) -> &Mutex<Vec<(SamplerKey, Weak<Sampler>)>> {
+        &self.sampler_cache
+    }
+
+    /// Returns the descriptor set layouts that were created while object deduplication was
+    /// enabled.
+    #[inline]
+    pub(crate) fn descriptor_set_layout_cache(&self) -> &Mutex<Vec<Weak<DescriptorSetLayout>>> {
+        &self.descriptor_set_layout_cache
+    }
+
     /// Returns the standard command buffer pool used by default if you don't provide any other
     /// pool.
     ///
@@ -625,6 +799,57 @@ impl Device {
             }
         }
     }
+
+    /// Samples the clocks of `time_domains` at the same time, so that timestamps taken in
+    /// different time domains can be correlated. For example, sampling `TimeDomain::Device`
+    /// with a CPU clock allows converting the results of timestamp queries to CPU time.
+    ///
+    /// Returns one timestamp for each element of `time_domains`, and the maximum deviation
+    /// between the sampled timestamps in nanoseconds.
+    ///
+    /// The `ext_calibrated_timestamps` extension must be enabled on the device, and the time
+    /// domains must be among the
+    /// [`calibrateable_time_domains`](crate::device::physical::PhysicalDevice::calibrateable_time_domains)
+    /// of the physical device.
+    pub fn calibrated_timestamps(
+        &self,
+        time_domains: &[TimeDomain],
+    ) -> Result<(Vec<u64>, u64), CalibratedTimestampsError> {
+        if !self.enabled_extensions().ext_calibrated_timestamps {
+            return Err(CalibratedTimestampsError::ExtensionNotEnabled);
+        }
+
+        let supported = self.physical_device().calibrateable_time_domains()?;
+        if let Some(&time_domain) = time_domains
+            .iter()
+            .find(|time_domain| !supported.contains(time_domain))
+        {
+            return Err(CalibratedTimestampsError::TimeDomainNotSupported { time_domain });
+        }
+
+        let infos: SmallVec<[_; 4]> = time_domains
+            .iter()
+            .map(|&time_domain| ash::vk::CalibratedTimestampInfoEXT {
+                time_domain: time_domain.into(),
+                ..Default::default()
+            })
+            .collect();
+        let mut timestamps = vec![0; time_domains.len()];
+        let mut max_deviation = 0;
+
+        unsafe {
+            let fns = self.fns();
+            check_errors(fns.ext_calibrated_timestamps.get_calibrated_timestamps_ext(
+                self.internal_object(),
+                infos.len() as u32,
+                infos.as_ptr(),
+                timestamps.as_mut_ptr(),
+                &mut max_deviation,
+            ))?;
+        }
+
+        Ok((timestamps, max_deviation))
+    }
 }
 
 impl fmt::Debug for Device {
@@ -783,6 +1008,8 @@ pub enum DeviceCreationError {
     ExtensionRestrictionNotMet(ExtensionRestrictionError),
     /// A restriction for a feature was not met.
     FeatureRestrictionNotMet(FeatureRestrictionError),
+    /// None of the queue families of the physical device supports compute operations.
+    NoComputeQueueFamily,
 }
 
 impl error::Error for DeviceCreationError {}
@@ -825,6 +1052,12 @@ impl fmt::Display for DeviceCreationError {
             }
             DeviceCreationError::ExtensionRestrictionNotMet(err) => err.fmt(fmt),
             DeviceCreationError::FeatureRestrictionNotMet(err) => err.fmt(fmt),
+            DeviceCreationError::NoComputeQueueFamily => {
+                write!(
+                    fmt,
+                    "none of the queue families of the physical device supports compute operations"
+                )
+            }
         }
     }
 }
@@ -859,6 +1092,71 @@ impl From<FeatureRestrictionError> for DeviceCreationError {
     }
 }
 
+/// Error that can be returned when sampling calibrated timestamps.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CalibratedTimestampsError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The `ext_calibrated_timestamps` extension was not enabled.
+    ExtensionNotEnabled,
+    /// The time domain can't be sampled by the physical device.
+    TimeDomainNotSupported {
+        /// The time domain that isn't supported.
+        time_domain: TimeDomain,
+    },
+}
+
+impl error::Error for CalibratedTimestampsError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            CalibratedTimestampsError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CalibratedTimestampsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                CalibratedTimestampsError::OomError(_) => "not enough memory",
+                CalibratedTimestampsError::ExtensionNotEnabled => {
+                    "the `ext_calibrated_timestamps` extension was not enabled"
+                }
+                CalibratedTimestampsError::TimeDomainNotSupported { .. } => {
+                    "the time domain can't be sampled by the physical device"
+                }
+            }
+        )
+    }
+}
+
+impl From<OomError> for CalibratedTimestampsError {
+    #[inline]
+    fn from(err: OomError) -> CalibratedTimestampsError {
+        CalibratedTimestampsError::OomError(err)
+    }
+}
+
+impl From<Error> for CalibratedTimestampsError {
+    #[inline]
+    fn from(err: Error) -> CalibratedTimestampsError {
+        match err {
+            err @ Error::OutOfHostMemory => {
+                CalibratedTimestampsError::OomError(OomError::from(err))
+            }
+            err @ Error::OutOfDeviceMemory => {
+                CalibratedTimestampsError::OomError(OomError::from(err))
+            }
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
 /// Represents a queue where commands can be submitted.
 // TODO: should use internal synchronization?
 #[derive(Debug)]
@@ -911,6 +1209,119 @@ impl Queue {
             Ok(())
         }
     }
+
+    /// Opens a debug label region in the queue.
+    ///
+    /// Debugging tools show the work that is submitted to the queue until the matching
+    /// [`end_debug_utils_label`](Self::end_debug_utils_label) as a group with the name of the
+    /// label. See also [`debug_utils_label_scope`](Self::debug_utils_label_scope), which ends
+    /// the region automatically.
+    ///
+    /// The [`ext_debug_utils`](crate::instance::InstanceExtensions::ext_debug_utils) extension
+    /// must be enabled on the instance.
+    #[inline]
+    pub fn begin_debug_utils_label(
+        &self,
+        label: DebugUtilsLabel,
+    ) -> Result<(), CheckDebugUtilsLabelError> {
+        check_debug_utils_label(&self.device, &label)?;
+
+        unsafe {
+            let fns = self.device.instance().fns();
+            let queue = self.queue.lock().unwrap();
+            let label_name = CString::new(label.label_name).unwrap();
+            let info = ash::vk::DebugUtilsLabelEXT {
+                p_label_name: label_name.as_ptr(),
+                color: label.color,
+                ..Default::default()
+            };
+            fns.ext_debug_utils
+                .queue_begin_debug_utils_label_ext(*queue, &info);
+        }
+
+        Ok(())
+    }
+
+    /// Closes the debug label region that was most recently opened in the queue.
+    ///
+    /// # Safety
+    ///
+    /// - There must be an open debug label region in the queue, either from
+    ///   [`begin_debug_utils_label`](Self::begin_debug_utils_label), or from a command buffer
+    ///   that was submitted to the queue.
+    #[inline]
+    pub unsafe fn end_debug_utils_label(&self) -> Result<(), CheckDebugUtilsLabelError> {
+        check_debug_utils_enabled(&self.device)?;
+
+        let fns = self.device.instance().fns();
+        let queue = self.queue.lock().unwrap();
+        fns.ext_debug_utils.queue_end_debug_utils_label_ext(*queue);
+
+        Ok(())
+    }
+
+    /// Inserts a single debug label into the queue.
+    ///
+    /// The same requirements as for [`begin_debug_utils_label`](Self::begin_debug_utils_label)
+    /// apply.
+    #[inline]
+    pub fn insert_debug_utils_label(
+        &self,
+        label: DebugUtilsLabel,
+    ) -> Result<(), CheckDebugUtilsLabelError> {
+        check_debug_utils_label(&self.device, &label)?;
+
+        unsafe {
+            let fns = self.device.instance().fns();
+            let queue = self.queue.lock().unwrap();
+            let label_name = CString::new(label.label_name).unwrap();
+            let info = ash::vk::DebugUtilsLabelEXT {
+                p_label_name: label_name.as_ptr(),
+                color: label.color,
+                ..Default::default()
+            };
+            fns.ext_debug_utils
+                .queue_insert_debug_utils_label_ext(*queue, &info);
+        }
+
+        Ok(())
+    }
+
+    /// Opens a debug label region in the queue that is closed when the returned guard is dropped.
+    #[inline]
+    pub fn debug_utils_label_scope(
+        &self,
+        label: DebugUtilsLabel,
+    ) -> Result<QueueDebugUtilsLabelScope, CheckDebugUtilsLabelError> {
+        self.begin_debug_utils_label(label)?;
+        Ok(QueueDebugUtilsLabelScope { queue: self })
+    }
+}
+
+/// A debug label region of a queue, which is closed when the scope is dropped.
+///
+/// Returned by [`Queue::debug_utils_label_scope`].
+#[must_use = "The debug label region is closed as soon as the scope is dropped"]
+pub struct QueueDebugUtilsLabelScope<'a> {
+    queue: &'a Queue,
+}
+
+impl<'a> QueueDebugUtilsLabelScope<'a> {
+    /// Returns the queue of the region.
+    #[inline]
+    pub fn queue(&self) -> &'a Queue {
+        self.queue
+    }
+}
+
+impl<'a> Drop for QueueDebugUtilsLabelScope<'a> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            // The region was opened when creating the scope, and the extension was checked then.
+            let _ = self.queue.end_debug_utils_label();
+        }
+    }
 }
 
 impl PartialEq for Queue {
@@ -943,6 +1354,7 @@ mod tests {
     use crate::device::DeviceCreationError;
     use crate::device::DeviceExtensions;
     use crate::device::{FeatureRestriction, FeatureRestrictionError, Features};
+    use crate::sampler::Sampler;
     use std::sync::Arc;
 
     #[test]
@@ -951,6 +1363,37 @@ mod tests {
         assert!(Arc::get_mut(&mut device).is_some());
     }
 
+    #[test]
+    fn compute_only() {
+        let instance = instance!();
+        let physical = match PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return,
+        };
+
+        let (device, queue) =
+            Device::new_compute_only(physical, &Features::none(), &DeviceExtensions::none())
+                .unwrap();
+        assert!(queue.family().supports_compute());
+        assert_eq!(device.active_queue_families().len(), 1);
+    }
+
+    #[test]
+    fn object_deduplication() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let first = Sampler::simple_repeat_linear(device.clone());
+        let second = Sampler::simple_repeat_linear(device.clone());
+        assert!(!Arc::ptr_eq(&first, &second));
+
+        device.set_object_deduplication(true);
+        let first = Sampler::simple_repeat_linear(device.clone());
+        let second = Sampler::simple_repeat_linear(device.clone());
+        let other = Sampler::simple_repeat_linear_no_mipmap(device.clone());
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(!Arc::ptr_eq(&first, &other));
+    }
+
     #[test]
     fn too_many_queues() {
         let instance = instance!();
@@ -12,12 +12,14 @@ use crate::device::{DeviceExtensions, Features, FeaturesFfi, Properties, Propert
 use crate::instance::{Instance, InstanceCreationError};
 use crate::sync::PipelineStage;
 use crate::DeviceSize;
+use crate::OomError;
 use crate::Version;
 use crate::VulkanObject;
 use std::convert::TryFrom;
 use std::ffi::CStr;
 use std::fmt;
 use std::hash::Hash;
+use std::mem;
 use std::mem::MaybeUninit;
 use std::ptr;
 use std::sync::Arc;
@@ -476,6 +478,54 @@ impl<'a> PhysicalDevice<'a> {
         }
     }
 
+    /// Queries the current budget and usage of all the memory heaps on this physical device, in
+    /// the same order as `memory_heaps`.
+    ///
+    /// The values change as memory is allocated and freed, by this process and by others, so they
+    /// are queried again every time this function is called.
+    ///
+    /// Returns `None` if the physical device doesn't support the `ext_memory_budget` extension.
+    pub fn memory_budget(&self) -> Option<Vec<MemoryHeapBudget>> {
+        if !self.supported_extensions().ext_memory_budget
+            || !(self.instance.api_version() >= Version::V1_1
+                || self
+                    .instance
+                    .enabled_extensions()
+                    .khr_get_physical_device_properties2)
+        {
+            return None;
+        }
+
+        let budget_properties = unsafe {
+            let fns = self.instance.fns();
+            let mut budget_properties = ash::vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+            let mut output = ash::vk::PhysicalDeviceMemoryProperties2KHR {
+                p_next: &mut budget_properties as *mut _ as *mut _,
+                ..Default::default()
+            };
+
+            if self.instance.api_version() >= Version::V1_1 {
+                fns.v1_1
+                    .get_physical_device_memory_properties2(self.info.handle, &mut output);
+            } else {
+                fns.khr_get_physical_device_properties2
+                    .get_physical_device_memory_properties2_khr(self.info.handle, &mut output);
+            }
+
+            budget_properties
+        };
+
+        Some(
+            (0..self.info.memory_properties.memory_heap_count)
+                .map(|heap_index| MemoryHeapBudget {
+                    heap_index,
+                    budget: budget_properties.heap_budget[heap_index as usize],
+                    usage: budget_properties.heap_usage[heap_index as usize],
+                })
+                .collect(),
+        )
+    }
+
     /// Builds an iterator that enumerates all the queue families on this physical device.
     #[inline]
     pub fn queue_families(&self) -> impl ExactSizeIterator<Item = QueueFamily<'a>> {
@@ -504,6 +554,55 @@ impl<'a> PhysicalDevice<'a> {
             None
         }
     }
+
+    /// Returns the time domains that can be sampled with
+    /// [`Device::calibrated_timestamps`](crate::device::Device::calibrated_timestamps).
+    ///
+    /// Returns an empty list if the `ext_calibrated_timestamps` extension is not supported.
+    pub fn calibrateable_time_domains(&self) -> Result<Vec<TimeDomain>, OomError> {
+        if !self.supported_extensions().ext_calibrated_timestamps {
+            return Ok(Vec::new());
+        }
+
+        // This is a physical device function of a device extension, so it isn't part of the
+        // function pointers of the instance.
+        let instance = self.instance();
+        let fns_i = instance.fns();
+        let fns = ash::vk::ExtCalibratedTimestampsFn::load(|name| unsafe {
+            mem::transmute(
+                fns_i
+                    .v1_0
+                    .get_instance_proc_addr(instance.internal_object(), name.as_ptr()),
+            )
+        });
+
+        let num = unsafe {
+            let mut num = 0;
+            check_errors(fns.get_physical_device_calibrateable_time_domains_ext(
+                self.internal_object(),
+                &mut num,
+                ptr::null_mut(),
+            ))?;
+            num
+        };
+
+        let time_domains: Vec<ash::vk::TimeDomainEXT> = unsafe {
+            let mut time_domains = Vec::with_capacity(num as usize);
+            let mut num = num;
+            check_errors(fns.get_physical_device_calibrateable_time_domains_ext(
+                self.internal_object(),
+                &mut num,
+                time_domains.as_mut_ptr(),
+            ))?;
+            time_domains.set_len(num as usize);
+            time_domains
+        };
+
+        Ok(time_domains
+            .into_iter()
+            .filter_map(|time_domain| TimeDomain::try_from(time_domain).ok())
+            .collect())
+    }
 }
 
 unsafe impl<'a> VulkanObject for PhysicalDevice<'a> {
@@ -554,6 +653,42 @@ impl TryFrom<ash::vk::PhysicalDeviceType> for PhysicalDeviceType {
     }
 }
 
+/// A clock that timestamps can be sampled from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum TimeDomain {
+    /// The clock of the device, which is used by timestamp queries.
+    Device = ash::vk::TimeDomainEXT::DEVICE.as_raw(),
+    /// The `CLOCK_MONOTONIC` clock of POSIX platforms.
+    ClockMonotonic = ash::vk::TimeDomainEXT::CLOCK_MONOTONIC.as_raw(),
+    /// The `CLOCK_MONOTONIC_RAW` clock of POSIX platforms.
+    ClockMonotonicRaw = ash::vk::TimeDomainEXT::CLOCK_MONOTONIC_RAW.as_raw(),
+    /// The clock of `QueryPerformanceCounter` on Windows.
+    QueryPerformanceCounter = ash::vk::TimeDomainEXT::QUERY_PERFORMANCE_COUNTER.as_raw(),
+}
+
+impl TryFrom<ash::vk::TimeDomainEXT> for TimeDomain {
+    type Error = ();
+
+    #[inline]
+    fn try_from(val: ash::vk::TimeDomainEXT) -> Result<Self, Self::Error> {
+        match val {
+            ash::vk::TimeDomainEXT::DEVICE => Ok(Self::Device),
+            ash::vk::TimeDomainEXT::CLOCK_MONOTONIC => Ok(Self::ClockMonotonic),
+            ash::vk::TimeDomainEXT::CLOCK_MONOTONIC_RAW => Ok(Self::ClockMonotonicRaw),
+            ash::vk::TimeDomainEXT::QUERY_PERFORMANCE_COUNTER => Ok(Self::QueryPerformanceCounter),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<TimeDomain> for ash::vk::TimeDomainEXT {
+    #[inline]
+    fn from(val: TimeDomain) -> Self {
+        Self::from_raw(val as i32)
+    }
+}
+
 /// Represents a memory type in a physical device.
 #[derive(Debug, Copy, Clone)]
 pub struct MemoryType<'a> {
@@ -667,6 +802,19 @@ impl<'a> MemoryHeap<'a> {
     }
 }
 
+/// Budget and usage of a memory heap, as returned by
+/// [`PhysicalDevice::memory_budget`](PhysicalDevice::memory_budget).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryHeapBudget {
+    /// Index of the memory heap.
+    pub heap_index: u32,
+    /// Approximate amount of memory in bytes that the process can use from the heap. Going over
+    /// it may make allocations fail or degrade performance.
+    pub budget: DeviceSize,
+    /// Approximate amount of memory in bytes that the process currently uses from the heap.
+    pub usage: DeviceSize,
+}
+
 /// Represents a queue family in a physical device.
 ///
 /// A queue family is group of one or multiple queues. All queues of one family have the same
@@ -751,7 +899,11 @@ impl<'a> QueueFamily<'a> {
     /// Returns `true` if the queues of this family support a particular pipeline stage.
     #[inline]
     pub fn supports_stage(&self, stage: PipelineStage) -> bool {
-        !(self.properties.queue_flags & stage.required_queue_flags()).is_empty()
+        let required_queue_flags = stage.required_queue_flags();
+
+        // Stages that don't require any queue flags are supported by all queue families.
+        required_queue_flags.is_empty()
+            || !(self.properties.queue_flags & required_queue_flags).is_empty()
     }
 }
 
@@ -97,6 +97,7 @@ use crate::device::physical::PhysicalDevice;
 use crate::image::ImageAspects;
 use crate::shader::spirv::ImageFormat;
 use crate::DeviceSize;
+use crate::Version;
 use crate::VulkanObject;
 use half::f16;
 use std::convert::TryFrom;
@@ -141,6 +142,76 @@ impl Format {
         }
     }
 
+    /// Retrieves the DRM format modifiers that `physical_device` supports for this format, with
+    /// the features that images using each of them support.
+    ///
+    /// Returns an empty list if the physical device doesn't support the
+    /// `ext_image_drm_format_modifier` extension.
+    pub fn drm_format_modifier_properties(
+        &self,
+        physical_device: PhysicalDevice,
+    ) -> Vec<DrmFormatModifierProperties> {
+        let instance = physical_device.instance();
+
+        if !physical_device
+            .supported_extensions()
+            .ext_image_drm_format_modifier
+            || !(instance.api_version() >= Version::V1_1
+                || instance
+                    .enabled_extensions()
+                    .khr_get_physical_device_properties2)
+        {
+            return Vec::new();
+        }
+
+        let query = |list: &mut ash::vk::DrmFormatModifierPropertiesListEXT| unsafe {
+            let fns_i = instance.fns();
+            let mut properties = ash::vk::FormatProperties2 {
+                p_next: list as *mut _ as *mut _,
+                ..Default::default()
+            };
+
+            if instance.api_version() >= Version::V1_1 {
+                fns_i.v1_1.get_physical_device_format_properties2(
+                    physical_device.internal_object(),
+                    (*self).into(),
+                    &mut properties,
+                );
+            } else {
+                fns_i
+                    .khr_get_physical_device_properties2
+                    .get_physical_device_format_properties2_khr(
+                        physical_device.internal_object(),
+                        (*self).into(),
+                        &mut properties,
+                    );
+            }
+        };
+
+        let mut list = ash::vk::DrmFormatModifierPropertiesListEXT::default();
+        query(&mut list);
+
+        let mut modifiers = Vec::with_capacity(list.drm_format_modifier_count as usize);
+        list.p_drm_format_modifier_properties = modifiers.as_mut_ptr();
+        query(&mut list);
+        unsafe {
+            modifiers.set_len(list.drm_format_modifier_count as usize);
+        }
+
+        modifiers
+            .into_iter()
+            .map(|properties: ash::vk::DrmFormatModifierPropertiesEXT| {
+                DrmFormatModifierProperties {
+                    drm_format_modifier: properties.drm_format_modifier,
+                    drm_format_modifier_plane_count: properties.drm_format_modifier_plane_count,
+                    drm_format_modifier_tiling_features: properties
+                        .drm_format_modifier_tiling_features
+                        .into(),
+                }
+            })
+            .collect()
+    }
+
     #[inline]
     pub fn decode_clear_value(&self, value: ClearValue) -> ClearValue {
         let aspects = self.aspects();
@@ -567,6 +638,18 @@ pub struct FormatProperties {
     pub buffer_features: FormatFeatures,
 }
 
+/// The properties of a DRM format modifier of an image format, as returned by
+/// [`Format::drm_format_modifier_properties`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DrmFormatModifierProperties {
+    /// The DRM format modifier.
+    pub drm_format_modifier: u64,
+    /// The number of memory planes of an image with this modifier.
+    pub drm_format_modifier_plane_count: u32,
+    /// Features available for images with this modifier.
+    pub drm_format_modifier_tiling_features: FormatFeatures,
+}
+
 /// The features supported by a device for images with a particular format.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 #[allow(missing_docs)]
@@ -597,6 +680,7 @@ pub struct FormatFeatures {
     pub img_sampled_image_filter_cubic: bool,
     pub khr_acceleration_structure_vertex_buffer: bool,
     pub ext_fragment_density_map: bool,
+    pub khr_fragment_shading_rate_attachment: bool,
 }
 
 impl From<ash::vk::FormatFeatureFlags> for FormatFeatures {
@@ -630,6 +714,7 @@ impl From<ash::vk::FormatFeatureFlags> for FormatFeatures {
             img_sampled_image_filter_cubic: !(val & ash::vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_CUBIC_IMG).is_empty(),
             khr_acceleration_structure_vertex_buffer: !(val & ash::vk::FormatFeatureFlags::ACCELERATION_STRUCTURE_VERTEX_BUFFER_KHR).is_empty(),
             ext_fragment_density_map: !(val & ash::vk::FormatFeatureFlags::FRAGMENT_DENSITY_MAP_EXT).is_empty(),
+            khr_fragment_shading_rate_attachment: !(val & ash::vk::FormatFeatureFlags::FRAGMENT_SHADING_RATE_ATTACHMENT_KHR).is_empty(),
         }
     }
 }
@@ -20,8 +20,11 @@ use crate::image::ImageDescriptorLayouts;
 use crate::image::ImageDimensions;
 use crate::image::ImageInner;
 use crate::image::ImageLayout;
+use crate::image::ImageTiling;
+use crate::image::ImageType;
 use crate::image::ImageUsage;
 use crate::image::SampleCount;
+use crate::image::SampleCounts;
 #[cfg(any(
     target_os = "linux",
     target_os = "dragonflybsd",
@@ -35,6 +38,7 @@ use crate::memory::pool::AllocLayout;
 use crate::memory::pool::MappingRequirement;
 use crate::memory::pool::MemoryPool;
 use crate::memory::pool::MemoryPoolAlloc;
+use crate::memory::pool::MemoryUsage;
 use crate::memory::pool::PotentialDedicatedAllocation;
 use crate::memory::pool::StdMemoryPoolAlloc;
 use crate::memory::DedicatedAlloc;
@@ -100,6 +104,10 @@ use std::sync::Arc;
 /// or a depth buffer that is only used once) then use a transient image as it may improve
 /// performance.
 ///
+/// The memory of transient `AttachmentImage`s is still allocated from device-local memory. Use a
+/// [`TransientAttachmentImage`](crate::image::TransientAttachmentImage) to also allocate it from
+/// lazily-allocated memory where available.
+///
 // TODO: forbid reading transient images outside render passes?
 #[derive(Debug)]
 pub struct AttachmentImage<A = PotentialDedicatedAllocation<StdMemoryPoolAlloc>> {
@@ -430,7 +438,83 @@ impl AttachmentImage {
         AttachmentImage::new_impl(device, dimensions, 1, format, base_usage, samples)
     }
 
+    /// Creates a transient multisampled image, and the image with one sample that it is resolved
+    /// into. `usage` is added to the usages of the second image, so that it can for example be
+    /// sampled or presented afterwards.
+    ///
+    /// The images must be attachments of the same subpass, with the second one registered as the
+    /// resolve attachment of the first one. See
+    /// [`SubpassDesc::add_resolved_color_attachment`](crate::render_pass::SubpassDesc::add_resolved_color_attachment).
+    ///
+    /// Use [`max_sample_count`](AttachmentImage::max_sample_count) to choose `samples`.
+    pub fn multisampled_with_resolve(
+        device: Arc<Device>,
+        dimensions: [u32; 2],
+        samples: SampleCount,
+        format: Format,
+        usage: ImageUsage,
+    ) -> Result<(Arc<AttachmentImage>, Arc<AttachmentImage>), ImageCreationError> {
+        let multisampled =
+            AttachmentImage::transient_multisampled(device.clone(), dimensions, samples, format)?;
+        let resolve =
+            AttachmentImage::new_impl(device, dimensions, 1, format, usage, SampleCount::Sample1)?;
+
+        Ok((multisampled, resolve))
+    }
+
+    /// Returns the sample counts that an attachment image with the given format supports, where
+    /// `usage` is the usages in addition to `color_attachment` or `depth_stencil_attachment`.
+    pub fn supported_sample_counts(
+        device: &Device,
+        format: Format,
+        usage: ImageUsage,
+    ) -> SampleCounts {
+        let aspects = format.aspects();
+        let is_depth = aspects.depth || aspects.stencil;
+
+        let usage = ImageUsage {
+            color_attachment: !is_depth,
+            depth_stencil_attachment: is_depth,
+            ..usage
+        };
+
+        // The sample counts take the framebuffer limits of the device into account.
+        device
+            .image_format_properties(
+                format,
+                ImageType::Dim2d,
+                ImageTiling::Optimal,
+                usage,
+                ImageCreateFlags::none(),
+            )
+            .map(|properties| properties.sample_counts)
+            .unwrap_or_default()
+    }
+
+    /// Returns the highest sample count that attachment images of all the given formats support,
+    /// with the additional `usage`. This is the sample count to use for the attachments of a
+    /// multisampled subpass.
+    ///
+    /// Returns `Sample1` if `formats` is empty or if the formats only support one sample.
+    pub fn max_sample_count<I>(device: &Device, formats: I, usage: ImageUsage) -> SampleCount
+    where
+        I: IntoIterator<Item = Format>,
+    {
+        formats
+            .into_iter()
+            .map(|format| AttachmentImage::supported_sample_counts(device, format, usage))
+            .fold(None, |counts: Option<SampleCounts>, format_counts| {
+                Some(match counts {
+                    Some(counts) => counts.intersection(&format_counts),
+                    None => format_counts,
+                })
+            })
+            .and_then(|counts| counts.max_count())
+            .unwrap_or(SampleCount::Sample1)
+    }
+
     // All constructors dispatch to this one.
+    #[inline]
     fn new_impl(
         device: Arc<Device>,
         dimensions: [u32; 2],
@@ -439,6 +523,132 @@ impl AttachmentImage {
         base_usage: ImageUsage,
         samples: SampleCount,
     ) -> Result<Arc<AttachmentImage>, ImageCreationError> {
+        AttachmentImage::with_pool(
+            &Device::standard_pool(&device),
+            dimensions,
+            array_layers,
+            samples,
+            format,
+            base_usage,
+        )
+    }
+
+    /// Same as `multisampled_with_usage_with_layers`, but allocates the memory of the image from
+    /// `pool` instead of the standard pool of the device.
+    pub fn with_pool<P>(
+        pool: &P,
+        dimensions: [u32; 2],
+        array_layers: u32,
+        samples: SampleCount,
+        format: Format,
+        base_usage: ImageUsage,
+    ) -> Result<Arc<AttachmentImage<PotentialDedicatedAllocation<P::Alloc>>>, ImageCreationError>
+    where
+        P: MemoryPool,
+    {
+        let dimensions = ImageDimensions::Dim2d {
+            width: dimensions[0],
+            height: dimensions[1],
+            array_layers,
+        };
+
+        AttachmentImage::with_pool_impl(
+            pool,
+            dimensions,
+            ImageCreateFlags::none(),
+            samples,
+            format,
+            base_usage,
+            false,
+        )
+        .map(Arc::new)
+    }
+
+    /// Creates a new three-dimensional image, for example a volume texture, that can be rendered
+    /// into through a two-dimensional array view of its depth slices.
+    ///
+    /// To render into the image, create an `ImageView` of type
+    /// [`Dim2dArray`](crate::image::view::ImageViewType::Dim2dArray) whose array layers are the
+    /// depth slices to render to, and attach it to a framebuffer. Each slice is then a layer of
+    /// the framebuffer, selected in shaders with `gl_Layer`.
+    ///
+    /// This requires Vulkan 1.1 or the `khr_maintenance1` extension, and returns
+    /// `CreationFlagRequirementsNotMet` otherwise.
+    #[inline]
+    pub fn with_usage_3d(
+        device: Arc<Device>,
+        dimensions: [u32; 3],
+        format: Format,
+        usage: ImageUsage,
+    ) -> Result<Arc<AttachmentImage>, ImageCreationError> {
+        let dimensions = ImageDimensions::Dim3d {
+            width: dimensions[0],
+            height: dimensions[1],
+            depth: dimensions[2],
+        };
+        let flags = ImageCreateFlags {
+            array_2d_compatible: true,
+            ..ImageCreateFlags::none()
+        };
+
+        AttachmentImage::with_pool_impl(
+            &Device::standard_pool(&device),
+            dimensions,
+            flags,
+            SampleCount::Sample1,
+            format,
+            usage,
+            false,
+        )
+        .map(Arc::new)
+    }
+
+    /// Creates a new image that holds `cube_count` cubes whose faces are `size` texels wide and
+    /// high, for example to render a shadow map of a point light or a reflection probe.
+    ///
+    /// Each face can be rendered to by attaching a view created with
+    /// [`with_cube_face`](crate::image::view::ImageViewBuilder::with_cube_face) to a framebuffer,
+    /// and the image can then be sampled through [`ImageView::cube`](crate::image::view::ImageView::cube)
+    /// if `usage` contains `sampled`.
+    #[inline]
+    pub fn with_usage_cube(
+        device: Arc<Device>,
+        size: u32,
+        cube_count: u32,
+        format: Format,
+        usage: ImageUsage,
+    ) -> Result<Arc<AttachmentImage>, ImageCreationError> {
+        let flags = ImageCreateFlags {
+            cube_compatible: true,
+            ..ImageCreateFlags::none()
+        };
+
+        AttachmentImage::with_pool_impl(
+            &Device::standard_pool(&device),
+            ImageDimensions::cube(size, cube_count),
+            flags,
+            SampleCount::Sample1,
+            format,
+            usage,
+            false,
+        )
+        .map(Arc::new)
+    }
+
+    // If `lazily_allocated` is true, the memory is allocated with `MemoryUsage::Transient`.
+    pub(super) fn with_pool_impl<P>(
+        pool: &P,
+        dimensions: ImageDimensions,
+        flags: ImageCreateFlags,
+        samples: SampleCount,
+        format: Format,
+        base_usage: ImageUsage,
+        lazily_allocated: bool,
+    ) -> Result<AttachmentImage<PotentialDedicatedAllocation<P::Alloc>>, ImageCreationError>
+    where
+        P: MemoryPool,
+    {
+        let device = pool.device();
         // TODO: check dimensions against the max_framebuffer_width/height/layers limits
 
         let aspects = format.aspects();
@@ -455,18 +665,12 @@ impl AttachmentImage {
         };
 
         let (image, mem_reqs) = unsafe {
-            let dims = ImageDimensions::Dim2d {
-                width: dimensions[0],
-                height: dimensions[1],
-                array_layers,
-            };
-
             UnsafeImage::new(
                 device.clone(),
                 usage,
                 format,
-                ImageCreateFlags::none(),
-                dims,
+                flags,
+                dimensions,
                 samples,
                 1,
                 Sharing::Exclusive::<Empty<u32>>,
@@ -475,14 +679,17 @@ impl AttachmentImage {
             )?
         };
 
+        let mut transient_filter = MemoryUsage::Transient.filter(device, mem_reqs.size);
         let memory = MemoryPool::alloc_from_requirements(
-            &Device::standard_pool(&device),
+            pool,
             &mem_reqs,
             AllocLayout::Optimal,
             MappingRequirement::DoNotMap,
             DedicatedAlloc::Image(&image),
             |t| {
-                if t.is_device_local() {
+                if lazily_allocated {
+                    transient_filter(t)
+                } else if t.is_device_local() {
                     AllocFromRequirementsFilter::Preferred
                 } else {
                     AllocFromRequirementsFilter::Allowed
@@ -494,7 +701,7 @@ impl AttachmentImage {
             image.bind_memory(memory.memory(), memory.offset())?;
         }
 
-        Ok(Arc::new(AttachmentImage {
+        Ok(AttachmentImage {
             image,
             memory,
             format,
@@ -505,7 +712,7 @@ impl AttachmentImage {
             },
             initialized: AtomicBool::new(false),
             gpu_lock: AtomicUsize::new(0),
-        }))
+        })
     }
 
     #[cfg(any(
@@ -621,6 +828,14 @@ impl AttachmentImage {
     }
 }
 
+impl<A> AttachmentImage<A> {
+    // Returns the memory that backs the image.
+    #[inline]
+    pub(super) fn memory(&self) -> &A {
+        &self.memory
+    }
+}
+
 unsafe impl<A> ImageAccess for AttachmentImage<A>
 where
     A: MemoryPoolAlloc,
@@ -786,6 +1001,12 @@ where
 mod tests {
     use super::AttachmentImage;
     use crate::format::Format;
+    use crate::image::view::{ImageView, ImageViewAbstract, ImageViewCreationError, ImageViewType};
+    use crate::image::CubeFace;
+    use crate::image::ImageAccess;
+    use crate::image::ImageUsage;
+    use crate::image::SampleCount;
+    use crate::Version;
 
     #[test]
     fn create_regular() {
@@ -799,9 +1020,102 @@ mod tests {
         let _img = AttachmentImage::transient(device, [32, 32], Format::R8G8B8A8_UNORM).unwrap();
     }
 
+    #[test]
+    fn multisampled_with_resolve() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let samples = AttachmentImage::max_sample_count(
+            &device,
+            [Format::R8G8B8A8_UNORM, Format::D16_UNORM],
+            ImageUsage::none(),
+        );
+        assert!(AttachmentImage::supported_sample_counts(
+            &device,
+            Format::D16_UNORM,
+            ImageUsage::none()
+        )
+        .contains(samples));
+
+        let (multisampled, resolve) = AttachmentImage::multisampled_with_resolve(
+            device,
+            [32, 32],
+            samples,
+            Format::R8G8B8A8_UNORM,
+            ImageUsage {
+                sampled: true,
+                ..ImageUsage::none()
+            },
+        )
+        .unwrap();
+        assert_eq!(multisampled.samples(), samples);
+        assert_eq!(resolve.samples(), SampleCount::Sample1);
+    }
+
     #[test]
     fn d16_unorm_always_supported() {
         let (device, _) = gfx_dev_and_queue!();
         let _img = AttachmentImage::new(device, [32, 32], Format::D16_UNORM).unwrap();
     }
+
+    #[test]
+    fn render_to_cube_faces() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let img = AttachmentImage::with_usage_cube(
+            device,
+            64,
+            1,
+            Format::R8G8B8A8_UNORM,
+            ImageUsage {
+                sampled: true,
+                ..ImageUsage::none()
+            },
+        )
+        .unwrap();
+
+        let cube = ImageView::cube(img.clone()).unwrap();
+        assert_eq!(cube.ty(), ImageViewType::Cube);
+
+        for &face in CubeFace::all().iter() {
+            let view = ImageView::start(img.clone())
+                .with_cube_face(face, 0)
+                .build()
+                .unwrap();
+            assert_eq!(view.array_layers(), face as u32..face as u32 + 1);
+        }
+    }
+
+    #[test]
+    fn render_to_3d_slices() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        if !(device.api_version() >= Version::V1_1 || device.enabled_extensions().khr_maintenance1)
+        {
+            return;
+        }
+
+        let img = AttachmentImage::with_usage_3d(
+            device,
+            [32, 32, 8],
+            Format::R8G8B8A8_UNORM,
+            ImageUsage::none(),
+        )
+        .unwrap();
+
+        let view = ImageView::start(img.clone())
+            .with_type(ImageViewType::Dim2dArray)
+            .with_array_layers(2..8)
+            .build()
+            .unwrap();
+        assert_eq!(view.array_layers(), 2..8);
+
+        match ImageView::start(img)
+            .with_type(ImageViewType::Dim2dArray)
+            .with_array_layers(0..9)
+            .build()
+        {
+            Err(ImageViewCreationError::ArrayLayersOutOfRange) => (),
+            _ => panic!(),
+        }
+    }
 }
@@ -0,0 +1,321 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::format::ClearValue;
+use crate::format::Format;
+use crate::image::sys::ImageCreationError;
+use crate::image::sys::UnsafeImage;
+use crate::image::traits::ImageAccess;
+use crate::image::traits::ImageClearValue;
+use crate::image::traits::ImageContent;
+use crate::image::ImageCreateFlags;
+use crate::image::ImageDescriptorLayouts;
+use crate::image::ImageDimensions;
+use crate::image::ImageInner;
+use crate::image::ImageLayout;
+use crate::image::ImageTiling;
+use crate::image::ImageType;
+use crate::image::ImageUsage;
+use crate::image::SampleCount;
+use crate::sync::AccessError;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// An image that was created outside of vulkano, for example by an OpenXR runtime.
+///
+/// The image is described by the parameters it was created with, and by the layout that it is
+/// in whenever vulkano may use it. Command buffers suppose that the image is in this layout when
+/// they start and transition it back to this layout at their end. For the images of an OpenXR
+/// swapchain, this is `ColorAttachmentOptimal` for color images and
+/// `DepthStencilAttachmentOptimal` for depth images, between the calls to
+/// `xrWaitSwapchainImage` and `xrReleaseSwapchainImage`.
+///
+/// Once created, an `ExternalImage` can be used in the same ways as the other images, such as
+/// a framebuffer attachment or a storage image, depending on its usage. The image is not
+/// destroyed when the `ExternalImage` is dropped, as it still belongs to whatever created it.
+#[derive(Debug)]
+pub struct ExternalImage {
+    image: UnsafeImage,
+
+    // The layout that the image is in when it is not used by a command buffer.
+    layout: ImageLayout,
+
+    // Number of times this image is locked on the GPU side.
+    gpu_lock: AtomicUsize,
+}
+
+impl ExternalImage {
+    /// Wraps an image handle that was created by something else than vulkano.
+    ///
+    /// Returns an error if the implementation doesn't support `format` with these `usage` and
+    /// `flags`, or if `usage` doesn't allow the image to be in `layout`.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid image of `device`, that is bound to memory and that was created
+    ///   with the given parameters and with optimal tiling.
+    /// - The image must be kept alive by its owner for as long as the `ExternalImage` and the
+    ///   command buffers that use it exist.
+    /// - The image must be in `layout` whenever a command buffer that uses it is executed, and
+    ///   its owner must not access it during the execution of such a command buffer. For the
+    ///   images of an OpenXR swapchain, this means that command buffers that use an image must
+    ///   only be submitted while it is acquired.
+    pub unsafe fn from_handle(
+        device: Arc<Device>,
+        handle: ash::vk::Image,
+        dimensions: ImageDimensions,
+        format: Format,
+        usage: ImageUsage,
+        flags: ImageCreateFlags,
+        samples: SampleCount,
+        mipmaps: u32,
+        layout: ImageLayout,
+    ) -> Result<Arc<ExternalImage>, ImageCreationError> {
+        if usage == ImageUsage::none() {
+            return Err(ImageCreationError::UnsupportedUsage);
+        }
+
+        let layout_supported = match layout {
+            ImageLayout::Undefined | ImageLayout::Preinitialized => false,
+            ImageLayout::General | ImageLayout::PresentSrc => true,
+            ImageLayout::ColorAttachmentOptimal => usage.color_attachment,
+            ImageLayout::DepthStencilAttachmentOptimal
+            | ImageLayout::DepthStencilReadOnlyOptimal => usage.depth_stencil_attachment,
+            ImageLayout::ShaderReadOnlyOptimal => usage.sampled || usage.input_attachment,
+            ImageLayout::TransferSrcOptimal => usage.transfer_source,
+            ImageLayout::TransferDstOptimal => usage.transfer_destination,
+            ImageLayout::FragmentShadingRateAttachmentOptimal => {
+                usage.fragment_shading_rate_attachment
+            }
+        };
+
+        if !layout_supported {
+            return Err(ImageCreationError::UnsupportedLayout { layout });
+        }
+
+        let ty = match dimensions {
+            ImageDimensions::Dim1d { .. } => ImageType::Dim1d,
+            ImageDimensions::Dim2d { .. } => ImageType::Dim2d,
+            ImageDimensions::Dim3d { .. } => ImageType::Dim3d,
+        };
+
+        if device
+            .image_format_properties(format, ty, ImageTiling::Optimal, usage, flags)
+            .is_err()
+        {
+            return Err(ImageCreationError::FormatNotSupported);
+        }
+
+        let image = UnsafeImage::from_raw(
+            device, handle, usage, format, flags, dimensions, samples, mipmaps,
+        );
+
+        Ok(Arc::new(ExternalImage {
+            image,
+            layout,
+            gpu_lock: AtomicUsize::new(0),
+        }))
+    }
+
+    /// Returns the layout that the image is in when it is not used by a command buffer.
+    #[inline]
+    pub fn layout(&self) -> ImageLayout {
+        self.layout
+    }
+}
+
+unsafe impl DeviceOwned for ExternalImage {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.image.device()
+    }
+}
+
+unsafe impl ImageAccess for ExternalImage {
+    #[inline]
+    fn inner(&self) -> ImageInner {
+        ImageInner {
+            image: &self.image,
+            first_layer: 0,
+            num_layers: self.image.dimensions().array_layers() as usize,
+            first_mipmap_level: 0,
+            num_mipmap_levels: self.image.mipmap_levels() as usize,
+        }
+    }
+
+    // The layout of the image is declared when it is created, so it must never be transitioned
+    // from `Undefined`.
+    #[inline]
+    fn is_layout_initialized(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn initial_layout_requirement(&self) -> ImageLayout {
+        self.layout
+    }
+
+    #[inline]
+    fn final_layout_requirement(&self) -> ImageLayout {
+        self.layout
+    }
+
+    #[inline]
+    fn descriptor_layouts(&self) -> Option<ImageDescriptorLayouts> {
+        if self.layout == ImageLayout::General {
+            Some(ImageDescriptorLayouts {
+                storage_image: ImageLayout::General,
+                combined_image_sampler: ImageLayout::General,
+                sampled_image: ImageLayout::General,
+                input_attachment: ImageLayout::General,
+            })
+        } else {
+            Some(ImageDescriptorLayouts {
+                storage_image: ImageLayout::General,
+                combined_image_sampler: ImageLayout::ShaderReadOnlyOptimal,
+                sampled_image: ImageLayout::ShaderReadOnlyOptimal,
+                input_attachment: ImageLayout::ShaderReadOnlyOptimal,
+            })
+        }
+    }
+
+    #[inline]
+    fn conflict_key(&self) -> u64 {
+        self.image.key()
+    }
+
+    #[inline]
+    fn try_gpu_lock(
+        &self,
+        _: bool,
+        _: bool,
+        expected_layout: ImageLayout,
+    ) -> Result<(), AccessError> {
+        if expected_layout != self.layout && expected_layout != ImageLayout::Undefined {
+            return Err(AccessError::UnexpectedImageLayout {
+                requested: expected_layout,
+                allowed: self.layout,
+            });
+        }
+
+        let val = self
+            .gpu_lock
+            .compare_exchange(0, 1, Ordering::SeqCst, Ordering::SeqCst)
+            .unwrap_or_else(|e| e);
+        if val == 0 {
+            Ok(())
+        } else {
+            Err(AccessError::AlreadyInUse)
+        }
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self) {
+        let val = self.gpu_lock.fetch_add(1, Ordering::SeqCst);
+        debug_assert!(val >= 1);
+    }
+
+    #[inline]
+    unsafe fn unlock(&self, new_layout: Option<ImageLayout>) {
+        debug_assert!(new_layout.is_none() || new_layout == Some(self.layout));
+        let prev_val = self.gpu_lock.fetch_sub(1, Ordering::SeqCst);
+        debug_assert!(prev_val >= 1);
+    }
+
+    #[inline]
+    fn current_miplevels_access(&self) -> std::ops::Range<u32> {
+        0..self.mipmap_levels()
+    }
+
+    #[inline]
+    fn current_layer_levels_access(&self) -> std::ops::Range<u32> {
+        0..self.dimensions().array_layers()
+    }
+}
+
+unsafe impl ImageClearValue<ClearValue> for ExternalImage {
+    #[inline]
+    fn decode(&self, value: ClearValue) -> Option<ClearValue> {
+        Some(self.image.format().decode_clear_value(value))
+    }
+}
+
+unsafe impl<P> ImageContent<P> for ExternalImage {
+    #[inline]
+    fn matches_format(&self) -> bool {
+        true // FIXME:
+    }
+}
+
+impl PartialEq for ExternalImage {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.inner() == other.inner()
+    }
+}
+
+impl Eq for ExternalImage {}
+
+impl Hash for ExternalImage {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner().hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExternalImage;
+    use crate::format::Format;
+    use crate::image::sys::ImageCreationError;
+    use crate::image::ImageCreateFlags;
+    use crate::image::ImageDimensions;
+    use crate::image::ImageLayout;
+    use crate::image::ImageUsage;
+    use crate::image::SampleCount;
+
+    #[test]
+    fn layout_not_allowed_by_usage() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        // The layout is checked before the handle is used.
+        let res = unsafe {
+            ExternalImage::from_handle(
+                device,
+                ash::vk::Image::null(),
+                ImageDimensions::Dim2d {
+                    width: 32,
+                    height: 32,
+                    array_layers: 2,
+                },
+                Format::R8G8B8A8_SRGB,
+                ImageUsage {
+                    sampled: true,
+                    ..ImageUsage::none()
+                },
+                ImageCreateFlags::none(),
+                SampleCount::Sample1,
+                1,
+                ImageLayout::ColorAttachmentOptimal,
+            )
+        };
+
+        match res {
+            Err(ImageCreationError::UnsupportedLayout {
+                layout: ImageLayout::ColorAttachmentOptimal,
+            }) => (),
+            _ => panic!(),
+        };
+    }
+}
@@ -11,6 +11,7 @@ use crate::buffer::BufferUsage;
 use crate::buffer::CpuAccessibleBuffer;
 use crate::buffer::TypedBufferAccess;
 use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::BufferImageCopy;
 use crate::command_buffer::CommandBufferExecFuture;
 use crate::command_buffer::CommandBufferUsage;
 use crate::command_buffer::PrimaryAutoCommandBuffer;
@@ -24,6 +25,7 @@ use crate::image::sys::ImageCreationError;
 use crate::image::sys::UnsafeImage;
 use crate::image::traits::ImageAccess;
 use crate::image::traits::ImageContent;
+use crate::image::ImageAspect;
 use crate::image::ImageCreateFlags;
 use crate::image::ImageDescriptorLayouts;
 use crate::image::ImageDimensions;
@@ -40,13 +42,16 @@ use crate::memory::pool::MemoryPoolAlloc;
 use crate::memory::pool::PotentialDedicatedAllocation;
 use crate::memory::pool::StdMemoryPoolAlloc;
 use crate::memory::DedicatedAlloc;
-use crate::sampler::Filter;
 use crate::sync::AccessError;
 use crate::sync::NowFuture;
 use crate::sync::Sharing;
+use crate::DeviceSize;
 use smallvec::SmallVec;
+use std::error;
+use std::fmt;
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::ops::Range;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
@@ -120,58 +125,6 @@ fn has_mipmaps(mipmaps: MipmapsCount) -> bool {
     }
 }
 
-fn generate_mipmaps<L>(
-    cbb: &mut AutoCommandBufferBuilder<L>,
-    image: Arc<dyn ImageAccess>,
-    dimensions: ImageDimensions,
-    layout: ImageLayout,
-) {
-    for level in 1..image.mipmap_levels() {
-        let [xs, ys, ds] = dimensions
-            .mipmap_dimensions(level - 1)
-            .unwrap()
-            .width_height_depth();
-        let [xd, yd, dd] = dimensions
-            .mipmap_dimensions(level)
-            .unwrap()
-            .width_height_depth();
-
-        let src = SubImage::new(
-            image.clone(),
-            level - 1,
-            1,
-            0,
-            dimensions.array_layers(),
-            layout,
-        );
-
-        let dst = SubImage::new(
-            image.clone(),
-            level,
-            1,
-            0,
-            dimensions.array_layers(),
-            layout,
-        );
-
-        cbb.blit_image(
-            src,                               //source
-            [0, 0, 0],                         //source_top_left
-            [xs as i32, ys as i32, ds as i32], //source_bottom_right
-            0,                                 //source_base_array_layer
-            level - 1,                         //source_mip_level
-            dst,                               //destination
-            [0, 0, 0],                         //destination_top_left
-            [xd as i32, yd as i32, dd as i32], //destination_bottom_right
-            0,                                 //destination_base_array_layer
-            level,                             //destination_mip_level
-            1,                                 //layer_count
-            Filter::Linear,                    //filter
-        )
-        .expect("failed to blit a mip map to image!");
-    }
-}
-
 impl ImmutableImage {
     #[deprecated(note = "use ImmutableImage::uninitialized instead")]
     #[inline]
@@ -247,6 +200,42 @@ impl ImmutableImage {
         I: IntoIterator<Item = QueueFamily<'a>>,
         M: Into<MipmapsCount>,
     {
+        ImmutableImage::uninitialized_with_pool(
+            &Device::standard_pool(&device),
+            dimensions,
+            format,
+            mipmaps,
+            usage,
+            flags,
+            layout,
+            queue_families,
+        )
+    }
+
+    /// Same as `uninitialized`, but allocates the memory of the image from `pool` instead of the
+    /// standard pool of the device.
+    pub fn uninitialized_with_pool<'a, I, M, P>(
+        pool: &P,
+        dimensions: ImageDimensions,
+        format: Format,
+        mipmaps: M,
+        usage: ImageUsage,
+        flags: ImageCreateFlags,
+        layout: ImageLayout,
+        queue_families: I,
+    ) -> Result<
+        (
+            Arc<ImmutableImage<PotentialDedicatedAllocation<P::Alloc>>>,
+            Arc<ImmutableImageInitialization<PotentialDedicatedAllocation<P::Alloc>>>,
+        ),
+        ImageCreationError,
+    >
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+        M: Into<MipmapsCount>,
+        P: MemoryPool,
+    {
+        let device = pool.device();
         let queue_families = queue_families
             .into_iter()
             .map(|f| f.id())
@@ -274,7 +263,7 @@ impl ImmutableImage {
         };
 
         let memory = MemoryPool::alloc_from_requirements(
-            &Device::standard_pool(&device),
+            pool,
             &mem_reqs,
             AllocLayout::Optimal,
             MappingRequirement::DoNotMap,
@@ -341,6 +330,7 @@ impl ImmutableImage {
     }
 
     /// Construct an ImmutableImage containing a copy of the data in `source`.
+    #[inline]
     pub fn from_buffer<B, Px>(
         source: Arc<B>,
         dimensions: ImageDimensions,
@@ -354,6 +344,104 @@ impl ImmutableImage {
         ),
         ImageCreationError,
     >
+    where
+        B: TypedBufferAccess<Content = [Px]> + 'static,
+        Px: Pixel + Send + Sync + Clone + 'static,
+    {
+        ImmutableImage::from_buffer_impl(
+            source,
+            dimensions,
+            ImageCreateFlags::none(),
+            mipmaps,
+            format,
+            queue,
+        )
+    }
+
+    /// Construct a cube map, or a cube array if `cube_count` is greater than 1, from the contents
+    /// of `iter`.
+    ///
+    /// The faces are `size` texels wide and high, and are read one after the other in the order
+    /// of [`CubeFace`](crate::image::CubeFace), one cube after the other. The image can be sampled through
+    /// [`ImageView::cube`](crate::image::view::ImageView::cube).
+    #[inline]
+    pub fn from_iter_cube<Px, I>(
+        iter: I,
+        size: u32,
+        cube_count: u32,
+        mipmaps: MipmapsCount,
+        format: Format,
+        queue: Arc<Queue>,
+    ) -> Result<
+        (
+            Arc<Self>,
+            CommandBufferExecFuture<NowFuture, PrimaryAutoCommandBuffer>,
+        ),
+        ImageCreationError,
+    >
+    where
+        Px: Pixel + Send + Sync + Clone + 'static,
+        I: IntoIterator<Item = Px>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let source = CpuAccessibleBuffer::from_iter(
+            queue.device().clone(),
+            BufferUsage::transfer_source(),
+            false,
+            iter,
+        )?;
+        ImmutableImage::from_buffer_cube(source, size, cube_count, mipmaps, format, queue)
+    }
+
+    /// Same as `from_iter_cube`, but with the data in `source`.
+    #[inline]
+    pub fn from_buffer_cube<B, Px>(
+        source: Arc<B>,
+        size: u32,
+        cube_count: u32,
+        mipmaps: MipmapsCount,
+        format: Format,
+        queue: Arc<Queue>,
+    ) -> Result<
+        (
+            Arc<Self>,
+            CommandBufferExecFuture<NowFuture, PrimaryAutoCommandBuffer>,
+        ),
+        ImageCreationError,
+    >
+    where
+        B: TypedBufferAccess<Content = [Px]> + 'static,
+        Px: Pixel + Send + Sync + Clone + 'static,
+    {
+        let flags = ImageCreateFlags {
+            cube_compatible: true,
+            ..ImageCreateFlags::none()
+        };
+
+        ImmutableImage::from_buffer_impl(
+            source,
+            ImageDimensions::cube(size, cube_count),
+            flags,
+            mipmaps,
+            format,
+            queue,
+        )
+    }
+
+    fn from_buffer_impl<B, Px>(
+        source: Arc<B>,
+        dimensions: ImageDimensions,
+        flags: ImageCreateFlags,
+        mipmaps: MipmapsCount,
+        format: Format,
+        queue: Arc<Queue>,
+    ) -> Result<
+        (
+            Arc<Self>,
+            CommandBufferExecFuture<NowFuture, PrimaryAutoCommandBuffer>,
+        ),
+        ImageCreationError,
+    >
     where
         B: TypedBufferAccess<Content = [Px]> + 'static,
         Px: Pixel + Send + Sync + Clone + 'static,
@@ -365,7 +453,6 @@ impl ImmutableImage {
             sampled: true,
             ..ImageUsage::none()
         };
-        let flags = ImageCreateFlags::none();
         let layout = ImageLayout::ShaderReadOnlyOptimal;
 
         let (image, initializer) = ImmutableImage::uninitialized(
@@ -398,12 +485,8 @@ impl ImmutableImage {
         .unwrap();
 
         if need_to_generate_mipmaps {
-            generate_mipmaps(
-                &mut cbb,
-                image.clone(),
-                image.dimensions,
-                ImageLayout::ShaderReadOnlyOptimal,
-            );
+            cbb.generate_mipmaps(image.clone())
+                .expect("failed to blit a mip map to image!");
         }
 
         let cb = cbb.build().unwrap();
@@ -415,6 +498,165 @@ impl ImmutableImage {
 
         image.initialized.store(true, Ordering::Relaxed);
 
+        Ok((image, future))
+    }
+    /// Construct an ImmutableImage from compressed data, such as BCn, ETC2 or ASTC blocks.
+    ///
+    /// `data` contains the blocks of all the mipmap levels of the image, and `mip_levels`
+    /// describes where each level is in `data`, starting with level 0. The number of mipmaps of
+    /// the image is the number of elements of `mip_levels`, as compressed formats usually can't be
+    /// blitted to generate the other levels.
+    pub fn from_compressed_iter<I, M>(
+        data: I,
+        mip_levels: M,
+        dimensions: ImageDimensions,
+        format: Format,
+        queue: Arc<Queue>,
+    ) -> Result<
+        (
+            Arc<Self>,
+            CommandBufferExecFuture<NowFuture, PrimaryAutoCommandBuffer>,
+        ),
+        CompressedImageError,
+    >
+    where
+        I: IntoIterator<Item = u8>,
+        I::IntoIter: ExactSizeIterator,
+        M: IntoIterator<Item = CompressedMipLevel>,
+    {
+        if format.compression().is_none() {
+            return Err(CompressedImageError::FormatNotCompressed);
+        }
+
+        let data = data.into_iter();
+        let data_len = data.len();
+        let mip_levels: SmallVec<[CompressedMipLevel; 16]> = mip_levels.into_iter().collect();
+
+        if mip_levels.is_empty() || mip_levels.len() as u32 > dimensions.max_mipmaps() {
+            return Err(CompressedImageError::InvalidMipmapsCount {
+                obtained: mip_levels.len() as u32,
+                max: dimensions.max_mipmaps(),
+            });
+        }
+
+        let block_size = format.size().unwrap();
+        let [block_width, block_height] = format.block_dimensions();
+
+        let regions = mip_levels
+            .iter()
+            .enumerate()
+            .map(|(level, mip_level)| {
+                let level = level as u32;
+                let [width, height, depth] = dimensions
+                    .mipmap_dimensions(level)
+                    .unwrap()
+                    .width_height_depth();
+
+                if mip_level.range.start > mip_level.range.end || mip_level.range.end > data_len {
+                    return Err(CompressedImageError::RangeOutOfBounds { level });
+                }
+
+                if mip_level.range.start as DeviceSize % block_size != 0 {
+                    return Err(CompressedImageError::RangeNotAligned {
+                        level,
+                        alignment: block_size,
+                    });
+                }
+
+                if mip_level.row_length != 0
+                    && (mip_level.row_length < width || mip_level.row_length % block_width != 0)
+                {
+                    return Err(CompressedImageError::InvalidRowLength { level });
+                }
+
+                // The rows of blocks of each depth slice and array layer follow each other.
+                let row_length = match mip_level.row_length {
+                    0 => width,
+                    row_length => row_length,
+                };
+                let blocks = ((row_length + block_width - 1) / block_width) as DeviceSize
+                    * ((height + block_height - 1) / block_height) as DeviceSize
+                    * depth as DeviceSize
+                    * dimensions.array_layers() as DeviceSize;
+                let required = blocks * block_size;
+                let obtained = (mip_level.range.end - mip_level.range.start) as DeviceSize;
+
+                if obtained < required {
+                    return Err(CompressedImageError::MipLevelTooSmall {
+                        level,
+                        required,
+                        obtained,
+                    });
+                }
+
+                Ok(BufferImageCopy {
+                    buffer_offset: mip_level.range.start as DeviceSize,
+                    buffer_row_length: mip_level.row_length,
+                    buffer_image_height: 0,
+                    image_aspect: ImageAspect::Color,
+                    image_mip_level: level,
+                    image_base_array_layer: 0,
+                    image_layer_count: dimensions.array_layers(),
+                    image_offset: [0, 0, 0],
+                    image_extent: [width, height, depth],
+                })
+            })
+            .collect::<Result<SmallVec<[_; 16]>, _>>()?;
+
+        let source = CpuAccessibleBuffer::from_iter(
+            queue.device().clone(),
+            BufferUsage::transfer_source(),
+            false,
+            data,
+        )
+        .map_err(ImageCreationError::from)?;
+
+        let usage = ImageUsage {
+            transfer_destination: true,
+            sampled: true,
+            ..ImageUsage::none()
+        };
+        let flags = ImageCreateFlags::none();
+        let layout = ImageLayout::ShaderReadOnlyOptimal;
+
+        let (image, initializer) = ImmutableImage::uninitialized(
+            source.device().clone(),
+            dimensions,
+            format,
+            MipmapsCount::Specific(regions.len() as u32),
+            usage,
+            flags,
+            layout,
+            source.device().active_queue_families(),
+        )?;
+
+        let init = SubImage::new(
+            initializer,
+            0,
+            regions.len() as u32,
+            0,
+            dimensions.array_layers(),
+            layout,
+        );
+
+        let mut cbb = AutoCommandBufferBuilder::primary(
+            source.device().clone(),
+            queue.family(),
+            CommandBufferUsage::MultipleSubmit,
+        )
+        .map_err(ImageCreationError::from)?;
+        cbb.copy_buffer_to_image_regions(source, init, regions)
+            .unwrap();
+
+        let cb = cbb.build().unwrap();
+
+        let future = match cb.execute(queue) {
+            Ok(f) => f,
+            Err(e) => unreachable!("{:?}", e),
+        };
+
+        image.initialized.store(true, Ordering::Relaxed);
+
         Ok((image, future))
     }
 }
@@ -699,3 +941,178 @@ where
         self.inner().hash(state);
     }
 }
+
+/// A mipmap level of the data of [`ImmutableImage::from_compressed_iter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedMipLevel {
+    /// The range in bytes of the blocks of the level in the data. The start must be a multiple of
+    /// the block size of the format.
+    pub range: Range<usize>,
+    /// The number of texels in each row of blocks of the data. If 0, the rows are tightly packed.
+    /// Otherwise, it must be a multiple of the block width of the format, and at least the width
+    /// of the level.
+    pub row_length: u32,
+}
+
+impl CompressedMipLevel {
+    /// Builds a `CompressedMipLevel` whose rows are tightly packed.
+    #[inline]
+    pub fn packed(range: Range<usize>) -> CompressedMipLevel {
+        CompressedMipLevel {
+            range,
+            row_length: 0,
+        }
+    }
+}
+
+/// Error that can happen when creating an image from compressed data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompressedImageError {
+    /// Creating the image failed.
+    ImageCreationError(ImageCreationError),
+    /// The format isn't a compressed format.
+    FormatNotCompressed,
+    /// No mipmap level was provided, or more than the dimensions allow.
+    InvalidMipmapsCount { obtained: u32, max: u32 },
+    /// The range of a mipmap level is outside of the data.
+    RangeOutOfBounds { level: u32 },
+    /// The start of the range of a mipmap level isn't a multiple of the block size.
+    RangeNotAligned { level: u32, alignment: DeviceSize },
+    /// The row length of a mipmap level isn't a multiple of the block width, or is smaller than
+    /// the width of the level.
+    InvalidRowLength { level: u32 },
+    /// The range of a mipmap level is too small to hold all its blocks.
+    MipLevelTooSmall {
+        level: u32,
+        required: DeviceSize,
+        obtained: DeviceSize,
+    },
+}
+
+impl error::Error for CompressedImageError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            CompressedImageError::ImageCreationError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CompressedImageError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                CompressedImageError::ImageCreationError(_) => "creating the image failed",
+                CompressedImageError::FormatNotCompressed => {
+                    "the format isn't a compressed format"
+                }
+                CompressedImageError::InvalidMipmapsCount { .. } => {
+                    "a wrong number of mipmap levels was provided"
+                }
+                CompressedImageError::RangeOutOfBounds { .. } => {
+                    "the range of a mipmap level is outside of the data"
+                }
+                CompressedImageError::RangeNotAligned { .. } => {
+                    "the start of the range of a mipmap level isn't a multiple of the block size"
+                }
+                CompressedImageError::InvalidRowLength { .. } => {
+                    "the row length of a mipmap level is invalid"
+                }
+                CompressedImageError::MipLevelTooSmall { .. } => {
+                    "the range of a mipmap level is too small to hold all its blocks"
+                }
+            }
+        )
+    }
+}
+
+impl From<ImageCreationError> for CompressedImageError {
+    #[inline]
+    fn from(err: ImageCreationError) -> CompressedImageError {
+        CompressedImageError::ImageCreationError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CompressedImageError;
+    use super::CompressedMipLevel;
+    use super::ImmutableImage;
+    use crate::format::Format;
+    use crate::image::ImageDimensions;
+
+    #[test]
+    fn compressed_validation() {
+        let (_, queue) = gfx_dev_and_queue!();
+        let dimensions = ImageDimensions::Dim2d {
+            width: 8,
+            height: 8,
+            array_layers: 1,
+        };
+
+        match ImmutableImage::from_compressed_iter(
+            vec![0u8; 256],
+            Some(CompressedMipLevel::packed(0..256)),
+            dimensions,
+            Format::R8G8B8A8_UNORM,
+            queue.clone(),
+        ) {
+            Err(CompressedImageError::FormatNotCompressed) => (),
+            _ => panic!(),
+        }
+
+        // The first level holds 2x2 blocks of 8 bytes.
+        match ImmutableImage::from_compressed_iter(
+            vec![0u8; 40],
+            vec![
+                CompressedMipLevel::packed(0..24),
+                CompressedMipLevel::packed(24..32),
+            ],
+            dimensions,
+            Format::BC1_RGB_UNORM_BLOCK,
+            queue.clone(),
+        ) {
+            Err(CompressedImageError::MipLevelTooSmall {
+                level: 0,
+                required: 32,
+                obtained: 24,
+            }) => (),
+            _ => panic!(),
+        }
+
+        match ImmutableImage::from_compressed_iter(
+            vec![0u8; 40],
+            vec![
+                CompressedMipLevel::packed(0..32),
+                CompressedMipLevel::packed(36..40),
+            ],
+            dimensions,
+            Format::BC1_RGB_UNORM_BLOCK,
+            queue.clone(),
+        ) {
+            Err(CompressedImageError::RangeNotAligned {
+                level: 1,
+                alignment: 8,
+            }) => (),
+            _ => panic!(),
+        }
+
+        match ImmutableImage::from_compressed_iter(
+            vec![0u8; 64],
+            Some(CompressedMipLevel {
+                range: 0..64,
+                row_length: 10,
+            }),
+            dimensions,
+            Format::BC1_RGB_UNORM_BLOCK,
+            queue,
+        ) {
+            Err(CompressedImageError::InvalidRowLength { level: 0 }) => (),
+            _ => panic!(),
+        }
+    }
+}
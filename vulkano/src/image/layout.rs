@@ -39,6 +39,8 @@ pub enum ImageLayout {
     TransferDstOptimal = ash::vk::ImageLayout::TRANSFER_DST_OPTIMAL.as_raw(),
     Preinitialized = ash::vk::ImageLayout::PREINITIALIZED.as_raw(),
     PresentSrc = ash::vk::ImageLayout::PRESENT_SRC_KHR.as_raw(),
+    FragmentShadingRateAttachmentOptimal =
+        ash::vk::ImageLayout::FRAGMENT_SHADING_RATE_ATTACHMENT_OPTIMAL_KHR.as_raw(),
 }
 
 impl From<ImageLayout> for ash::vk::ImageLayout {
@@ -38,8 +38,14 @@
 //! are going to use the image:
 //!
 //! - An `AttachmentImage` can be used when you want to draw to an image.
+//! - A `TransientAttachmentImage` can be used for attachments whose content is only needed within
+//!   a render pass, like an intermediate G-buffer or a depth buffer.
 //! - An `ImmutableImage` stores data which never need be changed after the initial upload,
 //!   like a texture.
+//! - An `ExternalImage` wraps an image that was created by something else than vulkano, such as
+//!   the images of an OpenXR swapchain.
+//! - A [`TextureStreamer`] keeps only some of the mip levels of its textures in memory, and
+//!   uploads the others as they are needed.
 //!
 //! # Low-level information
 //!
@@ -49,14 +55,19 @@
 pub use self::aspect::ImageAspect;
 pub use self::aspect::ImageAspects;
 pub use self::attachment::AttachmentImage;
+pub use self::external::ExternalImage;
+pub use self::immutable::CompressedImageError;
+pub use self::immutable::CompressedMipLevel;
 pub use self::immutable::ImmutableImage;
 pub use self::layout::ImageDescriptorLayouts;
 pub use self::layout::ImageLayout;
 pub use self::storage::StorageImage;
+pub use self::streaming::TextureStreamer;
 pub use self::swapchain::SwapchainImage;
 pub use self::sys::ImageCreationError;
 pub use self::traits::ImageAccess;
 pub use self::traits::ImageInner;
+pub use self::transient::TransientAttachmentImage;
 pub use self::usage::ImageUsage;
 pub use self::view::ImageViewAbstract;
 use std::cmp;
@@ -64,12 +75,15 @@ use std::convert::TryFrom;
 
 mod aspect;
 pub mod attachment; // TODO: make private
+mod external;
 pub mod immutable; // TODO: make private
 mod layout;
 mod storage;
+pub mod streaming;
 pub mod swapchain; // TODO: make private
 pub mod sys;
 pub mod traits;
+mod transient;
 mod usage;
 pub mod view;
 
@@ -191,6 +205,37 @@ impl From<SampleCounts> for ash::vk::SampleCountFlags {
     }
 }
 
+impl SampleCounts {
+    /// Returns true if `samples` is one of the sample counts.
+    #[inline]
+    pub fn contains(&self, samples: SampleCount) -> bool {
+        !(ash::vk::SampleCountFlags::from(*self) & samples.into()).is_empty()
+    }
+
+    /// Returns the sample counts that are in both `self` and `other`.
+    #[inline]
+    pub fn intersection(&self, other: &SampleCounts) -> SampleCounts {
+        (ash::vk::SampleCountFlags::from(*self) & ash::vk::SampleCountFlags::from(*other)).into()
+    }
+
+    /// Returns the highest of the sample counts, or `None` if there are none.
+    #[inline]
+    pub fn max_count(&self) -> Option<SampleCount> {
+        [
+            SampleCount::Sample64,
+            SampleCount::Sample32,
+            SampleCount::Sample16,
+            SampleCount::Sample8,
+            SampleCount::Sample4,
+            SampleCount::Sample2,
+            SampleCount::Sample1,
+        ]
+        .iter()
+        .copied()
+        .find(|&samples| self.contains(samples))
+    }
+}
+
 /// Specifies how many mipmaps must be allocated.
 ///
 /// Note that at least one mipmap must be allocated, to store the main level of the image.
@@ -325,6 +370,12 @@ pub struct ImageCreateFlags {
     ///
     /// Requires `mutable_format`.
     pub block_texel_view_compatible: bool,
+    /// For images with a multi-planar format, each plane is bound to memory separately with
+    /// [`bind_plane_memory`](crate::image::sys::UnsafeImage::bind_plane_memory).
+    ///
+    /// Requires Vulkan 1.1 or the `khr_sampler_ycbcr_conversion` extension, and the format must
+    /// support the `disjoint` format feature.
+    pub disjoint: bool,
 }
 
 impl ImageCreateFlags {
@@ -343,6 +394,7 @@ impl From<ImageCreateFlags> for ash::vk::ImageCreateFlags {
             cube_compatible,
             array_2d_compatible,
             block_texel_view_compatible,
+            disjoint,
         } = flags;
 
         let mut vk_flags = Self::default();
@@ -367,6 +419,9 @@ impl From<ImageCreateFlags> for ash::vk::ImageCreateFlags {
         if block_texel_view_compatible {
             vk_flags |= ash::vk::ImageCreateFlags::BLOCK_TEXEL_VIEW_COMPATIBLE
         };
+        if disjoint {
+            vk_flags |= ash::vk::ImageCreateFlags::DISJOINT
+        };
         vk_flags
     }
 }
@@ -397,6 +452,43 @@ impl From<ImageTiling> for ash::vk::ImageTiling {
     }
 }
 
+/// A face of a cube map.
+///
+/// The six faces of a cube are stored in consecutive array layers of a cube-compatible image, in
+/// the order of the variants of this enum. A cube array stores its cubes one after the other.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum CubeFace {
+    PositiveX = 0,
+    NegativeX = 1,
+    PositiveY = 2,
+    NegativeY = 3,
+    PositiveZ = 4,
+    NegativeZ = 5,
+}
+
+impl CubeFace {
+    /// Returns the six faces, in the order of their array layers.
+    #[inline]
+    pub fn all() -> [CubeFace; 6] {
+        [
+            CubeFace::PositiveX,
+            CubeFace::NegativeX,
+            CubeFace::PositiveY,
+            CubeFace::NegativeY,
+            CubeFace::PositiveZ,
+            CubeFace::NegativeZ,
+        ]
+    }
+
+    /// Returns the array layer of this face in the cube at index `cube_index` of a cube array.
+    /// Use 0 for an image that contains a single cube.
+    #[inline]
+    pub fn array_layer(self, cube_index: u32) -> u32 {
+        cube_index * 6 + self as u32
+    }
+}
+
 /// The dimensions of an image.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ImageDimensions {
@@ -417,6 +509,17 @@ pub enum ImageDimensions {
 }
 
 impl ImageDimensions {
+    /// Returns the dimensions of an image that holds `cube_count` cubes whose faces are `size`
+    /// texels wide and high. The image must be created with the `cube_compatible` flag.
+    #[inline]
+    pub fn cube(size: u32, cube_count: u32) -> ImageDimensions {
+        ImageDimensions::Dim2d {
+            width: size,
+            height: size,
+            array_layers: cube_count * 6,
+        }
+    }
+
     #[inline]
     pub fn width(&self) -> u32 {
         match *self {
@@ -7,8 +7,11 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::PrimaryAutoCommandBuffer;
 use crate::device::physical::QueueFamily;
 use crate::device::Device;
+use crate::device::DeviceOwned;
 use crate::format::ClearValue;
 use crate::format::Format;
 use crate::image::sys::ImageCreationError;
@@ -23,6 +26,8 @@ use crate::image::ImageInner;
 use crate::image::ImageLayout;
 use crate::image::ImageUsage;
 use crate::image::SampleCount;
+use crate::memory::defragmentation::DefragmentationError;
+use crate::memory::defragmentation::Relocate;
 #[cfg(any(
     target_os = "linux",
     target_os = "dragonflybsd",
@@ -39,6 +44,14 @@ use crate::memory::pool::MemoryPoolAlloc;
 use crate::memory::pool::PotentialDedicatedAllocation;
 use crate::memory::pool::StdMemoryPool;
 use crate::memory::DedicatedAlloc;
+use crate::memory::DeviceMemory;
+#[cfg(target_os = "windows")]
+use crate::memory::Win32Handle;
+use crate::memory::{DeviceMemoryAllocError, DeviceMemoryBuilder, ExternalMemoryHandleType};
+use crate::sync::AccessError;
+use crate::sync::Sharing;
+use crate::DeviceSize;
+use crate::Version;
 #[cfg(any(
     target_os = "linux",
     target_os = "dragonflybsd",
@@ -46,9 +59,10 @@ use crate::memory::DedicatedAlloc;
     target_os = "netbsd",
     target_os = "openbsd"
 ))]
-use crate::memory::{DeviceMemoryAllocError, ExternalMemoryHandleType};
-use crate::sync::AccessError;
-use crate::sync::Sharing;
+use crate::{check_errors, image::sys::DrmFormatModifier, image::sys::LinearLayout, VulkanObject};
+use smallvec::SmallVec;
+#[cfg(target_os = "windows")]
+use std::ffi::c_void;
 #[cfg(any(
     target_os = "linux",
     target_os = "dragonflybsd",
@@ -56,8 +70,9 @@ use crate::sync::Sharing;
     target_os = "netbsd",
     target_os = "openbsd"
 ))]
-use crate::DeviceSize;
-use smallvec::SmallVec;
+use std::fs::File;
+use std::hash::Hash;
+use std::hash::Hasher;
 #[cfg(any(
     target_os = "linux",
     target_os = "dragonflybsd",
@@ -65,9 +80,15 @@ use smallvec::SmallVec;
     target_os = "netbsd",
     target_os = "openbsd"
 ))]
-use std::fs::File;
-use std::hash::Hash;
-use std::hash::Hasher;
+use std::mem::MaybeUninit;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonflybsd",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+use std::os::unix::io::AsRawFd;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
@@ -126,8 +147,15 @@ impl StorageImage {
             depth_stencil_attachment: is_depth,
             input_attachment: true,
             transient_attachment: false,
+            fragment_shading_rate_attachment: false,
+        };
+        // Allows rendering to the depth slices of 3D images through 2D array views, if supported.
+        let flags = ImageCreateFlags {
+            array_2d_compatible: matches!(dimensions, ImageDimensions::Dim3d { .. })
+                && (device.api_version() >= Version::V1_1
+                    || device.enabled_extensions().khr_maintenance1),
+            ..ImageCreateFlags::none()
         };
-        let flags = ImageCreateFlags::none();
 
         StorageImage::with_usage(device, dimensions, format, usage, flags, queue_families)
     }
@@ -141,6 +169,68 @@ impl StorageImage {
         flags: ImageCreateFlags,
         queue_families: I,
     ) -> Result<Arc<StorageImage>, ImageCreationError>
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        StorageImage::with_pool(
+            &Device::standard_pool(&device),
+            dimensions,
+            format,
+            usage,
+            flags,
+            queue_families,
+        )
+    }
+
+    /// Same as `with_usage`, but the image can be viewed with any format of `view_formats`.
+    ///
+    /// The `mutable_format` flag is added to `flags`, and the formats are passed to the
+    /// implementation as in [`UnsafeImage::new_with_view_formats`]. They must be compatible with
+    /// `format`. This allows for example writing to an sRGB image from a compute shader through
+    /// a UNORM view, by giving both formats.
+    pub fn with_view_formats<'a, I>(
+        device: Arc<Device>,
+        dimensions: ImageDimensions,
+        format: Format,
+        view_formats: &[Format],
+        usage: ImageUsage,
+        flags: ImageCreateFlags,
+        queue_families: I,
+    ) -> Result<Arc<StorageImage>, ImageCreationError>
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        let flags = ImageCreateFlags {
+            mutable_format: true,
+            ..flags
+        };
+
+        StorageImage::with_pool_impl(
+            &Device::standard_pool(&device),
+            dimensions,
+            format,
+            usage,
+            flags,
+            queue_families,
+            view_formats,
+        )
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonflybsd",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn new_with_exportable_fd<'a, I>(
+        device: Arc<Device>,
+        dimensions: ImageDimensions,
+        format: Format,
+        usage: ImageUsage,
+        flags: ImageCreateFlags,
+        queue_families: I,
+    ) -> Result<Arc<StorageImage>, ImageCreationError>
     where
         I: IntoIterator<Item = QueueFamily<'a>>,
     {
@@ -156,7 +246,7 @@ impl StorageImage {
                 Sharing::Exclusive
             };
 
-            UnsafeImage::new(
+            UnsafeImage::new_with_exportable_fd(
                 device.clone(),
                 usage,
                 format,
@@ -170,8 +260,8 @@ impl StorageImage {
             )?
         };
 
-        let memory = MemoryPool::alloc_from_requirements(
-            &Device::standard_pool(&device),
+        let memory = alloc_dedicated_with_exportable_fd(
+            device.clone(),
             &mem_reqs,
             AllocLayout::Optimal,
             MappingRequirement::DoNotMap,
@@ -199,6 +289,9 @@ impl StorageImage {
         }))
     }
 
+    /// Exports posix file descriptor for the allocated memory
+    /// requires `khr_external_memory_fd` and `khr_external_memory` extensions to be loaded.
+    /// Only works on Linux.
     #[cfg(any(
         target_os = "linux",
         target_os = "dragonflybsd",
@@ -206,22 +299,519 @@ impl StorageImage {
         target_os = "netbsd",
         target_os = "openbsd"
     ))]
-    pub fn new_with_exportable_fd<'a, I>(
+    pub fn export_posix_fd(&self) -> Result<File, DeviceMemoryAllocError> {
+        self.memory
+            .memory()
+            .export_fd(ExternalMemoryHandleType::posix())
+    }
+
+    /// Return the size of the allocated memory (used for e.g. with cuda)
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonflybsd",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn mem_size(&self) -> DeviceSize {
+        self.memory.memory().size()
+    }
+
+    /// Returns the memory that backs the image, for example to acquire or release its keyed
+    /// mutex when submitting command buffers.
+    ///
+    /// Unless the image was created with external memory, this memory may be shared with other
+    /// resources.
+    #[inline]
+    pub fn memory(&self) -> &DeviceMemory {
+        self.memory.memory()
+    }
+
+    /// Same as `with_usage`, but the memory of the image is a dedicated allocation that can be
+    /// exported as any of `handle_types` with [`export_fd`](StorageImage::export_fd) or
+    /// [`export_win32_handle`](StorageImage::export_win32_handle). This makes it possible to
+    /// share the image with Direct3D or OpenGL.
+    ///
+    /// The extensions that correspond to `handle_types` must be enabled on the device.
+    pub fn new_with_exportable_handles<'a, I>(
         device: Arc<Device>,
         dimensions: ImageDimensions,
         format: Format,
         usage: ImageUsage,
         flags: ImageCreateFlags,
         queue_families: I,
+        handle_types: ExternalMemoryHandleType,
     ) -> Result<Arc<StorageImage>, ImageCreationError>
     where
         I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        unsafe {
+            StorageImage::with_external_memory(
+                device,
+                dimensions,
+                format,
+                usage,
+                flags,
+                queue_families,
+                handle_types,
+                |builder| builder.export_info(handle_types),
+            )
+        }
+    }
+
+    /// Creates a new image whose memory is imported from an opaque file descriptor, for example
+    /// one that was exported by OpenGL with `GL_EXT_memory_object_fd` or by another Vulkan
+    /// device.
+    ///
+    /// On success, the ownership of `fd` is transferred to the Vulkan implementation.
+    ///
+    /// # Safety
+    ///
+    /// - `fd` must be an opaque file descriptor that holds an image created with the same
+    ///   parameters, exported from a device with the same `device_uuid` and `driver_uuid` as
+    ///   `device`.
+    ///
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonflybsd",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub unsafe fn import_fd<'a, I>(
+        device: Arc<Device>,
+        dimensions: ImageDimensions,
+        format: Format,
+        usage: ImageUsage,
+        flags: ImageCreateFlags,
+        fd: File,
+        queue_families: I,
+    ) -> Result<Arc<StorageImage>, ImageCreationError>
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        let handle_type = ExternalMemoryHandleType::posix();
+
+        StorageImage::with_external_memory(
+            device,
+            dimensions,
+            format,
+            usage,
+            flags,
+            queue_families,
+            handle_type,
+            |builder| builder.import_info(fd, handle_type),
+        )
+    }
+
+    /// Creates a new image whose memory is imported from a Windows handle of type `handle_type`,
+    /// for example a shared Direct3D 11 texture or Direct3D 12 resource.
+    ///
+    /// If the resource has a keyed mutex, it must be acquired and released around each use of
+    /// the image with
+    /// [`add_keyed_mutex_acquire`](crate::command_buffer::submit::SubmitCommandBufferBuilder::add_keyed_mutex_acquire)
+    /// and
+    /// [`add_keyed_mutex_release`](crate::command_buffer::submit::SubmitCommandBufferBuilder::add_keyed_mutex_release).
+    ///
+    /// Contrary to file descriptors, the ownership of `handle` is not transferred.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid handle of type `handle_type` that holds an image created with
+    ///   the same parameters.
+    ///
+    #[cfg(target_os = "windows")]
+    pub unsafe fn import_win32_handle<'a, I>(
+        device: Arc<Device>,
+        dimensions: ImageDimensions,
+        format: Format,
+        usage: ImageUsage,
+        flags: ImageCreateFlags,
+        handle: *mut c_void,
+        handle_type: ExternalMemoryHandleType,
+        queue_families: I,
+    ) -> Result<Arc<StorageImage>, ImageCreationError>
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        StorageImage::with_external_memory(
+            device,
+            dimensions,
+            format,
+            usage,
+            flags,
+            queue_families,
+            handle_type,
+            |builder| builder.import_win32_info(handle, handle_type),
+        )
+    }
+
+    /// Exports the memory of the image as a file descriptor of type `handle_type`.
+    ///
+    /// The image must have been created with
+    /// [`new_with_exportable_handles`](StorageImage::new_with_exportable_handles), with
+    /// `handle_type` included. The returned file is owned by the caller.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonflybsd",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    #[inline]
+    pub fn export_fd(
+        &self,
+        handle_type: ExternalMemoryHandleType,
+    ) -> Result<File, DeviceMemoryAllocError> {
+        self.memory.memory().export_fd(handle_type)
+    }
+
+    /// Exports the memory of the image as a Windows handle of type `handle_type`.
+    ///
+    /// The image must have been created with
+    /// [`new_with_exportable_handles`](StorageImage::new_with_exportable_handles), with
+    /// `handle_type` included. NT handles are closed when the returned `Win32Handle` is dropped.
+    #[cfg(target_os = "windows")]
+    #[inline]
+    pub fn export_win32_handle(
+        &self,
+        handle_type: ExternalMemoryHandleType,
+    ) -> Result<Win32Handle, DeviceMemoryAllocError> {
+        self.memory.memory().export_win32_handle(handle_type)
+    }
+
+    // Implementation of the functions that create images with external memory. The image is
+    // bound to a dedicated allocation, built by `allocate` from a builder with the memory type
+    // and size already set.
+    unsafe fn with_external_memory<'a, I, F>(
+        device: Arc<Device>,
+        dimensions: ImageDimensions,
+        format: Format,
+        usage: ImageUsage,
+        flags: ImageCreateFlags,
+        queue_families: I,
+        handle_types: ExternalMemoryHandleType,
+        allocate: F,
+    ) -> Result<Arc<StorageImage>, ImageCreationError>
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+        F: for<'b> FnOnce(DeviceMemoryBuilder<'b>) -> DeviceMemoryBuilder<'b>,
     {
         let queue_families = queue_families
             .into_iter()
             .map(|f| f.id())
             .collect::<SmallVec<[u32; 4]>>();
 
+        let (image, mem_reqs) = {
+            let sharing = if queue_families.len() >= 2 {
+                Sharing::Concurrent(queue_families.iter().cloned())
+            } else {
+                Sharing::Exclusive
+            };
+
+            UnsafeImage::new_with_external_memory(
+                device.clone(),
+                usage,
+                format,
+                flags,
+                dimensions,
+                SampleCount::Sample1,
+                1,
+                sharing,
+                false,
+                false,
+                handle_types,
+            )?
+        };
+
+        let allowed = || {
+            device
+                .physical_device()
+                .memory_types()
+                .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
+        };
+        let memory_type = allowed()
+            .find(|t| t.is_device_local())
+            .or_else(|| allowed().next())
+            .ok_or(DeviceMemoryAllocError::MemoryIndexInvalid)?;
+
+        let memory = allocate(
+            DeviceMemoryBuilder::new(device.clone(), memory_type.id(), mem_reqs.size)
+                .dedicated_info(DedicatedAlloc::Image(&image)),
+        )
+        .build()?;
+
+        // Will never panic because the builder only returns an atomically refcounted
+        // DeviceMemory object on success.
+        let memory = Arc::try_unwrap(memory).unwrap();
+        image.bind_memory(&memory, 0)?;
+
+        Ok(Arc::new(StorageImage {
+            image,
+            memory: PotentialDedicatedAllocation::Dedicated(memory),
+            dimensions,
+            format,
+            queue_families,
+            gpu_lock: AtomicUsize::new(0),
+        }))
+    }
+
+    /// Creates a new two-dimensional image with one of the given DRM format modifiers, whose
+    /// memory can be exported as a dma-buf with [`export_dma_buf`](StorageImage::export_dma_buf).
+    ///
+    /// The implementation chooses the modifier among `modifiers`. The chosen modifier and the
+    /// layouts of the memory planes, which are needed to import the image elsewhere, can be
+    /// retrieved with [`drm_format_modifier`](UnsafeImage::drm_format_modifier) and
+    /// [`drm_memory_plane_layout`](UnsafeImage::drm_memory_plane_layout).
+    ///
+    /// Requires the `ext_image_drm_format_modifier` and `ext_external_memory_dma_buf` extensions
+    /// to be enabled.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonflybsd",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn new_with_drm_format_modifiers<'a, M, I>(
+        device: Arc<Device>,
+        dimensions: ImageDimensions,
+        format: Format,
+        usage: ImageUsage,
+        flags: ImageCreateFlags,
+        modifiers: M,
+        queue_families: I,
+    ) -> Result<Arc<StorageImage>, ImageCreationError>
+    where
+        M: IntoIterator<Item = u64>,
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        unsafe {
+            StorageImage::with_drm_format_modifier(
+                device,
+                dimensions,
+                format,
+                usage,
+                flags,
+                DrmFormatModifier::List(modifiers.into_iter().collect()),
+                queue_families,
+                None,
+            )
+        }
+    }
+
+    /// Creates a new two-dimensional image whose memory is imported from a dma-buf, for example
+    /// one that was exported by a video decoder or a Wayland client.
+    ///
+    /// On success, the ownership of `fd` is transferred to the Vulkan implementation.
+    ///
+    /// Requires the `ext_image_drm_format_modifier` and `ext_external_memory_dma_buf` extensions
+    /// to be enabled.
+    ///
+    /// # Safety
+    ///
+    /// - `fd` must be a dma-buf that holds an image with the given dimensions and format, laid out
+    ///   according to `modifier` and `plane_layouts`.
+    /// - There must be one plane layout for each memory plane of `modifier`.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonflybsd",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub unsafe fn import_dma_buf<'a, L, I>(
+        device: Arc<Device>,
+        dimensions: ImageDimensions,
+        format: Format,
+        usage: ImageUsage,
+        flags: ImageCreateFlags,
+        fd: File,
+        modifier: u64,
+        plane_layouts: L,
+        queue_families: I,
+    ) -> Result<Arc<StorageImage>, ImageCreationError>
+    where
+        L: IntoIterator<Item = LinearLayout>,
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        StorageImage::with_drm_format_modifier(
+            device,
+            dimensions,
+            format,
+            usage,
+            flags,
+            DrmFormatModifier::Explicit {
+                modifier,
+                plane_layouts: plane_layouts.into_iter().collect(),
+            },
+            queue_families,
+            Some(fd),
+        )
+    }
+
+    /// Exports the memory of an image created with
+    /// [`new_with_drm_format_modifiers`](StorageImage::new_with_drm_format_modifiers) as a
+    /// dma-buf.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonflybsd",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn export_dma_buf(&self) -> Result<File, DeviceMemoryAllocError> {
+        self.memory.memory().export_fd(ExternalMemoryHandleType {
+            dma_buf: true,
+            ..ExternalMemoryHandleType::none()
+        })
+    }
+
+    // Implementation of the functions that create images with a DRM format modifier. The memory
+    // is imported from `import` if it is `Some`, and is exportable otherwise.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonflybsd",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    unsafe fn with_drm_format_modifier<'a, I>(
+        device: Arc<Device>,
+        dimensions: ImageDimensions,
+        format: Format,
+        usage: ImageUsage,
+        flags: ImageCreateFlags,
+        drm_format_modifier: DrmFormatModifier,
+        queue_families: I,
+        import: Option<File>,
+    ) -> Result<Arc<StorageImage>, ImageCreationError>
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        if !device.enabled_extensions().ext_external_memory_dma_buf {
+            return Err(
+                DeviceMemoryAllocError::MissingExtension("ext_external_memory_dma_buf").into(),
+            );
+        }
+
+        let handle_type = ExternalMemoryHandleType {
+            dma_buf: true,
+            ..ExternalMemoryHandleType::none()
+        };
+        let queue_families = queue_families
+            .into_iter()
+            .map(|f| f.id())
+            .collect::<SmallVec<[u32; 4]>>();
+
+        let (image, mem_reqs) = {
+            let sharing = if queue_families.len() >= 2 {
+                Sharing::Concurrent(queue_families.iter().cloned())
+            } else {
+                Sharing::Exclusive
+            };
+
+            UnsafeImage::new_with_drm_format_modifier(
+                device.clone(),
+                usage,
+                format,
+                flags,
+                dimensions,
+                sharing,
+                &drm_format_modifier,
+                Some(handle_type),
+            )?
+        };
+
+        let mut memory_type_bits = mem_reqs.memory_type_bits;
+
+        // The memory types that a dma-buf can be imported into must be queried.
+        if let Some(fd) = &import {
+            let fns = device.fns();
+            let mut properties = MaybeUninit::<ash::vk::MemoryFdPropertiesKHR>::zeroed();
+            (*properties.as_mut_ptr()).s_type = ash::vk::StructureType::MEMORY_FD_PROPERTIES_KHR;
+            check_errors(fns.khr_external_memory_fd.get_memory_fd_properties_khr(
+                device.internal_object(),
+                handle_type.into(),
+                fd.as_raw_fd(),
+                properties.as_mut_ptr(),
+            ))?;
+            memory_type_bits &= properties.assume_init().memory_type_bits;
+        }
+
+        let allowed = || {
+            device
+                .physical_device()
+                .memory_types()
+                .filter(|t| (memory_type_bits & (1 << t.id())) != 0)
+        };
+        let memory_type = allowed()
+            .find(|t| t.is_device_local())
+            .or_else(|| allowed().next())
+            .ok_or(DeviceMemoryAllocError::MemoryIndexInvalid)?;
+
+        let builder = DeviceMemoryBuilder::new(device.clone(), memory_type.id(), mem_reqs.size)
+            .dedicated_info(DedicatedAlloc::Image(&image));
+        let memory = match import {
+            Some(fd) => builder.import_info(fd, handle_type),
+            None => builder.export_info(handle_type),
+        }
+        .build()?;
+
+        // Will never panic because the builder only returns an atomically refcounted
+        // DeviceMemory object on success.
+        let memory = Arc::try_unwrap(memory).unwrap();
+        image.bind_memory(&memory, 0)?;
+
+        Ok(Arc::new(StorageImage {
+            image,
+            memory: PotentialDedicatedAllocation::Dedicated(memory),
+            dimensions,
+            format,
+            queue_families,
+            gpu_lock: AtomicUsize::new(0),
+        }))
+    }
+}
+
+impl<A> StorageImage<A>
+where
+    A: MemoryPool,
+{
+    /// Same as `with_usage`, but allocates the memory of the image from `pool` instead of the
+    /// standard pool of the device.
+    pub fn with_pool<'a, I>(
+        pool: &A,
+        dimensions: ImageDimensions,
+        format: Format,
+        usage: ImageUsage,
+        flags: ImageCreateFlags,
+        queue_families: I,
+    ) -> Result<Arc<StorageImage<A>>, ImageCreationError>
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        StorageImage::with_pool_impl(pool, dimensions, format, usage, flags, queue_families, &[])
+    }
+
+    fn with_pool_impl<'a, I>(
+        pool: &A,
+        dimensions: ImageDimensions,
+        format: Format,
+        usage: ImageUsage,
+        flags: ImageCreateFlags,
+        queue_families: I,
+        view_formats: &[Format],
+    ) -> Result<Arc<StorageImage<A>>, ImageCreationError>
+    where
+        I: IntoIterator<Item = QueueFamily<'a>>,
+    {
+        let device = pool.device();
+        let queue_families = queue_families
+            .into_iter()
+            .map(|f| f.id())
+            .collect::<SmallVec<[u32; 4]>>();
+
         let (image, mem_reqs) = unsafe {
             let sharing = if queue_families.len() >= 2 {
                 Sharing::Concurrent(queue_families.iter().cloned())
@@ -229,7 +819,7 @@ impl StorageImage {
                 Sharing::Exclusive
             };
 
-            UnsafeImage::new_with_exportable_fd(
+            UnsafeImage::new_with_view_formats(
                 device.clone(),
                 usage,
                 format,
@@ -240,11 +830,12 @@ impl StorageImage {
                 sharing,
                 false,
                 false,
+                view_formats,
             )?
         };
 
-        let memory = alloc_dedicated_with_exportable_fd(
-            device.clone(),
+        let memory = MemoryPool::alloc_from_requirements(
+            pool,
             &mem_reqs,
             AllocLayout::Optimal,
             MappingRequirement::DoNotMap,
@@ -271,33 +862,72 @@ impl StorageImage {
             gpu_lock: AtomicUsize::new(0),
         }))
     }
+}
 
-    /// Exports posix file descriptor for the allocated memory
-    /// requires `khr_external_memory_fd` and `khr_external_memory` extensions to be loaded.
-    /// Only works on Linux.
-    #[cfg(any(
-        target_os = "linux",
-        target_os = "dragonflybsd",
-        target_os = "freebsd",
-        target_os = "netbsd",
-        target_os = "openbsd"
-    ))]
-    pub fn export_posix_fd(&self) -> Result<File, DeviceMemoryAllocError> {
-        self.memory
-            .memory()
-            .export_fd(ExternalMemoryHandleType::posix())
+unsafe impl<A> DeviceOwned for StorageImage<A>
+where
+    A: MemoryPool,
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.image.device()
     }
+}
 
-    /// Return the size of the allocated memory (used for e.g. with cuda)
-    #[cfg(any(
-        target_os = "linux",
-        target_os = "dragonflybsd",
-        target_os = "freebsd",
-        target_os = "netbsd",
-        target_os = "openbsd"
-    ))]
-    pub fn mem_size(&self) -> DeviceSize {
-        self.memory.memory().size()
+// Images with the transfer source and destination usages can be moved by a `Defragmenter`.
+unsafe impl Relocate for StorageImage {
+    #[inline]
+    fn allocation(&self) -> Option<(&DeviceMemory, DeviceSize)> {
+        let usage = self.image.usage();
+        if !usage.transfer_source || !usage.transfer_destination {
+            return None;
+        }
+
+        match self.memory {
+            PotentialDedicatedAllocation::Generic(ref alloc) => {
+                Some((alloc.memory(), alloc.size()))
+            }
+            _ => None,
+        }
+    }
+
+    fn duplicate(&self) -> Result<Arc<Self>, DefragmentationError> {
+        Ok(StorageImage::with_pool_impl(
+            &Device::standard_pool(self.device()),
+            self.dimensions,
+            self.format,
+            self.image.usage(),
+            self.image.flags(),
+            self.queue_families.iter().map(|&id| {
+                self.device()
+                    .physical_device()
+                    .queue_family_by_id(id)
+                    .unwrap()
+            }),
+            self.image.view_formats(),
+        )?)
+    }
+
+    #[inline]
+    fn copy(
+        source: Arc<Self>,
+        destination: Arc<Self>,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) -> Result<(), DefragmentationError> {
+        let dimensions = source.dimensions;
+        builder.copy_image(
+            source,
+            [0, 0, 0],
+            0,
+            0,
+            destination,
+            [0, 0, 0],
+            0,
+            0,
+            dimensions.width_height_depth(),
+            dimensions.array_layers(),
+        )?;
+        Ok(())
     }
 }
 
@@ -435,8 +1065,15 @@ where
 #[cfg(test)]
 mod tests {
     use super::StorageImage;
+    use crate::command_buffer::AutoCommandBufferBuilder;
+    use crate::command_buffer::CommandBufferUsage;
+    use crate::command_buffer::PrimaryCommandBuffer;
+    use crate::format::ClearValue;
     use crate::format::Format;
+    use crate::image::ImageAccess;
     use crate::image::ImageDimensions;
+    use crate::image::ImageLayout;
+    use crate::sync::GpuFuture;
 
     #[test]
     fn create() {
@@ -453,4 +1090,50 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn with_layouts() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let img = StorageImage::new(
+            device.clone(),
+            ImageDimensions::Dim2d {
+                width: 32,
+                height: 32,
+                array_layers: 1,
+            },
+            Format::R8G8B8A8_UNORM,
+            Some(queue.family()),
+        )
+        .unwrap();
+
+        // The content of the image is discarded, and the image is handed over in the
+        // `ShaderReadOnlyOptimal` layout instead of `General`.
+        let wrapped = unsafe {
+            img.clone()
+                .with_layouts(ImageLayout::Undefined, ImageLayout::ShaderReadOnlyOptimal)
+        };
+        assert_eq!(wrapped.initial_layout_requirement(), ImageLayout::Undefined);
+        assert_eq!(
+            wrapped.final_layout_requirement(),
+            ImageLayout::ShaderReadOnlyOptimal
+        );
+        assert!(wrapped.is_layout_initialized());
+
+        let mut cbb = AutoCommandBufferBuilder::primary(
+            device,
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        cbb.clear_color_image(wrapped, ClearValue::Float([0.0; 4]))
+            .unwrap();
+        let cb = cbb.build().unwrap();
+
+        cb.execute(queue)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+    }
 }
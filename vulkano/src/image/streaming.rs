@@ -0,0 +1,1512 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Streaming of the mip levels of textures that don't all fit in memory at the same time.
+//!
+//! A [`TextureStreamer`] manages a set of [`StreamedTexture`]s, each of which only has some of
+//! its mip levels in memory at any given time: its *resident* levels. The resident levels of a
+//! texture always go from its [`min_resident_level`](StreamedTexture::min_resident_level) to its
+//! last level, and its smallest levels are always resident once it has been uploaded for the
+//! first time.
+//!
+//! Levels are made resident by requesting them, either with [`TextureStreamer::request`] or
+//! through the feedback buffer, and are uploaded in order of priority by the next call to
+//! [`TextureStreamer::flush`]. The data of a level is read from the [`MipLevelSource`] of its
+//! texture whenever the level is uploaded. When making levels resident would exceed the memory
+//! budget of the streamer, levels that are more detailed than what was last requested for their
+//! texture are evicted first, and then the levels of the textures with a lower priority.
+//!
+//! # Sparse residency
+//!
+//! If the `sparse_binding` and `sparse_residency_image2_d` features are enabled on the device,
+//! the queue of the streamer supports sparse binding and the format supports sparse residency,
+//! a texture is a single sparse image, and memory is bound to its levels as they are made
+//! resident. The image never changes, but the levels that aren't resident must not be sampled:
+//! shaders must clamp the level of detail to `min_resident_level`, for example by passing it as
+//! a push constant.
+//!
+//! Otherwise, a texture is backed by an image that only has its resident levels, and that is
+//! replaced whenever they change. The level 0 of this image is the level
+//! [`first_level`](StreamedImage::first_level) of the texture. Descriptor sets must be updated
+//! when [`StreamedTexture::image`] returns a different image.
+//!
+//! # Feedback
+//!
+//! The [`feedback_buffer`](TextureStreamer::feedback_buffer) of the streamer has one `u32` per
+//! texture, at the [`feedback_index`](StreamedTexture::feedback_index) of the texture. Shaders can
+//! write the most detailed level that they would like to sample into it with an atomic min:
+//!
+//! ```glsl
+//! float lod = max(textureQueryLod(tex, uv).y, 0.0);
+//! atomicMin(feedback.levels[feedback_index], uint(lod) + first_level);
+//! ```
+//!
+//! Once the work that writes the buffer has finished, [`TextureStreamer::process_feedback`]
+//! requests the levels that were written and resets the buffer. A texture that wasn't sampled
+//! since the last call only needs its smallest levels, and its other levels can be evicted.
+
+use crate::buffer::BufferUsage;
+use crate::buffer::CpuAccessibleBuffer;
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::BufferImageCopy;
+use crate::command_buffer::BuildError;
+use crate::command_buffer::CommandBufferExecError;
+use crate::command_buffer::CommandBufferUsage;
+use crate::command_buffer::CopyBufferImageError;
+use crate::command_buffer::CopyImageError;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::device::Queue;
+use crate::format::ClearValue;
+use crate::format::Format;
+use crate::image::sys::ImageCreationError;
+use crate::image::sys::SparseImageMemoryRequirements;
+use crate::image::sys::UnsafeImage;
+use crate::image::traits::ImageAccess;
+use crate::image::traits::ImageClearValue;
+use crate::image::traits::ImageContent;
+use crate::image::ImageAspect;
+use crate::image::ImageCreateFlags;
+use crate::image::ImageDescriptorLayouts;
+use crate::image::ImageDimensions;
+use crate::image::ImageInner;
+use crate::image::ImageLayout;
+use crate::image::ImageUsage;
+use crate::image::SampleCount;
+use crate::memory::DedicatedAlloc;
+use crate::memory::DeviceMemory;
+use crate::memory::DeviceMemoryAllocError;
+use crate::memory::MemoryRequirements;
+use crate::sync::AccessError;
+use crate::sync::GpuFuture;
+use crate::sync::Sharing;
+use crate::sync::SparseBindError;
+use crate::sync::SparseBindInfo;
+use crate::DeviceSize;
+use crate::OomError;
+use smallvec::SmallVec;
+use std::cmp;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::ptr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// Provides the data of the mip levels of a [`StreamedTexture`].
+pub trait MipLevelSource: Send + Sync {
+    /// Writes the data of mip level `level` into `destination`.
+    ///
+    /// The texels, or the blocks of a compressed format, are tightly packed in rows, and
+    /// `destination` has exactly the size of the level.
+    fn load(&self, level: u32, destination: &mut [u8]);
+}
+
+/// Manages the resident mip levels of a set of textures. See [the module-level
+/// documentation](self) for more information.
+pub struct TextureStreamer {
+    queue: Arc<Queue>,
+    budget: DeviceSize,
+    sparse: bool,
+    feedback: Arc<CpuAccessibleBuffer<[u32]>>,
+    state: Mutex<StreamerState>,
+}
+
+impl TextureStreamer {
+    /// Builds a new streamer that uploads levels on `queue`, with room for `max_textures`
+    /// textures.
+    ///
+    /// `budget` is the number of bytes of memory that the streamed levels of all the textures can
+    /// use. The smallest levels of each texture are always resident, and don't count towards the
+    /// budget.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `max_textures` is 0.
+    pub fn new(
+        queue: Arc<Queue>,
+        budget: DeviceSize,
+        max_textures: u32,
+    ) -> Result<Arc<TextureStreamer>, TextureStreamError> {
+        assert!(max_textures != 0);
+
+        let device = queue.device().clone();
+        let sparse = device.enabled_features().sparse_binding
+            && device.enabled_features().sparse_residency_image2_d
+            && queue.family().supports_sparse_binding();
+
+        let feedback = CpuAccessibleBuffer::from_iter(
+            device,
+            BufferUsage {
+                storage_buffer: true,
+                ..BufferUsage::none()
+            },
+            true,
+            (0..max_textures).map(|_| u32::MAX),
+        )?;
+
+        Ok(Arc::new(TextureStreamer {
+            queue,
+            budget,
+            sparse,
+            feedback,
+            state: Mutex::new(StreamerState {
+                textures: (0..max_textures).map(|_| None).collect(),
+                requests: BinaryHeap::new(),
+            }),
+        }))
+    }
+
+    /// Returns the queue that levels are uploaded on.
+    #[inline]
+    pub fn queue(&self) -> &Arc<Queue> {
+        &self.queue
+    }
+
+    /// Returns the number of bytes of memory that the streamed levels can use.
+    #[inline]
+    pub fn budget(&self) -> DeviceSize {
+        self.budget
+    }
+
+    /// Returns the number of bytes of memory that the streamed levels currently use.
+    #[inline]
+    pub fn resident_bytes(&self) -> DeviceSize {
+        self.state.lock().unwrap().resident_bytes()
+    }
+
+    /// Returns true if textures are created as sparse images when their format allows it.
+    #[inline]
+    pub fn uses_sparse_residency(&self) -> bool {
+        self.sparse
+    }
+
+    /// Returns the buffer that shaders write the levels they would like to sample into. See
+    /// [the module-level documentation](self) for more information.
+    #[inline]
+    pub fn feedback_buffer(&self) -> &Arc<CpuAccessibleBuffer<[u32]>> {
+        &self.feedback
+    }
+
+    /// Creates a new texture with `mip_levels` levels, whose data is read from `source`.
+    ///
+    /// The smallest levels of the texture are uploaded by the next call to `flush`, before any
+    /// other request. Only two-dimensional textures with a single array layer can be streamed,
+    /// in a format that has a single plane.
+    pub fn create_texture(
+        streamer: &Arc<TextureStreamer>,
+        dimensions: ImageDimensions,
+        format: Format,
+        mip_levels: u32,
+        source: Arc<dyn MipLevelSource>,
+    ) -> Result<Arc<StreamedTexture>, TextureStreamError> {
+        match dimensions {
+            ImageDimensions::Dim2d {
+                array_layers: 1, ..
+            } => (),
+            _ => return Err(TextureStreamError::UnsupportedDimensions),
+        }
+
+        if format.size().is_none()
+            || !format.planes().is_empty()
+            || !format.aspects().color
+            || format.requires_sampler_ycbcr_conversion()
+        {
+            return Err(TextureStreamError::UnsupportedFormat);
+        }
+
+        if mip_levels == 0 || mip_levels > dimensions.max_mipmaps() {
+            return Err(TextureStreamError::InvalidMipmapsCount {
+                obtained: mip_levels,
+                max: dimensions.max_mipmaps(),
+            });
+        }
+
+        let mut state = streamer.state.lock().unwrap();
+        let index = match state.textures.iter().position(|texture| texture.is_none()) {
+            Some(index) => index as u32,
+            None => return Err(TextureStreamError::TooManyTextures),
+        };
+
+        let device = streamer.queue.device();
+        let sparse = if streamer.sparse {
+            SparseTexture::new(device, dimensions, format, mip_levels)?
+        } else {
+            None
+        };
+
+        let tail_level = match &sparse {
+            Some((sparse, _)) => {
+                cmp::min(sparse.requirements.image_mip_tail_first_lod, mip_levels - 1)
+            }
+            None => (0..mip_levels)
+                .find(|&level| {
+                    let [width, height, _] = dimensions
+                        .mipmap_dimensions(level)
+                        .unwrap()
+                        .width_height_depth();
+                    width <= MAX_TAIL_EXTENT && height <= MAX_TAIL_EXTENT
+                })
+                .unwrap_or(mip_levels - 1),
+        };
+
+        let (sparse, image) = match sparse {
+            Some((sparse, image)) => (Some(sparse), Some(image)),
+            None => (None, None),
+        };
+
+        state.textures[index as usize] = Some(TextureState {
+            source,
+            dimensions,
+            format,
+            mip_levels,
+            tail_level,
+            min_resident_level: mip_levels,
+            requested_level: tail_level,
+            priority: 0,
+            image,
+            sparse,
+        });
+        state.requests.push(Request {
+            priority: u32::MAX,
+            level: Reverse(tail_level),
+            index,
+        });
+
+        Ok(Arc::new(StreamedTexture {
+            streamer: streamer.clone(),
+            index,
+            dimensions,
+            format,
+            mip_levels,
+        }))
+    }
+
+    /// Requests that `texture` has its levels starting at `level` resident, with the given
+    /// priority. Requests with a higher priority are uploaded first.
+    ///
+    /// The request replaces the previous ones for the texture: if `level` is less detailed than
+    /// the resident levels, the more detailed ones can be evicted.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `texture` doesn't belong to this streamer.
+    pub fn request(&self, texture: &StreamedTexture, level: u32, priority: u32) {
+        assert!(ptr::eq(&*texture.streamer, self));
+        self.state
+            .lock()
+            .unwrap()
+            .request(texture.index, level, priority);
+    }
+
+    /// Requests the levels that were written into the feedback buffer with `priority`, and resets
+    /// the buffer.
+    ///
+    /// Returns an error if the buffer is still in use by the GPU.
+    pub fn process_feedback(&self, priority: u32) -> Result<(), TextureStreamError> {
+        let mut feedback = self
+            .feedback
+            .write()
+            .map_err(|_| TextureStreamError::FeedbackBufferLocked)?;
+        let mut state = self.state.lock().unwrap();
+
+        for (index, level) in feedback.iter_mut().enumerate() {
+            if *level != u32::MAX {
+                state.request(index as u32, *level, priority);
+            } else if let Some(texture) = state.textures[index].as_mut() {
+                // The texture wasn't sampled, so only its smallest levels are needed.
+                texture.requested_level = texture.tail_level;
+            }
+
+            *level = u32::MAX;
+        }
+
+        Ok(())
+    }
+
+    /// Uploads the requested levels after `future`, in order of priority, and with at most around
+    /// `max_bytes` bytes of data. The first request is always uploaded, even if it is larger.
+    /// Returns a future that represents the end of the uploads.
+    ///
+    /// The requests that remain are kept for the next call. The requests that can't be satisfied
+    /// without exceeding the budget are dropped. If an error is returned, no level is made
+    /// resident and all the requests are kept.
+    pub fn flush<F>(
+        &self,
+        future: F,
+        max_bytes: DeviceSize,
+    ) -> Result<Box<dyn GpuFuture>, TextureStreamError>
+    where
+        F: GpuFuture + 'static,
+    {
+        let mut state = self.state.lock().unwrap();
+        let (targets, planned) = state.plan(self.budget, max_bytes);
+
+        let (future, commits) = match self.upload(&state, &targets, future) {
+            Ok(result) => result,
+            Err(err) => {
+                state.requests.extend(planned);
+                return Err(err);
+            }
+        };
+
+        // Nothing can fail anymore, so the new state of the textures can be recorded.
+        for commit in commits {
+            let texture = state.textures[commit.index as usize].as_mut().unwrap();
+            texture.min_resident_level = commit.min_resident_level;
+
+            if let Some(image) = commit.image {
+                texture.image = Some(image);
+            }
+
+            let image = texture.image.as_ref().unwrap();
+            let mut memory = image.memory.lock().unwrap();
+
+            for (slot, bound) in commit.bound {
+                memory[slot] = Some(bound);
+            }
+
+            for slot in commit.unbound {
+                memory[slot] = None;
+            }
+        }
+
+        Ok(future)
+    }
+
+    // Records the uploads and the sparse binds that make the levels of the textures resident up to
+    // their level in `targets`, and submits them after `future`. Returns the future of the uploads
+    // and the changes to record in the textures once nothing can fail anymore.
+    fn upload<F>(
+        &self,
+        state: &StreamerState,
+        targets: &HashMap<u32, u32>,
+        future: F,
+    ) -> Result<(Box<dyn GpuFuture>, Vec<Commit>), TextureStreamError>
+    where
+        F: GpuFuture + 'static,
+    {
+        let device = self.queue.device();
+
+        // Uploads from the staging buffer, with the image to upload into, the level of the texture
+        // and the offset of its data.
+        let mut uploads: Vec<(Arc<StreamedImage>, u32, u32, DeviceSize)> = Vec::new();
+        // Copies of the levels that are kept when a texture gets a new image.
+        let mut copies: Vec<(Arc<StreamedImage>, Arc<StreamedImage>, u32, [u32; 3])> = Vec::new();
+        let mut binds = SparseBindInfo::new();
+        let mut commits = Vec::with_capacity(targets.len());
+        let mut staging_size: DeviceSize = 0;
+
+        for (&index, &target) in targets {
+            let texture = state.textures[index as usize].as_ref().unwrap();
+            let current = texture.min_resident_level;
+
+            if target == current {
+                continue;
+            }
+
+            let mut commit = Commit {
+                index,
+                min_resident_level: target,
+                image: None,
+                bound: Vec::new(),
+                unbound: Vec::new(),
+            };
+
+            let image = match &texture.sparse {
+                Some(sparse) => {
+                    let image = texture.image.clone().unwrap();
+                    sparse.update(device, texture, &image, target, &mut binds, &mut commit)?;
+                    image
+                }
+                None => {
+                    let image = StreamedImage::with_levels(
+                        device,
+                        texture.dimensions,
+                        texture.format,
+                        target,
+                        texture.mip_levels,
+                    )?;
+
+                    if let Some(old_image) = &texture.image {
+                        for level in cmp::max(current, target)..texture.mip_levels {
+                            copies.push((
+                                old_image.clone(),
+                                image.clone(),
+                                level,
+                                texture.level_extent(level),
+                            ));
+                        }
+                    }
+
+                    commit.image = Some(image.clone());
+                    image
+                }
+            };
+
+            for level in target..current {
+                let alignment = texture.format.size().unwrap() * 4;
+                staging_size = (staging_size + alignment - 1) / alignment * alignment;
+                uploads.push((image.clone(), index, level, staging_size));
+                staging_size += texture.data_size(level);
+            }
+
+            commits.push(commit);
+        }
+
+        // The command buffer is recorded before anything is submitted.
+        let command_buffer = if !uploads.is_empty() || !copies.is_empty() {
+            let mut cbb = AutoCommandBufferBuilder::primary(
+                device.clone(),
+                self.queue.family(),
+                CommandBufferUsage::OneTimeSubmit,
+            )?;
+
+            for (source, destination, level, extent) in copies {
+                cbb.copy_image(
+                    source.clone(),
+                    [0, 0, 0],
+                    0,
+                    level - source.first_level,
+                    destination.clone(),
+                    [0, 0, 0],
+                    0,
+                    level - destination.first_level,
+                    extent,
+                    1,
+                )?;
+            }
+
+            if !uploads.is_empty() {
+                let staging = unsafe {
+                    CpuAccessibleBuffer::<[u8]>::uninitialized_array(
+                        device.clone(),
+                        staging_size,
+                        BufferUsage::transfer_source(),
+                        false,
+                    )?
+                };
+
+                {
+                    let mut data = staging.write().unwrap();
+
+                    for &(_, index, level, offset) in &uploads {
+                        let texture = state.textures[index as usize].as_ref().unwrap();
+                        let range = offset as usize..(offset + texture.data_size(level)) as usize;
+                        texture.source.load(level, &mut data[range]);
+                    }
+                }
+
+                for (image, index, level, offset) in uploads {
+                    let texture = state.textures[index as usize].as_ref().unwrap();
+                    let region = BufferImageCopy {
+                        buffer_offset: offset,
+                        buffer_row_length: 0,
+                        buffer_image_height: 0,
+                        image_aspect: ImageAspect::Color,
+                        image_mip_level: level - image.first_level,
+                        image_base_array_layer: 0,
+                        image_layer_count: 1,
+                        image_offset: [0, 0, 0],
+                        image_extent: texture.level_extent(level),
+                    };
+
+                    cbb.copy_buffer_to_image_regions(staging.clone(), image, Some(region))?;
+                }
+            }
+
+            Some(cbb.build()?)
+        } else {
+            None
+        };
+
+        let mut future = future.boxed();
+
+        if !binds.is_empty() {
+            future = future.then_bind_sparse(self.queue.clone(), binds)?.boxed();
+        }
+
+        if let Some(command_buffer) = command_buffer {
+            future = future
+                .then_execute(self.queue.clone(), command_buffer)?
+                .boxed();
+        }
+
+        Ok((future, commits))
+    }
+}
+
+impl fmt::Debug for TextureStreamer {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("TextureStreamer")
+            .field("queue", &self.queue)
+            .field("budget", &self.budget)
+            .field("sparse", &self.sparse)
+            .finish()
+    }
+}
+
+unsafe impl DeviceOwned for TextureStreamer {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.queue.device()
+    }
+}
+
+// Textures whose levels are at most this wide and high are always resident when they aren't
+// sparse.
+const MAX_TAIL_EXTENT: u32 = 64;
+
+struct StreamerState {
+    // The textures, indexed by their feedback index.
+    textures: Vec<Option<TextureState>>,
+    requests: BinaryHeap<Request>,
+}
+
+impl StreamerState {
+    fn resident_bytes(&self) -> DeviceSize {
+        self.textures
+            .iter()
+            .flatten()
+            .map(|texture| texture.budgeted_size(texture.min_resident_level))
+            .sum()
+    }
+
+    fn request(&mut self, index: u32, level: u32, priority: u32) {
+        let texture = match self.textures[index as usize].as_mut() {
+            Some(texture) => texture,
+            None => return,
+        };
+
+        let level = cmp::min(level, texture.tail_level);
+        texture.requested_level = level;
+        texture.priority = priority;
+
+        if level < texture.min_resident_level {
+            self.requests.push(Request {
+                priority,
+                level: Reverse(level),
+                index,
+            });
+        }
+    }
+
+    // Chooses the new minimum resident level of the textures, by taking the requests in order of
+    // priority until `max_bytes` bytes would be uploaded. Also returns the requests that the
+    // targets satisfy, which must be pushed back if they can't be uploaded.
+    fn plan(
+        &mut self,
+        budget: DeviceSize,
+        max_bytes: DeviceSize,
+    ) -> (HashMap<u32, u32>, Vec<Request>) {
+        let mut targets = HashMap::new();
+        let mut planned = Vec::new();
+        let mut resident_bytes = self.resident_bytes();
+        let mut uploaded: DeviceSize = 0;
+
+        while let Some(request) = self.requests.pop() {
+            let texture = match &self.textures[request.index as usize] {
+                Some(texture) => texture,
+                None => continue,
+            };
+            let level = request.level.0;
+            let current = targets
+                .get(&request.index)
+                .copied()
+                .unwrap_or(texture.min_resident_level);
+
+            // Skip the requests that are satisfied, or that were replaced by a less detailed one.
+            if level >= current || level < texture.requested_level {
+                continue;
+            }
+
+            let upload_size: DeviceSize = (level..current).map(|l| texture.data_size(l)).sum();
+
+            if uploaded != 0 && uploaded + upload_size > max_bytes {
+                self.requests.push(request);
+                break;
+            }
+
+            let needed = texture.budgeted_size(level) - texture.budgeted_size(current);
+            let mut new_targets = targets.clone();
+            let mut new_resident_bytes = resident_bytes + needed;
+
+            if !self.evict(budget, &request, &mut new_targets, &mut new_resident_bytes) {
+                continue;
+            }
+
+            new_targets.insert(request.index, level);
+            targets = new_targets;
+            resident_bytes = new_resident_bytes;
+            uploaded += upload_size;
+            planned.push(request);
+        }
+
+        (targets, planned)
+    }
+
+    // Evicts levels of other textures until `resident_bytes` fits in the budget. Returns false if
+    // there aren't enough levels that can be evicted for `request`.
+    fn evict(
+        &self,
+        budget: DeviceSize,
+        request: &Request,
+        targets: &mut HashMap<u32, u32>,
+        resident_bytes: &mut DeviceSize,
+    ) -> bool {
+        while *resident_bytes > budget {
+            let candidate = self
+                .textures
+                .iter()
+                .enumerate()
+                .filter_map(|(index, texture)| {
+                    let texture = texture.as_ref()?;
+                    let index = index as u32;
+                    let current = targets
+                        .get(&index)
+                        .copied()
+                        .unwrap_or(texture.min_resident_level);
+
+                    if index == request.index || current >= texture.tail_level {
+                        return None;
+                    }
+
+                    // Levels that are more detailed than the last request aren't needed anymore.
+                    let needed = current >= texture.requested_level;
+
+                    if needed && texture.priority >= request.priority {
+                        return None;
+                    }
+
+                    Some((needed, texture.priority, index, current))
+                })
+                .min();
+
+            let (_, _, index, current) = match candidate {
+                Some(candidate) => candidate,
+                None => return false,
+            };
+
+            let texture = self.textures[index as usize].as_ref().unwrap();
+            *resident_bytes -= texture.budgeted_size(current) - texture.budgeted_size(current + 1);
+            targets.insert(index, current + 1);
+        }
+
+        true
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Request {
+    priority: u32,
+    // Reversed so that the most detailed levels come first for the same priority.
+    level: Reverse<u32>,
+    index: u32,
+}
+
+struct TextureState {
+    source: Arc<dyn MipLevelSource>,
+    dimensions: ImageDimensions,
+    format: Format,
+    mip_levels: u32,
+    // The first of the levels that are always resident.
+    tail_level: u32,
+    // Equal to `mip_levels` until the first upload.
+    min_resident_level: u32,
+    // The level and priority of the last request, used to choose the levels to evict.
+    requested_level: u32,
+    priority: u32,
+    // `None` until the first upload when the texture isn't sparse.
+    image: Option<Arc<StreamedImage>>,
+    sparse: Option<SparseTexture>,
+}
+
+impl TextureState {
+    fn level_extent(&self, level: u32) -> [u32; 3] {
+        self.dimensions
+            .mipmap_dimensions(level)
+            .unwrap()
+            .width_height_depth()
+    }
+
+    // The number of bytes of data of a level.
+    fn data_size(&self, level: u32) -> DeviceSize {
+        let [width, height, depth] = self.level_extent(level);
+        let [block_width, block_height] = self.format.block_dimensions();
+
+        ((width + block_width - 1) / block_width) as DeviceSize
+            * ((height + block_height - 1) / block_height) as DeviceSize
+            * depth as DeviceSize
+            * self.format.size().unwrap()
+    }
+
+    // The number of bytes of memory of a level.
+    fn memory_size(&self, level: u32) -> DeviceSize {
+        match &self.sparse {
+            Some(sparse) => {
+                let [width, height, depth] = self.level_extent(level);
+                let [block_width, block_height, block_depth] =
+                    sparse.requirements.image_granularity;
+
+                ((width + block_width - 1) / block_width) as DeviceSize
+                    * ((height + block_height - 1) / block_height) as DeviceSize
+                    * ((depth + block_depth - 1) / block_depth) as DeviceSize
+                    * sparse.alignment
+            }
+            None => self.data_size(level),
+        }
+    }
+
+    // The number of bytes of memory that count towards the budget when the levels starting at
+    // `min_resident_level` are resident.
+    fn budgeted_size(&self, min_resident_level: u32) -> DeviceSize {
+        (min_resident_level..self.tail_level)
+            .map(|level| self.memory_size(level))
+            .sum()
+    }
+}
+
+// The sparse image of a texture.
+struct SparseTexture {
+    memory_type: u32,
+    alignment: DeviceSize,
+    requirements: SparseImageMemoryRequirements,
+}
+
+impl SparseTexture {
+    // Creates a sparse image for a texture, or returns `None` if the format doesn't support it.
+    fn new(
+        device: &Arc<Device>,
+        dimensions: ImageDimensions,
+        format: Format,
+        mip_levels: u32,
+    ) -> Result<Option<(SparseTexture, Arc<StreamedImage>)>, TextureStreamError> {
+        let flags = ImageCreateFlags {
+            sparse_binding: true,
+            sparse_residency: true,
+            ..ImageCreateFlags::none()
+        };
+
+        let (image, mem_reqs) =
+            match StreamedImage::create(device, dimensions, format, flags, mip_levels) {
+                Ok(image) => image,
+                Err(ImageCreationError::FormatNotSupported) => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
+
+        let requirements = match image
+            .sparse_memory_requirements()
+            .into_iter()
+            .find(|requirements| requirements.aspects.color)
+        {
+            Some(requirements) => requirements,
+            None => return Ok(None),
+        };
+
+        let memory_type = match memory_type(device, mem_reqs.memory_type_bits) {
+            Some(memory_type) => memory_type,
+            None => return Ok(None),
+        };
+
+        let image = Arc::new(StreamedImage {
+            image: Arc::new(image),
+            first_level: 0,
+            // One slot per level, followed by one for the mip tail.
+            memory: Mutex::new(vec![None; mip_levels as usize + 1]),
+            initialized: AtomicBool::new(false),
+            gpu_lock: AtomicUsize::new(0),
+        });
+
+        Ok(Some((
+            SparseTexture {
+                memory_type,
+                alignment: mem_reqs.alignment,
+                requirements,
+            },
+            image,
+        )))
+    }
+
+    // Adds the binds that change the resident levels of `image` to `target`.
+    fn update(
+        &self,
+        device: &Arc<Device>,
+        texture: &TextureState,
+        image: &StreamedImage,
+        target: u32,
+        binds: &mut SparseBindInfo,
+        commit: &mut Commit,
+    ) -> Result<(), TextureStreamError> {
+        let memory = image.memory.lock().unwrap();
+        let memory_type = device
+            .physical_device()
+            .memory_type_by_id(self.memory_type)
+            .unwrap();
+        let tail_slot = texture.mip_levels as usize;
+
+        for level in texture.min_resident_level..target {
+            if let Some(evicted) = &memory[level as usize] {
+                binds.unbind_image(
+                    image.image.clone(),
+                    ImageAspect::Color,
+                    level,
+                    0,
+                    [0, 0, 0],
+                    texture.level_extent(level),
+                )?;
+                // The memory must stay alive until it is unbound.
+                binds.retain_memory(evicted.clone());
+                commit.unbound.push(level as usize);
+            }
+        }
+
+        for level in target..texture.min_resident_level {
+            if level >= self.requirements.image_mip_tail_first_lod {
+                if memory[tail_slot].is_some()
+                    || commit.bound.iter().any(|&(slot, _)| slot == tail_slot)
+                {
+                    continue;
+                }
+
+                let size = self.requirements.image_mip_tail_size;
+                let tail = Arc::new(DeviceMemory::alloc(device.clone(), memory_type, size)?);
+                binds.bind_image_opaque(
+                    image.image.clone(),
+                    self.requirements.image_mip_tail_offset,
+                    size,
+                    tail.clone(),
+                    0,
+                    false,
+                )?;
+                commit.bound.push((tail_slot, tail));
+            } else {
+                let size = texture.memory_size(level);
+                let bound = Arc::new(DeviceMemory::alloc(device.clone(), memory_type, size)?);
+                binds.bind_image(
+                    image.image.clone(),
+                    ImageAspect::Color,
+                    level,
+                    0,
+                    [0, 0, 0],
+                    texture.level_extent(level),
+                    bound.clone(),
+                    0,
+                )?;
+                commit.bound.push((level as usize, bound));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The changes to a texture, that are recorded once the uploads are submitted.
+struct Commit {
+    index: u32,
+    min_resident_level: u32,
+    image: Option<Arc<StreamedImage>>,
+    bound: Vec<(usize, Arc<DeviceMemory>)>,
+    unbound: Vec<usize>,
+}
+
+// Returns the memory type to allocate the memory of an image from, preferring device-local memory.
+fn memory_type(device: &Device, memory_type_bits: u32) -> Option<u32> {
+    let allowed = || {
+        device
+            .physical_device()
+            .memory_types()
+            .filter(|ty| memory_type_bits & (1 << ty.id()) != 0)
+    };
+
+    allowed()
+        .find(|ty| ty.is_device_local())
+        .or_else(|| allowed().next())
+        .map(|ty| ty.id())
+}
+
+/// A texture whose mip levels are streamed by a [`TextureStreamer`].
+///
+/// The texture is removed from the streamer when it is dropped.
+pub struct StreamedTexture {
+    streamer: Arc<TextureStreamer>,
+    index: u32,
+    dimensions: ImageDimensions,
+    format: Format,
+    mip_levels: u32,
+}
+
+impl StreamedTexture {
+    /// Returns the streamer of the texture.
+    #[inline]
+    pub fn streamer(&self) -> &Arc<TextureStreamer> {
+        &self.streamer
+    }
+
+    /// Returns the index of the texture in the feedback buffer of the streamer.
+    #[inline]
+    pub fn feedback_index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the dimensions of the level 0 of the texture.
+    #[inline]
+    pub fn dimensions(&self) -> ImageDimensions {
+        self.dimensions
+    }
+
+    /// Returns the format of the texture.
+    #[inline]
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Returns the number of mip levels of the texture.
+    #[inline]
+    pub fn mip_levels(&self) -> u32 {
+        self.mip_levels
+    }
+
+    /// Returns the most detailed level that is resident, or the number of levels if the texture
+    /// hasn't been uploaded yet.
+    #[inline]
+    pub fn min_resident_level(&self) -> u32 {
+        self.streamer.state.lock().unwrap().textures[self.index as usize]
+            .as_ref()
+            .unwrap()
+            .min_resident_level
+    }
+
+    /// Returns the image that currently backs the texture.
+    ///
+    /// Returns `None` if the texture isn't sparse and hasn't been uploaded yet.
+    #[inline]
+    pub fn image(&self) -> Option<Arc<StreamedImage>> {
+        self.streamer.state.lock().unwrap().textures[self.index as usize]
+            .as_ref()
+            .unwrap()
+            .image
+            .clone()
+    }
+}
+
+impl fmt::Debug for StreamedTexture {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("StreamedTexture")
+            .field("index", &self.index)
+            .field("dimensions", &self.dimensions)
+            .field("format", &self.format)
+            .field("mip_levels", &self.mip_levels)
+            .finish()
+    }
+}
+
+impl Drop for StreamedTexture {
+    fn drop(&mut self) {
+        // The images stay alive as long as the command buffers that use them.
+        self.streamer.state.lock().unwrap().textures[self.index as usize] = None;
+    }
+}
+
+/// The image of a [`StreamedTexture`].
+///
+/// The image is in the `General` layout, and can be sampled like any other image.
+#[derive(Debug)]
+pub struct StreamedImage {
+    image: Arc<UnsafeImage>,
+
+    // The level of the texture that is the level 0 of the image.
+    first_level: u32,
+
+    // The memory bound to the image. A sparse image has one slot per level, followed by one for
+    // its mip tail, and other images have a single slot.
+    memory: Mutex<Vec<Option<Arc<DeviceMemory>>>>,
+
+    initialized: AtomicBool,
+
+    // Number of times this image is locked on the GPU side.
+    gpu_lock: AtomicUsize,
+}
+
+impl StreamedImage {
+    // Creates the image of a texture that isn't sparse, with the levels starting at `first_level`.
+    fn with_levels(
+        device: &Arc<Device>,
+        dimensions: ImageDimensions,
+        format: Format,
+        first_level: u32,
+        mip_levels: u32,
+    ) -> Result<Arc<StreamedImage>, TextureStreamError> {
+        let (image, mem_reqs) = StreamedImage::create(
+            device,
+            dimensions.mipmap_dimensions(first_level).unwrap(),
+            format,
+            ImageCreateFlags::none(),
+            mip_levels - first_level,
+        )?;
+
+        let memory_type = memory_type(device, mem_reqs.memory_type_bits)
+            .map(|id| device.physical_device().memory_type_by_id(id).unwrap())
+            .ok_or(TextureStreamError::DeviceMemoryAllocError(
+                DeviceMemoryAllocError::OomError(OomError::OutOfDeviceMemory),
+            ))?;
+        let memory = DeviceMemory::dedicated_alloc(
+            device.clone(),
+            memory_type,
+            mem_reqs.size,
+            DedicatedAlloc::Image(&image),
+        )?;
+
+        unsafe {
+            image.bind_memory(&memory, 0)?;
+        }
+
+        Ok(Arc::new(StreamedImage {
+            image: Arc::new(image),
+            first_level,
+            memory: Mutex::new(vec![Some(Arc::new(memory))]),
+            initialized: AtomicBool::new(false),
+            gpu_lock: AtomicUsize::new(0),
+        }))
+    }
+
+    fn create(
+        device: &Arc<Device>,
+        dimensions: ImageDimensions,
+        format: Format,
+        flags: ImageCreateFlags,
+        mip_levels: u32,
+    ) -> Result<(UnsafeImage, MemoryRequirements), ImageCreationError> {
+        let usage = ImageUsage {
+            transfer_source: true,
+            transfer_destination: true,
+            sampled: true,
+            ..ImageUsage::none()
+        };
+        let queue_families = device
+            .active_queue_families()
+            .map(|family| family.id())
+            .collect::<SmallVec<[u32; 4]>>();
+        let sharing = if queue_families.len() >= 2 {
+            Sharing::Concurrent(queue_families.iter().cloned())
+        } else {
+            Sharing::Exclusive
+        };
+
+        unsafe {
+            UnsafeImage::new(
+                device.clone(),
+                usage,
+                format,
+                flags,
+                dimensions,
+                SampleCount::Sample1,
+                mip_levels,
+                sharing,
+                false,
+                false,
+            )
+        }
+    }
+
+    /// Returns the level of the texture that is the level 0 of the image.
+    #[inline]
+    pub fn first_level(&self) -> u32 {
+        self.first_level
+    }
+
+    /// Returns true if the image is a sparse image, that has all the levels of the texture.
+    #[inline]
+    pub fn is_sparse(&self) -> bool {
+        self.image.create_flags().sparse_residency
+    }
+}
+
+unsafe impl DeviceOwned for StreamedImage {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.image.device()
+    }
+}
+
+unsafe impl ImageAccess for StreamedImage {
+    #[inline]
+    fn inner(&self) -> ImageInner {
+        ImageInner {
+            image: &self.image,
+            first_layer: 0,
+            num_layers: 1,
+            first_mipmap_level: 0,
+            num_mipmap_levels: self.image.mipmap_levels() as usize,
+        }
+    }
+
+    #[inline]
+    unsafe fn layout_initialized(&self) {
+        self.initialized.store(true, Ordering::SeqCst);
+    }
+
+    #[inline]
+    fn is_layout_initialized(&self) -> bool {
+        self.initialized.load(Ordering::SeqCst)
+    }
+
+    #[inline]
+    fn initial_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    #[inline]
+    fn final_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    #[inline]
+    fn descriptor_layouts(&self) -> Option<ImageDescriptorLayouts> {
+        Some(ImageDescriptorLayouts {
+            storage_image: ImageLayout::General,
+            combined_image_sampler: ImageLayout::General,
+            sampled_image: ImageLayout::General,
+            input_attachment: ImageLayout::General,
+        })
+    }
+
+    #[inline]
+    fn conflict_key(&self) -> u64 {
+        self.image.key()
+    }
+
+    #[inline]
+    fn try_gpu_lock(
+        &self,
+        _: bool,
+        _: bool,
+        expected_layout: ImageLayout,
+    ) -> Result<(), AccessError> {
+        if expected_layout != ImageLayout::General && expected_layout != ImageLayout::Undefined {
+            return Err(AccessError::UnexpectedImageLayout {
+                requested: expected_layout,
+                allowed: ImageLayout::General,
+            });
+        }
+
+        let val = self
+            .gpu_lock
+            .compare_exchange(0, 1, Ordering::SeqCst, Ordering::SeqCst)
+            .unwrap_or_else(|e| e);
+        if val == 0 {
+            Ok(())
+        } else {
+            Err(AccessError::AlreadyInUse)
+        }
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self) {
+        let val = self.gpu_lock.fetch_add(1, Ordering::SeqCst);
+        debug_assert!(val >= 1);
+    }
+
+    #[inline]
+    unsafe fn unlock(&self, new_layout: Option<ImageLayout>) {
+        debug_assert!(new_layout.is_none() || new_layout == Some(ImageLayout::General));
+        let prev_val = self.gpu_lock.fetch_sub(1, Ordering::SeqCst);
+        debug_assert!(prev_val >= 1);
+    }
+
+    #[inline]
+    fn current_miplevels_access(&self) -> std::ops::Range<u32> {
+        0..self.mipmap_levels()
+    }
+
+    #[inline]
+    fn current_layer_levels_access(&self) -> std::ops::Range<u32> {
+        0..1
+    }
+}
+
+unsafe impl ImageClearValue<ClearValue> for StreamedImage {
+    #[inline]
+    fn decode(&self, value: ClearValue) -> Option<ClearValue> {
+        Some(self.image.format().decode_clear_value(value))
+    }
+}
+
+unsafe impl<P> ImageContent<P> for StreamedImage {
+    #[inline]
+    fn matches_format(&self) -> bool {
+        true // FIXME:
+    }
+}
+
+impl PartialEq for StreamedImage {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.inner() == other.inner()
+    }
+}
+
+impl Eq for StreamedImage {}
+
+impl Hash for StreamedImage {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner().hash(state);
+    }
+}
+
+/// Error that can happen when streaming textures.
+#[derive(Debug, Clone)]
+pub enum TextureStreamError {
+    /// Creating an image failed.
+    ImageCreationError(ImageCreationError),
+    /// Allocating memory failed.
+    DeviceMemoryAllocError(DeviceMemoryAllocError),
+    /// Binding the memory of a sparse image failed.
+    SparseBindError(SparseBindError),
+    /// Recording the copy of the levels that are kept when a texture gets a new image failed.
+    CopyImageError(CopyImageError),
+    /// Recording the upload of a level failed.
+    CopyBufferImageError(CopyBufferImageError),
+    /// Building the command buffer of the uploads failed.
+    BuildError(BuildError),
+    /// Executing the uploads failed.
+    CommandBufferExecError(CommandBufferExecError),
+    /// Not enough memory.
+    OomError(OomError),
+    /// The feedback buffer is still in use.
+    FeedbackBufferLocked,
+    /// Only two-dimensional textures with a single array layer can be streamed.
+    UnsupportedDimensions,
+    /// The format isn't a color format with a single plane.
+    UnsupportedFormat,
+    /// No mipmap level was requested, or more than the dimensions allow.
+    InvalidMipmapsCount { obtained: u32, max: u32 },
+    /// All the feedback indices of the streamer are used.
+    TooManyTextures,
+}
+
+impl error::Error for TextureStreamError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            TextureStreamError::ImageCreationError(ref err) => Some(err),
+            TextureStreamError::DeviceMemoryAllocError(ref err) => Some(err),
+            TextureStreamError::SparseBindError(ref err) => Some(err),
+            TextureStreamError::CopyImageError(ref err) => Some(err),
+            TextureStreamError::CopyBufferImageError(ref err) => Some(err),
+            TextureStreamError::BuildError(ref err) => Some(err),
+            TextureStreamError::CommandBufferExecError(ref err) => Some(err),
+            TextureStreamError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TextureStreamError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                TextureStreamError::ImageCreationError(_) => "creating an image failed",
+                TextureStreamError::DeviceMemoryAllocError(_) => "allocating memory failed",
+                TextureStreamError::SparseBindError(_) => {
+                    "binding the memory of a sparse image failed"
+                }
+                TextureStreamError::CopyImageError(_) => {
+                    "recording the copy of the kept levels of a texture failed"
+                }
+                TextureStreamError::CopyBufferImageError(_) => {
+                    "recording the upload of a level failed"
+                }
+                TextureStreamError::BuildError(_) => {
+                    "building the command buffer of the uploads failed"
+                }
+                TextureStreamError::CommandBufferExecError(_) => "executing the uploads failed",
+                TextureStreamError::OomError(_) => "not enough memory available",
+                TextureStreamError::FeedbackBufferLocked => "the feedback buffer is still in use",
+                TextureStreamError::UnsupportedDimensions => {
+                    "only two-dimensional textures with a single array layer can be streamed"
+                }
+                TextureStreamError::UnsupportedFormat => {
+                    "the format isn't a color format with a single plane"
+                }
+                TextureStreamError::InvalidMipmapsCount { .. } => {
+                    "a wrong number of mipmap levels was provided"
+                }
+                TextureStreamError::TooManyTextures => {
+                    "all the feedback indices of the streamer are used"
+                }
+            }
+        )
+    }
+}
+
+impl From<ImageCreationError> for TextureStreamError {
+    #[inline]
+    fn from(err: ImageCreationError) -> TextureStreamError {
+        TextureStreamError::ImageCreationError(err)
+    }
+}
+
+impl From<DeviceMemoryAllocError> for TextureStreamError {
+    #[inline]
+    fn from(err: DeviceMemoryAllocError) -> TextureStreamError {
+        TextureStreamError::DeviceMemoryAllocError(err)
+    }
+}
+
+impl From<SparseBindError> for TextureStreamError {
+    #[inline]
+    fn from(err: SparseBindError) -> TextureStreamError {
+        TextureStreamError::SparseBindError(err)
+    }
+}
+
+impl From<CopyImageError> for TextureStreamError {
+    #[inline]
+    fn from(err: CopyImageError) -> TextureStreamError {
+        TextureStreamError::CopyImageError(err)
+    }
+}
+
+impl From<CopyBufferImageError> for TextureStreamError {
+    #[inline]
+    fn from(err: CopyBufferImageError) -> TextureStreamError {
+        TextureStreamError::CopyBufferImageError(err)
+    }
+}
+
+impl From<BuildError> for TextureStreamError {
+    #[inline]
+    fn from(err: BuildError) -> TextureStreamError {
+        TextureStreamError::BuildError(err)
+    }
+}
+
+impl From<CommandBufferExecError> for TextureStreamError {
+    #[inline]
+    fn from(err: CommandBufferExecError) -> TextureStreamError {
+        TextureStreamError::CommandBufferExecError(err)
+    }
+}
+
+impl From<OomError> for TextureStreamError {
+    #[inline]
+    fn from(err: OomError) -> TextureStreamError {
+        TextureStreamError::OomError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MipLevelSource;
+    use super::TextureStreamError;
+    use super::TextureStreamer;
+    use crate::format::Format;
+    use crate::image::ImageDimensions;
+    use crate::sync::now;
+    use crate::sync::GpuFuture;
+    use std::sync::Arc;
+
+    struct Zeroes;
+
+    impl MipLevelSource for Zeroes {
+        fn load(&self, _: u32, destination: &mut [u8]) {
+            for byte in destination {
+                *byte = 0;
+            }
+        }
+    }
+
+    #[test]
+    fn unsupported_dimensions() {
+        let (_, queue) = gfx_dev_and_queue!();
+        let streamer = TextureStreamer::new(queue, 1 << 20, 4).unwrap();
+
+        let res = TextureStreamer::create_texture(
+            &streamer,
+            ImageDimensions::Dim2d {
+                width: 256,
+                height: 256,
+                array_layers: 2,
+            },
+            Format::R8G8B8A8_UNORM,
+            9,
+            Arc::new(Zeroes),
+        );
+
+        match res {
+            Err(TextureStreamError::UnsupportedDimensions) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn upload_and_evict() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let dimensions = ImageDimensions::Dim2d {
+            width: 256,
+            height: 256,
+            array_layers: 1,
+        };
+
+        // The budget only allows the level 1 of one texture to be resident.
+        let streamer = TextureStreamer::new(queue, 128 * 128 * 4, 2).unwrap();
+        let first = TextureStreamer::create_texture(
+            &streamer,
+            dimensions,
+            Format::R8G8B8A8_UNORM,
+            9,
+            Arc::new(Zeroes),
+        )
+        .unwrap();
+        let second = TextureStreamer::create_texture(
+            &streamer,
+            dimensions,
+            Format::R8G8B8A8_UNORM,
+            9,
+            Arc::new(Zeroes),
+        )
+        .unwrap();
+
+        streamer.request(&first, 1, 1);
+        streamer
+            .flush(now(device.clone()), u64::MAX)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        assert_eq!(first.min_resident_level(), 1);
+        assert_eq!(second.min_resident_level(), 2);
+
+        streamer.request(&first, 8, 1);
+        streamer.request(&second, 1, 1);
+        streamer
+            .flush(now(device), u64::MAX)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        assert!(first.min_resident_level() > 1);
+        assert_eq!(second.min_resident_level(), 1);
+    }
+}
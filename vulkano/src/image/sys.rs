@@ -20,8 +20,10 @@ use crate::format::Format;
 use crate::format::FormatFeatures;
 use crate::format::NumericType;
 use crate::image::ImageAspect;
+use crate::image::ImageAspects;
 use crate::image::ImageCreateFlags;
 use crate::image::ImageDimensions;
+use crate::image::ImageLayout;
 use crate::image::ImageUsage;
 use crate::image::MipmapsCount;
 use crate::image::SampleCount;
@@ -71,6 +73,10 @@ pub struct UnsafeImage {
 
     // Features that are supported for this particular format.
     format_features: FormatFeatures,
+    // The DRM format modifier of the image, if it was created with one.
+    drm_format_modifier: Option<u64>,
+    // The formats that views of the image can have. Empty if no list was given.
+    view_formats: SmallVec<[Format; 4]>,
 
     // `vkDestroyImage` is called only if `needs_destruction` is true.
     needs_destruction: bool,
@@ -122,6 +128,62 @@ impl UnsafeImage {
             linear_tiling,
             preinitialized_layout,
             None,
+            None,
+            &[],
+        )
+    }
+
+    /// Same as `new`, but with the list of the formats that views of the image can have, given
+    /// to the implementation through `VkImageFormatListCreateInfo`.
+    ///
+    /// With the `mutable_format` flag, views of the image can then have any format of
+    /// `view_formats`, which must be compatible with `format`. Giving the list is required
+    /// instead of just setting the flag by some usages, and may let the implementation keep
+    /// optimizations such as framebuffer compression that it would otherwise disable. The list
+    /// is not needed to create views with the format of the image, but the format of the image
+    /// must be in the list to use it.
+    ///
+    /// Requires Vulkan 1.2 or the `khr_image_format_list` extension if `view_formats` isn't
+    /// empty.
+    #[inline]
+    pub unsafe fn new_with_view_formats<'a, Mi, I>(
+        device: Arc<Device>,
+        usage: ImageUsage,
+        format: Format,
+        flags: ImageCreateFlags,
+        dimensions: ImageDimensions,
+        num_samples: SampleCount,
+        mipmaps: Mi,
+        sharing: Sharing<I>,
+        linear_tiling: bool,
+        preinitialized_layout: bool,
+        view_formats: &[Format],
+    ) -> Result<(UnsafeImage, MemoryRequirements), ImageCreationError>
+    where
+        Mi: Into<MipmapsCount>,
+        I: IntoIterator<Item = u32>,
+    {
+        let sharing = match sharing {
+            Sharing::Exclusive => (ash::vk::SharingMode::EXCLUSIVE, SmallVec::<[u32; 8]>::new()),
+            Sharing::Concurrent(ids) => {
+                (ash::vk::SharingMode::CONCURRENT, ids.into_iter().collect())
+            }
+        };
+
+        UnsafeImage::new_impl(
+            device,
+            usage,
+            format,
+            flags,
+            dimensions,
+            num_samples,
+            mipmaps.into(),
+            sharing,
+            linear_tiling,
+            preinitialized_layout,
+            None,
+            None,
+            view_formats,
         )
     }
 
@@ -169,6 +231,108 @@ impl UnsafeImage {
             linear_tiling,
             preinitialized_layout,
             Some(crate::memory::ExternalMemoryHandleType::posix()),
+            None,
+            &[],
+        )
+    }
+
+    /// Same as `new`, but the memory of the image can be exported or imported with any of
+    /// `handle_types`, for example a Direct3D 11 texture or an opaque file descriptor shared
+    /// with OpenGL.
+    ///
+    /// The extensions that correspond to `handle_types` must be enabled on the device, and the
+    /// memory should be a dedicated allocation.
+    #[inline]
+    pub unsafe fn new_with_external_memory<'a, Mi, I>(
+        device: Arc<Device>,
+        usage: ImageUsage,
+        format: Format,
+        flags: ImageCreateFlags,
+        dimensions: ImageDimensions,
+        num_samples: SampleCount,
+        mipmaps: Mi,
+        sharing: Sharing<I>,
+        linear_tiling: bool,
+        preinitialized_layout: bool,
+        handle_types: crate::memory::ExternalMemoryHandleType,
+    ) -> Result<(UnsafeImage, MemoryRequirements), ImageCreationError>
+    where
+        Mi: Into<MipmapsCount>,
+        I: IntoIterator<Item = u32>,
+    {
+        let sharing = match sharing {
+            Sharing::Exclusive => (ash::vk::SharingMode::EXCLUSIVE, SmallVec::<[u32; 8]>::new()),
+            Sharing::Concurrent(ids) => {
+                (ash::vk::SharingMode::CONCURRENT, ids.into_iter().collect())
+            }
+        };
+
+        UnsafeImage::new_impl(
+            device,
+            usage,
+            format,
+            flags,
+            dimensions,
+            num_samples,
+            mipmaps.into(),
+            sharing,
+            linear_tiling,
+            preinitialized_layout,
+            Some(handle_types),
+            None,
+            &[],
+        )
+    }
+
+    /// Creates a new image whose memory layout is described by a DRM format modifier, for example
+    /// to share it with other APIs and processes through a dma-buf.
+    ///
+    /// The image has a single mipmap level and sample. If `external_memory` is `Some`, the memory
+    /// of the image can be exported or imported with these handle types.
+    ///
+    /// The `ext_image_drm_format_modifier` extension must be enabled on the device, and the
+    /// modifiers must be supported for `format`, as returned by
+    /// [`Format::drm_format_modifier_properties`].
+    ///
+    /// # Safety
+    ///
+    /// - With [`DrmFormatModifier::Explicit`], there must be one plane layout for each memory
+    ///   plane of the modifier, and the layouts must be valid for the image.
+    #[inline]
+    pub unsafe fn new_with_drm_format_modifier<'a, I>(
+        device: Arc<Device>,
+        usage: ImageUsage,
+        format: Format,
+        flags: ImageCreateFlags,
+        dimensions: ImageDimensions,
+        sharing: Sharing<I>,
+        drm_format_modifier: &DrmFormatModifier,
+        external_memory: Option<crate::memory::ExternalMemoryHandleType>,
+    ) -> Result<(UnsafeImage, MemoryRequirements), ImageCreationError>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        let sharing = match sharing {
+            Sharing::Exclusive => (ash::vk::SharingMode::EXCLUSIVE, SmallVec::<[u32; 8]>::new()),
+            Sharing::Concurrent(ids) => {
+                (ash::vk::SharingMode::CONCURRENT, ids.into_iter().collect())
+            }
+        };
+
+        UnsafeImage::new_impl(
+            device,
+            usage,
+            format,
+            flags,
+            dimensions,
+            SampleCount::Sample1,
+            MipmapsCount::One,
+            sharing,
+            false,
+            false,
+            external_memory,
+            Some(drm_format_modifier),
+            &[],
         )
     }
 
@@ -185,26 +349,22 @@ impl UnsafeImage {
         linear_tiling: bool,
         preinitialized_layout: bool,
         external_mem_handle_type: Option<crate::memory::ExternalMemoryHandleType>,
+        drm_format_modifier: Option<&DrmFormatModifier>,
+        view_formats: &[Format],
     ) -> Result<(UnsafeImage, MemoryRequirements), ImageCreationError> {
-        // TODO: doesn't check that the proper features are enabled
-
-        if flags.sparse_binding || flags.sparse_residency || flags.sparse_aliased {
-            unimplemented!();
-        }
-
         let fns = device.fns();
         let fns_i = device.instance().fns();
 
-        // Checking if image usage conforms to what is supported.
-        let format_features = {
-            let format_properties = format.properties(device.physical_device());
+        if drm_format_modifier.is_some() {
+            if !device.enabled_extensions().ext_image_drm_format_modifier {
+                return Err(ImageCreationError::ImageDrmFormatModifierExtensionNotEnabled);
+            }
 
-            let features = if linear_tiling {
-                format_properties.linear_tiling_features
-            } else {
-                format_properties.optimal_tiling_features
-            };
+            debug_assert!(!linear_tiling);
+        }
 
+        // Checking if image usage conforms to what is supported.
+        let check_features = |features: FormatFeatures| -> Result<(), ImageCreationError> {
             if features == FormatFeatures::default() {
                 return Err(ImageCreationError::FormatNotSupported);
             }
@@ -226,6 +386,15 @@ impl UnsafeImage {
             {
                 return Err(ImageCreationError::UnsupportedUsage);
             }
+            if usage.fragment_shading_rate_attachment {
+                if !device.enabled_features().attachment_fragment_shading_rate {
+                    return Err(ImageCreationError::AttachmentFragmentShadingRateFeatureNotEnabled);
+                }
+
+                if !features.khr_fragment_shading_rate_attachment {
+                    return Err(ImageCreationError::UnsupportedUsage);
+                }
+            }
             if device.api_version() >= Version::V1_1 || device.enabled_extensions().khr_maintenance1
             {
                 if usage.transfer_source && !features.transfer_src {
@@ -236,7 +405,51 @@ impl UnsafeImage {
                 }
             }
 
-            features
+            Ok(())
+        };
+
+        // The features of the DRM format modifiers that the format supports.
+        let drm_format_modifier_features = |modifier: u64| {
+            format
+                .drm_format_modifier_properties(device.physical_device())
+                .into_iter()
+                .find(|properties| properties.drm_format_modifier == modifier)
+                .map(|properties| properties.drm_format_modifier_tiling_features)
+                .ok_or(ImageCreationError::FormatNotSupported)
+        };
+
+        let mut format_features = match drm_format_modifier {
+            None => {
+                let format_properties = format.properties(device.physical_device());
+
+                let features = if linear_tiling {
+                    format_properties.linear_tiling_features
+                } else {
+                    format_properties.optimal_tiling_features
+                };
+                check_features(features)?;
+
+                features
+            }
+            Some(DrmFormatModifier::Explicit { modifier, .. }) => {
+                let features = drm_format_modifier_features(*modifier)?;
+                check_features(features)?;
+
+                features
+            }
+            Some(DrmFormatModifier::List(modifiers)) => {
+                // The implementation can choose any of the modifiers, so they must all support
+                // the usage. The features of the chosen one are queried after the creation.
+                if modifiers.is_empty() {
+                    return Err(ImageCreationError::FormatNotSupported);
+                }
+
+                for &modifier in modifiers.iter() {
+                    check_features(drm_format_modifier_features(modifier)?)?;
+                }
+
+                FormatFeatures::default()
+            }
         };
 
         //  VUID-VkImageCreateInfo-usage-requiredbitmask: usage must not be 0
@@ -463,6 +676,13 @@ impl UnsafeImage {
             if !(ty == ash::vk::ImageType::TYPE_3D) {
                 return Err(ImageCreationError::CreationFlagRequirementsNotMet);
             }
+
+            // The flag was introduced by `VK_KHR_maintenance1`.
+            if !(device.api_version() >= Version::V1_1
+                || device.enabled_extensions().khr_maintenance1)
+            {
+                return Err(ImageCreationError::CreationFlagRequirementsNotMet);
+            }
         }
 
         if flags.block_texel_view_compatible {
@@ -488,6 +708,90 @@ impl UnsafeImage {
             }
         }
 
+        if !view_formats.is_empty() {
+            if !(device.api_version() >= Version::V1_2
+                || device.enabled_extensions().khr_image_format_list)
+            {
+                return Err(ImageCreationError::ImageFormatListExtensionNotEnabled);
+            }
+
+            // VUID-VkImageCreateInfo-flags-04738
+            if !flags.mutable_format && !(view_formats.len() == 1 && view_formats[0] == format) {
+                return Err(ImageCreationError::CreationFlagRequirementsNotMet);
+            }
+
+            // VUID-VkImageCreateInfo-pNext-06722
+            for &view_format in view_formats {
+                let block_texel_compatible = flags.block_texel_view_compatible
+                    && view_format.compression().is_none()
+                    && view_format.size() == format.size();
+
+                if view_format.compatibility() != format.compatibility() && !block_texel_compatible
+                {
+                    return Err(ImageCreationError::IncompatibleViewFormat {
+                        format: view_format,
+                    });
+                }
+            }
+        }
+
+        if flags.disjoint {
+            if !(device.api_version() >= Version::V1_1
+                || device.enabled_extensions().khr_sampler_ycbcr_conversion)
+            {
+                return Err(ImageCreationError::CreationFlagRequirementsNotMet);
+            }
+
+            // VUID-VkImageCreateInfo-format-01577 and
+            // VUID-VkImageCreateInfo-imageCreateFormatFeatures-02260
+            if format.planes().is_empty() || !format_features.disjoint {
+                return Err(ImageCreationError::CreationFlagRequirementsNotMet);
+            }
+        }
+
+        // Checking sparse features.
+        if flags.sparse_binding || flags.sparse_residency || flags.sparse_aliased {
+            // VUID-VkImageCreateInfo-flags-00987 and VUID-VkImageCreateInfo-flags-00988
+            if !flags.sparse_binding {
+                return Err(ImageCreationError::CreationFlagRequirementsNotMet);
+            }
+
+            if !device.enabled_features().sparse_binding {
+                return Err(ImageCreationError::SparseBindingFeatureNotEnabled);
+            }
+
+            if flags.sparse_residency {
+                // VUID-VkImageCreateInfo-imageType-00970 and VUID-VkImageCreateInfo-tiling-04121
+                if ty == ash::vk::ImageType::TYPE_1D || linear_tiling {
+                    return Err(ImageCreationError::CreationFlagRequirementsNotMet);
+                }
+
+                let features = device.enabled_features();
+                let supported = match ty {
+                    ash::vk::ImageType::TYPE_2D => {
+                        features.sparse_residency_image2_d
+                            && match num_samples {
+                                SampleCount::Sample1 => true,
+                                SampleCount::Sample2 => features.sparse_residency2_samples,
+                                SampleCount::Sample4 => features.sparse_residency4_samples,
+                                SampleCount::Sample8 => features.sparse_residency8_samples,
+                                SampleCount::Sample16 => features.sparse_residency16_samples,
+                                SampleCount::Sample32 | SampleCount::Sample64 => false,
+                            }
+                    }
+                    _ => features.sparse_residency_image3_d,
+                };
+
+                if !supported {
+                    return Err(ImageCreationError::SparseResidencyFeatureNotEnabled);
+                }
+            }
+
+            if flags.sparse_aliased && !device.enabled_features().sparse_residency_aliased {
+                return Err(ImageCreationError::SparseResidencyAliasedFeatureNotEnabled);
+            }
+        }
+
         // Checking the dimensions against the limits.
         if array_layers > device.physical_device().properties().max_image_array_layers {
             let err = ImageCreationError::UnsupportedDimensions { dimensions };
@@ -534,6 +838,12 @@ impl UnsafeImage {
         // Now that all checks have been performed, if any of the check failed we query the Vulkan
         // implementation for additional image capabilities.
         if let Some(capabilities_error) = capabilities_error {
+            // TODO: query the capabilities of images with a DRM format modifier, which requires
+            // `vkGetPhysicalDeviceImageFormatProperties2`.
+            if drm_format_modifier.is_some() {
+                return Err(capabilities_error);
+            }
+
             let tiling = if linear_tiling {
                 ash::vk::ImageTiling::LINEAR
             } else {
@@ -548,7 +858,7 @@ impl UnsafeImage {
                 ty,
                 tiling,
                 usage_bits,
-                ash::vk::ImageCreateFlags::empty(), /* TODO */
+                flags.into(),
                 output.as_mut_ptr(),
             );
 
@@ -583,7 +893,9 @@ impl UnsafeImage {
                 mip_levels: mipmaps,
                 array_layers: array_layers,
                 samples: num_samples.into(),
-                tiling: if linear_tiling {
+                tiling: if drm_format_modifier.is_some() {
+                    ash::vk::ImageTiling::DRM_FORMAT_MODIFIER_EXT
+                } else if linear_tiling {
                     ash::vk::ImageTiling::LINEAR
                 } else {
                     ash::vk::ImageTiling::OPTIMAL
@@ -600,11 +912,79 @@ impl UnsafeImage {
                 ..Default::default()
             };
 
-            if let Some(mem_types) = external_mem_handle_type {
-                infos.p_next = std::mem::transmute(&ash::vk::ExternalMemoryImageCreateInfo {
-                    handle_types: mem_types.into(),
+            let mut external_memory_info = external_mem_handle_type.map(|handle_types| {
+                ash::vk::ExternalMemoryImageCreateInfo {
+                    handle_types: handle_types.into(),
+                    ..Default::default()
+                }
+            });
+            let plane_layouts: SmallVec<[ash::vk::SubresourceLayout; 4]>;
+            let mut drm_list_info = None;
+            let mut drm_explicit_info = None;
+
+            match drm_format_modifier {
+                None => (),
+                Some(DrmFormatModifier::List(modifiers)) => {
+                    drm_list_info = Some(ash::vk::ImageDrmFormatModifierListCreateInfoEXT {
+                        drm_format_modifier_count: modifiers.len() as u32,
+                        p_drm_format_modifiers: modifiers.as_ptr(),
+                        ..Default::default()
+                    });
+                }
+                Some(DrmFormatModifier::Explicit {
+                    modifier,
+                    plane_layouts: layouts,
+                }) => {
+                    plane_layouts = layouts
+                        .iter()
+                        .map(|layout| ash::vk::SubresourceLayout {
+                            offset: layout.offset,
+                            size: 0,
+                            row_pitch: layout.row_pitch,
+                            array_pitch: layout.array_pitch,
+                            depth_pitch: layout.depth_pitch,
+                        })
+                        .collect();
+                    drm_explicit_info =
+                        Some(ash::vk::ImageDrmFormatModifierExplicitCreateInfoEXT {
+                            drm_format_modifier: *modifier,
+                            drm_format_modifier_plane_count: plane_layouts.len() as u32,
+                            p_plane_layouts: plane_layouts.as_ptr(),
+                            ..Default::default()
+                        });
+                }
+            }
+
+            if let Some(info) = external_memory_info.as_mut() {
+                info.p_next = infos.p_next;
+                infos.p_next = info as *const _ as *const _;
+            }
+
+            if let Some(info) = drm_list_info.as_mut() {
+                info.p_next = infos.p_next;
+                infos.p_next = info as *const _ as *const _;
+            }
+
+            if let Some(info) = drm_explicit_info.as_mut() {
+                info.p_next = infos.p_next;
+                infos.p_next = info as *const _ as *const _;
+            }
+
+            let vk_view_formats: SmallVec<[ash::vk::Format; 4]> =
+                view_formats.iter().map(|&format| format.into()).collect();
+            let mut format_list_info = if vk_view_formats.is_empty() {
+                None
+            } else {
+                Some(ash::vk::ImageFormatListCreateInfo {
+                    view_format_count: vk_view_formats.len() as u32,
+                    p_view_formats: vk_view_formats.as_ptr(),
                     ..Default::default()
-                });
+                })
+            };
+
+            if let Some(info) = format_list_info.as_mut() {
+                info.p_next = infos.p_next;
+                infos.p_next = info as *const _ as *const _;
             }
 
             let mut output = MaybeUninit::uninit();
@@ -620,7 +1000,18 @@ impl UnsafeImage {
         let mem_reqs = if device.api_version() >= Version::V1_1
             || device.enabled_extensions().khr_get_memory_requirements2
         {
+            // The requirements of a disjoint image are those of each plane. Those of the first
+            // plane are returned.
+            let plane_info = ash::vk::ImagePlaneMemoryRequirementsInfo {
+                plane_aspect: ash::vk::ImageAspectFlags::PLANE_0,
+                ..Default::default()
+            };
             let infos = ash::vk::ImageMemoryRequirementsInfo2 {
+                p_next: if flags.disjoint {
+                    &plane_info as *const _ as *const _
+                } else {
+                    ptr::null()
+                },
                 image,
                 ..Default::default()
             };
@@ -675,6 +1066,25 @@ impl UnsafeImage {
             MemoryRequirements::from(output)
         };
 
+        let drm_format_modifier = match drm_format_modifier {
+            None => None,
+            Some(&DrmFormatModifier::Explicit { modifier, .. }) => Some(modifier),
+            Some(DrmFormatModifier::List(_)) => {
+                let mut properties = ash::vk::ImageDrmFormatModifierPropertiesEXT::default();
+                check_errors(
+                    fns.ext_image_drm_format_modifier
+                        .get_image_drm_format_modifier_properties_ext(
+                            device.internal_object(),
+                            image,
+                            &mut properties,
+                        ),
+                )?;
+                format_features = drm_format_modifier_features(properties.drm_format_modifier)?;
+
+                Some(properties.drm_format_modifier)
+            }
+        };
+
         let image = UnsafeImage {
             device: device.clone(),
             image,
@@ -685,6 +1095,8 @@ impl UnsafeImage {
             samples: num_samples,
             mipmaps,
             format_features,
+            drm_format_modifier,
+            view_formats: view_formats.iter().cloned().collect(),
             needs_destruction: true,
             preinitialized_layout,
         };
@@ -704,6 +1116,32 @@ impl UnsafeImage {
         dimensions: ImageDimensions,
         samples: SampleCount,
         mipmaps: u32,
+    ) -> UnsafeImage {
+        UnsafeImage::from_raw_with_view_formats(
+            device,
+            handle,
+            usage,
+            format,
+            flags,
+            dimensions,
+            samples,
+            mipmaps,
+            &[],
+        )
+    }
+
+    // Same as `from_raw`, for images such as those of a swapchain whose list of view formats is
+    // known.
+    pub(crate) unsafe fn from_raw_with_view_formats(
+        device: Arc<Device>,
+        handle: ash::vk::Image,
+        usage: ImageUsage,
+        format: Format,
+        flags: ImageCreateFlags,
+        dimensions: ImageDimensions,
+        samples: SampleCount,
+        mipmaps: u32,
+        view_formats: &[Format],
     ) -> UnsafeImage {
         let format_properties = format.properties(device.physical_device());
 
@@ -719,6 +1157,8 @@ impl UnsafeImage {
             samples,
             mipmaps,
             format_features: format_properties.optimal_tiling_features,
+            drm_format_modifier: None,
+            view_formats: view_formats.iter().cloned().collect(),
             needs_destruction: false,     // TODO: pass as parameter
             preinitialized_layout: false, // TODO: Maybe this should be passed in?
         }
@@ -731,6 +1171,9 @@ impl UnsafeImage {
     ) -> Result<(), OomError> {
         let fns = self.device.fns();
 
+        // The planes of a disjoint image are bound separately.
+        debug_assert!(!self.flags.disjoint);
+
         // We check for correctness in debug mode.
         debug_assert!({
             let mut mem_reqs = MaybeUninit::uninit();
@@ -755,6 +1198,97 @@ impl UnsafeImage {
         Ok(())
     }
 
+    /// Returns the memory requirements of a plane of a disjoint image.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the image wasn't created with the `disjoint` flag.
+    /// - Panics if `plane` isn't a plane of the format of the image.
+    pub fn plane_memory_requirements(&self, plane: usize) -> MemoryRequirements {
+        assert!(self.flags.disjoint);
+        let fns = self.device.fns();
+
+        let plane_info = ash::vk::ImagePlaneMemoryRequirementsInfo {
+            plane_aspect: plane_aspect(self.format, plane),
+            ..Default::default()
+        };
+        let infos = ash::vk::ImageMemoryRequirementsInfo2 {
+            p_next: &plane_info as *const _ as *const _,
+            image: self.image,
+            ..Default::default()
+        };
+        let mut output = ash::vk::MemoryRequirements2::default();
+
+        unsafe {
+            if self.device.api_version() >= Version::V1_1 {
+                fns.v1_1.get_image_memory_requirements2(
+                    self.device.internal_object(),
+                    &infos,
+                    &mut output,
+                );
+            } else {
+                fns.khr_get_memory_requirements2
+                    .get_image_memory_requirements2_khr(
+                        self.device.internal_object(),
+                        &infos,
+                        &mut output,
+                    );
+            }
+        }
+
+        debug_assert!(output.memory_requirements.memory_type_bits != 0);
+        MemoryRequirements::from(output.memory_requirements)
+    }
+
+    /// Binds memory to a plane of a disjoint image.
+    ///
+    /// # Safety
+    ///
+    /// - Each plane must be bound exactly once before the image is used.
+    /// - `memory` and `offset` must match the requirements returned by
+    ///   [`plane_memory_requirements`](UnsafeImage::plane_memory_requirements) for `plane`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the image wasn't created with the `disjoint` flag.
+    /// - Panics if `plane` isn't a plane of the format of the image.
+    pub unsafe fn bind_plane_memory(
+        &self,
+        plane: usize,
+        memory: &DeviceMemory,
+        offset: DeviceSize,
+    ) -> Result<(), OomError> {
+        assert!(self.flags.disjoint);
+        let fns = self.device.fns();
+
+        let plane_info = ash::vk::BindImagePlaneMemoryInfo {
+            plane_aspect: plane_aspect(self.format, plane),
+            ..Default::default()
+        };
+        let infos = ash::vk::BindImageMemoryInfo {
+            p_next: &plane_info as *const _ as *const _,
+            image: self.image,
+            memory: memory.internal_object(),
+            memory_offset: offset,
+            ..Default::default()
+        };
+
+        if self.device.api_version() >= Version::V1_1 {
+            check_errors(
+                fns.v1_1
+                    .bind_image_memory2(self.device.internal_object(), 1, &infos),
+            )?;
+        } else {
+            check_errors(fns.khr_bind_memory2.bind_image_memory2_khr(
+                self.device.internal_object(),
+                1,
+                &infos,
+            ))?;
+        }
+
+        Ok(())
+    }
+
     #[inline]
     pub fn device(&self) -> &Arc<Device> {
         &self.device
@@ -784,6 +1318,43 @@ impl UnsafeImage {
         self.samples
     }
 
+    /// Returns the memory requirements of the sparse blocks of the image, one element per group
+    /// of aspects that are bound together.
+    ///
+    /// The image must have been created with the `sparse_residency` flag, otherwise the list is
+    /// empty.
+    pub fn sparse_memory_requirements(&self) -> Vec<SparseImageMemoryRequirements> {
+        if !self.flags.sparse_residency {
+            return Vec::new();
+        }
+
+        unsafe {
+            let fns = self.device.fns();
+
+            let mut count = 0;
+            fns.v1_0.get_image_sparse_memory_requirements(
+                self.device.internal_object(),
+                self.image,
+                &mut count,
+                ptr::null_mut(),
+            );
+
+            let mut requirements = Vec::with_capacity(count as usize);
+            fns.v1_0.get_image_sparse_memory_requirements(
+                self.device.internal_object(),
+                self.image,
+                &mut count,
+                requirements.as_mut_ptr(),
+            );
+            requirements.set_len(count as usize);
+
+            requirements
+                .into_iter()
+                .map(SparseImageMemoryRequirements::from)
+                .collect()
+        }
+    }
+
     /// Returns a key unique to each `UnsafeImage`. Can be used for the `conflicts_key` method.
     #[inline]
     pub fn key(&self) -> u64 {
@@ -906,6 +1477,47 @@ impl UnsafeImage {
         }
     }
 
+    /// Returns the formats that views of the image can have, if they were given at creation with
+    /// [`new_with_view_formats`](UnsafeImage::new_with_view_formats). An empty list means that
+    /// views can have any format that is compatible with the format of the image, if the image
+    /// has the `mutable_format` flag.
+    #[inline]
+    pub fn view_formats(&self) -> &[Format] {
+        &self.view_formats
+    }
+
+    /// Returns the DRM format modifier of the image, if it was created with
+    /// [`new_with_drm_format_modifier`](UnsafeImage::new_with_drm_format_modifier).
+    ///
+    /// If a list of modifiers was given, this is the one that the implementation chose.
+    #[inline]
+    pub fn drm_format_modifier(&self) -> Option<u64> {
+        self.drm_format_modifier
+    }
+
+    /// Returns the layout of a memory plane of an image with a DRM format modifier.
+    ///
+    /// The number of memory planes is the `drm_format_modifier_plane_count` of the modifier. The
+    /// layouts are needed to import the memory of the image elsewhere, together with the
+    /// modifier.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the image wasn't created with a DRM format modifier.
+    /// - Panics if `plane` is greater than 2.
+    #[inline]
+    pub unsafe fn drm_memory_plane_layout(&self, plane: usize) -> LinearLayout {
+        assert!(self.drm_format_modifier.is_some());
+
+        let aspect = match plane {
+            0 => ImageAspect::MemoryPlane0,
+            1 => ImageAspect::MemoryPlane1,
+            2 => ImageAspect::MemoryPlane2,
+            _ => panic!(),
+        };
+        self.linear_layout_impl(0, aspect)
+    }
+
     /// Returns the flags the image was created with.
     #[inline]
     pub fn flags(&self) -> ImageCreateFlags {
@@ -978,6 +1590,96 @@ impl Hash for UnsafeImage {
     }
 }
 
+/// Describes the sparse blocks of a group of aspects of a sparse image.
+///
+/// Memory is bound to the image in blocks of `image_granularity` texels, with
+/// [`SubmitBindSparseImageBindBuilder`](crate::command_buffer::submit::SubmitBindSparseImageBindBuilder).
+/// The mip levels starting at `image_mip_tail_first_lod` are too small to be split in blocks,
+/// and form the mip tail, which is bound as a range of bytes with
+/// [`SubmitBindSparseImageOpaqueBindBuilder`](crate::command_buffer::submit::SubmitBindSparseImageOpaqueBindBuilder).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SparseImageMemoryRequirements {
+    /// The aspects that these requirements apply to.
+    pub aspects: ImageAspects,
+    /// The width, height and depth in texels of a sparse block.
+    pub image_granularity: [u32; 3],
+    /// If true, there is a single mip tail shared by all the array layers.
+    pub single_miptail: bool,
+    /// If true, the first mip level whose dimensions are not a multiple of the block size is the
+    /// start of the mip tail.
+    pub aligned_mip_size: bool,
+    /// If true, the block size doesn't match the standard sparse block shapes.
+    pub nonstandard_block_size: bool,
+    /// The first mip level of the mip tail.
+    pub image_mip_tail_first_lod: u32,
+    /// The size in bytes of the mip tail of an array layer.
+    pub image_mip_tail_size: DeviceSize,
+    /// The offset of the mip tail of the first array layer, to use when binding it.
+    pub image_mip_tail_offset: DeviceSize,
+    /// The offset between the mip tails of two array layers.
+    pub image_mip_tail_stride: DeviceSize,
+}
+
+impl From<ash::vk::SparseImageMemoryRequirements> for SparseImageMemoryRequirements {
+    #[inline]
+    fn from(val: ash::vk::SparseImageMemoryRequirements) -> Self {
+        let properties = val.format_properties;
+
+        SparseImageMemoryRequirements {
+            aspects: properties.aspect_mask.into(),
+            image_granularity: [
+                properties.image_granularity.width,
+                properties.image_granularity.height,
+                properties.image_granularity.depth,
+            ],
+            single_miptail: properties
+                .flags
+                .contains(ash::vk::SparseImageFormatFlags::SINGLE_MIPTAIL),
+            aligned_mip_size: properties
+                .flags
+                .contains(ash::vk::SparseImageFormatFlags::ALIGNED_MIP_SIZE),
+            nonstandard_block_size: properties
+                .flags
+                .contains(ash::vk::SparseImageFormatFlags::NONSTANDARD_BLOCK_SIZE),
+            image_mip_tail_first_lod: val.image_mip_tail_first_lod,
+            image_mip_tail_size: val.image_mip_tail_size,
+            image_mip_tail_offset: val.image_mip_tail_offset,
+            image_mip_tail_stride: val.image_mip_tail_stride,
+        }
+    }
+}
+
+/// The DRM format modifier of an image created with
+/// [`UnsafeImage::new_with_drm_format_modifier`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DrmFormatModifier {
+    /// The implementation chooses one of the modifiers. This is used to create an image whose
+    /// memory is then exported.
+    List(SmallVec<[u64; 4]>),
+    /// The image has this modifier, and the given layout for each of its memory planes. This is
+    /// used to create an image whose memory is imported.
+    ///
+    /// The `size` of each layout is ignored, and the `array_pitch` and `depth_pitch` must be 0
+    /// for images with a single array layer and depth.
+    Explicit {
+        modifier: u64,
+        plane_layouts: SmallVec<[LinearLayout; 4]>,
+    },
+}
+
+// Returns the aspect of a plane of a multi-planar format.
+#[inline]
+fn plane_aspect(format: Format, plane: usize) -> ash::vk::ImageAspectFlags {
+    assert!(plane < format.planes().len());
+
+    match plane {
+        0 => ImageAspect::Plane0,
+        1 => ImageAspect::Plane1,
+        _ => ImageAspect::Plane2,
+    }
+    .into()
+}
+
 /// Error that can happen when creating an instance.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ImageCreationError {
@@ -1000,6 +1702,26 @@ pub enum ImageCreationError {
     UnsupportedUsage,
     /// The `shader_storage_image_multisample` feature must be enabled to create such an image.
     ShaderStorageImageMultisampleFeatureNotEnabled,
+    /// The `attachment_fragment_shading_rate` feature must be enabled to create an image with
+    /// the `fragment_shading_rate_attachment` usage.
+    AttachmentFragmentShadingRateFeatureNotEnabled,
+    /// Sparse binding was requested but the corresponding feature wasn't enabled.
+    SparseBindingFeatureNotEnabled,
+    /// Sparse residency was requested but the feature for this type of image or number of
+    /// samples wasn't enabled.
+    SparseResidencyFeatureNotEnabled,
+    /// Sparse aliasing was requested but the corresponding feature wasn't enabled.
+    SparseResidencyAliasedFeatureNotEnabled,
+    /// A DRM format modifier was requested but the `ext_image_drm_format_modifier` extension
+    /// wasn't enabled.
+    ImageDrmFormatModifierExtensionNotEnabled,
+    /// A list of view formats was given but neither Vulkan 1.2 nor the `khr_image_format_list`
+    /// extension is enabled.
+    ImageFormatListExtensionNotEnabled,
+    /// A format of the list of view formats is not compatible with the format of the image.
+    IncompatibleViewFormat { format: Format },
+    /// The layout that an external image is declared to be in is not allowed by its usage.
+    UnsupportedLayout { layout: ImageLayout },
 }
 
 impl error::Error for ImageCreationError {
@@ -1043,6 +1765,35 @@ impl fmt::Display for ImageCreationError {
                     "the `shader_storage_image_multisample` feature must be enabled to create such \
                  an image"
                 }
+                ImageCreationError::AttachmentFragmentShadingRateFeatureNotEnabled => {
+                    "the `attachment_fragment_shading_rate` feature must be enabled to create an \
+                 image with the `fragment_shading_rate_attachment` usage"
+                }
+                ImageCreationError::SparseBindingFeatureNotEnabled => {
+                    "sparse binding was requested but the corresponding feature wasn't enabled"
+                }
+                ImageCreationError::SparseResidencyFeatureNotEnabled => {
+                    "sparse residency was requested but the feature for this type of image or \
+                     number of samples wasn't enabled"
+                }
+                ImageCreationError::SparseResidencyAliasedFeatureNotEnabled => {
+                    "sparse aliasing was requested but the corresponding feature wasn't enabled"
+                }
+                ImageCreationError::ImageDrmFormatModifierExtensionNotEnabled => {
+                    "a DRM format modifier was requested but the corresponding extension wasn't \
+                     enabled"
+                }
+                ImageCreationError::ImageFormatListExtensionNotEnabled => {
+                    "a list of view formats was given but the corresponding extension wasn't \
+                     enabled"
+                }
+                ImageCreationError::IncompatibleViewFormat { .. } => {
+                    "a format of the list of view formats is not compatible with the format of \
+                     the image"
+                }
+                ImageCreationError::UnsupportedLayout { .. } => {
+                    "the layout of the image is not allowed by its usage"
+                }
             }
         )
     }
@@ -1099,6 +1850,7 @@ pub struct LinearLayout {
 
 #[cfg(test)]
 mod tests {
+    use super::DrmFormatModifier;
     use super::ImageCreateFlags;
     use super::ImageCreationError;
     use super::ImageUsage;
@@ -1387,4 +2139,109 @@ mod tests {
             _ => panic!(),
         };
     }
+
+    #[test]
+    fn disjoint_not_multi_planar() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = ImageUsage {
+            sampled: true,
+            ..ImageUsage::none()
+        };
+
+        let res = unsafe {
+            UnsafeImage::new(
+                device,
+                usage,
+                Format::R8G8B8A8_UNORM,
+                ImageCreateFlags {
+                    disjoint: true,
+                    ..ImageCreateFlags::none()
+                },
+                ImageDimensions::Dim2d {
+                    width: 32,
+                    height: 32,
+                    array_layers: 1,
+                },
+                SampleCount::Sample1,
+                1,
+                Sharing::Exclusive::<Empty<_>>,
+                false,
+                false,
+            )
+        };
+
+        match res {
+            Err(ImageCreationError::CreationFlagRequirementsNotMet) => (),
+            _ => panic!(),
+        };
+    }
+
+    #[test]
+    fn drm_format_modifier_extension_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = ImageUsage {
+            sampled: true,
+            ..ImageUsage::none()
+        };
+
+        let res = unsafe {
+            UnsafeImage::new_with_drm_format_modifier(
+                device,
+                usage,
+                Format::R8G8B8A8_UNORM,
+                ImageCreateFlags::none(),
+                ImageDimensions::Dim2d {
+                    width: 32,
+                    height: 32,
+                    array_layers: 1,
+                },
+                Sharing::Exclusive::<Empty<_>>,
+                &DrmFormatModifier::List(smallvec![0]),
+                None,
+            )
+        };
+
+        match res {
+            Err(ImageCreationError::ImageDrmFormatModifierExtensionNotEnabled) => (),
+            _ => panic!(),
+        };
+    }
+
+    #[test]
+    fn view_formats_without_mutable_format() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = ImageUsage {
+            sampled: true,
+            ..ImageUsage::none()
+        };
+
+        let res = unsafe {
+            UnsafeImage::new_with_view_formats(
+                device,
+                usage,
+                Format::R8G8B8A8_SRGB,
+                ImageCreateFlags::none(),
+                ImageDimensions::Dim2d {
+                    width: 32,
+                    height: 32,
+                    array_layers: 1,
+                },
+                SampleCount::Sample1,
+                1,
+                Sharing::Exclusive::<Empty<_>>,
+                false,
+                false,
+                &[Format::R8G8B8A8_SRGB, Format::R8G8B8A8_UNORM],
+            )
+        };
+
+        match res {
+            Err(ImageCreationError::CreationFlagRequirementsNotMet) => (),
+            Err(ImageCreationError::ImageFormatListExtensionNotEnabled) => (),
+            _ => panic!(),
+        };
+    }
 }
@@ -121,6 +121,48 @@ pub unsafe trait ImageAccess: Send + Sync {
         })
     }
 
+    /// Wraps around this `ImageAccess` and returns an identical `ImageAccess` whose initial
+    /// layout requirement is `initial_layout` and whose final layout requirement is
+    /// `final_layout`.
+    ///
+    /// This is for the cases where vulkano can't know the layout of the image, for example after
+    /// importing it from another API or after transitioning it with manual barriers. A command
+    /// buffer that uses the returned image supposes that the image is in `initial_layout` when
+    /// it starts, instead of transitioning it from `Undefined` the first time, and transitions
+    /// it to `final_layout` at its end, for example to hand it back to another API.
+    ///
+    /// The locking of the wrapped image doesn't check its layout anymore, and only transitions
+    /// to the final layout requirement of the wrapped image are reported to it.
+    ///
+    /// # Safety
+    ///
+    /// - The image must be in `initial_layout` whenever a command buffer that uses the returned
+    ///   image starts executing.
+    /// - Whatever uses the image after such a command buffer must expect it to be in
+    ///   `final_layout`. In particular, if `final_layout` isn't the final layout requirement of
+    ///   the wrapped image, the wrapped image must not be used again before it is transitioned
+    ///   back to that layout.
+    /// - `final_layout` must not be `Undefined` or `Preinitialized`.
+    #[inline]
+    unsafe fn with_layouts(
+        self,
+        initial_layout: ImageLayout,
+        final_layout: ImageLayout,
+    ) -> Arc<ImageAccessWithLayouts<Self>>
+    where
+        Self: Sized,
+    {
+        debug_assert!(
+            final_layout != ImageLayout::Undefined && final_layout != ImageLayout::Preinitialized
+        );
+
+        Arc::new(ImageAccessWithLayouts {
+            image: self,
+            initial_layout,
+            final_layout,
+        })
+    }
+
     /// Returns an [`ImageDescriptorLayouts`] structure specifying the image layout to use
     /// in descriptors of various kinds.
     ///
@@ -329,6 +371,118 @@ where
     }
 }
 
+/// Wraps around an object that implements `ImageAccess` and overrides its initial and final
+/// layout requirements. See [`ImageAccess::with_layouts`].
+#[derive(Debug, Copy, Clone)]
+pub struct ImageAccessWithLayouts<I> {
+    image: I,
+    initial_layout: ImageLayout,
+    final_layout: ImageLayout,
+}
+
+impl<I> ImageAccessWithLayouts<I> {
+    /// Returns the wrapped image.
+    #[inline]
+    pub fn image(&self) -> &I {
+        &self.image
+    }
+}
+
+unsafe impl<I> ImageAccess for ImageAccessWithLayouts<I>
+where
+    I: ImageAccess,
+{
+    #[inline]
+    fn inner(&self) -> ImageInner {
+        self.image.inner()
+    }
+
+    // The layout was declared by the user, so it must not be transitioned from `Undefined`.
+    #[inline]
+    fn is_layout_initialized(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    unsafe fn layout_initialized(&self) {
+        self.image.layout_initialized()
+    }
+
+    #[inline]
+    fn initial_layout_requirement(&self) -> ImageLayout {
+        self.initial_layout
+    }
+
+    #[inline]
+    fn final_layout_requirement(&self) -> ImageLayout {
+        self.final_layout
+    }
+
+    #[inline]
+    fn descriptor_layouts(&self) -> Option<ImageDescriptorLayouts> {
+        self.image.descriptor_layouts()
+    }
+
+    #[inline]
+    fn conflict_key(&self) -> u64 {
+        self.image.conflict_key()
+    }
+
+    #[inline]
+    fn try_gpu_lock(
+        &self,
+        exclusive_access: bool,
+        uninitialized_safe: bool,
+        _expected_layout: ImageLayout,
+    ) -> Result<(), AccessError> {
+        // The layout is guaranteed by the caller of `with_layouts`.
+        self.image
+            .try_gpu_lock(exclusive_access, uninitialized_safe, ImageLayout::Undefined)
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self) {
+        self.image.increase_gpu_lock()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self, new_layout: Option<ImageLayout>) {
+        let final_layout = self.image.final_layout_requirement();
+        self.image
+            .unlock(new_layout.filter(|&layout| layout == final_layout))
+    }
+
+    fn current_miplevels_access(&self) -> std::ops::Range<u32> {
+        self.image.current_miplevels_access()
+    }
+
+    fn current_layer_levels_access(&self) -> std::ops::Range<u32> {
+        self.image.current_layer_levels_access()
+    }
+}
+
+impl<I> PartialEq for ImageAccessWithLayouts<I>
+where
+    I: ImageAccess,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.inner() == other.inner()
+    }
+}
+
+impl<I> Eq for ImageAccessWithLayouts<I> where I: ImageAccess {}
+
+impl<I> Hash for ImageAccessWithLayouts<I>
+where
+    I: ImageAccess,
+{
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner().hash(state);
+    }
+}
+
 /// Extension trait for images. Checks whether the value `T` can be used as a clear value for the
 /// given image.
 // TODO: isn't that for image views instead?
@@ -0,0 +1,350 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::format::Format;
+use crate::image::attachment::AttachmentImage;
+use crate::image::sys::ImageCreationError;
+use crate::image::traits::ImageAccess;
+use crate::image::ImageCreateFlags;
+use crate::image::ImageDescriptorLayouts;
+use crate::image::ImageDimensions;
+use crate::image::ImageInner;
+use crate::image::ImageLayout;
+use crate::image::ImageUsage;
+use crate::image::SampleCount;
+use crate::memory::pool::MemoryPool;
+use crate::memory::pool::MemoryPoolAlloc;
+use crate::memory::pool::PotentialDedicatedAllocation;
+use crate::memory::pool::StdMemoryPoolAlloc;
+use crate::sync::AccessError;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::Arc;
+
+/// Attachment image whose content only lives during a render pass.
+///
+/// The image is created with the `transient_attachment` usage, and its memory is allocated from
+/// a lazily-allocated memory type if the device has one. On tile-based GPUs, which are most mobile
+/// GPUs, the content of such an image can then stay in tile memory for the whole render pass and
+/// no memory has to be committed for it. This is ideal for intermediate attachments such as the
+/// G-buffer of a deferred renderer or a depth buffer, which are written and read by the subpasses
+/// of a single render pass. On devices without lazily-allocated memory, the memory is allocated
+/// from device-local memory instead.
+///
+/// The content of the image is undefined outside of render passes, so the image can't be used
+/// for transfers or be sampled. Besides the attachment usage that is inferred from the format,
+/// it can only be used as an input attachment. Use `is_lazily_allocated` to know whether memory
+/// had to be committed for the image.
+#[derive(Debug)]
+pub struct TransientAttachmentImage<A = PotentialDedicatedAllocation<StdMemoryPoolAlloc>> {
+    inner: AttachmentImage<A>,
+}
+
+impl TransientAttachmentImage {
+    /// Creates a new transient image with the given dimensions and format.
+    ///
+    /// Returns an error if the dimensions are too large or if the backend doesn't support this
+    /// format as a transient framebuffer attachment.
+    #[inline]
+    pub fn new(
+        device: Arc<Device>,
+        dimensions: [u32; 2],
+        format: Format,
+    ) -> Result<Arc<TransientAttachmentImage>, ImageCreationError> {
+        TransientAttachmentImage::with_usage(
+            device,
+            dimensions,
+            1,
+            SampleCount::Sample1,
+            format,
+            ImageUsage::none(),
+        )
+    }
+
+    /// Same as `new`, but creates an image that can be used as an input attachment.
+    ///
+    /// > **Note**: This function is just a convenient shortcut for `with_usage`.
+    #[inline]
+    pub fn input_attachment(
+        device: Arc<Device>,
+        dimensions: [u32; 2],
+        format: Format,
+    ) -> Result<Arc<TransientAttachmentImage>, ImageCreationError> {
+        let base_usage = ImageUsage {
+            input_attachment: true,
+            ..ImageUsage::none()
+        };
+
+        TransientAttachmentImage::with_usage(
+            device,
+            dimensions,
+            1,
+            SampleCount::Sample1,
+            format,
+            base_usage,
+        )
+    }
+
+    /// Same as `new`, but creates a multisampled image.
+    ///
+    /// > **Note**: You can also use this function and pass `1` for the number of samples if you
+    /// > want a regular image.
+    #[inline]
+    pub fn multisampled(
+        device: Arc<Device>,
+        dimensions: [u32; 2],
+        samples: SampleCount,
+        format: Format,
+    ) -> Result<Arc<TransientAttachmentImage>, ImageCreationError> {
+        TransientAttachmentImage::with_usage(
+            device,
+            dimensions,
+            1,
+            samples,
+            format,
+            ImageUsage::none(),
+        )
+    }
+
+    /// Same as `new`, but allows specifying the number of array layers, the number of samples and
+    /// the usage.
+    ///
+    /// The `transient_attachment` usage and the color or depth-stencil attachment usage, depending
+    /// on the format, are added to `base_usage`. Any usage other than `input_attachment` in
+    /// `base_usage` results in an `UnsupportedUsage` error.
+    #[inline]
+    pub fn with_usage(
+        device: Arc<Device>,
+        dimensions: [u32; 2],
+        array_layers: u32,
+        samples: SampleCount,
+        format: Format,
+        base_usage: ImageUsage,
+    ) -> Result<Arc<TransientAttachmentImage>, ImageCreationError> {
+        TransientAttachmentImage::with_pool(
+            &Device::standard_pool(&device),
+            dimensions,
+            array_layers,
+            samples,
+            format,
+            base_usage,
+        )
+    }
+
+    /// Same as `with_usage`, but allocates the memory of the image from `pool` instead of the
+    /// standard pool of the device.
+    pub fn with_pool<P>(
+        pool: &P,
+        dimensions: [u32; 2],
+        array_layers: u32,
+        samples: SampleCount,
+        format: Format,
+        base_usage: ImageUsage,
+    ) -> Result<
+        Arc<TransientAttachmentImage<PotentialDedicatedAllocation<P::Alloc>>>,
+        ImageCreationError,
+    >
+    where
+        P: MemoryPool,
+    {
+        let dimensions = ImageDimensions::Dim2d {
+            width: dimensions[0],
+            height: dimensions[1],
+            array_layers,
+        };
+        let usage = ImageUsage {
+            transient_attachment: true,
+            ..base_usage
+        };
+
+        let inner = AttachmentImage::with_pool_impl(
+            pool,
+            dimensions,
+            ImageCreateFlags::none(),
+            samples,
+            format,
+            usage,
+            true,
+        )?;
+
+        Ok(Arc::new(TransientAttachmentImage { inner }))
+    }
+}
+
+impl<A> TransientAttachmentImage<A>
+where
+    A: MemoryPoolAlloc,
+{
+    /// Returns true if the memory of the image was allocated from a lazily-allocated memory type,
+    /// in which case the implementation may never commit memory for it.
+    #[inline]
+    pub fn is_lazily_allocated(&self) -> bool {
+        self.inner
+            .memory()
+            .memory()
+            .memory_type()
+            .is_lazily_allocated()
+    }
+}
+
+unsafe impl<A> DeviceOwned for TransientAttachmentImage<A>
+where
+    A: MemoryPoolAlloc,
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.inner.inner().image.device()
+    }
+}
+
+unsafe impl<A> ImageAccess for TransientAttachmentImage<A>
+where
+    A: MemoryPoolAlloc,
+{
+    #[inline]
+    fn inner(&self) -> ImageInner {
+        self.inner.inner()
+    }
+
+    #[inline]
+    fn initial_layout_requirement(&self) -> ImageLayout {
+        self.inner.initial_layout_requirement()
+    }
+
+    #[inline]
+    fn final_layout_requirement(&self) -> ImageLayout {
+        self.inner.final_layout_requirement()
+    }
+
+    #[inline]
+    fn descriptor_layouts(&self) -> Option<ImageDescriptorLayouts> {
+        self.inner.descriptor_layouts()
+    }
+
+    #[inline]
+    fn conflict_key(&self) -> u64 {
+        self.inner.conflict_key()
+    }
+
+    #[inline]
+    fn try_gpu_lock(
+        &self,
+        exclusive_access: bool,
+        uninitialized_safe: bool,
+        expected_layout: ImageLayout,
+    ) -> Result<(), AccessError> {
+        self.inner
+            .try_gpu_lock(exclusive_access, uninitialized_safe, expected_layout)
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self) {
+        self.inner.increase_gpu_lock()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self, new_layout: Option<ImageLayout>) {
+        self.inner.unlock(new_layout)
+    }
+
+    #[inline]
+    unsafe fn layout_initialized(&self) {
+        self.inner.layout_initialized()
+    }
+
+    #[inline]
+    fn is_layout_initialized(&self) -> bool {
+        self.inner.is_layout_initialized()
+    }
+
+    #[inline]
+    fn current_miplevels_access(&self) -> std::ops::Range<u32> {
+        self.inner.current_miplevels_access()
+    }
+
+    #[inline]
+    fn current_layer_levels_access(&self) -> std::ops::Range<u32> {
+        self.inner.current_layer_levels_access()
+    }
+}
+
+impl<A> PartialEq for TransientAttachmentImage<A>
+where
+    A: MemoryPoolAlloc,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.inner() == other.inner()
+    }
+}
+
+impl<A> Eq for TransientAttachmentImage<A> where A: MemoryPoolAlloc {}
+
+impl<A> Hash for TransientAttachmentImage<A>
+where
+    A: MemoryPoolAlloc,
+{
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner().hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TransientAttachmentImage;
+    use crate::format::Format;
+    use crate::image::sys::ImageCreationError;
+    use crate::image::ImageAccess;
+    use crate::image::ImageUsage;
+    use crate::image::SampleCount;
+
+    #[test]
+    fn create_transient() {
+        let (device, _) = gfx_dev_and_queue!();
+        let img =
+            TransientAttachmentImage::new(device.clone(), [32, 32], Format::D16_UNORM).unwrap();
+
+        let usage = img.inner().image.usage();
+        assert!(usage.transient_attachment);
+        assert!(usage.depth_stencil_attachment);
+
+        let has_lazily_allocated = device
+            .physical_device()
+            .memory_types()
+            .any(|t| t.is_lazily_allocated());
+        if !has_lazily_allocated {
+            assert!(!img.is_lazily_allocated());
+        }
+    }
+
+    #[test]
+    fn sampled_usage_rejected() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let res = TransientAttachmentImage::with_usage(
+            device,
+            [32, 32],
+            1,
+            SampleCount::Sample1,
+            Format::R8G8B8A8_UNORM,
+            ImageUsage {
+                sampled: true,
+                ..ImageUsage::none()
+            },
+        );
+
+        match res {
+            Err(ImageCreationError::UnsupportedUsage) => (),
+            _ => panic!(),
+        };
+    }
+}
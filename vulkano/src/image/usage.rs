@@ -46,6 +46,13 @@ pub struct ImageUsage {
     /// Can be used as an input attachment. In other words, you can draw to it in a subpass then
     /// read from it in a following pass.
     pub input_attachment: bool,
+
+    /// Can be used as a fragment shading rate attachment, which sets the fragment shading rate of
+    /// the areas of the framebuffer.
+    ///
+    /// The [`attachment_fragment_shading_rate`](crate::device::Features::attachment_fragment_shading_rate)
+    /// feature must be enabled on the device.
+    pub fragment_shading_rate_attachment: bool,
 }
 
 impl ImageUsage {
@@ -62,6 +69,7 @@ impl ImageUsage {
             depth_stencil_attachment: true,
             transient_attachment: true,
             input_attachment: true,
+            fragment_shading_rate_attachment: true,
         }
     }
 
@@ -89,6 +97,7 @@ impl ImageUsage {
             depth_stencil_attachment: false,
             transient_attachment: false,
             input_attachment: false,
+            fragment_shading_rate_attachment: false,
         }
     }
 
@@ -104,6 +113,7 @@ impl ImageUsage {
             depth_stencil_attachment: false,
             transient_attachment: false,
             input_attachment: false,
+            fragment_shading_rate_attachment: false,
         }
     }
 
@@ -119,6 +129,7 @@ impl ImageUsage {
             depth_stencil_attachment: true,
             transient_attachment: false,
             input_attachment: false,
+            fragment_shading_rate_attachment: false,
         }
     }
 
@@ -134,6 +145,7 @@ impl ImageUsage {
             depth_stencil_attachment: false,
             transient_attachment: true,
             input_attachment: false,
+            fragment_shading_rate_attachment: false,
         }
     }
 
@@ -149,6 +161,7 @@ impl ImageUsage {
             depth_stencil_attachment: true,
             transient_attachment: true,
             input_attachment: false,
+            fragment_shading_rate_attachment: false,
         }
     }
 }
@@ -181,6 +194,9 @@ impl From<ImageUsage> for ash::vk::ImageUsageFlags {
         if val.input_attachment {
             result |= ash::vk::ImageUsageFlags::INPUT_ATTACHMENT;
         }
+        if val.fragment_shading_rate_attachment {
+            result |= ash::vk::ImageUsageFlags::FRAGMENT_SHADING_RATE_ATTACHMENT_KHR;
+        }
         result
     }
 }
@@ -199,6 +215,9 @@ impl From<ash::vk::ImageUsageFlags> for ImageUsage {
             transient_attachment: !(val & ash::vk::ImageUsageFlags::TRANSIENT_ATTACHMENT)
                 .is_empty(),
             input_attachment: !(val & ash::vk::ImageUsageFlags::INPUT_ATTACHMENT).is_empty(),
+            fragment_shading_rate_attachment: !(val
+                & ash::vk::ImageUsageFlags::FRAGMENT_SHADING_RATE_ATTACHMENT_KHR)
+                .is_empty(),
         }
     }
 }
@@ -217,6 +236,8 @@ impl BitOr for ImageUsage {
             depth_stencil_attachment: self.depth_stencil_attachment || rhs.depth_stencil_attachment,
             transient_attachment: self.transient_attachment || rhs.transient_attachment,
             input_attachment: self.input_attachment || rhs.input_attachment,
+            fragment_shading_rate_attachment: self.fragment_shading_rate_attachment
+                || rhs.fragment_shading_rate_attachment,
         }
     }
 }
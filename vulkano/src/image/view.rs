@@ -17,11 +17,16 @@ use crate::check_errors;
 use crate::device::Device;
 use crate::format::Format;
 use crate::image::sys::UnsafeImage;
+use crate::image::CubeFace;
 use crate::image::ImageAccess;
+use crate::image::ImageAspects;
 use crate::image::ImageDimensions;
+use crate::image::ImageUsage;
 use crate::memory::DeviceMemoryAllocError;
+use crate::sampler::ycbcr::SamplerYcbcrConversion;
 use crate::sampler::Sampler;
 use crate::OomError;
+use crate::Version;
 use crate::VulkanObject;
 use std::error;
 use std::fmt;
@@ -43,7 +48,9 @@ where
     array_layers: Range<u32>,
     component_mapping: ComponentMapping,
     format: Format,
+    sampler_ycbcr_conversion: Option<Arc<SamplerYcbcrConversion>>,
     ty: ImageViewType,
+    usage: ImageUsage,
 }
 
 impl<I> ImageView<I>
@@ -77,12 +84,31 @@ where
             component_mapping: ComponentMapping::default(),
             format: image.format(),
             mipmap_levels,
+            plane: None,
+            sampler_ycbcr_conversion: None,
             ty,
+            usage: None,
 
             image,
         }
     }
 
+    /// Creates a view of type `Cube` of an image that contains 6 array layers, or of type
+    /// `CubeArray` of all the cubes of an image with more layers. The image must have been
+    /// created with the `cube_compatible` flag.
+    ///
+    /// Cube array views require the `image_cube_array` feature.
+    #[inline]
+    pub fn cube(image: Arc<I>) -> Result<Arc<ImageView<I>>, ImageViewCreationError> {
+        let ty = if image.dimensions().array_layers() == 6 {
+            ImageViewType::Cube
+        } else {
+            ImageViewType::CubeArray
+        };
+
+        Self::start(image).with_type(ty).build()
+    }
+
     /// Returns the wrapped image that this image view was created from.
     pub fn image(&self) -> &Arc<I> {
         &self.image
@@ -95,7 +121,10 @@ pub struct ImageViewBuilder<I> {
     component_mapping: ComponentMapping,
     format: Format,
     mipmap_levels: Range<u32>,
+    plane: Option<usize>,
+    sampler_ycbcr_conversion: Option<Arc<SamplerYcbcrConversion>>,
     ty: ImageViewType,
+    usage: Option<ImageUsage>,
 
     image: Arc<I>,
 }
@@ -117,6 +146,12 @@ where
 
     /// Sets how to map components of each pixel.
     ///
+    /// A component mapping other than identity is only allowed for views that are sampled, as
+    /// storage images and attachments must be identity swizzled. If the image has other usages,
+    /// restrict the usage of the view with [`with_usage`](Self::with_usage). The component
+    /// mapping of a view with a sampler YCbCr conversion must be identity, as the conversion
+    /// has its own component mapping.
+    ///
     /// By default, this is the identity mapping, with every component mapped directly.
     #[inline]
     pub fn with_component_mapping(mut self, component_mapping: ComponentMapping) -> Self {
@@ -124,10 +159,27 @@ where
         self
     }
 
+    /// Restricts the usage of the view to `usage`, which must be a subset of the usage of the
+    /// image.
+    ///
+    /// By default, the view has all the usages of the image. Restricting it makes it possible,
+    /// for example, to create a swizzled view for sampling of an image that is also used as a
+    /// storage image, or a view with a format that doesn't support all the usages of the image.
+    ///
+    /// Requires Vulkan 1.1 or the `khr_maintenance2` extension.
+    #[inline]
+    pub fn with_usage(mut self, usage: ImageUsage) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
     /// Sets the format of the image view.
     ///
     /// By default, this is the format of the image. Using a different format requires enabling the
-    /// `mutable_format` flag on the image.
+    /// `mutable_format` flag on the image, and the format must be compatible with the format of
+    /// the image. If the image was created with a list of view formats, the format must also be in
+    /// that list. For example, this allows writing to an sRGB image from a compute shader through
+    /// a UNORM view.
     #[inline]
     pub fn with_format(mut self, format: Format) -> Self {
         self.format = format;
@@ -145,13 +197,59 @@ where
 
     /// Sets the range of array layers that the view should cover.
     ///
-    /// By default, this is the full range of array layers present in the image.
+    /// By default, this is the full range of array layers present in the image. For a 2D or 2D
+    /// array view of a 3D image, the array layers are the depth slices of the first mipmap level
+    /// of the view.
     #[inline]
     pub fn with_array_layers(mut self, array_layers: Range<u32>) -> Self {
         self.array_layers = array_layers;
         self
     }
 
+    /// Restricts the view to a single face of a cube, with a 2D view type. This is for example how
+    /// a face of a cube map is used as a framebuffer attachment.
+    ///
+    /// `cube_index` is the index of the cube in a cube array, or 0 for an image that contains a
+    /// single cube. This overrides the type and array layers of the view.
+    #[inline]
+    pub fn with_cube_face(mut self, face: CubeFace, cube_index: u32) -> Self {
+        let layer = face.array_layer(cube_index);
+        self.ty = ImageViewType::Dim2d;
+        self.array_layers = layer..layer + 1;
+        self
+    }
+
+    /// Restricts the view to a single plane of an image with a multi-planar format, and sets the
+    /// format of the view to the format of that plane.
+    ///
+    /// By default, the view covers all the planes, which are sampled together through a sampler
+    /// YCbCr conversion. A view of a single plane requires enabling the `mutable_format` flag on
+    /// the image. Its format can then be changed with [`with_format`](Self::with_format) to
+    /// another format that is compatible with the format of the plane.
+    #[inline]
+    pub fn with_plane(mut self, plane: usize) -> Self {
+        if let Some(&format) = self.image.format().planes().get(plane) {
+            self.format = format;
+        }
+
+        self.plane = Some(plane);
+        self
+    }
+
+    /// Sets the sampler YCbCr conversion to apply to the view.
+    ///
+    /// By default, no conversion is applied. A conversion is required if the format of the image
+    /// requires it, and its format must be the format of the view. The view can then only be
+    /// sampled with samplers that were created with the same conversion.
+    #[inline]
+    pub fn with_sampler_ycbcr_conversion(
+        mut self,
+        conversion: Arc<SamplerYcbcrConversion>,
+    ) -> Self {
+        self.sampler_ycbcr_conversion = Some(conversion);
+        self
+    }
+
     /// Builds the `ImageView`.
     pub fn build(self) -> Result<Arc<ImageView<I>>, ImageViewCreationError> {
         let dimensions = self.image.dimensions();
@@ -160,6 +258,25 @@ where
         let image_format = image_inner.format();
         let image_usage = image_inner.usage();
 
+        if let Some(usage) = self.usage {
+            let device = image_inner.device();
+
+            if !(device.api_version() >= Version::V1_1
+                || device.enabled_extensions().khr_maintenance2)
+            {
+                return Err(ImageViewCreationError::Maintenance2ExtensionNotEnabled);
+            }
+
+            let usage_bits = ash::vk::ImageUsageFlags::from(usage);
+            if usage_bits.is_empty()
+                || !ash::vk::ImageUsageFlags::from(image_usage).contains(usage_bits)
+            {
+                return Err(ImageViewCreationError::UsageNotSupportedByImage);
+            }
+        }
+
+        let usage = self.usage.unwrap_or(image_usage);
+
         if self.mipmap_levels.end <= self.mipmap_levels.start
             || self.mipmap_levels.end > image_inner.mipmap_levels()
         {
@@ -167,21 +284,29 @@ where
         }
 
         if self.array_layers.end <= self.array_layers.start
-            || self.array_layers.end > dimensions.array_layers()
+            || self.array_layers.end
+                > available_array_layers(dimensions, self.ty, self.mipmap_levels.start)
         {
             return Err(ImageViewCreationError::ArrayLayersOutOfRange);
         }
 
-        if !(image_usage.sampled
-            || image_usage.storage
-            || image_usage.color_attachment
-            || image_usage.depth_stencil_attachment
-            || image_usage.input_attachment
-            || image_usage.transient_attachment)
+        if !(usage.sampled
+            || usage.storage
+            || usage.color_attachment
+            || usage.depth_stencil_attachment
+            || usage.input_attachment
+            || usage.transient_attachment)
         {
             return Err(ImageViewCreationError::InvalidImageUsage);
         }
 
+        // VUID-VkImageViewCreateInfo-pNext-01970
+        if !self.component_mapping.is_identity()
+            && (!usage.sampled || self.sampler_ycbcr_conversion.is_some())
+        {
+            return Err(ImageViewCreationError::InvalidComponentMapping);
+        }
+
         // Check for compatibility with the image
         match (
             self.ty,
@@ -217,71 +342,100 @@ where
             _ => return Err(ImageViewCreationError::IncompatibleType),
         }
 
-        if image_format.requires_sampler_ycbcr_conversion() {
-            unimplemented!()
+        if self.ty == ImageViewType::CubeArray
+            && !image_inner.device().enabled_features().image_cube_array
+        {
+            return Err(ImageViewCreationError::ImageCubeArrayFeatureNotEnabled);
         }
 
-        if image_flags.block_texel_view_compatible {
-            if self.format.compatibility() != image_format.compatibility()
-                || self.format.size() != image_format.size()
-            {
-                return Err(ImageViewCreationError::IncompatibleFormat);
-            }
-
-            if self.array_layers.end - self.array_layers.start != 1 {
-                return Err(ImageViewCreationError::ArrayLayersOutOfRange);
+        if let Some(plane) = self.plane {
+            if plane >= image_format.planes().len() {
+                return Err(ImageViewCreationError::InvalidPlane);
             }
+        }
 
-            if self.mipmap_levels.end - self.mipmap_levels.start != 1 {
-                return Err(ImageViewCreationError::MipMapLevelsOutOfRange);
+        if let Some(conversion) = &self.sampler_ycbcr_conversion {
+            if conversion.format() != Some(self.format) {
+                return Err(ImageViewCreationError::SamplerYcbcrConversionFormatMismatch);
             }
+        } else if self.format.requires_sampler_ycbcr_conversion() {
+            return Err(ImageViewCreationError::SamplerYcbcrConversionRequired);
+        }
 
-            if self.format.compression().is_none() && self.ty == ImageViewType::Dim3d {
-                return Err(ImageViewCreationError::IncompatibleType);
-            }
-        } else if image_flags.mutable_format {
+        if image_flags.mutable_format {
             if image_format.planes().is_empty() {
-                if self.format != image_format {
-                    return Err(ImageViewCreationError::IncompatibleFormat);
+                if self.format.compatibility() != image_format.compatibility() {
+                    // VUID-VkImageViewCreateInfo-image-01583
+                    if !image_flags.block_texel_view_compatible
+                        || self.format.compression().is_some()
+                        || self.format.size() != image_format.size()
+                    {
+                        return Err(ImageViewCreationError::IncompatibleFormat);
+                    }
+
+                    if self.array_layers.end - self.array_layers.start != 1 {
+                        return Err(ImageViewCreationError::ArrayLayersOutOfRange);
+                    }
+
+                    if self.mipmap_levels.end - self.mipmap_levels.start != 1 {
+                        return Err(ImageViewCreationError::MipMapLevelsOutOfRange);
+                    }
+
+                    if self.ty == ImageViewType::Dim3d {
+                        return Err(ImageViewCreationError::IncompatibleType);
+                    }
                 }
-            } else {
-                // TODO: VUID-VkImageViewCreateInfo-image-01586
-                // If image was created with the VK_IMAGE_CREATE_MUTABLE_FORMAT_BIT flag, if the
-                // format of the image is a multi-planar format, and if subresourceRange.aspectMask
-                // is one of VK_IMAGE_ASPECT_PLANE_0_BIT, VK_IMAGE_ASPECT_PLANE_1_BIT, or
-                // VK_IMAGE_ASPECT_PLANE_2_BIT, then format must be compatible with the VkFormat for
-                // the plane of the image format indicated by subresourceRange.aspectMask, as
-                // defined in Compatible formats of planes of multi-planar formats
-
-                // TODO: VUID-VkImageViewCreateInfo-image-01762
-                // If image was not created with the VK_IMAGE_CREATE_MUTABLE_FORMAT_BIT flag, or if
-                // the format of the image is a multi-planar format and if
-                // subresourceRange.aspectMask is VK_IMAGE_ASPECT_COLOR_BIT, format must be
-                // identical to the format used to create image
-            }
-        } else if self.format != image_format {
-            return Err(ImageViewCreationError::IncompatibleFormat);
-        }
 
-        if self.format != image_format {
-            if !(image_flags.mutable_format && image_format.planes().is_empty()) {
-                return Err(ImageViewCreationError::IncompatibleFormat);
-            } else if self.format.compatibility() != image_format.compatibility() {
-                if !image_flags.block_texel_view_compatible {
+                // VUID-VkImageViewCreateInfo-pNext-01585
+                let view_formats = image_inner.view_formats();
+                if !view_formats.is_empty() && !view_formats.contains(&self.format) {
                     return Err(ImageViewCreationError::IncompatibleFormat);
-                } else if self.format.size() != image_format.size() {
+                }
+            } else if let Some(plane) = self.plane {
+                // VUID-VkImageViewCreateInfo-image-01586
+                if self.format.compatibility() != image_format.planes()[plane].compatibility() {
                     return Err(ImageViewCreationError::IncompatibleFormat);
                 }
+            } else if self.format != image_format {
+                // VUID-VkImageViewCreateInfo-image-01762
+                return Err(ImageViewCreationError::IncompatibleFormat);
             }
+        } else if self.format != image_format {
+            return Err(ImageViewCreationError::IncompatibleFormat);
         }
 
+        let aspects = match self.plane {
+            Some(0) => ImageAspects {
+                plane0: true,
+                ..ImageAspects::none()
+            },
+            Some(1) => ImageAspects {
+                plane1: true,
+                ..ImageAspects::none()
+            },
+            Some(_) => ImageAspects {
+                plane2: true,
+                ..ImageAspects::none()
+            },
+            // The planes of a multi-planar image are sampled together through the color aspect.
+            None if !image_format.planes().is_empty() => ImageAspects {
+                color: true,
+                ..ImageAspects::none()
+            },
+            None => image_format.aspects(),
+        };
+
         let inner = unsafe {
             UnsafeImageView::new(
                 image_inner,
                 self.ty,
+                self.format,
+                aspects,
                 self.component_mapping,
                 self.mipmap_levels,
                 self.array_layers.clone(),
+                self.usage,
+                self.sampler_ycbcr_conversion.as_deref(),
             )?
         };
 
@@ -292,7 +446,9 @@ where
             array_layers: self.array_layers,
             component_mapping: self.component_mapping,
             format: self.format,
+            sampler_ycbcr_conversion: self.sampler_ycbcr_conversion,
             ty: self.ty,
+            usage,
         }))
     }
 }
@@ -310,10 +466,26 @@ pub enum ImageViewCreationError {
     IncompatibleFormat,
     /// The requested [`ImageViewType`] was not compatible with the image, or with the specified ranges of array layers and mipmap levels.
     IncompatibleType,
+    /// A plane was selected, but the image doesn't have a multi-planar format with that plane.
+    InvalidPlane,
     /// The image was not created with
     /// [one of the required usages](https://www.khronos.org/registry/vulkan/specs/1.2-extensions/html/vkspec.html#valid-imageview-imageusage)
     /// for image views.
     InvalidImageUsage,
+    /// The format of the sampler YCbCr conversion is not the format of the view.
+    SamplerYcbcrConversionFormatMismatch,
+    /// The format of the image requires a sampler YCbCr conversion, but none was provided.
+    SamplerYcbcrConversionRequired,
+    /// A cube array view was requested, but the `image_cube_array` feature is not enabled.
+    ImageCubeArrayFeatureNotEnabled,
+    /// The component mapping is not identity, but the view doesn't have the `sampled` usage or
+    /// has a sampler YCbCr conversion.
+    InvalidComponentMapping,
+    /// A usage was specified for the view, but neither Vulkan 1.1 nor the `khr_maintenance2`
+    /// extension is enabled.
+    Maintenance2ExtensionNotEnabled,
+    /// The usage of the view is empty or is not a subset of the usage of the image.
+    UsageNotSupportedByImage,
 }
 
 impl error::Error for ImageViewCreationError {
@@ -339,8 +511,22 @@ impl fmt::Display for ImageViewCreationError {
                 ImageViewCreationError::IncompatibleFormat => "format is not compatible with image",
                 ImageViewCreationError::IncompatibleType =>
                     "image view type is not compatible with image, array layers or mipmap levels",
+                ImageViewCreationError::InvalidPlane => "the image doesn't have the selected plane",
                 ImageViewCreationError::InvalidImageUsage =>
                     "the usage of the image is not compatible with image views",
+                ImageViewCreationError::SamplerYcbcrConversionFormatMismatch =>
+                    "the format of the sampler YCbCr conversion is not the format of the view",
+                ImageViewCreationError::SamplerYcbcrConversionRequired =>
+                    "the format of the image requires a sampler YCbCr conversion",
+                ImageViewCreationError::ImageCubeArrayFeatureNotEnabled =>
+                    "the `image_cube_array` feature must be enabled to create cube array views",
+                ImageViewCreationError::InvalidComponentMapping =>
+                    "the component mapping must be identity for views that are not only sampled",
+                ImageViewCreationError::Maintenance2ExtensionNotEnabled =>
+                    "the usage of a view can only be restricted with Vulkan 1.1 or the \
+                     `khr_maintenance2` extension",
+                ImageViewCreationError::UsageNotSupportedByImage =>
+                    "the usage of the view is not a subset of the usage of the image",
             }
         )
     }
@@ -366,39 +552,66 @@ impl UnsafeImageView {
     /// - The returned `UnsafeImageView` must not outlive `image`.
     /// - `image` must have a usage that is compatible with image views.
     /// - `ty` must be compatible with the dimensions and flags of the image.
+    /// - `format` must be compatible with the format and flags of the image.
+    /// - `aspects` must be the aspects of the format of the image, the color aspect for a
+    ///   multi-planar format, or a single plane of a multi-planar format.
     /// - `mipmap_levels` must not be empty, must be within the range of levels of the image, and be compatible with the requested `ty`.
     /// - `array_layers` must not be empty, must be within the range of layers of the image, and be compatible with the requested `ty`.
-    ///
-    /// # Panics
-    /// Panics if the image is a YcbCr image, since the Vulkano API is not yet flexible enough to
-    /// specify the aspect of image.
+    /// - If `usage` is `Some`, it must be a subset of the usage of the image, and Vulkan 1.1 or the
+    ///   `khr_maintenance2` extension must be enabled.
+    /// - `sampler_ycbcr_conversion` must be `Some` if the format of the image requires it, and
+    ///   must then have been created with the format of the image.
     pub unsafe fn new(
         image: &UnsafeImage,
         ty: ImageViewType,
+        format: Format,
+        aspects: ImageAspects,
         component_mapping: ComponentMapping,
         mipmap_levels: Range<u32>,
         array_layers: Range<u32>,
+        usage: Option<ImageUsage>,
+        sampler_ycbcr_conversion: Option<&SamplerYcbcrConversion>,
     ) -> Result<UnsafeImageView, OomError> {
         let fns = image.device().fns();
 
         debug_assert!(mipmap_levels.end > mipmap_levels.start);
         debug_assert!(mipmap_levels.end <= image.mipmap_levels());
         debug_assert!(array_layers.end > array_layers.start);
-        debug_assert!(array_layers.end <= image.dimensions().array_layers());
+        debug_assert!(
+            array_layers.end <= available_array_layers(image.dimensions(), ty, mipmap_levels.start)
+        );
 
-        if image.format().requires_sampler_ycbcr_conversion() {
-            unimplemented!();
-        }
-
-        // TODO: Let user choose
-        let aspects = image.format().aspects();
+        debug_assert!(
+            sampler_ycbcr_conversion.is_some()
+                || !image.format().requires_sampler_ycbcr_conversion()
+        );
 
         let view = {
+            let conversion_info =
+                sampler_ycbcr_conversion.map(|conversion| ash::vk::SamplerYcbcrConversionInfo {
+                    conversion: conversion.internal_object(),
+                    ..Default::default()
+                });
+            let usage_info = usage.map(|usage| ash::vk::ImageViewUsageCreateInfo {
+                p_next: conversion_info
+                    .as_ref()
+                    .map_or(ptr::null(), |info| info as *const _ as *const _),
+                usage: usage.into(),
+                ..Default::default()
+            });
+
+            let p_next = match (&usage_info, &conversion_info) {
+                (Some(info), _) => info as *const _ as *const _,
+                (None, Some(info)) => info as *const _ as *const _,
+                (None, None) => ptr::null(),
+            };
+
             let infos = ash::vk::ImageViewCreateInfo {
+                p_next,
                 flags: ash::vk::ImageViewCreateFlags::empty(),
                 image: image.internal_object(),
                 view_type: ty.into(),
-                format: image.format().into(),
+                format: format.into(),
                 components: component_mapping.into(),
                 subresource_range: ash::vk::ImageSubresourceRange {
                     aspect_mask: aspects.into(),
@@ -580,6 +793,23 @@ impl Default for ComponentSwizzle {
     }
 }
 
+// Returns the number of array layers that a view of type `ty` can cover, starting at
+// `mipmap_level`. The array layers of a 2D or 2D array view of a 3D image are its depth slices.
+fn available_array_layers(
+    dimensions: ImageDimensions,
+    ty: ImageViewType,
+    mipmap_level: u32,
+) -> u32 {
+    match (ty, dimensions) {
+        (ImageViewType::Dim2d | ImageViewType::Dim2dArray, ImageDimensions::Dim3d { .. }) => {
+            dimensions
+                .mipmap_dimensions(mipmap_level)
+                .map_or(0, |dimensions| dimensions.depth())
+        }
+        _ => dimensions.array_layers(),
+    }
+}
+
 /// Trait for types that represent the GPU can access an image view.
 pub unsafe trait ImageViewAbstract: Send + Sync {
     /// Returns the wrapped image that this image view was created from.
@@ -600,13 +830,26 @@ pub unsafe trait ImageViewAbstract: Send + Sync {
     /// Returns the [`ImageViewType`] of this image view.
     fn ty(&self) -> ImageViewType;
 
+    /// Returns the usage of this view. This can be a subset of the usage of the parent image.
+    #[inline]
+    fn usage(&self) -> ImageUsage {
+        self.image().inner().image.usage()
+    }
+
+    /// Returns the sampler YCbCr conversion of this view, if any.
+    #[inline]
+    fn sampler_ycbcr_conversion(&self) -> Option<&Arc<SamplerYcbcrConversion>> {
+        None
+    }
+
     /// Returns true if the given sampler can be used with this image view.
     ///
     /// This method should check whether the sampler's configuration can be used with the format
     /// of the view.
     // TODO: return a Result and propagate it when binding to a descriptor set
-    fn can_be_sampled(&self, _sampler: &Sampler) -> bool {
-        true /* FIXME */
+    fn can_be_sampled(&self, sampler: &Sampler) -> bool {
+        // FIXME: check the other restrictions
+        sampler.sampler_ycbcr_conversion() == self.sampler_ycbcr_conversion()
     }
 }
 
@@ -644,6 +887,16 @@ where
     fn ty(&self) -> ImageViewType {
         self.ty
     }
+
+    #[inline]
+    fn usage(&self) -> ImageUsage {
+        self.usage
+    }
+
+    #[inline]
+    fn sampler_ycbcr_conversion(&self) -> Option<&Arc<SamplerYcbcrConversion>> {
+        self.sampler_ycbcr_conversion.as_ref()
+    }
 }
 
 impl PartialEq for dyn ImageViewAbstract {
@@ -661,3 +914,83 @@ impl Hash for dyn ImageViewAbstract {
         self.inner().hash(state);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ComponentMapping;
+    use super::ComponentSwizzle;
+    use super::ImageView;
+    use super::ImageViewAbstract;
+    use super::ImageViewCreationError;
+    use crate::format::Format;
+    use crate::image::ImageDimensions;
+    use crate::image::ImageUsage;
+    use crate::image::StorageImage;
+    use crate::Version;
+
+    #[test]
+    fn swizzle_requires_sampled_usage() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = StorageImage::new(
+            device.clone(),
+            ImageDimensions::Dim2d {
+                width: 32,
+                height: 32,
+                array_layers: 1,
+            },
+            Format::R8G8B8A8_UNORM,
+            Some(queue.family()),
+        )
+        .unwrap();
+        let swizzle = ComponentMapping {
+            r: ComponentSwizzle::Blue,
+            b: ComponentSwizzle::Red,
+            ..ComponentMapping::default()
+        };
+        let storage = ImageUsage {
+            storage: true,
+            ..ImageUsage::none()
+        };
+
+        if !(device.api_version() >= Version::V1_1 || device.enabled_extensions().khr_maintenance2)
+        {
+            match ImageView::start(image).with_usage(storage).build() {
+                Err(ImageViewCreationError::Maintenance2ExtensionNotEnabled) => (),
+                _ => panic!(),
+            }
+            return;
+        }
+
+        match ImageView::start(image.clone())
+            .with_component_mapping(swizzle)
+            .with_usage(storage)
+            .build()
+        {
+            Err(ImageViewCreationError::InvalidComponentMapping) => (),
+            _ => panic!(),
+        }
+
+        match ImageView::start(image.clone())
+            .with_usage(ImageUsage {
+                transient_attachment: true,
+                ..ImageUsage::none()
+            })
+            .build()
+        {
+            Err(ImageViewCreationError::UsageNotSupportedByImage) => (),
+            _ => panic!(),
+        }
+
+        let sampled = ImageUsage {
+            sampled: true,
+            ..ImageUsage::none()
+        };
+        let view = ImageView::start(image)
+            .with_component_mapping(swizzle)
+            .with_usage(sampled)
+            .build()
+            .unwrap();
+        assert_eq!(view.usage(), sampled);
+    }
+}
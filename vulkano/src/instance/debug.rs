@@ -411,6 +411,19 @@ impl std::ops::BitOr for MessageType {
     }
 }
 
+/// A label that can be inserted into a command buffer or queue, or that marks the start of a
+/// region of commands, so that debugging tools can show it alongside the commands.
+///
+/// Labels require the `ext_debug_utils` extension to be enabled on the instance.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DebugUtilsLabel {
+    /// The name of the label. It must not contain nul characters.
+    pub label_name: String,
+    /// The RGBA color of the label, with values in the range 0.0 to 1.0. If all the values are
+    /// 0.0, the color is ignored.
+    pub color: [f32; 4],
+}
+
 /// Error that can happen when creating a debug callback.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum DebugCallbackCreationError {
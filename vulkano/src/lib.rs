@@ -75,6 +75,7 @@ pub use version::Version;
 mod tests;
 #[macro_use]
 mod extensions;
+pub mod acceleration_structure;
 pub mod buffer;
 pub mod command_buffer;
 pub mod descriptor_set;
@@ -90,10 +91,12 @@ pub mod memory;
 pub mod pipeline;
 pub mod query;
 pub mod range_set;
+pub mod readback;
 pub mod sampler;
 pub mod shader;
 pub mod swapchain;
 pub mod sync;
+pub mod upload;
 
 /// Represents memory size and offset values on a Vulkan device.
 /// Analogous to the Rust `usize` type on the host.
@@ -171,6 +174,7 @@ enum Success {
     EventReset = ash::vk::Result::EVENT_RESET.as_raw(),
     Incomplete = ash::vk::Result::INCOMPLETE.as_raw(),
     Suboptimal = ash::vk::Result::SUBOPTIMAL_KHR.as_raw(),
+    PipelineCompileRequired = ash::vk::Result::PIPELINE_COMPILE_REQUIRED_EXT.as_raw(),
 }
 
 /// All possible errors returned by any Vulkan function.
@@ -199,6 +203,7 @@ pub(crate) enum Error {
     ValidationFailed = ash::vk::Result::ERROR_VALIDATION_FAILED_EXT.as_raw(),
     OutOfPoolMemory = ash::vk::Result::ERROR_OUT_OF_POOL_MEMORY_KHR.as_raw(),
     FullscreenExclusiveLost = ash::vk::Result::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT.as_raw(),
+    InvalidExternalHandle = ash::vk::Result::ERROR_INVALID_EXTERNAL_HANDLE.as_raw(),
 }
 
 /// Checks whether the result returned correctly.
@@ -224,6 +229,7 @@ fn check_errors(result: ash::vk::Result) -> Result<Success, Error> {
         ash::vk::Result::ERROR_SURFACE_LOST_KHR => Err(Error::SurfaceLost),
         ash::vk::Result::ERROR_NATIVE_WINDOW_IN_USE_KHR => Err(Error::NativeWindowInUse),
         ash::vk::Result::SUBOPTIMAL_KHR => Ok(Success::Suboptimal),
+        ash::vk::Result::PIPELINE_COMPILE_REQUIRED_EXT => Ok(Success::PipelineCompileRequired),
         ash::vk::Result::ERROR_OUT_OF_DATE_KHR => Err(Error::OutOfDate),
         ash::vk::Result::ERROR_INCOMPATIBLE_DISPLAY_KHR => Err(Error::IncompatibleDisplay),
         ash::vk::Result::ERROR_VALIDATION_FAILED_EXT => Err(Error::ValidationFailed),
@@ -231,6 +237,7 @@ fn check_errors(result: ash::vk::Result) -> Result<Success, Error> {
         ash::vk::Result::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT => {
             Err(Error::FullscreenExclusiveLost)
         }
+        ash::vk::Result::ERROR_INVALID_EXTERNAL_HANDLE => Err(Error::InvalidExternalHandle),
         ash::vk::Result::ERROR_INVALID_SHADER_NV => panic!(
             "Vulkan function returned \
                                                VK_ERROR_INVALID_SHADER_NV"
@@ -0,0 +1,516 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Moving resources between blocks of memory to reduce fragmentation.
+//!
+//! When an application creates and destroys lots of resources, for example when streaming
+//! textures and meshes, the blocks of memory of the standard pool end up with many small free
+//! ranges. Large resources no longer fit in them, and the pool allocates new blocks even though
+//! the total amount of free memory is enough.
+//!
+//! A [`Defragmenter`] fixes this by moving resources out of the emptiest blocks into the fuller
+//! ones, so that the emptiest blocks are eventually freed. Resources must opt in by being wrapped
+//! in a [`Relocatable`], which holds the current version of the resource:
+//!
+//! ```
+//! use vulkano::buffer::BufferUsage;
+//! use vulkano::buffer::DeviceLocalBuffer;
+//! use vulkano::memory::defragmentation::Defragmenter;
+//! use vulkano::memory::defragmentation::Relocatable;
+//!
+//! # let device: std::sync::Arc<vulkano::device::Device> = return;
+//! # let queue: std::sync::Arc<vulkano::device::Queue> = return;
+//! let usage = BufferUsage {
+//!     transfer_source: true,
+//!     transfer_destination: true,
+//!     ..BufferUsage::vertex_buffer()
+//! };
+//! let buffer = Relocatable::new(
+//!     DeviceLocalBuffer::<[f32]>::array(device.clone(), 1024, usage, Some(queue.family()))
+//!         .unwrap(),
+//! );
+//!
+//! let mut defragmenter = Defragmenter::new(queue.clone());
+//! defragmenter.register(&buffer);
+//!
+//! // Once per frame, move up to 16 MB.
+//! if let Some(pass) = defragmenter.step(16 * 1024 * 1024).unwrap() {
+//!     // ... record and submit the frame, using `buffer.current()` ...
+//!     pass.finish().unwrap();
+//! }
+//! ```
+//!
+//! Each pass copies the resources with a command buffer on the queue of the defragmenter, and
+//! replaces them in their `Relocatable` when it is finished. The old versions of the resources
+//! are freed once nothing uses them anymore. The resources that are moved must not be written
+//! to by the GPU while the pass is in progress, otherwise the writes are lost.
+
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::BuildError;
+use crate::command_buffer::CommandBufferExecError;
+use crate::command_buffer::CommandBufferExecFuture;
+use crate::command_buffer::CommandBufferUsage;
+use crate::command_buffer::CopyBufferError;
+use crate::command_buffer::CopyImageError;
+use crate::command_buffer::PrimaryAutoCommandBuffer;
+use crate::command_buffer::PrimaryCommandBuffer;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::device::Queue;
+use crate::image::ImageCreationError;
+use crate::memory::DeviceMemory;
+use crate::memory::DeviceMemoryAllocError;
+use crate::sync::FenceSignalFuture;
+use crate::sync::FlushError;
+use crate::sync::GpuFuture;
+use crate::sync::NowFuture;
+use crate::DeviceSize;
+use crate::OomError;
+use crate::VulkanObject;
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
+
+/// A resource that a `Defragmenter` can move to another location in memory.
+///
+/// After the resource was moved, `current` returns the new version. The previous versions stay
+/// valid for as long as they are used.
+#[derive(Debug)]
+pub struct Relocatable<R> {
+    current: Mutex<Arc<R>>,
+}
+
+impl<R> Relocatable<R> {
+    /// Wraps `resource` so that it can be moved.
+    #[inline]
+    pub fn new(resource: Arc<R>) -> Arc<Relocatable<R>> {
+        Arc::new(Relocatable {
+            current: Mutex::new(resource),
+        })
+    }
+
+    /// Returns the current version of the resource.
+    ///
+    /// This should be called every time commands that use the resource are recorded, rather
+    /// than keeping the result around.
+    #[inline]
+    pub fn current(&self) -> Arc<R> {
+        self.current.lock().unwrap().clone()
+    }
+}
+
+/// Trait for resources that can be moved by a `Defragmenter`.
+///
+/// # Safety
+///
+/// - `allocation` must return the memory that the resource is bound to.
+/// - `duplicate` must return a resource that can be used in place of `self` in every way.
+///
+pub unsafe trait Relocate: DeviceOwned + Send + Sync + 'static {
+    /// Returns the block of memory that the resource is sub-allocated from and the size of the
+    /// allocation, or `None` if the resource can't be moved.
+    ///
+    /// Resources that have a dedicated allocation can never be moved, since they are alone in
+    /// their memory.
+    fn allocation(&self) -> Option<(&DeviceMemory, DeviceSize)>;
+
+    /// Creates a resource with the same parameters as `self`, with memory allocated from the
+    /// standard pool of the device.
+    fn duplicate(&self) -> Result<Arc<Self>, DefragmentationError>
+    where
+        Self: Sized;
+
+    /// Adds to `builder` a command that copies the content of `source` to `destination`.
+    fn copy(
+        source: Arc<Self>,
+        destination: Arc<Self>,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) -> Result<(), DefragmentationError>
+    where
+        Self: Sized;
+}
+
+/// Moves resources allocated from the standard pool of a device, a few at a time, in order to
+/// free its emptiest blocks of memory.
+///
+/// See [the module-level documentation](self) for more information.
+pub struct Defragmenter {
+    queue: Arc<Queue>,
+    resources: Vec<Box<dyn Registered>>,
+}
+
+impl Defragmenter {
+    /// Creates a defragmenter that copies resources on `queue`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the queue family of `queue` doesn't support transfer operations.
+    ///
+    pub fn new(queue: Arc<Queue>) -> Defragmenter {
+        let family = queue.family();
+        assert!(
+            family.explicitly_supports_transfers()
+                || family.supports_graphics()
+                || family.supports_compute()
+        );
+
+        Defragmenter {
+            queue,
+            resources: Vec::new(),
+        }
+    }
+
+    /// Allows the defragmenter to move `resource`.
+    ///
+    /// The defragmenter doesn't keep the resource alive.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the resource doesn't belong to the same device as the queue of the
+    ///   defragmenter.
+    ///
+    pub fn register<R>(&mut self, resource: &Arc<Relocatable<R>>)
+    where
+        R: Relocate,
+    {
+        assert_eq!(
+            resource.current().device().internal_object(),
+            self.queue.device().internal_object()
+        );
+
+        self.resources.push(Box::new(Arc::downgrade(resource)));
+    }
+
+    /// Starts moving resources, up to about `max_bytes` bytes.
+    ///
+    /// Returns `None` if no resource can be moved to a fuller block of memory. Otherwise, the
+    /// copies are submitted to the queue, and the returned pass must be finished in order to
+    /// replace the resources with their moved version.
+    pub fn step(
+        &mut self,
+        max_bytes: DeviceSize,
+    ) -> Result<Option<DefragmentationPass>, DefragmentationError> {
+        self.resources.retain(|resource| resource.is_alive());
+
+        let device = self.queue.device();
+        let occupancy = Device::standard_pool(device).block_occupancy();
+
+        // Move the resources of the emptiest blocks first, since these blocks are the closest to
+        // being freed.
+        let mut candidates: Vec<(f64, usize)> = self
+            .resources
+            .iter()
+            .enumerate()
+            .filter_map(|(index, resource)| {
+                let block = resource.block()?;
+                Some((*occupancy.get(&block)?, index))
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device.clone(),
+            self.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        let mut swaps = Vec::new();
+        let mut bytes_moved = 0;
+
+        for (_, index) in candidates {
+            if bytes_moved >= max_bytes {
+                break;
+            }
+
+            if let Some((size, swap)) = self.resources[index].relocate(&occupancy, &mut builder)? {
+                bytes_moved += size;
+                swaps.push(swap);
+            }
+        }
+
+        if swaps.is_empty() {
+            return Ok(None);
+        }
+
+        let future = builder
+            .build()?
+            .execute(self.queue.clone())?
+            .then_signal_fence_and_flush()?;
+
+        Ok(Some(DefragmentationPass {
+            future,
+            swaps,
+            bytes_moved,
+        }))
+    }
+}
+
+impl fmt::Debug for Defragmenter {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Defragmenter")
+            .field("queue", &self.queue)
+            .field("resources", &self.resources.len())
+            .finish()
+    }
+}
+
+/// Copies of resources that were submitted by `Defragmenter::step`.
+///
+/// If the pass is dropped without calling `finish`, the resources are not replaced and their
+/// copies are freed.
+#[must_use = "the moved resources are only replaced when calling `finish`"]
+pub struct DefragmentationPass {
+    future: FenceSignalFuture<CommandBufferExecFuture<NowFuture, PrimaryAutoCommandBuffer>>,
+    swaps: Vec<Box<dyn FnOnce() + Send>>,
+    bytes_moved: DeviceSize,
+}
+
+impl DefragmentationPass {
+    /// Returns the total size of the resources that the pass moves.
+    #[inline]
+    pub fn bytes_moved(&self) -> DeviceSize {
+        self.bytes_moved
+    }
+
+    /// Waits for the copies to be finished, then replaces every moved resource in its
+    /// `Relocatable`.
+    pub fn finish(self) -> Result<(), FlushError> {
+        self.future.wait(None)?;
+
+        for swap in self.swaps {
+            swap();
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for DefragmentationPass {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("DefragmentationPass")
+            .field("resources", &self.swaps.len())
+            .field("bytes_moved", &self.bytes_moved)
+            .finish()
+    }
+}
+
+// Type-erased resource registered in a `Defragmenter`.
+trait Registered: Send + Sync {
+    fn is_alive(&self) -> bool;
+
+    // Returns the block of memory of the current version of the resource, if it can be moved.
+    fn block(&self) -> Option<ash::vk::DeviceMemory>;
+
+    // Creates a moved version of the resource and records the copy. Returns the size of the
+    // resource and a function that replaces it, or `None` if moving it wouldn't help.
+    fn relocate(
+        &self,
+        occupancy: &HashMap<ash::vk::DeviceMemory, f64>,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) -> Result<Option<(DeviceSize, Box<dyn FnOnce() + Send>)>, DefragmentationError>;
+}
+
+impl<R> Registered for Weak<Relocatable<R>>
+where
+    R: Relocate,
+{
+    fn is_alive(&self) -> bool {
+        self.strong_count() != 0
+    }
+
+    fn block(&self) -> Option<ash::vk::DeviceMemory> {
+        let resource = self.upgrade()?.current();
+        let block = resource.allocation()?.0.internal_object();
+        Some(block)
+    }
+
+    fn relocate(
+        &self,
+        occupancy: &HashMap<ash::vk::DeviceMemory, f64>,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) -> Result<Option<(DeviceSize, Box<dyn FnOnce() + Send>)>, DefragmentationError> {
+        let relocatable = match self.upgrade() {
+            Some(relocatable) => relocatable,
+            None => return Ok(None),
+        };
+        let source = relocatable.current();
+        let (block, size) = match source.allocation() {
+            Some((memory, size)) => (memory.internal_object(), size),
+            None => return Ok(None),
+        };
+
+        // The pool decides where the copy goes. It is only useful if it ends up in a block
+        // that is fuller than the current one; otherwise the copy is freed right away.
+        let destination = source.duplicate()?;
+        let destination_block = destination
+            .allocation()
+            .map(|(memory, _)| memory.internal_object());
+        match (
+            occupancy.get(&block),
+            destination_block.and_then(|block| occupancy.get(&block)),
+        ) {
+            (Some(from), Some(to)) if to > from => (),
+            _ => return Ok(None),
+        }
+
+        R::copy(source, destination.clone(), builder)?;
+
+        Ok(Some((
+            size,
+            Box::new(move || {
+                *relocatable.current.lock().unwrap() = destination;
+            }),
+        )))
+    }
+}
+
+/// Error that can happen when moving resources.
+#[derive(Clone, Debug)]
+pub enum DefragmentationError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// Allocating the memory of a moved buffer failed.
+    AllocError(DeviceMemoryAllocError),
+    /// Creating a moved image failed.
+    ImageCreationError(ImageCreationError),
+    /// Recording the copy of a buffer failed.
+    CopyBufferError(CopyBufferError),
+    /// Recording the copy of an image failed.
+    CopyImageError(CopyImageError),
+    /// Building the command buffer of the copies failed.
+    BuildError(BuildError),
+    /// Executing the command buffer of the copies failed.
+    CommandBufferExecError(CommandBufferExecError),
+    /// Submitting the command buffer of the copies failed.
+    FlushError(FlushError),
+}
+
+impl error::Error for DefragmentationError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            DefragmentationError::OomError(ref err) => Some(err),
+            DefragmentationError::AllocError(ref err) => Some(err),
+            DefragmentationError::ImageCreationError(ref err) => Some(err),
+            DefragmentationError::CopyBufferError(ref err) => Some(err),
+            DefragmentationError::CopyImageError(ref err) => Some(err),
+            DefragmentationError::BuildError(ref err) => Some(err),
+            DefragmentationError::CommandBufferExecError(ref err) => Some(err),
+            DefragmentationError::FlushError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for DefragmentationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                DefragmentationError::OomError(_) => "not enough memory available",
+                DefragmentationError::AllocError(_) =>
+                    "allocating the memory of a moved buffer failed",
+                DefragmentationError::ImageCreationError(_) => "creating a moved image failed",
+                DefragmentationError::CopyBufferError(_) => "recording the copy of a buffer failed",
+                DefragmentationError::CopyImageError(_) => "recording the copy of an image failed",
+                DefragmentationError::BuildError(_) =>
+                    "building the command buffer of the copies failed",
+                DefragmentationError::CommandBufferExecError(_) =>
+                    "executing the command buffer of the copies failed",
+                DefragmentationError::FlushError(_) =>
+                    "submitting the command buffer of the copies failed",
+            }
+        )
+    }
+}
+
+impl From<OomError> for DefragmentationError {
+    #[inline]
+    fn from(err: OomError) -> DefragmentationError {
+        DefragmentationError::OomError(err)
+    }
+}
+
+impl From<DeviceMemoryAllocError> for DefragmentationError {
+    #[inline]
+    fn from(err: DeviceMemoryAllocError) -> DefragmentationError {
+        DefragmentationError::AllocError(err)
+    }
+}
+
+impl From<ImageCreationError> for DefragmentationError {
+    #[inline]
+    fn from(err: ImageCreationError) -> DefragmentationError {
+        DefragmentationError::ImageCreationError(err)
+    }
+}
+
+impl From<CopyBufferError> for DefragmentationError {
+    #[inline]
+    fn from(err: CopyBufferError) -> DefragmentationError {
+        DefragmentationError::CopyBufferError(err)
+    }
+}
+
+impl From<CopyImageError> for DefragmentationError {
+    #[inline]
+    fn from(err: CopyImageError) -> DefragmentationError {
+        DefragmentationError::CopyImageError(err)
+    }
+}
+
+impl From<BuildError> for DefragmentationError {
+    #[inline]
+    fn from(err: BuildError) -> DefragmentationError {
+        DefragmentationError::BuildError(err)
+    }
+}
+
+impl From<CommandBufferExecError> for DefragmentationError {
+    #[inline]
+    fn from(err: CommandBufferExecError) -> DefragmentationError {
+        DefragmentationError::CommandBufferExecError(err)
+    }
+}
+
+impl From<FlushError> for DefragmentationError {
+    #[inline]
+    fn from(err: FlushError) -> DefragmentationError {
+        DefragmentationError::FlushError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::BufferUsage;
+    use crate::buffer::DeviceLocalBuffer;
+    use crate::memory::defragmentation::Defragmenter;
+    use crate::memory::defragmentation::Relocatable;
+
+    #[test]
+    fn nothing_to_move() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let usage = BufferUsage {
+            transfer_source: true,
+            transfer_destination: true,
+            ..BufferUsage::none()
+        };
+        let buffer = Relocatable::new(
+            DeviceLocalBuffer::<[u32]>::array(device, 256, usage, Some(queue.family())).unwrap(),
+        );
+
+        // The only block is also the fullest one.
+        let mut defragmenter = Defragmenter::new(queue);
+        defragmenter.register(&buffer);
+        assert!(defragmenter.step(1024 * 1024).unwrap().is_none());
+    }
+}
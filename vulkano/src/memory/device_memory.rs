@@ -11,14 +11,23 @@ use crate::check_errors;
 use crate::device::physical::MemoryType;
 use crate::device::Device;
 use crate::device::DeviceOwned;
+use crate::format::Format;
+use crate::format::FormatFeatures;
+use crate::image::view::ComponentMapping;
+use crate::image::view::ComponentSwizzle;
 use crate::memory::Content;
 use crate::memory::DedicatedAlloc;
 use crate::memory::ExternalMemoryHandleType;
+use crate::sampler::ycbcr::ChromaLocation;
+use crate::sampler::ycbcr::SamplerYcbcrModelConversion;
+use crate::sampler::ycbcr::SamplerYcbcrRange;
 use crate::DeviceSize;
 use crate::Error;
 use crate::OomError;
 use crate::Version;
 use crate::VulkanObject;
+use std::cmp;
+use std::convert::TryFrom;
 use std::error;
 use std::fmt;
 #[cfg(any(
@@ -31,6 +40,8 @@ use std::fmt;
 ))]
 use std::fs::File;
 use std::marker::PhantomData;
+#[cfg(target_os = "windows")]
+use std::mem;
 use std::mem::MaybeUninit;
 use std::ops::Deref;
 use std::ops::DerefMut;
@@ -72,6 +83,9 @@ pub unsafe trait ExtendsMemoryAllocateInfo {}
 unsafe impl ExtendsMemoryAllocateInfo for ash::vk::MemoryDedicatedAllocateInfoKHR {}
 unsafe impl ExtendsMemoryAllocateInfo for ash::vk::ExportMemoryAllocateInfo {}
 unsafe impl ExtendsMemoryAllocateInfo for ash::vk::ImportMemoryFdInfoKHR {}
+unsafe impl ExtendsMemoryAllocateInfo for ash::vk::ImportMemoryWin32HandleInfoKHR {}
+unsafe impl ExtendsMemoryAllocateInfo for ash::vk::ImportMemoryHostPointerInfoEXT {}
+unsafe impl ExtendsMemoryAllocateInfo for ash::vk::ImportAndroidHardwareBufferInfoANDROID {}
 
 /// Represents memory that has been allocated.
 ///
@@ -94,6 +108,7 @@ pub struct DeviceMemory {
     size: DeviceSize,
     memory_type_index: u32,
     handle_types: ExternalMemoryHandleType,
+    capture_replay: bool,
     mapped: Mutex<bool>,
 }
 
@@ -116,6 +131,11 @@ pub struct DeviceMemoryBuilder<'a> {
     dedicated_info: Option<ash::vk::MemoryDedicatedAllocateInfoKHR>,
     export_info: Option<ash::vk::ExportMemoryAllocateInfo>,
     import_info: Option<ash::vk::ImportMemoryFdInfoKHR>,
+    import_win32_info: Option<ash::vk::ImportMemoryWin32HandleInfoKHR>,
+    import_host_pointer_info: Option<ash::vk::ImportMemoryHostPointerInfoEXT>,
+    import_android_hardware_buffer_info: Option<ash::vk::ImportAndroidHardwareBufferInfoANDROID>,
+    capture_replay_info: Option<ash::vk::MemoryOpaqueCaptureAddressAllocateInfo>,
+    priority_info: Option<ash::vk::MemoryPriorityAllocateInfoEXT>,
     marker: PhantomData<&'a ()>,
 }
 
@@ -135,6 +155,11 @@ impl<'a> DeviceMemoryBuilder<'a> {
             dedicated_info: None,
             export_info: None,
             import_info: None,
+            import_win32_info: None,
+            import_host_pointer_info: None,
+            import_android_hardware_buffer_info: None,
+            capture_replay_info: None,
+            priority_info: None,
             marker: PhantomData,
         }
     }
@@ -211,7 +236,7 @@ impl<'a> DeviceMemoryBuilder<'a> {
         fd: File,
         handle_types: ExternalMemoryHandleType,
     ) -> DeviceMemoryBuilder<'a> {
-        assert!(self.import_info.is_none());
+        assert!(self.import_info.is_none() && self.import_win32_info.is_none());
 
         let import_info = ash::vk::ImportMemoryFdInfoKHR {
             handle_type: handle_types.into(),
@@ -223,6 +248,153 @@ impl<'a> DeviceMemoryBuilder<'a> {
         self
     }
 
+    /// Sets a Windows handle to import the memory from.
+    ///
+    /// Contrary to file descriptors, importing a handle doesn't transfer its ownership. The
+    /// handle can be closed once the memory is built.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid handle of type `handle_type`, and must stay valid until the
+    ///   memory is built.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the import info has already been set.
+    #[cfg(target_os = "windows")]
+    pub unsafe fn import_win32_info(
+        mut self,
+        handle: *mut c_void,
+        handle_type: ExternalMemoryHandleType,
+    ) -> DeviceMemoryBuilder<'a> {
+        assert!(self.import_info.is_none() && self.import_win32_info.is_none());
+
+        let import_info = ash::vk::ImportMemoryWin32HandleInfoKHR {
+            handle_type: handle_type.into(),
+            handle,
+            ..Default::default()
+        };
+
+        self.import_win32_info = Some(import_info);
+        self
+    }
+
+    /// Sets a host pointer to import the memory from. The memory isn't copied: the device
+    /// accesses the host allocation directly.
+    ///
+    /// `handle_type` must be either `host_allocation` or `host_mapped_foreign_memory`, and the
+    /// memory type must be one of those returned by
+    /// [`host_pointer_memory_type_bits`](DeviceMemory::host_pointer_memory_type_bits).
+    ///
+    /// # Safety
+    ///
+    /// - `pointer` must point to a host allocation of at least the size of the memory, which
+    ///   must outlive the `DeviceMemory`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the import info has already been set.
+    pub unsafe fn import_host_pointer(
+        mut self,
+        pointer: *mut c_void,
+        handle_type: ExternalMemoryHandleType,
+    ) -> DeviceMemoryBuilder<'a> {
+        assert!(self.import_host_pointer_info.is_none());
+
+        let import_info = ash::vk::ImportMemoryHostPointerInfoEXT {
+            handle_type: handle_type.into(),
+            p_host_pointer: pointer,
+            ..Default::default()
+        };
+
+        self.import_host_pointer_info = Some(import_info);
+        self
+    }
+
+    /// Sets an Android hardware buffer to import the memory from. The memory is shared with the
+    /// buffer, which the implementation keeps a reference to for as long as the memory exists.
+    ///
+    /// The allocation size and memory type must be compatible with the values returned by
+    /// [`android_hardware_buffer_properties`](DeviceMemory::android_hardware_buffer_properties).
+    /// If the buffer is bound to an image, the allocation must be dedicated to that image, which
+    /// must have been created with
+    /// [`UnsafeImage::new_with_external_memory`](crate::image::sys::UnsafeImage::new_with_external_memory)
+    /// and the `android_hardware_buffer` handle type.
+    ///
+    /// # Safety
+    ///
+    /// - `buffer` must be a valid pointer to an `AHardwareBuffer`, whose usage includes at least
+    ///   one of the GPU usage flags.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the import info has already been set.
+    pub unsafe fn import_android_hardware_buffer(
+        mut self,
+        buffer: *mut c_void,
+    ) -> DeviceMemoryBuilder<'a> {
+        assert!(
+            self.import_info.is_none()
+                && self.import_win32_info.is_none()
+                && self.import_host_pointer_info.is_none()
+                && self.import_android_hardware_buffer_info.is_none()
+        );
+
+        let import_info = ash::vk::ImportAndroidHardwareBufferInfoANDROID {
+            buffer: buffer as *mut _,
+            ..Default::default()
+        };
+
+        self.import_android_hardware_buffer_info = Some(import_info);
+        self
+    }
+
+    /// Allows the device addresses of the buffers bound to the memory to be captured and replayed
+    /// by tools.
+    ///
+    /// When capturing, `opaque_capture_address` must be 0, and the address to replay with must be
+    /// obtained with [`DeviceMemory::opaque_capture_address`]. When replaying,
+    /// `opaque_capture_address` must be this address.
+    ///
+    /// The `buffer_device_address_capture_replay` feature must be enabled on the device.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the capture replay info has already been set.
+    pub fn capture_replay(mut self, opaque_capture_address: u64) -> DeviceMemoryBuilder<'a> {
+        assert!(self.capture_replay_info.is_none());
+
+        let capture_replay_info = ash::vk::MemoryOpaqueCaptureAddressAllocateInfo {
+            opaque_capture_address,
+            ..Default::default()
+        };
+
+        self.capture_replay_info = Some(capture_replay_info);
+        self
+    }
+
+    /// Sets the priority of the allocation, from 0.0 for the lowest to 1.0 for the highest. The
+    /// default is 0.5. When memory is oversubscribed, the implementation may use the priority to
+    /// decide which allocations to keep in device-local memory.
+    ///
+    /// To maintain portability, this function does nothing when the `memory_priority` feature
+    /// has not been enabled on the device.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the priority has already been set.
+    pub fn priority(mut self, priority: f32) -> DeviceMemoryBuilder<'a> {
+        assert!(self.priority_info.is_none());
+
+        let priority_info = ash::vk::MemoryPriorityAllocateInfoEXT {
+            priority,
+            ..Default::default()
+        };
+
+        self.priority_info = Some(priority_info);
+        self
+    }
+
     /// Creates a `DeviceMemory` object on success, consuming the `DeviceMemoryBuilder`.  An error
     /// is returned if the requested allocation is too large or if the total number of allocations
     /// would exceed per-device limits.
@@ -263,9 +435,26 @@ impl<'a> DeviceMemoryBuilder<'a> {
             return Err(DeviceMemoryAllocError::SpecViolation(1713));
         }
 
+        let heap_index = memory_type.heap().id();
+
+        if let Some(priority_info) = self.priority_info {
+            // VUID-VkMemoryPriorityAllocateInfoEXT-priority-02602: "priority must be between 0
+            // and 1, inclusive".
+            if !(0.0..=1.0).contains(&priority_info.priority) {
+                return Err(DeviceMemoryAllocError::SpecViolation(2602));
+            }
+
+            if !self.device.enabled_features().memory_priority {
+                self.priority_info = None;
+            }
+        }
+
         let mut export_handle_bits = ash::vk::ExternalMemoryHandleTypeFlags::empty();
 
-        if self.export_info.is_some() || self.import_info.is_some() {
+        if self.export_info.is_some()
+            || self.import_info.is_some()
+            || self.import_win32_info.is_some()
+        {
             // TODO: check exportFromImportedHandleTypes
             export_handle_bits = match self.export_info {
                 Some(export_info) => export_info.handle_types,
@@ -316,6 +505,112 @@ impl<'a> DeviceMemoryBuilder<'a> {
             }
         }
 
+        if let Some(import_info) = self.import_host_pointer_info {
+            if !self.device.enabled_extensions().ext_external_memory_host {
+                return Err(DeviceMemoryAllocError::MissingExtension(
+                    "ext_external_memory_host",
+                ));
+            }
+
+            // VUID-VkImportMemoryHostPointerInfoEXT-handleType-01748: "If handleType is not 0, it
+            // must be VK_EXTERNAL_MEMORY_HANDLE_TYPE_HOST_ALLOCATION_BIT_EXT or
+            // VK_EXTERNAL_MEMORY_HANDLE_TYPE_HOST_MAPPED_FOREIGN_MEMORY_BIT_EXT".
+            if import_info.handle_type
+                != ash::vk::ExternalMemoryHandleTypeFlags::HOST_ALLOCATION_EXT
+                && import_info.handle_type
+                    != ash::vk::ExternalMemoryHandleTypeFlags::HOST_MAPPED_FOREIGN_MEMORY_EXT
+            {
+                return Err(DeviceMemoryAllocError::SpecViolation(1748));
+            }
+
+            // The extension being enabled, the property is always present.
+            let alignment = self
+                .device
+                .physical_device()
+                .properties()
+                .min_imported_host_pointer_alignment
+                .unwrap();
+
+            // VUID-VkImportMemoryHostPointerInfoEXT-pHostPointer-01749: "pHostPointer must be a
+            // pointer aligned to an integer multiple of
+            // VkPhysicalDeviceExternalMemoryHostPropertiesEXT::minImportedHostPointerAlignment".
+            if import_info.p_host_pointer as DeviceSize % alignment != 0 {
+                return Err(DeviceMemoryAllocError::SpecViolation(1749));
+            }
+
+            // VUID-VkMemoryAllocateInfo-allocationSize-01745: "allocationSize must be an integer
+            // multiple of VkPhysicalDeviceExternalMemoryHostPropertiesEXT::
+            // minImportedHostPointerAlignment".
+            if self.builder.allocation_size % alignment != 0 {
+                return Err(DeviceMemoryAllocError::SpecViolation(1745));
+            }
+
+            // Host pointers can't be imported into a dedicated allocation.
+            if self.dedicated_info.is_some() {
+                return Err(DeviceMemoryAllocError::ImplicitSpecViolation(
+                    "host pointer imports can't be dedicated allocations",
+                ));
+            }
+        }
+
+        if self.import_android_hardware_buffer_info.is_some()
+            || !(export_handle_bits
+                & ash::vk::ExternalMemoryHandleTypeFlags::ANDROID_HARDWARE_BUFFER_ANDROID)
+                .is_empty()
+        {
+            if !self
+                .device
+                .enabled_extensions()
+                .android_external_memory_android_hardware_buffer
+            {
+                return Err(DeviceMemoryAllocError::MissingExtension(
+                    "android_external_memory_android_hardware_buffer",
+                ));
+            }
+        }
+
+        if self.import_win32_info.is_some() || !(export_handle_bits & WIN32_HANDLE_TYPES).is_empty()
+        {
+            if !self.device.enabled_extensions().khr_external_memory_win32 {
+                return Err(DeviceMemoryAllocError::MissingExtension(
+                    "khr_external_memory_win32",
+                ));
+            }
+        }
+
+        // Buffers created with the `device_address` usage must be bound to memory allocated with
+        // the corresponding flag. Since the buffers that will be bound to the memory aren't known
+        // here, the flag is always set when the feature is enabled.
+        let mut flags_info = if self.device.enabled_features().buffer_device_address
+            && (self.device.api_version() >= Version::V1_2
+                || self.device.enabled_extensions().khr_buffer_device_address)
+            && (self.device.api_version() >= Version::V1_1
+                || self.device.enabled_extensions().khr_device_group)
+        {
+            Some(ash::vk::MemoryAllocateFlagsInfo {
+                flags: ash::vk::MemoryAllocateFlags::DEVICE_ADDRESS,
+                ..Default::default()
+            })
+        } else {
+            None
+        };
+
+        if self.capture_replay_info.is_some() {
+            // VUID-VkMemoryAllocateInfo-flags-03330: "If VkMemoryAllocateFlagsInfo::flags
+            // includes VK_MEMORY_ALLOCATE_DEVICE_ADDRESS_CAPTURE_REPLAY_BIT, the
+            // bufferDeviceAddressCaptureReplay feature must be enabled".
+            if !self
+                .device
+                .enabled_features()
+                .buffer_device_address_capture_replay
+            {
+                return Err(DeviceMemoryAllocError::SpecViolation(3330));
+            }
+
+            flags_info.get_or_insert_with(Default::default).flags |=
+                ash::vk::MemoryAllocateFlags::DEVICE_ADDRESS_CAPTURE_REPLAY;
+        }
+
         let mut builder = self.builder;
         let size = builder.allocation_size;
         let memory_type_index = builder.memory_type_index;
@@ -332,6 +627,30 @@ impl<'a> DeviceMemoryBuilder<'a> {
             builder = builder.push_next(info);
         }
 
+        if let Some(info) = self.import_win32_info.as_mut() {
+            builder = builder.push_next(info);
+        }
+
+        if let Some(info) = self.import_host_pointer_info.as_mut() {
+            builder = builder.push_next(info);
+        }
+
+        if let Some(info) = self.import_android_hardware_buffer_info.as_mut() {
+            builder = builder.push_next(info);
+        }
+
+        if let Some(info) = flags_info.as_mut() {
+            builder = builder.push_next(info);
+        }
+
+        if let Some(info) = self.capture_replay_info.as_mut() {
+            builder = builder.push_next(info);
+        }
+
+        if let Some(info) = self.priority_info.as_mut() {
+            builder = builder.push_next(info);
+        }
+
         let memory = unsafe {
             let physical_device = self.device.physical_device();
             let mut allocation_count = self
@@ -357,14 +676,18 @@ impl<'a> DeviceMemoryBuilder<'a> {
             output.assume_init()
         };
 
-        Ok(Arc::new(DeviceMemory {
+        let memory = Arc::new(DeviceMemory {
             memory,
             device: self.device,
             size,
             memory_type_index,
             handle_types: ExternalMemoryHandleType::from(export_handle_bits),
+            capture_replay: self.capture_replay_info.is_some(),
             mapped: Mutex::new(false),
-        }))
+        });
+
+        memory.device.check_memory_budget(heap_index);
+        Ok(memory)
     }
 }
 
@@ -594,6 +917,33 @@ impl DeviceMemory {
         self.size
     }
 
+    /// Returns the opaque address that the memory must be allocated with to replay the device
+    /// addresses of the buffers bound to it, or `None` if it wasn't allocated with
+    /// [`DeviceMemoryBuilder::capture_replay`].
+    pub fn opaque_capture_address(&self) -> Option<u64> {
+        if !self.capture_replay {
+            return None;
+        }
+
+        unsafe {
+            let info = ash::vk::DeviceMemoryOpaqueCaptureAddressInfo {
+                memory: self.memory,
+                ..Default::default()
+            };
+            let fns = self.device.fns();
+            Some(if self.device.api_version() >= Version::V1_2 {
+                fns.v1_2
+                    .get_device_memory_opaque_capture_address(self.device.internal_object(), &info)
+            } else {
+                fns.khr_buffer_device_address
+                    .get_device_memory_opaque_capture_address_khr(
+                        self.device.internal_object(),
+                        &info,
+                    )
+            })
+        }
+    }
+
     /// Exports the device memory into a Unix file descriptor.  The caller retains ownership of the
     /// file, as per the Vulkan spec.
     ///
@@ -650,6 +1000,253 @@ impl DeviceMemory {
         let file = unsafe { File::from_raw_fd(fd) };
         Ok(file)
     }
+
+    /// Returns the memory types that the host allocation at `pointer` can be imported into, as a
+    /// bit mask of memory type indices.
+    ///
+    /// Requires the `ext_external_memory_host` extension. `pointer` must be aligned to the
+    /// `min_imported_host_pointer_alignment` property of the physical device.
+    pub fn host_pointer_memory_type_bits(
+        device: &Arc<Device>,
+        pointer: *const c_void,
+        handle_type: ExternalMemoryHandleType,
+    ) -> Result<u32, DeviceMemoryAllocError> {
+        if !device.enabled_extensions().ext_external_memory_host {
+            return Err(DeviceMemoryAllocError::MissingExtension(
+                "ext_external_memory_host",
+            ));
+        }
+
+        let alignment = device
+            .physical_device()
+            .properties()
+            .min_imported_host_pointer_alignment
+            .unwrap();
+        if pointer as DeviceSize % alignment != 0 {
+            return Err(DeviceMemoryAllocError::SpecViolation(1749));
+        }
+
+        let fns = device.fns();
+        let mut properties = ash::vk::MemoryHostPointerPropertiesEXT::default();
+        unsafe {
+            check_errors(
+                fns.ext_external_memory_host
+                    .get_memory_host_pointer_properties_ext(
+                        device.internal_object(),
+                        handle_type.into(),
+                        pointer,
+                        &mut properties,
+                    ),
+            )?;
+        }
+
+        Ok(properties.memory_type_bits)
+    }
+
+    /// Returns the properties of an Android hardware buffer: the size and memory types that its
+    /// memory can be imported with, and the format that images bound to it must be created with.
+    ///
+    /// Requires the `android_external_memory_android_hardware_buffer` extension.
+    ///
+    /// # Safety
+    ///
+    /// - `buffer` must be a valid pointer to an `AHardwareBuffer`.
+    pub unsafe fn android_hardware_buffer_properties(
+        device: &Arc<Device>,
+        buffer: *const c_void,
+    ) -> Result<AndroidHardwareBufferProperties, DeviceMemoryAllocError> {
+        if !device
+            .enabled_extensions()
+            .android_external_memory_android_hardware_buffer
+        {
+            return Err(DeviceMemoryAllocError::MissingExtension(
+                "android_external_memory_android_hardware_buffer",
+            ));
+        }
+
+        let mut format_properties =
+            ash::vk::AndroidHardwareBufferFormatPropertiesANDROID::default();
+        let mut properties = ash::vk::AndroidHardwareBufferPropertiesANDROID {
+            p_next: &mut format_properties as *mut _ as *mut _,
+            ..Default::default()
+        };
+
+        let fns = device.fns();
+        check_errors(
+            fns.android_external_memory_android_hardware_buffer
+                .get_android_hardware_buffer_properties_android(
+                    device.internal_object(),
+                    buffer as *const _,
+                    &mut properties,
+                ),
+        )?;
+
+        Ok(AndroidHardwareBufferProperties {
+            allocation_size: properties.allocation_size,
+            memory_type_bits: properties.memory_type_bits,
+            format: Format::try_from(format_properties.format).ok(),
+            external_format: format_properties.external_format,
+            format_features: format_properties.format_features.into(),
+            suggested_component_mapping: ComponentMapping {
+                r: component_swizzle(format_properties.sampler_ycbcr_conversion_components.r),
+                g: component_swizzle(format_properties.sampler_ycbcr_conversion_components.g),
+                b: component_swizzle(format_properties.sampler_ycbcr_conversion_components.b),
+                a: component_swizzle(format_properties.sampler_ycbcr_conversion_components.a),
+            },
+            suggested_ycbcr_model: match format_properties.suggested_ycbcr_model {
+                ash::vk::SamplerYcbcrModelConversion::YCBCR_IDENTITY => {
+                    SamplerYcbcrModelConversion::YcbcrIdentity
+                }
+                ash::vk::SamplerYcbcrModelConversion::YCBCR_709 => {
+                    SamplerYcbcrModelConversion::Ycbcr709
+                }
+                ash::vk::SamplerYcbcrModelConversion::YCBCR_601 => {
+                    SamplerYcbcrModelConversion::Ycbcr601
+                }
+                ash::vk::SamplerYcbcrModelConversion::YCBCR_2020 => {
+                    SamplerYcbcrModelConversion::Ycbcr2020
+                }
+                _ => SamplerYcbcrModelConversion::RgbIdentity,
+            },
+            suggested_ycbcr_range: match format_properties.suggested_ycbcr_range {
+                ash::vk::SamplerYcbcrRange::ITU_NARROW => SamplerYcbcrRange::ItuNarrow,
+                _ => SamplerYcbcrRange::ItuFull,
+            },
+            suggested_chroma_offset: [
+                chroma_location(format_properties.suggested_x_chroma_offset),
+                chroma_location(format_properties.suggested_y_chroma_offset),
+            ],
+        })
+    }
+
+    /// Exports the device memory into a Windows handle.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the user requests an invalid handle type for this device memory object.
+    #[inline]
+    #[cfg(target_os = "windows")]
+    pub fn export_win32_handle(
+        &self,
+        handle_type: ExternalMemoryHandleType,
+    ) -> Result<Win32Handle, DeviceMemoryAllocError> {
+        let fns = self.device.fns();
+
+        // VUID-VkMemoryGetWin32HandleInfoKHR-handleType-00663: "handleType must have been
+        // included in VkExportMemoryAllocateInfo::handleTypes when memory was created".
+        let bits = ash::vk::ExternalMemoryHandleTypeFlags::from(handle_type);
+        let self_bits = ash::vk::ExternalMemoryHandleTypeFlags::from(self.handle_types);
+        if bits.is_empty() || (bits & self_bits) != bits {
+            return Err(DeviceMemoryAllocError::SpecViolation(663));
+        }
+
+        // VUID-VkMemoryGetWin32HandleInfoKHR-handleType-00664: "handleType must be defined as an
+        // NT handle or a global share handle".
+        if bits.as_raw().count_ones() != 1 || (bits & WIN32_HANDLE_TYPES).is_empty() {
+            return Err(DeviceMemoryAllocError::SpecViolation(664));
+        }
+
+        let handle = unsafe {
+            let info = ash::vk::MemoryGetWin32HandleInfoKHR {
+                memory: self.memory,
+                handle_type: bits,
+                ..Default::default()
+            };
+
+            let mut output = MaybeUninit::uninit();
+            check_errors(fns.khr_external_memory_win32.get_memory_win32_handle_khr(
+                self.device.internal_object(),
+                &info,
+                output.as_mut_ptr(),
+            ))?;
+            output.assume_init()
+        };
+
+        // Global share handles (the `kmt` types) are not reference-counted and must not be closed.
+        let owned = (bits & WIN32_KMT_HANDLE_TYPES).is_empty();
+        Ok(unsafe { Win32Handle::from_raw(handle, owned) })
+    }
+}
+
+// Handle types that are Windows handles.
+const WIN32_HANDLE_TYPES: ash::vk::ExternalMemoryHandleTypeFlags =
+    ash::vk::ExternalMemoryHandleTypeFlags::from_raw(
+        ash::vk::ExternalMemoryHandleTypeFlags::OPAQUE_WIN32.as_raw()
+            | ash::vk::ExternalMemoryHandleTypeFlags::OPAQUE_WIN32_KMT.as_raw()
+            | ash::vk::ExternalMemoryHandleTypeFlags::D3D11_TEXTURE.as_raw()
+            | ash::vk::ExternalMemoryHandleTypeFlags::D3D11_TEXTURE_KMT.as_raw()
+            | ash::vk::ExternalMemoryHandleTypeFlags::D3D12_HEAP.as_raw()
+            | ash::vk::ExternalMemoryHandleTypeFlags::D3D12_RESOURCE.as_raw(),
+    );
+
+// Handle types that are global share handles rather than NT handles.
+#[cfg(target_os = "windows")]
+const WIN32_KMT_HANDLE_TYPES: ash::vk::ExternalMemoryHandleTypeFlags =
+    ash::vk::ExternalMemoryHandleTypeFlags::from_raw(
+        ash::vk::ExternalMemoryHandleTypeFlags::OPAQUE_WIN32_KMT.as_raw()
+            | ash::vk::ExternalMemoryHandleTypeFlags::D3D11_TEXTURE_KMT.as_raw(),
+    );
+
+/// A Windows handle exported from a `DeviceMemory`.
+///
+/// NT handles are closed when this object is dropped, unless `into_raw` is called. Global share
+/// handles are never closed, as they are not reference-counted.
+#[cfg(target_os = "windows")]
+#[derive(Debug)]
+pub struct Win32Handle {
+    handle: *mut c_void,
+    owned: bool,
+}
+
+#[cfg(target_os = "windows")]
+impl Win32Handle {
+    /// Wraps a raw handle. If `owned` is true, the handle is closed on drop.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid handle, and if `owned` is true it must not be closed by
+    ///   anything else.
+    ///
+    #[inline]
+    pub unsafe fn from_raw(handle: *mut c_void, owned: bool) -> Win32Handle {
+        Win32Handle { handle, owned }
+    }
+
+    /// Returns the raw handle, which stays owned by this object.
+    #[inline]
+    pub fn as_raw(&self) -> *mut c_void {
+        self.handle
+    }
+
+    /// Returns the raw handle. The caller becomes responsible for closing it.
+    #[inline]
+    pub fn into_raw(self) -> *mut c_void {
+        let handle = self.handle;
+        mem::forget(self);
+        handle
+    }
+}
+
+#[cfg(target_os = "windows")]
+unsafe impl Send for Win32Handle {}
+#[cfg(target_os = "windows")]
+unsafe impl Sync for Win32Handle {}
+
+#[cfg(target_os = "windows")]
+impl Drop for Win32Handle {
+    #[inline]
+    fn drop(&mut self) {
+        #[link(name = "kernel32")]
+        extern "system" {
+            fn CloseHandle(handle: *mut c_void) -> i32;
+        }
+
+        if self.owned {
+            unsafe {
+                CloseHandle(self.handle);
+            }
+        }
+    }
 }
 
 unsafe impl DeviceOwned for DeviceMemory {
@@ -659,6 +1256,61 @@ unsafe impl DeviceOwned for DeviceMemory {
     }
 }
 
+/// Properties of an Android hardware buffer, as returned by
+/// [`DeviceMemory::android_hardware_buffer_properties`].
+#[derive(Clone, Copy, Debug)]
+pub struct AndroidHardwareBufferProperties {
+    /// The size that the imported memory must be allocated with.
+    pub allocation_size: DeviceSize,
+
+    /// The memory types that the buffer can be imported into, as a bit mask of memory type
+    /// indices.
+    pub memory_type_bits: u32,
+
+    /// The Vulkan format that corresponds to the format of the buffer, or `None` if the format
+    /// has no Vulkan equivalent and can only be used through `external_format`.
+    pub format: Option<Format>,
+
+    /// An implementation-defined identifier of the format of the buffer, that can be used to
+    /// create a sampler YCbCr conversion when `format` is `None`.
+    pub external_format: u64,
+
+    /// The features that are supported for images bound to the buffer.
+    pub format_features: FormatFeatures,
+
+    /// The component mapping that sampler YCbCr conversions of the buffer should use.
+    pub suggested_component_mapping: ComponentMapping,
+
+    /// The conversion model that sampler YCbCr conversions of the buffer should use.
+    pub suggested_ycbcr_model: SamplerYcbcrModelConversion,
+
+    /// The range that sampler YCbCr conversions of the buffer should use.
+    pub suggested_ycbcr_range: SamplerYcbcrRange,
+
+    /// The chroma offsets that sampler YCbCr conversions of the buffer should use, for the x and
+    /// y coordinates.
+    pub suggested_chroma_offset: [ChromaLocation; 2],
+}
+
+fn component_swizzle(val: ash::vk::ComponentSwizzle) -> ComponentSwizzle {
+    match val {
+        ash::vk::ComponentSwizzle::ZERO => ComponentSwizzle::Zero,
+        ash::vk::ComponentSwizzle::ONE => ComponentSwizzle::One,
+        ash::vk::ComponentSwizzle::R => ComponentSwizzle::Red,
+        ash::vk::ComponentSwizzle::G => ComponentSwizzle::Green,
+        ash::vk::ComponentSwizzle::B => ComponentSwizzle::Blue,
+        ash::vk::ComponentSwizzle::A => ComponentSwizzle::Alpha,
+        _ => ComponentSwizzle::Identity,
+    }
+}
+
+fn chroma_location(val: ash::vk::ChromaLocation) -> ChromaLocation {
+    match val {
+        ash::vk::ChromaLocation::MIDPOINT => ChromaLocation::Midpoint,
+        _ => ChromaLocation::CositedEven,
+    }
+}
+
 impl fmt::Debug for DeviceMemory {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.debug_struct("DeviceMemory")
@@ -750,12 +1402,55 @@ impl MappedDeviceMemory {
         self.memory
     }
 
+    /// Returns true if the memory type is host-coherent.
+    ///
+    /// If it isn't, writes from the CPU must be flushed before the GPU can see them, and writes
+    /// from the GPU must be invalidated before the CPU can see them. `read`, `read_write` and
+    /// `write` do this automatically.
+    #[inline]
+    pub fn is_coherent(&self) -> bool {
+        self.coherent
+    }
+
+    /// Gives read-only access to the content of the memory.
+    ///
+    /// This function takes care of calling `vkInvalidateMappedMemoryRanges` on the given range,
+    /// extended to the `non_coherent_atom_size` of the device. Contrary to `read_write`, nothing
+    /// is flushed when the returned object is dropped.
+    ///
+    /// # Safety
+    ///
+    /// - Type safety is not checked. You must ensure that `T` corresponds to the content of the
+    ///   buffer.
+    /// - Accesses are not synchronized. Synchronization must be handled outside of
+    ///   the `MappedDeviceMemory`.
+    ///
+    #[inline]
+    pub unsafe fn read<T: ?Sized>(&self, range: Range<DeviceSize>) -> CpuReadAccess<T>
+    where
+        T: Content,
+    {
+        let pointer = T::ref_from_ptr(
+            (self.pointer as usize + range.start as usize) as *mut _,
+            (range.end - range.start) as usize,
+        )
+        .unwrap(); // TODO: error
+
+        // TODO: return result instead?
+        self.invalidate_range(range).unwrap();
+
+        CpuReadAccess {
+            pointer: pointer,
+            marker: PhantomData,
+        }
+    }
+
     /// Gives access to the content of the memory.
     ///
     /// This function takes care of calling `vkInvalidateMappedMemoryRanges` and
-    /// `vkFlushMappedMemoryRanges` on the given range. You are therefore encouraged to use the
-    /// smallest range as possible, and to not call this function multiple times in a row for
-    /// several small changes.
+    /// `vkFlushMappedMemoryRanges` on the given range, extended to the `non_coherent_atom_size`
+    /// of the device. You are therefore encouraged to use the smallest range as possible, and to
+    /// not call this function multiple times in a row for several small changes.
     ///
     /// # Safety
     ///
@@ -769,29 +1464,49 @@ impl MappedDeviceMemory {
     where
         T: Content,
     {
-        let fns = self.memory.device().fns();
         let pointer = T::ref_from_ptr(
             (self.pointer as usize + range.start as usize) as *mut _,
             (range.end - range.start) as usize,
         )
         .unwrap(); // TODO: error
 
-        if !self.coherent {
-            let range = ash::vk::MappedMemoryRange {
-                memory: self.memory.internal_object(),
-                offset: range.start,
-                size: range.end - range.start,
-                ..Default::default()
-            };
+        // TODO: return result instead?
+        self.invalidate_range(range.clone()).unwrap();
 
-            // TODO: return result instead?
-            check_errors(fns.v1_0.invalidate_mapped_memory_ranges(
-                self.memory.device().internal_object(),
-                1,
-                &range,
-            ))
-            .unwrap();
+        CpuAccess {
+            pointer: pointer,
+            mem: self,
+            coherent: self.coherent,
+            range,
         }
+    }
+
+    /// Gives write access to the content of the memory, for memory that the GPU doesn't write to.
+    ///
+    /// Contrary to `read_write`, this function doesn't call `vkInvalidateMappedMemoryRanges`,
+    /// which would discard the CPU writes to the rest of the atoms of the range that haven't
+    /// been flushed yet. `vkFlushMappedMemoryRanges` is called on the given range, extended to
+    /// the `non_coherent_atom_size` of the device, when the returned object is dropped.
+    ///
+    /// # Safety
+    ///
+    /// - Type safety is not checked. You must ensure that `T` corresponds to the content of the
+    ///   buffer.
+    /// - Accesses are not synchronized. Synchronization must be handled outside of
+    ///   the `MappedDeviceMemory`.
+    /// - The GPU must not have written to the range since it was last read by the CPU, otherwise
+    ///   reading the content returns stale data.
+    ///
+    #[inline]
+    pub unsafe fn write<T: ?Sized>(&self, range: Range<DeviceSize>) -> CpuAccess<T>
+    where
+        T: Content,
+    {
+        let pointer = T::ref_from_ptr(
+            (self.pointer as usize + range.start as usize) as *mut _,
+            (range.end - range.start) as usize,
+        )
+        .unwrap(); // TODO: error
 
         CpuAccess {
             pointer: pointer,
@@ -800,6 +1515,90 @@ impl MappedDeviceMemory {
             range,
         }
     }
+
+    /// Makes the writes of the GPU to `range` visible to the CPU. Does nothing if the memory is
+    /// host-coherent.
+    ///
+    /// The range is extended to the `non_coherent_atom_size` of the device.
+    ///
+    /// # Safety
+    ///
+    /// - The GPU must not be writing to the range.
+    /// - The CPU writes to the range, and to the rest of its atoms, that weren't flushed are
+    ///   lost.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `range` is out of the memory.
+    ///
+    pub unsafe fn invalidate_range(&self, range: Range<DeviceSize>) -> Result<(), OomError> {
+        if self.coherent {
+            return Ok(());
+        }
+
+        let range = self.atom_range(range);
+        let fns = self.memory.device().fns();
+        check_errors(fns.v1_0.invalidate_mapped_memory_ranges(
+            self.memory.device().internal_object(),
+            1,
+            &range,
+        ))?;
+        Ok(())
+    }
+
+    /// Makes the writes of the CPU to `range` visible to the GPU. Does nothing if the memory is
+    /// host-coherent.
+    ///
+    /// The range is extended to the `non_coherent_atom_size` of the device.
+    ///
+    /// # Safety
+    ///
+    /// - The GPU must not be accessing the range, including the rest of its atoms.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `range` is out of the memory.
+    ///
+    pub unsafe fn flush_range(&self, range: Range<DeviceSize>) -> Result<(), OomError> {
+        if self.coherent {
+            return Ok(());
+        }
+
+        let range = self.atom_range(range);
+        let fns = self.memory.device().fns();
+        check_errors(fns.v1_0.flush_mapped_memory_ranges(
+            self.memory.device().internal_object(),
+            1,
+            &range,
+        ))?;
+        Ok(())
+    }
+
+    // Extends `range` to multiples of `non_coherent_atom_size`, as required by
+    // `vkFlushMappedMemoryRanges` and `vkInvalidateMappedMemoryRanges`. The end is clamped to the
+    // size of the memory, which is also allowed.
+    fn atom_range(&self, range: Range<DeviceSize>) -> ash::vk::MappedMemoryRange {
+        assert!(range.start <= range.end && range.end <= self.memory.size());
+
+        let atom_size = self
+            .memory
+            .device()
+            .physical_device()
+            .properties()
+            .non_coherent_atom_size;
+        let start = range.start / atom_size * atom_size;
+        let end = cmp::min(
+            (range.end + atom_size - 1) / atom_size * atom_size,
+            self.memory.size(),
+        );
+
+        ash::vk::MappedMemoryRange {
+            memory: self.memory.internal_object(),
+            offset: start,
+            size: end - start,
+            ..Default::default()
+        }
+    }
 }
 
 impl AsRef<DeviceMemory> for MappedDeviceMemory {
@@ -958,6 +1757,43 @@ impl Drop for DeviceMemoryMapping {
     }
 }
 
+/// Object that can be used to read the content of a `MappedDeviceMemory`.
+///
+/// This object derefs to the content, just like a `MutexGuard` for example.
+pub struct CpuReadAccess<'a, T: ?Sized + 'a> {
+    pointer: *mut T,
+    marker: PhantomData<&'a MappedDeviceMemory>,
+}
+
+impl<'a, T: ?Sized + 'a> CpuReadAccess<'a, T> {
+    /// Builds a new `CpuReadAccess` to access a sub-part of the current `CpuReadAccess`.
+    ///
+    /// This function is unstable. Don't use it directly.
+    #[doc(hidden)]
+    #[inline]
+    pub fn map<U: ?Sized + 'a, F>(self, f: F) -> CpuReadAccess<'a, U>
+    where
+        F: FnOnce(*mut T) -> *mut U,
+    {
+        CpuReadAccess {
+            pointer: f(self.pointer),
+            marker: PhantomData,
+        }
+    }
+}
+
+unsafe impl<'a, T: ?Sized + 'a> Send for CpuReadAccess<'a, T> {}
+unsafe impl<'a, T: ?Sized + 'a> Sync for CpuReadAccess<'a, T> {}
+
+impl<'a, T: ?Sized + 'a> Deref for CpuReadAccess<'a, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        unsafe { &*self.pointer }
+    }
+}
+
 /// Object that can be used to read or write the content of a `MappedDeviceMemory`.
 ///
 /// This object derefs to the content, just like a `MutexGuard` for example.
@@ -1013,22 +1849,8 @@ impl<'a, T: ?Sized + 'a> Drop for CpuAccess<'a, T> {
     fn drop(&mut self) {
         // If the memory doesn't have the `coherent` flag, we need to flush the data.
         if !self.coherent {
-            let fns = self.mem.as_ref().device().fns();
-
-            let range = ash::vk::MappedMemoryRange {
-                memory: self.mem.as_ref().internal_object(),
-                offset: self.range.start,
-                size: self.range.end - self.range.start,
-                ..Default::default()
-            };
-
             unsafe {
-                check_errors(fns.v1_0.flush_mapped_memory_ranges(
-                    self.mem.as_ref().device().internal_object(),
-                    1,
-                    &range,
-                ))
-                .unwrap();
+                self.mem.flush_range(self.range.clone()).unwrap();
             }
         }
     }
@@ -1055,6 +1877,8 @@ pub enum DeviceMemoryAllocError {
     MissingExtension(&'static str),
     /// Invalid Size
     InvalidSize,
+    /// The imported handle is not a valid handle of the requested type.
+    InvalidExternalHandle,
 }
 
 impl error::Error for DeviceMemoryAllocError {
@@ -1090,6 +1914,9 @@ impl fmt::Display for DeviceMemoryAllocError {
                 write!(fmt, "Implicit spec violation failed {}", e)
             }
             DeviceMemoryAllocError::InvalidSize => write!(fmt, "invalid size"),
+            DeviceMemoryAllocError::InvalidExternalHandle => {
+                write!(fmt, "the imported handle is not valid")
+            }
         }
     }
 }
@@ -1103,6 +1930,7 @@ impl From<Error> for DeviceMemoryAllocError {
             }
             Error::TooManyObjects => DeviceMemoryAllocError::TooManyObjects,
             Error::MemoryMapFailed => DeviceMemoryAllocError::MemoryMapFailed,
+            Error::InvalidExternalHandle => DeviceMemoryAllocError::InvalidExternalHandle,
             _ => panic!("unexpected error: {:?}", err),
         }
     }
@@ -1119,6 +1947,8 @@ impl From<OomError> for DeviceMemoryAllocError {
 mod tests {
     use crate::memory::DeviceMemory;
     use crate::memory::DeviceMemoryAllocError;
+    use crate::memory::DeviceMemoryBuilder;
+    use crate::memory::ExternalMemoryHandleType;
     use crate::OomError;
 
     #[test]
@@ -1137,6 +1967,74 @@ mod tests {
         });
     }
 
+    #[test]
+    fn priority_out_of_range() {
+        let (device, _) = gfx_dev_and_queue!();
+        let mem_ty = device.physical_device().memory_types().next().unwrap();
+
+        match DeviceMemoryBuilder::new(device, mem_ty.id(), 256)
+            .priority(2.0)
+            .build()
+        {
+            Err(DeviceMemoryAllocError::SpecViolation(2602)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn export_missing_extension() {
+        let (device, _) = gfx_dev_and_queue!();
+        let mem_ty = device.physical_device().memory_types().next().unwrap();
+
+        match DeviceMemoryBuilder::new(device, mem_ty.id(), 256)
+            .export_info(ExternalMemoryHandleType {
+                dma_buf: true,
+                ..ExternalMemoryHandleType::none()
+            })
+            .build()
+        {
+            Err(DeviceMemoryAllocError::MissingExtension(_)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn host_pointer_missing_extension() {
+        let (device, _) = gfx_dev_and_queue!();
+        let data = vec![0u8; 4096];
+
+        match DeviceMemory::host_pointer_memory_type_bits(
+            &device,
+            data.as_ptr() as *const _,
+            ExternalMemoryHandleType {
+                host_allocation: true,
+                ..ExternalMemoryHandleType::none()
+            },
+        ) {
+            Err(DeviceMemoryAllocError::MissingExtension("ext_external_memory_host")) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn read_after_write() {
+        let (device, _) = gfx_dev_and_queue!();
+        let mem_ty = device
+            .physical_device()
+            .memory_types()
+            .filter(|t| t.is_host_visible())
+            .next()
+            .unwrap();
+        let memory = DeviceMemory::alloc_and_map(device, mem_ty, 1024).unwrap();
+        assert_eq!(memory.is_coherent(), mem_ty.is_host_coherent());
+
+        unsafe {
+            // Ranges that aren't aligned to the atom size must be accepted.
+            memory.read_write::<[u8]>(3..17).copy_from_slice(&[7; 14]);
+            assert_eq!(&*memory.read::<[u8]>(3..17), &[7; 14]);
+        }
+    }
+
     #[test]
     #[cfg(target_pointer_width = "64")]
     fn oom_single() {
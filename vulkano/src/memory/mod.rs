@@ -82,8 +82,15 @@
 //!
 //! A memory pool is any object that implements the `MemoryPool` trait. You can implement that
 //! trait on your own structure and then use it when you create buffers and images so that they
-//! get memory from that pool. By default if you don't specify any pool when creating a buffer or
-//! an image, an instance of `StdMemoryPool` that is shared by the `Device` object is used.
+//! get memory from that pool, by calling the constructors whose name ends with `with_pool`, such
+//! as `DeviceLocalBuffer::raw_with_pool` or `StorageImage::with_pool`. By default if you don't
+//! specify any pool when creating a buffer or an image, an instance of `StdMemoryPool` that is
+//! shared by the `Device` object is used.
+//!
+//! `StdMemoryPool` sub-allocates resources from large blocks of memory, with an allocator whose
+//! cost doesn't depend on the number of allocations. Resources that the implementation prefers
+//! to have a dedicated allocation for, and resources that are large compared to the blocks, get
+//! their own allocation instead.
 
 use std::mem;
 use std::os::raw::c_void;
@@ -92,16 +99,21 @@ use std::slice;
 use crate::buffer::sys::UnsafeBuffer;
 use crate::image::sys::UnsafeImage;
 
+pub use self::device_memory::AndroidHardwareBufferProperties;
 pub use self::device_memory::CpuAccess;
+pub use self::device_memory::CpuReadAccess;
 pub use self::device_memory::DeviceMemory;
 pub use self::device_memory::DeviceMemoryAllocError;
 pub use self::device_memory::DeviceMemoryBuilder;
 pub use self::device_memory::DeviceMemoryMapping;
 pub use self::device_memory::MappedDeviceMemory;
+#[cfg(target_os = "windows")]
+pub use self::device_memory::Win32Handle;
 pub use self::external_memory_handle_type::ExternalMemoryHandleType;
 pub use self::pool::MemoryPool;
 use crate::DeviceSize;
 
+pub mod defragmentation;
 mod device_memory;
 mod external_memory_handle_type;
 pub mod pool;
@@ -10,12 +10,18 @@
 use crate::device::physical::MemoryType;
 use crate::device::Device;
 use crate::instance::Instance;
+use crate::memory::pool::next_block_size;
+use crate::memory::pool::statistics::Backtraces;
+use crate::memory::pool::statistics::BlockSnapshot;
+use crate::memory::pool::statistics::RangeSnapshot;
+use crate::memory::pool::tlsf::Tlsf;
+use crate::memory::pool::tlsf::TlsfAlloc;
 use crate::memory::DeviceMemory;
 use crate::memory::DeviceMemoryAllocError;
 use crate::memory::MappedDeviceMemory;
 use crate::DeviceSize;
+use crate::VulkanObject;
 use std::cmp;
-use std::ops::Range;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -24,8 +30,9 @@ use std::sync::Mutex;
 pub struct StdHostVisibleMemoryTypePool {
     device: Arc<Device>,
     memory_type: u32,
-    // TODO: obviously very inefficient
-    occupied: Mutex<Vec<(Arc<MappedDeviceMemory>, Vec<Range<DeviceSize>>)>>,
+    // Blocks of memory, and the sub-allocator of each block.
+    blocks: Mutex<Vec<(Arc<MappedDeviceMemory>, Tlsf)>>,
+    backtraces: Backtraces,
 }
 
 impl StdHostVisibleMemoryTypePool {
@@ -51,7 +58,8 @@ impl StdHostVisibleMemoryTypePool {
         Arc::new(StdHostVisibleMemoryTypePool {
             device: device.clone(),
             memory_type: memory_type.id(),
-            occupied: Mutex::new(Vec::new()),
+            blocks: Mutex::new(Vec::new()),
+            backtraces: Backtraces::default(),
         })
     }
 
@@ -60,7 +68,7 @@ impl StdHostVisibleMemoryTypePool {
     /// # Panic
     ///
     /// - Panics if `size` is 0.
-    /// - Panics if `alignment` is 0.
+    /// - Panics if `alignment` is not a power of two.
     ///
     pub fn alloc(
         me: &Arc<Self>,
@@ -68,61 +76,59 @@ impl StdHostVisibleMemoryTypePool {
         alignment: DeviceSize,
     ) -> Result<StdHostVisibleMemoryTypePoolAlloc, DeviceMemoryAllocError> {
         assert!(size != 0);
-        assert!(alignment != 0);
+        assert!(alignment.is_power_of_two());
+
+        // Ranges of non-coherent memory are flushed and invalidated in multiples of the atom
+        // size, so the allocations must not share an atom.
+        let (alignment, size) = if me.memory_type().is_host_coherent() {
+            (alignment, size)
+        } else {
+            let atom_size = me
+                .device
+                .physical_device()
+                .properties()
+                .non_coherent_atom_size;
+            (
+                cmp::max(alignment, atom_size),
+                (size + atom_size - 1) / atom_size * atom_size,
+            )
+        };
 
-        #[inline]
-        fn align(val: DeviceSize, al: DeviceSize) -> DeviceSize {
-            al * (1 + (val - 1) / al)
-        }
+        let mut blocks = me.blocks.lock().unwrap();
 
-        // Find a location.
-        let mut occupied = me.occupied.lock().unwrap();
-
-        // Try finding an entry in already-allocated chunks.
-        for &mut (ref dev_mem, ref mut entries) in occupied.iter_mut() {
-            // Try find some free space in-between two entries.
-            for i in 0..entries.len().saturating_sub(1) {
-                let entry1 = entries[i].clone();
-                let entry1_end = align(entry1.end, alignment);
-                let entry2 = entries[i + 1].clone();
-                if entry1_end + size <= entry2.start {
-                    entries.insert(i + 1, entry1_end..entry1_end + size);
-                    return Ok(StdHostVisibleMemoryTypePoolAlloc {
-                        pool: me.clone(),
-                        memory: dev_mem.clone(),
-                        offset: entry1_end,
-                        size: size,
-                    });
-                }
-            }
-
-            // Try append at the end.
-            let last_end = entries.last().map(|e| align(e.end, alignment)).unwrap_or(0);
-            if last_end + size <= (**dev_mem).as_ref().size() {
-                entries.push(last_end..last_end + size);
+        // Try finding some free space in the already-allocated blocks.
+        for &mut (ref memory, ref mut tlsf) in blocks.iter_mut() {
+            if let Some(range) = tlsf.alloc(size, alignment) {
+                me.backtraces
+                    .record(Arc::as_ptr(memory) as usize, range.offset());
                 return Ok(StdHostVisibleMemoryTypePoolAlloc {
                     pool: me.clone(),
-                    memory: dev_mem.clone(),
-                    offset: last_end,
-                    size: size,
+                    memory: memory.clone(),
+                    range,
+                    size,
                 });
             }
         }
 
         // We need to allocate a new block.
-        let new_block = {
-            const MIN_BLOCK_SIZE: DeviceSize = 8 * 1024 * 1024; // 8 MB
-            let to_alloc = cmp::max(MIN_BLOCK_SIZE, size.next_power_of_two());
-            let new_block =
-                DeviceMemory::alloc_and_map(me.device.clone(), me.memory_type(), to_alloc)?;
-            Arc::new(new_block)
-        };
+        let allocated: DeviceSize = blocks.iter().map(|(_, tlsf)| tlsf.capacity()).sum();
+        let block_size = next_block_size(me.memory_type(), allocated, size);
+        let memory = Arc::new(DeviceMemory::alloc_and_map(
+            me.device.clone(),
+            me.memory_type(),
+            block_size,
+        )?);
+
+        let mut tlsf = Tlsf::new(block_size);
+        let range = tlsf.alloc(size, alignment).unwrap();
+        blocks.push((memory.clone(), tlsf));
+        me.backtraces
+            .record(Arc::as_ptr(&memory) as usize, range.offset());
 
-        occupied.push((new_block.clone(), vec![0..size]));
         Ok(StdHostVisibleMemoryTypePoolAlloc {
             pool: me.clone(),
-            memory: new_block,
-            offset: 0,
+            memory,
+            range,
             size,
         })
     }
@@ -141,13 +147,39 @@ impl StdHostVisibleMemoryTypePool {
             .memory_type_by_id(self.memory_type)
             .unwrap()
     }
+
+    // Returns the content of the blocks of the pool.
+    pub(crate) fn snapshot(&self) -> Vec<BlockSnapshot> {
+        let blocks = self.blocks.lock().unwrap();
+
+        blocks
+            .iter()
+            .map(|(memory, tlsf)| BlockSnapshot {
+                memory: AsRef::<DeviceMemory>::as_ref(&**memory).internal_object(),
+                size: tlsf.capacity(),
+                ranges: tlsf
+                    .ranges()
+                    .map(|(offset, size, free)| RangeSnapshot {
+                        offset,
+                        size,
+                        free,
+                        backtrace: if free {
+                            None
+                        } else {
+                            self.backtraces.get(Arc::as_ptr(memory) as usize, offset)
+                        },
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug)]
 pub struct StdHostVisibleMemoryTypePoolAlloc {
     pool: Arc<StdHostVisibleMemoryTypePool>,
     memory: Arc<MappedDeviceMemory>,
-    offset: DeviceSize,
+    range: TlsfAlloc,
     size: DeviceSize,
 }
 
@@ -159,7 +191,7 @@ impl StdHostVisibleMemoryTypePoolAlloc {
 
     #[inline]
     pub fn offset(&self) -> DeviceSize {
-        self.offset
+        self.range.offset()
     }
 
     #[inline]
@@ -170,13 +202,26 @@ impl StdHostVisibleMemoryTypePoolAlloc {
 
 impl Drop for StdHostVisibleMemoryTypePoolAlloc {
     fn drop(&mut self) {
-        let mut occupied = self.pool.occupied.lock().unwrap();
+        let mut blocks = self.pool.blocks.lock().unwrap();
 
-        let entries = occupied
-            .iter_mut()
-            .find(|e| &*e.0 as *const MappedDeviceMemory == &*self.memory)
+        let index = blocks
+            .iter()
+            .position(|e| &*e.0 as *const MappedDeviceMemory == &*self.memory)
             .unwrap();
-
-        entries.1.retain(|e| e.start != self.offset);
+        blocks[index].1.free(self.range);
+        self.pool
+            .backtraces
+            .remove(Arc::as_ptr(&self.memory) as usize, self.range.offset());
+
+        // Keep at most one empty block around, so that allocating and freeing repeatedly doesn't
+        // allocate device memory every time.
+        if blocks[index].1.is_empty()
+            && blocks
+                .iter()
+                .enumerate()
+                .any(|(i, e)| i != index && e.1.is_empty())
+        {
+            blocks.swap_remove(index);
+        }
     }
 }
@@ -13,7 +13,11 @@ pub use self::non_host_visible::StdNonHostVisibleMemoryTypePool;
 pub use self::non_host_visible::StdNonHostVisibleMemoryTypePoolAlloc;
 pub use self::pool::StdMemoryPool;
 pub use self::pool::StdMemoryPoolAlloc;
+pub use self::statistics::LiveAllocation;
+pub use self::statistics::MemoryTypeStatistics;
+use crate::device::physical::MemoryHeap;
 use crate::device::physical::MemoryType;
+use crate::device::physical::PhysicalDevice;
 use crate::device::{Device, DeviceOwned};
 use crate::memory::DedicatedAlloc;
 use crate::memory::DeviceMemory;
@@ -21,15 +25,39 @@ use crate::memory::DeviceMemoryAllocError;
 use crate::memory::MappedDeviceMemory;
 use crate::memory::MemoryRequirements;
 use crate::DeviceSize;
+use std::cmp;
 use std::sync::Arc;
 
 mod host_visible;
 mod non_host_visible;
 mod pool;
+mod statistics;
+mod tlsf;
 
-// If the allocation size goes beyond this, then we perform a dedicated allocation which bypasses
-// the pool. This prevents the pool from overallocating a significant amount of memory.
-const MAX_POOL_ALLOC: DeviceSize = 256 * 1024 * 1024;
+// Sizes of the blocks of memory that the standard pools sub-allocate from. The first block of
+// a memory type is small, and each new block is as large as all the previous ones together, up
+// to the maximum. This way applications that use little memory don't reserve a lot of it.
+const MIN_BLOCK_SIZE: DeviceSize = 8 * 1024 * 1024; // 8 MB
+const MAX_BLOCK_SIZE: DeviceSize = 256 * 1024 * 1024; // 256 MB
+
+// Returns the size that the blocks of the standard pools grow up to for a memory type. Small
+// heaps, such as the device-local host-visible heap of desktop GPUs, get smaller blocks.
+fn preferred_block_size(memory_type: MemoryType) -> DeviceSize {
+    cmp::max(
+        MIN_BLOCK_SIZE,
+        cmp::min(MAX_BLOCK_SIZE, memory_type.heap().size() / 8),
+    )
+}
+
+// Returns the size of the next block that a standard pool allocates in order to fit `size`
+// bytes, given the total size of the blocks that it already allocated.
+fn next_block_size(memory_type: MemoryType, allocated: DeviceSize, size: DeviceSize) -> DeviceSize {
+    let block_size = cmp::min(
+        preferred_block_size(memory_type),
+        cmp::max(MIN_BLOCK_SIZE, allocated),
+    );
+    cmp::max(block_size, size)
+}
 
 fn choose_allocation_memory_type<'s, F>(
     device: &'s Arc<Device>,
@@ -191,8 +219,10 @@ pub unsafe trait MemoryPool: DeviceOwned {
         // Choose a suitable memory type.
         let mem_ty = choose_allocation_memory_type(self.device(), requirements, filter, map);
 
-        // Redirect to `self.alloc_generic` if we don't perform a dedicated allocation.
-        if !requirements.prefer_dedicated && requirements.size <= MAX_POOL_ALLOC {
+        // Redirect to `self.alloc_generic` if we don't perform a dedicated allocation. Resources
+        // that take more than half of a block get dedicated memory, since they would otherwise
+        // leave most of the block unused or make the pool allocate an oversized block.
+        if !requirements.prefer_dedicated && requirements.size <= preferred_block_size(mem_ty) / 2 {
             let alloc = self.alloc_generic(
                 mem_ty,
                 requirements.size,
@@ -244,6 +274,124 @@ pub enum AllocFromRequirementsFilter {
     Forbidden,
 }
 
+/// Hint about how a resource is accessed, used to choose the memory type of its memory.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MemoryUsage {
+    /// Only the GPU accesses the memory.
+    ///
+    /// Device-local memory types are preferred. The ones that are also host-visible are avoided
+    /// if possible, so that they stay available for `DeviceLocalHostWritable`.
+    GpuOnly,
+
+    /// The CPU writes the memory often and the GPU reads it, for example for per-frame uniforms
+    /// or dynamic vertex data.
+    ///
+    /// Memory types that are both device-local and host-visible are preferred. On most desktop
+    /// GPUs their heap is small, unless resizable BAR is enabled, so they are only preferred
+    /// while the resource takes at most half of what is left in the heap. Otherwise, other
+    /// host-visible memory types are preferred.
+    DeviceLocalHostWritable,
+
+    /// The memory only backs transient attachments, whose content is never needed outside of a
+    /// render pass.
+    ///
+    /// Lazily-allocated memory types are preferred, so that tile-based GPUs can keep the content
+    /// in tile memory and never commit memory for it. Otherwise, this is the same as `GpuOnly`.
+    Transient,
+}
+
+impl MemoryUsage {
+    /// Returns a filter to pass to `MemoryPool::alloc_from_requirements` in order to allocate
+    /// `size` bytes with this usage.
+    ///
+    /// What is left in each heap is queried with the `ext_memory_budget` extension if it is
+    /// supported, or otherwise computed from the blocks that the standard pool of the device
+    /// allocated.
+    pub fn filter(
+        self,
+        device: &Arc<Device>,
+        size: DeviceSize,
+    ) -> impl FnMut(MemoryType) -> AllocFromRequirementsFilter {
+        let physical_device = device.physical_device();
+        let decisions: Vec<AllocFromRequirementsFilter> = match self {
+            MemoryUsage::GpuOnly => gpu_only_decisions(physical_device),
+            MemoryUsage::DeviceLocalHostWritable => physical_device
+                .memory_types()
+                .map(|t| {
+                    let device_local_host_visible = t.is_device_local() && t.is_host_visible();
+                    let fits =
+                        device_local_host_visible && size <= heap_available(device, t.heap()) / 2;
+                    if fits || (t.is_host_visible() && !device_local_host_visible) {
+                        AllocFromRequirementsFilter::Preferred
+                    } else {
+                        AllocFromRequirementsFilter::Allowed
+                    }
+                })
+                .collect(),
+            MemoryUsage::Transient => {
+                if physical_device
+                    .memory_types()
+                    .any(|t| t.is_lazily_allocated())
+                {
+                    physical_device
+                        .memory_types()
+                        .map(|t| {
+                            if t.is_lazily_allocated() {
+                                AllocFromRequirementsFilter::Preferred
+                            } else {
+                                AllocFromRequirementsFilter::Allowed
+                            }
+                        })
+                        .collect()
+                } else {
+                    gpu_only_decisions(physical_device)
+                }
+            }
+        };
+
+        move |t| decisions[t.id() as usize]
+    }
+}
+
+// Returns the decision of `MemoryUsage::GpuOnly` for each memory type of `physical_device`.
+fn gpu_only_decisions(physical_device: PhysicalDevice) -> Vec<AllocFromRequirementsFilter> {
+    let has_device_only = physical_device
+        .memory_types()
+        .any(|t| t.is_device_local() && !t.is_host_visible());
+    physical_device
+        .memory_types()
+        .map(|t| {
+            if t.is_device_local() && (!has_device_only || !t.is_host_visible()) {
+                AllocFromRequirementsFilter::Preferred
+            } else {
+                AllocFromRequirementsFilter::Allowed
+            }
+        })
+        .collect()
+}
+
+// Returns the number of bytes that can still be allocated from `heap`.
+fn heap_available(device: &Arc<Device>, heap: MemoryHeap) -> DeviceSize {
+    if let Some(budgets) = device.physical_device().memory_budget() {
+        let budget = &budgets[heap.id() as usize];
+        return budget.budget.saturating_sub(budget.usage);
+    }
+
+    let allocated: DeviceSize = Device::standard_pool(device)
+        .statistics()
+        .iter()
+        .filter(|stats| {
+            let memory_type = device
+                .physical_device()
+                .memory_type_by_id(stats.memory_type)
+                .unwrap();
+            memory_type.heap().id() == heap.id()
+        })
+        .map(|stats| stats.used_bytes + stats.free_bytes)
+        .sum();
+    heap.size().saturating_sub(allocated)
+}
+
 /// Object that represents a single allocation. Its destructor should free the chunk.
 pub unsafe trait MemoryPoolAlloc: Send + Sync {
     /// Returns the memory object from which this is allocated. Returns `None` if the memory is
@@ -323,3 +471,21 @@ impl<A> From<A> for PotentialDedicatedAllocation<A> {
         PotentialDedicatedAllocation::Generic(alloc)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::memory::pool::AllocFromRequirementsFilter;
+    use crate::memory::pool::MemoryUsage;
+
+    #[test]
+    fn gpu_only_prefers_device_local() {
+        let (device, _) = gfx_dev_and_queue!();
+        let mut filter = MemoryUsage::GpuOnly.filter(&device, 1024);
+
+        for ty in device.physical_device().memory_types() {
+            if filter(ty) == AllocFromRequirementsFilter::Preferred {
+                assert!(ty.is_device_local());
+            }
+        }
+    }
+}
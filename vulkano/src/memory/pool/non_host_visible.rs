@@ -10,11 +10,16 @@
 use crate::device::physical::MemoryType;
 use crate::device::Device;
 use crate::instance::Instance;
+use crate::memory::pool::next_block_size;
+use crate::memory::pool::statistics::Backtraces;
+use crate::memory::pool::statistics::BlockSnapshot;
+use crate::memory::pool::statistics::RangeSnapshot;
+use crate::memory::pool::tlsf::Tlsf;
+use crate::memory::pool::tlsf::TlsfAlloc;
 use crate::memory::DeviceMemory;
 use crate::memory::DeviceMemoryAllocError;
 use crate::DeviceSize;
-use std::cmp;
-use std::ops::Range;
+use crate::VulkanObject;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -23,8 +28,9 @@ use std::sync::Mutex;
 pub struct StdNonHostVisibleMemoryTypePool {
     device: Arc<Device>,
     memory_type: u32,
-    // TODO: obviously very inefficient
-    occupied: Mutex<Vec<(Arc<DeviceMemory>, Vec<Range<DeviceSize>>)>>,
+    // Blocks of memory, and the sub-allocator of each block.
+    blocks: Mutex<Vec<(Arc<DeviceMemory>, Tlsf)>>,
+    backtraces: Backtraces,
 }
 
 impl StdNonHostVisibleMemoryTypePool {
@@ -51,7 +57,8 @@ impl StdNonHostVisibleMemoryTypePool {
         Arc::new(StdNonHostVisibleMemoryTypePool {
             device: device.clone(),
             memory_type: memory_type.id(),
-            occupied: Mutex::new(Vec::new()),
+            blocks: Mutex::new(Vec::new()),
+            backtraces: Backtraces::default(),
         })
     }
 
@@ -60,7 +67,7 @@ impl StdNonHostVisibleMemoryTypePool {
     /// # Panic
     ///
     /// - Panics if `size` is 0.
-    /// - Panics if `alignment` is 0.
+    /// - Panics if `alignment` is not a power of two.
     ///
     pub fn alloc(
         me: &Arc<Self>,
@@ -68,61 +75,44 @@ impl StdNonHostVisibleMemoryTypePool {
         alignment: DeviceSize,
     ) -> Result<StdNonHostVisibleMemoryTypePoolAlloc, DeviceMemoryAllocError> {
         assert!(size != 0);
-        assert!(alignment != 0);
+        assert!(alignment.is_power_of_two());
 
-        #[inline]
-        fn align(val: DeviceSize, al: DeviceSize) -> DeviceSize {
-            al * (1 + (val - 1) / al)
-        }
-
-        // Find a location.
-        let mut occupied = me.occupied.lock().unwrap();
-
-        // Try finding an entry in already-allocated chunks.
-        for &mut (ref dev_mem, ref mut entries) in occupied.iter_mut() {
-            // Try find some free space in-between two entries.
-            for i in 0..entries.len().saturating_sub(1) {
-                let entry1 = entries[i].clone();
-                let entry1_end = align(entry1.end, alignment);
-                let entry2 = entries[i + 1].clone();
-                if entry1_end + size <= entry2.start {
-                    entries.insert(i + 1, entry1_end..entry1_end + size);
-                    return Ok(StdNonHostVisibleMemoryTypePoolAlloc {
-                        pool: me.clone(),
-                        memory: dev_mem.clone(),
-                        offset: entry1_end,
-                        size: size,
-                    });
-                }
-            }
+        let mut blocks = me.blocks.lock().unwrap();
 
-            // Try append at the end.
-            let last_end = entries.last().map(|e| align(e.end, alignment)).unwrap_or(0);
-            if last_end + size <= dev_mem.size() {
-                entries.push(last_end..last_end + size);
+        // Try finding some free space in the already-allocated blocks.
+        for &mut (ref memory, ref mut tlsf) in blocks.iter_mut() {
+            if let Some(range) = tlsf.alloc(size, alignment) {
+                me.backtraces
+                    .record(Arc::as_ptr(memory) as usize, range.offset());
                 return Ok(StdNonHostVisibleMemoryTypePoolAlloc {
                     pool: me.clone(),
-                    memory: dev_mem.clone(),
-                    offset: last_end,
-                    size: size,
+                    memory: memory.clone(),
+                    range,
+                    size,
                 });
             }
         }
 
         // We need to allocate a new block.
-        let new_block = {
-            const MIN_BLOCK_SIZE: DeviceSize = 8 * 1024 * 1024; // 8 MB
-            let to_alloc = cmp::max(MIN_BLOCK_SIZE, size.next_power_of_two());
-            let new_block = DeviceMemory::alloc(me.device.clone(), me.memory_type(), to_alloc)?;
-            Arc::new(new_block)
-        };
-
-        occupied.push((new_block.clone(), vec![0..size]));
+        let allocated: DeviceSize = blocks.iter().map(|(_, tlsf)| tlsf.capacity()).sum();
+        let block_size = next_block_size(me.memory_type(), allocated, size);
+        let memory = Arc::new(DeviceMemory::alloc(
+            me.device.clone(),
+            me.memory_type(),
+            block_size,
+        )?);
+
+        let mut tlsf = Tlsf::new(block_size);
+        let range = tlsf.alloc(size, alignment).unwrap();
+        blocks.push((memory.clone(), tlsf));
+        me.backtraces
+            .record(Arc::as_ptr(&memory) as usize, range.offset());
+
         Ok(StdNonHostVisibleMemoryTypePoolAlloc {
             pool: me.clone(),
-            memory: new_block,
-            offset: 0,
-            size: size,
+            memory,
+            range,
+            size,
         })
     }
 
@@ -140,13 +130,39 @@ impl StdNonHostVisibleMemoryTypePool {
             .memory_type_by_id(self.memory_type)
             .unwrap()
     }
+
+    // Returns the content of the blocks of the pool.
+    pub(crate) fn snapshot(&self) -> Vec<BlockSnapshot> {
+        let blocks = self.blocks.lock().unwrap();
+
+        blocks
+            .iter()
+            .map(|(memory, tlsf)| BlockSnapshot {
+                memory: memory.internal_object(),
+                size: tlsf.capacity(),
+                ranges: tlsf
+                    .ranges()
+                    .map(|(offset, size, free)| RangeSnapshot {
+                        offset,
+                        size,
+                        free,
+                        backtrace: if free {
+                            None
+                        } else {
+                            self.backtraces.get(Arc::as_ptr(memory) as usize, offset)
+                        },
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug)]
 pub struct StdNonHostVisibleMemoryTypePoolAlloc {
     pool: Arc<StdNonHostVisibleMemoryTypePool>,
     memory: Arc<DeviceMemory>,
-    offset: DeviceSize,
+    range: TlsfAlloc,
     size: DeviceSize,
 }
 
@@ -158,7 +174,7 @@ impl StdNonHostVisibleMemoryTypePoolAlloc {
 
     #[inline]
     pub fn offset(&self) -> DeviceSize {
-        self.offset
+        self.range.offset()
     }
 
     #[inline]
@@ -169,13 +185,26 @@ impl StdNonHostVisibleMemoryTypePoolAlloc {
 
 impl Drop for StdNonHostVisibleMemoryTypePoolAlloc {
     fn drop(&mut self) {
-        let mut occupied = self.pool.occupied.lock().unwrap();
+        let mut blocks = self.pool.blocks.lock().unwrap();
 
-        let entries = occupied
-            .iter_mut()
-            .find(|e| &*e.0 as *const DeviceMemory == &*self.memory)
+        let index = blocks
+            .iter()
+            .position(|e| &*e.0 as *const DeviceMemory == &*self.memory)
             .unwrap();
-
-        entries.1.retain(|e| e.start != self.offset);
+        blocks[index].1.free(self.range);
+        self.pool
+            .backtraces
+            .remove(Arc::as_ptr(&self.memory) as usize, self.range.offset());
+
+        // Keep at most one empty block around, so that allocating and freeing repeatedly doesn't
+        // allocate device memory every time.
+        if blocks[index].1.is_empty()
+            && blocks
+                .iter()
+                .enumerate()
+                .any(|(i, e)| i != index && e.1.is_empty())
+        {
+            blocks.swap_remove(index);
+        }
     }
 }
@@ -10,10 +10,14 @@
 use crate::device::physical::MemoryType;
 use crate::device::Device;
 use crate::device::DeviceOwned;
+use crate::memory::pool::statistics;
+use crate::memory::pool::statistics::BlockSnapshot;
 use crate::memory::pool::AllocLayout;
+use crate::memory::pool::LiveAllocation;
 use crate::memory::pool::MappingRequirement;
 use crate::memory::pool::MemoryPool;
 use crate::memory::pool::MemoryPoolAlloc;
+use crate::memory::pool::MemoryTypeStatistics;
 use crate::memory::pool::StdHostVisibleMemoryTypePool;
 use crate::memory::pool::StdHostVisibleMemoryTypePoolAlloc;
 use crate::memory::pool::StdNonHostVisibleMemoryTypePool;
@@ -33,7 +37,9 @@ use std::sync::Mutex;
 pub struct StdMemoryPool {
     device: Arc<Device>,
 
-    // For each memory type index, stores the associated pool.
+    // For each memory type index, stores the associated pool. Linear and optimal resources are
+    // allocated from different pools, so that they never share a block and the
+    // `buffer_image_granularity` limit doesn't need to be taken into account.
     pools:
         Mutex<HashMap<(u32, AllocLayout, MappingRequirement), Pool, BuildHasherDefault<FnvHasher>>>,
 }
@@ -50,6 +56,99 @@ impl StdMemoryPool {
             pools: Mutex::new(HashMap::with_capacity_and_hasher(cap, hasher)),
         })
     }
+
+    /// Returns statistics about the blocks of memory of each memory type, ordered by memory type.
+    ///
+    /// Dedicated allocations, which don't go through the pool, are not included.
+    pub fn statistics(&self) -> Vec<MemoryTypeStatistics> {
+        let mut stats: Vec<MemoryTypeStatistics> = self
+            .device
+            .physical_device()
+            .memory_types()
+            .map(|ty| MemoryTypeStatistics {
+                memory_type: ty.id(),
+                ..Default::default()
+            })
+            .collect();
+
+        for (memory_type, _, blocks) in self.snapshot() {
+            statistics::accumulate(&mut stats[memory_type as usize], &blocks);
+        }
+
+        stats
+    }
+
+    /// Returns the list of the allocations of the pool that haven't been freed yet.
+    ///
+    /// Calling this function before destroying the device lists the resources that are leaked.
+    /// If the `allocation_backtraces` feature of vulkano is enabled, each allocation contains
+    /// the backtrace of where it was made. Recording the backtraces is slow, so the feature should
+    /// only be enabled while debugging.
+    pub fn live_allocations(&self) -> Vec<LiveAllocation> {
+        let mut allocations = Vec::new();
+
+        for (memory_type, layout, blocks) in self.snapshot() {
+            for block in blocks {
+                for range in block.ranges.into_iter().filter(|range| !range.free) {
+                    allocations.push(LiveAllocation {
+                        memory_type,
+                        layout,
+                        offset: range.offset,
+                        size: range.size,
+                        backtrace: range.backtrace,
+                    });
+                }
+            }
+        }
+
+        allocations
+    }
+
+    /// Returns a JSON description of the content of the pool.
+    ///
+    /// The format is the same as the one of `vmaBuildStatsString` in the Vulkan Memory Allocator
+    /// library, which means that its tools, such as `VmaDumpVis.py`, can be used to visualize the
+    /// fragmentation of the memory.
+    pub fn dump_json(&self) -> String {
+        statistics::write_json(&self.device, &self.snapshot())
+    }
+
+    // Returns the fraction of each block of the pool that is sub-allocated.
+    pub(crate) fn block_occupancy(&self) -> HashMap<ash::vk::DeviceMemory, f64> {
+        self.snapshot()
+            .into_iter()
+            .flat_map(|(_, _, blocks)| blocks)
+            .map(|block| {
+                let used: DeviceSize = block
+                    .ranges
+                    .iter()
+                    .filter(|range| !range.free)
+                    .map(|range| range.size)
+                    .sum();
+                (block.memory, used as f64 / block.size as f64)
+            })
+            .collect()
+    }
+
+    // Returns the content of the pool of each memory type and layout, ordered by memory type.
+    fn snapshot(&self) -> Vec<(u32, AllocLayout, Vec<BlockSnapshot>)> {
+        let pools = self.pools.lock().unwrap();
+
+        let mut snapshot: Vec<_> = pools
+            .iter()
+            .map(|(&(memory_type, layout, _), pool)| {
+                let blocks = match pool {
+                    Pool::HostVisible(pool) => pool.snapshot(),
+                    Pool::NonHostVisible(pool) => pool.snapshot(),
+                };
+                (memory_type, layout, blocks)
+            })
+            .collect();
+        snapshot
+            .sort_by_key(|&(memory_type, layout, _)| (memory_type, layout == AllocLayout::Optimal));
+
+        snapshot
+    }
 }
 
 fn generic_allocation(
@@ -185,3 +284,41 @@ enum StdMemoryPoolAllocInner {
     NonHostVisible(StdNonHostVisibleMemoryTypePoolAlloc),
     HostVisible(StdHostVisibleMemoryTypePoolAlloc),
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::memory::pool::AllocLayout;
+    use crate::memory::pool::MappingRequirement;
+    use crate::memory::pool::MemoryPool;
+    use crate::memory::pool::StdMemoryPool;
+
+    #[test]
+    fn statistics_and_live_allocations() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = StdMemoryPool::new(device.clone());
+        let memory_type = device.physical_device().memory_types().next().unwrap();
+
+        let alloc = pool
+            .alloc_generic(
+                memory_type,
+                256,
+                16,
+                AllocLayout::Linear,
+                MappingRequirement::DoNotMap,
+            )
+            .unwrap();
+
+        let stats = pool.statistics()[memory_type.id() as usize];
+        assert_eq!(stats.block_count, 1);
+        assert_eq!(stats.allocation_count, 1);
+        assert!(stats.used_bytes >= 256);
+
+        let live = pool.live_allocations();
+        assert_eq!(live.len(), 1);
+        assert!(live[0].size >= 256);
+        assert!(pool.dump_json().starts_with("{\"General\":"));
+
+        drop(alloc);
+        assert!(pool.live_allocations().is_empty());
+    }
+}
@@ -0,0 +1,382 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::device::Device;
+use crate::memory::pool::preferred_block_size;
+use crate::memory::pool::AllocLayout;
+use crate::DeviceSize;
+#[cfg(feature = "allocation_backtraces")]
+use std::backtrace::Backtrace;
+#[cfg(feature = "allocation_backtraces")]
+use std::collections::HashMap;
+use std::fmt::Write;
+#[cfg(feature = "allocation_backtraces")]
+use std::sync::Mutex;
+
+/// Statistics about the memory that a `StdMemoryPool` manages for a memory type.
+///
+/// Dedicated allocations don't go through the pool and aren't counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryTypeStatistics {
+    /// Index of the memory type.
+    pub memory_type: u32,
+    /// Number of blocks of device memory that the pool allocated.
+    pub block_count: u32,
+    /// Number of resources that the blocks are sub-allocated to.
+    pub allocation_count: u32,
+    /// Number of bytes of the blocks that are sub-allocated.
+    pub used_bytes: DeviceSize,
+    /// Number of bytes of the blocks that are free.
+    pub free_bytes: DeviceSize,
+    /// Number of free ranges in the blocks. A high number compared to `allocation_count` means
+    /// that the free memory is fragmented.
+    pub free_range_count: u32,
+}
+
+/// An allocation of a `StdMemoryPool` that hasn't been freed, as returned by
+/// [`StdMemoryPool::live_allocations`](crate::memory::pool::StdMemoryPool::live_allocations).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LiveAllocation {
+    /// Index of the memory type.
+    pub memory_type: u32,
+    /// Layout of the resource the memory was allocated for.
+    pub layout: AllocLayout,
+    /// Offset of the allocation within its block of device memory.
+    pub offset: DeviceSize,
+    /// Size of the allocation in bytes.
+    pub size: DeviceSize,
+    /// Where the allocation was made. Only available if the `allocation_backtraces` feature of
+    /// vulkano is enabled.
+    pub backtrace: Option<String>,
+}
+
+// Content of a block of a memory type pool, at the time of the call to `snapshot`.
+#[derive(Debug)]
+pub(crate) struct BlockSnapshot {
+    pub(crate) memory: ash::vk::DeviceMemory,
+    pub(crate) size: DeviceSize,
+    pub(crate) ranges: Vec<RangeSnapshot>,
+}
+
+#[derive(Debug)]
+pub(crate) struct RangeSnapshot {
+    pub(crate) offset: DeviceSize,
+    pub(crate) size: DeviceSize,
+    pub(crate) free: bool,
+    pub(crate) backtrace: Option<String>,
+}
+
+// Backtraces of the live allocations of a memory type pool, indexed by the address of their block
+// and their offset. Doesn't record anything unless the `allocation_backtraces` feature is enabled.
+#[derive(Debug, Default)]
+pub(crate) struct Backtraces {
+    #[cfg(feature = "allocation_backtraces")]
+    backtraces: Mutex<HashMap<(usize, DeviceSize), Backtrace>>,
+}
+
+impl Backtraces {
+    #[inline]
+    pub(crate) fn record(&self, block: usize, offset: DeviceSize) {
+        #[cfg(feature = "allocation_backtraces")]
+        {
+            self.backtraces
+                .lock()
+                .unwrap()
+                .insert((block, offset), Backtrace::force_capture());
+        }
+        #[cfg(not(feature = "allocation_backtraces"))]
+        {
+            let _ = (block, offset);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn remove(&self, block: usize, offset: DeviceSize) {
+        #[cfg(feature = "allocation_backtraces")]
+        {
+            self.backtraces.lock().unwrap().remove(&(block, offset));
+        }
+        #[cfg(not(feature = "allocation_backtraces"))]
+        {
+            let _ = (block, offset);
+        }
+    }
+
+    pub(crate) fn get(&self, block: usize, offset: DeviceSize) -> Option<String> {
+        #[cfg(feature = "allocation_backtraces")]
+        {
+            self.backtraces
+                .lock()
+                .unwrap()
+                .get(&(block, offset))
+                .map(|backtrace| backtrace.to_string())
+        }
+        #[cfg(not(feature = "allocation_backtraces"))]
+        {
+            let _ = (block, offset);
+            None
+        }
+    }
+}
+
+// Adds the content of `blocks` to `stats`.
+pub(crate) fn accumulate(stats: &mut MemoryTypeStatistics, blocks: &[BlockSnapshot]) {
+    for block in blocks {
+        stats.block_count += 1;
+
+        for range in &block.ranges {
+            if range.free {
+                stats.free_range_count += 1;
+                stats.free_bytes += range.size;
+            } else {
+                stats.allocation_count += 1;
+                stats.used_bytes += range.size;
+            }
+        }
+    }
+}
+
+// Writes the content of the pools of a `StdMemoryPool` in the JSON format of the
+// `vmaBuildStatsString` function of the Vulkan Memory Allocator library, so that the tools
+// written for it can be used.
+//
+// `pools` contains the blocks of each memory type, along with the layout of their resources.
+pub(crate) fn write_json(
+    device: &Device,
+    pools: &[(u32, AllocLayout, Vec<BlockSnapshot>)],
+) -> String {
+    let physical_device = device.physical_device();
+    let properties = physical_device.properties();
+    let budgets = physical_device.memory_budget();
+
+    let mut type_stats: Vec<MemoryTypeStatistics> = physical_device
+        .memory_types()
+        .map(|ty| MemoryTypeStatistics {
+            memory_type: ty.id(),
+            ..Default::default()
+        })
+        .collect();
+    for (memory_type, _, blocks) in pools {
+        accumulate(&mut type_stats[*memory_type as usize], blocks);
+    }
+
+    let mut out = String::new();
+    out.push('{');
+
+    write!(
+        out,
+        "\"General\":{{\"API\":\"Vulkan\",\"apiVersion\":\"{}\",\"GPU\":{},\
+         \"maxMemoryAllocationCount\":{},\"bufferImageGranularity\":{},\
+         \"nonCoherentAtomSize\":{},\"memoryHeapCount\":{},\"memoryTypeCount\":{}}},",
+        physical_device.api_version(),
+        json_string(&properties.device_name),
+        properties.max_memory_allocation_count,
+        properties.buffer_image_granularity,
+        properties.non_coherent_atom_size,
+        physical_device.memory_heaps().len(),
+        physical_device.memory_types().len(),
+    )
+    .unwrap();
+
+    let mut total = MemoryTypeStatistics::default();
+    for stats in &type_stats {
+        add_stats(&mut total, stats);
+    }
+    out.push_str("\"Total\":");
+    write_stats(&mut out, &total);
+
+    out.push_str(",\"MemoryInfo\":{");
+    for (i, heap) in physical_device.memory_heaps().enumerate() {
+        if i != 0 {
+            out.push(',');
+        }
+
+        let mut heap_stats = MemoryTypeStatistics::default();
+        for ty in physical_device
+            .memory_types()
+            .filter(|ty| ty.heap().id() == heap.id())
+        {
+            add_stats(&mut heap_stats, &type_stats[ty.id() as usize]);
+        }
+
+        write!(out, "\"Heap {}\":{{\"Flags\":[", heap.id()).unwrap();
+        write_flags(
+            &mut out,
+            &[
+                (heap.is_device_local(), "DEVICE_LOCAL"),
+                (heap.is_multi_instance(), "MULTI_INSTANCE"),
+            ],
+        );
+        write!(out, "],\"Size\":{}", heap.size()).unwrap();
+
+        if let Some(budget) = budgets.as_ref().and_then(|b| b.get(heap.id() as usize)) {
+            write!(
+                out,
+                ",\"Budget\":{{\"BudgetBytes\":{},\"UsageBytes\":{}}}",
+                budget.budget, budget.usage
+            )
+            .unwrap();
+        }
+
+        out.push_str(",\"Stats\":");
+        write_stats(&mut out, &heap_stats);
+
+        out.push_str(",\"MemoryPools\":{");
+        for (j, ty) in physical_device
+            .memory_types()
+            .filter(|ty| ty.heap().id() == heap.id())
+            .enumerate()
+        {
+            if j != 0 {
+                out.push(',');
+            }
+            write!(out, "\"Type {}\":{{\"Flags\":[", ty.id()).unwrap();
+            write_flags(
+                &mut out,
+                &[
+                    (ty.is_device_local(), "DEVICE_LOCAL"),
+                    (ty.is_host_visible(), "HOST_VISIBLE"),
+                    (ty.is_host_coherent(), "HOST_COHERENT"),
+                    (ty.is_host_cached(), "HOST_CACHED"),
+                    (ty.is_lazily_allocated(), "LAZILY_ALLOCATED"),
+                ],
+            );
+            out.push_str("],\"Stats\":");
+            write_stats(&mut out, &type_stats[ty.id() as usize]);
+            out.push('}');
+        }
+        out.push_str("}}");
+    }
+    out.push('}');
+
+    out.push_str(",\"DefaultPools\":{");
+    for (i, ty) in physical_device.memory_types().enumerate() {
+        if i != 0 {
+            out.push(',');
+        }
+
+        write!(
+            out,
+            "\"Type {}\":{{\"PreferredBlockSize\":{},\"Blocks\":{{",
+            ty.id(),
+            preferred_block_size(ty)
+        )
+        .unwrap();
+
+        let blocks = pools
+            .iter()
+            .filter(|(memory_type, _, _)| *memory_type == ty.id())
+            .flat_map(|(_, layout, blocks)| blocks.iter().map(move |block| (*layout, block)));
+        for (j, (layout, block)) in blocks.enumerate() {
+            if j != 0 {
+                out.push(',');
+            }
+
+            let mut block_stats = MemoryTypeStatistics::default();
+            accumulate(&mut block_stats, std::slice::from_ref(block));
+            write!(
+                out,
+                "\"{}\":{{\"MapRefCount\":0,\"TotalBytes\":{},\"UnusedBytes\":{},\
+                 \"Allocations\":{},\"UnusedRanges\":{},\"Suballocations\":[",
+                j,
+                block.size,
+                block_stats.free_bytes,
+                block_stats.allocation_count,
+                block_stats.free_range_count
+            )
+            .unwrap();
+
+            for (k, range) in block.ranges.iter().enumerate() {
+                if k != 0 {
+                    out.push(',');
+                }
+
+                // The pools don't know whether linear resources are buffers or linear images,
+                // but linear images are rare.
+                let ty = match (range.free, layout) {
+                    (true, _) => "FREE",
+                    (false, AllocLayout::Linear) => "BUFFER",
+                    (false, AllocLayout::Optimal) => "IMAGE_OPTIMAL",
+                };
+                write!(
+                    out,
+                    "{{\"Offset\":{},\"Type\":\"{}\",\"Size\":{}}}",
+                    range.offset, ty, range.size
+                )
+                .unwrap();
+            }
+            out.push_str("]}");
+        }
+
+        out.push_str("},\"DedicatedAllocations\":[]}");
+    }
+    out.push('}');
+
+    out.push_str(",\"CustomPools\":{}}");
+    out
+}
+
+fn add_stats(total: &mut MemoryTypeStatistics, stats: &MemoryTypeStatistics) {
+    total.block_count += stats.block_count;
+    total.allocation_count += stats.allocation_count;
+    total.used_bytes += stats.used_bytes;
+    total.free_bytes += stats.free_bytes;
+    total.free_range_count += stats.free_range_count;
+}
+
+fn write_stats(out: &mut String, stats: &MemoryTypeStatistics) {
+    write!(
+        out,
+        "{{\"BlockCount\":{},\"BlockBytes\":{},\"AllocationCount\":{},\"AllocationBytes\":{},\
+         \"UnusedRangeCount\":{}}}",
+        stats.block_count,
+        stats.used_bytes + stats.free_bytes,
+        stats.allocation_count,
+        stats.used_bytes,
+        stats.free_range_count
+    )
+    .unwrap();
+}
+
+fn write_flags(out: &mut String, flags: &[(bool, &str)]) {
+    let mut first = true;
+    for &(_, name) in flags.iter().filter(|&&(set, _)| set) {
+        if !first {
+            out.push(',');
+        }
+        first = false;
+        write!(out, "\"{}\"", name).unwrap();
+    }
+}
+
+// Turns `value` into a JSON string literal.
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::json_string;
+
+    #[test]
+    fn escape_json_string() {
+        assert_eq!(json_string("GPU \"1\"\\\n"), "\"GPU \\\"1\\\"\\\\\\u000a\"");
+    }
+}
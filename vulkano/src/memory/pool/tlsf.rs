@@ -0,0 +1,442 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Two-level segregated fit sub-allocator, used to sub-allocate the blocks of device memory of
+//! the standard memory pool.
+//!
+//! Free ranges are sorted into size classes: the first level is the power of two of the size,
+//! and the second level divides each power of two into `SL_COUNT` linear sub-classes. Two bitmaps
+//! record which classes have free ranges, so that finding a free range and freeing one are both
+//! done in constant time regardless of the number of allocations. Neighbouring free ranges are
+//! always merged.
+
+use crate::DeviceSize;
+
+const SL_LOG2: u32 = 5;
+const SL_COUNT: usize = 1 << SL_LOG2;
+// Sizes below this are all put in the first level, with one second-level class per size.
+const SMALL_SIZE: DeviceSize = 1 << SL_LOG2;
+
+const NIL: u32 = u32::MAX;
+
+/// Sub-allocator for a range of `0..capacity` offsets.
+#[derive(Debug)]
+pub(crate) struct Tlsf {
+    capacity: DeviceSize,
+    allocated: DeviceSize,
+    // All the ranges, free or not. Ranges are referred to by their index in this list.
+    nodes: Vec<Node>,
+    // Indices of `nodes` that are unused and can be reused.
+    unused_nodes: Vec<u32>,
+    // Bit `fl` is set if `sl_bitmaps[fl]` is not zero.
+    fl_bitmap: u64,
+    // Bit `sl` of `sl_bitmaps[fl]` is set if `heads[fl][sl]` is not `NIL`.
+    sl_bitmaps: Vec<u32>,
+    // First free range of each size class.
+    heads: Vec<[u32; SL_COUNT]>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    offset: DeviceSize,
+    size: DeviceSize,
+    free: bool,
+    // Neighbouring ranges in memory.
+    prev_phys: u32,
+    next_phys: u32,
+    // Neighbouring ranges in the free list of the size class, if `free` is true.
+    prev_free: u32,
+    next_free: u32,
+}
+
+/// A range returned by `Tlsf::alloc`, to pass back to `Tlsf::free`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TlsfAlloc {
+    node: u32,
+    offset: DeviceSize,
+}
+
+impl TlsfAlloc {
+    /// Returns the offset of the start of the allocation.
+    #[inline]
+    pub(crate) fn offset(&self) -> DeviceSize {
+        self.offset
+    }
+}
+
+impl Tlsf {
+    /// Creates a sub-allocator where `0..capacity` is free.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `capacity` is 0.
+    ///
+    pub(crate) fn new(capacity: DeviceSize) -> Tlsf {
+        assert!(capacity != 0);
+
+        let fl_count = mapping(capacity).0 + 1;
+        let mut tlsf = Tlsf {
+            capacity,
+            allocated: 0,
+            nodes: Vec::new(),
+            unused_nodes: Vec::new(),
+            fl_bitmap: 0,
+            sl_bitmaps: vec![0; fl_count],
+            heads: vec![[NIL; SL_COUNT]; fl_count],
+        };
+
+        let node = tlsf.new_node(Node {
+            offset: 0,
+            size: capacity,
+            free: true,
+            prev_phys: NIL,
+            next_phys: NIL,
+            prev_free: NIL,
+            next_free: NIL,
+        });
+        tlsf.insert_free(node);
+        tlsf
+    }
+
+    /// Returns the size of the range managed by the sub-allocator.
+    #[inline]
+    pub(crate) fn capacity(&self) -> DeviceSize {
+        self.capacity
+    }
+
+    /// Returns true if nothing is allocated.
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.allocated == 0
+    }
+
+    /// Returns the offset, size and whether it is free of every range, in order of offset.
+    pub(crate) fn ranges(&self) -> impl Iterator<Item = (DeviceSize, DeviceSize, bool)> + '_ {
+        // Node 0 is created for offset 0 and is never merged into a previous range.
+        let mut node = 0;
+        std::iter::from_fn(move || {
+            if node == NIL {
+                return None;
+            }
+            let n = &self.nodes[node as usize];
+            node = n.next_phys;
+            Some((n.offset, n.size, n.free))
+        })
+    }
+
+    /// Allocates `size` bytes at an offset that is a multiple of `alignment`. Returns `None` if
+    /// there is no free range large enough.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `size` is 0.
+    /// - Panics if `alignment` is not a power of two.
+    ///
+    pub(crate) fn alloc(&mut self, size: DeviceSize, alignment: DeviceSize) -> Option<TlsfAlloc> {
+        assert!(size != 0);
+        assert!(alignment.is_power_of_two());
+
+        if size > self.capacity - self.allocated {
+            return None;
+        }
+
+        // Any range of the size class above `size` is large enough, but it may be too small once
+        // the start is aligned. In that case, look again for a range that is large enough for any
+        // start.
+        let node = match self.find_free(size) {
+            Some(node) if self.fits(node, size, alignment) => node,
+            _ => match size
+                .checked_add(alignment - 1)
+                .and_then(|s| self.find_free(s))
+            {
+                Some(node) => node,
+                None => return None,
+            },
+        };
+        debug_assert!(self.fits(node, size, alignment));
+
+        self.remove_free(node);
+        let Node {
+            offset: node_offset,
+            size: node_size,
+            ..
+        } = self.nodes[node as usize];
+        let offset = align_up(node_offset, alignment);
+
+        // Give back the part before the aligned start. Since neighbouring free ranges are always
+        // merged, the range before `node` isn't free and there is nothing to merge with.
+        let padding = offset - node_offset;
+        if padding != 0 {
+            let prev_phys = self.nodes[node as usize].prev_phys;
+            let front = self.new_node(Node {
+                offset: node_offset,
+                size: padding,
+                free: true,
+                prev_phys,
+                next_phys: node,
+                prev_free: NIL,
+                next_free: NIL,
+            });
+            if prev_phys != NIL {
+                self.nodes[prev_phys as usize].next_phys = front;
+            }
+            self.nodes[node as usize].prev_phys = front;
+            self.nodes[node as usize].offset = offset;
+            self.nodes[node as usize].size = node_size - padding;
+            self.insert_free(front);
+        }
+
+        // Give back the part after the end.
+        let remainder = self.nodes[node as usize].size - size;
+        if remainder != 0 {
+            let next_phys = self.nodes[node as usize].next_phys;
+            let back = self.new_node(Node {
+                offset: offset + size,
+                size: remainder,
+                free: true,
+                prev_phys: node,
+                next_phys,
+                prev_free: NIL,
+                next_free: NIL,
+            });
+            if next_phys != NIL {
+                self.nodes[next_phys as usize].prev_phys = back;
+            }
+            self.nodes[node as usize].next_phys = back;
+            self.nodes[node as usize].size = size;
+            self.insert_free(back);
+        }
+
+        self.nodes[node as usize].free = false;
+        self.allocated += size;
+
+        Some(TlsfAlloc { node, offset })
+    }
+
+    /// Frees an allocation returned by `alloc`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `alloc` is already free.
+    ///
+    pub(crate) fn free(&mut self, alloc: TlsfAlloc) {
+        let mut node = alloc.node;
+        assert!(!self.nodes[node as usize].free);
+        debug_assert_eq!(self.nodes[node as usize].offset, alloc.offset);
+
+        self.allocated -= self.nodes[node as usize].size;
+        self.nodes[node as usize].free = true;
+
+        // Merge with the previous range.
+        let prev = self.nodes[node as usize].prev_phys;
+        if prev != NIL && self.nodes[prev as usize].free {
+            self.remove_free(prev);
+            self.absorb_next(prev);
+            node = prev;
+        }
+
+        // Merge with the next range.
+        let next = self.nodes[node as usize].next_phys;
+        if next != NIL && self.nodes[next as usize].free {
+            self.remove_free(next);
+            self.absorb_next(node);
+        }
+
+        self.insert_free(node);
+    }
+
+    // Merges the range after `node` into `node`.
+    fn absorb_next(&mut self, node: u32) {
+        let next = self.nodes[node as usize].next_phys;
+        let Node {
+            size, next_phys, ..
+        } = self.nodes[next as usize];
+
+        self.nodes[node as usize].size += size;
+        self.nodes[node as usize].next_phys = next_phys;
+        if next_phys != NIL {
+            self.nodes[next_phys as usize].prev_phys = node;
+        }
+
+        self.unused_nodes.push(next);
+    }
+
+    // Returns true if `size` bytes aligned to `alignment` fit in the free range `node`.
+    fn fits(&self, node: u32, size: DeviceSize, alignment: DeviceSize) -> bool {
+        let node = &self.nodes[node as usize];
+        let padding = align_up(node.offset, alignment) - node.offset;
+        node.size >= padding && node.size - padding >= size
+    }
+
+    // Returns a free range that is at least `size` bytes large.
+    fn find_free(&self, size: DeviceSize) -> Option<u32> {
+        let (fl, sl) = mapping_search(size)?;
+
+        if fl >= self.sl_bitmaps.len() {
+            return None;
+        }
+
+        let sl_map = self.sl_bitmaps[fl] & (!0u32 << sl);
+        let (fl, sl) = if sl_map != 0 {
+            (fl, sl_map.trailing_zeros() as usize)
+        } else {
+            let fl_map = self.fl_bitmap & (!0u64).checked_shl(fl as u32 + 1).unwrap_or(0);
+            if fl_map == 0 {
+                return None;
+            }
+            let fl = fl_map.trailing_zeros() as usize;
+            (fl, self.sl_bitmaps[fl].trailing_zeros() as usize)
+        };
+
+        let node = self.heads[fl][sl];
+        debug_assert!(node != NIL);
+        Some(node)
+    }
+
+    fn insert_free(&mut self, node: u32) {
+        let (fl, sl) = mapping(self.nodes[node as usize].size);
+        let head = self.heads[fl][sl];
+
+        self.nodes[node as usize].free = true;
+        self.nodes[node as usize].prev_free = NIL;
+        self.nodes[node as usize].next_free = head;
+        if head != NIL {
+            self.nodes[head as usize].prev_free = node;
+        }
+
+        self.heads[fl][sl] = node;
+        self.sl_bitmaps[fl] |= 1 << sl;
+        self.fl_bitmap |= 1 << fl;
+    }
+
+    fn remove_free(&mut self, node: u32) {
+        let Node {
+            size,
+            prev_free,
+            next_free,
+            ..
+        } = self.nodes[node as usize];
+
+        if prev_free != NIL {
+            self.nodes[prev_free as usize].next_free = next_free;
+        } else {
+            let (fl, sl) = mapping(size);
+            self.heads[fl][sl] = next_free;
+
+            if next_free == NIL {
+                self.sl_bitmaps[fl] &= !(1 << sl);
+                if self.sl_bitmaps[fl] == 0 {
+                    self.fl_bitmap &= !(1 << fl);
+                }
+            }
+        }
+
+        if next_free != NIL {
+            self.nodes[next_free as usize].prev_free = prev_free;
+        }
+    }
+
+    fn new_node(&mut self, node: Node) -> u32 {
+        match self.unused_nodes.pop() {
+            Some(index) => {
+                self.nodes[index as usize] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                (self.nodes.len() - 1) as u32
+            }
+        }
+    }
+}
+
+#[inline]
+fn align_up(value: DeviceSize, alignment: DeviceSize) -> DeviceSize {
+    (value + alignment - 1) & !(alignment - 1)
+}
+
+#[inline]
+fn log2(value: DeviceSize) -> u32 {
+    63 - value.leading_zeros()
+}
+
+// Returns the size class that a free range of `size` bytes belongs to.
+fn mapping(size: DeviceSize) -> (usize, usize) {
+    if size < SMALL_SIZE {
+        (0, size as usize)
+    } else {
+        let fl = log2(size);
+        let sl = (size >> (fl - SL_LOG2)) as usize - SL_COUNT;
+        ((fl - SL_LOG2 + 1) as usize, sl)
+    }
+}
+
+// Returns the smallest size class whose free ranges are all at least `size` bytes large.
+fn mapping_search(size: DeviceSize) -> Option<(usize, usize)> {
+    if size < SMALL_SIZE {
+        Some(mapping(size))
+    } else {
+        let round = (1 << (log2(size) - SL_LOG2)) - 1;
+        size.checked_add(round).map(mapping)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tlsf;
+
+    #[test]
+    fn alloc_free_merge() {
+        let mut tlsf = Tlsf::new(1024);
+
+        let a = tlsf.alloc(100, 1).unwrap();
+        let b = tlsf.alloc(200, 256).unwrap();
+        let c = tlsf.alloc(300, 4).unwrap();
+        assert_eq!(a.offset(), 0);
+        assert_eq!(b.offset() % 256, 0);
+        assert!(c.offset() >= b.offset() + 200 || c.offset() + 300 <= b.offset());
+        assert!(tlsf.alloc(1024, 1).is_none());
+
+        tlsf.free(b);
+        tlsf.free(a);
+        tlsf.free(c);
+        assert!(tlsf.is_empty());
+
+        // Everything was merged back into a single range.
+        let d = tlsf.alloc(1024, 1).unwrap();
+        assert_eq!(d.offset(), 0);
+    }
+
+    #[test]
+    fn ranges_cover_capacity() {
+        let mut tlsf = Tlsf::new(1024);
+        let _a = tlsf.alloc(100, 1).unwrap();
+        let _b = tlsf.alloc(200, 256).unwrap();
+
+        let mut end = 0;
+        for (offset, size, _) in tlsf.ranges() {
+            assert_eq!(offset, end);
+            end += size;
+        }
+        assert_eq!(end, 1024);
+        assert_eq!(tlsf.ranges().filter(|&(_, _, free)| !free).count(), 2);
+    }
+
+    #[test]
+    fn reuse_after_free() {
+        let mut tlsf = Tlsf::new(4096);
+
+        let allocs: Vec<_> = (0..16).map(|_| tlsf.alloc(256, 256).unwrap()).collect();
+        assert!(tlsf.alloc(1, 1).is_none());
+
+        let freed = allocs[7];
+        tlsf.free(freed);
+        assert_eq!(tlsf.alloc(256, 256), Some(freed));
+    }
+}
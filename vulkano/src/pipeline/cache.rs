@@ -20,14 +20,34 @@
 //! Once that is done, you can extract the data from the cache and store it. See the documentation
 //! of [`get_data`](crate::pipeline::cache::PipelineCache::get_data) for example of how to store the data
 //! on the disk, and [`with_data`](crate::pipeline::cache::PipelineCache::with_data) for how to reload it.
+//!
+//! The data starts with a header that identifies the physical device and driver that produced
+//! it. [`with_checked_data`](crate::pipeline::cache::PipelineCache::with_checked_data) refuses
+//! data whose header doesn't match the device, and
+//! [`from_file_or_empty`](crate::pipeline::cache::PipelineCache::from_file_or_empty) and
+//! [`save_to_file`](crate::pipeline::cache::PipelineCache::save_to_file) take care of reading
+//! and writing the data to a file.
+//!
+//! When pipelines are compiled from several threads, each thread can use its own cache, and the
+//! caches can then be combined into one with
+//! [`merge`](crate::pipeline::cache::PipelineCache::merge) before being stored.
 
 use crate::check_errors;
+use crate::device::physical::PhysicalDevice;
 use crate::device::Device;
+use crate::device::DeviceOwned;
 use crate::OomError;
 use crate::VulkanObject;
+use std::convert::TryInto;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
 use std::mem::MaybeUninit;
+use std::path::Path;
 use std::ptr;
 use std::sync::Arc;
+use std::sync::Mutex;
 
 /// Opaque cache that contains pipeline objects.
 ///
@@ -36,6 +56,8 @@ use std::sync::Arc;
 pub struct PipelineCache {
     device: Arc<Device>,
     cache: ash::vk::PipelineCache,
+    // Held while the cache is the destination of `vkMergePipelineCaches`.
+    merge_lock: Mutex<()>,
 }
 
 impl PipelineCache {
@@ -50,7 +72,9 @@ impl PipelineCache {
     ///
     /// This example loads a cache from a file, if it exists.
     /// See [`get_data`](#method.get_data) for how to store the data in a file.
-    /// TODO: there's a header in the cached data that must be checked ; talk about this
+    ///
+    /// The header of the data is not checked. Use [`with_checked_data`](#method.with_checked_data)
+    /// to make sure that the data was produced by the same device and driver.
     ///
     /// ```
     /// # use std::sync::Arc;
@@ -85,6 +109,64 @@ impl PipelineCache {
         PipelineCache::new_impl(device, Some(initial_data))
     }
 
+    /// Same as [`with_data`](#method.with_data), but first checks that the header of the data
+    /// matches the physical device of `device`.
+    ///
+    /// This catches data that was produced by a different device or driver version, or data that
+    /// was truncated. The rest of the data is still passed as-is to the Vulkan implementation,
+    /// which is why this function remains unsafe.
+    pub unsafe fn with_checked_data(
+        device: Arc<Device>,
+        initial_data: &[u8],
+    ) -> Result<Arc<PipelineCache>, PipelineCacheCreationError> {
+        let header = PipelineCacheHeader::from_data(initial_data)
+            .ok_or(PipelineCacheCreationError::DataInvalid)?;
+
+        if header != PipelineCacheHeader::for_physical_device(device.physical_device()) {
+            return Err(PipelineCacheCreationError::DataIncompatible);
+        }
+
+        Ok(PipelineCache::new_impl(device, Some(initial_data))?)
+    }
+
+    /// Loads a pipeline cache from the file at `path`, or builds an empty one if the file can't
+    /// be read or its data doesn't match the device.
+    ///
+    /// The file should have been written with [`save_to_file`](#method.save_to_file).
+    ///
+    /// # Safety
+    ///
+    /// Only the header of the data is checked, see [`with_checked_data`](#method.with_checked_data).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use vulkano::device::Device;
+    /// use vulkano::pipeline::cache::PipelineCache;
+    /// # let device: Arc<Device> = return;
+    ///
+    /// let cache = unsafe { PipelineCache::from_file_or_empty(device.clone(), "pipeline_cache.bin") }
+    ///     .unwrap();
+    /// ```
+    pub unsafe fn from_file_or_empty<P>(
+        device: Arc<Device>,
+        path: P,
+    ) -> Result<Arc<PipelineCache>, OomError>
+    where
+        P: AsRef<Path>,
+    {
+        if let Ok(data) = fs::read(path) {
+            match PipelineCache::with_checked_data(device.clone(), &data) {
+                Ok(cache) => return Ok(cache),
+                Err(PipelineCacheCreationError::OomError(err)) => return Err(err),
+                Err(_) => (),
+            }
+        }
+
+        PipelineCache::empty(device)
+    }
+
     /// Builds a new empty pipeline cache.
     ///
     /// # Example
@@ -131,6 +213,7 @@ impl PipelineCache {
         Ok(Arc::new(PipelineCache {
             device: device.clone(),
             cache: cache,
+            merge_lock: Mutex::new(()),
         }))
     }
 
@@ -138,12 +221,24 @@ impl PipelineCache {
     ///
     /// It is `self` that is modified here. The pipeline caches passed as parameter are untouched.
     ///
+    /// Merges into the same cache from several threads are serialized.
+    ///
     /// # Panic
     ///
     /// - Panics if `self` is included in the list of other pipelines.
+    /// - Panics if one of the other pipelines doesn't belong to the same device as `self`.
+    ///
+    /// # Example
     ///
-    // FIXME: vkMergePipelineCaches is not thread safe for the destination cache
-    // TODO: write example
+    /// This example combines caches that were used by several threads into one.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use vulkano::pipeline::cache::PipelineCache;
+    /// # let cache: Arc<PipelineCache> = return;
+    /// # let thread_caches: Vec<Arc<PipelineCache>> = return;
+    /// cache.merge(thread_caches.iter().collect::<Vec<_>>().iter()).unwrap();
+    /// ```
     pub fn merge<'a, I>(&self, pipelines: I) -> Result<(), OomError>
     where
         I: IntoIterator<Item = &'a &'a Arc<PipelineCache>>,
@@ -155,10 +250,15 @@ impl PipelineCache {
                 .into_iter()
                 .map(|pipeline| {
                     assert!(&***pipeline as *const _ != &*self as *const _);
+                    assert_eq!(
+                        pipeline.device.internal_object(),
+                        self.device.internal_object()
+                    );
                     pipeline.cache
                 })
                 .collect::<Vec<_>>();
 
+            let _lock = self.merge_lock.lock().unwrap();
             check_errors(fns.v1_0.merge_pipeline_caches(
                 self.device.internal_object(),
                 self.cache,
@@ -222,6 +322,38 @@ impl PipelineCache {
             Ok(data)
         }
     }
+
+    /// Writes the data of the cache to the file at `path`, so that it can be reloaded with
+    /// [`from_file_or_empty`](#method.from_file_or_empty).
+    ///
+    /// The data is first written to a temporary file next to `path`, which is then renamed, so
+    /// that an interrupted write doesn't leave a truncated cache behind.
+    pub fn save_to_file<P>(&self, path: P) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let data = self
+            .get_data()
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+
+        if let Err(err) = fs::write(&tmp_path, &data) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(err);
+        }
+
+        fs::rename(&tmp_path, path)
+    }
+}
+
+unsafe impl DeviceOwned for PipelineCache {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
 }
 
 unsafe impl VulkanObject for PipelineCache {
@@ -244,11 +376,123 @@ impl Drop for PipelineCache {
     }
 }
 
+/// The header at the start of the data of a pipeline cache.
+///
+/// Caches can only be reused by the same physical device and driver version that produced them.
+/// The Vulkan implementation ignores data whose header doesn't match, but this header allows
+/// checking it beforehand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineCacheHeader {
+    /// The vendor ID of the physical device.
+    pub vendor_id: u32,
+    /// The device ID of the physical device.
+    pub device_id: u32,
+    /// The pipeline cache UUID of the physical device, which usually changes with the driver
+    /// version.
+    pub pipeline_cache_uuid: [u8; 16],
+}
+
+impl PipelineCacheHeader {
+    // Size of the header for `VK_PIPELINE_CACHE_HEADER_VERSION_ONE`.
+    const SIZE: usize = 32;
+
+    /// Reads the header at the start of `data`.
+    ///
+    /// Returns `None` if `data` is too short, or if the header is not a
+    /// `VK_PIPELINE_CACHE_HEADER_VERSION_ONE` header.
+    pub fn from_data(data: &[u8]) -> Option<PipelineCacheHeader> {
+        if data.len() < Self::SIZE {
+            return None;
+        }
+
+        // The fields of the header are always stored as little-endian.
+        let read_u32 =
+            |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+
+        let header_size = read_u32(0) as usize;
+        let header_version = read_u32(4);
+
+        if header_size < Self::SIZE
+            || header_size > data.len()
+            || header_version != ash::vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32
+        {
+            return None;
+        }
+
+        Some(PipelineCacheHeader {
+            vendor_id: read_u32(8),
+            device_id: read_u32(12),
+            pipeline_cache_uuid: data[16..32].try_into().unwrap(),
+        })
+    }
+
+    /// Returns the header that `physical_device` writes to, and expects in, its pipeline caches.
+    #[inline]
+    pub fn for_physical_device(physical_device: PhysicalDevice) -> PipelineCacheHeader {
+        let properties = physical_device.properties();
+
+        PipelineCacheHeader {
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            pipeline_cache_uuid: properties.pipeline_cache_uuid,
+        }
+    }
+}
+
+/// Error that can happen when creating a pipeline cache from existing data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PipelineCacheCreationError {
+    /// The header of the data was produced by a different physical device or driver version.
+    DataIncompatible,
+
+    /// The data doesn't start with a valid header.
+    DataInvalid,
+
+    /// Not enough memory.
+    OomError(OomError),
+}
+
+impl error::Error for PipelineCacheCreationError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Self::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PipelineCacheCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                Self::DataIncompatible => {
+                    "the data was produced by a different physical device or driver version"
+                }
+                Self::DataInvalid => "the data doesn't start with a valid pipeline cache header",
+                Self::OomError(_) => "not enough memory available",
+            }
+        )
+    }
+}
+
+impl From<OomError> for PipelineCacheCreationError {
+    #[inline]
+    fn from(err: OomError) -> Self {
+        Self::OomError(err)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::pipeline::cache::PipelineCache;
-    use crate::shader::ShaderModule;
+    use crate::pipeline::cache::PipelineCacheCreationError;
+    use crate::pipeline::cache::PipelineCacheHeader;
     use crate::pipeline::ComputePipeline;
+    use crate::shader::ShaderModule;
     use std::sync::Arc;
 
     #[test]
@@ -260,6 +504,45 @@ mod tests {
         });
     }
 
+    #[test]
+    fn header_matches_device() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let cache = PipelineCache::empty(device.clone()).unwrap();
+        let data = cache.get_data().unwrap();
+
+        assert_eq!(
+            PipelineCacheHeader::from_data(&data),
+            Some(PipelineCacheHeader::for_physical_device(
+                device.physical_device()
+            ))
+        );
+        unsafe {
+            assert!(PipelineCache::with_checked_data(device.clone(), &data).is_ok());
+        }
+    }
+
+    #[test]
+    fn checked_data_rejects_bad_header() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let mut data = PipelineCache::empty(device.clone())
+            .unwrap()
+            .get_data()
+            .unwrap();
+
+        unsafe {
+            assert_eq!(
+                PipelineCache::with_checked_data(device.clone(), &data[..16]).unwrap_err(),
+                PipelineCacheCreationError::DataInvalid
+            );
+
+            data[16] = data[16].wrapping_add(1);
+            assert_eq!(
+                PipelineCache::with_checked_data(device.clone(), &data).unwrap_err(),
+                PipelineCacheCreationError::DataIncompatible
+            );
+        }
+    }
+
     #[test]
     fn cache_returns_same_data() {
         let (device, queue) = gfx_dev_and_queue!();
@@ -22,7 +22,11 @@
 //! will take precedence if it is activated, otherwise the blending operation is applied.
 
 use super::{DynamicState, GraphicsPipelineCreationError};
-use crate::{device::Device, pipeline::StateMode, render_pass::Subpass};
+use crate::{
+    device::{Device, DeviceOwned},
+    pipeline::StateMode,
+    render_pass::Subpass,
+};
 use fnv::FnvHashMap;
 use smallvec::SmallVec;
 
@@ -70,6 +74,58 @@ impl ColorBlendState {
         }
     }
 
+    /// Creates a `ColorBlendState` with one attachment entry for each color attachment of
+    /// `subpass`. Alpha blending is enabled for the attachments whose format supports blending,
+    /// and disabled for the others, such as integer formats. Logical operations are disabled and
+    /// the blend constants are set to zero.
+    ///
+    /// If the [`independent_blend`](crate::device::Features::independent_blend) feature is not
+    /// enabled on the device, all the attachments must have the same blend state. In that case,
+    /// alpha blending is only enabled if the formats of all the color attachments support it.
+    pub fn automatic_for_render_pass(subpass: &Subpass) -> Self {
+        let device = subpass.render_pass().device();
+        let attachments = subpass.render_pass().desc().attachments();
+
+        let supports_blend: SmallVec<[bool; 4]> = subpass
+            .subpass_desc()
+            .color_attachments
+            .iter()
+            .map(|&(atch_num, _)| {
+                attachments[atch_num]
+                    .format
+                    .properties(device.physical_device())
+                    .optimal_tiling_features
+                    .color_attachment_blend
+            })
+            .collect();
+        let all_support_blend = supports_blend.iter().all(|&supported| supported);
+
+        Self {
+            logic_op: None,
+            attachments: supports_blend
+                .into_iter()
+                .map(|supported| {
+                    let blend = if device.enabled_features().independent_blend {
+                        supported
+                    } else {
+                        all_support_blend
+                    };
+
+                    ColorBlendAttachmentState {
+                        blend: if blend {
+                            Some(AttachmentBlend::alpha())
+                        } else {
+                            None
+                        },
+                        color_write_mask: ColorComponents::all(),
+                        color_write_enable: StateMode::Fixed(true),
+                    }
+                })
+                .collect(),
+            blend_constants: StateMode::Fixed([0.0, 0.0, 0.0, 0.0]),
+        }
+    }
+
     /// Enables logical operations with the given logical operation.
     #[inline]
     pub fn logic_op(mut self, logic_op: LogicOp) -> Self {
@@ -182,8 +238,26 @@ impl ColorBlendState {
         let mut color_blend_attachments = SmallVec::with_capacity(self.attachments.len());
         let mut color_write_enables = SmallVec::with_capacity(self.attachments.len());
 
-        for state in self.attachments.iter() {
+        for (attachment_index, (state, &(atch_num, _))) in self
+            .attachments
+            .iter()
+            .zip(&subpass.subpass_desc().color_attachments)
+            .enumerate()
+        {
             let blend = if let Some(blend) = &state.blend {
+                if !subpass.render_pass().desc().attachments()[atch_num]
+                    .format
+                    .properties(device.physical_device())
+                    .optimal_tiling_features
+                    .color_attachment_blend
+                {
+                    return Err(
+                        GraphicsPipelineCreationError::ColorAttachmentFormatBlendNotSupported {
+                            attachment_index: attachment_index as u32,
+                        },
+                    );
+                }
+
                 if !device.enabled_features().dual_src_blend
                     && std::array::IntoIter::new([
                         blend.color_source,
@@ -666,3 +740,82 @@ impl From<ColorComponents> for ash::vk::ColorComponentFlags {
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::AttachmentBlend;
+    use super::ColorBlendState;
+    use crate::format::Format;
+    use crate::render_pass::Subpass;
+    use fnv::FnvHashMap;
+
+    #[test]
+    fn automatic_mixed_formats() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let render_pass = single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: Format::R8G8B8A8_UNORM,
+                    samples: 1,
+                },
+                index: {
+                    load: Clear,
+                    store: Store,
+                    format: Format::R32_UINT,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color, index],
+                depth_stencil: {}
+            }
+        )
+        .unwrap();
+        let subpass = Subpass::from(render_pass, 0).unwrap();
+
+        // Without `independent_blend`, blending can only be enabled for all the attachments.
+        let mut state = ColorBlendState::automatic_for_render_pass(&subpass);
+        assert!(state.attachments.iter().all(|state| state.blend.is_none()));
+        state
+            .to_vulkan_attachments(&device, &mut FnvHashMap::default(), &subpass)
+            .unwrap();
+    }
+
+    #[test]
+    fn automatic_mixed_formats_independent_blend() {
+        let (device, _) = gfx_dev_and_queue!(independent_blend);
+
+        let render_pass = single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: Format::R8G8B8A8_UNORM,
+                    samples: 1,
+                },
+                index: {
+                    load: Clear,
+                    store: Store,
+                    format: Format::R32_UINT,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color, index],
+                depth_stencil: {}
+            }
+        )
+        .unwrap();
+        let subpass = Subpass::from(render_pass, 0).unwrap();
+
+        let mut state = ColorBlendState::automatic_for_render_pass(&subpass);
+        assert_eq!(state.attachments[0].blend, Some(AttachmentBlend::alpha()));
+        assert_eq!(state.attachments[1].blend, None);
+        state
+            .to_vulkan_attachments(&device, &mut FnvHashMap::default(), &subpass)
+            .unwrap();
+    }
+}
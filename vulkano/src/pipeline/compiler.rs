@@ -0,0 +1,301 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Compiling graphics pipelines on background threads.
+//!
+//! Creating a graphics pipeline can take a long time if the implementation has to compile its
+//! shaders, which causes stutters if it happens in the middle of rendering. A `PipelineCompiler`
+//! owns a pool of threads that build pipelines in the background. Submitting a pipeline returns
+//! a `PipelineHandle`, which the render loop can poll every frame while it keeps drawing with
+//! another pipeline that already exists.
+//!
+//! A `GraphicsPipelineBuilder` borrows its shaders, so it can't be sent to another thread
+//! directly. Instead, the compiler is given a closure that owns the shader modules and returns
+//! the built pipeline.
+//!
+//! If the [`pipeline_creation_cache_control`](crate::device::Features::pipeline_creation_cache_control)
+//! feature is enabled, [`compile_or_defer`](PipelineCompiler::compile_or_defer) first tries to
+//! create the pipeline on the current thread with
+//! [`fail_on_compile_required`](crate::pipeline::GraphicsPipelineBuilder::fail_on_compile_required),
+//! which succeeds without delay if the pipeline is found in a cache. The pipeline is only
+//! compiled in the background if that fails.
+//!
+//! # Example
+//!
+//! ```
+//! # use std::sync::Arc;
+//! # use vulkano::device::Device;
+//! # use vulkano::pipeline::GraphicsPipeline;
+//! # use vulkano::pipeline::GraphicsPipelineCreationError;
+//! use vulkano::pipeline::compiler::PipelineCompiler;
+//! # let device: Arc<Device> = return;
+//! # let fallback: Arc<GraphicsPipeline> = return;
+//! # fn build(device: Arc<Device>, fail_on_compile_required: bool) -> Result<Arc<GraphicsPipeline>, GraphicsPipelineCreationError> { unimplemented!() }
+//!
+//! let compiler = PipelineCompiler::new(device.clone(), 2);
+//! let handle = compiler.compile_or_defer(move |fail_on_compile_required| {
+//!     build(device.clone(), fail_on_compile_required)
+//! });
+//!
+//! // In the render loop, draw with `fallback` until the new pipeline is ready.
+//! let pipeline = handle.pipeline_or(&fallback);
+//! ```
+
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::pipeline::GraphicsPipeline;
+use crate::pipeline::GraphicsPipelineCreationError;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::thread::JoinHandle;
+
+type PipelineResult = Result<Arc<GraphicsPipeline>, GraphicsPipelineCreationError>;
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A pool of threads that build graphics pipelines in the background.
+///
+/// Dropping the compiler waits for the pipelines that were already submitted to be built.
+pub struct PipelineCompiler {
+    device: Arc<Device>,
+    sender: Option<mpsc::Sender<Job>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl PipelineCompiler {
+    /// Starts a new `PipelineCompiler` with `num_threads` threads.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `num_threads` is 0.
+    /// - Panics if the threads can't be spawned.
+    pub fn new(device: Arc<Device>, num_threads: usize) -> PipelineCompiler {
+        assert!(num_threads != 0);
+
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let threads = (0..num_threads)
+            .map(|index| {
+                let receiver = receiver.clone();
+
+                thread::Builder::new()
+                    .name(format!("vulkano pipeline compiler {}", index))
+                    .spawn(move || loop {
+                        // The lock is released before running the job.
+                        let job = match receiver.lock().unwrap().recv() {
+                            Ok(job) => job,
+                            Err(_) => break,
+                        };
+
+                        // A panicking job drops its result sender, which the handle reports.
+                        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                    })
+                    .expect("failed to spawn a pipeline compiler thread")
+            })
+            .collect();
+
+        PipelineCompiler {
+            device,
+            sender: Some(sender),
+            threads,
+        }
+    }
+
+    /// Returns the number of threads of the compiler.
+    #[inline]
+    pub fn num_threads(&self) -> usize {
+        self.threads.len()
+    }
+
+    /// Queues `build` to be run on one of the threads of the compiler.
+    ///
+    /// The returned handle gives access to the pipeline once `build` has returned.
+    pub fn compile<F>(&self, build: F) -> PipelineHandle
+    where
+        F: FnOnce() -> PipelineResult + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+
+        self.sender
+            .as_ref()
+            .unwrap()
+            .send(Box::new(move || {
+                // The handle may have been dropped in the meantime.
+                let _ = sender.send(build());
+            }))
+            .unwrap();
+
+        PipelineHandle {
+            state: Mutex::new(HandleState::Pending(receiver)),
+        }
+    }
+
+    /// Builds a pipeline without compiling it on the current thread if possible, and queues it
+    /// to be compiled otherwise.
+    ///
+    /// `build` is given whether
+    /// [`fail_on_compile_required`](crate::pipeline::GraphicsPipelineBuilder::fail_on_compile_required)
+    /// must be enabled on the builder. If the `pipeline_creation_cache_control` feature is
+    /// enabled on the device, `build(true)` is first called on the current thread. If that fails
+    /// with `GraphicsPipelineCreationError::CompileRequired`, or if the feature is not enabled,
+    /// `build(false)` is queued like with [`compile`](#method.compile).
+    pub fn compile_or_defer<F>(&self, build: F) -> PipelineHandle
+    where
+        F: FnOnce(bool) -> PipelineResult + Clone + Send + 'static,
+    {
+        if self
+            .device
+            .enabled_features()
+            .pipeline_creation_cache_control
+        {
+            match build.clone()(true) {
+                Err(GraphicsPipelineCreationError::CompileRequired) => (),
+                result => return PipelineHandle::ready(result),
+            }
+        }
+
+        self.compile(move || build(false))
+    }
+}
+
+unsafe impl DeviceOwned for PipelineCompiler {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+impl Drop for PipelineCompiler {
+    fn drop(&mut self) {
+        // Closing the channel makes the threads exit once the queued jobs are done.
+        self.sender = None;
+
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Gives access to a pipeline that is being built by a `PipelineCompiler`.
+pub struct PipelineHandle {
+    state: Mutex<HandleState>,
+}
+
+enum HandleState {
+    Pending(mpsc::Receiver<PipelineResult>),
+    Done(PipelineResult),
+}
+
+impl PipelineHandle {
+    /// Returns a handle whose pipeline has already been built.
+    #[inline]
+    pub fn ready(result: PipelineResult) -> PipelineHandle {
+        PipelineHandle {
+            state: Mutex::new(HandleState::Done(result)),
+        }
+    }
+
+    /// Returns `true` if building the pipeline has finished, successfully or not.
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.try_get().is_some()
+    }
+
+    /// Returns the result of building the pipeline, or `None` if it hasn't finished yet.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the closure that builds the pipeline panicked.
+    pub fn try_get(&self) -> Option<PipelineResult> {
+        let mut state = self.state.lock().unwrap();
+
+        if let HandleState::Pending(ref receiver) = *state {
+            match receiver.try_recv() {
+                Ok(result) => *state = HandleState::Done(result),
+                Err(mpsc::TryRecvError::Empty) => return None,
+                Err(mpsc::TryRecvError::Disconnected) => panic!("building the pipeline panicked"),
+            }
+        }
+
+        match *state {
+            HandleState::Done(ref result) => Some(result.clone()),
+            HandleState::Pending(_) => unreachable!(),
+        }
+    }
+
+    /// Blocks the current thread until building the pipeline has finished, and returns the
+    /// result.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the closure that builds the pipeline panicked.
+    pub fn wait(&self) -> PipelineResult {
+        let mut state = self.state.lock().unwrap();
+
+        if let HandleState::Pending(ref receiver) = *state {
+            match receiver.recv() {
+                Ok(result) => *state = HandleState::Done(result),
+                Err(_) => panic!("building the pipeline panicked"),
+            }
+        }
+
+        match *state {
+            HandleState::Done(ref result) => result.clone(),
+            HandleState::Pending(_) => unreachable!(),
+        }
+    }
+
+    /// Returns the pipeline if it was built successfully, or `fallback` if it is not ready yet
+    /// or if building it failed.
+    #[inline]
+    pub fn pipeline_or(&self, fallback: &Arc<GraphicsPipeline>) -> Arc<GraphicsPipeline> {
+        match self.try_get() {
+            Some(Ok(pipeline)) => pipeline,
+            _ => fallback.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pipeline::compiler::PipelineCompiler;
+    use crate::pipeline::GraphicsPipelineCreationError;
+
+    #[test]
+    fn returns_result() {
+        let (device, _) = gfx_dev_and_queue!();
+        let compiler = PipelineCompiler::new(device, 2);
+
+        let handle = compiler.compile(|| Err(GraphicsPipelineCreationError::WrongShaderType));
+        assert_eq!(
+            handle.wait().unwrap_err(),
+            GraphicsPipelineCreationError::WrongShaderType
+        );
+        assert!(handle.is_ready());
+    }
+
+    #[test]
+    fn panicking_build() {
+        let (device, _) = gfx_dev_and_queue!();
+        let compiler = PipelineCompiler::new(device, 1);
+
+        let handle = compiler.compile(|| panic!());
+        assert_should_panic!({
+            handle.wait();
+        });
+
+        // The thread survives the panic.
+        let handle = compiler.compile(|| Err(GraphicsPipelineCreationError::WrongShaderType));
+        assert!(handle.wait().is_err());
+    }
+}
@@ -8,24 +8,32 @@
 // according to those terms.
 
 use crate::check_errors;
-use crate::descriptor_set::layout::{DescriptorSetDesc, DescriptorSetLayout};
+use crate::descriptor_set::layout::DescriptorSetDesc;
 use crate::device::{Device, DeviceOwned};
 use crate::pipeline::cache::PipelineCache;
+use crate::pipeline::creation_feedback::PipelineCreationFeedback;
+use crate::pipeline::executable;
 use crate::pipeline::layout::{
     PipelineLayout, PipelineLayoutCreationError, PipelineLayoutSupersetError,
 };
 use crate::pipeline::{Pipeline, PipelineBindPoint};
-use crate::shader::{DescriptorRequirements, EntryPoint, SpecializationConstants};
+use crate::shader::{
+    DescriptorRequirements, EntryPoint, LocalSizeSpecializationError, ShaderExecution,
+    SpecializationConstants, SpecializationMap, SpecializationMapEntry,
+};
 use crate::DeviceSize;
 use crate::Error;
 use crate::OomError;
+use crate::Version;
 use crate::VulkanObject;
 use fnv::FnvHashMap;
 use std::error;
 use std::fmt;
+use std::iter;
 use std::mem;
 use std::mem::MaybeUninit;
 use std::ptr;
+use std::slice;
 use std::sync::Arc;
 
 /// A pipeline object that describes to the Vulkan implementation how it should perform compute
@@ -42,6 +50,8 @@ pub struct ComputePipeline {
     layout: Arc<PipelineLayout>,
     descriptor_requirements: FnvHashMap<(u32, u32), DescriptorRequirements>,
     num_used_descriptor_sets: u32,
+    local_size: [u32; 3],
+    creation_feedback: Option<PipelineCreationFeedback>,
 }
 
 impl ComputePipeline {
@@ -61,19 +71,7 @@ impl ComputePipeline {
         Css: SpecializationConstants,
         F: FnOnce(&mut [DescriptorSetDesc]),
     {
-        let mut descriptor_set_layout_descs =
-            DescriptorSetDesc::from_requirements(shader.descriptor_requirements());
-        func(&mut descriptor_set_layout_descs);
-        let descriptor_set_layouts = descriptor_set_layout_descs
-            .iter()
-            .map(|desc| Ok(DescriptorSetLayout::new(device.clone(), desc.clone())?))
-            .collect::<Result<Vec<_>, PipelineLayoutCreationError>>()?;
-
-        let layout = PipelineLayout::new(
-            device.clone(),
-            descriptor_set_layouts,
-            shader.push_constant_requirements().cloned(),
-        )?;
+        let layout = shader_layout(&device, &shader, func)?;
 
         unsafe {
             ComputePipeline::with_unchecked_pipeline_layout(
@@ -86,6 +84,49 @@ impl ComputePipeline {
         }
     }
 
+    /// Builds a new `ComputePipeline`, with specialization constants that are set at runtime.
+    ///
+    /// This is the same as `new`, except that the values of the specialization constants are
+    /// taken from a `SpecializationMap` instead of a type that implements
+    /// `SpecializationConstants`. This is useful for shaders that are loaded at runtime, for which
+    /// `vulkano-shaders` can't generate a struct.
+    ///
+    /// An error will be returned if `specialization_map` contains a constant that `shader`
+    /// doesn't have, or that has a different size, which can happen if the map was built for
+    /// another entry point.
+    pub fn with_specialization_map<F>(
+        device: Arc<Device>,
+        shader: EntryPoint,
+        specialization_map: &SpecializationMap,
+        cache: Option<Arc<PipelineCache>>,
+        func: F,
+    ) -> Result<Arc<ComputePipeline>, ComputePipelineCreationError>
+    where
+        F: FnOnce(&mut [DescriptorSetDesc]),
+    {
+        let reqs: FnvHashMap<_, _> = shader.specialization_constant_requirements().collect();
+
+        for map_entry in specialization_map.entries() {
+            match reqs.get(&map_entry.constant_id) {
+                Some(reqs) if reqs.size == map_entry.size as DeviceSize => (),
+                _ => return Err(ComputePipelineCreationError::IncompatibleSpecializationConstants),
+            }
+        }
+
+        let layout = shader_layout(&device, &shader, func)?;
+
+        unsafe {
+            ComputePipeline::create(
+                device,
+                shader,
+                specialization_map.entries(),
+                specialization_map.data(),
+                layout,
+                cache,
+            )
+        }
+    }
+
     /// Builds a new `ComputePipeline` with a specific pipeline layout.
     ///
     /// An error will be returned if the pipeline layout isn't a superset of what the shader
@@ -100,23 +141,7 @@ impl ComputePipeline {
     where
         Css: SpecializationConstants,
     {
-        let spec_descriptors = Css::descriptors();
-
-        for (constant_id, reqs) in shader.specialization_constant_requirements() {
-            let map_entry = spec_descriptors
-                .iter()
-                .find(|desc| desc.constant_id == constant_id)
-                .ok_or(ComputePipelineCreationError::IncompatibleSpecializationConstants)?;
-
-            if map_entry.size as DeviceSize != reqs.size {
-                return Err(ComputePipelineCreationError::IncompatibleSpecializationConstants);
-            }
-        }
-
-        layout.ensure_compatible_with_shader(
-            shader.descriptor_requirements(),
-            shader.push_constant_requirements(),
-        )?;
+        check_shader::<Css>(&shader, &layout)?;
 
         unsafe {
             ComputePipeline::with_unchecked_pipeline_layout(
@@ -141,15 +166,36 @@ impl ComputePipeline {
     where
         Css: SpecializationConstants,
     {
+        let spec_data = slice::from_raw_parts(
+            specialization_constants as *const Css as *const u8,
+            mem::size_of_val(specialization_constants),
+        );
+
+        ComputePipeline::create(device, shader, Css::descriptors(), spec_data, layout, cache)
+    }
+
+    unsafe fn create(
+        device: Arc<Device>,
+        shader: EntryPoint,
+        spec_descriptors: &[SpecializationMapEntry],
+        spec_data: &[u8],
+        layout: Arc<PipelineLayout>,
+        cache: Option<Arc<PipelineCache>>,
+    ) -> Result<Arc<ComputePipeline>, ComputePipelineCreationError> {
+        let local_size = check_local_size(&device, &shader, spec_descriptors, spec_data)?;
         let fns = device.fns();
 
+        // Creation feedback, written by the implementation.
+        let has_creation_feedback = device.enabled_extensions().ext_pipeline_creation_feedback;
+        let mut pipeline_feedback = ash::vk::PipelineCreationFeedbackEXT::default();
+        let mut stage_feedback = ash::vk::PipelineCreationFeedbackEXT::default();
+
         let handle = {
-            let spec_descriptors = Css::descriptors();
             let specialization = ash::vk::SpecializationInfo {
                 map_entry_count: spec_descriptors.len() as u32,
                 p_map_entries: spec_descriptors.as_ptr() as *const _,
-                data_size: mem::size_of_val(specialization_constants),
-                p_data: specialization_constants as *const Css as *const _,
+                data_size: spec_data.len(),
+                p_data: spec_data.as_ptr() as *const _,
             };
 
             let stage = ash::vk::PipelineShaderStageCreateInfo {
@@ -165,8 +211,8 @@ impl ComputePipeline {
                 ..Default::default()
             };
 
-            let infos = ash::vk::ComputePipelineCreateInfo {
-                flags: ash::vk::PipelineCreateFlags::empty(),
+            let mut infos = ash::vk::ComputePipelineCreateInfo {
+                flags: executable::capture_flags(&device) | dispatch_base_flags(&device),
                 stage,
                 layout: layout.internal_object(),
                 base_pipeline_handle: ash::vk::Pipeline::null(),
@@ -174,6 +220,21 @@ impl ComputePipeline {
                 ..Default::default()
             };
 
+            let creation_feedback_info = if has_creation_feedback {
+                Some(ash::vk::PipelineCreationFeedbackCreateInfoEXT {
+                    p_pipeline_creation_feedback: &mut pipeline_feedback,
+                    pipeline_stage_creation_feedback_count: 1,
+                    p_pipeline_stage_creation_feedbacks: &mut stage_feedback,
+                    ..Default::default()
+                })
+            } else {
+                None
+            };
+
+            if let Some(creation_feedback_info) = creation_feedback_info.as_ref() {
+                infos.p_next = creation_feedback_info as *const _ as *const _;
+            }
+
             let cache_handle = match cache {
                 Some(ref cache) => cache.internal_object(),
                 None => ash::vk::PipelineCache::null(),
@@ -191,6 +252,215 @@ impl ComputePipeline {
             output.assume_init()
         };
 
+        let creation_feedback = if has_creation_feedback {
+            Some(PipelineCreationFeedback::from_vulkan(
+                &pipeline_feedback,
+                iter::once((ash::vk::ShaderStageFlags::COMPUTE, &stage_feedback)),
+            ))
+        } else {
+            None
+        };
+
+        Ok(ComputePipeline::from_handle(
+            device,
+            handle,
+            &shader,
+            layout,
+            local_size,
+            creation_feedback,
+        ))
+    }
+
+    /// Builds several `ComputePipeline`s that share the same pipeline layout with a single
+    /// `vkCreateComputePipelines` call.
+    ///
+    /// The first pipeline is created with derivatives allowed, and the other ones are created as
+    /// derivatives of it. This lets the implementation share work between pipelines that are
+    /// similar, for example variants of the same shader with different specialization constants.
+    /// The pipelines are returned in the same order as `shaders`.
+    ///
+    /// An error will be returned if the pipeline layout isn't a superset of what one of the
+    /// shaders uses.
+    pub fn batch_with_pipeline_layout<'a, Css, I>(
+        device: Arc<Device>,
+        shaders: I,
+        layout: Arc<PipelineLayout>,
+        cache: Option<Arc<PipelineCache>>,
+    ) -> Result<Vec<Arc<ComputePipeline>>, ComputePipelineCreationError>
+    where
+        Css: SpecializationConstants,
+        I: IntoIterator<Item = (EntryPoint<'a>, &'a Css)>,
+    {
+        let shaders: Vec<_> = shaders.into_iter().collect();
+
+        for (shader, _) in &shaders {
+            check_shader::<Css>(shader, &layout)?;
+        }
+
+        if shaders.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let fns = device.fns();
+        let spec_descriptors = Css::descriptors();
+
+        let local_sizes = shaders
+            .iter()
+            .map(|(shader, specialization_constants)| {
+                let spec_data = unsafe {
+                    slice::from_raw_parts(
+                        *specialization_constants as *const Css as *const u8,
+                        mem::size_of_val(*specialization_constants),
+                    )
+                };
+
+                check_local_size(&device, shader, spec_descriptors, spec_data)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Built before the stages, which point to them.
+        let specializations: Vec<_> = shaders
+            .iter()
+            .map(
+                |(_, specialization_constants)| ash::vk::SpecializationInfo {
+                    map_entry_count: spec_descriptors.len() as u32,
+                    p_map_entries: spec_descriptors.as_ptr() as *const _,
+                    data_size: mem::size_of_val(*specialization_constants),
+                    p_data: *specialization_constants as *const Css as *const _,
+                },
+            )
+            .collect();
+
+        // Creation feedback, written by the implementation.
+        let has_creation_feedback = device.enabled_extensions().ext_pipeline_creation_feedback;
+        let mut pipeline_feedbacks =
+            vec![ash::vk::PipelineCreationFeedbackEXT::default(); shaders.len()];
+        let mut stage_feedbacks =
+            vec![ash::vk::PipelineCreationFeedbackEXT::default(); shaders.len()];
+        let creation_feedback_infos: Vec<_> = pipeline_feedbacks
+            .iter_mut()
+            .zip(stage_feedbacks.iter_mut())
+            .map(|(pipeline_feedback, stage_feedback)| {
+                ash::vk::PipelineCreationFeedbackCreateInfoEXT {
+                    p_pipeline_creation_feedback: pipeline_feedback,
+                    pipeline_stage_creation_feedback_count: 1,
+                    p_pipeline_stage_creation_feedbacks: stage_feedback,
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        let infos: Vec<_> = shaders
+            .iter()
+            .zip(&specializations)
+            .enumerate()
+            .map(|(index, ((shader, _), specialization))| {
+                let stage = ash::vk::PipelineShaderStageCreateInfo {
+                    flags: ash::vk::PipelineShaderStageCreateFlags::empty(),
+                    stage: ash::vk::ShaderStageFlags::COMPUTE,
+                    module: shader.module().internal_object(),
+                    p_name: shader.name().as_ptr(),
+                    p_specialization_info: if specialization.data_size == 0 {
+                        ptr::null()
+                    } else {
+                        specialization
+                    },
+                    ..Default::default()
+                };
+
+                let (flags, base_pipeline_index) = if index == 0 {
+                    (ash::vk::PipelineCreateFlags::ALLOW_DERIVATIVES, -1)
+                } else {
+                    (ash::vk::PipelineCreateFlags::DERIVATIVE, 0)
+                };
+
+                ash::vk::ComputePipelineCreateInfo {
+                    p_next: if has_creation_feedback {
+                        &creation_feedback_infos[index] as *const _ as *const _
+                    } else {
+                        ptr::null()
+                    },
+                    flags: flags
+                        | executable::capture_flags(&device)
+                        | dispatch_base_flags(&device),
+                    stage,
+                    layout: layout.internal_object(),
+                    base_pipeline_handle: ash::vk::Pipeline::null(),
+                    base_pipeline_index,
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        let cache_handle = match cache {
+            Some(ref cache) => cache.internal_object(),
+            None => ash::vk::PipelineCache::null(),
+        };
+
+        let handles = unsafe {
+            // The implementation writes a null handle for each pipeline it fails to create.
+            let mut output = vec![ash::vk::Pipeline::null(); infos.len()];
+            let result = check_errors(fns.v1_0.create_compute_pipelines(
+                device.internal_object(),
+                cache_handle,
+                infos.len() as u32,
+                infos.as_ptr(),
+                ptr::null(),
+                output.as_mut_ptr(),
+            ));
+
+            if let Err(err) = result {
+                // Don't leak the pipelines that were created.
+                for handle in output {
+                    if handle != ash::vk::Pipeline::null() {
+                        fns.v1_0
+                            .destroy_pipeline(device.internal_object(), handle, ptr::null());
+                    }
+                }
+
+                return Err(err.into());
+            }
+
+            output
+        };
+
+        Ok(handles
+            .into_iter()
+            .zip(&shaders)
+            .zip(local_sizes)
+            .zip(pipeline_feedbacks.iter().zip(&stage_feedbacks))
+            .map(
+                |(((handle, (shader, _)), local_size), (pipeline_feedback, stage_feedback))| {
+                    let creation_feedback = if has_creation_feedback {
+                        Some(PipelineCreationFeedback::from_vulkan(
+                            pipeline_feedback,
+                            iter::once((ash::vk::ShaderStageFlags::COMPUTE, stage_feedback)),
+                        ))
+                    } else {
+                        None
+                    };
+
+                    ComputePipeline::from_handle(
+                        device.clone(),
+                        handle,
+                        shader,
+                        layout.clone(),
+                        local_size,
+                        creation_feedback,
+                    )
+                },
+            )
+            .collect())
+    }
+
+    fn from_handle(
+        device: Arc<Device>,
+        handle: ash::vk::Pipeline,
+        shader: &EntryPoint,
+        layout: Arc<PipelineLayout>,
+        local_size: [u32; 3],
+        creation_feedback: Option<PipelineCreationFeedback>,
+    ) -> Arc<ComputePipeline> {
         let descriptor_requirements: FnvHashMap<_, _> = shader
             .descriptor_requirements()
             .map(|(loc, reqs)| (loc, reqs.clone()))
@@ -202,13 +472,15 @@ impl ComputePipeline {
             .map(|x| x + 1)
             .unwrap_or(0);
 
-        Ok(Arc::new(ComputePipeline {
+        Arc::new(ComputePipeline {
             handle,
-            device: device.clone(),
+            device,
             layout,
             descriptor_requirements,
             num_used_descriptor_sets,
-        }))
+            local_size,
+            creation_feedback,
+        })
     }
 
     /// Returns the `Device` this compute pipeline was created with.
@@ -217,6 +489,23 @@ impl ComputePipeline {
         &self.device
     }
 
+    /// Returns the number of invocations in each dimension of a workgroup, after the
+    /// specialization constants have been applied.
+    #[inline]
+    pub fn local_size(&self) -> [u32; 3] {
+        self.local_size
+    }
+
+    /// Returns the feedback that the implementation returned when creating the pipeline.
+    ///
+    /// This is `None` if the
+    /// [`ext_pipeline_creation_feedback`](crate::device::DeviceExtensions::ext_pipeline_creation_feedback)
+    /// extension was not enabled on the device.
+    #[inline]
+    pub fn creation_feedback(&self) -> Option<&PipelineCreationFeedback> {
+        self.creation_feedback.as_ref()
+    }
+
     /// Returns an iterator over the descriptor requirements for this pipeline.
     #[inline]
     pub fn descriptor_requirements(
@@ -288,6 +577,106 @@ impl Drop for ComputePipeline {
     }
 }
 
+// Builds the pipeline layout that `shader` expects, after letting `func` modify the descriptor
+// set layouts.
+fn shader_layout<F>(
+    device: &Arc<Device>,
+    shader: &EntryPoint,
+    func: F,
+) -> Result<Arc<PipelineLayout>, ComputePipelineCreationError>
+where
+    F: FnOnce(&mut [DescriptorSetDesc]),
+{
+    let mut descriptor_set_layout_descs =
+        DescriptorSetDesc::from_requirements(shader.descriptor_requirements());
+    func(&mut descriptor_set_layout_descs);
+
+    Ok(PipelineLayout::get_or_create(
+        device.clone(),
+        descriptor_set_layout_descs,
+        shader.push_constant_requirements().cloned(),
+    )?)
+}
+
+// Returns the local size of `shader` with the given specialization constants, and checks it
+// against the limits of the device.
+fn check_local_size(
+    device: &Device,
+    shader: &EntryPoint,
+    spec_descriptors: &[SpecializationMapEntry],
+    spec_data: &[u8],
+) -> Result<[u32; 3], ComputePipelineCreationError> {
+    let local_size = match *shader.execution() {
+        ShaderExecution::Compute(execution) => {
+            execution.specialized_local_size(spec_descriptors, spec_data)?
+        }
+        _ => return Ok([1, 1, 1]),
+    };
+    let properties = device.physical_device().properties();
+
+    let max = properties.max_compute_work_group_size;
+    if local_size.iter().zip(&max).any(|(size, max)| size > max) {
+        return Err(
+            ComputePipelineCreationError::MaxComputeWorkGroupSizeExceeded {
+                max,
+                obtained: local_size,
+            },
+        );
+    }
+
+    let max = properties.max_compute_work_group_invocations;
+    let invocations = local_size.iter().map(|&size| size as u64).product::<u64>();
+    if invocations > max as u64 {
+        return Err(
+            ComputePipelineCreationError::MaxComputeWorkGroupInvocationsExceeded {
+                max,
+                obtained: invocations,
+            },
+        );
+    }
+
+    Ok(local_size)
+}
+
+// Returns the flags that allow the pipeline to be used with `dispatch_base`, if the device
+// supports it.
+fn dispatch_base_flags(device: &Device) -> ash::vk::PipelineCreateFlags {
+    if device.api_version() >= Version::V1_1 || device.enabled_extensions().khr_device_group {
+        ash::vk::PipelineCreateFlags::DISPATCH_BASE
+    } else {
+        ash::vk::PipelineCreateFlags::empty()
+    }
+}
+
+// Checks that the specialization constants and the pipeline layout match what `shader` expects.
+fn check_shader<Css>(
+    shader: &EntryPoint,
+    layout: &PipelineLayout,
+) -> Result<(), ComputePipelineCreationError>
+where
+    Css: SpecializationConstants,
+{
+    let spec_descriptors = Css::descriptors();
+
+    for (constant_id, reqs) in shader.specialization_constant_requirements() {
+        let map_entry = spec_descriptors
+            .iter()
+            .find(|desc| desc.constant_id == constant_id)
+            .ok_or(ComputePipelineCreationError::IncompatibleSpecializationConstants)?;
+
+        if map_entry.size as DeviceSize != reqs.size {
+            return Err(ComputePipelineCreationError::IncompatibleSpecializationConstants);
+        }
+    }
+
+    layout.ensure_compatible_with_shader(
+        shader.descriptor_requirements(),
+        shader.push_constant_requirements(),
+    )?;
+
+    Ok(())
+}
+
 /// Error that can happen when creating a compute pipeline.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ComputePipelineCreationError {
@@ -299,6 +688,23 @@ pub enum ComputePipelineCreationError {
     IncompatiblePipelineLayout(PipelineLayoutSupersetError),
     /// The provided specialization constants are not compatible with what the shader expects.
     IncompatibleSpecializationConstants,
+    /// The specialization constants of the local size of the shader could not be applied.
+    LocalSizeSpecializationError(LocalSizeSpecializationError),
+    /// The number of invocations in a workgroup exceeds the `max_compute_work_group_invocations`
+    /// limit.
+    MaxComputeWorkGroupInvocationsExceeded {
+        /// Maximum allowed value.
+        max: u32,
+        /// Value of the shader, after specialization.
+        obtained: u64,
+    },
+    /// The size of a workgroup exceeds the `max_compute_work_group_size` limit.
+    MaxComputeWorkGroupSizeExceeded {
+        /// Maximum allowed value.
+        max: [u32; 3],
+        /// Value of the shader, after specialization.
+        obtained: [u32; 3],
+    },
 }
 
 impl error::Error for ComputePipelineCreationError {
@@ -309,6 +715,9 @@ impl error::Error for ComputePipelineCreationError {
             ComputePipelineCreationError::PipelineLayoutCreationError(ref err) => Some(err),
             ComputePipelineCreationError::IncompatiblePipelineLayout(ref err) => Some(err),
             ComputePipelineCreationError::IncompatibleSpecializationConstants => None,
+            ComputePipelineCreationError::LocalSizeSpecializationError(ref err) => Some(err),
+            ComputePipelineCreationError::MaxComputeWorkGroupInvocationsExceeded { .. } => None,
+            ComputePipelineCreationError::MaxComputeWorkGroupSizeExceeded { .. } => None,
         }
     }
 }
@@ -330,6 +739,15 @@ impl fmt::Display for ComputePipelineCreationError {
                 ComputePipelineCreationError::IncompatibleSpecializationConstants => {
                     "the provided specialization constants are not compatible with what the shader expects"
                 }
+                ComputePipelineCreationError::LocalSizeSpecializationError(_) => {
+                    "the specialization constants of the local size could not be applied"
+                }
+                ComputePipelineCreationError::MaxComputeWorkGroupInvocationsExceeded { .. } => {
+                    "the number of invocations in a workgroup exceeds the device's limit"
+                }
+                ComputePipelineCreationError::MaxComputeWorkGroupSizeExceeded { .. } => {
+                    "the size of a workgroup exceeds the device's limit"
+                }
             }
         )
     }
@@ -356,6 +774,13 @@ impl From<PipelineLayoutSupersetError> for ComputePipelineCreationError {
     }
 }
 
+impl From<LocalSizeSpecializationError> for ComputePipelineCreationError {
+    #[inline]
+    fn from(err: LocalSizeSpecializationError) -> ComputePipelineCreationError {
+        ComputePipelineCreationError::LocalSizeSpecializationError(err)
+    }
+}
+
 impl From<Error> for ComputePipelineCreationError {
     #[inline]
     fn from(err: Error) -> ComputePipelineCreationError {
@@ -379,65 +804,256 @@ mod tests {
     use crate::command_buffer::CommandBufferUsage;
     use crate::descriptor_set::PersistentDescriptorSet;
     use crate::pipeline::ComputePipeline;
+    use crate::pipeline::ComputePipelineCreationError;
     use crate::pipeline::Pipeline;
     use crate::pipeline::PipelineBindPoint;
+    use crate::shader::LocalSizeSpecializationError;
+    use crate::shader::ShaderExecution;
     use crate::shader::ShaderModule;
+    use crate::shader::SpecializationConstantType;
     use crate::shader::SpecializationConstants;
+    use crate::shader::SpecializationMap;
     use crate::shader::SpecializationMapEntry;
+    use crate::shader::SpecializationMapError;
     use crate::sync::now;
     use crate::sync::GpuFuture;
+    use std::sync::Arc;
 
     // TODO: test for basic creation
     // TODO: test for pipeline layout error
 
-    #[test]
-    fn specialization_constants() {
-        // This test checks whether specialization constants work.
-        // It executes a single compute shader (one invocation) that writes the value of a spec.
-        // constant to a buffer. The buffer content is then checked for the right value.
+    /*
+    #version 450
 
-        let (device, queue) = gfx_dev_and_queue!();
+    layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
 
-        let module = unsafe {
-            /*
-            #version 450
+    layout(constant_id = 83) const int VALUE = 0xdeadbeef;
 
-            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+    layout(set = 0, binding = 0) buffer Output {
+        int write;
+    } write;
 
-            layout(constant_id = 83) const int VALUE = 0xdeadbeef;
+    void main() {
+        write.write = VALUE;
+    }
+    */
+    const SPEC_CONSTANT_MODULE: [u8; 480] = [
+        3, 2, 35, 7, 0, 0, 1, 0, 1, 0, 8, 0, 14, 0, 0, 0, 0, 0, 0, 0, 17, 0, 2, 0, 1, 0, 0, 0, 11,
+        0, 6, 0, 1, 0, 0, 0, 71, 76, 83, 76, 46, 115, 116, 100, 46, 52, 53, 48, 0, 0, 0, 0, 14, 0,
+        3, 0, 0, 0, 0, 0, 1, 0, 0, 0, 15, 0, 5, 0, 5, 0, 0, 0, 4, 0, 0, 0, 109, 97, 105, 110, 0, 0,
+        0, 0, 16, 0, 6, 0, 4, 0, 0, 0, 17, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 3, 0, 3, 0,
+        2, 0, 0, 0, 194, 1, 0, 0, 5, 0, 4, 0, 4, 0, 0, 0, 109, 97, 105, 110, 0, 0, 0, 0, 5, 0, 4,
+        0, 7, 0, 0, 0, 79, 117, 116, 112, 117, 116, 0, 0, 6, 0, 5, 0, 7, 0, 0, 0, 0, 0, 0, 0, 119,
+        114, 105, 116, 101, 0, 0, 0, 5, 0, 4, 0, 9, 0, 0, 0, 119, 114, 105, 116, 101, 0, 0, 0, 5,
+        0, 4, 0, 11, 0, 0, 0, 86, 65, 76, 85, 69, 0, 0, 0, 72, 0, 5, 0, 7, 0, 0, 0, 0, 0, 0, 0, 35,
+        0, 0, 0, 0, 0, 0, 0, 71, 0, 3, 0, 7, 0, 0, 0, 3, 0, 0, 0, 71, 0, 4, 0, 9, 0, 0, 0, 34, 0,
+        0, 0, 0, 0, 0, 0, 71, 0, 4, 0, 9, 0, 0, 0, 33, 0, 0, 0, 0, 0, 0, 0, 71, 0, 4, 0, 11, 0, 0,
+        0, 1, 0, 0, 0, 83, 0, 0, 0, 19, 0, 2, 0, 2, 0, 0, 0, 33, 0, 3, 0, 3, 0, 0, 0, 2, 0, 0, 0,
+        21, 0, 4, 0, 6, 0, 0, 0, 32, 0, 0, 0, 1, 0, 0, 0, 30, 0, 3, 0, 7, 0, 0, 0, 6, 0, 0, 0, 32,
+        0, 4, 0, 8, 0, 0, 0, 2, 0, 0, 0, 7, 0, 0, 0, 59, 0, 4, 0, 8, 0, 0, 0, 9, 0, 0, 0, 2, 0, 0,
+        0, 43, 0, 4, 0, 6, 0, 0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 50, 0, 4, 0, 6, 0, 0, 0, 11, 0, 0, 0,
+        239, 190, 173, 222, 32, 0, 4, 0, 12, 0, 0, 0, 2, 0, 0, 0, 6, 0, 0, 0, 54, 0, 5, 0, 2, 0, 0,
+        0, 4, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 248, 0, 2, 0, 5, 0, 0, 0, 65, 0, 5, 0, 12, 0, 0, 0,
+        13, 0, 0, 0, 9, 0, 0, 0, 10, 0, 0, 0, 62, 0, 3, 0, 13, 0, 0, 0, 11, 0, 0, 0, 253, 0, 1, 0,
+        56, 0, 1, 0,
+    ];
 
-            layout(set = 0, binding = 0) buffer Output {
-                int write;
-            } write;
+    /*
+    #version 450
 
-            void main() {
-                write.write = VALUE;
-            }
-            */
-            const MODULE: [u8; 480] = [
-                3, 2, 35, 7, 0, 0, 1, 0, 1, 0, 8, 0, 14, 0, 0, 0, 0, 0, 0, 0, 17, 0, 2, 0, 1, 0, 0,
-                0, 11, 0, 6, 0, 1, 0, 0, 0, 71, 76, 83, 76, 46, 115, 116, 100, 46, 52, 53, 48, 0,
-                0, 0, 0, 14, 0, 3, 0, 0, 0, 0, 0, 1, 0, 0, 0, 15, 0, 5, 0, 5, 0, 0, 0, 4, 0, 0, 0,
-                109, 97, 105, 110, 0, 0, 0, 0, 16, 0, 6, 0, 4, 0, 0, 0, 17, 0, 0, 0, 1, 0, 0, 0, 1,
-                0, 0, 0, 1, 0, 0, 0, 3, 0, 3, 0, 2, 0, 0, 0, 194, 1, 0, 0, 5, 0, 4, 0, 4, 0, 0, 0,
-                109, 97, 105, 110, 0, 0, 0, 0, 5, 0, 4, 0, 7, 0, 0, 0, 79, 117, 116, 112, 117, 116,
-                0, 0, 6, 0, 5, 0, 7, 0, 0, 0, 0, 0, 0, 0, 119, 114, 105, 116, 101, 0, 0, 0, 5, 0,
-                4, 0, 9, 0, 0, 0, 119, 114, 105, 116, 101, 0, 0, 0, 5, 0, 4, 0, 11, 0, 0, 0, 86,
-                65, 76, 85, 69, 0, 0, 0, 72, 0, 5, 0, 7, 0, 0, 0, 0, 0, 0, 0, 35, 0, 0, 0, 0, 0, 0,
-                0, 71, 0, 3, 0, 7, 0, 0, 0, 3, 0, 0, 0, 71, 0, 4, 0, 9, 0, 0, 0, 34, 0, 0, 0, 0, 0,
-                0, 0, 71, 0, 4, 0, 9, 0, 0, 0, 33, 0, 0, 0, 0, 0, 0, 0, 71, 0, 4, 0, 11, 0, 0, 0,
-                1, 0, 0, 0, 83, 0, 0, 0, 19, 0, 2, 0, 2, 0, 0, 0, 33, 0, 3, 0, 3, 0, 0, 0, 2, 0, 0,
-                0, 21, 0, 4, 0, 6, 0, 0, 0, 32, 0, 0, 0, 1, 0, 0, 0, 30, 0, 3, 0, 7, 0, 0, 0, 6, 0,
-                0, 0, 32, 0, 4, 0, 8, 0, 0, 0, 2, 0, 0, 0, 7, 0, 0, 0, 59, 0, 4, 0, 8, 0, 0, 0, 9,
-                0, 0, 0, 2, 0, 0, 0, 43, 0, 4, 0, 6, 0, 0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 50, 0, 4, 0,
-                6, 0, 0, 0, 11, 0, 0, 0, 239, 190, 173, 222, 32, 0, 4, 0, 12, 0, 0, 0, 2, 0, 0, 0,
-                6, 0, 0, 0, 54, 0, 5, 0, 2, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 248, 0, 2,
-                0, 5, 0, 0, 0, 65, 0, 5, 0, 12, 0, 0, 0, 13, 0, 0, 0, 9, 0, 0, 0, 10, 0, 0, 0, 62,
-                0, 3, 0, 13, 0, 0, 0, 11, 0, 0, 0, 253, 0, 1, 0, 56, 0, 1, 0,
+    layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+    void main() {
+    }
+    */
+    const LOCAL_SIZE_MODULE: [u8; 308] = [
+        3, 2, 35, 7, 0, 0, 1, 0, 0, 0, 0, 0, 11, 0, 0, 0, 0, 0, 0, 0, 17, 0, 2, 0, 1, 0, 0, 0, 14,
+        0, 3, 0, 0, 0, 0, 0, 1, 0, 0, 0, 15, 0, 5, 0, 5, 0, 0, 0, 1, 0, 0, 0, 109, 97, 105, 110, 0,
+        0, 0, 0, 16, 0, 6, 0, 1, 0, 0, 0, 17, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 71, 0,
+        4, 0, 6, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 71, 0, 4, 0, 7, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0,
+        71, 0, 4, 0, 8, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 71, 0, 4, 0, 9, 0, 0, 0, 11, 0, 0, 0, 25,
+        0, 0, 0, 19, 0, 2, 0, 2, 0, 0, 0, 33, 0, 3, 0, 3, 0, 0, 0, 2, 0, 0, 0, 21, 0, 4, 0, 4, 0,
+        0, 0, 32, 0, 0, 0, 0, 0, 0, 0, 23, 0, 4, 0, 5, 0, 0, 0, 4, 0, 0, 0, 3, 0, 0, 0, 50, 0, 4,
+        0, 4, 0, 0, 0, 6, 0, 0, 0, 1, 0, 0, 0, 50, 0, 4, 0, 4, 0, 0, 0, 7, 0, 0, 0, 1, 0, 0, 0, 50,
+        0, 4, 0, 4, 0, 0, 0, 8, 0, 0, 0, 1, 0, 0, 0, 51, 0, 6, 0, 5, 0, 0, 0, 9, 0, 0, 0, 6, 0, 0,
+        0, 7, 0, 0, 0, 8, 0, 0, 0, 54, 0, 5, 0, 2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0,
+        248, 0, 2, 0, 10, 0, 0, 0, 253, 0, 1, 0, 56, 0, 1, 0,
+    ];
+
+    #[derive(Debug, Copy, Clone)]
+    #[repr(C)]
+    struct LocalSize {
+        x: u32,
+        y: u32,
+        z: u32,
+    }
+    unsafe impl SpecializationConstants for LocalSize {
+        fn descriptors() -> &'static [SpecializationMapEntry] {
+            static DESCRIPTORS: [SpecializationMapEntry; 3] = [
+                SpecializationMapEntry {
+                    constant_id: 0,
+                    offset: 0,
+                    size: 4,
+                },
+                SpecializationMapEntry {
+                    constant_id: 1,
+                    offset: 4,
+                    size: 4,
+                },
+                SpecializationMapEntry {
+                    constant_id: 2,
+                    offset: 8,
+                    size: 4,
+                },
             ];
-            ShaderModule::from_bytes(device.clone(), &MODULE).unwrap()
+            &DESCRIPTORS
+        }
+    }
+
+    #[test]
+    fn specialized_local_size() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let module =
+            unsafe { ShaderModule::from_bytes(device.clone(), &LOCAL_SIZE_MODULE).unwrap() };
+        let shader = module.entry_point("main").unwrap();
+
+        let pipeline = ComputePipeline::new(
+            device,
+            shader,
+            &LocalSize { x: 2, y: 3, z: 4 },
+            None,
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(pipeline.local_size(), [2, 3, 4]);
+    }
+
+    #[test]
+    fn specialized_local_size_invalid_entries() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let module =
+            unsafe { ShaderModule::from_bytes(device.clone(), &LOCAL_SIZE_MODULE).unwrap() };
+        let shader = module.entry_point("main").unwrap();
+        let execution = match *shader.execution() {
+            ShaderExecution::Compute(execution) => execution,
+            _ => panic!(),
         };
 
+        let entries = [SpecializationMapEntry {
+            constant_id: 1,
+            offset: 0,
+            size: 2,
+        }];
+        assert_eq!(
+            execution.specialized_local_size(&entries, &[0; 4]),
+            Err(LocalSizeSpecializationError::SizeMismatch {
+                constant_id: 1,
+                size: 2,
+            }),
+        );
+
+        let entries = [SpecializationMapEntry {
+            constant_id: 2,
+            offset: 2,
+            size: 4,
+        }];
+        assert_eq!(
+            execution.specialized_local_size(&entries, &[0; 4]),
+            Err(LocalSizeSpecializationError::DataOutOfRange {
+                constant_id: 2,
+                offset: 2,
+                data_size: 4,
+            }),
+        );
+
+        // Constants without an entry keep their default value.
+        assert_eq!(execution.specialized_local_size(&[], &[]), Ok([1, 1, 1]));
+    }
+
+    #[test]
+    fn max_compute_work_group_size_exceeded() {
+        let (device, _) = gfx_dev_and_queue!();
+        let max = device
+            .physical_device()
+            .properties()
+            .max_compute_work_group_size;
+
+        let module =
+            unsafe { ShaderModule::from_bytes(device.clone(), &LOCAL_SIZE_MODULE).unwrap() };
+
+        match ComputePipeline::new(
+            device,
+            module.entry_point("main").unwrap(),
+            &LocalSize {
+                x: max[0] + 1,
+                y: 1,
+                z: 1,
+            },
+            None,
+            |_| {},
+        ) {
+            Err(ComputePipelineCreationError::MaxComputeWorkGroupSizeExceeded {
+                max: error_max,
+                obtained,
+            }) => {
+                assert_eq!(error_max, max);
+                assert_eq!(obtained, [max[0] + 1, 1, 1]);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn max_compute_work_group_invocations_exceeded() {
+        let (device, _) = gfx_dev_and_queue!();
+        let properties = device.physical_device().properties();
+        let max_size = properties.max_compute_work_group_size;
+        let max_invocations = properties.max_compute_work_group_invocations;
+
+        // Each dimension is within its limit, but not their product.
+        let invocations = max_size[0] as u64 * max_size[1] as u64;
+        if invocations <= max_invocations as u64 {
+            return;
+        }
+
+        let module =
+            unsafe { ShaderModule::from_bytes(device.clone(), &LOCAL_SIZE_MODULE).unwrap() };
+
+        match ComputePipeline::new(
+            device.clone(),
+            module.entry_point("main").unwrap(),
+            &LocalSize {
+                x: max_size[0],
+                y: max_size[1],
+                z: 1,
+            },
+            None,
+            |_| {},
+        ) {
+            Err(ComputePipelineCreationError::MaxComputeWorkGroupInvocationsExceeded {
+                max,
+                obtained,
+            }) => {
+                assert_eq!(max, max_invocations);
+                assert_eq!(obtained, invocations);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn specialization_constants() {
+        // This test checks whether specialization constants work.
+        // It executes a single compute shader (one invocation) that writes the value of a spec.
+        // constant to a buffer. The buffer content is then checked for the right value.
+
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let module =
+            unsafe { ShaderModule::from_bytes(device.clone(), &SPEC_CONSTANT_MODULE).unwrap() };
+
         #[derive(Debug, Copy, Clone)]
         #[allow(non_snake_case)]
         #[repr(C)]
@@ -500,4 +1116,101 @@ mod tests {
         let data_buffer_content = data_buffer.read().unwrap();
         assert_eq!(*data_buffer_content, 0x12345678);
     }
+
+    #[test]
+    fn specialization_map() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let module =
+            unsafe { ShaderModule::from_bytes(device.clone(), &SPEC_CONSTANT_MODULE).unwrap() };
+        let shader = module.entry_point("main").unwrap();
+
+        let mut map = SpecializationMap::new(&shader);
+        assert_eq!(
+            map.set("VALUE", 1.0f32),
+            Err(SpecializationMapError::TypeMismatch {
+                constant_id: 83,
+                expected: SpecializationConstantType::Int {
+                    width: 32,
+                    signed: true
+                },
+                provided: SpecializationConstantType::Float { width: 32 },
+            })
+        );
+        assert_eq!(
+            map.set("MISSING", 0i32),
+            Err(SpecializationMapError::ConstantNotFound)
+        );
+        map.set("VALUE", 0x12345678i32).unwrap();
+        assert_eq!(map.entries().len(), 1);
+        assert_eq!(map.data(), &0x12345678i32.to_ne_bytes());
+
+        let pipeline =
+            ComputePipeline::with_specialization_map(device, shader, &map, None, |_| {}).unwrap();
+        assert_eq!(pipeline.local_size(), [1, 1, 1]);
+    }
+
+    #[test]
+    fn shared_layout() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let module =
+            unsafe { ShaderModule::from_bytes(device.clone(), &SPEC_CONSTANT_MODULE).unwrap() };
+        let shader = module.entry_point("main").unwrap();
+
+        let first =
+            ComputePipeline::new(device.clone(), shader.clone(), &(), None, |_| {}).unwrap();
+        let second = ComputePipeline::new(device, shader, &(), None, |_| {}).unwrap();
+        assert!(Arc::ptr_eq(first.layout(), second.layout()));
+        assert!(first.layout().is_compatible_with(second.layout(), 1));
+        assert!(!first.layout().is_compatible_with(second.layout(), 2));
+    }
+
+    #[test]
+    fn batch_derivatives() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let module =
+            unsafe { ShaderModule::from_bytes(device.clone(), &SPEC_CONSTANT_MODULE).unwrap() };
+
+        #[derive(Debug, Copy, Clone)]
+        #[allow(non_snake_case)]
+        #[repr(C)]
+        struct SpecConsts {
+            VALUE: i32,
+        }
+        unsafe impl SpecializationConstants for SpecConsts {
+            fn descriptors() -> &'static [SpecializationMapEntry] {
+                static DESCRIPTORS: [SpecializationMapEntry; 1] = [SpecializationMapEntry {
+                    constant_id: 83,
+                    offset: 0,
+                    size: 4,
+                }];
+                &DESCRIPTORS
+            }
+        }
+
+        let pipeline = ComputePipeline::new(
+            device.clone(),
+            module.entry_point("main").unwrap(),
+            &SpecConsts { VALUE: 0 },
+            None,
+            |_| {},
+        )
+        .unwrap();
+
+        let spec_consts = [SpecConsts { VALUE: 1 }, SpecConsts { VALUE: 2 }];
+        let pipelines = ComputePipeline::batch_with_pipeline_layout(
+            device.clone(),
+            spec_consts
+                .iter()
+                .map(|spec_consts| (module.entry_point("main").unwrap(), spec_consts)),
+            pipeline.layout().clone(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(pipelines.len(), 2);
+        assert!(pipelines[0] != pipelines[1]);
+    }
 }
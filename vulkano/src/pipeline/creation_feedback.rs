@@ -0,0 +1,103 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Information about how a pipeline was created.
+//!
+//! If the
+//! [`ext_pipeline_creation_feedback`](crate::device::DeviceExtensions::ext_pipeline_creation_feedback)
+//! extension is enabled on the device, the implementation reports how long it took to create
+//! each pipeline and each of its shader stages, and whether they were found in the pipeline
+//! cache. This is useful to check that a [`PipelineCache`](crate::pipeline::cache::PipelineCache)
+//! works as expected, or to find out which shaders are slow to compile.
+//!
+//! The feedback is available with the `creation_feedback` method of
+//! [`GraphicsPipeline`](crate::pipeline::GraphicsPipeline::creation_feedback) and
+//! [`ComputePipeline`](crate::pipeline::ComputePipeline::creation_feedback).
+
+use crate::shader::ShaderStage;
+use std::time::Duration;
+
+/// The feedback that the implementation returned when a pipeline was created.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PipelineCreationFeedback {
+    /// Feedback about the pipeline as a whole.
+    pub pipeline: CreationFeedback,
+
+    /// Feedback about each shader stage of the pipeline.
+    pub stages: Vec<(ShaderStage, CreationFeedback)>,
+}
+
+impl PipelineCreationFeedback {
+    pub(crate) fn from_vulkan<'a>(
+        pipeline: &ash::vk::PipelineCreationFeedbackEXT,
+        stages: impl IntoIterator<
+            Item = (
+                ash::vk::ShaderStageFlags,
+                &'a ash::vk::PipelineCreationFeedbackEXT,
+            ),
+        >,
+    ) -> Self {
+        PipelineCreationFeedback {
+            pipeline: pipeline.into(),
+            stages: stages
+                .into_iter()
+                .map(|(stage, feedback)| (shader_stage(stage), feedback.into()))
+                .collect(),
+        }
+    }
+}
+
+/// The feedback about the creation of a pipeline or of one of its shader stages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CreationFeedback {
+    /// Whether the implementation provided the feedback. If `false`, the other members must be
+    /// ignored.
+    pub valid: bool,
+
+    /// Whether the pipeline or stage was found in the pipeline cache that was given when
+    /// creating the pipeline, so that it didn't need to be compiled.
+    pub application_pipeline_cache_hit: bool,
+
+    /// Whether creating the pipeline or stage was made faster by using the base pipeline.
+    pub base_pipeline_acceleration: bool,
+
+    /// How long it took to create the pipeline or stage.
+    pub duration: Duration,
+}
+
+impl From<&ash::vk::PipelineCreationFeedbackEXT> for CreationFeedback {
+    #[inline]
+    fn from(val: &ash::vk::PipelineCreationFeedbackEXT) -> Self {
+        CreationFeedback {
+            valid: val
+                .flags
+                .intersects(ash::vk::PipelineCreationFeedbackFlagsEXT::VALID),
+            application_pipeline_cache_hit: val.flags.intersects(
+                ash::vk::PipelineCreationFeedbackFlagsEXT::APPLICATION_PIPELINE_CACHE_HIT,
+            ),
+            base_pipeline_acceleration: val
+                .flags
+                .intersects(ash::vk::PipelineCreationFeedbackFlagsEXT::BASE_PIPELINE_ACCELERATION),
+            duration: Duration::from_nanos(val.duration),
+        }
+    }
+}
+
+// Only the stages that vulkano can put in a pipeline are handled.
+fn shader_stage(stage: ash::vk::ShaderStageFlags) -> ShaderStage {
+    match stage {
+        ash::vk::ShaderStageFlags::VERTEX => ShaderStage::Vertex,
+        ash::vk::ShaderStageFlags::TESSELLATION_CONTROL => ShaderStage::TessellationControl,
+        ash::vk::ShaderStageFlags::TESSELLATION_EVALUATION => ShaderStage::TessellationEvaluation,
+        ash::vk::ShaderStageFlags::GEOMETRY => ShaderStage::Geometry,
+        ash::vk::ShaderStageFlags::FRAGMENT => ShaderStage::Fragment,
+        ash::vk::ShaderStageFlags::COMPUTE => ShaderStage::Compute,
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,432 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Inspecting the code that the implementation generated for a pipeline.
+//!
+//! When a pipeline is created, the implementation compiles its shaders into one or more
+//! *executables*. With the
+//! [`pipeline_executable_info`](crate::device::Features::pipeline_executable_info) feature of the
+//! `khr_pipeline_executable_properties` extension, these executables can be listed, and the
+//! implementation can report statistics about them, such as the number of registers that they
+//! use, and internal representations such as their disassembly. This is meant for profiling and
+//! debugging tools.
+//!
+//! If the feature is enabled on the device, all the pipelines that vulkano creates capture their
+//! statistics and internal representations.
+//!
+//! # Example
+//!
+//! ```
+//! # use std::sync::Arc;
+//! # use vulkano::pipeline::ComputePipeline;
+//! use vulkano::pipeline::executable::executables;
+//! # let pipeline: Arc<ComputePipeline> = return;
+//!
+//! for executable in executables(&*pipeline).unwrap() {
+//!     println!("{}: {}", executable.name(), executable.description());
+//!
+//!     for statistic in executable.statistics().unwrap() {
+//!         println!("    {} = {:?}", statistic.name, statistic.value);
+//!     }
+//! }
+//! ```
+
+use crate::check_errors;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::shader::ShaderStages;
+use crate::Error;
+use crate::OomError;
+use crate::VulkanObject;
+use std::error;
+use std::ffi::CStr;
+use std::fmt;
+use std::os::raw::c_char;
+use std::ptr;
+
+/// Returns the executables of `pipeline`.
+///
+/// Returns an error if the `pipeline_executable_info` feature is not enabled on the device.
+pub fn executables<P>(pipeline: &P) -> Result<Vec<PipelineExecutable>, PipelineExecutableError>
+where
+    P: VulkanObject<Object = ash::vk::Pipeline> + DeviceOwned,
+{
+    let device: &Device = pipeline.device();
+
+    if !device.enabled_features().pipeline_executable_info {
+        return Err(PipelineExecutableError::FeatureNotEnabled);
+    }
+
+    let handle = pipeline.internal_object();
+    let info = ash::vk::PipelineInfoKHR {
+        pipeline: handle,
+        ..Default::default()
+    };
+
+    let properties = unsafe {
+        let fns = device.fns();
+
+        let mut num = 0;
+        check_errors(
+            fns.khr_pipeline_executable_properties
+                .get_pipeline_executable_properties_khr(
+                    device.internal_object(),
+                    &info,
+                    &mut num,
+                    ptr::null_mut(),
+                ),
+        )?;
+
+        let mut properties =
+            vec![ash::vk::PipelineExecutablePropertiesKHR::default(); num as usize];
+        check_errors(
+            fns.khr_pipeline_executable_properties
+                .get_pipeline_executable_properties_khr(
+                    device.internal_object(),
+                    &info,
+                    &mut num,
+                    properties.as_mut_ptr(),
+                ),
+        )?;
+        properties.truncate(num as usize);
+        properties
+    };
+
+    Ok(properties
+        .into_iter()
+        .enumerate()
+        .map(|(index, properties)| PipelineExecutable {
+            device,
+            pipeline: handle,
+            index: index as u32,
+            stages: properties.stages.into(),
+            name: string_from_array(&properties.name),
+            description: string_from_array(&properties.description),
+            subgroup_size: properties.subgroup_size,
+        })
+        .collect())
+}
+
+// Returns the flags that pipelines must be created with, depending on the enabled features.
+pub(crate) fn capture_flags(device: &Device) -> ash::vk::PipelineCreateFlags {
+    if device.enabled_features().pipeline_executable_info {
+        ash::vk::PipelineCreateFlags::CAPTURE_STATISTICS_KHR
+            | ash::vk::PipelineCreateFlags::CAPTURE_INTERNAL_REPRESENTATIONS_KHR
+    } else {
+        ash::vk::PipelineCreateFlags::empty()
+    }
+}
+
+/// One of the executables that the implementation generated for a pipeline.
+///
+/// Borrows the device of the pipeline, and must not outlive the pipeline.
+#[derive(Clone, Debug)]
+pub struct PipelineExecutable<'a> {
+    device: &'a Device,
+    pipeline: ash::vk::Pipeline,
+    index: u32,
+    stages: ShaderStages,
+    name: String,
+    description: String,
+    subgroup_size: u32,
+}
+
+impl<'a> PipelineExecutable<'a> {
+    /// Returns the index of the executable in the pipeline.
+    #[inline]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the shader stages that were compiled into the executable.
+    #[inline]
+    pub fn stages(&self) -> ShaderStages {
+        self.stages
+    }
+
+    /// Returns a short name for the executable given by the implementation.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns a human-readable description of the executable.
+    #[inline]
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Returns the subgroup size that the executable runs with, or 0 if it is not relevant.
+    #[inline]
+    pub fn subgroup_size(&self) -> u32 {
+        self.subgroup_size
+    }
+
+    /// Returns the statistics that the implementation reports for the executable.
+    ///
+    /// The statistics that are available depend on the implementation.
+    pub fn statistics(&self) -> Result<Vec<PipelineExecutableStatistic>, PipelineExecutableError> {
+        let info = self.executable_info();
+
+        let statistics = unsafe {
+            let fns = self.device.fns();
+
+            let mut num = 0;
+            check_errors(
+                fns.khr_pipeline_executable_properties
+                    .get_pipeline_executable_statistics_khr(
+                        self.device.internal_object(),
+                        &info,
+                        &mut num,
+                        ptr::null_mut(),
+                    ),
+            )?;
+
+            let mut statistics =
+                vec![ash::vk::PipelineExecutableStatisticKHR::default(); num as usize];
+            check_errors(
+                fns.khr_pipeline_executable_properties
+                    .get_pipeline_executable_statistics_khr(
+                        self.device.internal_object(),
+                        &info,
+                        &mut num,
+                        statistics.as_mut_ptr(),
+                    ),
+            )?;
+            statistics.truncate(num as usize);
+            statistics
+        };
+
+        Ok(statistics
+            .into_iter()
+            .map(|statistic| PipelineExecutableStatistic {
+                name: string_from_array(&statistic.name),
+                description: string_from_array(&statistic.description),
+                value: unsafe {
+                    match statistic.format {
+                        ash::vk::PipelineExecutableStatisticFormatKHR::BOOL32 => {
+                            PipelineExecutableStatisticValue::Bool(statistic.value.b32 != 0)
+                        }
+                        ash::vk::PipelineExecutableStatisticFormatKHR::INT64 => {
+                            PipelineExecutableStatisticValue::I64(statistic.value.i64)
+                        }
+                        ash::vk::PipelineExecutableStatisticFormatKHR::UINT64 => {
+                            PipelineExecutableStatisticValue::U64(statistic.value.u64)
+                        }
+                        ash::vk::PipelineExecutableStatisticFormatKHR::FLOAT64 => {
+                            PipelineExecutableStatisticValue::F64(statistic.value.f64)
+                        }
+                        _ => PipelineExecutableStatisticValue::Unknown,
+                    }
+                },
+            })
+            .collect())
+    }
+
+    /// Returns the internal representations of the executable, for example the intermediate
+    /// code and the disassembly of the generated machine code.
+    pub fn internal_representations(
+        &self,
+    ) -> Result<Vec<PipelineExecutableInternalRepresentation>, PipelineExecutableError> {
+        let info = self.executable_info();
+
+        unsafe {
+            let fns = self.device.fns();
+
+            let mut num = 0;
+            check_errors(
+                fns.khr_pipeline_executable_properties
+                    .get_pipeline_executable_internal_representations_khr(
+                        self.device.internal_object(),
+                        &info,
+                        &mut num,
+                        ptr::null_mut(),
+                    ),
+            )?;
+
+            // The first call with null data pointers returns the size of each representation.
+            let mut representations =
+                vec![ash::vk::PipelineExecutableInternalRepresentationKHR::default(); num as usize];
+            check_errors(
+                fns.khr_pipeline_executable_properties
+                    .get_pipeline_executable_internal_representations_khr(
+                        self.device.internal_object(),
+                        &info,
+                        &mut num,
+                        representations.as_mut_ptr(),
+                    ),
+            )?;
+            representations.truncate(num as usize);
+
+            let mut data: Vec<Vec<u8>> = representations
+                .iter()
+                .map(|representation| vec![0; representation.data_size])
+                .collect();
+
+            for (representation, data) in representations.iter_mut().zip(&mut data) {
+                representation.p_data = data.as_mut_ptr() as *mut _;
+            }
+
+            check_errors(
+                fns.khr_pipeline_executable_properties
+                    .get_pipeline_executable_internal_representations_khr(
+                        self.device.internal_object(),
+                        &info,
+                        &mut num,
+                        representations.as_mut_ptr(),
+                    ),
+            )?;
+
+            Ok(representations
+                .into_iter()
+                .zip(data)
+                .map(|(representation, mut data)| {
+                    data.truncate(representation.data_size);
+
+                    PipelineExecutableInternalRepresentation {
+                        name: string_from_array(&representation.name),
+                        description: string_from_array(&representation.description),
+                        is_text: representation.is_text != ash::vk::FALSE,
+                        data,
+                    }
+                })
+                .collect())
+        }
+    }
+
+    #[inline]
+    fn executable_info(&self) -> ash::vk::PipelineExecutableInfoKHR {
+        ash::vk::PipelineExecutableInfoKHR {
+            pipeline: self.pipeline,
+            executable_index: self.index,
+            ..Default::default()
+        }
+    }
+}
+
+/// A statistic about a pipeline executable.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PipelineExecutableStatistic {
+    /// A short name for the statistic.
+    pub name: String,
+
+    /// A human-readable description of the statistic.
+    pub description: String,
+
+    /// The value of the statistic.
+    pub value: PipelineExecutableStatisticValue,
+}
+
+/// The value of a pipeline executable statistic.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PipelineExecutableStatisticValue {
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    /// The implementation returned the value in a format that vulkano doesn't know about.
+    Unknown,
+}
+
+/// An internal representation of a pipeline executable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PipelineExecutableInternalRepresentation {
+    /// A short name for the representation.
+    pub name: String,
+
+    /// A human-readable description of the representation.
+    pub description: String,
+
+    /// Whether `data` is text. If so, it is a null-terminated UTF-8 string.
+    pub is_text: bool,
+
+    /// The content of the representation.
+    pub data: Vec<u8>,
+}
+
+impl PipelineExecutableInternalRepresentation {
+    /// Returns the content of the representation as a string, if it is text.
+    #[inline]
+    pub fn text(&self) -> Option<String> {
+        if !self.is_text {
+            return None;
+        }
+
+        let end = self
+            .data
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(self.data.len());
+        Some(String::from_utf8_lossy(&self.data[..end]).into_owned())
+    }
+}
+
+fn string_from_array(array: &[c_char]) -> String {
+    unsafe {
+        CStr::from_ptr(array.as_ptr())
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Error that can happen when querying the executables of a pipeline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PipelineExecutableError {
+    /// The `pipeline_executable_info` feature is not enabled on the device.
+    FeatureNotEnabled,
+
+    /// Not enough memory.
+    OomError(OomError),
+}
+
+impl error::Error for PipelineExecutableError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            PipelineExecutableError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PipelineExecutableError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                PipelineExecutableError::FeatureNotEnabled => {
+                    "the `pipeline_executable_info` feature is not enabled on the device"
+                }
+                PipelineExecutableError::OomError(_) => "not enough memory available",
+            }
+        )
+    }
+}
+
+impl From<OomError> for PipelineExecutableError {
+    #[inline]
+    fn from(err: OomError) -> PipelineExecutableError {
+        PipelineExecutableError::OomError(err)
+    }
+}
+
+impl From<Error> for PipelineExecutableError {
+    #[inline]
+    fn from(err: Error) -> PipelineExecutableError {
+        match err {
+            err @ Error::OutOfHostMemory => PipelineExecutableError::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => {
+                PipelineExecutableError::OomError(OomError::from(err))
+            }
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
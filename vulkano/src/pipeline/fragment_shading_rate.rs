@@ -0,0 +1,256 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Running the fragment shader once for a group of pixels instead of once per pixel.
+//!
+//! The fragment shading rate is the size of the area of the framebuffer that is covered by a
+//! single invocation of the fragment shader. It can come from three sources, which are combined
+//! to give the final rate:
+//!
+//! - The pipeline itself, either fixed in the pipeline or set with the
+//!   `set_fragment_shading_rate` command.
+//! - The primitive, if a vertex or geometry shader writes to the `PrimitiveShadingRateKHR`
+//!   built-in.
+//! - The fragment shading rate attachment of the subpass, if there is one.
+//!
+//! These are available if the
+//! [`khr_fragment_shading_rate`](crate::device::DeviceExtensions::khr_fragment_shading_rate)
+//! extension is enabled, and respectively the `pipeline_fragment_shading_rate`,
+//! `primitive_fragment_shading_rate` and `attachment_fragment_shading_rate` features.
+
+use crate::device::Device;
+use crate::pipeline::{DynamicState, GraphicsPipelineCreationError, StateMode};
+use fnv::FnvHashMap;
+
+/// The state in a graphics pipeline describing the fragment shading rate.
+#[derive(Clone, Copy, Debug)]
+pub struct FragmentShadingRateState {
+    /// The fragment shading rate of the pipeline, and how it is combined with the other rates.
+    ///
+    /// If set to anything other than `Fixed` with the value of `FragmentShadingRate::new()`, the
+    /// [`khr_fragment_shading_rate`](crate::device::DeviceExtensions::khr_fragment_shading_rate)
+    /// extension must be enabled on the device.
+    pub rate: StateMode<FragmentShadingRate>,
+}
+
+impl FragmentShadingRateState {
+    /// Creates a `FragmentShadingRateState` with a fixed rate of one invocation per pixel, that
+    /// ignores the primitive and attachment rates.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            rate: StateMode::Fixed(FragmentShadingRate::new()),
+        }
+    }
+
+    /// Sets the rate to a fixed value.
+    #[inline]
+    pub fn fixed(rate: FragmentShadingRate) -> Self {
+        Self {
+            rate: StateMode::Fixed(rate),
+        }
+    }
+
+    /// Sets the rate to be dynamic.
+    #[inline]
+    pub fn dynamic() -> Self {
+        Self {
+            rate: StateMode::Dynamic,
+        }
+    }
+
+    pub(crate) fn to_vulkan(
+        &self,
+        device: &Device,
+        dynamic_state_modes: &mut FnvHashMap<DynamicState, bool>,
+    ) -> Result<
+        Option<ash::vk::PipelineFragmentShadingRateStateCreateInfoKHR>,
+        GraphicsPipelineCreationError,
+    > {
+        let rate = match self.rate {
+            StateMode::Fixed(rate) => {
+                if !device.enabled_extensions().khr_fragment_shading_rate {
+                    if rate != FragmentShadingRate::new() {
+                        return Err(GraphicsPipelineCreationError::ExtensionNotEnabled {
+                            extension: "khr_fragment_shading_rate",
+                            reason: "FragmentShadingRateState::rate was not the default value",
+                        });
+                    }
+
+                    return Ok(None);
+                }
+
+                rate.validate(device)?;
+                dynamic_state_modes.insert(DynamicState::FragmentShadingRate, false);
+                rate
+            }
+            StateMode::Dynamic => {
+                if !device.enabled_extensions().khr_fragment_shading_rate {
+                    return Err(GraphicsPipelineCreationError::ExtensionNotEnabled {
+                        extension: "khr_fragment_shading_rate",
+                        reason: "FragmentShadingRateState::rate was set to Dynamic",
+                    });
+                }
+
+                dynamic_state_modes.insert(DynamicState::FragmentShadingRate, true);
+                FragmentShadingRate::new()
+            }
+        };
+
+        Ok(Some(
+            ash::vk::PipelineFragmentShadingRateStateCreateInfoKHR {
+                fragment_size: ash::vk::Extent2D {
+                    width: rate.fragment_size[0],
+                    height: rate.fragment_size[1],
+                },
+                combiner_ops: [rate.combiner_ops[0].into(), rate.combiner_ops[1].into()],
+                ..Default::default()
+            },
+        ))
+    }
+}
+
+impl Default for FragmentShadingRateState {
+    /// Returns [`FragmentShadingRateState::new()`].
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A fragment shading rate, and how it is combined with the primitive and attachment rates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FragmentShadingRate {
+    /// The width and height in pixels of the area covered by one fragment shader invocation.
+    ///
+    /// Both values must be 1, 2 or 4. If the
+    /// [`pipeline_fragment_shading_rate`](crate::device::Features::pipeline_fragment_shading_rate)
+    /// feature is not enabled, they must both be 1.
+    pub fragment_size: [u32; 2],
+
+    /// How the rate is combined with the other rates. The first operation combines the rate of
+    /// the pipeline with the rate of the primitive, the second one combines the result with the
+    /// rate of the fragment shading rate attachment.
+    ///
+    /// If the
+    /// [`primitive_fragment_shading_rate`](crate::device::Features::primitive_fragment_shading_rate)
+    /// feature is not enabled, the first operation must be `Keep`. If the
+    /// [`attachment_fragment_shading_rate`](crate::device::Features::attachment_fragment_shading_rate)
+    /// feature is not enabled, the second operation must be `Keep`. If the
+    /// [`fragment_shading_rate_non_trivial_combiner_ops`](crate::device::Properties::fragment_shading_rate_non_trivial_combiner_ops)
+    /// device property is `false`, the operations must be `Keep` or `Replace`.
+    pub combiner_ops: [FragmentShadingRateCombinerOp; 2],
+}
+
+impl FragmentShadingRate {
+    /// Returns a rate of one invocation per pixel, that ignores the primitive and attachment
+    /// rates.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            fragment_size: [1, 1],
+            combiner_ops: [FragmentShadingRateCombinerOp::Keep; 2],
+        }
+    }
+
+    pub(crate) fn validate(&self, device: &Device) -> Result<(), GraphicsPipelineCreationError> {
+        let features = device.enabled_features();
+
+        if !self
+            .fragment_size
+            .iter()
+            .all(|&size| size == 1 || size == 2 || size == 4)
+        {
+            return Err(GraphicsPipelineCreationError::InvalidFragmentShadingRateSize);
+        }
+
+        if self.fragment_size != [1, 1] && !features.pipeline_fragment_shading_rate {
+            return Err(GraphicsPipelineCreationError::FeatureNotEnabled {
+                feature: "pipeline_fragment_shading_rate",
+                reason: "FragmentShadingRate::fragment_size was not [1, 1]",
+            });
+        }
+
+        if self.combiner_ops[0] != FragmentShadingRateCombinerOp::Keep
+            && !features.primitive_fragment_shading_rate
+        {
+            return Err(GraphicsPipelineCreationError::FeatureNotEnabled {
+                feature: "primitive_fragment_shading_rate",
+                reason: "the first element of FragmentShadingRate::combiner_ops was not Keep",
+            });
+        }
+
+        if self.combiner_ops[1] != FragmentShadingRateCombinerOp::Keep
+            && !features.attachment_fragment_shading_rate
+        {
+            return Err(GraphicsPipelineCreationError::FeatureNotEnabled {
+                feature: "attachment_fragment_shading_rate",
+                reason: "the second element of FragmentShadingRate::combiner_ops was not Keep",
+            });
+        }
+
+        if !device
+            .physical_device()
+            .properties()
+            .fragment_shading_rate_non_trivial_combiner_ops
+            .unwrap_or(false)
+            && self.combiner_ops.iter().any(|op| op.is_non_trivial())
+        {
+            return Err(GraphicsPipelineCreationError::NonTrivialFragmentShadingRateCombinerOp);
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for FragmentShadingRate {
+    /// Returns [`FragmentShadingRate::new()`].
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How two fragment shading rates are combined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum FragmentShadingRateCombinerOp {
+    /// The first rate is used.
+    Keep = ash::vk::FragmentShadingRateCombinerOpKHR::KEEP.as_raw(),
+
+    /// The second rate is used.
+    Replace = ash::vk::FragmentShadingRateCombinerOpKHR::REPLACE.as_raw(),
+
+    /// The smallest of the two rates is used, for each dimension.
+    Min = ash::vk::FragmentShadingRateCombinerOpKHR::MIN.as_raw(),
+
+    /// The largest of the two rates is used, for each dimension.
+    Max = ash::vk::FragmentShadingRateCombinerOpKHR::MAX.as_raw(),
+
+    /// The product of the two rates is used, for each dimension.
+    Mul = ash::vk::FragmentShadingRateCombinerOpKHR::MUL.as_raw(),
+}
+
+impl FragmentShadingRateCombinerOp {
+    /// Returns whether the operation is not `Keep` or `Replace`.
+    #[inline]
+    pub fn is_non_trivial(&self) -> bool {
+        !matches!(
+            self,
+            FragmentShadingRateCombinerOp::Keep | FragmentShadingRateCombinerOp::Replace
+        )
+    }
+}
+
+impl From<FragmentShadingRateCombinerOp> for ash::vk::FragmentShadingRateCombinerOpKHR {
+    #[inline]
+    fn from(val: FragmentShadingRateCombinerOp) -> Self {
+        Self::from_raw(val as i32)
+    }
+}
@@ -12,34 +12,42 @@
 #![allow(deprecated)]
 
 use crate::check_errors;
-use crate::descriptor_set::layout::{DescriptorSetDesc, DescriptorSetLayout};
+use crate::descriptor_set::layout::DescriptorSetDesc;
 use crate::device::Device;
 use crate::pipeline::cache::PipelineCache;
 use crate::pipeline::color_blend::{
     AttachmentBlend, ColorBlendAttachmentState, ColorBlendState, ColorComponents, LogicOp,
 };
+use crate::pipeline::creation_feedback::PipelineCreationFeedback;
 use crate::pipeline::depth_stencil::DepthStencilState;
 use crate::pipeline::discard_rectangle::DiscardRectangleState;
+use crate::pipeline::executable;
+use crate::pipeline::fragment_shading_rate::FragmentShadingRateState;
 use crate::pipeline::graphics_pipeline::{GraphicsPipeline, GraphicsPipelineCreationError};
 use crate::pipeline::input_assembly::{InputAssemblyState, PrimitiveTopology};
-use crate::pipeline::layout::{PipelineLayout, PipelineLayoutCreationError, PipelineLayoutPcRange};
+use crate::pipeline::layout::{PipelineLayout, PipelineLayoutPcRange};
 use crate::pipeline::multisample::MultisampleState;
 use crate::pipeline::rasterization::{CullMode, FrontFace, PolygonMode, RasterizationState};
 use crate::pipeline::tessellation::TessellationState;
-use crate::pipeline::vertex::{BuffersDefinition, Vertex, VertexDefinition, VertexInputRate};
+use crate::pipeline::vertex::{
+    BuffersDefinition, Vertex, VertexDefinition, VertexInput, VertexInputRate,
+};
 use crate::pipeline::viewport::{Scissor, Viewport, ViewportState};
 use crate::pipeline::{DynamicState, PartialStateMode, StateMode};
 use crate::render_pass::Subpass;
 use crate::shader::{
     DescriptorRequirements, EntryPoint, ShaderExecution, ShaderStage, SpecializationConstants,
+    TessellationShaderExecution,
 };
 use crate::DeviceSize;
+use crate::Success;
 use crate::VulkanObject;
 use fnv::FnvHashMap;
 use smallvec::SmallVec;
 use std::collections::hash_map::{Entry, HashMap};
-use std::mem::{self, MaybeUninit};
+use std::mem;
 use std::ptr;
+use std::slice;
 use std::sync::Arc;
 use std::u32;
 
@@ -48,6 +56,10 @@ use std::u32;
 pub struct GraphicsPipelineBuilder<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss> {
     subpass: Option<Subpass>,
     cache: Option<Arc<PipelineCache>>,
+    fail_on_compile_required: bool,
+    allow_derivatives: bool,
+    base_pipeline: Option<Arc<GraphicsPipeline>>,
+    indirect_bindable: bool,
 
     vertex_shader: Option<(EntryPoint<'vs>, Vss)>,
     tessellation_shaders: Option<TessellationShaders<'tcs, 'tes, Tcss, Tess>>,
@@ -55,10 +67,12 @@ pub struct GraphicsPipelineBuilder<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, T
     fragment_shader: Option<(EntryPoint<'fs>, Fss)>,
 
     vertex_definition: Vdef,
+    vertex_input_dynamic: bool,
     input_assembly_state: InputAssemblyState,
     tessellation_state: TessellationState,
     viewport_state: ViewportState,
     discard_rectangle_state: DiscardRectangleState,
+    fragment_shading_rate_state: FragmentShadingRateState,
     rasterization_state: RasterizationState,
     multisample_state: MultisampleState,
     depth_stencil_state: DepthStencilState,
@@ -92,6 +106,10 @@ impl
         GraphicsPipelineBuilder {
             subpass: None,
             cache: None,
+            fail_on_compile_required: false,
+            allow_derivatives: false,
+            base_pipeline: None,
+            indirect_bindable: false,
 
             vertex_shader: None,
             tessellation_shaders: None,
@@ -99,10 +117,12 @@ impl
             fragment_shader: None,
 
             vertex_definition: BuffersDefinition::new(),
+            vertex_input_dynamic: false,
             input_assembly_state: Default::default(),
             tessellation_state: Default::default(),
             viewport_state: Default::default(),
             discard_rectangle_state: Default::default(),
+            fragment_shading_rate_state: Default::default(),
             rasterization_state: Default::default(),
             multisample_state: Default::default(),
             depth_stencil_state: Default::default(),
@@ -224,13 +244,11 @@ where
             (descriptor_set_layout_descs, push_constant_ranges)
         };
 
-        let descriptor_set_layouts = descriptor_set_layout_descs
-            .into_iter()
-            .map(|desc| Ok(DescriptorSetLayout::new(device.clone(), desc)?))
-            .collect::<Result<Vec<_>, PipelineLayoutCreationError>>()?;
-        let pipeline_layout =
-            PipelineLayout::new(device.clone(), descriptor_set_layouts, push_constant_ranges)
-                .unwrap();
+        let pipeline_layout = PipelineLayout::get_or_create(
+            device.clone(),
+            descriptor_set_layout_descs,
+            push_constant_ranges,
+        )?;
         self.with_pipeline_layout(device, pipeline_layout)
     }
 
@@ -240,13 +258,134 @@ where
     /// object corresponding to the union of your shaders while this function allows you to specify
     /// the pipeline layout.
     pub fn with_pipeline_layout(
-        mut self,
+        self,
         device: Arc<Device>,
         pipeline_layout: Arc<PipelineLayout>,
     ) -> Result<Arc<GraphicsPipeline>, GraphicsPipelineCreationError> {
+        let cache_handle = match self.cache.as_ref() {
+            Some(cache) => cache.internal_object(),
+            None => ash::vk::PipelineCache::null(),
+        };
+
+        self.create(
+            device.clone(),
+            pipeline_layout,
+            None,
+            |create_info| unsafe {
+                let handles =
+                    create_pipelines(&device, cache_handle, slice::from_ref(create_info))?;
+                Ok(handles[0])
+            },
+        )
+    }
+
+    /// Builds several graphics pipelines that share the same pipeline layout with a single
+    /// `vkCreateGraphicsPipelines` call.
+    ///
+    /// The first pipeline is created with derivatives allowed, and the other ones are created as
+    /// derivatives of it, unless a [`base_pipeline`](#method.base_pipeline) was set on their
+    /// builder. This lets the implementation share work between pipelines that are similar, for
+    /// example variants of the same shaders with different specialization constants or states.
+    /// The pipelines are returned in the same order as `builders`.
+    ///
+    /// `cache` is used for all the pipelines; the caches set on the builders with
+    /// [`build_with_cache`](#method.build_with_cache) are ignored.
+    ///
+    /// If creating any of the pipelines fails, the ones that were successfully created are
+    /// destroyed and the error is returned.
+    pub fn batch_with_pipeline_layout<I>(
+        builders: I,
+        device: Arc<Device>,
+        pipeline_layout: Arc<PipelineLayout>,
+        cache: Option<Arc<PipelineCache>>,
+    ) -> Result<Vec<Arc<GraphicsPipeline>>, GraphicsPipelineCreationError>
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        let cache_handle = match cache {
+            Some(ref cache) => cache.internal_object(),
+            None => ash::vk::PipelineCache::null(),
+        };
+
+        let mut pipelines = Vec::new();
+        Self::create_batch(
+            &device,
+            &pipeline_layout,
+            cache_handle,
+            builders.into_iter(),
+            &mut Vec::new(),
+            &mut pipelines,
+        )?;
+
+        // The innermost builder pushes its pipeline first.
+        pipelines.reverse();
+        Ok(pipelines)
+    }
+
+    // Gathers the create infos of `builders` after the ones already in `infos`, then creates all
+    // the pipelines at once and returns their handles.
+    //
+    // The create infos point to data owned by `create`, so each builder is processed in the
+    // closure given to the `create` of the previous one, which keeps that data alive until the
+    // pipelines are created.
+    fn create_batch<I>(
+        device: &Arc<Device>,
+        pipeline_layout: &Arc<PipelineLayout>,
+        cache_handle: ash::vk::PipelineCache,
+        mut builders: I,
+        infos: &mut Vec<ash::vk::GraphicsPipelineCreateInfo>,
+        pipelines: &mut Vec<Arc<GraphicsPipeline>>,
+    ) -> Result<Vec<ash::vk::Pipeline>, GraphicsPipelineCreationError>
+    where
+        I: Iterator<Item = Self>,
+    {
+        let builder = match builders.next() {
+            Some(builder) => builder,
+            None if infos.is_empty() => return Ok(Vec::new()),
+            None => return unsafe { create_pipelines(device, cache_handle, infos) },
+        };
+
+        let index = infos.len();
+        let mut handles = Vec::new();
+        let pipeline = builder.create(
+            device.clone(),
+            pipeline_layout.clone(),
+            Some(index),
+            |create_info| {
+                infos.push(*create_info);
+                handles = Self::create_batch(
+                    device,
+                    pipeline_layout,
+                    cache_handle,
+                    builders,
+                    infos,
+                    pipelines,
+                )?;
+                Ok(handles[index])
+            },
+        )?;
+        pipelines.push(pipeline);
+
+        Ok(handles)
+    }
+
+    // Checks the builder and builds the create info of the pipeline, then calls `create_fn` to
+    // create it. `batch_index` is the index of the pipeline in a batch created by
+    // `batch_with_pipeline_layout`, if any.
+    fn create<F>(
+        mut self,
+        device: Arc<Device>,
+        pipeline_layout: Arc<PipelineLayout>,
+        batch_index: Option<usize>,
+        create_fn: F,
+    ) -> Result<Arc<GraphicsPipeline>, GraphicsPipelineCreationError>
+    where
+        F: FnOnce(
+            &ash::vk::GraphicsPipelineCreateInfo,
+        ) -> Result<ash::vk::Pipeline, GraphicsPipelineCreationError>,
+    {
         // TODO: return errors instead of panicking if missing param
 
-        let fns = device.fns();
         let subpass = self.subpass.take().expect("Missing subpass");
 
         // Checking that the pipeline layout matches the shader stages.
@@ -528,8 +667,6 @@ where
             });
 
             if let Some(ref tess) = self.tessellation_shaders {
-                // FIXME: must check that the control shader and evaluation shader are compatible
-
                 if !device.enabled_features().tessellation_shader {
                     return Err(GraphicsPipelineCreationError::FeatureNotEnabled {
                         feature: "tessellation_shader",
@@ -537,16 +674,18 @@ where
                     });
                 }
 
-                match tess.tessellation_control_shader.0.execution() {
-                    ShaderExecution::TessellationControl => {}
+                let control = match *tess.tessellation_control_shader.0.execution() {
+                    ShaderExecution::TessellationControl(execution) => execution,
                     _ => return Err(GraphicsPipelineCreationError::WrongShaderType),
                 };
 
-                match tess.tessellation_evaluation_shader.0.execution() {
-                    ShaderExecution::TessellationEvaluation => {}
+                let evaluation = match *tess.tessellation_evaluation_shader.0.execution() {
+                    ShaderExecution::TessellationEvaluation(execution) => execution,
                     _ => return Err(GraphicsPipelineCreationError::WrongShaderType),
                 };
 
+                check_tessellation_execution(device, &control, &evaluation)?;
+
                 shaders.insert(ShaderStage::TessellationControl, ());
                 stages.push(ash::vk::PipelineShaderStageCreateInfo {
                     flags: ash::vk::PipelineShaderStageCreateFlags::empty(),
@@ -640,9 +779,25 @@ where
         };
 
         // Vertex input state
-        let vertex_input = self
-            .vertex_definition
-            .definition(self.vertex_shader.as_ref().unwrap().0.input_interface())?;
+        let vertex_shader_input = self
+            .vertex_shader
+            .as_ref()
+            .unwrap()
+            .0
+            .input_interface()
+            .clone();
+        let vertex_input = if self.vertex_input_dynamic {
+            if !device.enabled_features().vertex_input_dynamic_state {
+                return Err(GraphicsPipelineCreationError::FeatureNotEnabled {
+                    feature: "vertex_input_dynamic_state",
+                    reason: "the vertex input was set to dynamic",
+                });
+            }
+
+            VertexInput::empty()
+        } else {
+            self.vertex_definition.definition(&vertex_shader_input)?
+        };
 
         let (binding_descriptions, binding_divisor_descriptions) = {
             let mut binding_descriptions = SmallVec::<[_; 8]>::new();
@@ -821,20 +976,24 @@ where
             None
         };
 
-        dynamic_state_modes.insert(DynamicState::VertexInput, false);
-        let vertex_input_state = Some(ash::vk::PipelineVertexInputStateCreateInfo {
-            p_next: if let Some(next) = vertex_input_divisor_state.as_ref() {
-                next as *const _ as *const _
-            } else {
-                ptr::null()
-            },
-            flags: ash::vk::PipelineVertexInputStateCreateFlags::empty(),
-            vertex_binding_description_count: binding_descriptions.len() as u32,
-            p_vertex_binding_descriptions: binding_descriptions.as_ptr(),
-            vertex_attribute_description_count: attribute_descriptions.len() as u32,
-            p_vertex_attribute_descriptions: attribute_descriptions.as_ptr(),
-            ..Default::default()
-        });
+        dynamic_state_modes.insert(DynamicState::VertexInput, self.vertex_input_dynamic);
+        let vertex_input_state = if self.vertex_input_dynamic {
+            None
+        } else {
+            Some(ash::vk::PipelineVertexInputStateCreateInfo {
+                p_next: if let Some(next) = vertex_input_divisor_state.as_ref() {
+                    next as *const _ as *const _
+                } else {
+                    ptr::null()
+                },
+                flags: ash::vk::PipelineVertexInputStateCreateFlags::empty(),
+                vertex_binding_description_count: binding_descriptions.len() as u32,
+                p_vertex_binding_descriptions: binding_descriptions.as_ptr(),
+                vertex_attribute_description_count: attribute_descriptions.len() as u32,
+                p_vertex_attribute_descriptions: attribute_descriptions.as_ptr(),
+                ..Default::default()
+            })
+        };
 
         // Input assembly state
         let input_assembly_state = if self.vertex_shader.is_some() {
@@ -884,10 +1043,25 @@ where
         let mut rasterization_line_state = self
             .rasterization_state
             .to_vulkan_line_state(&device, &mut dynamic_state_modes)?;
+        let mut rasterization_conservative_state = self
+            .rasterization_state
+            .to_vulkan_conservative_state(&device)?;
+        let mut rasterization_depth_clip_state = self
+            .rasterization_state
+            .to_vulkan_depth_clip_state(&device)?;
+        let mut rasterization_provoking_vertex_state = self
+            .rasterization_state
+            .to_vulkan_provoking_vertex_state(&device)?;
+        let mut rasterization_stream_state =
+            self.rasterization_state.to_vulkan_stream_state(&device)?;
         let rasterization_state = Some(self.rasterization_state.to_vulkan(
             &device,
             &mut dynamic_state_modes,
             rasterization_line_state.as_mut(),
+            rasterization_conservative_state.as_mut(),
+            rasterization_depth_clip_state.as_mut(),
+            rasterization_provoking_vertex_state.as_mut(),
+            rasterization_stream_state.as_mut(),
         )?);
 
         // Fragment shader state
@@ -904,6 +1078,14 @@ where
             None
         };
 
+        // Fragment shading rate state
+        let mut fragment_shading_rate_state = if has_fragment_shader_state {
+            self.fragment_shading_rate_state
+                .to_vulkan(&device, &mut dynamic_state_modes)?
+        } else {
+            None
+        };
+
         // Depth/stencil state
         let depth_stencil_state = if has_fragment_shader_state
             && subpass.subpass_desc().depth_stencil.is_some()
@@ -961,7 +1143,6 @@ where
         // - ViewportShadingRatePalette (VkPipelineViewportShadingRateImageStateCreateInfoNV)
         // - ViewportCoarseSampleOrder (VkPipelineViewportCoarseSampleOrderStateCreateInfoNV)
         // - ExclusiveScissor (VkPipelineViewportExclusiveScissorStateCreateInfoNV)
-        // - FragmentShadingRate (VkPipelineFragmentShadingRateStateCreateInfoKHR)
 
         if let Some(multiview) = subpass.render_pass().desc().multiview().as_ref() {
             if multiview.used_layer_count() > 0 {
@@ -987,9 +1168,76 @@ where
             }
         }
 
+        let mut flags = executable::capture_flags(&device); // TODO: some flags are available but none are critical
+
+        if self.fail_on_compile_required {
+            if !device.enabled_features().pipeline_creation_cache_control {
+                return Err(GraphicsPipelineCreationError::FeatureNotEnabled {
+                    feature: "pipeline_creation_cache_control",
+                    reason: "fail_on_compile_required was enabled",
+                });
+            }
+
+            flags |= ash::vk::PipelineCreateFlags::FAIL_ON_PIPELINE_COMPILE_REQUIRED_EXT;
+        }
+
+        // The first pipeline of a batch is the base of the other ones.
+        let allows_derivatives = self.allow_derivatives || batch_index == Some(0);
+
+        if allows_derivatives {
+            flags |= ash::vk::PipelineCreateFlags::ALLOW_DERIVATIVES;
+        }
+
+        if self.indirect_bindable {
+            if !device.enabled_features().device_generated_commands {
+                return Err(GraphicsPipelineCreationError::FeatureNotEnabled {
+                    feature: "device_generated_commands",
+                    reason: "indirect_bindable was enabled",
+                });
+            }
+
+            flags |= ash::vk::PipelineCreateFlags::INDIRECT_BINDABLE_NV;
+        }
+
+        let (base_pipeline_handle, base_pipeline_index) =
+            if let Some(base_pipeline) = self.base_pipeline.as_ref() {
+                assert_eq!(
+                    base_pipeline.device().internal_object(),
+                    device.internal_object()
+                );
+
+                if !base_pipeline.allows_derivatives() {
+                    return Err(GraphicsPipelineCreationError::BasePipelineDerivativesNotAllowed);
+                }
+
+                flags |= ash::vk::PipelineCreateFlags::DERIVATIVE;
+                (base_pipeline.internal_object(), -1)
+            } else if batch_index.map_or(false, |index| index > 0) {
+                flags |= ash::vk::PipelineCreateFlags::DERIVATIVE;
+                (ash::vk::Pipeline::null(), 0)
+            } else {
+                (ash::vk::Pipeline::null(), -1)
+            };
+
+        // Creation feedback, written by the implementation.
+        let mut pipeline_feedback = ash::vk::PipelineCreationFeedbackEXT::default();
+        let mut stage_feedbacks =
+            vec![ash::vk::PipelineCreationFeedbackEXT::default(); stages.len()];
+        let mut creation_feedback_info =
+            if device.enabled_extensions().ext_pipeline_creation_feedback {
+                Some(ash::vk::PipelineCreationFeedbackCreateInfoEXT {
+                    p_pipeline_creation_feedback: &mut pipeline_feedback,
+                    pipeline_stage_creation_feedback_count: stage_feedbacks.len() as u32,
+                    p_pipeline_stage_creation_feedbacks: stage_feedbacks.as_mut_ptr(),
+                    ..Default::default()
+                })
+            } else {
+                None
+            };
+
         let handle = unsafe {
             let mut create_info = ash::vk::GraphicsPipelineCreateInfo {
-                flags: ash::vk::PipelineCreateFlags::empty(), // TODO: some flags are available but none are critical
+                flags,
                 stage_count: stages.len() as u32,
                 p_stages: stages.as_ptr(),
                 p_vertex_input_state: vertex_input_state
@@ -1031,8 +1279,8 @@ where
                 layout: pipeline_layout.internal_object(),
                 render_pass: subpass.render_pass().internal_object(),
                 subpass: subpass.index(),
-                base_pipeline_handle: ash::vk::Pipeline::null(), // TODO:
-                base_pipeline_index: -1,                         // TODO:
+                base_pipeline_handle,
+                base_pipeline_index,
                 ..Default::default()
             };
 
@@ -1041,21 +1289,17 @@ where
                 create_info.p_next = discard_rectangle_state as *const _ as *const _;
             }
 
-            let cache_handle = match self.cache.as_ref() {
-                Some(cache) => cache.internal_object(),
-                None => ash::vk::PipelineCache::null(),
-            };
+            if let Some(fragment_shading_rate_state) = fragment_shading_rate_state.as_mut() {
+                fragment_shading_rate_state.p_next = create_info.p_next;
+                create_info.p_next = fragment_shading_rate_state as *const _ as *const _;
+            }
 
-            let mut output = MaybeUninit::uninit();
-            check_errors(fns.v1_0.create_graphics_pipelines(
-                device.internal_object(),
-                cache_handle,
-                1,
-                &create_info,
-                ptr::null(),
-                output.as_mut_ptr(),
-            ))?;
-            output.assume_init()
+            if let Some(creation_feedback_info) = creation_feedback_info.as_mut() {
+                creation_feedback_info.p_next = create_info.p_next;
+                create_info.p_next = creation_feedback_info as *const _ as *const _;
+            }
+
+            create_fn(&create_info)?
         };
 
         // Some drivers return `VK_SUCCESS` but provide a null handle if they
@@ -1065,6 +1309,15 @@ where
             panic!("vkCreateGraphicsPipelines provided a NULL handle");
         }
 
+        let creation_feedback = if creation_feedback_info.is_some() {
+            Some(PipelineCreationFeedback::from_vulkan(
+                &pipeline_feedback,
+                stages.iter().map(|stage| stage.stage).zip(&stage_feedbacks),
+            ))
+        } else {
+            None
+        };
+
         Ok(Arc::new(GraphicsPipeline {
             handle,
             device: device.clone(),
@@ -1075,6 +1328,7 @@ where
             num_used_descriptor_sets,
 
             vertex_input, // Can be None if there's a mesh shader, but we don't support that yet
+            vertex_shader_input,
             input_assembly_state: self.input_assembly_state, // Can be None if there's a mesh shader, but we don't support that yet
             tessellation_state: if tessellation_state.is_some() {
                 Some(self.tessellation_state)
@@ -1091,6 +1345,11 @@ where
             } else {
                 None
             },
+            fragment_shading_rate_state: if fragment_shading_rate_state.is_some() {
+                Some(self.fragment_shading_rate_state)
+            } else {
+                None
+            },
             rasterization_state: self.rasterization_state,
             multisample_state: if multisample_state.is_some() {
                 Some(self.multisample_state)
@@ -1108,17 +1367,90 @@ where
                 None
             },
             dynamic_state: dynamic_state_modes,
+            allows_derivatives,
+            indirect_bindable: self.indirect_bindable,
+            creation_feedback,
         }))
     }
 
     // TODO: add build_with_cache method
 }
 
+// Creates the pipelines described by `infos`. If any of them can't be created, destroys the ones
+// that were created and returns the error.
+unsafe fn create_pipelines(
+    device: &Device,
+    cache_handle: ash::vk::PipelineCache,
+    infos: &[ash::vk::GraphicsPipelineCreateInfo],
+) -> Result<Vec<ash::vk::Pipeline>, GraphicsPipelineCreationError> {
+    let fns = device.fns();
+
+    // The implementation writes a null handle for each pipeline it fails to create.
+    let mut output = vec![ash::vk::Pipeline::null(); infos.len()];
+    let result = check_errors(fns.v1_0.create_graphics_pipelines(
+        device.internal_object(),
+        cache_handle,
+        infos.len() as u32,
+        infos.as_ptr(),
+        ptr::null(),
+        output.as_mut_ptr(),
+    ));
+
+    let err = match result {
+        Ok(Success::PipelineCompileRequired) => GraphicsPipelineCreationError::CompileRequired,
+        Ok(_) => return Ok(output),
+        Err(err) => err.into(),
+    };
+
+    for handle in output {
+        if handle != ash::vk::Pipeline::null() {
+            fns.v1_0
+                .destroy_pipeline(device.internal_object(), handle, ptr::null());
+        }
+    }
+
+    Err(err)
+}
+
+// Checks that the execution modes of the tessellation control and evaluation shaders of a
+// pipeline are compatible.
+fn check_tessellation_execution(
+    device: &Device,
+    control: &TessellationShaderExecution,
+    evaluation: &TessellationShaderExecution,
+) -> Result<(), GraphicsPipelineCreationError> {
+    // Each execution mode must be declared in at least one of the two shaders, and with the same
+    // value if it is declared in both.
+    let output_vertices = match (control.output_vertices, evaluation.output_vertices) {
+        (Some(a), Some(b)) if a != b => {
+            return Err(GraphicsPipelineCreationError::TessellationShadersIncompatible)
+        }
+        (a, b) => a.or(b),
+    };
+
+    match output_vertices {
+        Some(output_vertices)
+            if output_vertices != 0
+                && output_vertices
+                    <= device
+                        .physical_device()
+                        .properties()
+                        .max_tessellation_patch_size => {}
+        _ => return Err(GraphicsPipelineCreationError::InvalidNumPatchOutputVertices),
+    }
+
+    match (control.subdivision, evaluation.subdivision) {
+        (Some(a), Some(b)) if a != b => {
+            Err(GraphicsPipelineCreationError::TessellationShadersIncompatible)
+        }
+        (None, None) => Err(GraphicsPipelineCreationError::TessellationShadersIncompatible),
+        _ => Ok(()),
+    }
+}
+
 impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
     GraphicsPipelineBuilder<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
 {
-    // TODO: add pipeline derivate system
-
     /// Sets the vertex shader to use.
     // TODO: correct specialization constants
     #[inline]
@@ -1133,6 +1465,10 @@ impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
         GraphicsPipelineBuilder {
             subpass: self.subpass,
             cache: self.cache,
+            fail_on_compile_required: self.fail_on_compile_required,
+            indirect_bindable: self.indirect_bindable,
+            allow_derivatives: self.allow_derivatives,
+            base_pipeline: self.base_pipeline,
 
             vertex_shader: Some((shader, specialization_constants)),
             tessellation_shaders: self.tessellation_shaders,
@@ -1140,10 +1476,12 @@ impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
             fragment_shader: self.fragment_shader,
 
             vertex_definition: self.vertex_definition,
+            vertex_input_dynamic: self.vertex_input_dynamic,
             input_assembly_state: self.input_assembly_state,
             tessellation_state: self.tessellation_state,
             viewport_state: self.viewport_state,
             discard_rectangle_state: self.discard_rectangle_state,
+            fragment_shading_rate_state: self.fragment_shading_rate_state,
             rasterization_state: self.rasterization_state,
             multisample_state: self.multisample_state,
             depth_stencil_state: self.depth_stencil_state,
@@ -1168,6 +1506,10 @@ impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
         GraphicsPipelineBuilder {
             subpass: self.subpass,
             cache: self.cache,
+            fail_on_compile_required: self.fail_on_compile_required,
+            indirect_bindable: self.indirect_bindable,
+            allow_derivatives: self.allow_derivatives,
+            base_pipeline: self.base_pipeline,
 
             vertex_shader: self.vertex_shader,
             tessellation_shaders: Some(TessellationShaders {
@@ -1181,10 +1523,12 @@ impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
             fragment_shader: self.fragment_shader,
 
             vertex_definition: self.vertex_definition,
+            vertex_input_dynamic: self.vertex_input_dynamic,
             input_assembly_state: self.input_assembly_state,
             tessellation_state: self.tessellation_state,
             viewport_state: self.viewport_state,
             discard_rectangle_state: self.discard_rectangle_state,
+            fragment_shading_rate_state: self.fragment_shading_rate_state,
             rasterization_state: self.rasterization_state,
             multisample_state: self.multisample_state,
             depth_stencil_state: self.depth_stencil_state,
@@ -1206,6 +1550,10 @@ impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
         GraphicsPipelineBuilder {
             subpass: self.subpass,
             cache: self.cache,
+            fail_on_compile_required: self.fail_on_compile_required,
+            indirect_bindable: self.indirect_bindable,
+            allow_derivatives: self.allow_derivatives,
+            base_pipeline: self.base_pipeline,
 
             vertex_shader: self.vertex_shader,
             tessellation_shaders: self.tessellation_shaders,
@@ -1213,10 +1561,12 @@ impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
             fragment_shader: self.fragment_shader,
 
             vertex_definition: self.vertex_definition,
+            vertex_input_dynamic: self.vertex_input_dynamic,
             input_assembly_state: self.input_assembly_state,
             tessellation_state: self.tessellation_state,
             viewport_state: self.viewport_state,
             discard_rectangle_state: self.discard_rectangle_state,
+            fragment_shading_rate_state: self.fragment_shading_rate_state,
             rasterization_state: self.rasterization_state,
             multisample_state: self.multisample_state,
             depth_stencil_state: self.depth_stencil_state,
@@ -1240,6 +1590,10 @@ impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
         GraphicsPipelineBuilder {
             subpass: self.subpass,
             cache: self.cache,
+            fail_on_compile_required: self.fail_on_compile_required,
+            indirect_bindable: self.indirect_bindable,
+            allow_derivatives: self.allow_derivatives,
+            base_pipeline: self.base_pipeline,
 
             vertex_shader: self.vertex_shader,
             tessellation_shaders: self.tessellation_shaders,
@@ -1247,10 +1601,12 @@ impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
             fragment_shader: Some((shader, specialization_constants)),
 
             vertex_definition: self.vertex_definition,
+            vertex_input_dynamic: self.vertex_input_dynamic,
             input_assembly_state: self.input_assembly_state,
             tessellation_state: self.tessellation_state,
             viewport_state: self.viewport_state,
             discard_rectangle_state: self.discard_rectangle_state,
+            fragment_shading_rate_state: self.fragment_shading_rate_state,
             rasterization_state: self.rasterization_state,
             multisample_state: self.multisample_state,
             depth_stencil_state: self.depth_stencil_state,
@@ -1267,6 +1623,10 @@ impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
         GraphicsPipelineBuilder {
             subpass: self.subpass,
             cache: self.cache,
+            fail_on_compile_required: self.fail_on_compile_required,
+            indirect_bindable: self.indirect_bindable,
+            allow_derivatives: self.allow_derivatives,
+            base_pipeline: self.base_pipeline,
 
             vertex_shader: self.vertex_shader,
             tessellation_shaders: self.tessellation_shaders,
@@ -1274,10 +1634,12 @@ impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
             fragment_shader: self.fragment_shader,
 
             vertex_definition,
+            vertex_input_dynamic: false,
             input_assembly_state: self.input_assembly_state,
             tessellation_state: self.tessellation_state,
             viewport_state: self.viewport_state,
             discard_rectangle_state: self.discard_rectangle_state,
+            fragment_shading_rate_state: self.fragment_shading_rate_state,
             rasterization_state: self.rasterization_state,
             multisample_state: self.multisample_state,
             depth_stencil_state: self.depth_stencil_state,
@@ -1308,6 +1670,18 @@ impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
         self.vertex_input(BuffersDefinition::new().vertex::<V>())
     }
 
+    /// Makes the vertex input dynamic, so that it must be set with
+    /// [`set_vertex_input`](crate::command_buffer::AutoCommandBufferBuilder::set_vertex_input)
+    /// before drawing. The vertex definition of the builder is then ignored.
+    ///
+    /// The [`vertex_input_dynamic_state`](crate::device::Features::vertex_input_dynamic_state)
+    /// feature must be enabled on the device.
+    #[inline]
+    pub fn vertex_input_dynamic(mut self) -> Self {
+        self.vertex_input_dynamic = true;
+        self
+    }
+
     /// Sets the input assembly state.
     ///
     /// The default value is [`InputAssemblyState::default()`].
@@ -1348,6 +1722,18 @@ impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
         self
     }
 
+    /// Sets the fragment shading rate state.
+    ///
+    /// The default value is [`FragmentShadingRateState::default()`].
+    #[inline]
+    pub fn fragment_shading_rate_state(
+        mut self,
+        fragment_shading_rate_state: FragmentShadingRateState,
+    ) -> Self {
+        self.fragment_shading_rate_state = fragment_shading_rate_state;
+        self
+    }
+
     /// Sets the rasterization state.
     ///
     /// The default value is [`RasterizationState::default()`].
@@ -1929,6 +2315,10 @@ impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
         GraphicsPipelineBuilder {
             subpass: Some(subpass),
             cache: self.cache,
+            fail_on_compile_required: self.fail_on_compile_required,
+            indirect_bindable: self.indirect_bindable,
+            allow_derivatives: self.allow_derivatives,
+            base_pipeline: self.base_pipeline,
 
             vertex_shader: self.vertex_shader,
             tessellation_shaders: self.tessellation_shaders,
@@ -1936,6 +2326,7 @@ impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
             fragment_shader: self.fragment_shader,
 
             vertex_definition: self.vertex_definition,
+            vertex_input_dynamic: self.vertex_input_dynamic,
             input_assembly_state: self.input_assembly_state,
             tessellation_state: self.tessellation_state,
             viewport_state: self.viewport_state,
@@ -1945,6 +2336,7 @@ impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
             color_blend_state: self.color_blend_state,
 
             discard_rectangle_state: self.discard_rectangle_state,
+            fragment_shading_rate_state: self.fragment_shading_rate_state,
         }
     }
 
@@ -1958,6 +2350,61 @@ impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss>
         self.cache = Some(pipeline_cache);
         self
     }
+
+    /// Makes building the pipeline fail with `GraphicsPipelineCreationError::CompileRequired`
+    /// instead of compiling it, if it can't be created from the pipeline cache or from data
+    /// that the implementation cached internally.
+    ///
+    /// This allows trying to create a pipeline quickly on a latency-sensitive thread, and
+    /// deferring the compilation to another thread if that fails. See also
+    /// [`PipelineCompiler`](crate::pipeline::compiler::PipelineCompiler).
+    ///
+    /// The [`pipeline_creation_cache_control`](crate::device::Features::pipeline_creation_cache_control)
+    /// feature must be enabled on the device.
+    #[inline]
+    pub fn fail_on_compile_required(mut self, enable: bool) -> Self {
+        self.fail_on_compile_required = enable;
+        self
+    }
+
+    /// Allows other pipelines to be created as derivatives of this one, with
+    /// [`base_pipeline`](#method.base_pipeline).
+    ///
+    /// The default value is `false`.
+    #[inline]
+    pub fn allow_derivatives(mut self, allow: bool) -> Self {
+        self.allow_derivatives = allow;
+        self
+    }
+
+    /// Allows the pipeline to be used to execute commands that are generated by the device. See
+    /// the [`indirect_commands`](crate::command_buffer::indirect_commands) module.
+    ///
+    /// The [`device_generated_commands`](crate::device::Features::device_generated_commands)
+    /// feature must be enabled on the device.
+    ///
+    /// The default value is `false`.
+    #[inline]
+    pub fn indirect_bindable(mut self, enable: bool) -> Self {
+        self.indirect_bindable = enable;
+        self
+    }
+
+    /// Creates the pipeline as a derivative of `base_pipeline`, which lets the implementation
+    /// reuse work from the base pipeline if the two are similar.
+    ///
+    /// `base_pipeline` must have been created with [`allow_derivatives`](#method.allow_derivatives)
+    /// enabled.
+    ///
+    /// # Panic
+    ///
+    /// - Building panics if `base_pipeline` doesn't belong to the device the pipeline is built
+    ///   with.
+    #[inline]
+    pub fn base_pipeline(mut self, base_pipeline: Arc<GraphicsPipeline>) -> Self {
+        self.base_pipeline = Some(base_pipeline);
+        self
+    }
 }
 
 impl<'vs, 'tcs, 'tes, 'gs, 'fs, Vdef, Vss, Tcss, Tess, Gss, Fss> Clone
@@ -1974,6 +2421,10 @@ where
         GraphicsPipelineBuilder {
             subpass: self.subpass.clone(),
             cache: self.cache.clone(),
+            fail_on_compile_required: self.fail_on_compile_required,
+            indirect_bindable: self.indirect_bindable,
+            allow_derivatives: self.allow_derivatives,
+            base_pipeline: self.base_pipeline.clone(),
 
             vertex_shader: self.vertex_shader.clone(),
             tessellation_shaders: self.tessellation_shaders.clone(),
@@ -1981,6 +2432,7 @@ where
             fragment_shader: self.fragment_shader.clone(),
 
             vertex_definition: self.vertex_definition.clone(),
+            vertex_input_dynamic: self.vertex_input_dynamic,
             input_assembly_state: self.input_assembly_state,
             tessellation_state: self.tessellation_state,
             viewport_state: self.viewport_state.clone(),
@@ -1990,6 +2442,90 @@ where
             color_blend_state: self.color_blend_state.clone(),
 
             discard_rectangle_state: self.discard_rectangle_state.clone(),
+            fragment_shading_rate_state: self.fragment_shading_rate_state,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_tessellation_execution;
+    use crate::pipeline::graphics_pipeline::GraphicsPipelineCreationError;
+    use crate::shader::TessellationShaderExecution;
+    use crate::shader::TessellationShaderSubdivision;
+
+    #[test]
+    fn tessellation_execution_split() {
+        let (device, _) = gfx_dev_and_queue!(tessellation_shader);
+
+        // The modes can be declared in either shader.
+        let control = TessellationShaderExecution {
+            output_vertices: Some(3),
+            ..Default::default()
+        };
+        let evaluation = TessellationShaderExecution {
+            subdivision: Some(TessellationShaderSubdivision::Triangles),
+            ..Default::default()
+        };
+
+        assert!(check_tessellation_execution(&device, &control, &evaluation).is_ok());
+    }
+
+    #[test]
+    fn tessellation_shaders_incompatible() {
+        let (device, _) = gfx_dev_and_queue!(tessellation_shader);
+
+        let control = TessellationShaderExecution {
+            output_vertices: Some(3),
+            subdivision: Some(TessellationShaderSubdivision::Triangles),
+            point_mode: false,
+        };
+        let evaluation = TessellationShaderExecution {
+            subdivision: Some(TessellationShaderSubdivision::Quads),
+            ..Default::default()
+        };
+
+        match check_tessellation_execution(&device, &control, &evaluation) {
+            Err(GraphicsPipelineCreationError::TessellationShadersIncompatible) => (),
+            _ => panic!(),
+        }
+
+        // Neither shader declares the type of primitive.
+        let evaluation = TessellationShaderExecution::default();
+        let control = TessellationShaderExecution {
+            output_vertices: Some(3),
+            ..Default::default()
+        };
+
+        match check_tessellation_execution(&device, &control, &evaluation) {
+            Err(GraphicsPipelineCreationError::TessellationShadersIncompatible) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn invalid_num_patch_output_vertices() {
+        let (device, _) = gfx_dev_and_queue!(tessellation_shader);
+        let max_patch_size = device
+            .physical_device()
+            .properties()
+            .max_tessellation_patch_size;
+
+        let evaluation = TessellationShaderExecution {
+            subdivision: Some(TessellationShaderSubdivision::Isolines),
+            ..Default::default()
+        };
+
+        for &output_vertices in [None, Some(0), Some(max_patch_size + 1)].iter() {
+            let control = TessellationShaderExecution {
+                output_vertices,
+                ..Default::default()
+            };
+
+            match check_tessellation_execution(&device, &control, &evaluation) {
+                Err(GraphicsPipelineCreationError::InvalidNumPatchOutputVertices) => (),
+                _ => panic!(),
+            }
         }
     }
 }
@@ -20,6 +20,21 @@ use std::u32;
 /// Error that can happen when creating a graphics pipeline.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum GraphicsPipelineCreationError {
+    /// The base pipeline was not created with
+    /// [`allow_derivatives`](crate::pipeline::GraphicsPipelineBuilder::allow_derivatives) enabled.
+    BasePipelineDerivativesNotAllowed,
+
+    /// Blending was enabled for a color attachment whose format doesn't support the
+    /// `color_attachment_blend` format feature.
+    ColorAttachmentFormatBlendNotSupported {
+        /// Index of the color attachment in the subpass.
+        attachment_index: u32,
+    },
+
+    /// [`fail_on_compile_required`](crate::pipeline::GraphicsPipelineBuilder::fail_on_compile_required)
+    /// was enabled, and the pipeline could not be created without compiling it.
+    CompileRequired,
+
     /// A device extension that was required for a particular setting on the graphics pipeline was not enabled.
     ExtensionNotEnabled {
         extension: &'static str,
@@ -45,6 +60,9 @@ pub enum GraphicsPipelineCreationError {
     /// The vertex definition is not compatible with the input of the vertex shader.
     IncompatibleVertexDefinition(IncompatibleVertexDefinitionError),
 
+    /// The fragment size of a fragment shading rate was not 1, 2 or 4 in each dimension.
+    InvalidFragmentShadingRateSize,
+
     /// Tried to use a patch list without a tessellation shader, or a non-patch-list with a
     /// tessellation shader.
     InvalidPrimitiveTopology,
@@ -52,6 +70,10 @@ pub enum GraphicsPipelineCreationError {
     /// `patch_control_points` was not greater than 0 and less than or equal to the `max_tessellation_patch_size` limit.
     InvalidNumPatchControlPoints,
 
+    /// The tessellation shaders don't declare the number of output vertices of a patch, or it
+    /// was not greater than 0 and less than or equal to the `max_tessellation_patch_size` limit.
+    InvalidNumPatchOutputVertices,
+
     /// The maximum number of discard rectangles has been exceeded.
     MaxDiscardRectanglesExceeded {
         /// Maximum allowed value.
@@ -60,6 +82,19 @@ pub enum GraphicsPipelineCreationError {
         obtained: u32,
     },
 
+    /// The extra primitive overestimation size was negative or greater than the
+    /// [`max_extra_primitive_overestimation_size`](crate::device::Properties::max_extra_primitive_overestimation_size)
+    /// device property.
+    MaxExtraPrimitiveOverestimationSizeExceeded,
+
+    /// The maximum number of transform feedback streams has been exceeded.
+    MaxTransformFeedbackStreamsExceeded {
+        /// Maximum allowed value.
+        max: u32,
+        /// Value that was passed.
+        obtained: u32,
+    },
+
     /// The maximum value for the instance rate divisor has been exceeded.
     MaxVertexAttribDivisorExceeded {
         /// Index of the faulty binding.
@@ -127,6 +162,12 @@ pub enum GraphicsPipelineCreationError {
     /// The device doesn't support using the `multiview´ feature with tessellation shaders.
     MultiviewTessellationShaderNotSupported,
 
+    /// A fragment shading rate combiner operation other than `Keep` or `Replace` was used, but
+    /// the
+    /// [`fragment_shading_rate_non_trivial_combiner_ops`](crate::device::Properties::fragment_shading_rate_non_trivial_combiner_ops)
+    /// device property was `false`.
+    NonTrivialFragmentShadingRateCombinerOp,
+
     /// The depth test requires a depth attachment but render pass has no depth attachment, or
     /// depth writing is enabled and the depth attachment is read-only.
     NoDepthAttachment,
@@ -141,12 +182,25 @@ pub enum GraphicsPipelineCreationError {
     /// Error while creating the pipeline layout object.
     PipelineLayoutCreationError(PipelineLayoutCreationError),
 
+    /// The [`primitive_underestimation`](crate::device::Properties::primitive_underestimation)
+    /// device property was `false`.
+    PrimitiveUnderestimationNotSupported,
+
+    /// The rasterization stream was not 0, but the
+    /// [`transform_feedback_rasterization_stream_select`](crate::device::Properties::transform_feedback_rasterization_stream_select)
+    /// device property was `false`.
+    RasterizationStreamSelectNotSupported,
+
     /// The output interface of one shader and the input interface of the next shader do not match.
     ShaderStagesMismatch(ShaderInterfaceMismatchError),
 
     /// The [`strict_lines`](crate::device::Properties::strict_lines) device property was `false`.
     StrictLinesNotSupported,
 
+    /// The tessellation control and evaluation shaders declare different execution modes, or
+    /// neither declares the type of primitive to generate.
+    TessellationShadersIncompatible,
+
     /// The primitives topology does not match what the geometry shader expects.
     TopologyNotMatchingGeometryShader,
 
@@ -181,6 +235,21 @@ impl fmt::Display for GraphicsPipelineCreationError {
     #[inline]
     fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match *self {
+            GraphicsPipelineCreationError::BasePipelineDerivativesNotAllowed => {
+                write!(fmt, "the base pipeline doesn't allow derivatives")
+            }
+            GraphicsPipelineCreationError::ColorAttachmentFormatBlendNotSupported { .. } => {
+                write!(
+                    fmt,
+                    "blending was enabled for a color attachment whose format doesn't support it"
+                )
+            }
+            GraphicsPipelineCreationError::CompileRequired => {
+                write!(
+                    fmt,
+                    "the pipeline could not be created without compiling it"
+                )
+            }
             GraphicsPipelineCreationError::ExtensionNotEnabled { extension, reason } => {
                 write!(
                     fmt,
@@ -209,18 +278,39 @@ impl fmt::Display for GraphicsPipelineCreationError {
                     "the vertex definition is not compatible with the input of the vertex shader"
                 )
             }
+            GraphicsPipelineCreationError::InvalidFragmentShadingRateSize => {
+                write!(
+                    fmt,
+                    "the fragment size of a fragment shading rate was not 1, 2 or 4 in each dimension"
+                )
+            }
             GraphicsPipelineCreationError::InvalidPrimitiveTopology => {
                 write!(fmt, "trying to use a patch list without a tessellation shader, or a non-patch-list with a tessellation shader")
             }
             GraphicsPipelineCreationError::InvalidNumPatchControlPoints => {
                 write!(fmt, "patch_control_points was not greater than 0 and less than or equal to the max_tessellation_patch_size limit")
             }
+            GraphicsPipelineCreationError::InvalidNumPatchOutputVertices => {
+                write!(fmt, "the number of output vertices of a patch is missing, or was not greater than 0 and less than or equal to the max_tessellation_patch_size limit")
+            }
             GraphicsPipelineCreationError::MaxDiscardRectanglesExceeded { .. } => {
                 write!(
                     fmt,
                     "the maximum number of discard rectangles has been exceeded"
                 )
             }
+            GraphicsPipelineCreationError::MaxExtraPrimitiveOverestimationSizeExceeded => {
+                write!(
+                    fmt,
+                    "the extra primitive overestimation size is outside the limits of the device"
+                )
+            }
+            GraphicsPipelineCreationError::MaxTransformFeedbackStreamsExceeded { .. } => {
+                write!(
+                    fmt,
+                    "the maximum number of transform feedback streams has been exceeded"
+                )
+            }
             GraphicsPipelineCreationError::MaxVertexAttribDivisorExceeded { .. } => {
                 write!(
                     fmt,
@@ -263,6 +353,9 @@ impl fmt::Display for GraphicsPipelineCreationError {
             GraphicsPipelineCreationError::MultiviewTessellationShaderNotSupported => {
                 write!(fmt, "the device doesn't support using the `multiview´ feature with tessellation shaders")
             }
+            GraphicsPipelineCreationError::NonTrivialFragmentShadingRateCombinerOp => {
+                write!(fmt, "a fragment shading rate combiner operation other than Keep or Replace was used, but the device doesn't support it")
+            }
             GraphicsPipelineCreationError::NoDepthAttachment => {
                 write!(
                     fmt,
@@ -281,12 +374,30 @@ impl fmt::Display for GraphicsPipelineCreationError {
             GraphicsPipelineCreationError::PipelineLayoutCreationError(_) => {
                 write!(fmt, "error while creating the pipeline layout object")
             }
+            GraphicsPipelineCreationError::PrimitiveUnderestimationNotSupported => {
+                write!(
+                    fmt,
+                    "the primitive_underestimation device property was false"
+                )
+            }
+            GraphicsPipelineCreationError::RasterizationStreamSelectNotSupported => {
+                write!(
+                    fmt,
+                    "the transform_feedback_rasterization_stream_select device property was false"
+                )
+            }
             GraphicsPipelineCreationError::ShaderStagesMismatch(_) => {
                 write!(fmt, "the output interface of one shader and the input interface of the next shader do not match")
             }
             GraphicsPipelineCreationError::StrictLinesNotSupported => {
                 write!(fmt, "the strict_lines device property was false")
             }
+            GraphicsPipelineCreationError::TessellationShadersIncompatible => {
+                write!(
+                    fmt,
+                    "the tessellation control and evaluation shaders have incompatible execution modes"
+                )
+            }
             GraphicsPipelineCreationError::TopologyNotMatchingGeometryShader => {
                 write!(
                     fmt,
@@ -11,8 +11,10 @@ pub use self::builder::GraphicsPipelineBuilder;
 pub use self::creation_error::GraphicsPipelineCreationError;
 use crate::device::{Device, DeviceOwned};
 use crate::pipeline::color_blend::ColorBlendState;
+use crate::pipeline::creation_feedback::PipelineCreationFeedback;
 use crate::pipeline::depth_stencil::DepthStencilState;
 use crate::pipeline::discard_rectangle::DiscardRectangleState;
+use crate::pipeline::fragment_shading_rate::FragmentShadingRateState;
 use crate::pipeline::input_assembly::InputAssemblyState;
 use crate::pipeline::layout::PipelineLayout;
 use crate::pipeline::multisample::MultisampleState;
@@ -22,7 +24,7 @@ use crate::pipeline::vertex::{BuffersDefinition, VertexInput};
 use crate::pipeline::viewport::ViewportState;
 use crate::pipeline::{DynamicState, Pipeline, PipelineBindPoint};
 use crate::render_pass::Subpass;
-use crate::shader::{DescriptorRequirements, ShaderStage};
+use crate::shader::{DescriptorRequirements, ShaderInterface, ShaderStage};
 use crate::VulkanObject;
 use fnv::FnvHashMap;
 use std::fmt;
@@ -51,15 +53,20 @@ pub struct GraphicsPipeline {
     num_used_descriptor_sets: u32,
 
     vertex_input: VertexInput,
+    vertex_shader_input: ShaderInterface,
     input_assembly_state: InputAssemblyState,
     tessellation_state: Option<TessellationState>,
     viewport_state: Option<ViewportState>,
     discard_rectangle_state: Option<DiscardRectangleState>,
+    fragment_shading_rate_state: Option<FragmentShadingRateState>,
     rasterization_state: RasterizationState,
     multisample_state: Option<MultisampleState>,
     depth_stencil_state: Option<DepthStencilState>,
     color_blend_state: Option<ColorBlendState>,
     dynamic_state: FnvHashMap<DynamicState, bool>,
+    allows_derivatives: bool,
+    indirect_bindable: bool,
+    creation_feedback: Option<PipelineCreationFeedback>,
 }
 
 impl GraphicsPipeline {
@@ -115,11 +122,19 @@ impl GraphicsPipeline {
     }
 
     /// Returns the vertex input state used to create this pipeline.
+    ///
+    /// If the vertex input is dynamic, this is empty.
     #[inline]
     pub fn vertex_input(&self) -> &VertexInput {
         &self.vertex_input
     }
 
+    /// Returns the input interface of the vertex shader.
+    #[inline]
+    pub(crate) fn vertex_shader_input(&self) -> &ShaderInterface {
+        &self.vertex_shader_input
+    }
+
     /// Returns the input assembly state used to create this pipeline.
     #[inline]
     pub fn input_assembly_state(&self) -> &InputAssemblyState {
@@ -144,6 +159,12 @@ impl GraphicsPipeline {
         self.discard_rectangle_state.as_ref()
     }
 
+    /// Returns the fragment shading rate state used to create this pipeline.
+    #[inline]
+    pub fn fragment_shading_rate_state(&self) -> Option<&FragmentShadingRateState> {
+        self.fragment_shading_rate_state.as_ref()
+    }
+
     /// Returns the rasterization state used to create this pipeline.
     #[inline]
     pub fn rasterization_state(&self) -> &RasterizationState {
@@ -176,6 +197,29 @@ impl GraphicsPipeline {
         self.dynamic_state.get(&state).copied()
     }
 
+    /// Returns whether other pipelines can be created as derivatives of this one.
+    #[inline]
+    pub fn allows_derivatives(&self) -> bool {
+        self.allows_derivatives
+    }
+
+    /// Returns whether the pipeline can be used to execute commands that are generated by the
+    /// device.
+    #[inline]
+    pub fn indirect_bindable(&self) -> bool {
+        self.indirect_bindable
+    }
+
+    /// Returns the feedback that the implementation returned when creating the pipeline.
+    ///
+    /// This is `None` if the
+    /// [`ext_pipeline_creation_feedback`](crate::device::DeviceExtensions::ext_pipeline_creation_feedback)
+    /// extension was not enabled on the device.
+    #[inline]
+    pub fn creation_feedback(&self) -> Option<&PipelineCreationFeedback> {
+        self.creation_feedback.as_ref()
+    }
+
     /// Returns all potentially dynamic states in the pipeline, and whether they are dynamic or not.
     pub fn dynamic_states(&self) -> impl ExactSizeIterator<Item = (DynamicState, bool)> + '_ {
         self.dynamic_state.iter().map(|(k, v)| (*k, *v))
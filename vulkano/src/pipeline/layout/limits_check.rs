@@ -16,6 +16,7 @@ use crate::pipeline::layout::PipelineLayoutPcRange;
 use crate::shader::ShaderStages;
 use std::error;
 use std::fmt;
+use std::slice;
 use std::sync::Arc;
 
 /// Checks whether the pipeline layout description fulfills the device limits requirements.
@@ -33,43 +34,67 @@ pub fn check_desc_against_limits(
     let mut num_sampled_images = Counter::default();
     let mut num_storage_images = Counter::default();
     let mut num_input_attachments = Counter::default();
+    let mut num_acceleration_structures = Counter::default();
 
     for set in descriptor_set_layouts {
         for descriptor in (0..set.num_bindings()).filter_map(|i| set.descriptor(i).map(|d| d)) {
             num_resources.increment(descriptor.descriptor_count, &descriptor.stages);
 
-            match descriptor.ty {
-                // TODO:
-                DescriptorType::Sampler => {
-                    num_samplers.increment(descriptor.descriptor_count, &descriptor.stages);
-                }
-                DescriptorType::CombinedImageSampler => {
-                    num_samplers.increment(descriptor.descriptor_count, &descriptor.stages);
-                    num_sampled_images.increment(descriptor.descriptor_count, &descriptor.stages);
-                }
-                DescriptorType::SampledImage | DescriptorType::UniformTexelBuffer => {
-                    num_sampled_images.increment(descriptor.descriptor_count, &descriptor.stages);
-                }
-                DescriptorType::StorageImage | DescriptorType::StorageTexelBuffer => {
-                    num_storage_images.increment(descriptor.descriptor_count, &descriptor.stages);
-                }
-                DescriptorType::UniformBuffer => {
-                    num_uniform_buffers.increment(descriptor.descriptor_count, &descriptor.stages);
-                }
-                DescriptorType::UniformBufferDynamic => {
-                    num_uniform_buffers.increment(descriptor.descriptor_count, &descriptor.stages);
-                    num_uniform_buffers_dynamic += 1;
-                }
-                DescriptorType::StorageBuffer => {
-                    num_storage_buffers.increment(descriptor.descriptor_count, &descriptor.stages);
-                }
-                DescriptorType::StorageBufferDynamic => {
-                    num_storage_buffers.increment(descriptor.descriptor_count, &descriptor.stages);
-                    num_storage_buffers_dynamic += 1;
-                }
-                DescriptorType::InputAttachment => {
-                    num_input_attachments
-                        .increment(descriptor.descriptor_count, &descriptor.stages);
+            // Mutable descriptors count against the limits of each of their mutable types.
+            let types = if descriptor.ty == DescriptorType::Mutable {
+                &descriptor.mutable_types[..]
+            } else {
+                slice::from_ref(&descriptor.ty)
+            };
+
+            for &ty in types {
+                match ty {
+                    // TODO:
+                    DescriptorType::Sampler => {
+                        num_samplers.increment(descriptor.descriptor_count, &descriptor.stages);
+                    }
+                    DescriptorType::CombinedImageSampler => {
+                        num_samplers.increment(descriptor.descriptor_count, &descriptor.stages);
+                        num_sampled_images
+                            .increment(descriptor.descriptor_count, &descriptor.stages);
+                    }
+                    DescriptorType::SampledImage | DescriptorType::UniformTexelBuffer => {
+                        num_sampled_images
+                            .increment(descriptor.descriptor_count, &descriptor.stages);
+                    }
+                    DescriptorType::StorageImage | DescriptorType::StorageTexelBuffer => {
+                        num_storage_images
+                            .increment(descriptor.descriptor_count, &descriptor.stages);
+                    }
+                    DescriptorType::UniformBuffer => {
+                        num_uniform_buffers
+                            .increment(descriptor.descriptor_count, &descriptor.stages);
+                    }
+                    DescriptorType::UniformBufferDynamic => {
+                        num_uniform_buffers
+                            .increment(descriptor.descriptor_count, &descriptor.stages);
+                        num_uniform_buffers_dynamic += 1;
+                    }
+                    DescriptorType::StorageBuffer => {
+                        num_storage_buffers
+                            .increment(descriptor.descriptor_count, &descriptor.stages);
+                    }
+                    DescriptorType::StorageBufferDynamic => {
+                        num_storage_buffers
+                            .increment(descriptor.descriptor_count, &descriptor.stages);
+                        num_storage_buffers_dynamic += 1;
+                    }
+                    DescriptorType::InputAttachment => {
+                        num_input_attachments
+                            .increment(descriptor.descriptor_count, &descriptor.stages);
+                    }
+                    DescriptorType::AccelerationStructure => {
+                        num_acceleration_structures
+                            .increment(descriptor.descriptor_count, &descriptor.stages);
+                    }
+                    // Checked when creating the layout that the mutable types don't include
+                    // `Mutable`.
+                    DescriptorType::Mutable => unreachable!(),
                 }
             }
         }
@@ -140,6 +165,16 @@ pub fn check_desc_against_limits(
             },
         );
     }
+    if let Some(limit) = properties.max_per_stage_descriptor_acceleration_structures {
+        if num_acceleration_structures.max_per_stage() > limit {
+            return Err(
+                PipelineLayoutLimitsError::MaxPerStageDescriptorAccelerationStructuresLimitExceeded {
+                    limit,
+                    requested: num_acceleration_structures.max_per_stage(),
+                },
+            );
+        }
+    }
 
     if num_samplers.total > properties.max_descriptor_set_samplers {
         return Err(
@@ -205,6 +240,16 @@ pub fn check_desc_against_limits(
             },
         );
     }
+    if let Some(limit) = properties.max_descriptor_set_acceleration_structures {
+        if num_acceleration_structures.total > limit {
+            return Err(
+                PipelineLayoutLimitsError::MaxDescriptorSetAccelerationStructuresLimitExceeded {
+                    limit,
+                    requested: num_acceleration_structures.total,
+                },
+            );
+        }
+    }
 
     for &PipelineLayoutPcRange { offset, size, .. } in push_constants_ranges {
         if offset + size > properties.max_push_constants_size {
@@ -293,6 +338,14 @@ pub enum PipelineLayoutLimitsError {
         requested: u32,
     },
 
+    /// The `max_per_stage_descriptor_acceleration_structures()` limit has been exceeded.
+    MaxPerStageDescriptorAccelerationStructuresLimitExceeded {
+        /// The limit that must be fulfilled.
+        limit: u32,
+        /// What was requested.
+        requested: u32,
+    },
+
     /// The `max_descriptor_set_samplers()` limit has been exceeded.
     MaxDescriptorSetSamplersLimitExceeded {
         /// The limit that must be fulfilled.
@@ -356,6 +409,14 @@ pub enum PipelineLayoutLimitsError {
         /// What was requested.
         requested: u32,
     },
+
+    /// The `max_descriptor_set_acceleration_structures()` limit has been exceeded.
+    MaxDescriptorSetAccelerationStructuresLimitExceeded {
+        /// The limit that must be fulfilled.
+        limit: u32,
+        /// What was requested.
+        requested: u32,
+    },
 }
 
 impl error::Error for PipelineLayoutLimitsError {}
@@ -396,6 +457,12 @@ impl fmt::Display for PipelineLayoutLimitsError {
                 PipelineLayoutLimitsError::MaxPerStageDescriptorInputAttachmentsLimitExceeded {
                     ..
                 } => "the `max_per_stage_descriptor_input_attachments()` limit has been exceeded",
+                PipelineLayoutLimitsError::MaxPerStageDescriptorAccelerationStructuresLimitExceeded {
+                    ..
+                } => {
+                    "the `max_per_stage_descriptor_acceleration_structures()` limit has been \
+                     exceeded"
+                }
                 PipelineLayoutLimitsError::MaxDescriptorSetSamplersLimitExceeded { .. } => {
                     "the `max_descriptor_set_samplers()` limit has been exceeded"
                 }
@@ -430,6 +497,11 @@ impl fmt::Display for PipelineLayoutLimitsError {
                 } => {
                     "the `max_descriptor_set_input_attachments()` limit has been exceeded"
                 }
+                PipelineLayoutLimitsError::MaxDescriptorSetAccelerationStructuresLimitExceeded {
+                    ..
+                } => {
+                    "the `max_descriptor_set_acceleration_structures()` limit has been exceeded"
+                }
             }
         )
     }
@@ -10,6 +10,7 @@
 use super::limits_check;
 use crate::check_errors;
 use crate::descriptor_set::layout::DescriptorRequirementsNotMet;
+use crate::descriptor_set::layout::DescriptorSetDesc;
 use crate::descriptor_set::layout::DescriptorSetLayout;
 use crate::descriptor_set::layout::DescriptorSetLayoutError;
 use crate::device::Device;
@@ -26,6 +27,7 @@ use std::fmt;
 use std::mem::MaybeUninit;
 use std::ptr;
 use std::sync::Arc;
+use std::sync::Weak;
 
 /// Wrapper around the `PipelineLayout` Vulkan object. Describes to the Vulkan implementation the
 /// descriptor sets and push constants available to your shaders.
@@ -77,15 +79,7 @@ impl PipelineLayout {
         }
 
         // Sort the ranges for the purpose of comparing for equality.
-        // The stage mask is guaranteed to be unique by the above check, so it's a suitable
-        // sorting key.
-        push_constant_ranges.sort_unstable_by_key(|range| {
-            (
-                range.offset,
-                range.size,
-                ash::vk::ShaderStageFlags::from(range.stages),
-            )
-        });
+        sort_push_constant_ranges(&mut push_constant_ranges);
 
         // Check against device limits
         limits_check::check_desc_against_limits(
@@ -172,6 +166,59 @@ impl PipelineLayout {
         }))
     }
 
+    /// Returns a pipeline layout for the given descriptor set descriptions and push constant
+    /// ranges, reusing an existing one if possible.
+    ///
+    /// The device keeps track of the pipeline layouts that were created with this function. If
+    /// one of them is still alive and is identically defined, it is returned instead of creating
+    /// a new `VkPipelineLayout`. This way, pipelines whose shaders have the same interface share
+    /// the same layout, and descriptor sets don't need to be bound again when switching between
+    /// them. This is used for the layouts that are automatically created when building a
+    /// pipeline.
+    pub fn get_or_create<D, P>(
+        device: Arc<Device>,
+        descriptor_set_descs: D,
+        push_constant_ranges: P,
+    ) -> Result<Arc<PipelineLayout>, PipelineLayoutCreationError>
+    where
+        D: IntoIterator<Item = DescriptorSetDesc>,
+        P: IntoIterator<Item = PipelineLayoutPcRange>,
+    {
+        let descriptor_set_descs: SmallVec<[DescriptorSetDesc; 4]> =
+            descriptor_set_descs.into_iter().collect();
+        let mut push_constant_ranges: SmallVec<[PipelineLayoutPcRange; 4]> =
+            push_constant_ranges.into_iter().collect();
+        sort_push_constant_ranges(&mut push_constant_ranges);
+
+        // The lock is held while creating the layout, so that two threads don't both create it.
+        let mut cache = device.pipeline_layout_cache().lock().unwrap();
+        cache.retain(|layout| layout.strong_count() != 0);
+
+        let existing = cache.iter().filter_map(Weak::upgrade).find(|layout| {
+            layout.push_constant_ranges == push_constant_ranges
+                && layout.descriptor_set_layouts.len() == descriptor_set_descs.len()
+                && layout
+                    .descriptor_set_layouts
+                    .iter()
+                    .zip(&descriptor_set_descs)
+                    .all(|(set_layout, desc)| set_layout.desc().is_compatible_with(desc))
+        });
+
+        if let Some(layout) = existing {
+            return Ok(layout);
+        }
+
+        let descriptor_set_layouts = descriptor_set_descs
+            .into_iter()
+            .map(|desc| Ok(DescriptorSetLayout::new(device.clone(), desc)?))
+            .collect::<Result<SmallVec<[_; 4]>, PipelineLayoutCreationError>>()?;
+        let layout =
+            PipelineLayout::new(device.clone(), descriptor_set_layouts, push_constant_ranges)?;
+        cache.push(Arc::downgrade(&layout));
+
+        Ok(layout)
+    }
+
     /// Returns the descriptor set layouts this pipeline layout was created from.
     #[inline]
     pub fn descriptor_set_layouts(&self) -> &[Arc<DescriptorSetLayout>] {
@@ -187,29 +234,40 @@ impl PipelineLayout {
         &self.push_constant_ranges
     }
 
-    /// Returns whether `self` is compatible with `other` for the given number of sets.
+    /// Returns whether `self` is compatible with `other` for the first `num_sets` descriptor sets.
+    ///
+    /// "Compatible" in this sense is defined by the Vulkan specification under the section
+    /// "Pipeline layout compatibility": both layouts must have been created with identical push
+    /// constant ranges, and with compatible descriptor set layouts for sets 0 to `num_sets - 1`.
+    /// Descriptor sets that are bound with one layout can then be used by a pipeline of the
+    /// other, without being bound again.
+    ///
+    /// Returns `false` if either layout has fewer than `num_sets` descriptor set layouts.
     pub fn is_compatible_with(&self, other: &PipelineLayout, num_sets: u32) -> bool {
         let num_sets = num_sets as usize;
-        assert!(num_sets >= self.descriptor_set_layouts.len());
 
         if self.handle == other.handle {
-            return true;
+            return num_sets <= self.descriptor_set_layouts.len();
         }
 
         if self.push_constant_ranges != other.push_constant_ranges {
             return false;
         }
 
-        let other_sets = match other.descriptor_set_layouts.get(0..num_sets) {
-            Some(x) => x,
-            None => return false,
+        let (self_sets, other_sets) = match (
+            self.descriptor_set_layouts.get(0..num_sets),
+            other.descriptor_set_layouts.get(0..num_sets),
+        ) {
+            (Some(self_sets), Some(other_sets)) => (self_sets, other_sets),
+            _ => return false,
         };
 
-        self.descriptor_set_layouts.iter().zip(other_sets).all(
-            |(self_set_layout, other_set_layout)| {
+        self_sets
+            .iter()
+            .zip(other_sets)
+            .all(|(self_set_layout, other_set_layout)| {
                 self_set_layout.is_compatible_with(other_set_layout)
-            },
-        )
+            })
     }
 
     /// Makes sure that `self` is a superset of the provided descriptor set layouts and push
@@ -263,6 +321,18 @@ impl PipelineLayout {
     }
 }
 
+// The stage mask is guaranteed to be unique by the check in `PipelineLayout::new`, so it's a
+// suitable sorting key.
+fn sort_push_constant_ranges(ranges: &mut [PipelineLayoutPcRange]) {
+    ranges.sort_unstable_by_key(|range| {
+        (
+            range.offset,
+            range.size,
+            ash::vk::ShaderStageFlags::from(range.stages),
+        )
+    });
+}
+
 unsafe impl DeviceOwned for PipelineLayout {
     #[inline]
     fn device(&self) -> &Arc<Device> {
@@ -85,9 +85,13 @@ use std::sync::Arc;
 
 pub mod cache;
 pub mod color_blend;
+pub mod compiler;
 mod compute_pipeline;
+pub mod creation_feedback;
 pub mod depth_stencil;
 pub mod discard_rectangle;
+pub mod executable;
+pub mod fragment_shading_rate;
 mod graphics_pipeline;
 pub mod input_assembly;
 pub mod layout;
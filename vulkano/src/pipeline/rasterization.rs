@@ -28,6 +28,16 @@ pub struct RasterizationState {
     /// enabled on the device.
     pub depth_clamp_enable: bool,
 
+    /// Whether fragments whose depth is outside of the [0.0, 1.0] range are discarded, separately
+    /// from `depth_clamp_enable`. If `None`, depth clipping is enabled if and only if depth
+    /// clamping is disabled.
+    ///
+    /// If set to `Some`, the
+    /// [`ext_depth_clip_enable`](crate::device::DeviceExtensions::ext_depth_clip_enable)
+    /// extension and the [`depth_clip_enable`](crate::device::Features::depth_clip_enable)
+    /// feature must be enabled on the device.
+    pub depth_clip_enable: Option<bool>,
+
     /// If true, all the fragments will be discarded, and the fragment shader will not be run. This
     /// is usually used when your vertex shader has some side effects and you don't need to run the
     /// fragment shader.
@@ -85,16 +95,63 @@ pub struct RasterizationState {
     /// [`ext_line_rasterization`](crate::device::DeviceExtensions::ext_line_rasterization)
     /// extension and an additional feature must be enabled on the device.
     pub line_stipple: Option<StateMode<LineStipple>>,
+
+    /// The vertex of each primitive whose outputs are used for flat shaded fragment shader
+    /// inputs.
+    ///
+    /// If this is set to `LastVertex`, the
+    /// [`ext_provoking_vertex`](crate::device::DeviceExtensions::ext_provoking_vertex) extension
+    /// and the [`provoking_vertex_last`](crate::device::Features::provoking_vertex_last) feature
+    /// must be enabled on the device. If the
+    /// [`provoking_vertex_mode_per_pipeline`](crate::device::Properties::provoking_vertex_mode_per_pipeline)
+    /// device property is `false`, all the pipelines used in the same render pass must have the
+    /// same provoking vertex.
+    pub provoking_vertex: ProvokingVertex,
+
+    /// The conservative rasterization mode, which changes which pixels are considered to be
+    /// covered by a primitive.
+    ///
+    /// If this is not set to `Disabled`, the
+    /// [`ext_conservative_rasterization`](crate::device::DeviceExtensions::ext_conservative_rasterization)
+    /// extension must be enabled on the device.
+    pub conservative_rasterization_mode: ConservativeRasterizationMode,
+
+    /// The extra size in pixels by which primitives are enlarged at each edge, in addition to
+    /// the [`primitive_overestimation_size`](crate::device::Properties::primitive_overestimation_size)
+    /// of the device, when `conservative_rasterization_mode` is `Overestimate`.
+    ///
+    /// Must be between 0.0 and the
+    /// [`max_extra_primitive_overestimation_size`](crate::device::Properties::max_extra_primitive_overestimation_size)
+    /// device property. The value is rounded up to a multiple of the
+    /// [`extra_primitive_overestimation_size_granularity`](crate::device::Properties::extra_primitive_overestimation_size_granularity)
+    /// property.
+    pub extra_primitive_overestimation_size: f32,
+
+    /// The vertex stream that is rasterized, when the geometry shader writes to multiple vertex
+    /// streams. The other streams are only captured by transform feedback.
+    ///
+    /// If this is not 0, the
+    /// [`ext_transform_feedback`](crate::device::DeviceExtensions::ext_transform_feedback)
+    /// extension and the [`geometry_streams`](crate::device::Features::geometry_streams) feature
+    /// must be enabled on the device, and the
+    /// [`transform_feedback_rasterization_stream_select`](crate::device::Properties::transform_feedback_rasterization_stream_select)
+    /// device property must be `true`. It must be less than the
+    /// [`max_transform_feedback_streams`](crate::device::Properties::max_transform_feedback_streams)
+    /// device property.
+    pub rasterization_stream: u32,
 }
 
 impl RasterizationState {
-    /// Creates a `RasterizationState` with depth clamping, discard, depth biasing and line
-    /// stippling disabled, filled polygons, no culling, counterclockwise front face, and the
-    /// default line width and line rasterization mode.
+    /// Creates a `RasterizationState` with depth clamping, discard, depth biasing, line
+    /// stippling and conservative rasterization disabled, depth clipping enabled, filled
+    /// polygons, no culling, counterclockwise front face, the first vertex as provoking vertex,
+    /// the default line width and line rasterization mode, and rasterization of vertex
+    /// stream 0.
     #[inline]
     pub fn new() -> Self {
         Self {
             depth_clamp_enable: false,
+            depth_clip_enable: None,
             rasterizer_discard_enable: StateMode::Fixed(false),
             polygon_mode: Default::default(),
             cull_mode: StateMode::Fixed(Default::default()),
@@ -103,6 +160,10 @@ impl RasterizationState {
             line_width: StateMode::Fixed(1.0),
             line_rasterization_mode: Default::default(),
             line_stipple: None,
+            provoking_vertex: Default::default(),
+            conservative_rasterization_mode: Default::default(),
+            extra_primitive_overestimation_size: 0.0,
+            rasterization_stream: 0,
         }
     }
 
@@ -274,11 +335,198 @@ impl RasterizationState {
         })
     }
 
+    pub(crate) fn to_vulkan_provoking_vertex_state(
+        &self,
+        device: &Device,
+    ) -> Result<
+        Option<ash::vk::PipelineRasterizationProvokingVertexStateCreateInfoEXT>,
+        GraphicsPipelineCreationError,
+    > {
+        match self.provoking_vertex {
+            // This is the default when the struct is not provided.
+            ProvokingVertex::FirstVertex => Ok(None),
+            ProvokingVertex::LastVertex => {
+                if !device.enabled_extensions().ext_provoking_vertex {
+                    return Err(GraphicsPipelineCreationError::ExtensionNotEnabled {
+                        extension: "ext_provoking_vertex",
+                        reason: "RasterizationState::provoking_vertex was LastVertex",
+                    });
+                }
+
+                if !device.enabled_features().provoking_vertex_last {
+                    return Err(GraphicsPipelineCreationError::FeatureNotEnabled {
+                        feature: "provoking_vertex_last",
+                        reason: "RasterizationState::provoking_vertex was LastVertex",
+                    });
+                }
+
+                Ok(Some(
+                    ash::vk::PipelineRasterizationProvokingVertexStateCreateInfoEXT {
+                        provoking_vertex_mode: self.provoking_vertex.into(),
+                        ..Default::default()
+                    },
+                ))
+            }
+        }
+    }
+
+    pub(crate) fn to_vulkan_depth_clip_state(
+        &self,
+        device: &Device,
+    ) -> Result<
+        Option<ash::vk::PipelineRasterizationDepthClipStateCreateInfoEXT>,
+        GraphicsPipelineCreationError,
+    > {
+        let depth_clip_enable = match self.depth_clip_enable {
+            Some(depth_clip_enable) => depth_clip_enable,
+            None => return Ok(None),
+        };
+
+        if !device.enabled_extensions().ext_depth_clip_enable {
+            return Err(GraphicsPipelineCreationError::ExtensionNotEnabled {
+                extension: "ext_depth_clip_enable",
+                reason: "RasterizationState::depth_clip_enable was Some",
+            });
+        }
+
+        if !device.enabled_features().depth_clip_enable {
+            return Err(GraphicsPipelineCreationError::FeatureNotEnabled {
+                feature: "depth_clip_enable",
+                reason: "RasterizationState::depth_clip_enable was Some",
+            });
+        }
+
+        Ok(Some(
+            ash::vk::PipelineRasterizationDepthClipStateCreateInfoEXT {
+                flags: ash::vk::PipelineRasterizationDepthClipStateCreateFlagsEXT::empty(),
+                depth_clip_enable: depth_clip_enable as ash::vk::Bool32,
+                ..Default::default()
+            },
+        ))
+    }
+
+    pub(crate) fn to_vulkan_stream_state(
+        &self,
+        device: &Device,
+    ) -> Result<
+        Option<ash::vk::PipelineRasterizationStateStreamCreateInfoEXT>,
+        GraphicsPipelineCreationError,
+    > {
+        // This is the default when the struct is not provided.
+        if self.rasterization_stream == 0 {
+            return Ok(None);
+        }
+
+        if !device.enabled_extensions().ext_transform_feedback {
+            return Err(GraphicsPipelineCreationError::ExtensionNotEnabled {
+                extension: "ext_transform_feedback",
+                reason: "RasterizationState::rasterization_stream was not 0",
+            });
+        }
+
+        if !device.enabled_features().geometry_streams {
+            return Err(GraphicsPipelineCreationError::FeatureNotEnabled {
+                feature: "geometry_streams",
+                reason: "RasterizationState::rasterization_stream was not 0",
+            });
+        }
+
+        let properties = device.physical_device().properties();
+
+        if !properties
+            .transform_feedback_rasterization_stream_select
+            .unwrap_or(false)
+        {
+            return Err(GraphicsPipelineCreationError::RasterizationStreamSelectNotSupported);
+        }
+
+        let max = properties.max_transform_feedback_streams.unwrap_or(0);
+
+        if self.rasterization_stream >= max {
+            return Err(
+                GraphicsPipelineCreationError::MaxTransformFeedbackStreamsExceeded {
+                    max,
+                    obtained: self.rasterization_stream,
+                },
+            );
+        }
+
+        Ok(Some(
+            ash::vk::PipelineRasterizationStateStreamCreateInfoEXT {
+                flags: ash::vk::PipelineRasterizationStateStreamCreateFlagsEXT::empty(),
+                rasterization_stream: self.rasterization_stream,
+                ..Default::default()
+            },
+        ))
+    }
+
+    pub(crate) fn to_vulkan_conservative_state(
+        &self,
+        device: &Device,
+    ) -> Result<
+        Option<ash::vk::PipelineRasterizationConservativeStateCreateInfoEXT>,
+        GraphicsPipelineCreationError,
+    > {
+        if !device.enabled_extensions().ext_conservative_rasterization {
+            if self.conservative_rasterization_mode != ConservativeRasterizationMode::Disabled {
+                return Err(GraphicsPipelineCreationError::ExtensionNotEnabled {
+                    extension: "ext_conservative_rasterization",
+                    reason: "RasterizationState::conservative_rasterization_mode was not Disabled",
+                });
+            }
+
+            return Ok(None);
+        }
+
+        let properties = device.physical_device().properties();
+
+        match self.conservative_rasterization_mode {
+            ConservativeRasterizationMode::Disabled => (),
+            ConservativeRasterizationMode::Overestimate => {
+                let max = properties.max_extra_primitive_overestimation_size.unwrap();
+
+                if !(0.0..=max).contains(&self.extra_primitive_overestimation_size) {
+                    return Err(
+                        GraphicsPipelineCreationError::MaxExtraPrimitiveOverestimationSizeExceeded,
+                    );
+                }
+            }
+            ConservativeRasterizationMode::Underestimate => {
+                if !properties.primitive_underestimation.unwrap() {
+                    return Err(
+                        GraphicsPipelineCreationError::PrimitiveUnderestimationNotSupported,
+                    );
+                }
+            }
+        }
+
+        Ok(Some(
+            ash::vk::PipelineRasterizationConservativeStateCreateInfoEXT {
+                flags: ash::vk::PipelineRasterizationConservativeStateCreateFlagsEXT::empty(),
+                conservative_rasterization_mode: self.conservative_rasterization_mode.into(),
+                extra_primitive_overestimation_size: self.extra_primitive_overestimation_size,
+                ..Default::default()
+            },
+        ))
+    }
+
     pub(crate) fn to_vulkan(
         &self,
         device: &Device,
         dynamic_state_modes: &mut FnvHashMap<DynamicState, bool>,
         rasterization_line_state: Option<&mut ash::vk::PipelineRasterizationLineStateCreateInfoEXT>,
+        rasterization_conservative_state: Option<
+            &mut ash::vk::PipelineRasterizationConservativeStateCreateInfoEXT,
+        >,
+        rasterization_depth_clip_state: Option<
+            &mut ash::vk::PipelineRasterizationDepthClipStateCreateInfoEXT,
+        >,
+        rasterization_provoking_vertex_state: Option<
+            &mut ash::vk::PipelineRasterizationProvokingVertexStateCreateInfoEXT,
+        >,
+        rasterization_stream_state: Option<
+            &mut ash::vk::PipelineRasterizationStateStreamCreateInfoEXT,
+        >,
     ) -> Result<ash::vk::PipelineRasterizationStateCreateInfo, GraphicsPipelineCreationError> {
         if self.depth_clamp_enable && !device.enabled_features().depth_clamp {
             return Err(GraphicsPipelineCreationError::FeatureNotEnabled {
@@ -423,6 +671,27 @@ impl RasterizationState {
             rasterization_state.p_next = rasterization_line_state as *const _ as *const _;
         }
 
+        if let Some(rasterization_conservative_state) = rasterization_conservative_state {
+            rasterization_conservative_state.p_next = rasterization_state.p_next;
+            rasterization_state.p_next = rasterization_conservative_state as *const _ as *const _;
+        }
+
+        if let Some(rasterization_depth_clip_state) = rasterization_depth_clip_state {
+            rasterization_depth_clip_state.p_next = rasterization_state.p_next;
+            rasterization_state.p_next = rasterization_depth_clip_state as *const _ as *const _;
+        }
+
+        if let Some(rasterization_provoking_vertex_state) = rasterization_provoking_vertex_state {
+            rasterization_provoking_vertex_state.p_next = rasterization_state.p_next;
+            rasterization_state.p_next =
+                rasterization_provoking_vertex_state as *const _ as *const _;
+        }
+
+        if let Some(rasterization_stream_state) = rasterization_stream_state {
+            rasterization_stream_state.p_next = rasterization_state.p_next;
+            rasterization_state.p_next = rasterization_stream_state as *const _ as *const _;
+        }
+
         Ok(rasterization_state)
     }
 }
@@ -600,6 +869,61 @@ impl From<LineRasterizationMode> for ash::vk::LineRasterizationModeEXT {
     }
 }
 
+/// The vertex of a primitive that is used to provide the values of flat shaded outputs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum ProvokingVertex {
+    /// The first vertex of the primitive is used, as defined by the primitive topology.
+    FirstVertex = ash::vk::ProvokingVertexModeEXT::FIRST_VERTEX.as_raw(),
+
+    /// The last vertex of the primitive is used. This is the OpenGL convention.
+    LastVertex = ash::vk::ProvokingVertexModeEXT::LAST_VERTEX.as_raw(),
+}
+
+impl Default for ProvokingVertex {
+    /// Returns `ProvokingVertex::FirstVertex`.
+    fn default() -> Self {
+        Self::FirstVertex
+    }
+}
+
+impl From<ProvokingVertex> for ash::vk::ProvokingVertexModeEXT {
+    fn from(val: ProvokingVertex) -> Self {
+        Self::from_raw(val as i32)
+    }
+}
+
+/// The conservative rasterization mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum ConservativeRasterizationMode {
+    /// Conservative rasterization is disabled, pixels are covered according to the usual rules.
+    Disabled = ash::vk::ConservativeRasterizationModeEXT::DISABLED.as_raw(),
+
+    /// A pixel is covered if any part of it is covered by the primitive, after the primitive has
+    /// been enlarged by the overestimation size.
+    Overestimate = ash::vk::ConservativeRasterizationModeEXT::OVERESTIMATE.as_raw(),
+
+    /// A pixel is only covered if it is entirely covered by the primitive.
+    ///
+    /// The [`primitive_underestimation`](crate::device::Properties::primitive_underestimation)
+    /// device property must be `true`.
+    Underestimate = ash::vk::ConservativeRasterizationModeEXT::UNDERESTIMATE.as_raw(),
+}
+
+impl Default for ConservativeRasterizationMode {
+    /// Returns `ConservativeRasterizationMode::Disabled`.
+    fn default() -> Self {
+        Self::Disabled
+    }
+}
+
+impl From<ConservativeRasterizationMode> for ash::vk::ConservativeRasterizationModeEXT {
+    fn from(val: ConservativeRasterizationMode) -> Self {
+        Self::from_raw(val as i32)
+    }
+}
+
 /// The parameters of a stippled line.
 #[derive(Clone, Copy, Debug)]
 pub struct LineStipple {
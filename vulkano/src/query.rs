@@ -14,8 +14,12 @@
 //! pool and the slot id within that query pool.
 
 use crate::check_errors;
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::ResetQueryPoolError;
+use crate::command_buffer::WriteTimestampError;
 use crate::device::Device;
 use crate::device::DeviceOwned;
+use crate::sync::PipelineStage;
 use crate::DeviceSize;
 use crate::Error;
 use crate::OomError;
@@ -28,6 +32,7 @@ use std::mem::MaybeUninit;
 use std::ops::Range;
 use std::ptr;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// A collection of one or more queries of a particular type.
 #[derive(Debug)]
@@ -668,8 +673,187 @@ impl From<QueryResultFlags> for ash::vk::QueryResultFlags {
     }
 }
 
+/// Measures the time spent by the device on the commands of each frame, with timestamp queries.
+///
+/// A `GpuTimer` holds one timestamp query pool for each frame in flight, which are used in turn.
+/// The timestamps of a frame can be read with [`frame_timestamps`](GpuTimer::frame_timestamps)
+/// once the command buffers that they were written in have finished executing, and before its
+/// frame slot is reused by [`begin_frame`](GpuTimer::begin_frame).
+///
+/// The timestamps are in the time domain of the device. They can be correlated with a CPU clock
+/// with [`Device::calibrated_timestamps`](crate::device::Device::calibrated_timestamps).
+#[derive(Debug)]
+pub struct GpuTimer {
+    pools: Vec<Arc<QueryPool>>,
+    // Number of timestamps written in each frame slot.
+    written: Vec<u32>,
+    current: usize,
+    timestamp_period: f32,
+}
+
+impl GpuTimer {
+    /// Builds a new `GpuTimer` that can write up to `timestamps_per_frame` timestamps in each of
+    /// `frames_in_flight` frames.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `frames_in_flight` or `timestamps_per_frame` is 0.
+    ///
+    pub fn new(
+        device: Arc<Device>,
+        frames_in_flight: usize,
+        timestamps_per_frame: u32,
+    ) -> Result<GpuTimer, QueryPoolCreationError> {
+        assert!(frames_in_flight != 0);
+        assert!(timestamps_per_frame != 0);
+
+        let pools = (0..frames_in_flight)
+            .map(|_| QueryPool::new(device.clone(), QueryType::Timestamp, timestamps_per_frame))
+            .collect::<Result<_, _>>()?;
+
+        Ok(GpuTimer {
+            pools,
+            written: vec![0; frames_in_flight],
+            current: frames_in_flight - 1,
+            timestamp_period: device.physical_device().properties().timestamp_period,
+        })
+    }
+
+    /// Returns the number of frame slots.
+    #[inline]
+    pub fn frames_in_flight(&self) -> usize {
+        self.pools.len()
+    }
+
+    /// Returns the frame slot that timestamps are currently written to.
+    #[inline]
+    pub fn current_frame(&self) -> usize {
+        self.current
+    }
+
+    /// Switches to the next frame slot, and adds a command to `builder` that resets its queries.
+    ///
+    /// # Safety
+    ///
+    /// The command buffers that timestamps were previously written to for this frame slot must
+    /// have finished executing.
+    pub unsafe fn begin_frame<L, P>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<L, P>,
+    ) -> Result<(), ResetQueryPoolError> {
+        let next = (self.current + 1) % self.pools.len();
+        let pool = &self.pools[next];
+        builder.reset_query_pool(pool.clone(), 0..pool.num_slots())?;
+
+        self.current = next;
+        self.written[next] = 0;
+        Ok(())
+    }
+
+    /// Adds a command to `builder` that writes a timestamp when `stage` is reached, and returns
+    /// the index of the timestamp within the current frame.
+    ///
+    /// # Safety
+    ///
+    /// The command recorded by the last call to `begin_frame` must be executed before this one.
+    pub unsafe fn write_timestamp<L, P>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<L, P>,
+        stage: PipelineStage,
+    ) -> Result<u32, GpuTimerError> {
+        let pool = &self.pools[self.current];
+        let index = self.written[self.current];
+
+        if index >= pool.num_slots() {
+            return Err(GpuTimerError::TooManyTimestamps {
+                max: pool.num_slots(),
+            });
+        }
+
+        builder.write_timestamp(pool.clone(), index, stage)?;
+        self.written[self.current] += 1;
+        Ok(index)
+    }
+
+    /// Returns the timestamps that were written for `frame`, in ticks of the device, or `None`
+    /// if they aren't available yet.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `frame` is not less than `frames_in_flight()`.
+    ///
+    pub fn frame_timestamps(&self, frame: usize) -> Result<Option<Vec<u64>>, GetResultsError> {
+        let count = self.written[frame];
+
+        if count == 0 {
+            return Ok(Some(Vec::new()));
+        }
+
+        let mut timestamps = vec![0; count as usize];
+        let available = self.pools[frame]
+            .queries_range(0..count)
+            .unwrap()
+            .get_results(&mut timestamps, QueryResultFlags::default())?;
+
+        Ok(if available { Some(timestamps) } else { None })
+    }
+
+    /// Returns the time between two timestamps returned by `frame_timestamps`.
+    #[inline]
+    pub fn elapsed(&self, start: u64, end: u64) -> Duration {
+        let nanoseconds = end.wrapping_sub(start) as f64 * self.timestamp_period as f64;
+        Duration::from_nanos(nanoseconds as u64)
+    }
+}
+
+/// Error that can happen when writing a timestamp with a [`GpuTimer`].
+#[derive(Clone, Debug)]
+pub enum GpuTimerError {
+    /// All the timestamps of the current frame have already been written.
+    TooManyTimestamps {
+        /// The number of timestamps per frame.
+        max: u32,
+    },
+    /// Writing the timestamp failed.
+    WriteTimestampError(WriteTimestampError),
+}
+
+impl error::Error for GpuTimerError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            GpuTimerError::WriteTimestampError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GpuTimerError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                GpuTimerError::TooManyTimestamps { .. } => {
+                    "all the timestamps of the current frame have already been written"
+                }
+                GpuTimerError::WriteTimestampError(_) => "writing the timestamp failed",
+            }
+        )
+    }
+}
+
+impl From<WriteTimestampError> for GpuTimerError {
+    #[inline]
+    fn from(err: WriteTimestampError) -> GpuTimerError {
+        GpuTimerError::WriteTimestampError(err)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::query::GpuTimer;
     use crate::query::QueryPipelineStatisticFlags;
     use crate::query::QueryPool;
     use crate::query::QueryPoolCreationError;
@@ -685,4 +869,14 @@ mod tests {
             _ => panic!(),
         };
     }
+
+    #[test]
+    fn gpu_timer_empty_frames() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let timer = GpuTimer::new(device, 2, 4).unwrap();
+        assert_eq!(timer.frames_in_flight(), 2);
+        assert_eq!(timer.current_frame(), 1);
+        assert_eq!(timer.frame_timestamps(0).unwrap(), Some(Vec::new()));
+    }
 }
@@ -0,0 +1,430 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Reading back the content of buffers and images on the CPU.
+//!
+//! This is the opposite of the [`upload`](crate::upload) module. A [`Readback`] copies the
+//! content of device-local buffers and images to host-visible staging buffers with a command
+//! buffer submitted to its queue, and returns a [`PendingReadback`] that gives access to the data
+//! once the copy has finished. In the meantime, the CPU and the other queues can keep working,
+//! for example to render the next frame.
+//!
+//! The staging buffers are kept by the `Readback` and reused once their data has been read or
+//! the `PendingReadback` has been dropped.
+
+use crate::buffer::BufferUsage;
+use crate::buffer::CpuAccessibleBuffer;
+use crate::buffer::Pod;
+use crate::buffer::TypedBufferAccess;
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::BufferCopy;
+use crate::command_buffer::BufferImageCopy;
+use crate::command_buffer::BuildError;
+use crate::command_buffer::CommandBufferExecError;
+use crate::command_buffer::CommandBufferExecFuture;
+use crate::command_buffer::CommandBufferUsage;
+use crate::command_buffer::CopyBufferError;
+use crate::command_buffer::CopyBufferImageError;
+use crate::command_buffer::PrimaryAutoCommandBuffer;
+use crate::command_buffer::PrimaryCommandBuffer;
+use crate::device::Queue;
+use crate::image::ImageAccess;
+use crate::memory::DeviceMemoryAllocError;
+use crate::sync;
+use crate::sync::FenceSignalFuture;
+use crate::sync::FlushError;
+use crate::sync::GpuFuture;
+use crate::DeviceSize;
+use crate::OomError;
+use std::error;
+use std::fmt;
+use std::iter;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The minimum size in bytes of the staging buffers that a `Readback` allocates.
+const MIN_STAGING_BUFFER_SIZE: DeviceSize = 4 * 1024 * 1024;
+
+/// Future that is signaled when the copy of a readback has finished.
+pub type ReadbackFuture = FenceSignalFuture<
+    CommandBufferExecFuture<Box<dyn GpuFuture + Send + Sync>, PrimaryAutoCommandBuffer>,
+>;
+
+/// Copies the content of buffers and images to staging buffers that it reuses, so that it can be
+/// read by the CPU.
+///
+/// The copies are executed on the queue that the `Readback` was created with, for example a
+/// queue of a transfer-only family. The sources must be created with the `transfer_source` usage,
+/// and with a sharing mode that allows them to be used on this queue as well as on the queues
+/// that write them.
+pub struct Readback {
+    queue: Arc<Queue>,
+    // The staging buffers. A staging buffer is free when no command buffer or pending readback
+    // holds it anymore.
+    staging_buffers: Mutex<Vec<Arc<CpuAccessibleBuffer<[u8]>>>>,
+}
+
+impl Readback {
+    /// Builds a new `Readback` that copies the data on `queue`.
+    #[inline]
+    pub fn new(queue: Arc<Queue>) -> Readback {
+        Readback {
+            queue,
+            staging_buffers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the queue that the copies are executed on.
+    #[inline]
+    pub fn queue(&self) -> &Arc<Queue> {
+        &self.queue
+    }
+
+    /// Reads back the whole content of `source`.
+    #[inline]
+    pub fn read_buffer<T, B>(&self, source: Arc<B>) -> Result<PendingReadback<T>, ReadbackError>
+    where
+        T: Pod,
+        B: TypedBufferAccess<Content = [T]> + 'static,
+    {
+        self.read_buffer_after(sync::now(self.queue.device().clone()), source)
+    }
+
+    /// Reads back the whole content of `source`, once `future` has been reached.
+    ///
+    /// `future` is usually the future of the submission that writes `source`.
+    pub fn read_buffer_after<F, T, B>(
+        &self,
+        future: F,
+        source: Arc<B>,
+    ) -> Result<PendingReadback<T>, ReadbackError>
+    where
+        F: GpuFuture + Send + Sync + 'static,
+        T: Pod,
+        B: TypedBufferAccess<Content = [T]> + 'static,
+    {
+        let size = source.size();
+        let staging = self.staging_buffer(size)?;
+        let mut builder = self.builder()?;
+        builder.copy_buffer_regions(
+            source,
+            staging.clone(),
+            iter::once(BufferCopy {
+                source_offset: 0,
+                destination_offset: 0,
+                size,
+            }),
+        )?;
+
+        self.submit(future, builder, staging, size)
+    }
+
+    /// Reads back a region of `source`.
+    ///
+    /// `region.buffer_offset` is the offset in bytes of the data of the region in the returned
+    /// data, and `buffer_row_length` and `buffer_image_height` can be used to lay it out the same
+    /// way as with [`copy_image_to_buffer_regions`](AutoCommandBufferBuilder::copy_image_to_buffer_regions).
+    #[inline]
+    pub fn read_image<T>(
+        &self,
+        source: Arc<dyn ImageAccess>,
+        region: BufferImageCopy,
+    ) -> Result<PendingReadback<T>, ReadbackError>
+    where
+        T: Pod,
+    {
+        self.read_image_after(sync::now(self.queue.device().clone()), source, region)
+    }
+
+    /// Reads back a region of `source`, once `future` has been reached.
+    ///
+    /// `future` is usually the future of the submission that writes `source`.
+    pub fn read_image_after<F, T>(
+        &self,
+        future: F,
+        source: Arc<dyn ImageAccess>,
+        region: BufferImageCopy,
+    ) -> Result<PendingReadback<T>, ReadbackError>
+    where
+        F: GpuFuture + Send + Sync + 'static,
+        T: Pod,
+    {
+        let format = source.format();
+        let block_size = format.size().unwrap_or(1).max(1);
+        let [block_width, block_height] = format.block_dimensions();
+        let row_length = match region.buffer_row_length {
+            0 => region.image_extent[0],
+            row_length => row_length,
+        };
+        let image_height = match region.buffer_image_height {
+            0 => region.image_extent[1],
+            image_height => image_height,
+        };
+        let blocks = ((row_length + block_width - 1) / block_width) as DeviceSize
+            * ((image_height + block_height - 1) / block_height) as DeviceSize
+            * region.image_extent[2] as DeviceSize
+            * region.image_layer_count as DeviceSize;
+        let size = region.buffer_offset + blocks * block_size;
+
+        let staging = self.staging_buffer(size)?;
+        let mut builder = self.builder()?;
+        builder.copy_image_to_buffer_regions(source, staging.clone(), iter::once(region))?;
+
+        self.submit(future, builder, staging, size)
+    }
+
+    fn builder(&self) -> Result<AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, ReadbackError> {
+        Ok(AutoCommandBufferBuilder::primary(
+            self.queue.device().clone(),
+            self.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?)
+    }
+
+    fn submit<F, T>(
+        &self,
+        future: F,
+        builder: AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        staging: Arc<CpuAccessibleBuffer<[u8]>>,
+        size: DeviceSize,
+    ) -> Result<PendingReadback<T>, ReadbackError>
+    where
+        F: GpuFuture + Send + Sync + 'static,
+        T: Pod,
+    {
+        let command_buffer = builder.build()?;
+        let future: Box<dyn GpuFuture + Send + Sync> = Box::new(future);
+        let future = command_buffer
+            .execute_after(future, self.queue.clone())?
+            .then_signal_fence_and_flush()?;
+
+        Ok(PendingReadback {
+            future,
+            staging,
+            len: size as usize / mem::size_of::<T>(),
+            marker: PhantomData,
+        })
+    }
+
+    // Returns a staging buffer of at least `size` bytes that isn't in use.
+    fn staging_buffer(
+        &self,
+        size: DeviceSize,
+    ) -> Result<Arc<CpuAccessibleBuffer<[u8]>>, DeviceMemoryAllocError> {
+        let mut staging_buffers = self.staging_buffers.lock().unwrap();
+
+        if let Some(buffer) = staging_buffers
+            .iter()
+            .find(|buffer| Arc::strong_count(*buffer) == 1 && buffer.len() >= size)
+        {
+            return Ok(buffer.clone());
+        }
+
+        // Host-cached memory is preferred, as the CPU reads the data.
+        let buffer = unsafe {
+            CpuAccessibleBuffer::uninitialized_array(
+                self.queue.device().clone(),
+                size.max(MIN_STAGING_BUFFER_SIZE),
+                BufferUsage::transfer_destination(),
+                true,
+            )?
+        };
+        staging_buffers.push(buffer.clone());
+
+        Ok(buffer)
+    }
+}
+
+/// Data being read back by a [`Readback`].
+///
+/// Dropping a `PendingReadback` blocks the current thread until the copy has finished.
+pub struct PendingReadback<T> {
+    future: ReadbackFuture,
+    staging: Arc<CpuAccessibleBuffer<[u8]>>,
+    // The number of elements of the data.
+    len: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> PendingReadback<T>
+where
+    T: Pod,
+{
+    /// Returns true if the copy has finished, in which case [`wait`](PendingReadback::wait)
+    /// doesn't block. Doesn't block.
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.future.is_signaled()
+    }
+
+    /// Returns the future that is signaled when the copy has finished.
+    #[inline]
+    pub fn future(&self) -> &ReadbackFuture {
+        &self.future
+    }
+
+    /// Blocks the current thread until the copy has finished, and returns the data.
+    ///
+    /// If `timeout` is `None`, then the wait is infinite. Otherwise the thread will unblock after
+    /// the specified timeout has elapsed and an error will be returned.
+    pub fn wait(self, timeout: Option<Duration>) -> Result<Vec<T>, FlushError> {
+        self.future.wait(timeout)?;
+
+        // The resources of the submission have been unlocked by the wait.
+        let lock = self.staging.read().unwrap();
+        let mut data = Vec::with_capacity(self.len);
+
+        // Safe because `T` is `Pod`, and the staging buffer is at least `len` elements long.
+        unsafe {
+            ptr::copy_nonoverlapping(
+                lock.as_ptr(),
+                data.as_mut_ptr() as *mut u8,
+                self.len * mem::size_of::<T>(),
+            );
+            data.set_len(self.len);
+        }
+
+        Ok(data)
+    }
+}
+
+/// Error that can happen when submitting a readback.
+#[derive(Clone, Debug)]
+pub enum ReadbackError {
+    /// Allocating the command buffer failed.
+    OomError(OomError),
+    /// Allocating a staging buffer failed.
+    DeviceMemoryAllocError(DeviceMemoryAllocError),
+    /// The copy from a buffer is invalid.
+    CopyBufferError(CopyBufferError),
+    /// The copy from an image is invalid.
+    CopyBufferImageError(CopyBufferImageError),
+    /// Building the command buffer failed.
+    BuildError(BuildError),
+    /// Executing the command buffer failed.
+    CommandBufferExecError(CommandBufferExecError),
+    /// Submitting the command buffer failed.
+    FlushError(FlushError),
+}
+
+impl error::Error for ReadbackError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ReadbackError::OomError(ref err) => Some(err),
+            ReadbackError::DeviceMemoryAllocError(ref err) => Some(err),
+            ReadbackError::CopyBufferError(ref err) => Some(err),
+            ReadbackError::CopyBufferImageError(ref err) => Some(err),
+            ReadbackError::BuildError(ref err) => Some(err),
+            ReadbackError::CommandBufferExecError(ref err) => Some(err),
+            ReadbackError::FlushError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for ReadbackError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                ReadbackError::OomError(_) => "allocating the command buffer failed",
+                ReadbackError::DeviceMemoryAllocError(_) => "allocating a staging buffer failed",
+                ReadbackError::CopyBufferError(_) => "the copy from a buffer is invalid",
+                ReadbackError::CopyBufferImageError(_) => "the copy from an image is invalid",
+                ReadbackError::BuildError(_) => "building the command buffer failed",
+                ReadbackError::CommandBufferExecError(_) => "executing the command buffer failed",
+                ReadbackError::FlushError(_) => "submitting the command buffer failed",
+            }
+        )
+    }
+}
+
+impl From<OomError> for ReadbackError {
+    #[inline]
+    fn from(err: OomError) -> ReadbackError {
+        ReadbackError::OomError(err)
+    }
+}
+
+impl From<DeviceMemoryAllocError> for ReadbackError {
+    #[inline]
+    fn from(err: DeviceMemoryAllocError) -> ReadbackError {
+        ReadbackError::DeviceMemoryAllocError(err)
+    }
+}
+
+impl From<CopyBufferError> for ReadbackError {
+    #[inline]
+    fn from(err: CopyBufferError) -> ReadbackError {
+        ReadbackError::CopyBufferError(err)
+    }
+}
+
+impl From<CopyBufferImageError> for ReadbackError {
+    #[inline]
+    fn from(err: CopyBufferImageError) -> ReadbackError {
+        ReadbackError::CopyBufferImageError(err)
+    }
+}
+
+impl From<BuildError> for ReadbackError {
+    #[inline]
+    fn from(err: BuildError) -> ReadbackError {
+        ReadbackError::BuildError(err)
+    }
+}
+
+impl From<CommandBufferExecError> for ReadbackError {
+    #[inline]
+    fn from(err: CommandBufferExecError) -> ReadbackError {
+        ReadbackError::CommandBufferExecError(err)
+    }
+}
+
+impl From<FlushError> for ReadbackError {
+    #[inline]
+    fn from(err: FlushError) -> ReadbackError {
+        ReadbackError::FlushError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Readback;
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+
+    #[test]
+    fn read_buffer() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let readback = Readback::new(queue);
+
+        let source = CpuAccessibleBuffer::from_iter(
+            device,
+            BufferUsage::transfer_source(),
+            false,
+            [1u32, 2, 3].iter().copied(),
+        )
+        .unwrap();
+
+        let pending = readback.read_buffer(source.clone()).unwrap();
+        assert_eq!(pending.wait(None).unwrap(), vec![1, 2, 3]);
+
+        // The staging buffer is reused once the data has been read.
+        let pending = readback.read_buffer(source).unwrap();
+        assert_eq!(pending.wait(None).unwrap(), vec![1, 2, 3]);
+        assert_eq!(readback.staging_buffers.lock().unwrap().len(), 1);
+    }
+}
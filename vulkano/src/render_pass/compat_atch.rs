@@ -11,6 +11,7 @@
 //! an image view can be used as a render pass attachment.
 
 use crate::image::view::ImageViewAbstract;
+use crate::image::view::ImageViewType;
 use crate::render_pass::RenderPassDesc;
 use crate::{format::Format, image::SampleCount};
 use std::error;
@@ -55,6 +56,11 @@ where
         return Err(IncompatibleRenderPassAttachmentError::NotIdentitySwizzled);
     }
 
+    // VUID-VkFramebufferCreateInfo-flags-04113
+    if image_view.ty() == ImageViewType::Dim3d {
+        return Err(IncompatibleRenderPassAttachmentError::Dim3dView);
+    }
+
     let aspects = image_view.image().format().aspects(); // TODO: should use view format?
 
     for subpass in render_pass_desc.subpasses() {
@@ -64,7 +70,7 @@ where
             .any(|&(n, _)| n == attachment_num)
         {
             debug_assert!(aspects.color); // Was normally checked by the render pass.
-            if !image_view.image().inner().image.usage().color_attachment {
+            if !image_view.usage().color_attachment {
                 return Err(IncompatibleRenderPassAttachmentError::MissingColorAttachmentUsage);
             }
         }
@@ -73,13 +79,7 @@ where
             if ds == attachment_num {
                 // Was normally checked by the render pass.
                 debug_assert!(aspects.depth || aspects.stencil);
-                if !image_view
-                    .image()
-                    .inner()
-                    .image
-                    .usage()
-                    .depth_stencil_attachment
-                {
+                if !image_view.usage().depth_stencil_attachment {
                     return Err(
                         IncompatibleRenderPassAttachmentError::MissingDepthStencilAttachmentUsage,
                     );
@@ -92,10 +92,20 @@ where
             .iter()
             .any(|&(n, _)| n == attachment_num)
         {
-            if !image_view.image().inner().image.usage().input_attachment {
+            if !image_view.usage().input_attachment {
                 return Err(IncompatibleRenderPassAttachmentError::MissingInputAttachmentUsage);
             }
         }
+
+        if let Some(fragment_shading_rate_attachment) = subpass.fragment_shading_rate_attachment {
+            if fragment_shading_rate_attachment.attachment == attachment_num
+                && !image_view.usage().fragment_shading_rate_attachment
+            {
+                return Err(
+                    IncompatibleRenderPassAttachmentError::MissingFragmentShadingRateAttachmentUsage,
+                );
+            }
+        }
     }
 
     // TODO: consider forbidding LoadOp::Load if image is transient
@@ -129,6 +139,10 @@ pub enum IncompatibleRenderPassAttachmentError {
     /// The image view has a component swizzle that is different from identity.
     NotIdentitySwizzled,
 
+    /// The image view is a 3D view. A 3D image must be attached through a 2D or 2D array view of
+    /// its depth slices instead.
+    Dim3dView,
+
     /// The image is used as a color attachment but is missing the color attachment usage.
     MissingColorAttachmentUsage,
 
@@ -138,6 +152,10 @@ pub enum IncompatibleRenderPassAttachmentError {
 
     /// The image is used as an input attachment but is missing the input attachment usage.
     MissingInputAttachmentUsage,
+
+    /// The image is used as a fragment shading rate attachment but is missing the fragment
+    /// shading rate attachment usage.
+    MissingFragmentShadingRateAttachmentUsage,
 }
 
 impl error::Error for IncompatibleRenderPassAttachmentError {}
@@ -159,6 +177,9 @@ impl fmt::Display for IncompatibleRenderPassAttachmentError {
                 IncompatibleRenderPassAttachmentError::NotIdentitySwizzled => {
                     "the image view's component mapping is not identity swizzled"
                 }
+                IncompatibleRenderPassAttachmentError::Dim3dView => {
+                    "the image view is a 3D view"
+                }
                 IncompatibleRenderPassAttachmentError::MissingColorAttachmentUsage => {
                     "the image is used as a color attachment but is missing the color attachment usage"
                 }
@@ -170,6 +191,10 @@ impl fmt::Display for IncompatibleRenderPassAttachmentError {
                     "the image is used as an input attachment but is missing the input \
                  attachment usage"
                 }
+                IncompatibleRenderPassAttachmentError::MissingFragmentShadingRateAttachmentUsage => {
+                    "the image is used as a fragment shading rate attachment but is missing the \
+                 fragment shading rate attachment usage"
+                }
             }
         )
     }
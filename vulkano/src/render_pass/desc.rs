@@ -65,6 +65,7 @@ impl RenderPassDesc {
                 input_attachments: vec![],
                 resolve_attachments: vec![],
                 preserve_attachments: vec![],
+                fragment_shading_rate_attachment: None,
             }],
             dependencies: vec![],
             multiview: None,
@@ -220,7 +221,11 @@ impl AttachmentDesc {
 /// - All the attachments in `color_attachments` and `depth_stencil` must have the same
 ///   samples count.
 /// - If any attachment is used as both an input attachment and a color or
-///   depth/stencil attachment, then each use must use the same layout.
+///   depth/stencil attachment, then each use must use the same layout. This feedback loop
+///   requires the `General` layout, or `DepthStencilReadOnlyOptimal` for a depth/stencil
+///   attachment that isn't written. Reading what a previous draw of the subpass wrote also
+///   requires a by-region dependency of the subpass on itself, and a pipeline barrier between
+///   the draws.
 /// - Elements of `preserve_attachments` must not be used in any of the other members.
 /// - If `resolve_attachments` is not empty, then all the resolve attachments must be attachments
 ///   with 1 sample and all the color attachments must have more than 1 sample.
@@ -251,6 +256,63 @@ pub struct SubpassDesc {
 
     /// Indices of attachments that will be preserved during this pass.
     pub preserve_attachments: Vec<usize>, // TODO: Vec is slow
+
+    /// The attachment that sets the fragment shading rate of the areas of the framebuffer, if
+    /// any.
+    ///
+    /// If this is `Some`, the
+    /// [`attachment_fragment_shading_rate`](crate::device::Features::attachment_fragment_shading_rate)
+    /// feature must be enabled on the device.
+    pub fragment_shading_rate_attachment: Option<FragmentShadingRateAttachmentDesc>,
+}
+
+impl SubpassDesc {
+    /// Adds the multisampled attachment `attachment` to the color attachments of the subpass, and
+    /// `resolve` as the attachment that it is resolved into at the end of the subpass. Both are
+    /// used in the `ColorAttachmentOptimal` layout.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the subpass already has color attachments that aren't resolved.
+    pub fn add_resolved_color_attachment(&mut self, attachment: usize, resolve: usize) {
+        assert_eq!(
+            self.color_attachments.len(),
+            self.resolve_attachments.len(),
+            "either all the color attachments of a subpass are resolved, or none of them"
+        );
+
+        self.color_attachments
+            .push((attachment, ImageLayout::ColorAttachmentOptimal));
+        self.resolve_attachments
+            .push((resolve, ImageLayout::ColorAttachmentOptimal));
+    }
+}
+
+/// Describes the fragment shading rate attachment of a subpass.
+///
+/// Each texel of the attachment contains the fragment shading rate of an area of the framebuffer.
+/// The attachment must have the `R8_UINT` format.
+#[derive(Debug, Clone, Copy)]
+pub struct FragmentShadingRateAttachmentDesc {
+    /// Index of the attachment.
+    pub attachment: usize,
+
+    /// Layout of the attachment during the subpass. Must be `General` or
+    /// `FragmentShadingRateAttachmentOptimal`.
+    pub layout: ImageLayout,
+
+    /// Width and height in pixels of the area of the framebuffer that corresponds to one texel of
+    /// the attachment.
+    ///
+    /// Both values must be powers of two, between the
+    /// [`min_fragment_shading_rate_attachment_texel_size`](crate::device::Properties::min_fragment_shading_rate_attachment_texel_size)
+    /// and
+    /// [`max_fragment_shading_rate_attachment_texel_size`](crate::device::Properties::max_fragment_shading_rate_attachment_texel_size)
+    /// device properties. The ratio between the largest and the smallest value must not exceed
+    /// the
+    /// [`max_fragment_shading_rate_attachment_texel_size_aspect_ratio`](crate::device::Properties::max_fragment_shading_rate_attachment_texel_size_aspect_ratio)
+    /// device property.
+    pub texel_size: [u32; 2],
 }
 
 /// Describes a dependency between two subpasses of a render pass.
@@ -223,9 +223,9 @@ impl FramebufferBuilder {
             Err(err) => return Err(FramebufferCreationError::IncompatibleAttachment(err)),
         };
 
+        // The array layers of a view of a 3D image are its depth slices.
         let image_dimensions = attachment.image().dimensions();
         let array_layers = attachment.array_layers();
-        debug_assert_eq!(image_dimensions.depth(), 1);
 
         let view_dimensions = [
             image_dimensions.width(),
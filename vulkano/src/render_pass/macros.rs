@@ -129,7 +129,8 @@ macro_rules! ordered_passes_renderpass {
                             $(if a == $input_atch { return false; })*
                             $($(if a == $resolve_atch { return false; })*)*
                             true
-                        }).collect()
+                        }).collect(),
+                        fragment_shading_rate_attachment: None,
                     };
 
                     assert!(desc.resolve_attachments.is_empty() ||
@@ -28,6 +28,7 @@
 pub use self::compat_atch::ensure_image_view_compatible;
 pub use self::compat_atch::IncompatibleRenderPassAttachmentError;
 pub use self::desc::AttachmentDesc;
+pub use self::desc::FragmentShadingRateAttachmentDesc;
 pub use self::desc::LoadOp;
 pub use self::desc::MultiviewDesc;
 pub use self::desc::RenderPassDesc;
@@ -15,16 +15,21 @@ use crate::image::SampleCount;
 use crate::render_pass::AttachmentDesc;
 use crate::render_pass::LoadOp;
 use crate::render_pass::RenderPassDesc;
+use crate::render_pass::SubpassDependencyDesc;
 use crate::render_pass::SubpassDesc;
 use crate::shader::ShaderInterface;
+use crate::sync::PipelineStages;
 use crate::Error;
 use crate::OomError;
+use crate::Version;
 use crate::VulkanObject;
 use smallvec::SmallVec;
+use std::cmp;
 use std::error;
 use std::fmt;
 use std::mem::MaybeUninit;
 use std::ptr;
+use std::slice;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -144,6 +149,56 @@ impl RenderPass {
             }
         ));
 
+        for pass in description.subpasses() {
+            if let Some(fragment_shading_rate_attachment) = pass.fragment_shading_rate_attachment {
+                if !device.enabled_features().attachment_fragment_shading_rate {
+                    return Err(RenderPassCreationError::FeatureNotEnabled {
+                        feature: "attachment_fragment_shading_rate",
+                        reason: "a subpass has a fragment shading rate attachment",
+                    });
+                }
+
+                if !is_valid_texel_size(&device, fragment_shading_rate_attachment.texel_size) {
+                    return Err(RenderPassCreationError::InvalidFragmentShadingRateTexelSize);
+                }
+            }
+
+            // An attachment that is both read as an input attachment and written as a color or
+            // depth/stencil attachment forms a feedback loop, which requires the `General`
+            // layout. A depth/stencil attachment that is only read can also be read-only.
+            for &(input, input_layout) in &pass.input_attachments {
+                let layout_supported = if pass.color_attachments.iter().any(|&(a, _)| a == input) {
+                    input_layout == ImageLayout::General
+                } else if pass.depth_stencil.map_or(false, |(a, _)| a == input) {
+                    matches!(
+                        input_layout,
+                        ImageLayout::General | ImageLayout::DepthStencilReadOnlyOptimal
+                    )
+                } else {
+                    true
+                };
+
+                if !layout_supported {
+                    return Err(RenderPassCreationError::FeedbackLoopLayoutNotSupported {
+                        attachment: input,
+                    });
+                }
+            }
+        }
+
+        for dependency in description.dependencies() {
+            // A self-dependency between framebuffer-space stages must be by region.
+            if dependency.source_subpass == dependency.destination_subpass
+                && has_framebuffer_space_stage(dependency.source_stages)
+                && has_framebuffer_space_stage(dependency.destination_stages)
+                && !dependency.by_region
+            {
+                return Err(RenderPassCreationError::SelfDependencyNotByRegion {
+                    subpass: dependency.source_subpass,
+                });
+            }
+        }
+
         let attachments = description
             .attachments()
             .iter()
@@ -454,43 +509,53 @@ impl RenderPass {
             None => ash::vk::RenderPassMultiviewCreateInfo::default(),
         };
 
-        let handle = unsafe {
-            let infos = ash::vk::RenderPassCreateInfo {
-                p_next: if description.multiview().is_none() {
-                    ptr::null()
-                } else {
-                    &multiview_create_info as *const _ as _
-                },
-                flags: ash::vk::RenderPassCreateFlags::empty(),
-                attachment_count: attachments.len() as u32,
-                p_attachments: if attachments.is_empty() {
-                    ptr::null()
-                } else {
-                    attachments.as_ptr()
-                },
-                subpass_count: passes.len() as u32,
-                p_subpasses: if passes.is_empty() {
-                    ptr::null()
-                } else {
-                    passes.as_ptr()
-                },
-                dependency_count: dependencies.len() as u32,
-                p_dependencies: if dependencies.is_empty() {
-                    ptr::null()
-                } else {
-                    dependencies.as_ptr()
-                },
-                ..Default::default()
-            };
-
-            let mut output = MaybeUninit::uninit();
-            check_errors(fns.v1_0.create_render_pass(
-                device.internal_object(),
-                &infos,
-                ptr::null(),
-                output.as_mut_ptr(),
-            ))?;
-            output.assume_init()
+        let handle = if description
+            .subpasses()
+            .iter()
+            .any(|pass| pass.fragment_shading_rate_attachment.is_some())
+        {
+            unsafe {
+                create_render_pass2(&device, &description, &attachments, &passes, &dependencies)?
+            }
+        } else {
+            unsafe {
+                let infos = ash::vk::RenderPassCreateInfo {
+                    p_next: if description.multiview().is_none() {
+                        ptr::null()
+                    } else {
+                        &multiview_create_info as *const _ as _
+                    },
+                    flags: ash::vk::RenderPassCreateFlags::empty(),
+                    attachment_count: attachments.len() as u32,
+                    p_attachments: if attachments.is_empty() {
+                        ptr::null()
+                    } else {
+                        attachments.as_ptr()
+                    },
+                    subpass_count: passes.len() as u32,
+                    p_subpasses: if passes.is_empty() {
+                        ptr::null()
+                    } else {
+                        passes.as_ptr()
+                    },
+                    dependency_count: dependencies.len() as u32,
+                    p_dependencies: if dependencies.is_empty() {
+                        ptr::null()
+                    } else {
+                        dependencies.as_ptr()
+                    },
+                    ..Default::default()
+                };
+
+                let mut output = MaybeUninit::uninit();
+                check_errors(fns.v1_0.create_render_pass(
+                    device.internal_object(),
+                    &infos,
+                    ptr::null(),
+                    output.as_mut_ptr(),
+                ))?;
+                output.assume_init()
+            }
         };
 
         Ok(Arc::new(RenderPass {
@@ -594,6 +659,259 @@ unsafe impl VulkanObject for RenderPass {
     }
 }
 
+// Returns whether `texel_size` is a valid texel size for a fragment shading rate attachment.
+fn is_valid_texel_size(device: &Device, texel_size: [u32; 2]) -> bool {
+    let properties = device.physical_device().properties();
+    let min = properties
+        .min_fragment_shading_rate_attachment_texel_size
+        .unwrap();
+    let max = properties
+        .max_fragment_shading_rate_attachment_texel_size
+        .unwrap();
+    let max_aspect_ratio = properties
+        .max_fragment_shading_rate_attachment_texel_size_aspect_ratio
+        .unwrap();
+
+    let [width, height] = texel_size;
+
+    width.is_power_of_two()
+        && height.is_power_of_two()
+        && (min[0]..=max[0]).contains(&width)
+        && (min[1]..=max[1]).contains(&height)
+        && cmp::max(width, height) / cmp::min(width, height) <= max_aspect_ratio
+}
+
+// Returns whether `stages` includes a framebuffer-space stage, as defined by the Vulkan
+// specification.
+fn has_framebuffer_space_stage(stages: PipelineStages) -> bool {
+    stages.early_fragment_tests
+        || stages.fragment_shader
+        || stages.late_fragment_tests
+        || stages.color_attachment_output
+        || stages.all_graphics
+        || stages.all_commands
+}
+
+// Creates the render pass with `vkCreateRenderPass2`, which is needed for fragment shading rate
+// attachments. The structs that were built for `vkCreateRenderPass` are converted to their
+// `2` equivalent.
+unsafe fn create_render_pass2(
+    device: &Device,
+    description: &RenderPassDesc,
+    attachments: &[ash::vk::AttachmentDescription],
+    passes: &[ash::vk::SubpassDescription],
+    dependencies: &[ash::vk::SubpassDependency],
+) -> Result<ash::vk::RenderPass, RenderPassCreationError> {
+    let fns = device.fns();
+
+    let attachments2 = attachments
+        .iter()
+        .map(|attachment| ash::vk::AttachmentDescription2 {
+            flags: attachment.flags,
+            format: attachment.format,
+            samples: attachment.samples,
+            load_op: attachment.load_op,
+            store_op: attachment.store_op,
+            stencil_load_op: attachment.stencil_load_op,
+            stencil_store_op: attachment.stencil_store_op,
+            initial_layout: attachment.initial_layout,
+            final_layout: attachment.final_layout,
+            ..Default::default()
+        })
+        .collect::<SmallVec<[_; 16]>>();
+
+    let convert_references =
+        |references: *const ash::vk::AttachmentReference, count: u32, input: bool| {
+            if references.is_null() {
+                return SmallVec::<[_; 8]>::new();
+            }
+
+            slice::from_raw_parts(references, count as usize)
+                .iter()
+                .map(|reference| ash::vk::AttachmentReference2 {
+                    attachment: reference.attachment,
+                    layout: reference.layout,
+                    // The aspect mask is only used for input attachments.
+                    aspect_mask: if input {
+                        description.attachments()[reference.attachment as usize]
+                            .format
+                            .aspects()
+                            .into()
+                    } else {
+                        ash::vk::ImageAspectFlags::empty()
+                    },
+                    ..Default::default()
+                })
+                .collect()
+        };
+
+    struct PassReferences {
+        input: SmallVec<[ash::vk::AttachmentReference2; 8]>,
+        color: SmallVec<[ash::vk::AttachmentReference2; 8]>,
+        resolve: SmallVec<[ash::vk::AttachmentReference2; 8]>,
+        depth_stencil: SmallVec<[ash::vk::AttachmentReference2; 8]>,
+        fragment_shading_rate: Option<ash::vk::AttachmentReference2>,
+    }
+
+    let references = passes
+        .iter()
+        .zip(description.subpasses())
+        .map(|(pass, pass_desc)| PassReferences {
+            input: convert_references(pass.p_input_attachments, pass.input_attachment_count, true),
+            color: convert_references(pass.p_color_attachments, pass.color_attachment_count, false),
+            resolve: convert_references(
+                pass.p_resolve_attachments,
+                pass.color_attachment_count,
+                false,
+            ),
+            depth_stencil: convert_references(pass.p_depth_stencil_attachment, 1, false),
+            fragment_shading_rate: pass_desc
+                .fragment_shading_rate_attachment
+                .map(|attachment| ash::vk::AttachmentReference2 {
+                    attachment: attachment.attachment as u32,
+                    layout: attachment.layout.into(),
+                    ..Default::default()
+                }),
+        })
+        .collect::<SmallVec<[_; 16]>>();
+
+    let fragment_shading_rate_infos = references
+        .iter()
+        .zip(description.subpasses())
+        .map(|(references, pass_desc)| {
+            let attachment = pass_desc.fragment_shading_rate_attachment?;
+
+            Some(ash::vk::FragmentShadingRateAttachmentInfoKHR {
+                p_fragment_shading_rate_attachment: references
+                    .fragment_shading_rate
+                    .as_ref()
+                    .unwrap(),
+                shading_rate_attachment_texel_size: ash::vk::Extent2D {
+                    width: attachment.texel_size[0],
+                    height: attachment.texel_size[1],
+                },
+                ..Default::default()
+            })
+        })
+        .collect::<SmallVec<[_; 16]>>();
+
+    let multiview = description.multiview().as_ref();
+
+    let passes2 = passes
+        .iter()
+        .zip(&references)
+        .zip(&fragment_shading_rate_infos)
+        .enumerate()
+        .map(
+            |(index, ((pass, references), fragment_shading_rate_info))| {
+                ash::vk::SubpassDescription2 {
+                    p_next: fragment_shading_rate_info
+                        .as_ref()
+                        .map(|info| info as *const _ as *const _)
+                        .unwrap_or(ptr::null()),
+                    flags: pass.flags,
+                    pipeline_bind_point: pass.pipeline_bind_point,
+                    view_mask: multiview
+                        .and_then(|multiview| multiview.view_masks.get(index).copied())
+                        .unwrap_or(0),
+                    input_attachment_count: references.input.len() as u32,
+                    p_input_attachments: if references.input.is_empty() {
+                        ptr::null()
+                    } else {
+                        references.input.as_ptr()
+                    },
+                    color_attachment_count: references.color.len() as u32,
+                    p_color_attachments: if references.color.is_empty() {
+                        ptr::null()
+                    } else {
+                        references.color.as_ptr()
+                    },
+                    p_resolve_attachments: if references.resolve.is_empty() {
+                        ptr::null()
+                    } else {
+                        references.resolve.as_ptr()
+                    },
+                    p_depth_stencil_attachment: if references.depth_stencil.is_empty() {
+                        ptr::null()
+                    } else {
+                        references.depth_stencil.as_ptr()
+                    },
+                    preserve_attachment_count: pass.preserve_attachment_count,
+                    p_preserve_attachments: pass.p_preserve_attachments,
+                    ..Default::default()
+                }
+            },
+        )
+        .collect::<SmallVec<[_; 16]>>();
+
+    let dependencies2 = dependencies
+        .iter()
+        .enumerate()
+        .map(|(index, dependency)| ash::vk::SubpassDependency2 {
+            src_subpass: dependency.src_subpass,
+            dst_subpass: dependency.dst_subpass,
+            src_stage_mask: dependency.src_stage_mask,
+            dst_stage_mask: dependency.dst_stage_mask,
+            src_access_mask: dependency.src_access_mask,
+            dst_access_mask: dependency.dst_access_mask,
+            dependency_flags: dependency.dependency_flags,
+            view_offset: multiview
+                .and_then(|multiview| multiview.view_offsets.get(index).copied())
+                .unwrap_or(0),
+            ..Default::default()
+        })
+        .collect::<SmallVec<[_; 16]>>();
+
+    let correlated_view_masks = multiview
+        .map(|multiview| multiview.correlation_masks.as_slice())
+        .unwrap_or(&[]);
+
+    let infos = ash::vk::RenderPassCreateInfo2 {
+        flags: ash::vk::RenderPassCreateFlags::empty(),
+        attachment_count: attachments2.len() as u32,
+        p_attachments: if attachments2.is_empty() {
+            ptr::null()
+        } else {
+            attachments2.as_ptr()
+        },
+        subpass_count: passes2.len() as u32,
+        p_subpasses: passes2.as_ptr(),
+        dependency_count: dependencies2.len() as u32,
+        p_dependencies: if dependencies2.is_empty() {
+            ptr::null()
+        } else {
+            dependencies2.as_ptr()
+        },
+        correlated_view_mask_count: correlated_view_masks.len() as u32,
+        p_correlated_view_masks: if correlated_view_masks.is_empty() {
+            ptr::null()
+        } else {
+            correlated_view_masks.as_ptr()
+        },
+        ..Default::default()
+    };
+
+    let mut output = MaybeUninit::uninit();
+
+    if device.api_version() >= Version::V1_2 {
+        check_errors(fns.v1_2.create_render_pass2(
+            device.internal_object(),
+            &infos,
+            ptr::null(),
+            output.as_mut_ptr(),
+        ))?;
+    } else {
+        check_errors(fns.khr_create_renderpass2.create_render_pass2_khr(
+            device.internal_object(),
+            &infos,
+            ptr::null(),
+            output.as_mut_ptr(),
+        ))?;
+    }
+
+    Ok(output.assume_init())
+}
+
 /// Error that can happen when creating a compute pipeline.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum RenderPassCreationError {
@@ -601,6 +919,28 @@ pub enum RenderPassCreationError {
     OomError(OomError),
     /// The maximum number of color attachments has been exceeded.
     ColorAttachmentsLimitExceeded,
+    /// A device feature that was required for a particular setting on the render pass was not
+    /// enabled.
+    FeatureNotEnabled {
+        feature: &'static str,
+        reason: &'static str,
+    },
+    /// An attachment is used as an input attachment and as a color or depth/stencil attachment
+    /// of the same subpass, but not in the `General` layout, or in the
+    /// `DepthStencilReadOnlyOptimal` layout for a depth/stencil attachment.
+    FeedbackLoopLayoutNotSupported {
+        /// The index of the attachment.
+        attachment: usize,
+    },
+    /// The texel size of a fragment shading rate attachment is not a power of two, is outside
+    /// the limits of the device, or its aspect ratio is too large.
+    InvalidFragmentShadingRateTexelSize,
+    /// A dependency of a subpass on itself includes framebuffer-space stages on both sides, but
+    /// is not by region.
+    SelfDependencyNotByRegion {
+        /// The index of the subpass.
+        subpass: usize,
+    },
 }
 
 impl error::Error for RenderPassCreationError {
@@ -624,6 +964,20 @@ impl fmt::Display for RenderPassCreationError {
                 RenderPassCreationError::ColorAttachmentsLimitExceeded => {
                     "the maximum number of color attachments has been exceeded"
                 }
+                RenderPassCreationError::FeatureNotEnabled { .. } => {
+                    "a device feature that was required was not enabled"
+                }
+                RenderPassCreationError::FeedbackLoopLayoutNotSupported { .. } => {
+                    "an attachment is used as an input attachment and as a color or depth/stencil \
+                     attachment of the same subpass, but its layout doesn't support a feedback loop"
+                }
+                RenderPassCreationError::InvalidFragmentShadingRateTexelSize => {
+                    "the texel size of a fragment shading rate attachment is invalid"
+                }
+                RenderPassCreationError::SelfDependencyNotByRegion { .. } => {
+                    "a dependency of a subpass on itself includes framebuffer-space stages on both \
+                     sides, but is not by region"
+                }
             }
         )
     }
@@ -688,6 +1042,23 @@ impl Subpass {
         self.subpass_id as usize == self.render_pass.desc().subpasses().len() - 1
     }
 
+    /// Returns the dependency of this subpass on itself, if the render pass has one.
+    ///
+    /// A self-dependency is required to record a pipeline barrier inside this subpass, for
+    /// example to read with an input attachment what a previous draw of the subpass wrote to the
+    /// same attachment.
+    #[inline]
+    pub fn self_dependency(&self) -> Option<&SubpassDependencyDesc> {
+        self.render_pass
+            .desc()
+            .dependencies()
+            .iter()
+            .find(|dependency| {
+                dependency.source_subpass == self.subpass_id as usize
+                    && dependency.destination_subpass == self.subpass_id as usize
+            })
+    }
+
     /// Tries to advance to the next subpass after this one, and returns `true` if successful.
     #[inline]
     pub fn try_next_subpass(&mut self) -> bool {
@@ -835,8 +1206,19 @@ impl From<Subpass> for (Arc<RenderPass>, u32) {
 #[cfg(test)]
 mod tests {
     use crate::format::Format;
+    use crate::image::ImageLayout;
+    use crate::image::SampleCount;
+    use crate::render_pass::AttachmentDesc;
+    use crate::render_pass::LoadOp;
     use crate::render_pass::RenderPass;
     use crate::render_pass::RenderPassCreationError;
+    use crate::render_pass::RenderPassDesc;
+    use crate::render_pass::StoreOp;
+    use crate::render_pass::Subpass;
+    use crate::render_pass::SubpassDependencyDesc;
+    use crate::render_pass::SubpassDesc;
+    use crate::sync::AccessFlags;
+    use crate::sync::PipelineStages;
 
     #[test]
     fn empty() {
@@ -878,6 +1260,74 @@ mod tests {
         }
     }
 
+    #[test]
+    fn feedback_loop() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let desc = |layout, by_region| {
+            RenderPassDesc::new(
+                vec![AttachmentDesc {
+                    format: Format::R8G8B8A8_UNORM,
+                    samples: SampleCount::Sample1,
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                    stencil_load: LoadOp::DontCare,
+                    stencil_store: StoreOp::DontCare,
+                    initial_layout: ImageLayout::General,
+                    final_layout: ImageLayout::General,
+                }],
+                vec![SubpassDesc {
+                    color_attachments: vec![(0, layout)],
+                    depth_stencil: None,
+                    input_attachments: vec![(0, layout)],
+                    resolve_attachments: vec![],
+                    preserve_attachments: vec![],
+                    fragment_shading_rate_attachment: None,
+                }],
+                vec![SubpassDependencyDesc {
+                    source_subpass: 0,
+                    destination_subpass: 0,
+                    source_stages: PipelineStages {
+                        color_attachment_output: true,
+                        ..PipelineStages::none()
+                    },
+                    destination_stages: PipelineStages {
+                        fragment_shader: true,
+                        ..PipelineStages::none()
+                    },
+                    source_access: AccessFlags {
+                        color_attachment_write: true,
+                        ..AccessFlags::none()
+                    },
+                    destination_access: AccessFlags {
+                        input_attachment_read: true,
+                        ..AccessFlags::none()
+                    },
+                    by_region,
+                }],
+            )
+        };
+
+        match RenderPass::new(
+            device.clone(),
+            desc(ImageLayout::ColorAttachmentOptimal, true),
+        ) {
+            Err(RenderPassCreationError::FeedbackLoopLayoutNotSupported { attachment: 0 }) => (),
+            _ => panic!(),
+        }
+
+        match RenderPass::new(device.clone(), desc(ImageLayout::General, false)) {
+            Err(RenderPassCreationError::SelfDependencyNotByRegion { subpass: 0 }) => (),
+            _ => panic!(),
+        }
+
+        let render_pass = RenderPass::new(device, desc(ImageLayout::General, true)).unwrap();
+        assert!(Subpass::from(render_pass, 0)
+            .unwrap()
+            .self_dependency()
+            .is_some());
+    }
+
     #[test]
     fn non_zero_granularity() {
         let (device, _) = gfx_dev_and_queue!();
@@ -60,21 +60,44 @@
 //!
 //! Samplers that don't use `ClampToBorder` are not concerned by these restrictions.
 //!
+//! With the `ext_custom_border_color` extension, a sampler can also use any color as border
+//! color, see [`SamplerBuilder::custom_border_color`].
+//!
+//! # Reduction modes
+//!
+//! By default, filtering computes a weighted average of the texels that are read. With a
+//! [`SamplerReductionMode`] of `Min` or `Max`, it returns their minimum or maximum instead, which
+//! is for example used to generate the mipmaps of a hierarchical depth buffer for occlusion
+//! culling. This requires Vulkan 1.2 with the `sampler_filter_minmax` feature, or the
+//! `ext_sampler_filter_minmax` extension.
+//!
+//! # Sampler YCbCr conversion
+//!
+//! Image views with a YCbCr format that requires a conversion can only be sampled with a sampler
+//! that was created with the same [`SamplerYcbcrConversion`](ycbcr::SamplerYcbcrConversion),
+//! using `Sampler::ycbcr`. Such a sampler must be provided as an immutable sampler of a combined
+//! image sampler descriptor in the descriptor set layout. See the [`ycbcr`] module for more info.
+//!
 // FIXME: restrictions aren't checked yet
 
 use crate::check_errors;
 use crate::device::Device;
 use crate::device::DeviceOwned;
+use crate::format::Format;
 use crate::pipeline::depth_stencil::CompareOp;
+use crate::sampler::ycbcr::SamplerYcbcrConversion;
 use crate::Error;
 use crate::OomError;
 use crate::VulkanObject;
 use std::error;
 use std::fmt;
 use std::mem::MaybeUninit;
+use std::ops::RangeInclusive;
 use std::ptr;
 use std::sync::Arc;
 
+pub mod ycbcr;
+
 /// Describes how to retrieve data from an image within a shader.
 pub struct Sampler {
     handle: ash::vk::Sampler,
@@ -84,9 +107,34 @@ pub struct Sampler {
     usable_with_float_formats: bool,
     usable_with_int_formats: bool,
     usable_with_swizzling: bool,
+    reduction_mode: SamplerReductionMode,
+    custom_border_color: Option<CustomBorderColor>,
+    sampler_ycbcr_conversion: Option<Arc<SamplerYcbcrConversion>>,
 }
 
 impl Sampler {
+    /// Starts building a new sampler, using default values for the parameters.
+    ///
+    /// Unlike `new`, the builder gives access to the parameters that most samplers don't need,
+    /// such as the reduction mode or a custom border color. See [`SamplerBuilder`] for the
+    /// defaults.
+    #[inline]
+    pub fn start(device: Arc<Device>) -> SamplerBuilder {
+        SamplerBuilder {
+            device,
+            mag_filter: Filter::Nearest,
+            min_filter: Filter::Nearest,
+            mipmap_mode: MipmapMode::Nearest,
+            address_mode: [SamplerAddressMode::ClampToEdge; 3],
+            mip_lod_bias: 0.0,
+            max_anisotropy: 1.0,
+            compare: None,
+            lod: 0.0..=0.0,
+            reduction_mode: SamplerReductionMode::WeightedAverage,
+            custom_border_color: None,
+        }
+    }
+
     /// Shortcut for creating a sampler with linear sampling, linear mipmaps, and with the repeat
     /// mode for borders.
     ///
@@ -193,6 +241,8 @@ impl Sampler {
             min_lod,
             max_lod,
             None,
+            SamplerReductionMode::WeightedAverage,
+            None,
         )
     }
 
@@ -240,6 +290,8 @@ impl Sampler {
             min_lod,
             max_lod,
             Some(compare),
+            SamplerReductionMode::WeightedAverage,
+            None,
         )
     }
 
@@ -256,6 +308,8 @@ impl Sampler {
         min_lod: f32,
         max_lod: f32,
         compare: Option<CompareOp>,
+        reduction_mode: SamplerReductionMode,
+        custom_border_color: Option<(CustomBorderColor, Option<Format>)>,
     ) -> Result<Arc<Sampler>, SamplerCreationError> {
         assert!(max_anisotropy >= 1.0);
         assert!(min_lod <= max_lod);
@@ -286,6 +340,19 @@ impl Sampler {
             }
         }
 
+        // Check cubic filter extension support
+        if mag_filter == Filter::Cubic || min_filter == Filter::Cubic {
+            if !(device.enabled_extensions().ext_filter_cubic
+                || device.enabled_extensions().img_filter_cubic)
+            {
+                return Err(SamplerCreationError::FilterCubicExtensionNotEnabled);
+            }
+
+            if max_anisotropy > 1.0 {
+                return Err(SamplerCreationError::FilterCubicWithAnisotropy);
+            }
+        }
+
         // Check MirrorClampToEdge extension support
         if [address_u, address_v, address_w]
             .iter()
@@ -296,6 +363,32 @@ impl Sampler {
             }
         }
 
+        // Check min/max reduction support
+        if reduction_mode != SamplerReductionMode::WeightedAverage {
+            if !(device.enabled_features().sampler_filter_minmax
+                || device.enabled_extensions().ext_sampler_filter_minmax)
+            {
+                return Err(SamplerCreationError::SamplerFilterMinmaxNotEnabled);
+            }
+
+            // VUID-VkSamplerCreateInfo-compareEnable-01423
+            if compare.is_some() {
+                return Err(SamplerCreationError::ReductionModeWithCompare);
+            }
+        }
+
+        // Check custom border color support
+        if let Some((_, format)) = custom_border_color {
+            if !device.enabled_features().custom_border_colors {
+                return Err(SamplerCreationError::CustomBorderColorsFeatureNotEnabled);
+            }
+
+            // VUID-VkSamplerCustomBorderColorCreateInfoEXT-format-04014
+            if format.is_none() && !device.enabled_features().custom_border_color_without_format {
+                return Err(SamplerCreationError::CustomBorderColorWithoutFormatFeatureNotEnabled);
+            }
+        }
+
         // Handling border color.
         let border_color = address_u.border_color();
         let border_color = match (border_color, address_v.border_color()) {
@@ -315,76 +408,149 @@ impl Sampler {
             (b, None) => b,
         };
 
-        let fns = device.fns();
-        let handle = unsafe {
-            let infos = ash::vk::SamplerCreateInfo {
-                flags: ash::vk::SamplerCreateFlags::empty(),
-                mag_filter: mag_filter.into(),
-                min_filter: min_filter.into(),
-                mipmap_mode: mipmap_mode.into(),
-                address_mode_u: address_u.into(),
-                address_mode_v: address_v.into(),
-                address_mode_w: address_w.into(),
-                mip_lod_bias: mip_lod_bias,
-                anisotropy_enable: if max_anisotropy > 1.0 {
-                    ash::vk::TRUE
-                } else {
-                    ash::vk::FALSE
-                },
-                max_anisotropy: max_anisotropy,
-                compare_enable: if compare.is_some() {
-                    ash::vk::TRUE
-                } else {
-                    ash::vk::FALSE
-                },
-                compare_op: compare
-                    .map(|c| c.into())
-                    .unwrap_or(ash::vk::CompareOp::NEVER),
-                min_lod: min_lod,
-                max_lod: max_lod,
-                border_color: border_color
+        let mut reduction_mode_info = if reduction_mode != SamplerReductionMode::WeightedAverage {
+            Some(ash::vk::SamplerReductionModeCreateInfo {
+                reduction_mode: reduction_mode.into(),
+                ..Default::default()
+            })
+        } else {
+            None
+        };
+
+        let mut custom_border_color_info = custom_border_color.map(|(color, format)| {
+            ash::vk::SamplerCustomBorderColorCreateInfoEXT {
+                custom_border_color: color.into(),
+                format: format.map(Into::into).unwrap_or(ash::vk::Format::UNDEFINED),
+                ..Default::default()
+            }
+        });
+
+        let mut infos = ash::vk::SamplerCreateInfo {
+            flags: ash::vk::SamplerCreateFlags::empty(),
+            mag_filter: mag_filter.into(),
+            min_filter: min_filter.into(),
+            mipmap_mode: mipmap_mode.into(),
+            address_mode_u: address_u.into(),
+            address_mode_v: address_v.into(),
+            address_mode_w: address_w.into(),
+            mip_lod_bias: mip_lod_bias,
+            anisotropy_enable: if max_anisotropy > 1.0 {
+                ash::vk::TRUE
+            } else {
+                ash::vk::FALSE
+            },
+            max_anisotropy: max_anisotropy,
+            compare_enable: if compare.is_some() {
+                ash::vk::TRUE
+            } else {
+                ash::vk::FALSE
+            },
+            compare_op: compare
+                .map(|c| c.into())
+                .unwrap_or(ash::vk::CompareOp::NEVER),
+            min_lod: min_lod,
+            max_lod: max_lod,
+            border_color: match custom_border_color {
+                Some((CustomBorderColor::Float(_), _)) => ash::vk::BorderColor::FLOAT_CUSTOM_EXT,
+                Some(_) => ash::vk::BorderColor::INT_CUSTOM_EXT,
+                None => border_color
                     .map(|b| b.into())
                     .unwrap_or(ash::vk::BorderColor::FLOAT_TRANSPARENT_BLACK),
-                unnormalized_coordinates: ash::vk::FALSE,
-                ..Default::default()
-            };
+            },
+            unnormalized_coordinates: ash::vk::FALSE,
+            ..Default::default()
+        };
 
-            let mut output = MaybeUninit::uninit();
-            check_errors(fns.v1_0.create_sampler(
-                device.internal_object(),
-                &infos,
-                ptr::null(),
-                output.as_mut_ptr(),
-            ))?;
-            output.assume_init()
+        if let Some(info) = reduction_mode_info.as_mut() {
+            info.p_next = infos.p_next;
+            infos.p_next = info as *const _ as *const _;
+        }
+
+        if let Some(info) = custom_border_color_info.as_mut() {
+            info.p_next = infos.p_next;
+            infos.p_next = info as *const _ as *const _;
+        }
+
+        let key = SamplerKey {
+            reduction_mode: reduction_mode.into(),
+            custom_border_color: custom_border_color.map(|(color, format)| {
+                (
+                    color.to_bits(),
+                    format.map(Into::into).unwrap_or(ash::vk::Format::UNDEFINED),
+                )
+            }),
+            ..SamplerKey::from(&infos)
         };
 
-        Ok(Arc::new(Sampler {
-            handle,
-            device: device.clone(),
-            compare_mode: compare.is_some(),
-            unnormalized: false,
-            usable_with_float_formats: match border_color {
-                Some(BorderColor::FloatTransparentBlack) => true,
-                Some(BorderColor::FloatOpaqueBlack) => true,
-                Some(BorderColor::FloatOpaqueWhite) => true,
-                Some(_) => false,
-                None => true,
-            },
-            usable_with_int_formats: compare.is_none()
-                && match border_color {
-                    Some(BorderColor::IntTransparentBlack) => true,
-                    Some(BorderColor::IntOpaqueBlack) => true,
-                    Some(BorderColor::IntOpaqueWhite) => true,
-                    Some(_) => false,
-                    None => true,
+        Sampler::get_or_create(&device, key, || {
+            let fns = device.fns();
+            let handle = unsafe {
+                let mut output = MaybeUninit::uninit();
+                check_errors(fns.v1_0.create_sampler(
+                    device.internal_object(),
+                    &infos,
+                    ptr::null(),
+                    output.as_mut_ptr(),
+                ))?;
+                output.assume_init()
+            };
+
+            let border_is_float = match (custom_border_color, border_color) {
+                (Some((CustomBorderColor::Float(_), _)), _) => Some(true),
+                (Some(_), _) => Some(false),
+                (None, Some(BorderColor::FloatTransparentBlack))
+                | (None, Some(BorderColor::FloatOpaqueBlack))
+                | (None, Some(BorderColor::FloatOpaqueWhite)) => Some(true),
+                (None, Some(_)) => Some(false),
+                (None, None) => None,
+            };
+
+            Ok(Arc::new(Sampler {
+                handle,
+                device: device.clone(),
+                compare_mode: compare.is_some(),
+                unnormalized: false,
+                usable_with_float_formats: border_is_float != Some(false),
+                usable_with_int_formats: compare.is_none() && border_is_float != Some(true),
+                usable_with_swizzling: match (custom_border_color, border_color) {
+                    // Without a format, the implementation can't apply the swizzle to the color.
+                    (Some((_, format)), _) => format.is_some(),
+                    (None, Some(BorderColor::FloatOpaqueBlack)) => false,
+                    (None, Some(BorderColor::IntOpaqueBlack)) => false,
+                    _ => true,
                 },
-            usable_with_swizzling: match border_color {
-                Some(BorderColor::FloatOpaqueBlack) => false,
-                Some(BorderColor::IntOpaqueBlack) => false,
-                _ => true,
-            },
-        }))
+                reduction_mode,
+                custom_border_color: custom_border_color.map(|(color, _)| color),
+                sampler_ycbcr_conversion: None,
+            }))
+        })
+    }
+
+    // Returns an existing sampler that was created with the same parameters if object
+    // deduplication is enabled on the device, and calls `create` otherwise.
+    fn get_or_create(
+        device: &Arc<Device>,
+        key: SamplerKey,
+        create: impl FnOnce() -> Result<Arc<Sampler>, SamplerCreationError>,
+    ) -> Result<Arc<Sampler>, SamplerCreationError> {
+        if !device.object_deduplication() {
+            return create();
+        }
+
+        let mut cache = device.sampler_cache().lock().unwrap();
+        cache.retain(|(_, sampler)| sampler.strong_count() > 0);
+
+        if let Some(sampler) = cache
+            .iter()
+            .filter(|(other, _)| *other == key)
+            .find_map(|(_, sampler)| sampler.upgrade())
+        {
+            return Ok(sampler);
+        }
+
+        let sampler = create()?;
+        cache.push((key, Arc::downgrade(&sampler)));
+        Ok(sampler)
     }
 
     /// Creates a sampler with unnormalized coordinates. This means that texture coordinates won't
@@ -406,7 +572,12 @@ impl Sampler {
         address_u: UnnormalizedSamplerAddressMode,
         address_v: UnnormalizedSamplerAddressMode,
     ) -> Result<Arc<Sampler>, SamplerCreationError> {
-        let fns = device.fns();
+        if filter == Filter::Cubic
+            && !(device.enabled_extensions().ext_filter_cubic
+                || device.enabled_extensions().img_filter_cubic)
+        {
+            return Err(SamplerCreationError::FilterCubicExtensionNotEnabled);
+        }
 
         let border_color = address_u.border_color();
         let border_color = match (border_color, address_v.border_color()) {
@@ -418,15 +589,101 @@ impl Sampler {
             (b, None) => b,
         };
 
+        let infos = ash::vk::SamplerCreateInfo {
+            flags: ash::vk::SamplerCreateFlags::empty(),
+            mag_filter: filter.into(),
+            min_filter: filter.into(),
+            mipmap_mode: ash::vk::SamplerMipmapMode::NEAREST,
+            address_mode_u: address_u.into(),
+            address_mode_v: address_v.into(),
+            address_mode_w: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE, // unused by the impl
+            mip_lod_bias: 0.0,
+            anisotropy_enable: ash::vk::FALSE,
+            max_anisotropy: 1.0,
+            compare_enable: ash::vk::FALSE,
+            compare_op: ash::vk::CompareOp::NEVER,
+            min_lod: 0.0,
+            max_lod: 0.0,
+            border_color: border_color
+                .map(|b| b.into())
+                .unwrap_or(ash::vk::BorderColor::FLOAT_TRANSPARENT_BLACK),
+            unnormalized_coordinates: ash::vk::TRUE,
+            ..Default::default()
+        };
+
+        Sampler::get_or_create(&device, SamplerKey::from(&infos), || {
+            let fns = device.fns();
+            let handle = unsafe {
+                let mut output = MaybeUninit::uninit();
+                check_errors(fns.v1_0.create_sampler(
+                    device.internal_object(),
+                    &infos,
+                    ptr::null(),
+                    output.as_mut_ptr(),
+                ))?;
+                output.assume_init()
+            };
+
+            Ok(Arc::new(Sampler {
+                handle,
+                device: device.clone(),
+                compare_mode: false,
+                unnormalized: true,
+                usable_with_float_formats: match border_color {
+                    Some(BorderColor::FloatTransparentBlack) => true,
+                    Some(BorderColor::FloatOpaqueBlack) => true,
+                    Some(BorderColor::FloatOpaqueWhite) => true,
+                    Some(_) => false,
+                    None => true,
+                },
+                usable_with_int_formats: match border_color {
+                    Some(BorderColor::IntTransparentBlack) => true,
+                    Some(BorderColor::IntOpaqueBlack) => true,
+                    Some(BorderColor::IntOpaqueWhite) => true,
+                    Some(_) => false,
+                    None => true,
+                },
+                usable_with_swizzling: match border_color {
+                    Some(BorderColor::FloatOpaqueBlack) => false,
+                    Some(BorderColor::IntOpaqueBlack) => false,
+                    _ => true,
+                },
+                reduction_mode: SamplerReductionMode::WeightedAverage,
+                custom_border_color: None,
+                sampler_ycbcr_conversion: None,
+            }))
+        })
+    }
+
+    /// Creates a sampler that applies a sampler YCbCr conversion.
+    ///
+    /// The filter of the sampler is the chroma filter of the conversion. The sampler uses the
+    /// `ClampToEdge` address mode, doesn't use mipmaps and doesn't use anisotropy.
+    ///
+    /// The sampler can only be used as an immutable sampler of a combined image sampler
+    /// descriptor, together with image views that were created with the same conversion.
+    pub fn ycbcr(
+        conversion: Arc<SamplerYcbcrConversion>,
+    ) -> Result<Arc<Sampler>, SamplerCreationError> {
+        let device = conversion.device().clone();
+        let filter = conversion.chroma_filter();
+        let fns = device.fns();
+
         let handle = unsafe {
+            let conversion_info = ash::vk::SamplerYcbcrConversionInfo {
+                conversion: conversion.internal_object(),
+                ..Default::default()
+            };
+
             let infos = ash::vk::SamplerCreateInfo {
+                p_next: &conversion_info as *const _ as *const _,
                 flags: ash::vk::SamplerCreateFlags::empty(),
                 mag_filter: filter.into(),
                 min_filter: filter.into(),
                 mipmap_mode: ash::vk::SamplerMipmapMode::NEAREST,
-                address_mode_u: address_u.into(),
-                address_mode_v: address_v.into(),
-                address_mode_w: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE, // unused by the impl
+                address_mode_u: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
                 mip_lod_bias: 0.0,
                 anisotropy_enable: ash::vk::FALSE,
                 max_anisotropy: 1.0,
@@ -434,10 +691,8 @@ impl Sampler {
                 compare_op: ash::vk::CompareOp::NEVER,
                 min_lod: 0.0,
                 max_lod: 0.0,
-                border_color: border_color
-                    .map(|b| b.into())
-                    .unwrap_or(ash::vk::BorderColor::FLOAT_TRANSPARENT_BLACK),
-                unnormalized_coordinates: ash::vk::TRUE,
+                border_color: ash::vk::BorderColor::FLOAT_TRANSPARENT_BLACK,
+                unnormalized_coordinates: ash::vk::FALSE,
                 ..Default::default()
             };
 
@@ -453,28 +708,15 @@ impl Sampler {
 
         Ok(Arc::new(Sampler {
             handle,
-            device: device.clone(),
+            device,
             compare_mode: false,
-            unnormalized: true,
-            usable_with_float_formats: match border_color {
-                Some(BorderColor::FloatTransparentBlack) => true,
-                Some(BorderColor::FloatOpaqueBlack) => true,
-                Some(BorderColor::FloatOpaqueWhite) => true,
-                Some(_) => false,
-                None => true,
-            },
-            usable_with_int_formats: match border_color {
-                Some(BorderColor::IntTransparentBlack) => true,
-                Some(BorderColor::IntOpaqueBlack) => true,
-                Some(BorderColor::IntOpaqueWhite) => true,
-                Some(_) => false,
-                None => true,
-            },
-            usable_with_swizzling: match border_color {
-                Some(BorderColor::FloatOpaqueBlack) => false,
-                Some(BorderColor::IntOpaqueBlack) => false,
-                _ => true,
-            },
+            unnormalized: false,
+            usable_with_float_formats: true,
+            usable_with_int_formats: true,
+            usable_with_swizzling: true,
+            reduction_mode: SamplerReductionMode::WeightedAverage,
+            custom_border_color: None,
+            sampler_ycbcr_conversion: Some(conversion),
         }))
     }
 
@@ -510,6 +752,24 @@ impl Sampler {
     pub fn usable_with_swizzling(&self) -> bool {
         self.usable_with_swizzling
     }
+
+    /// Returns the reduction mode of the sampler.
+    #[inline]
+    pub fn reduction_mode(&self) -> SamplerReductionMode {
+        self.reduction_mode
+    }
+
+    /// Returns the custom border color of the sampler, if any.
+    #[inline]
+    pub fn custom_border_color(&self) -> Option<CustomBorderColor> {
+        self.custom_border_color
+    }
+
+    /// Returns the sampler YCbCr conversion of the sampler, if any.
+    #[inline]
+    pub fn sampler_ycbcr_conversion(&self) -> Option<&Arc<SamplerYcbcrConversion>> {
+        self.sampler_ycbcr_conversion.as_ref()
+    }
 }
 
 unsafe impl DeviceOwned for Sampler {
@@ -555,6 +815,205 @@ impl Drop for Sampler {
     }
 }
 
+/// Builder for a [`Sampler`].
+///
+/// The defaults are the `Nearest` filters and mipmap mode, the `ClampToEdge` address mode, no
+/// LOD bias, no anisotropy, no compare operation, a LOD range of `0.0..=0.0` which only uses the
+/// first mipmap, the `WeightedAverage` reduction mode and no custom border color.
+#[derive(Debug)]
+pub struct SamplerBuilder {
+    device: Arc<Device>,
+    mag_filter: Filter,
+    min_filter: Filter,
+    mipmap_mode: MipmapMode,
+    address_mode: [SamplerAddressMode; 3],
+    mip_lod_bias: f32,
+    max_anisotropy: f32,
+    compare: Option<CompareOp>,
+    lod: RangeInclusive<f32>,
+    reduction_mode: SamplerReductionMode,
+    custom_border_color: Option<(CustomBorderColor, Option<Format>)>,
+}
+
+impl SamplerBuilder {
+    /// Builds the sampler.
+    ///
+    /// # Panic
+    ///
+    /// Same panic reasons as `Sampler::new`.
+    #[inline]
+    pub fn build(self) -> Result<Arc<Sampler>, SamplerCreationError> {
+        let [address_u, address_v, address_w] = self.address_mode;
+
+        Sampler::new_impl(
+            self.device,
+            self.mag_filter,
+            self.min_filter,
+            self.mipmap_mode,
+            address_u,
+            address_v,
+            address_w,
+            self.mip_lod_bias,
+            self.max_anisotropy,
+            *self.lod.start(),
+            *self.lod.end(),
+            self.compare,
+            self.reduction_mode,
+            self.custom_border_color,
+        )
+    }
+
+    /// Sets both the magnification and minification filters.
+    #[inline]
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.mag_filter = filter;
+        self.min_filter = filter;
+        self
+    }
+
+    /// Sets the filter used when the image is larger than the original.
+    #[inline]
+    pub fn mag_filter(mut self, filter: Filter) -> Self {
+        self.mag_filter = filter;
+        self
+    }
+
+    /// Sets the filter used when the image is smaller than the original.
+    #[inline]
+    pub fn min_filter(mut self, filter: Filter) -> Self {
+        self.min_filter = filter;
+        self
+    }
+
+    /// Sets how the implementation chooses which mipmap to use.
+    #[inline]
+    pub fn mipmap_mode(mut self, mode: MipmapMode) -> Self {
+        self.mipmap_mode = mode;
+        self
+    }
+
+    /// Sets the address mode of all three coordinates.
+    #[inline]
+    pub fn address_mode(mut self, mode: SamplerAddressMode) -> Self {
+        self.address_mode = [mode; 3];
+        self
+    }
+
+    /// Sets the address mode of the `u`, `v` and `w` coordinates separately.
+    #[inline]
+    pub fn address_modes(mut self, modes: [SamplerAddressMode; 3]) -> Self {
+        self.address_mode = modes;
+        self
+    }
+
+    /// Sets the value to add to the mipmap level of detail.
+    #[inline]
+    pub fn mip_lod_bias(mut self, bias: f32) -> Self {
+        self.mip_lod_bias = bias;
+        self
+    }
+
+    /// Sets the maximum anisotropy. A value greater than 1.0 enables anisotropic filtering and
+    /// requires the `sampler_anisotropy` feature.
+    #[inline]
+    pub fn max_anisotropy(mut self, max_anisotropy: f32) -> Self {
+        self.max_anisotropy = max_anisotropy;
+        self
+    }
+
+    /// Sets the compare operation, which makes the sampler a compare-mode sampler. See
+    /// `Sampler::compare`.
+    #[inline]
+    pub fn compare(mut self, compare: Option<CompareOp>) -> Self {
+        self.compare = compare;
+        self
+    }
+
+    /// Sets the range of the mipmap levels of detail that can be used.
+    #[inline]
+    pub fn lod(mut self, lod: RangeInclusive<f32>) -> Self {
+        self.lod = lod;
+        self
+    }
+
+    /// Sets how the texels that are read are combined into the returned value.
+    ///
+    /// Any mode other than `WeightedAverage` requires Vulkan 1.2 and the
+    /// `sampler_filter_minmax` feature, or the `ext_sampler_filter_minmax` extension, and can't
+    /// be used together with a compare operation.
+    ///
+    /// > **Note**: Which formats support the `Min` and `Max` modes is indicated by the
+    /// > `sampled_image_filter_minmax` format feature. At least single-component formats of depth
+    /// > images support them if the `filter_minmax_single_component_formats` property is true.
+    #[inline]
+    pub fn reduction_mode(mut self, mode: SamplerReductionMode) -> Self {
+        self.reduction_mode = mode;
+        self
+    }
+
+    /// Sets a custom border color, which is used instead of the `BorderColor` of the
+    /// `ClampToBorder` address modes. Whether the sampler can be used with floating-point or
+    /// integer image views depends on the variant of `color`.
+    ///
+    /// `format` is the format of the image views that the sampler will be used with. It can only
+    /// be `None` if the `custom_border_color_without_format` feature is enabled, in which case
+    /// the sampler can only be used with image views that have identity swizzling.
+    ///
+    /// Requires the `custom_border_colors` feature, which is part of the `ext_custom_border_color`
+    /// extension. The number of samplers with a custom border color that can exist at the same
+    /// time is limited by the `max_custom_border_color_samplers` property; this is not checked.
+    #[inline]
+    pub fn custom_border_color(mut self, color: CustomBorderColor, format: Option<Format>) -> Self {
+        self.custom_border_color = Some((color, format));
+        self
+    }
+}
+
+/// The parameters that a sampler was created with, used to deduplicate samplers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct SamplerKey {
+    mag_filter: ash::vk::Filter,
+    min_filter: ash::vk::Filter,
+    mipmap_mode: ash::vk::SamplerMipmapMode,
+    address_mode: [ash::vk::SamplerAddressMode; 3],
+    mip_lod_bias: f32,
+    anisotropy_enable: ash::vk::Bool32,
+    max_anisotropy: f32,
+    compare_enable: ash::vk::Bool32,
+    compare_op: ash::vk::CompareOp,
+    lod: [f32; 2],
+    border_color: ash::vk::BorderColor,
+    unnormalized_coordinates: ash::vk::Bool32,
+    reduction_mode: ash::vk::SamplerReductionMode,
+    custom_border_color: Option<([u32; 4], ash::vk::Format)>,
+}
+
+impl From<&ash::vk::SamplerCreateInfo> for SamplerKey {
+    #[inline]
+    fn from(infos: &ash::vk::SamplerCreateInfo) -> Self {
+        SamplerKey {
+            mag_filter: infos.mag_filter,
+            min_filter: infos.min_filter,
+            mipmap_mode: infos.mipmap_mode,
+            address_mode: [
+                infos.address_mode_u,
+                infos.address_mode_v,
+                infos.address_mode_w,
+            ],
+            mip_lod_bias: infos.mip_lod_bias,
+            anisotropy_enable: infos.anisotropy_enable,
+            max_anisotropy: infos.max_anisotropy,
+            compare_enable: infos.compare_enable,
+            compare_op: infos.compare_op,
+            lod: [infos.min_lod, infos.max_lod],
+            border_color: infos.border_color,
+            unnormalized_coordinates: infos.unnormalized_coordinates,
+            reduction_mode: ash::vk::SamplerReductionMode::WEIGHTED_AVERAGE,
+            custom_border_color: None,
+        }
+    }
+}
+
 /// Describes how the color of each pixel should be determined.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[repr(i32)]
@@ -566,6 +1025,12 @@ pub enum Filter {
     /// The pixel whose center is nearest to the requested coordinates is taken from the source
     /// and its value is returned as-is.
     Nearest = ash::vk::Filter::NEAREST.as_raw(),
+
+    /// The sixteen pixels whose center surround the requested coordinates are taken, then their
+    /// values are interpolated with a cubic filter.
+    ///
+    /// The `ext_filter_cubic` or `img_filter_cubic` extension must be enabled on the device.
+    Cubic = ash::vk::Filter::CUBIC_EXT.as_raw(),
 }
 
 impl From<Filter> for ash::vk::Filter {
@@ -725,6 +1190,69 @@ impl From<BorderColor> for ash::vk::BorderColor {
     }
 }
 
+/// A border color of any value, set with [`SamplerBuilder::custom_border_color`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CustomBorderColor {
+    /// A floating-point color. Can only be used with floating-point images.
+    Float([f32; 4]),
+
+    /// A signed integer color. Can only be used with integer images.
+    Int([i32; 4]),
+
+    /// An unsigned integer color. Can only be used with integer images.
+    Uint([u32; 4]),
+}
+
+impl CustomBorderColor {
+    // Returns the bits of the color, to compare colors when deduplicating samplers.
+    #[inline]
+    fn to_bits(self) -> [u32; 4] {
+        match self {
+            CustomBorderColor::Float(c) => [
+                c[0].to_bits(),
+                c[1].to_bits(),
+                c[2].to_bits(),
+                c[3].to_bits(),
+            ],
+            CustomBorderColor::Int(c) => [c[0] as u32, c[1] as u32, c[2] as u32, c[3] as u32],
+            CustomBorderColor::Uint(c) => c,
+        }
+    }
+}
+
+impl From<CustomBorderColor> for ash::vk::ClearColorValue {
+    #[inline]
+    fn from(val: CustomBorderColor) -> Self {
+        match val {
+            CustomBorderColor::Float(float32) => Self { float32 },
+            CustomBorderColor::Int(int32) => Self { int32 },
+            CustomBorderColor::Uint(uint32) => Self { uint32 },
+        }
+    }
+}
+
+/// How the texels that are read by a filter are combined into the returned value.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum SamplerReductionMode {
+    /// The texels are combined by computing their weighted average, as specified by the filter.
+    /// This is the regular behavior of samplers.
+    WeightedAverage = ash::vk::SamplerReductionMode::WEIGHTED_AVERAGE.as_raw(),
+
+    /// The minimum of the texels is returned, component by component.
+    Min = ash::vk::SamplerReductionMode::MIN.as_raw(),
+
+    /// The maximum of the texels is returned, component by component.
+    Max = ash::vk::SamplerReductionMode::MAX.as_raw(),
+}
+
+impl From<SamplerReductionMode> for ash::vk::SamplerReductionMode {
+    #[inline]
+    fn from(val: SamplerReductionMode) -> Self {
+        Self::from_raw(val as i32)
+    }
+}
+
 /// Error that can happen when creating an instance.
 #[derive(Clone, Debug, PartialEq)]
 pub enum SamplerCreationError {
@@ -758,6 +1286,29 @@ pub enum SamplerCreationError {
     /// Using `MirrorClampToEdge` requires enabling the `VK_KHR_sampler_mirror_clamp_to_edge`
     /// extension when creating the device.
     SamplerMirrorClampToEdgeExtensionNotEnabled,
+
+    /// Using the `Cubic` filter requires enabling the `VK_EXT_filter_cubic` or
+    /// `VK_IMG_filter_cubic` extension when creating the device.
+    FilterCubicExtensionNotEnabled,
+
+    /// The `Cubic` filter can't be used with an anisotropy greater than 1.0.
+    FilterCubicWithAnisotropy,
+
+    /// Using a reduction mode other than `WeightedAverage` requires enabling the
+    /// `sampler_filter_minmax` feature or the `VK_EXT_sampler_filter_minmax` extension when
+    /// creating the device.
+    SamplerFilterMinmaxNotEnabled,
+
+    /// A reduction mode other than `WeightedAverage` can't be used with a compare operation.
+    ReductionModeWithCompare,
+
+    /// Using a custom border color requires enabling the `custom_border_colors` feature when
+    /// creating the device.
+    CustomBorderColorsFeatureNotEnabled,
+
+    /// Using a custom border color without a format requires enabling the
+    /// `custom_border_color_without_format` feature when creating the device.
+    CustomBorderColorWithoutFormatFeatureNotEnabled,
 }
 
 impl error::Error for SamplerCreationError {
@@ -788,6 +1339,26 @@ impl fmt::Display for SamplerCreationError {
                 SamplerCreationError::SamplerMirrorClampToEdgeExtensionNotEnabled => {
                     "the device extension `VK_KHR_sampler_mirror_clamp_to_edge` is not enabled"
                 }
+                SamplerCreationError::FilterCubicExtensionNotEnabled => {
+                    "neither the device extension `VK_EXT_filter_cubic` nor `VK_IMG_filter_cubic` \
+                     is enabled"
+                }
+                SamplerCreationError::FilterCubicWithAnisotropy => {
+                    "the cubic filter can't be used with anisotropy"
+                }
+                SamplerCreationError::SamplerFilterMinmaxNotEnabled => {
+                    "neither the `sampler_filter_minmax` feature nor the device extension \
+                     `VK_EXT_sampler_filter_minmax` is enabled"
+                }
+                SamplerCreationError::ReductionModeWithCompare => {
+                    "a min/max reduction mode can't be used with a compare operation"
+                }
+                SamplerCreationError::CustomBorderColorsFeatureNotEnabled => {
+                    "the `custom_border_colors` feature is not enabled"
+                }
+                SamplerCreationError::CustomBorderColorWithoutFormatFeatureNotEnabled => {
+                    "the `custom_border_color_without_format` feature is not enabled"
+                }
             }
         )
     }
@@ -814,6 +1385,7 @@ impl From<Error> for SamplerCreationError {
 
 #[cfg(test)]
 mod tests {
+    use crate::format::Format;
     use crate::sampler;
 
     #[test]
@@ -1051,4 +1623,63 @@ mod tests {
             _ => panic!(),
         }
     }
+
+    #[test]
+    fn filter_cubic_extension() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let r = sampler::Sampler::new(
+            device,
+            sampler::Filter::Cubic,
+            sampler::Filter::Cubic,
+            sampler::MipmapMode::Nearest,
+            sampler::SamplerAddressMode::Repeat,
+            sampler::SamplerAddressMode::Repeat,
+            sampler::SamplerAddressMode::Repeat,
+            1.0,
+            1.0,
+            0.0,
+            2.0,
+        );
+
+        match r {
+            Err(sampler::SamplerCreationError::FilterCubicExtensionNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn reduction_mode_not_enabled() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let r = sampler::Sampler::start(device)
+            .filter(sampler::Filter::Linear)
+            .reduction_mode(sampler::SamplerReductionMode::Max)
+            .build();
+
+        match r {
+            Err(sampler::SamplerCreationError::SamplerFilterMinmaxNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn custom_border_color_not_enabled() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let r = sampler::Sampler::start(device)
+            .address_mode(sampler::SamplerAddressMode::ClampToBorder(
+                sampler::BorderColor::FloatTransparentBlack,
+            ))
+            .custom_border_color(
+                sampler::CustomBorderColor::Float([1.0, 0.0, 1.0, 1.0]),
+                Some(Format::R8G8B8A8_UNORM),
+            )
+            .build();
+
+        match r {
+            Err(sampler::SamplerCreationError::CustomBorderColorsFeatureNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
 }
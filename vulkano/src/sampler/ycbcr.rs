@@ -0,0 +1,673 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Conversion from sampled YCbCr image data to RGB shader data.
+//!
+//! A sampler YCbCr conversion is an object that assists a sampler when converting from YCbCr
+//! formats and/or YCbCr texel input data. It is used to read frames of video data within a shader,
+//! possibly to apply it as texture on a rendered primitive. Sampler YCbCr conversion can only be
+//! used with certain formats, and conversely, some formats require the use of a sampler YCbCr
+//! conversion to be sampled at all.
+//!
+//! A sampler YCbCr conversion can only be used with a combined image sampler descriptor in a
+//! descriptor set. The conversion must be attached on both the image view and sampler in the
+//! descriptor, and the sampler must be included in the descriptor set layout as an immutable
+//! sampler.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! let conversion = SamplerYcbcrConversion::start(device.clone(), Format::G8_B8R8_2PLANE_420_UNORM)
+//!     .with_ycbcr_model(SamplerYcbcrModelConversion::Ycbcr709)
+//!     .build()?;
+//! let sampler = Sampler::ycbcr(conversion.clone())?;
+//! let image_view = ImageView::start(image)
+//!     .with_sampler_ycbcr_conversion(conversion)
+//!     .build()?;
+//!
+//! let mut desc = DescriptorSetDesc::from_requirements(entry_point.descriptor_requirements());
+//! desc[0].set_immutable_samplers(0, [sampler]);
+//! ```
+//!
+//! On Android, a conversion can also be created for the external format of an
+//! `AHardwareBuffer`, such as the frames produced by a camera or a media decoder, with
+//! [`SamplerYcbcrConversion::start_android_external`].
+
+use crate::check_errors;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::format::Format;
+use crate::format::FormatFeatures;
+use crate::image::view::ComponentMapping;
+use crate::memory::AndroidHardwareBufferProperties;
+use crate::sampler::Filter;
+use crate::Error;
+use crate::OomError;
+use crate::Version;
+use crate::VulkanObject;
+use std::error;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::Arc;
+
+/// Describes how sampled image data should converted from a YCbCr representation to an RGB one.
+pub struct SamplerYcbcrConversion {
+    handle: ash::vk::SamplerYcbcrConversion,
+    device: Arc<Device>,
+
+    format: Option<Format>,
+    external_format: u64,
+    ycbcr_model: SamplerYcbcrModelConversion,
+    ycbcr_range: SamplerYcbcrRange,
+    component_mapping: ComponentMapping,
+    chroma_offset: [ChromaLocation; 2],
+    chroma_filter: Filter,
+    force_explicit_reconstruction: bool,
+}
+
+impl SamplerYcbcrConversion {
+    /// Begins building a `SamplerYcbcrConversion` for images of `format`.
+    ///
+    /// The [`sampler_ycbcr_conversion`](crate::device::Features::sampler_ycbcr_conversion)
+    /// feature must be enabled on the device.
+    #[inline]
+    pub fn start(device: Arc<Device>, format: Format) -> SamplerYcbcrConversionBuilder {
+        SamplerYcbcrConversionBuilder {
+            device,
+            format: Some(format),
+            external_format: 0,
+            external_format_features: None,
+            ycbcr_model: SamplerYcbcrModelConversion::RgbIdentity,
+            ycbcr_range: SamplerYcbcrRange::ItuFull,
+            component_mapping: ComponentMapping::default(),
+            chroma_offset: [ChromaLocation::CositedEven; 2],
+            chroma_filter: Filter::Nearest,
+            force_explicit_reconstruction: false,
+        }
+    }
+
+    /// Begins building a `SamplerYcbcrConversion` for the external format of an Android hardware
+    /// buffer, described by `properties`.
+    ///
+    /// The conversion model, range, component mapping and chroma offsets default to the values
+    /// suggested by the implementation in `properties`. If `properties.format` is `Some`, prefer
+    /// [`start`](SamplerYcbcrConversion::start) with that format instead.
+    ///
+    /// The `android_external_memory_android_hardware_buffer` extension and the
+    /// [`sampler_ycbcr_conversion`](crate::device::Features::sampler_ycbcr_conversion) feature
+    /// must be enabled on the device.
+    #[inline]
+    pub fn start_android_external(
+        device: Arc<Device>,
+        properties: &AndroidHardwareBufferProperties,
+    ) -> SamplerYcbcrConversionBuilder {
+        SamplerYcbcrConversionBuilder {
+            device,
+            format: None,
+            external_format: properties.external_format,
+            external_format_features: Some(properties.format_features),
+            ycbcr_model: properties.suggested_ycbcr_model,
+            ycbcr_range: properties.suggested_ycbcr_range,
+            component_mapping: properties.suggested_component_mapping,
+            chroma_offset: properties.suggested_chroma_offset,
+            chroma_filter: Filter::Nearest,
+            force_explicit_reconstruction: false,
+        }
+    }
+
+    /// Returns the format that the conversion was created for, or `None` if it was created for
+    /// an external format.
+    #[inline]
+    pub fn format(&self) -> Option<Format> {
+        self.format
+    }
+
+    /// Returns the Android external format that the conversion was created for, if any.
+    #[inline]
+    pub fn external_format(&self) -> Option<u64> {
+        if self.external_format != 0 {
+            Some(self.external_format)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the conversion model that is applied.
+    #[inline]
+    pub fn ycbcr_model(&self) -> SamplerYcbcrModelConversion {
+        self.ycbcr_model
+    }
+
+    /// Returns the range of the encoded values.
+    #[inline]
+    pub fn ycbcr_range(&self) -> SamplerYcbcrRange {
+        self.ycbcr_range
+    }
+
+    /// Returns the component mapping that is applied before the conversion.
+    #[inline]
+    pub fn component_mapping(&self) -> ComponentMapping {
+        self.component_mapping
+    }
+
+    /// Returns the location of the chroma samples relative to the luma samples, for the x and y
+    /// coordinates.
+    #[inline]
+    pub fn chroma_offset(&self) -> [ChromaLocation; 2] {
+        self.chroma_offset
+    }
+
+    /// Returns the filter that is used to reconstruct the chroma components.
+    #[inline]
+    pub fn chroma_filter(&self) -> Filter {
+        self.chroma_filter
+    }
+
+    /// Returns whether reconstruction is forced to be explicit.
+    #[inline]
+    pub fn force_explicit_reconstruction(&self) -> bool {
+        self.force_explicit_reconstruction
+    }
+}
+
+unsafe impl DeviceOwned for SamplerYcbcrConversion {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+unsafe impl VulkanObject for SamplerYcbcrConversion {
+    type Object = ash::vk::SamplerYcbcrConversion;
+
+    #[inline]
+    fn internal_object(&self) -> ash::vk::SamplerYcbcrConversion {
+        self.handle
+    }
+}
+
+impl fmt::Debug for SamplerYcbcrConversion {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<Vulkan sampler YCbCr conversion {:?}>", self.handle)
+    }
+}
+
+impl PartialEq for SamplerYcbcrConversion {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.handle == other.handle && self.device == other.device
+    }
+}
+
+impl Eq for SamplerYcbcrConversion {}
+
+impl Drop for SamplerYcbcrConversion {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            let fns = self.device.fns();
+
+            if self.device.api_version() >= Version::V1_1 {
+                fns.v1_1.destroy_sampler_ycbcr_conversion(
+                    self.device.internal_object(),
+                    self.handle,
+                    ptr::null(),
+                );
+            } else {
+                fns.khr_sampler_ycbcr_conversion
+                    .destroy_sampler_ycbcr_conversion_khr(
+                        self.device.internal_object(),
+                        self.handle,
+                        ptr::null(),
+                    );
+            }
+        }
+    }
+}
+
+/// Builder for a `SamplerYcbcrConversion`.
+#[derive(Debug)]
+pub struct SamplerYcbcrConversionBuilder {
+    device: Arc<Device>,
+    format: Option<Format>,
+    external_format: u64,
+    external_format_features: Option<FormatFeatures>,
+    ycbcr_model: SamplerYcbcrModelConversion,
+    ycbcr_range: SamplerYcbcrRange,
+    component_mapping: ComponentMapping,
+    chroma_offset: [ChromaLocation; 2],
+    chroma_filter: Filter,
+    force_explicit_reconstruction: bool,
+}
+
+impl SamplerYcbcrConversionBuilder {
+    /// Sets the conversion model that is applied.
+    ///
+    /// The default value is `RgbIdentity`.
+    #[inline]
+    pub fn with_ycbcr_model(mut self, ycbcr_model: SamplerYcbcrModelConversion) -> Self {
+        self.ycbcr_model = ycbcr_model;
+        self
+    }
+
+    /// Sets the range of the encoded values.
+    ///
+    /// The default value is `ItuFull`.
+    #[inline]
+    pub fn with_ycbcr_range(mut self, ycbcr_range: SamplerYcbcrRange) -> Self {
+        self.ycbcr_range = ycbcr_range;
+        self
+    }
+
+    /// Sets the component mapping that is applied before the conversion.
+    ///
+    /// The default value is the identity mapping.
+    #[inline]
+    pub fn with_component_mapping(mut self, component_mapping: ComponentMapping) -> Self {
+        self.component_mapping = component_mapping;
+        self
+    }
+
+    /// Sets the location of the chroma samples relative to the luma samples, for the x and y
+    /// coordinates.
+    ///
+    /// The default value is `CositedEven` for both coordinates.
+    #[inline]
+    pub fn with_chroma_offset(mut self, chroma_offset: [ChromaLocation; 2]) -> Self {
+        self.chroma_offset = chroma_offset;
+        self
+    }
+
+    /// Sets the filter that is used to reconstruct the chroma components.
+    ///
+    /// The default value is `Nearest`.
+    #[inline]
+    pub fn with_chroma_filter(mut self, chroma_filter: Filter) -> Self {
+        self.chroma_filter = chroma_filter;
+        self
+    }
+
+    /// Sets whether reconstruction is forced to be explicit.
+    ///
+    /// The default value is `false`.
+    #[inline]
+    pub fn with_force_explicit_reconstruction(mut self, enabled: bool) -> Self {
+        self.force_explicit_reconstruction = enabled;
+        self
+    }
+
+    /// Builds the `SamplerYcbcrConversion`.
+    pub fn build(self) -> Result<Arc<SamplerYcbcrConversion>, SamplerYcbcrConversionCreationError> {
+        let device = self.device;
+
+        if !device.enabled_features().sampler_ycbcr_conversion {
+            return Err(SamplerYcbcrConversionCreationError::FeatureNotEnabled {
+                feature: "sampler_ycbcr_conversion",
+                reason: "tried to create a SamplerYcbcrConversion",
+            });
+        }
+
+        if self.external_format != 0
+            && !device
+                .enabled_extensions()
+                .android_external_memory_android_hardware_buffer
+        {
+            return Err(SamplerYcbcrConversionCreationError::ExtensionNotEnabled {
+                extension: "android_external_memory_android_hardware_buffer",
+                reason: "tried to create a SamplerYcbcrConversion for an external format",
+            });
+        }
+
+        let potential_format_features = match (self.format, self.external_format_features) {
+            (Some(format), _) => {
+                let format_properties = format.properties(device.physical_device());
+                let linear = format_properties.linear_tiling_features;
+                let optimal = format_properties.optimal_tiling_features;
+
+                (
+                    linear.midpoint_chroma_samples || optimal.midpoint_chroma_samples,
+                    linear.cosited_chroma_samples || optimal.cosited_chroma_samples,
+                    linear.sampled_image_ycbcr_conversion_linear_filter
+                        || optimal.sampled_image_ycbcr_conversion_linear_filter,
+                    linear.sampled_image_ycbcr_conversion_chroma_reconstruction_explicit_forceable
+                        || optimal
+                            .sampled_image_ycbcr_conversion_chroma_reconstruction_explicit_forceable,
+                )
+            }
+            // Images of external formats always use optimal tiling, and the features of the
+            // format are those reported for the hardware buffer.
+            (None, Some(features)) => (
+                features.midpoint_chroma_samples,
+                features.cosited_chroma_samples,
+                features.sampled_image_ycbcr_conversion_linear_filter,
+                features.sampled_image_ycbcr_conversion_chroma_reconstruction_explicit_forceable,
+            ),
+            (None, None) => unreachable!(),
+        };
+        let (midpoint, cosited, linear_filter, explicit_forceable) = potential_format_features;
+
+        if !(midpoint || cosited) {
+            return Err(SamplerYcbcrConversionCreationError::FormatNotSupported);
+        }
+
+        for &offset in &self.chroma_offset {
+            let supported = match offset {
+                ChromaLocation::CositedEven => cosited,
+                ChromaLocation::Midpoint => midpoint,
+            };
+
+            if !supported {
+                return Err(SamplerYcbcrConversionCreationError::FormatChromaOffsetNotSupported);
+            }
+        }
+
+        if self.chroma_filter == Filter::Linear && !linear_filter {
+            return Err(SamplerYcbcrConversionCreationError::FormatLinearFilterNotSupported);
+        }
+
+        if self.force_explicit_reconstruction && !explicit_forceable {
+            return Err(
+                SamplerYcbcrConversionCreationError::FormatForceExplicitReconstructionNotSupported,
+            );
+        }
+
+        let handle = unsafe {
+            let mut external_format_info = if self.external_format != 0 {
+                Some(ash::vk::ExternalFormatANDROID {
+                    external_format: self.external_format,
+                    ..Default::default()
+                })
+            } else {
+                None
+            };
+
+            let mut infos = ash::vk::SamplerYcbcrConversionCreateInfo {
+                format: self
+                    .format
+                    .map_or(ash::vk::Format::UNDEFINED, |format| format.into()),
+                ycbcr_model: self.ycbcr_model.into(),
+                ycbcr_range: self.ycbcr_range.into(),
+                components: self.component_mapping.into(),
+                x_chroma_offset: self.chroma_offset[0].into(),
+                y_chroma_offset: self.chroma_offset[1].into(),
+                chroma_filter: self.chroma_filter.into(),
+                force_explicit_reconstruction: self.force_explicit_reconstruction
+                    as ash::vk::Bool32,
+                ..Default::default()
+            };
+
+            if let Some(info) = external_format_info.as_mut() {
+                info.p_next = infos.p_next as *mut _;
+                infos.p_next = info as *const _ as *const _;
+            }
+
+            let mut output = MaybeUninit::uninit();
+            let fns = device.fns();
+
+            if device.api_version() >= Version::V1_1 {
+                check_errors(fns.v1_1.create_sampler_ycbcr_conversion(
+                    device.internal_object(),
+                    &infos,
+                    ptr::null(),
+                    output.as_mut_ptr(),
+                ))?;
+            } else {
+                check_errors(
+                    fns.khr_sampler_ycbcr_conversion
+                        .create_sampler_ycbcr_conversion_khr(
+                            device.internal_object(),
+                            &infos,
+                            ptr::null(),
+                            output.as_mut_ptr(),
+                        ),
+                )?;
+            }
+
+            output.assume_init()
+        };
+
+        Ok(Arc::new(SamplerYcbcrConversion {
+            handle,
+            device,
+
+            format: self.format,
+            external_format: self.external_format,
+            ycbcr_model: self.ycbcr_model,
+            ycbcr_range: self.ycbcr_range,
+            component_mapping: self.component_mapping,
+            chroma_offset: self.chroma_offset,
+            chroma_filter: self.chroma_filter,
+            force_explicit_reconstruction: self.force_explicit_reconstruction,
+        }))
+    }
+}
+
+/// Error that can happen when creating a `SamplerYcbcrConversion`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SamplerYcbcrConversionCreationError {
+    FeatureNotEnabled {
+        feature: &'static str,
+        reason: &'static str,
+    },
+
+    ExtensionNotEnabled {
+        extension: &'static str,
+        reason: &'static str,
+    },
+
+    /// The format doesn't support one of the chroma offsets.
+    FormatChromaOffsetNotSupported,
+
+    /// Forcing explicit reconstruction was requested, but the format doesn't support it.
+    FormatForceExplicitReconstructionNotSupported,
+
+    /// A linear chroma filter was requested, but the format doesn't support it.
+    FormatLinearFilterNotSupported,
+
+    /// The format doesn't support sampler YCbCr conversion.
+    FormatNotSupported,
+
+    /// Not enough memory.
+    OomError(OomError),
+}
+
+impl error::Error for SamplerYcbcrConversionCreationError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::OomError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SamplerYcbcrConversionCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Self::FeatureNotEnabled { feature, reason } => {
+                write!(fmt, "the feature {} must be enabled: {}", feature, reason)
+            }
+            Self::ExtensionNotEnabled { extension, reason } => {
+                write!(
+                    fmt,
+                    "the extension {} must be enabled: {}",
+                    extension, reason
+                )
+            }
+            Self::FormatChromaOffsetNotSupported => {
+                write!(fmt, "the format doesn't support one of the chroma offsets")
+            }
+            Self::FormatForceExplicitReconstructionNotSupported => write!(
+                fmt,
+                "forcing explicit reconstruction was requested, but the format doesn't support it",
+            ),
+            Self::FormatLinearFilterNotSupported => write!(
+                fmt,
+                "a linear chroma filter was requested, but the format doesn't support it",
+            ),
+            Self::FormatNotSupported => {
+                write!(fmt, "the format doesn't support sampler YCbCr conversion")
+            }
+            Self::OomError(_) => write!(fmt, "not enough memory available"),
+        }
+    }
+}
+
+impl From<OomError> for SamplerYcbcrConversionCreationError {
+    #[inline]
+    fn from(err: OomError) -> SamplerYcbcrConversionCreationError {
+        SamplerYcbcrConversionCreationError::OomError(err)
+    }
+}
+
+impl From<Error> for SamplerYcbcrConversionCreationError {
+    #[inline]
+    fn from(err: Error) -> SamplerYcbcrConversionCreationError {
+        match err {
+            err @ Error::OutOfHostMemory => {
+                SamplerYcbcrConversionCreationError::OomError(OomError::from(err))
+            }
+            err @ Error::OutOfDeviceMemory => {
+                SamplerYcbcrConversionCreationError::OomError(OomError::from(err))
+            }
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+/// The conversion between the color model of the source image and the color model of the shader.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum SamplerYcbcrModelConversion {
+    /// The input values are already in the shader's model, and are passed through unmodified.
+    RgbIdentity = ash::vk::SamplerYcbcrModelConversion::RGB_IDENTITY.as_raw(),
+
+    /// The input values are only range expanded, no other modifications are done.
+    YcbcrIdentity = ash::vk::SamplerYcbcrModelConversion::YCBCR_IDENTITY.as_raw(),
+
+    /// The input values are converted according to the
+    /// [ITU-R BT.709](https://en.wikipedia.org/wiki/Rec._709) standard.
+    Ycbcr709 = ash::vk::SamplerYcbcrModelConversion::YCBCR_709.as_raw(),
+
+    /// The input values are converted according to the
+    /// [ITU-R BT.601](https://en.wikipedia.org/wiki/Rec._601) standard.
+    Ycbcr601 = ash::vk::SamplerYcbcrModelConversion::YCBCR_601.as_raw(),
+
+    /// The input values are converted according to the
+    /// [ITU-R BT.2020](https://en.wikipedia.org/wiki/Rec._2020) standard.
+    Ycbcr2020 = ash::vk::SamplerYcbcrModelConversion::YCBCR_2020.as_raw(),
+}
+
+impl From<SamplerYcbcrModelConversion> for ash::vk::SamplerYcbcrModelConversion {
+    #[inline]
+    fn from(val: SamplerYcbcrModelConversion) -> Self {
+        Self::from_raw(val as i32)
+    }
+}
+
+/// How the numeric range of the input data is converted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum SamplerYcbcrRange {
+    /// The input values cover the full numeric range, and are interpreted according to the ITU
+    /// "full range" rules.
+    ItuFull = ash::vk::SamplerYcbcrRange::ITU_FULL.as_raw(),
+
+    /// The input values cover only a subset of the numeric range, with the remainder reserved as
+    /// headroom/footroom. The values are interpreted according to the ITU "narrow range" rules.
+    ItuNarrow = ash::vk::SamplerYcbcrRange::ITU_NARROW.as_raw(),
+}
+
+impl From<SamplerYcbcrRange> for ash::vk::SamplerYcbcrRange {
+    #[inline]
+    fn from(val: SamplerYcbcrRange) -> Self {
+        Self::from_raw(val as i32)
+    }
+}
+
+/// For formats with chroma subsampling, the location where the chroma components are sampled,
+/// relative to the luma component.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum ChromaLocation {
+    /// The chroma components are sampled at the even luma coordinate.
+    CositedEven = ash::vk::ChromaLocation::COSITED_EVEN.as_raw(),
+
+    /// The chroma components are sampled at the midpoint between the even luma coordinate and
+    /// the next higher odd luma coordinate.
+    Midpoint = ash::vk::ChromaLocation::MIDPOINT.as_raw(),
+}
+
+impl From<ChromaLocation> for ash::vk::ChromaLocation {
+    #[inline]
+    fn from(val: ChromaLocation) -> Self {
+        Self::from_raw(val as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChromaLocation;
+    use super::SamplerYcbcrConversion;
+    use super::SamplerYcbcrConversionCreationError;
+    use super::SamplerYcbcrModelConversion;
+    use super::SamplerYcbcrRange;
+    use crate::format::Format;
+    use crate::format::FormatFeatures;
+    use crate::image::view::ComponentMapping;
+    use crate::memory::AndroidHardwareBufferProperties;
+
+    #[test]
+    fn feature_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let r = SamplerYcbcrConversion::start(device, Format::G8_B8R8_2PLANE_420_UNORM).build();
+
+        match r {
+            Err(SamplerYcbcrConversionCreationError::FeatureNotEnabled {
+                feature: "sampler_ycbcr_conversion",
+                ..
+            }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn android_external_extension_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!(sampler_ycbcr_conversion);
+
+        let properties = AndroidHardwareBufferProperties {
+            allocation_size: 4096,
+            memory_type_bits: 1,
+            format: None,
+            external_format: 1,
+            format_features: FormatFeatures {
+                cosited_chroma_samples: true,
+                ..FormatFeatures::default()
+            },
+            suggested_component_mapping: ComponentMapping::default(),
+            suggested_ycbcr_model: SamplerYcbcrModelConversion::Ycbcr601,
+            suggested_ycbcr_range: SamplerYcbcrRange::ItuNarrow,
+            suggested_chroma_offset: [ChromaLocation::CositedEven; 2],
+        };
+
+        let r = SamplerYcbcrConversion::start_android_external(device, &properties).build();
+
+        match r {
+            Err(SamplerYcbcrConversionCreationError::ExtensionNotEnabled {
+                extension: "android_external_memory_android_hardware_buffer",
+                ..
+            }) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -0,0 +1,391 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Compiling GLSL and HLSL shaders at runtime.
+//!
+//! This module is only available if the `shaderc` feature of vulkano is enabled. It uses the same
+//! compiler, with the same defaults, as the `shader!` macro of `vulkano-shaders`. Shader modules
+//! that are created from the compiled code have the same reflection information as the modules
+//! that the macro generates, so that a shader can be reloaded at runtime, for example while it is
+//! being edited, and behave like the version that was compiled at build time.
+//!
+//! # Example
+//!
+//! ```
+//! # use std::sync::Arc;
+//! # use vulkano::device::Device;
+//! use vulkano::shader::compile::ShaderCompiler;
+//! use vulkano::shader::ShaderStage;
+//! # let device: Arc<Device> = return;
+//!
+//! let compiler = ShaderCompiler::new()
+//!     .unwrap()
+//!     .include_directory("shaders/include")
+//!     .define("MAX_LIGHTS", "16");
+//!
+//! let compiled = compiler
+//!     .compile_file("shaders/lighting.comp", ShaderStage::Compute)
+//!     .unwrap();
+//! let module = compiled.load(device).unwrap();
+//!
+//! // Watch these files to know when the shader must be reloaded.
+//! let files = compiled.includes();
+//! ```
+
+use crate::device::Device;
+use crate::shader::spirv::SpirvError;
+use crate::shader::ShaderCreationError;
+use crate::shader::ShaderModule;
+use crate::shader::ShaderReflection;
+use crate::shader::ShaderStage;
+use crate::Version;
+use shaderc::CompileOptions;
+use shaderc::Compiler;
+use shaderc::EnvVersion;
+use shaderc::IncludeType;
+use shaderc::OptimizationLevel;
+use shaderc::ResolvedInclude;
+use shaderc::ShaderKind;
+use shaderc::SourceLanguage;
+use shaderc::SpirvVersion;
+use shaderc::TargetEnv;
+use std::cell::RefCell;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// The language that a shader is written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShaderLanguage {
+    Glsl,
+    Hlsl,
+}
+
+/// Compiles shader source code to SPIR-V.
+///
+/// The options are set with the builder methods, and apply to all the shaders that are compiled
+/// afterwards.
+pub struct ShaderCompiler {
+    compiler: Compiler,
+    language: ShaderLanguage,
+    entry_point: String,
+    include_directories: Vec<PathBuf>,
+    macro_defines: Vec<(String, String)>,
+    vulkan_version: Version,
+    spirv_version: Option<Version>,
+    optimize: bool,
+}
+
+impl ShaderCompiler {
+    /// Initializes the compiler.
+    ///
+    /// By default, shaders are written in GLSL, have an entry point named `main`, and are
+    /// compiled for Vulkan 1.0 without optimizations, like with the `shader!` macro.
+    pub fn new() -> Result<ShaderCompiler, ShaderCompileError> {
+        let compiler = Compiler::new().ok_or(ShaderCompileError::CompilerUnavailable)?;
+
+        Ok(ShaderCompiler {
+            compiler,
+            language: ShaderLanguage::Glsl,
+            entry_point: "main".to_owned(),
+            include_directories: Vec::new(),
+            macro_defines: Vec::new(),
+            vulkan_version: Version::V1_0,
+            spirv_version: None,
+            optimize: false,
+        })
+    }
+
+    /// Sets the language of the source code.
+    #[inline]
+    pub fn language(mut self, language: ShaderLanguage) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Sets the name of the entry point in the source code.
+    ///
+    /// This is only relevant for HLSL, as the entry point of GLSL shaders is always `main`.
+    #[inline]
+    pub fn entry_point(mut self, name: impl Into<String>) -> Self {
+        self.entry_point = name.into();
+        self
+    }
+
+    /// Adds a directory in which the files of `#include <...>` directives are searched.
+    #[inline]
+    pub fn include_directory(mut self, path: impl Into<PathBuf>) -> Self {
+        self.include_directories.push(path.into());
+        self
+    }
+
+    /// Defines a macro, as if `#define name value` was at the start of the source code.
+    #[inline]
+    pub fn define(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.macro_defines.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the version of Vulkan that the shaders are compiled for.
+    ///
+    /// Versions that are more recent than what the compiler knows about are treated as the most
+    /// recent version that it supports.
+    #[inline]
+    pub fn target_vulkan_version(mut self, version: Version) -> Self {
+        self.vulkan_version = version;
+        self
+    }
+
+    /// Sets the version of SPIR-V to generate. By default, this is the version that corresponds
+    /// to the target Vulkan version.
+    #[inline]
+    pub fn target_spirv_version(mut self, version: Version) -> Self {
+        self.spirv_version = Some(version);
+        self
+    }
+
+    /// Sets whether the generated code is optimized for performance.
+    ///
+    /// Optimizing can remove the names of unused variables and specialization constants.
+    #[inline]
+    pub fn optimize(mut self, enable: bool) -> Self {
+        self.optimize = enable;
+        self
+    }
+
+    /// Compiles a shader that is read from the file at `path`.
+    ///
+    /// Relative `#include "..."` directives are resolved relative to the directory of the file.
+    pub fn compile_file(
+        &self,
+        path: impl AsRef<Path>,
+        stage: ShaderStage,
+    ) -> Result<CompiledShader, ShaderCompileError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(ShaderCompileError::IoError)?;
+        let mut compiled = self.compile_impl(&source, stage, path)?;
+        compiled.includes.insert(0, path.to_owned());
+        Ok(compiled)
+    }
+
+    /// Compiles a shader from its source code.
+    ///
+    /// `source_name` is used in error messages, and relative `#include "..."` directives are
+    /// resolved relative to its directory.
+    #[inline]
+    pub fn compile(
+        &self,
+        source: &str,
+        stage: ShaderStage,
+        source_name: &str,
+    ) -> Result<CompiledShader, ShaderCompileError> {
+        self.compile_impl(source, stage, Path::new(source_name))
+    }
+
+    fn compile_impl(
+        &self,
+        source: &str,
+        stage: ShaderStage,
+        source_path: &Path,
+    ) -> Result<CompiledShader, ShaderCompileError> {
+        let includes = RefCell::new(Vec::new());
+        let mut options = CompileOptions::new().ok_or(ShaderCompileError::CompilerUnavailable)?;
+
+        options.set_source_language(match self.language {
+            ShaderLanguage::Glsl => SourceLanguage::GLSL,
+            ShaderLanguage::Hlsl => SourceLanguage::HLSL,
+        });
+        options.set_target_env(TargetEnv::Vulkan, env_version(self.vulkan_version) as u32);
+
+        if let Some(version) = self.spirv_version {
+            options.set_target_spirv(spirv_version(version)?);
+        }
+
+        if self.optimize {
+            options.set_optimization_level(OptimizationLevel::Performance);
+        }
+
+        for (name, value) in &self.macro_defines {
+            options.add_macro_definition(name, Some(value));
+        }
+
+        options.set_include_callback(|requested, include_type, contained_within, _depth| {
+            let requested = Path::new(requested);
+            let relative_dir = match include_type {
+                IncludeType::Relative => Path::new(contained_within).parent(),
+                IncludeType::Standard => None,
+            };
+            let resolved = relative_dir
+                .into_iter()
+                .chain(self.include_directories.iter().map(PathBuf::as_path))
+                .map(|dir| dir.join(requested))
+                .find(|path| path.is_file())
+                .ok_or_else(|| format!("file `{}` not found", requested.display()))?;
+            let content = fs::read_to_string(&resolved)
+                .map_err(|err| format!("failed to read `{}`: {}", resolved.display(), err))?;
+            includes.borrow_mut().push(resolved.clone());
+
+            Ok(ResolvedInclude {
+                resolved_name: resolved.to_string_lossy().into_owned(),
+                content,
+            })
+        });
+
+        let artifact = self
+            .compiler
+            .compile_into_spirv(
+                source,
+                shader_kind(stage),
+                &source_path.to_string_lossy(),
+                &self.entry_point,
+                Some(&options),
+            )
+            .map_err(|err| ShaderCompileError::CompilationFailed(err.to_string()))?;
+
+        // Drops the callback, which borrows `includes`.
+        drop(options);
+
+        Ok(CompiledShader {
+            words: artifact.as_binary().to_vec(),
+            warnings: artifact.get_warning_messages(),
+            includes: includes.into_inner(),
+        })
+    }
+}
+
+/// The result of compiling a shader with a `ShaderCompiler`.
+#[derive(Clone, Debug)]
+pub struct CompiledShader {
+    words: Vec<u32>,
+    warnings: String,
+    includes: Vec<PathBuf>,
+}
+
+impl CompiledShader {
+    /// Returns the SPIR-V code of the shader.
+    #[inline]
+    pub fn words(&self) -> &[u32] {
+        &self.words
+    }
+
+    /// Returns the warnings that the compiler emitted, or an empty string if there were none.
+    #[inline]
+    pub fn warnings(&self) -> &str {
+        &self.warnings
+    }
+
+    /// Returns the files that the shader was built from: the source file if it was compiled with
+    /// `compile_file`, and all the files that it included.
+    #[inline]
+    pub fn includes(&self) -> &[PathBuf] {
+        &self.includes
+    }
+
+    /// Returns the reflection information of the shader.
+    #[inline]
+    pub fn reflect(&self) -> Result<ShaderReflection, SpirvError> {
+        ShaderReflection::from_words(&self.words)
+    }
+
+    /// Creates a shader module from the compiled code.
+    #[inline]
+    pub fn load(&self, device: Arc<Device>) -> Result<Arc<ShaderModule>, ShaderCreationError> {
+        // Safe because the code was generated by the compiler.
+        unsafe { ShaderModule::from_words(device, &self.words) }
+    }
+}
+
+fn shader_kind(stage: ShaderStage) -> ShaderKind {
+    match stage {
+        ShaderStage::Vertex => ShaderKind::Vertex,
+        ShaderStage::TessellationControl => ShaderKind::TessControl,
+        ShaderStage::TessellationEvaluation => ShaderKind::TessEvaluation,
+        ShaderStage::Geometry => ShaderKind::Geometry,
+        ShaderStage::Fragment => ShaderKind::Fragment,
+        ShaderStage::Compute => ShaderKind::Compute,
+        ShaderStage::Raygen => ShaderKind::RayGeneration,
+        ShaderStage::AnyHit => ShaderKind::AnyHit,
+        ShaderStage::ClosestHit => ShaderKind::ClosestHit,
+        ShaderStage::Miss => ShaderKind::Miss,
+        ShaderStage::Intersection => ShaderKind::Intersection,
+        ShaderStage::Callable => ShaderKind::Callable,
+    }
+}
+
+fn env_version(version: Version) -> EnvVersion {
+    if version >= Version::V1_2 {
+        EnvVersion::Vulkan1_2
+    } else if version >= Version::V1_1 {
+        EnvVersion::Vulkan1_1
+    } else {
+        EnvVersion::Vulkan1_0
+    }
+}
+
+fn spirv_version(version: Version) -> Result<SpirvVersion, ShaderCompileError> {
+    Ok(match (version.major, version.minor) {
+        (1, 0) => SpirvVersion::V1_0,
+        (1, 1) => SpirvVersion::V1_1,
+        (1, 2) => SpirvVersion::V1_2,
+        (1, 3) => SpirvVersion::V1_3,
+        (1, 4) => SpirvVersion::V1_4,
+        (1, 5) => SpirvVersion::V1_5,
+        _ => return Err(ShaderCompileError::SpirvVersionNotSupported(version)),
+    })
+}
+
+/// Error that can happen when compiling a shader.
+#[derive(Debug)]
+pub enum ShaderCompileError {
+    /// The compiler reported errors in the source code.
+    CompilationFailed(String),
+
+    /// The compiler could not be initialized.
+    CompilerUnavailable,
+
+    /// The source file could not be read.
+    IoError(io::Error),
+
+    /// The compiler can't generate the requested SPIR-V version.
+    SpirvVersionNotSupported(Version),
+}
+
+impl error::Error for ShaderCompileError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ShaderCompileError::IoError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ShaderCompileError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            ShaderCompileError::CompilationFailed(ref message) => {
+                write!(fmt, "the shader failed to compile: {}", message)
+            }
+            ShaderCompileError::CompilerUnavailable => {
+                write!(fmt, "the shader compiler could not be initialized")
+            }
+            ShaderCompileError::IoError(_) => write!(fmt, "the source file could not be read"),
+            ShaderCompileError::SpirvVersionNotSupported(version) => write!(
+                fmt,
+                "the shader compiler can't generate SPIR-V version {}",
+                version
+            ),
+        }
+    }
+}
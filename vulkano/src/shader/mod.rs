@@ -16,10 +16,17 @@
 //! The vulkano library can parse and introspect SPIR-V code, but it does not fully validate the
 //! code. You are encouraged to use the `vulkano-shaders` crate that will generate Rust code that
 //! wraps around vulkano's shaders API.
+//!
+//! For shaders that are loaded at runtime, the information that vulkano extracts from the code is
+//! available as a [`ShaderReflection`], either from an existing module with
+//! [`ShaderModule::reflect`] or directly from the SPIR-V words with
+//! [`ShaderReflection::from_words`]. This is the same information that vulkano uses to check
+//! pipelines against their shaders.
 
 use crate::check_errors;
 use crate::descriptor_set::layout::DescriptorType;
 use crate::device::Device;
+use crate::device::DeviceOwned;
 use crate::format::Format;
 use crate::image::view::ImageViewType;
 use crate::pipeline::input_assembly::PrimitiveTopology;
@@ -31,6 +38,7 @@ use crate::OomError;
 use crate::Version;
 use crate::VulkanObject;
 use fnv::FnvHashMap;
+use fnv::FnvHasher;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::error;
@@ -39,6 +47,8 @@ use std::ffi::CStr;
 use std::ffi::CString;
 use std::fmt;
 use std::fmt::Display;
+use std::hash::Hash;
+use std::hash::Hasher;
 use std::mem;
 use std::mem::MaybeUninit;
 use std::ops::BitOr;
@@ -46,6 +56,8 @@ use std::ops::Range;
 use std::ptr;
 use std::sync::Arc;
 
+#[cfg(feature = "shaderc")]
+pub mod compile;
 pub mod reflect;
 pub mod spirv;
 
@@ -57,7 +69,7 @@ include!(concat!(env!("OUT_DIR"), "/spirv_reqs.rs"));
 pub struct ShaderModule {
     handle: ash::vk::ShaderModule,
     device: Arc<Device>,
-    entry_points: HashMap<String, EntryPointInfo>,
+    reflection: ShaderReflection,
 }
 
 impl ShaderModule {
@@ -67,20 +79,15 @@ impl ShaderModule {
     /// # Safety
     ///
     /// - The SPIR-V code is not validated beyond the minimum needed to extract the information.
+    ///
+    /// If [object deduplication](Device::set_object_deduplication) is enabled on the device, an
+    /// existing module with the same code may be returned.
     pub unsafe fn from_words(
         device: Arc<Device>,
         words: &[u32],
     ) -> Result<Arc<ShaderModule>, ShaderCreationError> {
-        let spirv = Spirv::new(words)?;
-
-        Self::from_words_with_data(
-            device,
-            words,
-            spirv.version(),
-            reflect::spirv_capabilities(&spirv),
-            reflect::spirv_extensions(&spirv),
-            reflect::entry_points(&spirv, false),
-        )
+        let reflection = ShaderReflection::from_words(words)?;
+        Self::from_words_with_reflection(device, words, reflection)
     }
 
     /// As `from_words`, but takes a slice of bytes.
@@ -118,6 +125,23 @@ impl ShaderModule {
         spirv_extensions: impl IntoIterator<Item = &'a str>,
         entry_points: impl IntoIterator<Item = (String, EntryPointInfo)>,
     ) -> Result<Arc<ShaderModule>, ShaderCreationError> {
+        let reflection = ShaderReflection {
+            spirv_version,
+            spirv_capabilities: spirv_capabilities.into_iter().cloned().collect(),
+            spirv_extensions: spirv_extensions.into_iter().map(str::to_owned).collect(),
+            entry_points: collect_entry_points(entry_points),
+        };
+
+        Self::from_words_with_reflection(device, words, reflection)
+    }
+
+    unsafe fn from_words_with_reflection(
+        device: Arc<Device>,
+        words: &[u32],
+        reflection: ShaderReflection,
+    ) -> Result<Arc<ShaderModule>, ShaderCreationError> {
+        let spirv_version = reflection.spirv_version;
+
         if let Err(reason) = check_spirv_version(&device, spirv_version) {
             return Err(ShaderCreationError::SpirvVersionNotSupported {
                 version: spirv_version,
@@ -125,7 +149,7 @@ impl ShaderModule {
             });
         }
 
-        for capability in spirv_capabilities {
+        for capability in &reflection.spirv_capabilities {
             if let Err(reason) = check_spirv_capability(&device, capability.clone()) {
                 return Err(ShaderCreationError::SpirvCapabilityNotSupported {
                     capability: capability.clone(),
@@ -134,15 +158,44 @@ impl ShaderModule {
             }
         }
 
-        for extension in spirv_extensions {
+        for extension in &reflection.spirv_extensions {
             if let Err(reason) = check_spirv_extension(&device, extension) {
                 return Err(ShaderCreationError::SpirvExtensionNotSupported {
-                    extension: extension.to_owned(),
+                    extension: extension.clone(),
                     reason,
                 });
             }
         }
 
+        if !device.object_deduplication() {
+            return Self::create(device, words, reflection);
+        }
+
+        let mut hasher = FnvHasher::default();
+        words.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let mut cache = device.shader_module_cache().lock().unwrap();
+        cache.retain(|(_, _, module)| module.strong_count() > 0);
+
+        if let Some(module) = cache
+            .iter()
+            .filter(|(other_hash, other_words, _)| *other_hash == hash && other_words == words)
+            .find_map(|(_, _, module)| module.upgrade())
+        {
+            return Ok(module);
+        }
+
+        let module = Self::create(device.clone(), words, reflection)?;
+        cache.push((hash, words.to_vec(), Arc::downgrade(&module)));
+        Ok(module)
+    }
+
+    unsafe fn create(
+        device: Arc<Device>,
+        words: &[u32],
+        reflection: ShaderReflection,
+    ) -> Result<Arc<ShaderModule>, ShaderCreationError> {
         let handle = {
             let infos = ash::vk::ShaderModuleCreateInfo {
                 flags: ash::vk::ShaderModuleCreateFlags::empty(),
@@ -165,7 +218,7 @@ impl ShaderModule {
         Ok(Arc::new(ShaderModule {
             handle,
             device,
-            entry_points: entry_points.into_iter().collect(),
+            reflection,
         }))
     }
 
@@ -198,12 +251,75 @@ impl ShaderModule {
 
     /// Returns information about the entry point with the provided name. Returns `None` if no entry
     /// point with that name exists in the shader module.
+    ///
+    /// A module can contain several entry points with the same name but for different shader
+    /// stages. In that case, this returns the one whose stage comes first in the order of
+    /// [`ShaderStage`], which is the order of the stages in a pipeline: vertex, tessellation
+    /// control, tessellation evaluation, geometry, fragment, then compute. Use
+    /// [`entry_point_for_stage`](Self::entry_point_for_stage) to select another one.
     pub fn entry_point<'a>(&'a self, name: &str) -> Option<EntryPoint<'a>> {
-        self.entry_points.get(name).map(|info| EntryPoint {
+        self.reflection
+            .entry_point(name)
+            .map(|info| self.make_entry_point(name, info))
+    }
+
+    /// Returns information about the entry point with the provided name and shader stage.
+    /// Returns `None` if no such entry point exists in the shader module.
+    pub fn entry_point_for_stage<'a>(
+        &'a self,
+        name: &str,
+        stage: ShaderStage,
+    ) -> Option<EntryPoint<'a>> {
+        self.reflection
+            .entry_point_for_stage(name, stage)
+            .map(|info| self.make_entry_point(name, info))
+    }
+
+    /// Returns all the entry points of the shader module, in no particular order.
+    #[inline]
+    pub fn entry_points(&self) -> impl ExactSizeIterator<Item = EntryPoint> {
+        let len = self
+            .reflection
+            .entry_points
+            .values()
+            .map(|infos| infos.len())
+            .sum();
+        let iter = self
+            .reflection
+            .entry_points
+            .iter()
+            .flat_map(move |(name, infos)| {
+                infos
+                    .values()
+                    .map(move |info| self.make_entry_point(name, info))
+            });
+
+        EntryPointsIter { iter, len }
+    }
+
+    #[inline]
+    fn make_entry_point<'a>(&'a self, name: &str, info: &'a EntryPointInfo) -> EntryPoint<'a> {
+        EntryPoint {
             module: self,
             name: CString::new(name).unwrap(),
             info,
-        })
+        }
+    }
+
+    /// Returns the information that was extracted from the SPIR-V code of the module.
+    ///
+    /// If the module was created with `from_words_with_data`, this is the information that was
+    /// provided by the caller.
+    #[inline]
+    pub fn reflect(&self) -> &ShaderReflection {
+        &self.reflection
+    }
+}
+
+unsafe impl DeviceOwned for ShaderModule {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
     }
 }
 
@@ -317,6 +433,126 @@ impl Display for ShaderSupportError {
     }
 }
 
+/// The information that vulkano extracts from a SPIR-V module.
+///
+/// All the fields are public, so that engines that load shaders at runtime can inspect the
+/// interface of a shader, for example to build descriptor set layouts or vertex buffers that
+/// match it, without having to parse the SPIR-V code a second time.
+#[derive(Clone, Debug)]
+pub struct ShaderReflection {
+    /// The version of SPIR-V that the module is written in.
+    pub spirv_version: Version,
+
+    /// The SPIR-V capabilities that the module uses.
+    pub spirv_capabilities: Vec<Capability>,
+
+    /// The SPIR-V extensions that the module uses.
+    pub spirv_extensions: Vec<String>,
+
+    /// The entry points of the module, indexed by name and then by shader stage.
+    pub entry_points: HashMap<String, HashMap<ShaderStage, EntryPointInfo>>,
+}
+
+impl ShaderReflection {
+    /// Parses SPIR-V code and extracts its information, without creating a shader module.
+    pub fn from_words(words: &[u32]) -> Result<ShaderReflection, SpirvError> {
+        Ok(ShaderReflection::from_spirv(&Spirv::new(words)?))
+    }
+
+    /// Extracts the information of already parsed SPIR-V code.
+    ///
+    /// If the module contains several entry points, the descriptors of each entry point are
+    /// only the ones that are statically used by it. Otherwise, all the descriptors that are
+    /// declared in the module are considered used.
+    pub fn from_spirv(spirv: &Spirv) -> ShaderReflection {
+        let exact_interface = spirv.iter_entry_point().len() > 1;
+
+        ShaderReflection {
+            spirv_version: spirv.version(),
+            spirv_capabilities: reflect::spirv_capabilities(spirv).cloned().collect(),
+            spirv_extensions: reflect::spirv_extensions(spirv)
+                .map(str::to_owned)
+                .collect(),
+            entry_points: collect_entry_points(reflect::entry_points(spirv, exact_interface)),
+        }
+    }
+
+    /// Returns the information of the entry point with the provided name.
+    ///
+    /// If there are several for different shader stages, returns the one whose stage comes first
+    /// in the order of [`ShaderStage`], as
+    /// [`ShaderModule::entry_point`](ShaderModule::entry_point) does.
+    #[inline]
+    pub fn entry_point(&self, name: &str) -> Option<&EntryPointInfo> {
+        self.entry_points
+            .get(name)?
+            .iter()
+            .min_by_key(|(&stage, _)| stage as u32)
+            .map(|(_, info)| info)
+    }
+
+    /// Returns the information of the entry point with the provided name and shader stage.
+    #[inline]
+    pub fn entry_point_for_stage(&self, name: &str, stage: ShaderStage) -> Option<&EntryPointInfo> {
+        self.entry_points.get(name)?.get(&stage)
+    }
+
+    /// Returns true if the module accesses buffers through `PhysicalStorageBuffer` pointers,
+    /// whose values are obtained with
+    /// [`BufferAccess::raw_device_address`](crate::buffer::BufferAccess::raw_device_address).
+    ///
+    /// Such modules can only be used on a device that has the `buffer_device_address` feature
+    /// enabled.
+    #[inline]
+    pub fn uses_physical_storage_buffers(&self) -> bool {
+        self.spirv_capabilities
+            .iter()
+            .any(|capability| matches!(capability, Capability::PhysicalStorageBufferAddresses))
+    }
+}
+
+// Iterator returned by `ShaderModule::entry_points`, which knows the number of entry points
+// across all the names.
+struct EntryPointsIter<I> {
+    iter: I,
+    len: usize,
+}
+
+impl<I> Iterator for EntryPointsIter<I>
+where
+    I: Iterator,
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let item = self.iter.next()?;
+        self.len -= 1;
+        Some(item)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<I> ExactSizeIterator for EntryPointsIter<I> where I: Iterator {}
+
+fn collect_entry_points(
+    entry_points: impl IntoIterator<Item = (String, EntryPointInfo)>,
+) -> HashMap<String, HashMap<ShaderStage, EntryPointInfo>> {
+    let mut map: HashMap<String, HashMap<ShaderStage, EntryPointInfo>> = HashMap::new();
+
+    for (name, info) in entry_points {
+        map.entry(name)
+            .or_default()
+            .insert(ShaderStage::from(info.execution), info);
+    }
+
+    map
+}
+
 /// The information associated with a single entry point in a shader.
 #[derive(Clone, Debug)]
 pub struct EntryPointInfo {
@@ -403,26 +639,136 @@ impl<'a> EntryPoint<'a> {
 #[derive(Clone, Copy, Debug)]
 pub enum ShaderExecution {
     Vertex,
-    TessellationControl,
-    TessellationEvaluation,
+    TessellationControl(TessellationShaderExecution),
+    TessellationEvaluation(TessellationShaderExecution),
     Geometry(GeometryShaderExecution),
     Fragment,
-    Compute,
+    Compute(ComputeShaderExecution),
+}
+
+/// The mode in which a compute shader executes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComputeShaderExecution {
+    /// The number of invocations in each dimension of a workgroup, before specialization.
+    pub local_size: [u32; 3],
+
+    /// For each dimension of `local_size`, the id of the specialization constant that the value
+    /// comes from, if any.
+    pub local_size_specialization: [Option<u32>; 3],
+}
+
+impl ComputeShaderExecution {
+    /// Returns the local size after the specialization constants described by `entries` and
+    /// `data` have been applied.
+    ///
+    /// A dimension whose specialization constant has no entry keeps its default value. An error
+    /// is returned if the entry of such a constant doesn't have a size of 4, or points outside of
+    /// `data`.
+    pub fn specialized_local_size(
+        &self,
+        entries: &[SpecializationMapEntry],
+        data: &[u8],
+    ) -> Result<[u32; 3], LocalSizeSpecializationError> {
+        let mut local_size = self.local_size;
+
+        for (size, constant_id) in local_size.iter_mut().zip(&self.local_size_specialization) {
+            let constant_id = match constant_id {
+                Some(constant_id) => *constant_id,
+                None => continue,
+            };
+
+            let entry = match entries
+                .iter()
+                .find(|entry| entry.constant_id == constant_id)
+            {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            if entry.size != 4 {
+                return Err(LocalSizeSpecializationError::SizeMismatch {
+                    constant_id,
+                    size: entry.size,
+                });
+            }
+
+            let offset = entry.offset as usize;
+            let bytes = offset
+                .checked_add(4)
+                .and_then(|end| data.get(offset..end))
+                .ok_or(LocalSizeSpecializationError::DataOutOfRange {
+                    constant_id,
+                    offset: entry.offset,
+                    data_size: data.len(),
+                })?;
+            *size = u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+
+        Ok(local_size)
+    }
+}
+
+/// Error that can happen when applying specialization constants to the local size of a compute
+/// shader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LocalSizeSpecializationError {
+    /// The entry of a local size constant doesn't have the size of a 32-bit integer.
+    SizeMismatch { constant_id: u32, size: usize },
+
+    /// The entry of a local size constant points outside of the specialization data.
+    DataOutOfRange {
+        constant_id: u32,
+        offset: u32,
+        data_size: usize,
+    },
+}
+
+impl Error for LocalSizeSpecializationError {}
+
+impl Display for LocalSizeSpecializationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::SizeMismatch { constant_id, size } => write!(
+                f,
+                "the local size specialization constant {} has an entry of {} bytes instead of 4",
+                constant_id, size,
+            ),
+            Self::DataOutOfRange {
+                constant_id,
+                offset,
+                data_size,
+            } => write!(
+                f,
+                "the entry of the local size specialization constant {} reads 4 bytes at offset {}, but the specialization data is only {} bytes",
+                constant_id, offset, data_size,
+            ),
+        }
+    }
 }
 
-/*#[derive(Clone, Copy, Debug)]
+/// The mode in which a tessellation control or evaluation shader executes.
+///
+/// The execution modes can be declared in either of the two tessellation shaders of a pipeline,
+/// or in both, so each of them may be missing from a single shader.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct TessellationShaderExecution {
-    pub num_output_vertices: u32,
+    /// The number of vertices in each output patch of the control shader.
+    pub output_vertices: Option<u32>,
+
+    /// The type of primitive that the tessellator generates.
+    pub subdivision: Option<TessellationShaderSubdivision>,
+
+    /// Whether the tessellator generates points instead of primitives.
     pub point_mode: bool,
-    pub subdivision: TessellationShaderSubdivision,
 }
 
+/// The type of primitive that is generated by the tessellator.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum TessellationShaderSubdivision {
     Triangles,
     Quads,
     Isolines,
-}*/
+}
 
 /// The mode in which a geometry shader executes.
 #[derive(Clone, Copy, Debug)]
@@ -595,9 +941,15 @@ impl Display for DescriptorRequirementsIncompatible {
 }
 
 /// The requirements imposed by a shader on a specialization constant.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct SpecializationConstantRequirements {
     pub size: DeviceSize,
+
+    /// The type of the constant.
+    pub ty: SpecializationConstantType,
+
+    /// The name of the constant, if the shader contains debug names.
+    pub name: Option<Cow<'static, str>>,
 }
 
 /// Trait for types that contain specialization data for shaders.
@@ -701,6 +1053,260 @@ impl From<SpecializationMapEntry> for ash::vk::SpecializationMapEntry {
     }
 }
 
+/// Specialization constant values that are looked up by name at runtime.
+///
+/// The [`SpecializationConstants`] trait requires the layout of the constants to be known at
+/// compile time, which is what the structs generated by `vulkano-shaders` provide. For shaders
+/// that are only loaded at runtime, a `SpecializationMap` can be built from the reflection data
+/// of an entry point instead. Constants are looked up by the name given to them in the shader,
+/// and the type of each value is checked against the type of the constant.
+///
+/// Constants that are not set keep the default value that was given in the shader.
+///
+/// # Example
+///
+/// ```
+/// # use vulkano::shader::EntryPoint;
+/// use vulkano::shader::SpecializationMap;
+/// # let entry_point: EntryPoint = return;
+///
+/// let mut map = SpecializationMap::new(&entry_point);
+/// map.set("WORKGROUP_SIZE", 64u32).unwrap();
+/// map.set("USE_FAST_PATH", true).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct SpecializationMap {
+    requirements: FnvHashMap<u32, SpecializationConstantRequirements>,
+    entries: Vec<SpecializationMapEntry>,
+    data: Vec<u8>,
+}
+
+impl SpecializationMap {
+    /// Builds a new `SpecializationMap` for the specialization constants of `entry_point`, with
+    /// no constants set.
+    pub fn new(entry_point: &EntryPoint) -> SpecializationMap {
+        SpecializationMap {
+            requirements: entry_point
+                .specialization_constant_requirements()
+                .map(|(constant_id, reqs)| (constant_id, reqs.clone()))
+                .collect(),
+            entries: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+
+    /// Sets the value of the constant named `name`.
+    ///
+    /// If several constants have the same name, all of them are set. The shader must have been
+    /// compiled with debug names for the constants to be found.
+    pub fn set(
+        &mut self,
+        name: &str,
+        value: impl Into<SpecializationConstant>,
+    ) -> Result<(), SpecializationMapError> {
+        let value = value.into();
+        let mut constant_ids: Vec<u32> = self
+            .requirements
+            .iter()
+            .filter(|(_, reqs)| reqs.name.as_deref() == Some(name))
+            .map(|(&constant_id, _)| constant_id)
+            .collect();
+
+        if constant_ids.is_empty() {
+            return Err(SpecializationMapError::ConstantNotFound);
+        }
+
+        constant_ids.sort_unstable();
+
+        for constant_id in constant_ids {
+            self.set_by_id(constant_id, value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets the value of the constant with the `SpecId` decoration `constant_id`.
+    pub fn set_by_id(
+        &mut self,
+        constant_id: u32,
+        value: impl Into<SpecializationConstant>,
+    ) -> Result<(), SpecializationMapError> {
+        let value = value.into();
+        let reqs = self
+            .requirements
+            .get(&constant_id)
+            .ok_or(SpecializationMapError::ConstantNotFound)?;
+
+        if value.ty() != reqs.ty {
+            return Err(SpecializationMapError::TypeMismatch {
+                constant_id,
+                expected: reqs.ty,
+                provided: value.ty(),
+            });
+        }
+
+        let bytes = value.to_bytes();
+
+        if let Some(entry) = self
+            .entries
+            .iter()
+            .find(|entry| entry.constant_id == constant_id)
+        {
+            let offset = entry.offset as usize;
+            self.data[offset..offset + bytes.len()].copy_from_slice(&bytes);
+        } else {
+            self.entries.push(SpecializationMapEntry {
+                constant_id,
+                offset: self.data.len() as u32,
+                size: bytes.len(),
+            });
+            self.data.extend_from_slice(&bytes);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the map entries of the constants that have been set.
+    #[inline]
+    pub fn entries(&self) -> &[SpecializationMapEntry] {
+        &self.entries
+    }
+
+    /// Returns the data of the constants that have been set, at the offsets given by
+    /// `entries`.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// The type of a specialization constant, as declared in the shader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SpecializationConstantType {
+    /// A boolean, stored as a 32-bit integer.
+    Bool,
+
+    /// An integer with the given width in bits.
+    Int { width: u32, signed: bool },
+
+    /// A floating-point number with the given width in bits.
+    Float { width: u32 },
+
+    /// A vector, matrix, array or struct. These can't be set with a `SpecializationMap`.
+    Composite,
+}
+
+/// The value of a single specialization constant.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpecializationConstant {
+    Bool(bool),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+}
+
+impl SpecializationConstant {
+    /// Returns the type of constant that this value can be assigned to.
+    #[inline]
+    pub fn ty(&self) -> SpecializationConstantType {
+        match *self {
+            Self::Bool(_) => SpecializationConstantType::Bool,
+            Self::I32(_) => SpecializationConstantType::Int {
+                width: 32,
+                signed: true,
+            },
+            Self::U32(_) => SpecializationConstantType::Int {
+                width: 32,
+                signed: false,
+            },
+            Self::I64(_) => SpecializationConstantType::Int {
+                width: 64,
+                signed: true,
+            },
+            Self::U64(_) => SpecializationConstantType::Int {
+                width: 64,
+                signed: false,
+            },
+            Self::F32(_) => SpecializationConstantType::Float { width: 32 },
+            Self::F64(_) => SpecializationConstantType::Float { width: 64 },
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        match *self {
+            Self::Bool(val) => (val as ash::vk::Bool32).to_ne_bytes().to_vec(),
+            Self::I32(val) => val.to_ne_bytes().to_vec(),
+            Self::U32(val) => val.to_ne_bytes().to_vec(),
+            Self::I64(val) => val.to_ne_bytes().to_vec(),
+            Self::U64(val) => val.to_ne_bytes().to_vec(),
+            Self::F32(val) => val.to_ne_bytes().to_vec(),
+            Self::F64(val) => val.to_ne_bytes().to_vec(),
+        }
+    }
+}
+
+macro_rules! specialization_constant_from {
+    ($($ty:ty => $variant:ident,)+) => {
+        $(
+            impl From<$ty> for SpecializationConstant {
+                #[inline]
+                fn from(val: $ty) -> Self {
+                    SpecializationConstant::$variant(val)
+                }
+            }
+        )+
+    };
+}
+
+specialization_constant_from! {
+    bool => Bool,
+    i32 => I32,
+    u32 => U32,
+    i64 => I64,
+    u64 => U64,
+    f32 => F32,
+    f64 => F64,
+}
+
+/// Error that can happen when setting a value in a `SpecializationMap`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpecializationMapError {
+    /// The shader has no specialization constant with the given name or id.
+    ConstantNotFound,
+
+    /// The type of the value doesn't match the type of the constant.
+    TypeMismatch {
+        constant_id: u32,
+        expected: SpecializationConstantType,
+        provided: SpecializationConstantType,
+    },
+}
+
+impl Error for SpecializationMapError {}
+
+impl Display for SpecializationMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::ConstantNotFound => write!(
+                f,
+                "the shader has no specialization constant with the given name or id"
+            ),
+            Self::TypeMismatch {
+                constant_id,
+                expected,
+                provided,
+            } => write!(
+                f,
+                "specialization constant {} has type {:?}, but a value of type {:?} was provided",
+                constant_id, expected, provided
+            ),
+        }
+    }
+}
+
 /// Type that contains the definition of an interface between two shader stages, or between
 /// the outside and a shader stage.
 #[derive(Clone, Debug)]
@@ -790,6 +1396,23 @@ pub struct ShaderInterfaceEntry {
     pub format: Format,
     /// Name of the element, or `None` if the name is unknown.
     pub name: Option<Cow<'static, str>>,
+    /// Vertex stream that the element is written to, as declared with the `Stream` decoration.
+    /// Only the outputs of a geometry shader can be written to a stream other than 0.
+    pub stream: u32,
+    /// Where the element is captured while transform feedback is active, as declared with the
+    /// `XfbBuffer`, `XfbStride` and `Offset` decorations, or `None` if it is not captured.
+    pub transform_feedback: Option<TransformFeedbackOutput>,
+}
+
+/// Where an output of a shader is captured by transform feedback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransformFeedbackOutput {
+    /// The transform feedback buffer binding that the output is written to.
+    pub buffer: u32,
+    /// The number of bytes between the start of one vertex and the next in the buffer.
+    pub stride: u32,
+    /// The offset in bytes of the output inside each vertex in the buffer.
+    pub offset: u32,
 }
 
 /// Error that can happen when the interface mismatches between two shader stages.
@@ -864,11 +1487,11 @@ impl From<ShaderExecution> for ShaderStage {
     fn from(val: ShaderExecution) -> Self {
         match val {
             ShaderExecution::Vertex => Self::Vertex,
-            ShaderExecution::TessellationControl => Self::TessellationControl,
-            ShaderExecution::TessellationEvaluation => Self::TessellationEvaluation,
+            ShaderExecution::TessellationControl(_) => Self::TessellationControl,
+            ShaderExecution::TessellationEvaluation(_) => Self::TessellationEvaluation,
             ShaderExecution::Geometry(_) => Self::Geometry,
             ShaderExecution::Fragment => Self::Fragment,
-            ShaderExecution::Compute => Self::Compute,
+            ShaderExecution::Compute(_) => Self::Compute,
         }
     }
 }
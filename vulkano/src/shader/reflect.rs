@@ -17,12 +17,13 @@ use crate::{
     pipeline::layout::PipelineLayoutPcRange,
     shader::{
         spirv::{
-            Capability, Decoration, Dim, ExecutionMode, ExecutionModel, Id, ImageFormat,
+            BuiltIn, Capability, Decoration, Dim, ExecutionMode, ExecutionModel, Id, ImageFormat,
             Instruction, Spirv, StorageClass,
         },
-        DescriptorRequirements, EntryPointInfo, GeometryShaderExecution, GeometryShaderInput,
-        ShaderExecution, ShaderInterface, ShaderInterfaceEntry, ShaderStage,
-        SpecializationConstantRequirements,
+        ComputeShaderExecution, DescriptorRequirements, EntryPointInfo, GeometryShaderExecution,
+        GeometryShaderInput, ShaderExecution, ShaderInterface, ShaderInterfaceEntry, ShaderStage,
+        SpecializationConstantRequirements, SpecializationConstantType,
+        TessellationShaderExecution, TessellationShaderSubdivision, TransformFeedbackOutput,
     },
 };
 use fnv::FnvHashMap;
@@ -113,9 +114,13 @@ fn shader_execution(
     match execution_model {
         ExecutionModel::Vertex => ShaderExecution::Vertex,
 
-        ExecutionModel::TessellationControl => ShaderExecution::TessellationControl,
+        ExecutionModel::TessellationControl => {
+            ShaderExecution::TessellationControl(tessellation_execution(spirv, function_id))
+        }
 
-        ExecutionModel::TessellationEvaluation => ShaderExecution::TessellationEvaluation,
+        ExecutionModel::TessellationEvaluation => {
+            ShaderExecution::TessellationEvaluation(tessellation_execution(spirv, function_id))
+        }
 
         ExecutionModel::Geometry => {
             let input = spirv
@@ -145,7 +150,9 @@ fn shader_execution(
 
         ExecutionModel::Fragment => ShaderExecution::Fragment,
 
-        ExecutionModel::GLCompute => ShaderExecution::Compute,
+        ExecutionModel::GLCompute => {
+            ShaderExecution::Compute(compute_execution(spirv, function_id))
+        }
 
         ExecutionModel::Kernel
         | ExecutionModel::TaskNV
@@ -161,6 +168,146 @@ fn shader_execution(
     }
 }
 
+/// Extracts the `TessellationShaderExecution` for the entry point `function_id` from `spirv`.
+fn tessellation_execution(spirv: &Spirv, function_id: Id) -> TessellationShaderExecution {
+    let mut execution = TessellationShaderExecution::default();
+
+    for instruction in spirv.iter_execution_mode() {
+        let mode = match instruction {
+            Instruction::ExecutionMode {
+                entry_point, mode, ..
+            } if *entry_point == function_id => mode,
+            _ => continue,
+        };
+
+        match *mode {
+            ExecutionMode::OutputVertices { vertex_count } => {
+                execution.output_vertices = Some(vertex_count)
+            }
+            ExecutionMode::Triangles => {
+                execution.subdivision = Some(TessellationShaderSubdivision::Triangles)
+            }
+            ExecutionMode::Quads => {
+                execution.subdivision = Some(TessellationShaderSubdivision::Quads)
+            }
+            ExecutionMode::Isolines => {
+                execution.subdivision = Some(TessellationShaderSubdivision::Isolines)
+            }
+            ExecutionMode::PointMode => execution.point_mode = true,
+            _ => (),
+        }
+    }
+
+    execution
+}
+
+/// Extracts the `ComputeShaderExecution` for the entry point `function_id` from `spirv`.
+fn compute_execution(spirv: &Spirv, function_id: Id) -> ComputeShaderExecution {
+    let mut execution = ComputeShaderExecution {
+        local_size: [1, 1, 1],
+        local_size_specialization: [None; 3],
+    };
+
+    for instruction in spirv.iter_execution_mode() {
+        match *instruction {
+            Instruction::ExecutionMode {
+                entry_point,
+                mode:
+                    ExecutionMode::LocalSize {
+                        x_size,
+                        y_size,
+                        z_size,
+                    },
+                ..
+            } if entry_point == function_id => {
+                execution.local_size = [x_size, y_size, z_size];
+            }
+            Instruction::ExecutionModeId {
+                entry_point,
+                mode:
+                    ExecutionMode::LocalSizeId {
+                        x_size,
+                        y_size,
+                        z_size,
+                    },
+                ..
+            } if entry_point == function_id => {
+                set_local_size_from_constants(spirv, &mut execution, &[x_size, y_size, z_size]);
+            }
+            _ => (),
+        }
+    }
+
+    // A constant decorated with the `WorkgroupSize` built-in overrides the execution mode.
+    let workgroup_size = spirv
+        .iter_global()
+        .find_map(|instruction| match instruction {
+            Instruction::ConstantComposite {
+                result_id,
+                constituents,
+                ..
+            }
+            | Instruction::SpecConstantComposite {
+                result_id,
+                constituents,
+                ..
+            } if spirv.id(*result_id).iter_decoration().any(|instruction| {
+                matches!(
+                    instruction,
+                    Instruction::Decorate {
+                        decoration: Decoration::BuiltIn {
+                            built_in: BuiltIn::WorkgroupSize,
+                        },
+                        ..
+                    }
+                )
+            }) =>
+            {
+                Some(constituents)
+            }
+            _ => None,
+        });
+
+    if let Some(constituents) = workgroup_size {
+        set_local_size_from_constants(spirv, &mut execution, constituents);
+    }
+
+    execution
+}
+
+// Sets the local size of `execution` to the values of the scalar constants `ids`.
+fn set_local_size_from_constants(
+    spirv: &Spirv,
+    execution: &mut ComputeShaderExecution,
+    ids: &[Id],
+) {
+    for (index, &id) in ids.iter().enumerate().take(3) {
+        match spirv.id(id).instruction() {
+            Instruction::Constant { value, .. } => {
+                execution.local_size[index] = value[0];
+                execution.local_size_specialization[index] = None;
+            }
+            Instruction::SpecConstant { value, .. } => {
+                execution.local_size[index] = value[0];
+                execution.local_size_specialization[index] = spirv
+                    .id(id)
+                    .iter_decoration()
+                    .find_map(|instruction| match instruction {
+                        Instruction::Decorate {
+                            decoration:
+                                Decoration::SpecId {
+                                    specialization_constant_id,
+                                },
+                            ..
+                        } => Some(*specialization_constant_id),
+                        _ => None,
+                    });
+            }
+            _ => (),
+        }
+    }
+}
+
 /// Extracts the `DescriptorRequirements` for the entry point `function_id` from `spirv`.
 fn descriptor_requirements(
     spirv: &Spirv,
@@ -565,6 +712,12 @@ fn descriptor_requirements_of(
             ..Default::default()
         }),
 
+        &Instruction::TypeAccelerationStructureKHR { .. } => Some(DescriptorRequirements {
+            descriptor_types: vec![DescriptorType::AccelerationStructure],
+            descriptor_count: 1,
+            ..Default::default()
+        }),
+
         &Instruction::TypeArray {
             element_type,
             length,
@@ -681,7 +834,32 @@ fn specialization_constant_requirements(
                             _ => size_of_type(spirv, result_type_id)
                                 .expect("Found runtime-sized specialization constant"),
                         };
-                        Some((constant_id, SpecializationConstantRequirements { size }))
+                        let ty = match *spirv.id(result_type_id).instruction() {
+                            Instruction::TypeBool { .. } => SpecializationConstantType::Bool,
+                            Instruction::TypeInt {
+                                width, signedness, ..
+                            } => SpecializationConstantType::Int {
+                                width,
+                                signed: signedness != 0,
+                            },
+                            Instruction::TypeFloat { width, .. } => {
+                                SpecializationConstantType::Float { width }
+                            }
+                            _ => SpecializationConstantType::Composite,
+                        };
+                        let name =
+                            spirv.id(result_id).iter_name().find_map(
+                                |instruction| match instruction {
+                                    Instruction::Name { name, .. } => {
+                                        Some(Cow::Owned(name.to_owned()))
+                                    }
+                                    _ => None,
+                                },
+                            );
+                        Some((
+                            constant_id,
+                            SpecializationConstantRequirements { size, ty, name },
+                        ))
                     }),
                 _ => None,
             }
@@ -738,12 +916,27 @@ fn shader_interface(
                     )
                 });
 
+            let stream = id_info
+                .iter_decoration()
+                .find_map(|instruction| match instruction {
+                    Instruction::Decorate {
+                        decoration: Decoration::Stream { stream_number },
+                        ..
+                    } => Some(*stream_number),
+                    _ => None,
+                })
+                .unwrap_or(0);
+
+            let transform_feedback = transform_feedback_output(spirv, result_id);
+
             let (format, num_locations) = format_of_type(spirv, result_type_id, ignore_first_array);
             assert!(num_locations >= 1);
             Some(ShaderInterfaceEntry {
                 location: location..location + num_locations,
                 format,
                 name,
+                stream,
+                transform_feedback,
             })
         })
         .collect();
@@ -773,6 +966,36 @@ fn shader_interface(
     ShaderInterface { elements }
 }
 
+/// Returns where the variable `id` is captured by transform feedback, or `None` if it is not
+/// decorated with `XfbBuffer`, `XfbStride` and `Offset`.
+fn transform_feedback_output(spirv: &Spirv, id: Id) -> Option<TransformFeedbackOutput> {
+    let (mut buffer, mut stride, mut offset) = (None, None, None);
+
+    for instruction in spirv.id(id).iter_decoration() {
+        match instruction {
+            Instruction::Decorate {
+                decoration: Decoration::XfbBuffer { xfb_buffer_number },
+                ..
+            } => buffer = Some(*xfb_buffer_number),
+            Instruction::Decorate {
+                decoration: Decoration::XfbStride { xfb_stride },
+                ..
+            } => stride = Some(*xfb_stride),
+            Instruction::Decorate {
+                decoration: Decoration::Offset { byte_offset },
+                ..
+            } => offset = Some(*byte_offset),
+            _ => (),
+        }
+    }
+
+    Some(TransformFeedbackOutput {
+        buffer: buffer?,
+        stride: stride?,
+        offset: offset?,
+    })
+}
+
 /// Returns the size of a type, or `None` if its size cannot be determined.
 fn size_of_type(spirv: &Spirv, id: Id) -> Option<DeviceSize> {
     let id_info = spirv.id(id);
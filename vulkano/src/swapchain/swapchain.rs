@@ -50,6 +50,7 @@ use crate::Error;
 use crate::OomError;
 use crate::Success;
 use crate::VulkanObject;
+use smallvec::SmallVec;
 use std::error;
 use std::fmt;
 use std::mem;
@@ -247,6 +248,7 @@ pub struct Swapchain<W> {
     fullscreen_exclusive: FullscreenExclusive,
     fullscreen_exclusive_held: AtomicBool,
     clipped: bool,
+    view_formats: Vec<Format>,
 }
 
 struct ImageEntry {
@@ -276,6 +278,7 @@ impl<W> Swapchain<W> {
             present_mode: PresentMode::Fifo,
             fullscreen_exclusive: FullscreenExclusive::Default,
             clipped: true,
+            view_formats: Vec::new(),
 
             old_swapchain: None,
         }
@@ -304,6 +307,7 @@ impl<W> Swapchain<W> {
             present_mode: self.present_mode,
             fullscreen_exclusive: self.fullscreen_exclusive,
             clipped: self.clipped,
+            view_formats: self.view_formats.clone(),
 
             old_swapchain: Some(self.clone()),
         }
@@ -375,6 +379,14 @@ impl<W> Swapchain<W> {
         self.clipped
     }
 
+    /// Returns the formats that views of the images can have, as passed when creating the
+    /// swapchain. An empty list means that the images can only be viewed with the format of the
+    /// swapchain.
+    #[inline]
+    pub fn view_formats(&self) -> &[Format] {
+        &self.view_formats
+    }
+
     /// Returns the value of 'fullscreen_exclusive` that was passed when creating the swapchain.
     #[inline]
     pub fn fullscreen_exclusive(&self) -> FullscreenExclusive {
@@ -524,6 +536,7 @@ pub struct SwapchainBuilder<W> {
     present_mode: PresentMode,
     fullscreen_exclusive: FullscreenExclusive,
     clipped: bool,
+    view_formats: Vec<Format>,
 }
 
 impl<W> SwapchainBuilder<W> {
@@ -563,6 +576,7 @@ impl<W> SwapchainBuilder<W> {
             present_mode,
             fullscreen_exclusive,
             clipped,
+            mut view_formats,
         } = self;
 
         assert_eq!(
@@ -648,7 +662,29 @@ impl<W> SwapchainBuilder<W> {
             return Err(SwapchainCreationError::UnsupportedPresentMode);
         }
 
-        let flags = ImageCreateFlags::none();
+        if !view_formats.is_empty() {
+            if !device.enabled_extensions().khr_swapchain_mutable_format {
+                return Err(SwapchainCreationError::MissingExtensionKHRSwapchainMutableFormat);
+            }
+
+            // VUID-VkSwapchainCreateInfoKHR-flags-03168
+            if !view_formats.contains(&format) {
+                view_formats.push(format);
+            }
+
+            // VUID-VkImageFormatListCreateInfo-viewFormatCount-01747
+            if view_formats
+                .iter()
+                .any(|view_format| view_format.compatibility() != format.compatibility())
+            {
+                return Err(SwapchainCreationError::IncompatibleViewFormat);
+            }
+        }
+
+        let flags = ImageCreateFlags {
+            mutable_format: !view_formats.is_empty(),
+            ..ImageCreateFlags::none()
+        };
 
         // check that the physical device supports the swapchain image configuration
         match device.image_format_properties(
@@ -703,11 +739,30 @@ impl<W> SwapchainBuilder<W> {
             });
         }
 
-        let p_next = match surface_full_screen_exclusive_info.as_ref() {
+        let mut p_next = match surface_full_screen_exclusive_info.as_ref() {
             Some(some) => unsafe { mem::transmute(some as *const _) },
             None => ptr::null(),
         };
 
+        let vk_view_formats: SmallVec<[ash::vk::Format; 4]> = view_formats
+            .iter()
+            .map(|&view_format| view_format.into())
+            .collect();
+        let format_list_info = if vk_view_formats.is_empty() {
+            None
+        } else {
+            Some(ash::vk::ImageFormatListCreateInfo {
+                p_next,
+                view_format_count: vk_view_formats.len() as u32,
+                p_view_formats: vk_view_formats.as_ptr(),
+                ..Default::default()
+            })
+        };
+
+        if let Some(info) = format_list_info.as_ref() {
+            p_next = info as *const _ as *const _;
+        }
+
         // Required by the specs.
         assert_ne!(usage, ImageUsage::none());
 
@@ -743,7 +798,11 @@ impl<W> SwapchainBuilder<W> {
 
             let infos = ash::vk::SwapchainCreateInfoKHR {
                 p_next,
-                flags: ash::vk::SwapchainCreateFlagsKHR::empty(),
+                flags: if view_formats.is_empty() {
+                    ash::vk::SwapchainCreateFlagsKHR::empty()
+                } else {
+                    ash::vk::SwapchainCreateFlagsKHR::MUTABLE_FORMAT
+                },
                 surface: surface.internal_object(),
                 min_image_count: num_images,
                 image_format: format.into(),
@@ -812,7 +871,7 @@ impl<W> SwapchainBuilder<W> {
                     array_layers: layers,
                 };
 
-                let img = UnsafeImage::from_raw(
+                let img = UnsafeImage::from_raw_with_view_formats(
                     device.clone(),
                     image,
                     usage,
@@ -821,6 +880,7 @@ impl<W> SwapchainBuilder<W> {
                     dims,
                     SampleCount::Sample1,
                     1,
+                    &view_formats,
                 );
 
                 ImageEntry {
@@ -862,6 +922,7 @@ impl<W> SwapchainBuilder<W> {
             fullscreen_exclusive,
             fullscreen_exclusive_held: AtomicBool::new(fullscreen_exclusive_held),
             clipped,
+            view_formats,
         });
 
         let swapchain_images = unsafe {
@@ -990,6 +1051,20 @@ impl<W> SwapchainBuilder<W> {
         self.clipped = clipped;
         self
     }
+
+    /// Sets the formats that views of the images of the swapchain can have, in addition to the
+    /// format of the swapchain. This makes it possible for example to view the images of an sRGB
+    /// swapchain as UNORM, to write to them from a compute shader.
+    ///
+    /// The formats must all be compatible with the format of the swapchain. A non-empty list
+    /// requires the `khr_swapchain_mutable_format` extension.
+    ///
+    /// The default is an empty list.
+    #[inline]
+    pub fn view_formats(mut self, view_formats: &[Format]) -> Self {
+        self.view_formats = view_formats.to_vec();
+        self
+    }
 }
 
 /// Error that can happen when creation a swapchain.
@@ -1009,6 +1084,10 @@ pub enum SwapchainCreationError {
     MissingExtensionKHRSwapchain,
     /// The `VK_EXT_full_screen_exclusive` extension was not enabled.
     MissingExtensionExtFullScreenExclusive,
+    /// The `VK_KHR_swapchain_mutable_format` extension was not enabled.
+    MissingExtensionKHRSwapchainMutableFormat,
+    /// A format of the list of view formats is not compatible with the format of the swapchain.
+    IncompatibleViewFormat,
     /// Surface mismatch between old and new swapchain.
     OldSwapchainSurfaceMismatch,
     /// The old swapchain has already been used to recreate another one.
@@ -1067,6 +1146,13 @@ impl fmt::Display for SwapchainCreationError {
                 SwapchainCreationError::MissingExtensionExtFullScreenExclusive => {
                     "the `VK_EXT_full_screen_exclusive` extension was not enabled"
                 }
+                SwapchainCreationError::MissingExtensionKHRSwapchainMutableFormat => {
+                    "the `VK_KHR_swapchain_mutable_format` extension was not enabled"
+                }
+                SwapchainCreationError::IncompatibleViewFormat => {
+                    "a format of the list of view formats is not compatible with the format of \
+                     the swapchain"
+                }
                 SwapchainCreationError::OldSwapchainSurfaceMismatch => {
                     "surface mismatch between old and new swapchain"
                 }
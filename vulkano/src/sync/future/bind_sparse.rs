@@ -0,0 +1,777 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::buffer::sys::UnsafeBuffer;
+use crate::buffer::BufferAccess;
+use crate::command_buffer::submit::SubmitAnyBuilder;
+use crate::command_buffer::submit::SubmitBindSparseBatchBuilder;
+use crate::command_buffer::submit::SubmitBindSparseBufferBindBuilder;
+use crate::command_buffer::submit::SubmitBindSparseBuilder;
+use crate::command_buffer::submit::SubmitBindSparseImageBindBuilder;
+use crate::command_buffer::submit::SubmitBindSparseImageOpaqueBindBuilder;
+use crate::command_buffer::submit::SubmitSemaphoresWaitBuilder;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::device::Queue;
+use crate::image::sys::UnsafeImage;
+use crate::image::ImageAccess;
+use crate::image::ImageAspect;
+use crate::image::ImageLayout;
+use crate::memory::DeviceMemory;
+use crate::sync::AccessCheckError;
+use crate::sync::AccessFlags;
+use crate::sync::FlushError;
+use crate::sync::GpuFuture;
+use crate::sync::PipelineStages;
+use crate::sync::Semaphore;
+use crate::DeviceSize;
+use crate::VulkanObject;
+
+/// List of sparse memory binding operations, to submit with
+/// [`GpuFuture::then_bind_sparse`].
+///
+/// The buffers, images and memories are kept alive by the list. Every operation is checked
+/// against the memory requirements of its resource when it is added.
+///
+/// Binding memory to a buffer requires it to have been created with a sparse level. Binding
+/// memory to a region of an image requires it to have been created with the `sparse_residency`
+/// flag, and its block size is given by
+/// [`UnsafeImage::sparse_memory_requirements`](crate::image::sys::UnsafeImage::sparse_memory_requirements).
+#[derive(Debug, Default)]
+pub struct SparseBindInfo {
+    buffer_binds: Vec<SparseBufferBind>,
+    image_opaque_binds: Vec<SparseImageOpaqueBind>,
+    image_binds: Vec<SparseImageBind>,
+    // Memory that was unbound by the operations, and that must stay alive until they are done.
+    retained_memory: Vec<Arc<DeviceMemory>>,
+}
+
+#[derive(Debug)]
+struct SparseBufferBind {
+    buffer: Arc<UnsafeBuffer>,
+    offset: DeviceSize,
+    size: DeviceSize,
+    memory: Option<(Arc<DeviceMemory>, DeviceSize)>,
+}
+
+#[derive(Debug)]
+struct SparseImageOpaqueBind {
+    image: Arc<UnsafeImage>,
+    offset: DeviceSize,
+    size: DeviceSize,
+    memory: Option<(Arc<DeviceMemory>, DeviceSize)>,
+    metadata: bool,
+}
+
+#[derive(Debug)]
+struct SparseImageBind {
+    image: Arc<UnsafeImage>,
+    aspect: ImageAspect,
+    mip_level: u32,
+    array_layer: u32,
+    offset: [u32; 3],
+    extent: [u32; 3],
+    memory: Option<(Arc<DeviceMemory>, DeviceSize)>,
+}
+
+impl SparseBindInfo {
+    /// Builds an empty list of operations.
+    #[inline]
+    pub fn new() -> SparseBindInfo {
+        SparseBindInfo::default()
+    }
+
+    /// Returns true if the list doesn't contain any operation.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.buffer_binds.is_empty()
+            && self.image_opaque_binds.is_empty()
+            && self.image_binds.is_empty()
+    }
+
+    /// Keeps `memory` alive until the operations have been executed, for memory that they unbind.
+    #[inline]
+    pub(crate) fn retain_memory(&mut self, memory: Arc<DeviceMemory>) {
+        self.retained_memory.push(memory);
+    }
+
+    /// Binds `size` bytes of `memory` starting at `memory_offset` to the `offset..offset + size`
+    /// range of `buffer`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `buffer` and `memory` don't belong to the same device.
+    ///
+    pub fn bind_buffer(
+        &mut self,
+        buffer: Arc<UnsafeBuffer>,
+        offset: DeviceSize,
+        size: DeviceSize,
+        memory: Arc<DeviceMemory>,
+        memory_offset: DeviceSize,
+    ) -> Result<(), SparseBindError> {
+        let requirements = check_buffer_range(&buffer, offset, size)?;
+        check_memory_range(&requirements, &memory, memory_offset, size)?;
+
+        self.buffer_binds.push(SparseBufferBind {
+            buffer,
+            offset,
+            size,
+            memory: Some((memory, memory_offset)),
+        });
+        Ok(())
+    }
+
+    /// Removes the memory of the `offset..offset + size` range of `buffer`.
+    pub fn unbind_buffer(
+        &mut self,
+        buffer: Arc<UnsafeBuffer>,
+        offset: DeviceSize,
+        size: DeviceSize,
+    ) -> Result<(), SparseBindError> {
+        check_buffer_range(&buffer, offset, size)?;
+
+        self.buffer_binds.push(SparseBufferBind {
+            buffer,
+            offset,
+            size,
+            memory: None,
+        });
+        Ok(())
+    }
+
+    /// Binds `size` bytes of `memory` starting at `memory_offset` to the `offset..offset + size`
+    /// range of the opaque memory of `image`, which is how images without sparse residency are
+    /// bound, and how the mip tail of sparse resident images is bound.
+    ///
+    /// If `metadata` is true, the range is in the metadata aspect of the image instead.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `image` and `memory` don't belong to the same device.
+    ///
+    pub fn bind_image_opaque(
+        &mut self,
+        image: Arc<UnsafeImage>,
+        offset: DeviceSize,
+        size: DeviceSize,
+        memory: Arc<DeviceMemory>,
+        memory_offset: DeviceSize,
+        metadata: bool,
+    ) -> Result<(), SparseBindError> {
+        let requirements = check_image_opaque_range(&image, offset, size, metadata)?;
+        check_memory_range(&requirements, &memory, memory_offset, size)?;
+
+        self.image_opaque_binds.push(SparseImageOpaqueBind {
+            image,
+            offset,
+            size,
+            memory: Some((memory, memory_offset)),
+            metadata,
+        });
+        Ok(())
+    }
+
+    /// Removes the memory of the `offset..offset + size` range of the opaque memory of `image`.
+    pub fn unbind_image_opaque(
+        &mut self,
+        image: Arc<UnsafeImage>,
+        offset: DeviceSize,
+        size: DeviceSize,
+    ) -> Result<(), SparseBindError> {
+        check_image_opaque_range(&image, offset, size, false)?;
+
+        self.image_opaque_binds.push(SparseImageOpaqueBind {
+            image,
+            offset,
+            size,
+            memory: None,
+            metadata: false,
+        });
+        Ok(())
+    }
+
+    /// Binds memory starting at `memory_offset` to a region of `aspect` of a mip level and array
+    /// layer of `image`. The region is in texels, and must be made of whole sparse blocks except
+    /// where it reaches the edge of the mip level.
+    ///
+    /// The memory needs one block of the alignment of the image's memory requirements for each
+    /// sparse block of the region.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `image` and `memory` don't belong to the same device.
+    ///
+    pub fn bind_image(
+        &mut self,
+        image: Arc<UnsafeImage>,
+        aspect: ImageAspect,
+        mip_level: u32,
+        array_layer: u32,
+        offset: [u32; 3],
+        extent: [u32; 3],
+        memory: Arc<DeviceMemory>,
+        memory_offset: DeviceSize,
+    ) -> Result<(), SparseBindError> {
+        let (requirements, size) =
+            check_image_region(&image, aspect, mip_level, array_layer, offset, extent)?;
+        check_memory_range(&requirements, &memory, memory_offset, size)?;
+
+        self.image_binds.push(SparseImageBind {
+            image,
+            aspect,
+            mip_level,
+            array_layer,
+            offset,
+            extent,
+            memory: Some((memory, memory_offset)),
+        });
+        Ok(())
+    }
+
+    /// Removes the memory of a region of `aspect` of a mip level and array layer of `image`.
+    pub fn unbind_image(
+        &mut self,
+        image: Arc<UnsafeImage>,
+        aspect: ImageAspect,
+        mip_level: u32,
+        array_layer: u32,
+        offset: [u32; 3],
+        extent: [u32; 3],
+    ) -> Result<(), SparseBindError> {
+        check_image_region(&image, aspect, mip_level, array_layer, offset, extent)?;
+
+        self.image_binds.push(SparseImageBind {
+            image,
+            aspect,
+            mip_level,
+            array_layer,
+            offset,
+            extent,
+            memory: None,
+        });
+        Ok(())
+    }
+
+    // Adds all the operations to `batch`.
+    unsafe fn add_to_batch<'a>(&'a self, batch: &mut SubmitBindSparseBatchBuilder<'a>) {
+        for bind in &self.buffer_binds {
+            let mut builder = SubmitBindSparseBufferBindBuilder::new(&bind.buffer);
+            match bind.memory {
+                Some((ref memory, memory_offset)) => {
+                    builder.add_bind(bind.offset, bind.size, memory, memory_offset)
+                }
+                None => builder.add_unbind(bind.offset, bind.size),
+            }
+            batch.add_buffer(builder);
+        }
+
+        for bind in &self.image_opaque_binds {
+            let mut builder = SubmitBindSparseImageOpaqueBindBuilder::new(&bind.image);
+            match bind.memory {
+                Some((ref memory, memory_offset)) => {
+                    builder.add_bind(bind.offset, bind.size, memory, memory_offset, bind.metadata)
+                }
+                None => builder.add_unbind(bind.offset, bind.size),
+            }
+            batch.add_image_opaque(builder);
+        }
+
+        for bind in &self.image_binds {
+            let mut builder = SubmitBindSparseImageBindBuilder::new(&bind.image);
+            let offset = [
+                bind.offset[0] as i32,
+                bind.offset[1] as i32,
+                bind.offset[2] as i32,
+            ];
+            match bind.memory {
+                Some((ref memory, memory_offset)) => builder.add_bind(
+                    bind.aspect,
+                    bind.mip_level,
+                    bind.array_layer,
+                    offset,
+                    bind.extent,
+                    memory,
+                    memory_offset,
+                ),
+                None => builder.add_unbind(
+                    bind.aspect,
+                    bind.mip_level,
+                    bind.array_layer,
+                    offset,
+                    bind.extent,
+                ),
+            }
+            batch.add_image(builder);
+        }
+    }
+
+    fn device(&self) -> Option<&Arc<Device>> {
+        self.buffer_binds
+            .first()
+            .map(|bind| bind.buffer.device())
+            .or_else(|| {
+                self.image_opaque_binds
+                    .first()
+                    .map(|bind| bind.image.device())
+            })
+            .or_else(|| self.image_binds.first().map(|bind| bind.image.device()))
+    }
+}
+
+// Checks that `offset..offset + size` is a valid range of a sparse buffer, and returns the memory
+// requirements of the buffer.
+fn check_buffer_range(
+    buffer: &UnsafeBuffer,
+    offset: DeviceSize,
+    size: DeviceSize,
+) -> Result<ash::vk::MemoryRequirements, SparseBindError> {
+    if buffer.sparse_level().is_none() {
+        return Err(SparseBindError::ResourceNotSparse);
+    }
+
+    let requirements = unsafe {
+        let device = buffer.device();
+        let mut output = MaybeUninit::uninit();
+        device.fns().v1_0.get_buffer_memory_requirements(
+            device.internal_object(),
+            buffer.internal_object(),
+            output.as_mut_ptr(),
+        );
+        output.assume_init()
+    };
+
+    check_resource_range(&requirements, offset, size)?;
+    Ok(requirements)
+}
+
+// Checks that `offset..offset + size` is a valid range of the opaque memory of a sparse image,
+// and returns the memory requirements of the image.
+fn check_image_opaque_range(
+    image: &UnsafeImage,
+    offset: DeviceSize,
+    size: DeviceSize,
+    metadata: bool,
+) -> Result<ash::vk::MemoryRequirements, SparseBindError> {
+    if !image.create_flags().sparse_binding {
+        return Err(SparseBindError::ResourceNotSparse);
+    }
+
+    let requirements = image_memory_requirements(image);
+
+    // The metadata aspect has its own range of offsets, given by its mip tail.
+    if !metadata {
+        check_resource_range(&requirements, offset, size)?;
+    }
+
+    Ok(requirements)
+}
+
+// Checks that a region of an image is valid for sparse binding, and returns the memory
+// requirements of the image along with the number of bytes of memory that the region needs.
+fn check_image_region(
+    image: &UnsafeImage,
+    aspect: ImageAspect,
+    mip_level: u32,
+    array_layer: u32,
+    offset: [u32; 3],
+    extent: [u32; 3],
+) -> Result<(ash::vk::MemoryRequirements, DeviceSize), SparseBindError> {
+    if !image.create_flags().sparse_residency {
+        return Err(SparseBindError::ImageNotSparseResident);
+    }
+
+    if array_layer >= image.dimensions().array_layers() {
+        return Err(SparseBindError::OutOfRange);
+    }
+
+    let mip_dimensions = match image.dimensions().mipmap_dimensions(mip_level) {
+        Some(dimensions) => dimensions.width_height_depth(),
+        None => return Err(SparseBindError::OutOfRange),
+    };
+
+    let aspect_flags = ash::vk::ImageAspectFlags::from(aspect);
+    let granularity = match image
+        .sparse_memory_requirements()
+        .into_iter()
+        .find(|req| ash::vk::ImageAspectFlags::from(req.aspects).contains(aspect_flags))
+    {
+        Some(req) => req.image_granularity,
+        None => return Err(SparseBindError::OutOfRange),
+    };
+
+    let mut num_blocks: DeviceSize = 1;
+
+    for i in 0..3 {
+        if extent[i] == 0 || offset[i] + extent[i] > mip_dimensions[i] {
+            return Err(SparseBindError::OutOfRange);
+        }
+
+        if offset[i] % granularity[i] != 0
+            || (extent[i] % granularity[i] != 0 && offset[i] + extent[i] != mip_dimensions[i])
+        {
+            return Err(SparseBindError::NotAligned);
+        }
+
+        num_blocks *= ((extent[i] + granularity[i] - 1) / granularity[i]) as DeviceSize;
+    }
+
+    let requirements = image_memory_requirements(image);
+    Ok((requirements, num_blocks * requirements.alignment))
+}
+
+fn image_memory_requirements(image: &UnsafeImage) -> ash::vk::MemoryRequirements {
+    unsafe {
+        let device = image.device();
+        let mut output = MaybeUninit::uninit();
+        device.fns().v1_0.get_image_memory_requirements(
+            device.internal_object(),
+            image.internal_object(),
+            output.as_mut_ptr(),
+        );
+        output.assume_init()
+    }
+}
+
+fn check_resource_range(
+    requirements: &ash::vk::MemoryRequirements,
+    offset: DeviceSize,
+    size: DeviceSize,
+) -> Result<(), SparseBindError> {
+    if size == 0 || offset + size > requirements.size {
+        return Err(SparseBindError::OutOfRange);
+    }
+
+    // The size doesn't need to be aligned if the range goes until the end of the resource.
+    if offset % requirements.alignment != 0
+        || (size % requirements.alignment != 0 && offset + size != requirements.size)
+    {
+        return Err(SparseBindError::NotAligned);
+    }
+
+    Ok(())
+}
+
+fn check_memory_range(
+    requirements: &ash::vk::MemoryRequirements,
+    memory: &DeviceMemory,
+    memory_offset: DeviceSize,
+    size: DeviceSize,
+) -> Result<(), SparseBindError> {
+    if requirements.memory_type_bits & (1 << memory.memory_type().id()) == 0 {
+        return Err(SparseBindError::MemoryTypeNotSupported);
+    }
+
+    if memory_offset % requirements.alignment != 0 {
+        return Err(SparseBindError::NotAligned);
+    }
+
+    if memory_offset + size > memory.size() {
+        return Err(SparseBindError::MemoryOutOfRange);
+    }
+
+    Ok(())
+}
+
+/// Builds a new sparse binding future.
+pub fn then_bind_sparse<F>(
+    future: F,
+    queue: Arc<Queue>,
+    binds: SparseBindInfo,
+) -> Result<BindSparseFuture<F>, SparseBindError>
+where
+    F: GpuFuture,
+{
+    assert_eq!(
+        future.device().internal_object(),
+        queue.device().internal_object()
+    );
+    if let Some(device) = binds.device() {
+        assert_eq!(device.internal_object(), queue.device().internal_object());
+    }
+
+    if !queue.family().supports_sparse_binding() {
+        return Err(SparseBindError::QueueFamilyNotSupported);
+    }
+
+    let device = queue.device().clone();
+
+    Ok(BindSparseFuture {
+        previous: future,
+        queue,
+        binds,
+        wait_semaphore: Semaphore::from_pool(device.clone()).unwrap(),
+        semaphore: Semaphore::from_pool(device).unwrap(),
+        submitted: Mutex::new(false),
+        finished: AtomicBool::new(false),
+    })
+}
+
+/// Represents sparse memory being bound after a previous event.
+///
+/// The following operations wait until the memory is bound, even on the same queue.
+#[must_use = "Dropping this object will immediately block the thread until the GPU has finished \
+              processing the submission"]
+pub struct BindSparseFuture<F>
+where
+    F: GpuFuture,
+{
+    previous: F,
+    queue: Arc<Queue>,
+    binds: SparseBindInfo,
+    // Signaled by a previous command buffer submission, so that the binding waits for it.
+    wait_semaphore: Semaphore,
+    // Signaled by the binding, so that the following operations wait for it.
+    semaphore: Semaphore,
+    // True if the binding has already been submitted.
+    // If flush is called multiple times, we want to block so that only one flushing is executed.
+    // Therefore we use a `Mutex<bool>` and not an `AtomicBool`.
+    submitted: Mutex<bool>,
+    finished: AtomicBool,
+}
+
+unsafe impl<F> GpuFuture for BindSparseFuture<F>
+where
+    F: GpuFuture,
+{
+    #[inline]
+    fn cleanup_finished(&mut self) {
+        self.previous.cleanup_finished();
+    }
+
+    #[inline]
+    unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
+        // Flushing the binding, since it must always be submitted before the waiting part.
+        self.flush()?;
+
+        let mut sem = SubmitSemaphoresWaitBuilder::new();
+        sem.add_wait_semaphore(&self.semaphore);
+        Ok(SubmitAnyBuilder::SemaphoresWait(sem))
+    }
+
+    fn flush(&self) -> Result<(), FlushError> {
+        unsafe {
+            let mut submitted = self.submitted.lock().unwrap();
+
+            if *submitted {
+                return Ok(());
+            }
+
+            let mut builder = SubmitBindSparseBuilder::new();
+
+            let mut batch = match self.previous.build_submission()? {
+                SubmitAnyBuilder::Empty => SubmitBindSparseBatchBuilder::new(),
+                SubmitAnyBuilder::SemaphoresWait(sem) => sem.into(),
+                SubmitAnyBuilder::CommandBuffer(mut cb_builder) => {
+                    debug_assert_eq!(cb_builder.num_signal_semaphores(), 0);
+                    cb_builder.add_signal_semaphore(&self.wait_semaphore);
+                    cb_builder.submit(&self.previous.queue().unwrap())?;
+
+                    let mut batch = SubmitBindSparseBatchBuilder::new();
+                    batch.add_wait_semaphore(&self.wait_semaphore);
+                    batch
+                }
+                SubmitAnyBuilder::BindSparse(sparse) => {
+                    // Batches start in order, so the previous bindings are submitted along with
+                    // this one.
+                    builder = sparse;
+                    SubmitBindSparseBatchBuilder::new()
+                }
+                SubmitAnyBuilder::QueuePresent(present) => {
+                    present.submit(&self.previous.queue().unwrap())?;
+                    SubmitBindSparseBatchBuilder::new()
+                }
+            };
+
+            self.binds.add_to_batch(&mut batch);
+            batch.add_signal_semaphore(&self.semaphore);
+            builder.add(batch);
+            builder.submit(&self.queue)?;
+
+            // Only write `true` here in order to try again next time if an error occurs.
+            *submitted = true;
+            Ok(())
+        }
+    }
+
+    #[inline]
+    unsafe fn signal_finished(&self) {
+        debug_assert!(*self.submitted.lock().unwrap());
+        self.finished.store(true, Ordering::SeqCst);
+        self.previous.signal_finished();
+    }
+
+    #[inline]
+    fn queue_change_allowed(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<Arc<Queue>> {
+        Some(self.queue.clone())
+    }
+
+    #[inline]
+    fn check_buffer_access(
+        &self,
+        buffer: &dyn BufferAccess,
+        exclusive: bool,
+        queue: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+        self.previous
+            .check_buffer_access(buffer, exclusive, queue)
+            .map(|_| None)
+    }
+
+    #[inline]
+    fn check_image_access(
+        &self,
+        image: &dyn ImageAccess,
+        layout: ImageLayout,
+        exclusive: bool,
+        queue: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+        self.previous
+            .check_image_access(image, layout, exclusive, queue)
+            .map(|_| None)
+    }
+}
+
+unsafe impl<F> DeviceOwned for BindSparseFuture<F>
+where
+    F: GpuFuture,
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.queue.device()
+    }
+}
+
+impl<F> Drop for BindSparseFuture<F>
+where
+    F: GpuFuture,
+{
+    fn drop(&mut self) {
+        unsafe {
+            if !*self.finished.get_mut() {
+                // TODO: handle errors?
+                self.flush().unwrap();
+                // Block until the queue finished.
+                self.queue.wait().unwrap();
+                self.previous.signal_finished();
+            }
+        }
+    }
+}
+
+/// Error that can happen when adding or submitting sparse binding operations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SparseBindError {
+    /// The queue family of the queue doesn't support sparse binding.
+    QueueFamilyNotSupported,
+    /// The resource wasn't created for sparse binding.
+    ResourceNotSparse,
+    /// Binding regions of an image requires it to have been created with the `sparse_residency`
+    /// flag.
+    ImageNotSparseResident,
+    /// The range or region is outside of the resource, or the aspect, mip level or array layer
+    /// doesn't exist.
+    OutOfRange,
+    /// An offset or size isn't a multiple of the sparse block size.
+    NotAligned,
+    /// The memory type of the memory can't be bound to the resource.
+    MemoryTypeNotSupported,
+    /// The range of memory is outside of the memory.
+    MemoryOutOfRange,
+}
+
+impl error::Error for SparseBindError {}
+
+impl fmt::Display for SparseBindError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                SparseBindError::QueueFamilyNotSupported => {
+                    "the queue family of the queue doesn't support sparse binding"
+                }
+                SparseBindError::ResourceNotSparse => {
+                    "the resource wasn't created for sparse binding"
+                }
+                SparseBindError::ImageNotSparseResident => {
+                    "binding regions of an image requires it to have been created with the \
+                     `sparse_residency` flag"
+                }
+                SparseBindError::OutOfRange => {
+                    "the range or region is outside of the resource"
+                }
+                SparseBindError::NotAligned => {
+                    "an offset or size isn't a multiple of the sparse block size"
+                }
+                SparseBindError::MemoryTypeNotSupported => {
+                    "the memory type of the memory can't be bound to the resource"
+                }
+                SparseBindError::MemoryOutOfRange => {
+                    "the range of memory is outside of the memory"
+                }
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SparseBindError;
+    use super::SparseBindInfo;
+    use crate::buffer::sys::UnsafeBuffer;
+    use crate::buffer::BufferUsage;
+    use crate::memory::DeviceMemory;
+    use crate::sync::Sharing;
+    use std::iter::Empty;
+    use std::sync::Arc;
+
+    #[test]
+    fn buffer_not_sparse() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let (buffer, requirements) = unsafe {
+            UnsafeBuffer::new(
+                device.clone(),
+                128,
+                BufferUsage::all(),
+                Sharing::Exclusive::<Empty<_>>,
+                None,
+            )
+            .unwrap()
+        };
+        let memory_type = device
+            .physical_device()
+            .memory_types()
+            .find(|ty| requirements.memory_type_bits & (1 << ty.id()) != 0)
+            .unwrap();
+        let memory = DeviceMemory::alloc(device, memory_type, requirements.size).unwrap();
+
+        let mut binds = SparseBindInfo::new();
+        assert_eq!(
+            binds.bind_buffer(Arc::new(buffer), 0, 128, Arc::new(memory), 0),
+            Err(SparseBindError::ResourceNotSparse)
+        );
+        assert!(binds.is_empty());
+    }
+}
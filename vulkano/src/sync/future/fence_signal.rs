@@ -154,6 +154,13 @@ where
             _ => unreachable!(),
         }
     }
+
+    // Returns true if the fence has been signaled, in which case the resources locked by previous
+    // submissions are cleaned. Doesn't block.
+    pub(crate) fn is_signaled(&self) -> bool {
+        self.cleanup_finished_impl();
+        matches!(*self.state.lock().unwrap(), FenceSignalFutureState::Cleaned)
+    }
 }
 
 impl<F> FenceSignalFuture<F>
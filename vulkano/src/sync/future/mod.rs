@@ -7,10 +7,12 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+pub use self::bind_sparse::{BindSparseFuture, SparseBindError, SparseBindInfo};
 pub use self::fence_signal::{FenceSignalFuture, FenceSignalFutureBehavior};
 pub use self::join::JoinFuture;
 pub use self::now::{now, NowFuture};
 pub use self::semaphore_signal::SemaphoreSignalFuture;
+pub use self::timeline::{TimelineSemaphoreFuture, TimelineSemaphoreSignalFuture};
 use crate::buffer::BufferAccess;
 use crate::command_buffer::submit::SubmitAnyBuilder;
 use crate::command_buffer::submit::SubmitBindSparseError;
@@ -30,15 +32,18 @@ use crate::swapchain::Swapchain;
 use crate::sync::AccessFlags;
 use crate::sync::FenceWaitError;
 use crate::sync::PipelineStages;
+use crate::sync::TimelineSemaphore;
 use crate::OomError;
 use std::error;
 use std::fmt;
 use std::sync::Arc;
 
+mod bind_sparse;
 mod fence_signal;
 mod join;
 mod now;
 mod semaphore_signal;
+mod timeline;
 
 /// Represents an event that will happen on the GPU in the future.
 ///
@@ -186,6 +191,23 @@ pub unsafe trait GpuFuture: DeviceOwned {
         command_buffer.execute_after(self, queue)
     }
 
+    /// Binds sparse memory on `queue` after this future. Returns another future that represents
+    /// the moment when the memory is bound.
+    ///
+    /// The queue must belong to a queue family that supports sparse binding. All the operations
+    /// of `binds` are checked when they are added to the list.
+    #[inline]
+    fn then_bind_sparse(
+        self,
+        queue: Arc<Queue>,
+        binds: SparseBindInfo,
+    ) -> Result<BindSparseFuture<Self>, SparseBindError>
+    where
+        Self: Sized,
+    {
+        bind_sparse::then_bind_sparse(self, queue, binds)
+    }
+
     /// Signals a semaphore after this future. Returns another future that represents the signal.
     ///
     /// Call this function when you want to execute some operations on a queue and want to see the
@@ -221,6 +243,46 @@ pub unsafe trait GpuFuture: DeviceOwned {
         Ok(f)
     }
 
+    /// Sets a timeline semaphore to `value` after this future. Returns another future that
+    /// represents the signal.
+    ///
+    /// Contrary to `then_signal_semaphore`, the semaphore is provided by the caller, so that the
+    /// same semaphore can be signaled by many submissions with increasing values. Other queues
+    /// and the host can wait for the value without having access to the returned future, with a
+    /// [`TimelineSemaphoreFuture`] or [`TimelineSemaphore::wait`](crate::sync::TimelineSemaphore::wait).
+    ///
+    /// The value must be greater than the value of the semaphore, and than the values of all the
+    /// signals of the semaphore that are submitted before this one.
+    #[inline]
+    fn then_signal_timeline_semaphore(
+        self,
+        semaphore: Arc<TimelineSemaphore>,
+        value: u64,
+    ) -> TimelineSemaphoreSignalFuture<Self>
+    where
+        Self: Sized,
+    {
+        timeline::then_signal_timeline_semaphore(self, semaphore, value)
+    }
+
+    /// Sets a timeline semaphore to `value` after this future and flushes it. Returns another
+    /// future that represents the signal.
+    ///
+    /// This is a just a shortcut for `then_signal_timeline_semaphore()` followed with `flush()`.
+    #[inline]
+    fn then_signal_timeline_semaphore_and_flush(
+        self,
+        semaphore: Arc<TimelineSemaphore>,
+        value: u64,
+    ) -> Result<TimelineSemaphoreSignalFuture<Self>, FlushError>
+    where
+        Self: Sized,
+    {
+        let f = self.then_signal_timeline_semaphore(semaphore, value);
+        f.flush()?;
+        Ok(f)
+    }
+
     /// Signals a fence after this future. Returns another future that represents the signal.
     ///
     /// > **Note**: More often than not you want to immediately flush the future after calling this
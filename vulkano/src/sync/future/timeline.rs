@@ -0,0 +1,404 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::buffer::BufferAccess;
+use crate::command_buffer::submit::SubmitAnyBuilder;
+use crate::command_buffer::submit::SubmitCommandBufferBuilder;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::device::Queue;
+use crate::image::ImageAccess;
+use crate::image::ImageLayout;
+use crate::sync::AccessCheckError;
+use crate::sync::AccessFlags;
+use crate::sync::FlushError;
+use crate::sync::GpuFuture;
+use crate::sync::PipelineStages;
+use crate::sync::TimelineSemaphore;
+use crate::sync::TimelineSemaphoreError;
+
+/// Builds a new timeline semaphore signal future.
+#[inline]
+pub fn then_signal_timeline_semaphore<F>(
+    future: F,
+    semaphore: Arc<TimelineSemaphore>,
+    value: u64,
+) -> TimelineSemaphoreSignalFuture<F>
+where
+    F: GpuFuture,
+{
+    assert!(future.queue().is_some()); // TODO: document
+
+    TimelineSemaphoreSignalFuture {
+        previous: future,
+        semaphore,
+        value,
+        wait_submitted: Mutex::new(false),
+        finished: AtomicBool::new(false),
+    }
+}
+
+/// Represents the moment when a timeline semaphore reaches a value.
+///
+/// The value can be reached through any queue or through the host. Operations that are chained
+/// after this future wait upon the semaphore on the GPU side, so that nothing is blocked on the
+/// CPU side. Since nothing is known about what brings the semaphore to the value, accesses to
+/// resources aren't checked against it, just like with [`now`](crate::sync::now).
+///
+/// > **Note**: Presenting can't wait upon a timeline semaphore. Execute a command buffer after
+/// > this future and present after it instead.
+#[must_use]
+pub struct TimelineSemaphoreFuture {
+    semaphore: Arc<TimelineSemaphore>,
+    value: u64,
+}
+
+impl TimelineSemaphoreFuture {
+    /// Builds a future that represents the moment when `semaphore` reaches `value`.
+    #[inline]
+    pub fn new(semaphore: Arc<TimelineSemaphore>, value: u64) -> TimelineSemaphoreFuture {
+        TimelineSemaphoreFuture { semaphore, value }
+    }
+
+    /// Returns the semaphore of the future.
+    #[inline]
+    pub fn semaphore(&self) -> &Arc<TimelineSemaphore> {
+        &self.semaphore
+    }
+
+    /// Returns the value that the semaphore must reach.
+    #[inline]
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+}
+
+unsafe impl GpuFuture for TimelineSemaphoreFuture {
+    #[inline]
+    fn cleanup_finished(&mut self) {}
+
+    #[inline]
+    unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
+        Ok(wait_submission(&self.semaphore, self.value))
+    }
+
+    #[inline]
+    fn flush(&self) -> Result<(), FlushError> {
+        Ok(())
+    }
+
+    #[inline]
+    unsafe fn signal_finished(&self) {}
+
+    #[inline]
+    fn queue_change_allowed(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<Arc<Queue>> {
+        None
+    }
+
+    #[inline]
+    fn check_buffer_access(
+        &self,
+        _: &dyn BufferAccess,
+        _: bool,
+        _: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+        Err(AccessCheckError::Unknown)
+    }
+
+    #[inline]
+    fn check_image_access(
+        &self,
+        _: &dyn ImageAccess,
+        _: ImageLayout,
+        _: bool,
+        _: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+        Err(AccessCheckError::Unknown)
+    }
+}
+
+unsafe impl DeviceOwned for TimelineSemaphoreFuture {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.semaphore.device()
+    }
+}
+
+/// Represents a timeline semaphore being set to a value after a previous event.
+///
+/// Contrary to a [`SemaphoreSignalFuture`](crate::sync::SemaphoreSignalFuture), the end of the
+/// previous event can also be waited upon from the host, with [`wait`](Self::wait), and any number
+/// of other futures can wait upon the semaphore through a [`TimelineSemaphoreFuture`] with the
+/// same value.
+#[must_use = "Dropping this object will immediately block the thread until the GPU has finished \
+              processing the submission"]
+pub struct TimelineSemaphoreSignalFuture<F>
+where
+    F: GpuFuture,
+{
+    previous: F,
+    semaphore: Arc<TimelineSemaphore>,
+    value: u64,
+    // True if the signaling command has already been submitted.
+    // If flush is called multiple times, we want to block so that only one flushing is executed.
+    // Therefore we use a `Mutex<bool>` and not an `AtomicBool`.
+    wait_submitted: Mutex<bool>,
+    finished: AtomicBool,
+}
+
+impl<F> TimelineSemaphoreSignalFuture<F>
+where
+    F: GpuFuture,
+{
+    /// Returns the semaphore that is signaled.
+    #[inline]
+    pub fn semaphore(&self) -> &Arc<TimelineSemaphore> {
+        &self.semaphore
+    }
+
+    /// Returns the value that the semaphore is set to.
+    #[inline]
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// Flushes the signal if needed, then blocks the current thread until the semaphore reaches
+    /// the value.
+    ///
+    /// If `timeout` is `None`, waits forever.
+    pub fn wait(&self, timeout: Option<Duration>) -> Result<(), FlushError> {
+        self.flush()?;
+
+        match self.semaphore.wait(self.value, timeout) {
+            Ok(()) => {
+                self.mark_finished();
+                Ok(())
+            }
+            Err(TimelineSemaphoreError::Timeout) => Err(FlushError::Timeout),
+            Err(TimelineSemaphoreError::DeviceLost) => Err(FlushError::DeviceLost),
+            Err(TimelineSemaphoreError::OomError(err)) => Err(FlushError::OomError(err)),
+            Err(TimelineSemaphoreError::FeatureNotEnabled) => unreachable!(),
+        }
+    }
+
+    // Releases the resources of the previous future once the signal is known to have happened.
+    fn mark_finished(&self) {
+        if !self.finished.swap(true, Ordering::SeqCst) {
+            unsafe {
+                self.previous.signal_finished();
+            }
+        }
+    }
+}
+
+unsafe impl<F> GpuFuture for TimelineSemaphoreSignalFuture<F>
+where
+    F: GpuFuture,
+{
+    #[inline]
+    fn cleanup_finished(&mut self) {
+        if *self.wait_submitted.get_mut()
+            && self
+                .semaphore
+                .value()
+                .map_or(false, |value| value >= self.value)
+        {
+            self.mark_finished();
+        }
+
+        self.previous.cleanup_finished();
+    }
+
+    #[inline]
+    unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
+        // Flushing the signaling part, since it must always be submitted before the waiting part.
+        self.flush()?;
+
+        Ok(wait_submission(&self.semaphore, self.value))
+    }
+
+    fn flush(&self) -> Result<(), FlushError> {
+        unsafe {
+            let mut wait_submitted = self.wait_submitted.lock().unwrap();
+
+            if *wait_submitted {
+                return Ok(());
+            }
+
+            let queue = self.previous.queue().unwrap().clone();
+
+            match self.previous.build_submission()? {
+                SubmitAnyBuilder::Empty => {
+                    let mut builder = SubmitCommandBufferBuilder::new();
+                    builder.add_signal_timeline_semaphore(&self.semaphore, self.value);
+                    builder.submit(&queue)?;
+                }
+                SubmitAnyBuilder::SemaphoresWait(sem) => {
+                    let mut builder: SubmitCommandBufferBuilder = sem.into();
+                    builder.add_signal_timeline_semaphore(&self.semaphore, self.value);
+                    builder.submit(&queue)?;
+                }
+                SubmitAnyBuilder::CommandBuffer(mut builder) => {
+                    debug_assert_eq!(builder.num_signal_semaphores(), 0);
+                    builder.add_signal_timeline_semaphore(&self.semaphore, self.value);
+                    builder.submit(&queue)?;
+                }
+                SubmitAnyBuilder::BindSparse(mut builder) => {
+                    // Same as `BindSparseFuture`, which signals its semaphore in its last batch.
+                    builder.add_signal_timeline_semaphore(&self.semaphore, self.value);
+                    builder.submit(&queue)?;
+                }
+                SubmitAnyBuilder::QueuePresent(present) => {
+                    present.submit(&queue)?;
+                    let mut builder = SubmitCommandBufferBuilder::new();
+                    builder.add_signal_timeline_semaphore(&self.semaphore, self.value);
+                    builder.submit(&queue)?; // FIXME: problematic because if we return an error and flush() is called again, then we'll submit the present twice
+                }
+            };
+
+            // Only write `true` here in order to try again next time if an error occurs.
+            *wait_submitted = true;
+            Ok(())
+        }
+    }
+
+    #[inline]
+    unsafe fn signal_finished(&self) {
+        debug_assert!(*self.wait_submitted.lock().unwrap());
+        self.mark_finished();
+    }
+
+    #[inline]
+    fn queue_change_allowed(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<Arc<Queue>> {
+        self.previous.queue()
+    }
+
+    #[inline]
+    fn check_buffer_access(
+        &self,
+        buffer: &dyn BufferAccess,
+        exclusive: bool,
+        queue: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+        self.previous
+            .check_buffer_access(buffer, exclusive, queue)
+            .map(|_| None)
+    }
+
+    #[inline]
+    fn check_image_access(
+        &self,
+        image: &dyn ImageAccess,
+        layout: ImageLayout,
+        exclusive: bool,
+        queue: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlags)>, AccessCheckError> {
+        self.previous
+            .check_image_access(image, layout, exclusive, queue)
+            .map(|_| None)
+    }
+}
+
+unsafe impl<F> DeviceOwned for TimelineSemaphoreSignalFuture<F>
+where
+    F: GpuFuture,
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.semaphore.device()
+    }
+}
+
+impl<F> Drop for TimelineSemaphoreSignalFuture<F>
+where
+    F: GpuFuture,
+{
+    fn drop(&mut self) {
+        if !*self.finished.get_mut() {
+            // TODO: handle errors?
+            self.wait(None).unwrap();
+        }
+    }
+}
+
+// Builds a submission that waits upon `semaphore` until it reaches `value`.
+unsafe fn wait_submission(semaphore: &TimelineSemaphore, value: u64) -> SubmitAnyBuilder {
+    let mut builder = SubmitCommandBufferBuilder::new();
+    builder.add_wait_timeline_semaphore(
+        semaphore,
+        value,
+        PipelineStages {
+            // TODO: correct stages ; hard
+            all_commands: true,
+            ..PipelineStages::none()
+        },
+    );
+    SubmitAnyBuilder::CommandBuffer(builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_buffer::AutoCommandBufferBuilder;
+    use crate::command_buffer::CommandBufferUsage;
+    use crate::sync::now;
+    use crate::sync::GpuFuture;
+    use crate::sync::TimelineSemaphore;
+    use crate::sync::TimelineSemaphoreFuture;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn signal_then_wait() {
+        let (device, queue) = gfx_dev_and_queue!(timeline_semaphore);
+        let semaphore = Arc::new(TimelineSemaphore::new(device.clone(), 0).unwrap());
+
+        let empty_cb = || {
+            AutoCommandBufferBuilder::primary(
+                device.clone(),
+                queue.family(),
+                CommandBufferUsage::OneTimeSubmit,
+            )
+            .unwrap()
+            .build()
+            .unwrap()
+        };
+
+        let signal = now(device.clone())
+            .then_execute(queue.clone(), empty_cb())
+            .unwrap()
+            .then_signal_timeline_semaphore_and_flush(semaphore.clone(), 1)
+            .unwrap();
+
+        TimelineSemaphoreFuture::new(semaphore.clone(), 1)
+            .then_execute(queue.clone(), empty_cb())
+            .unwrap()
+            .then_signal_timeline_semaphore(semaphore.clone(), 2)
+            .wait(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        assert_eq!(semaphore.value().unwrap(), 2);
+        signal.wait(Some(Duration::from_secs(5))).unwrap();
+    }
+}
@@ -90,6 +90,15 @@
 //!
 //! TODO: this is not yet implemented
 //!
+//! ## Timeline semaphores
+//!
+//! If the `timeline_semaphore` feature is enabled, a [`TimelineSemaphore`] holds a value that
+//! each signal operation increases. `prev_future.then_signal_timeline_semaphore(semaphore, value)`
+//! sets it to `value` once `prev_future` is finished, and a [`TimelineSemaphoreFuture`] represents
+//! the moment when the semaphore reaches a value, on any queue. Since the host can also wait for
+//! a value, one semaphore can replace the fence of each submission, for example to know which
+//! frames are finished.
+//!
 //! # Fences
 //!
 //! A `Fence` is an object that is used to signal the CPU when an operation on the GPU is finished.
@@ -112,12 +121,17 @@ pub use self::fence::FenceWaitError;
 pub use self::future::now;
 pub use self::future::AccessCheckError;
 pub use self::future::AccessError;
+pub use self::future::BindSparseFuture;
 pub use self::future::FenceSignalFuture;
 pub use self::future::FlushError;
 pub use self::future::GpuFuture;
 pub use self::future::JoinFuture;
 pub use self::future::NowFuture;
 pub use self::future::SemaphoreSignalFuture;
+pub use self::future::SparseBindError;
+pub use self::future::SparseBindInfo;
+pub use self::future::TimelineSemaphoreFuture;
+pub use self::future::TimelineSemaphoreSignalFuture;
 pub use self::pipeline::AccessFlags;
 pub use self::pipeline::PipelineMemoryAccess;
 pub use self::pipeline::PipelineStage;
@@ -125,6 +139,8 @@ pub use self::pipeline::PipelineStages;
 pub use self::semaphore::ExternalSemaphoreHandleType;
 pub use self::semaphore::Semaphore;
 pub use self::semaphore::SemaphoreError;
+pub use self::semaphore::TimelineSemaphore;
+pub use self::semaphore::TimelineSemaphoreError;
 
 mod event;
 mod fence;
@@ -7,10 +7,17 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use crate::device::physical::QueueFamily;
 use std::ops;
 
 macro_rules! pipeline_stages {
-    ($($elem:ident, $var:ident => $val:expr, $queue:expr;)+) => (
+    ($($elem:ident, $var:ident => $val:expr, $legacy:expr, $queue:expr;)+) => (
+        /// A set of pipeline stages.
+        ///
+        /// The `copy`, `resolve`, `blit`, `clear`, `index_input`, `vertex_attribute_input` and
+        /// `pre_rasterization_shaders` stages were added by `VK_KHR_synchronization2`. When the
+        /// `synchronization2` feature is not enabled, they are replaced by the stages of Vulkan 1.0
+        /// that include them.
         #[derive(Debug, Copy, Clone, PartialEq, Eq)]
         pub struct PipelineStages {
             $(
@@ -27,6 +34,42 @@ macro_rules! pipeline_stages {
                     )+
                 }
             }
+
+            /// Returns `true` if all the stages that are set are supported by the queues of
+            /// `queue_family`.
+            #[inline]
+            pub fn supported_by_queue_family(&self, queue_family: QueueFamily) -> bool {
+                $(
+                    if self.$elem && !queue_family.supports_stage(PipelineStage::$var) {
+                        return false;
+                    }
+                )+
+
+                true
+            }
+
+            /// Returns the names of the stages that are set.
+            #[inline]
+            pub(crate) fn names(&self) -> Vec<&'static str> {
+                let mut names = Vec::new();
+                $(
+                    if self.$elem {
+                        names.push(stringify!($elem));
+                    }
+                )+
+                names
+            }
+        }
+
+        impl From<PipelineStages> for ash::vk::PipelineStageFlags2KHR {
+            #[inline]
+            fn from(val: PipelineStages) -> Self {
+                let mut result = ash::vk::PipelineStageFlags2KHR::empty();
+                $(
+                    if val.$elem { result |= $val }
+                )+
+                result
+            }
         }
 
         impl From<PipelineStages> for ash::vk::PipelineStageFlags {
@@ -34,7 +77,7 @@ macro_rules! pipeline_stages {
             fn from(val: PipelineStages) -> Self {
                 let mut result = ash::vk::PipelineStageFlags::empty();
                 $(
-                    if val.$elem { result |= $val }
+                    if val.$elem { result |= $legacy }
                 )+
                 result
             }
@@ -63,7 +106,7 @@ macro_rules! pipeline_stages {
         }
 
         #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-        #[repr(u32)]
+        #[repr(u64)]
         pub enum PipelineStage {
             $(
                 $var = $val.as_raw(),
@@ -80,39 +123,66 @@ macro_rules! pipeline_stages {
                 }
             }
         }
+
+        impl From<PipelineStage> for ash::vk::PipelineStageFlags {
+            #[inline]
+            fn from(val: PipelineStage) -> Self {
+                match val {
+                    $(
+                        PipelineStage::$var => $legacy,
+                    )+
+                }
+            }
+        }
     );
 }
 
-impl From<PipelineStage> for ash::vk::PipelineStageFlags {
+impl From<PipelineStage> for ash::vk::PipelineStageFlags2KHR {
     #[inline]
     fn from(val: PipelineStage) -> Self {
-        Self::from_raw(val as u32)
+        Self::from_raw(val as u64)
     }
 }
 
 pipeline_stages! {
-    top_of_pipe, TopOfPipe => ash::vk::PipelineStageFlags::TOP_OF_PIPE, ash::vk::QueueFlags::empty();
-    draw_indirect, DrawIndirect => ash::vk::PipelineStageFlags::DRAW_INDIRECT, ash::vk::QueueFlags::GRAPHICS | ash::vk::QueueFlags::COMPUTE;
-    vertex_input, VertexInput => ash::vk::PipelineStageFlags::VERTEX_INPUT, ash::vk::QueueFlags::GRAPHICS;
-    vertex_shader, VertexShader => ash::vk::PipelineStageFlags::VERTEX_SHADER, ash::vk::QueueFlags::GRAPHICS;
-    tessellation_control_shader, TessellationControlShader => ash::vk::PipelineStageFlags::TESSELLATION_CONTROL_SHADER, ash::vk::QueueFlags::GRAPHICS;
-    tessellation_evaluation_shader, TessellationEvaluationShader => ash::vk::PipelineStageFlags::TESSELLATION_EVALUATION_SHADER, ash::vk::QueueFlags::GRAPHICS;
-    geometry_shader, GeometryShader => ash::vk::PipelineStageFlags::GEOMETRY_SHADER, ash::vk::QueueFlags::GRAPHICS;
-    fragment_shader, FragmentShader => ash::vk::PipelineStageFlags::FRAGMENT_SHADER, ash::vk::QueueFlags::GRAPHICS;
-    early_fragment_tests, EarlyFragmentTests => ash::vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS, ash::vk::QueueFlags::GRAPHICS;
-    late_fragment_tests, LateFragmentTests => ash::vk::PipelineStageFlags::LATE_FRAGMENT_TESTS, ash::vk::QueueFlags::GRAPHICS;
-    color_attachment_output, ColorAttachmentOutput => ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT, ash::vk::QueueFlags::GRAPHICS;
-    compute_shader, ComputeShader => ash::vk::PipelineStageFlags::COMPUTE_SHADER, ash::vk::QueueFlags::COMPUTE;
-    transfer, Transfer => ash::vk::PipelineStageFlags::TRANSFER, ash::vk::QueueFlags::GRAPHICS | ash::vk::QueueFlags::COMPUTE | ash::vk::QueueFlags::TRANSFER;
-    bottom_of_pipe, BottomOfPipe => ash::vk::PipelineStageFlags::BOTTOM_OF_PIPE, ash::vk::QueueFlags::empty();
-    host, Host => ash::vk::PipelineStageFlags::HOST, ash::vk::QueueFlags::empty();
-    all_graphics, AllGraphics => ash::vk::PipelineStageFlags::ALL_GRAPHICS, ash::vk::QueueFlags::GRAPHICS;
-    all_commands, AllCommands => ash::vk::PipelineStageFlags::ALL_COMMANDS, ash::vk::QueueFlags::empty();
-    ray_tracing_shader, RayTracingShader => ash::vk::PipelineStageFlags::RAY_TRACING_SHADER_KHR, ash::vk::QueueFlags::GRAPHICS | ash::vk::QueueFlags::COMPUTE | ash::vk::QueueFlags::TRANSFER;
+    top_of_pipe, TopOfPipe => ash::vk::PipelineStageFlags2KHR::TOP_OF_PIPE, ash::vk::PipelineStageFlags::TOP_OF_PIPE, ash::vk::QueueFlags::empty();
+    draw_indirect, DrawIndirect => ash::vk::PipelineStageFlags2KHR::DRAW_INDIRECT, ash::vk::PipelineStageFlags::DRAW_INDIRECT, ash::vk::QueueFlags::GRAPHICS | ash::vk::QueueFlags::COMPUTE;
+    vertex_input, VertexInput => ash::vk::PipelineStageFlags2KHR::VERTEX_INPUT, ash::vk::PipelineStageFlags::VERTEX_INPUT, ash::vk::QueueFlags::GRAPHICS;
+    vertex_shader, VertexShader => ash::vk::PipelineStageFlags2KHR::VERTEX_SHADER, ash::vk::PipelineStageFlags::VERTEX_SHADER, ash::vk::QueueFlags::GRAPHICS;
+    tessellation_control_shader, TessellationControlShader => ash::vk::PipelineStageFlags2KHR::TESSELLATION_CONTROL_SHADER, ash::vk::PipelineStageFlags::TESSELLATION_CONTROL_SHADER, ash::vk::QueueFlags::GRAPHICS;
+    tessellation_evaluation_shader, TessellationEvaluationShader => ash::vk::PipelineStageFlags2KHR::TESSELLATION_EVALUATION_SHADER, ash::vk::PipelineStageFlags::TESSELLATION_EVALUATION_SHADER, ash::vk::QueueFlags::GRAPHICS;
+    geometry_shader, GeometryShader => ash::vk::PipelineStageFlags2KHR::GEOMETRY_SHADER, ash::vk::PipelineStageFlags::GEOMETRY_SHADER, ash::vk::QueueFlags::GRAPHICS;
+    fragment_shader, FragmentShader => ash::vk::PipelineStageFlags2KHR::FRAGMENT_SHADER, ash::vk::PipelineStageFlags::FRAGMENT_SHADER, ash::vk::QueueFlags::GRAPHICS;
+    early_fragment_tests, EarlyFragmentTests => ash::vk::PipelineStageFlags2KHR::EARLY_FRAGMENT_TESTS, ash::vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS, ash::vk::QueueFlags::GRAPHICS;
+    late_fragment_tests, LateFragmentTests => ash::vk::PipelineStageFlags2KHR::LATE_FRAGMENT_TESTS, ash::vk::PipelineStageFlags::LATE_FRAGMENT_TESTS, ash::vk::QueueFlags::GRAPHICS;
+    color_attachment_output, ColorAttachmentOutput => ash::vk::PipelineStageFlags2KHR::COLOR_ATTACHMENT_OUTPUT, ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT, ash::vk::QueueFlags::GRAPHICS;
+    compute_shader, ComputeShader => ash::vk::PipelineStageFlags2KHR::COMPUTE_SHADER, ash::vk::PipelineStageFlags::COMPUTE_SHADER, ash::vk::QueueFlags::COMPUTE;
+    transfer, Transfer => ash::vk::PipelineStageFlags2KHR::TRANSFER, ash::vk::PipelineStageFlags::TRANSFER, ash::vk::QueueFlags::GRAPHICS | ash::vk::QueueFlags::COMPUTE | ash::vk::QueueFlags::TRANSFER;
+    bottom_of_pipe, BottomOfPipe => ash::vk::PipelineStageFlags2KHR::BOTTOM_OF_PIPE, ash::vk::PipelineStageFlags::BOTTOM_OF_PIPE, ash::vk::QueueFlags::empty();
+    host, Host => ash::vk::PipelineStageFlags2KHR::HOST, ash::vk::PipelineStageFlags::HOST, ash::vk::QueueFlags::empty();
+    all_graphics, AllGraphics => ash::vk::PipelineStageFlags2KHR::ALL_GRAPHICS, ash::vk::PipelineStageFlags::ALL_GRAPHICS, ash::vk::QueueFlags::GRAPHICS;
+    all_commands, AllCommands => ash::vk::PipelineStageFlags2KHR::ALL_COMMANDS, ash::vk::PipelineStageFlags::ALL_COMMANDS, ash::vk::QueueFlags::empty();
+    ray_tracing_shader, RayTracingShader => ash::vk::PipelineStageFlags2KHR::RAY_TRACING_SHADER, ash::vk::PipelineStageFlags::RAY_TRACING_SHADER_KHR, ash::vk::QueueFlags::GRAPHICS | ash::vk::QueueFlags::COMPUTE | ash::vk::QueueFlags::TRANSFER;
+    conditional_rendering, ConditionalRendering => ash::vk::PipelineStageFlags2KHR::CONDITIONAL_RENDERING_EXT, ash::vk::PipelineStageFlags::CONDITIONAL_RENDERING_EXT, ash::vk::QueueFlags::GRAPHICS | ash::vk::QueueFlags::COMPUTE;
+    transform_feedback, TransformFeedback => ash::vk::PipelineStageFlags2KHR::TRANSFORM_FEEDBACK_EXT, ash::vk::PipelineStageFlags::TRANSFORM_FEEDBACK_EXT, ash::vk::QueueFlags::GRAPHICS;
+    command_preprocess, CommandPreprocess => ash::vk::PipelineStageFlags2KHR::COMMAND_PREPROCESS_NV, ash::vk::PipelineStageFlags::COMMAND_PREPROCESS_NV, ash::vk::QueueFlags::GRAPHICS | ash::vk::QueueFlags::COMPUTE;
+    fragment_shading_rate_attachment, FragmentShadingRateAttachment => ash::vk::PipelineStageFlags2KHR::FRAGMENT_SHADING_RATE_ATTACHMENT_KHR, ash::vk::PipelineStageFlags::FRAGMENT_SHADING_RATE_ATTACHMENT_KHR, ash::vk::QueueFlags::GRAPHICS;
+    copy, Copy => ash::vk::PipelineStageFlags2KHR::COPY, ash::vk::PipelineStageFlags::TRANSFER, ash::vk::QueueFlags::GRAPHICS | ash::vk::QueueFlags::COMPUTE | ash::vk::QueueFlags::TRANSFER;
+    resolve, Resolve => ash::vk::PipelineStageFlags2KHR::RESOLVE, ash::vk::PipelineStageFlags::TRANSFER, ash::vk::QueueFlags::GRAPHICS | ash::vk::QueueFlags::COMPUTE | ash::vk::QueueFlags::TRANSFER;
+    blit, Blit => ash::vk::PipelineStageFlags2KHR::BLIT, ash::vk::PipelineStageFlags::TRANSFER, ash::vk::QueueFlags::GRAPHICS;
+    clear, Clear => ash::vk::PipelineStageFlags2KHR::CLEAR, ash::vk::PipelineStageFlags::TRANSFER, ash::vk::QueueFlags::GRAPHICS | ash::vk::QueueFlags::COMPUTE;
+    index_input, IndexInput => ash::vk::PipelineStageFlags2KHR::INDEX_INPUT, ash::vk::PipelineStageFlags::VERTEX_INPUT, ash::vk::QueueFlags::GRAPHICS;
+    vertex_attribute_input, VertexAttributeInput => ash::vk::PipelineStageFlags2KHR::VERTEX_ATTRIBUTE_INPUT, ash::vk::PipelineStageFlags::VERTEX_INPUT, ash::vk::QueueFlags::GRAPHICS;
+    pre_rasterization_shaders, PreRasterizationShaders => ash::vk::PipelineStageFlags2KHR::PRE_RASTERIZATION_SHADERS, ash::vk::PipelineStageFlags::VERTEX_SHADER | ash::vk::PipelineStageFlags::TESSELLATION_CONTROL_SHADER | ash::vk::PipelineStageFlags::TESSELLATION_EVALUATION_SHADER | ash::vk::PipelineStageFlags::GEOMETRY_SHADER, ash::vk::QueueFlags::GRAPHICS;
 }
 
 macro_rules! access_flags {
-    ($($elem:ident => $val:expr,)+) => (
+    ($($elem:ident => $val:expr, $legacy:expr,)+) => (
+        /// A set of memory accesses.
+        ///
+        /// The `shader_sampled_read`, `shader_storage_read` and `shader_storage_write` accesses
+        /// were added by `VK_KHR_synchronization2`. When the `synchronization2` feature is not
+        /// enabled, they are replaced by `shader_read` and `shader_write`.
         #[derive(Debug, Copy, Clone)]
         #[allow(missing_docs)]
         pub struct AccessFlags {
@@ -139,6 +209,29 @@ macro_rules! access_flags {
                     )+
                 }
             }
+
+            /// Returns the names of the accesses that are set.
+            #[inline]
+            pub(crate) fn names(&self) -> Vec<&'static str> {
+                let mut names = Vec::new();
+                $(
+                    if self.$elem {
+                        names.push(stringify!($elem));
+                    }
+                )+
+                names
+            }
+        }
+
+        impl From<AccessFlags> for ash::vk::AccessFlags2KHR {
+            #[inline]
+            fn from(val: AccessFlags) -> Self {
+                let mut result = ash::vk::AccessFlags2KHR::empty();
+                $(
+                    if val.$elem { result |= $val }
+                )+
+                result
+            }
         }
 
         impl From<AccessFlags> for ash::vk::AccessFlags {
@@ -146,7 +239,7 @@ macro_rules! access_flags {
             fn from(val: AccessFlags) -> Self {
                 let mut result = ash::vk::AccessFlags::empty();
                 $(
-                    if val.$elem { result |= $val }
+                    if val.$elem { result |= $legacy }
                 )+
                 result
             }
@@ -177,26 +270,95 @@ macro_rules! access_flags {
 }
 
 access_flags! {
-    indirect_command_read => ash::vk::AccessFlags::INDIRECT_COMMAND_READ,
-    index_read => ash::vk::AccessFlags::INDEX_READ,
-    vertex_attribute_read => ash::vk::AccessFlags::VERTEX_ATTRIBUTE_READ,
-    uniform_read => ash::vk::AccessFlags::UNIFORM_READ,
-    input_attachment_read => ash::vk::AccessFlags::INPUT_ATTACHMENT_READ,
-    shader_read => ash::vk::AccessFlags::SHADER_READ,
-    shader_write => ash::vk::AccessFlags::SHADER_WRITE,
-    color_attachment_read => ash::vk::AccessFlags::COLOR_ATTACHMENT_READ,
-    color_attachment_write => ash::vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
-    depth_stencil_attachment_read => ash::vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ,
-    depth_stencil_attachment_write => ash::vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
-    transfer_read => ash::vk::AccessFlags::TRANSFER_READ,
-    transfer_write => ash::vk::AccessFlags::TRANSFER_WRITE,
-    host_read => ash::vk::AccessFlags::HOST_READ,
-    host_write => ash::vk::AccessFlags::HOST_WRITE,
-    memory_read => ash::vk::AccessFlags::MEMORY_READ,
-    memory_write => ash::vk::AccessFlags::MEMORY_WRITE,
+    indirect_command_read => ash::vk::AccessFlags2KHR::INDIRECT_COMMAND_READ, ash::vk::AccessFlags::INDIRECT_COMMAND_READ,
+    index_read => ash::vk::AccessFlags2KHR::INDEX_READ, ash::vk::AccessFlags::INDEX_READ,
+    vertex_attribute_read => ash::vk::AccessFlags2KHR::VERTEX_ATTRIBUTE_READ, ash::vk::AccessFlags::VERTEX_ATTRIBUTE_READ,
+    uniform_read => ash::vk::AccessFlags2KHR::UNIFORM_READ, ash::vk::AccessFlags::UNIFORM_READ,
+    input_attachment_read => ash::vk::AccessFlags2KHR::INPUT_ATTACHMENT_READ, ash::vk::AccessFlags::INPUT_ATTACHMENT_READ,
+    shader_read => ash::vk::AccessFlags2KHR::SHADER_READ, ash::vk::AccessFlags::SHADER_READ,
+    shader_write => ash::vk::AccessFlags2KHR::SHADER_WRITE, ash::vk::AccessFlags::SHADER_WRITE,
+    color_attachment_read => ash::vk::AccessFlags2KHR::COLOR_ATTACHMENT_READ, ash::vk::AccessFlags::COLOR_ATTACHMENT_READ,
+    color_attachment_write => ash::vk::AccessFlags2KHR::COLOR_ATTACHMENT_WRITE, ash::vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+    depth_stencil_attachment_read => ash::vk::AccessFlags2KHR::DEPTH_STENCIL_ATTACHMENT_READ, ash::vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ,
+    depth_stencil_attachment_write => ash::vk::AccessFlags2KHR::DEPTH_STENCIL_ATTACHMENT_WRITE, ash::vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+    transfer_read => ash::vk::AccessFlags2KHR::TRANSFER_READ, ash::vk::AccessFlags::TRANSFER_READ,
+    transfer_write => ash::vk::AccessFlags2KHR::TRANSFER_WRITE, ash::vk::AccessFlags::TRANSFER_WRITE,
+    host_read => ash::vk::AccessFlags2KHR::HOST_READ, ash::vk::AccessFlags::HOST_READ,
+    host_write => ash::vk::AccessFlags2KHR::HOST_WRITE, ash::vk::AccessFlags::HOST_WRITE,
+    memory_read => ash::vk::AccessFlags2KHR::MEMORY_READ, ash::vk::AccessFlags::MEMORY_READ,
+    memory_write => ash::vk::AccessFlags2KHR::MEMORY_WRITE, ash::vk::AccessFlags::MEMORY_WRITE,
+    conditional_rendering_read => ash::vk::AccessFlags2KHR::CONDITIONAL_RENDERING_READ_EXT, ash::vk::AccessFlags::CONDITIONAL_RENDERING_READ_EXT,
+    transform_feedback_write => ash::vk::AccessFlags2KHR::TRANSFORM_FEEDBACK_WRITE_EXT, ash::vk::AccessFlags::TRANSFORM_FEEDBACK_WRITE_EXT,
+    transform_feedback_counter_read => ash::vk::AccessFlags2KHR::TRANSFORM_FEEDBACK_COUNTER_READ_EXT, ash::vk::AccessFlags::TRANSFORM_FEEDBACK_COUNTER_READ_EXT,
+    transform_feedback_counter_write => ash::vk::AccessFlags2KHR::TRANSFORM_FEEDBACK_COUNTER_WRITE_EXT, ash::vk::AccessFlags::TRANSFORM_FEEDBACK_COUNTER_WRITE_EXT,
+    command_preprocess_read => ash::vk::AccessFlags2KHR::COMMAND_PREPROCESS_READ_NV, ash::vk::AccessFlags::COMMAND_PREPROCESS_READ_NV,
+    command_preprocess_write => ash::vk::AccessFlags2KHR::COMMAND_PREPROCESS_WRITE_NV, ash::vk::AccessFlags::COMMAND_PREPROCESS_WRITE_NV,
+    shader_sampled_read => ash::vk::AccessFlags2KHR::SHADER_SAMPLED_READ, ash::vk::AccessFlags::SHADER_READ,
+    shader_storage_read => ash::vk::AccessFlags2KHR::SHADER_STORAGE_READ, ash::vk::AccessFlags::SHADER_READ,
+    shader_storage_write => ash::vk::AccessFlags2KHR::SHADER_STORAGE_WRITE, ash::vk::AccessFlags::SHADER_WRITE,
+    acceleration_structure_read => ash::vk::AccessFlags2KHR::ACCELERATION_STRUCTURE_READ_KHR, ash::vk::AccessFlags::ACCELERATION_STRUCTURE_READ_KHR,
+    fragment_shading_rate_attachment_read => ash::vk::AccessFlags2KHR::FRAGMENT_SHADING_RATE_ATTACHMENT_READ_KHR, ash::vk::AccessFlags::FRAGMENT_SHADING_RATE_ATTACHMENT_READ_KHR,
+}
+
+impl PipelineStages {
+    /// Returns `true` if all the stages of `other` are also set in `self`.
+    #[inline]
+    pub(crate) fn contains(&self, other: &PipelineStages) -> bool {
+        ash::vk::PipelineStageFlags2KHR::from(*self)
+            .contains(ash::vk::PipelineStageFlags2KHR::from(*other))
+    }
+
+    /// Returns `true` if any of the stages that were added by `VK_KHR_synchronization2` is set.
+    #[inline]
+    pub(crate) fn requires_synchronization2(&self) -> bool {
+        self.copy
+            || self.resolve
+            || self.blit
+            || self.clear
+            || self.index_input
+            || self.vertex_attribute_input
+            || self.pre_rasterization_shaders
+    }
 }
 
 impl AccessFlags {
+    /// Returns `true` if all the accesses of `other` are also set in `self`.
+    #[inline]
+    pub(crate) fn contains(&self, other: &AccessFlags) -> bool {
+        ash::vk::AccessFlags2KHR::from(*self).contains(ash::vk::AccessFlags2KHR::from(*other))
+    }
+
+    /// Returns only the accesses of `self` that write to memory.
+    #[inline]
+    pub(crate) fn writes(&self) -> AccessFlags {
+        AccessFlags {
+            shader_write: self.shader_write,
+            color_attachment_write: self.color_attachment_write,
+            depth_stencil_attachment_write: self.depth_stencil_attachment_write,
+            transfer_write: self.transfer_write,
+            host_write: self.host_write,
+            memory_write: self.memory_write,
+            transform_feedback_write: self.transform_feedback_write,
+            transform_feedback_counter_write: self.transform_feedback_counter_write,
+            command_preprocess_write: self.command_preprocess_write,
+            shader_storage_write: self.shader_storage_write,
+            ..AccessFlags::none()
+        }
+    }
+
+    /// Returns `true` if any of the accesses of `self` writes to memory.
+    #[inline]
+    pub(crate) fn has_writes(&self) -> bool {
+        !ash::vk::AccessFlags2KHR::from(self.writes()).is_empty()
+    }
+
+    /// Returns `true` if any of the accesses that were added by `VK_KHR_synchronization2` is
+    /// set.
+    #[inline]
+    pub(crate) fn requires_synchronization2(&self) -> bool {
+        self.shader_sampled_read || self.shader_storage_read || self.shader_storage_write
+    }
+
     /// Returns true if the access flags can be used with the given pipeline stages.
     ///
     /// Corresponds to `Table 4. Supported access types` in section `6.1.3. Access Types` of the
@@ -210,14 +372,25 @@ impl AccessFlags {
             return false;
         }
 
-        if (self.index_read || self.vertex_attribute_read)
+        if self.index_read && !stages.index_input && !stages.vertex_input && !stages.all_graphics {
+            return false;
+        }
+
+        if self.vertex_attribute_read
+            && !stages.vertex_attribute_input
             && !stages.vertex_input
             && !stages.all_graphics
         {
             return false;
         }
 
-        if (self.uniform_read || self.shader_read || self.shader_write)
+        if (self.uniform_read
+            || self.shader_read
+            || self.shader_write
+            || self.shader_sampled_read
+            || self.shader_storage_read
+            || self.shader_storage_write)
+            && !stages.pre_rasterization_shaders
             && !stages.vertex_shader
             && !stages.tessellation_control_shader
             && !stages.tessellation_evaluation_shader
@@ -248,7 +421,18 @@ impl AccessFlags {
             return false;
         }
 
-        if (self.transfer_read || self.transfer_write) && !stages.transfer {
+        if self.transfer_read && !stages.transfer && !stages.copy && !stages.blit && !stages.resolve
+        {
+            return false;
+        }
+
+        if self.transfer_write
+            && !stages.transfer
+            && !stages.copy
+            && !stages.blit
+            && !stages.resolve
+            && !stages.clear
+        {
             return false;
         }
 
@@ -256,6 +440,50 @@ impl AccessFlags {
             return false;
         }
 
+        if self.conditional_rendering_read && !stages.conditional_rendering {
+            return false;
+        }
+
+        if (self.transform_feedback_write || self.transform_feedback_counter_write)
+            && !stages.transform_feedback
+        {
+            return false;
+        }
+
+        if self.transform_feedback_counter_read
+            && !stages.transform_feedback
+            && !stages.draw_indirect
+        {
+            return false;
+        }
+
+        if (self.command_preprocess_read || self.command_preprocess_write)
+            && !stages.command_preprocess
+        {
+            return false;
+        }
+
+        if self.acceleration_structure_read
+            && !stages.pre_rasterization_shaders
+            && !stages.vertex_shader
+            && !stages.tessellation_control_shader
+            && !stages.tessellation_evaluation_shader
+            && !stages.geometry_shader
+            && !stages.fragment_shader
+            && !stages.compute_shader
+            && !stages.ray_tracing_shader
+            && !stages.all_graphics
+        {
+            return false;
+        }
+
+        if self.fragment_shading_rate_attachment_read
+            && !stages.fragment_shading_rate_attachment
+            && !stages.all_graphics
+        {
+            return false;
+        }
+
         true
     }
 }
@@ -270,3 +498,96 @@ pub struct PipelineMemoryAccess {
     /// Whether the resource needs exclusive (mutable) access or can be shared.
     pub exclusive: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::AccessFlags;
+    use super::PipelineStage;
+    use super::PipelineStages;
+
+    #[test]
+    fn synchronization2_stages_conversion() {
+        let stages = PipelineStages {
+            copy: true,
+            index_input: true,
+            pre_rasterization_shaders: true,
+            fragment_shader: true,
+            ..PipelineStages::none()
+        };
+        assert!(stages.requires_synchronization2());
+        assert_eq!(
+            ash::vk::PipelineStageFlags2KHR::from(stages),
+            ash::vk::PipelineStageFlags2KHR::COPY
+                | ash::vk::PipelineStageFlags2KHR::INDEX_INPUT
+                | ash::vk::PipelineStageFlags2KHR::PRE_RASTERIZATION_SHADERS
+                | ash::vk::PipelineStageFlags2KHR::FRAGMENT_SHADER
+        );
+
+        // Without synchronization2, the new stages are replaced by the ones that include them.
+        assert_eq!(
+            ash::vk::PipelineStageFlags::from(stages),
+            ash::vk::PipelineStageFlags::TRANSFER
+                | ash::vk::PipelineStageFlags::VERTEX_INPUT
+                | ash::vk::PipelineStageFlags::VERTEX_SHADER
+                | ash::vk::PipelineStageFlags::TESSELLATION_CONTROL_SHADER
+                | ash::vk::PipelineStageFlags::TESSELLATION_EVALUATION_SHADER
+                | ash::vk::PipelineStageFlags::GEOMETRY_SHADER
+                | ash::vk::PipelineStageFlags::FRAGMENT_SHADER
+        );
+
+        assert!(!PipelineStages {
+            transfer: true,
+            vertex_input: true,
+            ..PipelineStages::none()
+        }
+        .requires_synchronization2());
+    }
+
+    #[test]
+    fn synchronization2_stage_conversion() {
+        assert_eq!(
+            ash::vk::PipelineStageFlags2KHR::from(PipelineStage::Blit),
+            ash::vk::PipelineStageFlags2KHR::BLIT
+        );
+        assert_eq!(
+            ash::vk::PipelineStageFlags::from(PipelineStage::Blit),
+            ash::vk::PipelineStageFlags::TRANSFER
+        );
+        assert_eq!(
+            ash::vk::PipelineStageFlags::from(PipelineStage::BottomOfPipe),
+            ash::vk::PipelineStageFlags::BOTTOM_OF_PIPE
+        );
+    }
+
+    #[test]
+    fn synchronization2_access_conversion() {
+        let access = AccessFlags {
+            shader_sampled_read: true,
+            shader_storage_write: true,
+            transfer_read: true,
+            ..AccessFlags::none()
+        };
+        assert!(access.requires_synchronization2());
+        assert_eq!(
+            ash::vk::AccessFlags2KHR::from(access),
+            ash::vk::AccessFlags2KHR::SHADER_SAMPLED_READ
+                | ash::vk::AccessFlags2KHR::SHADER_STORAGE_WRITE
+                | ash::vk::AccessFlags2KHR::TRANSFER_READ
+        );
+
+        // Without synchronization2, the new accesses are replaced by the ones that include them.
+        assert_eq!(
+            ash::vk::AccessFlags::from(access),
+            ash::vk::AccessFlags::SHADER_READ
+                | ash::vk::AccessFlags::SHADER_WRITE
+                | ash::vk::AccessFlags::TRANSFER_READ
+        );
+
+        assert!(!AccessFlags {
+            shader_read: true,
+            shader_write: true,
+            ..AccessFlags::none()
+        }
+        .requires_synchronization2());
+    }
+}
@@ -10,10 +10,14 @@
 use crate::check_errors;
 use crate::device::Device;
 use crate::device::DeviceOwned;
+#[cfg(target_os = "windows")]
+use crate::memory::Win32Handle;
 use crate::Error;
 use crate::OomError;
 use crate::SafeDeref;
 use crate::VulkanObject;
+#[cfg(target_os = "windows")]
+use std::ffi::c_void;
 use std::fmt;
 #[cfg(any(
     target_os = "linux",
@@ -31,7 +35,7 @@ use std::mem::MaybeUninit;
     target_os = "netbsd",
     target_os = "openbsd"
 ))]
-use std::os::unix::io::FromRawFd;
+use std::os::unix::io::{FromRawFd, IntoRawFd};
 use std::ptr;
 use std::sync::Arc;
 
@@ -52,7 +56,6 @@ where
 }
 
 // TODO: Add support for VkExportSemaphoreWin32HandleInfoKHR
-// TODO: Add suport for importable semaphores
 pub struct SemaphoreBuilder<D = Arc<Device>>
 where
     D: SafeDeref<Target = Device>,
@@ -187,16 +190,48 @@ where
         target_os = "netbsd",
         target_os = "openbsd"
     ))]
+    #[inline]
     pub fn export_opaque_fd(&self) -> Result<File, SemaphoreError> {
-        let fns = self.device.fns();
+        self.export_fd(ExternalSemaphoreHandleType::posix())
+    }
 
-        assert!(self.device.enabled_extensions().khr_external_semaphore);
-        assert!(self.device.enabled_extensions().khr_external_semaphore_fd);
+    /// Exports the payload of the semaphore as a file descriptor of type `handle_type`, which
+    /// must be exactly one of `opaque_fd` or `sync_fd`.
+    ///
+    /// The semaphore must have been created with `handle_type` in its export info. Exporting a
+    /// `sync_fd` resets the semaphore to the unsignaled state, and requires the semaphore to be
+    /// signaled or to have a pending signal operation.
+    ///
+    /// Requires the `khr_external_semaphore_fd` extension.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `handle_type` isn't exactly one of `opaque_fd` or `sync_fd`.
+    ///
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonflybsd",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn export_fd(
+        &self,
+        handle_type: ExternalSemaphoreHandleType,
+    ) -> Result<File, SemaphoreError> {
+        assert!(handle_type == ExternalSemaphoreHandleType::posix() || handle_type == sync_fd());
+
+        if !self.device.enabled_extensions().khr_external_semaphore_fd {
+            return Err(SemaphoreError::MissingExtension(
+                "khr_external_semaphore_fd",
+            ));
+        }
 
+        let fns = self.device.fns();
         let fd = unsafe {
             let info = ash::vk::SemaphoreGetFdInfoKHR {
                 semaphore: self.semaphore,
-                handle_type: ash::vk::ExternalSemaphoreHandleTypeFlagsKHR::OPAQUE_FD,
+                handle_type: handle_type.into(),
                 ..Default::default()
             };
 
@@ -211,6 +246,236 @@ where
         let file = unsafe { File::from_raw_fd(fd) };
         Ok(file)
     }
+
+    /// Imports a payload into the semaphore from a file descriptor of type `handle_type`, which
+    /// must be exactly one of `opaque_fd` or `sync_fd`.
+    ///
+    /// If `temporary` is true, the imported payload is only used until the next wait operation on
+    /// the semaphore, after which the semaphore goes back to its previous payload. A `sync_fd`
+    /// can only be imported temporarily.
+    ///
+    /// On success, the ownership of `fd` is transferred to the Vulkan implementation. On failure,
+    /// the file descriptor is closed.
+    ///
+    /// Requires the `khr_external_semaphore_fd` extension.
+    ///
+    /// # Safety
+    ///
+    /// - `fd` must be a valid handle of type `handle_type`. If it is an opaque file descriptor, it
+    ///   must have been exported from a device with the same `device_uuid` and `driver_uuid` as
+    ///   the device of the semaphore.
+    /// - The semaphore must not be used by any pending queue operation.
+    /// - If `temporary` is false, the semaphore must not come from the semaphore pool.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `handle_type` isn't exactly one of `opaque_fd` or `sync_fd`.
+    /// - Panics if `handle_type` is `sync_fd` and `temporary` is false.
+    ///
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonflybsd",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub unsafe fn import_fd(
+        &self,
+        handle_type: ExternalSemaphoreHandleType,
+        fd: File,
+        temporary: bool,
+    ) -> Result<(), SemaphoreError> {
+        assert!(handle_type == ExternalSemaphoreHandleType::posix() || handle_type == sync_fd());
+        assert!(temporary || !handle_type.sync_fd);
+
+        if !self.device.enabled_extensions().khr_external_semaphore_fd {
+            return Err(SemaphoreError::MissingExtension(
+                "khr_external_semaphore_fd",
+            ));
+        }
+
+        let fns = self.device.fns();
+        let info = ash::vk::ImportSemaphoreFdInfoKHR {
+            semaphore: self.semaphore,
+            flags: import_flags(temporary),
+            handle_type: handle_type.into(),
+            fd: fd.into_raw_fd(),
+            ..Default::default()
+        };
+
+        let result = check_errors(
+            fns.khr_external_semaphore_fd
+                .import_semaphore_fd_khr(self.device.internal_object(), &info),
+        );
+
+        if let Err(err) = result {
+            drop(File::from_raw_fd(info.fd));
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+
+    /// Exports the payload of the semaphore as a Windows handle of type `handle_type`, which must
+    /// be exactly one of `opaque_win32`, `opaque_win32_kmt` or `d3d12_fence`.
+    ///
+    /// The semaphore must have been created with `handle_type` in its export info. NT handles are
+    /// closed when the returned `Win32Handle` is dropped.
+    ///
+    /// Requires the `khr_external_semaphore_win32` extension.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `handle_type` isn't exactly one of the Windows handle types.
+    ///
+    #[cfg(target_os = "windows")]
+    pub fn export_win32_handle(
+        &self,
+        handle_type: ExternalSemaphoreHandleType,
+    ) -> Result<Win32Handle, SemaphoreError> {
+        assert!(is_single_win32_handle_type(handle_type));
+
+        if !self
+            .device
+            .enabled_extensions()
+            .khr_external_semaphore_win32
+        {
+            return Err(SemaphoreError::MissingExtension(
+                "khr_external_semaphore_win32",
+            ));
+        }
+
+        let fns = self.device.fns();
+        let handle = unsafe {
+            let info = ash::vk::SemaphoreGetWin32HandleInfoKHR {
+                semaphore: self.semaphore,
+                handle_type: handle_type.into(),
+                ..Default::default()
+            };
+
+            let mut output = MaybeUninit::uninit();
+            check_errors(
+                fns.khr_external_semaphore_win32
+                    .get_semaphore_win32_handle_khr(
+                        self.device.internal_object(),
+                        &info,
+                        output.as_mut_ptr(),
+                    ),
+            )?;
+            output.assume_init()
+        };
+
+        // Global share handles are not reference-counted and must not be closed.
+        let owned = !handle_type.opaque_win32_kmt;
+        Ok(unsafe { Win32Handle::from_raw(handle, owned) })
+    }
+
+    /// Imports a payload into the semaphore from a Windows handle of type `handle_type`, which
+    /// must be exactly one of `opaque_win32`, `opaque_win32_kmt` or `d3d12_fence`. This is how a
+    /// fence shared by Direct3D 12 is waited upon or signaled by Vulkan.
+    ///
+    /// If `temporary` is true, the imported payload is only used until the next wait operation on
+    /// the semaphore. Contrary to file descriptors, the ownership of `handle` is not transferred.
+    ///
+    /// Requires the `khr_external_semaphore_win32` extension.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid handle of type `handle_type`.
+    /// - The semaphore must not be used by any pending queue operation.
+    /// - If `temporary` is false, the semaphore must not come from the semaphore pool.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `handle_type` isn't exactly one of the Windows handle types.
+    ///
+    #[cfg(target_os = "windows")]
+    pub unsafe fn import_win32_handle(
+        &self,
+        handle_type: ExternalSemaphoreHandleType,
+        handle: *mut c_void,
+        temporary: bool,
+    ) -> Result<(), SemaphoreError> {
+        assert!(is_single_win32_handle_type(handle_type));
+
+        if !self
+            .device
+            .enabled_extensions()
+            .khr_external_semaphore_win32
+        {
+            return Err(SemaphoreError::MissingExtension(
+                "khr_external_semaphore_win32",
+            ));
+        }
+
+        let fns = self.device.fns();
+        let info = ash::vk::ImportSemaphoreWin32HandleInfoKHR {
+            semaphore: self.semaphore,
+            flags: import_flags(temporary),
+            handle_type: handle_type.into(),
+            handle,
+            ..Default::default()
+        };
+
+        check_errors(
+            fns.khr_external_semaphore_win32
+                .import_semaphore_win32_handle_khr(self.device.internal_object(), &info),
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonflybsd",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+#[inline]
+fn sync_fd() -> ExternalSemaphoreHandleType {
+    ExternalSemaphoreHandleType {
+        sync_fd: true,
+        ..ExternalSemaphoreHandleType::none()
+    }
+}
+
+#[cfg(target_os = "windows")]
+#[inline]
+fn is_single_win32_handle_type(handle_type: ExternalSemaphoreHandleType) -> bool {
+    let none = ExternalSemaphoreHandleType::none();
+    handle_type
+        == (ExternalSemaphoreHandleType {
+            opaque_win32: true,
+            ..none
+        })
+        || handle_type
+            == (ExternalSemaphoreHandleType {
+                opaque_win32_kmt: true,
+                ..none
+            })
+        || handle_type
+            == (ExternalSemaphoreHandleType {
+                d3d12_fence: true,
+                ..none
+            })
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonflybsd",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "windows"
+))]
+#[inline]
+fn import_flags(temporary: bool) -> ash::vk::SemaphoreImportFlags {
+    if temporary {
+        ash::vk::SemaphoreImportFlags::TEMPORARY
+    } else {
+        ash::vk::SemaphoreImportFlags::empty()
+    }
 }
 
 unsafe impl DeviceOwned for Semaphore {
@@ -384,4 +649,25 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonflybsd",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    fn export_fd_missing_extension() {
+        use crate::sync::semaphore::ExternalSemaphoreHandleType;
+        use crate::sync::SemaphoreError;
+
+        let (device, _) = gfx_dev_and_queue!();
+        let sem = Semaphore::alloc(device.clone()).unwrap();
+
+        match sem.export_fd(ExternalSemaphoreHandleType::posix()) {
+            Err(SemaphoreError::MissingExtension("khr_external_semaphore_fd")) => (),
+            _ => panic!(),
+        }
+    }
 }
@@ -0,0 +1,355 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::check_errors;
+use crate::device::Device;
+use crate::device::DeviceOwned;
+use crate::Error;
+use crate::OomError;
+use crate::Success;
+use crate::Version;
+use crate::VulkanObject;
+use smallvec::SmallVec;
+use std::error;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A semaphore that holds a 64-bit value, which only ever increases.
+///
+/// Contrary to a regular [`Semaphore`](crate::sync::Semaphore), a timeline semaphore is never
+/// reset: each signal operation sets it to a new, greater, value, and each wait operation waits
+/// until it reaches a value. This means that a single timeline semaphore can be signaled by many
+/// submissions and waited upon any number of times, both by queues and by the host.
+///
+/// Requires the [`timeline_semaphore`](crate::device::Features::timeline_semaphore) feature to be
+/// enabled on the device.
+#[derive(Debug)]
+pub struct TimelineSemaphore {
+    semaphore: ash::vk::Semaphore,
+    device: Arc<Device>,
+}
+
+impl TimelineSemaphore {
+    /// Builds a new timeline semaphore, with `initial_value` as its value.
+    pub fn new(
+        device: Arc<Device>,
+        initial_value: u64,
+    ) -> Result<TimelineSemaphore, TimelineSemaphoreError> {
+        if !device.enabled_features().timeline_semaphore {
+            return Err(TimelineSemaphoreError::FeatureNotEnabled);
+        }
+
+        let semaphore = unsafe {
+            let type_info = ash::vk::SemaphoreTypeCreateInfo {
+                semaphore_type: ash::vk::SemaphoreType::TIMELINE,
+                initial_value,
+                ..Default::default()
+            };
+            let infos = ash::vk::SemaphoreCreateInfo {
+                p_next: &type_info as *const _ as *const _,
+                ..Default::default()
+            };
+
+            let fns = device.fns();
+            let mut output = MaybeUninit::uninit();
+            check_errors(fns.v1_0.create_semaphore(
+                device.internal_object(),
+                &infos,
+                ptr::null(),
+                output.as_mut_ptr(),
+            ))?;
+            output.assume_init()
+        };
+
+        Ok(TimelineSemaphore { semaphore, device })
+    }
+
+    /// Returns the current value of the semaphore.
+    pub fn value(&self) -> Result<u64, TimelineSemaphoreError> {
+        unsafe {
+            let fns = self.device.fns();
+            let mut output = MaybeUninit::uninit();
+
+            if self.device.api_version() >= Version::V1_2 {
+                check_errors(fns.v1_2.get_semaphore_counter_value(
+                    self.device.internal_object(),
+                    self.semaphore,
+                    output.as_mut_ptr(),
+                ))?;
+            } else {
+                check_errors(fns.khr_timeline_semaphore.get_semaphore_counter_value_khr(
+                    self.device.internal_object(),
+                    self.semaphore,
+                    output.as_mut_ptr(),
+                ))?;
+            }
+
+            Ok(output.assume_init())
+        }
+    }
+
+    /// Sets the value of the semaphore to `value` from the host.
+    ///
+    /// # Safety
+    ///
+    /// - `value` must be greater than the current value of the semaphore.
+    /// - `value` must be less than the value of all the pending signal operations of the
+    ///   semaphore.
+    ///
+    pub unsafe fn signal(&self, value: u64) -> Result<(), TimelineSemaphoreError> {
+        let fns = self.device.fns();
+        let info = ash::vk::SemaphoreSignalInfo {
+            semaphore: self.semaphore,
+            value,
+            ..Default::default()
+        };
+
+        if self.device.api_version() >= Version::V1_2 {
+            check_errors(
+                fns.v1_2
+                    .signal_semaphore(self.device.internal_object(), &info),
+            )?;
+        } else {
+            check_errors(
+                fns.khr_timeline_semaphore
+                    .signal_semaphore_khr(self.device.internal_object(), &info),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Blocks the current thread until the semaphore reaches `value`.
+    ///
+    /// If `timeout` is `None`, waits forever. Otherwise returns `Timeout` if the value wasn't
+    /// reached in time.
+    #[inline]
+    pub fn wait(
+        &self,
+        value: u64,
+        timeout: Option<Duration>,
+    ) -> Result<(), TimelineSemaphoreError> {
+        TimelineSemaphore::multi_wait(Some((self, value)), false, timeout)
+    }
+
+    /// Blocks the current thread until all the semaphores reach their value or, if `wait_any` is
+    /// true, until one of them does.
+    ///
+    /// Returns immediately if the iterator is empty.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if not all the semaphores belong to the same device.
+    ///
+    pub fn multi_wait<'a, I>(
+        iter: I,
+        wait_any: bool,
+        timeout: Option<Duration>,
+    ) -> Result<(), TimelineSemaphoreError>
+    where
+        I: IntoIterator<Item = (&'a TimelineSemaphore, u64)>,
+    {
+        let mut device: Option<&Device> = None;
+        let mut semaphores: SmallVec<[ash::vk::Semaphore; 8]> = SmallVec::new();
+        let mut values: SmallVec<[u64; 8]> = SmallVec::new();
+
+        for (semaphore, value) in iter {
+            match &mut device {
+                dev @ &mut None => *dev = Some(&*semaphore.device),
+                &mut Some(ref dev)
+                    if &**dev as *const Device == &*semaphore.device as *const Device => {}
+                _ => panic!(
+                    "Tried to wait for multiple semaphores that didn't belong to the \
+                             same device"
+                ),
+            };
+
+            semaphores.push(semaphore.semaphore);
+            values.push(value);
+        }
+
+        let device = match device {
+            Some(device) => device,
+            None => return Ok(()),
+        };
+
+        let timeout_ns = if let Some(timeout) = timeout {
+            timeout
+                .as_secs()
+                .saturating_mul(1_000_000_000)
+                .saturating_add(timeout.subsec_nanos() as u64)
+        } else {
+            u64::MAX
+        };
+
+        let info = ash::vk::SemaphoreWaitInfo {
+            flags: if wait_any {
+                ash::vk::SemaphoreWaitFlags::ANY
+            } else {
+                ash::vk::SemaphoreWaitFlags::empty()
+            },
+            semaphore_count: semaphores.len() as u32,
+            p_semaphores: semaphores.as_ptr(),
+            p_values: values.as_ptr(),
+            ..Default::default()
+        };
+
+        let r = unsafe {
+            let fns = device.fns();
+
+            if device.api_version() >= Version::V1_2 {
+                check_errors(
+                    fns.v1_2
+                        .wait_semaphores(device.internal_object(), &info, timeout_ns),
+                )?
+            } else {
+                check_errors(fns.khr_timeline_semaphore.wait_semaphores_khr(
+                    device.internal_object(),
+                    &info,
+                    timeout_ns,
+                ))?
+            }
+        };
+
+        match r {
+            Success::Success => Ok(()),
+            Success::Timeout => Err(TimelineSemaphoreError::Timeout),
+            _ => unreachable!(),
+        }
+    }
+}
+
+unsafe impl DeviceOwned for TimelineSemaphore {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+unsafe impl VulkanObject for TimelineSemaphore {
+    type Object = ash::vk::Semaphore;
+
+    #[inline]
+    fn internal_object(&self) -> ash::vk::Semaphore {
+        self.semaphore
+    }
+}
+
+impl Drop for TimelineSemaphore {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            let fns = self.device.fns();
+            fns.v1_0
+                .destroy_semaphore(self.device.internal_object(), self.semaphore, ptr::null());
+        }
+    }
+}
+
+/// Error that can be returned when using a timeline semaphore.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimelineSemaphoreError {
+    /// Not enough memory available.
+    OomError(OomError),
+
+    /// The specified timeout wasn't long enough.
+    Timeout,
+
+    /// The device has been lost.
+    DeviceLost,
+
+    /// The `timeline_semaphore` feature isn't enabled on the device.
+    FeatureNotEnabled,
+}
+
+impl error::Error for TimelineSemaphoreError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            TimelineSemaphoreError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TimelineSemaphoreError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                TimelineSemaphoreError::OomError(_) => "not enough memory available",
+                TimelineSemaphoreError::Timeout => "the timeout has been reached",
+                TimelineSemaphoreError::DeviceLost => "the device was lost",
+                TimelineSemaphoreError::FeatureNotEnabled => {
+                    "the `timeline_semaphore` feature isn't enabled"
+                }
+            }
+        )
+    }
+}
+
+impl From<Error> for TimelineSemaphoreError {
+    #[inline]
+    fn from(err: Error) -> TimelineSemaphoreError {
+        match err {
+            e @ Error::OutOfHostMemory | e @ Error::OutOfDeviceMemory => {
+                TimelineSemaphoreError::OomError(e.into())
+            }
+            Error::DeviceLost => TimelineSemaphoreError::DeviceLost,
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+impl From<OomError> for TimelineSemaphoreError {
+    #[inline]
+    fn from(err: OomError) -> TimelineSemaphoreError {
+        TimelineSemaphoreError::OomError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sync::TimelineSemaphore;
+    use crate::sync::TimelineSemaphoreError;
+    use std::time::Duration;
+
+    #[test]
+    fn feature_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match TimelineSemaphore::new(device, 0) {
+            Err(TimelineSemaphoreError::FeatureNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn host_signal_and_wait() {
+        let (device, _) = gfx_dev_and_queue!(timeline_semaphore);
+
+        let semaphore = TimelineSemaphore::new(device, 1).unwrap();
+        assert_eq!(semaphore.value().unwrap(), 1);
+        assert_eq!(
+            semaphore.wait(2, Some(Duration::from_millis(1))),
+            Err(TimelineSemaphoreError::Timeout)
+        );
+
+        unsafe {
+            semaphore.signal(3).unwrap();
+        }
+        assert_eq!(semaphore.value().unwrap(), 3);
+        semaphore.wait(2, Some(Duration::from_secs(5))).unwrap();
+    }
+}
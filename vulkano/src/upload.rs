@@ -0,0 +1,559 @@
+// Copyright (c) 2021 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Uploading data from the CPU to buffers and images.
+//!
+//! Most of the data of an application, such as meshes and textures, is written once by the CPU
+//! and then only accessed by the device, so it is best stored in device-local buffers and images
+//! that the CPU can't access directly. An [`Uploader`] writes this data to host-visible staging
+//! buffers, and copies it to its destination with a command buffer submitted to its queue.
+//!
+//! The uploads of an [`UploadBatch`] are copied with a single submission. The staging buffers
+//! are kept by the `Uploader` and reused once the fence of the submission that used them has been
+//! signaled, until they are freed with [`Uploader::trim`].
+
+use crate::buffer::BufferUsage;
+use crate::buffer::CpuAccessibleBuffer;
+use crate::buffer::TypedBufferAccess;
+use crate::command_buffer::AutoCommandBufferBuilder;
+use crate::command_buffer::BufferCopy;
+use crate::command_buffer::BufferImageCopy;
+use crate::command_buffer::BuildError;
+use crate::command_buffer::CommandBufferExecError;
+use crate::command_buffer::CommandBufferExecFuture;
+use crate::command_buffer::CommandBufferUsage;
+use crate::command_buffer::CopyBufferError;
+use crate::command_buffer::CopyBufferImageError;
+use crate::command_buffer::PrimaryAutoCommandBuffer;
+use crate::command_buffer::PrimaryCommandBuffer;
+use crate::device::Queue;
+use crate::image::ImageAccess;
+use crate::memory::DeviceMemoryAllocError;
+use crate::sync::FenceSignalFuture;
+use crate::sync::FlushError;
+use crate::sync::GpuFuture;
+use crate::sync::NowFuture;
+use crate::DeviceSize;
+use crate::OomError;
+use std::error;
+use std::fmt;
+use std::iter;
+use std::mem;
+use std::slice;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// The minimum size in bytes of the staging buffers that an `Uploader` allocates.
+const MIN_STAGING_BUFFER_SIZE: DeviceSize = 4 * 1024 * 1024;
+
+/// Future that is signaled when the copies of an upload have finished.
+pub type UploadFuture =
+    Arc<FenceSignalFuture<CommandBufferExecFuture<NowFuture, PrimaryAutoCommandBuffer>>>;
+
+type UploadCopy = Box<
+    dyn FnOnce(
+            &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+            Arc<CpuAccessibleBuffer<[u8]>>,
+        ) -> Result<(), UploadError>
+        + Send,
+>;
+
+/// Uploads data from the CPU to buffers and images, through staging buffers that it reuses.
+///
+/// The copies are executed on the queue that the `Uploader` was created with, for example a
+/// queue of a transfer-only family. The destinations must be created with the
+/// `transfer_destination` usage, and with a sharing mode that allows them to be used on this
+/// queue as well as on the queues that use them afterwards.
+pub struct Uploader {
+    queue: Arc<Queue>,
+    // The staging buffers that aren't being recorded into a command buffer.
+    staging_buffers: Mutex<Vec<StagingBuffer>>,
+}
+
+struct StagingBuffer {
+    buffer: Arc<CpuAccessibleBuffer<[u8]>>,
+    // The last submission that used the buffer, or `None` if it has finished.
+    future: Option<UploadFuture>,
+}
+
+impl StagingBuffer {
+    // Returns true if the buffer isn't used by a submission that hasn't finished. The resources
+    // of the submission are released when its fence is found signaled.
+    #[inline]
+    fn is_free(&mut self) -> bool {
+        if self
+            .future
+            .as_ref()
+            .map_or(false, |future| future.is_signaled())
+        {
+            self.future = None;
+        }
+
+        self.future.is_none()
+    }
+}
+
+impl Uploader {
+    /// Builds a new `Uploader` that copies the uploaded data on `queue`.
+    #[inline]
+    pub fn new(queue: Arc<Queue>) -> Uploader {
+        Uploader {
+            queue,
+            staging_buffers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the queue that the copies are executed on.
+    #[inline]
+    pub fn queue(&self) -> &Arc<Queue> {
+        &self.queue
+    }
+
+    /// Starts a batch of uploads that are copied with a single submission.
+    #[inline]
+    pub fn batch(&self) -> UploadBatch {
+        UploadBatch {
+            uploader: self,
+            data: Vec::new(),
+            copies: Vec::new(),
+        }
+    }
+
+    /// Uploads `data` to the start of `destination`, in a submission of its own.
+    #[inline]
+    pub fn upload_buffer<T, B>(
+        &self,
+        data: &[T],
+        destination: Arc<B>,
+    ) -> Result<UploadFuture, UploadError>
+    where
+        T: Copy + 'static,
+        B: TypedBufferAccess<Content = [T]> + 'static,
+    {
+        let mut batch = self.batch();
+        batch.upload_buffer(data, destination);
+        batch.submit()
+    }
+
+    /// Uploads `data` to a region of `destination`, in a submission of its own.
+    ///
+    /// `region.buffer_offset` is the offset in bytes of the data of the region from the start of
+    /// `data`.
+    #[inline]
+    pub fn upload_image<T>(
+        &self,
+        data: &[T],
+        destination: Arc<dyn ImageAccess>,
+        region: BufferImageCopy,
+    ) -> Result<UploadFuture, UploadError>
+    where
+        T: Copy + 'static,
+    {
+        let mut batch = self.batch();
+        batch.upload_image(data, destination, region);
+        batch.submit()
+    }
+
+    /// Releases the resources of the submissions that have finished, so that their staging
+    /// buffers can be reused.
+    ///
+    /// This is called automatically each time a batch is submitted.
+    pub fn cleanup_finished(&self) {
+        for staging in self.staging_buffers.lock().unwrap().iter_mut() {
+            staging.is_free();
+        }
+    }
+
+    /// Frees the staging buffers that aren't used by a submission that hasn't finished.
+    ///
+    /// The staging buffers are otherwise kept to be reused by the following uploads, so the
+    /// memory that they use only grows to fit the largest batches submitted at the same time.
+    pub fn trim(&self) {
+        self.cleanup_finished();
+        self.staging_buffers
+            .lock()
+            .unwrap()
+            .retain(|staging| staging.future.is_some());
+    }
+
+    /// Returns the total size in bytes of the staging buffers that the `Uploader` holds.
+    pub fn staging_size(&self) -> DeviceSize {
+        self.staging_buffers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|staging| staging.buffer.len())
+            .sum()
+    }
+
+    // Takes the smallest free staging buffer of at least `size` bytes, or allocates one. The
+    // buffer must be given back with `recycle`.
+    fn staging_buffer(
+        &self,
+        size: DeviceSize,
+    ) -> Result<Arc<CpuAccessibleBuffer<[u8]>>, DeviceMemoryAllocError> {
+        {
+            let mut staging_buffers = self.staging_buffers.lock().unwrap();
+            let index = staging_buffers
+                .iter_mut()
+                .enumerate()
+                .filter(|(_, staging)| staging.buffer.len() >= size && staging.is_free())
+                .min_by_key(|(_, staging)| staging.buffer.len())
+                .map(|(index, _)| index);
+
+            if let Some(index) = index {
+                return Ok(staging_buffers.swap_remove(index).buffer);
+            }
+        }
+
+        unsafe {
+            CpuAccessibleBuffer::uninitialized_array(
+                self.queue.device().clone(),
+                size.max(MIN_STAGING_BUFFER_SIZE),
+                BufferUsage::transfer_source(),
+                false,
+            )
+        }
+    }
+
+    // Gives back a staging buffer taken with `staging_buffer`, with the submission that uses it.
+    #[inline]
+    fn recycle(&self, buffer: Arc<CpuAccessibleBuffer<[u8]>>, future: Option<UploadFuture>) {
+        self.staging_buffers
+            .lock()
+            .unwrap()
+            .push(StagingBuffer { buffer, future });
+    }
+}
+
+/// A batch of uploads that are copied with a single submission.
+///
+/// The data is copied in memory when an upload is added to the batch. The copy commands are
+/// checked when the batch is submitted.
+pub struct UploadBatch<'a> {
+    uploader: &'a Uploader,
+    // The data of the uploads, as it is written to the staging buffer.
+    data: Vec<u8>,
+    // Records the copy of each upload from the staging buffer.
+    copies: Vec<UploadCopy>,
+}
+
+impl<'a> UploadBatch<'a> {
+    /// Adds an upload of `data` to the start of `destination`.
+    pub fn upload_buffer<T, B>(&mut self, data: &[T], destination: Arc<B>) -> &mut Self
+    where
+        T: Copy + 'static,
+        B: TypedBufferAccess<Content = [T]> + 'static,
+    {
+        let source_offset = self.push_data(data, mem::align_of::<T>().max(4) as DeviceSize);
+        let size = mem::size_of_val(data) as DeviceSize;
+
+        self.copies.push(Box::new(move |builder, staging| {
+            builder.copy_buffer_regions(
+                staging,
+                destination,
+                iter::once(BufferCopy {
+                    source_offset,
+                    destination_offset: 0,
+                    size,
+                }),
+            )?;
+            Ok(())
+        }));
+
+        self
+    }
+
+    /// Adds an upload of `data` to a region of `destination`.
+    ///
+    /// `region.buffer_offset` is the offset in bytes of the data of the region from the start of
+    /// `data`.
+    pub fn upload_image<T>(
+        &mut self,
+        data: &[T],
+        destination: Arc<dyn ImageAccess>,
+        mut region: BufferImageCopy,
+    ) -> &mut Self
+    where
+        T: Copy + 'static,
+    {
+        // The offset of the data in the staging buffer must be a multiple of 4 and of the size
+        // of a texel block.
+        let block_size = destination.format().size().unwrap_or(1).max(1);
+        let alignment = block_size * 4 / gcd(block_size, 4);
+        region.buffer_offset += self.push_data(data, alignment);
+
+        self.copies.push(Box::new(move |builder, staging| {
+            builder.copy_buffer_to_image_regions(staging, destination, iter::once(region))?;
+            Ok(())
+        }));
+
+        self
+    }
+
+    /// Returns the number of uploads in the batch.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.copies.len()
+    }
+
+    /// Returns true if the batch doesn't contain any upload.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.copies.is_empty()
+    }
+
+    /// Writes the data of the uploads to a staging buffer, and submits the copies to the queue of
+    /// the `Uploader`.
+    pub fn submit(self) -> Result<UploadFuture, UploadError> {
+        let uploader = self.uploader;
+        uploader.cleanup_finished();
+        let staging = uploader.staging_buffer(self.data.len() as DeviceSize)?;
+
+        {
+            // The submission that last used the staging buffer has finished and released it, so
+            // it can't be locked.
+            let mut lock = staging.write().unwrap();
+            lock[..self.data.len()].copy_from_slice(&self.data);
+        }
+
+        let command_buffer = match Self::record(uploader, self.copies, &staging) {
+            Ok(command_buffer) => command_buffer,
+            Err(err) => {
+                // Nothing was submitted, so the staging buffer can be reused right away.
+                uploader.recycle(staging, None);
+                return Err(err);
+            }
+        };
+
+        // If the submission fails, the staging buffer is dropped with the command buffer.
+        let future = Arc::new(
+            command_buffer
+                .execute(uploader.queue.clone())?
+                .then_signal_fence_and_flush()?,
+        );
+        uploader.recycle(staging, Some(future.clone()));
+
+        Ok(future)
+    }
+
+    // Records the copies of the uploads from `staging`.
+    fn record(
+        uploader: &Uploader,
+        copies: Vec<UploadCopy>,
+        staging: &Arc<CpuAccessibleBuffer<[u8]>>,
+    ) -> Result<PrimaryAutoCommandBuffer, UploadError> {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            uploader.queue.device().clone(),
+            uploader.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+
+        for copy in copies {
+            copy(&mut builder, staging.clone())?;
+        }
+
+        Ok(builder.build()?)
+    }
+
+    // Appends `data` at an offset that is a multiple of `alignment`, and returns the offset.
+    fn push_data<T>(&mut self, data: &[T], alignment: DeviceSize) -> DeviceSize
+    where
+        T: Copy + 'static,
+    {
+        let offset = (self.data.len() as DeviceSize + alignment - 1) / alignment * alignment;
+        self.data.resize(offset as usize, 0);
+
+        // Safe because `T` is `Copy`, so it doesn't hold any resource.
+        let bytes =
+            unsafe { slice::from_raw_parts(data.as_ptr() as *const u8, mem::size_of_val(data)) };
+        self.data.extend_from_slice(bytes);
+
+        offset
+    }
+}
+
+#[inline]
+fn gcd(mut a: DeviceSize, mut b: DeviceSize) -> DeviceSize {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+/// Error that can happen when submitting uploads.
+#[derive(Clone, Debug)]
+pub enum UploadError {
+    /// Allocating the command buffer failed.
+    OomError(OomError),
+    /// Allocating a staging buffer failed.
+    DeviceMemoryAllocError(DeviceMemoryAllocError),
+    /// The copy to a buffer is invalid.
+    CopyBufferError(CopyBufferError),
+    /// The copy to an image is invalid.
+    CopyBufferImageError(CopyBufferImageError),
+    /// Building the command buffer failed.
+    BuildError(BuildError),
+    /// Executing the command buffer failed.
+    CommandBufferExecError(CommandBufferExecError),
+    /// Submitting the command buffer failed.
+    FlushError(FlushError),
+}
+
+impl error::Error for UploadError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            UploadError::OomError(ref err) => Some(err),
+            UploadError::DeviceMemoryAllocError(ref err) => Some(err),
+            UploadError::CopyBufferError(ref err) => Some(err),
+            UploadError::CopyBufferImageError(ref err) => Some(err),
+            UploadError::BuildError(ref err) => Some(err),
+            UploadError::CommandBufferExecError(ref err) => Some(err),
+            UploadError::FlushError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for UploadError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            fmt,
+            "{}",
+            match *self {
+                UploadError::OomError(_) => "allocating the command buffer failed",
+                UploadError::DeviceMemoryAllocError(_) => "allocating a staging buffer failed",
+                UploadError::CopyBufferError(_) => "the copy to a buffer is invalid",
+                UploadError::CopyBufferImageError(_) => "the copy to an image is invalid",
+                UploadError::BuildError(_) => "building the command buffer failed",
+                UploadError::CommandBufferExecError(_) => "executing the command buffer failed",
+                UploadError::FlushError(_) => "submitting the command buffer failed",
+            }
+        )
+    }
+}
+
+impl From<OomError> for UploadError {
+    #[inline]
+    fn from(err: OomError) -> UploadError {
+        UploadError::OomError(err)
+    }
+}
+
+impl From<DeviceMemoryAllocError> for UploadError {
+    #[inline]
+    fn from(err: DeviceMemoryAllocError) -> UploadError {
+        UploadError::DeviceMemoryAllocError(err)
+    }
+}
+
+impl From<CopyBufferError> for UploadError {
+    #[inline]
+    fn from(err: CopyBufferError) -> UploadError {
+        UploadError::CopyBufferError(err)
+    }
+}
+
+impl From<CopyBufferImageError> for UploadError {
+    #[inline]
+    fn from(err: CopyBufferImageError) -> UploadError {
+        UploadError::CopyBufferImageError(err)
+    }
+}
+
+impl From<BuildError> for UploadError {
+    #[inline]
+    fn from(err: BuildError) -> UploadError {
+        UploadError::BuildError(err)
+    }
+}
+
+impl From<CommandBufferExecError> for UploadError {
+    #[inline]
+    fn from(err: CommandBufferExecError) -> UploadError {
+        UploadError::CommandBufferExecError(err)
+    }
+}
+
+impl From<FlushError> for UploadError {
+    #[inline]
+    fn from(err: FlushError) -> UploadError {
+        UploadError::FlushError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Uploader;
+    use crate::buffer::BufferUsage;
+    use crate::buffer::CpuAccessibleBuffer;
+
+    #[test]
+    fn upload_batch_to_buffers() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let uploader = Uploader::new(queue);
+
+        let usage = BufferUsage::transfer_destination();
+        let first =
+            CpuAccessibleBuffer::from_iter(device.clone(), usage, false, [0u32; 3].iter().copied())
+                .unwrap();
+        let second =
+            CpuAccessibleBuffer::from_iter(device, usage, false, [0u8; 5].iter().copied()).unwrap();
+
+        let mut batch = uploader.batch();
+        batch
+            .upload_buffer(&[1u32, 2, 3], first.clone())
+            .upload_buffer(&[4u8, 5, 6, 7, 8], second.clone());
+        assert_eq!(batch.len(), 2);
+        let future = batch.submit().unwrap();
+        future.wait(None).unwrap();
+
+        assert_eq!(&*first.read().unwrap(), &[1, 2, 3]);
+        assert_eq!(&*second.read().unwrap(), &[4, 5, 6, 7, 8]);
+
+        // The staging buffer of the first batch is reused once its fence is signaled, even though
+        // its future is still alive.
+        uploader
+            .upload_buffer(&[9u32, 10, 11], first.clone())
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        assert_eq!(uploader.staging_buffers.lock().unwrap().len(), 1);
+        assert_eq!(&*first.read().unwrap(), &[9, 10, 11]);
+        drop(future);
+    }
+
+    #[test]
+    fn trim_staging_buffers() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let uploader = Uploader::new(queue);
+
+        let destination = CpuAccessibleBuffer::from_iter(
+            device,
+            BufferUsage::transfer_destination(),
+            false,
+            [0u32; 4].iter().copied(),
+        )
+        .unwrap();
+
+        uploader
+            .upload_buffer(&[1u32, 2, 3, 4], destination.clone())
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        assert!(uploader.staging_size() >= 16);
+
+        uploader.trim();
+        assert_eq!(uploader.staging_size(), 0);
+        assert_eq!(&*destination.read().unwrap(), &[1, 2, 3, 4]);
+    }
+}